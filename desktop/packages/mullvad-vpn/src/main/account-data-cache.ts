@@ -81,6 +81,12 @@ export default class AccountDataCache {
     }
   }
 
+  public handleExpiryUpdate(accountNumber: AccountNumber, expiry: string) {
+    if (accountNumber === this.currentAccount) {
+      this.setValue({ expiry });
+    }
+  }
+
   private setValue(accountData: IAccountData) {
     this.validUntil = this.getValidUntil(accountData);
     this.updateHandler(accountData);
@@ -5,6 +5,7 @@ import {
   DeviceEvent,
   DeviceState,
   IAccountData,
+  IAccountExpiryEvent,
   IDeviceRemoval,
   TunnelState,
 } from '../shared/daemon-rpc-types';
@@ -119,6 +120,13 @@ export default class Account {
     }
   }
 
+  public handleAccountExpiryEvent(event: IAccountExpiryEvent) {
+    const accountNumber = this.getAccountNumber();
+    if (accountNumber) {
+      this.accountDataCache.handleExpiryUpdate(accountNumber, event.expiry);
+    }
+  }
+
   public handleDeviceEvent(deviceEvent: DeviceEvent) {
     this.delegate.closeNotificationsInCategory(SystemNotificationCategory.expiry);
 
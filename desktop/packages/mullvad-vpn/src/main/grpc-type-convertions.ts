@@ -736,6 +736,16 @@ export function convertFromDaemonEvent(data: grpcTypes.DaemonEvent): DaemonEvent
     return { accessMethodSetting: convertFromApiAccessMethodSetting(newAccessMethod) };
   }
 
+  const accountExpiry = data.getAccountExpiry();
+  if (accountExpiry !== undefined) {
+    return {
+      accountExpiry: {
+        expiry: accountExpiry.getExpiry()!.toDate().toISOString(),
+        paymentReceived: accountExpiry.getPaymentReceived(),
+      },
+    };
+  }
+
   // Handle unknown daemon events
   const keys = Object.entries(data.toObject())
     .filter(([, value]) => value !== undefined)
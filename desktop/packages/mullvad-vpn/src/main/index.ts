@@ -726,6 +726,8 @@ class ApplicationMain
           IpcMainEventChannel.settings.notifyApiAccessMethodSettingChange?.(
             daemonEvent.accessMethodSetting,
           );
+        } else if ('accountExpiry' in daemonEvent) {
+          this.account.handleAccountExpiryEvent(daemonEvent.accountExpiry);
         }
       },
       (error: Error) => {
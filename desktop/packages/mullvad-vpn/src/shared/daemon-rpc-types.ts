@@ -2,6 +2,11 @@ export interface IAccountData {
   expiry: string;
 }
 
+export interface IAccountExpiryEvent {
+  expiry: string;
+  paymentReceived: boolean;
+}
+
 export type AccountDataError = {
   type: 'error';
   error: 'invalid-account' | 'too-many-devices' | 'list-devices' | 'communication';
@@ -171,7 +176,8 @@ export type DaemonEvent =
   | { appVersionInfo: IAppVersionInfo }
   | { device: DeviceEvent }
   | { deviceRemoval: Array<IDevice> }
-  | { accessMethodSetting: AccessMethodSetting };
+  | { accessMethodSetting: AccessMethodSetting }
+  | { accountExpiry: IAccountExpiryEvent };
 
 export interface ITunnelStateRelayInfo {
   endpoint: ITunnelEndpoint;
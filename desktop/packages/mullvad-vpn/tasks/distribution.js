@@ -213,6 +213,7 @@ function newConfig() {
           '=/usr/lib/systemd/system/mullvad-daemon.service',
         distAssets('linux/mullvad-early-boot-blocking.service') +
           '=/usr/lib/systemd/system/mullvad-early-boot-blocking.service',
+        distAssets('linux/net.mullvad.Daemon.conf') + '=/usr/share/dbus-1/system.d/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad')) + '=/usr/bin/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-daemon')) + '=/usr/bin/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-exclude')) + '=/usr/bin/',
@@ -246,6 +247,7 @@ function newConfig() {
           '=/usr/lib/systemd/system/mullvad-daemon.service',
         distAssets('linux/mullvad-early-boot-blocking.service') +
           '=/usr/lib/systemd/system/mullvad-early-boot-blocking.service',
+        distAssets('linux/net.mullvad.Daemon.conf') + '=/usr/share/dbus-1/system.d/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad')) + '=/usr/bin/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-daemon')) + '=/usr/bin/',
         distAssets(path.join(getLinuxTargetSubdir(), 'mullvad-exclude')) + '=/usr/bin/',
@@ -361,6 +361,7 @@ impl<D: AppDelegate + 'static, A: From<UiAppDownloaderParameters<D>> + AppDownlo
                 app_size,
                 app_progress: UiProgressUpdater::new(self_.queue()),
                 app_sha256,
+                app_provenance: None,
                 cache_dir: download_dir,
             });
 
@@ -4,16 +4,31 @@
 //! [`ApiConnectionMode`], which in turn is used by `mullvad-api` for
 //! establishing connections when performing API requests.
 
-use crate::proxy::{ApiConnectionMode, ConnectionModeProvider};
+use crate::proxy::{ApiConnectionMode, ConnectionModeProvider, RequestOutcome};
 #[cfg(feature = "api-override")]
 use crate::ApiEndpoint;
 use async_trait::async_trait;
+use chrono::Utc;
 use futures::{
     channel::{mpsc, oneshot},
     StreamExt,
 };
-use mullvad_types::access_method::{AccessMethod, AccessMethodSetting, Id, Settings};
-use talpid_types::net::AllowedEndpoint;
+use mullvad_types::access_method::{
+    AccessMethod, AccessMethodSetting, AccessMethodStats, Id, Settings,
+};
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use talpid_types::{net::AllowedEndpoint, ErrorExt};
+use tokio::{fs, io::AsyncWriteExt};
+
+/// File in the cache directory where [`AccessMethodStats`] are stored.
+const STATS_FILENAME: &str = "access-method-stats.json";
+/// Minimum time between writes of the statistics file, unless a request failed.
+const STATS_SAVE_INTERVAL: Duration = Duration::from_secs(60);
 
 pub enum Message {
     Get(ResponseTx<ResolvedConnectionMode>),
@@ -24,6 +39,10 @@ pub enum Message {
         ResponseTx<Option<ResolvedConnectionMode>>,
         AccessMethodSetting,
     ),
+    Report(ApiConnectionMode, RequestOutcome),
+    GetStats(ResponseTx<HashMap<Id, AccessMethodStats>>),
+    #[cfg(feature = "api-override")]
+    SetEndpoint(ResponseTx<()>, ApiEndpoint),
 }
 
 pub enum AccessMethodEvent {
@@ -107,6 +126,10 @@ impl std::fmt::Display for Message {
             Message::Rotate(_) => f.write_str("Rotate"),
             Message::Update(..) => f.write_str("Update"),
             Message::Resolve(..) => f.write_str("Resolve"),
+            Message::Report(..) => f.write_str("Report"),
+            Message::GetStats(_) => f.write_str("GetStats"),
+            #[cfg(feature = "api-override")]
+            Message::SetEndpoint(..) => f.write_str("SetEndpoint"),
         }
     }
 }
@@ -181,6 +204,24 @@ impl AccessModeSelectorHandle {
             log::debug!("Failed while getting the next access method");
         })
     }
+
+    /// Returns how well each access method has worked, keyed by access method ID.
+    pub async fn get_stats(&self) -> Result<HashMap<Id, AccessMethodStats>> {
+        self.send_command(Message::GetStats).await.inspect_err(|_| {
+            log::debug!("Failed to get access method statistics");
+        })
+    }
+
+    /// Select access methods for `api_endpoint` instead of the endpoint the selector was spawned
+    /// with. The current access method is announced again, since its endpoint may have changed.
+    #[cfg(feature = "api-override")]
+    pub async fn set_api_endpoint(&self, api_endpoint: ApiEndpoint) -> Result<()> {
+        self.send_command(|tx| Message::SetEndpoint(tx, api_endpoint))
+            .await
+            .inspect_err(|_| {
+                log::debug!("Failed to set the API endpoint");
+            })
+    }
 }
 
 pub struct AccessModeConnectionModeProvider {
@@ -218,6 +259,13 @@ impl ConnectionModeProvider for AccessModeConnectionModeProvider {
             handle.rotate().await.ok();
         }
     }
+
+    fn report(&self, connection_mode: ApiConnectionMode, outcome: RequestOutcome) {
+        let _ = self
+            .handle
+            .cmd_tx
+            .unbounded_send(Message::Report(connection_mode, outcome));
+    }
 }
 
 /// A small actor which takes care of handling the logic around rotating
@@ -228,6 +276,9 @@ impl ConnectionModeProvider for AccessModeConnectionModeProvider {
 /// [`ApiConnectionMode::Direct`]) via a bridge ([`ApiConnectionMode::Proxied`])
 /// or via any supported custom proxy protocol
 /// ([`talpid_types::net::proxy::CustomProxy`]).
+///
+/// Access methods are tried in order of how well they have worked in the past,
+/// as recorded by [`AccessMethodStats`].
 pub struct AccessModeSelector<B: AccessMethodResolver> {
     #[cfg(feature = "api-override")]
     api_endpoint: ApiEndpoint,
@@ -237,8 +288,10 @@ pub struct AccessModeSelector<B: AccessMethodResolver> {
     access_method_event_sender: mpsc::UnboundedSender<(AccessMethodEvent, oneshot::Sender<()>)>,
     connection_mode_provider_sender: mpsc::UnboundedSender<ApiConnectionMode>,
     current: ResolvedConnectionMode,
-    /// `index` is used to keep track of the [`AccessMethodSetting`] to use.
-    index: usize,
+    /// Access methods that have failed since the last successful request. These
+    /// are only tried again once every other access method has failed.
+    failed: Vec<Id>,
+    stats: StatsStore,
 }
 
 impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
@@ -246,6 +299,7 @@ impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
         mut method_resolver: B,
         #[cfg_attr(not(feature = "api-override"), allow(unused_mut))]
         mut access_method_settings: Settings,
+        cache_dir: &Path,
         #[cfg(feature = "api-override")] api_endpoint: ApiEndpoint,
         access_method_event_sender: mpsc::UnboundedSender<(AccessMethodEvent, oneshot::Sender<()>)>,
     ) -> Result<(AccessModeSelectorHandle, AccessModeConnectionModeProvider)> {
@@ -259,8 +313,15 @@ impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
             }
         }
 
-        // Always start looking from the position of `Direct`.
-        let (index, next) = Self::find_next_active(0, &access_method_settings);
+        method_resolver
+            .update_access_methods(&access_method_settings)
+            .await;
+
+        let stats = StatsStore::load(cache_dir.join(STATS_FILENAME)).await;
+
+        // Start with the access method that has worked best in the past.
+        let next = Self::find_next_active(&access_method_settings, &stats.stats, &[])
+            .unwrap_or_else(|| access_method_settings.direct().clone());
         let initial_connection_mode = Self::resolve_with_default(&next, &mut method_resolver).await;
 
         let (change_tx, change_rx) = mpsc::unbounded();
@@ -276,7 +337,8 @@ impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
             access_method_event_sender,
             connection_mode_provider_sender: change_tx,
             current: initial_connection_mode,
-            index,
+            failed: vec![],
+            stats,
         };
 
         tokio::spawn(selector.into_future());
@@ -298,6 +360,14 @@ impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
                 Message::Rotate(tx) => self.on_next_connection_mode(tx).await,
                 Message::Update(tx, values) => self.on_update_access_methods(tx, values).await,
                 Message::Resolve(tx, setting) => self.on_resolve_access_method(tx, setting).await,
+                Message::Report(connection_mode, outcome) => {
+                    self.on_report(connection_mode, outcome).await
+                }
+                Message::GetStats(tx) => self.on_get_stats(tx),
+                #[cfg(feature = "api-override")]
+                Message::SetEndpoint(tx, api_endpoint) => {
+                    self.on_set_api_endpoint(tx, api_endpoint).await
+                }
             };
             match execution {
                 Ok(_) => (),
@@ -338,16 +408,14 @@ impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
             }
         }
 
-        let Some((index, method)) = self
+        let Some(method) = self
             .access_method_settings
             .iter()
-            .enumerate()
-            .find(|(_, access_method)| access_method.get_id() == id)
+            .find(|access_method| access_method.get_id() == id)
         else {
             return;
         };
 
-        self.index = index;
         self.set_current(method.to_owned()).await;
     }
 
@@ -370,9 +438,27 @@ impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
             );
         }
 
-        let (next_index, next) =
-            Self::find_next_active(self.index + 1, &self.access_method_settings);
-        self.index = next_index;
+        let current = self.current.setting.get_id();
+        if !self.failed.contains(&current) {
+            self.failed.push(current.clone());
+        }
+
+        let settings = &self.access_method_settings;
+        let next = match Self::find_next_active(settings, &self.stats.stats, &self.failed) {
+            Some(next) => next,
+            None => {
+                // Every access method has failed. Start over, but try the current one last.
+                self.failed = vec![current.clone()];
+                Self::find_next_active(settings, &self.stats.stats, &self.failed)
+                    .or_else(|| {
+                        settings
+                            .iter()
+                            .find(|method| method.get_id() == current && method.enabled())
+                            .cloned()
+                    })
+                    .unwrap_or_else(|| settings.direct().clone())
+            }
+        };
         self.set_current(next).await;
         Ok(self.current.connection_mode.clone())
     }
@@ -416,20 +502,55 @@ impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
         );
     }
 
-    /// Find the next access method to use.
+    /// Find the next access method to use, which is the enabled access method
+    /// with the highest score. Access methods with equal scores are tried in
+    /// the order they are configured in.
     ///
-    /// * `start`: From which point in `access_methods` to start the search.
     /// * `access_methods`: The search space.
-    fn find_next_active(start: usize, access_methods: &Settings) -> (usize, AccessMethodSetting) {
+    /// * `stats`: How well each access method has worked in the past.
+    /// * `excluded`: Access methods which should not be considered.
+    fn find_next_active(
+        access_methods: &Settings,
+        stats: &HashMap<Id, AccessMethodStats>,
+        excluded: &[Id],
+    ) -> Option<AccessMethodSetting> {
+        let now = Utc::now();
+        let score = |access_method: &AccessMethodSetting| {
+            stats
+                .get(&access_method.get_id())
+                .map(|stats| stats.score(now))
+                .unwrap_or(AccessMethodStats::NEUTRAL_SCORE)
+        };
         access_methods
             .iter()
-            .cloned()
-            .enumerate()
-            .cycle()
-            .skip(start)
-            .take(access_methods.cardinality())
-            .find(|(_index, access_method)| access_method.enabled())
-            .unwrap_or_else(|| (0, access_methods.direct().clone()))
+            .filter(|access_method| {
+                access_method.enabled() && !excluded.contains(&access_method.get_id())
+            })
+            .map(|access_method| (access_method, score(access_method)))
+            .fold(None, |best, (access_method, score)| match best {
+                Some((_, best_score)) if best_score >= score => best,
+                _ => Some((access_method, score)),
+            })
+            .map(|(access_method, _)| access_method.clone())
+    }
+
+    #[cfg(feature = "api-override")]
+    async fn on_set_api_endpoint(
+        &mut self,
+        tx: ResponseTx<()>,
+        api_endpoint: ApiEndpoint,
+    ) -> Result<()> {
+        self.api_endpoint = api_endpoint;
+        self.failed.clear();
+        let next = if self.api_endpoint.force_direct {
+            self.access_method_settings
+                .update(|setting| setting.is_direct(), |setting| setting.enable());
+            self.access_method_settings.direct().clone()
+        } else {
+            self.current.setting.clone()
+        };
+        self.set_current(next).await;
+        self.reply(tx, ())
     }
 
     async fn on_update_access_methods(
@@ -442,18 +563,20 @@ impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
     }
 
     async fn update_access_methods(&mut self, access_methods: Settings) -> Result<()> {
+        self.method_resolver
+            .update_access_methods(&access_methods)
+            .await;
         self.access_method_settings = access_methods;
+        self.stats.retain(&self.access_method_settings).await;
 
         let new_current = self
             .access_method_settings
             .iter()
-            .enumerate()
-            .find(|(_, access_method)| access_method.get_id() == self.current.setting.get_id());
+            .find(|access_method| access_method.get_id() == self.current.setting.get_id());
 
         match new_current {
-            Some((index, new_current)) => {
+            Some(new_current) => {
                 // If the current method was modified, announce changes
-                self.index = index;
                 if self.current.setting != *new_current {
                     if new_current.enabled() {
                         self.set_current(new_current.to_owned()).await;
@@ -463,17 +586,36 @@ impl<B: AccessMethodResolver + 'static> AccessModeSelector<B> {
                 }
             }
             None => {
-                // Current method was removed: A new access method will suddenly have the same index
-                // as the one we are removing, but we want it to still be a
-                // candidate. A minor hack to achieve this is to simply decrement
-                // the current index.
-                self.index = self.index.saturating_sub(1);
+                // Current method was removed
                 self.next_connection_mode().await?;
             }
         }
         Ok(())
     }
 
+    async fn on_report(
+        &mut self,
+        connection_mode: ApiConnectionMode,
+        outcome: RequestOutcome,
+    ) -> Result<()> {
+        // Requests sent using a previous access method can't be reliably attributed to it, since
+        // several access methods may resolve to the same connection mode.
+        if connection_mode != self.current.connection_mode {
+            return Ok(());
+        }
+        if let RequestOutcome::Success { .. } = outcome {
+            self.failed.clear();
+        }
+        self.stats
+            .record(self.current.setting.get_id(), outcome)
+            .await;
+        Ok(())
+    }
+
+    fn on_get_stats(&mut self, tx: ResponseTx<HashMap<Id, AccessMethodStats>>) -> Result<()> {
+        self.reply(tx, self.stats.stats.clone())
+    }
+
     pub async fn on_resolve_access_method(
         &mut self,
         tx: ResponseTx<Option<ResolvedConnectionMode>>,
@@ -530,4 +672,91 @@ pub trait AccessMethodResolver: Send + Sync {
     ) -> Option<(AllowedEndpoint, ApiConnectionMode)>;
 
     async fn default_connection_mode(&self) -> AllowedEndpoint;
+
+    /// Called with the initial access method settings, and whenever they change.
+    async fn update_access_methods(&mut self, _access_methods: &Settings) {}
+}
+
+/// [`AccessMethodStats`] for each access method, which are persisted across restarts.
+struct StatsStore {
+    path: PathBuf,
+    stats: HashMap<Id, AccessMethodStats>,
+    last_saved: Option<Instant>,
+}
+
+impl StatsStore {
+    async fn load(path: PathBuf) -> Self {
+        let stats = match fs::read(&path).await {
+            Ok(contents) => serde_json::from_slice(&contents).unwrap_or_else(|error| {
+                log::warn!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to parse access method statistics")
+                );
+                HashMap::new()
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(error) => {
+                log::warn!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to read access method statistics")
+                );
+                HashMap::new()
+            }
+        };
+        Self {
+            path,
+            stats,
+            last_saved: None,
+        }
+    }
+
+    async fn record(&mut self, id: Id, outcome: RequestOutcome) {
+        let now = Utc::now();
+        let stats = self
+            .stats
+            .entry(id)
+            .or_insert_with(|| AccessMethodStats::new(now));
+        match outcome {
+            RequestOutcome::Success { latency } => stats.record_success(latency, now),
+            RequestOutcome::Failure => stats.record_failure(now),
+        }
+
+        let save_due = self
+            .last_saved
+            .map(|last_saved| last_saved.elapsed() >= STATS_SAVE_INTERVAL)
+            .unwrap_or(true);
+        if save_due || outcome == RequestOutcome::Failure {
+            self.save().await;
+        }
+    }
+
+    /// Forget the statistics of access methods which no longer exist.
+    async fn retain(&mut self, access_methods: &Settings) {
+        let count = self.stats.len();
+        self.stats.retain(|id, _| {
+            access_methods
+                .iter()
+                .any(|access_method| &access_method.get_id() == id)
+        });
+        if self.stats.len() != count {
+            self.save().await;
+        }
+    }
+
+    async fn save(&mut self) {
+        self.last_saved = Some(Instant::now());
+        if let Err(error) = self.write().await {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to save access method statistics")
+            );
+        }
+    }
+
+    async fn write(&self) -> io::Result<()> {
+        let data = serde_json::to_vec_pretty(&self.stats)?;
+        let mut file = mullvad_fs::AtomicFile::new(&self.path).await?;
+        file.write_all(&data).await?;
+        file.finalize().await
+    }
 }
@@ -1,8 +1,23 @@
-//! This module keeps track of the last known good API IP address and reads and stores it on disk.
+//! This module keeps track of the known API IP addresses and reads and stores them on disk.
+//!
+//! The cache file contains one address per line, ordered by preference, optionally preceded by
+//! an `expires <RFC 3339 timestamp>` line. Older versions stored a single address without an
+//! expiry, which is read as a stale list with one entry.
+//!
+//! Since the file decides where API traffic is sent, its contents are authenticated by an
+//! `hmac <hex>` first line. The HMAC key is generated once per installation and is only readable
+//! by the daemon. Files that fail verification, including those written by older versions, are
+//! discarded.
 
 use crate::{ApiEndpoint, DnsResolver};
 use async_trait::async_trait;
-use std::{io, net::SocketAddr, path::Path, sync::Arc};
+use chrono::{DateTime, Utc};
+use ring::{
+    hmac,
+    rand::{SecureRandom, SystemRandom},
+};
+use std::{io, net::SocketAddr, path::Path, sync::Arc, time::Duration};
+use talpid_types::net::IpVersion;
 use tokio::{
     fs,
     io::{AsyncReadExt, AsyncWriteExt},
@@ -20,8 +35,116 @@ pub enum Error {
     #[error("Failed to parse the address cache file")]
     Parse,
 
+    #[error("The address cache file failed its integrity check")]
+    Tampered,
+
     #[error("Failed to update the address cache file")]
     Write(#[source] io::Error),
+
+    #[error("Failed to read the address cache key")]
+    ReadKey(#[source] io::Error),
+
+    #[error("The address cache key is invalid")]
+    InvalidKey,
+
+    #[error("Failed to generate the address cache key")]
+    GenerateKey,
+
+    #[error("Failed to save the address cache key")]
+    WriteKey(#[source] io::Error),
+}
+
+/// How long fetched API addresses are trusted before they should be fetched again.
+pub const ADDRESS_TTL: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+const EXPIRY_PREFIX: &str = "expires ";
+const HMAC_PREFIX: &str = "hmac ";
+const KEY_LEN: usize = 32;
+
+/// Per-installation key used to authenticate the address cache file.
+#[derive(Clone)]
+pub struct CacheKey(hmac::Key);
+
+impl CacheKey {
+    /// Load the key stored at `path`.
+    pub async fn load(path: &Path) -> Result<Self, Error> {
+        let key = fs::read(path).await.map_err(Error::ReadKey)?;
+        if key.len() != KEY_LEN {
+            return Err(Error::InvalidKey);
+        }
+        Ok(Self::from_bytes(&key))
+    }
+
+    /// Load the key stored at `path`, or generate and store a new one if there is no valid key.
+    /// A new key invalidates any existing cache file.
+    pub async fn load_or_create(path: &Path) -> Result<Self, Error> {
+        match Self::load(path).await {
+            Ok(key) => return Ok(key),
+            Err(Error::ReadKey(error)) if error.kind() == io::ErrorKind::NotFound => (),
+            Err(Error::InvalidKey) => log::warn!("Replacing invalid address cache key"),
+            Err(error) => return Err(error),
+        }
+
+        let mut key = [0u8; KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut key)
+            .map_err(|_| Error::GenerateKey)?;
+
+        let mut file = Self::file_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .await
+            .map_err(Error::WriteKey)?;
+        file.write_all(&key).await.map_err(Error::WriteKey)?;
+        file.sync_all().await.map_err(Error::WriteKey)?;
+
+        Ok(Self::from_bytes(&key))
+    }
+
+    fn from_bytes(key: &[u8]) -> Self {
+        Self(hmac::Key::new(hmac::HMAC_SHA256, key))
+    }
+
+    fn file_options() -> fs::OpenOptions {
+        let mut options = fs::OpenOptions::new();
+        #[cfg(unix)]
+        {
+            // Only the daemon may read the key
+            options.mode(0o600);
+        }
+        #[cfg(windows)]
+        {
+            // The cache directory is only accessible by administrators
+            options.share_mode(0);
+        }
+        options
+    }
+
+    /// Prepend an HMAC of `contents` to it.
+    fn sign(&self, contents: &str) -> String {
+        let tag = hmac::sign(&self.0, contents.as_bytes());
+        format!("{HMAC_PREFIX}{}\n{contents}", hex::encode(tag.as_ref()))
+    }
+
+    /// Verify and strip the HMAC line of `signed`.
+    fn verify<'a>(&self, signed: &'a str) -> Result<&'a str, Error> {
+        let (tag, contents) = signed
+            .strip_prefix(HMAC_PREFIX)
+            .and_then(|signed| signed.split_once('\n'))
+            .ok_or(Error::Tampered)?;
+        let tag = hex::decode(tag.trim()).map_err(|_| Error::Tampered)?;
+        hmac::verify(&self.0, contents.as_bytes(), &tag).map_err(|_| Error::Tampered)?;
+        Ok(contents)
+    }
+}
+
+/// Location of the address cache file and the key that authenticates it.
+#[derive(Clone)]
+pub struct CacheFile {
+    pub path: Box<Path>,
+    pub key: CacheKey,
 }
 
 /// A DNS resolver which resolves using `AddressCache`.
@@ -30,80 +153,191 @@ impl DnsResolver for AddressCache {
     async fn resolve(&self, host: String) -> Result<Vec<SocketAddr>, io::Error> {
         self.resolve_hostname(&host)
             .await
-            .map(|addr| vec![addr])
             .ok_or(io::Error::other("host does not match API host"))
     }
+
+    async fn report_failure(&self, address: SocketAddr) {
+        if let Err(error) = self.rotate_address(address).await {
+            log::error!("Failed to save rotated API addresses: {error}");
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct AddressCache {
     hostname: String,
     inner: Arc<Mutex<AddressCacheInner>>,
-    write_path: Option<Arc<Path>>,
+    write_file: Option<Arc<CacheFile>>,
 }
 
 impl AddressCache {
-    /// Initialize cache using the hardcoded address, and write changes to `write_path`.
-    pub fn new(endpoint: &ApiEndpoint, write_path: Option<Box<Path>>) -> Self {
-        Self::new_inner(endpoint.address(), endpoint.host().to_owned(), write_path)
+    /// Initialize cache using the hardcoded address, and write changes to `write_file`. The
+    /// hardcoded address is considered stale.
+    pub fn new(endpoint: &ApiEndpoint, write_file: Option<CacheFile>) -> Self {
+        let cache = AddressCacheInner::from_addresses(vec![endpoint.address()], None);
+        Self::new_inner(cache, endpoint.host().to_owned(), write_file)
     }
 
-    /// Initialize cache using `read_path`, and write changes to `write_path`.
+    /// Initialize cache using `cache_file`, and write changes back to it if `write_changes` is
+    /// set. If the cached addresses have expired, the hardcoded address of `endpoint` is kept as a
+    /// last resort. A file that fails its integrity check is removed if `write_changes` is set.
     pub async fn from_file(
-        read_path: &Path,
-        write_path: Option<Box<Path>>,
-        hostname: String,
+        cache_file: CacheFile,
+        write_changes: bool,
+        endpoint: &ApiEndpoint,
     ) -> Result<Self, Error> {
-        log::debug!("Loading API addresses from {}", read_path.display());
-        let address = read_address_file(read_path).await?;
-        Ok(Self::new_inner(address, hostname, write_path))
+        log::debug!("Loading API addresses from {}", cache_file.path.display());
+        let mut cache = match read_address_file(&cache_file).await {
+            Ok(cache) => cache,
+            Err(Error::Tampered) => {
+                if write_changes {
+                    if let Err(error) = fs::remove_file(&cache_file.path).await {
+                        log::error!("Failed to remove address cache file: {error}");
+                    }
+                }
+                return Err(Error::Tampered);
+            }
+            Err(error) => return Err(error),
+        };
+        if cache.is_stale(Utc::now()) {
+            log::debug!("Cached API addresses have expired");
+            cache.add_fallback(endpoint.address());
+        }
+        Ok(Self::new_inner(
+            cache,
+            endpoint.host().to_owned(),
+            write_changes.then_some(cache_file),
+        ))
     }
 
-    fn new_inner(address: SocketAddr, hostname: String, write_path: Option<Box<Path>>) -> Self {
-        let cache = AddressCacheInner::from_address(address);
-        log::debug!("Using API address: {}", cache.address);
+    fn new_inner(
+        cache: AddressCacheInner,
+        hostname: String,
+        write_file: Option<CacheFile>,
+    ) -> Self {
+        log::debug!("Using API address: {}", cache.address());
 
         Self {
             inner: Arc::new(Mutex::new(cache)),
-            write_path: write_path.map(Arc::from),
+            write_file: write_file.map(Arc::new),
             hostname,
         }
     }
 
-    /// Returns the address if the hostname equals `API.host`. Otherwise, returns `None`.
-    async fn resolve_hostname(&self, hostname: &str) -> Option<SocketAddr> {
+    /// Returns all addresses in the order they should be tried if the hostname equals
+    /// `API.host`. Otherwise, returns `None`. See [`Self::set_ip_version_preference`].
+    async fn resolve_hostname(&self, hostname: &str) -> Option<Vec<SocketAddr>> {
+        let inner = self.inner.lock().await;
+        #[cfg(feature = "api-override")]
+        if let Some(endpoint_override) = &inner.endpoint_override {
+            if hostname.eq_ignore_ascii_case(&endpoint_override.host) {
+                return Some(vec![endpoint_override.address]);
+            }
+        }
         if hostname.eq_ignore_ascii_case(&self.hostname) {
-            Some(self.get_address().await)
+            Some(inner.candidates())
         } else {
             None
         }
     }
 
+    /// Send requests for `endpoint` to its address instead of the cached ones, or stop doing so if
+    /// `endpoint` is `None`. While an override is set, fetched addresses and failures are ignored,
+    /// and nothing is written to disk.
+    #[cfg(feature = "api-override")]
+    pub async fn set_override(&self, endpoint: Option<&ApiEndpoint>) {
+        let endpoint_override = endpoint.map(|endpoint| EndpointOverride {
+            host: endpoint.host().to_owned(),
+            address: endpoint.address(),
+        });
+        match &endpoint_override {
+            Some(endpoint_override) => log::debug!(
+                "Overriding API address: {} at {}",
+                endpoint_override.host,
+                endpoint_override.address
+            ),
+            None => log::debug!("Removing API address override"),
+        }
+        self.inner.lock().await.endpoint_override = endpoint_override;
+    }
+
+    /// Set which IP version to try first when resolving the API hostname. The resolved addresses
+    /// alternate between IPv4 and IPv6, so that connection attempts can be raced against each
+    /// other. If `preference` is `None`, the version of the selected address goes first.
+    pub async fn set_ip_version_preference(&self, preference: Option<IpVersion>) {
+        self.inner.lock().await.ip_version_preference = preference;
+    }
+
     /// Returns the currently selected address.
     pub async fn get_address(&self) -> SocketAddr {
-        self.inner.lock().await.address
+        let inner = self.inner.lock().await;
+        #[cfg(feature = "api-override")]
+        if let Some(endpoint_override) = &inner.endpoint_override {
+            return endpoint_override.address;
+        }
+        inner.address()
+    }
+
+    /// Returns whether the addresses have expired and should be fetched again.
+    pub async fn is_stale(&self) -> bool {
+        self.inner.lock().await.is_stale(Utc::now())
     }
 
-    pub async fn set_address(&self, address: SocketAddr) -> Result<(), Error> {
+    /// Returns when the addresses expire, or `None` if they are already considered stale.
+    pub async fn expires(&self) -> Option<DateTime<Utc>> {
+        self.inner.lock().await.expires
+    }
+
+    /// Replace the known addresses with freshly fetched `addresses`, ordered by preference. The
+    /// first address is selected, and the addresses expire after [`ADDRESS_TTL`]. Empty lists
+    /// are ignored.
+    pub async fn set_addresses(&self, addresses: Vec<SocketAddr>) -> Result<(), Error> {
+        if addresses.is_empty() {
+            return Ok(());
+        }
+        let expires = Utc::now() + ADDRESS_TTL;
+        let mut new_cache = AddressCacheInner::from_addresses(addresses, Some(expires));
         let mut inner = self.inner.lock().await;
-        if address != inner.address {
-            self.save_to_disk(&address).await?;
-            inner.address = address;
+        #[cfg(feature = "api-override")]
+        if inner.endpoint_override.is_some() {
+            log::debug!("Ignoring fetched API addresses since the API endpoint is overridden");
+            return Ok(());
         }
+        new_cache.ip_version_preference = inner.ip_version_preference;
+        // Refresh the expiry even if the addresses are unchanged
+        self.save_to_disk(&new_cache).await?;
+        *inner = new_cache;
         Ok(())
     }
 
-    async fn save_to_disk(&self, address: &SocketAddr) -> Result<(), Error> {
-        let write_path = match self.write_path.as_ref() {
-            Some(write_path) => write_path,
+    /// Move `failed_address` to the back of the list if it is the selected address, so that the
+    /// next best address is used instead.
+    async fn rotate_address(&self, failed_address: SocketAddr) -> Result<(), Error> {
+        let mut inner = self.inner.lock().await;
+        #[cfg(feature = "api-override")]
+        if inner.endpoint_override.is_some() {
+            return Ok(());
+        }
+        if !inner.rotate(failed_address) {
+            return Ok(());
+        }
+        log::debug!(
+            "Switching API address from {failed_address} to {}",
+            inner.address()
+        );
+        self.save_to_disk(&inner).await
+    }
+
+    async fn save_to_disk(&self, cache: &AddressCacheInner) -> Result<(), Error> {
+        let write_file = match self.write_file.as_ref() {
+            Some(write_file) => write_file,
             None => return Ok(()),
         };
 
-        let mut file = mullvad_fs::AtomicFile::new(&**write_path)
+        let contents = write_file.key.sign(&cache.to_file_contents());
+        let mut file = mullvad_fs::AtomicFile::new(&*write_file.path)
             .await
             .map_err(Error::Open)?;
-        let mut contents = address.to_string();
-        contents += "\n";
         file.write_all(contents.as_bytes())
             .await
             .map_err(Error::Write)?;
@@ -111,22 +345,251 @@ impl AddressCache {
     }
 }
 
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Debug)]
 struct AddressCacheInner {
+    /// Known addresses, ordered by preference. This is never empty.
+    addresses: Vec<SocketAddr>,
+    /// When the addresses should be fetched again. `None` if their age is unknown.
+    expires: Option<DateTime<Utc>>,
+    /// IP version to try first. This is not persisted.
+    ip_version_preference: Option<IpVersion>,
+    /// Endpoint set at runtime, which takes precedence over `addresses`. This is not persisted.
+    #[cfg(feature = "api-override")]
+    endpoint_override: Option<EndpointOverride>,
+}
+
+#[cfg(feature = "api-override")]
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct EndpointOverride {
+    host: String,
     address: SocketAddr,
 }
 
 impl AddressCacheInner {
-    fn from_address(address: SocketAddr) -> Self {
-        Self { address }
+    fn from_addresses(addresses: Vec<SocketAddr>, expires: Option<DateTime<Utc>>) -> Self {
+        assert!(!addresses.is_empty(), "no API addresses");
+        Self {
+            addresses,
+            expires,
+            ip_version_preference: None,
+            #[cfg(feature = "api-override")]
+            endpoint_override: None,
+        }
+    }
+
+    /// All addresses, alternating between IP versions and starting with the preferred one, as
+    /// recommended by RFC 8305. The ranking is kept within each IP version.
+    fn candidates(&self) -> Vec<SocketAddr> {
+        let first_version = self
+            .ip_version_preference
+            .unwrap_or_else(|| IpVersion::from(self.address().ip()));
+        let (preferred, other): (Vec<_>, Vec<_>) = self
+            .addresses
+            .iter()
+            .partition(|addr| IpVersion::from(addr.ip()) == first_version);
+
+        let mut preferred = preferred.into_iter();
+        let mut other = other.into_iter();
+        let mut candidates = Vec::with_capacity(self.addresses.len());
+        loop {
+            match (preferred.next(), other.next()) {
+                (None, None) => break,
+                (first, second) => {
+                    candidates.extend(first);
+                    candidates.extend(second);
+                }
+            }
+        }
+        candidates
+    }
+
+    fn is_stale(&self, now: DateTime<Utc>) -> bool {
+        self.expires.map_or(true, |expires| expires <= now)
+    }
+
+    /// Add `address` as the least preferred address, unless it is already known.
+    fn add_fallback(&mut self, address: SocketAddr) {
+        if !self.addresses.contains(&address) {
+            self.addresses.push(address);
+        }
+    }
+
+    fn to_file_contents(&self) -> String {
+        let mut contents = String::new();
+        if let Some(expires) = self.expires {
+            contents += EXPIRY_PREFIX;
+            contents += &expires.to_rfc3339();
+            contents += "\n";
+        }
+        for address in &self.addresses {
+            contents += &address.to_string();
+            contents += "\n";
+        }
+        contents
+    }
+
+    /// The selected address.
+    fn address(&self) -> SocketAddr {
+        self.addresses[0]
+    }
+
+    /// Move `failed_address` to the back of the list if it is the selected address. Returns
+    /// whether another address was selected.
+    fn rotate(&mut self, failed_address: SocketAddr) -> bool {
+        if self.addresses.len() < 2 || self.address() != failed_address {
+            return false;
+        }
+        self.addresses.rotate_left(1);
+        true
     }
 }
 
-async fn read_address_file(path: &Path) -> Result<SocketAddr, Error> {
-    let mut file = fs::File::open(path).await.map_err(Error::Open)?;
-    let mut address = String::new();
-    file.read_to_string(&mut address)
+async fn read_address_file(cache_file: &CacheFile) -> Result<AddressCacheInner, Error> {
+    let mut file = fs::File::open(&cache_file.path)
+        .await
+        .map_err(Error::Open)?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)
         .await
         .map_err(Error::Read)?;
-    address.trim().parse().map_err(|_| Error::Parse)
+    parse_addresses(cache_file.key.verify(&contents)?)
+}
+
+/// Parse an optional expiry line followed by one address per line, ignoring blank lines.
+fn parse_addresses(contents: &str) -> Result<AddressCacheInner, Error> {
+    let mut lines = contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .peekable();
+    let expires = match lines.next_if(|line| line.starts_with(EXPIRY_PREFIX)) {
+        Some(line) => Some(
+            DateTime::parse_from_rfc3339(&line[EXPIRY_PREFIX.len()..])
+                .map_err(|_| Error::Parse)?
+                .with_timezone(&Utc),
+        ),
+        None => None,
+    };
+    let addresses = lines
+        .map(|line| line.parse().map_err(|_| Error::Parse))
+        .collect::<Result<Vec<_>, _>>()?;
+    if addresses.is_empty() {
+        return Err(Error::Parse);
+    }
+    Ok(AddressCacheInner::from_addresses(addresses, expires))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_single_address() {
+        assert_eq!(
+            parse_addresses("45.83.223.196:443\n").unwrap(),
+            AddressCacheInner::from_addresses(vec!["45.83.223.196:443".parse().unwrap()], None)
+        );
+    }
+
+    #[test]
+    fn test_parse_multiple_addresses() {
+        assert_eq!(
+            parse_addresses("45.83.223.196:443\n\n[2a03:1b20:4:f011::999]:443\n")
+                .unwrap()
+                .addresses,
+            vec![
+                "45.83.223.196:443".parse::<SocketAddr>().unwrap(),
+                "[2a03:1b20:4:f011::999]:443".parse().unwrap(),
+            ]
+        );
+        assert!(parse_addresses("").is_err());
+        assert!(parse_addresses("45.83.223.196:443\nnot an address\n").is_err());
+    }
+
+    #[test]
+    fn test_rotate() {
+        let first: SocketAddr = "45.83.223.196:443".parse().unwrap();
+        let second: SocketAddr = "45.83.223.197:443".parse().unwrap();
+        let mut cache = AddressCacheInner::from_addresses(vec![first, second], None);
+
+        // Failures of addresses that are not selected are stale
+        assert!(!cache.rotate(second));
+        assert_eq!(cache.address(), first);
+
+        assert!(cache.rotate(first));
+        assert_eq!(cache.address(), second);
+        assert!(cache.rotate(second));
+        assert_eq!(cache.address(), first);
+
+        let mut single = AddressCacheInner::from_addresses(vec![first], None);
+        assert!(!single.rotate(first));
+    }
+
+    #[test]
+    fn test_expiry() {
+        let address: SocketAddr = "45.83.223.196:443".parse().unwrap();
+        let expires = DateTime::parse_from_rfc3339("2024-01-08T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let cache = AddressCacheInner::from_addresses(vec![address], Some(expires));
+
+        let contents = cache.to_file_contents();
+        assert_eq!(parse_addresses(&contents).unwrap(), cache);
+
+        assert!(!cache.is_stale(expires - ADDRESS_TTL));
+        assert!(cache.is_stale(expires));
+
+        // Addresses of unknown age are always stale
+        assert!(parse_addresses("45.83.223.196:443\n")
+            .unwrap()
+            .is_stale(expires - ADDRESS_TTL));
+        assert!(parse_addresses("expires yesterday\n45.83.223.196:443\n").is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let key = CacheKey::from_bytes(&[1; KEY_LEN]);
+        let contents = "45.83.223.196:443\n";
+
+        let signed = key.sign(contents);
+        assert_eq!(key.verify(&signed).unwrap(), contents);
+
+        let tampered = signed.replace("196", "197");
+        assert!(matches!(key.verify(&tampered), Err(Error::Tampered)));
+
+        let other_key = CacheKey::from_bytes(&[2; KEY_LEN]);
+        assert!(matches!(other_key.verify(&signed), Err(Error::Tampered)));
+
+        // Files written by older versions are not signed
+        assert!(matches!(key.verify(contents), Err(Error::Tampered)));
+    }
+
+    #[test]
+    fn test_candidates() {
+        let v4_first: SocketAddr = "45.83.223.196:443".parse().unwrap();
+        let v4_second: SocketAddr = "45.83.223.197:443".parse().unwrap();
+        let v4_third: SocketAddr = "45.83.223.198:443".parse().unwrap();
+        let v6: SocketAddr = "[2a03:1b20:4:f011::999]:443".parse().unwrap();
+        let mut cache =
+            AddressCacheInner::from_addresses(vec![v4_first, v4_second, v6, v4_third], None);
+
+        assert_eq!(cache.candidates(), vec![v4_first, v6, v4_second, v4_third]);
+
+        cache.ip_version_preference = Some(IpVersion::V6);
+        assert_eq!(cache.candidates(), vec![v6, v4_first, v4_second, v4_third]);
+
+        cache.ip_version_preference = Some(IpVersion::V4);
+        assert_eq!(cache.candidates(), vec![v4_first, v6, v4_second, v4_third]);
+    }
+
+    #[test]
+    fn test_add_fallback() {
+        let cached: SocketAddr = "45.83.223.196:443".parse().unwrap();
+        let bundled: SocketAddr = "45.83.223.197:443".parse().unwrap();
+        let mut cache = AddressCacheInner::from_addresses(vec![cached], None);
+
+        cache.add_fallback(bundled);
+        cache.add_fallback(cached);
+        assert_eq!(cache.addresses, vec![cached, bundled]);
+    }
 }
@@ -19,7 +19,7 @@ mod imp {
         let relay_list_request = RelayListProxy::new(
             runtime.mullvad_rest_handle(ApiConnectionMode::Direct.into_provider()),
         )
-        .relay_list(None)
+        .relay_list(None, None)
         .await;
 
         let relay_list = match relay_list_request {
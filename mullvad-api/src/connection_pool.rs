@@ -0,0 +1,183 @@
+//! Keeps one HTTP client for each [`ApiConnectionMode`], so that connections established using
+//! an access method are reused by subsequent requests made using the same method.
+
+#[cfg(target_os = "android")]
+use crate::https_client_with_sni::SocketBypassRequest;
+use crate::{
+    https_client_with_sni::{HttpsConnectorWithSni, HttpsConnectorWithSniHandle},
+    proxy::ApiConnectionMode,
+    rest::Error,
+    DnsResolver,
+};
+#[cfg(target_os = "android")]
+use futures::channel::mpsc;
+use http_body_util::combinators::BoxBody;
+use hyper::body::Bytes;
+use hyper_util::rt::{TokioExecutor, TokioTimer};
+use std::{sync::Arc, time::Duration};
+use tokio::time::Instant;
+
+/// How long an unused connection is kept open.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+/// How long connections established using the same client may be reused. After this, new
+/// connections are established even if the old ones are still open.
+const MAX_AGE: Duration = Duration::from_secs(10 * 60);
+
+// TODO: Look into an alternative to using the legacy hyper client `DES-1288`
+pub(crate) type RequestClient =
+    hyper_util::client::legacy::Client<HttpsConnectorWithSni, BoxBody<Bytes, Error>>;
+
+pub(crate) struct ConnectionPool {
+    entries: Vec<PoolEntry>,
+    dns_resolver: Arc<dyn DnsResolver>,
+    #[cfg(target_os = "android")]
+    socket_bypass_tx: Option<mpsc::Sender<SocketBypassRequest>>,
+    #[cfg(any(feature = "api-override", test))]
+    disable_tls: bool,
+}
+
+struct PoolEntry {
+    connection_mode: ApiConnectionMode,
+    client: RequestClient,
+    connector_handle: HttpsConnectorWithSniHandle,
+    created: Instant,
+    last_used: Instant,
+}
+
+impl PoolEntry {
+    fn is_expired(&self, now: Instant) -> bool {
+        now.duration_since(self.created) >= MAX_AGE
+            || now.duration_since(self.last_used) >= IDLE_TIMEOUT
+    }
+}
+
+impl ConnectionPool {
+    pub fn new(
+        dns_resolver: Arc<dyn DnsResolver>,
+        #[cfg(target_os = "android")] socket_bypass_tx: Option<mpsc::Sender<SocketBypassRequest>>,
+        #[cfg(any(feature = "api-override", test))] disable_tls: bool,
+    ) -> Self {
+        Self {
+            entries: vec![],
+            dns_resolver,
+            #[cfg(target_os = "android")]
+            socket_bypass_tx,
+            #[cfg(any(feature = "api-override", test))]
+            disable_tls,
+        }
+    }
+
+    /// Returns the client to use for requests sent using `connection_mode`.
+    ///
+    /// Clients that have not been used for a while, or that are too old, are dropped. Their idle
+    /// connections are closed, but requests that are in flight are allowed to finish.
+    pub fn get(&mut self, connection_mode: &ApiConnectionMode) -> RequestClient {
+        let now = Instant::now();
+        self.entries.retain(|entry| !entry.is_expired(now));
+
+        if let Some(entry) = self
+            .entries
+            .iter_mut()
+            .find(|entry| &entry.connection_mode == connection_mode)
+        {
+            entry.last_used = now;
+            return entry.client.clone();
+        }
+
+        let (connector, connector_handle) = HttpsConnectorWithSni::new(
+            connection_mode.clone(),
+            self.dns_resolver.clone(),
+            #[cfg(target_os = "android")]
+            self.socket_bypass_tx.clone(),
+            #[cfg(any(feature = "api-override", test))]
+            self.disable_tls,
+        );
+        let client = hyper_util::client::legacy::Client::builder(TokioExecutor::new())
+            .pool_idle_timeout(IDLE_TIMEOUT)
+            .pool_timer(TokioTimer::new())
+            .build(connector);
+
+        self.entries.push(PoolEntry {
+            connection_mode: connection_mode.clone(),
+            client: client.clone(),
+            connector_handle,
+            created: now,
+            last_used: now,
+        });
+        client
+    }
+
+    /// Close all connections, including the ones used by in-flight requests.
+    pub fn reset(&mut self) {
+        for entry in self.entries.drain(..) {
+            entry.connector_handle.reset();
+        }
+    }
+
+    /// Set whether new connections should use TLS. Existing connections are closed.
+    #[cfg(feature = "api-override")]
+    pub fn set_disable_tls(&mut self, disable_tls: bool) {
+        self.reset();
+        self.disable_tls = disable_tls;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{proxy::ProxyConfig, NullDnsResolver};
+
+    const TOR: ApiConnectionMode =
+        ApiConnectionMode::Proxied(ProxyConfig::Tor { socks_port: 9050 });
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pool_keyed_by_connection_mode() {
+        let mut pool = ConnectionPool::new(
+            Arc::new(NullDnsResolver),
+            #[cfg(target_os = "android")]
+            None,
+            true,
+        );
+
+        pool.get(&ApiConnectionMode::Direct);
+        pool.get(&ApiConnectionMode::Direct);
+        assert_eq!(pool.entries.len(), 1);
+
+        pool.get(&TOR);
+        assert_eq!(pool.entries.len(), 2);
+
+        pool.reset();
+        assert!(pool.entries.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_pool_expiry() {
+        let mut pool = ConnectionPool::new(
+            Arc::new(NullDnsResolver),
+            #[cfg(target_os = "android")]
+            None,
+            true,
+        );
+
+        pool.get(&ApiConnectionMode::Direct);
+        let created = pool.entries[0].created;
+
+        // Clients that are in use are kept until they reach the maximum age
+        let mut elapsed = Duration::ZERO;
+        while elapsed + IDLE_TIMEOUT / 2 < MAX_AGE {
+            tokio::time::advance(IDLE_TIMEOUT / 2).await;
+            elapsed += IDLE_TIMEOUT / 2;
+            pool.get(&ApiConnectionMode::Direct);
+            assert_eq!(pool.entries[0].created, created);
+        }
+        tokio::time::advance(IDLE_TIMEOUT / 2).await;
+        pool.get(&ApiConnectionMode::Direct);
+        assert_ne!(pool.entries[0].created, created);
+
+        // Idle clients are dropped
+        pool.get(&TOR);
+        tokio::time::advance(IDLE_TIMEOUT).await;
+        pool.get(&ApiConnectionMode::Direct);
+        assert_eq!(pool.entries.len(), 1);
+    }
+}
@@ -121,14 +121,37 @@ impl DevicesProxy {
         &self,
         account: AccountNumber,
         id: DeviceId,
+    ) -> impl Future<Output = Result<(), rest::Error>> + use<> {
+        self.remove_inner(account, id, None)
+    }
+
+    /// Like [`Self::remove`], but the request is tagged with `idempotency_key` so that it can be
+    /// safely replayed.
+    pub fn remove_idempotent(
+        &self,
+        account: AccountNumber,
+        id: DeviceId,
+        idempotency_key: String,
+    ) -> impl Future<Output = Result<(), rest::Error>> + use<> {
+        self.remove_inner(account, id, Some(idempotency_key))
+    }
+
+    fn remove_inner(
+        &self,
+        account: AccountNumber,
+        id: DeviceId,
+        idempotency_key: Option<String>,
     ) -> impl Future<Output = Result<(), rest::Error>> + use<> {
         let service = self.handle.service.clone();
         let factory = self.handle.factory.clone();
         async move {
-            let request = factory
+            let mut request = factory
                 .delete(&format!("{ACCOUNTS_URL_PREFIX}/devices/{id}"))?
                 .expected_status(&[StatusCode::NO_CONTENT])
                 .account(account)?;
+            if let Some(key) = idempotency_key {
+                request = request.idempotency_key(&key)?;
+            }
             service.request(request).await?;
             Ok(())
         }
@@ -0,0 +1,94 @@
+//! Resolve the API hostname over DNS-over-HTTPS (DoH). This is used to bootstrap API connectivity
+//! when system DNS is blocked or poisoned.
+
+use crate::DnsResolver;
+use async_trait::async_trait;
+use hickory_resolver::{config::ResolverOpts, TokioAsyncResolver};
+use mullvad_encrypted_dns_proxy::config_resolver::{
+    default_resolvers, doh_resolver_config, Nameserver,
+};
+use std::{io, net::SocketAddr, time::Duration};
+
+/// How long to wait for the DoH resolvers before giving up on them.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// DNS resolver that looks up hostnames over DoH, followed by the addresses returned by a
+/// fallback resolver, such as the [`AddressCache`](crate::AddressCache).
+pub struct DohDnsResolver<F> {
+    resolver: TokioAsyncResolver,
+    fallback: F,
+}
+
+impl<F: DnsResolver> DohDnsResolver<F> {
+    /// Resolve hostnames using `resolvers`, and then `fallback`.
+    pub fn new(resolvers: &[Nameserver], fallback: F) -> Self {
+        let mut options = ResolverOpts::default();
+        options.timeout = LOOKUP_TIMEOUT;
+        Self {
+            resolver: TokioAsyncResolver::tokio(doh_resolver_config(resolvers), options),
+            fallback,
+        }
+    }
+
+    /// Resolve hostnames using well known public DoH resolvers, and then `fallback`.
+    pub fn with_default_resolvers(fallback: F) -> Self {
+        Self::new(&default_resolvers(), fallback)
+    }
+
+    async fn doh_lookup(&self, host: &str) -> Vec<SocketAddr> {
+        match tokio::time::timeout(LOOKUP_TIMEOUT, self.resolver.lookup_ip(host)).await {
+            Ok(Ok(lookup)) => lookup.iter().map(|ip| SocketAddr::new(ip, 0)).collect(),
+            Ok(Err(error)) => {
+                log::debug!("DoH lookup of {host} failed: {error}");
+                vec![]
+            }
+            Err(_) => {
+                log::debug!("DoH lookup of {host} timed out");
+                vec![]
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<F: DnsResolver> DnsResolver for DohDnsResolver<F> {
+    async fn resolve(&self, host: String) -> io::Result<Vec<SocketAddr>> {
+        let doh_addrs = self.doh_lookup(&host).await;
+        match self.fallback.resolve(host).await {
+            Ok(fallback_addrs) => Ok(merge_addresses(doh_addrs, fallback_addrs)),
+            Err(error) if doh_addrs.is_empty() => Err(error),
+            Err(_) => Ok(doh_addrs),
+        }
+    }
+
+    async fn report_failure(&self, address: SocketAddr) {
+        self.fallback.report_failure(address).await;
+    }
+}
+
+/// Append the addresses in `fallback` whose IPs are not already in `addrs`.
+fn merge_addresses(mut addrs: Vec<SocketAddr>, fallback: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    for addr in fallback {
+        if !addrs.iter().any(|known| known.ip() == addr.ip()) {
+            addrs.push(addr);
+        }
+    }
+    addrs
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_merge_addresses() {
+        let doh_addr: SocketAddr = "45.83.223.196:0".parse().unwrap();
+        let cached_addr: SocketAddr = "45.83.223.197:443".parse().unwrap();
+
+        let merged = merge_addresses(
+            vec![doh_addr],
+            vec!["45.83.223.196:443".parse().unwrap(), cached_addr],
+        );
+        assert_eq!(merged, vec![doh_addr, cached_addr]);
+    }
+}
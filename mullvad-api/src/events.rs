@@ -0,0 +1,110 @@
+//! Long-polling subscription to account and device events, so that changes made elsewhere, such
+//! as payments or revoked devices, are learned about as soon as they happen.
+
+use chrono::{DateTime, Utc};
+use hyper::StatusCode;
+use mullvad_types::{account::AccountNumber, device::DeviceId};
+use std::{future::Future, time::Duration};
+
+use crate::{rest, ACCOUNTS_URL_PREFIX};
+
+/// How long the API holds a poll request open when there are no new events.
+pub const POLL_TIMEOUT: Duration = Duration::from_secs(60);
+/// Extra time given to a poll request before it is considered to have failed.
+const POLL_TIMEOUT_MARGIN: Duration = Duration::from_secs(10);
+
+/// An event concerning the account, as pushed by the API.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AccountEvent {
+    /// The expiry of the account changed for some other reason than a payment.
+    ExpiryUpdated { expiry: DateTime<Utc> },
+    /// A payment was received.
+    PaymentReceived { new_expiry: DateTime<Utc> },
+    /// A device was removed from the account.
+    DeviceRevoked { device_id: DeviceId },
+    /// Events not known to this version.
+    #[serde(other)]
+    Unknown,
+}
+
+/// Events returned by a single poll request.
+#[derive(Debug, serde::Deserialize)]
+pub struct EventBatch {
+    /// Passed to the next poll request to receive only events that happened after this batch.
+    pub cursor: String,
+    pub events: Vec<AccountEvent>,
+}
+
+#[derive(Clone)]
+pub struct EventsProxy {
+    handle: rest::MullvadRestHandle,
+}
+
+impl EventsProxy {
+    pub fn new(handle: rest::MullvadRestHandle) -> Self {
+        Self { handle }
+    }
+
+    /// Wait for events that happened after `cursor`. If `cursor` is `None`, only events that
+    /// happen after the request is made are returned. If nothing happens within
+    /// [`POLL_TIMEOUT`], an empty batch is returned.
+    pub fn poll(
+        &self,
+        account: AccountNumber,
+        cursor: Option<String>,
+    ) -> impl Future<Output = Result<EventBatch, rest::Error>> + use<> {
+        let service = self.handle.service.clone();
+        let factory = self.handle.factory.clone();
+
+        async move {
+            let mut path = format!(
+                "{ACCOUNTS_URL_PREFIX}/events?timeout={}",
+                POLL_TIMEOUT.as_secs()
+            );
+            if let Some(cursor) = cursor {
+                path += &format!("&cursor={cursor}");
+            }
+            let request = factory
+                .get(&path)?
+                .expected_status(&[StatusCode::OK])
+                .timeout(POLL_TIMEOUT + POLL_TIMEOUT_MARGIN)
+                .account(account)?;
+            service.request(request).await?.deserialize().await
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_events() {
+        let batch: EventBatch = serde_json::from_str(
+            r#"{
+                "cursor": "42",
+                "events": [
+                    { "type": "payment_received", "new_expiry": "2024-01-08T12:00:00Z" },
+                    { "type": "device_revoked", "device_id": "abc" },
+                    { "type": "newsletter" }
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        assert_eq!(batch.cursor, "42");
+        assert_eq!(
+            batch.events,
+            vec![
+                AccountEvent::PaymentReceived {
+                    new_expiry: "2024-01-08T12:00:00Z".parse().unwrap()
+                },
+                AccountEvent::DeviceRevoked {
+                    device_id: "abc".to_owned()
+                },
+                AccountEvent::Unknown,
+            ]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! HTTP/3 transport for API requests. Some censored networks block the TLS over TCP pattern of
+//! regular API connections, while QUIC traffic to port 443 passes.
+
+#[cfg(target_os = "android")]
+use crate::https_client_with_sni::SocketBypassRequest;
+use crate::{
+    rest::{Error, ResponseBody},
+    tls_stream, DnsResolver,
+};
+#[cfg(target_os = "android")]
+use futures::{
+    channel::{mpsc, oneshot},
+    SinkExt,
+};
+use h3::client::SendRequest;
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
+use hyper::{
+    body::{Buf, Bytes},
+    Uri,
+};
+use quinn::{crypto::rustls::QuicClientConfig, ClientConfig, Endpoint, EndpointConfig};
+#[cfg(target_os = "android")]
+use std::os::unix::io::AsRawFd;
+use std::{
+    future, io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, UdpSocket},
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+use tokio::{sync::Mutex, time::timeout};
+use tokio_rustls::rustls;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_PORT: u16 = 443;
+
+type Connection = SendRequest<h3_quinn::OpenStreams, Bytes>;
+
+/// Sends requests over a shared HTTP/3 connection, which is established on demand.
+#[derive(Clone)]
+pub(crate) struct Http3Client {
+    dns_resolver: Arc<dyn DnsResolver>,
+    connection: Arc<Mutex<Option<Connection>>>,
+    #[cfg(target_os = "android")]
+    socket_bypass_tx: Option<mpsc::Sender<SocketBypassRequest>>,
+}
+
+impl Http3Client {
+    pub fn new(
+        dns_resolver: Arc<dyn DnsResolver>,
+        #[cfg(target_os = "android")] socket_bypass_tx: Option<mpsc::Sender<SocketBypassRequest>>,
+    ) -> Self {
+        Self {
+            dns_resolver,
+            connection: Arc::new(Mutex::new(None)),
+            #[cfg(target_os = "android")]
+            socket_bypass_tx,
+        }
+    }
+
+    /// Drop the current connection, so that the next request establishes a new one.
+    pub fn reset(&self) {
+        let connection = self.connection.clone();
+        tokio::spawn(async move { connection.lock().await.take() });
+    }
+
+    /// Send `request` and wait for the complete response. Unlike with the TCP transport, the
+    /// whole response body is received before this returns.
+    pub async fn request(
+        &self,
+        request: hyper::Request<BoxBody<Bytes, Error>>,
+    ) -> Result<hyper::Response<ResponseBody>, Error> {
+        let mut connection = self.get_connection(request.uri()).await?;
+        let result = Self::send_request(&mut connection, request).await;
+        if result.is_err() {
+            // The connection may be broken. Start over with the next request
+            self.connection.lock().await.take();
+        }
+        result
+    }
+
+    async fn send_request(
+        connection: &mut Connection,
+        request: hyper::Request<BoxBody<Bytes, Error>>,
+    ) -> Result<hyper::Response<ResponseBody>, Error> {
+        let (parts, body) = request.into_parts();
+        let body = body.collect().await?.to_bytes();
+
+        let mut stream = connection
+            .send_request(hyper::Request::from_parts(parts, ()))
+            .await
+            .map_err(Error::http3)?;
+        if !body.is_empty() {
+            stream.send_data(body).await.map_err(Error::http3)?;
+        }
+        stream.finish().await.map_err(Error::http3)?;
+
+        let response = stream.recv_response().await.map_err(Error::http3)?;
+        let mut body = vec![];
+        while let Some(mut chunk) = stream.recv_data().await.map_err(Error::http3)? {
+            body.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+        }
+
+        Ok(response.map(|()| Full::new(Bytes::from(body)).map_err(Error::from).boxed()))
+    }
+
+    /// Returns the current connection, or connects to the host of `uri` if there is none.
+    async fn get_connection(&self, uri: &Uri) -> Result<Connection, Error> {
+        let mut connection = self.connection.lock().await;
+        if let Some(connection) = &*connection {
+            return Ok(connection.clone());
+        }
+
+        let hostname = uri
+            .host()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "missing host"))
+            .map_err(Error::http3)?;
+        let addr = self
+            .resolve_address(hostname, uri.port_u16())
+            .await
+            .map_err(Error::http3)?;
+
+        let new_connection = match timeout(CONNECT_TIMEOUT, self.connect(addr, hostname)).await {
+            Ok(Ok(connection)) => connection,
+            Ok(Err(error)) => {
+                self.dns_resolver.report_failure(addr).await;
+                return Err(error);
+            }
+            Err(_) => {
+                self.dns_resolver.report_failure(addr).await;
+                return Err(Error::TimeoutError);
+            }
+        };
+        *connection = Some(new_connection.clone());
+        Ok(new_connection)
+    }
+
+    async fn resolve_address(&self, hostname: &str, port: Option<u16>) -> io::Result<SocketAddr> {
+        if let Ok(addr) = hostname.parse::<IpAddr>() {
+            return Ok(SocketAddr::new(addr, port.unwrap_or(DEFAULT_PORT)));
+        }
+        let addr = self
+            .dns_resolver
+            .resolve(hostname.to_owned())
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Empty DNS response"))?;
+        let port = match (addr.port(), port) {
+            (_, Some(port)) => port,
+            (0, None) => DEFAULT_PORT,
+            (addr_port, None) => addr_port,
+        };
+        Ok(SocketAddr::new(addr.ip(), port))
+    }
+
+    async fn connect(&self, addr: SocketAddr, hostname: &str) -> Result<Connection, Error> {
+        let bind_addr: IpAddr = match addr {
+            SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+            SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+        };
+        let socket = UdpSocket::bind((bind_addr, 0)).map_err(Error::http3)?;
+        socket.set_nonblocking(true).map_err(Error::http3)?;
+
+        #[cfg(target_os = "android")]
+        if let Some(mut tx) = self.socket_bypass_tx.clone() {
+            let (done_tx, done_rx) = oneshot::channel();
+            let _ = tx.send((socket.as_raw_fd(), done_tx)).await;
+            if done_rx.await.is_err() {
+                log::error!("Failed to bypass socket, connection might fail");
+            }
+        }
+
+        let endpoint = Endpoint::new(
+            EndpointConfig::default(),
+            None,
+            socket,
+            Arc::new(quinn::TokioRuntime),
+        )
+        .map_err(Error::http3)?;
+        let connection = endpoint
+            .connect_with(client_config(), addr, hostname)
+            .map_err(Error::http3)?
+            .await
+            .map_err(Error::http3)?;
+
+        let (mut driver, send_request) = h3::client::new(h3_quinn::Connection::new(connection))
+            .await
+            .map_err(Error::http3)?;
+        tokio::spawn(async move {
+            if let Err(error) = future::poll_fn(|cx| driver.poll_close(cx)).await {
+                log::debug!("HTTP/3 connection closed: {error}");
+            }
+        });
+
+        Ok(send_request)
+    }
+}
+
+fn client_config() -> ClientConfig {
+    static TLS_CONFIG: LazyLock<Arc<QuicClientConfig>> = LazyLock::new(|| {
+        let mut config = rustls::ClientConfig::builder_with_provider(Arc::new(
+            rustls::crypto::ring::default_provider(),
+        ))
+        .with_protocol_versions(&[&rustls::version::TLS13])
+        .expect("ring crypt-prover should support TLS 1.3")
+        .with_root_certificates(tls_stream::read_cert_store())
+        .with_no_client_auth();
+        config.alpn_protocols = vec![b"h3".to_vec()];
+        Arc::new(QuicClientConfig::try_from(config).expect("TLS 1.3 config should support QUIC"))
+    });
+
+    ClientConfig::new(TLS_CONFIG.clone())
+}
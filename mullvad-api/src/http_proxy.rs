@@ -0,0 +1,360 @@
+//! Tunnel TCP connections through HTTP proxies using the `CONNECT` method. This lets API traffic
+//! reach the API on networks where all egress has to go through such a proxy.
+
+use crate::ntlm;
+use base64::{prelude::BASE64_STANDARD, Engine};
+use hickory_resolver::{
+    name_server::{RuntimeProvider, TokioRuntimeProvider},
+    proto::iocompat::AsyncIoTokioAsStd,
+};
+use std::{future::Future, io, net::SocketAddr, pin::Pin, sync::Arc};
+use talpid_types::net::proxy::{HttpAuth, HttpAuthScheme, HttpProxy};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+
+/// Upper limit for the size of the response head sent by the proxy.
+const MAX_RESPONSE_HEAD_LEN: usize = 16 * 1024;
+/// Upper limit for the size of a response body that is discarded during authentication.
+const MAX_RESPONSE_BODY_LEN: usize = 64 * 1024;
+const MAX_HEADERS: usize = 64;
+
+struct Response {
+    status: u16,
+    /// Values of all `Proxy-Authenticate` headers.
+    authenticate: Vec<String>,
+    content_length: usize,
+}
+
+/// Ask the HTTP proxy at the other end of `stream` to open a tunnel to `target`. Once this
+/// returns successfully, everything written to `stream` is forwarded to `target`.
+pub async fn establish_tunnel<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    target: SocketAddr,
+    auth: Option<&HttpAuth>,
+) -> io::Result<()> {
+    let response = match auth {
+        None => connect(stream, target, None).await?,
+        Some(auth) if auth.scheme() == HttpAuthScheme::Basic => {
+            let credentials = format!("{}:{}", auth.username(), auth.password());
+            let authorization = format!("Basic {}", BASE64_STANDARD.encode(credentials));
+            connect(stream, target, Some(&authorization)).await?
+        }
+        Some(auth) => connect_ntlm(stream, target, auth).await?,
+    };
+
+    match response.status {
+        200..=299 => Ok(()),
+        407 => Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "HTTP proxy authentication failed",
+        )),
+        status => Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("HTTP proxy refused to connect to {target}: status {status}"),
+        )),
+    }
+}
+
+/// Authenticate using NTLM. This takes two round trips, both of which have to be made over the
+/// same connection.
+async fn connect_ntlm<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    target: SocketAddr,
+    auth: &HttpAuth,
+) -> io::Result<Response> {
+    let negotiate = format!("NTLM {}", BASE64_STANDARD.encode(ntlm::negotiate_message()));
+    let response = connect(stream, target, Some(&negotiate)).await?;
+    if response.status != 407 {
+        return Ok(response);
+    }
+
+    let challenge = response
+        .authenticate
+        .iter()
+        .find_map(|value| value.strip_prefix("NTLM "))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "HTTP proxy does not support NTLM authentication",
+            )
+        })?;
+    let challenge = BASE64_STANDARD
+        .decode(challenge.trim())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid NTLM challenge"))?;
+    let challenge = ntlm::parse_challenge(&challenge)?;
+
+    discard(stream, response.content_length).await?;
+
+    let credentials = ntlm::Credentials::new(auth.username(), auth.password());
+    let authenticate = format!(
+        "NTLM {}",
+        BASE64_STANDARD.encode(ntlm::authenticate_message(&credentials, &challenge))
+    );
+    connect(stream, target, Some(&authenticate)).await
+}
+
+/// Send a `CONNECT` request and read the head of the response.
+async fn connect<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    target: SocketAddr,
+    authorization: Option<&str>,
+) -> io::Result<Response> {
+    let mut request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+    if let Some(authorization) = authorization {
+        request.push_str(&format!("Proxy-Authorization: {authorization}\r\n"));
+    }
+    request.push_str("Proxy-Connection: Keep-Alive\r\n\r\n");
+    stream.write_all(request.as_bytes()).await?;
+    stream.flush().await?;
+
+    read_response(stream).await
+}
+
+/// Read the response head. This reads a byte at a time, to not consume any data that is sent
+/// through the tunnel after the head.
+async fn read_response<S: AsyncRead + Unpin>(stream: &mut S) -> io::Result<Response> {
+    let mut head = Vec::new();
+    while !head.ends_with(b"\r\n\r\n") {
+        if head.len() >= MAX_RESPONSE_HEAD_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "HTTP proxy response is too large",
+            ));
+        }
+        head.push(stream.read_u8().await?);
+    }
+    parse_response(&head)
+}
+
+fn parse_response(head: &[u8]) -> io::Result<Response> {
+    let invalid_response =
+        || io::Error::new(io::ErrorKind::InvalidData, "Invalid HTTP proxy response");
+
+    let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+    let mut response = httparse::Response::new(&mut headers);
+    if !response
+        .parse(head)
+        .map_err(|_| invalid_response())?
+        .is_complete()
+    {
+        return Err(invalid_response());
+    }
+
+    let mut authenticate = vec![];
+    let mut content_length = 0;
+    for header in response.headers.iter() {
+        if header.name.eq_ignore_ascii_case("Proxy-Authenticate") {
+            let value = std::str::from_utf8(header.value).map_err(|_| invalid_response())?;
+            authenticate.push(value.to_owned());
+        } else if header.name.eq_ignore_ascii_case("Content-Length") {
+            content_length = std::str::from_utf8(header.value)
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .ok_or_else(invalid_response)?;
+        }
+    }
+
+    Ok(Response {
+        status: response.code.ok_or_else(invalid_response)?,
+        authenticate,
+        content_length,
+    })
+}
+
+/// Read and throw away the body of a response, so that the connection can be reused.
+async fn discard<S: AsyncRead + Unpin>(stream: &mut S, len: usize) -> io::Result<()> {
+    if len > MAX_RESPONSE_BODY_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "HTTP proxy response is too large",
+        ));
+    }
+    let mut body = vec![0; len];
+    stream.read_exact(&mut body).await?;
+    Ok(())
+}
+
+/// Runtime for [`hickory_resolver`] which tunnels all TCP connections through an HTTP proxy.
+/// This is used to reach DNS-over-HTTPS resolvers through the proxy.
+#[derive(Clone)]
+pub struct HttpProxyRuntimeProvider {
+    proxy: Arc<HttpProxy>,
+    runtime: TokioRuntimeProvider,
+}
+
+impl HttpProxyRuntimeProvider {
+    pub fn new(proxy: HttpProxy) -> Self {
+        Self {
+            proxy: Arc::new(proxy),
+            runtime: TokioRuntimeProvider::new(),
+        }
+    }
+}
+
+impl RuntimeProvider for HttpProxyRuntimeProvider {
+    type Handle = <TokioRuntimeProvider as RuntimeProvider>::Handle;
+    type Timer = <TokioRuntimeProvider as RuntimeProvider>::Timer;
+    type Udp = <TokioRuntimeProvider as RuntimeProvider>::Udp;
+    type Tcp = AsyncIoTokioAsStd<TcpStream>;
+
+    fn create_handle(&self) -> Self::Handle {
+        self.runtime.create_handle()
+    }
+
+    fn connect_tcp(
+        &self,
+        server_addr: SocketAddr,
+    ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Tcp>>>> {
+        let proxy = self.proxy.clone();
+        Box::pin(async move {
+            let mut stream = TcpStream::connect(proxy.endpoint).await?;
+            establish_tunnel(&mut stream, server_addr, proxy.auth.as_ref()).await?;
+            Ok(AsyncIoTokioAsStd(stream))
+        })
+    }
+
+    /// UDP cannot be sent through an HTTP proxy, so this is not tunneled.
+    fn bind_udp(
+        &self,
+        local_addr: SocketAddr,
+        server_addr: SocketAddr,
+    ) -> Pin<Box<dyn Send + Future<Output = io::Result<Self::Udp>>>> {
+        self.runtime.bind_udp(local_addr, server_addr)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::io::{duplex, AsyncBufReadExt, BufReader};
+
+    /// Read a request head sent by `establish_tunnel`, and return the `Proxy-Authorization`
+    /// header.
+    async fn read_request<S: AsyncRead + Unpin>(stream: &mut BufReader<S>) -> Option<String> {
+        let mut authorization = None;
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            if line == "\r\n" {
+                return authorization;
+            }
+            if let Some(value) = line.strip_prefix("Proxy-Authorization: ") {
+                authorization = Some(value.trim_end().to_owned());
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_basic_auth() {
+        let (mut client, server) = duplex(1024);
+        let target: SocketAddr = "45.83.223.196:443".parse().unwrap();
+
+        let server = tokio::spawn(async move {
+            let mut server = BufReader::new(server);
+            let authorization = read_request(&mut server).await;
+            server
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\ntunneled")
+                .await
+                .unwrap();
+            authorization
+        });
+
+        let auth = HttpAuth::new(
+            HttpAuthScheme::Basic,
+            "Aladdin".to_owned(),
+            "open sesame".to_owned(),
+        )
+        .unwrap();
+        establish_tunnel(&mut client, target, Some(&auth))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            server.await.unwrap().as_deref(),
+            Some("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==")
+        );
+        // Data following the response head must be left in the stream
+        let mut tunneled = [0; 8];
+        client.read_exact(&mut tunneled).await.unwrap();
+        assert_eq!(&tunneled, b"tunneled");
+    }
+
+    #[tokio::test]
+    async fn test_ntlm_auth() {
+        let (mut client, server) = duplex(4096);
+        let target: SocketAddr = "45.83.223.196:443".parse().unwrap();
+
+        let server = tokio::spawn(async move {
+            let mut server = BufReader::new(server);
+            let negotiate = read_request(&mut server).await.unwrap();
+            assert!(negotiate.starts_with("NTLM "));
+
+            let mut challenge = b"NTLMSSP\0".to_vec();
+            challenge.extend_from_slice(&2u32.to_le_bytes());
+            challenge.extend_from_slice(&[0, 0, 0, 0, 48, 0, 0, 0]);
+            challenge.extend_from_slice(&[0; 4]);
+            challenge.extend_from_slice(&[7; 8]);
+            challenge.extend_from_slice(&[0; 8]);
+            challenge.extend_from_slice(&[0, 0, 0, 0, 48, 0, 0, 0]);
+            let response = format!(
+                "HTTP/1.1 407 Proxy Authentication Required\r\n\
+                 Proxy-Authenticate: NTLM {}\r\n\
+                 Content-Length: 4\r\n\r\nbody",
+                BASE64_STANDARD.encode(challenge)
+            );
+            server
+                .get_mut()
+                .write_all(response.as_bytes())
+                .await
+                .unwrap();
+
+            let authenticate = read_request(&mut server).await.unwrap();
+            server
+                .get_mut()
+                .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+                .await
+                .unwrap();
+            authenticate
+        });
+
+        let auth = HttpAuth::new(
+            HttpAuthScheme::Ntlm,
+            "CORP\\user".to_owned(),
+            "hunter2".to_owned(),
+        )
+        .unwrap();
+        establish_tunnel(&mut client, target, Some(&auth))
+            .await
+            .unwrap();
+
+        let authenticate = server.await.unwrap();
+        let authenticate = BASE64_STANDARD
+            .decode(authenticate.strip_prefix("NTLM ").unwrap())
+            .unwrap();
+        assert_eq!(&authenticate[..12], b"NTLMSSP\0\x03\0\0\0");
+    }
+
+    #[tokio::test]
+    async fn test_rejected() {
+        let (mut client, server) = duplex(1024);
+        let target: SocketAddr = "45.83.223.196:443".parse().unwrap();
+
+        tokio::spawn(async move {
+            let mut server = BufReader::new(server);
+            read_request(&mut server).await;
+            server
+                .get_mut()
+                .write_all(b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n")
+                .await
+                .unwrap();
+        });
+
+        let error = establish_tunnel(&mut client, target, None)
+            .await
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::PermissionDenied);
+    }
+}
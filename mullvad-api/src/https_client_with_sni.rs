@@ -1,10 +1,11 @@
 use crate::{
     abortable_stream::{AbortableStream, AbortableStreamHandle},
+    http_proxy,
     proxy::{ApiConnection, ApiConnectionMode, ProxyConfig},
     tls_stream::TlsStream,
     DnsResolver,
 };
-use futures::{channel::mpsc, future, pin_mut, StreamExt};
+use futures::{channel::mpsc, future, pin_mut, stream::FuturesUnordered, StreamExt};
 #[cfg(target_os = "android")]
 use futures::{channel::oneshot, sink::SinkExt};
 use http::uri::Scheme;
@@ -45,29 +46,20 @@ use tower::Service;
 use crate::proxy::ConnectionDecorator;
 
 const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long to wait for a connection attempt before also trying the next address. This is the
+/// "Connection Attempt Delay" recommended by RFC 8305.
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
 
 #[derive(Clone)]
 pub struct HttpsConnectorWithSniHandle {
-    tx: mpsc::UnboundedSender<HttpsConnectorRequest>,
+    tx: mpsc::UnboundedSender<()>,
 }
 
 impl HttpsConnectorWithSniHandle {
     /// Stop all streams produced by this connector
     pub fn reset(&self) {
-        let _ = self.tx.unbounded_send(HttpsConnectorRequest::Reset);
+        let _ = self.tx.unbounded_send(());
     }
-
-    /// Change the proxy settings for the connector
-    pub fn set_connection_mode(&self, proxy: ApiConnectionMode) {
-        let _ = self
-            .tx
-            .unbounded_send(HttpsConnectorRequest::SetConnectionMode(proxy));
-    }
-}
-
-enum HttpsConnectorRequest {
-    Reset,
-    SetConnectionMode(ApiConnectionMode),
 }
 
 #[derive(Clone)]
@@ -78,6 +70,8 @@ enum InnerConnectionMode {
     Shadowsocks(ShadowsocksConfig),
     /// Connect to the destination via a Socks proxy.
     Socks5(SocksConfig),
+    /// Connect to the destination via an HTTP proxy.
+    Http(proxy::HttpProxy),
     /// Connect to the destination via Mullvad Encrypted DNS proxy.
     /// See [`mullvad-encrypted-dns-proxy`] for how the proxy works.
     EncryptedDnsProxy(EncryptedDNSConfig),
@@ -163,6 +157,25 @@ impl InnerConnectionMode {
                 )
                 .await
             }
+            // Set up a tunnel through an HTTP proxy.
+            InnerConnectionMode::Http(http) => {
+                let first_hop = http.endpoint;
+                let make_proxy_stream = |mut tcp_stream| async move {
+                    http_proxy::establish_tunnel(&mut tcp_stream, *addr, http.auth.as_ref())
+                        .await?;
+                    Ok(tcp_stream)
+                };
+                Self::connect_proxied(
+                    first_hop,
+                    hostname,
+                    make_proxy_stream,
+                    #[cfg(target_os = "android")]
+                    socket_bypass_tx,
+                    #[cfg(any(feature = "api-override", test))]
+                    disable_tls,
+                )
+                .await
+            }
             InnerConnectionMode::EncryptedDnsProxy(proxy_config) => {
                 let first_hop = SocketAddr::V4(proxy_config.addr);
                 let make_proxy_stream = |tcp_stream| async {
@@ -263,7 +276,10 @@ impl TryFrom<ApiConnectionMode> for InnerConnectionMode {
     fn try_from(config: ApiConnectionMode) -> Result<Self, Self::Error> {
         use std::net::Ipv4Addr;
         Ok(match config {
-            ApiConnectionMode::Direct => InnerConnectionMode::Direct,
+            // HTTP/3 requests are not sent through this connector
+            ApiConnectionMode::Direct | ApiConnectionMode::DirectHttp3 => {
+                InnerConnectionMode::Direct
+            }
             ApiConnectionMode::Proxied(proxy_settings) => match proxy_settings {
                 ProxyConfig::Shadowsocks(config) => {
                     InnerConnectionMode::Shadowsocks(ShadowsocksConfig {
@@ -284,18 +300,25 @@ impl TryFrom<ApiConnectionMode> for InnerConnectionMode {
                     peer: config.endpoint,
                     authentication: config.auth,
                 }),
+                ProxyConfig::Http(config) => InnerConnectionMode::Http(config),
                 ProxyConfig::EncryptedDnsProxy(config) => {
                     InnerConnectionMode::EncryptedDnsProxy(config)
                 }
+                ProxyConfig::Tor { socks_port } => InnerConnectionMode::Socks5(SocksConfig {
+                    peer: SocketAddr::new(IpAddr::from(Ipv4Addr::LOCALHOST), socks_port),
+                    authentication: None,
+                }),
             },
         })
     }
 }
 
-/// A Connector for the `https` scheme.
+/// A Connector for the `https` scheme. All connections are established using the same
+/// [`ApiConnectionMode`].
 #[derive(Clone)]
 pub struct HttpsConnectorWithSni {
     inner: Arc<Mutex<HttpsConnectorWithSniInner>>,
+    proxy_config: InnerConnectionMode,
     abort_notify: Arc<tokio::sync::Notify>,
     dns_resolver: Arc<dyn DnsResolver>,
     #[cfg(target_os = "android")]
@@ -306,7 +329,6 @@ pub struct HttpsConnectorWithSni {
 
 struct HttpsConnectorWithSniInner {
     stream_handles: Vec<AbortableStreamHandle>,
-    proxy_config: InnerConnectionMode,
 }
 
 #[cfg(target_os = "android")]
@@ -314,6 +336,7 @@ pub type SocketBypassRequest = (RawFd, oneshot::Sender<()>);
 
 impl HttpsConnectorWithSni {
     pub fn new(
+        connection_mode: ApiConnectionMode,
         dns_resolver: Arc<dyn DnsResolver>,
         #[cfg(target_os = "android")] socket_bypass_tx: Option<mpsc::Sender<SocketBypassRequest>>,
         #[cfg(any(feature = "api-override", test))] disable_tls: bool,
@@ -322,35 +345,21 @@ impl HttpsConnectorWithSni {
         let abort_notify = Arc::new(tokio::sync::Notify::new());
         let inner = Arc::new(Mutex::new(HttpsConnectorWithSniInner {
             stream_handles: vec![],
-            proxy_config: InnerConnectionMode::Direct,
         }));
+        let proxy_config = InnerConnectionMode::try_from(connection_mode).unwrap_or_else(|error| {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to parse API proxy config")
+            );
+            InnerConnectionMode::Direct
+        });
 
         let inner_copy = inner.clone();
         let notify = abort_notify.clone();
         tokio::spawn(async move {
-            // Handle requests by `HttpsConnectorWithSniHandle`s
-            while let Some(request) = rx.next().await {
-                let handles = {
-                    let mut inner = inner_copy.lock().unwrap();
-
-                    if let HttpsConnectorRequest::SetConnectionMode(config) = request {
-                        match InnerConnectionMode::try_from(config) {
-                            Ok(config) => {
-                                inner.proxy_config = config;
-                            }
-                            Err(error) => {
-                                log::error!(
-                                    "{}",
-                                    error.display_chain_with_msg(
-                                        "Failed to parse new API proxy config"
-                                    )
-                                );
-                            }
-                        }
-                    }
-
-                    std::mem::take(&mut inner.stream_handles)
-                };
+            // Handle resets requested by `HttpsConnectorWithSniHandle`s
+            while rx.next().await.is_some() {
+                let handles = std::mem::take(&mut inner_copy.lock().unwrap().stream_handles);
                 for handle in handles {
                     handle.close();
                 }
@@ -361,6 +370,7 @@ impl HttpsConnectorWithSni {
         (
             HttpsConnectorWithSni {
                 inner,
+                proxy_config,
                 abort_notify,
                 dns_resolver,
                 #[cfg(target_os = "android")]
@@ -398,10 +408,13 @@ impl HttpsConnectorWithSni {
             .map_err(|err| io::Error::new(io::ErrorKind::TimedOut, err))?
     }
 
-    /// Resolve the provided `uri` to an IP and port. If the URI contains an IP, that IP will be used.
-    /// Otherwise `dns_resolver` will be used as a fallback.
+    /// Resolve the provided `uri` to IPs and ports, in the order they should be tried. If the URI
+    /// contains an IP, that IP will be used. Otherwise `dns_resolver` will be used as a fallback.
     /// If the URI contains a port, then that port will be used.
-    async fn resolve_address(dns_resolver: &dyn DnsResolver, uri: Uri) -> io::Result<SocketAddr> {
+    async fn resolve_addresses(
+        dns_resolver: &dyn DnsResolver,
+        uri: Uri,
+    ) -> io::Result<Vec<SocketAddr>> {
         const DEFAULT_PORT: u16 = 443;
 
         let hostname = uri.host().ok_or_else(|| {
@@ -409,19 +422,68 @@ impl HttpsConnectorWithSni {
         })?;
         let port = uri.port_u16();
         if let Ok(addr) = hostname.parse::<IpAddr>() {
-            return Ok(SocketAddr::new(addr, port.unwrap_or(DEFAULT_PORT)));
+            return Ok(vec![SocketAddr::new(addr, port.unwrap_or(DEFAULT_PORT))]);
         }
 
         let addrs = dns_resolver.resolve(hostname.to_owned()).await?;
-        let addr = addrs
-            .first()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Empty DNS response"))?;
-        let port = match (addr.port(), port) {
-            (_, Some(port)) => port,
-            (0, None) => DEFAULT_PORT,
-            (addr_port, None) => addr_port,
-        };
-        Ok(SocketAddr::new(addr.ip(), port))
+        if addrs.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::Other, "Empty DNS response"));
+        }
+        Ok(addrs
+            .into_iter()
+            .map(|addr| {
+                let port = match (addr.port(), port) {
+                    (_, Some(port)) => port,
+                    (0, None) => DEFAULT_PORT,
+                    (addr_port, None) => addr_port,
+                };
+                SocketAddr::new(addr.ip(), port)
+            })
+            .collect())
+    }
+
+    /// Connect to the first of `addrs` that accepts a connection. A new attempt is started
+    /// whenever the previous one fails or has not completed within [`CONNECTION_ATTEMPT_DELAY`],
+    /// while earlier attempts are kept running ("Happy Eyeballs", RFC 8305). Addresses that fail
+    /// are reported to `dns_resolver`.
+    async fn connect_first<T, Fut>(
+        addrs: &[SocketAddr],
+        dns_resolver: &dyn DnsResolver,
+        connect: impl Fn(SocketAddr) -> Fut,
+    ) -> io::Result<T>
+    where
+        Fut: Future<Output = io::Result<T>>,
+    {
+        let mut candidates = addrs.iter().copied().peekable();
+        let mut attempts = FuturesUnordered::new();
+        let mut last_error = io::Error::new(io::ErrorKind::Other, "No addresses to connect to");
+
+        loop {
+            if let Some(addr) = candidates.next() {
+                let attempt = connect(addr);
+                attempts.push(async move { (addr, attempt.await) });
+            }
+
+            let finished = if candidates.peek().is_some() {
+                match timeout(CONNECTION_ATTEMPT_DELAY, attempts.next()).await {
+                    Ok(finished) => finished,
+                    // Start the next attempt alongside the pending ones
+                    Err(_) => continue,
+                }
+            } else {
+                attempts.next().await
+            };
+
+            match finished {
+                Some((_, Ok(stream))) => return Ok(stream),
+                Some((addr, Err(error))) => {
+                    log::trace!("Failed to connect to {addr}: {error}");
+                    dns_resolver.report_failure(addr).await;
+                    last_error = error;
+                }
+                None => return Err(last_error),
+            }
+        }
     }
 }
 
@@ -445,6 +507,7 @@ impl Service<Uri> for HttpsConnectorWithSni {
 
     fn call(&mut self, uri: Uri) -> Self::Future {
         let inner = self.inner.clone();
+        let proxy_config = self.proxy_config.clone();
         let abort_notify = self.abort_notify.clone();
         #[cfg(target_os = "android")]
         let socket_bypass_tx = self.socket_bypass_tx.clone();
@@ -466,26 +529,40 @@ impl Service<Uri> for HttpsConnectorWithSni {
                     "invalid url, missing host",
                 ));
             };
-            let addr = Self::resolve_address(&*dns_resolver, uri).await?;
+            let addrs = Self::resolve_addresses(&*dns_resolver, uri).await?;
 
-            // Loop until we have established a connection. This starts over if a new endpoint
-            // is selected while connecting.
+            // Loop until we have established a connection. This starts over if the connector is
+            // reset while connecting.
             let stream = loop {
                 let notify = abort_notify.notified();
-                let proxy_config = { inner.lock().unwrap().proxy_config.clone() };
-                let stream_fut = proxy_config.connect(
-                    &hostname,
-                    &addr,
+                // Racing addresses only makes sense when connecting to them directly
+                let candidates = match proxy_config {
+                    InnerConnectionMode::Direct => &addrs[..],
+                    _ => &addrs[..1],
+                };
+                let stream_fut = Self::connect_first(candidates, &*dns_resolver, |addr| {
+                    let proxy_config = proxy_config.clone();
+                    let hostname = &hostname;
                     #[cfg(target_os = "android")]
-                    socket_bypass_tx.clone(),
-                    #[cfg(any(feature = "api-override", test))]
-                    disable_tls,
-                );
+                    let socket_bypass_tx = socket_bypass_tx.clone();
+                    async move {
+                        proxy_config
+                            .connect(
+                                hostname,
+                                &addr,
+                                #[cfg(target_os = "android")]
+                                socket_bypass_tx,
+                                #[cfg(any(feature = "api-override", test))]
+                                disable_tls,
+                            )
+                            .await
+                    }
+                });
 
                 pin_mut!(stream_fut);
                 pin_mut!(notify);
 
-                // Wait for connection. Abort and retry if we switched to a different server.
+                // Wait for connection. Abort and retry if the connector was reset.
                 if let future::Either::Left((stream, _)) = future::select(stream_fut, notify).await
                 {
                     break stream?;
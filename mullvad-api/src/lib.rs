@@ -2,7 +2,6 @@
 use async_trait::async_trait;
 #[cfg(target_os = "android")]
 use futures::channel::mpsc;
-use hyper::body::Incoming;
 use mullvad_types::account::{AccountData, AccountNumber, VoucherSubmission};
 #[cfg(target_os = "android")]
 use mullvad_types::account::{PlayPurchase, PlayPurchasePaymentToken};
@@ -20,12 +19,17 @@ use talpid_types::ErrorExt;
 pub mod availability;
 use availability::ApiAvailability;
 pub mod rest;
+pub mod retry;
 #[cfg(not(target_os = "ios"))]
 pub mod version;
 
 mod abortable_stream;
 pub mod access_mode;
+mod connection_pool;
+mod http3;
+pub mod http_proxy;
 mod https_client_with_sni;
+mod ntlm;
 pub mod proxy;
 mod tls_stream;
 #[cfg(target_os = "android")]
@@ -34,12 +38,16 @@ pub use crate::https_client_with_sni::SocketBypassRequest;
 mod access;
 mod address_cache;
 pub mod device;
+mod doh_resolver;
+pub mod events;
 mod relay_list;
 
 pub mod ffi;
 
-pub use address_cache::AddressCache;
+pub use address_cache::{AddressCache, CacheFile, CacheKey};
 pub use device::DevicesProxy;
+pub use doh_resolver::DohDnsResolver;
+pub use events::EventsProxy;
 pub use hyper::StatusCode;
 pub use relay_list::RelayListProxy;
 
@@ -62,6 +70,8 @@ pub const MAX_DEVICES_REACHED: &str = "MAX_DEVICES_REACHED";
 pub const PUBKEY_IN_USE: &str = "PUBKEY_IN_USE";
 
 pub const API_IP_CACHE_FILENAME: &str = "api-ip-address.txt";
+/// Key used to authenticate the contents of [`API_IP_CACHE_FILENAME`].
+pub const API_IP_CACHE_KEY_FILENAME: &str = "api-ip-address.key";
 
 const ACCOUNTS_URL_PREFIX: &str = "accounts/v1";
 const APP_URL_PREFIX: &str = "app/v1";
@@ -296,6 +306,9 @@ impl ApiEndpoint {
 #[async_trait]
 pub trait DnsResolver: 'static + Send + Sync {
     async fn resolve(&self, host: String) -> io::Result<Vec<SocketAddr>>;
+
+    /// Called when a connection to `address`, as returned by `resolve`, could not be established.
+    async fn report_failure(&self, _address: SocketAddr) {}
 }
 
 /// DNS resolver that relies on `ToSocketAddrs` (`getaddrinfo`).
@@ -388,30 +401,30 @@ impl Runtime {
         }
 
         let cache_file = cache_dir.join(API_IP_CACHE_FILENAME);
-        let write_file = if write_changes {
-            Some(cache_file.clone().into_boxed_path())
+        let key_file = cache_dir.join(API_IP_CACHE_KEY_FILENAME);
+        // Only the daemon creates the key. Other readers must use an existing one
+        let key = if write_changes {
+            CacheKey::load_or_create(&key_file).await
         } else {
-            None
+            CacheKey::load(&key_file).await
         };
 
-        let address_cache = match AddressCache::from_file(
-            &cache_file,
-            write_file.clone(),
-            endpoint.host().to_owned(),
-        )
-        .await
-        {
-            Ok(cache) => cache,
+        let address_cache = match key {
+            Ok(key) => {
+                let cache_file = CacheFile {
+                    path: cache_file.into_boxed_path(),
+                    key,
+                };
+                Self::load_address_cache(endpoint, cache_file, write_changes).await
+            }
             Err(error) => {
-                if cache_file.exists() {
-                    log::error!(
-                        "{}",
-                        error.display_chain_with_msg(
-                            "Failed to load cached API addresses. Falling back on bundled address"
-                        )
-                    );
-                }
-                AddressCache::new(endpoint, write_file)
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg(
+                        "Failed to load API address cache key. Falling back on bundled address"
+                    )
+                );
+                AddressCache::new(endpoint, None)
             }
         };
 
@@ -427,15 +440,62 @@ impl Runtime {
         })
     }
 
+    /// Load the API addresses from `cache_file`, or fall back on the bundled address.
+    async fn load_address_cache(
+        endpoint: &ApiEndpoint,
+        cache_file: CacheFile,
+        write_changes: bool,
+    ) -> AddressCache {
+        let exists = cache_file.path.exists();
+        let write_file = write_changes.then(|| cache_file.clone());
+        match AddressCache::from_file(cache_file, write_changes, endpoint).await {
+            Ok(cache) => cache,
+            Err(error) => {
+                if exists {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg(
+                            "Failed to load cached API addresses. Falling back on bundled address"
+                        )
+                    );
+                }
+                AddressCache::new(endpoint, write_file)
+            }
+        }
+    }
+
     /// Returns a request factory initialized to create requests for the master API Assumes an API
     /// endpoint that is constructed from env vars, or uses default values.
     pub fn mullvad_rest_handle<T: ConnectionModeProvider + 'static>(
         &self,
         connection_mode_provider: T,
     ) -> rest::MullvadRestHandle {
-        let service = self.new_request_service(
+        self.mullvad_rest_handle_inner(
             connection_mode_provider,
             Arc::new(self.address_cache.clone()),
+        )
+    }
+
+    /// Like [`Self::mullvad_rest_handle`], but the API hostname is first resolved over DoH using
+    /// `resolvers`, before falling back on the cached and bundled addresses. This helps when
+    /// system DNS is blocked or poisoned.
+    pub fn mullvad_rest_handle_with_doh<T: ConnectionModeProvider + 'static>(
+        &self,
+        connection_mode_provider: T,
+        resolvers: &[mullvad_encrypted_dns_proxy::config_resolver::Nameserver],
+    ) -> rest::MullvadRestHandle {
+        let dns_resolver = DohDnsResolver::new(resolvers, self.address_cache.clone());
+        self.mullvad_rest_handle_inner(connection_mode_provider, Arc::new(dns_resolver))
+    }
+
+    fn mullvad_rest_handle_inner<T: ConnectionModeProvider + 'static>(
+        &self,
+        connection_mode_provider: T,
+        dns_resolver: Arc<dyn DnsResolver>,
+    ) -> rest::MullvadRestHandle {
+        let service = self.new_request_service(
+            connection_mode_provider,
+            dns_resolver,
             #[cfg(target_os = "android")]
             self.socket_bypass_tx.clone(),
             #[cfg(any(feature = "api-override", test))]
@@ -490,6 +550,11 @@ impl Runtime {
     pub fn address_cache(&self) -> &AddressCache {
         &self.address_cache
     }
+
+    /// Returns the endpoint the runtime was created with.
+    pub fn endpoint(&self) -> &ApiEndpoint {
+        &self.endpoint
+    }
 }
 
 #[derive(Clone)]
@@ -514,7 +579,7 @@ impl AccountsProxy {
     pub fn get_data_response(
         &self,
         account: AccountNumber,
-    ) -> impl Future<Output = Result<rest::Response<Incoming>, rest::Error>> {
+    ) -> impl Future<Output = Result<rest::Response<rest::ResponseBody>, rest::Error>> {
         let service = self.handle.service.clone();
         let factory = self.handle.factory.clone();
 
@@ -545,7 +610,7 @@ impl AccountsProxy {
 
     pub fn create_account_response(
         &self,
-    ) -> impl Future<Output = Result<rest::Response<Incoming>, rest::Error>> {
+    ) -> impl Future<Output = Result<rest::Response<rest::ResponseBody>, rest::Error>> {
         let service = self.handle.service.clone();
         let factory = self.handle.factory.clone();
 
@@ -561,6 +626,26 @@ impl AccountsProxy {
         &self,
         account: AccountNumber,
         voucher_code: String,
+    ) -> impl Future<Output = Result<VoucherSubmission, rest::Error>> + use<> {
+        self.submit_voucher_inner(account, voucher_code, None)
+    }
+
+    /// Like [`Self::submit_voucher`], but the request is tagged with `idempotency_key` so that it
+    /// can be safely replayed.
+    pub fn submit_voucher_idempotent(
+        &self,
+        account: AccountNumber,
+        voucher_code: String,
+        idempotency_key: String,
+    ) -> impl Future<Output = Result<VoucherSubmission, rest::Error>> + use<> {
+        self.submit_voucher_inner(account, voucher_code, Some(idempotency_key))
+    }
+
+    fn submit_voucher_inner(
+        &self,
+        account: AccountNumber,
+        voucher_code: String,
+        idempotency_key: Option<String>,
     ) -> impl Future<Output = Result<VoucherSubmission, rest::Error>> + use<> {
         #[derive(serde::Serialize)]
         struct VoucherSubmission {
@@ -572,10 +657,13 @@ impl AccountsProxy {
         let submission = VoucherSubmission { voucher_code };
 
         async move {
-            let request = factory
+            let mut request = factory
                 .post_json(&format!("{APP_URL_PREFIX}/submit-voucher"), &submission)?
                 .account(account)?
                 .expected_status(&[StatusCode::OK]);
+            if let Some(key) = idempotency_key {
+                request = request.idempotency_key(&key)?;
+            }
             service.request(request).await?.deserialize().await
         }
     }
@@ -729,7 +817,7 @@ impl ApiProxy {
         self.get_api_addrs_response().await?.deserialize().await
     }
 
-    pub async fn get_api_addrs_response(&self) -> Result<rest::Response<Incoming>, rest::Error> {
+    pub async fn get_api_addrs_response(&self) -> Result<rest::Response<rest::ResponseBody>, rest::Error> {
         let request = self
             .handle
             .factory
@@ -0,0 +1,276 @@
+//! Client side of the NTLMv2 challenge-response handshake described in [MS-NLMP]. Only what is
+//! needed to authenticate against an HTTP proxy is implemented. Signing and sealing of messages
+//! are not supported.
+//!
+//! [MS-NLMP]: https://learn.microsoft.com/en-us/openspecs/windows_protocols/ms-nlmp
+
+use hmac::{Hmac, Mac};
+use md4::{Digest, Md4};
+use md5::Md5;
+use std::{
+    io,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+type HmacMd5 = Hmac<Md5>;
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+
+const NEGOTIATE_MESSAGE: u32 = 1;
+const CHALLENGE_MESSAGE: u32 = 2;
+const AUTHENTICATE_MESSAGE: u32 = 3;
+
+const NEGOTIATE_UNICODE: u32 = 0x0000_0001;
+const REQUEST_TARGET: u32 = 0x0000_0004;
+const NEGOTIATE_NTLM: u32 = 0x0000_0200;
+const NEGOTIATE_ALWAYS_SIGN: u32 = 0x0000_8000;
+const NEGOTIATE_EXTENDED_SESSIONSECURITY: u32 = 0x0008_0000;
+const NEGOTIATE_128: u32 = 0x2000_0000;
+const NEGOTIATE_56: u32 = 0x8000_0000;
+
+const NEGOTIATE_FLAGS: u32 = NEGOTIATE_UNICODE
+    | REQUEST_TARGET
+    | NEGOTIATE_NTLM
+    | NEGOTIATE_ALWAYS_SIGN
+    | NEGOTIATE_EXTENDED_SESSIONSECURITY
+    | NEGOTIATE_128
+    | NEGOTIATE_56;
+
+/// Size of the fixed part of an AUTHENTICATE message, without the optional version and MIC.
+const AUTHENTICATE_HEADER_LEN: usize = 64;
+
+/// Seconds between the Windows epoch (1601-01-01) and the Unix epoch.
+const WINDOWS_EPOCH_OFFSET: u64 = 11_644_473_600;
+
+/// Credentials to authenticate with. The domain is taken from the username if it is given as
+/// `DOMAIN\user`.
+pub struct Credentials<'a> {
+    domain: &'a str,
+    username: &'a str,
+    password: &'a str,
+}
+
+impl<'a> Credentials<'a> {
+    pub fn new(username: &'a str, password: &'a str) -> Self {
+        let (domain, username) = username.split_once('\\').unwrap_or(("", username));
+        Self {
+            domain,
+            username,
+            password,
+        }
+    }
+}
+
+/// The parts of a CHALLENGE message that are needed to compute a response.
+pub struct Challenge {
+    server_challenge: [u8; 8],
+    target_info: Vec<u8>,
+}
+
+/// Returns the NEGOTIATE message which starts the handshake.
+pub fn negotiate_message() -> Vec<u8> {
+    let mut message = Vec::with_capacity(32);
+    message.extend_from_slice(SIGNATURE);
+    message.extend_from_slice(&NEGOTIATE_MESSAGE.to_le_bytes());
+    message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+    // Empty domain and workstation fields
+    message.extend_from_slice(&[0; 16]);
+    message
+}
+
+/// Parses the CHALLENGE message sent by the server in response to the NEGOTIATE message.
+pub fn parse_challenge(message: &[u8]) -> io::Result<Challenge> {
+    if message.len() < 32
+        || &message[..8] != SIGNATURE
+        || read_u32(message, 8) != Some(CHALLENGE_MESSAGE)
+    {
+        return Err(invalid_challenge());
+    }
+    let server_challenge = message[24..32].try_into().unwrap();
+    // Old servers may omit the target info fields entirely
+    let target_info = if message.len() >= 48 {
+        read_field(message, 40)?.to_vec()
+    } else {
+        vec![]
+    };
+    Ok(Challenge {
+        server_challenge,
+        target_info,
+    })
+}
+
+/// Returns the AUTHENTICATE message which answers `challenge`.
+pub fn authenticate_message(credentials: &Credentials<'_>, challenge: &Challenge) -> Vec<u8> {
+    let mut client_challenge = [0u8; 8];
+    ring::rand::SecureRandom::fill(&ring::rand::SystemRandom::new(), &mut client_challenge)
+        .expect("failed to generate NTLM client challenge");
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| (since_epoch.as_secs() + WINDOWS_EPOCH_OFFSET) * 10_000_000)
+        .unwrap_or(0);
+    authenticate_message_inner(credentials, challenge, client_challenge, timestamp)
+}
+
+fn authenticate_message_inner(
+    credentials: &Credentials<'_>,
+    challenge: &Challenge,
+    client_challenge: [u8; 8],
+    timestamp: u64,
+) -> Vec<u8> {
+    let nt_hash = Md4::digest(utf16le(credentials.password));
+    let user_and_domain = [
+        utf16le(&credentials.username.to_uppercase()),
+        utf16le(credentials.domain),
+    ]
+    .concat();
+    let response_key = hmac_md5(&nt_hash, &[&user_and_domain]);
+
+    let mut blob = vec![1, 1, 0, 0, 0, 0, 0, 0];
+    blob.extend_from_slice(&timestamp.to_le_bytes());
+    blob.extend_from_slice(&client_challenge);
+    blob.extend_from_slice(&[0; 4]);
+    blob.extend_from_slice(&challenge.target_info);
+    blob.extend_from_slice(&[0; 4]);
+
+    let nt_proof = hmac_md5(&response_key, &[&challenge.server_challenge, &blob]);
+    let nt_response = [&nt_proof[..], &blob].concat();
+    let lm_proof = hmac_md5(
+        &response_key,
+        &[&challenge.server_challenge, &client_challenge],
+    );
+    let lm_response = [&lm_proof[..], &client_challenge].concat();
+
+    let fields = [
+        lm_response,
+        nt_response,
+        utf16le(credentials.domain),
+        utf16le(credentials.username),
+        // Workstation
+        vec![],
+        // Encrypted random session key
+        vec![],
+    ];
+
+    let mut message =
+        Vec::with_capacity(AUTHENTICATE_HEADER_LEN + fields.iter().map(Vec::len).sum::<usize>());
+    message.extend_from_slice(SIGNATURE);
+    message.extend_from_slice(&AUTHENTICATE_MESSAGE.to_le_bytes());
+    let mut offset = AUTHENTICATE_HEADER_LEN;
+    for field in &fields {
+        let len = field.len() as u16;
+        message.extend_from_slice(&len.to_le_bytes());
+        message.extend_from_slice(&len.to_le_bytes());
+        message.extend_from_slice(&(offset as u32).to_le_bytes());
+        offset += field.len();
+    }
+    message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+    for field in fields {
+        message.extend_from_slice(&field);
+    }
+    message
+}
+
+fn hmac_md5(key: &[u8], data: &[&[u8]]) -> [u8; 16] {
+    let mut mac = HmacMd5::new_from_slice(key).expect("HMAC accepts keys of any size");
+    for data in data {
+        mac.update(data);
+    }
+    mac.finalize().into_bytes().into()
+}
+
+fn utf16le(s: &str) -> Vec<u8> {
+    s.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+fn read_u16(message: &[u8], offset: usize) -> Option<u16> {
+    let bytes = message.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+fn read_u32(message: &[u8], offset: usize) -> Option<u32> {
+    let bytes = message.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Reads the variable length field described by the security buffer at `offset`.
+fn read_field(message: &[u8], offset: usize) -> io::Result<&[u8]> {
+    let len = read_u16(message, offset).ok_or_else(invalid_challenge)?;
+    let start = read_u32(message, offset + 4).ok_or_else(invalid_challenge)?;
+    let start = usize::try_from(start).map_err(|_| invalid_challenge())?;
+    message
+        .get(start..start + usize::from(len))
+        .ok_or_else(invalid_challenge)
+}
+
+fn invalid_challenge() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "Invalid NTLM challenge message")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Test vectors from section 4.2.4 of [MS-NLMP].
+    #[test]
+    fn test_ntlmv2_response() {
+        let target_info = [
+            // MsvAvNbDomainName: "Domain"
+            &[0x02, 0x00, 0x0c, 0x00][..],
+            &utf16le("Domain"),
+            // MsvAvNbComputerName: "Server"
+            &[0x01, 0x00, 0x0c, 0x00],
+            &utf16le("Server"),
+            // MsvAvEOL
+            &[0x00, 0x00, 0x00, 0x00],
+        ]
+        .concat();
+        let challenge = Challenge {
+            server_challenge: [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef],
+            target_info,
+        };
+        let credentials = Credentials::new("Domain\\User", "Password");
+
+        let message = authenticate_message_inner(&credentials, &challenge, [0xaa; 8], 0);
+
+        let lm_response = read_field(&message, 12).unwrap();
+        assert_eq!(
+            lm_response,
+            [
+                0x86, 0xc3, 0x50, 0x97, 0xac, 0x9c, 0xec, 0x10, 0x25, 0x54, 0x76, 0x4a, 0x57, 0xcc,
+                0xcc, 0x19, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa, 0xaa,
+            ]
+        );
+        let nt_response = read_field(&message, 20).unwrap();
+        assert_eq!(
+            nt_response[..16],
+            [
+                0x68, 0xcd, 0x0a, 0xb8, 0x51, 0xe5, 0x1c, 0x96, 0xaa, 0xbc, 0x92, 0x7b, 0xeb, 0xef,
+                0x6a, 0x1c,
+            ]
+        );
+        assert_eq!(read_field(&message, 28).unwrap(), utf16le("Domain"));
+        assert_eq!(read_field(&message, 36).unwrap(), utf16le("User"));
+    }
+
+    #[test]
+    fn test_parse_challenge() {
+        let mut message = SIGNATURE.to_vec();
+        message.extend_from_slice(&CHALLENGE_MESSAGE.to_le_bytes());
+        // Empty target name
+        message.extend_from_slice(&[0, 0, 0, 0, 48, 0, 0, 0]);
+        message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+        message.extend_from_slice(&[7; 8]);
+        // Reserved
+        message.extend_from_slice(&[0; 8]);
+        // Target info
+        message.extend_from_slice(&[4, 0, 4, 0, 48, 0, 0, 0]);
+        message.extend_from_slice(&[0, 0, 0, 0]);
+
+        let challenge = parse_challenge(&message).unwrap();
+        assert_eq!(challenge.server_challenge, [7; 8]);
+        assert_eq!(challenge.target_info, [0, 0, 0, 0]);
+
+        assert!(parse_challenge(&message[..30]).is_err());
+        assert!(parse_challenge(&negotiate_message()).is_err());
+    }
+}
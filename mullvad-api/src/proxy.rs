@@ -2,10 +2,11 @@ use hyper_util::client::legacy::connect::{Connected, Connection};
 use serde::{Deserialize, Serialize};
 use std::{
     io,
-    net::SocketAddr,
+    net::{Ipv4Addr, SocketAddr},
     path::Path,
     pin::Pin,
     task::{self, Poll},
+    time::Duration,
 };
 use talpid_types::{
     net::{proxy, Endpoint, TransportProtocol},
@@ -27,6 +28,18 @@ pub trait ConnectionModeProvider: Send {
 
     /// Receive changes to the connection mode, announced by the provider
     fn receive(&mut self) -> impl std::future::Future<Output = Option<ApiConnectionMode>> + Send;
+
+    /// Report the outcome of a request that was sent using `connection_mode`
+    fn report(&self, _connection_mode: ApiConnectionMode, _outcome: RequestOutcome) {}
+}
+
+/// Outcome of an API request, as reported to a [`ConnectionModeProvider`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RequestOutcome {
+    /// The API responded after `latency`.
+    Success { latency: Duration },
+    /// The API could not be reached.
+    Failure,
 }
 
 pub struct StaticConnectionModeProvider {
@@ -57,6 +70,8 @@ impl ConnectionModeProvider for StaticConnectionModeProvider {
 pub enum ApiConnectionMode {
     /// Connect directly to the target.
     Direct,
+    /// Connect directly to the target using HTTP/3 over QUIC instead of TLS over TCP.
+    DirectHttp3,
     /// Connect to the destination via a proxy.
     Proxied(ProxyConfig),
 }
@@ -66,7 +81,12 @@ pub enum ProxyConfig {
     Shadowsocks(proxy::Shadowsocks),
     Socks5Local(proxy::Socks5Local),
     Socks5Remote(proxy::Socks5Remote),
+    Http(proxy::HttpProxy),
     EncryptedDnsProxy(mullvad_encrypted_dns_proxy::config::ProxyConfig),
+    /// The SOCKS port of a Tor client running on localhost.
+    Tor {
+        socks_port: u16,
+    },
 }
 
 impl ProxyConfig {
@@ -80,10 +100,17 @@ impl ProxyConfig {
             ProxyConfig::Socks5Remote(remote) => {
                 Endpoint::from_socket_address(remote.endpoint, TransportProtocol::Tcp)
             }
+            ProxyConfig::Http(http) => {
+                Endpoint::from_socket_address(http.endpoint, TransportProtocol::Tcp)
+            }
             ProxyConfig::EncryptedDnsProxy(proxy) => {
                 let addr = SocketAddr::V4(proxy.addr);
                 Endpoint::from_socket_address(addr, TransportProtocol::Tcp)
             }
+            ProxyConfig::Tor { socks_port } => {
+                let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, *socks_port));
+                Endpoint::from_socket_address(addr, TransportProtocol::Tcp)
+            }
         }
     }
 }
@@ -94,6 +121,7 @@ impl From<proxy::CustomProxy> for ProxyConfig {
             proxy::CustomProxy::Shadowsocks(shadowsocks) => ProxyConfig::Shadowsocks(shadowsocks),
             proxy::CustomProxy::Socks5Local(socks) => ProxyConfig::Socks5Local(socks),
             proxy::CustomProxy::Socks5Remote(socks) => ProxyConfig::Socks5Remote(socks),
+            proxy::CustomProxy::Http(http) => ProxyConfig::Http(http),
         }
     }
 }
@@ -165,16 +193,24 @@ impl ApiConnectionMode {
     }
 
     /// Returns the remote endpoint required to reach the API, or `None` for
-    /// `ApiConnectionMode::Direct`.
+    /// `ApiConnectionMode::Direct` and `ApiConnectionMode::DirectHttp3`.
     pub fn get_endpoint(&self) -> Option<Endpoint> {
         match self {
-            ApiConnectionMode::Direct => None,
+            ApiConnectionMode::Direct | ApiConnectionMode::DirectHttp3 => None,
             ApiConnectionMode::Proxied(proxy_config) => Some(proxy_config.get_endpoint()),
         }
     }
 
     pub fn is_proxy(&self) -> bool {
-        *self != ApiConnectionMode::Direct
+        matches!(self, ApiConnectionMode::Proxied(_))
+    }
+
+    /// Returns the transport protocol used to reach the API, or the proxy in front of it.
+    pub fn transport_protocol(&self) -> TransportProtocol {
+        match self {
+            ApiConnectionMode::DirectHttp3 => TransportProtocol::Udp,
+            ApiConnectionMode::Direct | ApiConnectionMode::Proxied(_) => TransportProtocol::Tcp,
+        }
     }
 
     pub fn into_provider(self) -> StaticConnectionModeProvider {
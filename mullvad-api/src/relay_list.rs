@@ -1,8 +1,8 @@
 //! A module dedicated to retrieving the relay list from the Mullvad API.
 
-use crate::rest;
+use crate::{rest, retry::RetryPolicy};
 
-use hyper::{body::Incoming, header, StatusCode};
+use hyper::{header, StatusCode};
 use mullvad_types::{location, relay_list};
 use talpid_types::net::wireguard;
 
@@ -11,59 +11,140 @@ use std::{
     future::Future,
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     ops::RangeInclusive,
-    time::Duration,
+    sync::{Arc, Mutex},
 };
 
 /// Fetches relay list from <https://api.mullvad.net/app/v1/relays>
 #[derive(Clone)]
 pub struct RelayListProxy {
     handle: rest::MullvadRestHandle,
+    /// The last complete relay list document received from the server. Deltas are applied to it.
+    delta_base: Arc<Mutex<Option<DeltaBase>>>,
 }
 
-const RELAY_LIST_TIMEOUT: Duration = Duration::from_secs(15);
+/// Relay list document which the server can send deltas against, using delta encoding as
+/// described in RFC 3229.
+struct DeltaBase {
+    etag: String,
+    document: serde_json::Value,
+}
+
+/// Instance manipulation for deltas in the JSON Patch format described in RFC 6902.
+const JSON_PATCH_IM: &str = "json-patch";
 
 impl RelayListProxy {
     /// Construct a new relay list rest client
     pub fn new(handle: rest::MullvadRestHandle) -> Self {
-        Self { handle }
+        Self::with_retry_policy(handle, &RetryPolicy::RELAY_LIST)
+    }
+
+    /// Construct a new relay list rest client, which times out requests according to `policy`
+    pub fn with_retry_policy(handle: rest::MullvadRestHandle, policy: &RetryPolicy) -> Self {
+        Self {
+            handle: handle.with_retry_policy(policy),
+            delta_base: Arc::new(Mutex::new(None)),
+        }
     }
 
-    /// Fetch the relay list
+    /// Fetch the relay list. `etag` and `last_modified` are the validators of the current relay
+    /// list, if there is one. `None` is returned if the current list is up to date.
+    ///
+    /// If the server supports it, only the changes since the last list that was fetched using
+    /// this client are downloaded.
     pub fn relay_list(
         &self,
         etag: Option<String>,
+        last_modified: Option<String>,
     ) -> impl Future<Output = Result<Option<relay_list::RelayList>, rest::Error>> {
-        let request = self.relay_list_response(etag.clone());
+        let delta_base = self.delta_base.clone();
+        let base_document = etag.as_ref().and_then(|etag| {
+            delta_base
+                .lock()
+                .unwrap()
+                .as_ref()
+                .filter(|base| &base.etag == etag)
+                .map(|base| base.document.clone())
+        });
+        let has_validator = etag.is_some() || last_modified.is_some();
+        let request = self.relay_list_request(etag, last_modified.clone(), base_document.is_some());
 
         async move {
-            let response = request.await.map_err(rest::Error::from)?;
+            let response = request.await?;
 
-            if etag.is_some() && response.status() == StatusCode::NOT_MODIFIED {
+            if has_validator && response.status() == StatusCode::NOT_MODIFIED {
                 return Ok(None);
             }
 
-            let etag = Self::extract_etag(&response);
-
-            let relay_list: ServerRelayList = response.deserialize().await?;
-            Ok(Some(relay_list.into_relay_list(etag)))
+            let etag = Self::extract_etag(&response).map(|mut tag| {
+                if tag.starts_with('"') {
+                    tag.insert_str(0, "W/");
+                }
+                tag
+            });
+            let new_last_modified = Self::extract_last_modified(&response);
+
+            let document = if response.status() == StatusCode::IM_USED {
+                let Some(mut document) = base_document.filter(|_| is_json_patch(&response)) else {
+                    return Err(rest::Error::ApiError(
+                        response.status(),
+                        "Unexpected relay list delta".to_owned(),
+                    ));
+                };
+                let delta: json_patch::Patch = response.deserialize().await?;
+                json_patch::patch(&mut document, &delta).map_err(|error| {
+                    rest::Error::ApiError(
+                        StatusCode::IM_USED,
+                        format!("Failed to apply relay list delta: {error}"),
+                    )
+                })?;
+                document
+            } else {
+                response.deserialize().await?
+            };
+
+            let relay_list: ServerRelayList = serde_json::from_value(document.clone())
+                .map_err(|error| rest::Error::DeserializeError(Arc::new(error)))?;
+            *delta_base.lock().unwrap() = etag.clone().map(|etag| DeltaBase { etag, document });
+
+            Ok(Some(relay_list.into_relay_list(
+                etag,
+                new_last_modified.or(last_modified),
+            )))
         }
     }
 
     pub fn relay_list_response(
         &self,
         etag: Option<String>,
-    ) -> impl Future<Output = Result<rest::Response<Incoming>, rest::Error>> {
+    ) -> impl Future<Output = Result<rest::Response<rest::ResponseBody>, rest::Error>> {
+        self.relay_list_request(etag, None, false)
+    }
+
+    fn relay_list_request(
+        &self,
+        etag: Option<String>,
+        last_modified: Option<String>,
+        accept_delta: bool,
+    ) -> impl Future<Output = Result<rest::Response<rest::ResponseBody>, rest::Error>> {
         let service = self.handle.service.clone();
         let request = self.handle.factory.get("app/v1/relays");
 
         async move {
-            let mut request = request?
-                .timeout(RELAY_LIST_TIMEOUT)
-                .expected_status(&[StatusCode::NOT_MODIFIED, StatusCode::OK]);
+            let mut request = request?.expected_status(&[
+                StatusCode::NOT_MODIFIED,
+                StatusCode::OK,
+                StatusCode::IM_USED,
+            ]);
 
             if let Some(ref tag) = etag {
                 request = request.header(header::IF_NONE_MATCH, tag)?;
             }
+            if let Some(ref last_modified) = last_modified {
+                request = request.header(header::IF_MODIFIED_SINCE, last_modified)?;
+            }
+            if accept_delta {
+                request = request.header("a-im", JSON_PATCH_IM)?;
+            }
 
             let response = service.request(request).await?;
 
@@ -71,7 +152,7 @@ impl RelayListProxy {
         }
     }
 
-    pub fn extract_etag(response: &rest::Response<Incoming>) -> Option<String> {
+    pub fn extract_etag(response: &rest::Response<rest::ResponseBody>) -> Option<String> {
         response
             .headers()
             .get(header::ETAG)
@@ -83,6 +164,25 @@ impl RelayListProxy {
                 }
             })
     }
+
+    fn extract_last_modified(response: &rest::Response<rest::ResponseBody>) -> Option<String> {
+        response
+            .headers()
+            .get(header::LAST_MODIFIED)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned)
+    }
+}
+
+/// Returns whether `response` is a delta in the JSON Patch format.
+fn is_json_patch(response: &rest::Response<rest::ResponseBody>) -> bool {
+    response
+        .headers()
+        .get_all("im")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|im| im.trim().eq_ignore_ascii_case(JSON_PATCH_IM))
 }
 
 #[derive(Debug, serde::Deserialize)]
@@ -94,7 +194,11 @@ struct ServerRelayList {
 }
 
 impl ServerRelayList {
-    fn into_relay_list(self, etag: Option<String>) -> relay_list::RelayList {
+    fn into_relay_list(
+        self,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    ) -> relay_list::RelayList {
         let mut countries = BTreeMap::new();
         let Self {
             locations,
@@ -121,12 +225,8 @@ impl ServerRelayList {
         }
 
         relay_list::RelayList {
-            etag: etag.map(|mut tag| {
-                if tag.starts_with('"') {
-                    tag.insert_str(0, "W/");
-                }
-                tag
-            }),
+            etag,
+            last_modified,
             openvpn: openvpn.extract_relays(&mut countries),
             wireguard: wireguard.extract_relays(&mut countries),
             bridge: bridge.extract_relays(&mut countries),
@@ -178,6 +278,7 @@ fn into_mullvad_relay(
         active: relay.active,
         owned: relay.owned,
         provider: relay.provider,
+        asn: relay.asn,
         weight: relay.weight,
         endpoint_data,
         location,
@@ -239,6 +340,8 @@ struct Relay {
     owned: bool,
     location: String,
     provider: String,
+    #[serde(default)]
+    asn: Option<u32>,
     ipv4_addr_in: Ipv4Addr,
     ipv6_addr_in: Option<Ipv6Addr>,
     weight: u64,
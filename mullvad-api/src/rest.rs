@@ -1,10 +1,14 @@
 #[cfg(target_os = "android")]
 pub use crate::https_client_with_sni::SocketBypassRequest;
+#[cfg(feature = "api-override")]
+use crate::ApiEndpoint;
 use crate::{
     access::AccessTokenStore,
     availability::ApiAvailability,
-    https_client_with_sni::{HttpsConnectorWithSni, HttpsConnectorWithSniHandle},
-    proxy::ConnectionModeProvider,
+    connection_pool::{ConnectionPool, RequestClient},
+    http3::Http3Client,
+    proxy::{ApiConnectionMode, ConnectionModeProvider, RequestOutcome},
+    retry::RetryPolicy,
     DnsResolver,
 };
 use futures::{
@@ -13,11 +17,10 @@ use futures::{
 };
 use http_body_util::{combinators::BoxBody, BodyExt, Empty, Full};
 use hyper::{
-    body::{Body, Buf, Bytes, Incoming},
+    body::{Body, Buf, Bytes},
     header::{self, HeaderValue},
     Method, Uri,
 };
-use hyper_util::client::legacy::connect::Connect;
 use mullvad_types::account::AccountNumber;
 use std::{
     borrow::Cow,
@@ -25,16 +28,19 @@ use std::{
     error::Error as StdError,
     str::FromStr,
     sync::{Arc, Weak},
-    time::Duration,
+    time::{Duration, Instant},
 };
 use talpid_types::ErrorExt;
 
 pub use hyper::StatusCode;
 
 const USER_AGENT: &str = "mullvad-app";
+const IDEMPOTENCY_KEY: &str = "idempotency-key";
 
 pub type Result<T> = std::result::Result<T, Error>;
-const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Body of a [`Response`], regardless of the transport used.
+pub type ResponseBody = BoxBody<Bytes, Error>;
+pub(crate) const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Describes all the ways a REST request can fail
 #[derive(thiserror::Error, Debug, Clone)]
@@ -51,6 +57,9 @@ pub enum Error {
     #[error("Hyper error")]
     HyperError(#[from] Arc<hyper::Error>),
 
+    #[error("HTTP/3 error")]
+    Http3Error(#[source] Arc<dyn StdError + Send + Sync>),
+
     #[error("Invalid header value")]
     InvalidHeaderError,
 
@@ -93,7 +102,10 @@ impl Error {
     pub fn is_network_error(&self) -> bool {
         matches!(
             self,
-            Error::HyperError(_) | Error::LegacyHyperError(_) | Error::TimeoutError
+            Error::HyperError(_)
+                | Error::LegacyHyperError(_)
+                | Error::Http3Error(_)
+                | Error::TimeoutError
         )
     }
 
@@ -115,6 +127,10 @@ impl Error {
         }
     }
 
+    pub(crate) fn http3(error: impl StdError + Send + Sync + 'static) -> Self {
+        Error::Http3Error(Arc::new(error))
+    }
+
     pub fn is_aborted(&self) -> bool {
         matches!(self, Error::Aborted)
     }
@@ -139,20 +155,50 @@ impl Error {
     }
 }
 
-// TODO: Look into an alternative to using the legacy hyper client `DES-1288`
-type RequestClient =
-    hyper_util::client::legacy::Client<HttpsConnectorWithSni, BoxBody<Bytes, Error>>;
+/// The client used to send requests, depending on the current [`ApiConnectionMode`].
+#[derive(Clone)]
+enum Transport {
+    /// HTTP/1.1 over TLS over TCP, possibly via a proxy.
+    Tcp(RequestClient),
+    /// HTTP/3 over QUIC.
+    Http3(Http3Client),
+}
+
+impl Transport {
+    async fn request(
+        self,
+        request: hyper::Request<BoxBody<Bytes, Error>>,
+    ) -> Result<hyper::Response<ResponseBody>> {
+        match self {
+            Transport::Tcp(client) => {
+                let response = client.request(request).await?;
+                Ok(response.map(|body| body.map_err(Error::from).boxed()))
+            }
+            Transport::Http3(client) => client.request(request).await,
+        }
+    }
+}
 
 /// A service that executes HTTP requests, allowing for on-demand termination of all in-flight
 /// requests
 pub(crate) struct RequestService<T: ConnectionModeProvider> {
     command_tx: Weak<mpsc::UnboundedSender<RequestCommand>>,
     command_rx: mpsc::UnboundedReceiver<RequestCommand>,
-    connector_handle: HttpsConnectorWithSniHandle,
-    client: RequestClient,
+    connection_pool: ConnectionPool,
+    http3_client: Http3Client,
+    connection_mode: ApiConnectionMode,
     connection_mode_provider: T,
     connection_mode_generation: usize,
     api_availability: ApiAvailability,
+    #[cfg(feature = "api-override")]
+    host_override: Option<HostOverride>,
+}
+
+/// Redirects requests for one API hostname to another.
+#[cfg(feature = "api-override")]
+struct HostOverride {
+    from: String,
+    to: String,
 }
 
 impl<T: ConnectionModeProvider + 'static> RequestService<T> {
@@ -164,31 +210,34 @@ impl<T: ConnectionModeProvider + 'static> RequestService<T> {
         #[cfg(target_os = "android")] socket_bypass_tx: Option<mpsc::Sender<SocketBypassRequest>>,
         #[cfg(any(feature = "api-override", test))] disable_tls: bool,
     ) -> RequestServiceHandle {
-        let (connector, connector_handle) = HttpsConnectorWithSni::new(
-            dns_resolver,
+        let http3_client = Http3Client::new(
+            dns_resolver.clone(),
             #[cfg(target_os = "android")]
             socket_bypass_tx.clone(),
+        );
+        let connection_pool = ConnectionPool::new(
+            dns_resolver,
+            #[cfg(target_os = "android")]
+            socket_bypass_tx,
             #[cfg(any(feature = "api-override", test))]
             disable_tls,
         );
 
-        connector_handle.set_connection_mode(connection_mode_provider.initial());
-
         let (command_tx, command_rx) = mpsc::unbounded();
-        let client =
-            hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
-                .build(connector);
-
         let command_tx = Arc::new(command_tx);
 
+        let connection_mode = connection_mode_provider.initial();
         let service = Self {
             command_tx: Arc::downgrade(&command_tx),
             command_rx,
-            connector_handle,
-            client,
+            connection_pool,
+            http3_client,
+            connection_mode,
             connection_mode_provider,
             connection_mode_generation: 0,
             api_availability,
+            #[cfg(feature = "api-override")]
+            host_override: None,
         };
         let handle = RequestServiceHandle { tx: command_tx };
         tokio::spawn(service.into_future());
@@ -202,7 +251,7 @@ impl<T: ConnectionModeProvider + 'static> RequestService<T> {
                     let Some(new_mode) = new_mode else {
                         break;
                     };
-                    self.connector_handle.set_connection_mode(new_mode);
+                    self.set_connection_mode(new_mode);
                 }
                 command = self.command_rx.next() => {
                     let Some(command) = command else {
@@ -213,7 +262,46 @@ impl<T: ConnectionModeProvider + 'static> RequestService<T> {
                 }
             }
         }
-        self.connector_handle.reset();
+        self.reset();
+    }
+
+    /// Send subsequent requests using `mode`. Connections that were established using other modes
+    /// are kept until they time out, so that they can be reused if the mode is selected again.
+    fn set_connection_mode(&mut self, mode: ApiConnectionMode) {
+        self.http3_client.reset();
+        self.connection_mode = mode;
+    }
+
+    fn reset(&mut self) {
+        self.http3_client.reset();
+        self.connection_pool.reset();
+    }
+
+    /// Send subsequent requests for `hostname` to `endpoint` instead. All connections are closed.
+    #[cfg(feature = "api-override")]
+    fn set_endpoint(&mut self, hostname: String, endpoint: ApiEndpoint) {
+        let host = endpoint.host();
+        self.host_override = (!host.eq_ignore_ascii_case(&hostname)).then(|| HostOverride {
+            from: hostname,
+            to: host.to_owned(),
+        });
+        self.http3_client.reset();
+        self.connection_pool.set_disable_tls(endpoint.disable_tls);
+    }
+
+    #[cfg(feature = "api-override")]
+    fn apply_host_override<B>(&self, mut request: Request<B>) -> Result<Request<B>> {
+        if let Some(host_override) = &self.host_override {
+            let matches = request
+                .request
+                .uri()
+                .host()
+                .is_some_and(|host| host.eq_ignore_ascii_case(&host_override.from));
+            if matches {
+                request.set_host(&host_override.to)?;
+            }
+        }
+        Ok(request)
     }
 
     async fn process_command(&mut self, command: RequestCommand) {
@@ -222,7 +310,15 @@ impl<T: ConnectionModeProvider + 'static> RequestService<T> {
                 self.handle_new_request(request, completion_tx);
             }
             RequestCommand::Reset => {
-                self.connector_handle.reset();
+                self.reset();
+            }
+            #[cfg(feature = "api-override")]
+            RequestCommand::SetEndpoint { hostname, endpoint } => {
+                self.set_endpoint(hostname, endpoint);
+            }
+            RequestCommand::ReportOutcome(connection_mode, outcome) => {
+                self.connection_mode_provider
+                    .report(connection_mode, outcome);
             }
             RequestCommand::NextApiConfig(generation) => {
                 if generation == self.connection_mode_generation {
@@ -237,29 +333,51 @@ impl<T: ConnectionModeProvider + 'static> RequestService<T> {
     fn handle_new_request(
         &mut self,
         request: Request<BoxBody<Bytes, Error>>,
-        completion_tx: oneshot::Sender<Result<Response<Incoming>>>,
+        completion_tx: oneshot::Sender<Result<Response<ResponseBody>>>,
     ) {
+        #[cfg(feature = "api-override")]
+        let request = match self.apply_host_override(request) {
+            Ok(request) => request,
+            Err(error) => {
+                let _ = completion_tx.send(Err(error));
+                return;
+            }
+        };
+
         let tx = self.command_tx.upgrade();
 
         let api_availability = self.api_availability.clone();
-        let request_future = request
-            .map(|r| http::Request::map(r, BodyExt::boxed))
-            .into_future(self.client.clone(), api_availability.clone());
+        let transport = if self.connection_mode == ApiConnectionMode::DirectHttp3 {
+            Transport::Http3(self.http3_client.clone())
+        } else {
+            Transport::Tcp(self.connection_pool.get(&self.connection_mode))
+        };
+        let request_future = request.into_future(transport, api_availability.clone());
 
+        let connection_mode = self.connection_mode.clone();
         let connection_mode_generation = self.connection_mode_generation;
 
         tokio::spawn(async move {
+            let started = Instant::now();
             let response = request_future.await.map_err(|error| error.map_aborted());
 
-            // Switch API endpoint if the request failed due to a network error
-            if let Err(err) = &response {
-                if err.is_network_error() && !api_availability.is_offline() {
+            let outcome = match &response {
+                Ok(_) | Err(Error::ApiError(..)) => Some(RequestOutcome::Success {
+                    latency: started.elapsed(),
+                }),
+                Err(err) if err.is_network_error() && !api_availability.is_offline() => {
                     log::error!("{}", err.display_chain_with_msg("HTTP request failed"));
-                    if let Some(tx) = tx {
-                        let _ = tx.unbounded_send(RequestCommand::NextApiConfig(
-                            connection_mode_generation,
-                        ));
-                    }
+                    Some(RequestOutcome::Failure)
+                }
+                Err(_) => None,
+            };
+
+            if let (Some(outcome), Some(tx)) = (outcome, tx) {
+                let _ = tx.unbounded_send(RequestCommand::ReportOutcome(connection_mode, outcome));
+                // Switch API endpoint if the request failed due to a network error
+                if outcome == RequestOutcome::Failure {
+                    let _ = tx
+                        .unbounded_send(RequestCommand::NextApiConfig(connection_mode_generation));
                 }
             }
 
@@ -281,7 +399,7 @@ impl RequestServiceHandle {
     }
 
     /// Submits a `RestRequest` for execution to the request service.
-    pub async fn request<B>(&self, request: Request<B>) -> Result<Response<Incoming>>
+    pub async fn request<B>(&self, request: Request<B>) -> Result<Response<ResponseBody>>
     where
         B: Body + Send + Sync + 'static,
         Error: From<B::Error>,
@@ -300,9 +418,15 @@ impl RequestServiceHandle {
 pub(crate) enum RequestCommand {
     NewRequest(
         Request<BoxBody<Bytes, Error>>,
-        oneshot::Sender<std::result::Result<Response<Incoming>, Error>>,
+        oneshot::Sender<std::result::Result<Response<ResponseBody>, Error>>,
     ),
     Reset,
+    #[cfg(feature = "api-override")]
+    SetEndpoint {
+        hostname: String,
+        endpoint: ApiEndpoint,
+    },
+    ReportOutcome(ApiConnectionMode, RequestOutcome),
     NextApiConfig(usize),
 }
 
@@ -375,12 +499,32 @@ impl<B: Body> Request<B> {
         Ok(self)
     }
 
+    /// Set a key which lets the API recognize retries of the same request, so that a request
+    /// which is replayed after its response was lost is only applied once.
+    pub fn idempotency_key(self, key: &str) -> Result<Self> {
+        self.header(IDEMPOTENCY_KEY, key)
+    }
+
     /// Returns the URI of the request
     pub fn uri(&self) -> &Uri {
         self.request.uri()
     }
 }
 impl<B> Request<B> {
+    /// Send the request to `host` instead, keeping the scheme and path.
+    #[cfg(feature = "api-override")]
+    fn set_host(&mut self, host: &str) -> Result<()> {
+        let uri = self.request.uri();
+        let scheme = uri.scheme_str().unwrap_or("https");
+        let path = uri.path_and_query().map_or("/", |path| path.as_str());
+        *self.request.uri_mut() = Uri::from_str(&format!("{scheme}://{host}{path}"))?;
+        self.request.headers_mut().insert(
+            header::HOST,
+            HeaderValue::from_str(host).map_err(|_| Error::InvalidHeaderError)?,
+        );
+        Ok(())
+    }
+
     /// Map the underlying [`hyper::Request`] type
     fn map<F, B2>(self, f: F) -> Request<B2>
     where
@@ -422,32 +566,24 @@ where
     }
 }
 
-impl<B> Request<B>
-where
-    B: Body + Send + 'static + Unpin,
-    B::Data: Send,
-    B::Error: Into<Box<dyn StdError + Send + Sync>>,
-{
-    async fn into_future<C: Connect + Clone + Send + Sync + 'static>(
+impl Request<BoxBody<Bytes, Error>> {
+    async fn into_future(
         self,
-        hyper_client: hyper_util::client::legacy::Client<C, B>,
+        transport: Transport,
         api_availability: ApiAvailability,
-    ) -> Result<Response<Incoming>> {
+    ) -> Result<Response<ResponseBody>> {
         let timeout = self.timeout;
-        let inner_fut = self.into_future_without_timeout(hyper_client, api_availability);
+        let inner_fut = self.into_future_without_timeout(transport, api_availability);
         tokio::time::timeout(timeout, inner_fut)
             .await
             .map_err(|_| Error::TimeoutError)?
     }
 
-    async fn into_future_without_timeout<C>(
+    async fn into_future_without_timeout(
         mut self,
-        hyper_client: hyper_util::client::legacy::Client<C, B>,
+        transport: Transport,
         api_availability: ApiAvailability,
-    ) -> Result<Response<Incoming>>
-    where
-        C: Connect + Clone + Send + Sync + 'static,
-    {
+    ) -> Result<Response<ResponseBody>> {
         let _ = api_availability.wait_for_unsuspend().await;
 
         // Obtain access token first
@@ -460,11 +596,8 @@ where
                 .insert(header::AUTHORIZATION, auth);
         }
 
-        // Make request to hyper client
-        let response = hyper_client
-            .request(self.request)
-            .await
-            .map_err(Error::from);
+        // Make request using the current transport
+        let response = transport.request(self.request).await;
 
         // Notify access token store of expired tokens
         if let (Some(account), Some(store)) = (&self.account, &self.access_token_store) {
@@ -742,6 +875,22 @@ impl MullvadRestHandle {
     pub fn service(&self) -> RequestServiceHandle {
         self.service.clone()
     }
+
+    /// Send subsequent requests to `endpoint` instead of the endpoint this handle was created for.
+    /// All connections are closed, including the ones used by in-flight requests.
+    #[cfg(feature = "api-override")]
+    pub fn set_endpoint(&self, endpoint: ApiEndpoint) {
+        let _ = self.service.tx.unbounded_send(RequestCommand::SetEndpoint {
+            hostname: self.factory.hostname.to_string(),
+            endpoint,
+        });
+    }
+
+    /// Use the request timeout of `policy` for all requests created by this handle.
+    pub fn with_retry_policy(mut self, policy: &RetryPolicy) -> Self {
+        self.factory = self.factory.default_timeout(policy.timeout);
+        self
+    }
 }
 
 macro_rules! impl_into_arc_err {
@@ -0,0 +1,183 @@
+//! Policies for how API requests are timed out and retried.
+
+use crate::rest;
+use std::{future::Future, time::Duration};
+use talpid_future::retry::{retry_future, ExponentialBackoff, Jittered};
+
+/// Describes how long to wait for a single API request, and how to retry it when it fails.
+///
+/// The retry count and backoff are applied by [`RetryPolicy::retry`], while the timeout is applied
+/// to all requests made through a handle returned by
+/// [`MullvadRestHandle::with_retry_policy`](rest::MullvadRestHandle::with_retry_policy).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Maximum number of retries. `None` means that the request is retried until it succeeds.
+    pub max_retries: Option<usize>,
+    /// The delays between attempts.
+    pub backoff: Backoff,
+    /// Whether to randomize each delay to avoid many clients retrying at the same time.
+    pub jitter: bool,
+    /// How long to wait for each individual request.
+    pub timeout: Duration,
+}
+
+/// How the delay between retries changes with each failed attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// Wait equally long between all attempts.
+    Constant(Duration),
+    /// The first delay is `initial` long, and each subsequent delay is `factor` times longer than
+    /// the previous one, up to `max_delay`.
+    Exponential {
+        initial: Duration,
+        factor: u32,
+        max_delay: Option<Duration>,
+    },
+}
+
+impl RetryPolicy {
+    /// Policy for user-initiated actions that require immediate feedback. Failed requests are
+    /// retried at most three times, without delay.
+    pub const INTERACTIVE: RetryPolicy = RetryPolicy {
+        max_retries: Some(3),
+        backoff: Backoff::Constant(Duration::ZERO),
+        jitter: false,
+        timeout: rest::DEFAULT_TIMEOUT,
+    };
+
+    /// Policy for background tasks. Failed requests are retried indefinitely, with the delay
+    /// growing up to a day.
+    pub const BACKGROUND: RetryPolicy = RetryPolicy {
+        max_retries: None,
+        backoff: Backoff::Exponential {
+            initial: Duration::from_secs(4),
+            factor: 5,
+            max_delay: Some(Duration::from_secs(24 * 60 * 60)),
+        },
+        jitter: true,
+        timeout: rest::DEFAULT_TIMEOUT,
+    };
+
+    /// Policy for refreshing the relay list in the background. The relay list is large, so each
+    /// request is given more time.
+    pub const RELAY_LIST: RetryPolicy = RetryPolicy {
+        max_retries: None,
+        backoff: Backoff::Exponential {
+            initial: Duration::from_secs(16),
+            factor: 8,
+            max_delay: Some(Duration::from_secs(2 * 60 * 60)),
+        },
+        jitter: true,
+        timeout: Duration::from_secs(15),
+    };
+
+    /// Set the maximum number of retries.
+    pub const fn max_retries(mut self, max_retries: Option<usize>) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delays between attempts.
+    pub const fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the maximum delay of an exponential backoff. This has no effect on a constant backoff.
+    pub const fn max_delay(mut self, max_delay: Option<Duration>) -> Self {
+        if let Backoff::Exponential {
+            initial, factor, ..
+        } = self.backoff
+        {
+            self.backoff = Backoff::Exponential {
+                initial,
+                factor,
+                max_delay,
+            };
+        }
+        self
+    }
+
+    /// Set whether the delays should be randomized.
+    pub const fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Set the timeout of each individual request.
+    pub const fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Returns the delays to wait between attempts. The iterator ends when no more retries
+    /// should be made.
+    pub fn delays(&self) -> Box<dyn Iterator<Item = Duration> + Send> {
+        let delays: Box<dyn Iterator<Item = Duration> + Send> = match self.backoff {
+            Backoff::Constant(interval) => Box::new(std::iter::repeat(interval)),
+            Backoff::Exponential {
+                initial,
+                factor,
+                max_delay,
+            } => Box::new(ExponentialBackoff::new(initial, factor).max_delay(max_delay)),
+        };
+        let delays = delays.take(self.max_retries.unwrap_or(usize::MAX));
+        if self.jitter {
+            Box::new(Jittered::jitter(delays))
+        } else {
+            Box::new(delays)
+        }
+    }
+
+    /// Run the future returned by `factory` until `should_retry` returns false for its output, or
+    /// until this policy runs out of retries.
+    pub fn retry<F, R, O, T>(self, factory: F, should_retry: R) -> impl Future<Output = T>
+    where
+        F: FnMut() -> O,
+        R: FnMut(&T) -> bool,
+        O: Future<Output = T>,
+    {
+        retry_future(factory, should_retry, self.delays())
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::INTERACTIVE
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_max_retries() {
+        let policy = RetryPolicy::INTERACTIVE;
+        assert_eq!(policy.delays().collect::<Vec<_>>(), vec![Duration::ZERO; 3]);
+
+        let policy = policy.max_retries(Some(0));
+        assert_eq!(policy.delays().next(), None);
+    }
+
+    #[test]
+    fn test_exponential_backoff() {
+        let policy = RetryPolicy::BACKGROUND
+            .jitter(false)
+            .backoff(Backoff::Exponential {
+                initial: Duration::from_secs(1),
+                factor: 2,
+                max_delay: Some(Duration::from_secs(5)),
+            });
+        let delays: Vec<_> = policy.delays().take(5).map(|d| d.as_secs()).collect();
+        assert_eq!(delays, vec![1, 2, 4, 5, 5]);
+    }
+
+    #[test]
+    fn test_jitter_bounded() {
+        let policy = RetryPolicy::BACKGROUND.max_retries(Some(10));
+        let mut delays = policy.delays();
+        assert!(delays.next().unwrap() <= Duration::from_secs(4));
+        assert_eq!(delays.count(), 9);
+    }
+}
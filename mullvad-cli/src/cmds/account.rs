@@ -2,12 +2,27 @@ use anyhow::{anyhow, Result};
 use clap::Subcommand;
 use itertools::Itertools;
 use mullvad_management_interface::MullvadProxyClient;
-use mullvad_types::{account::AccountNumber, device::DeviceState};
+use mullvad_types::{
+    account::{AccountData, AccountNumber},
+    device::DeviceState,
+    pending_request::PendingRequest,
+};
+use serde::Serialize;
 use std::io::{self, Write};
 
+use crate::format::{self, OutputFormat};
+
 const NOT_LOGGED_IN_MESSAGE: &str = "Not logged in on any account";
 const REVOKED_MESSAGE: &str = "The current device has been revoked";
 
+/// Output of `account get` in JSON.
+#[derive(Serialize)]
+struct AccountInfo {
+    device: DeviceState,
+    /// Expiry of the account. Only set while logged in.
+    account: Option<AccountData>,
+}
+
 #[derive(Subcommand, Debug)]
 pub enum Account {
     /// Create and log in on a new account
@@ -40,25 +55,48 @@ pub enum Account {
         verbose: bool,
     },
 
-    /// Revoke a device associated with an account
+    /// Revoke one or more devices associated with an account
     RevokeDevice {
-        /// Name or UID of the device to revoke
-        device: String,
+        /// Names or UIDs of the devices to revoke
+        #[arg(value_name = "DEVICE", required_unless_present = "all_except_current")]
+        devices: Vec<String>,
+
+        /// Revoke every device on the account except this one
+        #[arg(long, conflicts_with_all = ["devices", "queue"])]
+        all_except_current: bool,
 
         /// Mullvad account number (current account if not specified)
         #[arg(long, short = 'a')]
         account: Option<String>,
+
+        /// Queue the requests and send them once the API is reachable. If the devices cannot be
+        /// listed, each DEVICE must be the UID of a device
+        #[arg(long)]
+        queue: bool,
     },
 
     /// Redeem a voucher
     Redeem {
         /// Voucher code to submit
         voucher: String,
+
+        /// Queue the request and send it once the API is reachable
+        #[arg(long)]
+        queue: bool,
+    },
+
+    /// List requests which are queued until the API is reachable
+    Pending,
+
+    /// Remove a queued request without sending it
+    CancelPending {
+        /// ID of the queued request
+        id: String,
     },
 }
 
 impl Account {
-    pub async fn handle(self) -> Result<()> {
+    pub async fn handle(self, output: OutputFormat) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         match self {
             Account::Create => Self::create(&mut rpc).await,
@@ -70,21 +108,28 @@ impl Account {
                 .await
             }
             Account::Logout => Self::logout(&mut rpc).await,
-            Account::Get { verbose } => Self::get(&mut rpc, verbose).await,
+            Account::Get { verbose } => Self::get(&mut rpc, verbose, output).await,
             Account::ListDevices { account, verbose } => {
                 Self::list_devices(&mut rpc, account, verbose).await
             }
-            Account::RevokeDevice { device, account } => {
-                Self::revoke_device(&mut rpc, device, account).await
+            Account::RevokeDevice {
+                devices,
+                all_except_current,
+                account,
+                queue,
+            } => Self::revoke_devices(&mut rpc, devices, all_except_current, account, queue).await,
+            Account::Redeem { voucher, queue } => {
+                Self::redeem_voucher(&mut rpc, voucher, queue).await
             }
-            Account::Redeem { voucher } => Self::redeem_voucher(&mut rpc, voucher).await,
+            Account::Pending => Self::list_pending(&mut rpc).await,
+            Account::CancelPending { id } => Self::cancel_pending(&mut rpc, id).await,
         }
     }
 
     async fn create(rpc: &mut MullvadProxyClient) -> Result<()> {
         rpc.create_new_account().await?;
         println!("New account created!");
-        Self::get(rpc, false).await
+        Self::get(rpc, false, OutputFormat::Human).await
     }
 
     async fn login(rpc: &mut MullvadProxyClient, account_number: AccountNumber) -> Result<()> {
@@ -99,11 +144,24 @@ impl Account {
         Ok(())
     }
 
-    async fn get(rpc: &mut MullvadProxyClient, verbose: bool) -> Result<()> {
+    async fn get(rpc: &mut MullvadProxyClient, verbose: bool, output: OutputFormat) -> Result<()> {
         let _ = rpc.update_device().await;
 
         let state = rpc.get_device().await?;
 
+        if output == OutputFormat::Json {
+            let account = match &state {
+                DeviceState::LoggedIn(device) => {
+                    Some(rpc.get_account_data(device.account_number.clone()).await?)
+                }
+                DeviceState::LoggedOut | DeviceState::Revoked => None,
+            };
+            return format::print_json(&AccountInfo {
+                device: state,
+                account,
+            });
+        }
+
         match state {
             DeviceState::LoggedIn(device) => {
                 println!("{:<20}{}", "Mullvad account:", device.account_number);
@@ -167,30 +225,101 @@ impl Account {
         Ok(())
     }
 
-    async fn revoke_device(
+    async fn revoke_devices(
         rpc: &mut MullvadProxyClient,
-        device: String,
+        devices: Vec<String>,
+        all_except_current: bool,
         account: Option<String>,
+        queue: bool,
     ) -> Result<()> {
         let account_number = account_else_current(rpc, account).await?;
 
-        let device_list = rpc.list_devices(account_number.clone()).await?;
-        let device_id = device_list
-            .into_iter()
-            .find(|dev| {
-                dev.name.eq_ignore_ascii_case(&device) || dev.id.eq_ignore_ascii_case(&device)
-            })
-            .map(|dev| dev.id)
-            .ok_or(mullvad_management_interface::Error::DeviceNotFound)?;
-
-        rpc.remove_device(account_number, device_id).await?;
-        println!("Removed device");
+        let device_list = match rpc.list_devices(account_number.clone()).await {
+            Ok(device_list) => device_list,
+            // The device list is unavailable while offline, so assume that UIDs were given
+            Err(_) if queue => vec![],
+            Err(error) => return Err(error.into()),
+        };
+        let device_ids = if device_list.is_empty() && queue {
+            devices
+        } else {
+            devices
+                .iter()
+                .map(|device| {
+                    device_list
+                        .iter()
+                        .find(|dev| {
+                            dev.name.eq_ignore_ascii_case(device)
+                                || dev.id.eq_ignore_ascii_case(device)
+                        })
+                        .map(|dev| dev.id.clone())
+                        .ok_or_else(|| anyhow!("Device not found: {device}"))
+                })
+                .collect::<Result<Vec<_>>>()?
+        };
+
+        if queue {
+            for device_id in device_ids {
+                let request = rpc
+                    .queue_device_removal(account_number.clone(), device_id)
+                    .await?;
+                print_queued(&request);
+            }
+            return Ok(());
+        }
+
+        if let [device_id] = &device_ids[..] {
+            rpc.remove_device(account_number, device_id.clone()).await?;
+            println!("Removed device");
+            return Ok(());
+        }
+
+        let result = rpc
+            .remove_devices(account_number, device_ids, all_except_current)
+            .await?;
+        let device_name = |id: &str| {
+            device_list
+                .iter()
+                .find(|dev| dev.id == id)
+                .map(|dev| dev.pretty_name())
+                .unwrap_or_else(|| id.to_owned())
+        };
+        if result.removed.is_empty() && result.failed.is_empty() {
+            println!("No devices to remove");
+        }
+        for device_id in &result.removed {
+            println!("Removed device: {}", device_name(device_id));
+        }
+        for failure in &result.failed {
+            eprintln!(
+                "Failed to remove device {}: {}",
+                device_name(&failure.device_id),
+                failure.error
+            );
+        }
+        if !result.failed.is_empty() {
+            return Err(anyhow!(
+                "{} of {} devices could not be removed",
+                result.failed.len(),
+                result.failed.len() + result.removed.len()
+            ));
+        }
         Ok(())
     }
 
-    async fn redeem_voucher(rpc: &mut MullvadProxyClient, mut voucher: String) -> Result<()> {
+    async fn redeem_voucher(
+        rpc: &mut MullvadProxyClient,
+        mut voucher: String,
+        queue: bool,
+    ) -> Result<()> {
         voucher.retain(|c| c.is_alphanumeric());
 
+        if queue {
+            let request = rpc.queue_voucher_submission(voucher).await?;
+            print_queued(&request);
+            return Ok(());
+        }
+
         let submission = rpc.submit_voucher(voucher).await?;
         println!(
             "Added {} to the account",
@@ -202,6 +331,38 @@ impl Account {
         );
         Ok(())
     }
+
+    async fn list_pending(rpc: &mut MullvadProxyClient) -> Result<()> {
+        let requests = rpc.get_pending_requests().await?;
+        if requests.is_empty() {
+            println!("No queued requests");
+            return Ok(());
+        }
+        for request in requests {
+            println!();
+            println!("Request : {}", request.kind);
+            println!("Id      : {}", request.id);
+            println!(
+                "Queued  : {}",
+                request.created.with_timezone(&chrono::Local)
+            );
+        }
+        Ok(())
+    }
+
+    async fn cancel_pending(rpc: &mut MullvadProxyClient, id: String) -> Result<()> {
+        rpc.cancel_pending_request(id).await?;
+        println!("Removed queued request");
+        Ok(())
+    }
+}
+
+fn print_queued(request: &PendingRequest) {
+    println!("Queued request: {}", request.kind);
+    println!(
+        "It will be sent once the API is reachable. Its ID is {}",
+        request.id
+    );
 }
 
 async fn account_else_current(
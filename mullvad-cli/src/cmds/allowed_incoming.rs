@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Result};
+use clap::{Args, Subcommand};
+use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::allowed_incoming::AllowedIncoming;
+use talpid_types::net::TransportProtocol;
+
+#[derive(Subcommand, Debug)]
+pub enum AllowIncoming {
+    /// List the incoming connections that are allowed
+    List,
+
+    /// Allow incoming connections to a local port, even when traffic is otherwise blocked
+    #[clap(arg_required_else_help = true)]
+    Add(AllowIncomingArgs),
+
+    /// Stop allowing incoming connections to a port. The interface must match the rule
+    #[clap(arg_required_else_help = true)]
+    Remove(AllowIncomingArgs),
+
+    /// Remove all rules
+    Clear,
+}
+
+#[derive(Args, Debug)]
+pub struct AllowIncomingArgs {
+    /// Transport protocol of the connections
+    protocol: TransportProtocol,
+
+    /// Local port to allow connections to
+    port: u16,
+
+    /// Only allow connections on this interface. Connections on any interface are allowed if
+    /// not specified
+    #[arg(long)]
+    interface: Option<String>,
+}
+
+impl From<AllowIncomingArgs> for AllowedIncoming {
+    fn from(args: AllowIncomingArgs) -> Self {
+        AllowedIncoming {
+            protocol: args.protocol,
+            port: args.port,
+            interface: args.interface,
+        }
+    }
+}
+
+impl AllowIncoming {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            AllowIncoming::List => Self::list().await,
+            AllowIncoming::Add(args) => Self::add(AllowedIncoming::from(args)).await,
+            AllowIncoming::Remove(args) => Self::remove(AllowedIncoming::from(args)).await,
+            AllowIncoming::Clear => Self::clear().await,
+        }
+    }
+
+    async fn list() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let rules = rpc.get_settings().await?.allowed_incoming;
+        if rules.is_empty() {
+            println!("No incoming connections are allowed");
+        }
+        for rule in rules {
+            println!("{rule}");
+        }
+        Ok(())
+    }
+
+    async fn add(rule: AllowedIncoming) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut rules = rpc.get_settings().await?.allowed_incoming;
+        rules.push(rule.clone());
+        rpc.set_allowed_incoming(rules).await?;
+        println!("Allowed incoming connections: {rule}");
+        Ok(())
+    }
+
+    async fn remove(rule: AllowedIncoming) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut rules = rpc.get_settings().await?.allowed_incoming;
+        let count = rules.len();
+        rules.retain(|existing| *existing != rule);
+        if rules.len() == count {
+            return Err(anyhow!("No such rule: {rule}"));
+        }
+        rpc.set_allowed_incoming(rules).await?;
+        println!("No longer allowing incoming connections: {rule}");
+        Ok(())
+    }
+
+    async fn clear() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_allowed_incoming(vec![]).await?;
+        println!("Removed all incoming connection rules");
+        Ok(())
+    }
+}
@@ -5,7 +5,7 @@ use talpid_types::net::proxy::CustomProxy;
 
 use clap::{Args, Subcommand};
 
-use super::proxies::{ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd};
+use super::proxies::{HttpAdd, ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd};
 
 #[derive(Subcommand, Debug, Clone)]
 pub enum ApiAccess {
@@ -17,8 +17,14 @@ pub enum ApiAccess {
     /// Lists all API access methods
     ///
     /// * = Enabled
-    List,
-    /// Edit a custom API access method
+    List {
+        /// Show how well each access method has worked. Access methods with a higher score are
+        /// tried first.
+        #[arg(long)]
+        stats: bool,
+    },
+    /// Edit a custom API access method, or the SOCKS port of the Tor access method (using
+    /// `--local-port`)
     Edit(EditCustomCommands),
     /// Remove a custom API access method
     Remove(SelectItem),
@@ -41,8 +47,8 @@ pub enum ApiAccess {
 impl ApiAccess {
     pub async fn handle(self) -> Result<()> {
         match self {
-            ApiAccess::List => {
-                Self::list().await?;
+            ApiAccess::List { stats } => {
+                Self::list(stats).await?;
             }
             ApiAccess::Add(cmd) => {
                 Self::add(cmd).await?;
@@ -69,14 +75,27 @@ impl ApiAccess {
     }
 
     /// Show all API access methods.
-    async fn list() -> Result<()> {
+    async fn list(show_stats: bool) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
+        let stats = if show_stats {
+            Some(rpc.get_api_access_method_stats().await?)
+        } else {
+            None
+        };
         for (index, api_access_method) in rpc.get_api_access_methods().await?.iter().enumerate() {
             println!(
                 "{}. {}",
                 index + 1,
                 pp::ApiAccessMethodFormatter::new(api_access_method)
             );
+            if let Some(stats) = &stats {
+                print!(
+                    "{}",
+                    pp::AccessMethodStatsFormatter {
+                        stats: stats.get(&api_access_method.get_id()),
+                    }
+                );
+            }
         }
         Ok(())
     }
@@ -102,14 +121,20 @@ impl ApiAccess {
 
     /// Edit the data of an API access method.
     async fn edit(cmd: EditCustomCommands) -> Result<()> {
+        use mullvad_types::access_method::BuiltInAccessMethod;
         use talpid_types::net::proxy::{Shadowsocks, Socks5Local, Socks5Remote, SocksAuth};
         let mut rpc = MullvadProxyClient::new().await?;
         let mut api_access_method = Self::get_access_method(&mut rpc, &cmd.item).await?;
 
         // Create a new access method combining the new params with the previous values
-        let access_method = match api_access_method.as_custom() {
-            None => return Err(anyhow!("Can not edit built-in access method")),
-            Some(x) => match x.clone() {
+        let access_method = match &api_access_method.access_method {
+            AccessMethod::BuiltIn(BuiltInAccessMethod::Tor { socks_port }) => {
+                AccessMethod::from(BuiltInAccessMethod::Tor {
+                    socks_port: cmd.params.local_port.unwrap_or(*socks_port),
+                })
+            }
+            AccessMethod::BuiltIn(_) => return Err(anyhow!("Can not edit built-in access method")),
+            AccessMethod::Custom(x) => match x.clone() {
                 CustomProxy::Shadowsocks(shadowsocks) => {
                     let ip = cmd.params.ip.unwrap_or(shadowsocks.endpoint.ip());
                     let port = cmd.params.port.unwrap_or(shadowsocks.endpoint.port());
@@ -153,6 +178,7 @@ impl ApiAccess {
                         }
                     })
                 }
+                CustomProxy::Http(http) => AccessMethod::from(cmd.params.merge_http(&http)?),
             },
         };
 
@@ -266,6 +292,17 @@ pub enum AddCustomCommands {
         #[clap(flatten)]
         add: ShadowsocksAdd,
     },
+    /// Configure an HTTP proxy, which is asked to tunnel API traffic using the CONNECT method
+    Http {
+        /// An easy to remember name for this custom proxy
+        name: String,
+        /// Disable the use of this custom access method. It has to be manually
+        /// enabled at a later stage to be used when accessing the Mullvad API.
+        #[arg(default_value_t = false, short, long)]
+        disabled: bool,
+        #[clap(flatten)]
+        add: HttpAdd,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -298,6 +335,7 @@ impl AddCustomCommands {
     fn name(&self) -> &str {
         match self {
             AddCustomCommands::Shadowsocks { name, .. }
+            | AddCustomCommands::Http { name, .. }
             | AddCustomCommands::Socks5(AddSocks5Commands::Remote { name, .. })
             | AddCustomCommands::Socks5(AddSocks5Commands::Local { name, .. }) => name,
         }
@@ -306,6 +344,7 @@ impl AddCustomCommands {
     fn enabled(&self) -> bool {
         match self {
             AddCustomCommands::Shadowsocks { disabled, .. }
+            | AddCustomCommands::Http { disabled, .. }
             | AddCustomCommands::Socks5(AddSocks5Commands::Remote { disabled, .. })
             | AddCustomCommands::Socks5(AddSocks5Commands::Local { disabled, .. }) => !disabled,
         }
@@ -401,6 +440,9 @@ mod conversions {
                         add.password,
                     ),
                 )),
+                AddCustomCommands::Http { add, .. } => Ok(daemon_types::AccessMethod::from(
+                    talpid_types::HttpProxy::try_from(add)?,
+                )),
             }
         }
     }
@@ -409,7 +451,10 @@ mod conversions {
 /// Pretty printing of [`AccessMethodSetting`]s
 mod pp {
     use crate::cmds::proxies::pp::CustomProxyFormatter;
-    use mullvad_types::access_method::{AccessMethod, AccessMethodSetting};
+    use chrono::Utc;
+    use mullvad_types::access_method::{
+        AccessMethod, AccessMethodSetting, AccessMethodStats, BuiltInAccessMethod,
+    };
 
     pub struct ApiAccessMethodFormatter<'a> {
         api_access_method: &'a AccessMethodSetting,
@@ -455,6 +500,10 @@ mod pp {
                     if self.settings.write_enabled {
                         write_status(f, self.api_access_method.enabled())?;
                     }
+                    if let BuiltInAccessMethod::Tor { socks_port } = method {
+                        writeln!(f)?;
+                        write!(f, "{:<4}{:<24}{}", "", "SOCKS port:", socks_port)?;
+                    }
                     Ok(())
                 }
                 AccessMethod::Custom(method) => {
@@ -472,4 +521,44 @@ mod pp {
             }
         }
     }
+
+    /// Formats the [`AccessMethodStats`] of an access method, one line per value.
+    pub struct AccessMethodStatsFormatter<'a> {
+        pub stats: Option<&'a AccessMethodStats>,
+    }
+
+    impl std::fmt::Display for AccessMethodStatsFormatter<'_> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let Some(stats) = self.stats else {
+                return writeln!(f, "{:<4}{:<24}{}", "", "Statistics:", "No requests sent");
+            };
+            let score = stats.score(Utc::now());
+            writeln!(f, "{:<4}{:<24}{:.2}", "", "Score:", score)?;
+            writeln!(
+                f,
+                "{:<4}{:<24}{:.0}%",
+                "",
+                "Success rate:",
+                stats.success_rate * 100.0
+            )?;
+            if let Some(latency) = stats.latency {
+                writeln!(f, "{:<4}{:<24}{} ms", "", "Latency:", latency.as_millis())?;
+            }
+            writeln!(
+                f,
+                "{:<4}{:<24}{} succeeded, {} failed",
+                "", "Requests:", stats.successes, stats.failures
+            )?;
+            writeln!(
+                f,
+                "{:<4}{:<24}{}",
+                "",
+                "Last used:",
+                stats
+                    .last_used
+                    .with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+            )
+        }
+    }
 }
@@ -0,0 +1,43 @@
+use anyhow::Result;
+use clap::Subcommand;
+use mullvad_management_interface::MullvadProxyClient;
+
+use super::BooleanOption;
+
+#[derive(Subcommand, Debug)]
+pub enum Block {
+    /// Display whether all traffic is being blocked
+    Get,
+    /// Block all traffic, regardless of whether the VPN is connected or disconnected
+    Enable,
+    /// Stop blocking all traffic and return to the connected or disconnected state
+    Disable,
+}
+
+impl Block {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            Block::Get => Self::get().await,
+            Block::Enable => Self::set(true).await,
+            Block::Disable => Self::set(false).await,
+        }
+    }
+
+    async fn set(block_all: bool) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_block_all(block_all).await?;
+        if block_all {
+            println!("Blocking all traffic");
+        } else {
+            println!("No longer blocking all traffic");
+        }
+        Ok(())
+    }
+
+    async fn get() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let state = BooleanOption::from(rpc.get_settings().await?.block_all);
+        println!("Block all traffic: {state}");
+        Ok(())
+    }
+}
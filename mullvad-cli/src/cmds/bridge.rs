@@ -9,12 +9,12 @@ use mullvad_types::{
     },
     relay_list::RelayEndpointData,
 };
-use talpid_types::net::proxy::{CustomProxy, Shadowsocks, Socks5Local, Socks5Remote};
+use talpid_types::net::proxy::{CustomProxy, HttpProxy, Shadowsocks, Socks5Local, Socks5Remote};
 
 use crate::cmds::proxies::pp::CustomProxyFormatter;
 
 use super::{
-    proxies::{ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd},
+    proxies::{HttpAdd, ProxyEditParams, ShadowsocksAdd, Socks5LocalAdd, Socks5RemoteAdd},
     relay::resolve_location_constraint,
     relay_constraints::LocationArgs,
 };
@@ -104,6 +104,11 @@ pub enum AddCustomCommands {
         #[clap(flatten)]
         add: ShadowsocksAdd,
     },
+    /// Configure an HTTP proxy
+    Http {
+        #[clap(flatten)]
+        add: HttpAdd,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -305,6 +310,7 @@ impl Bridge {
             CustomProxy::Shadowsocks(ss) => *ss = edit.merge_shadowsocks(ss),
             CustomProxy::Socks5Local(local) => *local = edit.merge_socks_local(local),
             CustomProxy::Socks5Remote(remote) => *remote = edit.merge_socks_remote(remote)?,
+            CustomProxy::Http(http) => *http = edit.merge_http(http)?,
         };
 
         rpc.set_bridge_settings(settings.bridge_settings)
@@ -349,6 +355,7 @@ impl Bridge {
             AddCustomCommands::Shadowsocks { add } => {
                 CustomProxy::Shadowsocks(Shadowsocks::from(add))
             }
+            AddCustomCommands::Http { add } => CustomProxy::Http(HttpProxy::try_from(add)?),
         });
 
         settings.bridge_settings.bridge_type = BridgeType::Custom;
@@ -0,0 +1,36 @@
+use anyhow::Result;
+use clap::Subcommand;
+use mullvad_management_interface::MullvadProxyClient;
+use std::time::Duration;
+
+use super::lockdown::parse_duration;
+
+#[derive(Subcommand, Debug)]
+pub enum CaptivePortal {
+    /// Allow traffic to the login page of a detected captive portal for a while, so that you can
+    /// log in to the network. The tunnel is connected once you have logged in
+    Allow {
+        /// How long to allow traffic for, e.g. '90s' or '10m'. At most 30 minutes
+        #[arg(long, value_parser = parse_duration, default_value = "5m")]
+        duration: Duration,
+    },
+}
+
+impl CaptivePortal {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            CaptivePortal::Allow { duration } => Self::allow(duration).await,
+        }
+    }
+
+    async fn allow(duration: Duration) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.allow_captive_portal_login(duration).await?;
+        let until = chrono::Local::now() + chrono::Duration::from_std(duration)?;
+        println!(
+            "Allowing traffic to the login page until {}",
+            until.format("%H:%M:%S")
+        );
+        Ok(())
+    }
+}
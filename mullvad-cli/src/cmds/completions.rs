@@ -0,0 +1,153 @@
+//! Shell completion scripts. Besides the static completions generated by `clap_complete`, the
+//! bash, zsh and fish scripts complete locations by calling `mullvad __complete-location`, which
+//! lists the countries, cities and relays in the relay list that the daemon has cached.
+
+use anyhow::{Context, Result};
+use clap::CommandFactory;
+use clap_complete::{Generator, Shell};
+use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::relay_list::{RelayListCity, RelayListCountry};
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use crate::{Args, BIN_NAME};
+
+/// Name of the hidden command that lists the candidates for the next location argument.
+pub const COMPLETE_LOCATION: &str = "__complete-location";
+
+/// Print the completion script for `shell`, or write it to a file in `dir`.
+pub fn generate(shell: Shell, dir: Option<PathBuf>) -> Result<()> {
+    // FIXME: The shell completions include hidden commands (including "shell-completions")
+    let mut script = vec![];
+    clap_complete::generate(shell, &mut Args::command(), BIN_NAME, &mut script);
+    let script = String::from_utf8(script).context("Invalid completion script")?;
+    let script = add_location_completion(shell, script)?;
+
+    match dir {
+        Some(dir) => {
+            let path = dir.join(shell.file_name(BIN_NAME));
+            println!("Generating shell completions to {}", path.display());
+            std::fs::write(&path, script).context("Failed to write shell completions")?;
+        }
+        None => io::stdout().write_all(script.as_bytes())?,
+    }
+    Ok(())
+}
+
+/// Make the static completion script for `shell` complete location arguments using
+/// [`COMPLETE_LOCATION`]. Other shells than bash, zsh and fish only get static completions.
+fn add_location_completion(shell: Shell, script: String) -> Result<String> {
+    let script = match shell {
+        Shell::Bash => {
+            // Wrap the generated function, and register the wrapper in its place
+            let wrapper = format!(
+                r#"
+_{BIN_NAME}_with_locations() {{
+    local locations
+    locations="$({BIN_NAME} {COMPLETE_LOCATION} -- "${{COMP_WORDS[@]:1:COMP_CWORD-1}}" 2>/dev/null)"
+    if [[ -n "$locations" ]]; then
+        COMPREPLY=($(compgen -W "$locations" -- "${{COMP_WORDS[COMP_CWORD]}}"))
+        return 0
+    fi
+    _{BIN_NAME} "$@"
+}}
+"#
+            );
+            let register = format!("complete -F _{BIN_NAME} ");
+            anyhow::ensure!(
+                script.contains(&register),
+                "Unexpected bash completion script"
+            );
+            let script = script.replace(
+                &register,
+                &format!("complete -F _{BIN_NAME}_with_locations "),
+            );
+            format!("{script}{wrapper}")
+        }
+        Shell::Zsh => {
+            // Check for locations at the start of the generated function
+            let function = format!("_{BIN_NAME}() {{\n");
+            let check = format!(
+                r#"    local -a locations
+    locations=(${{(f)"$({BIN_NAME} {COMPLETE_LOCATION} -- "${{(@)words[2,CURRENT-1]}}" 2>/dev/null)"}})
+    if (( ${{#locations}} )); then
+        compadd -a locations
+        return
+    fi
+"#
+            );
+            anyhow::ensure!(
+                script.contains(&function),
+                "Unexpected zsh completion script"
+            );
+            script.replacen(&function, &format!("{function}{check}"), 1)
+        }
+        Shell::Fish => format!(
+            "{script}complete -c {BIN_NAME} -a \
+             '({BIN_NAME} {COMPLETE_LOCATION} -- (commandline -opc)[2..-1] 2>/dev/null)'\n"
+        ),
+        _ => script,
+    };
+    Ok(script)
+}
+
+/// Print the candidates for the next location argument, one per line, given the arguments that
+/// precede it. Nothing is printed unless the next argument is part of a location.
+pub async fn complete_location(words: Vec<String>) -> Result<()> {
+    let Some(typed) = typed_location(&words) else {
+        return Ok(());
+    };
+
+    let mut rpc = MullvadProxyClient::new().await?;
+    let countries = rpc.get_relay_locations().await?.countries;
+
+    let candidates: Vec<&str> = match typed[..] {
+        [] => std::iter::once("any")
+            .chain(countries.iter().map(|country| country.code.as_str()))
+            .collect(),
+        [country] => cities(&countries, country)
+            .map(|city| city.code.as_str())
+            .collect(),
+        [country, city] => cities(&countries, country)
+            .filter(|c| c.code.eq_ignore_ascii_case(city))
+            .flat_map(|city| &city.relays)
+            .map(|relay| relay.hostname.as_str())
+            .collect(),
+        _ => vec![],
+    };
+    for candidate in candidates {
+        println!("{candidate}");
+    }
+    Ok(())
+}
+
+/// Return the cities in the country with the code `country`.
+fn cities<'a>(
+    countries: &'a [RelayListCountry],
+    country: &'a str,
+) -> impl Iterator<Item = &'a RelayListCity> {
+    countries
+        .iter()
+        .filter(move |c| c.code.eq_ignore_ascii_case(country))
+        .flat_map(|country| &country.cities)
+}
+
+/// Return the location arguments that precede the next argument, if it is part of a location.
+fn typed_location(words: &[String]) -> Option<Vec<&str>> {
+    let words: Vec<&str> = words
+        .iter()
+        .map(String::as_str)
+        .filter(|word| !word.starts_with('-'))
+        .collect();
+    let typed = match words[..] {
+        ["relay" | "bridge", "set", "location", ref typed @ ..]
+        | ["relay", "set", "tunnel", "wireguard", "entry-location", ref typed @ ..]
+        | ["relay", "exclude", "add" | "remove", ref typed @ ..]
+        | ["custom-list", "edit", "add" | "remove", _, ref typed @ ..] => typed,
+        _ => return None,
+    };
+    // A location is at most a country, a city and a host name
+    (typed.len() < 3).then(|| typed.to_vec())
+}
@@ -0,0 +1,104 @@
+use super::obfuscation::BridgeSetCommands;
+use anyhow::Result;
+use clap::Subcommand;
+use ipnetwork::IpNetwork;
+use itertools::Itertools;
+use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::{
+    custom_relay::CustomRelay as CustomRelayDefinition, relay_constraints::CustomObfuscationBridge,
+};
+use std::net::SocketAddr;
+use talpid_types::net::wireguard;
+
+#[derive(Subcommand, Debug)]
+pub enum CustomRelay {
+    /// Add a WireGuard relay that you run yourself. Select it like any other relay, e.g. with
+    /// 'mullvad relay set location <name>' or as the multihop entry location.
+    /// Quantum-resistant tunnels and DAITA are not supported with custom relays.
+    #[clap(arg_required_else_help = true)]
+    Add {
+        /// A unique name for the relay, used as its hostname
+        name: String,
+        /// IP address and port that the relay listens on
+        endpoint: SocketAddr,
+        /// Base64 encoded public key of the relay
+        #[arg(value_parser = wireguard::PublicKey::from_base64)]
+        public_key: wireguard::PublicKey,
+        /// IP networks that the relay may route traffic to. By default, only the next relay in
+        /// the circuit, or any address if it is the exit relay
+        #[arg(long = "allowed-ip")]
+        allowed_ips: Vec<IpNetwork>,
+        /// Reach the relay through an obfuscation server
+        #[clap(subcommand)]
+        obfuscation: Option<BridgeSetCommands>,
+    },
+
+    /// List the custom relays
+    List,
+
+    /// Remove a custom relay
+    #[clap(arg_required_else_help = true)]
+    Remove {
+        /// Name of the relay
+        name: String,
+    },
+}
+
+impl CustomRelay {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            CustomRelay::Add {
+                name,
+                endpoint,
+                public_key,
+                allowed_ips,
+                obfuscation,
+            } => {
+                let obfuscation = obfuscation.map(CustomObfuscationBridge::from);
+                if let Some(bridge) = &obfuscation {
+                    bridge.validate()?;
+                }
+                Self::add(CustomRelayDefinition {
+                    name,
+                    public_key,
+                    endpoint,
+                    allowed_ips,
+                    obfuscation,
+                })
+                .await
+            }
+            CustomRelay::List => Self::list().await,
+            CustomRelay::Remove { name } => Self::remove(name).await,
+        }
+    }
+
+    async fn add(relay: CustomRelayDefinition) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let name = relay.name.clone();
+        rpc.add_custom_relay(relay).await?;
+        println!("Added custom relay. Select it with: mullvad relay set location {name}");
+        Ok(())
+    }
+
+    async fn list() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        for relay in rpc.get_settings().await?.custom_relays {
+            println!("{} ({})", relay.name, relay.endpoint);
+            println!("\tPublic key: {}", relay.public_key);
+            if !relay.allowed_ips.is_empty() {
+                println!("\tAllowed IPs: {}", relay.allowed_ips.iter().join(", "));
+            }
+            if let Some(bridge) = &relay.obfuscation {
+                println!("\tObfuscation: {bridge}");
+            }
+        }
+        Ok(())
+    }
+
+    async fn remove(name: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.remove_custom_relay(name).await?;
+        println!("Removed custom relay");
+        Ok(())
+    }
+}
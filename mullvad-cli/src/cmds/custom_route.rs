@@ -0,0 +1,114 @@
+use anyhow::{anyhow, Result};
+use clap::{Subcommand, ValueEnum};
+use ipnetwork::IpNetwork;
+use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::custom_route::{CustomRoute, RouteTarget};
+
+#[derive(Subcommand, Debug)]
+pub enum Routes {
+    /// List the custom routes
+    List,
+
+    /// Route traffic to a network through or around the tunnel while it is up. Reconnects the
+    /// tunnel
+    #[clap(arg_required_else_help = true)]
+    Add {
+        /// Network in CIDR notation, e.g. '10.10.0.0/16'
+        network: IpNetwork,
+
+        /// Where to send traffic to the network. Traffic that is routed around the tunnel must
+        /// also be allowed by a firewall exception
+        #[arg(long)]
+        via: Via,
+    },
+
+    /// Remove the custom route to a network. Reconnects the tunnel
+    #[clap(arg_required_else_help = true)]
+    Remove {
+        /// Network in CIDR notation
+        network: IpNetwork,
+    },
+
+    /// Remove all custom routes. Reconnects the tunnel
+    Clear,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum Via {
+    /// Through the tunnel
+    Tunnel,
+    /// Around the tunnel, through the gateway of the default route
+    Gateway,
+}
+
+impl From<Via> for RouteTarget {
+    fn from(via: Via) -> Self {
+        match via {
+            Via::Tunnel => RouteTarget::Tunnel,
+            Via::Gateway => RouteTarget::DefaultGateway,
+        }
+    }
+}
+
+impl Routes {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            Routes::List => Self::list().await,
+            Routes::Add { network, via } => {
+                Self::add(CustomRoute {
+                    network,
+                    via: via.into(),
+                })
+                .await
+            }
+            Routes::Remove { network } => Self::remove(network).await,
+            Routes::Clear => Self::clear().await,
+        }
+    }
+
+    async fn list() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let routes = Self::routes(&mut rpc).await?;
+        if routes.is_empty() {
+            println!("No custom routes");
+        }
+        for route in routes {
+            println!("{route}");
+        }
+        Ok(())
+    }
+
+    async fn add(route: CustomRoute) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut routes = Self::routes(&mut rpc).await?;
+        routes.push(route);
+        rpc.set_custom_routes(routes).await?;
+        println!("Added custom route: {route}");
+        Ok(())
+    }
+
+    async fn remove(network: IpNetwork) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut routes = Self::routes(&mut rpc).await?;
+        let count = routes.len();
+        routes.retain(|route| route.network != network);
+        if routes.len() == count {
+            return Err(anyhow!("No custom route to {network}"));
+        }
+        rpc.set_custom_routes(routes).await?;
+        println!("Removed custom route to {network}");
+        Ok(())
+    }
+
+    async fn routes(rpc: &mut MullvadProxyClient) -> Result<Vec<CustomRoute>> {
+        let settings = rpc.get_settings().await?;
+        Ok(settings.tunnel_options.generic.custom_routes)
+    }
+
+    async fn clear() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_custom_routes(vec![]).await?;
+        println!("Removed all custom routes");
+        Ok(())
+    }
+}
@@ -1,9 +1,10 @@
 use anyhow::Result;
-use mullvad_management_interface::MullvadProxyClient;
+use mullvad_management_interface::{types, MullvadProxyClient};
 use mullvad_types::{
     constraints::Constraint,
     relay_constraints::{RelayConstraints, RelaySettings},
 };
+use std::net::SocketAddr;
 
 #[derive(clap::Subcommand, Debug)]
 pub enum DebugCommands {
@@ -12,6 +13,24 @@ pub enum DebugCommands {
     /// Relay
     #[clap(subcommand)]
     Relay(RelayDebugCommands),
+    /// Change the API endpoint used by the daemon. This requires a daemon built with the
+    /// `api-override` feature.
+    #[clap(subcommand)]
+    ApiEndpoint(ApiEndpointDebugCommands),
+    /// Print the applied tunnel configuration as JSON: the WireGuard parameters, the routes
+    /// through the tunnel, the firewall policy and the DNS servers
+    DumpTunnel {
+        /// Include the WireGuard private key. Do not share the output if this is set
+        #[arg(long)]
+        include_private_key: bool,
+    },
+    /// Describe all WFP sublayers, and all WFP filters that either belong to the app or block
+    /// traffic
+    #[cfg(target_os = "windows")]
+    Wfp,
+    /// Describe the rules in the PF anchor used by the app, and the anchors in the main ruleset
+    #[cfg(target_os = "macos")]
+    Pf,
 }
 
 #[derive(clap::Subcommand, Debug)]
@@ -24,6 +43,27 @@ pub enum RelayDebugCommands {
     Enable { relay: String },
 }
 
+#[derive(clap::Subcommand, Debug)]
+pub enum ApiEndpointDebugCommands {
+    /// Send API requests to another endpoint
+    Set {
+        /// Hostname of the API. Defaults to the production API
+        #[arg(long)]
+        host: Option<String>,
+        /// IP address and port of the API. Resolved from the hostname if not given
+        #[arg(long)]
+        address: Option<SocketAddr>,
+        /// Use plain HTTP instead of HTTPS
+        #[arg(long)]
+        disable_tls: bool,
+        /// Allow bridges and proxies to be used to reach the API
+        #[arg(long)]
+        allow_proxies: bool,
+    },
+    /// Send API requests to the endpoint the daemon was started with
+    Reset,
+}
+
 impl DebugCommands {
     pub async fn handle(self) -> Result<()> {
         match self {
@@ -66,6 +106,48 @@ impl DebugCommands {
                 println!("{relay} is now marked as active");
                 Ok(())
             }
+            DebugCommands::ApiEndpoint(ApiEndpointDebugCommands::Set {
+                host,
+                address,
+                disable_tls,
+                allow_proxies,
+            }) => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                rpc.set_api_endpoint(types::ApiEndpointOverride {
+                    host,
+                    address: address.map(|address| address.to_string()),
+                    disable_tls,
+                    force_direct: !allow_proxies,
+                })
+                .await?;
+                println!("Updated API endpoint");
+                Ok(())
+            }
+            DebugCommands::ApiEndpoint(ApiEndpointDebugCommands::Reset) => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                rpc.reset_api_endpoint().await?;
+                println!("Reset API endpoint");
+                Ok(())
+            }
+            DebugCommands::DumpTunnel {
+                include_private_key,
+            } => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                println!("{}", rpc.get_tunnel_diagnostics(include_private_key).await?);
+                Ok(())
+            }
+            #[cfg(target_os = "windows")]
+            DebugCommands::Wfp => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                println!("{}", rpc.get_wfp_diagnostics().await?);
+                Ok(())
+            }
+            #[cfg(target_os = "macos")]
+            DebugCommands::Pf => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                println!("{}", rpc.get_pf_diagnostics().await?);
+                Ok(())
+            }
         }
     }
 }
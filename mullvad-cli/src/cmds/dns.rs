@@ -1,8 +1,12 @@
-use anyhow::Result;
-use clap::Subcommand;
+use anyhow::{anyhow, Context, Result};
+use clap::{Subcommand, ValueEnum};
 use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::dns_filter::FilterListSource;
 use mullvad_types::settings::{CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState};
-use std::net::IpAddr;
+use std::{net::IpAddr, path::Path};
+#[cfg(target_os = "linux")]
+use talpid_types::dns::DnsBackend;
+use talpid_types::dns::{EncryptedDnsProtocol, EncryptedDnsServer, SplitDnsRule};
 
 #[derive(Subcommand, Debug)]
 pub enum Dns {
@@ -14,6 +18,105 @@ pub enum Dns {
         #[clap(subcommand)]
         cmd: DnsSet,
     },
+
+    /// Send queries for some domains to DNS servers on the local network
+    Split {
+        #[clap(subcommand)]
+        cmd: DnsSplit,
+    },
+
+    /// Block the domains in lists of hosts or domains, in addition to the default
+    /// content blockers. Not supported on Android
+    FilterList {
+        #[clap(subcommand)]
+        cmd: DnsFilterList,
+    },
+
+    /// Choose how the system DNS configuration is managed
+    #[cfg(target_os = "linux")]
+    Backend {
+        #[clap(subcommand)]
+        cmd: DnsBackendCmd,
+    },
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Subcommand, Debug, Clone)]
+pub enum DnsBackendCmd {
+    /// Display the order that DNS backends are tried in, and the backend that is in effect
+    Get,
+
+    /// Only try these DNS backends, in this order. Give a single backend to always use it.
+    /// This takes effect when the daemon is restarted
+    #[clap(arg_required_else_help = true)]
+    Set {
+        #[arg(required(true), num_args = 1..)]
+        backends: Vec<DnsBackendArg>,
+    },
+
+    /// Try all DNS backends in the default order. This takes effect when the daemon is
+    /// restarted
+    Reset,
+}
+
+#[cfg(target_os = "linux")]
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsBackendArg {
+    SystemdResolved,
+    NetworkManager,
+    Resolvconf,
+    /// Overwrite /etc/resolv.conf
+    StaticFile,
+}
+
+#[cfg(target_os = "linux")]
+impl From<DnsBackendArg> for DnsBackend {
+    fn from(backend: DnsBackendArg) -> Self {
+        match backend {
+            DnsBackendArg::SystemdResolved => DnsBackend::SystemdResolved,
+            DnsBackendArg::NetworkManager => DnsBackend::NetworkManager,
+            DnsBackendArg::Resolvconf => DnsBackend::Resolvconf,
+            DnsBackendArg::StaticFile => DnsBackend::StaticFile,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum DnsFilterList {
+    /// Add a filter list. Lists are loaded again once a day
+    #[clap(arg_required_else_help = true)]
+    Add {
+        /// HTTPS URL or path of a list in hosts file format, or with one domain per line
+        source: String,
+    },
+
+    /// Remove a filter list
+    #[clap(arg_required_else_help = true)]
+    Remove {
+        /// URL or path of a list that was previously added
+        source: String,
+    },
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum DnsSplit {
+    /// Resolve a domain and its subdomains using DNS servers on the local network
+    #[clap(arg_required_else_help = true)]
+    Add {
+        /// Domain to resolve locally, such as corp.example or *.corp.example
+        domain: String,
+
+        /// One or more private IP addresses of DNS resolvers
+        #[arg(required(true), num_args = 1..)]
+        servers: Vec<IpAddr>,
+    },
+
+    /// Stop resolving a domain using local DNS servers
+    #[clap(arg_required_else_help = true)]
+    Remove {
+        /// Domain that was previously added
+        domain: String,
+    },
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -52,6 +155,43 @@ pub enum DnsSet {
         #[arg(required(true), num_args = 1..)]
         servers: Vec<IpAddr>,
     },
+
+    /// Use a custom DNS server that is queried over DNS-over-TLS or DNS-over-HTTPS.
+    /// Plaintext custom DNS servers are not used while this is set
+    Encrypted {
+        /// Protocol used to query the server
+        protocol: EncryptedDnsProtocolArg,
+
+        /// IP address of the server
+        address: IpAddr,
+
+        /// Hostname that the certificate of the server is validated against
+        hostname: String,
+
+        /// Port of the server. Defaults to 853 for TLS and 443 for HTTPS
+        #[arg(long)]
+        port: Option<u16>,
+
+        /// Only accept the server if the base64 encoded SHA-256 digest of its
+        /// SubjectPublicKeyInfo matches this pin
+        #[arg(long)]
+        spki_pin: Option<String>,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum EncryptedDnsProtocolArg {
+    Tls,
+    Https,
+}
+
+impl From<EncryptedDnsProtocolArg> for EncryptedDnsProtocol {
+    fn from(protocol: EncryptedDnsProtocolArg) -> Self {
+        match protocol {
+            EncryptedDnsProtocolArg::Tls => EncryptedDnsProtocol::Tls,
+            EncryptedDnsProtocolArg::Https => EncryptedDnsProtocol::Https,
+        }
+    }
 }
 
 impl Dns {
@@ -82,6 +222,49 @@ impl Dns {
             Dns::Set {
                 cmd: DnsSet::Custom { servers },
             } => Self::set_custom(servers).await,
+            Dns::Set {
+                cmd:
+                    DnsSet::Encrypted {
+                        protocol,
+                        address,
+                        hostname,
+                        port,
+                        spki_pin,
+                    },
+            } => {
+                Self::set_encrypted(EncryptedDnsServer {
+                    address,
+                    protocol: EncryptedDnsProtocol::from(protocol),
+                    hostname,
+                    port,
+                    spki_pin,
+                })
+                .await
+            }
+            Dns::Split {
+                cmd: DnsSplit::Add { domain, servers },
+            } => Self::add_split_rule(domain, servers).await,
+            Dns::Split {
+                cmd: DnsSplit::Remove { domain },
+            } => Self::remove_split_rule(domain).await,
+            Dns::FilterList {
+                cmd: DnsFilterList::Add { source },
+            } => Self::add_filter_list(parse_filter_list_source(&source)?).await,
+            Dns::FilterList {
+                cmd: DnsFilterList::Remove { source },
+            } => Self::remove_filter_list(source).await,
+            #[cfg(target_os = "linux")]
+            Dns::Backend {
+                cmd: DnsBackendCmd::Get,
+            } => Self::get_backend().await,
+            #[cfg(target_os = "linux")]
+            Dns::Backend {
+                cmd: DnsBackendCmd::Set { backends },
+            } => Self::set_backends(backends.into_iter().map(DnsBackend::from).collect()).await,
+            #[cfg(target_os = "linux")]
+            Dns::Backend {
+                cmd: DnsBackendCmd::Reset,
+            } => Self::set_backends(vec![]).await,
         }
     }
 
@@ -110,6 +293,26 @@ impl Dns {
                 for server in &options.custom_options.addresses {
                     println!("{server}");
                 }
+                if !options.custom_options.encrypted_servers.is_empty() {
+                    println!("Encrypted servers:");
+                    for server in &options.custom_options.encrypted_servers {
+                        println!("{server}");
+                    }
+                }
+            }
+        }
+
+        if !options.split_dns_rules.is_empty() {
+            println!("Split DNS:");
+            for rule in &options.split_dns_rules {
+                println!("{rule}");
+            }
+        }
+
+        if !options.filter_lists.is_empty() {
+            println!("Filter lists:");
+            for source in &options.filter_lists {
+                println!("{source}");
             }
         }
 
@@ -148,11 +351,137 @@ impl Dns {
         let settings = rpc.get_settings().await?;
         rpc.set_dns_options(DnsOptions {
             state: DnsState::Custom,
-            custom_options: CustomDnsOptions { addresses: servers },
+            custom_options: CustomDnsOptions {
+                addresses: servers,
+                encrypted_servers: vec![],
+            },
             ..settings.tunnel_options.dns_options
         })
         .await?;
         println!("Updated DNS settings");
         Ok(())
     }
+
+    async fn set_encrypted(server: EncryptedDnsServer) -> Result<()> {
+        server.spki_digest()?;
+
+        let mut rpc = MullvadProxyClient::new().await?;
+        let settings = rpc.get_settings().await?;
+        let dns_options = settings.tunnel_options.dns_options;
+        rpc.set_dns_options(DnsOptions {
+            state: DnsState::Custom,
+            custom_options: CustomDnsOptions {
+                encrypted_servers: vec![server],
+                ..dns_options.custom_options
+            },
+            ..dns_options
+        })
+        .await?;
+        println!("Updated DNS settings");
+        Ok(())
+    }
+
+    async fn add_split_rule(domain: String, servers: Vec<IpAddr>) -> Result<()> {
+        let domain = normalize_domain(&domain);
+
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut dns_options = rpc.get_settings().await?.tunnel_options.dns_options;
+        dns_options
+            .split_dns_rules
+            .retain(|rule| !rule.domain.eq_ignore_ascii_case(&domain));
+        let rule = SplitDnsRule { domain, servers };
+        dns_options.split_dns_rules.push(rule.clone());
+        rpc.set_dns_options(dns_options).await?;
+        println!("Added split DNS rule: {rule}");
+        Ok(())
+    }
+
+    async fn remove_split_rule(domain: String) -> Result<()> {
+        let domain = normalize_domain(&domain);
+
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut dns_options = rpc.get_settings().await?.tunnel_options.dns_options;
+        let count = dns_options.split_dns_rules.len();
+        dns_options
+            .split_dns_rules
+            .retain(|rule| !rule.domain.eq_ignore_ascii_case(&domain));
+        if dns_options.split_dns_rules.len() == count {
+            return Err(anyhow!("No split DNS rule for domain: {domain}"));
+        }
+        rpc.set_dns_options(dns_options).await?;
+        println!("Removed split DNS rule for domain: {domain}");
+        Ok(())
+    }
+
+    async fn add_filter_list(source: FilterListSource) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut dns_options = rpc.get_settings().await?.tunnel_options.dns_options;
+        if dns_options.filter_lists.contains(&source) {
+            return Err(anyhow!("Filter list was already added: {source}"));
+        }
+        dns_options.filter_lists.push(source.clone());
+        rpc.set_dns_options(dns_options).await?;
+        println!("Added filter list: {source}");
+        Ok(())
+    }
+
+    async fn remove_filter_list(source: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut dns_options = rpc.get_settings().await?.tunnel_options.dns_options;
+        // The list may have been added using a relative path
+        let parsed_source = parse_filter_list_source(&source).ok();
+        let count = dns_options.filter_lists.len();
+        dns_options.filter_lists.retain(|existing| {
+            existing.to_string() != source && Some(existing) != parsed_source.as_ref()
+        });
+        if dns_options.filter_lists.len() == count {
+            return Err(anyhow!("No such filter list: {source}"));
+        }
+        rpc.set_dns_options(dns_options).await?;
+        println!("Removed filter list: {source}");
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn get_backend() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let backends = rpc.get_settings().await?.dns_backends;
+        let order = if backends.is_empty() {
+            "default".to_owned()
+        } else {
+            backends
+                .iter()
+                .map(|backend| backend.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        println!("Backend order: {order}");
+        println!("In effect: {}", rpc.get_active_dns_backend().await?);
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_backends(backends: Vec<DnsBackend>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_dns_backends(&backends).await?;
+        println!("DNS backends have been updated. Restart the daemon for them to take effect");
+        Ok(())
+    }
+}
+
+/// Treat `source` as a URL if it has a scheme, and as a path to a file otherwise.
+fn parse_filter_list_source(source: &str) -> Result<FilterListSource> {
+    if source.contains("://") {
+        return Ok(FilterListSource::Url(source.to_owned()));
+    }
+    let path = Path::new(source)
+        .canonicalize()
+        .with_context(|| format!("Failed to find {source}"))?;
+    Ok(FilterListSource::File(path))
+}
+
+/// Rules always match subdomains, so accept and strip a leading wildcard and a trailing dot.
+fn normalize_domain(domain: &str) -> String {
+    let domain = domain.strip_prefix("*.").unwrap_or(domain);
+    domain.strip_suffix('.').unwrap_or(domain).to_owned()
 }
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use clap::{Subcommand, ValueEnum};
+use mullvad_management_interface::MullvadProxyClient;
+use talpid_types::drivers::{Driver, DriverStatus};
+
+#[derive(Subcommand, Debug)]
+pub enum Drivers {
+    /// Display the state of the kernel drivers used by the app
+    Status,
+
+    /// Remove a driver so that it is reinstalled the next time it is needed.
+    /// The tunnel must be disconnected.
+    Repair { driver: DriverArg },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum DriverArg {
+    Wintun,
+    WireguardNt,
+    SplitTunnel,
+}
+
+impl From<DriverArg> for Driver {
+    fn from(driver: DriverArg) -> Self {
+        match driver {
+            DriverArg::Wintun => Driver::Wintun,
+            DriverArg::WireguardNt => Driver::WireguardNt,
+            DriverArg::SplitTunnel => Driver::SplitTunnel,
+        }
+    }
+}
+
+impl Drivers {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            Drivers::Status => Self::status().await,
+            Drivers::Repair { driver } => Self::repair(Driver::from(driver)).await,
+        }
+    }
+
+    async fn status() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        for status in rpc.get_driver_status().await? {
+            print_status(&status);
+        }
+        Ok(())
+    }
+
+    async fn repair(driver: Driver) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let result = rpc.repair_driver(driver).await?;
+        println!("Removed {driver} driver");
+        print_status(&result.status);
+        if result.restart_required {
+            println!("Restart the Mullvad service to reinstall the driver");
+        } else {
+            println!("The driver will be reinstalled the next time it is needed");
+        }
+        Ok(())
+    }
+}
+
+fn print_status(status: &DriverStatus) {
+    println!("{}: {}", status.driver, status.state);
+    if let Some(version) = &status.version {
+        println!("    version: {version}");
+    }
+    if let Some(path) = &status.image_path {
+        println!("    path: {}", path.display());
+    }
+}
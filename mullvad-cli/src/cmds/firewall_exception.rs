@@ -0,0 +1,100 @@
+use anyhow::{anyhow, Result};
+use clap::{Args, Subcommand};
+use ipnetwork::IpNetwork;
+use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::firewall_exception::FirewallException;
+use talpid_types::net::TransportProtocol;
+
+#[derive(Subcommand, Debug)]
+pub enum FirewallExceptions {
+    /// List the traffic that is allowed outside the tunnel
+    List,
+
+    /// Allow traffic to a host or network outside the tunnel, even when traffic is otherwise
+    /// blocked. DNS requests are never allowed
+    #[clap(arg_required_else_help = true)]
+    Add(ExceptionArgs),
+
+    /// Remove an exception. The protocol and port must match the exception
+    #[clap(arg_required_else_help = true)]
+    Remove(ExceptionArgs),
+
+    /// Remove all exceptions
+    Clear,
+}
+
+#[derive(Args, Debug)]
+pub struct ExceptionArgs {
+    /// IP address or network in CIDR notation, e.g. '192.168.1.10' or '10.0.0.0/8'
+    network: IpNetwork,
+
+    /// Only allow this protocol. Any protocol is allowed if not specified
+    #[arg(long)]
+    protocol: Option<TransportProtocol>,
+
+    /// Only allow this destination port. Requires '--protocol'
+    #[arg(long, requires = "protocol")]
+    port: Option<u16>,
+}
+
+impl From<ExceptionArgs> for FirewallException {
+    fn from(args: ExceptionArgs) -> Self {
+        FirewallException {
+            network: args.network,
+            protocol: args.protocol,
+            port: args.port,
+        }
+    }
+}
+
+impl FirewallExceptions {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            FirewallExceptions::List => Self::list().await,
+            FirewallExceptions::Add(args) => Self::add(FirewallException::from(args)).await,
+            FirewallExceptions::Remove(args) => Self::remove(FirewallException::from(args)).await,
+            FirewallExceptions::Clear => Self::clear().await,
+        }
+    }
+
+    async fn list() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let exceptions = rpc.get_settings().await?.firewall_exceptions;
+        if exceptions.is_empty() {
+            println!("No firewall exceptions");
+        }
+        for exception in exceptions {
+            println!("{exception}");
+        }
+        Ok(())
+    }
+
+    async fn add(exception: FirewallException) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut exceptions = rpc.get_settings().await?.firewall_exceptions;
+        exceptions.push(exception);
+        rpc.set_firewall_exceptions(exceptions).await?;
+        println!("Added firewall exception: {exception}");
+        Ok(())
+    }
+
+    async fn remove(exception: FirewallException) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut exceptions = rpc.get_settings().await?.firewall_exceptions;
+        let count = exceptions.len();
+        exceptions.retain(|existing| *existing != exception);
+        if exceptions.len() == count {
+            return Err(anyhow!("No such firewall exception: {exception}"));
+        }
+        rpc.set_firewall_exceptions(exceptions).await?;
+        println!("Removed firewall exception: {exception}");
+        Ok(())
+    }
+
+    async fn clear() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_firewall_exceptions(vec![]).await?;
+        println!("Removed all firewall exceptions");
+        Ok(())
+    }
+}
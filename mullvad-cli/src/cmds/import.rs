@@ -0,0 +1,294 @@
+use anyhow::{bail, Context, Result};
+use clap::Subcommand;
+use ipnetwork::IpNetwork;
+use itertools::Itertools;
+use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::{
+    relay_constraints::RelaySettings,
+    settings::{CustomDnsOptions, DnsOptions, DnsState},
+    ConnectionConfig, CustomTunnelEndpoint,
+};
+use std::{
+    fs::File,
+    io::{read_to_string, stdin, BufReader},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+};
+use talpid_types::net::wireguard;
+
+#[derive(Subcommand, Debug)]
+pub enum Import {
+    /// Use the WireGuard server in a wg-quick configuration file as a custom relay. The private
+    /// key, addresses, allowed IPs and DNS servers are read from the file, as well as the MTU and
+    /// persistent keepalive, which apply to all WireGuard tunnels. Only one peer is supported
+    #[clap(arg_required_else_help = true)]
+    WgQuick {
+        /// File to read from. If this is "-", read from standard input
+        file: String,
+        /// IPv4 gateway address. By default, the first IPv4 DNS server in the file
+        #[arg(long)]
+        v4_gateway: Option<Ipv4Addr>,
+        /// IPv6 gateway address. By default, the first IPv6 DNS server in the file, if any
+        #[arg(long)]
+        v6_gateway: Option<Ipv6Addr>,
+    },
+}
+
+impl Import {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            Import::WgQuick {
+                file,
+                v4_gateway,
+                v6_gateway,
+            } => Self::wg_quick(file, v4_gateway, v6_gateway).await,
+        }
+    }
+
+    async fn wg_quick(
+        source: String,
+        v4_gateway: Option<Ipv4Addr>,
+        v6_gateway: Option<Ipv6Addr>,
+    ) -> Result<()> {
+        let config = tokio::task::spawn_blocking(move || match source.as_str() {
+            "-" => read_to_string(BufReader::new(stdin())).context("Failed to read from stdin"),
+            _ => read_to_string(File::open(&source)?)
+                .context(format!("Failed to read from path: {source}")),
+        })
+        .await
+        .unwrap()?;
+        let config = WgQuickConfig::parse(&config)?;
+        let endpoint = config.to_custom_endpoint(v4_gateway, v6_gateway)?;
+        let peer = &config.peers[0];
+
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_relay_settings(RelaySettings::CustomTunnelEndpoint(endpoint))
+            .await?;
+        println!("Relay constraints updated");
+
+        if !config.dns.is_empty() {
+            let settings = rpc.get_settings().await?;
+            rpc.set_dns_options(DnsOptions {
+                state: DnsState::Custom,
+                custom_options: CustomDnsOptions {
+                    addresses: config.dns.clone(),
+                    encrypted_servers: vec![],
+                },
+                ..settings.tunnel_options.dns_options
+            })
+            .await?;
+            println!("Using DNS servers: {}", config.dns.iter().join(", "));
+        }
+        if let Some(mtu) = config.mtu {
+            rpc.set_wireguard_mtu(Some(mtu)).await?;
+            println!("Set WireGuard MTU to {mtu}");
+        }
+        if let Some(interval) = peer.persistent_keepalive {
+            rpc.set_wireguard_persistent_keepalive(Some(interval))
+                .await?;
+            println!("Set WireGuard persistent keepalive to {interval} seconds");
+        }
+        Ok(())
+    }
+}
+
+/// The settings in a wg-quick configuration file that can be applied.
+#[derive(Default)]
+struct WgQuickConfig {
+    private_key: Option<wireguard::PrivateKey>,
+    addresses: Vec<IpAddr>,
+    dns: Vec<IpAddr>,
+    mtu: Option<u16>,
+    peers: Vec<WgQuickPeer>,
+}
+
+#[derive(Default)]
+struct WgQuickPeer {
+    public_key: Option<wireguard::PublicKey>,
+    allowed_ips: Vec<IpNetwork>,
+    /// Host name or address, and port
+    endpoint: Option<(String, u16)>,
+    persistent_keepalive: Option<u16>,
+}
+
+enum Section {
+    None,
+    Interface,
+    Peer,
+}
+
+impl WgQuickConfig {
+    fn parse(config: &str) -> Result<Self> {
+        let mut parsed = WgQuickConfig::default();
+        let mut section = Section::None;
+
+        for (index, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if line.eq_ignore_ascii_case("[Interface]") {
+                section = Section::Interface;
+                continue;
+            }
+            if line.eq_ignore_ascii_case("[Peer]") {
+                section = Section::Peer;
+                parsed.peers.push(WgQuickPeer::default());
+                continue;
+            }
+            parsed
+                .parse_line(&section, line)
+                .with_context(|| format!("Invalid configuration on line {}", index + 1))?;
+        }
+        Ok(parsed)
+    }
+
+    fn parse_line(&mut self, section: &Section, line: &str) -> Result<()> {
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("Expected 'Key = Value'");
+        };
+        let (key, value) = (key.trim().to_ascii_lowercase(), value.trim());
+
+        match (section, key.as_str()) {
+            (Section::Interface, "privatekey") => {
+                self.private_key =
+                    Some(wireguard::PrivateKey::from_base64(value).context("Invalid private key")?);
+            }
+            (Section::Interface, "address") => {
+                for address in split_list(value) {
+                    let address: IpNetwork = address.parse().context("Invalid address")?;
+                    self.addresses.push(address.ip());
+                }
+            }
+            (Section::Interface, "dns") => {
+                for server in split_list(value) {
+                    match server.parse() {
+                        Ok(server) => self.dns.push(server),
+                        Err(_) => eprintln!("Ignoring DNS search domain: {server}"),
+                    }
+                }
+            }
+            (Section::Interface, "mtu") => {
+                self.mtu = Some(value.parse().context("Invalid MTU")?);
+            }
+            (
+                Section::Interface,
+                "listenport" | "fwmark" | "table" | "preup" | "postup" | "predown" | "postdown"
+                | "saveconfig",
+            ) => eprintln!("Ignoring unsupported setting: {line}"),
+            (Section::Peer, "publickey") => {
+                self.current_peer().public_key =
+                    Some(wireguard::PublicKey::from_base64(value).context("Invalid public key")?);
+            }
+            (Section::Peer, "allowedips") => {
+                for network in split_list(value) {
+                    let network = network.parse().context("Invalid allowed IP")?;
+                    self.current_peer().allowed_ips.push(network);
+                }
+            }
+            (Section::Peer, "endpoint") => {
+                self.current_peer().endpoint = Some(parse_endpoint(value)?);
+            }
+            (Section::Peer, "persistentkeepalive") => {
+                self.current_peer().persistent_keepalive = match value {
+                    "off" => None,
+                    _ => Some(value.parse().context("Invalid persistent keepalive")?),
+                };
+            }
+            (Section::Peer, "presharedkey") => {
+                bail!("Preshared keys are not supported")
+            }
+            (Section::None, _) => bail!("Expected [Interface] or [Peer]"),
+            _ => bail!("Unknown setting"),
+        }
+        Ok(())
+    }
+
+    fn current_peer(&mut self) -> &mut WgQuickPeer {
+        self.peers
+            .last_mut()
+            .expect("a peer is added when its section begins")
+    }
+
+    fn to_custom_endpoint(
+        &self,
+        v4_gateway: Option<Ipv4Addr>,
+        v6_gateway: Option<Ipv6Addr>,
+    ) -> Result<CustomTunnelEndpoint> {
+        let [peer] = &self.peers[..] else {
+            bail!(
+                "Expected exactly one [Peer] section, found {}",
+                self.peers.len()
+            );
+        };
+        let private_key = self.private_key.clone().context("PrivateKey is missing")?;
+        if self.addresses.is_empty() {
+            bail!("Address is missing");
+        }
+        let public_key = peer.public_key.clone().context("PublicKey is missing")?;
+        let (host, port) = peer.endpoint.clone().context("Endpoint is missing")?;
+        if peer.allowed_ips.is_empty() {
+            bail!("AllowedIPs is missing");
+        }
+
+        // wg-quick has no notion of a gateway, but it is usually also the DNS server
+        let ipv4_gateway = v4_gateway
+            .or_else(|| {
+                self.dns.iter().find_map(|server| match server {
+                    IpAddr::V4(server) => Some(*server),
+                    IpAddr::V6(_) => None,
+                })
+            })
+            .context("No IPv4 gateway address was found. Set it with '--v4-gateway'")?;
+        let ipv6_gateway = v6_gateway.or_else(|| {
+            self.dns.iter().find_map(|server| match server {
+                IpAddr::V6(server) => Some(*server),
+                IpAddr::V4(_) => None,
+            })
+        });
+
+        Ok(CustomTunnelEndpoint {
+            host,
+            config: ConnectionConfig::Wireguard(wireguard::ConnectionConfig {
+                tunnel: wireguard::TunnelConfig {
+                    private_key,
+                    addresses: self.addresses.clone(),
+                },
+                peer: wireguard::PeerConfig {
+                    public_key,
+                    allowed_ips: peer.allowed_ips.clone(),
+                    endpoint: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port),
+                    psk: None,
+                    persistent_keepalive: None,
+                    constant_packet_size: false,
+                },
+                exit_peer: None,
+                middle_peers: vec![],
+                ipv4_gateway,
+                ipv6_gateway,
+                // NOTE: Ignored in gRPC
+                #[cfg(target_os = "linux")]
+                fwmark: None,
+            }),
+        })
+    }
+}
+
+fn split_list(value: &str) -> impl Iterator<Item = &str> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Parse `host:port`, where an IPv6 address host is enclosed in brackets.
+fn parse_endpoint(value: &str) -> Result<(String, u16)> {
+    let (host, port) = value
+        .rsplit_once(':')
+        .context("Expected the endpoint to be 'host:port'")?;
+    let host = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    let port = port.parse().context("Invalid endpoint port")?;
+    Ok((host.to_owned(), port))
+}
@@ -1,6 +1,8 @@
-use anyhow::Result;
-use clap::Subcommand;
+use anyhow::{anyhow, Result};
+use clap::{Args, Subcommand};
+use ipnetwork::IpNetwork;
 use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::lan_sharing::{AllowedLan, LanNetwork};
 
 use super::BooleanOption;
 
@@ -9,11 +11,41 @@ pub enum Lan {
     /// Display the current local network sharing setting
     Get,
 
-    /// Change allow LAN setting
+    /// Allow or block all local networks
     Set {
         #[arg(value_parser = BooleanOption::custom_parser("allow", "block"))]
         policy: BooleanOption,
     },
+
+    /// Only allow some local networks. Other local networks are blocked
+    #[clap(arg_required_else_help = true)]
+    Add(LanNetworkArgs),
+
+    /// Stop allowing a local network. Local network sharing is blocked if none remain
+    #[clap(arg_required_else_help = true)]
+    Remove(LanNetworkArgs),
+}
+
+#[derive(Args, Debug)]
+pub struct LanNetworkArgs {
+    /// Private network to allow, in CIDR notation. All private networks are allowed if not
+    /// specified
+    #[arg(long)]
+    network: Option<IpNetwork>,
+
+    /// Only allow traffic on this interface. Traffic on any interface is allowed if not
+    /// specified
+    #[arg(long)]
+    interface: Option<String>,
+}
+
+impl From<LanNetworkArgs> for LanNetwork {
+    fn from(args: LanNetworkArgs) -> Self {
+        LanNetwork {
+            network: args.network,
+            interface: args.interface,
+        }
+    }
 }
 
 impl Lan {
@@ -21,6 +53,8 @@ impl Lan {
         match self {
             Lan::Get => Self::get().await,
             Lan::Set { policy } => Self::set(policy).await,
+            Lan::Add(args) => Self::add(LanNetwork::from(args)).await,
+            Lan::Remove(args) => Self::remove(LanNetwork::from(args)).await,
         }
     }
 
@@ -33,9 +67,40 @@ impl Lan {
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        let allow_lan =
-            BooleanOption::with_labels(rpc.get_settings().await?.allow_lan, "allow", "block");
+        let allow_lan = rpc.get_settings().await?.allow_lan;
         println!("Local network sharing setting: {allow_lan}");
         Ok(())
     }
+
+    async fn add(network: LanNetwork) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut networks = match rpc.get_settings().await?.allow_lan {
+            AllowedLan::Only(networks) => networks,
+            AllowedLan::Blocked | AllowedLan::All => vec![],
+        };
+        networks.push(network.clone());
+        rpc.set_lan_sharing(AllowedLan::Only(networks)).await?;
+        println!("Allowed local network: {network}");
+        Ok(())
+    }
+
+    async fn remove(network: LanNetwork) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let AllowedLan::Only(mut networks) = rpc.get_settings().await?.allow_lan else {
+            return Err(anyhow!("No such local network: {network}"));
+        };
+        let count = networks.len();
+        networks.retain(|existing| *existing != network);
+        if networks.len() == count {
+            return Err(anyhow!("No such local network: {network}"));
+        }
+        let allow_lan = if networks.is_empty() {
+            AllowedLan::Blocked
+        } else {
+            AllowedLan::Only(networks)
+        };
+        rpc.set_lan_sharing(allow_lan).await?;
+        println!("No longer allowing local network: {network}");
+        Ok(())
+    }
 }
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use clap::Subcommand;
 use mullvad_management_interface::MullvadProxyClient;
+use std::time::Duration;
 
 use super::BooleanOption;
 
@@ -10,6 +11,13 @@ pub enum LockdownMode {
     Get,
     /// Change the lockdown mode setting
     Set { policy: BooleanOption },
+    /// Stop blocking traffic while disconnected for a while. Lockdown mode is enforced again
+    /// when the time has passed, or when lockdown mode is set
+    Pause {
+        /// How long to pause for, e.g. '90s', '10m' or '2h'. At most 24 hours
+        #[arg(long, value_parser = parse_duration)]
+        duration: Duration,
+    },
 }
 
 impl LockdownMode {
@@ -17,6 +25,7 @@ impl LockdownMode {
         match self {
             LockdownMode::Get => Self::get().await,
             LockdownMode::Set { policy } => Self::set(policy).await,
+            LockdownMode::Pause { duration } => Self::pause(duration).await,
         }
     }
 
@@ -27,6 +36,14 @@ impl LockdownMode {
         Ok(())
     }
 
+    async fn pause(duration: Duration) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.pause_lockdown_mode(duration).await?;
+        let until = chrono::Local::now() + chrono::Duration::from_std(duration)?;
+        println!("Paused lockdown mode until {}", until.format("%H:%M:%S"));
+        Ok(())
+    }
+
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let state = BooleanOption::from(rpc.get_settings().await?.block_when_disconnected);
@@ -34,3 +51,21 @@ impl LockdownMode {
         Ok(())
     }
 }
+
+/// Parse a number of seconds, minutes or hours, e.g. `90s`, `10m` or `2h`.
+pub(super) fn parse_duration(value: &str) -> Result<Duration, String> {
+    let (number, unit_seconds) = match value.char_indices().last() {
+        Some((index, 's')) => (&value[..index], 1),
+        Some((index, 'm')) => (&value[..index], 60),
+        Some((index, 'h')) => (&value[..index], 60 * 60),
+        _ => return Err("expected a number followed by 's', 'm' or 'h'".to_owned()),
+    };
+    let number = number.parse::<u64>().map_err(|error| error.to_string())?;
+    if number == 0 {
+        return Err("the duration must not be zero".to_owned());
+    }
+    number
+        .checked_mul(unit_seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(|| "the duration is too long".to_owned())
+}
@@ -0,0 +1,49 @@
+use anyhow::Result;
+use clap::Subcommand;
+use mullvad_management_interface::MullvadProxyClient;
+
+use super::BooleanOption;
+
+#[derive(Subcommand, Debug)]
+pub enum Metrics {
+    /// Display the metrics server settings
+    Get,
+
+    /// Serve tunnel and API metrics in the Prometheus format on localhost
+    Set {
+        policy: BooleanOption,
+
+        /// TCP port to listen on
+        #[arg(long)]
+        port: Option<u16>,
+    },
+}
+
+impl Metrics {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            Metrics::Get => Self::get().await,
+            Metrics::Set { policy, port } => Self::set(policy, port).await,
+        }
+    }
+
+    async fn get() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let metrics = rpc.get_settings().await?.metrics;
+        println!("Metrics server: {}", BooleanOption::from(metrics.enabled));
+        println!("Address: http://127.0.0.1:{}/metrics", metrics.port);
+        Ok(())
+    }
+
+    async fn set(policy: BooleanOption, port: Option<u16>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut metrics = rpc.get_settings().await?.metrics;
+        metrics.enabled = *policy;
+        if let Some(port) = port {
+            metrics.port = port;
+        }
+        rpc.set_metrics_settings(metrics).await?;
+        println!("Changed metrics server setting");
+        Ok(())
+    }
+}
@@ -2,25 +2,44 @@ use clap::builder::{PossibleValuesParser, TypedValueParser, ValueParser};
 use std::{io::stdin, ops::Deref};
 
 pub mod account;
+pub mod allowed_incoming;
 pub mod api_access;
 pub mod auto_connect;
 pub mod beta_program;
+pub mod block;
 pub mod bridge;
+pub mod captive_portal;
+#[cfg(all(unix, not(target_os = "android")))]
+pub mod completions;
 pub mod custom_list;
+pub mod custom_relay;
+pub mod custom_route;
 pub mod debug;
 pub mod dns;
+#[cfg(target_os = "windows")]
+pub mod drivers;
+pub mod firewall_exception;
+pub mod import;
 pub mod lan;
 pub mod lockdown;
+pub mod metrics;
 pub mod obfuscation;
 pub mod patch;
+pub mod profile;
 pub mod proxies;
 pub mod relay;
 pub mod relay_constraints;
 pub mod reset;
+pub mod settings;
 pub mod split_tunnel;
+pub mod state_hooks;
 pub mod status;
+pub mod trusted_networks;
+#[cfg(all(unix, not(target_os = "android")))]
+pub mod tui;
 pub mod tunnel;
 pub mod tunnel_state;
+pub mod usage;
 pub mod version;
 
 /// A value parser that parses "on" or "off" into a boolean
@@ -4,9 +4,12 @@ use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
     constraints::Constraint,
     relay_constraints::{
-        ObfuscationSettings, SelectedObfuscation, ShadowsocksSettings, Udp2TcpObfuscationSettings,
+        CustomObfuscationBridge, ObfuscationSettings, PortHoppingSettings, SelectedObfuscation,
+        ShadowsocksCipher, ShadowsocksSettings, Udp2TcpObfuscationSettings,
+        MAX_PORT_HOPPING_INTERVAL_MINUTES, MIN_PORT_HOPPING_INTERVAL_MINUTES,
     },
 };
+use std::net::{IpAddr, SocketAddr};
 
 #[derive(Subcommand, Debug)]
 pub enum Obfuscation {
@@ -16,6 +19,72 @@ pub enum Obfuscation {
     /// Set obfuscation settings
     #[clap(subcommand)]
     Set(SetCommands),
+
+    /// Manage a self-hosted bridge to obfuscate traffic through. Use it by setting the
+    /// obfuscation mode to 'custom-bridge'.
+    #[clap(subcommand)]
+    Bridge(BridgeCommands),
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum BridgeCommands {
+    /// Display the custom bridge
+    Get,
+
+    /// Set the custom bridge
+    #[clap(subcommand)]
+    Set(BridgeSetCommands),
+
+    /// Remove the custom bridge
+    Clear,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum BridgeSetCommands {
+    /// A udp2tcp server. It must forward traffic to the relay that is selected, so the relay
+    /// location should be set to that relay.
+    Udp2tcp {
+        /// IP address of the bridge
+        remote_ip: IpAddr,
+        /// Port on which the bridge listens for traffic
+        remote_port: u16,
+    },
+
+    /// A Shadowsocks server. It forwards traffic to whichever relay is selected.
+    Shadowsocks {
+        /// IP address of the bridge
+        remote_ip: IpAddr,
+        /// Port on which the bridge listens for traffic
+        remote_port: u16,
+        /// Password for authentication
+        password: String,
+        /// Cipher to use
+        #[arg(long, default_value_t = ShadowsocksCipher::default())]
+        cipher: ShadowsocksCipher,
+    },
+}
+
+impl From<BridgeSetCommands> for CustomObfuscationBridge {
+    fn from(command: BridgeSetCommands) -> Self {
+        match command {
+            BridgeSetCommands::Udp2tcp {
+                remote_ip,
+                remote_port,
+            } => CustomObfuscationBridge::Udp2Tcp {
+                endpoint: SocketAddr::new(remote_ip, remote_port),
+            },
+            BridgeSetCommands::Shadowsocks {
+                remote_ip,
+                remote_port,
+                password,
+                cipher,
+            } => CustomObfuscationBridge::Shadowsocks {
+                endpoint: SocketAddr::new(remote_ip, remote_port),
+                password,
+                cipher,
+            },
+        }
+    }
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -31,10 +100,25 @@ pub enum SetCommands {
     },
 
     /// Configure Shadowsocks obfuscation.
+    #[clap(arg_required_else_help = true)]
     Shadowsocks {
         /// Port to use, or 'any'
         #[arg(long, short = 'p')]
-        port: Constraint<u16>,
+        port: Option<Constraint<u16>>,
+
+        /// Cipher to use, or 'any' for the default cipher
+        #[arg(long, short = 'c')]
+        cipher: Option<Constraint<ShadowsocksCipher>>,
+    },
+
+    /// Configure port hopping. The tunnel periodically moves to a random WireGuard port on the
+    /// same relay. Any WireGuard port constraint only applies to the first port.
+    PortHopping {
+        /// Minutes to stay on a port before moving to another one
+        #[arg(long, value_parser = clap::value_parser!(u16).range(
+            i64::from(MIN_PORT_HOPPING_INTERVAL_MINUTES)..=i64::from(MAX_PORT_HOPPING_INTERVAL_MINUTES)
+        ))]
+        interval: u16,
     },
 }
 
@@ -50,23 +134,72 @@ impl Obfuscation {
                 );
                 println!("udp2tcp settings: {}", obfuscation_settings.udp2tcp);
                 println!("Shadowsocks settings: {}", obfuscation_settings.shadowsocks);
+                println!(
+                    "Port hopping settings: {}",
+                    obfuscation_settings.port_hopping
+                );
+                Self::print_custom_bridge(obfuscation_settings.custom_bridge.as_ref());
                 Ok(())
             }
             Obfuscation::Set(subcmd) => Self::set(subcmd).await,
+            Obfuscation::Bridge(subcmd) => Self::bridge(subcmd).await,
         }
     }
 
+    fn print_custom_bridge(bridge: Option<&CustomObfuscationBridge>) {
+        match bridge {
+            Some(bridge) => println!("Custom bridge: {bridge}"),
+            None => println!("Custom bridge: none"),
+        }
+    }
+
+    async fn bridge(subcmd: BridgeCommands) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let current_settings = rpc.get_settings().await?.obfuscation_settings;
+
+        let custom_bridge = match subcmd {
+            BridgeCommands::Get => {
+                Self::print_custom_bridge(current_settings.custom_bridge.as_ref());
+                return Ok(());
+            }
+            BridgeCommands::Set(bridge) => {
+                let bridge = CustomObfuscationBridge::from(bridge);
+                bridge.validate()?;
+                Some(bridge)
+            }
+            BridgeCommands::Clear => {
+                if current_settings.selected_obfuscation == SelectedObfuscation::CustomBridge {
+                    anyhow::bail!(
+                        "The custom bridge is in use. Select another obfuscation mode first."
+                    );
+                }
+                None
+            }
+        };
+
+        rpc.set_obfuscation_settings(ObfuscationSettings {
+            custom_bridge,
+            ..current_settings
+        })
+        .await?;
+
+        println!("Updated custom bridge");
+
+        Ok(())
+    }
+
     async fn set(subcmd: SetCommands) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let current_settings = rpc.get_settings().await?.obfuscation_settings;
 
         match subcmd {
             SetCommands::Mode { mode } => {
-                rpc.set_obfuscation_settings(ObfuscationSettings {
+                let settings = ObfuscationSettings {
                     selected_obfuscation: mode,
                     ..current_settings
-                })
-                .await?;
+                };
+                settings.validate()?;
+                rpc.set_obfuscation_settings(settings).await?;
             }
             SetCommands::Udp2tcp { port } => {
                 rpc.set_obfuscation_settings(ObfuscationSettings {
@@ -75,9 +208,21 @@ impl Obfuscation {
                 })
                 .await?;
             }
-            SetCommands::Shadowsocks { port } => {
+            SetCommands::Shadowsocks { port, cipher } => {
+                rpc.set_obfuscation_settings(ObfuscationSettings {
+                    shadowsocks: ShadowsocksSettings {
+                        port: port.unwrap_or(current_settings.shadowsocks.port),
+                        cipher: cipher.unwrap_or(current_settings.shadowsocks.cipher),
+                    },
+                    ..current_settings
+                })
+                .await?;
+            }
+            SetCommands::PortHopping { interval } => {
                 rpc.set_obfuscation_settings(ObfuscationSettings {
-                    shadowsocks: ShadowsocksSettings { port },
+                    port_hopping: PortHoppingSettings {
+                        interval_minutes: interval,
+                    },
                     ..current_settings
                 })
                 .await?;
@@ -0,0 +1,77 @@
+use anyhow::Result;
+use clap::Subcommand;
+use mullvad_management_interface::MullvadProxyClient;
+
+#[derive(Subcommand, Debug)]
+pub enum Profile {
+    /// List the saved profiles. The profile matching the current settings, if any, is marked
+    List,
+
+    /// Save the current relay, obfuscation, DNS and split tunneling settings as a profile,
+    /// replacing any existing profile with the same name
+    #[clap(arg_required_else_help = true)]
+    Save {
+        /// Name of the profile
+        name: String,
+    },
+
+    /// Switch to the settings stored in a profile. Reconnects if this changes the relay or
+    /// obfuscation settings
+    #[clap(arg_required_else_help = true)]
+    Use {
+        /// Name of the profile
+        name: String,
+    },
+
+    /// Remove a profile
+    #[clap(arg_required_else_help = true)]
+    Remove {
+        /// Name of the profile
+        name: String,
+    },
+}
+
+impl Profile {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            Profile::List => Self::list().await,
+            Profile::Save { name } => Self::save(name).await,
+            Profile::Use { name } => Self::use_profile(name).await,
+            Profile::Remove { name } => Self::remove(name).await,
+        }
+    }
+
+    async fn list() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let settings = rpc.get_settings().await?;
+        for profile in settings.profiles.iter() {
+            if profile.is_active(&settings) {
+                println!("{} (active)", profile.name);
+            } else {
+                println!("{}", profile.name);
+            }
+        }
+        Ok(())
+    }
+
+    async fn save(name: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.save_profile(name.clone()).await?;
+        println!("Saved profile. Switch to it with: mullvad profile use {name}");
+        Ok(())
+    }
+
+    async fn use_profile(name: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.use_profile(name).await?;
+        println!("Switched profile");
+        Ok(())
+    }
+
+    async fn remove(name: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.remove_profile(name).await?;
+        println!("Removed profile");
+        Ok(())
+    }
+}
@@ -1,7 +1,10 @@
 use clap::Args;
 use std::net::{IpAddr, SocketAddr};
 use talpid_types::net::{
-    proxy::{Shadowsocks, Socks5Local, Socks5Remote, SocksAuth, SHADOWSOCKS_CIPHERS},
+    proxy::{
+        HttpAuth, HttpAuthScheme, HttpProxy, Shadowsocks, Socks5Local, Socks5Remote, SocksAuth,
+        SHADOWSOCKS_CIPHERS,
+    },
     Endpoint, TransportProtocol,
 };
 
@@ -89,6 +92,46 @@ impl From<ShadowsocksAdd> for Shadowsocks {
     }
 }
 
+#[derive(Args, Debug, Clone)]
+pub struct HttpAdd {
+    /// The IP of the HTTP proxy
+    pub remote_ip: IpAddr,
+    /// The port of the HTTP proxy
+    pub remote_port: u16,
+
+    #[clap(flatten)]
+    pub authentication: Option<HttpAuthentication>,
+
+    /// Authentication scheme to use, if credentials are given: basic or ntlm
+    #[arg(long, default_value_t = HttpAuthScheme::Basic)]
+    pub auth_scheme: HttpAuthScheme,
+}
+
+impl TryFrom<HttpAdd> for HttpProxy {
+    type Error = Error;
+    fn try_from(add: HttpAdd) -> Result<Self, Self::Error> {
+        Ok(Self {
+            endpoint: SocketAddr::new(add.remote_ip, add.remote_port),
+            auth: add
+                .authentication
+                .map(|auth| HttpAuth::new(add.auth_scheme, auth.username, auth.password))
+                .transpose()?,
+        })
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+#[group(requires_all = ["username", "password"])] // https://github.com/clap-rs/clap/issues/5092
+pub struct HttpAuthentication {
+    /// Username for authentication against an HTTP proxy. For NTLM, this may be given as
+    /// DOMAIN\user
+    #[arg(short, long, required = false)]
+    pub username: String,
+    /// Password for authentication against an HTTP proxy
+    #[arg(short, long, required = false)]
+    pub password: String,
+}
+
 #[derive(Args, Debug, Clone)]
 #[group(requires_all = ["username", "password"])] // https://github.com/clap-rs/clap/issues/5092
 pub struct SocksAuthentication {
@@ -102,19 +145,22 @@ pub struct SocksAuthentication {
 
 #[derive(Args, Debug, Clone)]
 pub struct ProxyEditParams {
-    /// Username for authentication \[Socks5 (Remote proxy)\]
+    /// Username for authentication \[Socks5 (Remote proxy), HTTP\]
     #[arg(long)]
     pub username: Option<String>,
-    /// Password for authentication \[Socks5 (Remote proxy), Shadowsocks\]
+    /// Password for authentication \[Socks5 (Remote proxy), Shadowsocks, HTTP\]
     #[arg(long)]
     pub password: Option<String>,
+    /// Authentication scheme, basic or ntlm \[HTTP\]
+    #[arg(long)]
+    pub auth_scheme: Option<HttpAuthScheme>,
     /// Cipher to use \[Shadowsocks\]
     #[arg(value_parser = SHADOWSOCKS_CIPHERS, long)]
     pub cipher: Option<String>,
-    /// The IP of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks\]
+    /// The IP of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks, HTTP\]
     #[arg(long)]
     pub ip: Option<IpAddr>,
-    /// The port of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks\]
+    /// The port of the remote proxy server \[Socks5 (Local & Remote proxy), Shadowsocks, HTTP\]
     #[arg(long)]
     pub port: Option<u16>,
     /// The port that the server on localhost is listening on \[Socks5 (Local proxy)\]
@@ -165,6 +211,33 @@ impl ProxyEditParams {
         Ok(config)
     }
 
+    pub fn merge_http(self, http: &HttpProxy) -> Result<HttpProxy, Error> {
+        let ip = self.ip.unwrap_or(http.endpoint.ip());
+        let port = self.port.unwrap_or(http.endpoint.port());
+        let config = match &http.auth {
+            None => match (self.username, self.password) {
+                (Some(username), Some(password)) => {
+                    let scheme = self.auth_scheme.unwrap_or(HttpAuthScheme::Basic);
+                    let auth = HttpAuth::new(scheme, username, password)?;
+                    HttpProxy::new_with_authentication((ip, port), auth)
+                }
+                (None, None) => HttpProxy::new((ip, port)),
+                _ => {
+                    println!("HTTP proxy does not have a username and password set already, so you must provide both or neither when you edit.");
+                    HttpProxy::new((ip, port))
+                }
+            },
+            Some(credentials) => {
+                let scheme = self.auth_scheme.unwrap_or(credentials.scheme());
+                let username = self.username.unwrap_or(credentials.username().to_string());
+                let password = self.password.unwrap_or(credentials.password().to_string());
+                let auth = HttpAuth::new(scheme, username, password)?;
+                HttpProxy::new_with_authentication((ip, port), auth)
+            }
+        };
+        Ok(config)
+    }
+
     pub fn merge_shadowsocks(self, shadowsocks: &Shadowsocks) -> Shadowsocks {
         let ip = self.ip.unwrap_or(shadowsocks.endpoint.ip());
         let port = self.port.unwrap_or(shadowsocks.endpoint.port());
@@ -212,6 +285,16 @@ pub mod pp {
                     print_option!("Local port", local.local_port);
                     Ok(())
                 }
+                CustomProxy::Http(http) => {
+                    print_option!("Protocol", "HTTP");
+                    print_option!("Peer", http.endpoint);
+                    if let Some(credentials) = &http.auth {
+                        print_option!("Authentication", credentials.scheme());
+                        print_option!("Username", credentials.username());
+                        print_option!("Password", credentials.password());
+                    }
+                    Ok(())
+                }
             }
         }
     }
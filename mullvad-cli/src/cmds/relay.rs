@@ -6,16 +6,21 @@ use mullvad_types::{
     constraints::{Constraint, Match},
     location::CountryCode,
     relay_constraints::{
-        GeographicLocationConstraint, LocationConstraint, LocationConstraintFormatter,
-        OpenVpnConstraints, Ownership, Provider, Providers, RelayConstraints, RelayOverride,
-        RelaySettings, TransportPort, WireguardConstraints,
+        Asn, AsnFilter, GeographicLocationConstraint, LocationConstraint,
+        LocationConstraintFormatter, OpenVpnConstraints, Ownership, Provider, Providers,
+        RelayConstraints, RelayOverride, RelaySettings, TransportPort, WireguardConstraints,
+        MAX_MIDDLE_HOPS,
+    },
+    relay_list::{
+        RelayEndpointData, RelayListCountry, MAX_UPDATE_INTERVAL_MINUTES,
+        MIN_UPDATE_INTERVAL_MINUTES,
     },
-    relay_list::{RelayEndpointData, RelayListCountry},
     ConnectionConfig, CustomTunnelEndpoint,
 };
 use std::{
     collections::HashMap,
-    io::BufRead,
+    fs::File,
+    io::{read_to_string, stdin, BufRead, BufReader},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
 };
 use talpid_types::net::{
@@ -23,7 +28,11 @@ use talpid_types::net::{
 };
 
 use super::{relay_constraints::LocationArgs, BooleanOption};
-use crate::{cmds::receive_confirmation, print_option};
+use crate::{
+    cmds::receive_confirmation,
+    format::{self, OutputFormat},
+    print_option,
+};
 
 #[derive(Subcommand, Debug)]
 pub enum Relay {
@@ -38,11 +47,47 @@ pub enum Relay {
     List,
 
     /// Update the relay list
-    Update,
+    Update {
+        /// Wait until the relay list has been downloaded and applied
+        #[arg(long)]
+        wait: bool,
+    },
+
+    /// Export the relay list, for importing on a machine that cannot reach the API
+    #[clap(arg_required_else_help = true)]
+    Export {
+        /// File to write to. If this is "-", write to standard output
+        file: String,
+    },
+
+    /// Replace the relay list with one generated by 'export'
+    #[clap(arg_required_else_help = true)]
+    Import {
+        /// File to read from. If this is "-", read from standard input
+        file: String,
+    },
+
+    /// Read the relay list overrides file (relay-overrides.json in the settings directory) again
+    /// and merge it over the relay list
+    ReloadOverrides,
 
     /// Override options for individual relays/servers
     #[clap(subcommand)]
     Override(OverrideCommands),
+
+    /// Exclude relays, cities or countries from relay selection
+    #[clap(subcommand)]
+    Exclude(ExcludeCommands),
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ExcludeCommands {
+    /// Show the excluded locations
+    List,
+    /// Never select relays in a location, even if they match the relay constraints
+    Add(LocationArgs),
+    /// Stop excluding a location
+    Remove(LocationArgs),
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -85,6 +130,11 @@ pub enum SetCommands {
         providers: Vec<Provider>,
     },
 
+    /// Filter relays based on the autonomous system number (ASN) of their upstream network. The
+    /// 'list' command shows the ASN of each relay, if it is known.
+    #[clap(subcommand)]
+    Asn(SetAsnCommands),
+
     /// Filter relays based on ownership. The 'list' command
     /// shows the available relays and whether they're rented.
     Ownership {
@@ -99,6 +149,23 @@ pub enum SetCommands {
     /// Set tunnel protocol to use: 'wireguard', or 'openvpn'.
     TunnelProtocol { protocol: TunnelType },
 
+    /// Measure the latency to matching relays while disconnected, and prefer relays with low
+    /// latency when connecting
+    PreferLowLatency { policy: BooleanOption },
+
+    /// Keep using the relays that were last connected to for the selected location, until
+    /// connecting to them fails
+    Sticky { policy: BooleanOption },
+
+    /// Set how often to download a new relay list, in minutes. A small random delay is added to
+    /// spread out requests.
+    UpdateInterval {
+        #[arg(value_parser = clap::value_parser!(u16).range(
+            i64::from(MIN_UPDATE_INTERVAL_MINUTES)..=i64::from(MAX_UPDATE_INTERVAL_MINUTES)
+        ))]
+        minutes: u16,
+    },
+
     /// Set a custom VPN relay to use
     #[clap(subcommand)]
     Custom(SetCustomCommands),
@@ -134,6 +201,16 @@ pub enum SetTunnelCommands {
         #[arg(long, short = 'm')]
         use_multihop: Option<BooleanOption>,
 
+        /// Number of randomly chosen relays to route through between the entry and exit relay
+        /// when multihop is enabled
+        #[arg(long, value_parser = clap::value_parser!(u8).range(0..=i64::from(MAX_MIDDLE_HOPS)))]
+        middle_hops: Option<u8>,
+
+        /// Whether to require that the relays used for multihop are run by different hosting
+        /// providers. Relays owned by Mullvad count as a single provider.
+        #[arg(long)]
+        multihop_diversity: Option<BooleanOption>,
+
         #[clap(subcommand)]
         entry: Option<EntryCommands>,
     },
@@ -174,6 +251,22 @@ pub enum EntryArgs {
     CustomList { custom_list_name: String },
 }
 
+#[derive(Subcommand, Debug, Clone)]
+pub enum SetAsnCommands {
+    /// Use relays in any network
+    Any,
+    /// Only use relays in one of these networks. Relays whose ASN is unknown are not used
+    Only {
+        #[arg(required(true), num_args = 1..)]
+        asns: Vec<Asn>,
+    },
+    /// Never use relays in any of these networks
+    Except {
+        #[arg(required(true), num_args = 1..)]
+        asns: Vec<Asn>,
+    },
+}
+
 #[derive(Subcommand, Debug, Clone)]
 pub enum SetCustomCommands {
     /// Use a custom OpenVPN relay
@@ -259,13 +352,17 @@ pub enum OverrideUnsetCommands {
 }
 
 impl Relay {
-    pub async fn handle(self) -> Result<()> {
+    pub async fn handle(self, output: OutputFormat) -> Result<()> {
         match self {
             Relay::Get => Self::get().await,
-            Relay::List => Self::list().await,
-            Relay::Update => Self::update().await,
+            Relay::List => Self::list(output).await,
+            Relay::Update { wait } => Self::update(wait).await,
+            Relay::Export { file } => Self::export(file).await,
+            Relay::Import { file } => Self::import(file).await,
+            Relay::ReloadOverrides => Self::reload_overrides().await,
             Relay::Set(subcmd) => Self::set(subcmd).await,
             Relay::Override(subcmd) => Self::r#override(subcmd).await,
+            Relay::Exclude(subcmd) => Self::exclude(subcmd).await,
         }
     }
 
@@ -296,6 +393,7 @@ impl Relay {
                 print_option!("Tunnel protocol", constraints.tunnel_protocol,);
 
                 print_option!("Provider(s)", constraints.providers,);
+                print_option!("ASN(s)", constraints.asns,);
                 print_option!("Ownership", constraints.ownership,);
 
                 println!("OpenVPN constraints");
@@ -336,23 +434,71 @@ impl Relay {
                             custom_lists: &settings.custom_lists
                         }),
                 );
+                print_option!(
+                    "Multihop middle relays",
+                    constraints.wireguard_constraints.middle_hops,
+                );
+                print_option!(
+                    "Multihop diversity",
+                    if constraints.wireguard_constraints.multihop_diversity {
+                        "enabled"
+                    } else {
+                        "disabled"
+                    },
+                );
             }
         }
 
+        print_option!(
+            "Prefer low latency",
+            BooleanOption::from(settings.prefer_low_latency_relays),
+        );
+        print_option!("Sticky relays", BooleanOption::from(settings.sticky_relays));
+        print_option!(
+            "Relay list updates",
+            format!(
+                "every {} minute(s)",
+                settings.relay_list_update_interval_minutes
+            ),
+        );
+
         Ok(())
     }
 
-    async fn list() -> Result<()> {
+    async fn set_prefer_low_latency(enabled: bool) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_prefer_low_latency_relays(enabled).await?;
+        println!("Updated low latency preference");
+        Ok(())
+    }
+
+    async fn set_sticky(enabled: bool) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_sticky_relays(enabled).await?;
+        println!("Updated sticky relays setting");
+        Ok(())
+    }
+
+    async fn list(output: OutputFormat) -> Result<()> {
         let mut countries = get_active_relays().await?;
         countries.sort_by(|c1, c2| natord::compare_ignore_case(&c1.name, &c2.name));
-        for mut country in countries {
+        for country in &mut countries {
             country
                 .cities
                 .sort_by(|c1, c2| natord::compare_ignore_case(&c1.name, &c2.name));
-            println!("{} ({})", country.name, country.code);
-            for mut city in country.cities {
+            for city in &mut country.cities {
                 city.relays
                     .sort_by(|r1, r2| natord::compare_ignore_case(&r1.hostname, &r2.hostname));
+            }
+        }
+
+        if output == OutputFormat::Json {
+            return format::print_json(&countries);
+        }
+
+        for country in countries {
+            println!("{} ({})", country.name, country.code);
+            for city in country.cities {
                 println!(
                     "\t{} ({}) @ {:.5}°N, {:.5}°W",
                     city.name, city.code, city.latitude, city.longitude
@@ -372,8 +518,12 @@ impl Relay {
                     if let Some(ipv6_addr) = relay.ipv6_addr_in {
                         addresses.push(ipv6_addr.into());
                     }
+                    let network = match relay.asn {
+                        Some(asn) => format!(", AS{asn}"),
+                        None => String::new(),
+                    };
                     println!(
-                        "\t\t{} ({}) - {}, hosted by {} ({ownership})",
+                        "\t\t{} ({}) - {}, hosted by {} ({ownership}{network})",
                         relay.hostname,
                         addresses.iter().join(", "),
                         support_msg,
@@ -386,12 +536,78 @@ impl Relay {
         Ok(())
     }
 
-    async fn update() -> Result<()> {
-        MullvadProxyClient::new()
-            .await?
-            .update_relay_locations()
-            .await?;
-        println!("Updating relay list in the background...");
+    async fn update(wait: bool) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        if wait {
+            rpc.update_relay_locations_and_wait()
+                .await
+                .context("Failed to update the relay list")?;
+            println!("Updated relay list");
+        } else {
+            rpc.update_relay_locations().await?;
+            println!("Updating relay list in the background...");
+        }
+        Ok(())
+    }
+
+    async fn set_update_interval(minutes: u16) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_relay_list_update_interval(minutes).await?;
+        println!("Updated relay list update interval");
+        Ok(())
+    }
+
+    async fn export(dest: String) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let blob = rpc
+            .export_relay_list()
+            .await
+            .context("Failed to export relay list")?;
+
+        match dest.as_str() {
+            "-" => {
+                println!("{blob}");
+                Ok(())
+            }
+            _ => tokio::fs::write(&dest, blob)
+                .await
+                .context(format!("Failed to write to path {dest}")),
+        }
+    }
+
+    async fn import(source: String) -> Result<()> {
+        let blob = tokio::task::spawn_blocking(move || match source.as_str() {
+            "-" => read_to_string(BufReader::new(stdin())).context("Failed to read from stdin"),
+            _ => read_to_string(File::open(&source)?)
+                .context(format!("Failed to read from path: {source}")),
+        })
+        .await
+        .unwrap()?;
+
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.import_relay_list(blob)
+            .await
+            .context("Failed to import relay list")?;
+
+        println!("Relay list imported");
+        Ok(())
+    }
+
+    async fn reload_overrides() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let errors = rpc
+            .reload_relay_list_overrides()
+            .await
+            .context("Failed to reload relay list overrides")?;
+
+        if errors.is_empty() {
+            println!("Relay list overrides applied");
+        } else {
+            println!("Some relay list overrides were skipped:");
+            for error in errors {
+                println!("{error}");
+            }
+        }
         Ok(())
     }
 
@@ -423,9 +639,13 @@ impl Relay {
                 Self::set_custom_list(custom_list_name).await
             }
             SetCommands::Provider { providers } => Self::set_providers(providers).await,
+            SetCommands::Asn(subcmd) => Self::set_asns(subcmd).await,
             SetCommands::Ownership { ownership } => Self::set_ownership(ownership).await,
             SetCommands::Tunnel(subcmd) => Self::set_tunnel(subcmd).await,
             SetCommands::TunnelProtocol { protocol } => Self::set_tunnel_protocol(protocol).await,
+            SetCommands::PreferLowLatency { policy } => Self::set_prefer_low_latency(*policy).await,
+            SetCommands::Sticky { policy } => Self::set_sticky(*policy).await,
+            SetCommands::UpdateInterval { minutes } => Self::set_update_interval(minutes).await,
         }
     }
 
@@ -439,10 +659,20 @@ impl Relay {
                 port,
                 ip_version,
                 use_multihop,
+                middle_hops,
+                multihop_diversity,
                 entry,
             } => {
                 let entry = entry.map(|EntryCommands::Entry(entry)| entry);
-                Self::set_wireguard_constraints(port, ip_version, use_multihop, entry).await
+                Self::set_wireguard_constraints(
+                    port,
+                    ip_version,
+                    use_multihop,
+                    middle_hops,
+                    multihop_diversity,
+                    entry,
+                )
+                .await
             }
         }
     }
@@ -541,9 +771,11 @@ impl Relay {
                     allowed_ips: all_of_the_internet(),
                     endpoint: SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port),
                     psk: None,
+                    persistent_keepalive: None,
                     constant_packet_size: false,
                 },
                 exit_peer: None,
+                middle_peers: vec![],
                 ipv4_gateway,
                 ipv6_gateway,
                 // NOTE: Ignored in gRPC
@@ -553,7 +785,7 @@ impl Relay {
         })
     }
 
-    async fn set_location(location_constraint_args: LocationArgs) -> Result<()> {
+    pub(super) async fn set_location(location_constraint_args: LocationArgs) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
         let relay_settings = rpc.get_settings().await?.get_relay_settings();
         let constraints = match relay_settings {
@@ -608,6 +840,22 @@ impl Relay {
         .await
     }
 
+    async fn set_asns(subcmd: SetAsnCommands) -> Result<()> {
+        let asns = match subcmd {
+            SetAsnCommands::Any => Constraint::Any,
+            SetAsnCommands::Only { asns } => {
+                Constraint::Only(AsnFilter::Only(asns.into_iter().collect()))
+            }
+            SetAsnCommands::Except { asns } => {
+                Constraint::Only(AsnFilter::Except(asns.into_iter().collect()))
+            }
+        };
+        Self::update_constraints(|constraints| {
+            constraints.asns = asns;
+        })
+        .await
+    }
+
     async fn set_ownership(ownership: Constraint<Ownership>) -> Result<()> {
         Self::update_constraints(|constraints| {
             constraints.ownership = ownership;
@@ -645,6 +893,8 @@ impl Relay {
         port: Option<Constraint<u16>>,
         ip_version: Option<Constraint<IpVersion>>,
         use_multihop: Option<BooleanOption>,
+        middle_hops: Option<u8>,
+        multihop_diversity: Option<BooleanOption>,
         entry_location: Option<EntryArgs>,
     ) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
@@ -673,6 +923,12 @@ impl Relay {
         if let Some(use_multihop) = use_multihop {
             wireguard_constraints.use_multihop(*use_multihop);
         }
+        if let Some(middle_hops) = middle_hops {
+            wireguard_constraints.middle_hops = middle_hops;
+        }
+        if let Some(multihop_diversity) = multihop_diversity {
+            wireguard_constraints.multihop_diversity = *multihop_diversity;
+        }
         match entry_location {
             Some(EntryArgs::Location(location_args)) => {
                 let relay_filter = |relay: &mullvad_types::relay_list::Relay| {
@@ -751,6 +1007,35 @@ impl Relay {
         Ok(())
     }
 
+    async fn exclude(subcmd: ExcludeCommands) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut excluded_locations = rpc.get_settings().await?.excluded_locations;
+        match subcmd {
+            ExcludeCommands::List => {
+                for location in excluded_locations.iter() {
+                    println!("{location}");
+                }
+            }
+            ExcludeCommands::Add(location_args) => {
+                let location = resolve_excluded_location(&mut rpc, location_args).await?;
+                if !excluded_locations.add(location) {
+                    bail!("The location is already excluded");
+                }
+                rpc.set_excluded_locations(excluded_locations).await?;
+                println!("Location excluded");
+            }
+            ExcludeCommands::Remove(location_args) => {
+                let location = resolve_excluded_location(&mut rpc, location_args).await?;
+                if !excluded_locations.remove(&location) {
+                    bail!("The location is not excluded");
+                }
+                rpc.set_excluded_locations(excluded_locations).await?;
+                println!("Location no longer excluded");
+            }
+        }
+        Ok(())
+    }
+
     async fn r#override(subcmd: OverrideCommands) -> Result<()> {
         match subcmd {
             OverrideCommands::Get => {
@@ -967,6 +1252,18 @@ pub async fn resolve_location_constraint(
     }
 }
 
+/// Parses the [`LocationArgs`] into a location that can be excluded, i.e. not "any".
+async fn resolve_excluded_location(
+    rpc: &mut MullvadProxyClient,
+    location_args: LocationArgs,
+) -> Result<GeographicLocationConstraint> {
+    // Allow excluding inactive relays as well
+    match resolve_location_constraint(rpc, location_args, |_| true).await? {
+        Constraint::Any => bail!("\"any\" is not a valid location"),
+        Constraint::Only(location) => Ok(location),
+    }
+}
+
 /// Return a list of all relays that are active and not bridges
 pub async fn get_active_relays() -> Result<Vec<RelayListCountry>> {
     let mut rpc = MullvadProxyClient::new().await?;
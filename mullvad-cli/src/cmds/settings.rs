@@ -0,0 +1,98 @@
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use mullvad_management_interface::MullvadProxyClient;
+use std::{
+    fs::File,
+    io::{read_to_string, stdin, BufReader},
+};
+
+use crate::format::{self, OutputFormat};
+
+#[derive(Subcommand, Debug)]
+pub enum Settings {
+    /// Display all settings as JSON, including those that contain secrets. With '--json', they
+    /// are printed on a single line
+    Get,
+
+    /// Export all settings to a versioned JSON document, for importing on another machine.
+    /// Settings that contain secrets, such as proxy passwords, are left out by default
+    #[clap(arg_required_else_help = true)]
+    Export {
+        /// File to write to. If this is "-", write to standard output
+        file: String,
+
+        /// Also export settings that contain secrets
+        #[arg(long)]
+        include_secrets: bool,
+    },
+
+    /// Replace the settings with a document created by 'export'. Settings that are missing from
+    /// the document are left unchanged
+    #[clap(arg_required_else_help = true)]
+    Import {
+        /// File to read from. If this is "-", read from standard input
+        file: String,
+    },
+}
+
+impl Settings {
+    pub async fn handle(self, output: OutputFormat) -> Result<()> {
+        match self {
+            Settings::Get => Self::get(output).await,
+            Settings::Export {
+                file,
+                include_secrets,
+            } => Self::export(file, include_secrets).await,
+            Settings::Import { file } => Self::import(file).await,
+        }
+    }
+
+    async fn get(output: OutputFormat) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let settings = rpc.get_settings().await?;
+        match output {
+            OutputFormat::Json => format::print_json(&settings),
+            OutputFormat::Human => {
+                let json = serde_json::to_string_pretty(&settings)
+                    .context("Failed to format settings as JSON")?;
+                println!("{json}");
+                Ok(())
+            }
+        }
+    }
+
+    async fn export(dest: String, include_secrets: bool) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let blob = rpc
+            .export_settings(include_secrets)
+            .await
+            .context("Failed to export settings")?;
+
+        match dest.as_str() {
+            "-" => {
+                println!("{blob}");
+                Ok(())
+            }
+            _ => tokio::fs::write(&dest, blob)
+                .await
+                .context(format!("Failed to write to path {dest}")),
+        }
+    }
+
+    async fn import(source: String) -> Result<()> {
+        let blob = tokio::task::spawn_blocking(move || match source.as_str() {
+            "-" => read_to_string(BufReader::new(stdin())).context("Failed to read from stdin"),
+            _ => read_to_string(File::open(&source)?)
+                .context(format!("Failed to read from path: {source}")),
+        })
+        .await
+        .unwrap()?;
+
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.import_settings(blob)
+            .await
+            .context("Failed to import settings")?;
+        println!("Imported settings");
+        Ok(())
+    }
+}
@@ -1,31 +1,151 @@
-use anyhow::Result;
-use clap::Subcommand;
+use anyhow::{anyhow, Result};
+use clap::{Args, Subcommand, ValueEnum};
 use mullvad_management_interface::MullvadProxyClient;
+use nix::unistd::{Gid, Group, Uid, User as UnixUser};
+use talpid_types::split_tunnel::{SplitTunnelMode, SplitTunnelOwner};
 
-/// Manage split tunneling. To launch applications outside the tunnel, use the program
-/// 'mullvad-exclude' instead of this command
+/// Manage split tunneling. To launch applications outside the tunnel, or inside it in
+/// include-only mode, use the program 'mullvad-exclude' instead of this command
 #[derive(Subcommand, Debug)]
 pub enum SplitTunnel {
-    /// List all processes that are excluded from the tunnel
+    /// List all processes that are excluded from the tunnel, or included in include-only mode
     List,
-    /// Add a PID to exclude from the tunnel
+    /// Add a PID to exclude from the tunnel, or to include in include-only mode
     Add { pid: i32 },
-    /// Stop excluding a PID from the tunnel
+    /// Stop excluding or including a PID
     Delete { pid: i32 },
-    /// Stop excluding all processes from the tunnel
+    /// Stop excluding or including all processes
     Clear,
+    /// Display or change whether split processes are excluded from the tunnel or are the only
+    /// processes that use it
+    #[clap(subcommand)]
+    Mode(Mode),
+    /// Manage users and groups whose processes are all excluded from the tunnel, or included in
+    /// include-only mode
+    #[clap(subcommand)]
+    User(User),
+    /// Manage domains whose traffic is excluded from the tunnel, along with their subdomains.
+    /// This requires DNS requests to be sent through the daemon's local DNS forwarder
+    #[clap(subcommand)]
+    Domain(Domain),
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Mode {
+    /// Display the split tunnel mode
+    Get,
+    /// Set the split tunnel mode
+    Set { mode: ModeArg },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum User {
+    /// List the users and groups
+    List,
+    /// Add a user or group
+    #[clap(arg_required_else_help = true)]
+    Add(UserArgs),
+    /// Remove a user or group
+    #[clap(arg_required_else_help = true)]
+    Remove(UserArgs),
+    /// Remove all users and groups
+    Clear,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Domain {
+    /// List the domains
+    List,
+    /// Add a domain, such as 'netflix.com'
+    Add { domain: String },
+    /// Remove a domain
+    Remove { domain: String },
+    /// Remove all domains
+    Clear,
+}
+
+#[derive(Args, Debug)]
+pub struct UserArgs {
+    /// Name or ID of the user, or of the group if '--group' is given
+    name: String,
+    /// Add or remove a group instead of a user
+    #[arg(long)]
+    group: bool,
+}
+
+impl TryFrom<UserArgs> for SplitTunnelOwner {
+    type Error = anyhow::Error;
+
+    fn try_from(args: UserArgs) -> Result<Self> {
+        if args.group {
+            let gid = match args.name.parse() {
+                Ok(gid) => gid,
+                Err(_) => Group::from_name(&args.name)?
+                    .ok_or_else(|| anyhow!("No such group: {}", args.name))?
+                    .gid
+                    .as_raw(),
+            };
+            Ok(SplitTunnelOwner::Group(gid))
+        } else {
+            let uid = match args.name.parse() {
+                Ok(uid) => uid,
+                Err(_) => UnixUser::from_name(&args.name)?
+                    .ok_or_else(|| anyhow!("No such user: {}", args.name))?
+                    .uid
+                    .as_raw(),
+            };
+            Ok(SplitTunnelOwner::User(uid))
+        }
+    }
+}
+
+/// Format an owner along with its name, if it has one on this system.
+fn format_owner(owner: SplitTunnelOwner) -> String {
+    let name = match owner {
+        SplitTunnelOwner::User(uid) => UnixUser::from_uid(Uid::from_raw(uid))
+            .ok()
+            .flatten()
+            .map(|user| user.name),
+        SplitTunnelOwner::Group(gid) => Group::from_gid(Gid::from_raw(gid))
+            .ok()
+            .flatten()
+            .map(|group| group.name),
+    };
+    match name {
+        Some(name) => format!("{owner} ({name})"),
+        None => owner.to_string(),
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeArg {
+    /// Split processes bypass the tunnel, and all other traffic uses it
+    Exclude,
+    /// Only split processes use the tunnel, and all other traffic bypasses it
+    IncludeOnly,
+}
+
+impl From<ModeArg> for SplitTunnelMode {
+    fn from(mode: ModeArg) -> Self {
+        match mode {
+            ModeArg::Exclude => SplitTunnelMode::Exclude,
+            ModeArg::IncludeOnly => SplitTunnelMode::IncludeOnly,
+        }
+    }
 }
 
 impl SplitTunnel {
     pub async fn handle(self) -> Result<()> {
         match self {
             SplitTunnel::List => {
-                let pids = MullvadProxyClient::new()
-                    .await?
-                    .get_split_tunnel_processes()
-                    .await?;
+                let mut rpc = MullvadProxyClient::new().await?;
+                let mode = rpc.get_settings().await?.split_tunnel_mode;
+                let pids = rpc.get_split_tunnel_processes().await?;
 
-                println!("Excluded PIDs:");
+                match mode {
+                    SplitTunnelMode::Exclude => println!("Excluded PIDs:"),
+                    SplitTunnelMode::IncludeOnly => println!("Included PIDs:"),
+                }
                 for pid in &pids {
                     println!("{pid}");
                 }
@@ -33,29 +153,140 @@ impl SplitTunnel {
                 Ok(())
             }
             SplitTunnel::Add { pid } => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                rpc.add_split_tunnel_process(pid).await?;
+                match rpc.get_settings().await?.split_tunnel_mode {
+                    SplitTunnelMode::Exclude => println!("Excluding process"),
+                    SplitTunnelMode::IncludeOnly => println!("Including process"),
+                }
+                Ok(())
+            }
+            SplitTunnel::Delete { pid } => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                rpc.remove_split_tunnel_process(pid).await?;
+                match rpc.get_settings().await?.split_tunnel_mode {
+                    SplitTunnelMode::Exclude => println!("Stopped excluding process"),
+                    SplitTunnelMode::IncludeOnly => println!("Stopped including process"),
+                }
+                Ok(())
+            }
+            SplitTunnel::Clear => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                rpc.clear_split_tunnel_processes().await?;
+                match rpc.get_settings().await?.split_tunnel_mode {
+                    SplitTunnelMode::Exclude => println!("Stopped excluding all processes"),
+                    SplitTunnelMode::IncludeOnly => println!("Stopped including all processes"),
+                }
+                Ok(())
+            }
+            SplitTunnel::Mode(Mode::Get) => {
+                let mode = MullvadProxyClient::new()
+                    .await?
+                    .get_settings()
+                    .await?
+                    .split_tunnel_mode;
+                println!("Split tunnel mode: {mode}");
+                Ok(())
+            }
+            SplitTunnel::Mode(Mode::Set { mode }) => {
+                let mode = SplitTunnelMode::from(mode);
                 MullvadProxyClient::new()
                     .await?
-                    .add_split_tunnel_process(pid)
+                    .set_split_tunnel_mode(mode)
                     .await?;
-                println!("Excluding process");
+                println!("Changed split tunnel mode to {mode}");
                 Ok(())
             }
-            SplitTunnel::Delete { pid } => {
+            SplitTunnel::User(User::List) => Self::list_owners().await,
+            SplitTunnel::User(User::Add(args)) => {
+                Self::add_owner(SplitTunnelOwner::try_from(args)?).await
+            }
+            SplitTunnel::User(User::Remove(args)) => {
+                Self::remove_owner(SplitTunnelOwner::try_from(args)?).await
+            }
+            SplitTunnel::User(User::Clear) => {
                 MullvadProxyClient::new()
                     .await?
-                    .remove_split_tunnel_process(pid)
+                    .set_split_tunnel_owners(&[])
                     .await?;
-                println!("Stopped excluding process");
+                println!("Removed all users and groups");
                 Ok(())
             }
-            SplitTunnel::Clear => {
+            SplitTunnel::Domain(Domain::List) => {
+                let domains = MullvadProxyClient::new()
+                    .await?
+                    .get_settings()
+                    .await?
+                    .split_tunnel_domains;
+                println!("Excluded domains:");
+                for domain in domains {
+                    println!("{domain}");
+                }
+                Ok(())
+            }
+            SplitTunnel::Domain(Domain::Add { domain }) => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                let mut domains = rpc.get_settings().await?.split_tunnel_domains;
+                domains.push(domain.clone());
+                rpc.set_split_tunnel_domains(&domains).await?;
+                println!("Excluding {domain}");
+                Ok(())
+            }
+            SplitTunnel::Domain(Domain::Remove { domain }) => {
+                let mut rpc = MullvadProxyClient::new().await?;
+                let mut domains = rpc.get_settings().await?.split_tunnel_domains;
+                let count = domains.len();
+                domains.retain(|existing| !existing.eq_ignore_ascii_case(&domain));
+                if domains.len() == count {
+                    return Err(anyhow!("Not excluded from the tunnel: {domain}"));
+                }
+                rpc.set_split_tunnel_domains(&domains).await?;
+                println!("Stopped excluding {domain}");
+                Ok(())
+            }
+            SplitTunnel::Domain(Domain::Clear) => {
                 MullvadProxyClient::new()
                     .await?
-                    .clear_split_tunnel_processes()
+                    .set_split_tunnel_domains(&[])
                     .await?;
-                println!("Stopped excluding all processes");
+                println!("Stopped excluding all domains");
                 Ok(())
             }
         }
     }
+
+    async fn list_owners() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let settings = rpc.get_settings().await?;
+        match settings.split_tunnel_mode {
+            SplitTunnelMode::Exclude => println!("Excluded users and groups:"),
+            SplitTunnelMode::IncludeOnly => println!("Included users and groups:"),
+        }
+        for owner in settings.split_tunnel_owners {
+            println!("{}", format_owner(owner));
+        }
+        Ok(())
+    }
+
+    async fn add_owner(owner: SplitTunnelOwner) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut owners = rpc.get_settings().await?.split_tunnel_owners;
+        owners.push(owner);
+        rpc.set_split_tunnel_owners(&owners).await?;
+        println!("Added {}", format_owner(owner));
+        Ok(())
+    }
+
+    async fn remove_owner(owner: SplitTunnelOwner) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut owners = rpc.get_settings().await?.split_tunnel_owners;
+        let count = owners.len();
+        owners.retain(|existing| *existing != owner);
+        if owners.len() == count {
+            return Err(anyhow!("Not split from the tunnel: {owner}"));
+        }
+        rpc.set_split_tunnel_owners(&owners).await?;
+        println!("Removed {}", format_owner(owner));
+        Ok(())
+    }
 }
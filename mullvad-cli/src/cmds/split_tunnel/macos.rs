@@ -22,8 +22,17 @@ pub enum SplitTunnel {
 
 #[derive(Subcommand, Debug)]
 pub enum App {
-    Add { path: PathBuf },
-    Remove { path: PathBuf },
+    /// Exclude an app from the tunnel
+    Add {
+        /// Path to the executable, or the bundle identifier of the app, e.g. `com.apple.Safari`.
+        /// A bundle identifier also matches helper executables inside the app bundle.
+        app: PathBuf,
+    },
+    /// Stop excluding an app from the tunnel
+    Remove {
+        /// Path or bundle identifier, as it was added
+        app: PathBuf,
+    },
     Clear,
 }
 
@@ -57,18 +66,18 @@ impl SplitTunnel {
 
     async fn app(subcmd: App) -> Result<()> {
         match subcmd {
-            App::Add { path } => {
+            App::Add { app } => {
                 MullvadProxyClient::new()
                     .await?
-                    .add_split_tunnel_app(path)
+                    .add_split_tunnel_app(app)
                     .await?;
-                println!("Added path to excluded apps list");
+                println!("Added app to excluded apps list");
                 Ok(())
             }
-            App::Remove { path } => {
+            App::Remove { app } => {
                 MullvadProxyClient::new()
                     .await?
-                    .remove_split_tunnel_app(path)
+                    .remove_split_tunnel_app(app)
                     .await?;
                 println!("Stopped excluding app from tunnel");
                 Ok(())
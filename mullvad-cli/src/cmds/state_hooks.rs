@@ -0,0 +1,77 @@
+use anyhow::{Context, Result};
+use clap::{Subcommand, ValueEnum};
+use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::state_hooks::HookEvent;
+use std::path::PathBuf;
+
+#[derive(Subcommand, Debug)]
+pub enum StateHooks {
+    /// Display the executables that are run when the tunnel enters a state
+    Get,
+
+    /// Run an executable when the tunnel enters a state. The executable and the directories
+    /// containing it must only be modifiable by administrators. The new state is described by
+    /// environment variables, such as MULLVAD_TUNNEL_STATE, MULLVAD_TUNNEL_INTERFACE and
+    /// MULLVAD_RELAY_HOSTNAME
+    #[clap(arg_required_else_help = true)]
+    Set { event: EventArg, path: PathBuf },
+
+    /// Stop running an executable when the tunnel enters a state
+    #[clap(arg_required_else_help = true)]
+    Unset { event: EventArg },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum EventArg {
+    Connected,
+    Disconnected,
+    Error,
+}
+
+impl From<EventArg> for HookEvent {
+    fn from(event: EventArg) -> Self {
+        match event {
+            EventArg::Connected => HookEvent::Connected,
+            EventArg::Disconnected => HookEvent::Disconnected,
+            EventArg::Error => HookEvent::Error,
+        }
+    }
+}
+
+impl StateHooks {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            StateHooks::Get => Self::get().await,
+            StateHooks::Set { event, path } => {
+                let path = path
+                    .canonicalize()
+                    .with_context(|| format!("Failed to find {}", path.display()))?;
+                Self::set(HookEvent::from(event), Some(path)).await
+            }
+            StateHooks::Unset { event } => Self::set(HookEvent::from(event), None).await,
+        }
+    }
+
+    async fn get() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let hooks = rpc.get_settings().await?.state_hooks;
+        for event in [
+            HookEvent::Connected,
+            HookEvent::Disconnected,
+            HookEvent::Error,
+        ] {
+            match hooks.get(event) {
+                Some(path) => println!("{event}: {}", path.display()),
+                None => println!("{event}: none"),
+            }
+        }
+        Ok(())
+    }
+
+    async fn set(event: HookEvent, path: Option<PathBuf>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_state_hook(event, path).await?;
+        println!("Changed {event} hook");
+        Ok(())
+    }
+}
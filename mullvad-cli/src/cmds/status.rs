@@ -1,12 +1,17 @@
-use anyhow::{Context, Result};
+use anyhow::{ensure, Result};
 use clap::{Args, Subcommand};
 use futures::StreamExt;
 use mullvad_management_interface::{client::DaemonEvent, MullvadProxyClient};
-use mullvad_types::{device::DeviceState, states::TunnelState};
+use mullvad_types::{
+    conflicting_software::ConflictingSoftware, device::DeviceState, states::TunnelState,
+};
 use serde::Serialize;
 use std::fmt::Debug;
 
-use crate::format;
+use crate::{
+    format::{self, OutputFormat},
+    print_option,
+};
 
 #[derive(Subcommand, Debug, PartialEq)]
 pub enum Status {
@@ -21,52 +26,91 @@ pub struct StatusArgs {
     verbose: bool,
 
     /// Enable debug output
-    #[arg(long, short = 'd', conflicts_with_all = ["verbose", "json"])]
+    #[arg(long, short = 'd', conflicts_with = "verbose")]
     debug: bool,
-
-    /// Format output as JSON
-    #[arg(long, short = 'j', conflicts_with_all = ["verbose", "debug"])]
-    json: bool,
 }
 
 impl Status {
     pub async fn listen(
         mut rpc: MullvadProxyClient,
         args: StatusArgs,
+        output: OutputFormat,
         mut previous_tunnel_state: TunnelState,
     ) -> Result<()> {
+        let json = output == OutputFormat::Json;
         let mut event_stream = rpc.events_listen().await?;
         while let Some(event) = event_stream.next().await {
             match event? {
                 DaemonEvent::TunnelState(new_state) => {
                     if args.debug {
                         println!("New tunnel state: {new_state:#?}");
-                    } else if args.json {
-                        let json = serde_json::to_string(&new_state)
-                            .context("Failed to format output as JSON")?;
-                        println!("{json}");
+                    } else if json {
+                        format::print_json(&new_state)?;
                     } else {
                         format::print_state(&new_state, Some(&previous_tunnel_state), args.verbose);
                         previous_tunnel_state = new_state;
                     }
                 }
                 DaemonEvent::Settings(settings) => {
-                    print_debug_or_json(&args, "New settings", &settings)?;
+                    print_debug_or_json(&args, json, "New settings", &settings)?;
                 }
                 DaemonEvent::RelayList(relay_list) => {
-                    print_debug_or_json(&args, "New relay list", &relay_list)?;
+                    print_debug_or_json(&args, json, "New relay list", &relay_list)?;
                 }
                 DaemonEvent::AppVersionInfo(app_version_info) => {
-                    print_debug_or_json(&args, "New app version info", &app_version_info)?;
+                    print_debug_or_json(&args, json, "New app version info", &app_version_info)?;
                 }
                 DaemonEvent::Device(device) => {
-                    print_debug_or_json(&args, "Device event", &device)?;
+                    print_debug_or_json(&args, json, "Device event", &device)?;
                 }
                 DaemonEvent::RemoveDevice(device) => {
-                    print_debug_or_json(&args, "Remove device event", &device)?;
+                    print_debug_or_json(&args, json, "Remove device event", &device)?;
                 }
                 DaemonEvent::NewAccessMethod(access_method) => {
-                    print_debug_or_json(&args, "New access method", &access_method)?;
+                    print_debug_or_json(&args, json, "New access method", &access_method)?;
+                }
+                DaemonEvent::ConflictingSoftware(conflicts) => {
+                    if args.debug || json {
+                        print_debug_or_json(&args, json, "Conflicting software", &conflicts)?;
+                    } else {
+                        print_conflicting_software(&conflicts);
+                    }
+                }
+                DaemonEvent::DnsInterference(interference) => {
+                    if args.debug || json {
+                        print_debug_or_json(&args, json, "DNS interference", &interference)?;
+                    } else {
+                        println!("Warning: {interference}. The DNS configuration was restored");
+                    }
+                }
+                DaemonEvent::WireguardBackendFallback(fallback) => {
+                    if args.debug || json {
+                        print_debug_or_json(&args, json, "WireGuard backend fallback", &fallback)?;
+                    } else {
+                        println!("Warning: {fallback}");
+                    }
+                }
+                DaemonEvent::NegotiationRetry(retry) => {
+                    if args.debug || json {
+                        print_debug_or_json(
+                            &args,
+                            json,
+                            "Ephemeral peer negotiation retry",
+                            &retry,
+                        )?;
+                    } else {
+                        println!("{retry}");
+                    }
+                }
+                DaemonEvent::AccountExpiry(event) => {
+                    if args.debug || json {
+                        print_debug_or_json(&args, json, "Account expiry", &event)?;
+                    } else if event.payment_received {
+                        println!(
+                            "Payment received. The account expires {}",
+                            event.expiry.with_timezone(&chrono::Local)
+                        );
+                    }
                 }
             }
         }
@@ -74,24 +118,54 @@ impl Status {
     }
 }
 
-pub async fn handle(cmd: Option<Status>, args: StatusArgs) -> Result<()> {
+pub async fn handle(cmd: Option<Status>, args: StatusArgs, output: OutputFormat) -> Result<()> {
+    ensure!(
+        output == OutputFormat::Human || !(args.verbose || args.debug),
+        "--json cannot be combined with --verbose or --debug"
+    );
+
     let mut rpc = MullvadProxyClient::new().await?;
     let state = rpc.get_tunnel_state().await?;
-    let device = rpc.get_device().await?;
 
-    print_account_logged_out(&state, &device);
+    if output == OutputFormat::Human {
+        let device = rpc.get_device().await?;
+        print_account_logged_out(&state, &device);
+        #[cfg(target_os = "macos")]
+        print_conflicting_software(&rpc.get_conflicting_software().await?);
+    }
 
     if args.debug {
         println!("Tunnel state: {state:#?}");
-    } else if args.json {
-        let json = serde_json::to_string(&state).context("Failed to format output as JSON")?;
-        println!("{json}");
+    } else if output == OutputFormat::Json {
+        format::print_json(&state)?;
     } else {
         format::print_state(&state, None, args.verbose);
+        if args.verbose && state.is_connected() {
+            if let Some(timings) = rpc.get_connection_timings().await? {
+                print_option!("Connection setup", timings);
+            }
+            if let Some(stats) = rpc.get_traffic_stats().await? {
+                print_option!("Traffic", stats);
+                print_option!("Throughput", stats.rate);
+                if let Some(endpoint) = stats.endpoint {
+                    print_option!("Peer endpoint", endpoint);
+                }
+                print_option!(
+                    "Last handshake",
+                    match stats.last_handshake {
+                        Some(time) => format!(
+                            "{} seconds ago",
+                            time.elapsed().unwrap_or_default().as_secs()
+                        ),
+                        None => "never".to_owned(),
+                    }
+                );
+            }
+        }
     }
 
     if cmd == Some(Status::Listen) {
-        Status::listen(rpc, args, state).await?;
+        Status::listen(rpc, args, output, state).await?;
     }
     Ok(())
 }
@@ -107,20 +181,26 @@ fn print_account_logged_out(state: &TunnelState, device: &DeviceState) {
                 DeviceState::LoggedIn(_) => (),
             }
         }
-        TunnelState::Disconnected { .. } | TunnelState::Disconnecting(_) => (),
+        TunnelState::Disconnected { .. } | TunnelState::Disconnecting(_) | TunnelState::Paused => {}
+    }
+}
+
+fn print_conflicting_software(conflicts: &[ConflictingSoftware]) {
+    for conflict in conflicts {
+        println!("Warning: {conflict} may interfere with the VPN");
     }
 }
 
 fn print_debug_or_json<T: Debug + Serialize>(
     args: &StatusArgs,
+    json: bool,
     debug_message: &str,
     t: &T,
 ) -> Result<()> {
     if args.debug {
         println!("{debug_message}: {t:#?}");
-    } else if args.json {
-        let json = serde_json::to_string(&t).context("Failed to format output as JSON")?;
-        println!("{json}");
+    } else if json {
+        format::print_json(t)?;
     }
 
     Ok(())
@@ -0,0 +1,115 @@
+use anyhow::Result;
+use clap::{Args, Subcommand, ValueEnum};
+use mullvad_management_interface::MullvadProxyClient;
+use mullvad_types::trusted_network::{NetworkAction, NetworkMatch, TrustedNetworkRule};
+
+#[derive(Subcommand, Debug)]
+pub enum TrustedNetworks {
+    /// List the trusted network rules and the current network
+    List,
+
+    /// Decide what to do when joining a network, replacing any existing rule for it. The rule is
+    /// applied immediately if the device is on the network
+    #[clap(arg_required_else_help = true)]
+    Set {
+        #[clap(flatten)]
+        network: NetworkArgs,
+        action: ActionArg,
+    },
+
+    /// Remove the rule for a network
+    #[clap(arg_required_else_help = true)]
+    Remove {
+        #[clap(flatten)]
+        network: NetworkArgs,
+    },
+}
+
+#[derive(Args, Debug)]
+#[group(required = true, multiple = false)]
+pub struct NetworkArgs {
+    /// The Wi-Fi network with the given SSID
+    #[arg(long)]
+    ssid: Option<String>,
+
+    /// Any Wi-Fi network that does not have a rule of its own
+    #[arg(long)]
+    unknown_wifi: bool,
+
+    /// Any wired network
+    #[arg(long)]
+    ethernet: bool,
+}
+
+impl From<NetworkArgs> for NetworkMatch {
+    fn from(args: NetworkArgs) -> Self {
+        match args.ssid {
+            Some(ssid) => NetworkMatch::Ssid(ssid),
+            None if args.unknown_wifi => NetworkMatch::UnknownWifi,
+            None => NetworkMatch::Ethernet,
+        }
+    }
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+pub enum ActionArg {
+    /// Disconnect, and do not connect automatically while on the network
+    Disconnect,
+    /// Connect
+    Connect,
+    /// Connect, and block all traffic while disconnected as long as the device is on the network
+    ConnectAndLockdown,
+}
+
+impl From<ActionArg> for NetworkAction {
+    fn from(action: ActionArg) -> Self {
+        match action {
+            ActionArg::Disconnect => NetworkAction::Disconnect,
+            ActionArg::Connect => NetworkAction::Connect,
+            ActionArg::ConnectAndLockdown => NetworkAction::ConnectAndLockdown,
+        }
+    }
+}
+
+impl TrustedNetworks {
+    pub async fn handle(self) -> Result<()> {
+        match self {
+            TrustedNetworks::List => Self::list().await,
+            TrustedNetworks::Set { network, action } => {
+                Self::set(TrustedNetworkRule {
+                    network: NetworkMatch::from(network),
+                    action: NetworkAction::from(action),
+                })
+                .await
+            }
+            TrustedNetworks::Remove { network } => Self::remove(NetworkMatch::from(network)).await,
+        }
+    }
+
+    async fn list() -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let (networks, current_network) = rpc.get_trusted_networks().await?;
+        match current_network {
+            Some(network) => println!("Current network: {network}"),
+            None => println!("Current network: unknown"),
+        }
+        for rule in networks.iter() {
+            println!("{}: {}", rule.network, rule.action);
+        }
+        Ok(())
+    }
+
+    async fn set(rule: TrustedNetworkRule) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_trusted_network_rule(rule).await?;
+        println!("Updated trusted network rule");
+        Ok(())
+    }
+
+    async fn remove(network: NetworkMatch) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.remove_trusted_network_rule(network).await?;
+        println!("Removed trusted network rule");
+        Ok(())
+    }
+}
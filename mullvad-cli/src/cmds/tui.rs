@@ -0,0 +1,274 @@
+//! An interactive dashboard that runs in the terminal, for systems where the desktop app is not
+//! installed. It is redrawn whenever the daemon reports a new tunnel state or new settings, and
+//! once a second to update the throughput. Only plain ANSI escape codes are used, so that it works
+//! in any terminal, including over SSH.
+
+use super::{relay::Relay, relay_constraints::LocationArgs, BooleanOption};
+use crate::format;
+use anyhow::{anyhow, Context, Result};
+use futures::{channel::mpsc, StreamExt};
+use mullvad_management_interface::{client::DaemonEvent, MullvadProxyClient};
+use mullvad_types::states::TunnelState;
+use nix::sys::termios::{self, LocalFlags, SetArg, SpecialCharacterIndices, Termios};
+use std::{
+    fmt::Write as _,
+    io::{self, Read, Write},
+    thread,
+    time::Duration,
+};
+use talpid_types::tunnel::TrafficStats;
+
+/// How often the traffic statistics are updated.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+const CTRL_C: u8 = 0x03;
+const BACKSPACE: u8 = 0x7f;
+const ESCAPE: u8 = 0x1b;
+
+const KEYS: &str = "[c] connect  [d] disconnect  [r] reconnect  [g] change location  \
+                    [l] toggle lockdown mode  [q] quit";
+
+pub async fn run() -> Result<()> {
+    let mut rpc = MullvadProxyClient::new().await?;
+    // Subscribe before fetching the state, so that no update is missed
+    let mut events = rpc.events_listen().await?;
+    let mut dashboard = Dashboard {
+        state: rpc.get_tunnel_state().await?,
+        lockdown: rpc.get_settings().await?.block_when_disconnected,
+        stats: None,
+        location_input: None,
+        message: None,
+    };
+
+    let _terminal = RawTerminal::enter()?;
+    let mut keys = read_keys();
+    let mut refresh = tokio::time::interval(REFRESH_INTERVAL);
+
+    loop {
+        dashboard.draw()?;
+        tokio::select! {
+            event = events.next() => {
+                match event.ok_or_else(|| anyhow!("Lost the connection to the daemon"))?? {
+                    DaemonEvent::TunnelState(state) => dashboard.state = state,
+                    DaemonEvent::Settings(settings) => {
+                        dashboard.lockdown = settings.block_when_disconnected
+                    }
+                    _ => (),
+                }
+            }
+            _ = refresh.tick() => {
+                dashboard.stats = if dashboard.state.is_connected() {
+                    rpc.get_traffic_stats().await?
+                } else {
+                    None
+                };
+            }
+            key = keys.next() => match key {
+                None => break,
+                Some(key) if dashboard.location_input.is_some() => {
+                    dashboard.on_location_key(key).await
+                }
+                Some(b'q' | CTRL_C) => break,
+                Some(key) => dashboard.on_key(&mut rpc, key).await,
+            },
+        }
+    }
+
+    Ok(())
+}
+
+struct Dashboard {
+    state: TunnelState,
+    lockdown: bool,
+    stats: Option<TrafficStats>,
+    /// What has been typed at the location prompt, while it is shown.
+    location_input: Option<String>,
+    /// Outcome of the most recent action.
+    message: Option<String>,
+}
+
+impl Dashboard {
+    async fn on_key(&mut self, rpc: &mut MullvadProxyClient, key: u8) {
+        let result = match key {
+            b'c' => rpc.connect_tunnel().await.map(|_| ()),
+            b'd' => rpc.disconnect_tunnel().await.map(|_| ()),
+            b'r' => rpc.reconnect_tunnel().await.map(|_| ()),
+            b'l' => rpc.set_block_when_disconnected(!self.lockdown).await,
+            b'g' => {
+                self.location_input = Some(String::new());
+                Ok(())
+            }
+            _ => return,
+        };
+        self.message = result.err().map(|error| format!("Error: {error}"));
+    }
+
+    async fn on_location_key(&mut self, key: u8) {
+        let Some(input) = &mut self.location_input else {
+            return;
+        };
+        match key {
+            b'\r' | b'\n' => {
+                let input = self.location_input.take().unwrap_or_default();
+                self.message = Some(match set_location(&input).await {
+                    Ok(()) => format!("Changed location to {}", input.trim()),
+                    Err(error) => format!("Error: {error:#}"),
+                });
+            }
+            ESCAPE | CTRL_C => self.location_input = None,
+            BACKSPACE | b'\x08' => {
+                input.pop();
+            }
+            key if key.is_ascii_graphic() || key == b' ' => input.push(char::from(key)),
+            _ => (),
+        }
+    }
+
+    fn draw(&self) -> io::Result<()> {
+        // Move the cursor to the top left corner and clear the screen
+        let mut screen = String::from("\x1b[H\x1b[2J");
+        screen.push_str("Mullvad VPN\n\n");
+
+        field(&mut screen, "Tunnel state", state_label(&self.state));
+        match &self.state {
+            TunnelState::Connecting {
+                endpoint,
+                location,
+                feature_indicators,
+            }
+            | TunnelState::Connected {
+                endpoint,
+                location,
+                feature_indicators,
+            } => {
+                field(
+                    &mut screen,
+                    "Relay",
+                    format::format_relay_connection(endpoint, location.as_ref(), false),
+                );
+                if let Some(location) = location {
+                    field(
+                        &mut screen,
+                        "Visible location",
+                        format::format_location(location),
+                    );
+                }
+                if !feature_indicators.is_empty() {
+                    field(&mut screen, "Features", feature_indicators);
+                }
+            }
+            TunnelState::Disconnected {
+                location: Some(location),
+                ..
+            } => field(
+                &mut screen,
+                "Visible location",
+                format::format_location(location),
+            ),
+            TunnelState::Error(error_state) => field(&mut screen, "Cause", error_state.cause()),
+            _ => (),
+        }
+        if let Some(stats) = &self.stats {
+            field(&mut screen, "Throughput", &stats.rate);
+            field(&mut screen, "Transferred", stats);
+        }
+        field(
+            &mut screen,
+            "Lockdown mode",
+            BooleanOption::from(self.lockdown),
+        );
+
+        let _ = write!(screen, "\n{KEYS}\n");
+        if let Some(input) = &self.location_input {
+            let _ = write!(
+                screen,
+                "\nLocation (country [city [hostname]], or 'any'), Esc to cancel: {input}_\n"
+            );
+        }
+        if let Some(message) = &self.message {
+            let _ = write!(screen, "\n{message}\n");
+        }
+
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(screen.as_bytes())?;
+        stdout.flush()
+    }
+}
+
+/// Append a line in the same layout as [`crate::print_option`].
+fn field(screen: &mut String, name: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(screen, "{:<4}{:<24}{}", "", format!("{name}:"), value);
+}
+
+fn state_label(state: &TunnelState) -> &'static str {
+    match state {
+        TunnelState::Disconnected {
+            locked_down: true, ..
+        } => "Disconnected (blocking internet access)",
+        TunnelState::Disconnected { .. } => "Disconnected",
+        TunnelState::Connecting { .. } => "Connecting",
+        TunnelState::Connected { .. } => "Connected",
+        TunnelState::Disconnecting(_) => "Disconnecting",
+        TunnelState::Paused => "Paused (blocking internet access)",
+        TunnelState::Error(_) => "Blocked",
+    }
+}
+
+/// Parse `<country> [<city> [<hostname>]]`, or a host name, and select it as the location.
+async fn set_location(input: &str) -> Result<()> {
+    let mut words = input.split_whitespace().map(str::to_lowercase);
+    let country = words.next().context("No location was entered")?;
+    Relay::set_location(LocationArgs {
+        country,
+        city: words.next(),
+        hostname: words.next(),
+    })
+    .await
+}
+
+/// Read key presses on a separate thread, since reading from standard input blocks.
+fn read_keys() -> mpsc::UnboundedReceiver<u8> {
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || {
+        for byte in io::stdin().lock().bytes() {
+            let Ok(byte) = byte else { break };
+            if tx.unbounded_send(byte).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+/// Makes key presses available immediately, without being echoed, and switches to the alternate
+/// screen. The terminal is restored when this is dropped.
+struct RawTerminal {
+    original: Termios,
+}
+
+impl RawTerminal {
+    fn enter() -> Result<Self> {
+        let original =
+            termios::tcgetattr(io::stdin()).context("Standard input is not a terminal")?;
+        let mut raw = original.clone();
+        // Ctrl-C is read as a key, so that the terminal is always restored
+        raw.local_flags
+            .remove(LocalFlags::ICANON | LocalFlags::ECHO | LocalFlags::ISIG);
+        raw.control_chars[SpecialCharacterIndices::VMIN as usize] = 1;
+        raw.control_chars[SpecialCharacterIndices::VTIME as usize] = 0;
+        termios::tcsetattr(io::stdin(), SetArg::TCSANOW, &raw)
+            .context("Failed to configure the terminal")?;
+
+        // Switch to the alternate screen and hide the cursor
+        print!("\x1b[?1049h\x1b[?25l");
+        Ok(RawTerminal { original })
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = termios::tcsetattr(io::stdin(), SetArg::TCSADRAIN, &self.original);
+    }
+}
@@ -1,10 +1,18 @@
 use anyhow::Result;
-use clap::Subcommand;
+use clap::{Subcommand, ValueEnum};
 use mullvad_management_interface::MullvadProxyClient;
 use mullvad_types::{
     constraints::Constraint,
     wireguard::{QuantumResistantState, RotationInterval, DEFAULT_ROTATION_INTERVAL},
 };
+use std::{net::Ipv4Addr, time::Duration};
+
+#[cfg(target_os = "linux")]
+use talpid_types::net::wireguard::Backend;
+use talpid_types::net::{
+    wireguard::{QuantumResistantKem, MAX_DAITA_BUFFER_CAPACITY},
+    OfflineMonitorBackend,
+};
 
 use super::BooleanOption;
 use crate::print_option;
@@ -35,9 +43,57 @@ pub enum TunnelOptions {
         /// Configure the tunnel MTU, or 'any'
         #[arg(long, short = 'm')]
         mtu: Option<Constraint<u16>>,
+        /// Configure the persistent keepalive interval in seconds, or 'any'
+        #[arg(long)]
+        persistent_keepalive: Option<Constraint<u16>>,
+        /// Send WireGuard traffic through this network interface instead of the one used by the
+        /// default route, or 'any'
+        #[cfg(target_os = "linux")]
+        #[arg(long, value_parser = <Constraint<String> as std::str::FromStr>::from_str)]
+        outgoing_interface: Option<Constraint<String>>,
+        /// Select the WireGuard implementation: 'kernel', 'network-manager' or 'userspace'. Use
+        /// 'any' to let the daemon pick one
+        #[cfg(target_os = "linux")]
+        #[arg(long)]
+        backend: Option<Constraint<Backend>>,
+        /// Number of threads that the userspace implementation processes packets on, or 'any' to
+        /// use one per CPU
+        #[arg(long, value_parser = parse_worker_threads)]
+        worker_threads: Option<Constraint<u32>>,
         /// Configure quantum-resistant key exchange
         #[arg(long)]
         quantum_resistant: Option<QuantumResistantState>,
+        /// Select the key encapsulation mechanisms used for quantum-resistant key exchange:
+        /// 'classic-mceliece' or 'ml-kem' to only use one of them, or 'auto' to combine them
+        #[arg(long)]
+        quantum_resistant_kem: Option<QuantumResistantKem>,
+        /// Seconds to wait for a connection to the config service of a relay when negotiating
+        /// quantum-resistant keys or DAITA
+        #[arg(long, value_parser = parse_seconds)]
+        negotiation_connect_timeout: Option<Duration>,
+        /// Number of times that a failed negotiation is retried before reconnecting
+        #[arg(long)]
+        negotiation_retries: Option<u32>,
+        /// Seconds to wait before the first negotiation retry. The delay is doubled for every
+        /// subsequent retry
+        #[arg(long, value_parser = parse_seconds)]
+        negotiation_initial_backoff: Option<Duration>,
+        /// Maximum number of seconds to wait between two negotiation retries
+        #[arg(long, value_parser = parse_seconds)]
+        negotiation_max_backoff: Option<Duration>,
+        /// Seconds between the pings that probe whether the tunnel works
+        #[arg(long, value_parser = parse_ping_interval)]
+        ping_interval: Option<Duration>,
+        /// Number of pings that may go unanswered before the tunnel is considered broken
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+        ping_failure_threshold: Option<u32>,
+        /// Address inside the tunnel to ping, or 'any' to ping the relay
+        #[arg(long)]
+        ping_target: Option<Constraint<Ipv4Addr>>,
+        /// Only use IPv6 inside the tunnel and reach IPv4 hosts through NAT64. This implies that
+        /// IPv6 is enabled
+        #[arg(long)]
+        ipv6_only: Option<BooleanOption>,
         /// Configure whether to enable DAITA
         #[arg(long)]
         daita: Option<BooleanOption>,
@@ -52,9 +108,127 @@ pub enum TunnelOptions {
         rotate_key: Option<RotateKey>,
     },
 
+    /// Limit the bandwidth overhead of DAITA. Lower limits make traffic analysis easier
+    #[clap(arg_required_else_help = true)]
+    Daita {
+        /// Maximum share of the traffic that may be padding, in percent, or 'any' to use the
+        /// limit of the relay
+        #[arg(long, value_parser = parse_percent)]
+        max_padding: Option<Constraint<u8>>,
+        /// Maximum share of the time that outgoing traffic may be blocked, in percent, or 'any'
+        /// to use the limit of the relay
+        #[arg(long, value_parser = parse_percent)]
+        max_blocking: Option<Constraint<u8>>,
+        /// Comma-separated indices of the machines provided by the relay to run, or 'any' to run
+        /// every machine
+        #[arg(long, value_parser = parse_machines)]
+        machines: Option<Constraint<Vec<u32>>>,
+        /// Number of events that DAITA can buffer, or 'any' for the default
+        #[arg(long, value_parser = parse_capacity)]
+        events_capacity: Option<Constraint<u32>>,
+        /// Number of actions that DAITA can buffer, or 'any' for the default
+        #[arg(long, value_parser = parse_capacity)]
+        actions_capacity: Option<Constraint<u32>>,
+    },
+
     /// Enable or disable IPv6 in the tunnel
     #[clap(arg_required_else_help = true)]
     Ipv6 { state: BooleanOption },
+
+    /// Drop all IPv6 traffic at the firewall in every state, even if the relay supports IPv6.
+    /// This cannot be combined with the IPv6-only WireGuard option
+    #[clap(arg_required_else_help = true)]
+    BlockIpv6 { state: BooleanOption },
+
+    /// Select how the daemon detects that the device is offline, e.g. if it is considered
+    /// offline while it is not. The tunnel is not connected while offline. This takes effect
+    /// when the daemon is restarted
+    #[clap(arg_required_else_help = true)]
+    OfflineMonitor { backend: OfflineMonitorArg },
+
+    /// Set the firewall mark used for tunnel traffic. This takes effect when the daemon is
+    /// restarted
+    #[cfg(target_os = "linux")]
+    #[clap(arg_required_else_help = true)]
+    Fwmark {
+        /// The mark, in decimal or prefixed with '0x' for hexadecimal, or 'any' to use the
+        /// default mark
+        #[arg(value_parser = parse_fwmark)]
+        mark: Constraint<u32>,
+    },
+
+    /// Configure how the firewall coexists with other nftables tables. This takes effect when
+    /// the daemon is restarted
+    #[cfg(target_os = "linux")]
+    #[clap(arg_required_else_help = true)]
+    Nftables {
+        /// Hook priority of the input, output and forward chains. Chains with a lower priority
+        /// are evaluated first. Must be between -149 and 149
+        #[arg(long, allow_hyphen_values = true)]
+        priority: Option<i32>,
+        /// Accept forwarded traffic that the firewall has no rule for, and leave it to other
+        /// tables, instead of dropping it
+        #[arg(long)]
+        cooperative: Option<BooleanOption>,
+    },
+
+    /// Set the routing table and rule priority used for policy routing, e.g. to avoid conflicts
+    /// with your own routing rules. This takes effect when the daemon is restarted
+    #[cfg(target_os = "linux")]
+    #[clap(arg_required_else_help = true)]
+    Routing {
+        /// Routing table that routes through the tunnel are added to, or 'any' to use the
+        /// default table. The tables 0, 253, 254 and 255 are reserved
+        #[arg(long)]
+        table: Option<Constraint<u32>>,
+        /// Priority of the routing rules, between 1 and 32764, or 'any' to let the kernel choose.
+        /// The rules use this priority and the one after it
+        #[arg(long)]
+        rule_priority: Option<Constraint<u32>>,
+    },
+
+    /// Set the weight of the WFP sublayers that contain the firewall filters. Sublayers with a
+    /// higher weight are evaluated first. This takes effect when the daemon is restarted
+    #[cfg(target_os = "windows")]
+    #[clap(arg_required_else_help = true)]
+    WfpSublayerWeight {
+        /// The weight, between 1 and 65535, or 'any' to use the highest weight
+        #[arg(value_parser = parse_sublayer_weight)]
+        weight: Constraint<u16>,
+    },
+
+    /// Install the firewall rules in a PF anchor that is referenced from your own ruleset,
+    /// instead of adding an anchor to the main ruleset. Traffic is not blocked unless the anchor
+    /// is referenced. This takes effect when the daemon is restarted
+    #[cfg(target_os = "macos")]
+    #[clap(arg_required_else_help = true)]
+    PfAnchor {
+        /// The anchor path, e.g. 'com.apple/mullvad', or 'any' to use the default anchor
+        #[arg(value_parser = <Constraint<String> as std::str::FromStr>::from_str)]
+        anchor: Constraint<String>,
+    },
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OfflineMonitorArg {
+    /// Consider the device offline while there is no default route
+    Routes,
+    /// Consider the device offline while NetworkManager reports that it is not connected
+    #[cfg(target_os = "linux")]
+    NetworkManager,
+    /// Never consider the device offline
+    Disabled,
+}
+
+impl From<OfflineMonitorArg> for OfflineMonitorBackend {
+    fn from(backend: OfflineMonitorArg) -> Self {
+        match backend {
+            OfflineMonitorArg::Routes => OfflineMonitorBackend::Routes,
+            #[cfg(target_os = "linux")]
+            OfflineMonitorArg::NetworkManager => OfflineMonitorBackend::NetworkManager,
+            OfflineMonitorArg::Disabled => OfflineMonitorBackend::Disabled,
+        }
+    }
 }
 
 #[derive(Subcommand, Debug, Clone)]
@@ -73,7 +247,8 @@ impl Tunnel {
 
     async fn get() -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
-        let tunnel_options = rpc.get_settings().await?.tunnel_options;
+        let settings = rpc.get_settings().await?;
+        let tunnel_options = &settings.tunnel_options;
 
         println!("OpenVPN options");
 
@@ -96,12 +271,113 @@ impl Tunnel {
                 .map(|val| val.to_string())
                 .unwrap_or("unset".to_string()),
         );
+        print_option!(
+            "Persistent keepalive",
+            tunnel_options
+                .wireguard
+                .persistent_keepalive
+                .map(|val| format!("{val} s"))
+                .unwrap_or("unset".to_string()),
+        );
+        #[cfg(target_os = "linux")]
+        print_option!(
+            "Outgoing interface",
+            tunnel_options
+                .wireguard
+                .outgoing_interface
+                .as_deref()
+                .unwrap_or("unset"),
+        );
+        #[cfg(target_os = "linux")]
+        print_option!(
+            "Backend",
+            tunnel_options
+                .wireguard
+                .backend
+                .map(|backend| backend.to_string())
+                .unwrap_or("automatic".to_string()),
+        );
+        print_option!(
+            "Worker threads",
+            tunnel_options
+                .wireguard
+                .worker_threads
+                .map(|val| val.to_string())
+                .unwrap_or("unset".to_string()),
+        );
         print_option!(
             "Quantum resistance",
             tunnel_options.wireguard.quantum_resistant,
         );
+        print_option!(
+            "Quantum-resistant KEM",
+            tunnel_options.wireguard.quantum_resistant_kem,
+        );
+        let policy = tunnel_options.wireguard.negotiation_retry_policy;
+        print_option!(
+            "Negotiation retries",
+            format!(
+                "{} (connect timeout {}s, backoff {}s to {}s)",
+                policy.retries,
+                policy.connect_timeout.as_secs(),
+                policy.initial_backoff.as_secs(),
+                policy.max_backoff.as_secs(),
+            ),
+        );
+
+        let check = tunnel_options.wireguard.connectivity_check;
+        print_option!(
+            "Connectivity check",
+            format!(
+                "ping {} every {}s, fail after {} pings",
+                check
+                    .ping_target
+                    .map(|target| target.to_string())
+                    .unwrap_or("relay".to_string()),
+                check.ping_interval.as_secs(),
+                check.failure_threshold,
+            ),
+        );
+
+        print_option!(
+            "IPv6 only",
+            if tunnel_options.wireguard.ipv6_only {
+                "on"
+            } else {
+                "off"
+            }
+        );
 
         print_option!("DAITA", tunnel_options.wireguard.daita.enabled);
+        let daita = &tunnel_options.wireguard.daita.parameters;
+        let percent = |percent: Option<u8>| {
+            percent
+                .map(|percent| format!("{percent}%"))
+                .unwrap_or("relay default".to_string())
+        };
+        let capacity = |capacity: Option<u32>| {
+            capacity
+                .map(|capacity| capacity.to_string())
+                .unwrap_or("default".to_string())
+        };
+        print_option!("DAITA max padding", percent(daita.max_padding_percent));
+        print_option!("DAITA max blocking", percent(daita.max_blocking_percent));
+        print_option!(
+            "DAITA machines",
+            daita
+                .machines
+                .as_ref()
+                .map(|machines| {
+                    machines
+                        .iter()
+                        .map(|index| index.to_string())
+                        .collect::<Vec<_>>()
+                        .join(",")
+                })
+                .unwrap_or("all".to_string()),
+        );
+        print_option!("DAITA events capacity", capacity(daita.events_capacity));
+        print_option!("DAITA actions capacity", capacity(daita.actions_capacity));
 
         let key = rpc.get_wireguard_key().await?;
         print_option!("Public key", key.key,);
@@ -109,6 +385,10 @@ impl Tunnel {
             "Created {}",
             key.created.with_timezone(&chrono::Local)
         ),);
+        print_option!(format_args!(
+            "Next rotation {}",
+            key.next_rotation.with_timezone(&chrono::Local)
+        ),);
         print_option!(
             "Rotation interval",
             match tunnel_options.wireguard.rotation_interval {
@@ -127,6 +407,50 @@ impl Tunnel {
                 "off"
             }
         );
+        print_option!(
+            "Block IPv6",
+            if tunnel_options.generic.block_ipv6 {
+                "on"
+            } else {
+                "off"
+            }
+        );
+
+        print_option!("Offline monitor", settings.offline_monitor);
+        #[cfg(target_os = "linux")]
+        print_option!("Tunnel fwmark", format!("{:#x}", settings.tunnel_fwmark()));
+        #[cfg(target_os = "macos")]
+        print_option!(
+            "PF anchor",
+            settings.pf_anchor.as_deref().unwrap_or("default")
+        );
+        #[cfg(target_os = "windows")]
+        print_option!(
+            "WFP sublayer weight",
+            settings.wfp_sublayer_weight.unwrap_or(u16::MAX)
+        );
+        #[cfg(target_os = "linux")]
+        print_option!("nftables priority", settings.nftables.priority);
+        #[cfg(target_os = "linux")]
+        print_option!(
+            "nftables cooperative",
+            if settings.nftables.cooperative {
+                "on"
+            } else {
+                "off"
+            }
+        );
+        #[cfg(target_os = "linux")]
+        print_option!("Routing table", settings.routing.table_id());
+        #[cfg(target_os = "linux")]
+        print_option!(
+            "Routing rule priority",
+            settings
+                .routing
+                .rule_priority
+                .map(|priority| priority.to_string())
+                .unwrap_or_else(|| "default".to_owned())
+        );
 
         Ok(())
     }
@@ -136,14 +460,71 @@ impl Tunnel {
             TunnelOptions::Openvpn { mssfix } => Self::handle_openvpn(mssfix).await,
             TunnelOptions::Wireguard {
                 mtu,
+                persistent_keepalive,
+                #[cfg(target_os = "linux")]
+                outgoing_interface,
+                #[cfg(target_os = "linux")]
+                backend,
+                worker_threads,
                 quantum_resistant,
+                quantum_resistant_kem,
+                negotiation_connect_timeout,
+                negotiation_retries,
+                negotiation_initial_backoff,
+                negotiation_max_backoff,
+                ping_interval,
+                ping_failure_threshold,
+                ping_target,
+                ipv6_only,
                 daita,
                 daita_direct_only,
                 rotation_interval,
                 rotate_key,
             } => {
+                #[cfg(target_os = "linux")]
+                if let Some(interface) = outgoing_interface {
+                    Self::handle_outgoing_interface(interface).await?;
+                }
+                #[cfg(target_os = "linux")]
+                if let Some(backend) = backend {
+                    Self::handle_backend(backend).await?;
+                }
+                if let Some(threads) = worker_threads {
+                    Self::handle_worker_threads(threads).await?;
+                }
+                if let Some(kem) = quantum_resistant_kem {
+                    Self::handle_quantum_resistant_kem(kem).await?;
+                }
+                if negotiation_connect_timeout.is_some()
+                    || negotiation_retries.is_some()
+                    || negotiation_initial_backoff.is_some()
+                    || negotiation_max_backoff.is_some()
+                {
+                    Self::handle_negotiation_retry_policy(
+                        negotiation_connect_timeout,
+                        negotiation_retries,
+                        negotiation_initial_backoff,
+                        negotiation_max_backoff,
+                    )
+                    .await?;
+                }
+                if ping_interval.is_some()
+                    || ping_failure_threshold.is_some()
+                    || ping_target.is_some()
+                {
+                    Self::handle_connectivity_check(
+                        ping_interval,
+                        ping_failure_threshold,
+                        ping_target,
+                    )
+                    .await?;
+                }
+                if let Some(ipv6_only) = ipv6_only {
+                    Self::handle_ipv6_only(ipv6_only).await?;
+                }
                 Self::handle_wireguard(
                     mtu,
+                    persistent_keepalive,
                     quantum_resistant,
                     daita,
                     daita_direct_only,
@@ -152,8 +533,144 @@ impl Tunnel {
                 )
                 .await
             }
+            TunnelOptions::Daita {
+                max_padding,
+                max_blocking,
+                machines,
+                events_capacity,
+                actions_capacity,
+            } => {
+                Self::handle_daita(
+                    max_padding,
+                    max_blocking,
+                    machines,
+                    events_capacity,
+                    actions_capacity,
+                )
+                .await
+            }
             TunnelOptions::Ipv6 { state } => Self::handle_ipv6(state).await,
+            TunnelOptions::BlockIpv6 { state } => Self::handle_block_ipv6(state).await,
+            TunnelOptions::OfflineMonitor { backend } => {
+                Self::handle_offline_monitor(OfflineMonitorBackend::from(backend)).await
+            }
+            #[cfg(target_os = "linux")]
+            TunnelOptions::Fwmark { mark } => Self::handle_fwmark(mark).await,
+            #[cfg(target_os = "linux")]
+            TunnelOptions::Nftables {
+                priority,
+                cooperative,
+            } => Self::handle_nftables(priority, cooperative).await,
+            #[cfg(target_os = "linux")]
+            TunnelOptions::Routing {
+                table,
+                rule_priority,
+            } => Self::handle_routing(table, rule_priority).await,
+            #[cfg(target_os = "windows")]
+            TunnelOptions::WfpSublayerWeight { weight } => {
+                Self::handle_wfp_sublayer_weight(weight).await
+            }
+            #[cfg(target_os = "macos")]
+            TunnelOptions::PfAnchor { anchor } => Self::handle_pf_anchor(anchor).await,
+        }
+    }
+
+    async fn handle_offline_monitor(backend: OfflineMonitorBackend) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_offline_monitor_backend(backend).await?;
+        println!("Offline monitor has been updated. Restart the daemon for it to take effect");
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn handle_fwmark(mark: Constraint<u32>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_tunnel_fwmark(mark.option()).await?;
+        println!("Tunnel fwmark has been updated. Restart the daemon for it to take effect");
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn handle_nftables(
+        priority: Option<i32>,
+        cooperative: Option<BooleanOption>,
+    ) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut nftables = rpc.get_settings().await?.nftables;
+        if let Some(priority) = priority {
+            nftables.priority = priority;
+        }
+        if let Some(cooperative) = cooperative {
+            nftables.cooperative = *cooperative;
+        }
+        rpc.set_nftables_settings(nftables).await?;
+        println!("nftables settings have been updated. Restart the daemon for them to take effect");
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn handle_routing(
+        table: Option<Constraint<u32>>,
+        rule_priority: Option<Constraint<u32>>,
+    ) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut routing = rpc.get_settings().await?.routing;
+        if let Some(table) = table {
+            routing.table_id = table.option();
         }
+        if let Some(rule_priority) = rule_priority {
+            routing.rule_priority = rule_priority.option();
+        }
+        rpc.set_routing_settings(routing).await?;
+        println!("Routing settings have been updated. Restart the daemon for them to take effect");
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    async fn handle_wfp_sublayer_weight(weight: Constraint<u16>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_wfp_sublayer_weight(weight.option()).await?;
+        println!("WFP sublayer weight has been updated. Restart the daemon for it to take effect");
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    async fn handle_pf_anchor(anchor: Constraint<String>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_pf_anchor(anchor.option()).await?;
+        println!("PF anchor has been updated. Restart the daemon for it to take effect");
+        Ok(())
+    }
+
+    async fn handle_daita(
+        max_padding: Option<Constraint<u8>>,
+        max_blocking: Option<Constraint<u8>>,
+        machines: Option<Constraint<Vec<u32>>>,
+        events_capacity: Option<Constraint<u32>>,
+        actions_capacity: Option<Constraint<u32>>,
+    ) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut daita = rpc.get_settings().await?.tunnel_options.wireguard.daita;
+        let parameters = &mut daita.parameters;
+        if let Some(max_padding) = max_padding {
+            parameters.max_padding_percent = max_padding.option();
+        }
+        if let Some(max_blocking) = max_blocking {
+            parameters.max_blocking_percent = max_blocking.option();
+        }
+        if let Some(machines) = machines {
+            parameters.machines = machines.option();
+        }
+        if let Some(events_capacity) = events_capacity {
+            parameters.events_capacity = events_capacity.option();
+        }
+        if let Some(actions_capacity) = actions_capacity {
+            parameters.actions_capacity = actions_capacity.option();
+        }
+        parameters.validate()?;
+        rpc.set_daita_settings(daita).await?;
+        println!("DAITA parameters have been updated");
+        Ok(())
     }
 
     async fn handle_ipv6(state: BooleanOption) -> Result<()> {
@@ -163,6 +680,20 @@ impl Tunnel {
         Ok(())
     }
 
+    async fn handle_ipv6_only(state: BooleanOption) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_wireguard_ipv6_only(*state).await?;
+        println!("IPv6 only: {state}");
+        Ok(())
+    }
+
+    async fn handle_block_ipv6(state: BooleanOption) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_block_ipv6(*state).await?;
+        println!("Block IPv6: {state}");
+        Ok(())
+    }
+
     async fn handle_openvpn(mssfix: Option<Constraint<u16>>) -> Result<()> {
         let mut rpc = MullvadProxyClient::new().await?;
 
@@ -174,8 +705,96 @@ impl Tunnel {
         Ok(())
     }
 
+    #[cfg(target_os = "linux")]
+    async fn handle_outgoing_interface(interface: Constraint<String>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_wireguard_outgoing_interface(interface.option())
+            .await?;
+        println!("Outgoing interface has been updated");
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn handle_backend(backend: Constraint<Backend>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_wireguard_backend(backend.option()).await?;
+        println!("WireGuard backend has been updated");
+        Ok(())
+    }
+
+    async fn handle_worker_threads(threads: Constraint<u32>) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_wireguard_worker_threads(threads.option()).await?;
+        println!("Worker thread setting has been updated");
+        Ok(())
+    }
+
+    async fn handle_quantum_resistant_kem(kem: QuantumResistantKem) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        rpc.set_quantum_resistant_kem(kem).await?;
+        println!("Quantum-resistant KEM setting has been updated");
+        Ok(())
+    }
+
+    async fn handle_negotiation_retry_policy(
+        connect_timeout: Option<Duration>,
+        retries: Option<u32>,
+        initial_backoff: Option<Duration>,
+        max_backoff: Option<Duration>,
+    ) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut policy = rpc
+            .get_settings()
+            .await?
+            .tunnel_options
+            .wireguard
+            .negotiation_retry_policy;
+        if let Some(connect_timeout) = connect_timeout {
+            policy.connect_timeout = connect_timeout;
+        }
+        if let Some(retries) = retries {
+            policy.retries = retries;
+        }
+        if let Some(initial_backoff) = initial_backoff {
+            policy.initial_backoff = initial_backoff;
+        }
+        if let Some(max_backoff) = max_backoff {
+            policy.max_backoff = max_backoff;
+        }
+        rpc.set_negotiation_retry_policy(policy).await?;
+        println!("Negotiation retry policy has been updated");
+        Ok(())
+    }
+
+    async fn handle_connectivity_check(
+        ping_interval: Option<Duration>,
+        failure_threshold: Option<u32>,
+        ping_target: Option<Constraint<Ipv4Addr>>,
+    ) -> Result<()> {
+        let mut rpc = MullvadProxyClient::new().await?;
+        let mut options = rpc
+            .get_settings()
+            .await?
+            .tunnel_options
+            .wireguard
+            .connectivity_check;
+        if let Some(ping_interval) = ping_interval {
+            options.ping_interval = ping_interval;
+        }
+        if let Some(failure_threshold) = failure_threshold {
+            options.failure_threshold = failure_threshold;
+        }
+        if let Some(ping_target) = ping_target {
+            options.ping_target = ping_target.option();
+        }
+        rpc.set_connectivity_check_options(options).await?;
+        println!("Connectivity check has been updated");
+        Ok(())
+    }
+
     async fn handle_wireguard(
         mtu: Option<Constraint<u16>>,
+        persistent_keepalive: Option<Constraint<u16>>,
         quantum_resistant: Option<QuantumResistantState>,
         daita: Option<BooleanOption>,
         daita_direct_only: Option<BooleanOption>,
@@ -189,6 +808,12 @@ impl Tunnel {
             println!("MTU parameter has been updated");
         }
 
+        if let Some(interval) = persistent_keepalive {
+            rpc.set_wireguard_persistent_keepalive(interval.option())
+                .await?;
+            println!("Persistent keepalive setting has been updated");
+        }
+
         if let Some(quantum_resistant) = quantum_resistant {
             rpc.set_quantum_resistant_tunnel(quantum_resistant).await?;
             println!("Quantum resistant setting has been updated");
@@ -229,3 +854,96 @@ impl Tunnel {
         Ok(())
     }
 }
+
+#[cfg(target_os = "linux")]
+fn parse_fwmark(value: &str) -> Result<Constraint<u32>, String> {
+    if value.eq_ignore_ascii_case("any") {
+        return Ok(Constraint::Any);
+    }
+    let mark = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|error| error.to_string())?;
+    if mark == 0 {
+        return Err("the mark must not be zero".to_owned());
+    }
+    Ok(Constraint::Only(mark))
+}
+
+#[cfg(target_os = "windows")]
+fn parse_sublayer_weight(value: &str) -> Result<Constraint<u16>, String> {
+    if value.eq_ignore_ascii_case("any") {
+        return Ok(Constraint::Any);
+    }
+    let weight = value.parse::<u16>().map_err(|error| error.to_string())?;
+    if weight == 0 {
+        return Err("the weight must not be zero".to_owned());
+    }
+    Ok(Constraint::Only(weight))
+}
+
+fn parse_worker_threads(value: &str) -> Result<Constraint<u32>, String> {
+    if value.eq_ignore_ascii_case("any") {
+        return Ok(Constraint::Any);
+    }
+    let threads = value.parse::<u32>().map_err(|error| error.to_string())?;
+    if threads == 0 {
+        return Err("at least one thread is required".to_owned());
+    }
+    Ok(Constraint::Only(threads))
+}
+
+fn parse_percent(value: &str) -> Result<Constraint<u8>, String> {
+    if value.eq_ignore_ascii_case("any") {
+        return Ok(Constraint::Any);
+    }
+    let percent = value.parse::<u8>().map_err(|error| error.to_string())?;
+    if !(1..=100).contains(&percent) {
+        return Err("the percentage must be between 1 and 100".to_owned());
+    }
+    Ok(Constraint::Only(percent))
+}
+
+fn parse_machines(value: &str) -> Result<Constraint<Vec<u32>>, String> {
+    if value.eq_ignore_ascii_case("any") {
+        return Ok(Constraint::Any);
+    }
+    let mut machines = value
+        .split(',')
+        .map(|index| index.trim().parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| error.to_string())?;
+    machines.sort_unstable();
+    machines.dedup();
+    Ok(Constraint::Only(machines))
+}
+
+fn parse_capacity(value: &str) -> Result<Constraint<u32>, String> {
+    if value.eq_ignore_ascii_case("any") {
+        return Ok(Constraint::Any);
+    }
+    let capacity = value.parse::<u32>().map_err(|error| error.to_string())?;
+    if !(1..=MAX_DAITA_BUFFER_CAPACITY).contains(&capacity) {
+        return Err(format!(
+            "the capacity must be between 1 and {MAX_DAITA_BUFFER_CAPACITY}"
+        ));
+    }
+    Ok(Constraint::Only(capacity))
+}
+
+fn parse_ping_interval(value: &str) -> Result<Duration, String> {
+    let interval = parse_seconds(value)?;
+    if interval.is_zero() {
+        return Err("the interval must be at least one second".to_owned());
+    }
+    Ok(interval)
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    let seconds = value.parse::<u64>().map_err(|error| error.to_string())?;
+    Ok(Duration::from_secs(seconds))
+}
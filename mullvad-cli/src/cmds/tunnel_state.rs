@@ -1,5 +1,6 @@
-use crate::format;
+use crate::{exit_code::BlockedError, format};
 use anyhow::{anyhow, Result};
+use clap::ValueEnum;
 use futures::{Stream, StreamExt};
 use mullvad_management_interface::{client::DaemonEvent, MullvadProxyClient};
 use mullvad_types::{device::DeviceState, states::TunnelState};
@@ -20,7 +21,10 @@ pub async fn connect(wait: bool) -> Result<()> {
         if let Some(receiver) = listener {
             wait_for_tunnel_state(receiver, |state| match state {
                 TunnelState::Connected { .. } => Ok(true),
-                TunnelState::Error(_) => Err(anyhow!("Failed to connect")),
+                TunnelState::Error(error_state) => Err(anyhow::Error::new(BlockedError(
+                    error_state.cause().clone(),
+                ))
+                .context("Failed to connect")),
                 _ => Ok(false),
             })
             .await?;
@@ -48,6 +52,32 @@ pub async fn disconnect(wait: bool) -> Result<()> {
     Ok(())
 }
 
+pub async fn pause(wait: bool) -> Result<()> {
+    let mut rpc = MullvadProxyClient::new().await?;
+
+    let listener = if wait {
+        Some(rpc.events_listen().await?)
+    } else {
+        None
+    };
+
+    if rpc.pause_tunnel().await? {
+        if let Some(receiver) = listener {
+            wait_for_tunnel_state(receiver, |state| match state {
+                TunnelState::Paused => Ok(true),
+                TunnelState::Error(error_state) => Err(anyhow::Error::new(BlockedError(
+                    error_state.cause().clone(),
+                ))
+                .context("Failed to pause")),
+                _ => Ok(false),
+            })
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
 pub async fn reconnect(wait: bool) -> Result<()> {
     let mut rpc = MullvadProxyClient::new().await?;
 
@@ -64,7 +94,10 @@ pub async fn reconnect(wait: bool) -> Result<()> {
         if let Some(receiver) = listener {
             wait_for_tunnel_state(receiver, |state| match state {
                 TunnelState::Connected { .. } => Ok(true),
-                TunnelState::Error(_) => Err(anyhow!("Failed to reconnect")),
+                TunnelState::Error(error_state) => Err(anyhow::Error::new(BlockedError(
+                    error_state.cause().clone(),
+                ))
+                .context("Failed to reconnect")),
                 _ => Ok(false),
             })
             .await?;
@@ -74,6 +107,40 @@ pub async fn reconnect(wait: bool) -> Result<()> {
     Ok(())
 }
 
+/// Tunnel states that can be waited for.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetState {
+    Connected,
+    Disconnected,
+    Paused,
+}
+
+/// Wait until the tunnel is in the `target` state, which may already be the case. Fails if the
+/// tunnel enters the error state while waiting to be connected or paused.
+pub async fn wait(target: TargetState) -> Result<()> {
+    let mut rpc = MullvadProxyClient::new().await?;
+
+    // Listen before getting the current state, so that no transition is missed
+    let listener = rpc.events_listen().await?;
+    let state = rpc.get_tunnel_state().await?;
+
+    let is_target = |state: &TunnelState| match (target, state) {
+        (TargetState::Connected, TunnelState::Connected { .. })
+        | (TargetState::Disconnected, TunnelState::Disconnected { .. })
+        | (TargetState::Paused, TunnelState::Paused) => Ok(true),
+        (TargetState::Connected | TargetState::Paused, TunnelState::Error(error_state)) => Err(
+            anyhow::Error::new(BlockedError(error_state.cause().clone())),
+        ),
+        _ => Ok(false),
+    };
+
+    format::print_state(&state, None, false);
+    if is_target(&state)? {
+        return Ok(());
+    }
+    wait_for_tunnel_state(listener, is_target).await
+}
+
 async fn wait_for_tunnel_state(
     mut event_stream: impl Stream<Item = std::result::Result<DaemonEvent, mullvad_management_interface::Error>>
         + Unpin,
@@ -0,0 +1,45 @@
+use anyhow::Result;
+use mullvad_management_interface::MullvadProxyClient;
+
+pub async fn print() -> Result<()> {
+    let mut rpc = MullvadProxyClient::new().await?;
+    let usage = rpc.get_data_usage().await?;
+
+    if usage.iter().next().is_none() {
+        println!("No data has gone through the tunnel yet");
+        return Ok(());
+    }
+    println!(
+        "{:<9}{:>12}{:>12}{:>12}",
+        "Month", "Sent", "Received", "Total"
+    );
+    for month in usage.iter() {
+        println!(
+            "{:<9}{:>12}{:>12}{:>12}",
+            month.to_string(),
+            format_bytes(month.tx_bytes),
+            format_bytes(month.rx_bytes),
+            format_bytes(month.total_bytes())
+        );
+    }
+    Ok(())
+}
+
+/// Format a number of bytes using decimal units, e.g. `1.5 GB`.
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["kB", "MB", "GB", "TB", "PB"];
+
+    if bytes < 1000 {
+        return format!("{bytes} B");
+    }
+    let mut value = bytes as f64 / 1000.0;
+    let mut unit = UNITS[0];
+    for next_unit in &UNITS[1..] {
+        if value < 1000.0 {
+            break;
+        }
+        value /= 1000.0;
+        unit = next_unit;
+    }
+    format!("{value:.1} {unit}")
+}
@@ -1,4 +1,5 @@
 use anyhow::{Context, Result};
+use itertools::Itertools;
 use mullvad_management_interface::MullvadProxyClient;
 
 pub async fn print() -> Result<()> {
@@ -17,6 +18,24 @@ pub async fn print() -> Result<()> {
         println!("{:22}: {}", "mullvad-daemon version", daemon_version);
     };
 
+    let daemon_api = rpc
+        .handshake()
+        .await
+        .context("Failed to negotiate the management interface version")?;
+    if daemon_api.is_legacy() {
+        println!("{:22}: unknown", "Daemon API version");
+    } else {
+        println!("{:22}: {}", "Daemon API version", daemon_api.api_version);
+        println!(
+            "{:22}: {}",
+            "Daemon capabilities",
+            daemon_api.capabilities.iter().join(", ")
+        );
+        if daemon_api.observe_only {
+            println!("{:22}: observe only", "Access");
+        }
+    }
+
     let version_info = rpc
         .get_version_info()
         .await
@@ -0,0 +1,86 @@
+//! Exit codes returned by the CLI.
+//!
+//! These are part of the CLI's interface and must not change meaning between releases, so that
+//! scripts can tell failure modes apart without parsing the error output.
+
+use mullvad_management_interface::Code;
+use talpid_types::tunnel::ErrorStateCause;
+
+/// Listing of the exit codes, shown in the long help text.
+pub const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0  Success
+  1  Unspecified error
+  2  Invalid arguments
+  3  The daemon could not be reached
+  4  The tunnel ended up in a blocked state
+  5  The command timed out";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ExitCode {
+    Success = 0,
+    /// Any error not covered by a more specific code.
+    Error = 1,
+    /// The arguments were rejected by the CLI or the daemon. This is the same code that clap
+    /// uses for usage errors.
+    InvalidArguments = 2,
+    /// Failed to connect to the daemon, or the connection was lost.
+    DaemonUnreachable = 3,
+    /// The tunnel entered the error state while waiting for it to connect.
+    Blocked = 4,
+    /// The time limit set by `--timeout` was reached.
+    Timeout = 5,
+}
+
+impl From<ExitCode> for std::process::ExitCode {
+    fn from(code: ExitCode) -> Self {
+        std::process::ExitCode::from(code as u8)
+    }
+}
+
+/// The tunnel is blocking all traffic instead of reaching the requested state.
+#[derive(thiserror::Error, Debug)]
+#[error("The tunnel is blocking traffic: {0}")]
+pub struct BlockedError(pub ErrorStateCause);
+
+/// The command did not complete within the time limit set by `--timeout`.
+#[derive(thiserror::Error, Debug)]
+#[error("Timed out after {0} seconds")]
+pub struct TimeoutError(pub u64);
+
+/// Return the exit code that best describes `error`.
+pub fn from_error(error: &anyhow::Error) -> ExitCode {
+    for cause in error.chain() {
+        if cause.is::<BlockedError>() {
+            return ExitCode::Blocked;
+        }
+        if cause.is::<TimeoutError>() {
+            return ExitCode::Timeout;
+        }
+        if let Some(error) = cause.downcast_ref::<mullvad_management_interface::Error>() {
+            match error {
+                mullvad_management_interface::Error::GrpcTransportError(_) => {
+                    return ExitCode::DaemonUnreachable
+                }
+                mullvad_management_interface::Error::Rpc(status) => {
+                    return from_status_code(status.code())
+                }
+                _ => (),
+            }
+        }
+        if let Some(status) = cause.downcast_ref::<mullvad_management_interface::Status>() {
+            return from_status_code(status.code());
+        }
+    }
+    ExitCode::Error
+}
+
+fn from_status_code(code: Code) -> ExitCode {
+    match code {
+        Code::InvalidArgument | Code::OutOfRange => ExitCode::InvalidArguments,
+        Code::Unavailable => ExitCode::DaemonUnreachable,
+        Code::DeadlineExceeded => ExitCode::Timeout,
+        _ => ExitCode::Error,
+    }
+}
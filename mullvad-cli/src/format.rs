@@ -1,15 +1,32 @@
 use std::collections::HashMap;
 
+use anyhow::{Context, Result};
 use itertools::Itertools;
 use mullvad_types::{
     auth_failed::AuthFailed, features::FeatureIndicators, location::GeoIpLocation,
     states::TunnelState,
 };
+use serde::Serialize;
 use talpid_types::{
     net::{Endpoint, TunnelEndpoint},
     tunnel::{ActionAfterDisconnect, ErrorState},
 };
 
+/// How commands that display information print it. JSON is selected with the global `--json`
+/// flag, and is only supported by some commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    Human,
+    Json,
+}
+
+/// Print `value` as JSON on a single line.
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    let json = serde_json::to_string(value).context("Failed to format output as JSON")?;
+    println!("{json}");
+    Ok(())
+}
+
 #[macro_export]
 macro_rules! print_option {
     ($value:expr_2021 $(,)?) => {{
@@ -141,6 +158,10 @@ pub fn print_state(state: &TunnelState, previous_state: Option<&TunnelState>, ve
         }
         Disconnecting(ActionAfterDisconnect::Reconnect) => {}
         Disconnecting(_) => println!("Disconnecting"),
+        Paused => {
+            println!("Paused");
+            print_option!("Internet access is blocked until you connect or disconnect");
+        }
         Error(e) => print_error_state(e),
     }
 }
@@ -159,6 +180,16 @@ fn connection_information(
         .filter(|_| verbose)
         .and_then(|endpoint| endpoint.tunnel_interface.clone());
     info.insert("Tunnel interface", tunnel_interface_fmt);
+    let wireguard_backend_fmt = endpoint
+        .filter(|_| verbose)
+        .and_then(|endpoint| endpoint.wireguard_backend)
+        .map(|backend| backend.to_string());
+    info.insert("WireGuard backend", wireguard_backend_fmt);
+    let worker_threads_fmt = endpoint
+        .filter(|_| verbose)
+        .and_then(|endpoint| endpoint.wireguard_worker_threads)
+        .map(|threads| threads.to_string());
+    info.insert("WireGuard worker threads", worker_threads_fmt);
 
     let bridge_type_fmt = endpoint
         .filter(|_| verbose)
@@ -224,7 +255,7 @@ pub fn format_location(location: &GeoIpLocation) -> String {
     formatted_location
 }
 
-fn format_relay_connection(
+pub fn format_relay_connection(
     endpoint: &TunnelEndpoint,
     location: Option<&GeoIpLocation>,
     verbose: bool,
@@ -306,6 +337,10 @@ fn print_error_state(error_state: &ErrorState) {
             println!("launchctl unload -w /Library/LaunchDaemons/net.mullvad.daemon.plist");
             println!("launchctl load -w /Library/LaunchDaemons/net.mullvad.daemon.plist");
         }
+        cause @ talpid_types::tunnel::ErrorStateCause::CaptivePortal { .. } => {
+            println!("Blocked: {cause}");
+            println!("Run 'mullvad captive-portal allow' and log in to the network in a browser");
+        }
         talpid_types::tunnel::ErrorStateCause::AuthFailed(Some(auth_failed)) => {
             println!(
                 "Blocked: Authentication with remote server failed: {}",
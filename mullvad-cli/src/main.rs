@@ -1,7 +1,11 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use exit_code::{ExitCode, TimeoutError, EXIT_CODES_HELP};
+use format::OutputFormat;
+use std::time::Duration;
 
 mod cmds;
+mod exit_code;
 mod format;
 use cmds::*;
 
@@ -9,7 +13,23 @@ pub const BIN_NAME: &str = env!("CARGO_BIN_NAME");
 
 #[derive(Debug, Parser)]
 #[command(author, version = mullvad_version::VERSION, about, long_about = None)]
-#[command(propagate_version = true)]
+#[command(propagate_version = true, after_long_help = EXIT_CODES_HELP)]
+struct Args {
+    #[clap(subcommand)]
+    cmd: Cli,
+
+    /// Give up if the command has not completed within this many seconds. This includes time
+    /// spent waiting for a tunnel state
+    #[arg(long, global = true, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    /// Print the output as JSON. Supported by 'status', 'account get', 'relay list' and
+    /// 'settings get'
+    #[arg(long, short = 'j', global = true)]
+    json: bool,
+}
+
+#[derive(Debug, Subcommand)]
 enum Cli {
     /// Control and display information about your Mullvad account
     #[clap(subcommand)]
@@ -27,6 +47,42 @@ enum Cli {
     #[clap(subcommand)]
     LockdownMode(lockdown::LockdownMode),
 
+    /// Log in to networks that require it before allowing other traffic
+    #[clap(subcommand)]
+    CaptivePortal(captive_portal::CaptivePortal),
+
+    /// Block all network access, whether or not the VPN should be connected
+    #[clap(subcommand)]
+    Block(block::Block),
+
+    /// Allow traffic to specific hosts or networks outside the tunnel, in every tunnel state
+    #[clap(subcommand)]
+    FirewallExceptions(firewall_exception::FirewallExceptions),
+
+    /// Allow incoming connections to specific ports, in every tunnel state
+    #[clap(subcommand)]
+    AllowIncoming(allowed_incoming::AllowIncoming),
+
+    /// Route traffic to specific networks through or around the tunnel
+    #[clap(subcommand)]
+    Routes(custom_route::Routes),
+
+    /// Connect or disconnect automatically depending on the Wi-Fi or wired network that the
+    /// device joins
+    #[clap(subcommand)]
+    TrustedNetworks(trusted_networks::TrustedNetworks),
+
+    /// Run executables when the tunnel connects, disconnects or fails
+    #[clap(subcommand)]
+    Hooks(state_hooks::StateHooks),
+
+    /// Serve metrics in the Prometheus format on localhost
+    #[clap(subcommand)]
+    Metrics(metrics::Metrics),
+
+    /// Show how much data has gone through the tunnel each month
+    Usage,
+
     /// Debug commands used for internal testing of the app.
     ///
     /// These commands will likely set the app in an invalid state, which is
@@ -38,6 +94,11 @@ enum Cli {
     #[clap(subcommand)]
     Dns(dns::Dns),
 
+    /// Diagnose and repair the kernel drivers used by the app
+    #[cfg(target_os = "windows")]
+    #[clap(subcommand)]
+    Drivers(drivers::Drivers),
+
     /// Control the allow local network sharing setting
     #[clap(subcommand)]
     Lan(lan::Lan),
@@ -63,6 +124,19 @@ enum Cli {
         wait: bool,
     },
 
+    /// Tear down the VPN tunnel but keep blocking all traffic until connecting or disconnecting
+    Pause {
+        /// Wait until paused before exiting
+        #[arg(long, short = 'w')]
+        wait: bool,
+    },
+
+    /// Wait until the tunnel is in the given state, without changing it. Exits with code 4 if
+    /// the tunnel is blocked while waiting to be connected or paused. Use '--timeout' to give up
+    /// after some time
+    #[clap(arg_required_else_help = true)]
+    Wait { state: tunnel_state::TargetState },
+
     /// Manage use of bridges, socks proxies and Shadowsocks for OpenVPN.
     /// Can make OpenVPN tunnels use Shadowsocks via one of the Mullvad bridge servers.
     /// Can also make OpenVPN connect through any custom SOCKS5 proxy.
@@ -112,20 +186,33 @@ enum Cli {
     #[clap(subcommand)]
     Tunnel(tunnel::Tunnel),
 
+    /// Show a dashboard with the tunnel state that is updated live, and keys for changing it
+    #[cfg(all(unix, not(target_os = "android")))]
+    Tui,
+
     /// Show information about the current Mullvad version
     /// and available versions
     Version,
 
-    /// Generate completion scripts for the specified shell
+    /// Generate completion scripts for the specified shell. Locations are completed using the
+    /// relay list of the daemon in bash, zsh and fish
     #[cfg(all(unix, not(target_os = "android")))]
-    #[command(hide = true)]
     ShellCompletions {
         /// The shell to generate the script for
         shell: clap_complete::Shell,
 
-        /// Output directory where the shell completions are written
-        #[arg(default_value = "./")]
-        dir: std::path::PathBuf,
+        /// Output directory where the shell completions are written. The script is printed if
+        /// this is omitted
+        dir: Option<std::path::PathBuf>,
+    },
+
+    /// List the candidates for the next location argument, given the preceding arguments. Used
+    /// by the shell completion scripts
+    #[cfg(all(unix, not(target_os = "android")))]
+    #[command(name = completions::COMPLETE_LOCATION, hide = true)]
+    CompleteLocation {
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        words: Vec<String>,
     },
 
     /// Reset settings, caches, and logs
@@ -135,6 +222,22 @@ enum Cli {
     #[clap(subcommand)]
     CustomList(custom_list::CustomList),
 
+    /// Manage WireGuard relays that you run yourself
+    #[clap(subcommand)]
+    CustomRelay(custom_relay::CustomRelay),
+
+    /// Manage named profiles of relay, obfuscation, DNS and split tunneling settings
+    #[clap(subcommand)]
+    Profile(profile::Profile),
+
+    /// Export or import all settings
+    #[clap(subcommand)]
+    Settings(settings::Settings),
+
+    /// Import configurations from other applications
+    #[clap(subcommand)]
+    Import(import::Import),
+
     /// Apply a JSON patch generated by 'export-settings'
     #[clap(arg_required_else_help = true)]
     ImportSettings {
@@ -150,49 +253,102 @@ enum Cli {
     },
 }
 
+impl Cli {
+    /// Returns whether the command can print its output as JSON.
+    fn supports_json(&self) -> bool {
+        matches!(
+            self,
+            Cli::Status { .. }
+                | Cli::Account(account::Account::Get { .. })
+                | Cli::Relay(relay::Relay::List)
+                | Cli::Settings(settings::Settings::Get)
+        )
+    }
+}
+
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> std::process::ExitCode {
     // Handle SIGPIPE
     // https://stackoverflow.com/questions/65755853/simple-word-count-rust-program-outputs-valid-stdout-but-panicks-when-piped-to-he/65760807
     // https://github.com/typst/typst/pull/5444
     #[cfg(unix)]
     handle_sigpipe().unwrap();
 
-    match Cli::parse() {
-        Cli::Account(cmd) => cmd.handle().await,
+    let args = Args::parse();
+    let output = if args.json {
+        OutputFormat::Json
+    } else {
+        OutputFormat::Human
+    };
+
+    let result = match args.timeout {
+        Some(timeout) => tokio::time::timeout(Duration::from_secs(timeout), run(args.cmd, output))
+            .await
+            .unwrap_or_else(|_elapsed| Err(TimeoutError(timeout).into())),
+        None => run(args.cmd, output).await,
+    };
+
+    match result {
+        Ok(()) => ExitCode::Success.into(),
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+            exit_code::from_error(&error).into()
+        }
+    }
+}
+
+async fn run(cmd: Cli, output: OutputFormat) -> Result<()> {
+    if output == OutputFormat::Json && !cmd.supports_json() {
+        anyhow::bail!("This command does not support '--json'");
+    }
+
+    match cmd {
+        Cli::Account(cmd) => cmd.handle(output).await,
         Cli::Bridge(cmd) => cmd.handle().await,
         Cli::Connect { wait } => tunnel_state::connect(wait).await,
         Cli::Reconnect { wait } => tunnel_state::reconnect(wait).await,
+        Cli::Pause { wait } => tunnel_state::pause(wait).await,
         Cli::Debug(cmd) => cmd.handle().await,
         Cli::Disconnect { wait } => tunnel_state::disconnect(wait).await,
+        Cli::Wait { state } => tunnel_state::wait(state).await,
         Cli::AutoConnect(cmd) => cmd.handle().await,
         Cli::BetaProgram(cmd) => cmd.handle().await,
         Cli::LockdownMode(cmd) => cmd.handle().await,
+        Cli::CaptivePortal(cmd) => cmd.handle().await,
+        Cli::Block(cmd) => cmd.handle().await,
+        Cli::FirewallExceptions(cmd) => cmd.handle().await,
+        Cli::AllowIncoming(cmd) => cmd.handle().await,
+        Cli::Routes(cmd) => cmd.handle().await,
+        Cli::TrustedNetworks(cmd) => cmd.handle().await,
+        Cli::Hooks(cmd) => cmd.handle().await,
+        Cli::Metrics(cmd) => cmd.handle().await,
+        Cli::Usage => usage::print().await,
         Cli::Dns(cmd) => cmd.handle().await,
+        #[cfg(target_os = "windows")]
+        Cli::Drivers(cmd) => cmd.handle().await,
         Cli::Lan(cmd) => cmd.handle().await,
         Cli::Obfuscation(cmd) => cmd.handle().await,
         Cli::ApiAccess(cmd) => cmd.handle().await,
+        #[cfg(all(unix, not(target_os = "android")))]
+        Cli::Tui => tui::run().await,
         Cli::Version => version::print().await,
         Cli::FactoryReset => reset::handle().await,
-        Cli::Relay(cmd) => cmd.handle().await,
+        Cli::Relay(cmd) => cmd.handle(output).await,
         Cli::Tunnel(cmd) => cmd.handle().await,
         Cli::SplitTunnel(cmd) => cmd.handle().await,
-        Cli::Status { cmd, args } => status::handle(cmd, args).await,
+        Cli::Status { cmd, args } => status::handle(cmd, args, output).await,
         Cli::CustomList(cmd) => cmd.handle().await,
+        Cli::CustomRelay(cmd) => cmd.handle().await,
+        Cli::Profile(cmd) => cmd.handle().await,
+        Cli::Settings(cmd) => cmd.handle(output).await,
+        Cli::Import(cmd) => cmd.handle().await,
         Cli::ImportSettings { file } => patch::import(file).await,
         Cli::ExportSettings { file } => patch::export(file).await,
 
         #[cfg(all(unix, not(target_os = "android")))]
-        Cli::ShellCompletions { shell, dir } => {
-            use anyhow::Context;
-            use clap::CommandFactory;
-
-            // FIXME: The shell completions include hidden commands (including "shell-completions")
-            println!("Generating shell completions to {}", dir.display());
-            clap_complete::generate_to(shell, &mut Cli::command(), BIN_NAME, dir)
-                .context("Failed to generate shell completions")?;
-            Ok(())
-        }
+        Cli::ShellCompletions { shell, dir } => completions::generate(shell, dir),
+        #[cfg(all(unix, not(target_os = "android")))]
+        Cli::CompleteLocation { words } => completions::complete_location(words).await,
     }
 }
 
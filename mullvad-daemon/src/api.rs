@@ -9,23 +9,29 @@ use mullvad_api::AddressCache;
 use mullvad_api::{
     access_mode::AccessMethodResolver,
     availability::ApiAvailability,
+    http_proxy::HttpProxyRuntimeProvider,
     proxy::{ApiConnectionMode, ProxyConfig},
 };
 use mullvad_encrypted_dns_proxy::state::EncryptedDnsProxyState;
 use mullvad_management_interface::async_trait;
 use mullvad_relay_selector::RelaySelector;
-use mullvad_types::access_method::{AccessMethod, BuiltInAccessMethod};
+use mullvad_types::access_method::{AccessMethod, BuiltInAccessMethod, Settings};
 #[cfg(target_os = "android")]
 use talpid_core::mpsc::Sender;
 use talpid_types::net::AllowedEndpoint;
 use talpid_types::net::Endpoint;
-use talpid_types::net::TransportProtocol;
-use talpid_types::net::{proxy::CustomProxy, AllowedClients, Connectivity};
+use talpid_types::net::{
+    proxy::{CustomProxy, HttpProxy},
+    AllowedClients, Connectivity,
+};
 
 pub struct DaemonAccessMethodResolver {
     relay_selector: RelaySelector,
     encrypted_dns_proxy_cache: EncryptedDnsProxyState,
     address_cache: AddressCache,
+    /// Enabled HTTP proxy, which is used to fetch Encrypted DNS proxy configs. On networks that
+    /// require such a proxy, the DoH resolvers are not reachable otherwise.
+    http_proxy: Option<HttpProxy>,
 }
 
 impl DaemonAccessMethodResolver {
@@ -38,6 +44,7 @@ impl DaemonAccessMethodResolver {
             relay_selector,
             encrypted_dns_proxy_cache,
             address_cache,
+            http_proxy: None,
         }
     }
 }
@@ -51,6 +58,14 @@ impl AccessMethodResolver for DaemonAccessMethodResolver {
         let connection_mode = {
             match access_method {
                 AccessMethod::BuiltIn(BuiltInAccessMethod::Direct) => ApiConnectionMode::Direct,
+                AccessMethod::BuiltIn(BuiltInAccessMethod::DirectHttp3) => {
+                    ApiConnectionMode::DirectHttp3
+                }
+                AccessMethod::BuiltIn(BuiltInAccessMethod::Tor { socks_port }) => {
+                    ApiConnectionMode::Proxied(ProxyConfig::Tor {
+                        socks_port: *socks_port,
+                    })
+                }
                 AccessMethod::BuiltIn(BuiltInAccessMethod::Bridge) => {
                     let Some(bridge) = self.relay_selector.get_bridge_forced() else {
                         log::warn!("Could not select a Mullvad bridge");
@@ -61,11 +76,20 @@ impl AccessMethodResolver for DaemonAccessMethodResolver {
                     ApiConnectionMode::Proxied(ProxyConfig::from(proxy))
                 }
                 AccessMethod::BuiltIn(BuiltInAccessMethod::EncryptedDnsProxy) => {
-                    if let Err(error) = self
-                        .encrypted_dns_proxy_cache
-                        .fetch_configs("frakta.eu")
-                        .await
-                    {
+                    let fetch_result = match &self.http_proxy {
+                        Some(proxy) => {
+                            let runtime = HttpProxyRuntimeProvider::new(proxy.clone());
+                            self.encrypted_dns_proxy_cache
+                                .fetch_configs_with_runtime("frakta.eu", runtime)
+                                .await
+                        }
+                        None => {
+                            self.encrypted_dns_proxy_cache
+                                .fetch_configs("frakta.eu")
+                                .await
+                        }
+                    };
+                    if let Err(error) = fetch_result {
                         log::warn!("Failed to fetch new Encrypted DNS Proxy configurations");
                         log::debug!("{error:#?}");
                     }
@@ -92,6 +116,16 @@ impl AccessMethodResolver for DaemonAccessMethodResolver {
             self.address_cache.get_address().await,
         )
     }
+
+    async fn update_access_methods(&mut self, access_methods: &Settings) {
+        self.http_proxy = access_methods
+            .iter_custom()
+            .filter(|setting| setting.enabled())
+            .find_map(|setting| match setting.as_custom() {
+                Some(CustomProxy::Http(proxy)) => Some(proxy.clone()),
+                _ => None,
+            });
+    }
 }
 
 pub fn resolve_allowed_endpoint(
@@ -100,7 +134,7 @@ pub fn resolve_allowed_endpoint(
 ) -> AllowedEndpoint {
     let endpoint = match connection_mode.get_endpoint() {
         Some(endpoint) => endpoint,
-        None => Endpoint::from_socket_address(fallback, TransportProtocol::Tcp),
+        None => Endpoint::from_socket_address(fallback, connection_mode.transport_protocol()),
     };
     let clients = allowed_clients(connection_mode);
     AllowedEndpoint { endpoint, clients }
@@ -109,16 +143,25 @@ pub fn resolve_allowed_endpoint(
 #[cfg(unix)]
 pub fn allowed_clients(connection_mode: &ApiConnectionMode) -> AllowedClients {
     match connection_mode {
-        ApiConnectionMode::Proxied(ProxyConfig::Socks5Local(_)) => AllowedClients::All,
-        ApiConnectionMode::Direct | ApiConnectionMode::Proxied(_) => AllowedClients::Root,
+        // Traffic to the proxy comes from the proxy client, which is not the daemon
+        ApiConnectionMode::Proxied(ProxyConfig::Socks5Local(_) | ProxyConfig::Tor { .. }) => {
+            AllowedClients::All
+        }
+        ApiConnectionMode::Direct
+        | ApiConnectionMode::DirectHttp3
+        | ApiConnectionMode::Proxied(_) => AllowedClients::Root,
     }
 }
 
 #[cfg(windows)]
 pub fn allowed_clients(connection_mode: &ApiConnectionMode) -> AllowedClients {
     match connection_mode {
-        ApiConnectionMode::Proxied(ProxyConfig::Socks5Local(_)) => AllowedClients::all(),
-        ApiConnectionMode::Direct | ApiConnectionMode::Proxied(_) => {
+        ApiConnectionMode::Proxied(ProxyConfig::Socks5Local(_) | ProxyConfig::Tor { .. }) => {
+            AllowedClients::all()
+        }
+        ApiConnectionMode::Direct
+        | ApiConnectionMode::DirectHttp3
+        | ApiConnectionMode::Proxied(_) => {
             let daemon_exe = std::env::current_exe().expect("failed to obtain executable path");
             vec![
                 daemon_exe
@@ -1,5 +1,6 @@
 //! A small updater that keeps the API IP address cache up to date by fetching changes from the
-//! Mullvad API.
+//! Mullvad API. Since the addresses are fetched over the authenticated API connection, a stale
+//! cache is refreshed right away instead of being trusted until the first scheduled check.
 #[cfg(feature = "api-override")]
 use mullvad_api::ApiEndpoint;
 use mullvad_api::{rest::MullvadRestHandle, AddressCache, ApiProxy};
@@ -21,7 +22,12 @@ pub async fn run_api_address_fetcher(
 
     let availability = handle.availability.clone();
     let api_proxy = ApiProxy::new(handle);
-    let mut next_delay = API_IP_CHECK_INITIAL;
+    let mut next_delay = if address_cache.is_stale().await {
+        log::debug!("Cached API addresses are stale. Fetching new addresses");
+        Duration::ZERO
+    } else {
+        API_IP_CHECK_INITIAL
+    };
 
     loop {
         talpid_time::sleep(next_delay).await;
@@ -32,17 +38,17 @@ pub async fn run_api_address_fetcher(
         }
         match api_proxy.clone().get_api_addrs().await {
             Ok(new_addrs) => {
-                if let Some(addr) = new_addrs.first() {
+                if new_addrs.is_empty() {
+                    log::error!("API returned no API addresses");
+                } else {
                     log::debug!(
-                        "Fetched new API address {:?}. Fetching again in {} hours",
-                        addr,
+                        "Fetched new API addresses {:?}. Fetching again in {} hours",
+                        new_addrs,
                         API_IP_CHECK_INTERVAL.as_secs() / (60 * 60)
                     );
-                    if let Err(err) = address_cache.set_address(*addr).await {
-                        log::error!("Failed to save newly updated API address: {}", err);
+                    if let Err(err) = address_cache.set_addresses(new_addrs).await {
+                        log::error!("Failed to save newly updated API addresses: {}", err);
                     }
-                } else {
-                    log::error!("API returned no API addresses");
                 }
 
                 next_delay = API_IP_CHECK_INTERVAL;
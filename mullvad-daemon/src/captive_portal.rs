@@ -0,0 +1,341 @@
+//! Detection of captive portals, which keep the tunnel from coming up until the user has logged in
+//! on a web page.
+//!
+//! While the tunnel is connecting, the API address is probed over plain HTTP outside the tunnel.
+//! If the probe is redirected to another host, traffic is blocked with
+//! [`ErrorStateCause::CaptivePortal`] until a probe is no longer redirected. The user may allow
+//! traffic to the login page for a while, so that they can log in. Nothing is ever saved.
+
+use crate::{Daemon, DaemonEventSender, InternalDaemonEvent};
+use mullvad_types::states::{TargetState, TunnelState};
+use std::{
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+use talpid_core::{
+    mpsc::Sender,
+    offline::captive_portal::{self, CaptivePortal},
+    tunnel_state_machine::TunnelCommand,
+};
+use talpid_types::{
+    net::{FirewallException, TransportProtocol},
+    tunnel::{ActionAfterDisconnect, ErrorStateCause},
+};
+use tokio::{task::AbortHandle, time::Instant};
+
+/// How long the tunnel must have been connecting before the network is probed.
+const PROBE_DELAY: Duration = Duration::from_secs(10);
+
+/// Time between probes while the tunnel is connecting, or blocked by a portal.
+const PROBE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How long to wait for the address of the login page to be resolved.
+const RESOLVE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest number of addresses of the login page that traffic is allowed to.
+const MAX_LOGIN_ADDRESSES: usize = 4;
+
+/// Traffic to the login page may not be allowed for longer than this.
+pub const MAX_LOGIN_DURATION: Duration = Duration::from_secs(30 * 60);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("No captive portal has been detected")]
+    NotDetected,
+
+    #[error("The address of the login page could not be resolved")]
+    UnknownLoginAddress,
+
+    #[error("Traffic to the login page may be allowed for at most 30 minutes")]
+    TooLong,
+}
+
+/// A portal that a probe was redirected to.
+#[derive(Debug, Clone)]
+pub struct DetectedPortal {
+    portal: CaptivePortal,
+    /// Addresses of the login page. Empty if they could not be resolved.
+    login_addresses: Vec<IpAddr>,
+}
+
+/// A timer that is cancelled when it is dropped.
+struct Timer(AbortHandle);
+
+impl Timer {
+    fn spawn(until: Instant, tx: DaemonEventSender, event: InternalDaemonEvent) -> Self {
+        let timer = tokio::spawn(async move {
+            tokio::time::sleep_until(until).await;
+            let _ = tx.send(event);
+        });
+        Timer(timer.abort_handle())
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+/// Traffic to the login page is allowed until `until`.
+struct Login {
+    until: Instant,
+    _timer: Timer,
+}
+
+pub struct CaptivePortalMonitor {
+    /// Firewall mark that lets probes bypass the tunnel.
+    #[cfg(target_os = "linux")]
+    fwmark: u32,
+    /// Set while a probe is scheduled.
+    next_probe: Option<Timer>,
+    /// Address that a probe is being sent to. Plain HTTP to it is allowed while probing.
+    probing: Option<IpAddr>,
+    /// The portal that the last probe was redirected to.
+    detected: Option<DetectedPortal>,
+    login: Option<Login>,
+}
+
+impl CaptivePortalMonitor {
+    pub fn new(#[cfg(target_os = "linux")] fwmark: u32) -> Self {
+        CaptivePortalMonitor {
+            #[cfg(target_os = "linux")]
+            fwmark,
+            next_probe: None,
+            probing: None,
+            detected: None,
+            login: None,
+        }
+    }
+
+    /// Traffic that must be allowed by the firewall, in addition to the user's exceptions.
+    pub fn firewall_exceptions(&self) -> impl Iterator<Item = FirewallException> + '_ {
+        let probe = self.probing.map(|address| FirewallException {
+            network: address.into(),
+            protocol: Some(TransportProtocol::Tcp),
+            port: Some(captive_portal::PROBE_PORT),
+        });
+        let login = self
+            .login
+            .iter()
+            .zip(self.detected.iter())
+            .flat_map(|(_, detected)| &detected.login_addresses)
+            .map(|address| FirewallException {
+                network: (*address).into(),
+                protocol: Some(TransportProtocol::Tcp),
+                port: None,
+            });
+        probe.into_iter().chain(login)
+    }
+}
+
+impl Daemon {
+    /// Probes are only sent while trying to connect, or while blocked by a portal.
+    fn should_probe_for_captive_portal(&self) -> bool {
+        match &self.tunnel_state {
+            TunnelState::Connecting { .. } => true,
+            TunnelState::Disconnecting(
+                ActionAfterDisconnect::Block | ActionAfterDisconnect::Reconnect,
+            ) => true,
+            TunnelState::Error(error_state) => {
+                matches!(error_state.cause(), ErrorStateCause::CaptivePortal { .. })
+            }
+            _ => false,
+        }
+    }
+
+    /// Start or stop probing for captive portals after a tunnel state transition.
+    pub(crate) fn update_captive_portal_monitor(&mut self) {
+        if !self.should_probe_for_captive_portal() {
+            self.captive_portal.next_probe = None;
+            if self.captive_portal.detected.take().is_some() {
+                self.captive_portal.login = None;
+                let _ = self.update_firewall_exceptions();
+            }
+            return;
+        }
+
+        if self.captive_portal.next_probe.is_none() && self.captive_portal.probing.is_none() {
+            self.schedule_captive_portal_probe(PROBE_DELAY);
+        }
+    }
+
+    fn schedule_captive_portal_probe(&mut self, delay: Duration) {
+        self.captive_portal.next_probe = Some(Timer::spawn(
+            Instant::now() + delay,
+            self.tx.clone(),
+            InternalDaemonEvent::CaptivePortalProbe,
+        ));
+    }
+
+    /// Allow plain HTTP to the API address, and send a probe to it.
+    pub(crate) async fn on_captive_portal_probe(&mut self) {
+        self.captive_portal.next_probe = None;
+        if !self.should_probe_for_captive_portal() {
+            return;
+        }
+
+        let address = self.api_runtime.address_cache().get_address().await.ip();
+        let host = self.api_runtime.endpoint().host().to_owned();
+        #[cfg(target_os = "linux")]
+        let fwmark = self.captive_portal.fwmark;
+
+        self.captive_portal.probing = Some(address);
+        let firewall_updated = self.update_firewall_exceptions();
+
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let _ = firewall_updated.await;
+            let result = captive_portal::probe(
+                address,
+                &host,
+                #[cfg(target_os = "linux")]
+                fwmark,
+            )
+            .await;
+            let result = match result {
+                Ok(Some(portal)) => {
+                    let login_addresses = resolve_login_addresses(&portal).await;
+                    Ok(Some(DetectedPortal {
+                        portal,
+                        login_addresses,
+                    }))
+                }
+                Ok(None) => Ok(None),
+                Err(error) => Err(error),
+            };
+            let _ = tx.send(InternalDaemonEvent::CaptivePortalProbeResult(result));
+        });
+    }
+
+    pub(crate) fn on_captive_portal_probe_result(
+        &mut self,
+        result: Result<Option<DetectedPortal>, captive_portal::Error>,
+    ) {
+        self.captive_portal.probing = None;
+        let _ = self.update_firewall_exceptions();
+
+        // The tunnel may have connected or been disconnected in the meantime
+        if !self.should_probe_for_captive_portal() {
+            return;
+        }
+
+        match result {
+            Ok(Some(detected)) => self.on_captive_portal_detected(detected),
+            Ok(None) => {
+                if self.captive_portal.detected.take().is_some() {
+                    log::info!("The captive portal is gone. Reconnecting");
+                    self.captive_portal.login = None;
+                    let _ = self.update_firewall_exceptions();
+                    if *self.target_state == TargetState::Secured {
+                        self.connect_tunnel();
+                    }
+                }
+            }
+            Err(error) => log::debug!("Failed to probe for captive portals: {error}"),
+        }
+
+        self.schedule_captive_portal_probe(PROBE_INTERVAL);
+    }
+
+    fn on_captive_portal_detected(&mut self, detected: DetectedPortal) {
+        let login_url = detected.portal.login_url.clone();
+        let is_new = self
+            .captive_portal
+            .detected
+            .as_ref()
+            .is_none_or(|current| current.portal != detected.portal);
+        if is_new {
+            log::info!("Detected a captive portal with the login page {login_url}");
+        }
+        self.captive_portal.detected = Some(detected);
+
+        // The user may also have asked to connect again while blocked
+        let is_blocked = matches!(
+            &self.tunnel_state,
+            TunnelState::Error(error_state)
+                if matches!(
+                    error_state.cause(),
+                    ErrorStateCause::CaptivePortal { login_url: blocked_url }
+                        if *blocked_url == login_url
+                )
+        );
+        if !is_blocked {
+            self.send_tunnel_command(TunnelCommand::Block(ErrorStateCause::CaptivePortal {
+                login_url,
+            }));
+        }
+    }
+
+    /// Allow traffic to the login page of the detected captive portal for `duration`. An ongoing
+    /// login is replaced.
+    pub(crate) fn allow_captive_portal_login(&mut self, duration: Duration) -> Result<(), Error> {
+        if duration > MAX_LOGIN_DURATION {
+            return Err(Error::TooLong);
+        }
+        let detected = self
+            .captive_portal
+            .detected
+            .as_ref()
+            .ok_or(Error::NotDetected)?;
+        if detected.login_addresses.is_empty() {
+            return Err(Error::UnknownLoginAddress);
+        }
+
+        log::info!(
+            "Allowing traffic to {} for {} seconds",
+            detected.portal.login_url,
+            duration.as_secs()
+        );
+        let until = Instant::now() + duration;
+        self.captive_portal.login = Some(Login {
+            until,
+            _timer: Timer::spawn(
+                until,
+                self.tx.clone(),
+                InternalDaemonEvent::CaptivePortalLoginExpired,
+            ),
+        });
+        let _ = self.update_firewall_exceptions();
+        Ok(())
+    }
+
+    pub(crate) fn on_captive_portal_login_expired(&mut self) {
+        // The login may have been replaced after the timer fired
+        let expired = self
+            .captive_portal
+            .login
+            .as_ref()
+            .is_some_and(|login| login.until <= Instant::now());
+        if expired {
+            log::info!("No longer allowing traffic to the captive portal");
+            self.captive_portal.login = None;
+            let _ = self.update_firewall_exceptions();
+        }
+    }
+}
+
+/// Resolve the host of the login page. This fails unless the resolver can be reached while
+/// traffic is blocked, in which case only login pages given by address can be allowed.
+async fn resolve_login_addresses(portal: &CaptivePortal) -> Vec<IpAddr> {
+    let Some(host) = portal.login_host() else {
+        return vec![];
+    };
+    if let Ok(address) = host.parse::<IpAddr>() {
+        return vec![address];
+    }
+    match tokio::time::timeout(RESOLVE_TIMEOUT, tokio::net::lookup_host((host, 0))).await {
+        Ok(Ok(addresses)) => addresses
+            .map(|address: SocketAddr| address.ip())
+            .take(MAX_LOGIN_ADDRESSES)
+            .collect(),
+        Ok(Err(error)) => {
+            log::debug!("Failed to resolve {host}: {error}");
+            vec![]
+        }
+        Err(_) => {
+            log::debug!("Timed out resolving {host}");
+            vec![]
+        }
+    }
+}
@@ -0,0 +1,185 @@
+//! Detection of software that is known to interfere with the tunnel or the firewall on macOS.
+//!
+//! Other VPNs and content filters installed as network system extensions may capture traffic
+//! before it reaches the tunnel, and firewalls that manage their own pf anchors may override or
+//! flush our rules.
+
+use mullvad_types::conflicting_software::{ConflictKind, ConflictingSoftware};
+use std::io;
+use talpid_types::ErrorExt;
+use tokio::process::Command;
+
+const SYSTEMEXTENSIONSCTL_PATH: &str = "/usr/bin/systemextensionsctl";
+const PFCTL_PATH: &str = "/sbin/pfctl";
+
+const NETWORK_EXTENSION_CATEGORY: &str = "com.apple.system_extension.network_extension";
+
+/// Team ID used to sign our own binaries.
+const MULLVAD_TEAM_ID: &str = "CKG9MXH72F";
+
+/// pf anchors that are expected to be present.
+const EXPECTED_PF_ANCHORS: &[&str] = &["com.apple", "mullvad"];
+
+/// Known pf frontends, identified by the prefix of the anchor they install.
+const KNOWN_PF_FIREWALLS: &[(&str, &str)] = &[
+    ("murus", "Murus"),
+    ("vallum", "Vallum"),
+    ("icefloor", "IceFloor"),
+    ("com.hanynet.icefloor", "IceFloor"),
+    ("pflist", "PFList"),
+];
+
+/// Return all conflicting software that is currently active.
+pub async fn detect() -> Vec<ConflictingSoftware> {
+    let mut conflicts = vec![];
+
+    match run(SYSTEMEXTENSIONSCTL_PATH, &["list"]).await {
+        Ok(output) => conflicts.extend(parse_network_extensions(&output)),
+        Err(error) => log::debug!(
+            "{}",
+            error.display_chain_with_msg("Failed to list system extensions")
+        ),
+    }
+
+    match run(PFCTL_PATH, &["-s", "Anchors"]).await {
+        Ok(output) => conflicts.extend(parse_pf_anchors(&output)),
+        Err(error) => log::debug!(
+            "{}",
+            error.display_chain_with_msg("Failed to list pf anchors")
+        ),
+    }
+
+    conflicts
+}
+
+async fn run(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = Command::new(program).args(args).output().await?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("{program} exited with status {}", output.status),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parse the output of `systemextensionsctl list` and return all enabled and active network
+/// extensions that do not belong to us.
+///
+/// The output consists of a header per extension category, followed by tab-separated rows:
+/// `enabled  active  teamID  bundleID (version)  name  [state]`.
+fn parse_network_extensions(output: &str) -> Vec<ConflictingSoftware> {
+    let mut category = None;
+    let mut conflicts = vec![];
+
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("--- ") {
+            category = Some(header.trim());
+            continue;
+        }
+        if category != Some(NETWORK_EXTENSION_CATEGORY) {
+            continue;
+        }
+
+        let columns: Vec<_> = line.split('\t').map(str::trim).collect();
+        let [enabled, active, team_id, bundle, name, ..] = columns[..] else {
+            continue;
+        };
+        if enabled != "*" || active != "*" || team_id == MULLVAD_TEAM_ID {
+            continue;
+        }
+        let bundle_id = bundle.split(" (").next().unwrap_or(bundle);
+
+        conflicts.push(ConflictingSoftware {
+            kind: ConflictKind::NetworkExtension,
+            name: name.to_owned(),
+            identifier: bundle_id.to_owned(),
+        });
+    }
+
+    conflicts
+}
+
+/// Parse the output of `pfctl -s Anchors` and return all unexpected top-level anchors.
+fn parse_pf_anchors(output: &str) -> Vec<ConflictingSoftware> {
+    let mut conflicts: Vec<ConflictingSoftware> = vec![];
+
+    for line in output.lines() {
+        let anchor = line.trim().split('/').next().unwrap_or_default();
+        if anchor.is_empty()
+            || EXPECTED_PF_ANCHORS.contains(&anchor)
+            || conflicts.iter().any(|c| c.identifier == anchor)
+        {
+            continue;
+        }
+
+        let name = KNOWN_PF_FIREWALLS
+            .iter()
+            .find(|(prefix, _)| anchor.to_ascii_lowercase().starts_with(prefix))
+            .map(|(_, name)| *name)
+            .unwrap_or("Unknown pf firewall");
+
+        conflicts.push(ConflictingSoftware {
+            kind: ConflictKind::PfFirewall,
+            name: name.to_owned(),
+            identifier: anchor.to_owned(),
+        });
+    }
+
+    conflicts
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_network_extensions() {
+        let output = "3 extension(s)
+--- com.apple.system_extension.network_extension
+enabled\tactive\tteamID\tbundleID (version)\tname\t[state]
+*\t*\tMLZF7K7B5R\tat.obdev.littlesnitch.networkextension (6.0.4/6317)\tLittle Snitch Network Extension\t[activated enabled]
+\t\tVBG97UB4TA\tcom.example.inactive (1.0/1)\tInactive\t[terminated waiting to uninstall on reboot]
+*\t*\tCKG9MXH72F\tnet.mullvad.extension (1.0/1)\tMullvad\t[activated enabled]
+--- com.apple.system_extension.endpoint_security
+enabled\tactive\tteamID\tbundleID (version)\tname\t[state]
+*\t*\tABCDEF1234\tcom.example.endpoint (1.0/1)\tEndpoint\t[activated enabled]
+";
+
+        assert_eq!(
+            parse_network_extensions(output),
+            vec![ConflictingSoftware {
+                kind: ConflictKind::NetworkExtension,
+                name: "Little Snitch Network Extension".to_owned(),
+                identifier: "at.obdev.littlesnitch.networkextension".to_owned(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_parse_pf_anchors() {
+        let output = "  com.apple
+  com.apple/250.ApplicationFirewall
+  mullvad
+  murus.inbound
+  murus.inbound/sub
+  com.example.fw
+";
+
+        assert_eq!(
+            parse_pf_anchors(output),
+            vec![
+                ConflictingSoftware {
+                    kind: ConflictKind::PfFirewall,
+                    name: "Murus".to_owned(),
+                    identifier: "murus.inbound".to_owned(),
+                },
+                ConflictingSoftware {
+                    kind: ConflictKind::PfFirewall,
+                    name: "Unknown pf firewall".to_owned(),
+                    identifier: "com.example.fw".to_owned(),
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{Daemon, Error};
+use mullvad_relay_selector::SelectorConfig;
+use mullvad_types::{
+    constraints::Constraint,
+    custom_relay::{self, CustomRelay},
+    relay_constraints::{GeographicLocationConstraint, LocationConstraint, RelaySettings},
+};
+
+impl Daemon {
+    /// Add a custom relay.
+    ///
+    /// Returns an error if the name is invalid or already used by another relay.
+    pub async fn add_custom_relay(&mut self, relay: CustomRelay) -> Result<(), Error> {
+        let name_taken = self
+            .relay_selector
+            .get_relays()
+            .relays()
+            .any(|existing| existing.hostname == relay.name);
+        if name_taken {
+            return Err(Error::CustomRelayError(custom_relay::Error::DuplicateName));
+        }
+
+        let settings_changed = self
+            .settings
+            .try_update(|settings| settings.custom_relays.add(relay))
+            .await
+            .map_err(Error::SettingsError);
+
+        if let Ok(true) = settings_changed {
+            self.on_custom_relays_changed();
+        }
+
+        settings_changed?;
+        Ok(())
+    }
+
+    /// Remove the custom relay with the given name.
+    ///
+    /// Returns an error if there is no such relay.
+    pub async fn remove_custom_relay(&mut self, name: String) -> Result<(), Error> {
+        let settings_changed = self
+            .settings
+            .try_update(|settings| settings.custom_relays.remove(&name))
+            .await
+            .map_err(Error::SettingsError);
+
+        if let Ok(true) = settings_changed {
+            self.on_custom_relays_changed();
+        }
+
+        settings_changed?;
+        Ok(())
+    }
+
+    fn on_custom_relays_changed(&mut self) {
+        self.relay_selector
+            .set_config(SelectorConfig::from_settings(&self.settings));
+        self.management_interface
+            .notifier()
+            .notify_relay_list(self.relay_selector.get_relay_locations());
+
+        if self.custom_relays_are_selected() {
+            log::info!("Initiating tunnel restart because the selected custom relays changed");
+            self.reconnect_tunnel();
+        }
+    }
+
+    /// Check whether the location or entry location constraint selects custom relays.
+    fn custom_relays_are_selected(&self) -> bool {
+        let RelaySettings::Normal(relay_settings) = &self.settings.relay_settings else {
+            return false;
+        };
+        let is_custom = |location: &Constraint<LocationConstraint>| match location {
+            Constraint::Only(LocationConstraint::Location(
+                GeographicLocationConstraint::Country(country)
+                | GeographicLocationConstraint::City(country, _)
+                | GeographicLocationConstraint::Hostname(country, _, _),
+            )) => country == custom_relay::COUNTRY_CODE,
+            _ => false,
+        };
+
+        is_custom(&relay_settings.location)
+            || (relay_settings.wireguard_constraints.multihop()
+                && is_custom(&relay_settings.wireguard_constraints.entry_location))
+    }
+}
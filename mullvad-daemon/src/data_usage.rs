@@ -0,0 +1,223 @@
+//! Keeps track of how much traffic has gone through the tunnel each calendar month, and persists
+//! it in the cache directory so that it survives restarts.
+//!
+//! The traffic statistics of the tunnel are sampled periodically. Traffic sent after the last
+//! sample of a tunnel is not counted.
+
+use chrono::{Datelike, Local};
+use futures::{
+    channel::{mpsc, oneshot},
+    StreamExt,
+};
+use mullvad_types::data_usage::DataUsage;
+use std::{
+    path::{Path, PathBuf},
+    sync::{Arc, Weak},
+    time::Duration,
+};
+use talpid_core::tunnel_state_machine::TunnelCommand;
+use talpid_types::{tunnel::TrafficStats, ErrorExt};
+use tokio::{fs, io, io::AsyncWriteExt};
+
+const DATA_USAGE_FILE: &str = "data-usage.json";
+/// How often to read the traffic statistics of the tunnel.
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(10);
+/// How often to write the data usage to disk, if it has changed.
+const SAVE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("The data usage tracker is not running")]
+    TrackerDown,
+}
+
+enum Command {
+    Get(oneshot::Sender<DataUsage>),
+    Save(oneshot::Sender<()>),
+}
+
+/// Handle to the data usage tracker. The tracker stops when the tunnel state machine does.
+#[derive(Clone)]
+pub struct DataUsageHandle {
+    tx: mpsc::UnboundedSender<Command>,
+}
+
+impl DataUsageHandle {
+    /// Load the data usage from `cache_dir` and start sampling the tunnel traffic.
+    pub async fn spawn(
+        cache_dir: &Path,
+        tunnel_command_tx: Weak<mpsc::UnboundedSender<TunnelCommand>>,
+    ) -> Self {
+        let cache_path = cache_dir.join(DATA_USAGE_FILE);
+        let tracker = Tracker {
+            usage: load(&cache_path).await,
+            cache_path,
+            previous: None,
+            changed: false,
+        };
+        let (tx, rx) = mpsc::unbounded();
+        tokio::spawn(tracker.run(rx, tunnel_command_tx));
+        Self { tx }
+    }
+
+    /// Returns the data usage for the most recent months.
+    pub async fn get(&self) -> Result<DataUsage, Error> {
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .unbounded_send(Command::Get(tx))
+            .map_err(|_| Error::TrackerDown)?;
+        rx.await.map_err(|_| Error::TrackerDown)
+    }
+
+    /// Write the data usage to disk, if it has changed since it was last saved.
+    pub async fn save(&self) {
+        let (tx, rx) = oneshot::channel();
+        if self.tx.unbounded_send(Command::Save(tx)).is_ok() {
+            let _ = rx.await;
+        }
+    }
+}
+
+struct Tracker {
+    cache_path: PathBuf,
+    usage: DataUsage,
+    /// Traffic statistics of the tunnel at the last sample
+    previous: Option<TrafficStats>,
+    changed: bool,
+}
+
+impl Tracker {
+    async fn run(
+        mut self,
+        mut commands: mpsc::UnboundedReceiver<Command>,
+        tunnel_command_tx: Weak<mpsc::UnboundedSender<TunnelCommand>>,
+    ) {
+        let mut sample_interval = tokio::time::interval(SAMPLE_INTERVAL);
+        let mut save_interval = tokio::time::interval(SAVE_INTERVAL);
+
+        loop {
+            tokio::select! {
+                _ = sample_interval.tick() => {
+                    let Some(tunnel_command_tx) = tunnel_command_tx.upgrade() else {
+                        break;
+                    };
+                    let stats = get_traffic_stats(&tunnel_command_tx).await;
+                    self.record(stats);
+                }
+                _ = save_interval.tick() => self.save().await,
+                command = commands.next() => match command {
+                    Some(Command::Get(tx)) => {
+                        let _ = tx.send(self.usage.clone());
+                    }
+                    Some(Command::Save(tx)) => {
+                        self.save().await;
+                        let _ = tx.send(());
+                    }
+                    None => break,
+                },
+            }
+        }
+        self.save().await;
+    }
+
+    fn record(&mut self, stats: Option<TrafficStats>) {
+        if let Some(stats) = &stats {
+            let (tx_bytes, rx_bytes) = traffic_since(self.previous.as_ref(), stats);
+            if tx_bytes > 0 || rx_bytes > 0 {
+                let now = Local::now();
+                self.usage.add(now.year(), now.month(), tx_bytes, rx_bytes);
+                self.changed = true;
+            }
+        }
+        self.previous = stats;
+    }
+
+    async fn save(&mut self) {
+        if !self.changed {
+            return;
+        }
+        match write(&self.cache_path, &self.usage).await {
+            Ok(()) => self.changed = false,
+            Err(error) => log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to write data usage")
+            ),
+        }
+    }
+}
+
+async fn get_traffic_stats(
+    tunnel_command_tx: &Arc<mpsc::UnboundedSender<TunnelCommand>>,
+) -> Option<TrafficStats> {
+    let (tx, rx) = oneshot::channel();
+    tunnel_command_tx
+        .unbounded_send(TunnelCommand::GetTrafficStats(tx))
+        .ok()?;
+    rx.await.ok().flatten()
+}
+
+/// Returns the bytes sent and received since `previous`. If the counters have decreased, a new
+/// tunnel has been created, and all of its traffic is new.
+fn traffic_since(previous: Option<&TrafficStats>, current: &TrafficStats) -> (u64, u64) {
+    match previous {
+        Some(previous)
+            if current.tx_bytes >= previous.tx_bytes && current.rx_bytes >= previous.rx_bytes =>
+        {
+            (
+                current.tx_bytes - previous.tx_bytes,
+                current.rx_bytes - previous.rx_bytes,
+            )
+        }
+        _ => (current.tx_bytes, current.rx_bytes),
+    }
+}
+
+async fn load(path: &Path) -> DataUsage {
+    match fs::read_to_string(path).await {
+        Ok(content) => serde_json::from_str(&content)
+            .map(DataUsage::new)
+            .unwrap_or_else(|error| {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to parse data usage")
+                );
+                DataUsage::default()
+            }),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => DataUsage::default(),
+        Err(error) => {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to read data usage")
+            );
+            DataUsage::default()
+        }
+    }
+}
+
+async fn write(path: &Path, usage: &DataUsage) -> io::Result<()> {
+    let buffer = serde_json::to_string(usage)?;
+    let mut file = mullvad_fs::AtomicFile::new(path).await?;
+    file.write_all(buffer.as_bytes()).await?;
+    file.finalize().await
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn stats(tx_bytes: u64, rx_bytes: u64) -> TrafficStats {
+        TrafficStats {
+            tx_bytes,
+            rx_bytes,
+            ..TrafficStats::default()
+        }
+    }
+
+    #[test]
+    fn test_traffic_since() {
+        assert_eq!(traffic_since(None, &stats(10, 20)), (10, 20));
+        assert_eq!(traffic_since(Some(&stats(10, 20)), &stats(15, 20)), (5, 0));
+        // A new tunnel starts counting from zero
+        assert_eq!(traffic_since(Some(&stats(10, 20)), &stats(3, 30)), (3, 30));
+    }
+}
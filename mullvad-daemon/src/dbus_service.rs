@@ -0,0 +1,265 @@
+//! Exports the tunnel state on the D-Bus system bus, so that desktop widgets and scripts can follow
+//! it without using the management interface.
+//!
+//! The daemon owns the name `net.mullvad.Daemon` and exports the object `/net/mullvad/Daemon`,
+//! which implements the `net.mullvad.Daemon` interface:
+//!
+//! * `GetState() -> (s state, a{ss} details)`: Returns the current tunnel state.
+//! * `StateChanged(s state, a{ss} details)`: Emitted whenever the tunnel state changes.
+//!
+//! `state` is one of `disconnected`, `connecting`, `connected`, `disconnecting`, `paused` or
+//! `error`. `details` contains the following keys, when they apply to the state and are known:
+//!
+//! * `tunnel_type`, `endpoint`, `tunnel_interface`: The tunnel protocol, the address of the relay
+//!   and the name of the tunnel interface. Set while connecting and connected.
+//! * `country`, `city`, `hostname`, `entry_hostname`: The location of the device, and the relays
+//!   that traffic goes through.
+//! * `locked_down`: Whether traffic is blocked while disconnected.
+//! * `after_disconnect`: What the daemon does once disconnected: `nothing`, `block`, `reconnect`
+//!   or `pause`.
+//! * `cause`, `blocking`: Why the tunnel failed, and whether traffic is blocked.
+
+use mullvad_types::{location::GeoIpLocation, states::TunnelState};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use talpid_dbus::dbus::{
+    self,
+    blocking::{stdintf::org_freedesktop_dbus::RequestNameReply, SyncConnection},
+    channel::{MatchingReceiver, Sender},
+    message::MatchRule,
+    strings::ErrorName,
+    Message,
+};
+use talpid_types::{net::TunnelEndpoint, tunnel::ActionAfterDisconnect};
+
+const BUS_NAME: &str = "net.mullvad.Daemon";
+const OBJECT_PATH: &str = "/net/mullvad/Daemon";
+const INTERFACE: &str = "net.mullvad.Daemon";
+const GET_STATE_METHOD: &str = "GetState";
+const STATE_CHANGED_SIGNAL: &str = "StateChanged";
+
+const INTROSPECTABLE_INTERFACE: &str = "org.freedesktop.DBus.Introspectable";
+const INTROSPECT_METHOD: &str = "Introspect";
+const UNKNOWN_METHOD_ERROR: &str = "org.freedesktop.DBus.Error.UnknownMethod";
+
+const INTROSPECTION_XML: &str = r#"<!DOCTYPE node PUBLIC "-//freedesktop//DTD D-BUS Object Introspection 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/introspect.dtd">
+<node>
+  <interface name="net.mullvad.Daemon">
+    <method name="GetState">
+      <arg name="state" type="s" direction="out"/>
+      <arg name="details" type="a{ss}" direction="out"/>
+    </method>
+    <signal name="StateChanged">
+      <arg name="state" type="s"/>
+      <arg name="details" type="a{ss}"/>
+    </signal>
+  </interface>
+  <interface name="org.freedesktop.DBus.Introspectable">
+    <method name="Introspect">
+      <arg name="xml_data" type="s" direction="out"/>
+    </method>
+  </interface>
+</node>
+"#;
+
+/// How long to wait for incoming messages before checking whether the service has been stopped.
+const PROCESS_TIMEOUT: Duration = Duration::from_secs(1);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to connect to the system bus")]
+    Connect(#[source] dbus::Error),
+
+    #[error("Failed to request the name {BUS_NAME}")]
+    RequestName(#[source] dbus::Error),
+
+    #[error("The name {BUS_NAME} is owned by another process")]
+    NameTaken,
+}
+
+type StateArgs = (&'static str, HashMap<&'static str, String>);
+
+/// Handle to the D-Bus service. The service is stopped when this is dropped.
+pub struct DbusService {
+    connection: Arc<SyncConnection>,
+    state: Arc<Mutex<StateArgs>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl DbusService {
+    /// Claim the bus name and start answering method calls on a background thread.
+    pub fn start(initial_state: &TunnelState) -> Result<Self, Error> {
+        let connection = talpid_dbus::get_connection().map_err(Error::Connect)?;
+        match connection
+            .request_name(BUS_NAME, false, true, true)
+            .map_err(Error::RequestName)?
+        {
+            RequestNameReply::PrimaryOwner | RequestNameReply::AlreadyOwner => (),
+            RequestNameReply::InQueue | RequestNameReply::Exists => return Err(Error::NameTaken),
+        }
+
+        let state = Arc::new(Mutex::new(state_args(initial_state)));
+        let handler_state = state.clone();
+        let token = connection.start_receive(
+            MatchRule::new_method_call().with_path(OBJECT_PATH),
+            Box::new(move |message, connection| {
+                let _ = connection.send(handle_method_call(&message, &handler_state));
+                true
+            }),
+        );
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_connection = connection.clone();
+        let thread_stop = stop.clone();
+        std::thread::spawn(move || {
+            while !thread_stop.load(Ordering::Acquire) {
+                if let Err(error) = thread_connection.process(PROCESS_TIMEOUT) {
+                    log::error!("Failed to process D-Bus messages: {error}");
+                    break;
+                }
+            }
+            thread_connection.stop_receive(token);
+            if let Err(error) = thread_connection.release_name(BUS_NAME) {
+                log::debug!("Failed to release {BUS_NAME}: {error}");
+            }
+        });
+
+        Ok(Self {
+            connection,
+            state,
+            stop,
+        })
+    }
+
+    /// Update the state returned by `GetState` and emit a `StateChanged` signal.
+    pub fn notify_new_state(&self, state: &TunnelState) {
+        let (name, details) = state_args(state);
+        let signal = Message::new_signal(OBJECT_PATH, INTERFACE, STATE_CHANGED_SIGNAL)
+            .expect("Invalid D-Bus signal")
+            .append2(name, &details);
+        *self.state.lock().expect("D-Bus state lock poisoned") = (name, details);
+
+        if self.connection.send(signal).is_err() {
+            log::error!("Failed to emit D-Bus {STATE_CHANGED_SIGNAL} signal");
+        }
+    }
+}
+
+impl Drop for DbusService {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+    }
+}
+
+fn handle_method_call(message: &Message, state: &Mutex<StateArgs>) -> Message {
+    let interface = message.interface();
+    let member = message.member();
+    match (interface.as_deref(), member.as_deref()) {
+        (Some(INTERFACE) | None, Some(GET_STATE_METHOD)) => {
+            let (name, details) = &*state.lock().expect("D-Bus state lock poisoned");
+            message.method_return().append2(*name, details)
+        }
+        (Some(INTROSPECTABLE_INTERFACE) | None, Some(INTROSPECT_METHOD)) => {
+            message.method_return().append1(INTROSPECTION_XML)
+        }
+        _ => message.error(&ErrorName::from(UNKNOWN_METHOD_ERROR), c"Unknown method"),
+    }
+}
+
+/// Returns the name of `state`, and the details that are sent along with it.
+fn state_args(state: &TunnelState) -> StateArgs {
+    let mut details = HashMap::new();
+    let name = match state {
+        TunnelState::Disconnected {
+            location,
+            locked_down,
+        } => {
+            details.insert("locked_down", locked_down.to_string());
+            insert_location(&mut details, location.as_ref());
+            "disconnected"
+        }
+        TunnelState::Connecting {
+            endpoint, location, ..
+        } => {
+            insert_endpoint(&mut details, endpoint);
+            insert_location(&mut details, location.as_ref());
+            "connecting"
+        }
+        TunnelState::Connected {
+            endpoint, location, ..
+        } => {
+            insert_endpoint(&mut details, endpoint);
+            insert_location(&mut details, location.as_ref());
+            "connected"
+        }
+        TunnelState::Disconnecting(action) => {
+            let action = match action {
+                ActionAfterDisconnect::Nothing => "nothing",
+                ActionAfterDisconnect::Block => "block",
+                ActionAfterDisconnect::Reconnect => "reconnect",
+                ActionAfterDisconnect::Pause => "pause",
+            };
+            details.insert("after_disconnect", action.to_owned());
+            "disconnecting"
+        }
+        TunnelState::Paused => "paused",
+        TunnelState::Error(error_state) => {
+            details.insert("cause", error_state.cause().to_string());
+            details.insert("blocking", error_state.is_blocking().to_string());
+            "error"
+        }
+    };
+    (name, details)
+}
+
+fn insert_endpoint(details: &mut HashMap<&'static str, String>, endpoint: &TunnelEndpoint) {
+    details.insert("tunnel_type", endpoint.tunnel_type.to_string());
+    details.insert("endpoint", endpoint.endpoint.address.to_string());
+    if let Some(interface) = &endpoint.tunnel_interface {
+        details.insert("tunnel_interface", interface.clone());
+    }
+}
+
+fn insert_location(details: &mut HashMap<&'static str, String>, location: Option<&GeoIpLocation>) {
+    let Some(location) = location else {
+        return;
+    };
+    details.insert("country", location.country.clone());
+    let optional = [
+        ("city", &location.city),
+        ("hostname", &location.hostname),
+        ("entry_hostname", &location.entry_hostname),
+    ];
+    for (key, value) in optional {
+        if let Some(value) = value {
+            details.insert(key, value.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_disconnected_state_args() {
+        let (name, details) = state_args(&TunnelState::Disconnected {
+            location: None,
+            locked_down: true,
+        });
+        assert_eq!(name, "disconnected");
+        assert_eq!(details.get("locked_down").map(String::as_str), Some("true"));
+        assert!(!details.contains_key("country"));
+
+        let (name, details) = state_args(&TunnelState::Paused);
+        assert_eq!(name, "paused");
+        assert!(details.is_empty());
+    }
+}
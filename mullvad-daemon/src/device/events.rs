@@ -0,0 +1,105 @@
+//! Listens for account and device events pushed by the API, so that payments and revoked devices
+//! are noticed right away instead of the next time the account or device is checked.
+
+use futures::channel::mpsc;
+use mullvad_api::{
+    availability::ApiAvailability,
+    events::{AccountEvent, EventsProxy},
+    rest::{self, MullvadRestHandle},
+    retry::RetryPolicy,
+    StatusCode,
+};
+use mullvad_types::account::AccountNumber;
+use talpid_types::ErrorExt;
+use tokio::task::JoinHandle;
+
+/// Policy used to wait before polling again after a failed request.
+const RETRY_POLICY: RetryPolicy = RetryPolicy::BACKGROUND;
+
+pub(super) struct EventListener {
+    proxy: EventsProxy,
+    api_availability: ApiAvailability,
+    events_tx: mpsc::UnboundedSender<AccountEvent>,
+    current: Option<(AccountNumber, JoinHandle<()>)>,
+}
+
+impl EventListener {
+    /// Returns a listener, which is initially not listening to any account, and the receiver of
+    /// the events that it receives.
+    pub fn new(
+        handle: MullvadRestHandle,
+        api_availability: ApiAvailability,
+    ) -> (Self, mpsc::UnboundedReceiver<AccountEvent>) {
+        let (events_tx, events_rx) = mpsc::unbounded();
+        let listener = Self {
+            proxy: EventsProxy::new(handle),
+            api_availability,
+            events_tx,
+            current: None,
+        };
+        (listener, events_rx)
+    }
+
+    /// Listen for events concerning `account`, or stop listening if `account` is `None`.
+    pub fn set_account(&mut self, account: Option<&AccountNumber>) {
+        if self.current.as_ref().map(|(current, _)| current) == account {
+            return;
+        }
+        if let Some((_, task)) = self.current.take() {
+            task.abort();
+        }
+        self.current = account.map(|account| {
+            let task = tokio::spawn(listen(
+                self.proxy.clone(),
+                self.api_availability.clone(),
+                account.clone(),
+                self.events_tx.clone(),
+            ));
+            (account.clone(), task)
+        });
+    }
+}
+
+impl Drop for EventListener {
+    fn drop(&mut self) {
+        self.set_account(None);
+    }
+}
+
+async fn listen(
+    proxy: EventsProxy,
+    api_availability: ApiAvailability,
+    account: AccountNumber,
+    events_tx: mpsc::UnboundedSender<AccountEvent>,
+) {
+    let mut cursor = None;
+    let mut delays = RETRY_POLICY.delays();
+    loop {
+        if api_availability.wait_background().await.is_err() {
+            return;
+        }
+        match proxy.poll(account.clone(), cursor.clone()).await {
+            Ok(batch) => {
+                delays = RETRY_POLICY.delays();
+                cursor = Some(batch.cursor);
+                for event in batch.events {
+                    if events_tx.unbounded_send(event).is_err() {
+                        return;
+                    }
+                }
+            }
+            Err(rest::Error::ApiError(StatusCode::NOT_FOUND, _)) => {
+                // Fall back on checking the account and device when needed
+                log::debug!("The API does not support account events");
+                return;
+            }
+            Err(error) => {
+                log::debug!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to poll account events")
+                );
+                talpid_time::sleep(delays.next().unwrap_or_default()).await;
+            }
+        }
+    }
+}
@@ -4,11 +4,11 @@ use futures::{
     stream::StreamExt,
 };
 
-use mullvad_api::rest;
+use mullvad_api::{events::AccountEvent as ApiAccountEvent, rest};
 #[cfg(target_os = "android")]
 use mullvad_types::account::{PlayPurchase, PlayPurchasePaymentToken};
 use mullvad_types::{
-    account::{AccountNumber, VoucherSubmission},
+    account::{AccountExpiryEvent, AccountNumber, VoucherSubmission},
     device::{
         AccountAndDevice, Device, DeviceEvent, DeviceEventCause, DeviceId, DeviceName, DeviceState,
     },
@@ -36,6 +36,7 @@ use tokio::{
 };
 
 mod api;
+mod events;
 mod service;
 pub(crate) use service::{AccountService, DeviceService};
 
@@ -241,8 +242,8 @@ impl From<PrivateDevice> for Device {
 pub(crate) enum AccountEvent {
     /// Emitted when the device state changes.
     Device(PrivateDeviceEvent),
-    /// Emitted when the account expiry is fetched.
-    Expiry(DateTime<Utc>),
+    /// Emitted when the account expiry is fetched or changes.
+    Expiry(AccountExpiryEvent),
 }
 
 #[derive(Clone)]
@@ -413,6 +414,7 @@ pub(crate) struct AccountManager {
     expiry_requests: Vec<ResponseTx<DateTime<Utc>>>,
     rotation_requests: Vec<ResponseTx<()>>,
     data_requests: Vec<ResponseTx<PrivateDeviceState>>,
+    event_listener: events::EventListener,
 }
 
 impl AccountManager {
@@ -432,6 +434,10 @@ impl AccountManager {
 
         let (cmd_tx, cmd_rx) = mpsc::unbounded();
 
+        let (mut event_listener, events_rx) =
+            events::EventListener::new(rest_handle.clone(), api_availability.clone());
+        event_listener.set_account(data.device().map(|config| &config.account_number));
+
         let device_service = DeviceService::new(rest_handle, api_availability);
         let manager = AccountManager {
             cacher,
@@ -445,9 +451,10 @@ impl AccountManager {
             expiry_requests: vec![],
             rotation_requests: vec![],
             data_requests: vec![],
+            event_listener,
         };
 
-        tokio::spawn(manager.run(cmd_rx));
+        tokio::spawn(manager.run(cmd_rx, events_rx));
         let handle = AccountManagerHandle {
             cmd_tx,
             account_service,
@@ -456,7 +463,11 @@ impl AccountManager {
         Ok((handle, data))
     }
 
-    async fn run(mut self, mut cmd_rx: mpsc::UnboundedReceiver<AccountManagerCommand>) {
+    async fn run(
+        mut self,
+        mut cmd_rx: mpsc::UnboundedReceiver<AccountManagerCommand>,
+        mut events_rx: mpsc::UnboundedReceiver<ApiAccountEvent>,
+    ) {
         let mut shutdown_tx = None;
         let mut current_api_call = api::CurrentApiCall::new();
 
@@ -472,6 +483,12 @@ impl AccountManager {
                     self.consume_api_result(api_result, &mut current_api_call).await;
                 }
 
+                event = events_rx.next() => {
+                    if let Some(event) = event {
+                        self.consume_account_event(event).await;
+                    }
+                }
+
                 cmd = cmd_rx.next() => {
                     match cmd {
                         Some(AccountManagerCommand::Shutdown(tx)) => {
@@ -750,10 +767,7 @@ impl AccountManager {
     ) {
         match &response {
             Ok(submission) => {
-                // Send expiry update event
-                let event = AccountEvent::Expiry(submission.new_expiry);
-                self.listeners
-                    .retain(|listener| listener.send(event.clone()).is_ok());
+                self.notify_expiry(submission.new_expiry, true);
             }
             Err(Error::InvalidAccount) => {
                 self.revoke_device(|| Error::InvalidAccount).await;
@@ -775,10 +789,7 @@ impl AccountManager {
                     log::debug!("Account has no time left");
                 }
 
-                // Send expiry update event
-                let event = AccountEvent::Expiry(expiry);
-                self.listeners
-                    .retain(|listener| listener.send(event.clone()).is_ok());
+                self.notify_expiry(expiry, false);
 
                 Self::drain_requests(&mut self.expiry_requests, || Ok(expiry));
             }
@@ -795,6 +806,40 @@ impl AccountManager {
         }
     }
 
+    async fn consume_account_event(&mut self, event: ApiAccountEvent) {
+        match event {
+            ApiAccountEvent::ExpiryUpdated { expiry } => {
+                log::debug!("Account expiry changed to {expiry}");
+                self.notify_expiry(expiry, false);
+            }
+            ApiAccountEvent::PaymentReceived { new_expiry } => {
+                log::info!("Received payment. The account expires {new_expiry}");
+                self.notify_expiry(new_expiry, true);
+            }
+            ApiAccountEvent::DeviceRevoked { device_id } => {
+                let is_current = self
+                    .data
+                    .device()
+                    .is_some_and(|config| config.device.id == device_id);
+                if is_current {
+                    log::info!("The current device was removed from the account");
+                    self.revoke_device(|| Error::InvalidDevice).await;
+                }
+            }
+            ApiAccountEvent::Unknown => (),
+        }
+    }
+
+    /// Send an expiry update event.
+    fn notify_expiry(&mut self, expiry: DateTime<Utc>, payment_received: bool) {
+        let event = AccountEvent::Expiry(AccountExpiryEvent {
+            expiry,
+            payment_received,
+        });
+        self.listeners
+            .retain(|listener| listener.send(event.clone()).is_ok());
+    }
+
     async fn consume_validation(
         &mut self,
         response: Result<Device, Error>,
@@ -971,6 +1016,7 @@ impl AccountManager {
             );
         }
         self.data.revoke();
+        self.event_listener.set_account(None);
 
         Self::drain_requests(&mut self.validation_requests, || Err(err_constructor()));
         Self::drain_requests(&mut self.rotation_requests, || Err(err_constructor()));
@@ -995,6 +1041,7 @@ impl AccountManager {
         }
 
         let old_config = self.data.logout();
+        self.event_listener.set_account(None);
 
         self.listeners.retain(|listener| {
             listener
@@ -1053,6 +1100,8 @@ impl AccountManager {
         }
 
         self.data = device_state;
+        self.event_listener
+            .set_account(self.data.device().map(|config| &config.account_number));
 
         let event = AccountEvent::Device(event);
         self.listeners
@@ -1333,7 +1382,7 @@ impl TunnelStateChangeHandler {
                 retry_attempt.wrapping_add(1)
             }
             // Only reset the counter if we managed to connect to a Wireguard relay
-            TunnelStateTransition::Connected(endpoint) if wireguard(endpoint) => 0,
+            TunnelStateTransition::Connected(endpoint, _) if wireguard(endpoint) => 0,
             // Any other state transition doesn't affect the counter
             _ => retry_attempt,
         }
@@ -1344,11 +1393,12 @@ impl TunnelStateChangeHandler {
     ///
     /// # Note
     /// The following state transition counts as breaking a connecting-loop: `Connected`,
-    /// `Disconnected` and `Error`.
+    /// `Disconnected`, `Paused` and `Error`.
     fn update_retry_bool(new_state: &TunnelStateTransition, can_retry: Arc<AtomicBool>) {
         match new_state {
             TunnelStateTransition::Disconnected { .. }
-            | TunnelStateTransition::Connected(_)
+            | TunnelStateTransition::Connected(..)
+            | TunnelStateTransition::Paused
             | TunnelStateTransition::Error(_) => {
                 can_retry.store(true, Ordering::SeqCst);
             }
@@ -15,27 +15,26 @@ use super::{Error, PrivateAccountAndDevice, PrivateDevice};
 use mullvad_api::{
     availability::ApiAvailability,
     rest::{self, MullvadRestHandle},
+    retry::{Backoff, RetryPolicy},
     AccountsProxy, DevicesProxy,
 };
-use talpid_future::retry::{retry_future, ConstantInterval, ExponentialBackoff, Jittered};
-/// Retry strategy used for user-initiated actions that require immediate feedback
-const RETRY_ACTION_STRATEGY: ConstantInterval = ConstantInterval::new(Duration::ZERO, Some(3));
-/// Retry strategy used for background tasks
-const RETRY_BACKOFF_STRATEGY: Jittered<ExponentialBackoff> = Jittered::jitter(
-    ExponentialBackoff::new(Duration::from_secs(4), 5)
-        .max_delay(Some(Duration::from_secs(24 * 60 * 60))),
-);
+/// Retry policy used for user-initiated actions that require immediate feedback
+const RETRY_ACTION_POLICY: RetryPolicy = RetryPolicy::INTERACTIVE;
+/// Retry policy used for background tasks
+const RETRY_BACKOFF_POLICY: RetryPolicy = RetryPolicy::BACKGROUND;
 
 #[derive(Clone)]
 pub struct DeviceService {
     api_availability: ApiAvailability,
     proxy: DevicesProxy,
+    background_proxy: DevicesProxy,
 }
 
 impl DeviceService {
     pub fn new(handle: rest::MullvadRestHandle, api_availability: ApiAvailability) -> Self {
         Self {
-            proxy: DevicesProxy::new(handle),
+            proxy: DevicesProxy::new(handle.clone().with_retry_policy(&RETRY_ACTION_POLICY)),
+            background_proxy: DevicesProxy::new(handle.with_retry_policy(&RETRY_BACKOFF_POLICY)),
             api_availability,
         }
     }
@@ -52,13 +51,13 @@ impl DeviceService {
         let api_handle = self.api_availability.clone();
         let number_copy = account_number.clone();
         async move {
-            let (device, addresses) = retry_future(
-                move || proxy.create(number_copy.clone(), pubkey.clone()),
-                move |result| should_retry(result, &api_handle),
-                RETRY_ACTION_STRATEGY,
-            )
-            .await
-            .map_err(map_rest_error)?;
+            let (device, addresses) = RETRY_ACTION_POLICY
+                .retry(
+                    move || proxy.create(number_copy.clone(), pubkey.clone()),
+                    move |result| should_retry(result, &api_handle),
+                )
+                .await
+                .map_err(map_rest_error)?;
 
             Ok(PrivateAccountAndDevice {
                 account_number,
@@ -81,16 +80,16 @@ impl DeviceService {
         let private_key = PrivateKey::new_from_random();
         let pubkey = private_key.public_key();
 
-        let proxy = self.proxy.clone();
+        let proxy = self.background_proxy.clone();
         let api_handle = self.api_availability.clone();
         let number_copy = account_number.clone();
-        let (device, addresses) = retry_future(
-            move || api_handle.when_online(proxy.create(number_copy.clone(), pubkey.clone())),
-            should_retry_backoff,
-            RETRY_BACKOFF_STRATEGY,
-        )
-        .await
-        .map_err(map_rest_error)?;
+        let (device, addresses) = RETRY_BACKOFF_POLICY
+            .retry(
+                move || api_handle.when_online(proxy.create(number_copy.clone(), pubkey.clone())),
+                should_retry_backoff,
+            )
+            .await
+            .map_err(map_rest_error)?;
 
         Ok(PrivateAccountAndDevice {
             account_number,
@@ -115,6 +114,27 @@ impl DeviceService {
         self.list_devices(account_number).await
     }
 
+    /// Remove several devices, one at a time. Failing to remove one device does not stop the rest
+    /// from being removed. Returns the devices that were removed, and those that were not.
+    pub async fn remove_devices(
+        &self,
+        account_number: AccountNumber,
+        device_ids: Vec<DeviceId>,
+    ) -> (Vec<DeviceId>, Vec<(DeviceId, Error)>) {
+        let mut removed = vec![];
+        let mut failed = vec![];
+        for device_id in device_ids {
+            match self
+                .remove_device_inner(account_number.clone(), device_id.clone())
+                .await
+            {
+                Ok(()) => removed.push(device_id),
+                Err(error) => failed.push((device_id, error)),
+            }
+        }
+        (removed, failed)
+    }
+
     async fn remove_device_inner(
         &self,
         number: AccountNumber,
@@ -122,13 +142,13 @@ impl DeviceService {
     ) -> Result<(), Error> {
         let proxy = self.proxy.clone();
         let api_handle = self.api_availability.clone();
-        retry_future(
-            move || proxy.remove(number.clone(), device.clone()),
-            move |result| should_retry(result, &api_handle),
-            RETRY_ACTION_STRATEGY,
-        )
-        .await
-        .map_err(map_rest_error)?;
+        RETRY_ACTION_POLICY
+            .retry(
+                move || proxy.remove(number.clone(), device.clone()),
+                move |result| should_retry(result, &api_handle),
+            )
+            .await
+            .map_err(map_rest_error)?;
         Ok(())
     }
 
@@ -137,18 +157,19 @@ impl DeviceService {
         number: AccountNumber,
         device: DeviceId,
     ) -> Result<(), Error> {
-        let proxy = self.proxy.clone();
+        let proxy = self.background_proxy.clone();
         let api_handle = self.api_availability.clone();
 
-        retry_future(
-            // NOTE: Not honoring "paused" state, because the account may have no time on it.
-            move || api_handle.when_online(proxy.remove(number.clone(), device.clone())),
-            should_retry_backoff,
-            // Not setting a maximum interval
-            RETRY_BACKOFF_STRATEGY.clone().max_delay(None),
-        )
-        .await
-        .map_err(map_rest_error)?;
+        // Not setting a maximum interval
+        RETRY_BACKOFF_POLICY
+            .max_delay(None)
+            .retry(
+                // NOTE: Not honoring "paused" state, because the account may have no time on it.
+                move || api_handle.when_online(proxy.remove(number.clone(), device.clone())),
+                should_retry_backoff,
+            )
+            .await
+            .map_err(map_rest_error)?;
 
         Ok(())
     }
@@ -163,13 +184,13 @@ impl DeviceService {
         let proxy = self.proxy.clone();
         let api_handle = self.api_availability.clone();
         let pubkey = private_key.public_key();
-        let addresses = retry_future(
-            move || proxy.replace_wg_key(number.clone(), device.clone(), pubkey.clone()),
-            move |result| should_retry(result, &api_handle),
-            RETRY_ACTION_STRATEGY,
-        )
-        .await
-        .map_err(map_rest_error)?;
+        let addresses = RETRY_ACTION_POLICY
+            .retry(
+                move || proxy.replace_wg_key(number.clone(), device.clone(), pubkey.clone()),
+                move |result| should_retry(result, &api_handle),
+            )
+            .await
+            .map_err(map_rest_error)?;
 
         Ok(WireguardData {
             private_key,
@@ -185,25 +206,27 @@ impl DeviceService {
     ) -> Result<WireguardData, Error> {
         let private_key = PrivateKey::new_from_random();
 
-        let proxy = self.proxy.clone();
+        let proxy = self.background_proxy.clone();
         let api_handle = self.api_availability.clone();
         let pubkey = private_key.public_key();
 
-        let rotate_retry_strategy = std::iter::repeat(Duration::from_secs(24 * 60 * 60));
-
-        let addresses = retry_future(
-            move || {
-                api_handle.when_bg_resumes(proxy.replace_wg_key(
-                    number.clone(),
-                    device.clone(),
-                    pubkey.clone(),
-                ))
-            },
-            should_retry_backoff,
-            rotate_retry_strategy,
-        )
-        .await
-        .map_err(map_rest_error)?;
+        let rotate_retry_policy = RETRY_BACKOFF_POLICY
+            .backoff(Backoff::Constant(Duration::from_secs(24 * 60 * 60)))
+            .jitter(false);
+
+        let addresses = rotate_retry_policy
+            .retry(
+                move || {
+                    api_handle.when_bg_resumes(proxy.replace_wg_key(
+                        number.clone(),
+                        device.clone(),
+                        pubkey.clone(),
+                    ))
+                },
+                should_retry_backoff,
+            )
+            .await
+            .map_err(map_rest_error)?;
 
         Ok(WireguardData {
             private_key,
@@ -215,41 +238,41 @@ impl DeviceService {
     pub async fn list_devices(&self, number: AccountNumber) -> Result<Vec<Device>, Error> {
         let proxy = self.proxy.clone();
         let api_handle = self.api_availability.clone();
-        retry_future(
-            move || proxy.list(number.clone()),
-            move |result| should_retry(result, &api_handle),
-            RETRY_ACTION_STRATEGY,
-        )
-        .await
-        .map_err(map_rest_error)
+        RETRY_ACTION_POLICY
+            .retry(
+                move || proxy.list(number.clone()),
+                move |result| should_retry(result, &api_handle),
+            )
+            .await
+            .map_err(map_rest_error)
     }
 
     pub async fn list_devices_with_backoff(
         &self,
         number: AccountNumber,
     ) -> Result<Vec<Device>, Error> {
-        let proxy = self.proxy.clone();
+        let proxy = self.background_proxy.clone();
         let api_handle = self.api_availability.clone();
 
-        retry_future(
-            move || api_handle.when_online(proxy.list(number.clone())),
-            should_retry_backoff,
-            RETRY_BACKOFF_STRATEGY,
-        )
-        .await
-        .map_err(map_rest_error)
+        RETRY_BACKOFF_POLICY
+            .retry(
+                move || api_handle.when_online(proxy.list(number.clone())),
+                should_retry_backoff,
+            )
+            .await
+            .map_err(map_rest_error)
     }
 
     pub async fn get(&self, number: AccountNumber, device: DeviceId) -> Result<Device, Error> {
         let proxy = self.proxy.clone();
         let api_handle = self.api_availability.clone();
-        retry_future(
-            move || proxy.get(number.clone(), device.clone()),
-            move |result| should_retry(result, &api_handle),
-            RETRY_ACTION_STRATEGY,
-        )
-        .await
-        .map_err(map_rest_error)
+        RETRY_ACTION_POLICY
+            .retry(
+                move || proxy.get(number.clone(), device.clone()),
+                move |result| should_retry(result, &api_handle),
+            )
+            .await
+            .map_err(map_rest_error)
     }
 }
 
@@ -266,10 +289,9 @@ impl AccountService {
     ) -> impl Future<Output = Result<AccountNumber, rest::Error>> + use<> {
         let proxy = self.proxy.clone();
         let api_handle = self.api_availability.clone();
-        retry_future(
+        RETRY_ACTION_POLICY.retry(
             move || proxy.create_account(),
             move |result| should_retry(result, &api_handle),
-            RETRY_ACTION_STRATEGY,
         )
     }
 
@@ -279,22 +301,21 @@ impl AccountService {
     ) -> impl Future<Output = Result<String, rest::Error>> + use<> {
         let proxy = self.proxy.clone();
         let api_handle = self.api_availability.clone();
-        retry_future(
+        RETRY_ACTION_POLICY.retry(
             move || proxy.get_www_auth_token(account.clone()),
             move |result| should_retry(result, &api_handle),
-            RETRY_ACTION_STRATEGY,
         )
     }
 
     pub async fn get_data(&self, number: AccountNumber) -> Result<AccountData, rest::Error> {
         let proxy = self.proxy.clone();
         let api_handle = self.api_availability.clone();
-        let result = retry_future(
-            move || proxy.get_data(number.clone()),
-            move |result| should_retry(result, &api_handle),
-            RETRY_ACTION_STRATEGY,
-        )
-        .await;
+        let result = RETRY_ACTION_POLICY
+            .retry(
+                move || proxy.get_data(number.clone()),
+                move |result| should_retry(result, &api_handle),
+            )
+            .await;
         if handle_account_data_result(&result, &self.api_availability) {
             self.initial_check_abort_handle.abort();
         }
@@ -312,12 +333,12 @@ impl AccountService {
     ) -> Result<VoucherSubmission, Error> {
         let proxy = self.proxy.clone();
         let api_handle = self.api_availability.clone();
-        let result = retry_future(
-            move || proxy.submit_voucher(account_number.clone(), voucher.clone()),
-            move |result| should_retry(result, &api_handle),
-            RETRY_ACTION_STRATEGY,
-        )
-        .await;
+        let result = RETRY_ACTION_POLICY
+            .retry(
+                move || proxy.submit_voucher(account_number.clone(), voucher.clone()),
+                move |result| should_retry(result, &api_handle),
+            )
+            .await;
         if result.is_ok() {
             self.initial_check_abort_handle.abort();
             self.api_availability.resume_background();
@@ -332,12 +353,12 @@ impl AccountService {
     ) -> Result<PlayPurchasePaymentToken, Error> {
         let mut proxy = self.proxy.clone();
         let api_handle = self.api_availability.clone();
-        let result = retry_future(
-            move || proxy.init_play_purchase(account_number.clone()),
-            move |result| should_retry(result, &api_handle),
-            RETRY_ACTION_STRATEGY,
-        )
-        .await;
+        let result = RETRY_ACTION_POLICY
+            .retry(
+                move || proxy.init_play_purchase(account_number.clone()),
+                move |result| should_retry(result, &api_handle),
+            )
+            .await;
         if result.is_ok() {
             self.initial_check_abort_handle.abort();
             self.api_availability.resume_background();
@@ -353,12 +374,12 @@ impl AccountService {
     ) -> Result<(), Error> {
         let mut proxy = self.proxy.clone();
         let api_handle = self.api_availability.clone();
-        let result = retry_future(
-            move || proxy.verify_play_purchase(account_number.clone(), play_purchase.clone()),
-            move |result| should_retry(result, &api_handle),
-            RETRY_ACTION_STRATEGY,
-        )
-        .await;
+        let result = RETRY_ACTION_POLICY
+            .retry(
+                move || proxy.verify_play_purchase(account_number.clone(), play_purchase.clone()),
+                move |result| should_retry(result, &api_handle),
+            )
+            .await;
         if result.is_ok() {
             self.initial_check_abort_handle.abort();
             self.api_availability.resume_background();
@@ -372,11 +393,13 @@ pub fn spawn_account_service(
     number: Option<AccountNumber>,
     api_availability: ApiAvailability,
 ) -> AccountService {
-    let accounts_proxy = AccountsProxy::new(api_handle);
+    let accounts_proxy =
+        AccountsProxy::new(api_handle.clone().with_retry_policy(&RETRY_BACKOFF_POLICY));
     api_availability.pause_background();
 
     let api_availability_copy = api_availability.clone();
-    let accounts_proxy_copy = accounts_proxy.clone();
+    let accounts_proxy_copy =
+        AccountsProxy::new(api_handle.with_retry_policy(&RETRY_ACTION_POLICY));
 
     let (future, initial_check_abort_handle) = abortable(async move {
         let Some(number) = number else {
@@ -390,7 +413,9 @@ pub fn spawn_account_service(
             async move { handle_account_data_result(&expiry_fut.await, &api_availability_copy) }
         };
         let should_retry = move |state_was_updated: &bool| -> bool { !*state_was_updated };
-        retry_future(future_generator, should_retry, RETRY_BACKOFF_STRATEGY).await;
+        RETRY_BACKOFF_POLICY
+            .retry(future_generator, should_retry)
+            .await;
     });
     tokio::spawn(future);
 
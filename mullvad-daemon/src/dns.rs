@@ -1,5 +1,11 @@
+#[cfg(not(target_os = "android"))]
+use mullvad_types::settings::Settings;
 use mullvad_types::settings::{DnsOptions, DnsState};
 use std::net::{IpAddr, Ipv4Addr};
+#[cfg(not(target_os = "android"))]
+use std::sync::Arc;
+#[cfg(not(target_os = "android"))]
+use talpid_core::dns::BypassedDomains;
 use talpid_core::{dns::DnsConfig, firewall::is_local_address};
 
 /// When we want to block certain contents with the help of DNS server side,
@@ -15,6 +21,23 @@ const DNS_SOCIAL_MEDIA_BLOCKING_IP_BIT: u8 = 1 << 5; // 0b00100000
 
 /// Return the DNS resolvers to use
 pub fn addresses_from_options(options: &DnsOptions) -> DnsConfig {
+    resolvers_from_options(options).with_split_dns_rules(&options.split_dns_rules)
+}
+
+/// Return the domains whose traffic is routed outside the tunnel
+#[cfg(target_os = "linux")]
+pub fn bypassed_domains(settings: &Settings) -> Arc<BypassedDomains> {
+    Arc::new(settings.split_tunnel_domains.iter().collect())
+}
+
+/// Return the domains whose traffic is routed outside the tunnel. This is only supported on
+/// Linux.
+#[cfg(any(windows, target_os = "macos"))]
+pub fn bypassed_domains(_: &Settings) -> Arc<BypassedDomains> {
+    Arc::default()
+}
+
+fn resolvers_from_options(options: &DnsOptions) -> DnsConfig {
     match options.state {
         DnsState::Default => {
             // Check if we should use a custom blocking DNS resolver.
@@ -48,6 +71,9 @@ pub fn addresses_from_options(options: &DnsOptions) -> DnsConfig {
                 DnsConfig::default()
             }
         }
+        DnsState::Custom if !options.custom_options.encrypted_servers.is_empty() => {
+            DnsConfig::from_encrypted_servers(&options.custom_options.encrypted_servers)
+        }
         DnsState::Custom if options.custom_options.addresses.is_empty() => DnsConfig::default(),
         DnsState::Custom => {
             let (non_tunnel_config, tunnel_config): (Vec<_>, Vec<_>) = options
@@ -66,6 +92,7 @@ mod test {
     use crate::dns::addresses_from_options;
     use mullvad_types::settings::{CustomDnsOptions, DefaultDnsOptions, DnsOptions, DnsState};
     use talpid_core::dns::DnsConfig;
+    use talpid_types::dns::{EncryptedDnsProtocol, EncryptedDnsServer, SplitDnsRule};
 
     #[test]
     fn test_default_dns() {
@@ -73,6 +100,8 @@ mod test {
             state: DnsState::Default,
             custom_options: CustomDnsOptions::default(),
             default_options: DefaultDnsOptions::default(),
+            split_dns_rules: vec![],
+            filter_lists: vec![],
         };
 
         assert_eq!(addresses_from_options(&public_cfg), DnsConfig::default());
@@ -87,6 +116,8 @@ mod test {
                 block_ads: true,
                 ..DefaultDnsOptions::default()
             },
+            split_dns_rules: vec![],
+            filter_lists: vec![],
         };
 
         assert_eq!(
@@ -104,8 +135,11 @@ mod test {
             state: DnsState::Custom,
             custom_options: CustomDnsOptions {
                 addresses: vec![public_ip, private_ip],
+                encrypted_servers: vec![],
             },
             default_options: DefaultDnsOptions::default(),
+            split_dns_rules: vec![],
+            filter_lists: vec![],
         };
 
         assert_eq!(
@@ -113,4 +147,52 @@ mod test {
             DnsConfig::from_addresses(&[public_ip], &[private_ip],)
         );
     }
+
+    // Encrypted servers take precedence over plaintext servers
+    #[test]
+    fn test_encrypted_dns() {
+        let encrypted_server = EncryptedDnsServer {
+            address: "1.1.1.1".parse().unwrap(),
+            protocol: EncryptedDnsProtocol::Tls,
+            hostname: "one.one.one.one".to_owned(),
+            port: None,
+            spki_pin: None,
+        };
+        let encrypted_cfg = DnsOptions {
+            state: DnsState::Custom,
+            custom_options: CustomDnsOptions {
+                addresses: vec!["1.2.3.4".parse().unwrap()],
+                encrypted_servers: vec![encrypted_server.clone()],
+            },
+            default_options: DefaultDnsOptions::default(),
+            split_dns_rules: vec![],
+            filter_lists: vec![],
+        };
+
+        assert_eq!(
+            addresses_from_options(&encrypted_cfg),
+            DnsConfig::from_encrypted_servers(&[encrypted_server])
+        );
+    }
+
+    // Split DNS rules apply to both default and custom DNS
+    #[test]
+    fn test_split_dns() {
+        let rule = SplitDnsRule {
+            domain: "corp.example".to_owned(),
+            servers: vec!["10.0.0.53".parse().unwrap()],
+        };
+        let split_cfg = DnsOptions {
+            state: DnsState::Default,
+            custom_options: CustomDnsOptions::default(),
+            default_options: DefaultDnsOptions::default(),
+            split_dns_rules: vec![rule.clone()],
+            filter_lists: vec![],
+        };
+
+        assert_eq!(
+            addresses_from_options(&split_cfg),
+            DnsConfig::default().with_split_dns_rules(&[rule])
+        );
+    }
 }
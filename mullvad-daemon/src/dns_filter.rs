@@ -0,0 +1,283 @@
+//! Loads user-supplied DNS filter lists and compiles them into a [DomainBlocklist], which is
+//! enforced by the DNS forwarder of the tunnel state machine.
+//!
+//! Lists are loaded when the configured sources change, and refreshed periodically after that.
+//! If a list cannot be loaded, the domains from the last successful load are kept.
+
+use crate::{Daemon, DaemonEventSender, InternalDaemonEvent};
+use futures::{
+    channel::{mpsc, oneshot},
+    StreamExt,
+};
+use mullvad_types::dns_filter::FilterListSource;
+use std::{collections::HashMap, io, net::IpAddr, path::Path, sync::Arc, time::Duration};
+use talpid_core::{dns::DomainBlocklist, mpsc::Sender, tunnel_state_machine::TunnelCommand};
+use talpid_types::ErrorExt;
+use tokio::io::AsyncReadExt;
+
+/// How often filter lists are loaded again.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+/// How soon to try again if a filter list could not be loaded.
+const RETRY_INTERVAL: Duration = Duration::from_secs(15 * 60);
+/// How long to wait for a filter list to be downloaded.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60);
+/// Largest filter list that is accepted, in bytes.
+const MAX_LIST_SIZE: usize = 16 * 1024 * 1024;
+/// Largest number of domains that are blocked, across all filter lists.
+const MAX_BLOCKED_DOMAINS: usize = 1_000_000;
+
+#[derive(thiserror::Error, Debug)]
+enum Error {
+    #[error("Failed to read filter list")]
+    Read(#[source] io::Error),
+
+    #[error("Failed to download filter list")]
+    Download(#[source] reqwest::Error),
+
+    #[error("Filter list is larger than {MAX_LIST_SIZE} bytes")]
+    TooLarge,
+}
+
+/// Handle used to change the filter lists that are loaded.
+pub struct DnsFilterHandle {
+    tx: mpsc::UnboundedSender<Vec<FilterListSource>>,
+}
+
+impl DnsFilterHandle {
+    /// Load `sources` and replace the blocked domains with the domains in them.
+    pub fn set_sources(&self, sources: Vec<FilterListSource>) {
+        let _ = self.tx.unbounded_send(sources);
+    }
+}
+
+/// Start loading `sources`. The daemon is sent a new blocklist whenever the blocked domains
+/// change.
+pub fn spawn(sources: Vec<FilterListSource>, daemon_tx: DaemonEventSender) -> DnsFilterHandle {
+    let (tx, rx) = mpsc::unbounded();
+    let updater = DnsFilterUpdater {
+        sources,
+        lists: HashMap::new(),
+        blocklist: Arc::default(),
+        daemon_tx,
+    };
+    tokio::spawn(updater.run(rx));
+    DnsFilterHandle { tx }
+}
+
+struct DnsFilterUpdater {
+    sources: Vec<FilterListSource>,
+    /// Domains of each list, as of the last time it was loaded successfully.
+    lists: HashMap<FilterListSource, Vec<String>>,
+    blocklist: Arc<DomainBlocklist>,
+    daemon_tx: DaemonEventSender,
+}
+
+impl DnsFilterUpdater {
+    async fn run(mut self, mut rx: mpsc::UnboundedReceiver<Vec<FilterListSource>>) {
+        let mut next_refresh = self.refresh().await;
+        loop {
+            tokio::select! {
+                sources = rx.next() => {
+                    let Some(sources) = sources else {
+                        return;
+                    };
+                    if sources != self.sources {
+                        self.sources = sources;
+                        self.lists.retain(|source, _| self.sources.contains(source));
+                        next_refresh = self.refresh().await;
+                    }
+                }
+                _ = talpid_time::sleep(next_refresh) => {
+                    next_refresh = self.refresh().await;
+                }
+            }
+        }
+    }
+
+    /// Load all lists and update the blocklist. Returns how long to wait until the next refresh.
+    async fn refresh(&mut self) -> Duration {
+        let mut next_refresh = REFRESH_INTERVAL;
+        for source in &self.sources {
+            match load(source).await {
+                Ok(domains) => {
+                    log::debug!(
+                        "Loaded {} domains from DNS filter list {source}",
+                        domains.len()
+                    );
+                    self.lists.insert(source.clone(), domains);
+                }
+                Err(error) => {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg(&format!(
+                            "Failed to load DNS filter list {source}"
+                        ))
+                    );
+                    next_refresh = RETRY_INTERVAL;
+                }
+            }
+        }
+
+        let blocklist = self.compile();
+        if blocklist != *self.blocklist {
+            log::info!("Blocking {} domains from DNS filter lists", blocklist.len());
+            self.blocklist = Arc::new(blocklist);
+            let _ = self
+                .daemon_tx
+                .send(InternalDaemonEvent::DnsBlocklist(self.blocklist.clone()));
+        }
+        next_refresh
+    }
+
+    fn compile(&self) -> DomainBlocklist {
+        let mut blocklist = DomainBlocklist::default();
+        let domains = self
+            .sources
+            .iter()
+            .filter_map(|source| self.lists.get(source))
+            .flatten();
+        for domain in domains {
+            if blocklist.len() >= MAX_BLOCKED_DOMAINS {
+                log::warn!(
+                    "Ignoring domains beyond the first {MAX_BLOCKED_DOMAINS} in DNS filter lists"
+                );
+                break;
+            }
+            blocklist.insert(domain);
+        }
+        blocklist
+    }
+}
+
+async fn load(source: &FilterListSource) -> Result<Vec<String>, Error> {
+    let contents = match source {
+        FilterListSource::Url(url) => download(url).await?,
+        FilterListSource::File(path) => read_file(path).await?,
+    };
+    Ok(parse_filter_list(&String::from_utf8_lossy(&contents)))
+}
+
+async fn download(url: &str) -> Result<Vec<u8>, Error> {
+    let client = reqwest::Client::builder()
+        .https_only(true)
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .map_err(Error::Download)?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(Error::Download)?;
+    if response
+        .content_length()
+        .is_some_and(|len| len > MAX_LIST_SIZE as u64)
+    {
+        return Err(Error::TooLarge);
+    }
+
+    let mut contents = vec![];
+    while let Some(chunk) = response.chunk().await.map_err(Error::Download)? {
+        if contents.len() + chunk.len() > MAX_LIST_SIZE {
+            return Err(Error::TooLarge);
+        }
+        contents.extend_from_slice(&chunk);
+    }
+    Ok(contents)
+}
+
+async fn read_file(path: &Path) -> Result<Vec<u8>, Error> {
+    let file = tokio::fs::File::open(path).await.map_err(Error::Read)?;
+    let mut contents = vec![];
+    file.take(MAX_LIST_SIZE as u64 + 1)
+        .read_to_end(&mut contents)
+        .await
+        .map_err(Error::Read)?;
+    if contents.len() > MAX_LIST_SIZE {
+        return Err(Error::TooLarge);
+    }
+    Ok(contents)
+}
+
+/// Parse a list in hosts file format, or with one domain per line. Comments, and entries that are
+/// not fully qualified domains, such as `localhost`, are ignored.
+fn parse_filter_list(contents: &str) -> Vec<String> {
+    let mut domains = vec![];
+    for line in contents.lines() {
+        let line = line.split('#').next().unwrap_or_default();
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        let hostnames = match fields.split_first() {
+            // Hosts files map an address to one or more hostnames
+            Some((address, hostnames)) if address.parse::<IpAddr>().is_ok() => hostnames,
+            _ => &fields[..fields.len().min(1)],
+        };
+        domains.extend(
+            hostnames
+                .iter()
+                .map(|domain| domain.trim_end_matches('.'))
+                .filter(|domain| is_blockable(domain))
+                .map(|domain| domain.to_ascii_lowercase()),
+        );
+    }
+    domains
+}
+
+fn is_blockable(domain: &str) -> bool {
+    domain.contains('.')
+        && domain != "localhost.localdomain"
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+impl Daemon {
+    /// Load the filter lists of the current settings.
+    pub(crate) fn update_dns_filter_lists(&self) {
+        let sources = self
+            .settings
+            .tunnel_options
+            .dns_options
+            .filter_lists
+            .clone();
+        self.dns_filter.set_sources(sources);
+    }
+
+    pub(crate) fn on_dns_blocklist(&mut self, blocklist: Arc<DomainBlocklist>) {
+        self.dns_blocklist = blocklist;
+        let (tx, _rx) = oneshot::channel();
+        self.send_tunnel_command(TunnelCommand::Dns(self.dns_config(), tx));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_filter_list;
+
+    #[test]
+    fn test_parse_filter_list() {
+        let list = "\
+# Hosts file
+127.0.0.1 localhost localhost.localdomain
+::1 ip6-localhost
+0.0.0.0 ads.example.com tracker.example.com # inline comment
+0.0.0.0 invalid..example.com
+
+# Domain list
+Malware.Example.org.
+  spaced.example.net
+*.wildcard.example
+";
+        assert_eq!(
+            parse_filter_list(list),
+            [
+                "ads.example.com",
+                "tracker.example.com",
+                "malware.example.org",
+                "spaced.example.net",
+            ]
+        );
+    }
+}
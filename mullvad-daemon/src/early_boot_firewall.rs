@@ -1,5 +1,7 @@
 use mullvad_daemon::settings::{self, SettingsPersister};
-use talpid_core::firewall::{self, Firewall, FirewallPolicy};
+use mullvad_types::{lan_sharing::AllowedLan, settings::Settings};
+use talpid_core::firewall::{self, Firewall, FirewallPolicy, NftablesOptions};
+use talpid_types::split_tunnel::SplitTunnelMode;
 
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -14,25 +16,60 @@ pub enum Error {
 }
 
 pub async fn initialize_firewall() -> Result<(), Error> {
-    let mut firewall = Firewall::new(mullvad_types::TUNNEL_FWMARK)?;
-    let allow_lan = get_allow_lan().await.unwrap_or_else(|err| {
-        log::info!(
-            "Not allowing LAN traffic due to failing to read settings: {}",
-            err
-        );
-        false
-    });
+    let (
+        allow_lan,
+        exceptions,
+        allowed_incoming,
+        block_ipv6,
+        split_tunnel_mode,
+        split_tunnel_owners,
+        fwmark,
+        nftables,
+    ) = match get_settings().await {
+        Ok(settings) => (
+            settings.allow_lan.clone(),
+            settings.firewall_exceptions.clone(),
+            settings.allowed_incoming.clone(),
+            settings.tunnel_options.generic.block_ipv6,
+            settings.split_tunnel_mode,
+            settings.split_tunnel_owners.clone(),
+            settings.tunnel_fwmark(),
+            settings::nftables_options(&settings),
+        ),
+        Err(err) => {
+            log::info!(
+                "Not allowing LAN traffic due to failing to read settings: {}",
+                err
+            );
+            (
+                AllowedLan::Blocked,
+                vec![],
+                vec![],
+                false,
+                SplitTunnelMode::default(),
+                vec![],
+                mullvad_types::TUNNEL_FWMARK,
+                NftablesOptions::default(),
+            )
+        }
+    };
+    let mut firewall = Firewall::new(fwmark, nftables)?;
     let policy = FirewallPolicy::Blocked {
         allow_lan,
         allowed_endpoint: None,
+        exceptions,
+        allowed_incoming,
+        block_ipv6,
+        split_tunnel_mode,
+        split_tunnel_owners,
     };
     log::info!("Applying firewall policy {policy}");
     firewall.apply_policy(policy)?;
     Ok(())
 }
 
-async fn get_allow_lan() -> Result<bool, Error> {
+async fn get_settings() -> Result<Settings, Error> {
     let path = mullvad_paths::settings_dir()?;
     let settings = SettingsPersister::load(&path).await;
-    Ok(settings.allow_lan)
+    Ok(settings.to_settings())
 }
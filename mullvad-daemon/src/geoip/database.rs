@@ -0,0 +1,186 @@
+//! Compact GeoIP database used to locate an IP address when am.i.mullvad.net cannot be reached.
+//!
+//! The database is a text file where each line describes an address range:
+//! `<first address>,<last address>,<country>,<city>,<latitude>,<longitude>`. The city may be left
+//! empty. Empty lines and lines starting with `#` are ignored.
+
+use std::{net::IpAddr, path::Path};
+use talpid_types::ErrorExt;
+
+/// Name of the GeoIP database file. A database in the cache directory takes precedence over the
+/// one bundled in the resource directory.
+pub const GEOIP_FILENAME: &str = "geoip.csv";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to read GeoIP database")]
+    Read(#[source] std::io::Error),
+
+    #[error("Invalid GeoIP database entry on line {0}")]
+    Parse(usize),
+}
+
+/// Location of an address range in the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub country: String,
+    pub city: Option<String>,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    first: u128,
+    last: u128,
+    location: Location,
+}
+
+#[derive(Debug)]
+pub struct GeoIpDatabase {
+    /// Non-overlapping ranges, sorted by their first address.
+    entries: Vec<Entry>,
+}
+
+impl GeoIpDatabase {
+    /// Load the database from `cache_dir`, or from `resource_dir` if there is none in the cache.
+    /// Returns `None` if neither contains a valid database.
+    pub async fn load(resource_dir: &Path, cache_dir: &Path) -> Option<Self> {
+        for path in [
+            cache_dir.join(GEOIP_FILENAME),
+            resource_dir.join(GEOIP_FILENAME),
+        ] {
+            match Self::from_file(&path).await {
+                Ok(database) => {
+                    log::debug!(
+                        "Loaded {} GeoIP ranges from {}",
+                        database.entries.len(),
+                        path.display()
+                    );
+                    return Some(database);
+                }
+                Err(Error::Read(error)) if error.kind() == std::io::ErrorKind::NotFound => (),
+                Err(error) => {
+                    log::warn!(
+                        "{}",
+                        error.display_chain_with_msg(&format!(
+                            "Failed to load GeoIP database from {}",
+                            path.display()
+                        ))
+                    );
+                }
+            }
+        }
+        None
+    }
+
+    async fn from_file(path: &Path) -> Result<Self, Error> {
+        let contents = tokio::fs::read_to_string(path).await.map_err(Error::Read)?;
+        Self::parse(&contents)
+    }
+
+    fn parse(contents: &str) -> Result<Self, Error> {
+        let mut entries = contents
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(line_number, line)| parse_entry(line).ok_or(Error::Parse(line_number)))
+            .collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.first);
+        Ok(Self { entries })
+    }
+
+    /// Return the location of `address`, if it is in the database.
+    pub fn lookup(&self, address: IpAddr) -> Option<&Location> {
+        let address = to_u128(address);
+        let index = self
+            .entries
+            .partition_point(|entry| entry.first <= address)
+            .checked_sub(1)?;
+        let entry = &self.entries[index];
+        (address <= entry.last).then_some(&entry.location)
+    }
+}
+
+fn parse_entry(line: &str) -> Option<Entry> {
+    let mut fields = line.split(',').map(str::trim);
+    let first: IpAddr = fields.next()?.parse().ok()?;
+    let last: IpAddr = fields.next()?.parse().ok()?;
+    if first.is_ipv4() != last.is_ipv4() {
+        return None;
+    }
+    let country = fields.next().filter(|country| !country.is_empty())?;
+    let city = fields.next()?;
+    let latitude = fields.next()?.parse().ok()?;
+    let longitude = fields.next()?.parse().ok()?;
+    if fields.next().is_some() {
+        return None;
+    }
+
+    let (first, last) = (to_u128(first), to_u128(last));
+    (first <= last).then(|| Entry {
+        first,
+        last,
+        location: Location {
+            country: country.to_owned(),
+            city: (!city.is_empty()).then(|| city.to_owned()),
+            latitude,
+            longitude,
+        },
+    })
+}
+
+/// Map IPv4 addresses to IPv4-mapped IPv6 addresses so that both can be kept in the same list.
+fn to_u128(address: IpAddr) -> u128 {
+    match address {
+        IpAddr::V4(address) => u128::from(address.to_ipv6_mapped()),
+        IpAddr::V6(address) => u128::from(address),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const DATABASE: &str = "
+# first,last,country,city,latitude,longitude
+193.138.218.0,193.138.218.255,Sweden,Gothenburg,57.70887,11.97456
+2a03:1b20::,2a03:1b20:ffff:ffff:ffff:ffff:ffff:ffff,Sweden,,59.3289,18.0649
+1.0.0.0,1.0.0.255,Australia,Sydney,-33.8688,151.2093
+";
+
+    #[test]
+    fn test_lookup() {
+        let database = GeoIpDatabase::parse(DATABASE).unwrap();
+
+        let location = database.lookup("193.138.218.74".parse().unwrap()).unwrap();
+        assert_eq!(location.country, "Sweden");
+        assert_eq!(location.city.as_deref(), Some("Gothenburg"));
+
+        let location = database.lookup("2a03:1b20:1::1".parse().unwrap()).unwrap();
+        assert_eq!(location.country, "Sweden");
+        assert_eq!(location.city, None);
+
+        assert_eq!(
+            database
+                .lookup("1.0.0.0".parse().unwrap())
+                .map(|location| location.country.as_str()),
+            Some("Australia")
+        );
+        assert!(database.lookup("1.0.1.0".parse().unwrap()).is_none());
+        assert!(database.lookup("::1.0.0.1".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_invalid_entry() {
+        assert!(matches!(
+            GeoIpDatabase::parse("1.0.0.0,::1,Australia,Sydney,-33.8688,151.2093"),
+            Err(Error::Parse(1))
+        ));
+        assert!(matches!(
+            GeoIpDatabase::parse("\n1.0.0.255,1.0.0.0,Australia,Sydney,-33.8688,151.2093"),
+            Err(Error::Parse(2))
+        ));
+    }
+}
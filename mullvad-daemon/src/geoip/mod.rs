@@ -1,15 +1,22 @@
-use std::time::Duration;
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
 
 use futures::join;
 use mullvad_api::rest::{Error, RequestServiceHandle};
 use mullvad_types::location::{AmIMullvad, GeoIpLocation, LocationEventData};
-use std::sync::LazyLock;
 use talpid_core::mpsc::Sender;
 use talpid_future::retry::{retry_future, ExponentialBackoff, Jittered};
 use talpid_types::ErrorExt;
 
 use crate::{DaemonEventSender, InternalDaemonEvent};
 
+mod database;
+
+pub use database::GeoIpDatabase;
+
 // Define the Mullvad connection checking api endpoint.
 //
 // In a development build the host name for the connection checking endpoint can
@@ -42,6 +49,71 @@ static MULLVAD_CONNCHECK_HOST: LazyLock<String> = LazyLock::new(|| {
 const LOCATION_RETRY_STRATEGY: Jittered<ExponentialBackoff> =
     Jittered::jitter(ExponentialBackoff::new(Duration::from_secs(1), 4));
 
+/// Publicly routable addresses used to find the source address of the default route. Nothing is
+/// sent to them.
+const PUBLIC_IPV4: Ipv4Addr = Ipv4Addr::new(194, 242, 2, 2);
+const PUBLIC_IPV6: Ipv6Addr = Ipv6Addr::new(0x2a07, 0xe340, 0, 0, 0, 0, 0, 2);
+
+/// What is known about the location without asking am.i.mullvad.net. Used to determine the
+/// location locally if am.i.mullvad.net cannot be reached.
+#[derive(Debug, Clone)]
+pub(crate) enum LocationFallback {
+    /// Connected through a relay. Its address is used as the exit IP, and its registered location
+    /// is used if there is one.
+    Relay {
+        exit_ip: IpAddr,
+        location: Option<GeoIpLocation>,
+    },
+    /// Not connected through a relay. The exit IP is assumed to be the source address of the
+    /// default route, if that address is public.
+    Direct,
+}
+
+impl LocationFallback {
+    async fn resolve(
+        self,
+        database: Option<&GeoIpDatabase>,
+        use_ipv6: bool,
+    ) -> Option<GeoIpLocation> {
+        match self {
+            LocationFallback::Relay { exit_ip, location } => {
+                let mut location = location.or_else(|| {
+                    let mut location = locate(database?, exit_ip)?;
+                    location.mullvad_exit_ip = true;
+                    Some(location)
+                })?;
+                match exit_ip {
+                    IpAddr::V4(v4) => location.ipv4 = Some(v4),
+                    IpAddr::V6(v6) => location.ipv6 = Some(v6),
+                }
+                Some(location)
+            }
+            LocationFallback::Direct => {
+                let database = database?;
+                let ipv4 = detect_public_address(IpAddr::V4(PUBLIC_IPV4)).await;
+                let ipv6 = if use_ipv6 {
+                    detect_public_address(IpAddr::V6(PUBLIC_IPV6)).await
+                } else {
+                    None
+                };
+                let mut location = [ipv4, ipv6]
+                    .into_iter()
+                    .flatten()
+                    .find_map(|ip| locate(database, ip))?;
+                location.ipv4 = ipv4.and_then(|ip| match ip {
+                    IpAddr::V4(v4) => Some(v4),
+                    IpAddr::V6(_) => None,
+                });
+                location.ipv6 = ipv6.and_then(|ip| match ip {
+                    IpAddr::V6(v6) => Some(v6),
+                    IpAddr::V4(_) => None,
+                });
+                Some(location)
+            }
+        }
+    }
+}
+
 /// Handler for request to am.i.mullvad.net, manages in-flight request and validity of responses.
 pub(crate) struct GeoIpHandler {
     /// Unique ID for each request. If the ID attached to the
@@ -53,18 +125,24 @@ pub(crate) struct GeoIpHandler {
 }
 
 impl GeoIpHandler {
-    pub fn new(rest_service: RequestServiceHandle, location_sender: DaemonEventSender) -> Self {
+    pub fn new(
+        rest_service: RequestServiceHandle,
+        location_sender: DaemonEventSender,
+        database: Option<GeoIpDatabase>,
+    ) -> Self {
         Self {
             request_id: 0,
             rest_service,
             location_sender,
+            database: database.map(Arc::new),
         }
     }
 
     /// Send a location request to am.i.mullvad.net. When it arrives, send an
     /// [`InternalDaemonEvent::LocationEvent`], which triggers an update of the current
-    /// tunnel state with the `ipv4` and/or `ipv6` fields filled in.
-    pub fn send_geo_location_request(&mut self, use_ipv6: bool) {
+    /// tunnel state with the `ipv4` and/or `ipv6` fields filled in. If the request fails, the
+    /// location is determined locally from `fallback` instead.
+    pub fn send_geo_location_request(&mut self, use_ipv6: bool, fallback: LocationFallback) {
         // Increment request ID
         self.request_id = self.request_id.wrapping_add(1);
 
@@ -73,8 +151,21 @@ impl GeoIpHandler {
         let request_id = self.request_id;
         let rest_service = self.rest_service.clone();
         let location_sender = self.location_sender.clone();
+        let database = self.database.clone();
         tokio::spawn(async move {
-            if let Ok(location) = get_geo_location_with_retry(use_ipv6, rest_service).await {
+            let location = match get_geo_location_with_retry(use_ipv6, rest_service).await {
+                Ok(location) => Some(location),
+                Err(error) => {
+                    log::debug!(
+                        "{}",
+                        error.display_chain_with_msg(
+                            "Unable to fetch GeoIP location. Determining it locally"
+                        )
+                    );
+                    fallback.resolve(database.as_deref(), use_ipv6).await
+                }
+            };
+            if let Some(location) = location {
                 let _ =
                     location_sender.send(InternalDaemonEvent::LocationEvent(LocationEventData {
                         request_id,
@@ -158,6 +249,59 @@ async fn send_location_request_internal(
     future_service.request(request).await?.deserialize().await
 }
 
+/// Look up `ip` in the local database.
+fn locate(database: &GeoIpDatabase, ip: IpAddr) -> Option<GeoIpLocation> {
+    let location = database.lookup(ip)?;
+    Some(GeoIpLocation {
+        ipv4: None,
+        ipv6: None,
+        country: location.country.clone(),
+        city: location.city.clone(),
+        latitude: location.latitude,
+        longitude: location.longitude,
+        mullvad_exit_ip: false,
+        hostname: None,
+        bridge_hostname: None,
+        entry_hostname: None,
+        obfuscator_hostname: None,
+    })
+}
+
+/// Return the source address that would be used to reach `destination`, if it is public.
+async fn detect_public_address(destination: IpAddr) -> Option<IpAddr> {
+    let bind_addr = match destination {
+        IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    };
+    let socket = tokio::net::UdpSocket::bind(SocketAddr::new(bind_addr, 0))
+        .await
+        .ok()?;
+    // Connecting a UDP socket only selects a route, so nothing is sent
+    socket
+        .connect(SocketAddr::new(destination, 53))
+        .await
+        .ok()?;
+    let address = socket.local_addr().ok()?.ip();
+    is_public(address).then_some(address)
+}
+
+fn is_public(address: IpAddr) -> bool {
+    match address {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            let is_shared = a == 100 && (b & 0b1100_0000) == 64;
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_documentation()
+                || is_shared)
+        }
+        // Global unicast addresses are in 2000::/3
+        IpAddr::V6(v6) => (v6.segments()[0] & 0xe000) == 0x2000,
+    }
+}
+
 fn log_network_error(err: Error, version: &'static str) {
     if !err.is_offline() {
         let err_message = &format!("Unable to fetch {version} GeoIP location");
@@ -14,6 +14,9 @@ pub struct LeakChecker {
 struct Task {
     events_rx: mpsc::UnboundedReceiver<TaskEvent>,
     route_manager: RouteManagerHandle,
+    /// Firewall mark of tunnel traffic, used to find the route for traffic outside the tunnel
+    #[cfg(target_os = "linux")]
+    fwmark: u32,
     callbacks: Vec<Box<dyn LeakCheckerCallback>>,
 }
 
@@ -36,12 +39,14 @@ pub trait LeakCheckerCallback: Send + 'static {
 }
 
 impl LeakChecker {
-    pub fn new(route_manager: RouteManagerHandle) -> Self {
+    pub fn new(route_manager: RouteManagerHandle, #[cfg(target_os = "linux")] fwmark: u32) -> Self {
         let (task_event_tx, events_rx) = mpsc::unbounded_channel();
 
         let task = Task {
             events_rx,
             route_manager,
+            #[cfg(target_os = "linux")]
+            fwmark,
             callbacks: vec![],
         };
 
@@ -88,17 +93,25 @@ impl Task {
 
     async fn on_new_tunnel_state(&mut self, mut tunnel_state: TunnelStateTransition) {
         'leak_test: loop {
-            let TunnelStateTransition::Connected(tunnel) = &tunnel_state else {
+            let TunnelStateTransition::Connected(tunnel, _) = &tunnel_state else {
                 break 'leak_test;
             };
 
             let ping_destination = tunnel.endpoint;
             let route_manager = self.route_manager.clone();
+            #[cfg(target_os = "linux")]
+            let fwmark = self.fwmark;
             let leak_test = async {
                 // Give the connection a little time to settle before starting the test.
                 tokio::time::sleep(Duration::from_millis(5000)).await;
 
-                check_for_leaks(&route_manager, ping_destination).await
+                check_for_leaks(
+                    &route_manager,
+                    ping_destination,
+                    #[cfg(target_os = "linux")]
+                    fwmark,
+                )
+                .await
             };
 
             // Make sure the tunnel state doesn't change while we're doing the leak test.
@@ -166,6 +179,7 @@ async fn check_for_leaks(
 async fn check_for_leaks(
     route_manager: &RouteManagerHandle,
     destination: Endpoint,
+    #[cfg(target_os = "linux")] fwmark: u32,
 ) -> anyhow::Result<Option<LeakInfo>> {
     use anyhow::{anyhow, Context};
     use mullvad_leak_checker::{traceroute::TracerouteOpt, LeakStatus};
@@ -174,7 +188,7 @@ async fn check_for_leaks(
     let interface = {
         // By setting FWMARK, we are effectively getting the same route as when using split tunneling.
         let route = route_manager
-            .get_destination_route(destination.address.ip(), Some(mullvad_types::TUNNEL_FWMARK))
+            .get_destination_route(destination.address.ip(), Some(fwmark))
             .await
             .context("Failed to get route to relay")?
             .ok_or(anyhow!("No route to relay"))?;
@@ -7,25 +7,48 @@ mod android_dns;
 mod api;
 mod api_address_updater;
 #[cfg(not(target_os = "android"))]
+mod captive_portal;
+#[cfg(not(target_os = "android"))]
 mod cleanup;
+#[cfg(target_os = "macos")]
+mod conflicting_software;
 mod custom_list;
+mod custom_relay;
+mod data_usage;
+#[cfg(target_os = "linux")]
+mod dbus_service;
 pub mod device;
 mod dns;
+#[cfg(not(target_os = "android"))]
+mod dns_filter;
 pub mod exception_logging;
 mod geoip;
 mod leak_checker;
+#[cfg(not(target_os = "android"))]
+mod lockdown_pause;
 pub mod logging;
 #[cfg(target_os = "macos")]
 mod macos;
 pub mod management_interface;
+#[cfg(not(target_os = "android"))]
+mod metrics;
 mod migrations;
+mod obfuscation_memory;
+mod profile;
+mod relay_latency;
 mod relay_list;
+mod request_queue;
 #[cfg(not(target_os = "android"))]
 pub mod rpc_uniqueness_check;
 pub mod runtime;
 pub mod settings;
 pub mod shutdown;
+#[cfg(not(target_os = "android"))]
+mod state_hooks;
+#[cfg(target_os = "linux")]
+mod systemd_notify;
 mod target_state;
+mod trusted_networks;
 mod tunnel;
 pub mod version;
 mod version_check;
@@ -38,7 +61,7 @@ use futures::{
     future::{abortable, AbortHandle, Future},
     StreamExt,
 };
-use geoip::GeoIpHandler;
+use geoip::{GeoIpDatabase, GeoIpHandler, LocationFallback};
 use leak_checker::{LeakChecker, LeakInfo};
 use management_interface::ManagementInterfaceServer;
 use mullvad_api::{access_mode::AccessMethodEvent, proxy::ApiConnectionMode, ApiEndpoint};
@@ -46,27 +69,37 @@ use mullvad_encrypted_dns_proxy::state::EncryptedDnsProxyState;
 use mullvad_relay_selector::{RelaySelector, SelectorConfig};
 #[cfg(target_os = "android")]
 use mullvad_types::account::{PlayPurchase, PlayPurchasePaymentToken};
+#[cfg(target_os = "macos")]
+use mullvad_types::conflicting_software::ConflictingSoftware;
 #[cfg(any(windows, target_os = "android", target_os = "macos"))]
 use mullvad_types::settings::SplitApp;
 #[cfg(daita)]
 use mullvad_types::wireguard::DaitaSettings;
 use mullvad_types::{
-    access_method::{AccessMethod, AccessMethodSetting},
+    access_method::{AccessMethod, AccessMethodSetting, AccessMethodStats},
     account::{AccountData, AccountNumber, VoucherSubmission},
     auth_failed::AuthFailed,
     custom_list::CustomList,
-    device::{Device, DeviceEvent, DeviceEventCause, DeviceId, DeviceState, RemoveDeviceEvent},
+    data_usage::DataUsage,
+    device::{
+        Device, DeviceEvent, DeviceEventCause, DeviceId, DeviceRemovalFailure, DeviceRemovalResult,
+        DeviceState, RemoveDeviceEvent,
+    },
     features::{compute_feature_indicators, FeatureIndicator, FeatureIndicators},
     location::{GeoIpLocation, LocationEventData},
+    pending_request::{PendingRequest, PendingRequestId, PendingRequestKind},
     relay_constraints::{
         BridgeSettings, BridgeState, BridgeType, ObfuscationSettings, RelayOverride, RelaySettings,
+        SelectedObfuscation,
     },
     relay_list::RelayList,
     settings::{DnsOptions, Settings},
     states::{Secured, TargetState, TargetStateStrict, TunnelState},
+    trusted_network::{Network, NetworkMatch, TrustedNetworkRule, TrustedNetworks},
     version::{AppVersion, AppVersionInfo},
     wireguard::{PublicKey, QuantumResistantState, RotationInterval},
 };
+use relay_latency::RelayLatencyMonitorHandle;
 use relay_list::{RelayListUpdater, RelayListUpdaterHandle, RELAYS_FILENAME};
 use settings::SettingsPersister;
 #[cfg(any(windows, target_os = "android", target_os = "macos"))]
@@ -74,13 +107,17 @@ use std::collections::HashSet;
 #[cfg(target_os = "android")]
 use std::os::unix::io::RawFd;
 use std::{
+    collections::HashMap,
     marker::PhantomData,
     path::PathBuf,
     pin::Pin,
     sync::{Arc, Weak},
     time::Duration,
 };
+#[cfg(not(target_os = "android"))]
+use talpid_core::dns::DomainBlocklist;
 use talpid_core::{
+    dns::DnsConfig,
     mpsc::Sender,
     split_tunnel,
     tunnel_state_machine::{self, TunnelCommand, TunnelStateMachineHandle},
@@ -88,11 +125,26 @@ use talpid_core::{
 use talpid_routing::RouteManagerHandle;
 #[cfg(target_os = "android")]
 use talpid_types::android::AndroidContext;
+#[cfg(target_os = "linux")]
+use talpid_types::{
+    dns::{DnsBackend, DnsInterference},
+    split_tunnel::{SplitTunnelMode, SplitTunnelOwner},
+};
 #[cfg(target_os = "windows")]
-use talpid_types::split_tunnel::ExcludedProcess;
 use talpid_types::{
-    net::{IpVersion, TunnelType},
-    tunnel::{ErrorStateCause, TunnelStateTransition},
+    drivers::{Driver, DriverRepairResult, DriverStatus},
+    split_tunnel::ExcludedProcess,
+};
+use talpid_types::{
+    net::{
+        wireguard::{
+            ConnectivityCheckOptions, NegotiationRetry, NegotiationRetryPolicy, QuantumResistantKem,
+        },
+        Connectivity, IpVersion, TunnelType,
+    },
+    tunnel::{
+        ConnectionTimings, ErrorStateCause, TrafficStats, TunnelDiagnostics, TunnelStateTransition,
+    },
     ErrorExt,
 };
 use tokio::io;
@@ -103,6 +155,9 @@ use talpid_core::connectivity_listener::ConnectivityListener;
 /// Delay between generating a new WireGuard key and reconnecting
 const WG_RECONNECT_DELAY: Duration = Duration::from_secs(4 * 60);
 
+/// Delay between entering the error state because the relay stopped responding and reconnecting
+const RELAY_NOT_RESPONDING_RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
 pub type ResponseTx<T, E> = oneshot::Sender<Result<T, E>>;
 
 #[derive(thiserror::Error, Debug)]
@@ -155,6 +210,9 @@ pub enum Error {
     #[error("Failed to submit voucher")]
     VoucherSubmission(#[source] device::Error),
 
+    #[error("Failed to update the queue of pending requests")]
+    RequestQueue(#[source] request_queue::Error),
+
     #[cfg(target_os = "linux")]
     #[error("Unable to initialize split tunneling")]
     InitSplitTunneling(#[source] split_tunnel::Error),
@@ -163,6 +221,18 @@ pub enum Error {
     #[error("Split tunneling error")]
     SplitTunnelError(#[source] split_tunnel::Error),
 
+    #[cfg(windows)]
+    #[error("Driver diagnostics failed")]
+    DriverError(#[source] talpid_core::drivers::Error),
+
+    #[cfg(windows)]
+    #[error("Drivers cannot be repaired while the tunnel is in use")]
+    DriverInUse,
+
+    #[cfg(windows)]
+    #[error("WFP diagnostics failed")]
+    WfpDiagnosticsError(#[source] talpid_core::firewall::Error),
+
     #[error("An account is already set")]
     AlreadyLoggedIn,
 
@@ -193,6 +263,96 @@ pub enum Error {
     #[error("Custom list error: {0}")]
     CustomListError(#[source] mullvad_types::custom_list::Error),
 
+    #[error("Custom relay error: {0}")]
+    CustomRelayError(#[source] mullvad_types::custom_relay::Error),
+
+    #[error("Profile error: {0}")]
+    ProfileError(#[source] mullvad_types::profile::Error),
+
+    #[cfg(not(target_os = "android"))]
+    #[error("Invalid state hook")]
+    StateHookError(#[source] state_hooks::Error),
+
+    #[cfg(not(target_os = "android"))]
+    #[error("Metrics server error")]
+    MetricsError(#[source] metrics::Error),
+
+    #[cfg(not(target_os = "android"))]
+    #[error("Failed to pause lockdown mode")]
+    LockdownPauseError(#[source] lockdown_pause::Error),
+
+    #[cfg(not(target_os = "android"))]
+    #[error("Failed to allow traffic to the captive portal")]
+    CaptivePortalError(#[source] captive_portal::Error),
+
+    #[cfg(target_os = "linux")]
+    #[error(
+        "The nftables priority {0} is not between {min} and {max}",
+        min = mullvad_types::settings::NftablesSettings::MIN_PRIORITY,
+        max = mullvad_types::settings::NftablesSettings::MAX_PRIORITY
+    )]
+    InvalidNftablesPriority(i32),
+
+    #[cfg(target_os = "linux")]
+    #[error("The routing table {0} is reserved")]
+    InvalidRoutingTable(u32),
+
+    #[cfg(target_os = "linux")]
+    #[error(
+        "The rule priority {0} is not between {min} and {max}",
+        min = mullvad_types::settings::RoutingSettings::MIN_RULE_PRIORITY,
+        max = mullvad_types::settings::RoutingSettings::MAX_RULE_PRIORITY
+    )]
+    InvalidRulePriority(u32),
+
+    #[cfg(target_os = "macos")]
+    #[error("Invalid PF anchor: {0}")]
+    InvalidPfAnchor(String),
+
+    #[cfg(target_os = "linux")]
+    #[error("A DNS backend was listed more than once")]
+    InvalidDnsBackendOrder,
+
+    #[cfg(target_os = "linux")]
+    #[error("Failed to determine the DNS backend")]
+    DnsBackendError(#[source] talpid_core::dns::Error),
+
+    #[cfg(target_os = "macos")]
+    #[error("PF diagnostics failed")]
+    PfDiagnosticsError(#[source] io::Error),
+
+    #[error("Invalid LAN sharing settings")]
+    LanSharingError(#[source] mullvad_types::lan_sharing::Error),
+
+    #[error("IPv6 cannot be blocked while the IPv6-only tunnel option is enabled")]
+    BlockIpv6WithIpv6Only,
+
+    #[error("Invalid split DNS rules")]
+    SplitDnsError(#[source] mullvad_types::split_dns::Error),
+
+    #[error("Invalid DNS filter lists")]
+    DnsFilterError(#[source] mullvad_types::dns_filter::Error),
+
+    #[cfg(not(target_os = "android"))]
+    #[error("Invalid firewall exceptions")]
+    FirewallExceptionError(#[source] mullvad_types::firewall_exception::Error),
+
+    #[cfg(not(target_os = "android"))]
+    #[error("Invalid custom routes")]
+    CustomRouteError(#[source] mullvad_types::custom_route::Error),
+
+    #[cfg(not(target_os = "android"))]
+    #[error("Invalid incoming connection rules")]
+    AllowedIncomingError(#[source] mullvad_types::allowed_incoming::Error),
+
+    #[cfg(target_os = "linux")]
+    #[error("Invalid split tunnel users and groups")]
+    SplitTunnelOwnerError(#[source] mullvad_types::split_tunnel_owner::Error),
+
+    #[cfg(target_os = "linux")]
+    #[error("Invalid split tunnel domains")]
+    SplitTunnelDomainError(#[source] mullvad_types::split_tunnel_domain::Error),
+
     #[error("Access method error")]
     AccessMethodError(#[source] access_method::Error),
 
@@ -220,8 +380,18 @@ pub enum DaemonCommand {
     SetTargetState(oneshot::Sender<bool>, TargetState),
     /// Reconnect the tunnel, if one is connecting/connected.
     Reconnect(oneshot::Sender<bool>),
+    /// Tear down the tunnel but keep blocking all traffic until the target state is set again.
+    Pause(oneshot::Sender<bool>),
     /// Request the current state.
     GetState(oneshot::Sender<TunnelState>),
+    /// Request the time spent in each phase of establishing the most recent connection.
+    GetConnectionTimings(oneshot::Sender<Option<ConnectionTimings>>),
+    /// Request the traffic statistics of the current tunnel.
+    GetTrafficStats(oneshot::Sender<Option<TrafficStats>>),
+    /// Describe the tunnel configuration that is currently applied
+    GetTunnelDiagnostics(oneshot::Sender<TunnelDiagnostics>),
+    /// Request how much data has gone through the tunnel each month.
+    GetDataUsage(ResponseTx<DataUsage, data_usage::Error>),
     CreateNewAccount(ResponseTx<String, Error>),
     /// Request the metadata for an account.
     GetAccountData(
@@ -241,6 +411,17 @@ pub enum DaemonCommand {
     /// Trigger an asynchronous relay list update. This returns before the relay list is actually
     /// updated.
     UpdateRelayLocations,
+    /// Trigger a relay list update and wait until it has completed or failed.
+    UpdateRelayLocationsAndWait(ResponseTx<(), relay_list::Error>),
+    /// Set how often to update the relay list, in minutes
+    SetRelayListUpdateInterval(ResponseTx<(), settings::Error>, u16),
+    /// Serialize the relay list along with metadata, so that it can be imported on another machine
+    ExportRelayList(ResponseTx<String, relay_list::export::Error>),
+    /// Replace the relay list with one created by `ExportRelayList`
+    ImportRelayList(ResponseTx<(), relay_list::export::Error>, String),
+    /// Read the relay list overrides file again and apply it. Returns the overrides that could not
+    /// be applied.
+    ReloadRelayListOverrides(ResponseTx<Vec<String>, relay_list::Error>),
     /// Log in with a given account and create a new device.
     LoginAccount(ResponseTx<(), Error>, AccountNumber),
     /// Log out of the current account and remove the device, if they exist.
@@ -253,15 +434,73 @@ pub enum DaemonCommand {
     ListDevices(ResponseTx<Vec<Device>, Error>, AccountNumber),
     /// Remove device from a given account.
     RemoveDevice(ResponseTx<(), Error>, AccountNumber, DeviceId),
+    /// Remove several devices from a given account. If the flag is set, every device except the
+    /// current one is removed instead of the given devices.
+    RemoveDevices(
+        ResponseTx<DeviceRemovalResult, Error>,
+        AccountNumber,
+        Vec<DeviceId>,
+        bool,
+    ),
+    /// Queue a voucher submission for the current account, to be sent once the API is reachable
+    QueueVoucherSubmission(ResponseTx<PendingRequest, Error>, String),
+    /// Queue the removal of a device, to be sent once the API is reachable
+    QueueDeviceRemoval(ResponseTx<PendingRequest, Error>, AccountNumber, DeviceId),
+    /// Return all queued requests that have not been sent yet
+    GetPendingRequests(oneshot::Sender<Vec<PendingRequest>>),
+    /// Remove a queued request without sending it
+    CancelPendingRequest(ResponseTx<(), Error>, PendingRequestId),
     /// Place constraints on the type of tunnel and relay
     SetRelaySettings(ResponseTx<(), settings::Error>, RelaySettings),
-    /// Set the allow LAN setting.
-    SetAllowLan(ResponseTx<(), settings::Error>, bool),
+    /// Set which local networks may be reached while the tunnel is up or blocking.
+    SetAllowLan(
+        ResponseTx<(), Error>,
+        mullvad_types::lan_sharing::AllowedLan,
+    ),
     /// Set the beta program setting.
     SetShowBetaReleases(ResponseTx<(), settings::Error>, bool),
+    /// Set whether to measure relay latencies and prefer low-latency relays
+    SetPreferLowLatencyRelays(ResponseTx<(), settings::Error>, bool),
+    /// Set whether to keep using the relays that were last connected to
+    SetStickyRelays(ResponseTx<(), settings::Error>, bool),
     /// Set the block_when_disconnected setting.
     #[cfg(not(target_os = "android"))]
     SetBlockWhenDisconnected(ResponseTx<(), settings::Error>, bool),
+    /// Stop blocking traffic while disconnected for some time, even though lockdown mode is
+    /// enabled.
+    #[cfg(not(target_os = "android"))]
+    PauseLockdownMode(ResponseTx<(), Error>, Duration),
+    /// Allow traffic to the login page of a detected captive portal for some time.
+    #[cfg(not(target_os = "android"))]
+    AllowCaptivePortalLogin(ResponseTx<(), Error>, Duration),
+    /// Set whether all traffic should be blocked, regardless of the target state.
+    #[cfg(not(target_os = "android"))]
+    SetBlockAll(ResponseTx<(), settings::Error>, bool),
+    /// Set the traffic that is allowed outside the tunnel in every state
+    #[cfg(not(target_os = "android"))]
+    SetFirewallExceptions(
+        ResponseTx<(), Error>,
+        Vec<mullvad_types::firewall_exception::FirewallException>,
+    ),
+    /// Set the incoming connections that are allowed in every state
+    #[cfg(not(target_os = "android"))]
+    SetAllowedIncoming(
+        ResponseTx<(), Error>,
+        Vec<mullvad_types::allowed_incoming::AllowedIncoming>,
+    ),
+    /// Set or remove the executable to run when the tunnel enters a state
+    #[cfg(not(target_os = "android"))]
+    SetStateHook(
+        ResponseTx<(), Error>,
+        mullvad_types::state_hooks::HookEvent,
+        Option<PathBuf>,
+    ),
+    /// Enable or disable the metrics server, or change its port
+    #[cfg(not(target_os = "android"))]
+    SetMetricsSettings(
+        ResponseTx<(), Error>,
+        mullvad_types::settings::MetricsSettings,
+    ),
     /// Set the auto-connect setting.
     SetAutoConnect(ResponseTx<(), settings::Error>, bool),
     /// Set the mssfix argument for OpenVPN
@@ -272,8 +511,25 @@ pub enum DaemonCommand {
     SetBridgeState(ResponseTx<(), settings::Error>, BridgeState),
     /// Set if IPv6 should be enabled in the tunnel
     SetEnableIpv6(ResponseTx<(), settings::Error>, bool),
+    /// Set if only IPv6 should be used inside WireGuard tunnels
+    SetWireguardIpv6Only(ResponseTx<(), Error>, bool),
+    /// Set if all IPv6 traffic should be blocked by the firewall
+    #[cfg(not(target_os = "android"))]
+    SetBlockIpv6(ResponseTx<(), Error>, bool),
+    /// Set the routes that are installed through or around the tunnel
+    #[cfg(not(target_os = "android"))]
+    SetCustomRoutes(
+        ResponseTx<(), Error>,
+        Vec<mullvad_types::custom_route::CustomRoute>,
+    ),
     /// Set whether to enable PQ PSK exchange in the tunnel
     SetQuantumResistantTunnel(ResponseTx<(), settings::Error>, QuantumResistantState),
+    /// Set which KEMs the PQ PSK is derived from
+    SetQuantumResistantKem(ResponseTx<(), settings::Error>, QuantumResistantKem),
+    /// Set how ephemeral peer negotiation is retried
+    SetNegotiationRetryPolicy(ResponseTx<(), settings::Error>, NegotiationRetryPolicy),
+    /// Set how the connectivity of the tunnel is probed
+    SetConnectivityCheckOptions(ResponseTx<(), settings::Error>, ConnectivityCheckOptions),
     /// Set DAITA settings for the tunnel
     #[cfg(daita)]
     SetEnableDaita(ResponseTx<(), settings::Error>, bool),
@@ -282,7 +538,7 @@ pub enum DaemonCommand {
     #[cfg(daita)]
     SetDaitaSettings(ResponseTx<(), settings::Error>, DaitaSettings),
     /// Set DNS options or servers to use
-    SetDnsOptions(ResponseTx<(), settings::Error>, DnsOptions),
+    SetDnsOptions(ResponseTx<(), Error>, DnsOptions),
     /// Set override options to use for a given relay
     SetRelayOverride(ResponseTx<(), settings::Error>, RelayOverride),
     /// Remove all relay override options
@@ -290,6 +546,55 @@ pub enum DaemonCommand {
     /// Toggle macOS network check leak
     /// Set MTU for wireguard tunnels
     SetWireguardMtu(ResponseTx<(), settings::Error>, Option<u16>),
+    /// Set persistent keepalive interval for wireguard tunnels
+    SetWireguardPersistentKeepalive(ResponseTx<(), settings::Error>, Option<u16>),
+    /// Set the number of worker threads used by userspace wireguard
+    SetWireguardWorkerThreads(ResponseTx<(), settings::Error>, Option<u32>),
+    /// Set the interface that wireguard traffic is sent through
+    #[cfg(target_os = "linux")]
+    SetWireguardOutgoingInterface(ResponseTx<(), settings::Error>, Option<String>),
+    /// Select the wireguard implementation, or let the daemon pick one
+    #[cfg(target_os = "linux")]
+    SetWireguardBackend(
+        ResponseTx<(), settings::Error>,
+        Option<talpid_types::net::wireguard::Backend>,
+    ),
+    /// Set the firewall mark used for tunnel traffic. Applied when the daemon is restarted
+    #[cfg(target_os = "linux")]
+    SetTunnelFwmark(ResponseTx<(), settings::Error>, Option<u32>),
+    /// Set how the firewall rules are ordered relative to other nftables tables. Applied when the
+    /// daemon is restarted
+    #[cfg(target_os = "linux")]
+    SetNftablesSettings(
+        ResponseTx<(), Error>,
+        mullvad_types::settings::NftablesSettings,
+    ),
+    /// Set the routing table and rule priority used for policy routing. Applied when the daemon
+    /// is restarted
+    #[cfg(target_os = "linux")]
+    SetRoutingSettings(
+        ResponseTx<(), Error>,
+        mullvad_types::settings::RoutingSettings,
+    ),
+    /// Set the weight of the WFP sublayers that contain the firewall filters. Applied when the
+    /// daemon is restarted
+    #[cfg(windows)]
+    SetWfpSublayerWeight(ResponseTx<(), settings::Error>, Option<u16>),
+    /// Set the PF anchor that the firewall rules are installed in, or use the default anchor.
+    /// Applied when the daemon is restarted
+    #[cfg(target_os = "macos")]
+    SetPfAnchor(ResponseTx<(), Error>, Option<String>),
+    /// Set the order that ways of managing the system DNS configuration are tried in. Applied
+    /// when the daemon is restarted
+    #[cfg(target_os = "linux")]
+    SetDnsBackends(ResponseTx<(), Error>, Vec<DnsBackend>),
+    /// Set the mechanism used to detect whether the device is offline. Applied when the daemon is
+    /// restarted
+    #[cfg(not(target_os = "android"))]
+    SetOfflineMonitorBackend(
+        ResponseTx<(), settings::Error>,
+        talpid_types::net::OfflineMonitorBackend,
+    ),
     /// Set automatic key rotation interval for wireguard tunnels
     SetWireguardRotationInterval(ResponseTx<(), settings::Error>, Option<RotationInterval>),
     /// Get the daemon settings
@@ -308,6 +613,30 @@ pub enum DaemonCommand {
     UpdateCustomList(ResponseTx<(), Error>, CustomList),
     /// Remove all custom lists
     ClearCustomLists(ResponseTx<(), Error>),
+    /// Add a custom relay
+    AddCustomRelay(
+        ResponseTx<(), Error>,
+        mullvad_types::custom_relay::CustomRelay,
+    ),
+    /// Remove the custom relay with the given name
+    RemoveCustomRelay(ResponseTx<(), Error>, String),
+    /// Save the current settings as a named profile
+    SaveProfile(ResponseTx<(), Error>, String),
+    /// Remove the profile with the given name
+    RemoveProfile(ResponseTx<(), Error>, String),
+    /// Replace the current settings with the ones stored in the profile with the given name
+    UseProfile(ResponseTx<(), Error>, String),
+    /// Return the trusted network rules, and the network that the device is connected to
+    GetTrustedNetworks(oneshot::Sender<(TrustedNetworks, Option<Network>)>),
+    /// Add a trusted network rule, replacing any existing rule for the same networks
+    SetTrustedNetworkRule(ResponseTx<(), trusted_networks::Error>, TrustedNetworkRule),
+    /// Remove the trusted network rule for the given networks
+    RemoveTrustedNetworkRule(ResponseTx<(), trusted_networks::Error>, NetworkMatch),
+    /// Replace the locations that are excluded from relay selection
+    SetExcludedLocations(
+        ResponseTx<(), settings::Error>,
+        mullvad_types::excluded_locations::ExcludedLocations,
+    ),
     /// Add API access methods
     AddApiAccessMethod(
         ResponseTx<mullvad_types::access_method::Id, Error>,
@@ -325,6 +654,14 @@ pub enum DaemonCommand {
     ClearCustomApiAccessMethods(ResponseTx<(), Error>),
     /// Get the currently used API access method
     GetCurrentAccessMethod(ResponseTx<AccessMethodSetting, Error>),
+    /// Get statistics on how well each API access method has worked
+    GetApiAccessMethodStats(
+        ResponseTx<HashMap<mullvad_types::access_method::Id, AccessMethodStats>, Error>,
+    ),
+    /// Send API requests to the given endpoint, or to the endpoint the daemon was started with if
+    /// `None`
+    #[cfg(feature = "api-override")]
+    SetApiEndpoint(ResponseTx<(), Error>, Option<ApiEndpoint>),
     /// Test an API access method
     TestApiAccessMethodById(ResponseTx<bool, Error>, mullvad_types::access_method::Id),
     /// Test a custom API access method
@@ -353,6 +690,15 @@ pub enum DaemonCommand {
     /// Clear list of processes excluded from the tunnel
     #[cfg(target_os = "linux")]
     ClearSplitTunnelProcesses(ResponseTx<(), split_tunnel::Error>),
+    /// Set whether split processes bypass the tunnel, or are the only ones that use it
+    #[cfg(target_os = "linux")]
+    SetSplitTunnelMode(ResponseTx<(), settings::Error>, SplitTunnelMode),
+    /// Set the users and groups whose processes are split from the tunnel
+    #[cfg(target_os = "linux")]
+    SetSplitTunnelOwners(ResponseTx<(), Error>, Vec<SplitTunnelOwner>),
+    /// Set the domains whose traffic is split from the tunnel
+    #[cfg(target_os = "linux")]
+    SetSplitTunnelDomains(ResponseTx<(), Error>, Vec<String>),
     /// Exclude traffic of an application from the tunnel
     #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     AddSplitTunnelApp(ResponseTx<(), Error>, SplitApp),
@@ -371,6 +717,24 @@ pub enum DaemonCommand {
     /// Notify the split tunnel monitor that a volume was mounted or dismounted
     #[cfg(target_os = "windows")]
     CheckVolumes(ResponseTx<(), Error>),
+    /// Return software that was last detected to interfere with the tunnel
+    #[cfg(target_os = "macos")]
+    GetConflictingSoftware(oneshot::Sender<Vec<ConflictingSoftware>>),
+    /// Return the state of the kernel drivers used by the daemon
+    #[cfg(windows)]
+    GetDriverStatus(ResponseTx<Vec<DriverStatus>, Error>),
+    /// Remove a kernel driver so that it is reinstalled the next time it is needed
+    #[cfg(windows)]
+    RepairDriver(ResponseTx<DriverRepairResult, Error>, Driver),
+    /// Describe the installed WFP sublayers and filters
+    #[cfg(windows)]
+    GetWfpDiagnostics(ResponseTx<String, Error>),
+    /// Describe the rules in the PF anchor used by the firewall
+    #[cfg(target_os = "macos")]
+    GetPfDiagnostics(ResponseTx<String, Error>),
+    /// Return the way that the system DNS configuration is managed
+    #[cfg(target_os = "linux")]
+    GetActiveDnsBackend(ResponseTx<DnsBackend, Error>),
     /// Register settings for WireGuard obfuscator
     SetObfuscationSettings(ResponseTx<(), settings::Error>, ObfuscationSettings),
     /// Saves the target tunnel state and enters a blocking state. The state is restored
@@ -390,6 +754,10 @@ pub enum DaemonCommand {
     ApplyJsonSettings(ResponseTx<(), settings::patch::Error>, String),
     /// Return a JSON blob containing all overridable settings, if there are any
     ExportJsonSettings(ResponseTx<String, settings::patch::Error>),
+    /// Replace the settings with ones created by `ExportSettings`
+    ImportSettings(ResponseTx<(), settings::export::Error>, String),
+    /// Serialize all settings, including secrets only if the flag is set
+    ExportSettings(ResponseTx<String, settings::export::Error>, bool),
     /// Request the current feature indicators.
     GetFeatureIndicators(oneshot::Sender<FeatureIndicators>),
 
@@ -433,6 +801,44 @@ pub(crate) enum InternalDaemonEvent {
     ExcludedPathsEvent(ExcludedPathsUpdate, oneshot::Sender<Result<(), Error>>),
     /// A network leak was detected.
     LeakDetected(LeakInfo),
+    /// Finished checking for software that may interfere with the tunnel.
+    #[cfg(target_os = "macos")]
+    ConflictingSoftware(Vec<ConflictingSoftware>),
+    /// Another process overwrote the DNS configuration, which has since been restored.
+    #[cfg(target_os = "linux")]
+    DnsInterference(DnsInterference),
+    /// Negotiation of an ephemeral peer failed and is being retried.
+    NegotiationRetry(NegotiationRetry),
+    /// The connectivity of the host changed, which may mean that it joined another network.
+    ConnectivityChanged(Connectivity),
+    /// Finished identifying the network that the device is connected to.
+    CurrentNetwork(Option<Network>),
+    /// A client of the metrics server requested the current metrics.
+    #[cfg(not(target_os = "android"))]
+    GetMetrics(oneshot::Sender<metrics::Metrics>),
+    /// Time to tell the systemd watchdog that the daemon is alive.
+    #[cfg(target_os = "linux")]
+    WatchdogPing,
+    /// The daemon was asked to reload its configuration.
+    #[cfg(target_os = "linux")]
+    Reload,
+    /// The time that lockdown mode was paused for has passed.
+    #[cfg(not(target_os = "android"))]
+    LockdownPauseExpired,
+    /// Time to probe the network for a captive portal.
+    #[cfg(not(target_os = "android"))]
+    CaptivePortalProbe,
+    /// Finished probing the network for a captive portal.
+    #[cfg(not(target_os = "android"))]
+    CaptivePortalProbeResult(
+        Result<Option<captive_portal::DetectedPortal>, talpid_core::offline::captive_portal::Error>,
+    ),
+    /// The time that traffic to a captive portal was allowed for has passed.
+    #[cfg(not(target_os = "android"))]
+    CaptivePortalLoginExpired,
+    /// The domains blocked by the DNS filter lists changed.
+    #[cfg(not(target_os = "android"))]
+    DnsBlocklist(Arc<DomainBlocklist>),
 }
 
 #[cfg(any(windows, target_os = "android", target_os = "macos"))]
@@ -609,7 +1015,11 @@ where
 
 pub struct Daemon {
     tunnel_state: TunnelState,
+    /// Timings of the most recently established connection
+    connection_timings: Option<ConnectionTimings>,
     target_state: PersistentTargetState,
+    /// Whether the user has paused the tunnel. This is cleared whenever the target state is set.
+    paused: bool,
     #[cfg(target_os = "linux")]
     exclude_pids: split_tunnel::PidManager,
     rx: mpsc::UnboundedReceiver<InternalDaemonEvent>,
@@ -619,6 +1029,7 @@ pub struct Daemon {
     migration_complete: migrations::MigrationComplete,
     settings: SettingsPersister,
     account_history: account_history::AccountHistory,
+    request_queue: request_queue::RequestQueue,
     device_checker: device::TunnelStateChangeHandler,
     account_manager: device::AccountManagerHandle,
     access_mode_handler: mullvad_api::access_mode::AccessModeSelectorHandle,
@@ -627,14 +1038,50 @@ pub struct Daemon {
     version_updater_handle: version_check::VersionUpdaterHandle,
     relay_selector: RelaySelector,
     relay_list_updater: RelayListUpdaterHandle,
+    relay_latency_monitor: RelayLatencyMonitorHandle,
     parameters_generator: tunnel::ParametersGenerator,
     shutdown_tasks: Vec<Pin<Box<dyn Future<Output = ()> + Send + Sync>>>,
     tunnel_state_machine_handle: TunnelStateMachineHandle,
+    data_usage: data_usage::DataUsageHandle,
     #[cfg(target_os = "windows")]
     volume_update_tx: mpsc::UnboundedSender<()>,
     location_handler: GeoIpHandler,
     leak_checker: LeakChecker,
+    #[cfg(target_os = "macos")]
+    conflicting_software: Vec<ConflictingSoftware>,
+    /// PF anchor that the firewall was created with.
+    #[cfg(target_os = "macos")]
+    pf_anchor: String,
+    /// DNS backends that the tunnel state machine was created with.
+    #[cfg(target_os = "linux")]
+    dns_backends: Vec<DnsBackend>,
+    trusted_networks: trusted_networks::TrustedNetworksPersister,
+    #[cfg(not(target_os = "android"))]
+    state_hook_runner: state_hooks::StateHookRunner,
+    /// Exports the tunnel state on D-Bus. `None` if the service could not be started.
+    #[cfg(target_os = "linux")]
+    dbus_service: Option<dbus_service::DbusService>,
+    #[cfg(not(target_os = "android"))]
+    metrics_server: Option<metrics::MetricsServer>,
+    /// Number of times the tunnel has reconnected since the daemon started.
+    #[cfg(not(target_os = "android"))]
+    reconnect_count: u64,
+    /// The network that the device was last seen connected to.
+    current_network: Option<Network>,
+    /// Whether traffic is blocked while disconnected because of the rule for the current network.
+    #[cfg(not(target_os = "android"))]
+    trusted_network_lockdown: bool,
+    /// Set while lockdown mode is paused.
+    #[cfg(not(target_os = "android"))]
+    lockdown_pause: Option<lockdown_pause::LockdownPause>,
+    #[cfg(not(target_os = "android"))]
+    captive_portal: captive_portal::CaptivePortalMonitor,
     cache_dir: PathBuf,
+    #[cfg(not(target_os = "android"))]
+    dns_filter: dns_filter::DnsFilterHandle,
+    /// Domains blocked by the DNS filter lists.
+    #[cfg(not(target_os = "android"))]
+    dns_blocklist: Arc<DomainBlocklist>,
 }
 pub struct DaemonConfig {
     pub log_dir: Option<PathBuf>,
@@ -729,6 +1176,7 @@ impl Daemon {
             mullvad_api::access_mode::AccessModeSelector::spawn(
                 method_resolver,
                 settings.api_access_methods.clone(),
+                &config.cache_dir,
                 #[cfg(feature = "api-override")]
                 config.endpoint.clone(),
                 internal_event_tx.to_unbounded_sender(),
@@ -778,6 +1226,9 @@ impl Daemon {
         .await
         .map_err(Error::LoadAccountManager)?;
 
+        let trusted_networks =
+            trusted_networks::TrustedNetworksPersister::load(&config.settings_dir).await;
+
         let account_history = account_history::AccountHistory::new(
             &config.settings_dir,
             data.device().map(|device| device.account_number.clone()),
@@ -785,6 +1236,13 @@ impl Daemon {
         .await
         .map_err(Error::LoadAccountHistory)?;
 
+        let request_queue = request_queue::RequestQueue::spawn(
+            &config.settings_dir,
+            api_handle.clone(),
+            api_availability.clone(),
+        )
+        .await;
+
         let target_state = if settings.auto_connect {
             log::info!("Automatically connecting since auto-connect is turned on");
             PersistentTargetState::new_secured(&config.cache_dir).await
@@ -805,10 +1263,18 @@ impl Daemon {
             vec![]
         };
 
+        #[cfg(target_os = "linux")]
+        let tunnel_fwmark = settings.tunnel_fwmark();
+        #[cfg(target_os = "linux")]
+        let routing = settings::routing_settings(&settings);
+
         let parameters_generator = tunnel::ParametersGenerator::new(
             account_manager.clone(),
             relay_selector.clone(),
             settings.tunnel_options.clone(),
+            #[cfg(target_os = "linux")]
+            tunnel_fwmark,
+            obfuscation_memory::ObfuscationMemory::load(&config.cache_dir).await,
         );
 
         let param_gen = parameters_generator.clone();
@@ -831,30 +1297,70 @@ impl Daemon {
 
         let route_manager = RouteManagerHandle::spawn(
             #[cfg(target_os = "linux")]
-            mullvad_types::TUNNEL_FWMARK,
+            tunnel_fwmark,
             #[cfg(target_os = "linux")]
-            mullvad_types::TUNNEL_TABLE_ID,
+            routing.table_id(),
+            #[cfg(target_os = "linux")]
+            routing.rule_priority,
             #[cfg(target_os = "android")]
             config.android_context.clone(),
         )
         .await
         .map_err(Error::RouteManager)?;
 
-        let (offline_state_tx, offline_state_rx) = mpsc::unbounded();
+        // If all traffic is blocked, the firewall must not be reset while the daemon starts
+        #[cfg(not(target_os = "android"))]
+        let reset_firewall = *target_state != TargetState::Secured && !settings.block_all;
+        #[cfg(target_os = "android")]
+        let reset_firewall = *target_state != TargetState::Secured;
+
+        #[cfg(target_os = "macos")]
+        let pf_anchor = settings::pf_anchor(&settings);
+        #[cfg(target_os = "linux")]
+        let dns_backends = settings::dns_backends(&settings);
+
+        let (offline_state_tx, mut offline_state_rx) = mpsc::unbounded();
+        let (negotiation_retry_tx, mut negotiation_retry_rx) = mpsc::unbounded();
+        #[cfg(target_os = "linux")]
+        let (dns_interference_tx, mut dns_interference_rx) = mpsc::unbounded();
         #[cfg(target_os = "windows")]
         let (volume_update_tx, volume_update_rx) = mpsc::unbounded();
         let tunnel_state_machine_handle = tunnel_state_machine::spawn(
             tunnel_state_machine::InitialTunnelState {
-                allow_lan: settings.allow_lan,
+                allow_lan: settings.allow_lan.clone(),
                 #[cfg(not(target_os = "android"))]
                 block_when_disconnected: settings.block_when_disconnected,
+                #[cfg(not(target_os = "android"))]
+                dns_config: dns::addresses_from_options(&settings.tunnel_options.dns_options)
+                    .with_bypassed_domains(dns::bypassed_domains(&settings)),
+                #[cfg(target_os = "android")]
                 dns_config: dns::addresses_from_options(&settings.tunnel_options.dns_options),
                 allowed_endpoint: access_mode_handler
                     .get_current()
                     .await
                     .map_err(Error::ApiConnectionModeError)?
                     .endpoint,
-                reset_firewall: *target_state != TargetState::Secured,
+                #[cfg(not(target_os = "android"))]
+                firewall_exceptions: settings.firewall_exceptions.clone(),
+                #[cfg(not(target_os = "android"))]
+                allowed_incoming: settings.allowed_incoming.clone(),
+                #[cfg(not(target_os = "android"))]
+                block_ipv6: settings.tunnel_options.generic.block_ipv6,
+                #[cfg(target_os = "linux")]
+                split_tunnel_mode: settings.split_tunnel_mode,
+                #[cfg(target_os = "linux")]
+                split_tunnel_owners: settings.split_tunnel_owners.clone(),
+                reset_firewall,
+                #[cfg(target_os = "linux")]
+                nftables: settings::nftables_options(&settings),
+                #[cfg(windows)]
+                sublayer_weight: settings::wfp_sublayer_weight(&settings),
+                #[cfg(target_os = "macos")]
+                pf_anchor: pf_anchor.clone(),
+                #[cfg(target_os = "linux")]
+                dns_backends: dns_backends.clone(),
+                #[cfg(not(target_os = "android"))]
+                offline_monitor: settings.offline_monitor,
                 #[cfg(any(windows, target_os = "android", target_os = "macos"))]
                 exclude_paths,
             },
@@ -863,6 +1369,7 @@ impl Daemon {
             config.resource_dir.clone(),
             internal_event_tx.to_specialized_sender(),
             offline_state_tx,
+            negotiation_retry_tx,
             route_manager.clone(),
             #[cfg(target_os = "windows")]
             volume_update_rx,
@@ -872,27 +1379,79 @@ impl Daemon {
             connectivity_listener.clone(),
             #[cfg(target_os = "linux")]
             tunnel_state_machine::LinuxNetworkingIdentifiers {
-                fwmark: mullvad_types::TUNNEL_FWMARK,
-                table_id: mullvad_types::TUNNEL_TABLE_ID,
+                fwmark: tunnel_fwmark,
+                table_id: routing.table_id(),
             },
+            #[cfg(target_os = "linux")]
+            dns_interference_tx,
         )
         .await
         .map_err(Error::TunnelError)?;
 
-        api::forward_offline_state(api_availability.clone(), offline_state_rx);
+        let data_usage = data_usage::DataUsageHandle::spawn(
+            &config.cache_dir,
+            Arc::downgrade(tunnel_state_machine_handle.command_tx()),
+        )
+        .await;
+
+        let (api_offline_state_tx, api_offline_state_rx) = mpsc::unbounded();
+        api::forward_offline_state(api_availability.clone(), api_offline_state_rx);
+        {
+            // Changes in connectivity are also used to detect when another network is joined
+            let internal_event_tx = internal_event_tx.clone();
+            tokio::spawn(async move {
+                while let Some(connectivity) = offline_state_rx.next().await {
+                    let _ = api_offline_state_tx.unbounded_send(connectivity);
+                    let _ = internal_event_tx
+                        .send(InternalDaemonEvent::ConnectivityChanged(connectivity));
+                }
+            });
+        }
+
+        {
+            let internal_event_tx = internal_event_tx.clone();
+            tokio::spawn(async move {
+                while let Some(retry) = negotiation_retry_rx.next().await {
+                    let _ = internal_event_tx.send(InternalDaemonEvent::NegotiationRetry(retry));
+                }
+            });
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            let internal_event_tx = internal_event_tx.clone();
+            tokio::spawn(async move {
+                while let Some(interference) = dns_interference_rx.next().await {
+                    let _ =
+                        internal_event_tx.send(InternalDaemonEvent::DnsInterference(interference));
+                }
+            });
+        }
 
         let relay_list_listener = management_interface.notifier().clone();
-        let on_relay_list_update = move |relay_list: &RelayList| {
-            relay_list_listener.notify_relay_list(relay_list.clone());
+        let relay_list_selector = relay_selector.clone();
+        let on_relay_list_update = move |_relay_list: &RelayList| {
+            // Frontends are also shown the custom relays
+            relay_list_listener.notify_relay_list(relay_list_selector.get_relay_locations());
         };
 
         let mut relay_list_updater = RelayListUpdater::spawn(
             relay_selector.clone(),
             api_handle.clone(),
             &config.cache_dir,
+            &config.settings_dir,
+            settings.relay_list_update_interval(),
             on_relay_list_update,
         );
 
+        let relay_latency_monitor = RelayLatencyMonitorHandle::spawn(relay_selector.clone());
+
+        #[cfg(not(target_os = "android"))]
+        let dns_filter = dns_filter::spawn(
+            settings.tunnel_options.dns_options.filter_lists.clone(),
+            internal_event_tx.clone(),
+        );
+
         let version_updater_handle = version_check::VersionUpdater::spawn(
             api_handle.clone(),
             api_availability.clone(),
@@ -913,10 +1472,15 @@ impl Daemon {
                 android_dns::AndroidDnsResolver::new(connectivity_listener),
             ),
             internal_event_tx.clone().to_specialized_sender(),
+            GeoIpDatabase::load(&config.resource_dir, &config.cache_dir).await,
         );
 
         let leak_checker = {
-            let mut leak_checker = LeakChecker::new(route_manager);
+            let mut leak_checker = LeakChecker::new(
+                route_manager,
+                #[cfg(target_os = "linux")]
+                tunnel_fwmark,
+            );
             let internal_event_tx = internal_event_tx.clone();
             leak_checker.add_leak_callback(move |info| {
                 internal_event_tx
@@ -926,13 +1490,27 @@ impl Daemon {
             leak_checker
         };
 
+        let tunnel_state = TunnelState::Disconnected {
+            location: None,
+            #[cfg(not(target_os = "android"))]
+            locked_down: settings.block_when_disconnected,
+        };
+
+        #[cfg(target_os = "linux")]
+        let dbus_service = dbus_service::DbusService::start(&tunnel_state)
+            .inspect_err(|error| {
+                log::warn!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to start D-Bus service")
+                )
+            })
+            .ok();
+
         let daemon = Daemon {
-            tunnel_state: TunnelState::Disconnected {
-                location: None,
-                #[cfg(not(target_os = "android"))]
-                locked_down: settings.block_when_disconnected,
-            },
+            tunnel_state,
+            connection_timings: None,
             target_state,
+            paused: false,
             #[cfg(target_os = "linux")]
             exclude_pids: split_tunnel::PidManager::new().map_err(Error::InitSplitTunneling)?,
             rx: internal_event_rx,
@@ -942,6 +1520,7 @@ impl Daemon {
             migration_complete,
             settings,
             account_history,
+            request_queue,
             device_checker: device::TunnelStateChangeHandler::new(account_manager.clone()),
             account_manager,
             access_mode_handler,
@@ -950,14 +1529,46 @@ impl Daemon {
             version_updater_handle,
             relay_selector,
             relay_list_updater,
+            relay_latency_monitor,
             parameters_generator,
             shutdown_tasks: vec![],
             tunnel_state_machine_handle,
+            data_usage,
             #[cfg(target_os = "windows")]
             volume_update_tx,
             location_handler,
             leak_checker,
+            #[cfg(target_os = "macos")]
+            conflicting_software: vec![],
+            #[cfg(target_os = "macos")]
+            pf_anchor: pf_anchor
+                .unwrap_or_else(|| talpid_core::firewall::DEFAULT_ANCHOR_NAME.to_owned()),
+            #[cfg(target_os = "linux")]
+            dns_backends,
+            trusted_networks,
+            #[cfg(not(target_os = "android"))]
+            state_hook_runner: state_hooks::StateHookRunner::spawn(),
+            #[cfg(target_os = "linux")]
+            dbus_service,
+            #[cfg(not(target_os = "android"))]
+            metrics_server: None,
+            #[cfg(not(target_os = "android"))]
+            reconnect_count: 0,
+            current_network: None,
+            #[cfg(not(target_os = "android"))]
+            trusted_network_lockdown: false,
+            #[cfg(not(target_os = "android"))]
+            lockdown_pause: None,
+            #[cfg(not(target_os = "android"))]
+            captive_portal: captive_portal::CaptivePortalMonitor::new(
+                #[cfg(target_os = "linux")]
+                tunnel_fwmark,
+            ),
             cache_dir: config.cache_dir,
+            #[cfg(not(target_os = "android"))]
+            dns_filter,
+            #[cfg(not(target_os = "android"))]
+            dns_blocklist: Arc::default(),
         };
 
         api_availability.unsuspend();
@@ -969,6 +1580,12 @@ impl Daemon {
     /// shutdown event is received.
     pub async fn run(mut self) -> Result<(), Error> {
         self.handle_initial_target_state();
+        #[cfg(target_os = "macos")]
+        self.check_conflicting_software();
+        #[cfg(not(target_os = "android"))]
+        self.update_metrics_server().await;
+        #[cfg(target_os = "linux")]
+        self.start_systemd_notifications();
         self.handle_events().await;
         self.disconnect_tunnel_and_wait().await;
         self.finalize().await;
@@ -976,6 +1593,12 @@ impl Daemon {
     }
 
     fn handle_initial_target_state(&mut self) {
+        #[cfg(not(target_os = "android"))]
+        if self.settings.block_all {
+            self.block_all_traffic();
+            return;
+        }
+
         match self.target_state.to_strict() {
             either::Either::Right(state) => {
                 self.send_tunnel_command(Self::secured_state_to_tunnel_command(state));
@@ -1000,7 +1623,7 @@ impl Daemon {
             return;
         }
 
-        self.disconnect_tunnel();
+        self.send_tunnel_command(TunnelCommand::Disconnect);
 
         while let Some(event) = self.rx.next().await {
             match event {
@@ -1026,6 +1649,7 @@ impl Daemon {
             shutdown_tasks,
             api_runtime,
             tunnel_state_machine_handle,
+            data_usage,
             target_state,
             account_manager,
             ..
@@ -1035,6 +1659,8 @@ impl Daemon {
             future.await;
         }
 
+        data_usage.save().await;
+
         target_state.finalize().await;
         account_manager.shutdown().await;
 
@@ -1078,6 +1704,9 @@ impl Daemon {
             LocationEvent(location_data) => self.handle_location_event(location_data),
             SettingsChanged => {
                 self.update_feature_indicators_on_settings_changed();
+                self.update_relay_latency_monitor();
+                #[cfg(not(target_os = "android"))]
+                self.update_metrics_server().await;
             }
             #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             ExcludedPathsEvent(update, tx) => self.handle_new_excluded_paths(update, tx).await,
@@ -1085,6 +1714,39 @@ impl Daemon {
                 log::warn!("Network leak detected! Please contact Mullvad support.");
                 log::warn!("{leak_info:?}")
             }
+            #[cfg(target_os = "macos")]
+            ConflictingSoftware(conflicts) => self.handle_conflicting_software(conflicts),
+            #[cfg(target_os = "linux")]
+            DnsInterference(interference) => self
+                .management_interface
+                .notifier()
+                .notify_dns_interference(interference),
+            NegotiationRetry(retry) => self
+                .management_interface
+                .notifier()
+                .notify_negotiation_retry(retry),
+            ConnectivityChanged(connectivity) => {
+                if connectivity.is_online() {
+                    self.check_current_network();
+                }
+            }
+            CurrentNetwork(network) => self.handle_current_network(network).await,
+            #[cfg(not(target_os = "android"))]
+            GetMetrics(tx) => self.on_get_metrics(tx),
+            #[cfg(target_os = "linux")]
+            WatchdogPing => self.on_watchdog_ping(),
+            #[cfg(target_os = "linux")]
+            Reload => self.on_reload(),
+            #[cfg(not(target_os = "android"))]
+            LockdownPauseExpired => self.on_lockdown_pause_expired(),
+            #[cfg(not(target_os = "android"))]
+            CaptivePortalProbe => self.on_captive_portal_probe().await,
+            #[cfg(not(target_os = "android"))]
+            CaptivePortalProbeResult(result) => self.on_captive_portal_probe_result(result),
+            #[cfg(not(target_os = "android"))]
+            CaptivePortalLoginExpired => self.on_captive_portal_login_expired(),
+            #[cfg(not(target_os = "android"))]
+            DnsBlocklist(blocklist) => self.on_dns_blocklist(blocklist),
         }
         should_stop
     }
@@ -1120,7 +1782,21 @@ impl Daemon {
                     feature_indicators,
                 }
             }
-            TunnelStateTransition::Connected(endpoint) => {
+            TunnelStateTransition::Connected(endpoint, timings) => {
+                self.connection_timings = Some(timings);
+                if endpoint.tunnel_type == TunnelType::Wireguard
+                    && self.settings.obfuscation_settings.selected_obfuscation
+                        == SelectedObfuscation::Auto
+                {
+                    self.parameters_generator
+                        .remember_obfuscation(
+                            endpoint
+                                .obfuscation
+                                .map(|obfuscation| obfuscation.obfuscation_type),
+                        )
+                        .await;
+                }
+                self.parameters_generator.remember_connected_relays().await;
                 let feature_indicators = compute_feature_indicators(
                     self.settings.settings(),
                     &endpoint,
@@ -1135,6 +1811,7 @@ impl Daemon {
             TunnelStateTransition::Disconnecting(after_disconnect) => {
                 TunnelState::Disconnecting(after_disconnect)
             }
+            TunnelStateTransition::Paused => TunnelState::Paused,
             TunnelStateTransition::Error(error_state) => TunnelState::Error(error_state),
         };
 
@@ -1165,7 +1842,19 @@ impl Daemon {
             TunnelState::Connecting { .. } => {
                 log::debug!("Settings: {}", self.settings.summary());
             }
+            #[cfg(target_os = "linux")]
+            TunnelState::Connected { endpoint, .. } => {
+                self.check_wireguard_backend_fallback(endpoint);
+            }
+            TunnelState::Paused => {
+                log::info!("Tunnel paused, blocking all network connections");
+            }
             TunnelState::Error(error_state) => {
+                // Conflicting software is a common cause of failures that are otherwise hard to
+                // diagnose
+                #[cfg(target_os = "macos")]
+                self.check_conflicting_software();
+
                 if error_state.is_blocking() {
                     log::info!(
                         "Blocking all network connections, reason: {}",
@@ -1183,15 +1872,67 @@ impl Daemon {
                     // are received. So we must continually try to reconnect.
                     self.schedule_reconnect(Duration::from_secs(60))
                 }
+                if let ErrorStateCause::RelayNotResponding = error_state.cause() {
+                    // Give clients a chance to show the cause, then try another relay.
+                    self.schedule_reconnect(RELAY_NOT_RESPONDING_RECONNECT_DELAY)
+                }
             }
             _ => {}
         }
 
+        #[cfg(not(target_os = "android"))]
+        self.state_hook_runner
+            .on_new_state(&self.settings.state_hooks, &tunnel_state);
+        #[cfg(not(target_os = "android"))]
+        self.count_reconnect(&tunnel_state);
+        #[cfg(target_os = "linux")]
+        if let Some(dbus_service) = &self.dbus_service {
+            dbus_service.notify_new_state(&tunnel_state);
+        }
+        #[cfg(target_os = "linux")]
+        systemd_notify::status(&tunnel_state);
+
         self.tunnel_state = tunnel_state.clone();
+        #[cfg(not(target_os = "android"))]
+        self.update_captive_portal_monitor();
         self.management_interface
             .notifier()
             .notify_new_state(tunnel_state);
         self.fetch_am_i_mullvad();
+        self.update_relay_latency_monitor();
+    }
+
+    /// Measure relay latencies if enabled, and if the relays can be reached directly, i.e. while
+    /// disconnected and not blocking traffic.
+    fn update_relay_latency_monitor(&self) {
+        let relays_reachable = match &self.tunnel_state {
+            #[cfg(not(target_os = "android"))]
+            TunnelState::Disconnected { locked_down, .. } => !locked_down,
+            #[cfg(target_os = "android")]
+            TunnelState::Disconnected { .. } => true,
+            _ => false,
+        };
+        self.relay_latency_monitor
+            .set_active(self.settings.prefer_low_latency_relays && relays_reachable);
+    }
+
+    /// Notify clients if the tunnel is not running on the WireGuard backend selected in the
+    /// settings.
+    #[cfg(target_os = "linux")]
+    fn check_wireguard_backend_fallback(&self, endpoint: &talpid_types::net::TunnelEndpoint) {
+        let (Some(requested), Some(actual)) = (
+            self.settings.tunnel_options.wireguard.backend,
+            endpoint.wireguard_backend,
+        ) else {
+            return;
+        };
+        if requested != actual {
+            let fallback = talpid_types::net::wireguard::BackendFallback { requested, actual };
+            log::warn!("{fallback}");
+            self.management_interface
+                .notifier()
+                .notify_wireguard_backend_fallback(fallback);
+        }
     }
 
     /// Get the geographical location from am.i.mullvad.net. When it arrives,
@@ -1206,7 +1947,10 @@ impl Daemon {
         // Whether or not to poll for an IPv6 exit IP
         let use_ipv6 = match &self.tunnel_state {
             // If connected, refer to the tunnel setting
-            TunnelState::Connected { .. } => self.settings.tunnel_options.generic.enable_ipv6,
+            TunnelState::Connected { .. } => {
+                self.settings.tunnel_options.generic.enable_ipv6
+                    && !self.settings.tunnel_options.generic.block_ipv6
+            }
             // If not connected, we have to guess whether the users local connection supports IPv6.
             // The only thing we have to go on is the wireguard setting.
             TunnelState::Disconnected { .. } => {
@@ -1226,7 +1970,18 @@ impl Daemon {
             _ => return,
         };
 
-        self.location_handler.send_geo_location_request(use_ipv6);
+        let fallback = match &self.tunnel_state {
+            TunnelState::Connected {
+                endpoint, location, ..
+            } => LocationFallback::Relay {
+                exit_ip: endpoint.endpoint.address.ip(),
+                location: location.clone(),
+            },
+            _ => LocationFallback::Direct,
+        };
+
+        self.location_handler
+            .send_geo_location_request(use_ipv6, fallback);
     }
 
     /// Receives and handles the geographical exit location received from am.i.mullvad.net, i.e. the
@@ -1311,7 +2066,7 @@ impl Daemon {
     ) {
         match (&self.tunnel_state, &tunnel_state_transition) {
             // Only reset the API sockets when entering or leaving the connected state
-            (&TunnelState::Connected { .. }, _) | (_, &TunnelStateTransition::Connected(_)) => {
+            (&TunnelState::Connected { .. }, _) | (_, &TunnelStateTransition::Connected(..)) => {
                 self.api_handle.service().reset();
             }
             _ => (),
@@ -1350,13 +2105,25 @@ impl Daemon {
         match command {
             SetTargetState(tx, state) => self.on_set_target_state(tx, state).await,
             Reconnect(tx) => self.on_reconnect(tx),
+            Pause(tx) => self.on_pause(tx).await,
             GetState(tx) => self.on_get_state(tx),
+            GetConnectionTimings(tx) => self.on_get_connection_timings(tx),
+            GetTrafficStats(tx) => self.on_get_traffic_stats(tx),
+            GetTunnelDiagnostics(tx) => self.on_get_tunnel_diagnostics(tx),
+            GetDataUsage(tx) => self.on_get_data_usage(tx),
             CreateNewAccount(tx) => self.on_create_new_account(tx),
             GetAccountData(tx, account_number) => self.on_get_account_data(tx, account_number),
             GetWwwAuthToken(tx) => self.on_get_www_auth_token(tx).await,
             SubmitVoucher(tx, voucher) => self.on_submit_voucher(tx, voucher),
             GetRelayLocations(tx) => self.on_get_relay_locations(tx),
             UpdateRelayLocations => self.on_update_relay_locations().await,
+            UpdateRelayLocationsAndWait(tx) => self.on_update_relay_locations_and_wait(tx),
+            SetRelayListUpdateInterval(tx, minutes) => {
+                self.on_set_relay_list_update_interval(tx, minutes).await
+            }
+            ExportRelayList(tx) => self.on_export_relay_list(tx),
+            ImportRelayList(tx, blob) => self.on_import_relay_list(tx, blob).await,
+            ReloadRelayListOverrides(tx) => self.on_reload_relay_list_overrides(tx),
             LoginAccount(tx, account_number) => self.on_login_account(tx, account_number),
             LogoutAccount(tx) => self.on_logout_account(tx),
             GetDevice(tx) => self.on_get_device(tx),
@@ -1365,16 +2132,52 @@ impl Daemon {
             RemoveDevice(tx, account_number, device_id) => {
                 self.on_remove_device(tx, account_number, device_id)
             }
-            GetAccountHistory(tx) => self.on_get_account_history(tx),
+            QueueVoucherSubmission(tx, voucher) => {
+                self.on_queue_voucher_submission(tx, voucher).await
+            }
+            RemoveDevices(tx, account_number, device_ids, all_except_current) => {
+                self.on_remove_devices(tx, account_number, device_ids, all_except_current)
+                    .await
+            }
+            QueueDeviceRemoval(tx, account_number, device_id) => {
+                self.on_queue_device_removal(tx, account_number, device_id)
+            }
+            GetPendingRequests(tx) => self.on_get_pending_requests(tx),
+            CancelPendingRequest(tx, id) => self.on_cancel_pending_request(tx, id),
+            GetAccountHistory(tx) => self.on_get_account_history(tx),
             ClearAccountHistory(tx) => self.on_clear_account_history(tx).await,
             SetRelaySettings(tx, update) => self.on_set_relay_settings(tx, update).await,
             SetAllowLan(tx, allow_lan) => self.on_set_allow_lan(tx, allow_lan).await,
             SetShowBetaReleases(tx, enabled) => self.on_set_show_beta_releases(tx, enabled).await,
+            SetPreferLowLatencyRelays(tx, enabled) => {
+                self.on_set_prefer_low_latency_relays(tx, enabled).await
+            }
+            SetStickyRelays(tx, enabled) => self.on_set_sticky_relays(tx, enabled).await,
             #[cfg(not(target_os = "android"))]
             SetBlockWhenDisconnected(tx, block_when_disconnected) => {
                 self.on_set_block_when_disconnected(tx, block_when_disconnected)
                     .await
             }
+            #[cfg(not(target_os = "android"))]
+            PauseLockdownMode(tx, duration) => self.on_pause_lockdown_mode(tx, duration),
+            #[cfg(not(target_os = "android"))]
+            AllowCaptivePortalLogin(tx, duration) => {
+                self.on_allow_captive_portal_login(tx, duration)
+            }
+            #[cfg(not(target_os = "android"))]
+            SetBlockAll(tx, block_all) => self.on_set_block_all(tx, block_all).await,
+            #[cfg(not(target_os = "android"))]
+            SetFirewallExceptions(tx, exceptions) => {
+                self.on_set_firewall_exceptions(tx, exceptions).await
+            }
+            #[cfg(not(target_os = "android"))]
+            SetAllowedIncoming(tx, allowed_incoming) => {
+                self.on_set_allowed_incoming(tx, allowed_incoming).await
+            }
+            #[cfg(not(target_os = "android"))]
+            SetStateHook(tx, event, path) => self.on_set_state_hook(tx, event, path).await,
+            #[cfg(not(target_os = "android"))]
+            SetMetricsSettings(tx, metrics) => self.on_set_metrics_settings(tx, metrics).await,
             SetAutoConnect(tx, auto_connect) => self.on_set_auto_connect(tx, auto_connect).await,
             SetOpenVpnMssfix(tx, mssfix_arg) => self.on_set_openvpn_mssfix(tx, mssfix_arg).await,
             SetBridgeSettings(tx, bridge_settings) => {
@@ -1382,10 +2185,24 @@ impl Daemon {
             }
             SetBridgeState(tx, bridge_state) => self.on_set_bridge_state(tx, bridge_state).await,
             SetEnableIpv6(tx, enable_ipv6) => self.on_set_enable_ipv6(tx, enable_ipv6).await,
+            SetWireguardIpv6Only(tx, ipv6_only) => {
+                self.on_set_wireguard_ipv6_only(tx, ipv6_only).await
+            }
+            #[cfg(not(target_os = "android"))]
+            SetBlockIpv6(tx, block_ipv6) => self.on_set_block_ipv6(tx, block_ipv6).await,
+            #[cfg(not(target_os = "android"))]
+            SetCustomRoutes(tx, routes) => self.on_set_custom_routes(tx, routes).await,
             SetQuantumResistantTunnel(tx, quantum_resistant_state) => {
                 self.on_set_quantum_resistant_tunnel(tx, quantum_resistant_state)
                     .await
             }
+            SetQuantumResistantKem(tx, kem) => self.on_set_quantum_resistant_kem(tx, kem).await,
+            SetNegotiationRetryPolicy(tx, policy) => {
+                self.on_set_negotiation_retry_policy(tx, policy).await
+            }
+            SetConnectivityCheckOptions(tx, options) => {
+                self.on_set_connectivity_check_options(tx, options).await
+            }
             #[cfg(daita)]
             SetEnableDaita(tx, value) => self.on_set_daita_enabled(tx, value).await,
             #[cfg(daita)]
@@ -1402,6 +2219,36 @@ impl Daemon {
             }
             ClearAllRelayOverrides(tx) => self.on_clear_all_relay_overrides(tx).await,
             SetWireguardMtu(tx, mtu) => self.on_set_wireguard_mtu(tx, mtu).await,
+            SetWireguardPersistentKeepalive(tx, interval) => {
+                self.on_set_wireguard_persistent_keepalive(tx, interval)
+                    .await
+            }
+            SetWireguardWorkerThreads(tx, threads) => {
+                self.on_set_wireguard_worker_threads(tx, threads).await
+            }
+            #[cfg(target_os = "linux")]
+            SetWireguardOutgoingInterface(tx, interface) => {
+                self.on_set_wireguard_outgoing_interface(tx, interface)
+                    .await
+            }
+            #[cfg(target_os = "linux")]
+            SetWireguardBackend(tx, backend) => self.on_set_wireguard_backend(tx, backend).await,
+            #[cfg(target_os = "linux")]
+            SetTunnelFwmark(tx, fwmark) => self.on_set_tunnel_fwmark(tx, fwmark).await,
+            #[cfg(target_os = "linux")]
+            SetNftablesSettings(tx, nftables) => self.on_set_nftables_settings(tx, nftables).await,
+            #[cfg(target_os = "linux")]
+            SetRoutingSettings(tx, routing) => self.on_set_routing_settings(tx, routing).await,
+            #[cfg(windows)]
+            SetWfpSublayerWeight(tx, weight) => self.on_set_wfp_sublayer_weight(tx, weight).await,
+            #[cfg(target_os = "macos")]
+            SetPfAnchor(tx, anchor) => self.on_set_pf_anchor(tx, anchor).await,
+            #[cfg(target_os = "linux")]
+            SetDnsBackends(tx, backends) => self.on_set_dns_backends(tx, backends).await,
+            #[cfg(not(target_os = "android"))]
+            SetOfflineMonitorBackend(tx, backend) => {
+                self.on_set_offline_monitor_backend(tx, backend).await
+            }
             SetWireguardRotationInterval(tx, interval) => {
                 self.on_set_wireguard_rotation_interval(tx, interval).await
             }
@@ -1413,6 +2260,19 @@ impl Daemon {
             DeleteCustomList(tx, id) => self.on_delete_custom_list(tx, id).await,
             UpdateCustomList(tx, update) => self.on_update_custom_list(tx, update).await,
             ClearCustomLists(tx) => self.on_clear_custom_lists(tx).await,
+            AddCustomRelay(tx, relay) => self.on_add_custom_relay(tx, relay).await,
+            RemoveCustomRelay(tx, name) => self.on_remove_custom_relay(tx, name).await,
+            SaveProfile(tx, name) => self.on_save_profile(tx, name).await,
+            RemoveProfile(tx, name) => self.on_remove_profile(tx, name).await,
+            UseProfile(tx, name) => self.on_use_profile(tx, name).await,
+            GetTrustedNetworks(tx) => self.on_get_trusted_networks(tx),
+            SetTrustedNetworkRule(tx, rule) => self.on_set_trusted_network_rule(tx, rule).await,
+            RemoveTrustedNetworkRule(tx, network) => {
+                self.on_remove_trusted_network_rule(tx, network).await
+            }
+            SetExcludedLocations(tx, excluded_locations) => {
+                self.on_set_excluded_locations(tx, excluded_locations).await
+            }
             GetVersionInfo(tx) => self.on_get_version_info(tx),
             AddApiAccessMethod(tx, name, enabled, access_method) => {
                 self.on_add_access_method(tx, name, enabled, access_method)
@@ -1422,6 +2282,9 @@ impl Daemon {
             UpdateApiAccessMethod(tx, method) => self.on_update_api_access_method(tx, method).await,
             ClearCustomApiAccessMethods(tx) => self.on_clear_custom_api_access_methods(tx).await,
             GetCurrentAccessMethod(tx) => self.on_get_current_api_access_method(tx),
+            GetApiAccessMethodStats(tx) => self.on_get_api_access_method_stats(tx),
+            #[cfg(feature = "api-override")]
+            SetApiEndpoint(tx, endpoint) => self.on_set_api_endpoint(tx, endpoint),
             SetApiAccessMethod(tx, method) => self.on_set_api_access_method(tx, method).await,
             TestApiAccessMethodById(tx, method) => self.on_test_api_access_method(tx, method).await,
             TestCustomApiAccessMethod(tx, proxy) => self.on_test_proxy_as_access_method(tx, proxy),
@@ -1437,6 +2300,14 @@ impl Daemon {
             RemoveSplitTunnelProcess(tx, pid) => self.on_remove_split_tunnel_process(tx, pid),
             #[cfg(target_os = "linux")]
             ClearSplitTunnelProcesses(tx) => self.on_clear_split_tunnel_processes(tx),
+            #[cfg(target_os = "linux")]
+            SetSplitTunnelMode(tx, mode) => self.on_set_split_tunnel_mode(tx, mode).await,
+            #[cfg(target_os = "linux")]
+            SetSplitTunnelOwners(tx, owners) => self.on_set_split_tunnel_owners(tx, owners).await,
+            #[cfg(target_os = "linux")]
+            SetSplitTunnelDomains(tx, domains) => {
+                self.on_set_split_tunnel_domains(tx, domains).await
+            }
             #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             AddSplitTunnelApp(tx, app) => self.on_add_split_tunnel_app(tx, app),
             #[cfg(any(windows, target_os = "android", target_os = "macos"))]
@@ -1449,6 +2320,22 @@ impl Daemon {
             GetSplitTunnelProcesses(tx) => self.on_get_split_tunnel_processes(tx),
             #[cfg(target_os = "windows")]
             CheckVolumes(tx) => self.on_check_volumes(tx),
+            #[cfg(target_os = "macos")]
+            GetConflictingSoftware(tx) => Self::oneshot_send(
+                tx,
+                self.conflicting_software.clone(),
+                "get_conflicting_software response",
+            ),
+            #[cfg(windows)]
+            GetDriverStatus(tx) => self.on_get_driver_status(tx),
+            #[cfg(windows)]
+            RepairDriver(tx, driver) => self.on_repair_driver(tx, driver),
+            #[cfg(windows)]
+            GetWfpDiagnostics(tx) => self.on_get_wfp_diagnostics(tx),
+            #[cfg(target_os = "macos")]
+            GetPfDiagnostics(tx) => self.on_get_pf_diagnostics(tx),
+            #[cfg(target_os = "linux")]
+            GetActiveDnsBackend(tx) => self.on_get_active_dns_backend(tx),
             SetObfuscationSettings(tx, settings) => {
                 self.on_set_obfuscation_settings(tx, settings).await
             }
@@ -1463,6 +2350,8 @@ impl Daemon {
             }
             ApplyJsonSettings(tx, blob) => self.on_apply_json_settings(tx, blob).await,
             ExportJsonSettings(tx) => self.on_export_json_settings(tx),
+            ImportSettings(tx, blob) => self.on_import_settings(tx, blob).await,
+            ExportSettings(tx, include_secrets) => self.on_export_settings(tx, include_secrets),
             GetFeatureIndicators(tx) => self.on_get_feature_indicators(tx),
             DisableRelay { relay, tx } => self.on_toggle_relay(relay, false, tx),
             EnableRelay { relay, tx } => self.on_toggle_relay(relay, true, tx),
@@ -1509,8 +2398,8 @@ impl Daemon {
                     self.schedule_reconnect(WG_RECONNECT_DELAY);
                 }
             }
-            AccountEvent::Expiry(expiry) if *self.target_state == TargetState::Secured => {
-                if expiry >= &chrono::Utc::now() {
+            AccountEvent::Expiry(event) if *self.target_state == TargetState::Secured => {
+                if event.expiry >= chrono::Utc::now() {
                     if let TunnelState::Error(ref state) = self.tunnel_state {
                         if matches!(state.cause(), ErrorStateCause::AuthFailed(_)) {
                             log::debug!("Reconnecting since the account has time on it");
@@ -1526,10 +2415,15 @@ impl Daemon {
             }
             _ => (),
         }
-        if let AccountEvent::Device(event) = event {
-            self.management_interface
+        match event {
+            AccountEvent::Device(event) => self
+                .management_interface
                 .notifier()
-                .notify_device_event(DeviceEvent::from(event));
+                .notify_device_event(DeviceEvent::from(event)),
+            AccountEvent::Expiry(event) => self
+                .management_interface
+                .notifier()
+                .notify_account_expiry(event),
         }
     }
 
@@ -1700,7 +2594,12 @@ impl Daemon {
     }
 
     fn on_reconnect(&mut self, tx: oneshot::Sender<bool>) {
-        if *self.target_state == TargetState::Secured || self.tunnel_state.is_in_error_state() {
+        if self.paused {
+            log::debug!("Ignoring reconnect command. The tunnel is paused");
+            Self::oneshot_send(tx, false, "reconnect issued");
+        } else if *self.target_state == TargetState::Secured
+            || self.tunnel_state.is_in_error_state()
+        {
             self.connect_tunnel();
             Self::oneshot_send(tx, true, "reconnect issued");
         } else {
@@ -1709,10 +2608,34 @@ impl Daemon {
         }
     }
 
+    async fn on_pause(&mut self, tx: oneshot::Sender<bool>) {
+        let pause_initiated = self.pause_tunnel().await;
+        Self::oneshot_send(tx, pause_initiated, "pause initiated");
+    }
+
     fn on_get_state(&self, tx: oneshot::Sender<TunnelState>) {
         Self::oneshot_send(tx, self.tunnel_state.clone(), "current state");
     }
 
+    fn on_get_connection_timings(&self, tx: oneshot::Sender<Option<ConnectionTimings>>) {
+        Self::oneshot_send(tx, self.connection_timings.clone(), "connection timings");
+    }
+
+    fn on_get_traffic_stats(&self, tx: oneshot::Sender<Option<TrafficStats>>) {
+        self.send_tunnel_command(TunnelCommand::GetTrafficStats(tx));
+    }
+
+    fn on_get_tunnel_diagnostics(&self, tx: oneshot::Sender<TunnelDiagnostics>) {
+        self.send_tunnel_command(TunnelCommand::GetDiagnostics(tx));
+    }
+
+    fn on_get_data_usage(&self, tx: ResponseTx<DataUsage, data_usage::Error>) {
+        let data_usage = self.data_usage.clone();
+        tokio::spawn(async move {
+            Self::oneshot_send(tx, data_usage.get().await, "get_data_usage response");
+        });
+    }
+
     fn on_is_performing_post_upgrade(&self, tx: oneshot::Sender<bool>) {
         let performing_post_upgrade = !self.migration_complete.is_complete();
         Self::oneshot_send(tx, performing_post_upgrade, "performing post upgrade");
@@ -1800,13 +2723,83 @@ impl Daemon {
     }
 
     fn on_get_relay_locations(&mut self, tx: oneshot::Sender<RelayList>) {
-        Self::oneshot_send(tx, self.relay_selector.get_relays(), "relay locations");
+        Self::oneshot_send(
+            tx,
+            self.relay_selector.get_relay_locations(),
+            "relay locations",
+        );
     }
 
     async fn on_update_relay_locations(&mut self) {
         self.relay_list_updater.update().await;
     }
 
+    fn on_update_relay_locations_and_wait(&mut self, tx: ResponseTx<(), relay_list::Error>) {
+        let mut relay_list_updater = self.relay_list_updater.clone();
+        tokio::spawn(async move {
+            let result = relay_list_updater.update_and_wait().await;
+            Self::oneshot_send(tx, result, "update_relay_locations_and_wait response");
+        });
+    }
+
+    async fn on_set_relay_list_update_interval(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        minutes: u16,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.relay_list_update_interval_minutes = minutes)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_relay_list_update_interval response");
+                if settings_changed {
+                    self.relay_list_updater
+                        .set_update_interval(self.settings.relay_list_update_interval())
+                        .await;
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_relay_list_update_interval response");
+            }
+        }
+    }
+
+    fn on_export_relay_list(&mut self, tx: ResponseTx<String, relay_list::export::Error>) {
+        let result = relay_list::export::export(
+            self.relay_selector.get_relays(),
+            self.relay_selector.last_updated(),
+        );
+        Self::oneshot_send(tx, result, "export_relay_list response");
+    }
+
+    async fn on_import_relay_list(
+        &mut self,
+        tx: ResponseTx<(), relay_list::export::Error>,
+        blob: String,
+    ) {
+        match relay_list::export::import(&blob, self.relay_selector.last_updated()) {
+            Ok(relay_list) => {
+                self.relay_list_updater.import(relay_list).await;
+                Self::oneshot_send(tx, Ok(()), "import_relay_list response");
+            }
+            Err(error) => Self::oneshot_send(tx, Err(error), "import_relay_list response"),
+        }
+    }
+
+    fn on_reload_relay_list_overrides(&mut self, tx: ResponseTx<Vec<String>, relay_list::Error>) {
+        let mut relay_list_updater = self.relay_list_updater.clone();
+        tokio::spawn(async move {
+            let result = relay_list_updater
+                .reload_overrides()
+                .await
+                .map(|errors| errors.iter().map(ToString::to_string).collect());
+            Self::oneshot_send(tx, result, "reload_relay_list_overrides response");
+        });
+    }
+
     fn on_login_account(&mut self, tx: ResponseTx<(), Error>, account_number: String) {
         let account_manager = self.account_manager.clone();
         let availability = self.api_runtime.availability_handle();
@@ -1914,6 +2907,156 @@ impl Daemon {
         });
     }
 
+    async fn on_remove_devices(
+        &mut self,
+        tx: ResponseTx<DeviceRemovalResult, Error>,
+        account_number: AccountNumber,
+        device_ids: Vec<DeviceId>,
+        all_except_current: bool,
+    ) {
+        // The current device is only known if it belongs to the same account
+        let current_device = self
+            .account_manager
+            .data()
+            .await
+            .ok()
+            .and_then(|state| state.into_device())
+            .filter(|device| device.account_number == account_number)
+            .map(|device| device.device.id);
+        let device_service = self.account_manager.device_service.clone();
+        let notifier = self.management_interface.notifier().clone();
+
+        tokio::spawn(async move {
+            let device_ids = if all_except_current {
+                match device_service.list_devices(account_number.clone()).await {
+                    Ok(devices) => devices
+                        .into_iter()
+                        .map(|device| device.id)
+                        .filter(|id| Some(id) != current_device.as_ref())
+                        .collect(),
+                    Err(error) => {
+                        Self::oneshot_send(
+                            tx,
+                            Err(Error::ListDevicesError(error)),
+                            "remove_devices response",
+                        );
+                        return;
+                    }
+                }
+            } else {
+                device_ids
+            };
+
+            let (removed, failed) = device_service
+                .remove_devices(account_number.clone(), device_ids)
+                .await;
+            for (device_id, error) in &failed {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg(&format!("Failed to remove device {device_id}"))
+                );
+            }
+
+            if !removed.is_empty() {
+                match device_service.list_devices(account_number.clone()).await {
+                    Ok(new_devices) => notifier.notify_remove_device_event(RemoveDeviceEvent {
+                        account_number,
+                        new_devices,
+                    }),
+                    Err(error) => log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to list devices after removal")
+                    ),
+                }
+            }
+
+            let result = DeviceRemovalResult {
+                removed,
+                failed: failed
+                    .into_iter()
+                    .map(|(device_id, error)| DeviceRemovalFailure {
+                        device_id,
+                        error: error.to_string(),
+                    })
+                    .collect(),
+            };
+            Self::oneshot_send(tx, Ok(result), "remove_devices response");
+        });
+    }
+
+    async fn on_queue_voucher_submission(
+        &mut self,
+        tx: ResponseTx<PendingRequest, Error>,
+        voucher: String,
+    ) {
+        let Ok(Some(device)) = self.account_manager.data().await.map(|s| s.into_device()) else {
+            Self::oneshot_send(
+                tx,
+                Err(Error::NoAccountNumber),
+                "queue_voucher_submission response",
+            );
+            return;
+        };
+        let request_queue = self.request_queue.clone();
+        tokio::spawn(async move {
+            let result = request_queue
+                .enqueue(PendingRequestKind::SubmitVoucher {
+                    account_number: device.account_number,
+                    voucher,
+                })
+                .await;
+            Self::oneshot_send(
+                tx,
+                result.map_err(Error::RequestQueue),
+                "queue_voucher_submission response",
+            );
+        });
+    }
+
+    fn on_queue_device_removal(
+        &mut self,
+        tx: ResponseTx<PendingRequest, Error>,
+        account_number: AccountNumber,
+        device_id: DeviceId,
+    ) {
+        let request_queue = self.request_queue.clone();
+        tokio::spawn(async move {
+            let result = request_queue
+                .enqueue(PendingRequestKind::RemoveDevice {
+                    account_number,
+                    device_id,
+                })
+                .await;
+            Self::oneshot_send(
+                tx,
+                result.map_err(Error::RequestQueue),
+                "queue_device_removal response",
+            );
+        });
+    }
+
+    fn on_get_pending_requests(&mut self, tx: oneshot::Sender<Vec<PendingRequest>>) {
+        let request_queue = self.request_queue.clone();
+        tokio::spawn(async move {
+            Self::oneshot_send(
+                tx,
+                request_queue.pending().await,
+                "get_pending_requests response",
+            );
+        });
+    }
+
+    fn on_cancel_pending_request(&mut self, tx: ResponseTx<(), Error>, id: PendingRequestId) {
+        let request_queue = self.request_queue.clone();
+        tokio::spawn(async move {
+            Self::oneshot_send(
+                tx,
+                request_queue.cancel(&id).await.map_err(Error::RequestQueue),
+                "cancel_pending_request response",
+            );
+        });
+    }
+
     fn on_get_account_history(&mut self, tx: oneshot::Sender<Option<AccountNumber>>) {
         Self::oneshot_send(
             tx,
@@ -2037,80 +3180,196 @@ impl Daemon {
         Self::oneshot_send(tx, result, "clear_split_tunnel_processes response");
     }
 
-    /// Update the split app paths in both the settings and tunnel
-    #[cfg(any(windows, target_os = "android"))]
-    fn set_split_tunnel_paths(
+    #[cfg(target_os = "linux")]
+    async fn on_set_split_tunnel_mode(
         &mut self,
-        tx: ResponseTx<(), Error>,
-        response_msg: &'static str,
-        settings: Settings,
-        update: ExcludedPathsUpdate,
+        tx: ResponseTx<(), settings::Error>,
+        mode: SplitTunnelMode,
     ) {
-        let new_list = match update {
-            ExcludedPathsUpdate::SetPaths(ref paths) => {
-                if *paths == settings.split_tunnel.apps {
-                    Self::oneshot_send(tx, Ok(()), response_msg);
-                    return;
+        match self
+            .settings
+            .update(|settings| settings.split_tunnel_mode = mode)
+            .await
+        {
+            Ok(settings_changed) => {
+                if settings_changed {
+                    log::info!("Split tunnel mode changed to {mode}");
+                    self.send_tunnel_command(TunnelCommand::SplitTunnelMode(
+                        mode,
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_split_tunnel_mode response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_split_tunnel_mode response");
                 }
-                paths.iter()
             }
-            ExcludedPathsUpdate::SetState(_) => settings.split_tunnel.apps.iter(),
-        };
-        let new_state = match update {
-            ExcludedPathsUpdate::SetPaths(_) => settings.split_tunnel.enable_exclusions,
-            ExcludedPathsUpdate::SetState(state) => {
-                if state == settings.split_tunnel.enable_exclusions {
-                    Self::oneshot_send(tx, Ok(()), response_msg);
-                    return;
-                }
-                state
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_split_tunnel_mode response");
             }
-        };
-
-        // Update the tunnel state
-        if new_state || new_state != settings.split_tunnel.enable_exclusions {
-            let tunnel_list = if new_state {
-                new_list
-                    .cloned()
-                    .map(SplitApp::to_tunnel_command_repr)
-                    .collect()
-            } else {
-                vec![]
-            };
-
-            let (result_tx, result_rx) = oneshot::channel();
-            self.send_tunnel_command(TunnelCommand::SetExcludedApps(result_tx, tunnel_list));
-            let daemon_tx = self.tx.clone();
-
-            tokio::spawn(async move {
-                match result_rx.await {
-                    Ok(Ok(_)) => (),
-                    Ok(Err(error)) => {
-                        log::error!(
-                            "{}",
-                            error.display_chain_with_msg("Failed to set excluded apps list")
-                        );
-                        Self::oneshot_send(tx, Err(Error::SplitTunnelError(error)), response_msg);
-                        return;
-                    }
-                    Err(_) => {
-                        log::error!("The tunnel failed to return a result");
-                        return;
-                    }
-                }
-
-                let _ = daemon_tx.send(InternalDaemonEvent::ExcludedPathsEvent(update, tx));
-            });
-        } else {
-            let _ = self
-                .tx
-                .send(InternalDaemonEvent::ExcludedPathsEvent(update, tx));
         }
     }
 
-    /// Update the split app paths in both the settings and tunnel
-    #[cfg(target_os = "macos")]
-    fn set_split_tunnel_paths(
+    #[cfg(target_os = "linux")]
+    async fn on_set_split_tunnel_owners(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        owners: Vec<SplitTunnelOwner>,
+    ) {
+        if let Err(error) = mullvad_types::split_tunnel_owner::validate(&owners) {
+            Self::oneshot_send(
+                tx,
+                Err(Error::SplitTunnelOwnerError(error)),
+                "set_split_tunnel_owners response",
+            );
+            return;
+        }
+        let new_owners = owners.clone();
+        match self
+            .settings
+            .update(move |settings| settings.split_tunnel_owners = new_owners)
+            .await
+        {
+            Ok(settings_changed) => {
+                if settings_changed {
+                    self.send_tunnel_command(TunnelCommand::SplitTunnelOwners(
+                        owners,
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_split_tunnel_owners response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_split_tunnel_owners response");
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(
+                    tx,
+                    Err(Error::SettingsError(e)),
+                    "set_split_tunnel_owners response",
+                );
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn on_set_split_tunnel_domains(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        domains: Vec<String>,
+    ) {
+        if let Err(error) = mullvad_types::split_tunnel_domain::validate(&domains) {
+            Self::oneshot_send(
+                tx,
+                Err(Error::SplitTunnelDomainError(error)),
+                "set_split_tunnel_domains response",
+            );
+            return;
+        }
+        match self
+            .settings
+            .update(move |settings| settings.split_tunnel_domains = domains)
+            .await
+        {
+            Ok(settings_changed) => {
+                if settings_changed {
+                    self.send_tunnel_command(TunnelCommand::Dns(
+                        self.dns_config(),
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_split_tunnel_domains response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_split_tunnel_domains response");
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(
+                    tx,
+                    Err(Error::SettingsError(e)),
+                    "set_split_tunnel_domains response",
+                );
+            }
+        }
+    }
+
+    /// Update the split app paths in both the settings and tunnel
+    #[cfg(any(windows, target_os = "android"))]
+    fn set_split_tunnel_paths(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        response_msg: &'static str,
+        settings: Settings,
+        update: ExcludedPathsUpdate,
+    ) {
+        let new_list = match update {
+            ExcludedPathsUpdate::SetPaths(ref paths) => {
+                if *paths == settings.split_tunnel.apps {
+                    Self::oneshot_send(tx, Ok(()), response_msg);
+                    return;
+                }
+                paths.iter()
+            }
+            ExcludedPathsUpdate::SetState(_) => settings.split_tunnel.apps.iter(),
+        };
+        let new_state = match update {
+            ExcludedPathsUpdate::SetPaths(_) => settings.split_tunnel.enable_exclusions,
+            ExcludedPathsUpdate::SetState(state) => {
+                if state == settings.split_tunnel.enable_exclusions {
+                    Self::oneshot_send(tx, Ok(()), response_msg);
+                    return;
+                }
+                state
+            }
+        };
+
+        // Update the tunnel state
+        if new_state || new_state != settings.split_tunnel.enable_exclusions {
+            let tunnel_list = if new_state {
+                new_list
+                    .cloned()
+                    .map(SplitApp::to_tunnel_command_repr)
+                    .collect()
+            } else {
+                vec![]
+            };
+
+            let (result_tx, result_rx) = oneshot::channel();
+            self.send_tunnel_command(TunnelCommand::SetExcludedApps(result_tx, tunnel_list));
+            let daemon_tx = self.tx.clone();
+
+            tokio::spawn(async move {
+                match result_rx.await {
+                    Ok(Ok(_)) => (),
+                    Ok(Err(error)) => {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg("Failed to set excluded apps list")
+                        );
+                        Self::oneshot_send(tx, Err(Error::SplitTunnelError(error)), response_msg);
+                        return;
+                    }
+                    Err(_) => {
+                        log::error!("The tunnel failed to return a result");
+                        return;
+                    }
+                }
+
+                let _ = daemon_tx.send(InternalDaemonEvent::ExcludedPathsEvent(update, tx));
+            });
+        } else {
+            let _ = self
+                .tx
+                .send(InternalDaemonEvent::ExcludedPathsEvent(update, tx));
+        }
+    }
+
+    /// Update the split app paths in both the settings and tunnel
+    #[cfg(target_os = "macos")]
+    fn set_split_tunnel_paths(
         &mut self,
         tx: ResponseTx<(), Error>,
         _response_msg: &'static str,
@@ -2238,6 +3497,82 @@ impl Daemon {
         }
     }
 
+    #[cfg(target_os = "macos")]
+    fn check_conflicting_software(&self) {
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let conflicts = conflicting_software::detect().await;
+            let _ = tx.send(InternalDaemonEvent::ConflictingSoftware(conflicts));
+        });
+    }
+
+    #[cfg(target_os = "macos")]
+    fn handle_conflicting_software(&mut self, conflicts: Vec<ConflictingSoftware>) {
+        if conflicts == self.conflicting_software {
+            return;
+        }
+        for conflict in &conflicts {
+            log::warn!("Detected software that may interfere with the tunnel: {conflict}");
+        }
+        self.conflicting_software = conflicts.clone();
+        self.management_interface
+            .notifier()
+            .notify_conflicting_software(conflicts);
+    }
+
+    #[cfg(windows)]
+    fn on_get_driver_status(&self, tx: ResponseTx<Vec<DriverStatus>, Error>) {
+        tokio::task::spawn_blocking(move || {
+            let result = talpid_core::drivers::query_all().map_err(Error::DriverError);
+            Self::oneshot_send(tx, result, "get_driver_status response");
+        });
+    }
+
+    #[cfg(target_os = "macos")]
+    fn on_get_pf_diagnostics(&self, tx: ResponseTx<String, Error>) {
+        let anchor = self.pf_anchor.clone();
+        tokio::task::spawn_blocking(move || {
+            let result =
+                talpid_core::firewall::describe_rules(&anchor).map_err(Error::PfDiagnosticsError);
+            Self::oneshot_send(tx, result, "get_pf_diagnostics response");
+        });
+    }
+
+    #[cfg(target_os = "linux")]
+    fn on_get_active_dns_backend(&self, tx: ResponseTx<DnsBackend, Error>) {
+        let backends = self.dns_backends.clone();
+        tokio::task::spawn_blocking(move || {
+            let result =
+                talpid_core::dns::active_backend(&backends).map_err(Error::DnsBackendError);
+            Self::oneshot_send(tx, result, "get_active_dns_backend response");
+        });
+    }
+
+    #[cfg(windows)]
+    fn on_get_wfp_diagnostics(&self, tx: ResponseTx<String, Error>) {
+        tokio::task::spawn_blocking(move || {
+            let result =
+                talpid_core::firewall::describe_filters().map_err(Error::WfpDiagnosticsError);
+            Self::oneshot_send(tx, result, "get_wfp_diagnostics response");
+        });
+    }
+
+    #[cfg(windows)]
+    fn on_repair_driver(&self, tx: ResponseTx<DriverRepairResult, Error>, driver: Driver) {
+        // Removing a driver while an adapter is using it may leave it stuck in a pending state
+        if !matches!(
+            self.tunnel_state,
+            TunnelState::Disconnected { .. } | TunnelState::Paused | TunnelState::Error(_)
+        ) {
+            Self::oneshot_send(tx, Err(Error::DriverInUse), "repair_driver response");
+            return;
+        }
+        tokio::task::spawn_blocking(move || {
+            let result = talpid_core::drivers::repair(driver).map_err(Error::DriverError);
+            Self::oneshot_send(tx, result, "repair_driver response");
+        });
+    }
+
     async fn on_set_relay_settings(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
@@ -2262,10 +3597,23 @@ impl Daemon {
         }
     }
 
-    async fn on_set_allow_lan(&mut self, tx: ResponseTx<(), settings::Error>, allow_lan: bool) {
+    async fn on_set_allow_lan(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        allow_lan: mullvad_types::lan_sharing::AllowedLan,
+    ) {
+        if let Err(error) = mullvad_types::lan_sharing::validate(&allow_lan) {
+            Self::oneshot_send(
+                tx,
+                Err(Error::LanSharingError(error)),
+                "set_allow_lan response",
+            );
+            return;
+        }
+        let new_allow_lan = allow_lan.clone();
         match self
             .settings
-            .update(move |settings| settings.allow_lan = allow_lan)
+            .update(move |settings| settings.allow_lan = new_allow_lan)
             .await
         {
             Ok(settings_changed) => {
@@ -2282,7 +3630,7 @@ impl Daemon {
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set_allow_lan response");
+                Self::oneshot_send(tx, Err(Error::SettingsError(e)), "set_allow_lan response");
             }
         }
     }
@@ -2311,119 +3659,341 @@ impl Daemon {
         }
     }
 
-    #[cfg(not(target_os = "android"))]
-    async fn on_set_block_when_disconnected(
+    async fn on_set_prefer_low_latency_relays(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
-        block_when_disconnected: bool,
+        enabled: bool,
     ) {
         match self
             .settings
-            .update(move |settings| settings.block_when_disconnected = block_when_disconnected)
+            .update(move |settings| settings.prefer_low_latency_relays = enabled)
             .await
         {
-            Ok(settings_changed) => {
-                if settings_changed {
-                    self.send_tunnel_command(TunnelCommand::BlockWhenDisconnected(
-                        block_when_disconnected,
-                        oneshot_map(tx, |tx, ()| {
-                            Self::oneshot_send(tx, Ok(()), "set_block_when_disconnected response");
-                        }),
-                    ));
-                } else {
-                    Self::oneshot_send(tx, Ok(()), "set_block_when_disconnected response");
-                }
+            Ok(_) => {
+                // The relay selector and latency monitor are updated by the settings listeners
+                Self::oneshot_send(tx, Ok(()), "set_prefer_low_latency_relays response");
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set_block_when_disconnected response");
+                Self::oneshot_send(tx, Err(e), "set_prefer_low_latency_relays response");
             }
         }
     }
 
-    async fn on_set_auto_connect(
-        &mut self,
-        tx: ResponseTx<(), settings::Error>,
-        auto_connect: bool,
-    ) {
+    async fn on_set_sticky_relays(&mut self, tx: ResponseTx<(), settings::Error>, enabled: bool) {
         match self
             .settings
-            .update(move |settings| settings.auto_connect = auto_connect)
+            .update(move |settings| settings.sticky_relays = enabled)
             .await
         {
-            Ok(_settings_changed) => {
-                Self::oneshot_send(tx, Ok(()), "set auto-connect response");
+            Ok(_) => {
+                // The relay selector is updated by the settings listeners
+                Self::oneshot_send(tx, Ok(()), "set_sticky_relays response");
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set auto-connect response");
+                Self::oneshot_send(tx, Err(e), "set_sticky_relays response");
             }
         }
     }
 
-    async fn on_set_openvpn_mssfix(
+    #[cfg(not(target_os = "android"))]
+    async fn on_set_block_when_disconnected(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
-        mssfix: Option<u16>,
+        block_when_disconnected: bool,
     ) {
+        // Changing the setting ends any pause
+        let was_paused = self.lockdown_pause.take().is_some();
         match self
             .settings
-            .update(move |settings| settings.tunnel_options.openvpn.mssfix = mssfix)
+            .update(move |settings| settings.block_when_disconnected = block_when_disconnected)
             .await
         {
             Ok(settings_changed) => {
-                Self::oneshot_send(tx, Ok(()), "set_openvpn_mssfix response");
-                if settings_changed && self.get_target_tunnel_type() == Some(TunnelType::OpenVpn) {
-                    log::info!(
-                        "Initiating tunnel restart because the OpenVPN mssfix setting changed"
-                    );
-                    self.reconnect_tunnel();
+                if settings_changed || was_paused {
+                    self.send_tunnel_command(TunnelCommand::BlockWhenDisconnected(
+                        self.block_when_disconnected(),
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_block_when_disconnected response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_block_when_disconnected response");
                 }
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set_openvpn_mssfix response");
+                Self::oneshot_send(tx, Err(e), "set_block_when_disconnected response");
             }
         }
     }
 
-    async fn on_set_bridge_settings(
+    #[cfg(not(target_os = "android"))]
+    async fn on_set_firewall_exceptions(
         &mut self,
         tx: ResponseTx<(), Error>,
-        new_settings: BridgeSettings,
+        exceptions: Vec<mullvad_types::firewall_exception::FirewallException>,
     ) {
-        if new_settings.custom.is_none() && new_settings.bridge_type == BridgeType::Custom {
-            log::info!("Tried to select custom bridge but no custom bridge settings exist");
+        if let Err(error) = mullvad_types::firewall_exception::validate(&exceptions) {
             Self::oneshot_send(
                 tx,
-                Err(Error::NoCustomProxySaved),
-                "set_bridge_settings response",
+                Err(Error::FirewallExceptionError(error)),
+                "set_firewall_exceptions response",
             );
             return;
         }
-
         match self
             .settings
-            .update(move |settings| settings.bridge_settings = new_settings)
+            .update(move |settings| settings.firewall_exceptions = exceptions)
             .await
         {
-            Ok(settings_changes) => {
-                if settings_changes {
-                    let access_mode_handler = self.access_mode_handler.clone();
-                    tokio::spawn(async move {
-                        if let Err(error) = access_mode_handler.rotate().await {
-                            log::error!("Failed to rotate API endpoint: {error}");
-                        }
-                    });
-                    self.reconnect_tunnel();
-                };
-                Self::oneshot_send(tx, Ok(()), "set_bridge_settings");
+            Ok(settings_changed) => {
+                if settings_changed {
+                    self.send_tunnel_command(TunnelCommand::FirewallExceptions(
+                        self.firewall_exceptions(),
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_firewall_exceptions response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_firewall_exceptions response");
+                }
             }
-
             Err(e) => {
-                log::error!(
-                    "{}",
-                    e.display_chain_with_msg("Failed to set new bridge settings")
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(
+                    tx,
+                    Err(Error::SettingsError(e)),
+                    "set_firewall_exceptions response",
+                );
+            }
+        }
+    }
+
+    /// Traffic that is allowed outside the tunnel: the user's exceptions, and any traffic to a
+    /// captive portal.
+    #[cfg(not(target_os = "android"))]
+    fn firewall_exceptions(&self) -> Vec<mullvad_types::firewall_exception::FirewallException> {
+        let mut exceptions = self.settings.firewall_exceptions.clone();
+        exceptions.extend(self.captive_portal.firewall_exceptions());
+        exceptions
+    }
+
+    /// Apply the current firewall exceptions. The returned channel is closed once the firewall
+    /// policy has been set, regardless of whether it succeeded.
+    #[cfg(not(target_os = "android"))]
+    fn update_firewall_exceptions(&self) -> oneshot::Receiver<()> {
+        let (tx, rx) = oneshot::channel();
+        self.send_tunnel_command(TunnelCommand::FirewallExceptions(
+            self.firewall_exceptions(),
+            tx,
+        ));
+        rx
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn on_set_allowed_incoming(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        allowed_incoming: Vec<mullvad_types::allowed_incoming::AllowedIncoming>,
+    ) {
+        if let Err(error) = mullvad_types::allowed_incoming::validate(&allowed_incoming) {
+            Self::oneshot_send(
+                tx,
+                Err(Error::AllowedIncomingError(error)),
+                "set_allowed_incoming response",
+            );
+            return;
+        }
+        let new_allowed_incoming = allowed_incoming.clone();
+        match self
+            .settings
+            .update(move |settings| settings.allowed_incoming = new_allowed_incoming)
+            .await
+        {
+            Ok(settings_changed) => {
+                if settings_changed {
+                    self.send_tunnel_command(TunnelCommand::AllowIncoming(
+                        allowed_incoming,
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_allowed_incoming response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_allowed_incoming response");
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(
+                    tx,
+                    Err(Error::SettingsError(e)),
+                    "set_allowed_incoming response",
+                );
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "android"))]
+    fn on_pause_lockdown_mode(&mut self, tx: ResponseTx<(), Error>, duration: Duration) {
+        let result = self
+            .pause_lockdown_mode(duration)
+            .map_err(Error::LockdownPauseError);
+        Self::oneshot_send(tx, result, "pause_lockdown_mode response");
+    }
+
+    #[cfg(not(target_os = "android"))]
+    fn on_allow_captive_portal_login(&mut self, tx: ResponseTx<(), Error>, duration: Duration) {
+        let result = self
+            .allow_captive_portal_login(duration)
+            .map_err(Error::CaptivePortalError);
+        Self::oneshot_send(tx, result, "allow_captive_portal_login response");
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn on_set_block_all(&mut self, tx: ResponseTx<(), settings::Error>, block_all: bool) {
+        match self
+            .settings
+            .update(move |settings| settings.block_all = block_all)
+            .await
+        {
+            Ok(settings_changed) => {
+                if settings_changed {
+                    if block_all {
+                        log::info!("Blocking all traffic");
+                        self.block_all_traffic();
+                    } else {
+                        log::info!("No longer blocking all traffic");
+                        match *self.target_state {
+                            TargetState::Secured => self.connect_tunnel(),
+                            TargetState::Unsecured => self.disconnect_tunnel(),
+                        }
+                    }
+                }
+                Self::oneshot_send(tx, Ok(()), "set_block_all response");
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_block_all response");
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn on_set_state_hook(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        event: mullvad_types::state_hooks::HookEvent,
+        path: Option<PathBuf>,
+    ) {
+        let result = async {
+            if let Some(path) = &path {
+                state_hooks::validate(path).map_err(Error::StateHookError)?;
+            }
+            self.settings
+                .update(move |settings| settings.state_hooks.set(event, path))
+                .await
+                .map_err(Error::SettingsError)?;
+            Ok(())
+        }
+        .await;
+        Self::oneshot_send(tx, result, "set_state_hook response");
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn on_set_metrics_settings(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        metrics: mullvad_types::settings::MetricsSettings,
+    ) {
+        let result = self.set_metrics_settings(metrics).await;
+        Self::oneshot_send(tx, result, "set_metrics_settings response");
+    }
+
+    async fn on_set_auto_connect(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        auto_connect: bool,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.auto_connect = auto_connect)
+            .await
+        {
+            Ok(_settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set auto-connect response");
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set auto-connect response");
+            }
+        }
+    }
+
+    async fn on_set_openvpn_mssfix(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        mssfix: Option<u16>,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.tunnel_options.openvpn.mssfix = mssfix)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_openvpn_mssfix response");
+                if settings_changed && self.get_target_tunnel_type() == Some(TunnelType::OpenVpn) {
+                    log::info!(
+                        "Initiating tunnel restart because the OpenVPN mssfix setting changed"
+                    );
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_openvpn_mssfix response");
+            }
+        }
+    }
+
+    async fn on_set_bridge_settings(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        new_settings: BridgeSettings,
+    ) {
+        if new_settings.custom.is_none() && new_settings.bridge_type == BridgeType::Custom {
+            log::info!("Tried to select custom bridge but no custom bridge settings exist");
+            Self::oneshot_send(
+                tx,
+                Err(Error::NoCustomProxySaved),
+                "set_bridge_settings response",
+            );
+            return;
+        }
+
+        match self
+            .settings
+            .update(move |settings| settings.bridge_settings = new_settings)
+            .await
+        {
+            Ok(settings_changes) => {
+                if settings_changes {
+                    let access_mode_handler = self.access_mode_handler.clone();
+                    tokio::spawn(async move {
+                        if let Err(error) = access_mode_handler.rotate().await {
+                            log::error!("Failed to rotate API endpoint: {error}");
+                        }
+                    });
+                    self.reconnect_tunnel();
+                };
+                Self::oneshot_send(tx, Ok(()), "set_bridge_settings");
+            }
+
+            Err(e) => {
+                log::error!(
+                    "{}",
+                    e.display_chain_with_msg("Failed to set new bridge settings")
                 );
                 Self::oneshot_send(tx, Err(Error::SettingsError(e)), "set_bridge_settings");
             }
@@ -2504,6 +4074,110 @@ impl Daemon {
         }
     }
 
+    async fn on_set_wireguard_ipv6_only(&mut self, tx: ResponseTx<(), Error>, ipv6_only: bool) {
+        if ipv6_only && self.settings.tunnel_options.generic.block_ipv6 {
+            Self::oneshot_send(
+                tx,
+                Err(Error::BlockIpv6WithIpv6Only),
+                "set_wireguard_ipv6_only response",
+            );
+            return;
+        }
+        match self
+            .settings
+            .update(|settings| settings.tunnel_options.wireguard.ipv6_only = ipv6_only)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_wireguard_ipv6_only response");
+                if settings_changed && self.get_target_tunnel_type() == Some(TunnelType::Wireguard)
+                {
+                    log::info!("Initiating tunnel restart because the IPv6-only setting changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(
+                    tx,
+                    Err(Error::SettingsError(e)),
+                    "set_wireguard_ipv6_only response",
+                );
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn on_set_block_ipv6(&mut self, tx: ResponseTx<(), Error>, block_ipv6: bool) {
+        if block_ipv6 && self.settings.tunnel_options.wireguard.ipv6_only {
+            Self::oneshot_send(
+                tx,
+                Err(Error::BlockIpv6WithIpv6Only),
+                "set_block_ipv6 response",
+            );
+            return;
+        }
+        match self
+            .settings
+            .update(|settings| settings.tunnel_options.generic.block_ipv6 = block_ipv6)
+            .await
+        {
+            Ok(settings_changed) => {
+                if settings_changed {
+                    self.send_tunnel_command(TunnelCommand::BlockIpv6(
+                        block_ipv6,
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_block_ipv6 response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_block_ipv6 response");
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(Error::SettingsError(e)), "set_block_ipv6 response");
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn on_set_custom_routes(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        routes: Vec<mullvad_types::custom_route::CustomRoute>,
+    ) {
+        if let Err(error) = mullvad_types::custom_route::validate(&routes) {
+            Self::oneshot_send(
+                tx,
+                Err(Error::CustomRouteError(error)),
+                "set_custom_routes response",
+            );
+            return;
+        }
+        match self
+            .settings
+            .update(move |settings| settings.tunnel_options.generic.custom_routes = routes)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_custom_routes response");
+                if settings_changed {
+                    log::info!("Initiating tunnel restart because the custom routes changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(
+                    tx,
+                    Err(Error::SettingsError(e)),
+                    "set_custom_routes response",
+                );
+            }
+        }
+    }
+
     async fn on_set_quantum_resistant_tunnel(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
@@ -2531,14 +4205,93 @@ impl Daemon {
         }
     }
 
-    #[cfg(daita)]
-    async fn on_set_daita_enabled(&mut self, tx: ResponseTx<(), settings::Error>, value: bool) {
-        let result = self
+    async fn on_set_quantum_resistant_kem(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        kem: QuantumResistantKem,
+    ) {
+        match self
             .settings
-            .update(|settings| {
-                settings.tunnel_options.wireguard.daita.enabled = value;
-            })
-            .await;
+            .update(|settings| settings.tunnel_options.wireguard.quantum_resistant_kem = kem)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_quantum_resistant_kem response");
+                if settings_changed
+                    && self
+                        .settings
+                        .tunnel_options
+                        .wireguard
+                        .quantum_resistant
+                        .enabled()
+                    && self.get_target_tunnel_type() == Some(TunnelType::Wireguard)
+                {
+                    log::info!("Reconnecting because the quantum-resistant KEM changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_quantum_resistant_kem response");
+            }
+        }
+    }
+
+    async fn on_set_negotiation_retry_policy(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        policy: NegotiationRetryPolicy,
+    ) {
+        // The policy only affects the next negotiation, so there is no need to reconnect
+        match self
+            .settings
+            .update(|settings| settings.tunnel_options.wireguard.negotiation_retry_policy = policy)
+            .await
+        {
+            Ok(_) => Self::oneshot_send(tx, Ok(()), "set_negotiation_retry_policy response"),
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_negotiation_retry_policy response");
+            }
+        }
+    }
+
+    async fn on_set_connectivity_check_options(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        options: ConnectivityCheckOptions,
+    ) {
+        match self
+            .settings
+            .update(|settings| settings.tunnel_options.wireguard.connectivity_check = options)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_connectivity_check_options response");
+                if settings_changed {
+                    if let Some(TunnelType::Wireguard) = self.get_connected_tunnel_type() {
+                        log::info!(
+                            "Initiating tunnel restart because the connectivity check changed"
+                        );
+                        self.reconnect_tunnel();
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_connectivity_check_options response");
+            }
+        }
+    }
+
+    #[cfg(daita)]
+    async fn on_set_daita_enabled(&mut self, tx: ResponseTx<(), settings::Error>, value: bool) {
+        let result = self
+            .settings
+            .update(|settings| {
+                settings.tunnel_options.wireguard.daita.enabled = value;
+            })
+            .await;
 
         match result {
             Ok(settings_changed) => {
@@ -2556,174 +4309,551 @@ impl Daemon {
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set_daita_enabled response");
+                Self::oneshot_send(tx, Err(e), "set_daita_enabled response");
+            }
+        }
+    }
+
+    #[cfg(daita)]
+    async fn on_set_daita_use_multihop_if_necessary(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        value: bool,
+    ) {
+        match self
+            .settings
+            .update(|settings| {
+                settings
+                    .tunnel_options
+                    .wireguard
+                    .daita
+                    .use_multihop_if_necessary = value
+            })
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_daita_use_multihop_if_necessary response");
+
+                let RelaySettings::Normal(constraints) = &self.settings.relay_settings else {
+                    return; // DAITA is not supported for custom relays
+                };
+
+                let wireguard_enabled = constraints.tunnel_protocol == TunnelType::Wireguard;
+
+                let daita_enabled = self.settings.tunnel_options.wireguard.daita.enabled;
+
+                if settings_changed && wireguard_enabled && daita_enabled {
+                    log::info!("Reconnecting because DAITA settings changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_daita_use_multihop_if_necessary response");
+            }
+        }
+    }
+
+    #[cfg(daita)]
+    async fn on_set_daita_settings(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        daita_settings: DaitaSettings,
+    ) {
+        match self
+            .settings
+            .update(|settings| settings.tunnel_options.wireguard.daita = daita_settings)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_daita_settings response");
+                if settings_changed && self.get_target_tunnel_type() != Some(TunnelType::OpenVpn) {
+                    log::info!("Reconnecting because DAITA settings changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_daita_settings response");
+            }
+        }
+    }
+
+    /// DNS config of the current settings, including the domains blocked by the filter lists and
+    /// the domains that are split from the tunnel.
+    #[cfg(not(target_os = "android"))]
+    fn dns_config(&self) -> DnsConfig {
+        dns::addresses_from_options(&self.settings.tunnel_options.dns_options)
+            .with_blocked_domains(self.dns_blocklist.clone())
+            .with_bypassed_domains(dns::bypassed_domains(&self.settings))
+    }
+
+    /// DNS config of the current settings.
+    #[cfg(target_os = "android")]
+    fn dns_config(&self) -> DnsConfig {
+        dns::addresses_from_options(&self.settings.tunnel_options.dns_options)
+    }
+
+    async fn on_set_dns_options(&mut self, tx: ResponseTx<(), Error>, dns_options: DnsOptions) {
+        if let Err(error) = mullvad_types::split_dns::validate(&dns_options.split_dns_rules) {
+            Self::oneshot_send(
+                tx,
+                Err(Error::SplitDnsError(error)),
+                "set_dns_options response",
+            );
+            return;
+        }
+        if let Err(error) = mullvad_types::dns_filter::validate(&dns_options.filter_lists) {
+            Self::oneshot_send(
+                tx,
+                Err(Error::DnsFilterError(error)),
+                "set_dns_options response",
+            );
+            return;
+        }
+        match self
+            .settings
+            .update(move |settings| settings.tunnel_options.dns_options = dns_options)
+            .await
+        {
+            Ok(settings_changed) => {
+                if settings_changed {
+                    #[cfg(not(target_os = "android"))]
+                    self.update_dns_filter_lists();
+                    self.send_tunnel_command(TunnelCommand::Dns(
+                        self.dns_config(),
+                        oneshot_map(tx, |tx, ()| {
+                            Self::oneshot_send(tx, Ok(()), "set_dns_options response");
+                        }),
+                    ));
+                } else {
+                    Self::oneshot_send(tx, Ok(()), "set_dns_options response");
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(Error::SettingsError(e)), "set_dns_options response");
+            }
+        }
+    }
+
+    async fn on_set_relay_override(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        relay_override: RelayOverride,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.set_relay_override(relay_override))
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_relay_override response");
+                if settings_changed {
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_relay_override response");
+            }
+        }
+    }
+
+    async fn on_clear_all_relay_overrides(&mut self, tx: ResponseTx<(), settings::Error>) {
+        match self
+            .settings
+            .update(move |settings| settings.relay_overrides.clear())
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "clear_all_relay_overrides response");
+                if settings_changed {
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "clear_all_relay_overrides response");
+            }
+        }
+    }
+
+    async fn on_set_wireguard_mtu(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        mtu: Option<u16>,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.tunnel_options.wireguard.mtu = mtu)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_wireguard_mtu response");
+                if settings_changed {
+                    if let Some(TunnelType::Wireguard) = self.get_connected_tunnel_type() {
+                        log::info!(
+                            "Initiating tunnel restart because the WireGuard MTU setting changed"
+                        );
+                        self.reconnect_tunnel();
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_wireguard_mtu response");
+            }
+        }
+    }
+
+    async fn on_set_wireguard_persistent_keepalive(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        interval: Option<u16>,
+    ) {
+        match self
+            .settings
+            .update(move |settings| {
+                settings.tunnel_options.wireguard.persistent_keepalive = interval
+            })
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_wireguard_persistent_keepalive response");
+                if settings_changed {
+                    if let Some(TunnelType::Wireguard) = self.get_connected_tunnel_type() {
+                        log::info!(
+                            "Initiating tunnel restart because the WireGuard keepalive setting changed"
+                        );
+                        self.reconnect_tunnel();
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_wireguard_persistent_keepalive response");
+            }
+        }
+    }
+
+    async fn on_set_wireguard_worker_threads(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        threads: Option<u32>,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.tunnel_options.wireguard.worker_threads = threads)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_wireguard_worker_threads response");
+                if settings_changed {
+                    if let Some(TunnelType::Wireguard) = self.get_connected_tunnel_type() {
+                        log::info!(
+                            "Initiating tunnel restart because the WireGuard worker thread count changed"
+                        );
+                        self.reconnect_tunnel();
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_wireguard_worker_threads response");
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn on_set_wireguard_outgoing_interface(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        interface: Option<String>,
+    ) {
+        match self
+            .settings
+            .update(move |settings| {
+                settings.tunnel_options.wireguard.outgoing_interface = interface
+            })
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_wireguard_outgoing_interface response");
+                if settings_changed {
+                    if let Some(TunnelType::Wireguard) = self.get_connected_tunnel_type() {
+                        log::info!(
+                            "Initiating tunnel restart because the WireGuard outgoing interface changed"
+                        );
+                        self.reconnect_tunnel();
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_wireguard_outgoing_interface response");
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn on_set_wireguard_backend(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        backend: Option<talpid_types::net::wireguard::Backend>,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.tunnel_options.wireguard.backend = backend)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_wireguard_backend response");
+                if settings_changed {
+                    if let Some(TunnelType::Wireguard) = self.get_connected_tunnel_type() {
+                        log::info!(
+                            "Initiating tunnel restart because the WireGuard backend changed"
+                        );
+                        self.reconnect_tunnel();
+                    }
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_wireguard_backend response");
             }
         }
     }
 
-    #[cfg(daita)]
-    async fn on_set_daita_use_multihop_if_necessary(
+    #[cfg(target_os = "linux")]
+    async fn on_set_tunnel_fwmark(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
-        value: bool,
+        fwmark: Option<u32>,
     ) {
         match self
             .settings
-            .update(|settings| {
-                settings
-                    .tunnel_options
-                    .wireguard
-                    .daita
-                    .use_multihop_if_necessary = value
-            })
+            .update(move |settings| settings.tunnel_fwmark = fwmark)
             .await
         {
             Ok(settings_changed) => {
-                Self::oneshot_send(tx, Ok(()), "set_daita_use_multihop_if_necessary response");
-
-                let RelaySettings::Normal(constraints) = &self.settings.relay_settings else {
-                    return; // DAITA is not supported for custom relays
-                };
-
-                let wireguard_enabled = constraints.tunnel_protocol == TunnelType::Wireguard;
-
-                let daita_enabled = self.settings.tunnel_options.wireguard.daita.enabled;
-
-                if settings_changed && wireguard_enabled && daita_enabled {
-                    log::info!("Reconnecting because DAITA settings changed");
-                    self.reconnect_tunnel();
+                Self::oneshot_send(tx, Ok(()), "set_tunnel_fwmark response");
+                if settings_changed {
+                    // The mark is shared by the firewall, routing rules and tunnel sockets, so it
+                    // cannot safely be swapped out while they are in use.
+                    log::info!(
+                        "Tunnel fwmark changed to {:#x}. It will be applied when the daemon is restarted",
+                        self.settings.tunnel_fwmark()
+                    );
                 }
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set_daita_use_multihop_if_necessary response");
+                Self::oneshot_send(tx, Err(e), "set_tunnel_fwmark response");
             }
         }
     }
 
-    #[cfg(daita)]
-    async fn on_set_daita_settings(
+    #[cfg(target_os = "linux")]
+    async fn on_set_nftables_settings(
         &mut self,
-        tx: ResponseTx<(), settings::Error>,
-        daita_settings: DaitaSettings,
+        tx: ResponseTx<(), Error>,
+        nftables: mullvad_types::settings::NftablesSettings,
     ) {
+        if !nftables.is_valid() {
+            Self::oneshot_send(
+                tx,
+                Err(Error::InvalidNftablesPriority(nftables.priority)),
+                "set_nftables_settings response",
+            );
+            return;
+        }
         match self
             .settings
-            .update(|settings| settings.tunnel_options.wireguard.daita = daita_settings)
+            .update(move |settings| settings.nftables = nftables)
             .await
         {
             Ok(settings_changed) => {
-                Self::oneshot_send(tx, Ok(()), "set_daita_settings response");
-                if settings_changed && self.get_target_tunnel_type() != Some(TunnelType::OpenVpn) {
-                    log::info!("Reconnecting because DAITA settings changed");
-                    self.reconnect_tunnel();
+                Self::oneshot_send(tx, Ok(()), "set_nftables_settings response");
+                if settings_changed {
+                    // The firewall is created with these options when the daemon starts
+                    log::info!(
+                        "nftables settings changed. They take effect when the daemon is restarted"
+                    );
                 }
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set_daita_settings response");
+                Self::oneshot_send(
+                    tx,
+                    Err(Error::SettingsError(e)),
+                    "set_nftables_settings response",
+                );
             }
         }
     }
 
-    async fn on_set_dns_options(
+    #[cfg(target_os = "linux")]
+    async fn on_set_routing_settings(
         &mut self,
-        tx: ResponseTx<(), settings::Error>,
-        dns_options: DnsOptions,
+        tx: ResponseTx<(), Error>,
+        routing: mullvad_types::settings::RoutingSettings,
     ) {
+        let validation = if !routing.is_valid_table_id() {
+            routing.table_id.map(Error::InvalidRoutingTable)
+        } else if !routing.is_valid_rule_priority() {
+            routing.rule_priority.map(Error::InvalidRulePriority)
+        } else {
+            None
+        };
+        if let Some(error) = validation {
+            Self::oneshot_send(tx, Err(error), "set_routing_settings response");
+            return;
+        }
         match self
             .settings
-            .update(move |settings| settings.tunnel_options.dns_options = dns_options)
+            .update(move |settings| settings.routing = routing)
             .await
         {
             Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_routing_settings response");
                 if settings_changed {
-                    let settings = self.settings.settings();
-                    let resolvers =
-                        dns::addresses_from_options(&settings.tunnel_options.dns_options);
-                    self.send_tunnel_command(TunnelCommand::Dns(
-                        resolvers,
-                        oneshot_map(tx, |tx, ()| {
-                            Self::oneshot_send(tx, Ok(()), "set_dns_options response");
-                        }),
-                    ));
-                } else {
-                    Self::oneshot_send(tx, Ok(()), "set_dns_options response");
+                    // The route manager is created with these options when the daemon starts
+                    log::info!(
+                        "Routing settings changed. They take effect when the daemon is restarted"
+                    );
                 }
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set_dns_options response");
+                Self::oneshot_send(
+                    tx,
+                    Err(Error::SettingsError(e)),
+                    "set_routing_settings response",
+                );
             }
         }
     }
 
-    async fn on_set_relay_override(
-        &mut self,
-        tx: ResponseTx<(), settings::Error>,
-        relay_override: RelayOverride,
-    ) {
+    #[cfg(target_os = "macos")]
+    async fn on_set_pf_anchor(&mut self, tx: ResponseTx<(), Error>, anchor: Option<String>) {
+        if let Some(anchor) = anchor
+            .as_ref()
+            .filter(|anchor| !mullvad_types::settings::is_valid_pf_anchor(anchor))
+        {
+            Self::oneshot_send(
+                tx,
+                Err(Error::InvalidPfAnchor(anchor.clone())),
+                "set_pf_anchor response",
+            );
+            return;
+        }
         match self
             .settings
-            .update(move |settings| settings.set_relay_override(relay_override))
+            .update(move |settings| settings.pf_anchor = anchor)
             .await
         {
             Ok(settings_changed) => {
-                Self::oneshot_send(tx, Ok(()), "set_relay_override response");
+                Self::oneshot_send(tx, Ok(()), "set_pf_anchor response");
                 if settings_changed {
-                    self.reconnect_tunnel();
+                    // Moving the rules to another anchor would leave the old anchor unmanaged
+                    log::info!(
+                        "PF anchor changed. It will be applied when the daemon is restarted"
+                    );
                 }
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set_relay_override response");
+                Self::oneshot_send(tx, Err(Error::SettingsError(e)), "set_pf_anchor response");
             }
         }
     }
 
-    async fn on_clear_all_relay_overrides(&mut self, tx: ResponseTx<(), settings::Error>) {
+    #[cfg(target_os = "linux")]
+    async fn on_set_dns_backends(&mut self, tx: ResponseTx<(), Error>, backends: Vec<DnsBackend>) {
+        if !mullvad_types::settings::is_valid_dns_backend_order(&backends) {
+            Self::oneshot_send(
+                tx,
+                Err(Error::InvalidDnsBackendOrder),
+                "set_dns_backends response",
+            );
+            return;
+        }
         match self
             .settings
-            .update(move |settings| settings.relay_overrides.clear())
+            .update(move |settings| settings.dns_backends = backends)
             .await
         {
             Ok(settings_changed) => {
-                Self::oneshot_send(tx, Ok(()), "clear_all_relay_overrides response");
+                Self::oneshot_send(tx, Ok(()), "set_dns_backends response");
                 if settings_changed {
-                    self.reconnect_tunnel();
+                    log::info!(
+                        "DNS backends changed. They will be applied when the daemon is restarted"
+                    );
                 }
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "clear_all_relay_overrides response");
+                Self::oneshot_send(
+                    tx,
+                    Err(Error::SettingsError(e)),
+                    "set_dns_backends response",
+                );
             }
         }
     }
 
-    async fn on_set_wireguard_mtu(
+    #[cfg(not(target_os = "android"))]
+    async fn on_set_offline_monitor_backend(
         &mut self,
         tx: ResponseTx<(), settings::Error>,
-        mtu: Option<u16>,
+        backend: talpid_types::net::OfflineMonitorBackend,
     ) {
         match self
             .settings
-            .update(move |settings| settings.tunnel_options.wireguard.mtu = mtu)
+            .update(move |settings| settings.offline_monitor = backend)
             .await
         {
             Ok(settings_changed) => {
-                Self::oneshot_send(tx, Ok(()), "set_wireguard_mtu response");
+                Self::oneshot_send(tx, Ok(()), "set_offline_monitor_backend response");
                 if settings_changed {
-                    if let Some(TunnelType::Wireguard) = self.get_connected_tunnel_type() {
-                        log::info!(
-                            "Initiating tunnel restart because the WireGuard MTU setting changed"
-                        );
-                        self.reconnect_tunnel();
-                    }
+                    log::info!(
+                        "Offline monitor backend changed to {backend}. It will be applied when \
+                         the daemon is restarted"
+                    );
                 }
             }
             Err(e) => {
                 log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
-                Self::oneshot_send(tx, Err(e), "set_wireguard_mtu response");
+                Self::oneshot_send(tx, Err(e), "set_offline_monitor_backend response");
+            }
+        }
+    }
+
+    #[cfg(windows)]
+    async fn on_set_wfp_sublayer_weight(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        weight: Option<u16>,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.wfp_sublayer_weight = weight)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_wfp_sublayer_weight response");
+                if settings_changed {
+                    log::info!(
+                        "WFP sublayer weight changed to {}. It will be applied when the daemon is \
+                         restarted",
+                        settings::wfp_sublayer_weight(&self.settings)
+                    );
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_wfp_sublayer_weight response");
             }
         }
     }
@@ -2773,8 +4903,16 @@ impl Daemon {
     }
 
     async fn on_get_wireguard_key(&self, tx: ResponseTx<Option<PublicKey>, Error>) {
+        let rotation_interval = self
+            .settings
+            .tunnel_options
+            .wireguard
+            .rotation_interval
+            .unwrap_or_default();
         let result = match self.account_manager.data().await.map(|s| s.into_device()) {
-            Ok(Some(config)) => Ok(Some(config.device.wg_data.get_public_key())),
+            Ok(Some(config)) => Ok(Some(
+                config.device.wg_data.get_public_key(rotation_interval),
+            )),
             _ => Err(Error::NoAccountNumber),
         };
         Self::oneshot_send(tx, result, "get_wireguard_key response");
@@ -2808,6 +4946,87 @@ impl Daemon {
         Self::oneshot_send(tx, result, "clear_custom_lists response");
     }
 
+    async fn on_add_custom_relay(
+        &mut self,
+        tx: ResponseTx<(), Error>,
+        relay: mullvad_types::custom_relay::CustomRelay,
+    ) {
+        let result = self.add_custom_relay(relay).await;
+        Self::oneshot_send(tx, result, "add_custom_relay response");
+    }
+
+    async fn on_remove_custom_relay(&mut self, tx: ResponseTx<(), Error>, name: String) {
+        let result = self.remove_custom_relay(name).await;
+        Self::oneshot_send(tx, result, "remove_custom_relay response");
+    }
+
+    async fn on_save_profile(&mut self, tx: ResponseTx<(), Error>, name: String) {
+        let result = self.save_profile(name).await;
+        Self::oneshot_send(tx, result, "save_profile response");
+    }
+
+    async fn on_remove_profile(&mut self, tx: ResponseTx<(), Error>, name: String) {
+        let result = self.remove_profile(name).await;
+        Self::oneshot_send(tx, result, "remove_profile response");
+    }
+
+    async fn on_use_profile(&mut self, tx: ResponseTx<(), Error>, name: String) {
+        let result = self.use_profile(name).await;
+        Self::oneshot_send(tx, result, "use_profile response");
+    }
+
+    fn on_get_trusted_networks(&self, tx: oneshot::Sender<(TrustedNetworks, Option<Network>)>) {
+        let trusted_networks = self.trusted_networks.get().clone();
+        let current_network = self.current_network.clone();
+        Self::oneshot_send(
+            tx,
+            (trusted_networks, current_network),
+            "get_trusted_networks response",
+        );
+    }
+
+    async fn on_set_trusted_network_rule(
+        &mut self,
+        tx: ResponseTx<(), trusted_networks::Error>,
+        rule: TrustedNetworkRule,
+    ) {
+        let result = self.set_trusted_network_rule(rule).await;
+        Self::oneshot_send(tx, result, "set_trusted_network_rule response");
+    }
+
+    async fn on_remove_trusted_network_rule(
+        &mut self,
+        tx: ResponseTx<(), trusted_networks::Error>,
+        network: NetworkMatch,
+    ) {
+        let result = self.remove_trusted_network_rule(network).await;
+        Self::oneshot_send(tx, result, "remove_trusted_network_rule response");
+    }
+
+    async fn on_set_excluded_locations(
+        &mut self,
+        tx: ResponseTx<(), settings::Error>,
+        excluded_locations: mullvad_types::excluded_locations::ExcludedLocations,
+    ) {
+        match self
+            .settings
+            .update(move |settings| settings.excluded_locations = excluded_locations)
+            .await
+        {
+            Ok(settings_changed) => {
+                Self::oneshot_send(tx, Ok(()), "set_excluded_locations response");
+                if settings_changed {
+                    log::info!("Initiating tunnel restart because the excluded locations changed");
+                    self.reconnect_tunnel();
+                }
+            }
+            Err(e) => {
+                log::error!("{}", e.display_chain_with_msg("Unable to save settings"));
+                Self::oneshot_send(tx, Err(e), "set_excluded_locations response");
+            }
+        }
+    }
+
     async fn on_add_access_method(
         &mut self,
         tx: ResponseTx<mullvad_types::access_method::Id, Error>,
@@ -2878,6 +5097,43 @@ impl Daemon {
         });
     }
 
+    fn on_get_api_access_method_stats(
+        &mut self,
+        tx: ResponseTx<HashMap<mullvad_types::access_method::Id, AccessMethodStats>, Error>,
+    ) {
+        let handle = self.access_mode_handler.clone();
+        tokio::spawn(async move {
+            let result = handle
+                .get_stats()
+                .await
+                .map_err(Error::ApiConnectionModeError);
+            Self::oneshot_send(tx, result, "get_api_access_method_stats response");
+        });
+    }
+
+    #[cfg(feature = "api-override")]
+    fn on_set_api_endpoint(&mut self, tx: ResponseTx<(), Error>, endpoint: Option<ApiEndpoint>) {
+        let endpoint = endpoint.unwrap_or_else(|| self.api_runtime.endpoint().clone());
+        log::info!(
+            "Using API endpoint {} at {}",
+            endpoint.host(),
+            endpoint.address()
+        );
+        let address_cache = self.api_runtime.address_cache().clone();
+        let api_handle = self.api_handle.clone();
+        let access_mode_handler = self.access_mode_handler.clone();
+        tokio::spawn(async move {
+            let endpoint_override = endpoint.should_disable_address_cache().then_some(&endpoint);
+            address_cache.set_override(endpoint_override).await;
+            api_handle.set_endpoint(endpoint.clone());
+            let result = access_mode_handler
+                .set_api_endpoint(endpoint)
+                .await
+                .map_err(Error::ApiConnectionModeError);
+            Self::oneshot_send(tx, result, "set_api_endpoint response");
+        });
+    }
+
     fn on_test_proxy_as_access_method(
         &mut self,
         tx: ResponseTx<bool, Error>,
@@ -2990,25 +5246,83 @@ impl Daemon {
             self.send_tunnel_command(TunnelCommand::SetExcludedApps(tx, vec![]));
         }
 
+        self.apply_replaced_settings().await;
+    }
+
+    async fn on_import_settings(
+        &mut self,
+        tx: ResponseTx<(), settings::export::Error>,
+        blob: String,
+    ) {
+        let new_settings = match settings::export::import(&blob, &self.settings).await {
+            Ok(new_settings) => new_settings,
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to import settings")
+                );
+                Self::oneshot_send(tx, Err(error), "import_settings response");
+                return;
+            }
+        };
+        match self
+            .settings
+            .update(move |settings| *settings = new_settings)
+            .await
+        {
+            Ok(_) => {
+                Self::oneshot_send(tx, Ok(()), "import_settings response");
+                self.apply_replaced_settings().await;
+            }
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Unable to save settings")
+                );
+                Self::oneshot_send(
+                    tx,
+                    Err(settings::export::Error::Settings(error)),
+                    "import_settings response",
+                );
+            }
+        }
+    }
+
+    fn on_export_settings(
+        &mut self,
+        tx: ResponseTx<String, settings::export::Error>,
+        include_secrets: bool,
+    ) {
+        let result = settings::export::export(&self.settings, include_secrets);
+        Self::oneshot_send(tx, result, "export_settings response");
+    }
+
+    /// Update the components that do not listen for settings changes, after all settings were
+    /// replaced at once.
+    async fn apply_replaced_settings(&mut self) {
         #[cfg(not(target_os = "android"))]
         {
             let (tx, _rx) = oneshot::channel();
             self.send_tunnel_command(TunnelCommand::BlockWhenDisconnected(
-                self.settings.block_when_disconnected,
+                self.block_when_disconnected(),
                 tx,
             ));
         }
 
         let (tx, _rx) = oneshot::channel();
-        self.send_tunnel_command(TunnelCommand::AllowLan(self.settings.allow_lan, tx));
+        self.send_tunnel_command(TunnelCommand::AllowLan(self.settings.allow_lan.clone(), tx));
 
+        #[cfg(not(target_os = "android"))]
+        self.update_dns_filter_lists();
         let (tx, _rx) = oneshot::channel();
-        let dns = dns::addresses_from_options(&self.settings.tunnel_options.dns_options);
-        self.send_tunnel_command(TunnelCommand::Dns(dns, tx));
+        self.send_tunnel_command(TunnelCommand::Dns(self.dns_config(), tx));
 
         self.version_updater_handle
             .set_show_beta_releases(self.settings.show_beta_releases)
             .await;
+        self.relay_list_updater
+            .set_update_interval(self.settings.relay_list_update_interval())
+            .await;
         let access_mode_handler = self.access_mode_handler.clone();
         tokio::spawn(async move {
             if let Err(error) = access_mode_handler.rotate().await {
@@ -3041,11 +5355,15 @@ impl Daemon {
 
     #[cfg_attr(target_os = "android", allow(unused_variables))]
     fn on_trigger_shutdown(&mut self, user_init_shutdown: bool) {
+        #[cfg(target_os = "linux")]
+        systemd_notify::stopping();
+
         // Block all traffic before shutting down to ensure that no traffic can leak on boot or
         // shutdown.
         #[cfg(not(target_os = "android"))]
-        if !user_init_shutdown
-            && (*self.target_state == TargetState::Secured || self.settings.auto_connect)
+        if self.settings.block_all
+            || (!user_init_shutdown
+                && (*self.target_state == TargetState::Secured || self.settings.auto_connect))
         {
             log::debug!("Blocking firewall during shutdown");
             let (tx, _rx) = oneshot::channel();
@@ -3202,10 +5520,11 @@ impl Daemon {
     /// progress towards that state.
     /// Returns a bool representing whether a state change was initiated.
     async fn set_target_state(&mut self, new_state: TargetState) -> bool {
-        if new_state != *self.target_state || self.tunnel_state.is_in_error_state() {
+        if new_state != *self.target_state || self.tunnel_state.is_in_error_state() || self.paused {
             log::debug!("Target state {:?} => {:?}", *self.target_state, new_state);
 
             self.target_state.set(new_state).await;
+            self.paused = false;
 
             match *self.target_state {
                 TargetState::Secured => self.connect_tunnel(),
@@ -3217,14 +5536,49 @@ impl Daemon {
         }
     }
 
+    /// Tear down the tunnel while keeping the device secured. Returns `false` if the tunnel is
+    /// already paused.
+    async fn pause_tunnel(&mut self) -> bool {
+        if self.paused && !self.tunnel_state.is_in_error_state() {
+            return false;
+        }
+        log::debug!("Pausing tunnel");
+        // A paused tunnel still blocks all traffic, so the device should remain secured after
+        // the daemon restarts.
+        self.target_state.set(TargetState::Secured).await;
+        self.paused = true;
+        self.connect_tunnel();
+        true
+    }
+
     fn connect_tunnel(&mut self) {
+        #[cfg(not(target_os = "android"))]
+        if self.settings.block_all {
+            self.block_all_traffic();
+            return;
+        }
+        if self.paused {
+            self.send_tunnel_command(TunnelCommand::Pause);
+            return;
+        }
         self.send_tunnel_command(TunnelCommand::Connect);
     }
 
     fn disconnect_tunnel(&self) {
+        #[cfg(not(target_os = "android"))]
+        if self.settings.block_all {
+            self.block_all_traffic();
+            return;
+        }
         self.send_tunnel_command(TunnelCommand::Disconnect);
     }
 
+    /// Enter the blocked state, regardless of the target state.
+    #[cfg(not(target_os = "android"))]
+    fn block_all_traffic(&self) {
+        self.send_tunnel_command(TunnelCommand::Block(ErrorStateCause::BlockedByUser));
+    }
+
     fn reconnect_tunnel(&mut self) {
         if *self.target_state == TargetState::Secured {
             self.connect_tunnel();
@@ -0,0 +1,80 @@
+//! Temporarily stops blocking traffic while disconnected, even though lockdown mode is enabled.
+//!
+//! Lockdown mode is enforced again when the pause ends, when the lockdown mode setting is changed,
+//! or when the daemon restarts, since the pause is never saved.
+
+use crate::{Daemon, InternalDaemonEvent};
+use futures::channel::oneshot;
+use std::time::Duration;
+use talpid_core::{mpsc::Sender, tunnel_state_machine::TunnelCommand};
+use tokio::{task::AbortHandle, time::Instant};
+
+/// Lockdown mode may not be paused for longer than this.
+pub const MAX_DURATION: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Lockdown mode is not enabled")]
+    NotEnabled,
+
+    #[error("Lockdown mode may be paused for at most 24 hours")]
+    TooLong,
+}
+
+/// An ongoing pause. The timer is cancelled when this is dropped.
+pub struct LockdownPause {
+    until: Instant,
+    timer: AbortHandle,
+}
+
+impl Drop for LockdownPause {
+    fn drop(&mut self) {
+        self.timer.abort();
+    }
+}
+
+impl Daemon {
+    /// Stop blocking traffic while disconnected for `duration`. An ongoing pause is replaced.
+    pub(crate) fn pause_lockdown_mode(&mut self, duration: Duration) -> Result<(), Error> {
+        if duration > MAX_DURATION {
+            return Err(Error::TooLong);
+        }
+        self.lockdown_pause = None;
+        if !self.block_when_disconnected() {
+            return Err(Error::NotEnabled);
+        }
+
+        log::info!("Pausing lockdown mode for {} seconds", duration.as_secs());
+        let until = Instant::now() + duration;
+        let tx = self.tx.clone();
+        let timer = tokio::spawn(async move {
+            tokio::time::sleep_until(until).await;
+            let _ = tx.send(InternalDaemonEvent::LockdownPauseExpired);
+        })
+        .abort_handle();
+        self.lockdown_pause = Some(LockdownPause { until, timer });
+        self.update_block_when_disconnected();
+        Ok(())
+    }
+
+    pub(crate) fn on_lockdown_pause_expired(&mut self) {
+        // The pause may have been replaced after the timer fired
+        let expired = self
+            .lockdown_pause
+            .as_ref()
+            .is_some_and(|pause| pause.until <= Instant::now());
+        if expired {
+            log::info!("Lockdown mode pause ended");
+            self.lockdown_pause = None;
+            self.update_block_when_disconnected();
+        }
+    }
+
+    fn update_block_when_disconnected(&self) {
+        let (tx, _rx) = oneshot::channel();
+        self.send_tunnel_command(TunnelCommand::BlockWhenDisconnected(
+            self.block_when_disconnected(),
+            tx,
+        ));
+    }
+}
@@ -1,21 +1,25 @@
-use crate::{account_history, device, version_check, DaemonCommand, DaemonCommandSender};
+use crate::{
+    account_history, device, request_queue, settings, version_check, DaemonCommand,
+    DaemonCommandSender,
+};
 use futures::{
     channel::{mpsc, oneshot},
     StreamExt,
 };
 use mullvad_api::{rest::Error as RestError, StatusCode};
 use mullvad_management_interface::{
+    api,
     types::{self, daemon_event, management_service_server::ManagementService},
-    Code, Request, Response, ServerJoinHandle, Status,
+    AccessLevel, Code, Request, Response, ServerJoinHandle, Status,
 };
 use mullvad_types::{
     account::AccountNumber,
     relay_constraints::{
         BridgeSettings, BridgeState, ObfuscationSettings, RelayOverride, RelaySettings,
     },
-    relay_list::RelayList,
+    relay_list::{RelayList, MAX_UPDATE_INTERVAL_MINUTES, MIN_UPDATE_INTERVAL_MINUTES},
     settings::{DnsOptions, Settings},
-    states::{TargetState, TunnelState},
+    states::{TargetState, TunnelState, TunnelStats},
     version,
     wireguard::{RotationInterval, RotationIntervalError},
 };
@@ -23,10 +27,10 @@ use std::{
     path::Path,
     str::FromStr,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, SystemTime},
 };
 use talpid_types::ErrorExt;
-use tokio::time::timeout;
+use tokio::time::{timeout, MissedTickBehavior};
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
 const RPC_SERVER_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(1);
@@ -50,9 +54,13 @@ type EventsListenerSender = tokio::sync::mpsc::UnboundedSender<Result<types::Dae
 const INVALID_VOUCHER_MESSAGE: &str = "This voucher code is invalid";
 const USED_VOUCHER_MESSAGE: &str = "This voucher code has already been used";
 
+/// Shortest interval at which tunnel statistics are sent to a listener.
+const MIN_TUNNEL_STATS_INTERVAL: Duration = Duration::from_millis(100);
+
 #[mullvad_management_interface::async_trait]
 impl ManagementService for ManagementServiceImpl {
     type GetSplitTunnelProcessesStream = UnboundedReceiverStream<Result<i32, Status>>;
+    type TunnelStatsListenStream = UnboundedReceiverStream<Result<types::TunnelStats, Status>>;
     type EventsListenStream = EventsListenerReceiver;
 
     // Control and get the tunnel state
@@ -84,6 +92,14 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(reconnect_issued))
     }
 
+    async fn pause_tunnel(&self, _: Request<()>) -> ServiceResult<bool> {
+        log::debug!("pause_tunnel");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::Pause(tx))?;
+        let pause_issued = self.wait_for_result(rx).await?;
+        Ok(Response::new(pause_issued))
+    }
+
     async fn get_tunnel_state(&self, _: Request<()>) -> ServiceResult<types::TunnelState> {
         log::debug!("get_tunnel_state");
         let (tx, rx) = oneshot::channel();
@@ -92,6 +108,94 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(types::TunnelState::from(state)))
     }
 
+    async fn get_connection_timings(
+        &self,
+        _: Request<()>,
+    ) -> ServiceResult<types::ConnectionTimings> {
+        log::debug!("get_connection_timings");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetConnectionTimings(tx))?;
+        match self.wait_for_result(rx).await? {
+            Some(timings) => Ok(Response::new(types::ConnectionTimings::from(timings))),
+            None => Err(Status::not_found("no connection has been established")),
+        }
+    }
+
+    async fn get_traffic_stats(&self, _: Request<()>) -> ServiceResult<types::TrafficStats> {
+        log::debug!("get_traffic_stats");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetTrafficStats(tx))?;
+        match self.wait_for_result(rx).await? {
+            Some(stats) => Ok(Response::new(types::TrafficStats::from(stats))),
+            None => Err(Status::not_found("the tunnel is not connected")),
+        }
+    }
+
+    async fn tunnel_stats_listen(
+        &self,
+        request: Request<types::Duration>,
+    ) -> ServiceResult<Self::TunnelStatsListenStream> {
+        let interval = Duration::try_from(request.into_inner())
+            .map_err(|_| Status::invalid_argument("unexpected negative duration"))?;
+        log::debug!("tunnel_stats_listen({interval:?})");
+
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        let daemon_tx = self.daemon_tx.clone();
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(interval.max(MIN_TUNNEL_STATS_INTERVAL));
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            loop {
+                tokio::select! {
+                    _ = tx.closed() => break,
+                    _ = ticks.tick() => (),
+                }
+                let stats = match get_tunnel_stats(&daemon_tx).await {
+                    Ok(Some(stats)) => Ok(types::TunnelStats::from(stats)),
+                    Ok(None) => continue,
+                    Err(status) => Err(status),
+                };
+                let failed = stats.is_err();
+                if tx.send(stats).is_err() || failed {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(UnboundedReceiverStream::new(rx)))
+    }
+
+    async fn get_tunnel_diagnostics(&self, request: Request<bool>) -> ServiceResult<String> {
+        let include_private_key = request.into_inner();
+        log::debug!("get_tunnel_diagnostics({include_private_key})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetTunnelDiagnostics(tx))?;
+        let diagnostics = self.wait_for_result(rx).await?;
+
+        let mut json = serde_json::to_value(&diagnostics)
+            .map_err(|error| Status::internal(error.to_string()))?;
+        if !include_private_key {
+            if let Some(tunnel) = json
+                .pointer_mut("/wireguard/tunnel")
+                .and_then(serde_json::Value::as_object_mut)
+            {
+                tunnel.remove("private_key");
+            }
+        }
+        serde_json::to_string_pretty(&json)
+            .map(Response::new)
+            .map_err(|error| Status::internal(error.to_string()))
+    }
+
+    async fn get_data_usage(&self, _: Request<()>) -> ServiceResult<types::DataUsage> {
+        log::debug!("get_data_usage");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetDataUsage(tx))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(|usage| Response::new(types::DataUsage::from(usage)))
+            .map_err(|error| Status::unavailable(error.to_string()))
+    }
+
     // Control the daemon and receive events
     //
 
@@ -135,6 +239,31 @@ impl ManagementService for ManagementServiceImpl {
         }
     }
 
+    async fn handshake(
+        &self,
+        request: Request<types::HandshakeRequest>,
+    ) -> ServiceResult<types::HandshakeResponse> {
+        let observe_only =
+            request.extensions().get::<AccessLevel>() == Some(&AccessLevel::ObserveOnly);
+        let client_version = request.into_inner().api_version;
+        log::debug!("handshake({client_version})");
+        if client_version < api::MIN_SUPPORTED_API_VERSION {
+            return Err(Status::failed_precondition(format!(
+                "management interface version {client_version} is no longer supported"
+            )));
+        }
+        Ok(Response::new(types::HandshakeResponse {
+            api_version: api::API_VERSION,
+            min_supported_api_version: api::MIN_SUPPORTED_API_VERSION,
+            daemon_version: mullvad_version::VERSION.to_owned(),
+            capabilities: api::Capability::supported()
+                .into_iter()
+                .map(|capability| capability.as_str().to_owned())
+                .collect(),
+            observe_only,
+        }))
+    }
+
     async fn get_current_version(&self, _: Request<()>) -> ServiceResult<String> {
         log::debug!("get_current_version");
         let (tx, rx) = oneshot::channel();
@@ -171,6 +300,34 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn update_relay_locations_and_wait(&self, _: Request<()>) -> ServiceResult<()> {
+        log::debug!("update_relay_locations_and_wait");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::UpdateRelayLocationsAndWait(tx))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_relay_list_update_interval(&self, request: Request<u32>) -> ServiceResult<()> {
+        let minutes = request.into_inner();
+        log::debug!("set_relay_list_update_interval({})", minutes);
+        let minutes = u16::try_from(minutes)
+            .ok()
+            .filter(|minutes| {
+                (MIN_UPDATE_INTERVAL_MINUTES..=MAX_UPDATE_INTERVAL_MINUTES).contains(minutes)
+            })
+            .ok_or_else(|| {
+                Status::invalid_argument(format!(
+                    "The relay list update interval must be between {} and {} minutes",
+                    MIN_UPDATE_INTERVAL_MINUTES, MAX_UPDATE_INTERVAL_MINUTES,
+                ))
+            })?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetRelayListUpdateInterval(tx, minutes))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
     async fn set_relay_settings(
         &self,
         request: Request<types::RelaySettings>,
@@ -196,6 +353,33 @@ impl ManagementService for ManagementServiceImpl {
             .map(|relays| Response::new(types::RelayList::from(relays)))
     }
 
+    async fn export_relay_list(&self, _: Request<()>) -> ServiceResult<String> {
+        log::debug!("export_relay_list");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ExportRelayList(tx))?;
+        let blob = self.wait_for_result(rx).await??;
+        Ok(Response::new(blob))
+    }
+
+    async fn import_relay_list(&self, blob: Request<String>) -> ServiceResult<()> {
+        log::debug!("import_relay_list");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ImportRelayList(tx, blob.into_inner()))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn reload_relay_list_overrides(
+        &self,
+        _: Request<()>,
+    ) -> ServiceResult<types::RelayListOverridesResult> {
+        log::debug!("reload_relay_list_overrides");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ReloadRelayListOverrides(tx))?;
+        let errors = self.wait_for_result(rx).await??;
+        Ok(Response::new(types::RelayListOverridesResult { errors }))
+    }
+
     async fn set_bridge_settings(
         &self,
         request: Request<types::BridgeSettings>,
@@ -230,123 +414,638 @@ impl ManagementService for ManagementServiceImpl {
 
         log::debug!("set_bridge_state({:?})", bridge_state);
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::SetBridgeState(tx, bridge_state))?;
+        self.send_command_to_daemon(DaemonCommand::SetBridgeState(tx, bridge_state))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    // Settings
+    //
+
+    async fn get_settings(&self, _: Request<()>) -> ServiceResult<types::Settings> {
+        log::debug!("get_settings");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetSettings(tx))?;
+        self.wait_for_result(rx)
+            .await
+            .map(|settings| Response::new(types::Settings::from(&settings)))
+    }
+
+    async fn reset_settings(&self, _: Request<()>) -> ServiceResult<()> {
+        log::debug!("reset_settings");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ResetSettings(tx))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_allow_lan(&self, request: Request<bool>) -> ServiceResult<()> {
+        let allow_lan = request.into_inner();
+        log::debug!("set_allow_lan({})", allow_lan);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetAllowLan(
+            tx,
+            mullvad_types::lan_sharing::AllowedLan::from(allow_lan),
+        ))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    async fn set_lan_sharing(&self, request: Request<types::LanSharing>) -> ServiceResult<()> {
+        let allow_lan = mullvad_types::lan_sharing::AllowedLan::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
+        log::debug!("set_lan_sharing({allow_lan})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetAllowLan(tx, allow_lan))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    async fn set_show_beta_releases(&self, request: Request<bool>) -> ServiceResult<()> {
+        let enabled = request.into_inner();
+        log::debug!("set_show_beta_releases({})", enabled);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetShowBetaReleases(tx, enabled))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_prefer_low_latency_relays(&self, request: Request<bool>) -> ServiceResult<()> {
+        let enabled = request.into_inner();
+        log::debug!("set_prefer_low_latency_relays({})", enabled);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetPreferLowLatencyRelays(tx, enabled))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_sticky_relays(&self, request: Request<bool>) -> ServiceResult<()> {
+        let enabled = request.into_inner();
+        log::debug!("set_sticky_relays({})", enabled);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetStickyRelays(tx, enabled))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn set_block_when_disconnected(&self, request: Request<bool>) -> ServiceResult<()> {
+        let block_when_disconnected = request.into_inner();
+        log::debug!("set_block_when_disconnected({})", block_when_disconnected);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetBlockWhenDisconnected(
+            tx,
+            block_when_disconnected,
+        ))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(target_os = "android")]
+    async fn set_block_when_disconnected(&self, request: Request<bool>) -> ServiceResult<()> {
+        let block_when_disconnected = request.into_inner();
+        log::debug!("set_block_when_disconnected({})", block_when_disconnected);
+        Err(Status::unimplemented(
+            "Setting Lockdown mode on Android is not supported - this is handled by the OS, not the daemon",
+        ))
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn pause_lockdown_mode(&self, request: Request<types::Duration>) -> ServiceResult<()> {
+        let duration = Duration::try_from(request.into_inner())
+            .map_err(|_| Status::invalid_argument("unexpected negative duration"))?;
+        log::debug!("pause_lockdown_mode({duration:?})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::PauseLockdownMode(tx, duration))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(target_os = "android")]
+    async fn pause_lockdown_mode(&self, _: Request<types::Duration>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Lockdown mode is handled by the OS on Android",
+        ))
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn allow_captive_portal_login(
+        &self,
+        request: Request<types::Duration>,
+    ) -> ServiceResult<()> {
+        let duration = Duration::try_from(request.into_inner())
+            .map_err(|_| Status::invalid_argument("unexpected negative duration"))?;
+        log::debug!("allow_captive_portal_login({duration:?})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::AllowCaptivePortalLogin(tx, duration))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(target_os = "android")]
+    async fn allow_captive_portal_login(&self, _: Request<types::Duration>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Captive portals are handled by the OS on Android",
+        ))
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn set_block_all(&self, request: Request<bool>) -> ServiceResult<()> {
+        let block_all = request.into_inner();
+        log::debug!("set_block_all({})", block_all);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetBlockAll(tx, block_all))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(target_os = "android")]
+    async fn set_block_all(&self, _: Request<bool>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Blocking all traffic is not supported on Android",
+        ))
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn set_firewall_exceptions(
+        &self,
+        request: Request<types::FirewallExceptions>,
+    ) -> ServiceResult<()> {
+        let exceptions = Vec::<mullvad_types::firewall_exception::FirewallException>::try_from(
+            request.into_inner(),
+        )
+        .map_err(map_protobuf_type_err)?;
+        log::debug!("set_firewall_exceptions({exceptions:?})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetFirewallExceptions(tx, exceptions))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(target_os = "android")]
+    async fn set_firewall_exceptions(
+        &self,
+        _: Request<types::FirewallExceptions>,
+    ) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Firewall exceptions are not supported on Android",
+        ))
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn set_allowed_incoming(
+        &self,
+        request: Request<types::AllowedIncomingRules>,
+    ) -> ServiceResult<()> {
+        let allowed_incoming =
+            Vec::<mullvad_types::allowed_incoming::AllowedIncoming>::try_from(request.into_inner())
+                .map_err(map_protobuf_type_err)?;
+        log::debug!("set_allowed_incoming({allowed_incoming:?})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetAllowedIncoming(tx, allowed_incoming))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(target_os = "android")]
+    async fn set_allowed_incoming(
+        &self,
+        _: Request<types::AllowedIncomingRules>,
+    ) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Allowing incoming connections is not supported on Android",
+        ))
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn set_state_hook(&self, request: Request<types::StateHook>) -> ServiceResult<()> {
+        use mullvad_types::state_hooks::HookEvent;
+        use std::path::PathBuf;
+
+        let (event, path) = <(HookEvent, Option<PathBuf>)>::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
+        log::debug!("set_state_hook({event}, {path:?})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetStateHook(tx, event, path))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(target_os = "android")]
+    async fn set_state_hook(&self, _: Request<types::StateHook>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "State hooks are not supported on Android",
+        ))
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn set_metrics_settings(
+        &self,
+        request: Request<types::MetricsSettings>,
+    ) -> ServiceResult<()> {
+        use mullvad_types::settings::MetricsSettings;
+
+        let metrics =
+            MetricsSettings::try_from(request.into_inner()).map_err(map_protobuf_type_err)?;
+        log::debug!("set_metrics_settings({metrics:?})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetMetricsSettings(tx, metrics))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(target_os = "android")]
+    async fn set_metrics_settings(&self, _: Request<types::MetricsSettings>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "The metrics server is not supported on Android",
+        ))
+    }
+
+    async fn set_auto_connect(&self, request: Request<bool>) -> ServiceResult<()> {
+        let auto_connect = request.into_inner();
+        log::debug!("set_auto_connect({})", auto_connect);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetAutoConnect(tx, auto_connect))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_openvpn_mssfix(&self, request: Request<u32>) -> ServiceResult<()> {
+        let mssfix = request.into_inner();
+        let mssfix = if mssfix != 0 {
+            Some(mssfix as u16)
+        } else {
+            None
+        };
+        log::debug!("set_openvpn_mssfix({:?})", mssfix);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetOpenVpnMssfix(tx, mssfix))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_wireguard_mtu(&self, request: Request<u32>) -> ServiceResult<()> {
+        let mtu = request.into_inner();
+        let mtu = if mtu != 0 { Some(mtu as u16) } else { None };
+        log::debug!("set_wireguard_mtu({:?})", mtu);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetWireguardMtu(tx, mtu))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_wireguard_persistent_keepalive(&self, request: Request<u32>) -> ServiceResult<()> {
+        let interval = match request.into_inner() {
+            0 => None,
+            interval => Some(u16::try_from(interval).map_err(|_| {
+                Status::invalid_argument("persistent keepalive interval is too large")
+            })?),
+        };
+        log::debug!("set_wireguard_persistent_keepalive({:?})", interval);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetWireguardPersistentKeepalive(tx, interval))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn set_wireguard_worker_threads(&self, request: Request<u32>) -> ServiceResult<()> {
+        let threads = match request.into_inner() {
+            0 => None,
+            threads => Some(threads),
+        };
+        log::debug!("set_wireguard_worker_threads({:?})", threads);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetWireguardWorkerThreads(tx, threads))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_wireguard_outgoing_interface(
+        &self,
+        request: Request<String>,
+    ) -> ServiceResult<()> {
+        let interface = Some(request.into_inner()).filter(|interface| !interface.is_empty());
+        log::debug!("set_wireguard_outgoing_interface({:?})", interface);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetWireguardOutgoingInterface(tx, interface))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_wireguard_outgoing_interface(&self, _: Request<String>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Setting the WireGuard outgoing interface is only supported on Linux",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_wireguard_backend(
+        &self,
+        request: Request<types::WireguardBackendSetting>,
+    ) -> ServiceResult<()> {
+        let backend =
+            Option::<talpid_types::net::wireguard::Backend>::try_from(request.into_inner())
+                .map_err(map_protobuf_type_err)?;
+        log::debug!("set_wireguard_backend({:?})", backend);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetWireguardBackend(tx, backend))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_wireguard_backend(
+        &self,
+        _: Request<types::WireguardBackendSetting>,
+    ) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Selecting the WireGuard backend is only supported on Linux",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_tunnel_fwmark(&self, request: Request<u32>) -> ServiceResult<()> {
+        let fwmark = match request.into_inner() {
+            0 => None,
+            fwmark => Some(fwmark),
+        };
+        log::debug!("set_tunnel_fwmark({:?})", fwmark);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetTunnelFwmark(tx, fwmark))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_tunnel_fwmark(&self, _: Request<u32>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Setting the tunnel fwmark is only supported on Linux",
+        ))
+    }
+
+    #[cfg(windows)]
+    async fn set_wfp_sublayer_weight(&self, request: Request<u32>) -> ServiceResult<()> {
+        let weight = match request.into_inner() {
+            0 => None,
+            weight => Some(u16::try_from(weight).map_err(|_| {
+                Status::invalid_argument(format!(
+                    "The WFP sublayer weight must be at most {}",
+                    u16::MAX
+                ))
+            })?),
+        };
+        log::debug!("set_wfp_sublayer_weight({:?})", weight);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetWfpSublayerWeight(tx, weight))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(windows))]
+    async fn set_wfp_sublayer_weight(&self, _: Request<u32>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Setting the WFP sublayer weight is only supported on Windows",
+        ))
+    }
+
+    #[cfg(target_os = "macos")]
+    async fn set_pf_anchor(&self, request: Request<String>) -> ServiceResult<()> {
+        let anchor = Some(request.into_inner()).filter(|anchor| !anchor.is_empty());
+        log::debug!("set_pf_anchor({:?})", anchor);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetPfAnchor(tx, anchor))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    async fn set_pf_anchor(&self, _: Request<String>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Setting the PF anchor is only supported on macOS",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_dns_backends(&self, request: Request<types::DnsBackends>) -> ServiceResult<()> {
+        let backends = Vec::<talpid_types::dns::DnsBackend>::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
+        log::debug!("set_dns_backends({:?})", backends);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetDnsBackends(tx, backends))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_dns_backends(&self, _: Request<types::DnsBackends>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Selecting the DNS backend is only supported on Linux",
+        ))
+    }
+
+    #[cfg(not(target_os = "android"))]
+    async fn set_offline_monitor_backend(
+        &self,
+        request: Request<types::OfflineMonitorBackend>,
+    ) -> ServiceResult<()> {
+        let backend = talpid_types::net::OfflineMonitorBackend::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
+        log::debug!("set_offline_monitor_backend({backend:?})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetOfflineMonitorBackend(tx, backend))?;
         self.wait_for_result(rx).await??;
         Ok(Response::new(()))
     }
 
-    // Settings
-    //
+    #[cfg(target_os = "android")]
+    async fn set_offline_monitor_backend(
+        &self,
+        _: Request<types::OfflineMonitorBackend>,
+    ) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Connectivity is monitored by the OS on Android",
+        ))
+    }
 
-    async fn get_settings(&self, _: Request<()>) -> ServiceResult<types::Settings> {
-        log::debug!("get_settings");
+    #[cfg(target_os = "linux")]
+    async fn set_nftables_settings(
+        &self,
+        request: Request<types::NftablesSettings>,
+    ) -> ServiceResult<()> {
+        let nftables = mullvad_types::settings::NftablesSettings::from(request.into_inner());
+        log::debug!("set_nftables_settings({nftables:?})");
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::GetSettings(tx))?;
+        self.send_command_to_daemon(DaemonCommand::SetNftablesSettings(tx, nftables))?;
         self.wait_for_result(rx)
-            .await
-            .map(|settings| Response::new(types::Settings::from(&settings)))
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
     }
 
-    async fn reset_settings(&self, _: Request<()>) -> ServiceResult<()> {
-        log::debug!("reset_settings");
+    #[cfg(not(target_os = "linux"))]
+    async fn set_nftables_settings(
+        &self,
+        _: Request<types::NftablesSettings>,
+    ) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "nftables settings are only supported on Linux",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_routing_settings(
+        &self,
+        request: Request<types::RoutingSettings>,
+    ) -> ServiceResult<()> {
+        let routing = mullvad_types::settings::RoutingSettings::from(request.into_inner());
+        log::debug!("set_routing_settings({routing:?})");
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::ResetSettings(tx))?;
-        self.wait_for_result(rx).await??;
-        Ok(Response::new(()))
+        self.send_command_to_daemon(DaemonCommand::SetRoutingSettings(tx, routing))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
     }
 
-    async fn set_allow_lan(&self, request: Request<bool>) -> ServiceResult<()> {
-        let allow_lan = request.into_inner();
-        log::debug!("set_allow_lan({})", allow_lan);
+    #[cfg(not(target_os = "linux"))]
+    async fn set_routing_settings(&self, _: Request<types::RoutingSettings>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Routing settings are only supported on Linux",
+        ))
+    }
+
+    async fn set_enable_ipv6(&self, request: Request<bool>) -> ServiceResult<()> {
+        let enable_ipv6 = request.into_inner();
+        log::debug!("set_enable_ipv6({})", enable_ipv6);
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::SetAllowLan(tx, allow_lan))?;
+        self.send_command_to_daemon(DaemonCommand::SetEnableIpv6(tx, enable_ipv6))?;
         self.wait_for_result(rx).await??;
         Ok(Response::new(()))
     }
 
-    async fn set_show_beta_releases(&self, request: Request<bool>) -> ServiceResult<()> {
-        let enabled = request.into_inner();
-        log::debug!("set_show_beta_releases({})", enabled);
+    async fn set_wireguard_ipv6_only(&self, request: Request<bool>) -> ServiceResult<()> {
+        let ipv6_only = request.into_inner();
+        log::debug!("set_wireguard_ipv6_only({})", ipv6_only);
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::SetShowBetaReleases(tx, enabled))?;
-        self.wait_for_result(rx).await??;
+        self.send_command_to_daemon(DaemonCommand::SetWireguardIpv6Only(tx, ipv6_only))?;
+        self.wait_for_result(rx).await?.map_err(map_daemon_error)?;
         Ok(Response::new(()))
     }
 
     #[cfg(not(target_os = "android"))]
-    async fn set_block_when_disconnected(&self, request: Request<bool>) -> ServiceResult<()> {
-        let block_when_disconnected = request.into_inner();
-        log::debug!("set_block_when_disconnected({})", block_when_disconnected);
+    async fn set_block_ipv6(&self, request: Request<bool>) -> ServiceResult<()> {
+        let block_ipv6 = request.into_inner();
+        log::debug!("set_block_ipv6({})", block_ipv6);
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::SetBlockWhenDisconnected(
-            tx,
-            block_when_disconnected,
-        ))?;
-        self.wait_for_result(rx).await??;
+        self.send_command_to_daemon(DaemonCommand::SetBlockIpv6(tx, block_ipv6))?;
+        self.wait_for_result(rx).await?.map_err(map_daemon_error)?;
         Ok(Response::new(()))
     }
 
     #[cfg(target_os = "android")]
-    async fn set_block_when_disconnected(&self, request: Request<bool>) -> ServiceResult<()> {
-        let block_when_disconnected = request.into_inner();
-        log::debug!("set_block_when_disconnected({})", block_when_disconnected);
+    async fn set_block_ipv6(&self, _: Request<bool>) -> ServiceResult<()> {
         Err(Status::unimplemented(
-            "Setting Lockdown mode on Android is not supported - this is handled by the OS, not the daemon",
+            "blocking IPv6 is not supported on Android",
         ))
     }
 
-    async fn set_auto_connect(&self, request: Request<bool>) -> ServiceResult<()> {
-        let auto_connect = request.into_inner();
-        log::debug!("set_auto_connect({})", auto_connect);
+    #[cfg(not(target_os = "android"))]
+    async fn set_custom_routes(&self, request: Request<types::CustomRoutes>) -> ServiceResult<()> {
+        let routes =
+            Vec::<mullvad_types::custom_route::CustomRoute>::try_from(request.into_inner())
+                .map_err(map_protobuf_type_err)?;
+        log::debug!("set_custom_routes({routes:?})");
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::SetAutoConnect(tx, auto_connect))?;
-        self.wait_for_result(rx).await??;
-        Ok(Response::new(()))
+        self.send_command_to_daemon(DaemonCommand::SetCustomRoutes(tx, routes))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
     }
 
-    async fn set_openvpn_mssfix(&self, request: Request<u32>) -> ServiceResult<()> {
-        let mssfix = request.into_inner();
-        let mssfix = if mssfix != 0 {
-            Some(mssfix as u16)
-        } else {
-            None
-        };
-        log::debug!("set_openvpn_mssfix({:?})", mssfix);
+    #[cfg(target_os = "android")]
+    async fn set_custom_routes(&self, _: Request<types::CustomRoutes>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Custom routes are not supported on Android",
+        ))
+    }
+
+    async fn set_quantum_resistant_tunnel(
+        &self,
+        request: Request<types::QuantumResistantState>,
+    ) -> ServiceResult<()> {
+        let state = mullvad_types::wireguard::QuantumResistantState::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
+
+        log::debug!("set_quantum_resistant_tunnel({state:?})");
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::SetOpenVpnMssfix(tx, mssfix))?;
+        self.send_command_to_daemon(DaemonCommand::SetQuantumResistantTunnel(tx, state))?;
         self.wait_for_result(rx).await??;
         Ok(Response::new(()))
     }
 
-    async fn set_wireguard_mtu(&self, request: Request<u32>) -> ServiceResult<()> {
-        let mtu = request.into_inner();
-        let mtu = if mtu != 0 { Some(mtu as u16) } else { None };
-        log::debug!("set_wireguard_mtu({:?})", mtu);
+    async fn set_quantum_resistant_kem(
+        &self,
+        request: Request<types::QuantumResistantKem>,
+    ) -> ServiceResult<()> {
+        let kem = talpid_types::net::wireguard::QuantumResistantKem::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
+
+        log::debug!("set_quantum_resistant_kem({kem:?})");
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::SetWireguardMtu(tx, mtu))?;
+        self.send_command_to_daemon(DaemonCommand::SetQuantumResistantKem(tx, kem))?;
         self.wait_for_result(rx).await??;
         Ok(Response::new(()))
     }
 
-    async fn set_enable_ipv6(&self, request: Request<bool>) -> ServiceResult<()> {
-        let enable_ipv6 = request.into_inner();
-        log::debug!("set_enable_ipv6({})", enable_ipv6);
+    async fn set_negotiation_retry_policy(
+        &self,
+        request: Request<types::NegotiationRetryPolicy>,
+    ) -> ServiceResult<()> {
+        let policy =
+            talpid_types::net::wireguard::NegotiationRetryPolicy::try_from(request.into_inner())
+                .map_err(map_protobuf_type_err)?;
+
+        log::debug!("set_negotiation_retry_policy({policy:?})");
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::SetEnableIpv6(tx, enable_ipv6))?;
+        self.send_command_to_daemon(DaemonCommand::SetNegotiationRetryPolicy(tx, policy))?;
         self.wait_for_result(rx).await??;
         Ok(Response::new(()))
     }
 
-    async fn set_quantum_resistant_tunnel(
+    async fn set_connectivity_check_options(
         &self,
-        request: Request<types::QuantumResistantState>,
+        request: Request<types::ConnectivityCheckOptions>,
     ) -> ServiceResult<()> {
-        let state = mullvad_types::wireguard::QuantumResistantState::try_from(request.into_inner())
-            .map_err(map_protobuf_type_err)?;
+        let options =
+            talpid_types::net::wireguard::ConnectivityCheckOptions::try_from(request.into_inner())
+                .map_err(map_protobuf_type_err)?;
 
-        log::debug!("set_quantum_resistant_tunnel({state:?})");
+        log::debug!("set_connectivity_check_options({options:?})");
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::SetQuantumResistantTunnel(tx, state))?;
+        self.send_command_to_daemon(DaemonCommand::SetConnectivityCheckOptions(tx, options))?;
         self.wait_for_result(rx).await??;
         Ok(Response::new(()))
     }
@@ -379,7 +1078,8 @@ impl ManagementService for ManagementServiceImpl {
         &self,
         request: Request<types::DaitaSettings>,
     ) -> ServiceResult<()> {
-        let state = mullvad_types::wireguard::DaitaSettings::from(request.into_inner());
+        let state = mullvad_types::wireguard::DaitaSettings::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
 
         log::debug!("set_daita_settings({state:?})");
         let (tx, rx) = oneshot::channel();
@@ -409,7 +1109,7 @@ impl ManagementService for ManagementServiceImpl {
 
         let (tx, rx) = oneshot::channel();
         self.send_command_to_daemon(DaemonCommand::SetDnsOptions(tx, options))?;
-        self.wait_for_result(rx).await??;
+        self.wait_for_result(rx).await?.map_err(map_daemon_error)?;
         Ok(Response::new(()))
     }
 
@@ -579,6 +1279,85 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    async fn remove_devices(
+        &self,
+        request: Request<types::DevicesRemoval>,
+    ) -> ServiceResult<types::DeviceRemovalResult> {
+        log::debug!("remove_devices");
+        let (tx, rx) = oneshot::channel();
+        let removal = request.into_inner();
+        self.send_command_to_daemon(DaemonCommand::RemoveDevices(
+            tx,
+            removal.account_number,
+            removal.device_ids,
+            removal.all_except_current,
+        ))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(|result| Response::new(types::DeviceRemovalResult::from(result)))
+            .map_err(map_daemon_error)
+    }
+
+    // Queued requests
+    //
+
+    async fn queue_voucher_submission(
+        &self,
+        request: Request<String>,
+    ) -> ServiceResult<types::PendingRequest> {
+        log::debug!("queue_voucher_submission");
+        let voucher = request.into_inner();
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::QueueVoucherSubmission(tx, voucher))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(|request| Response::new(types::PendingRequest::from(request)))
+            .map_err(map_daemon_error)
+    }
+
+    async fn queue_device_removal(
+        &self,
+        request: Request<types::DeviceRemoval>,
+    ) -> ServiceResult<types::PendingRequest> {
+        log::debug!("queue_device_removal");
+        let (tx, rx) = oneshot::channel();
+        let removal = request.into_inner();
+        self.send_command_to_daemon(DaemonCommand::QueueDeviceRemoval(
+            tx,
+            removal.account_number,
+            removal.device_id,
+        ))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(|request| Response::new(types::PendingRequest::from(request)))
+            .map_err(map_daemon_error)
+    }
+
+    async fn get_pending_requests(
+        &self,
+        _: Request<()>,
+    ) -> ServiceResult<types::PendingRequestList> {
+        log::debug!("get_pending_requests");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetPendingRequests(tx))?;
+        self.wait_for_result(rx)
+            .await
+            .map(|requests| Response::new(types::PendingRequestList::from(requests)))
+    }
+
+    async fn cancel_pending_request(&self, request: Request<String>) -> ServiceResult<()> {
+        log::debug!("cancel_pending_request");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::CancelPendingRequest(
+            tx,
+            request.into_inner(),
+        ))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
     // WireGuard key management
     //
 
@@ -659,25 +1438,138 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
     }
 
-    async fn update_custom_list(&self, request: Request<types::CustomList>) -> ServiceResult<()> {
-        log::debug!("update_custom_list");
-        let custom_list = mullvad_types::custom_list::CustomList::try_from(request.into_inner())?;
+    async fn update_custom_list(&self, request: Request<types::CustomList>) -> ServiceResult<()> {
+        log::debug!("update_custom_list");
+        let custom_list = mullvad_types::custom_list::CustomList::try_from(request.into_inner())?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::UpdateCustomList(tx, custom_list))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    async fn clear_custom_lists(&self, _: Request<()>) -> ServiceResult<()> {
+        log::debug!("clear_custom_lists");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ClearCustomLists(tx))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    // Custom relays
+    //
+
+    async fn add_custom_relay(&self, request: Request<types::CustomRelay>) -> ServiceResult<()> {
+        log::debug!("add_custom_relay");
+        let relay = mullvad_types::custom_relay::CustomRelay::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::AddCustomRelay(tx, relay))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    async fn remove_custom_relay(&self, request: Request<String>) -> ServiceResult<()> {
+        log::debug!("remove_custom_relay");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::RemoveCustomRelay(tx, request.into_inner()))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    // Profiles
+    //
+
+    async fn save_profile(&self, request: Request<String>) -> ServiceResult<()> {
+        log::debug!("save_profile");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SaveProfile(tx, request.into_inner()))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    async fn remove_profile(&self, request: Request<String>) -> ServiceResult<()> {
+        log::debug!("remove_profile");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::RemoveProfile(tx, request.into_inner()))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    async fn use_profile(&self, request: Request<String>) -> ServiceResult<()> {
+        log::debug!("use_profile");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::UseProfile(tx, request.into_inner()))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    // Trusted networks
+    //
+
+    async fn get_trusted_networks(&self, _: Request<()>) -> ServiceResult<types::TrustedNetworks> {
+        log::debug!("get_trusted_networks");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetTrustedNetworks(tx))?;
+        let networks = self.wait_for_result(rx).await?;
+        Ok(Response::new(types::TrustedNetworks::from(networks)))
+    }
+
+    async fn set_trusted_network_rule(
+        &self,
+        request: Request<types::TrustedNetworkRule>,
+    ) -> ServiceResult<()> {
+        log::debug!("set_trusted_network_rule");
+        let rule =
+            mullvad_types::trusted_network::TrustedNetworkRule::try_from(request.into_inner())
+                .map_err(map_protobuf_type_err)?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetTrustedNetworkRule(tx, rule))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    async fn remove_trusted_network_rule(
+        &self,
+        request: Request<types::NetworkMatch>,
+    ) -> ServiceResult<()> {
+        log::debug!("remove_trusted_network_rule");
+        let network = mullvad_types::trusted_network::NetworkMatch::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::UpdateCustomList(tx, custom_list))?;
-        self.wait_for_result(rx)
-            .await?
-            .map(Response::new)
-            .map_err(map_daemon_error)
+        self.send_command_to_daemon(DaemonCommand::RemoveTrustedNetworkRule(tx, network))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
     }
 
-    async fn clear_custom_lists(&self, _: Request<()>) -> ServiceResult<()> {
-        log::debug!("clear_custom_lists");
+    // Excluded locations
+    //
+
+    async fn set_excluded_locations(
+        &self,
+        request: Request<types::ExcludedLocations>,
+    ) -> ServiceResult<()> {
+        log::debug!("set_excluded_locations");
+        let excluded_locations =
+            mullvad_types::excluded_locations::ExcludedLocations::try_from(request.into_inner())
+                .map_err(map_protobuf_type_err)?;
         let (tx, rx) = oneshot::channel();
-        self.send_command_to_daemon(DaemonCommand::ClearCustomLists(tx))?;
-        self.wait_for_result(rx)
-            .await?
-            .map(Response::new)
-            .map_err(map_daemon_error)
+        self.send_command_to_daemon(DaemonCommand::SetExcludedLocations(tx, excluded_locations))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
     }
 
     // Access Methods
@@ -771,6 +1663,20 @@ impl ManagementService for ManagementServiceImpl {
             .map_err(map_daemon_error)
     }
 
+    async fn get_api_access_method_stats(
+        &self,
+        _: Request<()>,
+    ) -> ServiceResult<types::AccessMethodStatsList> {
+        log::debug!("get_api_access_method_stats");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetApiAccessMethodStats(tx))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(types::AccessMethodStatsList::from)
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
     async fn test_custom_api_access_method(
         &self,
         config: Request<types::CustomProxy>,
@@ -884,6 +1790,79 @@ impl ManagementService for ManagementServiceImpl {
         }
     }
 
+    #[cfg(target_os = "linux")]
+    async fn set_split_tunnel_mode(
+        &self,
+        request: Request<types::SplitTunnelMode>,
+    ) -> ServiceResult<()> {
+        let mode = talpid_types::split_tunnel::SplitTunnelMode::try_from(request.into_inner())
+            .map_err(map_protobuf_type_err)?;
+        log::debug!("set_split_tunnel_mode({mode})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetSplitTunnelMode(tx, mode))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_split_tunnel_mode(&self, _: Request<types::SplitTunnelMode>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Include-only split tunneling is only supported on Linux",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_split_tunnel_owners(
+        &self,
+        request: Request<types::SplitTunnelOwners>,
+    ) -> ServiceResult<()> {
+        let owners =
+            Vec::<talpid_types::split_tunnel::SplitTunnelOwner>::try_from(request.into_inner())
+                .map_err(map_protobuf_type_err)?;
+        log::debug!("set_split_tunnel_owners({owners:?})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetSplitTunnelOwners(tx, owners))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_split_tunnel_owners(
+        &self,
+        _: Request<types::SplitTunnelOwners>,
+    ) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Splitting users and groups from the tunnel is only supported on Linux",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn set_split_tunnel_domains(
+        &self,
+        request: Request<types::SplitTunnelDomains>,
+    ) -> ServiceResult<()> {
+        let domains = request.into_inner().domains;
+        log::debug!("set_split_tunnel_domains({domains:?})");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetSplitTunnelDomains(tx, domains))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn set_split_tunnel_domains(
+        &self,
+        _: Request<types::SplitTunnelDomains>,
+    ) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Splitting domains from the tunnel is only supported on Linux",
+        ))
+    }
+
     #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     async fn add_split_tunnel_app(&self, request: Request<String>) -> ServiceResult<()> {
         use mullvad_types::settings::SplitApp;
@@ -1009,6 +1988,135 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(()))
     }
 
+    #[cfg(target_os = "macos")]
+    async fn get_conflicting_software(
+        &self,
+        _: Request<()>,
+    ) -> ServiceResult<types::ConflictingSoftwareList> {
+        log::debug!("get_conflicting_software");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetConflictingSoftware(tx))?;
+        let conflicts = self.wait_for_result(rx).await?;
+        Ok(Response::new(types::ConflictingSoftwareList::from(
+            conflicts,
+        )))
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    async fn get_conflicting_software(
+        &self,
+        _: Request<()>,
+    ) -> ServiceResult<types::ConflictingSoftwareList> {
+        Ok(Response::new(types::ConflictingSoftwareList {
+            conflicts: vec![],
+        }))
+    }
+
+    #[cfg(windows)]
+    async fn get_driver_status(&self, _: Request<()>) -> ServiceResult<types::DriverStatusList> {
+        log::debug!("get_driver_status");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetDriverStatus(tx))?;
+        self.wait_for_result(rx)
+            .await?
+            .map_err(map_daemon_error)
+            .map(|drivers| {
+                Response::new(types::DriverStatusList {
+                    drivers: drivers.into_iter().map(types::DriverStatus::from).collect(),
+                })
+            })
+    }
+
+    #[cfg(not(windows))]
+    async fn get_driver_status(&self, _: Request<()>) -> ServiceResult<types::DriverStatusList> {
+        Ok(Response::new(types::DriverStatusList { drivers: vec![] }))
+    }
+
+    #[cfg(windows)]
+    async fn repair_driver(
+        &self,
+        request: Request<types::DriverRepairRequest>,
+    ) -> ServiceResult<types::DriverRepairResult> {
+        log::debug!("repair_driver");
+        let driver = talpid_types::drivers::Driver::try_from(request.into_inner())?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::RepairDriver(tx, driver))?;
+        self.wait_for_result(rx)
+            .await?
+            .map_err(map_daemon_error)
+            .map(|result| Response::new(types::DriverRepairResult::from(result)))
+    }
+
+    #[cfg(not(windows))]
+    async fn repair_driver(
+        &self,
+        _: Request<types::DriverRepairRequest>,
+    ) -> ServiceResult<types::DriverRepairResult> {
+        Err(Status::unimplemented(
+            "Driver repair is only supported on Windows",
+        ))
+    }
+
+    #[cfg(target_os = "macos")]
+    async fn get_pf_diagnostics(&self, _: Request<()>) -> ServiceResult<String> {
+        log::debug!("get_pf_diagnostics");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetPfDiagnostics(tx))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    async fn get_pf_diagnostics(&self, _: Request<()>) -> ServiceResult<String> {
+        Err(Status::unimplemented(
+            "PF diagnostics are only supported on macOS",
+        ))
+    }
+
+    #[cfg(target_os = "linux")]
+    async fn get_active_dns_backend(
+        &self,
+        _: Request<()>,
+    ) -> ServiceResult<types::ActiveDnsBackend> {
+        log::debug!("get_active_dns_backend");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetActiveDnsBackend(tx))?;
+        let backend = self.wait_for_result(rx).await?.map_err(map_daemon_error)?;
+        Ok(Response::new(types::ActiveDnsBackend {
+            backend: i32::from(types::dns_backends::Backend::from(backend)),
+        }))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    async fn get_active_dns_backend(
+        &self,
+        _: Request<()>,
+    ) -> ServiceResult<types::ActiveDnsBackend> {
+        Err(Status::unimplemented(
+            "Selecting the DNS backend is only supported on Linux",
+        ))
+    }
+
+    #[cfg(windows)]
+    async fn get_wfp_diagnostics(&self, _: Request<()>) -> ServiceResult<String> {
+        log::debug!("get_wfp_diagnostics");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::GetWfpDiagnostics(tx))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(not(windows))]
+    async fn get_wfp_diagnostics(&self, _: Request<()>) -> ServiceResult<String> {
+        Err(Status::unimplemented(
+            "WFP diagnostics are only supported on Windows",
+        ))
+    }
+
     async fn apply_json_settings(&self, blob: Request<String>) -> ServiceResult<()> {
         log::debug!("apply_json_settings");
         let (tx, rx) = oneshot::channel();
@@ -1025,6 +2133,23 @@ impl ManagementService for ManagementServiceImpl {
         Ok(Response::new(blob))
     }
 
+    async fn export_settings(&self, request: Request<bool>) -> ServiceResult<String> {
+        let include_secrets = request.into_inner();
+        log::debug!("export_settings({})", include_secrets);
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ExportSettings(tx, include_secrets))?;
+        let blob = self.wait_for_result(rx).await??;
+        Ok(Response::new(blob))
+    }
+
+    async fn import_settings(&self, blob: Request<String>) -> ServiceResult<()> {
+        log::debug!("import_settings");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::ImportSettings(tx, blob.into_inner()))?;
+        self.wait_for_result(rx).await??;
+        Ok(Response::new(()))
+    }
+
     #[cfg(target_os = "android")]
     async fn init_play_purchase(
         &self,
@@ -1115,6 +2240,46 @@ impl ManagementService for ManagementServiceImpl {
         self.wait_for_result(rx).await?;
         Ok(Response::new(()))
     }
+
+    #[cfg(feature = "api-override")]
+    async fn set_api_endpoint(
+        &self,
+        request: Request<types::ApiEndpointOverride>,
+    ) -> ServiceResult<()> {
+        log::debug!("set_api_endpoint");
+        let endpoint = api_endpoint_from_proto(request.into_inner()).await?;
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetApiEndpoint(tx, Some(endpoint)))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(not(feature = "api-override"))]
+    async fn set_api_endpoint(&self, _: Request<types::ApiEndpointOverride>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Changing the API endpoint requires the `api-override` feature",
+        ))
+    }
+
+    #[cfg(feature = "api-override")]
+    async fn reset_api_endpoint(&self, _: Request<()>) -> ServiceResult<()> {
+        log::debug!("reset_api_endpoint");
+        let (tx, rx) = oneshot::channel();
+        self.send_command_to_daemon(DaemonCommand::SetApiEndpoint(tx, None))?;
+        self.wait_for_result(rx)
+            .await?
+            .map(Response::new)
+            .map_err(map_daemon_error)
+    }
+
+    #[cfg(not(feature = "api-override"))]
+    async fn reset_api_endpoint(&self, _: Request<()>) -> ServiceResult<()> {
+        Err(Status::unimplemented(
+            "Changing the API endpoint requires the `api-override` feature",
+        ))
+    }
 }
 
 impl ManagementServiceImpl {
@@ -1130,6 +2295,37 @@ impl ManagementServiceImpl {
     }
 }
 
+/// Collect the statistics of the current tunnel, or return `None` if it is not connected.
+async fn get_tunnel_stats(daemon_tx: &DaemonCommandSender) -> Result<Option<TunnelStats>, Status> {
+    let send = |command| {
+        daemon_tx
+            .send(command)
+            .map_err(|_| Status::internal("the daemon channel receiver has been dropped"))
+    };
+    let dropped = |_| Status::internal("sender was dropped");
+
+    let (tx, rx) = oneshot::channel();
+    send(DaemonCommand::GetTrafficStats(tx))?;
+    let Some(traffic) = rx.await.map_err(dropped)? else {
+        return Ok(None);
+    };
+    let (tx, rx) = oneshot::channel();
+    send(DaemonCommand::GetState(tx))?;
+    let relay = match rx.await.map_err(dropped)? {
+        TunnelState::Connected { location, .. } => location.and_then(|location| location.hostname),
+        _ => return Ok(None),
+    };
+
+    let handshake_age = traffic
+        .last_handshake
+        .and_then(|handshake| SystemTime::now().duration_since(handshake).ok());
+    Ok(Some(TunnelStats {
+        traffic,
+        handshake_age,
+        relay,
+    }))
+}
+
 /// The running management interface serving gRPC requests.
 pub struct ManagementInterfaceServer {
     /// The rpc server spawned by [`Self::start`]. When the underlying join handle yields, the rpc
@@ -1275,6 +2471,16 @@ impl ManagementInterfaceEventBroadcaster {
         })
     }
 
+    /// Notify that the account expiry was fetched or changed.
+    pub(crate) fn notify_account_expiry(&self, event: mullvad_types::account::AccountExpiryEvent) {
+        log::debug!("Broadcasting account expiry event");
+        self.notify(types::DaemonEvent {
+            event: Some(daemon_event::Event::AccountExpiry(
+                types::AccountExpiryEvent::from(event),
+            )),
+        })
+    }
+
     /// Notify that a device was revoked using `RemoveDevice`.
     pub(crate) fn notify_remove_device_event(
         &self,
@@ -1300,6 +2506,58 @@ impl ManagementInterfaceEventBroadcaster {
             )),
         })
     }
+
+    /// Notify that the set of detected conflicting software changed.
+    #[cfg(target_os = "macos")]
+    pub(crate) fn notify_conflicting_software(
+        &self,
+        conflicts: Vec<mullvad_types::conflicting_software::ConflictingSoftware>,
+    ) {
+        log::debug!("Broadcasting conflicting software");
+        self.notify(types::DaemonEvent {
+            event: Some(daemon_event::Event::ConflictingSoftware(
+                types::ConflictingSoftwareList::from(conflicts),
+            )),
+        })
+    }
+
+    /// Notify that the tunnel could not use the selected WireGuard backend.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn notify_wireguard_backend_fallback(
+        &self,
+        fallback: talpid_types::net::wireguard::BackendFallback,
+    ) {
+        log::debug!("Broadcasting WireGuard backend fallback");
+        self.notify(types::DaemonEvent {
+            event: Some(daemon_event::Event::WireguardBackendFallback(
+                types::WireguardBackendFallback::from(fallback),
+            )),
+        })
+    }
+
+    /// Notify that another process overwrote the DNS configuration.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn notify_dns_interference(&self, interference: talpid_types::dns::DnsInterference) {
+        log::debug!("Broadcasting DNS interference");
+        self.notify(types::DaemonEvent {
+            event: Some(daemon_event::Event::DnsInterference(
+                types::DnsInterference::from(interference),
+            )),
+        })
+    }
+
+    /// Notify that negotiation of an ephemeral peer failed and is being retried.
+    pub(crate) fn notify_negotiation_retry(
+        &self,
+        retry: talpid_types::net::wireguard::NegotiationRetry,
+    ) {
+        log::debug!("Broadcasting ephemeral peer negotiation retry");
+        self.notify(types::DaemonEvent {
+            event: Some(daemon_event::Event::NegotiationRetry(
+                types::NegotiationRetry::from(retry),
+            )),
+        })
+    }
 }
 
 /// Converts [`crate::Error`] into a tonic status.
@@ -1309,6 +2567,52 @@ fn map_daemon_error(error: crate::Error) -> Status {
     match error {
         DaemonError::RestError(error) => map_rest_error(&error),
         DaemonError::SettingsError(error) => Status::from(error),
+        DaemonError::CustomRelayError(error) => settings::handle_custom_relay_error(error),
+        DaemonError::ProfileError(error) => settings::handle_profile_error(error),
+        #[cfg(not(target_os = "android"))]
+        DaemonError::StateHookError(error) => Status::invalid_argument(error.to_string()),
+        #[cfg(not(target_os = "android"))]
+        DaemonError::MetricsError(error) => Status::failed_precondition(error.to_string()),
+        #[cfg(not(target_os = "android"))]
+        DaemonError::LockdownPauseError(error @ crate::lockdown_pause::Error::TooLong) => {
+            Status::invalid_argument(error.to_string())
+        }
+        #[cfg(not(target_os = "android"))]
+        DaemonError::LockdownPauseError(error) => Status::failed_precondition(error.to_string()),
+        #[cfg(not(target_os = "android"))]
+        DaemonError::CaptivePortalError(error @ crate::captive_portal::Error::TooLong) => {
+            Status::invalid_argument(error.to_string())
+        }
+        #[cfg(not(target_os = "android"))]
+        DaemonError::CaptivePortalError(error) => Status::failed_precondition(error.to_string()),
+        #[cfg(not(target_os = "android"))]
+        DaemonError::FirewallExceptionError(error) => Status::invalid_argument(error.to_string()),
+        #[cfg(not(target_os = "android"))]
+        DaemonError::CustomRouteError(error) => Status::invalid_argument(error.to_string()),
+        #[cfg(not(target_os = "android"))]
+        DaemonError::AllowedIncomingError(error) => Status::invalid_argument(error.to_string()),
+        #[cfg(target_os = "linux")]
+        DaemonError::SplitTunnelOwnerError(error) => Status::invalid_argument(error.to_string()),
+        #[cfg(target_os = "linux")]
+        DaemonError::SplitTunnelDomainError(error) => Status::invalid_argument(error.to_string()),
+        DaemonError::LanSharingError(error) => Status::invalid_argument(error.to_string()),
+        error @ DaemonError::BlockIpv6WithIpv6Only => Status::invalid_argument(error.to_string()),
+        DaemonError::SplitDnsError(error) => Status::invalid_argument(error.to_string()),
+        DaemonError::DnsFilterError(error) => Status::invalid_argument(error.to_string()),
+        #[cfg(target_os = "linux")]
+        error @ DaemonError::InvalidNftablesPriority(_) => {
+            Status::invalid_argument(error.to_string())
+        }
+        #[cfg(target_os = "linux")]
+        error @ DaemonError::InvalidRoutingTable(_) => Status::invalid_argument(error.to_string()),
+        #[cfg(target_os = "linux")]
+        error @ DaemonError::InvalidRulePriority(_) => Status::invalid_argument(error.to_string()),
+        #[cfg(target_os = "macos")]
+        error @ DaemonError::InvalidPfAnchor(_) => Status::invalid_argument(error.to_string()),
+        #[cfg(target_os = "linux")]
+        error @ DaemonError::InvalidDnsBackendOrder => Status::invalid_argument(error.to_string()),
+        #[cfg(target_os = "linux")]
+        error @ DaemonError::DnsBackendError(_) => Status::unavailable(error.to_string()),
         DaemonError::AlreadyLoggedIn => Status::already_exists(error.to_string()),
         DaemonError::LoginError(error) => map_device_error(&error),
         DaemonError::LogoutError(error) => map_device_error(&error),
@@ -1317,8 +2621,13 @@ fn map_daemon_error(error: crate::Error) -> Status {
         DaemonError::RemoveDeviceError(error) => map_device_error(&error),
         DaemonError::UpdateDeviceError(error) => map_device_error(&error),
         DaemonError::VoucherSubmission(error) => map_device_error(&error),
+        DaemonError::RequestQueue(error @ request_queue::Error::NotFound) => {
+            Status::not_found(error.to_string())
+        }
         #[cfg(any(target_os = "windows", target_os = "macos"))]
         DaemonError::SplitTunnelError(error) => map_split_tunnel_error(error),
+        #[cfg(windows)]
+        DaemonError::DriverInUse => Status::failed_precondition(error.to_string()),
         DaemonError::AccountHistory(error) => map_account_history_error(error),
         DaemonError::NoAccountNumber | DaemonError::NoAccountNumberHistory => {
             Status::unauthenticated(error.to_string())
@@ -1407,3 +2716,37 @@ fn map_protobuf_type_err(err: types::FromProtobufTypeError) -> Status {
         types::FromProtobufTypeError::InvalidArgument(err) => Status::invalid_argument(err),
     }
 }
+
+/// Convert an endpoint given over RPC, resolving the API address from the hostname if no address
+/// is given.
+#[cfg(feature = "api-override")]
+async fn api_endpoint_from_proto(
+    endpoint: types::ApiEndpointOverride,
+) -> Result<mullvad_api::ApiEndpoint, Status> {
+    const API_PORT: u16 = 443;
+
+    let address = match (endpoint.address, &endpoint.host) {
+        (Some(address), _) => Some(
+            address
+                .parse()
+                .map_err(|_| Status::invalid_argument("Invalid API address"))?,
+        ),
+        (None, Some(host)) => {
+            let address = tokio::net::lookup_host((host.as_str(), API_PORT))
+                .await
+                .ok()
+                .and_then(|mut addresses| addresses.next())
+                .ok_or_else(|| {
+                    Status::invalid_argument(format!("Unable to resolve API host {host}"))
+                })?;
+            Some(address)
+        }
+        (None, None) => None,
+    };
+    Ok(mullvad_api::ApiEndpoint {
+        host: endpoint.host,
+        address,
+        disable_tls: endpoint.disable_tls,
+        force_direct: endpoint.force_direct,
+    })
+}
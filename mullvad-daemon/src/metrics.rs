@@ -0,0 +1,431 @@
+//! Serves metrics about the tunnel and the API in the Prometheus text format, so that they can be
+//! scraped by a local Prometheus instance. The server is disabled by default, and it only listens
+//! on localhost.
+
+use crate::{Daemon, DaemonEventSender, InternalDaemonEvent};
+use futures::channel::oneshot;
+use mullvad_types::{settings::MetricsSettings, states::TunnelState};
+use std::{
+    fmt, io,
+    net::{Ipv4Addr, SocketAddr},
+    time::{Duration, SystemTime},
+};
+use talpid_core::{mpsc::Sender, tunnel_state_machine::TunnelCommand};
+use talpid_types::{
+    tunnel::{ActionAfterDisconnect, TrafficStats},
+    ErrorExt,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    task::JoinHandle,
+};
+
+const METRICS_PATH: &str = "/metrics";
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Requests with headers larger than this are rejected.
+const MAX_REQUEST_SIZE: usize = 8 * 1024;
+/// Clients that have not sent a complete request within this time are disconnected.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+const TUNNEL_STATES: &[&str] = &[
+    "disconnected",
+    "connecting",
+    "connected",
+    "disconnecting",
+    "paused",
+    "error",
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to listen on {0}")]
+    Bind(SocketAddr, #[source] io::Error),
+}
+
+/// Serves metrics until it is dropped.
+pub struct MetricsServer {
+    port: u16,
+    task: JoinHandle<()>,
+}
+
+impl MetricsServer {
+    async fn start(port: u16, daemon_tx: DaemonEventSender) -> Result<Self, Error> {
+        let address = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+        let listener = TcpListener::bind(address)
+            .await
+            .map_err(|error| Error::Bind(address, error))?;
+        log::info!("Serving metrics on http://{address}{METRICS_PATH}");
+
+        let task = tokio::spawn(async move {
+            loop {
+                let stream = match listener.accept().await {
+                    Ok((stream, _)) => stream,
+                    Err(error) => {
+                        log::debug!(
+                            "{}",
+                            error.display_chain_with_msg("Failed to accept metrics connection")
+                        );
+                        // Avoid spinning if e.g. the process is out of file descriptors
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let daemon_tx = daemon_tx.clone();
+                tokio::spawn(async move {
+                    if let Err(error) = handle_connection(stream, daemon_tx).await {
+                        log::debug!(
+                            "{}",
+                            error.display_chain_with_msg("Failed to serve metrics request")
+                        );
+                    }
+                });
+            }
+        });
+
+        Ok(Self { port, task })
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, daemon_tx: DaemonEventSender) -> io::Result<()> {
+    let request = tokio::time::timeout(REQUEST_TIMEOUT, read_request(&mut stream))
+        .await
+        .map_err(|_| io::Error::from(io::ErrorKind::TimedOut))??;
+
+    let response = match parse_request_line(&request) {
+        Some(("GET", path)) if path == METRICS_PATH => {
+            let (tx, rx) = oneshot::channel();
+            let metrics = match daemon_tx.send(InternalDaemonEvent::GetMetrics(tx)) {
+                Ok(()) => rx.await.ok(),
+                Err(_) => None,
+            };
+            match metrics {
+                Some(metrics) => response("200 OK", CONTENT_TYPE, &metrics.to_string()),
+                None => response(
+                    "503 Service Unavailable",
+                    "text/plain",
+                    "The daemon is shutting down\n",
+                ),
+            }
+        }
+        Some(("GET", _)) => response("404 Not Found", "text/plain", "Not found\n"),
+        Some(_) => response("405 Method Not Allowed", "text/plain", "Use GET\n"),
+        None => response("400 Bad Request", "text/plain", "Bad request\n"),
+    };
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Read the request line and headers. The body, if any, is ignored.
+async fn read_request(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let mut request = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        if request.len() > MAX_REQUEST_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Request is too large",
+            ));
+        }
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+    Ok(request)
+}
+
+/// Returns the method and the path, without any query, of an HTTP/1.x request.
+fn parse_request_line(request: &[u8]) -> Option<(&str, &str)> {
+    let line = request.split(|&byte| byte == b'\r').next()?;
+    let mut parts = std::str::from_utf8(line).ok()?.split(' ');
+    let method = parts.next()?;
+    let target = parts.next()?;
+    let version = parts.next()?;
+    if !version.starts_with("HTTP/1.") || parts.next().is_some() {
+        return None;
+    }
+    let path = target.split('?').next()?;
+    Some((method, path))
+}
+
+fn response(status: &str, content_type: &str, body: &str) -> String {
+    format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: \
+         close\r\n\r\n{body}",
+        body.len()
+    )
+}
+
+/// The values that are served. Rendered in the Prometheus text format by the [fmt::Display]
+/// implementation.
+pub struct Metrics {
+    tunnel_state: &'static str,
+    traffic_stats: Option<TrafficStats>,
+    reconnects: u64,
+    api_requests: Vec<ApiRequests>,
+}
+
+/// Number of API requests that were sent using an access method.
+struct ApiRequests {
+    access_method: String,
+    successes: u64,
+    failures: u64,
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        header(
+            f,
+            "mullvad_tunnel_state",
+            "gauge",
+            "Whether the tunnel is in the given state.",
+        )?;
+        for state in TUNNEL_STATES {
+            let value = u8::from(*state == self.tunnel_state);
+            writeln!(f, "mullvad_tunnel_state{{state=\"{state}\"}} {value}")?;
+        }
+
+        if let Some(stats) = &self.traffic_stats {
+            header(
+                f,
+                "mullvad_tunnel_transmitted_bytes_total",
+                "counter",
+                "Bytes sent through the current tunnel.",
+            )?;
+            writeln!(
+                f,
+                "mullvad_tunnel_transmitted_bytes_total {}",
+                stats.tx_bytes
+            )?;
+            header(
+                f,
+                "mullvad_tunnel_received_bytes_total",
+                "counter",
+                "Bytes received through the current tunnel.",
+            )?;
+            writeln!(f, "mullvad_tunnel_received_bytes_total {}", stats.rx_bytes)?;
+
+            let handshake_age = stats
+                .last_handshake
+                .and_then(|handshake| SystemTime::now().duration_since(handshake).ok());
+            if let Some(age) = handshake_age {
+                header(
+                    f,
+                    "mullvad_tunnel_handshake_age_seconds",
+                    "gauge",
+                    "Time since the last handshake with the exit relay.",
+                )?;
+                writeln!(
+                    f,
+                    "mullvad_tunnel_handshake_age_seconds {}",
+                    age.as_secs_f64()
+                )?;
+            }
+        }
+
+        header(
+            f,
+            "mullvad_tunnel_reconnects_total",
+            "counter",
+            "Number of times the tunnel has reconnected since the daemon started.",
+        )?;
+        writeln!(f, "mullvad_tunnel_reconnects_total {}", self.reconnects)?;
+
+        header(
+            f,
+            "mullvad_api_requests_total",
+            "counter",
+            "API requests sent using each access method, by outcome.",
+        )?;
+        for requests in &self.api_requests {
+            let access_method = escape_label(&requests.access_method);
+            for (outcome, count) in [
+                ("success", requests.successes),
+                ("failure", requests.failures),
+            ] {
+                let labels = format!("access_method=\"{access_method}\",outcome=\"{outcome}\"");
+                writeln!(f, "mullvad_api_requests_total{{{labels}}} {count}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn header(f: &mut fmt::Formatter<'_>, name: &str, kind: &str, help: &str) -> fmt::Result {
+    writeln!(f, "# HELP {name} {help}")?;
+    writeln!(f, "# TYPE {name} {kind}")
+}
+
+fn escape_label(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn tunnel_state_name(state: &TunnelState) -> &'static str {
+    match state {
+        TunnelState::Disconnected { .. } => "disconnected",
+        TunnelState::Connecting { .. } => "connecting",
+        TunnelState::Connected { .. } => "connected",
+        TunnelState::Disconnecting(_) => "disconnecting",
+        TunnelState::Paused => "paused",
+        TunnelState::Error(_) => "error",
+    }
+}
+
+impl Daemon {
+    /// Start or stop the metrics server so that it matches the settings.
+    pub(crate) async fn update_metrics_server(&mut self) {
+        if let Err(error) = self.apply_metrics_settings(self.settings.metrics).await {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to start metrics server")
+            );
+        }
+    }
+
+    /// Start the metrics server before saving the settings, so that the user is told if the port
+    /// is unavailable.
+    pub(crate) async fn set_metrics_settings(
+        &mut self,
+        metrics: MetricsSettings,
+    ) -> Result<(), crate::Error> {
+        self.apply_metrics_settings(metrics)
+            .await
+            .map_err(crate::Error::MetricsError)?;
+        self.settings
+            .update(move |settings| settings.metrics = metrics)
+            .await
+            .map_err(crate::Error::SettingsError)?;
+        Ok(())
+    }
+
+    async fn apply_metrics_settings(&mut self, metrics: MetricsSettings) -> Result<(), Error> {
+        let port = metrics.enabled.then_some(metrics.port);
+        if self.metrics_server.as_ref().map(|server| server.port) == port {
+            return Ok(());
+        }
+        self.metrics_server = match port {
+            Some(port) => Some(MetricsServer::start(port, self.tx.clone()).await?),
+            None => None,
+        };
+        Ok(())
+    }
+
+    /// Count the transition to `new_state` if it means that the tunnel is reconnecting.
+    pub(crate) fn count_reconnect(&mut self, new_state: &TunnelState) {
+        let reconnecting = matches!(new_state, TunnelState::Connecting { .. })
+            && matches!(
+                self.tunnel_state,
+                TunnelState::Connecting { .. }
+                    | TunnelState::Connected { .. }
+                    | TunnelState::Error(_)
+                    | TunnelState::Disconnecting(ActionAfterDisconnect::Reconnect)
+            );
+        if reconnecting {
+            self.reconnect_count += 1;
+        }
+    }
+
+    pub(crate) fn on_get_metrics(&self, tx: oneshot::Sender<Metrics>) {
+        let tunnel_state = tunnel_state_name(&self.tunnel_state);
+        let reconnects = self.reconnect_count;
+        let access_methods: Vec<_> = self
+            .settings
+            .api_access_methods
+            .iter()
+            .map(|method| (method.get_id(), method.name.clone()))
+            .collect();
+
+        let (stats_tx, stats_rx) = oneshot::channel();
+        self.send_tunnel_command(TunnelCommand::GetTrafficStats(stats_tx));
+        let access_mode_handler = self.access_mode_handler.clone();
+
+        tokio::spawn(async move {
+            let traffic_stats = stats_rx.await.ok().flatten();
+            let access_method_stats = access_mode_handler
+                .get_stats()
+                .await
+                .inspect_err(|error| {
+                    log::debug!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to get access method statistics")
+                    )
+                })
+                .unwrap_or_default();
+            let api_requests = access_methods
+                .into_iter()
+                .filter_map(|(id, access_method)| {
+                    let stats = access_method_stats.get(&id)?;
+                    Some(ApiRequests {
+                        access_method,
+                        successes: stats.successes,
+                        failures: stats.failures,
+                    })
+                })
+                .collect();
+            let _ = tx.send(Metrics {
+                tunnel_state,
+                traffic_stats,
+                reconnects,
+                api_requests,
+            });
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_line() {
+        let request = b"GET /metrics?name=x HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        assert_eq!(parse_request_line(request), Some(("GET", "/metrics")));
+        assert_eq!(parse_request_line(b"GET /metrics\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn test_render_metrics() {
+        let metrics = Metrics {
+            tunnel_state: "connected",
+            traffic_stats: Some(TrafficStats {
+                tx_bytes: 10,
+                rx_bytes: 20,
+                ..Default::default()
+            }),
+            reconnects: 2,
+            api_requests: vec![ApiRequests {
+                access_method: "My \"proxy\"".to_owned(),
+                successes: 3,
+                failures: 1,
+            }],
+        }
+        .to_string();
+
+        assert!(metrics.contains("mullvad_tunnel_state{state=\"connected\"} 1\n"));
+        assert!(metrics.contains("mullvad_tunnel_state{state=\"error\"} 0\n"));
+        assert!(metrics.contains("mullvad_tunnel_received_bytes_total 20\n"));
+        assert!(!metrics.contains("mullvad_tunnel_handshake_age_seconds"));
+        assert!(metrics.contains("mullvad_tunnel_reconnects_total 2\n"));
+        assert!(metrics.contains(
+            "mullvad_api_requests_total{access_method=\"My \\\"proxy\\\"\",outcome=\"failure\"} 1\n"
+        ));
+    }
+}
@@ -47,6 +47,7 @@ mod account_history;
 mod device;
 mod v1;
 mod v10;
+mod v11;
 mod v2;
 mod v3;
 mod v4;
@@ -173,6 +174,12 @@ pub async fn migrate_all(cache_dir: &Path, settings_dir: &Path) -> Result<Option
     Ok(migration_data)
 }
 
+/// Migrate settings that were not read from the settings file, such as imported settings, to the
+/// current format.
+pub(crate) async fn migrate_settings_value(settings: &mut serde_json::Value) -> Result<()> {
+    migrate_settings(None, settings).await.map(|_| ())
+}
+
 async fn migrate_settings(
     directories: Option<Directories<'_>>,
     settings: &mut serde_json::Value,
@@ -209,6 +216,7 @@ async fn migrate_settings(
     )?;
 
     v10::migrate(settings)?;
+    v11::migrate(settings)?;
 
     Ok(migration_data)
 }
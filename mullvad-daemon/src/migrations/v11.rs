@@ -0,0 +1,77 @@
+use super::{Error, Result};
+use mullvad_types::settings::SettingsVersion;
+
+/// The `allow_lan` setting has been changed from a boolean to an enum, so that only some local
+/// networks can be allowed. `true` is migrated to `"all"`, and `false` to `"blocked"`.
+pub fn migrate(settings: &mut serde_json::Value) -> Result<()> {
+    if !version_matches(settings) {
+        return Ok(());
+    }
+
+    log::info!("Migrating settings format to v12");
+
+    migrate_allow_lan(settings)?;
+
+    settings["settings_version"] = serde_json::json!(SettingsVersion::V12);
+
+    Ok(())
+}
+
+fn version_matches(settings: &serde_json::Value) -> bool {
+    settings
+        .get("settings_version")
+        .map(|version| version == SettingsVersion::V11 as u64)
+        .unwrap_or(false)
+}
+
+fn migrate_allow_lan(settings: &mut serde_json::Value) -> Result<()> {
+    let allow_lan = match settings.get("allow_lan") {
+        Some(serde_json::Value::Bool(true)) => "all",
+        Some(serde_json::Value::Bool(false)) => "blocked",
+        Some(_) => return Err(Error::InvalidSettingsContent),
+        None => return Ok(()),
+    };
+    settings["allow_lan"] = serde_json::json!(allow_lan);
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{migrate, version_matches};
+    use serde_json::json;
+
+    #[test]
+    fn test_v11_to_v12_migration() {
+        let mut old_settings = json!({
+            "allow_lan": true,
+            "settings_version": 11,
+        });
+        assert!(version_matches(&old_settings));
+        migrate(&mut old_settings).unwrap();
+        let new_settings = json!({
+            "allow_lan": "all",
+            "settings_version": 12,
+        });
+        assert_eq!(&old_settings, &new_settings);
+
+        let mut old_settings = json!({
+            "allow_lan": false,
+            "settings_version": 11,
+        });
+        migrate(&mut old_settings).unwrap();
+        let new_settings = json!({
+            "allow_lan": "blocked",
+            "settings_version": 12,
+        });
+        assert_eq!(&old_settings, &new_settings);
+    }
+
+    #[test]
+    fn test_v11_to_v12_migration_invalid() {
+        let mut old_settings = json!({
+            "allow_lan": "yes",
+            "settings_version": 11,
+        });
+        assert!(migrate(&mut old_settings).is_err());
+    }
+}
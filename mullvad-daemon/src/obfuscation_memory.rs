@@ -0,0 +1,259 @@
+//! Remembers the obfuscation method that last worked on each network, so that automatic
+//! obfuscation can start with it instead of going through every method again.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use talpid_types::{net::ObfuscationType, ErrorExt};
+use tokio::{fs, io};
+
+const OBFUSCATION_MEMORY_FILE: &str = "obfuscation-memory.json";
+/// Number of networks to remember. The least recently used network is forgotten first.
+const MAX_NETWORKS: usize = 32;
+
+/// Identifies a network without revealing anything about it. This is a hash of the MAC address
+/// of the default gateway.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct NetworkId(String);
+
+impl NetworkId {
+    fn from_gateway_mac(mac: &str) -> Self {
+        use sha2::{Digest, Sha256};
+        NetworkId(format!("{:x}", Sha256::digest(mac.to_lowercase())))
+    }
+
+    /// Identify the network that the host is currently connected to, if possible.
+    #[cfg(target_os = "linux")]
+    pub async fn current() -> Option<Self> {
+        let routes = fs::read_to_string("/proc/net/route").await.ok()?;
+        let (interface, gateway) = linux::default_gateway(&routes)?;
+        let neighbors = fs::read_to_string("/proc/net/arp").await.ok()?;
+        let mac = linux::neighbor_mac(&neighbors, &interface, gateway)?;
+        Some(Self::from_gateway_mac(&mac))
+    }
+
+    /// Identify the network that the host is currently connected to, if possible.
+    #[cfg(not(target_os = "linux"))]
+    pub async fn current() -> Option<Self> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    network: NetworkId,
+    /// `None` if the tunnel worked without obfuscation
+    obfuscation: Option<ObfuscationType>,
+}
+
+/// Persistent map from networks to the obfuscation method that last worked on them.
+pub struct ObfuscationMemory {
+    cache_path: PathBuf,
+    /// Ordered from most to least recently used
+    entries: Vec<Entry>,
+}
+
+impl ObfuscationMemory {
+    /// Load the remembered networks from `cache_dir`. Nothing is remembered if this fails.
+    pub async fn load(cache_dir: &Path) -> Self {
+        let cache_path = cache_dir.join(OBFUSCATION_MEMORY_FILE);
+        let entries = match fs::read_to_string(&cache_path).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to parse obfuscation memory")
+                );
+                vec![]
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => vec![],
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to read obfuscation memory")
+                );
+                vec![]
+            }
+        };
+        ObfuscationMemory {
+            cache_path,
+            entries,
+        }
+    }
+
+    /// Returns the obfuscation method that last worked on `network`. `Some(None)` means that no
+    /// obfuscation was needed.
+    pub fn get(&self, network: &NetworkId) -> Option<Option<ObfuscationType>> {
+        self.entries
+            .iter()
+            .find(|entry| &entry.network == network)
+            .map(|entry| entry.obfuscation)
+    }
+
+    /// Remember that `obfuscation` worked on `network`.
+    pub async fn remember(&mut self, network: NetworkId, obfuscation: Option<ObfuscationType>) {
+        let entry = Entry {
+            network,
+            obfuscation,
+        };
+        if self.entries.first() == Some(&entry) {
+            return;
+        }
+        self.insert(entry);
+        self.save().await;
+    }
+
+    fn insert(&mut self, entry: Entry) {
+        self.entries.retain(|existing| existing.network != entry.network);
+        self.entries.insert(0, entry);
+        self.entries.truncate(MAX_NETWORKS);
+    }
+
+    async fn save(&self) {
+        match serde_json::to_string(&self.entries) {
+            Ok(data) => {
+                if let Err(error) = fs::write(&self.cache_path, data).await {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Failed to write obfuscation memory")
+                    );
+                }
+            }
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to serialize obfuscation memory")
+                )
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::net::Ipv4Addr;
+
+    const RTF_GATEWAY: u16 = 0x2;
+    const ATF_COM: u16 = 0x2;
+
+    /// Find the interface and gateway of the IPv4 default route with the lowest metric in the
+    /// contents of `/proc/net/route`.
+    pub fn default_gateway(routes: &str) -> Option<(String, Ipv4Addr)> {
+        routes
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let columns: Vec<_> = line.split_whitespace().collect();
+                let [interface, destination, gateway, flags, _, _, metric, mask, ..] =
+                    columns[..]
+                else {
+                    return None;
+                };
+                let flags = u16::from_str_radix(flags, 16).ok()?;
+                if destination != "00000000" || mask != "00000000" || flags & RTF_GATEWAY == 0 {
+                    return None;
+                }
+                // The address is stored in network byte order, but printed as a native integer
+                let gateway = u32::from_str_radix(gateway, 16).ok()?;
+                let gateway = Ipv4Addr::from(gateway.to_le_bytes());
+                let metric: u32 = metric.parse().ok()?;
+                Some((metric, interface.to_owned(), gateway))
+            })
+            .min_by_key(|(metric, ..)| *metric)
+            .map(|(_, interface, gateway)| (interface, gateway))
+    }
+
+    /// Find the MAC address of `address` on `interface` in the contents of `/proc/net/arp`.
+    pub fn neighbor_mac(neighbors: &str, interface: &str, address: Ipv4Addr) -> Option<String> {
+        neighbors.lines().skip(1).find_map(|line| {
+            let columns: Vec<_> = line.split_whitespace().collect();
+            let [ip, _, flags, mac, _, device] = columns[..] else {
+                return None;
+            };
+            let flags = u16::from_str_radix(flags.trim_start_matches("0x"), 16).ok()?;
+            let matches = device == interface
+                && ip.parse::<Ipv4Addr>() == Ok(address)
+                && flags & ATF_COM != 0
+                && mac != "00:00:00:00:00:00";
+            matches.then(|| mac.to_owned())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn memory() -> ObfuscationMemory {
+        ObfuscationMemory {
+            cache_path: PathBuf::new(),
+            entries: vec![],
+        }
+    }
+
+    #[test]
+    fn test_remember_networks() {
+        let mut memory = memory();
+        let home = NetworkId::from_gateway_mac("AA:BB:CC:DD:EE:FF");
+        let cafe = NetworkId::from_gateway_mac("11:22:33:44:55:66");
+        assert_eq!(home, NetworkId::from_gateway_mac("aa:bb:cc:dd:ee:ff"));
+
+        memory.insert(Entry {
+            network: home.clone(),
+            obfuscation: Some(ObfuscationType::Shadowsocks),
+        });
+        memory.insert(Entry {
+            network: cafe.clone(),
+            obfuscation: None,
+        });
+        assert_eq!(memory.get(&home), Some(Some(ObfuscationType::Shadowsocks)));
+        assert_eq!(memory.get(&cafe), Some(None));
+
+        memory.insert(Entry {
+            network: home.clone(),
+            obfuscation: Some(ObfuscationType::Tls),
+        });
+        assert_eq!(memory.get(&home), Some(Some(ObfuscationType::Tls)));
+        assert_eq!(memory.entries.len(), 2);
+        assert_eq!(memory.entries[0].network, home);
+    }
+
+    #[test]
+    fn test_forget_least_recently_used() {
+        let mut memory = memory();
+        for i in 0..=MAX_NETWORKS {
+            memory.insert(Entry {
+                network: NetworkId::from_gateway_mac(&format!("00:00:00:00:00:{i:02x}")),
+                obfuscation: None,
+            });
+        }
+        assert_eq!(memory.entries.len(), MAX_NETWORKS);
+        assert_eq!(
+            memory.get(&NetworkId::from_gateway_mac("00:00:00:00:00:00")),
+            None
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_gateway() {
+        const ROUTES: &str = "\
+Iface	Destination	Gateway 	Flags	RefCnt	Use	Metric	Mask		MTU	Window	IRTT
+wlan0	00000000	0101A8C0	0003	0	0	600	00000000	0	0	0
+eth0	00000000	FE01A8C0	0003	0	0	100	00000000	0	0	0
+eth0	0001A8C0	00000000	0001	0	0	100	00FFFFFF	0	0	0
+";
+        const NEIGHBORS: &str = "\
+IP address       HW type     Flags       HW address            Mask     Device
+192.168.1.254    0x1         0x2         aa:bb:cc:dd:ee:ff     *        eth0
+192.168.1.1      0x1         0x2         11:22:33:44:55:66     *        wlan0
+";
+        let (interface, gateway) = linux::default_gateway(ROUTES).unwrap();
+        assert_eq!(interface, "eth0");
+        assert_eq!(gateway, std::net::Ipv4Addr::new(192, 168, 1, 254));
+        assert_eq!(
+            linux::neighbor_mac(NEIGHBORS, &interface, gateway).as_deref(),
+            Some("aa:bb:cc:dd:ee:ff")
+        );
+        assert_eq!(linux::neighbor_mac(NEIGHBORS, "wlan0", gateway), None);
+    }
+}
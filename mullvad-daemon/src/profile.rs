@@ -0,0 +1,105 @@
+use crate::{dns, Daemon, Error};
+use futures::channel::oneshot;
+use mullvad_types::profile::{self, Profile};
+use talpid_core::tunnel_state_machine::TunnelCommand;
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "android"))]
+use {mullvad_types::settings::SplitApp, talpid_types::ErrorExt};
+
+impl Daemon {
+    /// Save the current settings as a profile with the given name, replacing any existing
+    /// profile with the same name.
+    ///
+    /// Returns an error if the name is invalid.
+    pub async fn save_profile(&mut self, name: String) -> Result<(), Error> {
+        self.settings
+            .try_update(|settings| {
+                let profile = Profile::from_settings(name, settings)?;
+                settings.profiles.save(profile);
+                Ok::<_, profile::Error>(())
+            })
+            .await
+            .map_err(Error::SettingsError)?;
+        Ok(())
+    }
+
+    /// Remove the profile with the given name.
+    ///
+    /// Returns an error if there is no such profile.
+    pub async fn remove_profile(&mut self, name: String) -> Result<(), Error> {
+        self.settings
+            .try_update(|settings| settings.profiles.remove(&name))
+            .await
+            .map_err(Error::SettingsError)?;
+        Ok(())
+    }
+
+    /// Replace the settings that are part of the profile with the given name, and reconnect if
+    /// the relay or obfuscation settings changed as a result.
+    ///
+    /// Returns an error if there is no such profile.
+    pub async fn use_profile(&mut self, name: String) -> Result<(), Error> {
+        let profile = self
+            .settings
+            .profiles
+            .find(&name)
+            .cloned()
+            .ok_or(Error::ProfileError(profile::Error::NotFound))?;
+        let old_settings = self.settings.to_settings();
+
+        let settings_changed = self
+            .settings
+            .update(|settings| profile.apply_to(settings))
+            .await
+            .map_err(Error::SettingsError)?;
+        if !settings_changed {
+            return Ok(());
+        }
+
+        if old_settings.tunnel_options.dns_options != self.settings.tunnel_options.dns_options {
+            let (tx, _rx) = oneshot::channel();
+            let dns = dns::addresses_from_options(&self.settings.tunnel_options.dns_options);
+            self.send_tunnel_command(TunnelCommand::Dns(dns, tx));
+        }
+
+        #[cfg(any(target_os = "windows", target_os = "macos", target_os = "android"))]
+        if old_settings.split_tunnel != self.settings.split_tunnel {
+            self.apply_profile_split_tunnel();
+        }
+
+        if old_settings.relay_settings != self.settings.relay_settings
+            || old_settings.obfuscation_settings != self.settings.obfuscation_settings
+        {
+            log::info!("Initiating tunnel restart because profile \"{name}\" was used");
+            self.reconnect_tunnel();
+        }
+        Ok(())
+    }
+
+    #[cfg(any(target_os = "windows", target_os = "macos", target_os = "android"))]
+    fn apply_profile_split_tunnel(&self) {
+        let split_tunnel = &self.settings.split_tunnel;
+        let tunnel_list = if split_tunnel.enable_exclusions {
+            split_tunnel
+                .apps
+                .iter()
+                .cloned()
+                .map(SplitApp::to_tunnel_command_repr)
+                .collect()
+        } else {
+            vec![]
+        };
+
+        let (result_tx, result_rx) = oneshot::channel();
+        self.send_tunnel_command(TunnelCommand::SetExcludedApps(result_tx, tunnel_list));
+        tokio::spawn(async move {
+            match result_rx.await {
+                Ok(Ok(())) => (),
+                Ok(Err(error)) => log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to set excluded apps list")
+                ),
+                Err(_) => log::error!("The tunnel failed to return a result"),
+            }
+        });
+    }
+}
@@ -0,0 +1,112 @@
+//! Opt-in measurement of the round-trip time to the relays that match the user's constraints, so
+//! that the relay selector can prefer low-latency relays.
+//!
+//! Relays are only measured while they can be reached directly, i.e. while disconnected and not
+//! blocking traffic. The latency is the time it takes to get a response to a TCP connection
+//! attempt, which works without any special privileges.
+
+use mullvad_relay_selector::{RelayLatencies, RelaySelector};
+use mullvad_types::relay_list::Relay;
+use rand::{seq::SliceRandom, Rng};
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+use tokio::{
+    net::TcpStream,
+    sync::watch,
+    time::{timeout, Instant},
+};
+
+/// How often the candidate relays are measured.
+const MEASUREMENT_INTERVAL: Duration = Duration::from_secs(30 * 60);
+/// Maximum number of relays measured each round.
+const MAX_RELAYS_PER_ROUND: usize = 50;
+/// Minimum time between measuring two relays. A random delay of up to the same length is added.
+const MEASUREMENT_DELAY: Duration = Duration::from_millis(200);
+/// Time to wait for a response. Relays that do not respond in time are assumed to have this
+/// latency.
+const MEASUREMENT_TIMEOUT: Duration = Duration::from_secs(2);
+/// Port that relays respond to TCP connections on.
+const MEASUREMENT_PORT: u16 = 443;
+
+/// Handle for enabling or disabling the measurements.
+pub(crate) struct RelayLatencyMonitorHandle {
+    active_tx: watch::Sender<bool>,
+}
+
+impl RelayLatencyMonitorHandle {
+    /// Start measuring relays while active. The monitor is initially inactive.
+    pub fn spawn(relay_selector: RelaySelector) -> Self {
+        let (active_tx, active_rx) = watch::channel(false);
+        tokio::spawn(run(relay_selector, active_rx));
+        Self { active_tx }
+    }
+
+    /// Set whether relays should be measured.
+    pub fn set_active(&self, active: bool) {
+        self.active_tx.send_if_modified(|current| {
+            let changed = *current != active;
+            *current = active;
+            changed
+        });
+    }
+}
+
+async fn run(relay_selector: RelaySelector, mut active_rx: watch::Receiver<bool>) {
+    let mut last_round: Option<Instant> = None;
+    loop {
+        if active_rx.wait_for(|active| *active).await.is_err() {
+            return;
+        }
+        let round = async {
+            if let Some(last_round) = last_round {
+                tokio::time::sleep_until(last_round + MEASUREMENT_INTERVAL).await;
+            }
+            // Count interrupted rounds too, so that relays are not measured more often when
+            // the tunnel state changes frequently
+            last_round = Some(Instant::now());
+            measure(&relay_selector).await;
+        };
+        tokio::select! {
+            _ = round => (),
+            result = active_rx.wait_for(|active| !*active) => {
+                if result.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Measure a random selection of the candidate relays.
+async fn measure(relay_selector: &RelaySelector) {
+    let mut relays = relay_selector.candidate_relays();
+    relays.shuffle(&mut rand::thread_rng());
+    relays.truncate(MAX_RELAYS_PER_ROUND);
+    log::debug!("Measuring the latency of {} relays", relays.len());
+
+    for relay in relays {
+        let jitter = rand::thread_rng().gen_range(Duration::ZERO..=MEASUREMENT_DELAY);
+        tokio::time::sleep(MEASUREMENT_DELAY + jitter).await;
+
+        let latency = measure_relay(&relay).await;
+        log::trace!("Latency of {}: {latency:?}", relay.hostname);
+        // Update the latencies as they are measured, so that a partial round is not lost
+        let mut latencies = RelayLatencies::default();
+        latencies.insert(relay.hostname, latency);
+        relay_selector.update_latencies(latencies);
+    }
+}
+
+async fn measure_relay(relay: &Relay) -> Duration {
+    let address = SocketAddr::new(IpAddr::V4(relay.ipv4_addr_in), MEASUREMENT_PORT);
+    let start = Instant::now();
+    match timeout(MEASUREMENT_TIMEOUT, TcpStream::connect(address)).await {
+        // A refused connection is also a response from the relay
+        Ok(Ok(_)) => start.elapsed(),
+        Ok(Err(error)) if error.kind() == io::ErrorKind::ConnectionRefused => start.elapsed(),
+        Ok(Err(_)) | Err(_) => MEASUREMENT_TIMEOUT,
+    }
+}
@@ -0,0 +1,178 @@
+//! Transfer of the relay list between machines, for seeding hosts that cannot reach the API.
+
+use chrono::{DateTime, Utc};
+use mullvad_types::relay_list::RelayList;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::time::SystemTime;
+
+/// Version of the export format. Exports with a newer version are rejected.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to serialize relay list")]
+    Serialize(#[source] serde_json::Error),
+
+    #[error("Failed to parse relay list export")]
+    Parse(#[source] serde_json::Error),
+
+    #[error("Unsupported relay list export format version: {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Relay list checksum does not match its contents")]
+    ChecksumMismatch,
+
+    #[error("Relay list export does not contain any relays")]
+    Empty,
+
+    #[error("Relay list export is older than the current relay list")]
+    Outdated,
+}
+
+/// Converts an [Error] to a management interface status
+impl From<Error> for mullvad_management_interface::Status {
+    fn from(error: Error) -> mullvad_management_interface::Status {
+        use mullvad_management_interface::Status;
+
+        match error {
+            Error::Serialize(_) => Status::internal(error.to_string()),
+            Error::Parse(_)
+            | Error::UnsupportedVersion(_)
+            | Error::ChecksumMismatch
+            | Error::Empty => Status::invalid_argument(error.to_string()),
+            Error::Outdated => Status::failed_precondition(error.to_string()),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RelayListExport {
+    format_version: u32,
+    /// Version of the daemon that created the export.
+    daemon_version: String,
+    /// When the exporting daemon last updated its relay list.
+    updated_at: DateTime<Utc>,
+    exported_at: DateTime<Utc>,
+    /// Hex-encoded SHA-256 digest of the JSON-serialized `relay_list`.
+    checksum: String,
+    relay_list: RelayList,
+}
+
+/// Serialize `relay_list` along with metadata describing where it came from.
+pub fn export(relay_list: RelayList, updated_at: SystemTime) -> Result<String, Error> {
+    let export = RelayListExport {
+        format_version: FORMAT_VERSION,
+        daemon_version: mullvad_version::VERSION.to_owned(),
+        updated_at: DateTime::from(updated_at),
+        exported_at: Utc::now(),
+        checksum: checksum(&relay_list)?,
+        relay_list,
+    };
+    serde_json::to_string_pretty(&export).map_err(Error::Serialize)
+}
+
+/// Parse and validate an export created by [export]. Exports that were updated before
+/// `current_updated_at` are rejected, so that importing cannot replace a newer relay list.
+pub fn import(blob: &str, current_updated_at: SystemTime) -> Result<RelayList, Error> {
+    let export: RelayListExport = serde_json::from_str(blob).map_err(Error::Parse)?;
+
+    if export.format_version > FORMAT_VERSION {
+        return Err(Error::UnsupportedVersion(export.format_version));
+    }
+    if checksum(&export.relay_list)? != export.checksum {
+        return Err(Error::ChecksumMismatch);
+    }
+    if export.relay_list.relays().next().is_none() {
+        return Err(Error::Empty);
+    }
+    if export.updated_at < DateTime::<Utc>::from(current_updated_at) {
+        return Err(Error::Outdated);
+    }
+
+    log::info!(
+        "Importing relay list exported by daemon version {}, last updated at {}",
+        export.daemon_version,
+        export.updated_at,
+    );
+
+    Ok(export.relay_list)
+}
+
+fn checksum(relay_list: &RelayList) -> Result<String, Error> {
+    let serialized = serde_json::to_vec(relay_list).map_err(Error::Serialize)?;
+    Ok(format!("{:x}", Sha256::digest(serialized)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::{
+        location::Location,
+        relay_list::{Relay, RelayEndpointData, RelayListCity, RelayListCountry},
+    };
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn relay_list() -> RelayList {
+        RelayList {
+            countries: vec![RelayListCountry {
+                name: "Sweden".to_owned(),
+                code: "se".to_owned(),
+                cities: vec![RelayListCity {
+                    name: "Gothenburg".to_owned(),
+                    code: "got".to_owned(),
+                    latitude: 57.70887,
+                    longitude: 11.97456,
+                    relays: vec![Relay {
+                        hostname: "se-got-wg-001".to_owned(),
+                        ipv4_addr_in: "185.213.154.68".parse().unwrap(),
+                        ipv6_addr_in: None,
+                        overridden_ipv4: false,
+                        overridden_ipv6: false,
+                        include_in_country: true,
+                        active: true,
+                        owned: true,
+                        provider: "31173".to_owned(),
+                        asn: None,
+                        weight: 1,
+                        endpoint_data: RelayEndpointData::Openvpn,
+                        location: Location {
+                            country: "Sweden".to_string(),
+                            country_code: "se".to_string(),
+                            city: "Gothenburg".to_string(),
+                            city_code: "got".to_string(),
+                            latitude: 57.70887,
+                            longitude: 11.97456,
+                        },
+                    }],
+                }],
+            }],
+            ..RelayList::empty()
+        }
+    }
+
+    #[test]
+    fn test_export_import() {
+        let updated_at = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let blob = export(relay_list(), updated_at).unwrap();
+
+        let imported = import(&blob, updated_at).unwrap();
+        assert_eq!(imported.relays().next().unwrap().hostname, "se-got-wg-001");
+
+        assert!(matches!(
+            import(&blob, updated_at + Duration::from_secs(1)),
+            Err(Error::Outdated)
+        ));
+    }
+
+    #[test]
+    fn test_import_tampered() {
+        let blob = export(relay_list(), UNIX_EPOCH).unwrap();
+        let tampered = blob.replace("185.213.154.68", "10.0.0.1");
+
+        assert!(matches!(
+            import(&tampered, UNIX_EPOCH),
+            Err(Error::ChecksumMismatch)
+        ));
+    }
+}
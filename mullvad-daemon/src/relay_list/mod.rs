@@ -1,33 +1,40 @@
 //! Relay list updater
 
 use futures::{
-    channel::mpsc,
+    channel::{mpsc, oneshot},
     future::{Fuse, FusedFuture},
     Future, FutureExt, SinkExt, StreamExt,
 };
+use rand::Rng;
 use std::{
     path::{Path, PathBuf},
+    sync::Arc,
     time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use tokio::fs::File;
 
-use mullvad_api::{availability::ApiAvailability, rest::MullvadRestHandle, RelayListProxy};
+use mullvad_api::{
+    availability::ApiAvailability, rest::MullvadRestHandle, retry::RetryPolicy, RelayListProxy,
+};
 use mullvad_relay_selector::RelaySelector;
 use mullvad_types::relay_list::RelayList;
-use talpid_future::retry::{retry_future, ExponentialBackoff, Jittered};
 use talpid_types::ErrorExt;
 
-/// How often the updater should wake up to check the cache of the in-memory cache of relays.
-/// This check is very cheap. The only reason to not have it very often is because if downloading
-/// constantly fails it will try very often and fill the logs etc.
+pub mod export;
+pub mod overrides;
+
+use overrides::{RelayListOverrides, ValidationError, RELAY_OVERRIDES_FILENAME};
+
+/// The longest time the updater sleeps before checking the age of the in-memory cache of relays
+/// again. This check is very cheap, but it is needed since the system clock may jump, e.g. when
+/// the machine is suspended.
 const UPDATE_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 15);
-/// How old the cached relays need to be to trigger an update
-const UPDATE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// The largest random delay added to the update interval, as a fraction of the interval. This
+/// spreads out the requests made by clients that were started at the same time.
+const UPDATE_JITTER_FRACTION: f64 = 0.1;
 
-const DOWNLOAD_RETRY_STRATEGY: Jittered<ExponentialBackoff> = Jittered::jitter(
-    ExponentialBackoff::new(Duration::from_secs(16), 8)
-        .max_delay(Some(Duration::from_secs(2 * 60 * 60))),
-);
+/// Timeout and retry policy used when downloading the relay list
+const DOWNLOAD_RETRY_POLICY: RetryPolicy = RetryPolicy::RELAY_LIST;
 
 /// Where the relay list is cached on disk.
 pub(crate) const RELAYS_FILENAME: &str = "relays.json";
@@ -39,18 +46,89 @@ pub enum Error {
 
     #[error("Mullvad relay selector error")]
     RelaySelector(#[from] mullvad_relay_selector::Error),
+
+    #[error("Failed to load relay list overrides")]
+    Overrides(#[from] overrides::Error),
+
+    #[error("Failed to download relay list")]
+    Download(#[source] Arc<mullvad_api::Error>),
+}
+
+/// Converts an [Error] to a management interface status
+impl From<Error> for mullvad_management_interface::Status {
+    fn from(error: Error) -> mullvad_management_interface::Status {
+        use mullvad_management_interface::Status;
+
+        match error {
+            Error::Overrides(overrides::Error::Parse(_)) => {
+                Status::invalid_argument(error.display_chain())
+            }
+            Error::Download(_) => Status::unavailable(error.display_chain()),
+            Error::DownloaderShutdown | Error::RelaySelector(_) | Error::Overrides(_) => {
+                Status::internal(error.display_chain())
+            }
+        }
+    }
+}
+
+enum UpdaterCommand {
+    /// Download a new relay list from the API. The sender, if any, is notified once the download
+    /// has completed or failed.
+    Update(Option<oneshot::Sender<Result<(), Error>>>),
+    /// Change how often the relay list is updated.
+    SetUpdateInterval(Duration),
+    /// Replace the relay list with one obtained out of band.
+    Import(RelayList),
+    /// Read the relay list overrides again and apply them.
+    ReloadOverrides(oneshot::Sender<Result<Vec<ValidationError>, overrides::Error>>),
 }
 
 #[derive(Clone)]
 pub struct RelayListUpdaterHandle {
-    tx: mpsc::Sender<()>,
+    tx: mpsc::Sender<UpdaterCommand>,
 }
 
 impl RelayListUpdaterHandle {
     pub async fn update(&mut self) {
+        self.send_command(UpdaterCommand::Update(None)).await
+    }
+
+    /// Download a new relay list and wait until it has been applied, or the download failed.
+    pub async fn update_and_wait(&mut self) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(UpdaterCommand::Update(Some(tx)))
+            .await
+            .map_err(|_| Error::DownloaderShutdown)?;
+        rx.await.map_err(|_| Error::DownloaderShutdown)?
+    }
+
+    /// Set how long to wait between relay list updates.
+    pub async fn set_update_interval(&mut self, interval: Duration) {
+        self.send_command(UpdaterCommand::SetUpdateInterval(interval))
+            .await
+    }
+
+    /// Use `relay_list` as the current relay list and cache it, as if it had been downloaded.
+    pub async fn import(&mut self, relay_list: RelayList) {
+        self.send_command(UpdaterCommand::Import(relay_list)).await
+    }
+
+    /// Read the relay list overrides again and apply them. Returns the overrides that could not
+    /// be applied.
+    pub async fn reload_overrides(&mut self) -> Result<Vec<ValidationError>, Error> {
+        let (tx, rx) = oneshot::channel();
+        self.tx
+            .send(UpdaterCommand::ReloadOverrides(tx))
+            .await
+            .map_err(|_| Error::DownloaderShutdown)?;
+        Ok(rx.await.map_err(|_| Error::DownloaderShutdown)??)
+    }
+
+    async fn send_command(&mut self, command: UpdaterCommand) {
         if let Err(error) = self
             .tx
-            .send(())
+            .send(command)
             .await
             .map_err(|_| Error::DownloaderShutdown)
         {
@@ -65,28 +143,46 @@ impl RelayListUpdaterHandle {
 pub struct RelayListUpdater {
     api_client: RelayListProxy,
     cache_path: PathBuf,
+    overrides_path: PathBuf,
+    overrides: RelayListOverrides,
+    /// The current relay list, without the overrides applied.
+    relay_list: RelayList,
     relay_selector: RelaySelector,
     on_update: Box<dyn Fn(&RelayList) + Send + 'static>,
     last_check: SystemTime,
+    /// How long to wait between updates, not counting jitter.
+    update_interval: Duration,
+    /// Random delay added to `update_interval` for the next update.
+    update_jitter: Duration,
+    /// Notified once the current download has completed or failed.
+    update_waiters: Vec<oneshot::Sender<Result<(), Error>>>,
     api_availability: ApiAvailability,
 }
 
 impl RelayListUpdater {
     pub fn spawn(
-        selector: RelaySelector,
+        mut selector: RelaySelector,
         api_handle: MullvadRestHandle,
         cache_dir: &Path,
+        settings_dir: &Path,
+        update_interval: Duration,
         on_update: impl Fn(&RelayList) + Send + 'static,
     ) -> RelayListUpdaterHandle {
         let (tx, cmd_rx) = mpsc::channel(1);
         let api_availability = api_handle.availability.clone();
-        let api_client = RelayListProxy::new(api_handle);
+        let api_client = RelayListProxy::with_retry_policy(api_handle, &DOWNLOAD_RETRY_POLICY);
         let updater = RelayListUpdater {
             api_client,
             cache_path: cache_dir.join(RELAYS_FILENAME),
+            overrides_path: settings_dir.join(RELAY_OVERRIDES_FILENAME),
+            overrides: RelayListOverrides::default(),
+            relay_list: selector.get_relays(),
             relay_selector: selector,
             on_update: Box::new(on_update),
             last_check: UNIX_EPOCH,
+            update_interval,
+            update_jitter: random_jitter(update_interval),
+            update_waiters: vec![],
             api_availability,
         };
 
@@ -95,31 +191,56 @@ impl RelayListUpdater {
         RelayListUpdaterHandle { tx }
     }
 
-    async fn run(mut self, mut cmd_rx: mpsc::Receiver<()>) {
+    async fn run(mut self, mut cmd_rx: mpsc::Receiver<UpdaterCommand>) {
+        if let Err(error) = self.reload_overrides().await {
+            log::error!("{}", error.display_chain());
+        }
+
         let mut download_future = Box::pin(Fuse::terminated());
         loop {
-            let next_check = tokio::time::sleep(UPDATE_CHECK_INTERVAL).fuse();
+            let next_check_delay = if download_future.is_terminated() {
+                self.time_until_update().min(UPDATE_CHECK_INTERVAL)
+            } else {
+                UPDATE_CHECK_INTERVAL
+            };
+            let next_check = tokio::time::sleep(next_check_delay).fuse();
             tokio::pin!(next_check);
 
             futures::select! {
                 _check_update = next_check => {
                     if download_future.is_terminated() && self.should_update() {
-                        let tag = self.relay_selector.etag();
-                        download_future = Box::pin(Self::download_relay_list(self.api_availability.clone(), self.api_client.clone(), tag).fuse());
-                        self.last_check = SystemTime::now();
+                        download_future = Box::pin(self.start_download().fuse());
                     }
                 },
 
                 new_relay_list = download_future => {
-                    self.consume_new_relay_list(new_relay_list).await;
+                    let result = self.consume_new_relay_list(new_relay_list).await;
+                    for tx in self.update_waiters.drain(..) {
+                        let _ = tx.send(result.clone().map_err(Error::Download));
+                    }
                 },
 
                 cmd = cmd_rx.next() => {
                     match cmd {
-                        Some(()) => {
-                            let tag = self.relay_selector.etag();
-                            download_future = Box::pin(Self::download_relay_list(self.api_availability.clone(), self.api_client.clone(), tag).fuse());
-                            self.last_check = SystemTime::now();
+                        Some(UpdaterCommand::Update(tx)) => {
+                            self.update_waiters.extend(tx);
+                            download_future = Box::pin(self.start_download().fuse());
+                        },
+                        Some(UpdaterCommand::SetUpdateInterval(interval)) => {
+                            self.update_interval = interval;
+                            self.update_jitter = random_jitter(interval);
+                        },
+                        Some(UpdaterCommand::Import(relay_list)) => {
+                            if let Err(err) = self.update_cache(relay_list).await {
+                                log::error!("Failed to import relay list: {}", err);
+                            }
+                        },
+                        Some(UpdaterCommand::ReloadOverrides(tx)) => {
+                            let result = self.reload_overrides().await;
+                            if let Err(error) = &result {
+                                log::error!("{}", error.display_chain());
+                            }
+                            let _ = tx.send(result);
                         },
                         None => {
                             log::trace!("Relay list updater shutting down");
@@ -132,33 +253,59 @@ impl RelayListUpdater {
         }
     }
 
+    /// Start downloading a new relay list, and pick a new jitter for the update after that.
+    fn start_download(
+        &mut self,
+    ) -> impl Future<Output = Result<Option<RelayList>, mullvad_api::Error>> + use<> {
+        self.last_check = SystemTime::now();
+        self.update_jitter = random_jitter(self.update_interval);
+        Self::download_relay_list(
+            self.api_availability.clone(),
+            self.api_client.clone(),
+            self.relay_selector.etag(),
+            self.relay_selector.last_modified(),
+        )
+    }
+
     async fn consume_new_relay_list(
         &mut self,
         result: Result<Option<RelayList>, mullvad_api::Error>,
-    ) {
+    ) -> Result<(), Arc<mullvad_api::Error>> {
         match result {
             Ok(Some(relay_list)) => {
                 if let Err(err) = self.update_cache(relay_list).await {
                     log::error!("Failed to update relay list cache: {}", err);
                 }
+                Ok(())
+            }
+            Ok(None) => {
+                log::debug!("Relay list is up-to-date");
+                Ok(())
+            }
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to fetch new relay list")
+                );
+                Err(Arc::new(error))
             }
-            Ok(None) => log::debug!("Relay list is up-to-date"),
-            Err(error) => log::error!(
-                "{}",
-                error.display_chain_with_msg("Failed to fetch new relay list")
-            ),
         }
     }
 
-    /// Returns true if the current parsed_relays is older than UPDATE_INTERVAL
+    /// Returns true if the current parsed_relays is older than the update interval, plus jitter
     fn should_update(&mut self) -> bool {
+        self.time_until_update().is_zero()
+    }
+
+    /// Returns how long it is until the relay list should be updated
+    fn time_until_update(&self) -> Duration {
         let last_check = std::cmp::max(self.relay_selector.last_updated(), self.last_check);
         match SystemTime::now().duration_since(last_check) {
-            Ok(duration) => duration >= UPDATE_INTERVAL,
+            Ok(duration) => (self.update_interval + self.update_jitter).saturating_sub(duration),
             // If the clock is skewed we have no idea by how much or when the last update
             // actually was, better download again to get in sync and get a `last_updated`
             // timestamp corresponding to the new time.
-            Err(_) => true,
+            Err(_) => Duration::ZERO,
         }
     }
 
@@ -166,21 +313,18 @@ impl RelayListUpdater {
         api_handle: ApiAvailability,
         proxy: RelayListProxy,
         tag: Option<String>,
+        last_modified: Option<String>,
     ) -> impl Future<Output = Result<Option<RelayList>, mullvad_api::Error>> + use<> {
         let download_futures = move || {
             let available = api_handle.wait_background();
-            let req = proxy.relay_list(tag.clone());
+            let req = proxy.relay_list(tag.clone(), last_modified.clone());
             async move {
                 available.await?;
                 req.await.map_err(mullvad_api::Error::from)
             }
         };
 
-        retry_future(
-            download_futures,
-            |result| result.is_err(),
-            DOWNLOAD_RETRY_STRATEGY,
-        )
+        DOWNLOAD_RETRY_POLICY.retry(download_futures, |result| result.is_err())
     }
 
     async fn update_cache(&mut self, new_relay_list: RelayList) -> Result<(), Error> {
@@ -191,11 +335,38 @@ impl RelayListUpdater {
             );
         }
 
-        self.relay_selector.set_relays(new_relay_list.clone());
-        (self.on_update)(&new_relay_list);
+        self.relay_list = new_relay_list;
+        self.set_relays();
         Ok(())
     }
 
+    /// Read the overrides from disk and apply them to the current relay list. If they cannot be
+    /// read, the previous overrides are kept.
+    async fn reload_overrides(&mut self) -> Result<Vec<ValidationError>, overrides::Error> {
+        let overrides = RelayListOverrides::load(&self.overrides_path).await?;
+        if overrides.is_empty() && self.overrides.is_empty() {
+            return Ok(vec![]);
+        }
+        log::info!(
+            "Applying relay list overrides from {}",
+            self.overrides_path.display()
+        );
+        self.overrides = overrides;
+        Ok(self.set_relays())
+    }
+
+    /// Use the current relay list, with the overrides applied. Returns the overrides that could
+    /// not be applied.
+    fn set_relays(&mut self) -> Vec<ValidationError> {
+        let (relay_list, errors) = self.overrides.apply(self.relay_list.clone());
+        for error in &errors {
+            log::warn!("Skipping relay list override: {error}");
+        }
+        self.relay_selector.set_relays(relay_list.clone());
+        (self.on_update)(&relay_list);
+        errors
+    }
+
     /// Write a `RelayList` to the cache file.
     async fn cache_relays(cache_path: &Path, relays: &RelayList) -> Result<(), Error> {
         log::debug!("Writing relays cache to {}", cache_path.display());
@@ -211,3 +382,8 @@ impl RelayListUpdater {
         Ok(())
     }
 }
+
+/// Returns a random delay of up to [`UPDATE_JITTER_FRACTION`] of `interval`.
+fn random_jitter(interval: Duration) -> Duration {
+    interval.mul_f64(rand::thread_rng().gen_range(0.0..UPDATE_JITTER_FRACTION))
+}
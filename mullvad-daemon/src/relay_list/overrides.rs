@@ -0,0 +1,344 @@
+//! Local overrides that are merged over the relay list, to add custom relays, change the addresses
+//! of relays (e.g. for relays that are unreachable due to NAT reflection issues) or the ASN of
+//! their network, or hide relays.
+//!
+//! The overrides are read from a JSON file in the settings directory:
+//!
+//! ```json
+//! {
+//!     "add": [{
+//!         "hostname": "custom-wg-001",
+//!         "ipv4_addr_in": "192.0.2.1",
+//!         "public_key": "BLNHNoGO88LjV/wDBa7CUUwUzPq/fO2UwcGLy56hKy4=",
+//!         "location": {
+//!             "country": "Sweden",
+//!             "country_code": "se",
+//!             "city": "Gothenburg",
+//!             "city_code": "got",
+//!             "latitude": 57.70887,
+//!             "longitude": 11.97456
+//!         }
+//!     }],
+//!     "modify": [{ "hostname": "se-got-wg-001", "ipv4_addr_in": "10.0.0.1", "asn": 64496 }],
+//!     "hide": ["se-got-wg-002"]
+//! }
+//! ```
+
+use mullvad_types::{
+    location::Location,
+    relay_list::{
+        Relay, RelayEndpointData, RelayList, RelayListCity, RelayListCountry,
+        WireguardRelayEndpointData,
+    },
+};
+use serde::Deserialize;
+use std::{
+    collections::HashSet,
+    io,
+    net::{Ipv4Addr, Ipv6Addr},
+    path::Path,
+};
+use talpid_types::net::wireguard;
+
+/// Name of the file in the settings directory that contains the overrides.
+pub(crate) const RELAY_OVERRIDES_FILENAME: &str = "relay-overrides.json";
+
+/// Provider reported for relays added by the overrides.
+const CUSTOM_RELAY_PROVIDER: &str = "custom";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to read relay list overrides")]
+    Read(#[source] io::Error),
+
+    #[error("Failed to parse relay list overrides")]
+    Parse(#[source] serde_json::Error),
+}
+
+/// Problem with an individual override. It is skipped, but does not prevent the other overrides
+/// from being applied.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    #[error("There is no relay with hostname \"{0}\"")]
+    UnknownRelay(String),
+
+    #[error("Relay \"{0}\" is overridden more than once")]
+    Duplicate(String),
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RelayListOverrides {
+    /// WireGuard relays to add. Relays with the same hostname are replaced.
+    add: Vec<CustomRelay>,
+    /// Relays whose addresses should be changed.
+    modify: Vec<RelayModification>,
+    /// Hostnames of relays to remove from the list.
+    hide: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CustomRelay {
+    hostname: String,
+    ipv4_addr_in: Ipv4Addr,
+    #[serde(default)]
+    ipv6_addr_in: Option<Ipv6Addr>,
+    public_key: wireguard::PublicKey,
+    location: Location,
+    #[serde(default)]
+    asn: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RelayModification {
+    hostname: String,
+    #[serde(default)]
+    ipv4_addr_in: Option<Ipv4Addr>,
+    #[serde(default)]
+    ipv6_addr_in: Option<Ipv6Addr>,
+    #[serde(default)]
+    asn: Option<u32>,
+}
+
+impl RelayListOverrides {
+    /// Read the overrides from `path`. A missing file is the same as no overrides.
+    pub async fn load(path: &Path) -> Result<Self, Error> {
+        match tokio::fs::read_to_string(path).await {
+            Ok(contents) => serde_json::from_str(&contents).map_err(Error::Parse),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(Error::Read(error)),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.add.is_empty() && self.modify.is_empty() && self.hide.is_empty()
+    }
+
+    /// Merge the overrides over `relay_list`. Overrides that cannot be applied are skipped and
+    /// returned as errors.
+    pub fn apply(&self, mut relay_list: RelayList) -> (RelayList, Vec<ValidationError>) {
+        let mut errors = vec![];
+        let mut overridden = HashSet::new();
+        let mut is_unique = |hostname: &str, errors: &mut Vec<_>| {
+            let unique = overridden.insert(hostname.to_owned());
+            if !unique {
+                errors.push(ValidationError::Duplicate(hostname.to_owned()));
+            }
+            unique
+        };
+
+        for hostname in &self.hide {
+            if is_unique(hostname, &mut errors) && !remove_relay(&mut relay_list, hostname) {
+                errors.push(ValidationError::UnknownRelay(hostname.to_owned()));
+            }
+        }
+
+        for modification in &self.modify {
+            if !is_unique(&modification.hostname, &mut errors) {
+                continue;
+            }
+            let relay = relay_list
+                .countries
+                .iter_mut()
+                .flat_map(|country| country.cities.iter_mut())
+                .flat_map(|city| city.relays.iter_mut())
+                .find(|relay| relay.hostname == modification.hostname);
+            let Some(relay) = relay else {
+                errors.push(ValidationError::UnknownRelay(modification.hostname.clone()));
+                continue;
+            };
+            if let Some(ipv4) = modification.ipv4_addr_in {
+                relay.override_ipv4(ipv4);
+            }
+            if let Some(ipv6) = modification.ipv6_addr_in {
+                relay.override_ipv6(ipv6);
+            }
+            if let Some(asn) = modification.asn {
+                relay.asn = Some(asn);
+            }
+        }
+
+        for custom_relay in &self.add {
+            if is_unique(&custom_relay.hostname, &mut errors) {
+                remove_relay(&mut relay_list, &custom_relay.hostname);
+                insert_relay(&mut relay_list, custom_relay.to_relay());
+            }
+        }
+
+        // Do not list locations whose relays were all hidden
+        for country in &mut relay_list.countries {
+            country.cities.retain(|city| !city.relays.is_empty());
+        }
+        relay_list
+            .countries
+            .retain(|country| !country.cities.is_empty());
+
+        (relay_list, errors)
+    }
+}
+
+impl CustomRelay {
+    fn to_relay(&self) -> Relay {
+        Relay {
+            hostname: self.hostname.clone(),
+            ipv4_addr_in: self.ipv4_addr_in,
+            ipv6_addr_in: self.ipv6_addr_in,
+            overridden_ipv4: false,
+            overridden_ipv6: false,
+            include_in_country: true,
+            active: true,
+            owned: false,
+            provider: CUSTOM_RELAY_PROVIDER.to_owned(),
+            asn: self.asn,
+            weight: 1,
+            endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
+                public_key: self.public_key.clone(),
+                daita: false,
+                shadowsocks_extra_addr_in: vec![],
+            }),
+            location: self.location.clone(),
+        }
+    }
+}
+
+/// Remove the relay with the given hostname. Returns whether it was found.
+fn remove_relay(relay_list: &mut RelayList, hostname: &str) -> bool {
+    let mut found = false;
+    for city in relay_list
+        .countries
+        .iter_mut()
+        .flat_map(|country| country.cities.iter_mut())
+    {
+        let len = city.relays.len();
+        city.relays.retain(|relay| relay.hostname != hostname);
+        found |= city.relays.len() != len;
+    }
+    found
+}
+
+/// Insert `relay` in the city given by its location. The country and city are created if needed.
+fn insert_relay(relay_list: &mut RelayList, relay: Relay) {
+    let location = &relay.location;
+
+    let country_index = match relay_list
+        .countries
+        .iter()
+        .position(|country| country.code == location.country_code)
+    {
+        Some(index) => index,
+        None => {
+            relay_list.countries.push(RelayListCountry {
+                name: location.country.clone(),
+                code: location.country_code.clone(),
+                cities: vec![],
+            });
+            relay_list.countries.len() - 1
+        }
+    };
+    let cities = &mut relay_list.countries[country_index].cities;
+
+    let city_index = match cities
+        .iter()
+        .position(|city| city.code == location.city_code)
+    {
+        Some(index) => index,
+        None => {
+            cities.push(RelayListCity {
+                name: location.city.clone(),
+                code: location.city_code.clone(),
+                latitude: location.latitude,
+                longitude: location.longitude,
+                relays: vec![],
+            });
+            cities.len() - 1
+        }
+    };
+    cities[city_index].relays.push(relay);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn relay_list() -> RelayList {
+        let overrides: RelayListOverrides = serde_json::from_value(serde_json::json!({
+            "add": [
+                relay_json("se-got-wg-001", "se", "got"),
+                relay_json("se-got-wg-002", "se", "got"),
+                relay_json("se-sto-wg-001", "se", "sto"),
+            ],
+        }))
+        .unwrap();
+        let (relay_list, errors) = overrides.apply(RelayList::empty());
+        assert!(errors.is_empty());
+        relay_list
+    }
+
+    fn relay_json(hostname: &str, country_code: &str, city_code: &str) -> serde_json::Value {
+        serde_json::json!({
+            "hostname": hostname,
+            "ipv4_addr_in": "192.0.2.1",
+            "public_key": "BLNHNoGO88LjV/wDBa7CUUwUzPq/fO2UwcGLy56hKy4=",
+            "location": {
+                "country": country_code,
+                "country_code": country_code,
+                "city": city_code,
+                "city_code": city_code,
+                "latitude": 0.0,
+                "longitude": 0.0,
+            },
+        })
+    }
+
+    fn hostnames(relay_list: &RelayList) -> Vec<&str> {
+        relay_list
+            .relays()
+            .map(|relay| relay.hostname.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_apply() {
+        let overrides: RelayListOverrides = serde_json::from_value(serde_json::json!({
+            "add": [relay_json("dk-cph-wg-001", "dk", "cph")],
+            "modify": [{ "hostname": "se-got-wg-001", "ipv4_addr_in": "10.0.0.1" }],
+            "hide": ["se-sto-wg-001"],
+        }))
+        .unwrap();
+
+        let (relay_list, errors) = overrides.apply(relay_list());
+
+        assert!(errors.is_empty());
+        assert_eq!(
+            hostnames(&relay_list),
+            ["se-got-wg-001", "se-got-wg-002", "dk-cph-wg-001"]
+        );
+        // The city without relays is not listed
+        assert_eq!(relay_list.countries[0].cities.len(), 1);
+        let relay = relay_list.relays().next().unwrap();
+        assert_eq!(relay.ipv4_addr_in, Ipv4Addr::new(10, 0, 0, 1));
+        assert!(relay.overridden_ipv4);
+    }
+
+    #[test]
+    fn test_invalid_overrides() {
+        let overrides: RelayListOverrides = serde_json::from_value(serde_json::json!({
+            "modify": [{ "hostname": "se-got-wg-001", "ipv4_addr_in": "10.0.0.1" }],
+            "hide": ["se-got-wg-001", "se-mma-wg-001"],
+        }))
+        .unwrap();
+
+        let (relay_list, errors) = overrides.apply(relay_list());
+
+        assert_eq!(
+            errors,
+            [
+                ValidationError::UnknownRelay("se-mma-wg-001".to_owned()),
+                ValidationError::Duplicate("se-got-wg-001".to_owned()),
+            ]
+        );
+        assert_eq!(hostnames(&relay_list), ["se-got-wg-002", "se-sto-wg-001"]);
+    }
+}
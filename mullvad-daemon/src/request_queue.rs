@@ -0,0 +1,228 @@
+//! Persisted queue of API requests which are not safe to simply retry, such as voucher
+//! submissions and device removals. Queued requests are replayed once the API is reachable, and
+//! are tagged with idempotency keys so that they are only applied once.
+
+use mullvad_api::{
+    availability::ApiAvailability, rest, retry::RetryPolicy, AccountsProxy, DevicesProxy,
+};
+use mullvad_types::pending_request::{PendingRequest, PendingRequestId, PendingRequestKind};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use talpid_types::ErrorExt;
+use tokio::{
+    fs,
+    io::{self, AsyncWriteExt},
+    sync::{Mutex, Notify},
+};
+
+const PENDING_REQUESTS_FILE: &str = "pending-requests.json";
+
+/// Policy used when replaying queued requests.
+const REPLAY_RETRY_POLICY: RetryPolicy = RetryPolicy::BACKGROUND;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to serialize pending requests")]
+    Serialize(#[source] serde_json::Error),
+
+    #[error("Unable to write pending requests file")]
+    Write(#[source] io::Error),
+
+    #[error("There is no pending request with the given ID")]
+    NotFound,
+}
+
+/// Handle to the request queue. Queued requests are replayed by a background task.
+#[derive(Clone)]
+pub struct RequestQueue {
+    state: Arc<Mutex<QueueState>>,
+    new_request: Arc<Notify>,
+}
+
+struct QueueState {
+    path: PathBuf,
+    requests: Vec<PendingRequest>,
+}
+
+impl RequestQueue {
+    /// Load the queued requests from `settings_dir`, and start replaying them once the API is
+    /// reachable.
+    pub async fn spawn(
+        settings_dir: &Path,
+        api_handle: rest::MullvadRestHandle,
+        availability: ApiAvailability,
+    ) -> Self {
+        let path = settings_dir.join(PENDING_REQUESTS_FILE);
+        let requests = match fs::read_to_string(&path).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to parse pending requests")
+                );
+                vec![]
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => vec![],
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to read pending requests")
+                );
+                vec![]
+            }
+        };
+
+        let queue = RequestQueue {
+            state: Arc::new(Mutex::new(QueueState { path, requests })),
+            new_request: Arc::new(Notify::new()),
+        };
+
+        let replayer = Replayer {
+            queue: queue.clone(),
+            accounts: AccountsProxy::new(api_handle.clone()),
+            devices: DevicesProxy::new(api_handle),
+            availability,
+        };
+        tokio::spawn(replayer.run());
+
+        queue
+    }
+
+    /// Add a request to the queue. It is sent as soon as the API is reachable.
+    pub async fn enqueue(&self, kind: PendingRequestKind) -> Result<PendingRequest, Error> {
+        let request = PendingRequest::new(kind);
+        {
+            let mut state = self.state.lock().await;
+            state.requests.push(request.clone());
+            state.save().await?;
+        }
+        self.new_request.notify_one();
+        Ok(request)
+    }
+
+    /// Returns all requests that have not been sent yet.
+    pub async fn pending(&self) -> Vec<PendingRequest> {
+        self.state.lock().await.requests.clone()
+    }
+
+    /// Remove a request from the queue without sending it.
+    pub async fn cancel(&self, id: &PendingRequestId) -> Result<(), Error> {
+        let mut state = self.state.lock().await;
+        if !state.requests.iter().any(|request| &request.id == id) {
+            return Err(Error::NotFound);
+        }
+        state.requests.retain(|request| &request.id != id);
+        state.save().await
+    }
+
+    async fn remove(&self, id: &PendingRequestId) {
+        let mut state = self.state.lock().await;
+        state.requests.retain(|request| &request.id != id);
+        if let Err(error) = state.save().await {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to save pending requests")
+            );
+        }
+    }
+}
+
+impl QueueState {
+    async fn save(&self) -> Result<(), Error> {
+        let data = serde_json::to_vec_pretty(&self.requests).map_err(Error::Serialize)?;
+        let mut file = mullvad_fs::AtomicFile::new(&self.path)
+            .await
+            .map_err(Error::Write)?;
+        file.write_all(&data).await.map_err(Error::Write)?;
+        file.finalize().await.map_err(Error::Write)
+    }
+}
+
+struct Replayer {
+    queue: RequestQueue,
+    accounts: AccountsProxy,
+    devices: DevicesProxy,
+    availability: ApiAvailability,
+}
+
+impl Replayer {
+    async fn run(self) {
+        let mut delays = REPLAY_RETRY_POLICY.delays();
+        loop {
+            let requests = self.queue.pending().await;
+            if requests.is_empty() {
+                self.queue.new_request.notified().await;
+                continue;
+            }
+            if self.availability.wait_online().await.is_err() {
+                return;
+            }
+
+            let mut should_retry = false;
+            for request in requests {
+                match self.send(&request).await {
+                    Ok(()) => {
+                        log::info!("Sent queued request: {}", request.kind);
+                        self.queue.remove(&request.id).await;
+                    }
+                    Err(error) if error.is_network_error() => {
+                        log::debug!(
+                            "{}",
+                            error.display_chain_with_msg("Failed to send queued request")
+                        );
+                        should_retry = true;
+                    }
+                    Err(error) => {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg(&format!(
+                                "Dropping queued request: {}",
+                                request.kind
+                            ))
+                        );
+                        self.queue.remove(&request.id).await;
+                    }
+                }
+            }
+
+            if should_retry {
+                let delay = delays.next().unwrap_or_default();
+                talpid_time::sleep(delay).await;
+            } else {
+                delays = REPLAY_RETRY_POLICY.delays();
+            }
+        }
+    }
+
+    async fn send(&self, request: &PendingRequest) -> Result<(), rest::Error> {
+        match &request.kind {
+            PendingRequestKind::SubmitVoucher {
+                account_number,
+                voucher,
+            } => {
+                self.accounts
+                    .submit_voucher_idempotent(
+                        account_number.clone(),
+                        voucher.clone(),
+                        request.id.clone(),
+                    )
+                    .await?;
+                self.availability.resume_background();
+            }
+            PendingRequestKind::RemoveDevice {
+                account_number,
+                device_id,
+            } => {
+                self.devices
+                    .remove_idempotent(
+                        account_number.clone(),
+                        device_id.clone(),
+                        request.id.clone(),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+//! Transfer of the complete settings between machines, or from a file managed by the user.
+//!
+//! Unlike [patches](super::patch), an export contains every setting. Settings that contain secrets,
+//! such as proxy passwords and private keys, are left out unless explicitly requested. Settings
+//! that only make sense on the machine they were created on are never exported. Settings that are
+//! missing from an export keep their current value when it is imported.
+
+use crate::migrations;
+use chrono::{DateTime, Utc};
+use mullvad_types::settings::{Settings, SettingsVersion, CURRENT_SETTINGS_VERSION};
+use serde::{Deserialize, Serialize};
+
+/// Version of the export format. Exports with a newer version are rejected.
+const FORMAT_VERSION: u32 = 1;
+
+/// Keys whose values are considered secret, wherever they appear in the settings.
+const SECRET_KEYS: &[&str] = &["password", "private_key"];
+
+/// Top-level settings that are specific to the machine, and are therefore neither exported nor
+/// imported. State hooks are also left out since they must be validated when set.
+const MACHINE_SPECIFIC_KEYS: &[&str] = &[
+    "split_tunnel",
+    "state_hooks",
+    "tunnel_fwmark",
+    "nftables",
+    "routing",
+    "wfp_sublayer_weight",
+    "pf_anchor",
+    "dns_backends",
+    "offline_monitor",
+];
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to serialize settings")]
+    Serialize(#[source] serde_json::Error),
+
+    #[error("Failed to parse settings export")]
+    Parse(#[source] serde_json::Error),
+
+    #[error("Unsupported settings export format version: {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Settings export has a missing or unsupported settings version")]
+    UnsupportedSettingsVersion,
+
+    #[error("Failed to migrate exported settings to the current format")]
+    Migrate(#[source] migrations::Error),
+
+    #[error("Exported settings are invalid")]
+    InvalidSettings(#[source] serde_json::Error),
+
+    #[error("Settings error")]
+    Settings(#[source] super::Error),
+}
+
+/// Converts an [Error] to a management interface status
+impl From<Error> for mullvad_management_interface::Status {
+    fn from(error: Error) -> mullvad_management_interface::Status {
+        use mullvad_management_interface::Status;
+
+        match error {
+            Error::Serialize(_) => Status::internal(error.to_string()),
+            Error::Parse(_)
+            | Error::UnsupportedVersion(_)
+            | Error::UnsupportedSettingsVersion
+            | Error::Migrate(_)
+            | Error::InvalidSettings(_) => Status::invalid_argument(error.to_string()),
+            Error::Settings(error) => Status::from(error),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct SettingsExport {
+    format_version: u32,
+    /// Version of the daemon that created the export.
+    daemon_version: String,
+    exported_at: DateTime<Utc>,
+    /// Whether settings containing secrets were included.
+    includes_secrets: bool,
+    /// The exported settings, in the same format as the settings file.
+    settings: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Serialize `settings` along with metadata describing where they came from. Settings that
+/// contain secrets are only included if `include_secrets` is set.
+pub fn export(settings: &Settings, include_secrets: bool) -> Result<String, Error> {
+    let serde_json::Value::Object(mut exported) =
+        serde_json::to_value(settings).map_err(Error::Serialize)?
+    else {
+        unreachable!("settings are always serialized as an object");
+    };
+    exported.retain(|key, value| {
+        if MACHINE_SPECIFIC_KEYS.contains(&key.as_str()) {
+            return false;
+        }
+        if !include_secrets && contains_secret(value) {
+            log::info!("Not exporting \"{key}\" since it contains secrets");
+            return false;
+        }
+        true
+    });
+
+    let export = SettingsExport {
+        format_version: FORMAT_VERSION,
+        daemon_version: mullvad_version::VERSION.to_owned(),
+        exported_at: Utc::now(),
+        includes_secrets: include_secrets,
+        settings: exported,
+    };
+    serde_json::to_string_pretty(&export).map_err(Error::Serialize)
+}
+
+/// Parse and validate an export created by [export], and return `current` with the exported
+/// settings applied. Settings from older versions of the daemon are migrated first.
+pub async fn import(blob: &str, current: &Settings) -> Result<Settings, Error> {
+    let export: SettingsExport = serde_json::from_str(blob).map_err(Error::Parse)?;
+
+    if export.format_version > FORMAT_VERSION {
+        return Err(Error::UnsupportedVersion(export.format_version));
+    }
+    let settings_version: SettingsVersion = export
+        .settings
+        .get("settings_version")
+        .and_then(|version| serde_json::from_value(version.clone()).ok())
+        .ok_or(Error::UnsupportedSettingsVersion)?;
+
+    log::info!(
+        "Importing settings exported by daemon version {} at {}",
+        export.daemon_version,
+        export.exported_at,
+    );
+
+    let mut imported = serde_json::Value::Object(export.settings);
+    if settings_version < CURRENT_SETTINGS_VERSION {
+        migrations::migrate_settings_value(&mut imported)
+            .await
+            .map_err(Error::Migrate)?;
+    }
+    let serde_json::Value::Object(imported) = imported else {
+        return Err(Error::Migrate(migrations::Error::InvalidSettingsContent));
+    };
+
+    let serde_json::Value::Object(mut merged) =
+        serde_json::to_value(current).map_err(Error::Serialize)?
+    else {
+        unreachable!("settings are always serialized as an object");
+    };
+    for (key, value) in imported {
+        if !MACHINE_SPECIFIC_KEYS.contains(&key.as_str()) {
+            merged.insert(key, value);
+        }
+    }
+
+    serde_json::from_value(serde_json::Value::Object(merged)).map_err(Error::InvalidSettings)
+}
+
+/// Returns whether `value` contains a non-null value for any of the [SECRET_KEYS].
+fn contains_secret(value: &serde_json::Value) -> bool {
+    match value {
+        serde_json::Value::Object(object) => object.iter().any(|(key, value)| {
+            (SECRET_KEYS.contains(&key.as_str()) && !value.is_null()) || contains_secret(value)
+        }),
+        serde_json::Value::Array(array) => array.iter().any(contains_secret),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::{
+        access_method::{AccessMethod, AccessMethodSetting},
+        lan_sharing::AllowedLan,
+    };
+    use std::net::SocketAddr;
+    use talpid_types::net::proxy::{Socks5Remote, SocksAuth};
+
+    fn settings_with_secret() -> Settings {
+        let mut settings = Settings::default();
+        let auth = SocksAuth::new("user".to_owned(), "hunter2".to_owned()).unwrap();
+        let endpoint: SocketAddr = "10.0.0.1:1080".parse().unwrap();
+        let proxy = Socks5Remote::new_with_authentication(endpoint, auth);
+        settings.api_access_methods.append(AccessMethodSetting::new(
+            "proxy".to_owned(),
+            true,
+            AccessMethod::Custom(proxy.into()),
+        ));
+        settings
+    }
+
+    #[tokio::test]
+    async fn test_export_import() {
+        let mut settings = Settings::default();
+        settings.allow_lan = AllowedLan::All;
+        settings.sticky_relays = true;
+        let blob = export(&settings, false).unwrap();
+
+        let imported = import(&blob, &Settings::default()).await.unwrap();
+        assert_eq!(imported, settings);
+    }
+
+    #[tokio::test]
+    async fn test_secrets_are_omitted() {
+        let settings = settings_with_secret();
+
+        let blob = export(&settings, false).unwrap();
+        assert!(!blob.contains("hunter2"));
+        // Omitted settings keep their current value
+        let imported = import(&blob, &settings).await.unwrap();
+        assert_eq!(imported.api_access_methods, settings.api_access_methods);
+
+        let blob = export(&settings, true).unwrap();
+        assert!(blob.contains("hunter2"));
+        let imported = import(&blob, &Settings::default()).await.unwrap();
+        assert_eq!(imported.api_access_methods, settings.api_access_methods);
+    }
+
+    #[tokio::test]
+    async fn test_import_newer_settings_version() {
+        let blob = export(&Settings::default(), false).unwrap();
+        let blob = blob.replace(
+            &format!("\"settings_version\": {}", CURRENT_SETTINGS_VERSION as u32),
+            "\"settings_version\": 4294967295",
+        );
+
+        assert!(matches!(
+            import(&blob, &Settings::default()).await,
+            Err(Error::UnsupportedSettingsVersion)
+        ));
+    }
+}
@@ -1,6 +1,8 @@
 use futures::TryFutureExt;
 use mullvad_types::{
     custom_list::Error as CustomListError,
+    custom_relay::Error as CustomRelayError,
+    profile::Error as ProfileError,
     relay_constraints::{RelayConstraints, RelaySettings, WireguardConstraints},
     settings::{DnsState, Settings},
 };
@@ -16,6 +18,7 @@ use tokio::{
     io::{self, AsyncWriteExt},
 };
 
+pub mod export;
 pub mod patch;
 
 const SETTINGS_FILE: &str = "settings.json";
@@ -57,6 +60,22 @@ impl From<Error> for mullvad_management_interface::Status {
                 let custom_list_err = *err.downcast::<CustomListError>().unwrap();
                 handle_custom_list_error(custom_list_err)
             }
+            Error::UpdateFailed(err)
+                if err
+                    .downcast_ref::<mullvad_types::custom_relay::Error>()
+                    .is_some() =>
+            {
+                let custom_relay_err = *err.downcast::<CustomRelayError>().unwrap();
+                handle_custom_relay_error(custom_relay_err)
+            }
+            Error::UpdateFailed(err)
+                if err
+                    .downcast_ref::<mullvad_types::profile::Error>()
+                    .is_some() =>
+            {
+                let profile_err = *err.downcast::<ProfileError>().unwrap();
+                handle_profile_error(profile_err)
+            }
             Error::SerializeError(..) | Error::ParseError(..) | Error::UpdateFailed(..) => {
                 Status::new(Code::Internal, error.to_string())
             }
@@ -89,6 +108,29 @@ fn handle_custom_list_error(
     }
 }
 
+pub(crate) fn handle_custom_relay_error(
+    custom_relay_err: CustomRelayError,
+) -> mullvad_management_interface::Status {
+    use mullvad_management_interface::{Code, Status};
+    let code = match custom_relay_err {
+        CustomRelayError::InvalidName => Code::InvalidArgument,
+        CustomRelayError::DuplicateName => Code::AlreadyExists,
+        CustomRelayError::NotFound => Code::NotFound,
+    };
+    Status::new(code, custom_relay_err.to_string())
+}
+
+pub(crate) fn handle_profile_error(
+    profile_err: ProfileError,
+) -> mullvad_management_interface::Status {
+    use mullvad_management_interface::{Code, Status};
+    let code = match profile_err {
+        ProfileError::InvalidName => Code::InvalidArgument,
+        ProfileError::NotFound => Code::NotFound,
+    };
+    Status::new(code, profile_err.to_string())
+}
+
 pub struct SettingsPersister {
     settings: Settings,
     path: PathBuf,
@@ -319,7 +361,7 @@ impl SettingsPersister {
     /// let settings = Settings::default_settings();
     /// let err = settings.try_update(|settings| {
     ///   // Perform some update on the settings
-    ///   settings.allow_lan = !settings.allow_lan;
+    ///   settings.auto_connect = !settings.auto_connect;
     ///   // Fail the update procedure due to some error
     ///   Err(MyError::Failed("No particular reason".to_string()))
     /// });
@@ -386,6 +428,79 @@ impl Deref for SettingsPersister {
     }
 }
 
+/// Returns how the firewall rules should be installed alongside other nftables tables. A priority
+/// that is out of range, e.g. because the settings file was edited by hand, is ignored.
+#[cfg(target_os = "linux")]
+pub fn nftables_options(settings: &Settings) -> talpid_core::firewall::NftablesOptions {
+    let nftables = settings.nftables;
+    let priority = if nftables.is_valid() {
+        nftables.priority
+    } else {
+        log::warn!("Ignoring invalid nftables priority {}", nftables.priority);
+        0
+    };
+    talpid_core::firewall::NftablesOptions {
+        priority,
+        cooperative: nftables.cooperative,
+    }
+}
+
+/// Returns the routing table and rule priority to use for policy routing. Values that are out of
+/// range, e.g. because the settings file was edited by hand, are ignored.
+#[cfg(target_os = "linux")]
+pub fn routing_settings(settings: &Settings) -> mullvad_types::settings::RoutingSettings {
+    let mut routing = settings.routing;
+    if !routing.is_valid_table_id() {
+        log::warn!("Ignoring invalid routing table {:?}", routing.table_id);
+        routing.table_id = None;
+    }
+    if !routing.is_valid_rule_priority() {
+        log::warn!("Ignoring invalid rule priority {:?}", routing.rule_priority);
+        routing.rule_priority = None;
+    }
+    routing
+}
+
+/// Returns the weight of the WFP sublayers that contain the firewall filters. A weight of zero,
+/// e.g. because the settings file was edited by hand, is ignored.
+#[cfg(windows)]
+pub fn wfp_sublayer_weight(settings: &Settings) -> u16 {
+    match settings.wfp_sublayer_weight {
+        Some(0) => {
+            log::warn!("Ignoring invalid WFP sublayer weight 0");
+            talpid_core::firewall::DEFAULT_SUBLAYER_WEIGHT
+        }
+        Some(weight) => weight,
+        None => talpid_core::firewall::DEFAULT_SUBLAYER_WEIGHT,
+    }
+}
+
+/// Returns the PF anchor that the firewall rules should be installed in, if not the default anchor.
+/// An invalid anchor, e.g. because the settings file was edited by hand, is ignored.
+#[cfg(target_os = "macos")]
+pub fn pf_anchor(settings: &Settings) -> Option<String> {
+    let anchor = settings.pf_anchor.as_ref()?;
+    if !mullvad_types::settings::is_valid_pf_anchor(anchor) {
+        log::warn!("Ignoring invalid PF anchor \"{anchor}\"");
+        return None;
+    }
+    Some(anchor.clone())
+}
+
+/// Returns the order that DNS backends should be tried in. An invalid order, e.g. because the
+/// settings file was edited by hand, is ignored.
+#[cfg(target_os = "linux")]
+pub fn dns_backends(settings: &Settings) -> Vec<talpid_types::dns::DnsBackend> {
+    if !mullvad_types::settings::is_valid_dns_backend_order(&settings.dns_backends) {
+        log::warn!(
+            "Ignoring invalid DNS backend order {:?}",
+            settings.dns_backends
+        );
+        return vec![];
+    }
+    settings.dns_backends.clone()
+}
+
 /// A compact summary of important settings
 pub struct SettingsSummary<'a> {
     settings: &'a Settings,
@@ -429,7 +544,7 @@ impl Display for SettingsSummary<'_> {
             ", multihop: {}, ipv6 (tun): {}, lan: {}, pq: {}, obfs: {}",
             bool_to_label(multihop),
             bool_to_label(self.settings.tunnel_options.generic.enable_ipv6),
-            bool_to_label(self.settings.allow_lan),
+            bool_to_label(self.settings.allow_lan.is_allowed()),
             self.settings.tunnel_options.wireguard.quantum_resistant,
             self.settings.obfuscation_settings.selected_obfuscation,
         )?;
@@ -576,7 +691,7 @@ mod test {
                 }
               },
               "bridge_state": "auto",
-              "allow_lan": true,
+              "allow_lan": "all",
               "block_when_disconnected": false,
               "auto_connect": true,
               "tunnel_options": {
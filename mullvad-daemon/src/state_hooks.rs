@@ -0,0 +1,254 @@
+//! Runs user-provided executables when the tunnel enters the connected, disconnected or error
+//! state.
+//!
+//! The daemon runs hooks with elevated privileges, so it refuses to run executables that
+//! unprivileged users could have modified. On Unix, the executable and every directory above it
+//! must be owned by root and must not be writable by anyone else. On Windows, the executable must
+//! be located in a directory that only administrators can write to, such as `Program Files`.
+//!
+//! Hooks are run one at a time, in the order that the states were entered. The new state is
+//! described by the following environment variables:
+//!
+//! * `MULLVAD_TUNNEL_STATE`: `connected`, `disconnected` or `error`.
+//! * `MULLVAD_TUNNEL_TYPE`, `MULLVAD_TUNNEL_INTERFACE`, `MULLVAD_ENDPOINT`: The tunnel protocol,
+//!   the name of the tunnel interface, and the address of the relay that the tunnel is connected
+//!   to. Only set when connected.
+//! * `MULLVAD_RELAY_HOSTNAME`, `MULLVAD_ENTRY_RELAY_HOSTNAME`, `MULLVAD_COUNTRY`, `MULLVAD_CITY`:
+//!   The exit relay, the multihop entry relay, and the location of the exit relay. Only set when
+//!   connected, and only if known.
+//! * `MULLVAD_LOCKED_DOWN`: `true` if traffic is blocked while disconnected, otherwise `false`.
+//!   Only set when disconnected.
+//! * `MULLVAD_ERROR_CAUSE`, `MULLVAD_BLOCKING`: Why the tunnel failed, and whether traffic is
+//!   blocked. Only set in the error state.
+
+use futures::{channel::mpsc, StreamExt};
+use mullvad_types::{
+    state_hooks::{HookEvent, StateHooks},
+    states::TunnelState,
+};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    process::{ExitStatus, Stdio},
+    time::Duration,
+};
+use talpid_types::ErrorExt;
+use tokio::process::Command;
+
+/// Hooks that have not finished within this time are killed.
+const HOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Hook path must be absolute")]
+    RelativePath,
+
+    #[error("Failed to read metadata of {0}")]
+    Metadata(PathBuf, #[source] io::Error),
+
+    #[error("Hook is not an executable file")]
+    NotExecutable,
+
+    #[error("{0} may be modified by users other than root")]
+    InsecurePermissions(PathBuf),
+
+    #[error("Hook must be located in a directory that only administrators can modify")]
+    InsecureLocation,
+
+    #[error("Failed to start hook")]
+    Start(#[source] io::Error),
+
+    #[error("Hook did not finish in time")]
+    Timeout,
+
+    #[error("Hook exited with status {0}")]
+    Failed(ExitStatus),
+}
+
+type Environment = Vec<(&'static str, String)>;
+
+/// Runs hooks in the background, one at a time.
+pub struct StateHookRunner {
+    tx: mpsc::UnboundedSender<(HookEvent, PathBuf, Environment)>,
+}
+
+impl StateHookRunner {
+    pub fn spawn() -> Self {
+        let (tx, mut rx) = mpsc::unbounded::<(HookEvent, PathBuf, Environment)>();
+        tokio::spawn(async move {
+            while let Some((event, path, environment)) = rx.next().await {
+                if let Err(error) = run_hook(&path, environment).await {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg(&format!(
+                            "Failed to run {event} hook {}",
+                            path.display()
+                        ))
+                    );
+                }
+            }
+        });
+        Self { tx }
+    }
+
+    /// Run the hook for `state`, if there is one.
+    pub fn on_new_state(&self, hooks: &StateHooks, state: &TunnelState) {
+        let Some((event, environment)) = environment(state) else {
+            return;
+        };
+        if let Some(path) = hooks.get(event) {
+            let _ = self
+                .tx
+                .unbounded_send((event, path.to_owned(), environment));
+        }
+    }
+}
+
+async fn run_hook(path: &Path, environment: Environment) -> Result<(), Error> {
+    // The executable may have been replaced since the hook was set
+    validate(path)?;
+
+    let output = Command::new(path)
+        .envs(environment)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(HOOK_TIMEOUT, output)
+        .await
+        .map_err(|_| Error::Timeout)?
+        .map_err(Error::Start)?;
+    if !output.status.success() {
+        log::debug!(
+            "Output of {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        return Err(Error::Failed(output.status));
+    }
+    Ok(())
+}
+
+/// Returns the hook event and environment variables for entering `state`.
+fn environment(state: &TunnelState) -> Option<(HookEvent, Environment)> {
+    match state {
+        TunnelState::Connected {
+            endpoint, location, ..
+        } => {
+            let mut environment = vec![
+                ("MULLVAD_TUNNEL_STATE", HookEvent::Connected.to_string()),
+                ("MULLVAD_TUNNEL_TYPE", endpoint.tunnel_type.to_string()),
+                ("MULLVAD_ENDPOINT", endpoint.endpoint.address.to_string()),
+            ];
+            if let Some(interface) = &endpoint.tunnel_interface {
+                environment.push(("MULLVAD_TUNNEL_INTERFACE", interface.clone()));
+            }
+            if let Some(location) = location {
+                environment.push(("MULLVAD_COUNTRY", location.country.clone()));
+                let optional = [
+                    ("MULLVAD_CITY", &location.city),
+                    ("MULLVAD_RELAY_HOSTNAME", &location.hostname),
+                    ("MULLVAD_ENTRY_RELAY_HOSTNAME", &location.entry_hostname),
+                ];
+                for (name, value) in optional {
+                    if let Some(value) = value {
+                        environment.push((name, value.clone()));
+                    }
+                }
+            }
+            Some((HookEvent::Connected, environment))
+        }
+        TunnelState::Disconnected { locked_down, .. } => Some((
+            HookEvent::Disconnected,
+            vec![
+                ("MULLVAD_TUNNEL_STATE", HookEvent::Disconnected.to_string()),
+                ("MULLVAD_LOCKED_DOWN", locked_down.to_string()),
+            ],
+        )),
+        TunnelState::Error(error_state) => Some((
+            HookEvent::Error,
+            vec![
+                ("MULLVAD_TUNNEL_STATE", HookEvent::Error.to_string()),
+                ("MULLVAD_ERROR_CAUSE", error_state.cause().to_string()),
+                ("MULLVAD_BLOCKING", error_state.is_blocking().to_string()),
+            ],
+        )),
+        _ => None,
+    }
+}
+
+/// Check that `path` is an executable that only administrators can modify.
+pub fn validate(path: &Path) -> Result<(), Error> {
+    if !path.is_absolute() {
+        return Err(Error::RelativePath);
+    }
+    let path = path
+        .canonicalize()
+        .map_err(|error| Error::Metadata(path.to_owned(), error))?;
+    let metadata =
+        std::fs::metadata(&path).map_err(|error| Error::Metadata(path.clone(), error))?;
+    if !metadata.is_file() {
+        return Err(Error::NotExecutable);
+    }
+    validate_permissions(&path, &metadata)
+}
+
+#[cfg(unix)]
+fn validate_permissions(path: &Path, metadata: &std::fs::Metadata) -> Result<(), Error> {
+    use std::os::unix::fs::MetadataExt;
+
+    if metadata.mode() & 0o111 == 0 {
+        return Err(Error::NotExecutable);
+    }
+    for path in path.ancestors() {
+        let metadata =
+            std::fs::metadata(path).map_err(|error| Error::Metadata(path.to_owned(), error))?;
+        if metadata.uid() != 0 || metadata.mode() & 0o022 != 0 {
+            return Err(Error::InsecurePermissions(path.to_owned()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(windows)]
+fn validate_permissions(path: &Path, _metadata: &std::fs::Metadata) -> Result<(), Error> {
+    /// Directories that only administrators can write to by default.
+    const ADMIN_DIRECTORY_VARIABLES: &[&str] = &["ProgramFiles", "ProgramFiles(x86)", "SystemRoot"];
+
+    let in_admin_directory = ADMIN_DIRECTORY_VARIABLES
+        .iter()
+        .filter_map(std::env::var_os)
+        .filter_map(|directory| Path::new(&directory).canonicalize().ok())
+        .any(|directory| path.starts_with(directory));
+    if !in_admin_directory {
+        return Err(Error::InsecureLocation);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mullvad_types::states::TunnelState;
+
+    #[test]
+    fn test_validate_relative_path() {
+        assert!(matches!(
+            validate(Path::new("hook.sh")),
+            Err(Error::RelativePath)
+        ));
+    }
+
+    #[test]
+    fn test_disconnected_environment() {
+        let state = TunnelState::Disconnected {
+            location: None,
+            locked_down: true,
+        };
+        let (event, environment) = environment(&state).unwrap();
+        assert_eq!(event, HookEvent::Disconnected);
+        assert!(environment.contains(&("MULLVAD_TUNNEL_STATE", "disconnected".to_owned())));
+        assert!(environment.contains(&("MULLVAD_LOCKED_DOWN", "true".to_owned())));
+
+        assert!(super::environment(&TunnelState::Paused).is_none());
+    }
+}
@@ -0,0 +1,195 @@
+//! Reports the state of the daemon to systemd, as described in `sd_notify(3)`, so that
+//! `systemctl status` shows the tunnel state and systemd can restart the daemon if it hangs.
+//!
+//! Nothing is sent unless the daemon was started by systemd with `NotifyAccess` enabled. The
+//! daemon reloads the relay list overrides when it receives `SIGHUP`, i.e. on `systemctl reload`.
+
+use crate::{Daemon, InternalDaemonEvent};
+use mullvad_types::{location::GeoIpLocation, states::TunnelState};
+use nix::time::{clock_gettime, ClockId};
+use std::{
+    env, io,
+    os::{
+        linux::net::SocketAddrExt,
+        unix::net::{SocketAddr, UnixDatagram},
+    },
+    time::Duration,
+};
+use talpid_core::mpsc::Sender;
+use talpid_types::ErrorExt;
+use tokio::signal::unix::{signal, SignalKind};
+
+const NOTIFY_SOCKET_VAR: &str = "NOTIFY_SOCKET";
+const WATCHDOG_USEC_VAR: &str = "WATCHDOG_USEC";
+const WATCHDOG_PID_VAR: &str = "WATCHDOG_PID";
+
+/// The daemon has finished starting up or reloading.
+pub fn ready() {
+    notify("READY=1");
+}
+
+/// The daemon has started reloading its configuration. Must be followed by [ready].
+pub fn reloading() {
+    match clock_gettime(ClockId::CLOCK_MONOTONIC) {
+        Ok(now) => {
+            let monotonic_usec = now.tv_sec() * 1_000_000 + now.tv_nsec() / 1_000;
+            notify(&format!("RELOADING=1\nMONOTONIC_USEC={monotonic_usec}"));
+        }
+        Err(error) => log::debug!("Failed to read monotonic clock: {error}"),
+    }
+}
+
+/// The daemon is shutting down.
+pub fn stopping() {
+    notify("STOPPING=1");
+}
+
+/// Describe `state` in `systemctl status`.
+pub fn status(state: &TunnelState) {
+    notify(&format!("STATUS={}", status_text(state)));
+}
+
+/// Returns how often systemd expects `WATCHDOG=1` to be sent, if the watchdog is enabled for the
+/// daemon.
+fn watchdog_interval() -> Option<Duration> {
+    if let Some(pid) = env::var_os(WATCHDOG_PID_VAR) {
+        if pid.to_str()?.parse::<u32>().ok()? != std::process::id() {
+            return None;
+        }
+    }
+    let usec: u64 = env::var(WATCHDOG_USEC_VAR).ok()?.parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+fn status_text(state: &TunnelState) -> String {
+    let relay = |location: &Option<GeoIpLocation>| {
+        location
+            .as_ref()
+            .and_then(|location| location.hostname.as_ref())
+            .map(|hostname| format!(" to {hostname}"))
+            .unwrap_or_default()
+    };
+    match state {
+        TunnelState::Disconnected {
+            locked_down: true, ..
+        } => "Disconnected, blocking traffic".to_owned(),
+        TunnelState::Disconnected { .. } => "Disconnected".to_owned(),
+        TunnelState::Connecting { location, .. } => format!("Connecting{}", relay(location)),
+        TunnelState::Connected { location, .. } => format!("Connected{}", relay(location)),
+        TunnelState::Disconnecting(_) => "Disconnecting".to_owned(),
+        TunnelState::Paused => "Paused, blocking traffic".to_owned(),
+        TunnelState::Error(error_state) if error_state.is_blocking() => {
+            format!("Blocking traffic: {}", error_state.cause())
+        }
+        TunnelState::Error(error_state) => {
+            format!("Failed to block traffic: {}", error_state.cause())
+        }
+    }
+}
+
+fn notify(message: &str) {
+    if let Err(error) = send(message) {
+        log::debug!(
+            "{}",
+            error.display_chain_with_msg("Failed to send notification to systemd")
+        );
+    }
+}
+
+fn send(message: &str) -> io::Result<()> {
+    let Some(socket_path) = env::var_os(NOTIFY_SOCKET_VAR) else {
+        return Ok(());
+    };
+    // Paths that start with '@' refer to the abstract namespace
+    let address = match socket_path.as_encoded_bytes().strip_prefix(b"@") {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(&socket_path)?,
+    };
+    UnixDatagram::unbound()?.send_to_addr(message.as_bytes(), &address)?;
+    Ok(())
+}
+
+impl Daemon {
+    /// Tell systemd that the daemon is ready, and start sending watchdog notifications and
+    /// handling reload requests.
+    pub(crate) fn start_systemd_notifications(&self) {
+        ready();
+        status(&self.tunnel_state);
+
+        if let Some(interval) = watchdog_interval() {
+            let tx = self.tx.clone();
+            tokio::spawn(async move {
+                // Ping twice per interval so that a late ping is not mistaken for a hang
+                let mut interval = tokio::time::interval(interval / 2);
+                loop {
+                    interval.tick().await;
+                    if tx.send(InternalDaemonEvent::WatchdogPing).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        match signal(SignalKind::hangup()) {
+            Ok(mut hangup) => {
+                let tx = self.tx.clone();
+                tokio::spawn(async move {
+                    while hangup.recv().await.is_some() {
+                        if tx.send(InternalDaemonEvent::Reload).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            Err(error) => log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to listen for SIGHUP")
+            ),
+        }
+    }
+
+    /// Handle a ping from the watchdog task. Since the ping goes through the event loop, systemd
+    /// notices if the loop stops making progress.
+    pub(crate) fn on_watchdog_ping(&self) {
+        notify("WATCHDOG=1");
+    }
+
+    /// Reload the relay list overrides from disk.
+    pub(crate) fn on_reload(&self) {
+        log::info!("Reloading relay list overrides");
+        reloading();
+        let mut relay_list_updater = self.relay_list_updater.clone();
+        tokio::spawn(async move {
+            match relay_list_updater.reload_overrides().await {
+                Ok(errors) => {
+                    for error in errors {
+                        log::warn!("Invalid relay list override: {error}");
+                    }
+                }
+                Err(error) => log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to reload relay list overrides")
+                ),
+            }
+            ready();
+        });
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_status_text() {
+        let state = TunnelState::Disconnected {
+            location: None,
+            locked_down: true,
+        };
+        assert_eq!(status_text(&state), "Disconnected, blocking traffic");
+        assert_eq!(
+            status_text(&TunnelState::Paused),
+            "Paused, blocking traffic"
+        );
+    }
+}
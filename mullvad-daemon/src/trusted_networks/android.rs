@@ -0,0 +1,6 @@
+use mullvad_types::trusted_network::Network;
+
+/// Identifying the current network is not supported on Android.
+pub async fn current_network() -> Option<Network> {
+    None
+}
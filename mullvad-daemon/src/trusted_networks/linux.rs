@@ -0,0 +1,94 @@
+//! Identifies the current network using NetworkManager.
+
+use mullvad_types::trusted_network::Network;
+use talpid_types::ErrorExt;
+
+const NMCLI_PATH: &str = "nmcli";
+
+/// Return the network that the device is connected to. Wi-Fi takes precedence if the device is
+/// connected to both a Wi-Fi and a wired network.
+pub async fn current_network() -> Option<Network> {
+    let devices = super::run(NMCLI_PATH, &["-t", "-f", "TYPE,STATE", "device"])
+        .await
+        .inspect_err(|error| {
+            log::debug!(
+                "{}",
+                error.display_chain_with_msg("Failed to list network devices")
+            )
+        })
+        .ok()?;
+    let (wifi_connected, ethernet_connected) = parse_devices(&devices);
+
+    if wifi_connected {
+        let args = [
+            "-t",
+            "-f",
+            "ACTIVE,SSID",
+            "device",
+            "wifi",
+            "list",
+            "--rescan",
+            "no",
+        ];
+        match super::run(NMCLI_PATH, &args).await {
+            Ok(output) => {
+                if let Some(ssid) = parse_active_ssid(&output) {
+                    return Some(Network::Wifi { ssid });
+                }
+            }
+            Err(error) => log::debug!(
+                "{}",
+                error.display_chain_with_msg("Failed to list Wi-Fi networks")
+            ),
+        }
+    }
+    ethernet_connected.then_some(Network::Ethernet)
+}
+
+/// Parse the output of `nmcli -t -f TYPE,STATE device` and return whether any Wi-Fi and any
+/// wired device is connected, respectively.
+fn parse_devices(output: &str) -> (bool, bool) {
+    let connected = |device_type: &str| {
+        output.lines().any(|line| {
+            line.split_once(':')
+                .is_some_and(|(kind, state)| kind == device_type && state.starts_with("connected"))
+        })
+    };
+    (connected("wifi"), connected("ethernet"))
+}
+
+/// Parse the output of `nmcli -t -f ACTIVE,SSID device wifi list` and return the SSID of the
+/// network that is in use. Colons and backslashes in the SSID are escaped by nmcli.
+fn parse_active_ssid(output: &str) -> Option<String> {
+    let escaped = output.lines().find_map(|line| line.strip_prefix("yes:"))?;
+    let mut ssid = String::with_capacity(escaped.len());
+    let mut chars = escaped.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => ssid.extend(chars.next()),
+            c => ssid.push(c),
+        }
+    }
+    (!ssid.is_empty()).then_some(ssid)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_devices() {
+        let output = "wifi:connected\nethernet:unavailable\nloopback:connected (externally)\n";
+        assert_eq!(parse_devices(output), (true, false));
+
+        let output = "wifi:disconnected\nethernet:connected\nwireguard:connected (externally)\n";
+        assert_eq!(parse_devices(output), (false, true));
+    }
+
+    #[test]
+    fn test_parse_active_ssid() {
+        let output = "no:Neighbour\nyes:Caf\\:e \\\\ Bar\nno:\n";
+        assert_eq!(parse_active_ssid(output).as_deref(), Some("Caf:e \\ Bar"));
+        assert_eq!(parse_active_ssid("no:Neighbour\n"), None);
+    }
+}
@@ -0,0 +1,104 @@
+//! Identifies the current network using `networksetup` and `ifconfig`.
+
+use mullvad_types::trusted_network::Network;
+use talpid_types::ErrorExt;
+
+const NETWORKSETUP_PATH: &str = "/usr/sbin/networksetup";
+const IFCONFIG_PATH: &str = "/sbin/ifconfig";
+
+/// Hardware port names of wired interfaces contain one of these.
+const WIRED_PORT_NAMES: &[&str] = &["Ethernet", "LAN", "Thunderbolt"];
+
+/// Return the network that the device is connected to. Wi-Fi takes precedence if the device is
+/// connected to both a Wi-Fi and a wired network.
+pub async fn current_network() -> Option<Network> {
+    let ports = super::run(NETWORKSETUP_PATH, &["-listallhardwareports"])
+        .await
+        .inspect_err(|error| {
+            log::debug!(
+                "{}",
+                error.display_chain_with_msg("Failed to list hardware ports")
+            )
+        })
+        .ok()?;
+    let ports = parse_hardware_ports(&ports);
+
+    for (_, device) in ports.iter().filter(|(port, _)| *port == "Wi-Fi") {
+        match super::run(NETWORKSETUP_PATH, &["-getairportnetwork", device]).await {
+            Ok(output) => {
+                if let Some(ssid) = parse_airport_network(&output) {
+                    return Some(Network::Wifi { ssid });
+                }
+            }
+            Err(error) => log::debug!(
+                "{}",
+                error.display_chain_with_msg("Failed to get Wi-Fi network")
+            ),
+        }
+    }
+
+    let wired_devices = ports.iter().filter(|(port, _)| {
+        WIRED_PORT_NAMES
+            .iter()
+            .any(|wired_port| port.contains(wired_port))
+    });
+    for (_, device) in wired_devices {
+        if let Ok(output) = super::run(IFCONFIG_PATH, &[device]).await {
+            if output.lines().any(|line| line.trim() == "status: active") {
+                return Some(Network::Ethernet);
+            }
+        }
+    }
+    None
+}
+
+/// Parse the output of `networksetup -listallhardwareports` and return the hardware port name and
+/// device of every port.
+fn parse_hardware_ports(output: &str) -> Vec<(&str, &str)> {
+    let mut ports = vec![];
+    let mut port = None;
+    for line in output.lines() {
+        if let Some(name) = line.strip_prefix("Hardware Port: ") {
+            port = Some(name.trim());
+        } else if let Some(device) = line.strip_prefix("Device: ") {
+            if let Some(port) = port.take() {
+                ports.push((port, device.trim()));
+            }
+        }
+    }
+    ports
+}
+
+/// Parse the output of `networksetup -getairportnetwork <device>`.
+fn parse_airport_network(output: &str) -> Option<String> {
+    let ssid = output.trim().strip_prefix("Current Wi-Fi Network: ")?;
+    (!ssid.is_empty()).then(|| ssid.to_owned())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_hardware_ports() {
+        let output = "\nHardware Port: Ethernet Adapter (en4)\nDevice: en4\nEthernet Address: \
+                      00:00:00:00:00:00\n\nHardware Port: Wi-Fi\nDevice: en0\nEthernet Address: \
+                      00:00:00:00:00:01\n";
+        assert_eq!(
+            parse_hardware_ports(output),
+            vec![("Ethernet Adapter (en4)", "en4"), ("Wi-Fi", "en0")]
+        );
+    }
+
+    #[test]
+    fn test_parse_airport_network() {
+        assert_eq!(
+            parse_airport_network("Current Wi-Fi Network: Cafe: Bar\n").as_deref(),
+            Some("Cafe: Bar")
+        );
+        assert_eq!(
+            parse_airport_network("You are not associated with an AirPort network.\n"),
+            None
+        );
+    }
+}
@@ -0,0 +1,255 @@
+//! Rules for connecting or disconnecting depending on the network that the device is connected
+//! to.
+//!
+//! The SSIDs of the networks that a user has rules for reveal where they have been, so the rules
+//! are stored in a file of their own that only the daemon can read, rather than in the settings.
+
+use crate::{Daemon, InternalDaemonEvent};
+#[cfg(not(target_os = "android"))]
+use futures::channel::oneshot;
+use mullvad_types::{
+    states::TargetState,
+    trusted_network::{
+        self, Network, NetworkAction, NetworkMatch, TrustedNetworkRule, TrustedNetworks,
+    },
+};
+use std::path::{Path, PathBuf};
+use talpid_core::mpsc::Sender;
+#[cfg(not(target_os = "android"))]
+use talpid_core::tunnel_state_machine::TunnelCommand;
+use talpid_types::ErrorExt;
+use tokio::{fs, io, io::AsyncWriteExt};
+
+#[cfg(target_os = "linux")]
+#[path = "linux.rs"]
+mod imp;
+
+#[cfg(target_os = "macos")]
+#[path = "macos.rs"]
+mod imp;
+
+#[cfg(target_os = "windows")]
+#[path = "windows.rs"]
+mod imp;
+
+#[cfg(target_os = "android")]
+#[path = "android.rs"]
+mod imp;
+
+pub use imp::current_network;
+
+const TRUSTED_NETWORKS_FILE: &str = "trusted-networks.json";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to serialize trusted networks")]
+    Serialize(#[source] serde_json::Error),
+
+    #[error("Unable to write trusted networks file")]
+    Write(#[source] io::Error),
+
+    #[error("Invalid trusted network rule")]
+    Rule(#[source] trusted_network::Error),
+}
+
+/// Converts an [Error] to a management interface status
+impl From<Error> for mullvad_management_interface::Status {
+    fn from(error: Error) -> mullvad_management_interface::Status {
+        use mullvad_management_interface::Status;
+
+        match error {
+            Error::Rule(trusted_network::Error::InvalidSsid) => {
+                Status::invalid_argument(error.to_string())
+            }
+            Error::Rule(trusted_network::Error::NotFound) => Status::not_found(error.to_string()),
+            Error::Serialize(_) | Error::Write(_) => Status::internal(error.to_string()),
+        }
+    }
+}
+
+pub struct TrustedNetworksPersister {
+    path: PathBuf,
+    networks: TrustedNetworks,
+}
+
+impl TrustedNetworksPersister {
+    /// Load the trusted network rules. If they cannot be read, no rules are used.
+    pub async fn load(settings_dir: &Path) -> Self {
+        let path = settings_dir.join(TRUSTED_NETWORKS_FILE);
+        let networks = match fs::read_to_string(&path).await {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|error| {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to parse trusted networks")
+                );
+                TrustedNetworks::default()
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => TrustedNetworks::default(),
+            Err(error) => {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to read trusted networks")
+                );
+                TrustedNetworks::default()
+            }
+        };
+        Self { path, networks }
+    }
+
+    pub fn get(&self) -> &TrustedNetworks {
+        &self.networks
+    }
+
+    /// Modify the rules and save them to disk. Nothing is changed if `update_fn` fails.
+    pub async fn update(
+        &mut self,
+        update_fn: impl FnOnce(&mut TrustedNetworks) -> Result<(), trusted_network::Error>,
+    ) -> Result<(), Error> {
+        let mut networks = self.networks.clone();
+        update_fn(&mut networks).map_err(Error::Rule)?;
+        Self::save(&self.path, &networks).await?;
+        self.networks = networks;
+        Ok(())
+    }
+
+    async fn save(path: &Path, networks: &TrustedNetworks) -> Result<(), Error> {
+        let buffer = serde_json::to_string_pretty(networks).map_err(Error::Serialize)?;
+        let mut file = mullvad_fs::AtomicFile::new(path)
+            .await
+            .map_err(Error::Write)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o600))
+                .await
+                .map_err(Error::Write)?;
+        }
+        file.write_all(buffer.as_bytes())
+            .await
+            .map_err(Error::Write)?;
+        file.finalize().await.map_err(Error::Write)
+    }
+}
+
+/// Run `program` and return its standard output.
+#[cfg(not(target_os = "android"))]
+async fn run(program: &str, args: &[&str]) -> io::Result<String> {
+    let output = tokio::process::Command::new(program)
+        .args(args)
+        .output()
+        .await?;
+    if !output.status.success() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("{program} exited with status {}", output.status),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+impl Daemon {
+    /// Add a rule, replacing any existing rule for the same networks, and apply it if it matches
+    /// the current network.
+    pub async fn set_trusted_network_rule(
+        &mut self,
+        rule: TrustedNetworkRule,
+    ) -> Result<(), Error> {
+        self.trusted_networks
+            .update(|networks| networks.set(rule))
+            .await?;
+        self.apply_trusted_network_rule().await;
+        Ok(())
+    }
+
+    /// Remove the rule for the given networks.
+    pub async fn remove_trusted_network_rule(
+        &mut self,
+        network: NetworkMatch,
+    ) -> Result<(), Error> {
+        self.trusted_networks
+            .update(|networks| networks.remove(&network))
+            .await?;
+        self.update_trusted_network_lockdown();
+        Ok(())
+    }
+
+    /// Identify the current network in the background. The result is sent to the daemon as an
+    /// [InternalDaemonEvent::CurrentNetwork] event.
+    pub(crate) fn check_current_network(&self) {
+        let tx = self.tx.clone();
+        tokio::spawn(async move {
+            let network = current_network().await;
+            let _ = tx.send(InternalDaemonEvent::CurrentNetwork(network));
+        });
+    }
+
+    /// Apply the rule for `network` if the device has joined a different network since the last
+    /// check. Rules are only applied when joining a network, so that the user may still connect
+    /// or disconnect manually afterwards.
+    pub(crate) async fn handle_current_network(&mut self, network: Option<Network>) {
+        if network == self.current_network {
+            return;
+        }
+        match &network {
+            Some(network) => log::info!("Joined network: {network}"),
+            None => log::debug!("Not connected to any known type of network"),
+        }
+        self.current_network = network;
+        self.apply_trusted_network_rule().await;
+    }
+
+    async fn apply_trusted_network_rule(&mut self) {
+        self.update_trusted_network_lockdown();
+
+        match self.current_network_action() {
+            Some(NetworkAction::Disconnect) => {
+                log::info!("Disconnecting because of the rule for the current network");
+                self.set_target_state(TargetState::Unsecured).await;
+            }
+            Some(NetworkAction::Connect | NetworkAction::ConnectAndLockdown) => {
+                let logged_in = self
+                    .account_manager
+                    .data()
+                    .await
+                    .is_ok_and(|data| data.logged_in());
+                if !logged_in {
+                    log::info!("Not connecting for the current network since there is no account");
+                    return;
+                }
+                log::info!("Connecting because of the rule for the current network");
+                self.set_target_state(TargetState::Secured).await;
+            }
+            None => (),
+        }
+    }
+
+    fn current_network_action(&self) -> Option<NetworkAction> {
+        let network = self.current_network.as_ref()?;
+        self.trusted_networks.get().action_for(network)
+    }
+
+    /// Block traffic while disconnected if the rule for the current network says so, and stop
+    /// doing so when it no longer applies.
+    fn update_trusted_network_lockdown(&mut self) {
+        #[cfg(not(target_os = "android"))]
+        {
+            let lockdown = self.current_network_action() == Some(NetworkAction::ConnectAndLockdown);
+            if lockdown != self.trusted_network_lockdown {
+                self.trusted_network_lockdown = lockdown;
+                let (tx, _rx) = oneshot::channel();
+                self.send_tunnel_command(TunnelCommand::BlockWhenDisconnected(
+                    self.block_when_disconnected(),
+                    tx,
+                ));
+            }
+        }
+    }
+
+    /// Whether to block traffic while disconnected, either because of the lockdown mode setting
+    /// or because of the rule for the current network, unless lockdown mode is paused.
+    #[cfg(not(target_os = "android"))]
+    pub(crate) fn block_when_disconnected(&self) -> bool {
+        (self.settings.block_when_disconnected || self.trusted_network_lockdown)
+            && self.lockdown_pause.is_none()
+    }
+}
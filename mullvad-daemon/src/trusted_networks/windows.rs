@@ -0,0 +1,75 @@
+//! Identifies the current network using `netsh` and `Get-NetAdapter`.
+
+use mullvad_types::trusted_network::Network;
+use talpid_types::ErrorExt;
+
+const NETSH_PATH: &str = "netsh";
+const POWERSHELL_PATH: &str = "powershell";
+
+/// Prints the number of connected physical wired adapters.
+const COUNT_WIRED_ADAPTERS: &str = "@(Get-NetAdapter -Physical | Where-Object { $_.Status -eq \
+                                    'Up' -and $_.MediaType -eq '802.3' }).Count";
+
+/// Return the network that the device is connected to. Wi-Fi takes precedence if the device is
+/// connected to both a Wi-Fi and a wired network.
+pub async fn current_network() -> Option<Network> {
+    match super::run(NETSH_PATH, &["wlan", "show", "interfaces"]).await {
+        Ok(output) => {
+            if let Some(ssid) = parse_wlan_interfaces(&output) {
+                return Some(Network::Wifi { ssid });
+            }
+        }
+        // This fails if the WLAN service is not running, e.g. if there is no Wi-Fi adapter
+        Err(error) => log::debug!(
+            "{}",
+            error.display_chain_with_msg("Failed to list WLAN interfaces")
+        ),
+    }
+
+    let args = [
+        "-NoProfile",
+        "-NonInteractive",
+        "-Command",
+        COUNT_WIRED_ADAPTERS,
+    ];
+    match super::run(POWERSHELL_PATH, &args).await {
+        Ok(output) => {
+            let wired_adapters: u32 = output.trim().parse().unwrap_or(0);
+            (wired_adapters > 0).then_some(Network::Ethernet)
+        }
+        Err(error) => {
+            log::debug!(
+                "{}",
+                error.display_chain_with_msg("Failed to list network adapters")
+            );
+            None
+        }
+    }
+}
+
+/// Parse the output of `netsh wlan show interfaces` and return the SSID of the first connected
+/// interface. The SSID is only listed for connected interfaces.
+fn parse_wlan_interfaces(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        let ssid = value.strip_prefix(' ').unwrap_or(value);
+        (key.trim() == "SSID" && !ssid.is_empty()).then(|| ssid.to_owned())
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_wlan_interfaces() {
+        let output = "\r\nThere is 1 interface on the system:\r\n\r\n    Name                   : \
+                      Wi-Fi\r\n    State                  : connected\r\n    SSID                   \
+                      : Cafe: Bar\r\n    BSSID                  : 00:00:00:00:00:00\r\n";
+        assert_eq!(parse_wlan_interfaces(output).as_deref(), Some("Cafe: Bar"));
+
+        let output = "\r\n    Name                   : Wi-Fi\r\n    State                  : \
+                      disconnected\r\n";
+        assert_eq!(parse_wlan_interfaces(output), None);
+    }
+}
@@ -17,15 +17,27 @@ use std::sync::LazyLock;
 use talpid_core::tunnel_state_machine::TunnelParametersGenerator;
 #[cfg(not(target_os = "android"))]
 use talpid_types::net::{
-    obfuscation::ObfuscatorConfig, openvpn, proxy::CustomProxy, wireguard, Endpoint,
-    TunnelParameters,
+    obfuscation::{ObfuscatorConfig, PortHoppingConfig},
+    openvpn,
+    proxy::CustomProxy,
+    wireguard, Endpoint, TunnelParameters,
 };
 #[cfg(target_os = "android")]
-use talpid_types::net::{obfuscation::ObfuscatorConfig, wireguard, TunnelParameters};
+use talpid_types::net::{
+    obfuscation::{ObfuscatorConfig, PortHoppingConfig},
+    wireguard, TunnelParameters,
+};
 
-use talpid_types::{net::IpAvailability, tunnel::ParameterGenerationError, ErrorExt};
+use talpid_types::{
+    net::{GenericTunnelOptions, IpAvailability, ObfuscationType},
+    tunnel::ParameterGenerationError,
+    ErrorExt,
+};
 
-use crate::device::{AccountManagerHandle, Error as DeviceError, PrivateAccountAndDevice};
+use crate::{
+    device::{AccountManagerHandle, Error as DeviceError, PrivateAccountAndDevice},
+    obfuscation_memory::{NetworkId, ObfuscationMemory},
+};
 
 /// The IP-addresses that the client uses when it connects to a server that supports the
 /// "Same IP" functionality. This means all clients have the same in-tunnel IP on these
@@ -61,8 +73,14 @@ struct InnerParametersGenerator {
     relay_selector: RelaySelector,
     tunnel_options: TunnelOptions,
     account_manager: AccountManagerHandle,
+    /// Firewall mark set on tunnel traffic
+    #[cfg(target_os = "linux")]
+    fwmark: u32,
+    obfuscation_memory: ObfuscationMemory,
 
     last_generated_relays: Option<LastSelectedRelays>,
+    /// Network that the host was connected to when the last parameters were generated
+    last_network: Option<NetworkId>,
 }
 
 impl ParametersGenerator {
@@ -71,17 +89,35 @@ impl ParametersGenerator {
         account_manager: AccountManagerHandle,
         relay_selector: RelaySelector,
         tunnel_options: TunnelOptions,
+        #[cfg(target_os = "linux")] fwmark: u32,
+        obfuscation_memory: ObfuscationMemory,
     ) -> Self {
         Self(Arc::new(Mutex::new(InnerParametersGenerator {
             tunnel_options,
             relay_selector,
 
             account_manager,
+            #[cfg(target_os = "linux")]
+            fwmark,
+            obfuscation_memory,
 
             last_generated_relays: None,
+            last_network: None,
         })))
     }
 
+    /// Remember that `obfuscation` worked on the network that the last tunnel parameters were
+    /// generated for, so that it is tried first the next time.
+    pub async fn remember_obfuscation(&self, obfuscation: Option<ObfuscationType>) {
+        let mut inner = self.0.lock().await;
+        if let Some(network) = inner.last_network.clone() {
+            inner
+                .obfuscation_memory
+                .remember(network, obfuscation)
+                .await;
+        }
+    }
+
     /// Sets the tunnel options to use when generating new tunnel parameters.
     pub async fn set_tunnel_options(&self, tunnel_options: &TunnelOptions) {
         self.0.lock().await.tunnel_options = tunnel_options.clone();
@@ -103,6 +139,29 @@ impl ParametersGenerator {
         }
     }
 
+    /// Lets the relay selector reuse the relays of the last generated tunnel parameters, since
+    /// connecting to them succeeded.
+    pub async fn remember_connected_relays(&self) {
+        let inner = self.0.lock().await;
+        match inner.last_generated_relays.as_ref() {
+            Some(LastSelectedRelays::WireGuard {
+                wg_entry,
+                wg_exit,
+                server_override: false,
+                ..
+            }) => inner
+                .relay_selector
+                .remember_connected_relays(wg_exit, wg_entry.as_ref()),
+            #[cfg(not(target_os = "android"))]
+            Some(LastSelectedRelays::OpenVpn {
+                relay,
+                server_override: false,
+                ..
+            }) => inner.relay_selector.remember_connected_relays(relay, None),
+            _ => (),
+        }
+    }
+
     /// Gets the location associated with the last generated tunnel parameters.
     pub async fn get_last_location(&self) -> Option<GeoIpLocation> {
         let inner = self.0.lock().await;
@@ -163,9 +222,31 @@ impl InnerParametersGenerator {
         ip_availability: IpAvailability,
     ) -> Result<TunnelParameters, Error> {
         let data = self.device().await?;
-        let selected_relay = self
-            .relay_selector
-            .get_relay(retry_attempt as usize, ip_availability)?;
+
+        // Retrying means that the previous attempt failed to connect
+        if retry_attempt > 0 {
+            if let Some(relays) = &self.last_generated_relays {
+                self.relay_selector
+                    .record_failure(&relays.first_relay().hostname);
+            }
+        }
+
+        self.last_network = NetworkId::current().await;
+        let preferred_obfuscation = self
+            .last_network
+            .as_ref()
+            .and_then(|network| self.obfuscation_memory.get(network))
+            .flatten();
+        let selected_relay = match preferred_obfuscation {
+            Some(obfuscation) => self.relay_selector.get_relay_with_preferred_obfuscation(
+                retry_attempt as usize,
+                ip_availability,
+                obfuscation,
+            )?,
+            None => self
+                .relay_selector
+                .get_relay(retry_attempt as usize, ip_availability)?,
+        };
 
         match selected_relay {
             #[cfg(not(target_os = "android"))]
@@ -191,6 +272,7 @@ impl InnerParametersGenerator {
             GetRelay::Wireguard {
                 endpoint,
                 obfuscator,
+                port_hopping,
                 inner,
             } => {
                 let (obfuscator_relay, obfuscator_config) = match obfuscator {
@@ -200,7 +282,7 @@ impl InnerParametersGenerator {
 
                 let (wg_entry, wg_exit) = match inner {
                     WireguardConfig::Singlehop { exit } => (None, exit),
-                    WireguardConfig::Multihop { exit, entry } => (Some(entry), exit),
+                    WireguardConfig::Multihop { exit, entry, .. } => (Some(entry), exit),
                 };
                 let server_override = {
                     let first_relay = wg_entry.as_ref().unwrap_or(&wg_exit);
@@ -215,17 +297,32 @@ impl InnerParametersGenerator {
                     server_override,
                 });
 
-                Ok(self.create_wireguard_tunnel_parameters(endpoint, data, obfuscator_config))
+                Ok(self.create_wireguard_tunnel_parameters(
+                    endpoint,
+                    data,
+                    obfuscator_config,
+                    port_hopping,
+                ))
             }
             GetRelay::Custom(custom_relay) => {
                 self.last_generated_relays = None;
-                custom_relay
+                self.last_network = None;
+                #[cfg_attr(not(target_os = "linux"), allow(unused_mut))]
+                let mut parameters = custom_relay
                     // TODO: generate proxy settings for custom tunnels
                     .to_tunnel_parameters(self.tunnel_options.clone(), None)
                     .map_err(|e| {
                         log::error!("Failed to resolve hostname for custom tunnel config: {}", e);
                         Error::ResolveCustomHostname
-                    })
+                    })?;
+                #[cfg(target_os = "linux")]
+                match &mut parameters {
+                    TunnelParameters::OpenVpn(params) => params.fwmark = self.fwmark,
+                    TunnelParameters::Wireguard(params) => {
+                        params.connection.fwmark = Some(self.fwmark)
+                    }
+                }
+                Ok(parameters)
             }
         }
     }
@@ -240,10 +337,15 @@ impl InnerParametersGenerator {
         openvpn::TunnelParameters {
             config: openvpn::ConnectionConfig::new(endpoint, data.account_number, "-".to_string()),
             options: self.tunnel_options.openvpn.clone(),
-            generic_options: self.tunnel_options.generic.clone(),
+            generic_options: GenericTunnelOptions {
+                enable_ipv6: self.tunnel_options.generic.enable_ipv6
+                    && !self.tunnel_options.generic.block_ipv6,
+                block_ipv6: self.tunnel_options.generic.block_ipv6,
+                custom_routes: self.tunnel_options.generic.custom_routes.clone(),
+            },
             proxy: bridge_settings,
             #[cfg(target_os = "linux")]
-            fwmark: mullvad_types::TUNNEL_FWMARK,
+            fwmark: self.fwmark,
         }
         .into()
     }
@@ -253,6 +355,7 @@ impl InnerParametersGenerator {
         endpoint: MullvadWireguardEndpoint,
         data: PrivateAccountAndDevice,
         obfuscator_config: Option<ObfuscatorConfig>,
+        port_hopping: Option<PortHoppingConfig>,
     ) -> TunnelParameters {
         let tunnel_ipv4 = data.device.wg_data.addresses.ipv4_address.ip();
         let tunnel_ipv6 = data.device.wg_data.addresses.ipv6_address.ip();
@@ -273,18 +376,27 @@ impl InnerParametersGenerator {
                 tunnel,
                 peer: endpoint.peer,
                 exit_peer: endpoint.exit_peer,
+                middle_peers: endpoint.middle_peers,
                 ipv4_gateway: endpoint.ipv4_gateway,
                 ipv6_gateway: Some(endpoint.ipv6_gateway),
                 #[cfg(target_os = "linux")]
-                fwmark: Some(mullvad_types::TUNNEL_FWMARK),
+                fwmark: Some(self.fwmark),
             },
             options: self
                 .tunnel_options
                 .wireguard
                 .clone()
                 .into_talpid_tunnel_options(),
-            generic_options: self.tunnel_options.generic.clone(),
+            generic_options: GenericTunnelOptions {
+                // An IPv6-only tunnel implies that IPv6 is used in the tunnel
+                enable_ipv6: (self.tunnel_options.generic.enable_ipv6
+                    || self.tunnel_options.wireguard.ipv6_only)
+                    && !self.tunnel_options.generic.block_ipv6,
+                block_ipv6: self.tunnel_options.generic.block_ipv6,
+                custom_routes: self.tunnel_options.generic.custom_routes.clone(),
+            },
             obfuscation: obfuscator_config,
+            port_hopping,
         }
         .into()
     }
@@ -360,3 +472,19 @@ enum LastSelectedRelays {
         server_override: bool,
     },
 }
+
+impl LastSelectedRelays {
+    /// Returns the relay that the client connects to directly.
+    fn first_relay(&self) -> &Relay {
+        match self {
+            LastSelectedRelays::WireGuard {
+                wg_entry,
+                wg_exit,
+                obfuscator,
+                ..
+            } => obfuscator.as_ref().or(wg_entry.as_ref()).unwrap_or(wg_exit),
+            #[cfg(not(target_os = "android"))]
+            LastSelectedRelays::OpenVpn { relay, bridge, .. } => bridge.as_ref().unwrap_or(relay),
+        }
+    }
+}
@@ -7,6 +7,7 @@ use futures::{
 use mullvad_api::{
     availability::ApiAvailability,
     rest::MullvadRestHandle,
+    retry::RetryPolicy,
     version::{AppVersionProxy, AppVersionResponse},
 };
 use mullvad_types::version::AppVersionInfo;
@@ -22,7 +23,7 @@ use std::{
     time::{Duration, SystemTime},
 };
 use talpid_core::mpsc::Sender;
-use talpid_future::retry::{retry_future, ConstantInterval};
+use talpid_future::retry::retry_future;
 use talpid_types::ErrorExt;
 use tokio::{fs::File, io::AsyncReadExt};
 
@@ -32,14 +33,13 @@ static APP_VERSION: LazyLock<Version> =
     LazyLock::new(|| Version::from_str(mullvad_version::VERSION).unwrap());
 static IS_DEV_BUILD: LazyLock<bool> = LazyLock::new(|| APP_VERSION.is_dev());
 
-const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(15);
-
 /// Wait this long until next check after a successful check
 const UPDATE_INTERVAL: Duration = Duration::from_secs(60 * 60 * 24);
 /// Wait this long until next try if an update failed
 const UPDATE_INTERVAL_ERROR: Duration = Duration::from_secs(60 * 60 * 6);
-/// Retry strategy for `GetVersionInfo`.
-const IMMEDIATE_RETRY_STRATEGY: ConstantInterval = ConstantInterval::new(Duration::ZERO, Some(3));
+/// Timeout and retry policy for `GetVersionInfo`.
+const IMMEDIATE_RETRY_POLICY: RetryPolicy =
+    RetryPolicy::INTERACTIVE.timeout(Duration::from_secs(15));
 
 #[cfg(target_os = "linux")]
 const PLATFORM: &str = "linux";
@@ -152,7 +152,7 @@ impl VersionUpdaterHandle {
 
 impl VersionUpdater {
     pub async fn spawn(
-        mut api_handle: MullvadRestHandle,
+        api_handle: MullvadRestHandle,
         availability_handle: ApiAvailability,
         cache_dir: PathBuf,
         update_sender: DaemonEventSender<AppVersionInfo>,
@@ -163,8 +163,8 @@ impl VersionUpdater {
 
         let (tx, rx) = mpsc::channel(1);
 
-        api_handle.factory = api_handle.factory.default_timeout(DOWNLOAD_TIMEOUT);
-        let version_proxy = AppVersionProxy::new(api_handle);
+        let version_proxy =
+            AppVersionProxy::new(api_handle.with_retry_policy(&IMMEDIATE_RETRY_POLICY));
         let cache_path = cache_dir.join(VERSION_INFO_FILENAME);
         let platform_version = talpid_platform_metadata::short_version();
 
@@ -440,11 +440,7 @@ fn do_version_check(api: ApiContext) -> BoxFuture<'static, Result<AppVersionResp
         }
     };
 
-    Box::pin(retry_future(
-        download_future_factory,
-        should_retry_immediate,
-        IMMEDIATE_RETRY_STRATEGY,
-    ))
+    Box::pin(IMMEDIATE_RETRY_POLICY.retry(download_future_factory, should_retry_immediate))
 }
 
 /// Query the API for the latest [AppVersionInfo].
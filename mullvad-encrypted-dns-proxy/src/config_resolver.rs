@@ -2,7 +2,12 @@
 //!
 use crate::config;
 use core::fmt;
-use hickory_resolver::{config::*, error::ResolveError, TokioAsyncResolver};
+use hickory_resolver::{
+    config::*,
+    error::ResolveError,
+    name_server::{ConnectionProvider, GenericConnector, RuntimeProvider},
+    AsyncResolver, TokioAsyncResolver,
+};
 use rustls::ClientConfig;
 use std::{net::IpAddr, sync::Arc, time::Duration};
 use tokio::time::error::Elapsed;
@@ -68,6 +73,30 @@ pub async fn resolve_configs(
     resolvers: &[Nameserver],
     domain: &str,
 ) -> Result<Vec<config::ProxyConfig>, Error> {
+    let nameservers = doh_resolver_config(resolvers);
+    let mut resolver_config: ResolverOpts = Default::default();
+
+    resolver_config.timeout = Duration::from_secs(5);
+    resolve_config_with_resolverconfig(nameservers, resolver_config, domain, DEFAULT_TIMEOUT).await
+}
+
+/// Like [resolve_configs], but connects to the resolvers using `runtime`. This can be used to reach
+/// the resolvers through a proxy.
+pub async fn resolve_configs_with_runtime<R: RuntimeProvider>(
+    resolvers: &[Nameserver],
+    domain: &str,
+    runtime: R,
+) -> Result<Vec<config::ProxyConfig>, Error> {
+    let nameservers = doh_resolver_config(resolvers);
+    let mut resolver_config: ResolverOpts = Default::default();
+
+    resolver_config.timeout = Duration::from_secs(5);
+    let resolver = AsyncResolver::new(nameservers, resolver_config, GenericConnector::new(runtime));
+    lookup_configs(resolver, domain, DEFAULT_TIMEOUT).await
+}
+
+/// Returns a resolver config that sends all queries over DoH to the given `resolvers`.
+pub fn doh_resolver_config(resolvers: &[Nameserver]) -> ResolverConfig {
     let mut nameservers = ResolverConfig::new();
     for resolver in resolvers.iter() {
         let ns_config_group = NameServerConfigGroup::from_ips_https(
@@ -83,10 +112,7 @@ pub async fn resolve_configs(
     }
 
     nameservers.set_tls_client_config(Arc::new(client_config_tls12()));
-    let mut resolver_config: ResolverOpts = Default::default();
-
-    resolver_config.timeout = Duration::from_secs(5);
-    resolve_config_with_resolverconfig(nameservers, resolver_config, domain, DEFAULT_TIMEOUT).await
+    nameservers
 }
 
 pub async fn resolve_config_with_resolverconfig(
@@ -96,6 +122,14 @@ pub async fn resolve_config_with_resolverconfig(
     timeout: Duration,
 ) -> Result<Vec<config::ProxyConfig>, Error> {
     let resolver = TokioAsyncResolver::tokio(resolver_config, options);
+    lookup_configs(resolver, domain, timeout).await
+}
+
+async fn lookup_configs<P: ConnectionProvider>(
+    resolver: AsyncResolver<P>,
+    domain: &str,
+    timeout: Duration,
+) -> Result<Vec<config::ProxyConfig>, Error> {
     let lookup = tokio::time::timeout(timeout, resolver.ipv6_lookup(domain))
         .await
         .map_err(Error::Timeout)?
@@ -3,8 +3,12 @@
 
 use std::collections::HashSet;
 
+use hickory_resolver::name_server::RuntimeProvider;
+
 use crate::config::ProxyConfig;
-use crate::config_resolver::{self, resolve_default_config};
+use crate::config_resolver::{
+    self, default_resolvers, resolve_configs_with_runtime, resolve_default_config,
+};
 
 /// Keep track of fetched proxy configurations.
 ///
@@ -60,7 +64,26 @@ impl EncryptedDnsProxyState {
 
     /// Fetch a config from `domain`, but error out only when no existing configuration was there.
     pub async fn fetch_configs(&mut self, domain: &str) -> Result<(), FetchConfigError> {
-        match resolve_default_config(domain).await {
+        let result = resolve_default_config(domain).await;
+        self.update_configs(result)
+    }
+
+    /// Like [`Self::fetch_configs`], but connects to the DoH resolvers using `runtime`. This can
+    /// be used to reach the resolvers through a proxy.
+    pub async fn fetch_configs_with_runtime<R: RuntimeProvider>(
+        &mut self,
+        domain: &str,
+        runtime: R,
+    ) -> Result<(), FetchConfigError> {
+        let result = resolve_configs_with_runtime(&default_resolvers(), domain, runtime).await;
+        self.update_configs(result)
+    }
+
+    fn update_configs(
+        &mut self,
+        result: Result<Vec<ProxyConfig>, config_resolver::Error>,
+    ) -> Result<(), FetchConfigError> {
+        match result {
             Ok(new_configs) => {
                 self.configurations = HashSet::from_iter(new_configs.into_iter());
             }
@@ -92,7 +92,7 @@ async fn do_request<F, T>(
 ) -> Result<SwiftMullvadApiResponse, rest::Error>
 where
     F: Fn() -> T,
-    T: Future<Output = Result<rest::Response<hyper::body::Incoming>, rest::Error>>,
+    T: Future<Output = Result<rest::Response<rest::ResponseBody>, rest::Error>>,
 {
     let response = retry_request(retry_strategy, future_factory).await?;
     SwiftMullvadApiResponse::with_body(response).await
@@ -20,7 +20,7 @@ pub struct SwiftMullvadApiResponse {
 }
 
 impl SwiftMullvadApiResponse {
-    pub async fn with_body(response: Response<hyper::body::Incoming>) -> Result<Self, rest::Error> {
+    pub async fn with_body(response: Response<rest::ResponseBody>) -> Result<Self, rest::Error> {
         let maybe_etag = RelayListProxy::extract_etag(&response);
 
         let status_code: u16 = response.status().into();
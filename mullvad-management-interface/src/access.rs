@@ -0,0 +1,171 @@
+//! Authorization of management interface clients. By default, every client that can connect has
+//! full control of the daemon. The daemon can be configured to give some clients observe-only
+//! access, which only lets them read the state and settings and listen for events. This allows
+//! e.g. status bar widgets to run without being able to disconnect the tunnel or log out.
+//!
+//! On Unix, clients are told apart by their peer credentials. If `MULLVAD_MANAGEMENT_CONTROL_GROUP`
+//! is set, only root and members of that group have full control.
+//!
+//! On Windows, if `MULLVAD_MANAGEMENT_CONTROL_TOKEN` is set, only requests that carry the token in
+//! the [`CONTROL_TOKEN_METADATA`] metadata are given full control.
+
+use futures::future::{self, Either, Ready};
+use std::{
+    env,
+    sync::LazyLock,
+    task::{Context, Poll},
+};
+use tonic::{body::BoxBody, codegen::http, Status};
+use tower::Service;
+
+#[cfg(unix)]
+static MULLVAD_MANAGEMENT_CONTROL_GROUP: LazyLock<Option<String>> =
+    LazyLock::new(|| env::var("MULLVAD_MANAGEMENT_CONTROL_GROUP").ok());
+
+#[cfg(windows)]
+static MULLVAD_MANAGEMENT_CONTROL_TOKEN: LazyLock<Option<String>> =
+    LazyLock::new(|| env::var("MULLVAD_MANAGEMENT_CONTROL_TOKEN").ok());
+
+/// Metadata key of the token that gives a request full control.
+#[cfg(windows)]
+pub const CONTROL_TOKEN_METADATA: &str = "mullvad-control-token";
+
+/// RPCs that observe-only clients may call. None of them change anything.
+const OBSERVE_ONLY_METHODS: &[&str] = &[
+    "Handshake",
+    "EventsListen",
+    "GetTunnelState",
+    "GetConnectionTimings",
+    "GetTrafficStats",
+    "TunnelStatsListen",
+    "GetDataUsage",
+    "GetCurrentVersion",
+    "GetVersionInfo",
+    "IsPerformingPostUpgrade",
+    "GetRelayLocations",
+    "GetSettings",
+    "GetFeatureIndicators",
+];
+
+/// What a client of the management interface is allowed to do. The access level of each request
+/// is available in its extensions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessLevel {
+    /// The client may call any RPC.
+    Full,
+    /// The client may only call RPCs that do not change anything.
+    ObserveOnly,
+}
+
+impl AccessLevel {
+    fn allows(self, method: &str) -> bool {
+        self == AccessLevel::Full || OBSERVE_ONLY_METHODS.contains(&method)
+    }
+}
+
+/// Return the access level of the client connected over `stream`.
+#[cfg(unix)]
+pub(crate) fn peer_access(stream: &tokio::net::UnixStream) -> AccessLevel {
+    let Some(group_name) = &*MULLVAD_MANAGEMENT_CONTROL_GROUP else {
+        return AccessLevel::Full;
+    };
+    let credentials = match stream.peer_cred() {
+        Ok(credentials) => credentials,
+        Err(error) => {
+            log::error!(
+                "Failed to obtain the credentials of a management interface client: {error}"
+            );
+            return AccessLevel::ObserveOnly;
+        }
+    };
+    if has_control(credentials.uid(), credentials.gid(), group_name) {
+        AccessLevel::Full
+    } else {
+        log::debug!(
+            "Management interface client with UID {} has observe-only access",
+            credentials.uid()
+        );
+        AccessLevel::ObserveOnly
+    }
+}
+
+/// Returns whether the user is root or a member of the control group, either as its primary group
+/// or as a supplementary group.
+#[cfg(unix)]
+fn has_control(uid: u32, gid: u32, group_name: &str) -> bool {
+    use nix::unistd::{Group, Uid, User};
+
+    if uid == 0 {
+        return true;
+    }
+    let group = match Group::from_name(group_name) {
+        Ok(Some(group)) => group,
+        Ok(None) => {
+            log::error!("Management interface control group not found: {group_name}");
+            return false;
+        }
+        Err(error) => {
+            log::error!("Failed to obtain management interface control group: {error}");
+            return false;
+        }
+    };
+    if group.gid.as_raw() == gid {
+        return true;
+    }
+    match User::from_uid(Uid::from_raw(uid)) {
+        Ok(Some(user)) => group.mem.contains(&user.name),
+        _ => false,
+    }
+}
+
+/// Return the access level of `request`, given the access level of its connection.
+fn request_access<B>(request: &http::Request<B>) -> AccessLevel {
+    #[cfg(windows)]
+    if let Some(token) = &*MULLVAD_MANAGEMENT_CONTROL_TOKEN {
+        let provided = request
+            .headers()
+            .get(CONTROL_TOKEN_METADATA)
+            .and_then(|value| value.to_str().ok());
+        return if provided == Some(token.as_str()) {
+            AccessLevel::Full
+        } else {
+            AccessLevel::ObserveOnly
+        };
+    }
+    request
+        .extensions()
+        .get::<AccessLevel>()
+        .copied()
+        .unwrap_or(AccessLevel::Full)
+}
+
+/// Rejects requests for RPCs that the client is not allowed to call, and adds the access level to
+/// the extensions of the requests that are let through.
+#[derive(Debug, Clone)]
+pub(crate) struct AccessControl<S>(pub S);
+
+impl<S, B> Service<http::Request<B>> for AccessControl<S>
+where
+    S: Service<http::Request<B>, Response = http::Response<BoxBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Either<S::Future, Ready<Result<Self::Response, Self::Error>>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        let access = request_access(&request);
+        // The path is "/<package>.<service>/<method>"
+        let method = request.uri().path().rsplit('/').next().unwrap_or_default();
+        if !access.allows(method) {
+            log::debug!("Denied {method} to an observe-only management interface client");
+            let status = Status::permission_denied("the client only has observe-only access");
+            return Either::Right(future::ready(Ok(status.into_http())));
+        }
+        request.extensions_mut().insert(access);
+        Either::Left(self.0.call(request))
+    }
+}
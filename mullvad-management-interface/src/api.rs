@@ -0,0 +1,126 @@
+//! Versioning of the management interface. Clients call the `Handshake` RPC to learn which version
+//! of the interface the daemon implements and which optional features it supports, rather than
+//! discovering it by calls failing.
+
+use std::{fmt, str::FromStr};
+
+/// Version of the management interface. It is increased whenever RPCs or fields are added or
+/// change meaning.
+pub const API_VERSION: u32 = 2;
+
+/// Oldest client version of the management interface that the daemon accepts.
+pub const MIN_SUPPORTED_API_VERSION: u32 = 1;
+
+/// Version reported for daemons that predate the `Handshake` RPC.
+pub const LEGACY_API_VERSION: u32 = 0;
+
+/// An optional feature of the daemon. Which features are available depends on the platform and on
+/// how the daemon was built.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability {
+    /// Lockdown mode can be enabled and paused.
+    LockdownMode,
+    /// Individual processes can be excluded from the tunnel.
+    SplitTunnelProcesses,
+    /// Applications can be excluded from the tunnel.
+    SplitTunnelApps,
+    /// DAITA can be enabled.
+    Daita,
+    /// Tunnel statistics can be streamed using `TunnelStatsListen`.
+    TunnelStatsStream,
+    /// The applied tunnel configuration can be dumped using `GetTunnelDiagnostics`.
+    TunnelDiagnostics,
+    /// Several devices can be revoked at once using `RemoveDevices`.
+    BulkDeviceRemoval,
+}
+
+impl Capability {
+    const ALL: [Capability; 7] = [
+        Capability::LockdownMode,
+        Capability::SplitTunnelProcesses,
+        Capability::SplitTunnelApps,
+        Capability::Daita,
+        Capability::TunnelStatsStream,
+        Capability::TunnelDiagnostics,
+        Capability::BulkDeviceRemoval,
+    ];
+
+    /// Name of the capability in the `Handshake` response.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Capability::LockdownMode => "lockdown_mode",
+            Capability::SplitTunnelProcesses => "split_tunnel_processes",
+            Capability::SplitTunnelApps => "split_tunnel_apps",
+            Capability::Daita => "daita",
+            Capability::TunnelStatsStream => "tunnel_stats_stream",
+            Capability::TunnelDiagnostics => "tunnel_diagnostics",
+            Capability::BulkDeviceRemoval => "bulk_device_removal",
+        }
+    }
+
+    /// Capabilities of a daemon built for the current platform.
+    pub fn supported() -> Vec<Capability> {
+        Self::ALL
+            .into_iter()
+            .filter(|capability| match capability {
+                Capability::LockdownMode => cfg!(not(target_os = "android")),
+                Capability::SplitTunnelProcesses => cfg!(target_os = "linux"),
+                Capability::SplitTunnelApps => {
+                    cfg!(any(target_os = "windows", target_os = "macos"))
+                }
+                Capability::Daita => cfg!(daita),
+                Capability::TunnelStatsStream
+                | Capability::TunnelDiagnostics
+                | Capability::BulkDeviceRemoval => true,
+            })
+            .collect()
+    }
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Capability {
+    type Err = UnknownCapability;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|capability| capability.as_str() == s)
+            .ok_or_else(|| UnknownCapability(s.to_owned()))
+    }
+}
+
+/// A capability that this version of the management interface does not know about.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Unknown capability: {0}")]
+pub struct UnknownCapability(pub String);
+
+/// What the daemon reported about itself in the handshake.
+#[derive(Debug, Clone)]
+pub struct DaemonApi {
+    /// Version of the management interface that the daemon implements.
+    pub api_version: u32,
+    /// Version of the daemon, unless it predates the handshake.
+    pub daemon_version: Option<String>,
+    /// Capabilities of the daemon. Capabilities unknown to this client are left out.
+    pub capabilities: Vec<Capability>,
+    /// Whether this client may only call RPCs that do not change anything.
+    pub observe_only: bool,
+}
+
+impl DaemonApi {
+    /// Returns whether the daemon supports `capability`.
+    pub fn supports(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Returns whether the daemon predates the handshake, in which case it is not known which
+    /// capabilities it has.
+    pub fn is_legacy(&self) -> bool {
+        self.api_version == LEGACY_API_VERSION
+    }
+}
@@ -1,5 +1,7 @@
 //! Client that returns and takes mullvad types as arguments instead of prost-generated types
 
+#[cfg(not(target_os = "android"))]
+use crate::api;
 use crate::types;
 #[cfg(not(target_os = "android"))]
 use futures::{Stream, StreamExt};
@@ -7,6 +9,8 @@ use futures::{Stream, StreamExt};
 use mullvad_types::wireguard::DaitaSettings;
 use mullvad_types::{
     access_method::AccessMethodSetting,
+    account::AccountExpiryEvent,
+    conflicting_software::ConflictingSoftware,
     device::{DeviceEvent, RemoveDeviceEvent},
     relay_list::RelayList,
     settings::Settings,
@@ -18,19 +22,50 @@ use mullvad_types::{
 use mullvad_types::{
     access_method::{self, AccessMethod},
     account::{AccountData, AccountNumber, VoucherSubmission},
+    allowed_incoming::AllowedIncoming,
     custom_list::{CustomList, Id},
-    device::{Device, DeviceId, DeviceState},
+    custom_relay::CustomRelay,
+    custom_route::CustomRoute,
+    data_usage::DataUsage,
+    device::{Device, DeviceId, DeviceRemovalResult, DeviceState},
+    excluded_locations::ExcludedLocations,
     features::FeatureIndicators,
+    firewall_exception::FirewallException,
+    lan_sharing::AllowedLan,
+    pending_request::{PendingRequest, PendingRequestId},
     relay_constraints::{
         BridgeSettings, BridgeState, ObfuscationSettings, RelayOverride, RelaySettings,
     },
-    settings::DnsOptions,
+    settings::{DnsOptions, MetricsSettings},
+    state_hooks::HookEvent,
+    states::TunnelStats,
+    trusted_network::{Network, NetworkMatch, TrustedNetworkRule, TrustedNetworks},
     wireguard::{PublicKey, QuantumResistantState, RotationInterval},
 };
 #[cfg(not(target_os = "android"))]
-use std::{path::Path, str::FromStr};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+use talpid_types::{
+    dns::DnsInterference,
+    net::wireguard::{
+        BackendFallback, ConnectivityCheckOptions, NegotiationRetry, NegotiationRetryPolicy,
+        QuantumResistantKem,
+    },
+};
 #[cfg(target_os = "windows")]
-use talpid_types::split_tunnel::ExcludedProcess;
+use talpid_types::{
+    drivers::{Driver, DriverRepairResult, DriverStatus},
+    split_tunnel::ExcludedProcess,
+};
+#[cfg(not(target_os = "android"))]
+use talpid_types::{
+    net::wireguard::Backend,
+    tunnel::{ConnectionTimings, TrafficStats},
+};
 #[cfg(not(target_os = "android"))]
 use tonic::{Code, Status};
 
@@ -51,6 +86,11 @@ pub enum DaemonEvent {
     Device(DeviceEvent),
     RemoveDevice(RemoveDeviceEvent),
     NewAccessMethod(AccessMethodSetting),
+    ConflictingSoftware(Vec<ConflictingSoftware>),
+    DnsInterference(DnsInterference),
+    WireguardBackendFallback(BackendFallback),
+    NegotiationRetry(NegotiationRetry),
+    AccountExpiry(AccountExpiryEvent),
 }
 
 impl TryFrom<types::daemon_event::Event> for DaemonEvent {
@@ -81,6 +121,27 @@ impl TryFrom<types::daemon_event::Event> for DaemonEvent {
                     .map(DaemonEvent::NewAccessMethod)
                     .map_err(Error::InvalidResponse)
             }
+            types::daemon_event::Event::ConflictingSoftware(list) => {
+                Vec::<ConflictingSoftware>::try_from(list)
+                    .map(DaemonEvent::ConflictingSoftware)
+                    .map_err(Error::InvalidResponse)
+            }
+            types::daemon_event::Event::DnsInterference(interference) => Ok(
+                DaemonEvent::DnsInterference(DnsInterference::from(interference)),
+            ),
+            types::daemon_event::Event::WireguardBackendFallback(fallback) => {
+                BackendFallback::try_from(fallback)
+                    .map(DaemonEvent::WireguardBackendFallback)
+                    .map_err(Error::InvalidResponse)
+            }
+            types::daemon_event::Event::NegotiationRetry(retry) => {
+                NegotiationRetry::try_from(retry)
+                    .map(DaemonEvent::NegotiationRetry)
+                    .map_err(Error::InvalidResponse)
+            }
+            types::daemon_event::Event::AccountExpiry(event) => AccountExpiryEvent::try_from(event)
+                .map(DaemonEvent::AccountExpiry)
+                .map_err(Error::InvalidResponse),
         }
     }
 }
@@ -123,6 +184,15 @@ impl MullvadProxyClient {
             .into_inner())
     }
 
+    pub async fn pause_tunnel(&mut self) -> Result<bool> {
+        Ok(self
+            .0
+            .pause_tunnel(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner())
+    }
+
     pub async fn get_tunnel_state(&mut self) -> Result<TunnelState> {
         let state = self
             .0
@@ -133,6 +203,69 @@ impl MullvadProxyClient {
         TunnelState::try_from(state).map_err(Error::InvalidResponse)
     }
 
+    /// Return the timings of the most recently established connection, or `None` if the daemon
+    /// has not connected since it was started.
+    pub async fn get_connection_timings(&mut self) -> Result<Option<ConnectionTimings>> {
+        let timings = match self.0.get_connection_timings(()).await {
+            Ok(timings) => timings.into_inner(),
+            Err(status) if status.code() == Code::NotFound => return Ok(None),
+            Err(status) => return Err(Error::Rpc(status)),
+        };
+        ConnectionTimings::try_from(timings)
+            .map(Some)
+            .map_err(Error::InvalidResponse)
+    }
+
+    /// Return the traffic statistics of the current tunnel, or `None` if it is not connected.
+    pub async fn get_traffic_stats(&mut self) -> Result<Option<TrafficStats>> {
+        let stats = match self.0.get_traffic_stats(()).await {
+            Ok(stats) => stats.into_inner(),
+            Err(status) if status.code() == Code::NotFound => return Ok(None),
+            Err(status) => return Err(Error::Rpc(status)),
+        };
+        TrafficStats::try_from(stats)
+            .map(Some)
+            .map_err(Error::InvalidResponse)
+    }
+
+    /// Receive statistics about the current tunnel every `interval`, while it is connected.
+    pub async fn tunnel_stats_listen<'a>(
+        &mut self,
+        interval: Duration,
+    ) -> Result<impl Stream<Item = Result<TunnelStats>> + 'a> {
+        let interval = types::Duration::try_from(interval).map_err(|_| Error::DurationTooLarge)?;
+        let listener = self
+            .0
+            .tunnel_stats_listen(interval)
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+
+        Ok(listener.map(|item| {
+            TunnelStats::try_from(item.map_err(Error::Rpc)?).map_err(Error::InvalidResponse)
+        }))
+    }
+
+    /// Return the applied tunnel configuration as a JSON document.
+    pub async fn get_tunnel_diagnostics(&mut self, include_private_key: bool) -> Result<String> {
+        Ok(self
+            .0
+            .get_tunnel_diagnostics(include_private_key)
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner())
+    }
+
+    pub async fn get_data_usage(&mut self) -> Result<DataUsage> {
+        let usage = self
+            .0
+            .get_data_usage(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+        DataUsage::try_from(usage).map_err(Error::InvalidResponse)
+    }
+
     pub async fn events_listen<'a>(
         &mut self,
     ) -> Result<impl Stream<Item = Result<DaemonEvent>> + 'a> {
@@ -176,6 +309,41 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    /// Exchange management interface versions with the daemon, and find out which optional
+    /// features it supports. Daemons that predate the handshake are reported as
+    /// [`api::LEGACY_API_VERSION`], without any capabilities.
+    pub async fn handshake(&mut self) -> Result<api::DaemonApi> {
+        let request = types::HandshakeRequest {
+            api_version: api::API_VERSION,
+        };
+        let response = match self.0.handshake(request).await {
+            Ok(response) => response.into_inner(),
+            Err(status) if status.code() == Code::Unimplemented => {
+                return Ok(api::DaemonApi {
+                    api_version: api::LEGACY_API_VERSION,
+                    daemon_version: None,
+                    capabilities: vec![],
+                    observe_only: false,
+                });
+            }
+            Err(status) if status.code() == Code::FailedPrecondition => {
+                return Err(Error::UnsupportedApiVersion(api::API_VERSION));
+            }
+            Err(status) => return Err(Error::Rpc(status)),
+        };
+        Ok(api::DaemonApi {
+            api_version: response.api_version,
+            daemon_version: Some(response.daemon_version),
+            // Capabilities added by newer daemons are unknown to this client
+            capabilities: response
+                .capabilities
+                .iter()
+                .filter_map(|capability| capability.parse().ok())
+                .collect(),
+            observe_only: response.observe_only,
+        })
+    }
+
     pub async fn get_current_version(&mut self) -> Result<String> {
         Ok(self
             .0
@@ -205,6 +373,27 @@ impl MullvadProxyClient {
         mullvad_types::relay_list::RelayList::try_from(list).map_err(Error::InvalidResponse)
     }
 
+    pub async fn export_relay_list(&mut self) -> Result<String> {
+        let blob = self.0.export_relay_list(()).await.map_err(Error::Rpc)?;
+        Ok(blob.into_inner())
+    }
+
+    pub async fn import_relay_list(&mut self, blob: String) -> Result<()> {
+        self.0.import_relay_list(blob).await.map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    /// Read the relay list overrides file again and apply it. Returns the overrides that could
+    /// not be applied.
+    pub async fn reload_relay_list_overrides(&mut self) -> Result<Vec<String>> {
+        let result = self
+            .0
+            .reload_relay_list_overrides(())
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(result.into_inner().errors)
+    }
+
     pub async fn get_api_access_methods(&mut self) -> Result<Vec<AccessMethodSetting>> {
         let access_method_settings = self
             .0
@@ -244,6 +433,19 @@ impl MullvadProxyClient {
             })
     }
 
+    /// Returns how well each API access method has worked, keyed by access method ID.
+    pub async fn get_api_access_method_stats(
+        &mut self,
+    ) -> Result<HashMap<access_method::Id, access_method::AccessMethodStats>> {
+        let stats = self
+            .0
+            .get_api_access_method_stats(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+        HashMap::try_from(stats).map_err(Error::InvalidResponse)
+    }
+
     pub async fn test_api_access_method(&mut self, id: access_method::Id) -> Result<bool> {
         let result = self
             .0
@@ -273,6 +475,23 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    /// Download a new relay list and wait until it has been applied.
+    pub async fn update_relay_locations_and_wait(&mut self) -> Result<()> {
+        self.0
+            .update_relay_locations_and_wait(())
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_relay_list_update_interval(&mut self, minutes: u16) -> Result<()> {
+        self.0
+            .set_relay_list_update_interval(u32::from(minutes))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     pub async fn set_relay_settings(&mut self, update: RelaySettings) -> Result<()> {
         let update = types::RelaySettings::from(update);
         self.0
@@ -326,6 +545,14 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn set_lan_sharing(&mut self, allow_lan: AllowedLan) -> Result<()> {
+        self.0
+            .set_lan_sharing(types::LanSharing::from(allow_lan))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     pub async fn set_show_beta_releases(&mut self, state: bool) -> Result<()> {
         self.0
             .set_show_beta_releases(state)
@@ -334,6 +561,19 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn set_prefer_low_latency_relays(&mut self, state: bool) -> Result<()> {
+        self.0
+            .set_prefer_low_latency_relays(state)
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_sticky_relays(&mut self, state: bool) -> Result<()> {
+        self.0.set_sticky_relays(state).await.map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     pub async fn set_block_when_disconnected(&mut self, state: bool) -> Result<()> {
         self.0
             .set_block_when_disconnected(state)
@@ -342,6 +582,69 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    /// Stop blocking traffic while disconnected for `duration`, even though lockdown mode is
+    /// enabled.
+    pub async fn pause_lockdown_mode(&mut self, duration: Duration) -> Result<()> {
+        let duration = types::Duration::try_from(duration).map_err(|_| Error::DurationTooLarge)?;
+        self.0
+            .pause_lockdown_mode(duration)
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    /// Allow traffic to the login page of a detected captive portal for `duration`.
+    pub async fn allow_captive_portal_login(&mut self, duration: Duration) -> Result<()> {
+        let duration = types::Duration::try_from(duration).map_err(|_| Error::DurationTooLarge)?;
+        self.0
+            .allow_captive_portal_login(duration)
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_block_all(&mut self, state: bool) -> Result<()> {
+        self.0.set_block_all(state).await.map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_firewall_exceptions(
+        &mut self,
+        exceptions: Vec<FirewallException>,
+    ) -> Result<()> {
+        self.0
+            .set_firewall_exceptions(types::FirewallExceptions::from(exceptions))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_allowed_incoming(&mut self, rules: Vec<AllowedIncoming>) -> Result<()> {
+        self.0
+            .set_allowed_incoming(types::AllowedIncomingRules::from(rules))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    /// Set the executable to run when the tunnel enters the state given by `event`, or remove it
+    /// if `path` is `None`.
+    pub async fn set_state_hook(&mut self, event: HookEvent, path: Option<PathBuf>) -> Result<()> {
+        self.0
+            .set_state_hook(types::StateHook::from((event, path)))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_metrics_settings(&mut self, metrics: MetricsSettings) -> Result<()> {
+        self.0
+            .set_metrics_settings(types::MetricsSettings::from(&metrics))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     pub async fn set_auto_connect(&mut self, state: bool) -> Result<()> {
         self.0.set_auto_connect(state).await.map_err(Error::Rpc)?;
         Ok(())
@@ -363,11 +666,145 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn set_wireguard_persistent_keepalive(
+        &mut self,
+        interval: Option<u16>,
+    ) -> Result<()> {
+        self.0
+            .set_wireguard_persistent_keepalive(interval.map(u32::from).unwrap_or(0))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_wireguard_worker_threads(&mut self, threads: Option<u32>) -> Result<()> {
+        self.0
+            .set_wireguard_worker_threads(threads.unwrap_or(0))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_wireguard_outgoing_interface(
+        &mut self,
+        interface: Option<String>,
+    ) -> Result<()> {
+        self.0
+            .set_wireguard_outgoing_interface(interface.unwrap_or_default())
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_wireguard_backend(&mut self, backend: Option<Backend>) -> Result<()> {
+        let backend = backend.map(|backend| i32::from(types::WireguardBackend::from(backend)));
+        self.0
+            .set_wireguard_backend(types::WireguardBackendSetting { backend })
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_tunnel_fwmark(&mut self, fwmark: Option<u32>) -> Result<()> {
+        self.0
+            .set_tunnel_fwmark(fwmark.unwrap_or(0))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn set_nftables_settings(
+        &mut self,
+        nftables: mullvad_types::settings::NftablesSettings,
+    ) -> Result<()> {
+        self.0
+            .set_nftables_settings(types::NftablesSettings::from(&nftables))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn set_routing_settings(
+        &mut self,
+        routing: mullvad_types::settings::RoutingSettings,
+    ) -> Result<()> {
+        self.0
+            .set_routing_settings(types::RoutingSettings::from(&routing))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    pub async fn set_wfp_sublayer_weight(&mut self, weight: Option<u16>) -> Result<()> {
+        self.0
+            .set_wfp_sublayer_weight(weight.map(u32::from).unwrap_or(0))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "macos")]
+    pub async fn set_pf_anchor(&mut self, anchor: Option<String>) -> Result<()> {
+        self.0
+            .set_pf_anchor(anchor.unwrap_or_default())
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn set_dns_backends(
+        &mut self,
+        backends: &[talpid_types::dns::DnsBackend],
+    ) -> Result<()> {
+        self.0
+            .set_dns_backends(types::DnsBackends::from(backends))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "android"))]
+    pub async fn set_offline_monitor_backend(
+        &mut self,
+        backend: talpid_types::net::OfflineMonitorBackend,
+    ) -> Result<()> {
+        self.0
+            .set_offline_monitor_backend(types::OfflineMonitorBackend::from(backend))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     pub async fn set_enable_ipv6(&mut self, state: bool) -> Result<()> {
         self.0.set_enable_ipv6(state).await.map_err(Error::Rpc)?;
         Ok(())
     }
 
+    pub async fn set_wireguard_ipv6_only(&mut self, state: bool) -> Result<()> {
+        self.0
+            .set_wireguard_ipv6_only(state)
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_block_ipv6(&mut self, state: bool) -> Result<()> {
+        self.0.set_block_ipv6(state).await.map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_custom_routes(&mut self, routes: Vec<CustomRoute>) -> Result<()> {
+        self.0
+            .set_custom_routes(types::CustomRoutes::from(routes))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     pub async fn set_quantum_resistant_tunnel(
         &mut self,
         state: QuantumResistantState,
@@ -380,6 +817,39 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    pub async fn set_quantum_resistant_kem(&mut self, kem: QuantumResistantKem) -> Result<()> {
+        let kem = types::QuantumResistantKem::from(kem);
+        self.0
+            .set_quantum_resistant_kem(kem)
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_negotiation_retry_policy(
+        &mut self,
+        policy: NegotiationRetryPolicy,
+    ) -> Result<()> {
+        let policy = types::NegotiationRetryPolicy::from(policy);
+        self.0
+            .set_negotiation_retry_policy(policy)
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn set_connectivity_check_options(
+        &mut self,
+        options: ConnectivityCheckOptions,
+    ) -> Result<()> {
+        let options = types::ConnectivityCheckOptions::from(options);
+        self.0
+            .set_connectivity_check_options(options)
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     #[cfg(daita)]
     pub async fn set_enable_daita(&mut self, value: bool) -> Result<()> {
         self.0.set_enable_daita(value).await.map_err(Error::Rpc)?;
@@ -534,6 +1004,79 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    /// Remove the given devices, or every device except the current one if `all_except_current`
+    /// is set. Devices that fail to be removed are listed in the result rather than returned as
+    /// an error.
+    pub async fn remove_devices(
+        &mut self,
+        account: AccountNumber,
+        device_ids: Vec<DeviceId>,
+        all_except_current: bool,
+    ) -> Result<DeviceRemovalResult> {
+        let result = self
+            .0
+            .remove_devices(types::DevicesRemoval {
+                account_number: account,
+                device_ids,
+                all_except_current,
+            })
+            .await
+            .map_err(map_device_error)?
+            .into_inner();
+        Ok(DeviceRemovalResult::from(result))
+    }
+
+    pub async fn queue_voucher_submission(&mut self, voucher: String) -> Result<PendingRequest> {
+        let request = self
+            .0
+            .queue_voucher_submission(voucher)
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+        PendingRequest::try_from(request).map_err(Error::InvalidResponse)
+    }
+
+    pub async fn queue_device_removal(
+        &mut self,
+        account: AccountNumber,
+        device_id: DeviceId,
+    ) -> Result<PendingRequest> {
+        let request = self
+            .0
+            .queue_device_removal(types::DeviceRemoval {
+                account_number: account,
+                device_id,
+            })
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+        PendingRequest::try_from(request).map_err(Error::InvalidResponse)
+    }
+
+    pub async fn get_pending_requests(&mut self) -> Result<Vec<PendingRequest>> {
+        let list = self
+            .0
+            .get_pending_requests(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+        list.requests
+            .into_iter()
+            .map(|request| PendingRequest::try_from(request).map_err(Error::InvalidResponse))
+            .collect()
+    }
+
+    pub async fn cancel_pending_request(&mut self, id: PendingRequestId) -> Result<()> {
+        self.0
+            .cancel_pending_request(id)
+            .await
+            .map_err(|status| match status.code() {
+                Code::NotFound => Error::PendingRequestNotFound,
+                _other => Error::Rpc(status),
+            })?;
+        Ok(())
+    }
+
     pub async fn set_wireguard_rotation_interval(
         &mut self,
         interval: RotationInterval,
@@ -605,6 +1148,81 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    /// Add a custom relay.
+    pub async fn add_custom_relay(&mut self, relay: CustomRelay) -> Result<()> {
+        self.0
+            .add_custom_relay(types::CustomRelay::from(relay))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    /// Remove the custom relay with the given name.
+    pub async fn remove_custom_relay(&mut self, name: String) -> Result<()> {
+        self.0.remove_custom_relay(name).await.map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    /// Save the current settings as a profile with the given name.
+    pub async fn save_profile(&mut self, name: String) -> Result<()> {
+        self.0.save_profile(name).await.map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    /// Remove the profile with the given name.
+    pub async fn remove_profile(&mut self, name: String) -> Result<()> {
+        self.0.remove_profile(name).await.map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    /// Switch to the settings stored in the profile with the given name.
+    pub async fn use_profile(&mut self, name: String) -> Result<()> {
+        self.0.use_profile(name).await.map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    /// Return the trusted network rules, and the network that the device is connected to if it
+    /// could be identified.
+    pub async fn get_trusted_networks(&mut self) -> Result<(TrustedNetworks, Option<Network>)> {
+        let networks = self
+            .0
+            .get_trusted_networks(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+        <(TrustedNetworks, Option<Network>)>::try_from(networks).map_err(Error::InvalidResponse)
+    }
+
+    /// Add a trusted network rule, replacing any existing rule for the same networks.
+    pub async fn set_trusted_network_rule(&mut self, rule: TrustedNetworkRule) -> Result<()> {
+        self.0
+            .set_trusted_network_rule(types::TrustedNetworkRule::from(rule))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    /// Remove the trusted network rule for the given networks.
+    pub async fn remove_trusted_network_rule(&mut self, network: NetworkMatch) -> Result<()> {
+        self.0
+            .remove_trusted_network_rule(types::NetworkMatch::from(network))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    /// Replace the locations that are excluded from relay selection.
+    pub async fn set_excluded_locations(
+        &mut self,
+        excluded_locations: ExcludedLocations,
+    ) -> Result<()> {
+        self.0
+            .set_excluded_locations(types::ExcludedLocations::from(excluded_locations))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     pub async fn add_access_method(
         &mut self,
         name: String,
@@ -699,6 +1317,41 @@ impl MullvadProxyClient {
         Ok(())
     }
 
+    #[cfg(target_os = "linux")]
+    pub async fn set_split_tunnel_mode(
+        &mut self,
+        mode: talpid_types::split_tunnel::SplitTunnelMode,
+    ) -> Result<()> {
+        self.0
+            .set_split_tunnel_mode(types::SplitTunnelMode::from(mode))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn set_split_tunnel_owners(
+        &mut self,
+        owners: &[talpid_types::split_tunnel::SplitTunnelOwner],
+    ) -> Result<()> {
+        self.0
+            .set_split_tunnel_owners(types::SplitTunnelOwners::from(owners))
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn set_split_tunnel_domains(&mut self, domains: &[String]) -> Result<()> {
+        self.0
+            .set_split_tunnel_domains(types::SplitTunnelDomains {
+                domains: domains.to_vec(),
+            })
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     pub async fn add_split_tunnel_app<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
         let path = path.as_ref().to_str().ok_or(Error::PathMustBeUtf8)?;
         self.0
@@ -748,8 +1401,74 @@ impl MullvadProxyClient {
             .collect::<Vec<_>>())
     }
 
+    pub async fn get_conflicting_software(&mut self) -> Result<Vec<ConflictingSoftware>> {
+        let list = self
+            .0
+            .get_conflicting_software(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+        Vec::<ConflictingSoftware>::try_from(list).map_err(Error::InvalidResponse)
+    }
+
     // check_volumes
 
+    #[cfg(target_os = "windows")]
+    pub async fn get_driver_status(&mut self) -> Result<Vec<DriverStatus>> {
+        self.0
+            .get_driver_status(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner()
+            .drivers
+            .into_iter()
+            .map(|status| DriverStatus::try_from(status).map_err(Error::InvalidResponse))
+            .collect()
+    }
+
+    #[cfg(target_os = "windows")]
+    pub async fn repair_driver(&mut self, driver: Driver) -> Result<DriverRepairResult> {
+        let result = self
+            .0
+            .repair_driver(types::DriverRepairRequest::from(driver))
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner();
+        DriverRepairResult::try_from(result).map_err(Error::InvalidResponse)
+    }
+
+    #[cfg(target_os = "windows")]
+    pub async fn get_wfp_diagnostics(&mut self) -> Result<String> {
+        Ok(self
+            .0
+            .get_wfp_diagnostics(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner())
+    }
+
+    #[cfg(target_os = "macos")]
+    pub async fn get_pf_diagnostics(&mut self) -> Result<String> {
+        Ok(self
+            .0
+            .get_pf_diagnostics(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner())
+    }
+
+    #[cfg(target_os = "linux")]
+    pub async fn get_active_dns_backend(&mut self) -> Result<talpid_types::dns::DnsBackend> {
+        let backend = self
+            .0
+            .get_active_dns_backend(())
+            .await
+            .map_err(Error::Rpc)?
+            .into_inner()
+            .backend;
+        types::try_dns_backend_from_i32(backend).map_err(Error::InvalidResponse)
+    }
+
     pub async fn apply_json_settings(&mut self, blob: String) -> Result<()> {
         self.0.apply_json_settings(blob).await.map_err(Error::Rpc)?;
         Ok(())
@@ -760,6 +1479,20 @@ impl MullvadProxyClient {
         Ok(blob.into_inner())
     }
 
+    pub async fn export_settings(&mut self, include_secrets: bool) -> Result<String> {
+        let blob = self
+            .0
+            .export_settings(include_secrets)
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(blob.into_inner())
+    }
+
+    pub async fn import_settings(&mut self, blob: String) -> Result<()> {
+        self.0.import_settings(blob).await.map_err(Error::Rpc)?;
+        Ok(())
+    }
+
     pub async fn get_feature_indicators(&mut self) -> Result<FeatureIndicators> {
         self.0
             .get_feature_indicators(())
@@ -779,6 +1512,19 @@ impl MullvadProxyClient {
         self.0.enable_relay(relay).await.map_err(Error::Rpc)?;
         Ok(())
     }
+
+    pub async fn set_api_endpoint(&mut self, endpoint: types::ApiEndpointOverride) -> Result<()> {
+        self.0
+            .set_api_endpoint(endpoint)
+            .await
+            .map_err(Error::Rpc)?;
+        Ok(())
+    }
+
+    pub async fn reset_api_endpoint(&mut self) -> Result<()> {
+        self.0.reset_api_endpoint(()).await.map_err(Error::Rpc)?;
+        Ok(())
+    }
 }
 
 #[cfg(not(target_os = "android"))]
@@ -1,6 +1,13 @@
+mod access;
+pub mod api;
 pub mod client;
 pub mod types;
 
+pub use access::AccessLevel;
+#[cfg(windows)]
+pub use access::CONTROL_TOKEN_METADATA;
+
+#[cfg(not(target_os = "android"))]
 use parity_tokio_ipc::Endpoint as IpcEndpoint;
 #[cfg(unix)]
 use std::{env, fs, os::unix::fs::PermissionsExt};
@@ -65,6 +72,9 @@ pub enum Error {
     #[error("Failed to parse gRPC response")]
     InvalidResponse(#[source] types::FromProtobufTypeError),
 
+    #[error("The daemon does not support management interface version {0}")]
+    UnsupportedApiVersion(u32),
+
     #[error("Duration is too large")]
     DurationTooLarge,
 
@@ -92,6 +102,9 @@ pub enum Error {
     #[error("There is no such device")]
     DeviceNotFound,
 
+    #[error("There is no pending request with that ID")]
+    PendingRequestNotFound,
+
     #[error("Location data is unavailable")]
     NoLocationData,
 
@@ -142,17 +155,40 @@ pub fn spawn_rpc_server<T: ManagementService, F: Future<Output = ()> + Send + 's
     abort_rx: F,
     rpc_socket_path: impl AsRef<std::path::Path>,
 ) -> std::result::Result<ServerJoinHandle, Error> {
-    use futures::stream::TryStreamExt;
-    use parity_tokio_ipc::SecurityAttributes;
-
-    let mut endpoint = IpcEndpoint::new(rpc_socket_path.as_ref().to_string_lossy().to_string());
-    endpoint.set_security_attributes(
-        SecurityAttributes::allow_everyone_create()
-            .map_err(Error::SecurityAttributes)?
-            .set_mode(0o766)
-            .map_err(Error::SecurityAttributes)?,
-    );
-    let incoming = endpoint.incoming().map_err(Error::StartServerError)?;
+    // The socket is bound directly, so that the credentials of each client can be obtained
+    #[cfg(unix)]
+    let incoming = {
+        let listener = tokio::net::UnixListener::bind(rpc_socket_path.as_ref())
+            .map_err(Error::StartServerError)?;
+        fs::set_permissions(rpc_socket_path.as_ref(), PermissionsExt::from_mode(0o766))
+            .map_err(Error::PermissionsError)?;
+        Box::pin(futures::stream::unfold(listener, |listener| async move {
+            let connection = listener.accept().await.map(|(stream, _)| {
+                let access = access::peer_access(&stream);
+                StreamBox(stream, access)
+            });
+            Some((connection, listener))
+        }))
+    };
+
+    #[cfg(windows)]
+    let incoming = {
+        use futures::stream::TryStreamExt;
+        use parity_tokio_ipc::SecurityAttributes;
+
+        let mut endpoint = IpcEndpoint::new(rpc_socket_path.as_ref().to_string_lossy().to_string());
+        endpoint.set_security_attributes(
+            SecurityAttributes::allow_everyone_create()
+                .map_err(Error::SecurityAttributes)?
+                .set_mode(0o766)
+                .map_err(Error::SecurityAttributes)?,
+        );
+        // The access level of each request is determined by its token
+        endpoint
+            .incoming()
+            .map_err(Error::StartServerError)?
+            .map_ok(|stream| StreamBox(stream, AccessLevel::Full))
+    };
 
     #[cfg(unix)]
     if let Some(group_name) = &*MULLVAD_MANAGEMENT_SOCKET_GROUP {
@@ -167,8 +203,9 @@ pub fn spawn_rpc_server<T: ManagementService, F: Future<Output = ()> + Send + 's
 
     Ok(tokio::spawn(async move {
         if let Err(execution_error) = Server::builder()
+            .layer(tower::layer::layer_fn(access::AccessControl))
             .add_service(ManagementServiceServer::new(service))
-            .serve_with_incoming_shutdown(incoming.map_ok(StreamBox), abort_rx)
+            .serve_with_incoming_shutdown(incoming, abort_rx)
             .await
             .map_err(Error::GrpcTransportError)
         {
@@ -178,13 +215,14 @@ pub fn spawn_rpc_server<T: ManagementService, F: Future<Output = ()> + Send + 's
     }))
 }
 
+/// A client connection, and what the client is allowed to do.
 #[derive(Debug)]
-struct StreamBox<T: AsyncRead + AsyncWrite>(pub T);
+struct StreamBox<T: AsyncRead + AsyncWrite>(pub T, AccessLevel);
 impl<T: AsyncRead + AsyncWrite> Connected for StreamBox<T> {
-    type ConnectInfo = Option<()>;
+    type ConnectInfo = AccessLevel;
 
     fn connect_info(&self) -> Self::ConnectInfo {
-        None
+        self.1
     }
 }
 impl<T: AsyncRead + AsyncWrite + Unpin> AsyncRead for StreamBox<T> {
@@ -11,6 +11,8 @@ mod settings {
                 direct: Some(settings.direct().clone().into()),
                 mullvad_bridges: Some(settings.mullvad_bridges().clone().into()),
                 encrypted_dns_proxy: Some(settings.encrypted_dns_proxy().clone().into()),
+                direct_http3: Some(settings.direct_http3().clone().into()),
+                tor: Some(settings.tor().clone().into()),
                 custom: settings
                     .iter_custom()
                     .cloned()
@@ -45,6 +47,20 @@ mod settings {
                 ))
                 .and_then(access_method::AccessMethodSetting::try_from)?;
 
+            let direct_http3 = settings
+                .direct_http3
+                .ok_or(FromProtobufTypeError::InvalidArgument(
+                    "Could not deserialize Direct HTTP/3 Access Method from protobuf",
+                ))
+                .and_then(access_method::AccessMethodSetting::try_from)?;
+
+            let tor = settings
+                .tor
+                .ok_or(FromProtobufTypeError::InvalidArgument(
+                    "Could not deserialize Tor Access Method from protobuf",
+                ))
+                .and_then(access_method::AccessMethodSetting::try_from)?;
+
             let custom = settings
                 .custom
                 .iter()
@@ -55,6 +71,8 @@ mod settings {
                 direct,
                 mullvad_bridges,
                 encrypted_dns_proxy,
+                direct_http3,
+                tor,
                 custom,
             ))
         }
@@ -130,6 +148,10 @@ mod data {
                 proto::access_method::AccessMethod::EncryptedDnsProxy(proxy) => {
                     AccessMethod::from(proxy)
                 }
+                proto::access_method::AccessMethod::DirectHttp3(direct) => {
+                    AccessMethod::from(direct)
+                }
+                proto::access_method::AccessMethod::Tor(tor) => AccessMethod::from(tor),
                 proto::access_method::AccessMethod::Custom(custom) => {
                     CustomProxy::try_from(custom).map(AccessMethod::from)?
                 }
@@ -164,6 +186,20 @@ mod data {
         }
     }
 
+    impl From<proto::access_method::DirectHttp3> for AccessMethod {
+        fn from(_value: proto::access_method::DirectHttp3) -> Self {
+            AccessMethod::from(BuiltInAccessMethod::DirectHttp3)
+        }
+    }
+
+    impl From<proto::access_method::Tor> for AccessMethod {
+        fn from(value: proto::access_method::Tor) -> Self {
+            AccessMethod::from(BuiltInAccessMethod::Tor {
+                socks_port: value.socks_port as u16,
+            })
+        }
+    }
+
     impl TryFrom<proto::Socks5Local> for AccessMethod {
         type Error = FromProtobufTypeError;
 
@@ -210,6 +246,16 @@ mod data {
                         proto::access_method::EncryptedDnsProxy {},
                     )
                 }
+                mullvad_types::access_method::BuiltInAccessMethod::DirectHttp3 => {
+                    proto::access_method::AccessMethod::DirectHttp3(
+                        proto::access_method::DirectHttp3 {},
+                    )
+                }
+                mullvad_types::access_method::BuiltInAccessMethod::Tor { socks_port } => {
+                    proto::access_method::AccessMethod::Tor(proto::access_method::Tor {
+                        socks_port: u32::from(socks_port),
+                    })
+                }
             }
         }
     }
@@ -260,3 +306,78 @@ mod data {
         }
     }
 }
+
+/// Implements conversions for the auxiliary
+/// [`crate::types::proto::AccessMethodStatsList`] type to a map of the internal
+/// [`mullvad_types::access_method::AccessMethodStats`] data type.
+mod stats {
+    use crate::types::{proto, FromProtobufTypeError};
+    use chrono::DateTime;
+    use mullvad_types::access_method::{AccessMethodStats, Id};
+    use prost_types::Timestamp;
+    use std::collections::HashMap;
+
+    impl From<HashMap<Id, AccessMethodStats>> for proto::AccessMethodStatsList {
+        fn from(stats: HashMap<Id, AccessMethodStats>) -> Self {
+            let stats = stats
+                .into_iter()
+                .map(|(id, stats)| proto::AccessMethodStats {
+                    id: Some(proto::Uuid::from(id)),
+                    success_rate: stats.success_rate,
+                    latency: stats.latency.map(|latency| {
+                        prost_types::Duration::try_from(latency).expect(
+                            "Failed to convert std::time::Duration to prost_types::Duration",
+                        )
+                    }),
+                    successes: stats.successes,
+                    failures: stats.failures,
+                    last_used: Some(Timestamp {
+                        seconds: stats.last_used.timestamp(),
+                        nanos: 0,
+                    }),
+                })
+                .collect();
+            proto::AccessMethodStatsList { stats }
+        }
+    }
+
+    impl TryFrom<proto::AccessMethodStatsList> for HashMap<Id, AccessMethodStats> {
+        type Error = FromProtobufTypeError;
+
+        fn try_from(list: proto::AccessMethodStatsList) -> Result<Self, Self::Error> {
+            list.stats
+                .into_iter()
+                .map(|stats| {
+                    let id = stats
+                        .id
+                        .ok_or(FromProtobufTypeError::InvalidArgument("missing 'id' field"))
+                        .and_then(Id::try_from)?;
+                    let latency = stats
+                        .latency
+                        .map(std::time::Duration::try_from)
+                        .transpose()
+                        .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid latency"))?;
+                    let last_used =
+                        stats
+                            .last_used
+                            .ok_or(FromProtobufTypeError::InvalidArgument(
+                                "missing 'last_used' field",
+                            ))?;
+                    let last_used =
+                        DateTime::from_timestamp(last_used.seconds, last_used.nanos as u32)
+                            .ok_or(FromProtobufTypeError::InvalidArgument("invalid timestamp"))?;
+                    Ok((
+                        id,
+                        AccessMethodStats {
+                            success_rate: stats.success_rate,
+                            latency,
+                            successes: stats.successes,
+                            failures: stats.failures,
+                            last_used,
+                        },
+                    ))
+                })
+                .collect()
+        }
+    }
+}
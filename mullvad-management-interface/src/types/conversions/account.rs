@@ -1,6 +1,6 @@
 use crate::types;
 use chrono::DateTime;
-use mullvad_types::account::{AccountData, VoucherSubmission};
+use mullvad_types::account::{AccountData, AccountExpiryEvent, VoucherSubmission};
 #[cfg(target_os = "android")]
 use mullvad_types::account::{PlayPurchase, PlayPurchasePaymentToken};
 
@@ -66,6 +66,36 @@ impl TryFrom<types::AccountData> for AccountData {
     }
 }
 
+impl From<AccountExpiryEvent> for types::AccountExpiryEvent {
+    fn from(event: AccountExpiryEvent) -> Self {
+        types::AccountExpiryEvent {
+            expiry: Some(types::Timestamp {
+                seconds: event.expiry.timestamp(),
+                nanos: 0,
+            }),
+            payment_received: event.payment_received,
+        }
+    }
+}
+
+impl TryFrom<types::AccountExpiryEvent> for AccountExpiryEvent {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(event: types::AccountExpiryEvent) -> Result<Self, FromProtobufTypeError> {
+        let expiry = event
+            .expiry
+            .ok_or(FromProtobufTypeError::InvalidArgument("missing expiry"))?;
+
+        let expiry = DateTime::from_timestamp(expiry.seconds, expiry.nanos as u32)
+            .ok_or(FromProtobufTypeError::InvalidArgument("invalid timestamp"))?;
+
+        Ok(AccountExpiryEvent {
+            expiry,
+            payment_received: event.payment_received,
+        })
+    }
+}
+
 #[cfg(target_os = "android")]
 impl TryFrom<types::PlayPurchase> for PlayPurchase {
     type Error = FromProtobufTypeError;
@@ -0,0 +1,51 @@
+use super::{net::try_transport_protocol_from_i32, FromProtobufTypeError};
+use crate::types::proto;
+use talpid_types::net::AllowedIncoming;
+
+impl From<AllowedIncoming> for proto::AllowedIncoming {
+    fn from(allowed: AllowedIncoming) -> Self {
+        proto::AllowedIncoming {
+            protocol: i32::from(proto::TransportProtocol::from(allowed.protocol)),
+            port: u32::from(allowed.port),
+            interface: allowed.interface,
+        }
+    }
+}
+
+impl TryFrom<proto::AllowedIncoming> for AllowedIncoming {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(allowed: proto::AllowedIncoming) -> Result<Self, Self::Error> {
+        let protocol = try_transport_protocol_from_i32(allowed.protocol)?;
+        let port = u16::try_from(allowed.port)
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid port"))?;
+        Ok(AllowedIncoming {
+            protocol,
+            port,
+            interface: allowed.interface,
+        })
+    }
+}
+
+impl From<Vec<AllowedIncoming>> for proto::AllowedIncomingRules {
+    fn from(rules: Vec<AllowedIncoming>) -> Self {
+        proto::AllowedIncomingRules {
+            rules: rules
+                .into_iter()
+                .map(proto::AllowedIncoming::from)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::AllowedIncomingRules> for Vec<AllowedIncoming> {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(rules: proto::AllowedIncomingRules) -> Result<Self, Self::Error> {
+        rules
+            .rules
+            .into_iter()
+            .map(AllowedIncoming::try_from)
+            .collect()
+    }
+}
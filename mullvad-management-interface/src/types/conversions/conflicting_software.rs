@@ -0,0 +1,62 @@
+use super::FromProtobufTypeError;
+use crate::types::proto;
+use mullvad_types::conflicting_software::{ConflictKind, ConflictingSoftware};
+
+impl From<ConflictingSoftware> for proto::ConflictingSoftware {
+    fn from(conflict: ConflictingSoftware) -> Self {
+        let kind = match conflict.kind {
+            ConflictKind::NetworkExtension => proto::conflicting_software::Kind::NetworkExtension,
+            ConflictKind::PfFirewall => proto::conflicting_software::Kind::PfFirewall,
+        };
+        proto::ConflictingSoftware {
+            kind: i32::from(kind),
+            name: conflict.name,
+            identifier: conflict.identifier,
+        }
+    }
+}
+
+impl TryFrom<proto::ConflictingSoftware> for ConflictingSoftware {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(conflict: proto::ConflictingSoftware) -> Result<Self, Self::Error> {
+        let kind = match proto::conflicting_software::Kind::try_from(conflict.kind) {
+            Ok(proto::conflicting_software::Kind::NetworkExtension) => {
+                ConflictKind::NetworkExtension
+            }
+            Ok(proto::conflicting_software::Kind::PfFirewall) => ConflictKind::PfFirewall,
+            Err(_) => {
+                return Err(FromProtobufTypeError::InvalidArgument(
+                    "invalid conflicting software kind",
+                ))
+            }
+        };
+        Ok(ConflictingSoftware {
+            kind,
+            name: conflict.name,
+            identifier: conflict.identifier,
+        })
+    }
+}
+
+impl From<Vec<ConflictingSoftware>> for proto::ConflictingSoftwareList {
+    fn from(conflicts: Vec<ConflictingSoftware>) -> Self {
+        proto::ConflictingSoftwareList {
+            conflicts: conflicts
+                .into_iter()
+                .map(proto::ConflictingSoftware::from)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::ConflictingSoftwareList> for Vec<ConflictingSoftware> {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(list: proto::ConflictingSoftwareList) -> Result<Self, Self::Error> {
+        list.conflicts
+            .into_iter()
+            .map(ConflictingSoftware::try_from)
+            .collect()
+    }
+}
@@ -0,0 +1,50 @@
+use crate::types::{conversions::bytes_to_pubkey, proto, FromProtobufTypeError};
+use mullvad_types::custom_relay::CustomRelay;
+
+impl From<CustomRelay> for proto::CustomRelay {
+    fn from(relay: CustomRelay) -> Self {
+        Self {
+            name: relay.name,
+            public_key: relay.public_key.as_bytes().to_vec(),
+            endpoint: relay.endpoint.to_string(),
+            allowed_ips: relay
+                .allowed_ips
+                .iter()
+                .map(|network| network.to_string())
+                .collect(),
+            obfuscation: relay
+                .obfuscation
+                .as_ref()
+                .map(proto::CustomObfuscationBridge::from),
+        }
+    }
+}
+
+impl TryFrom<proto::CustomRelay> for CustomRelay {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(relay: proto::CustomRelay) -> Result<Self, Self::Error> {
+        let endpoint = relay
+            .endpoint
+            .parse()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid custom relay endpoint"))?;
+        let allowed_ips = relay
+            .allowed_ips
+            .iter()
+            .map(|network| network.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid allowed IP"))?;
+        let obfuscation = relay
+            .obfuscation
+            .map(mullvad_types::relay_constraints::CustomObfuscationBridge::try_from)
+            .transpose()?;
+
+        Ok(Self {
+            name: relay.name,
+            public_key: bytes_to_pubkey(&relay.public_key)?,
+            endpoint,
+            allowed_ips,
+            obfuscation,
+        })
+    }
+}
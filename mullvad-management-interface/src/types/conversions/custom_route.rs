@@ -0,0 +1,54 @@
+use super::FromProtobufTypeError;
+use crate::types::proto;
+use talpid_types::net::{CustomRoute, RouteTarget};
+
+impl From<CustomRoute> for proto::CustomRoute {
+    fn from(route: CustomRoute) -> Self {
+        let via = match route.via {
+            RouteTarget::Tunnel => proto::custom_route::Target::Tunnel,
+            RouteTarget::DefaultGateway => proto::custom_route::Target::DefaultGateway,
+        };
+        proto::CustomRoute {
+            network: route.network.to_string(),
+            via: i32::from(via),
+        }
+    }
+}
+
+impl TryFrom<proto::CustomRoute> for CustomRoute {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(route: proto::CustomRoute) -> Result<Self, Self::Error> {
+        let network = route
+            .network
+            .parse()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid IP network"))?;
+        let via = match proto::custom_route::Target::try_from(route.via)
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid route target"))?
+        {
+            proto::custom_route::Target::Tunnel => RouteTarget::Tunnel,
+            proto::custom_route::Target::DefaultGateway => RouteTarget::DefaultGateway,
+        };
+        Ok(CustomRoute { network, via })
+    }
+}
+
+impl From<Vec<CustomRoute>> for proto::CustomRoutes {
+    fn from(routes: Vec<CustomRoute>) -> Self {
+        proto::CustomRoutes {
+            routes: routes.into_iter().map(proto::CustomRoute::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::CustomRoutes> for Vec<CustomRoute> {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(routes: proto::CustomRoutes) -> Result<Self, Self::Error> {
+        routes
+            .routes
+            .into_iter()
+            .map(CustomRoute::try_from)
+            .collect()
+    }
+}
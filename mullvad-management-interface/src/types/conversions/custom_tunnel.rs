@@ -91,10 +91,12 @@ impl TryFrom<proto::ConnectionConfig> for mullvad_types::ConnectionConfig {
                             allowed_ips,
                             endpoint,
                             psk: None,
+                            persistent_keepalive: None,
                             #[cfg(daita)]
                             constant_packet_size: false,
                         },
                         exit_peer: None,
+                        middle_peers: vec![],
                         ipv4_gateway,
                         ipv6_gateway,
                         #[cfg(target_os = "linux")]
@@ -0,0 +1,42 @@
+use super::FromProtobufTypeError;
+use crate::types::proto;
+use mullvad_types::data_usage::{DataUsage, MonthlyUsage};
+
+impl From<DataUsage> for proto::DataUsage {
+    fn from(usage: DataUsage) -> Self {
+        proto::DataUsage {
+            months: usage
+                .iter()
+                .map(|month| proto::MonthlyDataUsage {
+                    year: month.year,
+                    month: month.month,
+                    tx_bytes: month.tx_bytes,
+                    rx_bytes: month.rx_bytes,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::DataUsage> for DataUsage {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(usage: proto::DataUsage) -> Result<Self, Self::Error> {
+        let months = usage
+            .months
+            .into_iter()
+            .map(|month| {
+                if !(1..=12).contains(&month.month) {
+                    return Err(FromProtobufTypeError::InvalidArgument("invalid month"));
+                }
+                Ok(MonthlyUsage {
+                    year: month.year,
+                    month: month.month,
+                    tx_bytes: month.tx_bytes,
+                    rx_bytes: month.rx_bytes,
+                })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(DataUsage::new(months))
+    }
+}
@@ -177,6 +177,38 @@ impl TryFrom<proto::RemoveDeviceEvent> for mullvad_types::device::RemoveDeviceEv
     }
 }
 
+impl From<mullvad_types::device::DeviceRemovalResult> for proto::DeviceRemovalResult {
+    fn from(result: mullvad_types::device::DeviceRemovalResult) -> Self {
+        proto::DeviceRemovalResult {
+            removed: result.removed,
+            failed: result
+                .failed
+                .into_iter()
+                .map(|failure| proto::device_removal_result::Failure {
+                    device_id: failure.device_id,
+                    error: failure.error,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<proto::DeviceRemovalResult> for mullvad_types::device::DeviceRemovalResult {
+    fn from(result: proto::DeviceRemovalResult) -> Self {
+        mullvad_types::device::DeviceRemovalResult {
+            removed: result.removed,
+            failed: result
+                .failed
+                .into_iter()
+                .map(|failure| mullvad_types::device::DeviceRemovalFailure {
+                    device_id: failure.device_id,
+                    error: failure.error,
+                })
+                .collect(),
+        }
+    }
+}
+
 impl From<mullvad_types::device::AccountAndDevice> for proto::AccountAndDevice {
     fn from(device: mullvad_types::device::AccountAndDevice) -> Self {
         proto::AccountAndDevice {
@@ -0,0 +1,79 @@
+use crate::types::proto;
+#[cfg(target_os = "linux")]
+use crate::types::FromProtobufTypeError;
+use std::path::PathBuf;
+use talpid_types::dns::DnsInterference;
+
+impl From<DnsInterference> for proto::DnsInterference {
+    fn from(interference: DnsInterference) -> Self {
+        proto::DnsInterference {
+            path: interference.path.to_string_lossy().into_owned(),
+            source: interference.source,
+        }
+    }
+}
+
+impl From<proto::DnsInterference> for DnsInterference {
+    fn from(interference: proto::DnsInterference) -> Self {
+        DnsInterference {
+            path: PathBuf::from(interference.path),
+            source: interference.source,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<talpid_types::dns::DnsBackend> for proto::dns_backends::Backend {
+    fn from(backend: talpid_types::dns::DnsBackend) -> Self {
+        use talpid_types::dns::DnsBackend;
+
+        match backend {
+            DnsBackend::SystemdResolved => proto::dns_backends::Backend::SystemdResolved,
+            DnsBackend::NetworkManager => proto::dns_backends::Backend::NetworkManager,
+            DnsBackend::Resolvconf => proto::dns_backends::Backend::Resolvconf,
+            DnsBackend::StaticFile => proto::dns_backends::Backend::StaticFile,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<&[talpid_types::dns::DnsBackend]> for proto::DnsBackends {
+    fn from(backends: &[talpid_types::dns::DnsBackend]) -> Self {
+        proto::DnsBackends {
+            backends: backends
+                .iter()
+                .map(|&backend| i32::from(proto::dns_backends::Backend::from(backend)))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl TryFrom<proto::DnsBackends> for Vec<talpid_types::dns::DnsBackend> {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(backends: proto::DnsBackends) -> Result<Self, Self::Error> {
+        backends
+            .backends
+            .into_iter()
+            .map(try_dns_backend_from_i32)
+            .collect()
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn try_dns_backend_from_i32(
+    backend: i32,
+) -> Result<talpid_types::dns::DnsBackend, FromProtobufTypeError> {
+    use talpid_types::dns::DnsBackend;
+
+    match proto::dns_backends::Backend::try_from(backend) {
+        Ok(proto::dns_backends::Backend::SystemdResolved) => Ok(DnsBackend::SystemdResolved),
+        Ok(proto::dns_backends::Backend::NetworkManager) => Ok(DnsBackend::NetworkManager),
+        Ok(proto::dns_backends::Backend::Resolvconf) => Ok(DnsBackend::Resolvconf),
+        Ok(proto::dns_backends::Backend::StaticFile) => Ok(DnsBackend::StaticFile),
+        Err(_) => Err(FromProtobufTypeError::InvalidArgument(
+            "invalid DNS backend",
+        )),
+    }
+}
@@ -0,0 +1,134 @@
+use super::FromProtobufTypeError;
+use crate::types::proto;
+use std::path::PathBuf;
+use talpid_types::drivers::{Driver, DriverRepairResult, DriverServiceState, DriverStatus};
+
+impl From<Driver> for proto::Driver {
+    fn from(driver: Driver) -> Self {
+        match driver {
+            Driver::Wintun => proto::Driver::Wintun,
+            Driver::WireguardNt => proto::Driver::WireguardNt,
+            Driver::SplitTunnel => proto::Driver::SplitTunnel,
+        }
+    }
+}
+
+impl From<proto::Driver> for Driver {
+    fn from(driver: proto::Driver) -> Self {
+        match driver {
+            proto::Driver::Wintun => Driver::Wintun,
+            proto::Driver::WireguardNt => Driver::WireguardNt,
+            proto::Driver::SplitTunnel => Driver::SplitTunnel,
+        }
+    }
+}
+
+impl From<Driver> for proto::DriverRepairRequest {
+    fn from(driver: Driver) -> Self {
+        proto::DriverRepairRequest {
+            driver: i32::from(proto::Driver::from(driver)),
+        }
+    }
+}
+
+impl TryFrom<proto::DriverRepairRequest> for Driver {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(request: proto::DriverRepairRequest) -> Result<Self, Self::Error> {
+        try_driver_from_i32(request.driver)
+    }
+}
+
+impl From<DriverServiceState> for proto::driver_status::State {
+    fn from(state: DriverServiceState) -> Self {
+        use proto::driver_status::State;
+        match state {
+            DriverServiceState::NotInstalled => State::NotInstalled,
+            DriverServiceState::MarkedForDeletion => State::MarkedForDeletion,
+            DriverServiceState::Stopped => State::Stopped,
+            DriverServiceState::StartPending => State::StartPending,
+            DriverServiceState::StopPending => State::StopPending,
+            DriverServiceState::Running => State::Running,
+            DriverServiceState::ContinuePending => State::ContinuePending,
+            DriverServiceState::PausePending => State::PausePending,
+            DriverServiceState::Paused => State::Paused,
+        }
+    }
+}
+
+impl From<proto::driver_status::State> for DriverServiceState {
+    fn from(state: proto::driver_status::State) -> Self {
+        use proto::driver_status::State;
+        match state {
+            State::NotInstalled => DriverServiceState::NotInstalled,
+            State::MarkedForDeletion => DriverServiceState::MarkedForDeletion,
+            State::Stopped => DriverServiceState::Stopped,
+            State::StartPending => DriverServiceState::StartPending,
+            State::StopPending => DriverServiceState::StopPending,
+            State::Running => DriverServiceState::Running,
+            State::ContinuePending => DriverServiceState::ContinuePending,
+            State::PausePending => DriverServiceState::PausePending,
+            State::Paused => DriverServiceState::Paused,
+        }
+    }
+}
+
+impl From<DriverStatus> for proto::DriverStatus {
+    fn from(status: DriverStatus) -> Self {
+        proto::DriverStatus {
+            driver: i32::from(proto::Driver::from(status.driver)),
+            state: i32::from(proto::driver_status::State::from(status.state)),
+            version: status.version,
+            image_path: status
+                .image_path
+                .map(|path| path.to_string_lossy().into_owned()),
+        }
+    }
+}
+
+impl TryFrom<proto::DriverStatus> for DriverStatus {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(status: proto::DriverStatus) -> Result<Self, Self::Error> {
+        let state = proto::driver_status::State::try_from(status.state)
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid driver state"))?;
+        Ok(DriverStatus {
+            driver: try_driver_from_i32(status.driver)?,
+            state: DriverServiceState::from(state),
+            version: status.version,
+            image_path: status.image_path.map(PathBuf::from),
+        })
+    }
+}
+
+impl From<DriverRepairResult> for proto::DriverRepairResult {
+    fn from(result: DriverRepairResult) -> Self {
+        proto::DriverRepairResult {
+            status: Some(proto::DriverStatus::from(result.status)),
+            restart_required: result.restart_required,
+        }
+    }
+}
+
+impl TryFrom<proto::DriverRepairResult> for DriverRepairResult {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(result: proto::DriverRepairResult) -> Result<Self, Self::Error> {
+        let status = result
+            .status
+            .ok_or(FromProtobufTypeError::InvalidArgument(
+                "missing driver status",
+            ))
+            .and_then(DriverStatus::try_from)?;
+        Ok(DriverRepairResult {
+            status,
+            restart_required: result.restart_required,
+        })
+    }
+}
+
+fn try_driver_from_i32(driver: i32) -> Result<Driver, FromProtobufTypeError> {
+    proto::Driver::try_from(driver)
+        .map(Driver::from)
+        .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid driver"))
+}
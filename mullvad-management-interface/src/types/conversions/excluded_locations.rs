@@ -0,0 +1,29 @@
+use crate::types::{proto, FromProtobufTypeError};
+use mullvad_types::{
+    excluded_locations::ExcludedLocations, relay_constraints::GeographicLocationConstraint,
+};
+
+impl From<ExcludedLocations> for proto::ExcludedLocations {
+    fn from(excluded_locations: ExcludedLocations) -> Self {
+        Self {
+            locations: excluded_locations
+                .iter()
+                .cloned()
+                .map(proto::GeographicLocationConstraint::from)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::ExcludedLocations> for ExcludedLocations {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(excluded_locations: proto::ExcludedLocations) -> Result<Self, Self::Error> {
+        let locations = excluded_locations
+            .locations
+            .into_iter()
+            .map(GeographicLocationConstraint::try_from)
+            .collect::<Result<Vec<_>, Self::Error>>()?;
+        Ok(Self::from(locations))
+    }
+}
@@ -0,0 +1,63 @@
+use super::{net::try_transport_protocol_from_i32, FromProtobufTypeError};
+use crate::types::proto;
+use talpid_types::net::FirewallException;
+
+impl From<FirewallException> for proto::FirewallException {
+    fn from(exception: FirewallException) -> Self {
+        proto::FirewallException {
+            network: exception.network.to_string(),
+            protocol: exception
+                .protocol
+                .map(|protocol| i32::from(proto::TransportProtocol::from(protocol))),
+            port: exception.port.map(u32::from),
+        }
+    }
+}
+
+impl TryFrom<proto::FirewallException> for FirewallException {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(exception: proto::FirewallException) -> Result<Self, Self::Error> {
+        let network = exception
+            .network
+            .parse()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid IP network"))?;
+        let protocol = exception
+            .protocol
+            .map(try_transport_protocol_from_i32)
+            .transpose()?;
+        let port = exception
+            .port
+            .map(u16::try_from)
+            .transpose()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid port"))?;
+        Ok(FirewallException {
+            network,
+            protocol,
+            port,
+        })
+    }
+}
+
+impl From<Vec<FirewallException>> for proto::FirewallExceptions {
+    fn from(exceptions: Vec<FirewallException>) -> Self {
+        proto::FirewallExceptions {
+            exceptions: exceptions
+                .into_iter()
+                .map(proto::FirewallException::from)
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::FirewallExceptions> for Vec<FirewallException> {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(exceptions: proto::FirewallExceptions) -> Result<Self, Self::Error> {
+        exceptions
+            .exceptions
+            .into_iter()
+            .map(FirewallException::try_from)
+            .collect()
+    }
+}
@@ -0,0 +1,65 @@
+use super::{arg_from_str, FromProtobufTypeError};
+use crate::types::proto;
+use talpid_types::net::{AllowedLan, LanNetwork};
+
+impl From<LanNetwork> for proto::LanNetwork {
+    fn from(network: LanNetwork) -> Self {
+        proto::LanNetwork {
+            network: network.network.map(|network| network.to_string()),
+            interface: network.interface,
+        }
+    }
+}
+
+impl TryFrom<proto::LanNetwork> for LanNetwork {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(network: proto::LanNetwork) -> Result<Self, Self::Error> {
+        let ip_network = network
+            .network
+            .map(|network| arg_from_str(&network, "invalid LAN network"))
+            .transpose()?;
+        Ok(LanNetwork {
+            network: ip_network,
+            interface: network.interface,
+        })
+    }
+}
+
+impl From<AllowedLan> for proto::LanSharing {
+    fn from(allow_lan: AllowedLan) -> Self {
+        use proto::lan_sharing::Mode;
+
+        let (mode, networks) = match allow_lan {
+            AllowedLan::Blocked => (Mode::Blocked, vec![]),
+            AllowedLan::All => (Mode::All, vec![]),
+            AllowedLan::Only(networks) => (Mode::Only, networks),
+        };
+        proto::LanSharing {
+            mode: i32::from(mode),
+            networks: networks.into_iter().map(proto::LanNetwork::from).collect(),
+        }
+    }
+}
+
+impl TryFrom<proto::LanSharing> for AllowedLan {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(lan_sharing: proto::LanSharing) -> Result<Self, Self::Error> {
+        use proto::lan_sharing::Mode;
+
+        let mode = Mode::try_from(lan_sharing.mode)
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid LAN sharing mode"))?;
+        Ok(match mode {
+            Mode::Blocked => AllowedLan::Blocked,
+            Mode::All => AllowedLan::All,
+            Mode::Only => AllowedLan::Only(
+                lan_sharing
+                    .networks
+                    .into_iter()
+                    .map(LanNetwork::try_from)
+                    .collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
@@ -2,18 +2,33 @@ use std::str::FromStr;
 
 mod access_method;
 mod account;
+mod allowed_incoming;
+mod conflicting_software;
 mod custom_list;
+mod custom_relay;
+mod custom_route;
 mod custom_tunnel;
+mod data_usage;
 mod device;
+mod dns;
+#[cfg(target_os = "windows")]
+mod drivers;
+mod excluded_locations;
 mod features;
+mod firewall_exception;
+mod lan_sharing;
 mod location;
 mod net;
+mod pending_request;
+mod profile;
 pub mod relay_constraints;
 mod relay_list;
 mod settings;
 #[cfg(target_os = "windows")]
 mod split_tunnel;
+mod state_hooks;
 mod states;
+mod trusted_network;
 mod version;
 mod wireguard;
 
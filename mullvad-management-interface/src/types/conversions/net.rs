@@ -39,6 +39,9 @@ impl From<talpid_types::net::TunnelEndpoint> for proto::TunnelEndpoint {
                         net::ObfuscationType::Shadowsocks => {
                             i32::from(proto::obfuscation_endpoint::ObfuscationType::Shadowsocks)
                         }
+                        net::ObfuscationType::Tls => {
+                            i32::from(proto::obfuscation_endpoint::ObfuscationType::Tls)
+                        }
                     },
                 }
             }),
@@ -46,9 +49,15 @@ impl From<talpid_types::net::TunnelEndpoint> for proto::TunnelEndpoint {
                 address: entry.address.to_string(),
                 protocol: i32::from(proto::TransportProtocol::from(entry.protocol)),
             }),
-            tunnel_metadata: endpoint
-                .tunnel_interface
-                .map(|tunnel_interface| proto::TunnelMetadata { tunnel_interface }),
+            tunnel_metadata: endpoint.tunnel_interface.map(|tunnel_interface| {
+                proto::TunnelMetadata {
+                    tunnel_interface,
+                    wireguard_backend: endpoint
+                        .wireguard_backend
+                        .map(|backend| i32::from(proto::WireguardBackend::from(backend))),
+                    wireguard_worker_threads: endpoint.wireguard_worker_threads,
+                }
+            }),
             #[cfg(daita)]
             daita: endpoint.daita,
             #[cfg(not(daita))]
@@ -123,6 +132,9 @@ impl TryFrom<proto::TunnelEndpoint> for talpid_types::net::TunnelEndpoint {
                                 Ok(proto::obfuscation_endpoint::ObfuscationType::Shadowsocks) => {
                                     talpid_net::ObfuscationType::Shadowsocks
                                 }
+                                Ok(proto::obfuscation_endpoint::ObfuscationType::Tls) => {
+                                    talpid_net::ObfuscationType::Tls
+                                }
                                 Err(_) => {
                                     return Err(FromProtobufTypeError::InvalidArgument(
                                         "unknown obfuscation type",
@@ -141,6 +153,16 @@ impl TryFrom<proto::TunnelEndpoint> for talpid_types::net::TunnelEndpoint {
                     })
                 })
                 .transpose()?,
+            wireguard_backend: endpoint
+                .tunnel_metadata
+                .as_ref()
+                .and_then(|tunnel_metadata| tunnel_metadata.wireguard_backend)
+                .map(try_wireguard_backend_from_i32)
+                .transpose()?,
+            wireguard_worker_threads: endpoint
+                .tunnel_metadata
+                .as_ref()
+                .and_then(|tunnel_metadata| tunnel_metadata.wireguard_worker_threads),
             tunnel_interface: endpoint
                 .tunnel_metadata
                 .map(|tunnel_metadata| tunnel_metadata.tunnel_interface),
@@ -150,6 +172,64 @@ impl TryFrom<proto::TunnelEndpoint> for talpid_types::net::TunnelEndpoint {
     }
 }
 
+impl From<talpid_types::net::wireguard::Backend> for proto::WireguardBackend {
+    fn from(backend: talpid_types::net::wireguard::Backend) -> Self {
+        use talpid_types::net::wireguard::Backend;
+
+        match backend {
+            Backend::Kernel => proto::WireguardBackend::Kernel,
+            Backend::NetworkManager => proto::WireguardBackend::NetworkManager,
+            Backend::Userspace => proto::WireguardBackend::Userspace,
+        }
+    }
+}
+
+impl TryFrom<proto::WireguardBackendSetting> for Option<talpid_types::net::wireguard::Backend> {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(setting: proto::WireguardBackendSetting) -> Result<Self, Self::Error> {
+        setting
+            .backend
+            .map(try_wireguard_backend_from_i32)
+            .transpose()
+    }
+}
+
+impl From<talpid_types::net::wireguard::BackendFallback> for proto::WireguardBackendFallback {
+    fn from(fallback: talpid_types::net::wireguard::BackendFallback) -> Self {
+        proto::WireguardBackendFallback {
+            requested: i32::from(proto::WireguardBackend::from(fallback.requested)),
+            actual: i32::from(proto::WireguardBackend::from(fallback.actual)),
+        }
+    }
+}
+
+impl TryFrom<proto::WireguardBackendFallback> for talpid_types::net::wireguard::BackendFallback {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(fallback: proto::WireguardBackendFallback) -> Result<Self, Self::Error> {
+        Ok(talpid_types::net::wireguard::BackendFallback {
+            requested: try_wireguard_backend_from_i32(fallback.requested)?,
+            actual: try_wireguard_backend_from_i32(fallback.actual)?,
+        })
+    }
+}
+
+pub fn try_wireguard_backend_from_i32(
+    backend: i32,
+) -> Result<talpid_types::net::wireguard::Backend, FromProtobufTypeError> {
+    use talpid_types::net::wireguard::Backend;
+
+    match proto::WireguardBackend::try_from(backend) {
+        Ok(proto::WireguardBackend::Kernel) => Ok(Backend::Kernel),
+        Ok(proto::WireguardBackend::NetworkManager) => Ok(Backend::NetworkManager),
+        Ok(proto::WireguardBackend::Userspace) => Ok(Backend::Userspace),
+        Err(_) => Err(FromProtobufTypeError::InvalidArgument(
+            "invalid WireGuard backend",
+        )),
+    }
+}
+
 impl From<talpid_types::net::TransportProtocol> for proto::TransportProtocol {
     fn from(protocol: talpid_types::net::TransportProtocol) -> Self {
         match protocol {
@@ -207,11 +287,12 @@ pub fn try_transport_protocol_from_i32(
 }
 
 mod proxy {
-    use std::net::Ipv4Addr;
+    use std::net::{IpAddr, Ipv4Addr};
 
     use crate::types::{proto, FromProtobufTypeError};
     use talpid_types::net::proxy::{
-        CustomProxy, Shadowsocks, Socks5Local, Socks5Remote, SocksAuth,
+        CustomProxy, HttpAuth, HttpAuthScheme, HttpProxy, Shadowsocks, Socks5Local, Socks5Remote,
+        SocksAuth,
     };
 
     impl TryFrom<proto::CustomProxy> for CustomProxy {
@@ -228,6 +309,9 @@ mod proxy {
                 Some(proto::custom_proxy::ProxyMethod::Shadowsocks(shadowsocks)) => {
                     CustomProxy::Shadowsocks(Shadowsocks::try_from(shadowsocks)?)
                 }
+                Some(proto::custom_proxy::ProxyMethod::Http(http)) => {
+                    CustomProxy::Http(HttpProxy::try_from(http)?)
+                }
                 None => {
                     return Err(FromProtobufTypeError::InvalidArgument(
                         "CustomProxy missing proxy_method field",
@@ -295,6 +379,28 @@ mod proxy {
         }
     }
 
+    impl TryFrom<proto::HttpProxy> for HttpProxy {
+        type Error = FromProtobufTypeError;
+
+        fn try_from(value: proto::HttpProxy) -> Result<Self, Self::Error> {
+            let ip = value.ip.parse::<IpAddr>().map_err(|_| {
+                FromProtobufTypeError::InvalidArgument(
+                    "Could not parse HTTP proxy message from protobuf",
+                )
+            })?;
+            let port = value.port as u16;
+            let proxy = match value.auth {
+                Some(credentials) => {
+                    let auth = HttpAuth::try_from(credentials)?;
+                    HttpProxy::new_with_authentication((ip, port), auth)
+                }
+                None => HttpProxy::new((ip, port)),
+            };
+
+            Ok(proxy)
+        }
+    }
+
     impl From<CustomProxy> for proto::CustomProxy {
         fn from(value: CustomProxy) -> Self {
             proto::CustomProxy {
@@ -314,6 +420,9 @@ mod proxy {
                             config,
                         ))
                     }
+                    CustomProxy::Http(config) => {
+                        proto::custom_proxy::ProxyMethod::Http(proto::HttpProxy::from(config))
+                    }
                 }),
             }
         }
@@ -353,6 +462,52 @@ mod proxy {
         }
     }
 
+    impl From<HttpProxy> for proto::HttpProxy {
+        fn from(value: HttpProxy) -> Self {
+            proto::HttpProxy {
+                ip: value.endpoint.ip().to_string(),
+                port: value.endpoint.port() as u32,
+                auth: value.auth.map(proto::HttpAuth::from),
+            }
+        }
+    }
+
+    impl From<HttpAuth> for proto::HttpAuth {
+        fn from(value: HttpAuth) -> Self {
+            let scheme = match value.scheme() {
+                HttpAuthScheme::Basic => proto::http_auth::Scheme::Basic,
+                HttpAuthScheme::Ntlm => proto::http_auth::Scheme::Ntlm,
+            };
+            proto::HttpAuth {
+                scheme: i32::from(scheme),
+                username: value.username().to_string(),
+                password: value.password().to_string(),
+            }
+        }
+    }
+
+    impl TryFrom<proto::HttpAuth> for HttpAuth {
+        type Error = FromProtobufTypeError;
+
+        fn try_from(value: proto::HttpAuth) -> Result<Self, Self::Error> {
+            let scheme = match proto::http_auth::Scheme::try_from(value.scheme) {
+                Ok(proto::http_auth::Scheme::Basic) => HttpAuthScheme::Basic,
+                Ok(proto::http_auth::Scheme::Ntlm) => HttpAuthScheme::Ntlm,
+                Err(_) => {
+                    return Err(FromProtobufTypeError::InvalidArgument(
+                        "invalid HTTP proxy authentication scheme",
+                    ))
+                }
+            };
+            HttpAuth::new(scheme, value.username, value.password).map_err(|_| {
+                FromProtobufTypeError::InvalidArgument(
+                    "Failed to parse HTTP proxy with authentication. \
+                     Make sure the credentials are valid.",
+                )
+            })
+        }
+    }
+
     impl From<SocksAuth> for proto::SocksAuth {
         fn from(value: SocksAuth) -> Self {
             proto::SocksAuth {
@@ -375,3 +530,45 @@ mod proxy {
         }
     }
 }
+
+#[cfg(not(target_os = "android"))]
+impl From<talpid_types::net::OfflineMonitorBackend> for proto::OfflineMonitorBackend {
+    fn from(backend: talpid_types::net::OfflineMonitorBackend) -> Self {
+        use proto::offline_monitor_backend::Backend;
+        use talpid_types::net::OfflineMonitorBackend;
+
+        let backend = match backend {
+            OfflineMonitorBackend::Routes => Backend::Routes,
+            #[cfg(target_os = "linux")]
+            OfflineMonitorBackend::NetworkManager => Backend::NetworkManager,
+            OfflineMonitorBackend::Disabled => Backend::Disabled,
+        };
+        proto::OfflineMonitorBackend {
+            backend: i32::from(backend),
+        }
+    }
+}
+
+#[cfg(not(target_os = "android"))]
+impl TryFrom<proto::OfflineMonitorBackend> for talpid_types::net::OfflineMonitorBackend {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(backend: proto::OfflineMonitorBackend) -> Result<Self, Self::Error> {
+        use proto::offline_monitor_backend::Backend;
+        use talpid_types::net::OfflineMonitorBackend;
+
+        match Backend::try_from(backend.backend) {
+            Ok(Backend::Routes) => Ok(OfflineMonitorBackend::Routes),
+            #[cfg(target_os = "linux")]
+            Ok(Backend::NetworkManager) => Ok(OfflineMonitorBackend::NetworkManager),
+            #[cfg(not(target_os = "linux"))]
+            Ok(Backend::NetworkManager) => Err(FromProtobufTypeError::InvalidArgument(
+                "NetworkManager is only supported on Linux",
+            )),
+            Ok(Backend::Disabled) => Ok(OfflineMonitorBackend::Disabled),
+            Err(_) => Err(FromProtobufTypeError::InvalidArgument(
+                "invalid offline monitor backend",
+            )),
+        }
+    }
+}
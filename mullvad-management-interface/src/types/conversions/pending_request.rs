@@ -0,0 +1,84 @@
+use crate::types::{proto, FromProtobufTypeError};
+use chrono::DateTime;
+use mullvad_types::pending_request::{PendingRequest, PendingRequestKind};
+use prost_types::Timestamp;
+
+impl From<PendingRequest> for proto::PendingRequest {
+    fn from(request: PendingRequest) -> Self {
+        let kind = match request.kind {
+            PendingRequestKind::SubmitVoucher {
+                account_number,
+                voucher,
+            } => proto::pending_request::Kind::VoucherSubmission(proto::PendingVoucherSubmission {
+                account_number,
+                voucher,
+            }),
+            PendingRequestKind::RemoveDevice {
+                account_number,
+                device_id,
+            } => proto::pending_request::Kind::DeviceRemoval(proto::DeviceRemoval {
+                account_number,
+                device_id,
+            }),
+        };
+        proto::PendingRequest {
+            id: request.id,
+            created: Some(Timestamp {
+                seconds: request.created.timestamp(),
+                nanos: 0,
+            }),
+            kind: Some(kind),
+        }
+    }
+}
+
+impl TryFrom<proto::PendingRequest> for PendingRequest {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(request: proto::PendingRequest) -> Result<Self, Self::Error> {
+        let created = request
+            .created
+            .ok_or(FromProtobufTypeError::InvalidArgument(
+                "missing 'created' field",
+            ))?;
+        let created = DateTime::from_timestamp(created.seconds, created.nanos as u32)
+            .ok_or(FromProtobufTypeError::InvalidArgument("invalid timestamp"))?;
+
+        let kind = match request.kind {
+            Some(proto::pending_request::Kind::VoucherSubmission(submission)) => {
+                PendingRequestKind::SubmitVoucher {
+                    account_number: submission.account_number,
+                    voucher: submission.voucher,
+                }
+            }
+            Some(proto::pending_request::Kind::DeviceRemoval(removal)) => {
+                PendingRequestKind::RemoveDevice {
+                    account_number: removal.account_number,
+                    device_id: removal.device_id,
+                }
+            }
+            None => {
+                return Err(FromProtobufTypeError::InvalidArgument(
+                    "missing pending request kind",
+                ))
+            }
+        };
+
+        Ok(PendingRequest {
+            id: request.id,
+            created,
+            kind,
+        })
+    }
+}
+
+impl From<Vec<PendingRequest>> for proto::PendingRequestList {
+    fn from(requests: Vec<PendingRequest>) -> Self {
+        proto::PendingRequestList {
+            requests: requests
+                .into_iter()
+                .map(proto::PendingRequest::from)
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use crate::types::{proto, FromProtobufTypeError};
+use mullvad_types::profile::Profile;
+
+impl From<&Profile> for proto::Profile {
+    fn from(profile: &Profile) -> Self {
+        #[cfg(any(windows, target_os = "android", target_os = "macos"))]
+        let split_tunnel = Some(proto::SplitTunnelSettings::from(&profile.split_tunnel));
+        #[cfg(target_os = "linux")]
+        let split_tunnel = None;
+
+        Self {
+            name: profile.name.clone(),
+            relay_settings: Some(proto::RelaySettings::from(profile.relay_settings.clone())),
+            obfuscation_settings: Some(proto::ObfuscationSettings::from(
+                &profile.obfuscation_settings,
+            )),
+            dns_options: Some(proto::DnsOptions::from(&profile.dns_options)),
+            split_tunnel,
+        }
+    }
+}
+
+impl TryFrom<proto::Profile> for Profile {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(profile: proto::Profile) -> Result<Self, Self::Error> {
+        let relay_settings =
+            profile
+                .relay_settings
+                .ok_or(FromProtobufTypeError::InvalidArgument(
+                    "missing profile relay settings",
+                ))?;
+        let obfuscation_settings =
+            profile
+                .obfuscation_settings
+                .ok_or(FromProtobufTypeError::InvalidArgument(
+                    "missing profile obfuscation settings",
+                ))?;
+        let dns_options = profile
+            .dns_options
+            .ok_or(FromProtobufTypeError::InvalidArgument(
+                "missing profile DNS options",
+            ))?;
+
+        Ok(Self {
+            name: profile.name,
+            relay_settings: mullvad_types::relay_constraints::RelaySettings::try_from(
+                relay_settings,
+            )?,
+            obfuscation_settings: mullvad_types::relay_constraints::ObfuscationSettings::try_from(
+                obfuscation_settings,
+            )?,
+            dns_options: mullvad_types::settings::DnsOptions::try_from(dns_options)?,
+            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
+            split_tunnel: profile
+                .split_tunnel
+                .map(mullvad_types::settings::SplitTunnelSettings::from)
+                .unwrap_or_default(),
+        })
+    }
+}
@@ -25,6 +25,13 @@ impl TryFrom<&proto::WireguardConstraints>
             None => None,
         };
 
+        let middle_hops = u8::try_from(constraints.middle_hops)
+            .ok()
+            .filter(|&hops| hops <= mullvad_constraints::MAX_MIDDLE_HOPS)
+            .ok_or(FromProtobufTypeError::InvalidArgument(
+                "too many middle hops",
+            ))?;
+
         Ok(mullvad_constraints::WireguardConstraints {
             port: Constraint::from(constraints.port.map(|port| port as u16)),
             ip_version: Constraint::from(ip_version),
@@ -39,6 +46,8 @@ impl TryFrom<&proto::WireguardConstraints>
                     .ok()
                 })
                 .unwrap_or(Constraint::Any),
+            middle_hops,
+            multihop_diversity: constraints.multihop_diversity,
         })
     }
 }
@@ -96,6 +105,12 @@ impl TryFrom<proto::RelaySettings> for mullvad_types::relay_constraints::RelaySe
                     .and_then(|loc| Constraint::<mullvad_types::relay_constraints::LocationConstraint>::try_from(loc).ok())
                     .unwrap_or(Constraint::Any);
                 let providers = try_providers_constraint_from_proto(&settings.providers)?;
+                let asns = settings
+                    .asns
+                    .map(mullvad_constraints::AsnFilter::try_from)
+                    .transpose()?
+                    .map(Constraint::Only)
+                    .unwrap_or(Constraint::Any);
                 let ownership = try_ownership_constraint_from_i32(settings.ownership)?;
                 let tunnel_protocol = try_tunnel_type_from_i32(settings.tunnel_type)?;
 
@@ -115,6 +130,7 @@ impl TryFrom<proto::RelaySettings> for mullvad_types::relay_constraints::RelaySe
                     mullvad_constraints::RelayConstraints {
                         location,
                         providers,
+                        asns,
                         ownership,
                         tunnel_protocol,
                         wireguard_constraints,
@@ -151,11 +167,23 @@ impl From<&mullvad_types::relay_constraints::ObfuscationSettings> for proto::Obf
             SelectedObfuscation::Shadowsocks => {
                 proto::obfuscation_settings::SelectedObfuscation::Shadowsocks
             }
+            SelectedObfuscation::PortHopping => {
+                proto::obfuscation_settings::SelectedObfuscation::PortHopping
+            }
+            SelectedObfuscation::Tls => proto::obfuscation_settings::SelectedObfuscation::Tls,
+            SelectedObfuscation::CustomBridge => {
+                proto::obfuscation_settings::SelectedObfuscation::CustomBridge
+            }
         });
         Self {
             selected_obfuscation,
             udp2tcp: Some(proto::Udp2TcpObfuscationSettings::from(&settings.udp2tcp)),
             shadowsocks: Some(proto::ShadowsocksSettings::from(&settings.shadowsocks)),
+            port_hopping: Some(proto::PortHoppingSettings::from(&settings.port_hopping)),
+            custom_bridge: settings
+                .custom_bridge
+                .as_ref()
+                .map(proto::CustomObfuscationBridge::from),
         }
     }
 }
@@ -180,6 +208,15 @@ impl From<&mullvad_types::relay_constraints::ShadowsocksSettings> for proto::Sha
     fn from(settings: &mullvad_types::relay_constraints::ShadowsocksSettings) -> Self {
         Self {
             port: settings.port.map(u32::from).option(),
+            cipher: settings.cipher.map(|cipher| cipher.to_string()).option(),
+        }
+    }
+}
+
+impl From<&mullvad_types::relay_constraints::PortHoppingSettings> for proto::PortHoppingSettings {
+    fn from(settings: &mullvad_types::relay_constraints::PortHoppingSettings) -> Self {
+        Self {
+            interval_minutes: u32::from(settings.interval_minutes),
         }
     }
 }
@@ -237,6 +274,7 @@ impl From<mullvad_types::relay_constraints::RelaySettings> for proto::RelaySetti
                         .option()
                         .map(proto::LocationConstraint::from),
                     providers: convert_providers_constraint(&constraints.providers),
+                    asns: constraints.asns.option().map(proto::AsnFilter::from),
                     ownership: convert_ownership_constraint(&constraints.ownership) as i32,
                     tunnel_type: constraints.tunnel_protocol as i32,
 
@@ -257,6 +295,8 @@ impl From<mullvad_types::relay_constraints::RelaySettings> for proto::RelaySetti
                             .entry_location
                             .option()
                             .map(proto::LocationConstraint::from),
+                        middle_hops: u32::from(constraints.wireguard_constraints.middle_hops),
+                        multihop_diversity: constraints.wireguard_constraints.multihop_diversity,
                     }),
 
                     openvpn_constraints: Some(proto::OpenvpnConstraints {
@@ -437,6 +477,9 @@ impl TryFrom<proto::ObfuscationSettings> for mullvad_types::relay_constraints::O
                 Ok(IpcSelectedObfuscation::Off) => SelectedObfuscation::Off,
                 Ok(IpcSelectedObfuscation::Udp2tcp) => SelectedObfuscation::Udp2Tcp,
                 Ok(IpcSelectedObfuscation::Shadowsocks) => SelectedObfuscation::Shadowsocks,
+                Ok(IpcSelectedObfuscation::PortHopping) => SelectedObfuscation::PortHopping,
+                Ok(IpcSelectedObfuscation::Tls) => SelectedObfuscation::Tls,
+                Ok(IpcSelectedObfuscation::CustomBridge) => SelectedObfuscation::CustomBridge,
                 Err(_) => {
                     return Err(FromProtobufTypeError::InvalidArgument(
                         "invalid obfuscation settings",
@@ -464,12 +507,33 @@ impl TryFrom<proto::ObfuscationSettings> for mullvad_types::relay_constraints::O
                 ));
             }
         };
+        let port_hopping = match settings.port_hopping {
+            Some(settings) => {
+                mullvad_types::relay_constraints::PortHoppingSettings::try_from(&settings)?
+            }
+            None => {
+                return Err(FromProtobufTypeError::InvalidArgument(
+                    "invalid port hopping settings",
+                ));
+            }
+        };
 
-        Ok(Self {
+        let custom_bridge = settings
+            .custom_bridge
+            .map(mullvad_types::relay_constraints::CustomObfuscationBridge::try_from)
+            .transpose()?;
+
+        let settings = Self {
             selected_obfuscation,
             udp2tcp,
             shadowsocks,
-        })
+            port_hopping,
+            custom_bridge,
+        };
+        settings
+            .validate()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid custom bridge"))?;
+        Ok(settings)
     }
 }
 
@@ -491,12 +555,108 @@ impl TryFrom<&proto::ShadowsocksSettings>
     type Error = FromProtobufTypeError;
 
     fn try_from(settings: &proto::ShadowsocksSettings) -> Result<Self, Self::Error> {
+        let cipher = settings
+            .cipher
+            .as_deref()
+            .map(str::parse::<mullvad_types::relay_constraints::ShadowsocksCipher>)
+            .transpose()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid shadowsocks cipher"))?;
         Ok(Self {
             port: Constraint::from(settings.port.map(|port| port as u16)),
+            cipher: Constraint::from(cipher),
         })
     }
 }
 
+impl From<&mullvad_types::relay_constraints::CustomObfuscationBridge>
+    for proto::CustomObfuscationBridge
+{
+    fn from(bridge: &mullvad_types::relay_constraints::CustomObfuscationBridge) -> Self {
+        use mullvad_types::relay_constraints::CustomObfuscationBridge;
+        let bridge = match bridge {
+            CustomObfuscationBridge::Udp2Tcp { endpoint } => {
+                proto::custom_obfuscation_bridge::Bridge::Udp2tcp(
+                    proto::custom_obfuscation_bridge::Udp2Tcp {
+                        ip: endpoint.ip().to_string(),
+                        port: u32::from(endpoint.port()),
+                    },
+                )
+            }
+            CustomObfuscationBridge::Shadowsocks {
+                endpoint,
+                password,
+                cipher,
+            } => proto::custom_obfuscation_bridge::Bridge::Shadowsocks(proto::Shadowsocks {
+                ip: endpoint.ip().to_string(),
+                port: u32::from(endpoint.port()),
+                password: password.clone(),
+                cipher: cipher.to_string(),
+            }),
+        };
+        Self {
+            bridge: Some(bridge),
+        }
+    }
+}
+
+impl TryFrom<proto::CustomObfuscationBridge>
+    for mullvad_types::relay_constraints::CustomObfuscationBridge
+{
+    type Error = FromProtobufTypeError;
+
+    fn try_from(bridge: proto::CustomObfuscationBridge) -> Result<Self, Self::Error> {
+        let parse_endpoint = |ip: &str, port: u32| {
+            let ip = ip.parse::<std::net::IpAddr>().map_err(|_| {
+                FromProtobufTypeError::InvalidArgument("invalid custom bridge address")
+            })?;
+            let port = u16::try_from(port).map_err(|_| {
+                FromProtobufTypeError::InvalidArgument("invalid custom bridge port")
+            })?;
+            Ok(std::net::SocketAddr::new(ip, port))
+        };
+
+        match bridge.bridge {
+            Some(proto::custom_obfuscation_bridge::Bridge::Udp2tcp(bridge)) => Ok(Self::Udp2Tcp {
+                endpoint: parse_endpoint(&bridge.ip, bridge.port)?,
+            }),
+            Some(proto::custom_obfuscation_bridge::Bridge::Shadowsocks(bridge)) => {
+                Ok(Self::Shadowsocks {
+                    endpoint: parse_endpoint(&bridge.ip, bridge.port)?,
+                    cipher: bridge.cipher.parse().map_err(|_| {
+                        FromProtobufTypeError::InvalidArgument("invalid shadowsocks cipher")
+                    })?,
+                    password: bridge.password,
+                })
+            }
+            None => Err(FromProtobufTypeError::InvalidArgument(
+                "missing custom bridge",
+            )),
+        }
+    }
+}
+
+impl TryFrom<&proto::PortHoppingSettings>
+    for mullvad_types::relay_constraints::PortHoppingSettings
+{
+    type Error = FromProtobufTypeError;
+
+    fn try_from(settings: &proto::PortHoppingSettings) -> Result<Self, Self::Error> {
+        use mullvad_types::relay_constraints::{
+            MAX_PORT_HOPPING_INTERVAL_MINUTES, MIN_PORT_HOPPING_INTERVAL_MINUTES,
+        };
+        let interval_minutes = u16::try_from(settings.interval_minutes)
+            .ok()
+            .filter(|interval| {
+                (MIN_PORT_HOPPING_INTERVAL_MINUTES..=MAX_PORT_HOPPING_INTERVAL_MINUTES)
+                    .contains(interval)
+            })
+            .ok_or(FromProtobufTypeError::InvalidArgument(
+                "invalid port hopping interval",
+            ))?;
+        Ok(Self { interval_minutes })
+    }
+}
+
 impl TryFrom<proto::BridgeState> for mullvad_types::relay_constraints::BridgeState {
     type Error = FromProtobufTypeError;
 
@@ -565,6 +725,41 @@ impl TryFrom<proto::RelayOverride> for mullvad_types::relay_constraints::RelayOv
     }
 }
 
+impl From<mullvad_types::relay_constraints::AsnFilter> for proto::AsnFilter {
+    fn from(filter: mullvad_types::relay_constraints::AsnFilter) -> Self {
+        use mullvad_types::relay_constraints::AsnFilter;
+
+        let (asns, except) = match filter {
+            AsnFilter::Only(asns) => (asns, false),
+            AsnFilter::Except(asns) => (asns, true),
+        };
+        Self {
+            asns: asns.into_iter().collect(),
+            except,
+        }
+    }
+}
+
+impl TryFrom<proto::AsnFilter> for mullvad_types::relay_constraints::AsnFilter {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(filter: proto::AsnFilter) -> Result<Self, Self::Error> {
+        use mullvad_types::relay_constraints::AsnFilter;
+
+        if filter.asns.is_empty() {
+            return Err(FromProtobufTypeError::InvalidArgument(
+                "must specify at least one ASN",
+            ));
+        }
+        let asns = filter.asns.into_iter().collect();
+        if filter.except {
+            Ok(AsnFilter::Except(asns))
+        } else {
+            Ok(AsnFilter::Only(asns))
+        }
+    }
+}
+
 pub fn try_providers_constraint_from_proto(
     providers: &[String],
 ) -> Result<Constraint<mullvad_types::relay_constraints::Providers>, FromProtobufTypeError> {
@@ -123,6 +123,7 @@ impl From<mullvad_types::relay_list::Relay> for proto::Relay {
             active: relay.active,
             owned: relay.owned,
             provider: relay.provider,
+            asn: relay.asn,
             weight: relay.weight,
             endpoint_type: match &relay.endpoint_data {
                 MullvadEndpointData::Openvpn => proto::relay::RelayType::Openvpn as i32,
@@ -180,6 +181,7 @@ impl TryFrom<proto::RelayList> for mullvad_types::relay_list::RelayList {
 
         Ok(mullvad_types::relay_list::RelayList {
             etag: None,
+            last_modified: None,
             countries,
             openvpn: mullvad_types::relay_list::OpenVpnEndpointData::try_from(openvpn)?,
             bridge: mullvad_types::relay_list::BridgeEndpointData::try_from(bridge)?,
@@ -297,6 +299,7 @@ impl TryFrom<proto::Relay> for mullvad_types::relay_list::Relay {
             active: relay.active,
             owned: relay.owned,
             provider: relay.provider,
+            asn: relay.asn,
             weight: relay.weight,
             endpoint_data,
             location: relay
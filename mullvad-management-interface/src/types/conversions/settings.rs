@@ -5,25 +5,7 @@ use talpid_types::ErrorExt;
 impl From<&mullvad_types::settings::Settings> for proto::Settings {
     fn from(settings: &mullvad_types::settings::Settings) -> Self {
         #[cfg(any(windows, target_os = "android", target_os = "macos"))]
-        let split_tunnel = {
-            let apps = settings
-                .split_tunnel
-                .apps
-                .iter()
-                .filter_map(|app| match app.clone().to_string() {
-                    None => {
-                        log::error!("Failed to convert application to string: {:?}", app);
-                        None
-                    }
-                    string => string,
-                })
-                .collect();
-
-            Some(proto::SplitTunnelSettings {
-                enable_exclusions: settings.split_tunnel.enable_exclusions,
-                apps,
-            })
-        };
+        let split_tunnel = Some(proto::SplitTunnelSettings::from(&settings.split_tunnel));
         #[cfg(target_os = "linux")]
         let split_tunnel = None;
 
@@ -33,14 +15,95 @@ impl From<&mullvad_types::settings::Settings> for proto::Settings {
                 settings.bridge_settings.clone(),
             )),
             bridge_state: Some(proto::BridgeState::from(settings.bridge_state)),
-            allow_lan: settings.allow_lan,
+            allow_lan: settings.allow_lan.is_allowed(),
+            lan_sharing: Some(proto::LanSharing::from(settings.allow_lan.clone())),
             #[cfg(not(target_os = "android"))]
             block_when_disconnected: settings.block_when_disconnected,
             #[cfg(target_os = "android")]
             block_when_disconnected: false,
+            #[cfg(not(target_os = "android"))]
+            block_all: settings.block_all,
+            #[cfg(target_os = "android")]
+            block_all: false,
+            #[cfg(not(target_os = "android"))]
+            firewall_exceptions: settings
+                .firewall_exceptions
+                .iter()
+                .copied()
+                .map(proto::FirewallException::from)
+                .collect(),
+            #[cfg(target_os = "android")]
+            firewall_exceptions: vec![],
+            #[cfg(not(target_os = "android"))]
+            allowed_incoming: settings
+                .allowed_incoming
+                .iter()
+                .cloned()
+                .map(proto::AllowedIncoming::from)
+                .collect(),
+            #[cfg(target_os = "android")]
+            allowed_incoming: vec![],
+            #[cfg(not(target_os = "android"))]
+            state_hooks: Some(proto::StateHooks::from(&settings.state_hooks)),
+            #[cfg(target_os = "android")]
+            state_hooks: None,
+            #[cfg(not(target_os = "android"))]
+            metrics: Some(proto::MetricsSettings::from(&settings.metrics)),
+            #[cfg(target_os = "android")]
+            metrics: None,
+            #[cfg(target_os = "linux")]
+            tunnel_fwmark: settings.tunnel_fwmark,
+            #[cfg(not(target_os = "linux"))]
+            tunnel_fwmark: None,
+            #[cfg(target_os = "linux")]
+            nftables: Some(proto::NftablesSettings::from(&settings.nftables)),
+            #[cfg(not(target_os = "linux"))]
+            nftables: None,
+            #[cfg(target_os = "linux")]
+            routing: Some(proto::RoutingSettings::from(&settings.routing)),
+            #[cfg(not(target_os = "linux"))]
+            routing: None,
+            #[cfg(not(target_os = "android"))]
+            offline_monitor: Some(proto::OfflineMonitorBackend::from(settings.offline_monitor)),
+            #[cfg(target_os = "android")]
+            offline_monitor: None,
+            #[cfg(windows)]
+            wfp_sublayer_weight: settings.wfp_sublayer_weight.map(u32::from),
+            #[cfg(not(windows))]
+            wfp_sublayer_weight: None,
+            #[cfg(target_os = "macos")]
+            pf_anchor: settings.pf_anchor.clone(),
+            #[cfg(not(target_os = "macos"))]
+            pf_anchor: None,
+            #[cfg(target_os = "linux")]
+            dns_backends: Some(proto::DnsBackends::from(settings.dns_backends.as_slice())),
+            #[cfg(not(target_os = "linux"))]
+            dns_backends: None,
+            #[cfg(target_os = "linux")]
+            split_tunnel_mode: Some(proto::SplitTunnelMode::from(settings.split_tunnel_mode)),
+            #[cfg(not(target_os = "linux"))]
+            split_tunnel_mode: None,
+            #[cfg(target_os = "linux")]
+            split_tunnel_owners: settings
+                .split_tunnel_owners
+                .iter()
+                .copied()
+                .map(proto::SplitTunnelOwner::from)
+                .collect(),
+            #[cfg(not(target_os = "linux"))]
+            split_tunnel_owners: vec![],
+            #[cfg(target_os = "linux")]
+            split_tunnel_domains: settings.split_tunnel_domains.clone(),
+            #[cfg(not(target_os = "linux"))]
+            split_tunnel_domains: vec![],
             auto_connect: settings.auto_connect,
             tunnel_options: Some(proto::TunnelOptions::from(&settings.tunnel_options)),
             show_beta_releases: settings.show_beta_releases,
+            prefer_low_latency_relays: settings.prefer_low_latency_relays,
+            sticky_relays: settings.sticky_relays,
+            relay_list_update_interval_minutes: u32::from(
+                settings.relay_list_update_interval_minutes,
+            ),
             obfuscation_settings: Some(proto::ObfuscationSettings::from(
                 &settings.obfuscation_settings,
             )),
@@ -48,6 +111,16 @@ impl From<&mullvad_types::settings::Settings> for proto::Settings {
             custom_lists: Some(proto::CustomListSettings::from(
                 settings.custom_lists.clone(),
             )),
+            custom_relays: settings
+                .custom_relays
+                .clone()
+                .into_iter()
+                .map(proto::CustomRelay::from)
+                .collect(),
+            profiles: settings.profiles.iter().map(proto::Profile::from).collect(),
+            excluded_locations: Some(proto::ExcludedLocations::from(
+                settings.excluded_locations.clone(),
+            )),
             api_access_methods: Some(proto::ApiAccessMethodSettings::from(
                 settings.api_access_methods.clone(),
             )),
@@ -85,7 +158,75 @@ impl From<&mullvad_types::settings::DnsOptions> for proto::DnsOptions {
                     .iter()
                     .map(|addr| addr.to_string())
                     .collect(),
+                encrypted_servers: options
+                    .custom_options
+                    .encrypted_servers
+                    .iter()
+                    .map(proto::EncryptedDnsServer::from)
+                    .collect(),
             }),
+            split_dns_rules: options
+                .split_dns_rules
+                .iter()
+                .map(|rule| proto::SplitDnsRule {
+                    domain: rule.domain.clone(),
+                    servers: rule.servers.iter().map(|addr| addr.to_string()).collect(),
+                })
+                .collect(),
+            filter_lists: options
+                .filter_lists
+                .iter()
+                .map(proto::DnsFilterList::from)
+                .collect(),
+        }
+    }
+}
+
+impl From<&mullvad_types::dns_filter::FilterListSource> for proto::DnsFilterList {
+    fn from(source: &mullvad_types::dns_filter::FilterListSource) -> Self {
+        use mullvad_types::dns_filter::FilterListSource;
+        use proto::dns_filter_list::Source;
+
+        let source = match source {
+            FilterListSource::Url(url) => Source::Url(url.clone()),
+            FilterListSource::File(path) => Source::Path(path.to_string_lossy().into_owned()),
+        };
+        proto::DnsFilterList {
+            source: Some(source),
+        }
+    }
+}
+
+impl TryFrom<proto::DnsFilterList> for mullvad_types::dns_filter::FilterListSource {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(list: proto::DnsFilterList) -> Result<Self, Self::Error> {
+        use proto::dns_filter_list::Source;
+
+        match list.source {
+            Some(Source::Url(url)) => Ok(Self::Url(url)),
+            Some(Source::Path(path)) => Ok(Self::File(path.into())),
+            None => Err(FromProtobufTypeError::InvalidArgument(
+                "missing DNS filter list source",
+            )),
+        }
+    }
+}
+
+impl From<&talpid_types::dns::EncryptedDnsServer> for proto::EncryptedDnsServer {
+    fn from(server: &talpid_types::dns::EncryptedDnsServer) -> Self {
+        use proto::encrypted_dns_server::Protocol;
+        use talpid_types::dns::EncryptedDnsProtocol;
+
+        proto::EncryptedDnsServer {
+            address: server.address.to_string(),
+            protocol: match server.protocol {
+                EncryptedDnsProtocol::Tls => Protocol::Tls as i32,
+                EncryptedDnsProtocol::Https => Protocol::Https as i32,
+            },
+            hostname: server.hostname.clone(),
+            port: server.port.map(u32::from),
+            spki_pin: server.spki_pin.clone(),
         }
     }
 }
@@ -98,11 +239,28 @@ impl From<&mullvad_types::settings::TunnelOptions> for proto::TunnelOptions {
             }),
             wireguard: Some(proto::tunnel_options::WireguardOptions {
                 mtu: options.wireguard.mtu.map(u32::from),
+                persistent_keepalive: options.wireguard.persistent_keepalive.map(u32::from),
+                #[cfg(target_os = "linux")]
+                outgoing_interface: options.wireguard.outgoing_interface.clone(),
+                #[cfg(not(target_os = "linux"))]
+                outgoing_interface: None,
+                #[cfg(target_os = "linux")]
+                backend: options
+                    .wireguard
+                    .backend
+                    .map(|backend| i32::from(proto::WireguardBackend::from(backend))),
+                #[cfg(not(target_os = "linux"))]
+                backend: None,
+                worker_threads: options.wireguard.worker_threads,
                 rotation_interval: options.wireguard.rotation_interval.map(|ivl| {
                     prost_types::Duration::try_from(std::time::Duration::from(ivl))
                         .expect("Failed to convert std::time::Duration to prost_types::Duration for tunnel_options.wireguard.rotation_interval")
                 }),
                 quantum_resistant: Some(proto::QuantumResistantState::from(options.wireguard.quantum_resistant)),
+                quantum_resistant_kem: Some(proto::QuantumResistantKem::from(options.wireguard.quantum_resistant_kem)),
+                negotiation_retry_policy: Some(proto::NegotiationRetryPolicy::from(options.wireguard.negotiation_retry_policy)),
+                connectivity_check: Some(proto::ConnectivityCheckOptions::from(options.wireguard.connectivity_check)),
+                ipv6_only: options.wireguard.ipv6_only,
                 #[cfg(daita)]
                 daita: Some(proto::DaitaSettings::from(options.wireguard.daita.clone())),
                 #[cfg(not(daita))]
@@ -110,6 +268,14 @@ impl From<&mullvad_types::settings::TunnelOptions> for proto::TunnelOptions {
             }),
             generic: Some(proto::tunnel_options::GenericOptions {
                 enable_ipv6: options.generic.enable_ipv6,
+                block_ipv6: options.generic.block_ipv6,
+                custom_routes: options
+                    .generic
+                    .custom_routes
+                    .iter()
+                    .copied()
+                    .map(proto::CustomRoute::from)
+                    .collect(),
             }),
             dns_options: Some(proto::DnsOptions::from(&options.dns_options)),
         }
@@ -156,6 +322,12 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
                 .ok_or(FromProtobufTypeError::InvalidArgument(
                     "missing custom lists settings",
                 ))?;
+        let excluded_locations =
+            settings
+                .excluded_locations
+                .ok_or(FromProtobufTypeError::InvalidArgument(
+                    "missing excluded locations",
+                ))?;
         let api_access_methods_settings =
             settings
                 .api_access_methods
@@ -177,9 +349,87 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
                 bridge_settings,
             )?,
             bridge_state,
-            allow_lan: settings.allow_lan,
+            // Older daemons only send the boolean
+            allow_lan: match settings.lan_sharing {
+                Some(lan_sharing) => talpid_types::net::AllowedLan::try_from(lan_sharing)?,
+                None => talpid_types::net::AllowedLan::from(settings.allow_lan),
+            },
             #[cfg(not(target_os = "android"))]
             block_when_disconnected: settings.block_when_disconnected,
+            #[cfg(not(target_os = "android"))]
+            block_all: settings.block_all,
+            #[cfg(not(target_os = "android"))]
+            firewall_exceptions: settings
+                .firewall_exceptions
+                .into_iter()
+                .map(talpid_types::net::FirewallException::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+            #[cfg(not(target_os = "android"))]
+            allowed_incoming: settings
+                .allowed_incoming
+                .into_iter()
+                .map(talpid_types::net::AllowedIncoming::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+            #[cfg(not(target_os = "android"))]
+            state_hooks: settings
+                .state_hooks
+                .map(mullvad_types::state_hooks::StateHooks::from)
+                .unwrap_or_default(),
+            #[cfg(not(target_os = "android"))]
+            metrics: settings
+                .metrics
+                .map(mullvad_types::settings::MetricsSettings::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+            #[cfg(target_os = "linux")]
+            tunnel_fwmark: settings.tunnel_fwmark,
+            #[cfg(target_os = "linux")]
+            nftables: settings
+                .nftables
+                .map(mullvad_types::settings::NftablesSettings::from)
+                .unwrap_or_default(),
+            #[cfg(target_os = "linux")]
+            routing: settings
+                .routing
+                .map(mullvad_types::settings::RoutingSettings::from)
+                .unwrap_or_default(),
+            #[cfg(not(target_os = "android"))]
+            offline_monitor: settings
+                .offline_monitor
+                .map(talpid_types::net::OfflineMonitorBackend::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+            #[cfg(windows)]
+            wfp_sublayer_weight: settings
+                .wfp_sublayer_weight
+                .map(|weight| {
+                    u16::try_from(weight).map_err(|_| {
+                        FromProtobufTypeError::InvalidArgument("invalid WFP sublayer weight")
+                    })
+                })
+                .transpose()?,
+            #[cfg(target_os = "macos")]
+            pf_anchor: settings.pf_anchor,
+            #[cfg(target_os = "linux")]
+            dns_backends: settings
+                .dns_backends
+                .map(Vec::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+            #[cfg(target_os = "linux")]
+            split_tunnel_mode: settings
+                .split_tunnel_mode
+                .map(talpid_types::split_tunnel::SplitTunnelMode::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+            #[cfg(target_os = "linux")]
+            split_tunnel_owners: settings
+                .split_tunnel_owners
+                .into_iter()
+                .map(talpid_types::split_tunnel::SplitTunnelOwner::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
+            #[cfg(target_os = "linux")]
+            split_tunnel_domains: settings.split_tunnel_domains,
             auto_connect: settings.auto_connect,
             tunnel_options: mullvad_types::settings::TunnelOptions::try_from(tunnel_options)?,
             relay_overrides: settings
@@ -188,6 +438,14 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
                 .map(mullvad_types::relay_constraints::RelayOverride::try_from)
                 .collect::<Result<Vec<_>, _>>()?,
             show_beta_releases: settings.show_beta_releases,
+            prefer_low_latency_relays: settings.prefer_low_latency_relays,
+            sticky_relays: settings.sticky_relays,
+            relay_list_update_interval_minutes: u16::try_from(
+                settings.relay_list_update_interval_minutes,
+            )
+            .map_err(|_| {
+                FromProtobufTypeError::InvalidArgument("invalid relay list update interval")
+            })?,
             #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             split_tunnel: mullvad_types::settings::SplitTunnelSettings::from(split_tunnel),
             obfuscation_settings: mullvad_types::relay_constraints::ObfuscationSettings::try_from(
@@ -199,6 +457,21 @@ impl TryFrom<proto::Settings> for mullvad_types::settings::Settings {
             custom_lists: mullvad_types::custom_list::CustomListsSettings::try_from(
                 custom_lists_settings,
             )?,
+            custom_relays: settings
+                .custom_relays
+                .into_iter()
+                .map(mullvad_types::custom_relay::CustomRelay::try_from)
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+            profiles: settings
+                .profiles
+                .into_iter()
+                .map(mullvad_types::profile::Profile::try_from)
+                .collect::<Result<Vec<_>, _>>()?
+                .into(),
+            excluded_locations: mullvad_types::excluded_locations::ExcludedLocations::try_from(
+                excluded_locations,
+            )?,
             api_access_methods: mullvad_types::access_method::Settings::try_from(
                 api_access_methods_settings,
             )?,
@@ -223,6 +496,89 @@ pub fn try_bridge_state_from_i32(
     }
 }
 
+impl From<&mullvad_types::settings::MetricsSettings> for proto::MetricsSettings {
+    fn from(settings: &mullvad_types::settings::MetricsSettings) -> Self {
+        proto::MetricsSettings {
+            enabled: settings.enabled,
+            port: u32::from(settings.port),
+        }
+    }
+}
+
+impl TryFrom<proto::MetricsSettings> for mullvad_types::settings::MetricsSettings {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(settings: proto::MetricsSettings) -> Result<Self, Self::Error> {
+        Ok(mullvad_types::settings::MetricsSettings {
+            enabled: settings.enabled,
+            port: u16::try_from(settings.port)
+                .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid metrics port"))?,
+        })
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<&mullvad_types::settings::NftablesSettings> for proto::NftablesSettings {
+    fn from(settings: &mullvad_types::settings::NftablesSettings) -> Self {
+        proto::NftablesSettings {
+            priority: settings.priority,
+            cooperative: settings.cooperative,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<proto::NftablesSettings> for mullvad_types::settings::NftablesSettings {
+    fn from(settings: proto::NftablesSettings) -> Self {
+        mullvad_types::settings::NftablesSettings {
+            priority: settings.priority,
+            cooperative: settings.cooperative,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<&mullvad_types::settings::RoutingSettings> for proto::RoutingSettings {
+    fn from(settings: &mullvad_types::settings::RoutingSettings) -> Self {
+        proto::RoutingSettings {
+            table_id: settings.table_id,
+            rule_priority: settings.rule_priority,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<proto::RoutingSettings> for mullvad_types::settings::RoutingSettings {
+    fn from(settings: proto::RoutingSettings) -> Self {
+        mullvad_types::settings::RoutingSettings {
+            table_id: settings.table_id,
+            rule_priority: settings.rule_priority,
+        }
+    }
+}
+
+#[cfg(any(windows, target_os = "android", target_os = "macos"))]
+impl From<&mullvad_types::settings::SplitTunnelSettings> for proto::SplitTunnelSettings {
+    fn from(settings: &mullvad_types::settings::SplitTunnelSettings) -> Self {
+        let apps = settings
+            .apps
+            .iter()
+            .filter_map(|app| match app.clone().to_string() {
+                None => {
+                    log::error!("Failed to convert application to string: {:?}", app);
+                    None
+                }
+                string => string,
+            })
+            .collect();
+
+        proto::SplitTunnelSettings {
+            enable_exclusions: settings.enable_exclusions,
+            apps,
+        }
+    }
+}
+
 #[cfg(any(windows, target_os = "android", target_os = "macos"))]
 impl From<proto::SplitTunnelSettings> for mullvad_types::settings::SplitTunnelSettings {
     fn from(value: proto::SplitTunnelSettings) -> Self {
@@ -234,6 +590,92 @@ impl From<proto::SplitTunnelSettings> for mullvad_types::settings::SplitTunnelSe
     }
 }
 
+#[cfg(target_os = "linux")]
+impl From<talpid_types::split_tunnel::SplitTunnelMode> for proto::SplitTunnelMode {
+    fn from(mode: talpid_types::split_tunnel::SplitTunnelMode) -> Self {
+        use talpid_types::split_tunnel::SplitTunnelMode;
+
+        let mode = match mode {
+            SplitTunnelMode::Exclude => proto::split_tunnel_mode::Mode::Exclude,
+            SplitTunnelMode::IncludeOnly => proto::split_tunnel_mode::Mode::IncludeOnly,
+        };
+        proto::SplitTunnelMode { mode: mode as i32 }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl TryFrom<proto::SplitTunnelMode> for talpid_types::split_tunnel::SplitTunnelMode {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(value: proto::SplitTunnelMode) -> Result<Self, Self::Error> {
+        use talpid_types::split_tunnel::SplitTunnelMode;
+
+        match proto::split_tunnel_mode::Mode::try_from(value.mode) {
+            Ok(proto::split_tunnel_mode::Mode::Exclude) => Ok(SplitTunnelMode::Exclude),
+            Ok(proto::split_tunnel_mode::Mode::IncludeOnly) => Ok(SplitTunnelMode::IncludeOnly),
+            Err(_) => Err(FromProtobufTypeError::InvalidArgument(
+                "invalid split tunnel mode",
+            )),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<talpid_types::split_tunnel::SplitTunnelOwner> for proto::SplitTunnelOwner {
+    fn from(owner: talpid_types::split_tunnel::SplitTunnelOwner) -> Self {
+        use talpid_types::split_tunnel::SplitTunnelOwner;
+
+        let owner = match owner {
+            SplitTunnelOwner::User(uid) => proto::split_tunnel_owner::Owner::Uid(uid),
+            SplitTunnelOwner::Group(gid) => proto::split_tunnel_owner::Owner::Gid(gid),
+        };
+        proto::SplitTunnelOwner { owner: Some(owner) }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl TryFrom<proto::SplitTunnelOwner> for talpid_types::split_tunnel::SplitTunnelOwner {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(value: proto::SplitTunnelOwner) -> Result<Self, Self::Error> {
+        use talpid_types::split_tunnel::SplitTunnelOwner;
+
+        match value.owner {
+            Some(proto::split_tunnel_owner::Owner::Uid(uid)) => Ok(SplitTunnelOwner::User(uid)),
+            Some(proto::split_tunnel_owner::Owner::Gid(gid)) => Ok(SplitTunnelOwner::Group(gid)),
+            None => Err(FromProtobufTypeError::InvalidArgument(
+                "missing split tunnel user or group",
+            )),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl From<&[talpid_types::split_tunnel::SplitTunnelOwner]> for proto::SplitTunnelOwners {
+    fn from(owners: &[talpid_types::split_tunnel::SplitTunnelOwner]) -> Self {
+        proto::SplitTunnelOwners {
+            owners: owners
+                .iter()
+                .copied()
+                .map(proto::SplitTunnelOwner::from)
+                .collect(),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl TryFrom<proto::SplitTunnelOwners> for Vec<talpid_types::split_tunnel::SplitTunnelOwner> {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(value: proto::SplitTunnelOwners) -> Result<Self, Self::Error> {
+        value
+            .owners
+            .into_iter()
+            .map(talpid_types::split_tunnel::SplitTunnelOwner::try_from)
+            .collect()
+    }
+}
+
 impl TryFrom<proto::TunnelOptions> for mullvad_types::settings::TunnelOptions {
     type Error = FromProtobufTypeError;
 
@@ -267,6 +709,21 @@ impl TryFrom<proto::TunnelOptions> for mullvad_types::settings::TunnelOptions {
             },
             wireguard: mullvad_types::wireguard::TunnelOptions {
                 mtu: wireguard_options.mtu.map(|mtu| mtu as u16),
+                persistent_keepalive: wireguard_options
+                    .persistent_keepalive
+                    .map(u16::try_from)
+                    .transpose()
+                    .map_err(|_| {
+                        FromProtobufTypeError::InvalidArgument("invalid persistent keepalive")
+                    })?,
+                #[cfg(target_os = "linux")]
+                outgoing_interface: wireguard_options.outgoing_interface,
+                #[cfg(target_os = "linux")]
+                backend: wireguard_options
+                    .backend
+                    .map(super::net::try_wireguard_backend_from_i32)
+                    .transpose()?,
+                worker_threads: wireguard_options.worker_threads,
                 rotation_interval: wireguard_options
                     .rotation_interval
                     .map(std::time::Duration::try_from)
@@ -287,16 +744,40 @@ impl TryFrom<proto::TunnelOptions> for mullvad_types::settings::TunnelOptions {
                     .ok_or(FromProtobufTypeError::InvalidArgument(
                         "missing quantum resistant state",
                     ))??,
+                // Older clients do not know about this option
+                quantum_resistant_kem: wireguard_options
+                    .quantum_resistant_kem
+                    .map(talpid_types::net::wireguard::QuantumResistantKem::try_from)
+                    .transpose()?
+                    .unwrap_or_default(),
+                negotiation_retry_policy: wireguard_options
+                    .negotiation_retry_policy
+                    .map(talpid_types::net::wireguard::NegotiationRetryPolicy::try_from)
+                    .transpose()?
+                    .unwrap_or_default(),
+                connectivity_check: wireguard_options
+                    .connectivity_check
+                    .map(talpid_types::net::wireguard::ConnectivityCheckOptions::try_from)
+                    .transpose()?
+                    .unwrap_or_default(),
+                ipv6_only: wireguard_options.ipv6_only,
                 #[cfg(daita)]
                 daita: wireguard_options
                     .daita
-                    .map(mullvad_types::wireguard::DaitaSettings::from)
+                    .map(mullvad_types::wireguard::DaitaSettings::try_from)
+                    .transpose()?
                     .ok_or(FromProtobufTypeError::InvalidArgument(
                         "missing daita settings",
                     ))?,
             },
             generic: net::GenericTunnelOptions {
                 enable_ipv6: generic_options.enable_ipv6,
+                block_ipv6: generic_options.block_ipv6,
+                custom_routes: generic_options
+                    .custom_routes
+                    .into_iter()
+                    .map(net::CustomRoute::try_from)
+                    .collect::<Result<_, _>>()?,
             },
             dns_options: mullvad_types::settings::DnsOptions::try_from(dns_options)?,
         })
@@ -356,7 +837,80 @@ impl TryFrom<proto::DnsOptions> for mullvad_types::settings::DnsOptions {
                         })
                     })
                     .collect::<Result<Vec<_>, _>>()?,
+                encrypted_servers: custom_options
+                    .encrypted_servers
+                    .into_iter()
+                    .map(talpid_types::dns::EncryptedDnsServer::try_from)
+                    .collect::<Result<Vec<_>, _>>()?,
             },
+            split_dns_rules: options
+                .split_dns_rules
+                .into_iter()
+                .map(|rule| {
+                    let servers = rule
+                        .servers
+                        .into_iter()
+                        .map(|addr| {
+                            addr.parse().map_err(|_| {
+                                FromProtobufTypeError::InvalidArgument("invalid IP address")
+                            })
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    Ok(talpid_types::dns::SplitDnsRule {
+                        domain: rule.domain,
+                        servers,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            filter_lists: options
+                .filter_lists
+                .into_iter()
+                .map(mullvad_types::dns_filter::FilterListSource::try_from)
+                .collect::<Result<Vec<_>, _>>()?,
         })
     }
 }
+
+impl TryFrom<proto::EncryptedDnsServer> for talpid_types::dns::EncryptedDnsServer {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(server: proto::EncryptedDnsServer) -> Result<Self, Self::Error> {
+        use proto::encrypted_dns_server::Protocol;
+        use talpid_types::dns::EncryptedDnsProtocol;
+
+        let address = server
+            .address
+            .parse()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid IP address"))?;
+        let protocol = match Protocol::try_from(server.protocol) {
+            Ok(Protocol::Tls) => EncryptedDnsProtocol::Tls,
+            Ok(Protocol::Https) => EncryptedDnsProtocol::Https,
+            Err(_) => {
+                return Err(FromProtobufTypeError::InvalidArgument(
+                    "invalid encrypted DNS protocol",
+                ))
+            }
+        };
+        if server.hostname.is_empty() {
+            return Err(FromProtobufTypeError::InvalidArgument(
+                "missing encrypted DNS server hostname",
+            ));
+        }
+        let port = server
+            .port
+            .map(u16::try_from)
+            .transpose()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid port"))?;
+        let server = talpid_types::dns::EncryptedDnsServer {
+            address,
+            protocol,
+            hostname: server.hostname,
+            port,
+            spki_pin: server.spki_pin,
+        };
+        server
+            .spki_digest()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid SPKI pin"))?;
+        Ok(server)
+    }
+}
@@ -0,0 +1,65 @@
+use super::FromProtobufTypeError;
+use crate::types::proto;
+use mullvad_types::state_hooks::{HookEvent, StateHooks};
+use std::path::{Path, PathBuf};
+
+fn path_to_string(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+impl From<&StateHooks> for proto::StateHooks {
+    fn from(hooks: &StateHooks) -> Self {
+        proto::StateHooks {
+            connected: hooks.connected.as_deref().map(path_to_string),
+            disconnected: hooks.disconnected.as_deref().map(path_to_string),
+            error: hooks.error.as_deref().map(path_to_string),
+        }
+    }
+}
+
+impl From<proto::StateHooks> for StateHooks {
+    fn from(hooks: proto::StateHooks) -> Self {
+        StateHooks {
+            connected: hooks.connected.map(PathBuf::from),
+            disconnected: hooks.disconnected.map(PathBuf::from),
+            error: hooks.error.map(PathBuf::from),
+        }
+    }
+}
+
+impl From<HookEvent> for proto::state_hook::Event {
+    fn from(event: HookEvent) -> Self {
+        match event {
+            HookEvent::Connected => proto::state_hook::Event::Connected,
+            HookEvent::Disconnected => proto::state_hook::Event::Disconnected,
+            HookEvent::Error => proto::state_hook::Event::Error,
+        }
+    }
+}
+
+impl From<(HookEvent, Option<PathBuf>)> for proto::StateHook {
+    fn from((event, path): (HookEvent, Option<PathBuf>)) -> Self {
+        proto::StateHook {
+            event: i32::from(proto::state_hook::Event::from(event)),
+            path: path.as_deref().map(path_to_string),
+        }
+    }
+}
+
+impl TryFrom<proto::StateHook> for (HookEvent, Option<PathBuf>) {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(hook: proto::StateHook) -> Result<Self, Self::Error> {
+        let event = match proto::state_hook::Event::try_from(hook.event) {
+            Ok(proto::state_hook::Event::Connected) => HookEvent::Connected,
+            Ok(proto::state_hook::Event::Disconnected) => HookEvent::Disconnected,
+            Ok(proto::state_hook::Event::Error) => HookEvent::Error,
+            Err(_) => {
+                return Err(FromProtobufTypeError::InvalidArgument(
+                    "invalid state hook event",
+                ))
+            }
+        };
+        Ok((event, hook.path.map(PathBuf::from)))
+    }
+}
@@ -78,9 +78,15 @@ impl From<mullvad_types::states::TunnelState> for proto::TunnelState {
                         talpid_tunnel::ActionAfterDisconnect::Reconnect => {
                             i32::from(proto::AfterDisconnect::Reconnect)
                         }
+                        talpid_tunnel::ActionAfterDisconnect::Pause => {
+                            i32::from(proto::AfterDisconnect::Pause)
+                        }
                     },
                 })
             }
+            MullvadTunnelState::Paused => {
+                proto::tunnel_state::State::Paused(proto::tunnel_state::Paused {})
+            }
             MullvadTunnelState::Error(error_state) => {
                 proto::tunnel_state::State::Error(proto::tunnel_state::Error {
                     error_state: Some(proto::ErrorState {
@@ -100,6 +106,9 @@ impl From<mullvad_types::states::TunnelState> for proto::TunnelState {
                             talpid_tunnel::ErrorStateCause::StartTunnelError => {
                                 i32::from(Cause::StartTunnelError)
                             }
+                            talpid_tunnel::ErrorStateCause::RelayNotResponding => {
+                                i32::from(Cause::RelayNotResponding)
+                            }
                             #[cfg(target_os = "windows")]
                             talpid_tunnel::ErrorStateCause::CreateTunnelDevice { os_error: _ } => {
                                 i32::from(Cause::CreateTunnelDevice)
@@ -138,6 +147,14 @@ impl From<mullvad_types::states::TunnelState> for proto::TunnelState {
                             talpid_tunnel::ErrorStateCause::NeedFullDiskPermissions => {
                                 i32::from(Cause::NeedFullDiskPermissions)
                             }
+                            #[cfg(not(target_os = "android"))]
+                            talpid_tunnel::ErrorStateCause::BlockedByUser => {
+                                i32::from(Cause::BlockedByUser)
+                            }
+                            #[cfg(not(target_os = "android"))]
+                            talpid_tunnel::ErrorStateCause::CaptivePortal { .. } => {
+                                i32::from(Cause::CaptivePortal)
+                            }
                         },
                         blocking_error: error_state.block_failure().map(map_firewall_error),
                         #[cfg(not(target_os = "android"))]
@@ -209,6 +226,19 @@ impl From<mullvad_types::states::TunnelState> for proto::TunnelState {
                             } else {
                                 None
                             },
+                        #[cfg(not(target_os = "android"))]
+                        captive_portal_error:
+                            if let talpid_tunnel::ErrorStateCause::CaptivePortal { login_url } =
+                                error_state.cause()
+                            {
+                                Some(proto::error_state::CaptivePortalError {
+                                    login_url: login_url.clone(),
+                                })
+                            } else {
+                                None
+                            },
+                        #[cfg(target_os = "android")]
+                        captive_portal_error: None,
                         #[cfg(not(target_os = "windows"))]
                         create_tunnel_error: None,
                         #[cfg(target_os = "windows")]
@@ -329,6 +359,9 @@ impl TryFrom<proto::TunnelState> for mullvad_types::states::TunnelState {
                     Ok(proto::AfterDisconnect::Reconnect) => {
                         talpid_tunnel::ActionAfterDisconnect::Reconnect
                     }
+                    Ok(proto::AfterDisconnect::Pause) => {
+                        talpid_tunnel::ActionAfterDisconnect::Pause
+                    }
                     _ => {
                         return Err(FromProtobufTypeError::InvalidArgument(
                             "invalid \"after_disconnect\" action",
@@ -336,6 +369,9 @@ impl TryFrom<proto::TunnelState> for mullvad_types::states::TunnelState {
                     }
                 },
             ),
+            Some(proto::tunnel_state::State::Paused(proto::tunnel_state::Paused {})) => {
+                MullvadState::Paused
+            }
             Some(proto::tunnel_state::State::Error(proto::tunnel_state::Error {
                 error_state:
                     Some(proto::ErrorState {
@@ -345,11 +381,14 @@ impl TryFrom<proto::TunnelState> for mullvad_types::states::TunnelState {
                         parameter_error,
                         policy_error,
                         create_tunnel_error,
+                        captive_portal_error,
                         ..
                     }),
             })) => {
                 #[cfg(not(target_os = "windows"))]
                 let _ = create_tunnel_error;
+                #[cfg(target_os = "android")]
+                let _ = captive_portal_error;
 
                 let cause = match proto::error_state::Cause::try_from(cause) {
                     Ok(proto::error_state::Cause::AuthFailed) => {
@@ -381,6 +420,9 @@ impl TryFrom<proto::TunnelState> for mullvad_types::states::TunnelState {
                     Ok(proto::error_state::Cause::StartTunnelError) => {
                         talpid_tunnel::ErrorStateCause::StartTunnelError
                     }
+                    Ok(proto::error_state::Cause::RelayNotResponding) => {
+                        talpid_tunnel::ErrorStateCause::RelayNotResponding
+                    }
                     #[cfg(target_os = "windows")]
                     Ok(proto::error_state::Cause::CreateTunnelDevice) => {
                         talpid_tunnel::ErrorStateCause::CreateTunnelDevice {
@@ -407,6 +449,19 @@ impl TryFrom<proto::TunnelState> for mullvad_types::states::TunnelState {
                     Ok(proto::error_state::Cause::NeedFullDiskPermissions) => {
                         talpid_tunnel::ErrorStateCause::NeedFullDiskPermissions
                     }
+                    #[cfg(not(target_os = "android"))]
+                    Ok(proto::error_state::Cause::BlockedByUser) => {
+                        talpid_tunnel::ErrorStateCause::BlockedByUser
+                    }
+                    #[cfg(not(target_os = "android"))]
+                    Ok(proto::error_state::Cause::CaptivePortal) => {
+                        let captive_portal_error = captive_portal_error.ok_or(
+                            FromProtobufTypeError::InvalidArgument("missing captive portal error"),
+                        )?;
+                        talpid_tunnel::ErrorStateCause::CaptivePortal {
+                            login_url: captive_portal_error.login_url,
+                        }
+                    }
                     _ => {
                         return Err(FromProtobufTypeError::InvalidArgument(
                             "invalid error cause",
@@ -437,6 +492,142 @@ impl TryFrom<proto::TunnelState> for mullvad_types::states::TunnelState {
     }
 }
 
+impl From<talpid_types::tunnel::ConnectionTimings> for proto::ConnectionTimings {
+    fn from(timings: talpid_types::tunnel::ConnectionTimings) -> Self {
+        let to_proto = |duration: Option<std::time::Duration>| {
+            duration.map(|duration| {
+                prost_types::Duration::try_from(duration)
+                    .expect("Failed to convert std::time::Duration to prost_types::Duration")
+            })
+        };
+        proto::ConnectionTimings {
+            relay_selection: to_proto(timings.relay_selection),
+            obfuscation: to_proto(timings.obfuscation),
+            ephemeral_peer: to_proto(timings.ephemeral_peer),
+            handshake: to_proto(timings.handshake),
+            firewall_and_dns: to_proto(timings.firewall_and_dns),
+            total: to_proto(timings.total),
+        }
+    }
+}
+
+impl TryFrom<proto::ConnectionTimings> for talpid_types::tunnel::ConnectionTimings {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(timings: proto::ConnectionTimings) -> Result<Self, Self::Error> {
+        let from_proto = |duration: Option<prost_types::Duration>| {
+            duration
+                .map(std::time::Duration::try_from)
+                .transpose()
+                .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid duration"))
+        };
+        Ok(talpid_types::tunnel::ConnectionTimings {
+            relay_selection: from_proto(timings.relay_selection)?,
+            obfuscation: from_proto(timings.obfuscation)?,
+            ephemeral_peer: from_proto(timings.ephemeral_peer)?,
+            handshake: from_proto(timings.handshake)?,
+            firewall_and_dns: from_proto(timings.firewall_and_dns)?,
+            total: from_proto(timings.total)?,
+        })
+    }
+}
+
+impl From<talpid_types::tunnel::TransferRate> for proto::TransferRate {
+    fn from(rate: talpid_types::tunnel::TransferRate) -> Self {
+        proto::TransferRate {
+            tx_bytes_per_second: rate.tx_bytes_per_second,
+            rx_bytes_per_second: rate.rx_bytes_per_second,
+        }
+    }
+}
+
+impl From<proto::TransferRate> for talpid_types::tunnel::TransferRate {
+    fn from(rate: proto::TransferRate) -> Self {
+        talpid_types::tunnel::TransferRate {
+            tx_bytes_per_second: rate.tx_bytes_per_second,
+            rx_bytes_per_second: rate.rx_bytes_per_second,
+        }
+    }
+}
+
+impl From<talpid_types::tunnel::TrafficStats> for proto::TrafficStats {
+    fn from(stats: talpid_types::tunnel::TrafficStats) -> Self {
+        proto::TrafficStats {
+            tx_bytes: stats.tx_bytes,
+            rx_bytes: stats.rx_bytes,
+            rate: Some(proto::TransferRate::from(stats.rate)),
+            history: stats
+                .history
+                .into_iter()
+                .map(proto::TransferRate::from)
+                .collect(),
+            last_handshake: stats.last_handshake.map(prost_types::Timestamp::from),
+            endpoint: stats.endpoint.map(|endpoint| endpoint.to_string()),
+        }
+    }
+}
+
+impl TryFrom<proto::TrafficStats> for talpid_types::tunnel::TrafficStats {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(stats: proto::TrafficStats) -> Result<Self, Self::Error> {
+        let rate = stats.rate.ok_or(FromProtobufTypeError::InvalidArgument(
+            "missing transfer rate",
+        ))?;
+        let last_handshake = stats
+            .last_handshake
+            .map(std::time::SystemTime::try_from)
+            .transpose()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid handshake timestamp"))?;
+        let endpoint = stats
+            .endpoint
+            .map(|endpoint| endpoint.parse())
+            .transpose()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid peer endpoint"))?;
+        Ok(talpid_types::tunnel::TrafficStats {
+            tx_bytes: stats.tx_bytes,
+            rx_bytes: stats.rx_bytes,
+            rate: rate.into(),
+            history: stats.history.into_iter().map(Into::into).collect(),
+            last_handshake,
+            endpoint,
+        })
+    }
+}
+
+impl From<mullvad_types::states::TunnelStats> for proto::TunnelStats {
+    fn from(stats: mullvad_types::states::TunnelStats) -> Self {
+        proto::TunnelStats {
+            traffic: Some(proto::TrafficStats::from(stats.traffic)),
+            handshake_age: stats.handshake_age.map(|age| {
+                prost_types::Duration::try_from(age)
+                    .expect("Failed to convert std::time::Duration to prost_types::Duration")
+            }),
+            relay: stats.relay,
+        }
+    }
+}
+
+impl TryFrom<proto::TunnelStats> for mullvad_types::states::TunnelStats {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(stats: proto::TunnelStats) -> Result<Self, Self::Error> {
+        let traffic = stats.traffic.ok_or(FromProtobufTypeError::InvalidArgument(
+            "missing traffic stats",
+        ))?;
+        let handshake_age = stats
+            .handshake_age
+            .map(std::time::Duration::try_from)
+            .transpose()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid handshake age"))?;
+        Ok(mullvad_types::states::TunnelStats {
+            traffic: talpid_types::tunnel::TrafficStats::try_from(traffic)?,
+            handshake_age,
+            relay: stats.relay,
+        })
+    }
+}
+
 #[cfg_attr(not(target_os = "windows"), allow(unused_variables))]
 fn try_firewall_policy_error_from_i32(
     policy_error: i32,
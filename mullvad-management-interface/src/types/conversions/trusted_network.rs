@@ -0,0 +1,135 @@
+use super::FromProtobufTypeError;
+use crate::types::proto;
+use mullvad_types::trusted_network::{
+    Network, NetworkAction, NetworkMatch, TrustedNetworkRule, TrustedNetworks,
+};
+
+impl From<Network> for proto::Network {
+    fn from(network: Network) -> Self {
+        let network = match network {
+            Network::Wifi { ssid } => proto::network::Network::WifiSsid(ssid),
+            Network::Ethernet => proto::network::Network::Ethernet(proto::network::Ethernet {}),
+        };
+        proto::Network {
+            network: Some(network),
+        }
+    }
+}
+
+impl TryFrom<proto::Network> for Network {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(network: proto::Network) -> Result<Self, Self::Error> {
+        match network.network {
+            Some(proto::network::Network::WifiSsid(ssid)) => Ok(Network::Wifi { ssid }),
+            Some(proto::network::Network::Ethernet(_)) => Ok(Network::Ethernet),
+            None => Err(FromProtobufTypeError::InvalidArgument("missing network")),
+        }
+    }
+}
+
+impl From<NetworkMatch> for proto::NetworkMatch {
+    fn from(network: NetworkMatch) -> Self {
+        use proto::network_match;
+
+        let network = match network {
+            NetworkMatch::Ssid(ssid) => network_match::Network::Ssid(ssid),
+            NetworkMatch::UnknownWifi => {
+                network_match::Network::UnknownWifi(network_match::UnknownWifi {})
+            }
+            NetworkMatch::Ethernet => network_match::Network::Ethernet(network_match::Ethernet {}),
+        };
+        proto::NetworkMatch {
+            network: Some(network),
+        }
+    }
+}
+
+impl TryFrom<proto::NetworkMatch> for NetworkMatch {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(network: proto::NetworkMatch) -> Result<Self, Self::Error> {
+        use proto::network_match;
+
+        match network.network {
+            Some(network_match::Network::Ssid(ssid)) => Ok(NetworkMatch::Ssid(ssid)),
+            Some(network_match::Network::UnknownWifi(_)) => Ok(NetworkMatch::UnknownWifi),
+            Some(network_match::Network::Ethernet(_)) => Ok(NetworkMatch::Ethernet),
+            None => Err(FromProtobufTypeError::InvalidArgument(
+                "missing trusted network match",
+            )),
+        }
+    }
+}
+
+impl From<TrustedNetworkRule> for proto::TrustedNetworkRule {
+    fn from(rule: TrustedNetworkRule) -> Self {
+        use proto::trusted_network_rule::Action;
+
+        let action = match rule.action {
+            NetworkAction::Disconnect => Action::Disconnect,
+            NetworkAction::Connect => Action::Connect,
+            NetworkAction::ConnectAndLockdown => Action::ConnectAndLockdown,
+        };
+        proto::TrustedNetworkRule {
+            network: Some(proto::NetworkMatch::from(rule.network)),
+            action: i32::from(action),
+        }
+    }
+}
+
+impl TryFrom<proto::TrustedNetworkRule> for TrustedNetworkRule {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(rule: proto::TrustedNetworkRule) -> Result<Self, Self::Error> {
+        use proto::trusted_network_rule::Action;
+
+        let network = rule.network.ok_or(FromProtobufTypeError::InvalidArgument(
+            "missing trusted network match",
+        ))?;
+        let action = match Action::try_from(rule.action) {
+            Ok(Action::Disconnect) => NetworkAction::Disconnect,
+            Ok(Action::Connect) => NetworkAction::Connect,
+            Ok(Action::ConnectAndLockdown) => NetworkAction::ConnectAndLockdown,
+            Err(_) => {
+                return Err(FromProtobufTypeError::InvalidArgument(
+                    "invalid trusted network action",
+                ))
+            }
+        };
+        Ok(TrustedNetworkRule {
+            network: NetworkMatch::try_from(network)?,
+            action,
+        })
+    }
+}
+
+impl From<(TrustedNetworks, Option<Network>)> for proto::TrustedNetworks {
+    fn from((networks, current_network): (TrustedNetworks, Option<Network>)) -> Self {
+        proto::TrustedNetworks {
+            rules: networks
+                .iter()
+                .cloned()
+                .map(proto::TrustedNetworkRule::from)
+                .collect(),
+            current_network: current_network.map(proto::Network::from),
+        }
+    }
+}
+
+impl TryFrom<proto::TrustedNetworks> for (TrustedNetworks, Option<Network>) {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(networks: proto::TrustedNetworks) -> Result<Self, Self::Error> {
+        let rules = networks
+            .rules
+            .into_iter()
+            .map(TrustedNetworkRule::try_from)
+            .collect::<Result<Vec<_>, _>>()?;
+        let current_network = networks
+            .current_network
+            .map(Network::try_from)
+            .transpose()?;
+        Ok((TrustedNetworks::from(rules), current_network))
+    }
+}
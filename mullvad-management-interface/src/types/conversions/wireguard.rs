@@ -11,6 +11,10 @@ impl From<mullvad_types::wireguard::PublicKey> for proto::PublicKey {
                 seconds: public_key.created.timestamp(),
                 nanos: 0,
             }),
+            next_rotation: Some(Timestamp {
+                seconds: public_key.next_rotation.timestamp(),
+                nanos: 0,
+            }),
         }
     }
 }
@@ -28,10 +32,21 @@ impl TryFrom<proto::PublicKey> for mullvad_types::wireguard::PublicKey {
         let created = DateTime::from_timestamp(created.seconds, created.nanos as u32)
             .ok_or(FromProtobufTypeError::InvalidArgument("invalid timestamp"))?;
 
+        let next_rotation =
+            public_key
+                .next_rotation
+                .ok_or(FromProtobufTypeError::InvalidArgument(
+                    "missing 'next_rotation' timestamp",
+                ))?;
+        let next_rotation =
+            DateTime::from_timestamp(next_rotation.seconds, next_rotation.nanos as u32)
+                .ok_or(FromProtobufTypeError::InvalidArgument("invalid timestamp"))?;
+
         Ok(mullvad_types::wireguard::PublicKey {
             key: talpid_types::net::wireguard::PublicKey::try_from(public_key.key.as_slice())
                 .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid wireguard key"))?,
             created,
+            next_rotation,
         })
     }
 }
@@ -73,22 +88,227 @@ impl TryFrom<proto::QuantumResistantState> for mullvad_types::wireguard::Quantum
     }
 }
 
+impl From<talpid_types::net::wireguard::QuantumResistantKem> for proto::QuantumResistantKem {
+    fn from(kem: talpid_types::net::wireguard::QuantumResistantKem) -> Self {
+        use talpid_types::net::wireguard::QuantumResistantKem;
+
+        let kem = match kem {
+            QuantumResistantKem::Auto => proto::quantum_resistant_kem::Kem::Auto,
+            QuantumResistantKem::ClassicMcEliece => {
+                proto::quantum_resistant_kem::Kem::ClassicMceliece
+            }
+            QuantumResistantKem::MlKem => proto::quantum_resistant_kem::Kem::MlKem,
+        };
+        proto::QuantumResistantKem {
+            kem: i32::from(kem),
+        }
+    }
+}
+
+impl TryFrom<proto::QuantumResistantKem> for talpid_types::net::wireguard::QuantumResistantKem {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(kem: proto::QuantumResistantKem) -> Result<Self, Self::Error> {
+        use talpid_types::net::wireguard::QuantumResistantKem;
+
+        match proto::quantum_resistant_kem::Kem::try_from(kem.kem) {
+            Ok(proto::quantum_resistant_kem::Kem::Auto) => Ok(QuantumResistantKem::Auto),
+            Ok(proto::quantum_resistant_kem::Kem::ClassicMceliece) => {
+                Ok(QuantumResistantKem::ClassicMcEliece)
+            }
+            Ok(proto::quantum_resistant_kem::Kem::MlKem) => Ok(QuantumResistantKem::MlKem),
+            Err(_) => Err(FromProtobufTypeError::InvalidArgument(
+                "invalid quantum resistant KEM",
+            )),
+        }
+    }
+}
+
+impl From<talpid_types::net::wireguard::NegotiationRetryPolicy> for proto::NegotiationRetryPolicy {
+    fn from(policy: talpid_types::net::wireguard::NegotiationRetryPolicy) -> Self {
+        proto::NegotiationRetryPolicy {
+            connect_timeout: Some(duration_to_proto(policy.connect_timeout)),
+            retries: policy.retries,
+            initial_backoff: Some(duration_to_proto(policy.initial_backoff)),
+            max_backoff: Some(duration_to_proto(policy.max_backoff)),
+        }
+    }
+}
+
+impl TryFrom<proto::NegotiationRetryPolicy>
+    for talpid_types::net::wireguard::NegotiationRetryPolicy
+{
+    type Error = FromProtobufTypeError;
+
+    fn try_from(policy: proto::NegotiationRetryPolicy) -> Result<Self, Self::Error> {
+        let connect_timeout = duration_from_proto(policy.connect_timeout)?;
+        if connect_timeout.is_zero() {
+            return Err(FromProtobufTypeError::InvalidArgument(
+                "connect timeout must be greater than zero",
+            ));
+        }
+        Ok(talpid_types::net::wireguard::NegotiationRetryPolicy {
+            connect_timeout,
+            retries: policy.retries,
+            initial_backoff: duration_from_proto(policy.initial_backoff)?,
+            max_backoff: duration_from_proto(policy.max_backoff)?,
+        })
+    }
+}
+
+impl From<talpid_types::net::wireguard::ConnectivityCheckOptions>
+    for proto::ConnectivityCheckOptions
+{
+    fn from(options: talpid_types::net::wireguard::ConnectivityCheckOptions) -> Self {
+        proto::ConnectivityCheckOptions {
+            ping_interval: Some(duration_to_proto(options.ping_interval)),
+            failure_threshold: options.failure_threshold,
+            ping_target: options.ping_target.map(|target| target.to_string()),
+        }
+    }
+}
+
+impl TryFrom<proto::ConnectivityCheckOptions>
+    for talpid_types::net::wireguard::ConnectivityCheckOptions
+{
+    type Error = FromProtobufTypeError;
+
+    fn try_from(options: proto::ConnectivityCheckOptions) -> Result<Self, Self::Error> {
+        let ping_interval = duration_from_proto(options.ping_interval)?;
+        if ping_interval.is_zero() {
+            return Err(FromProtobufTypeError::InvalidArgument(
+                "ping interval must be greater than zero",
+            ));
+        }
+        if options.failure_threshold == 0 {
+            return Err(FromProtobufTypeError::InvalidArgument(
+                "failure threshold must be greater than zero",
+            ));
+        }
+        let ping_target = options
+            .ping_target
+            .map(|target| target.parse())
+            .transpose()
+            .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid ping target"))?;
+        Ok(talpid_types::net::wireguard::ConnectivityCheckOptions {
+            ping_interval,
+            failure_threshold: options.failure_threshold,
+            ping_target,
+        })
+    }
+}
+
+impl From<talpid_types::net::wireguard::NegotiationRetry> for proto::NegotiationRetry {
+    fn from(retry: talpid_types::net::wireguard::NegotiationRetry) -> Self {
+        proto::NegotiationRetry {
+            retry: retry.retry,
+            max_retries: retry.max_retries,
+            delay: Some(duration_to_proto(retry.delay)),
+        }
+    }
+}
+
+impl TryFrom<proto::NegotiationRetry> for talpid_types::net::wireguard::NegotiationRetry {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(retry: proto::NegotiationRetry) -> Result<Self, Self::Error> {
+        Ok(talpid_types::net::wireguard::NegotiationRetry {
+            retry: retry.retry,
+            max_retries: retry.max_retries,
+            delay: duration_from_proto(retry.delay)?,
+        })
+    }
+}
+
+fn duration_to_proto(duration: std::time::Duration) -> prost_types::Duration {
+    prost_types::Duration::try_from(duration)
+        .expect("Failed to convert std::time::Duration to prost_types::Duration")
+}
+
+fn duration_from_proto(
+    duration: Option<prost_types::Duration>,
+) -> Result<std::time::Duration, FromProtobufTypeError> {
+    duration
+        .ok_or(FromProtobufTypeError::InvalidArgument("missing duration"))
+        .and_then(|duration| {
+            std::time::Duration::try_from(duration)
+                .map_err(|_| FromProtobufTypeError::InvalidArgument("invalid duration"))
+        })
+}
+
 #[cfg(daita)]
 impl From<mullvad_types::wireguard::DaitaSettings> for proto::DaitaSettings {
     fn from(settings: mullvad_types::wireguard::DaitaSettings) -> Self {
         proto::DaitaSettings {
             enabled: settings.enabled,
             direct_only: !settings.use_multihop_if_necessary,
+            parameters: Some(proto::DaitaParameters::from(settings.parameters)),
         }
     }
 }
 
 #[cfg(daita)]
-impl From<proto::DaitaSettings> for mullvad_types::wireguard::DaitaSettings {
-    fn from(settings: proto::DaitaSettings) -> Self {
-        mullvad_types::wireguard::DaitaSettings {
+impl TryFrom<proto::DaitaSettings> for mullvad_types::wireguard::DaitaSettings {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(settings: proto::DaitaSettings) -> Result<Self, Self::Error> {
+        Ok(mullvad_types::wireguard::DaitaSettings {
             enabled: settings.enabled,
             use_multihop_if_necessary: !settings.direct_only,
+            parameters: settings
+                .parameters
+                .map(talpid_types::net::wireguard::DaitaParameters::try_from)
+                .transpose()?
+                .unwrap_or_default(),
+        })
+    }
+}
+
+impl From<talpid_types::net::wireguard::DaitaParameters> for proto::DaitaParameters {
+    fn from(parameters: talpid_types::net::wireguard::DaitaParameters) -> Self {
+        proto::DaitaParameters {
+            max_padding_percent: parameters.max_padding_percent.map(u32::from),
+            max_blocking_percent: parameters.max_blocking_percent.map(u32::from),
+            machines: parameters.machines.unwrap_or_default(),
+            events_capacity: parameters.events_capacity,
+            actions_capacity: parameters.actions_capacity,
         }
     }
 }
+
+impl TryFrom<proto::DaitaParameters> for talpid_types::net::wireguard::DaitaParameters {
+    type Error = FromProtobufTypeError;
+
+    fn try_from(parameters: proto::DaitaParameters) -> Result<Self, Self::Error> {
+        use talpid_types::net::wireguard::DaitaParametersError;
+
+        let percent = |percent: Option<u32>, error: &'static str| {
+            percent
+                .map(u8::try_from)
+                .transpose()
+                .map_err(|_| FromProtobufTypeError::InvalidArgument(error))
+        };
+        let parameters = talpid_types::net::wireguard::DaitaParameters {
+            max_padding_percent: percent(
+                parameters.max_padding_percent,
+                "invalid max padding percentage",
+            )?,
+            max_blocking_percent: percent(
+                parameters.max_blocking_percent,
+                "invalid max blocking percentage",
+            )?,
+            machines: (!parameters.machines.is_empty()).then_some(parameters.machines),
+            events_capacity: parameters.events_capacity,
+            actions_capacity: parameters.actions_capacity,
+        };
+        parameters.validate().map_err(|error| {
+            FromProtobufTypeError::InvalidArgument(match error {
+                DaitaParametersError::InvalidMaxPadding => "invalid max padding percentage",
+                DaitaParametersError::InvalidMaxBlocking => "invalid max blocking percentage",
+                DaitaParametersError::NoMachines => "no DAITA machines selected",
+                DaitaParametersError::InvalidCapacity => "invalid DAITA buffer capacity",
+            })
+        })?;
+        Ok(parameters)
+    }
+}
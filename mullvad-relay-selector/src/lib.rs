@@ -10,6 +10,7 @@ mod relay_selector;
 pub use error::Error;
 pub use relay_selector::{
     detailer, matcher, matcher::filter_matching_relay_list, query, relays::WireguardConfig,
-    AdditionalRelayConstraints, AdditionalWireguardConstraints, GetRelay, RelaySelector,
-    SelectedBridge, SelectedObfuscator, SelectorConfig, OPENVPN_RETRY_ORDER, WIREGUARD_RETRY_ORDER,
+    AdditionalRelayConstraints, AdditionalWireguardConstraints, GetRelay, RelayLatencies,
+    RelaySelector, SelectedBridge, SelectedObfuscator, SelectorConfig, OPENVPN_RETRY_ORDER,
+    WIREGUARD_RETRY_ORDER,
 };
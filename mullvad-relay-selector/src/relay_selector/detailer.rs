@@ -12,6 +12,7 @@ use std::net::{IpAddr, SocketAddr};
 use ipnetwork::IpNetwork;
 use mullvad_types::{
     constraints::Constraint,
+    custom_relay::{CustomRelay, CustomRelaySettings},
     endpoint::MullvadWireguardEndpoint,
     relay_constraints::TransportPort,
     relay_list::{
@@ -59,9 +60,11 @@ pub fn wireguard_endpoint(
 ) -> Result<MullvadWireguardEndpoint, Error> {
     match relay {
         WireguardConfig::Singlehop { exit } => wireguard_singlehop_endpoint(query, data, exit),
-        WireguardConfig::Multihop { exit, entry } => {
-            wireguard_multihop_endpoint(query, data, exit, entry)
-        }
+        WireguardConfig::Multihop {
+            exit,
+            entry,
+            middle,
+        } => wireguard_multihop_endpoint(query, data, exit, entry, middle),
     }
 }
 
@@ -82,6 +85,7 @@ fn wireguard_singlehop_endpoint(
         allowed_ips: all_of_the_internet(),
         // This will be filled in later, not the relay selector's problem
         psk: None,
+        persistent_keepalive: None,
         // This will be filled in later
         #[cfg(daita)]
         constant_packet_size: false,
@@ -89,45 +93,57 @@ fn wireguard_singlehop_endpoint(
     Ok(MullvadWireguardEndpoint {
         peer: peer_config,
         exit_peer: None,
+        middle_peers: vec![],
         ipv4_gateway: data.ipv4_gateway,
         ipv6_gateway: data.ipv6_gateway,
     })
 }
 
-/// Configure a multihop connection using the entry & exit relay data.
+/// Configure a multihop connection using the entry, middle & exit relay data.
 ///
 /// # Note
 /// In a multihop circuit, we need to provide an exit peer configuration in addition to the
-/// peer configuration.
+/// peer configuration. Each relay before the exit may only route traffic to the relay after it.
 fn wireguard_multihop_endpoint(
     query: &WireguardRelayQuery,
     data: &WireguardEndpointData,
     exit: &Relay,
     entry: &Relay,
+    middle: &[Relay],
 ) -> Result<MullvadWireguardEndpoint, Error> {
     /// The standard port on which an exit relay accepts connections from an entry relay in a
     /// multihop circuit.
     const WIREGUARD_EXIT_PORT: u16 = 51820;
-    let exit_endpoint = {
-        let ip = exit.ipv4_addr_in;
-        // The port that the exit relay listens for incoming connections from entry
-        // relays is *not* derived from the original query / user settings.
-        let port = WIREGUARD_EXIT_PORT;
-        SocketAddr::from((ip, port))
-    };
-    let exit = PeerConfig {
-        public_key: get_public_key(exit)?.clone(),
-        endpoint: exit_endpoint,
-        // The exit peer should be able to route incoming VPN traffic to the rest of
-        // the internet.
-        allowed_ips: all_of_the_internet(),
-        // This will be filled in later, not the relay selector's problem
-        psk: None,
-        // This will be filled in later
-        #[cfg(daita)]
-        constant_packet_size: false,
+
+    // Relays after the entry are always reached through the relay before them, so the port that
+    // they listen for incoming connections on is *not* derived from the original query / user
+    // settings.
+    let inner_peer = |relay: &Relay, next_hop: Option<IpAddr>| -> Result<PeerConfig, Error> {
+        Ok(PeerConfig {
+            public_key: get_public_key(relay)?.clone(),
+            endpoint: SocketAddr::from((relay.ipv4_addr_in, WIREGUARD_EXIT_PORT)),
+            allowed_ips: next_hop_allowed_ips(next_hop),
+            // This will be filled in later, not the relay selector's problem
+            psk: None,
+            persistent_keepalive: None,
+            // This will be filled in later
+            #[cfg(daita)]
+            constant_packet_size: false,
+        })
     };
 
+    // The exit peer should be able to route incoming VPN traffic to the rest of the internet.
+    let exit = inner_peer(exit, None)?;
+
+    let mut middle_peers = Vec::with_capacity(middle.len());
+    let mut next_hop = exit.endpoint.ip();
+    for relay in middle.iter().rev() {
+        let peer = inner_peer(relay, Some(next_hop))?;
+        next_hop = peer.endpoint.ip();
+        middle_peers.push(peer);
+    }
+    middle_peers.reverse();
+
     let entry_endpoint = {
         let host = get_address_for_wireguard_relay(query, entry)?;
         let port = get_port_for_wireguard_relay(query, data)?;
@@ -137,10 +153,11 @@ fn wireguard_multihop_endpoint(
         public_key: get_public_key(entry)?.clone(),
         endpoint: entry_endpoint,
         // The entry peer should only be able to route incoming VPN traffic to the
-        // exit peer.
-        allowed_ips: vec![IpNetwork::from(exit.endpoint.ip())],
+        // next relay.
+        allowed_ips: next_hop_allowed_ips(Some(next_hop)),
         // This will be filled in later
         psk: None,
+        persistent_keepalive: None,
         // This will be filled in later
         #[cfg(daita)]
         constant_packet_size: false,
@@ -149,11 +166,66 @@ fn wireguard_multihop_endpoint(
     Ok(MullvadWireguardEndpoint {
         peer: entry,
         exit_peer: Some(exit),
+        middle_peers,
         ipv4_gateway: data.ipv4_gateway,
         ipv6_gateway: data.ipv6_gateway,
     })
 }
 
+/// Connect to the [custom relays][CustomRelay] in `relay` using their own endpoints and allowed
+/// IPs, instead of those derived from the relay list.
+pub fn apply_custom_relays(
+    endpoint: &mut MullvadWireguardEndpoint,
+    relay: &WireguardConfig,
+    custom_relays: &CustomRelaySettings,
+) {
+    // The relays and their peers, in the order that traffic passes through them
+    let (relays, mut peers): (Vec<&Relay>, Vec<&mut PeerConfig>) = match relay {
+        WireguardConfig::Singlehop { exit } => (vec![exit], vec![&mut endpoint.peer]),
+        WireguardConfig::Multihop {
+            exit,
+            entry,
+            middle,
+        } => (
+            std::iter::once(entry)
+                .chain(middle)
+                .chain(std::iter::once(exit))
+                .collect(),
+            std::iter::once(&mut endpoint.peer)
+                .chain(endpoint.middle_peers.iter_mut())
+                .chain(endpoint.exit_peer.as_mut())
+                .collect(),
+        ),
+    };
+    let custom_relay = |index: usize| custom_relays.find(&relays[index].hostname);
+
+    for index in 0..peers.len() {
+        let Some(custom_relay) = custom_relay(index) else {
+            continue;
+        };
+        peers[index].endpoint = custom_relay.endpoint;
+        if !custom_relay.allowed_ips.is_empty() {
+            peers[index].allowed_ips = custom_relay.allowed_ips.clone();
+        }
+        // The relay before this one must be allowed to route traffic to the new endpoint
+        if let Some(previous) = index.checked_sub(1) {
+            if custom_relay(previous).is_none_or(|previous| previous.allowed_ips.is_empty()) {
+                peers[previous].allowed_ips =
+                    next_hop_allowed_ips(Some(custom_relay.endpoint.ip()));
+            }
+        }
+    }
+}
+
+/// Return the allowed IPs of a relay in a multihop circuit: only `next_hop` if there is a relay
+/// after it, or else the whole internet.
+fn next_hop_allowed_ips(next_hop: Option<IpAddr>) -> Vec<IpNetwork> {
+    match next_hop {
+        Some(ip) => vec![IpNetwork::from(ip)],
+        None => all_of_the_internet(),
+    }
+}
+
 /// Get the correct IP address for the given relay.
 fn get_address_for_wireguard_relay(
     query: &WireguardRelayQuery,
@@ -0,0 +1,89 @@
+//! Relays that connection attempts recently failed for. They are deprioritized for a while, so that
+//! the same broken relay is not picked again and again, e.g. during an outage.
+
+use mullvad_types::relay_list::RelayList;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// Time after which a failed connection attempt no longer affects the selection.
+const FAILURE_DECAY: Duration = Duration::from_secs(10 * 60);
+/// The weight of a relay that just failed is divided by this. The penalty decreases linearly until
+/// the failure has decayed.
+const MAX_PENALTY: u64 = 100;
+
+#[derive(Debug, Default)]
+pub struct RelayFailures {
+    /// Times of recent failures, by hostname.
+    failures: HashMap<String, Vec<Instant>>,
+}
+
+impl RelayFailures {
+    /// Record that a connection attempt to the relay with the given hostname failed.
+    pub fn record(&mut self, hostname: &str) {
+        let now = Instant::now();
+        self.remove_decayed(now);
+        self.failures
+            .entry(hostname.to_owned())
+            .or_default()
+            .push(now);
+    }
+
+    /// Divide the weight of each relay in `relay_list` by the penalties of its recent failures.
+    pub fn apply_to(&mut self, relay_list: &mut RelayList) {
+        let now = Instant::now();
+        self.remove_decayed(now);
+        self.apply_at(relay_list, now);
+    }
+
+    fn apply_at(&self, relay_list: &mut RelayList, now: Instant) {
+        if self.failures.is_empty() {
+            return;
+        }
+        for country in &mut relay_list.countries {
+            for city in &mut country.cities {
+                for relay in &mut city.relays {
+                    if let Some(failures) = self.failures.get(&relay.hostname) {
+                        let penalty = failures
+                            .iter()
+                            .map(|failure| penalty(now.saturating_duration_since(*failure)))
+                            .fold(1u64, u64::saturating_mul);
+                        if relay.weight > 0 {
+                            // Never exclude a relay entirely, since it may be the only match for
+                            // the constraints
+                            relay.weight = (relay.weight / penalty).max(1);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn remove_decayed(&mut self, now: Instant) {
+        self.failures.retain(|_, failures| {
+            failures.retain(|failure| now.saturating_duration_since(*failure) < FAILURE_DECAY);
+            !failures.is_empty()
+        });
+    }
+}
+
+/// Weight divisor for a failure that happened `age` ago.
+fn penalty(age: Duration) -> u64 {
+    let remaining = FAILURE_DECAY.saturating_sub(age).as_millis();
+    let penalty = 1 + u128::from(MAX_PENALTY - 1) * remaining / FAILURE_DECAY.as_millis();
+    u64::try_from(penalty).unwrap_or(MAX_PENALTY)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_penalty_decays() {
+        assert_eq!(penalty(Duration::ZERO), MAX_PENALTY);
+        assert_eq!(penalty(FAILURE_DECAY / 2), 50);
+        assert_eq!(penalty(FAILURE_DECAY), 1);
+        assert_eq!(penalty(FAILURE_DECAY * 2), 1);
+    }
+}
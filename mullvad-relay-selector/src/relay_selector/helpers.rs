@@ -8,7 +8,7 @@ use std::{
 use mullvad_types::{
     constraints::Constraint,
     endpoint::MullvadWireguardEndpoint,
-    relay_constraints::{ShadowsocksSettings, Udp2TcpObfuscationSettings},
+    relay_constraints::{CustomObfuscationBridge, ShadowsocksSettings, Udp2TcpObfuscationSettings},
     relay_list::Relay,
 };
 use rand::{
@@ -23,6 +23,9 @@ use crate::SelectedObfuscator;
 /// For relays that have no additional IPs, only ports provided by the relay list are available.
 const SHADOWSOCKS_EXTRA_PORT_RANGES: &[RangeInclusive<u16>] = &[1..=u16::MAX];
 
+/// TLS obfuscation only makes sense on the HTTPS port.
+const TLS_PORT: u16 = 443;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("Found no valid port matching the selected settings")]
@@ -38,6 +41,31 @@ pub fn pick_random_relay_excluding<'a>(
     pick_random_relay_weighted(filtered_relays, |relay: &Relay| relay.weight)
 }
 
+/// Returns whether `a` and `b` are run by different hosting providers. All relays owned by
+/// Mullvad are considered to be run by the same provider.
+pub fn run_by_different_providers(a: &Relay, b: &Relay) -> bool {
+    a.provider != b.provider && !(a.owned && b.owned)
+}
+
+/// Picks one relay from `exits` and one from `entries`, such that the two are run by different
+/// hosting providers. Exits that cannot be paired with any entry are never picked.
+pub fn pick_random_relays_from_different_providers<'a>(
+    exits: &'a [Relay],
+    entries: &'a [Relay],
+) -> Option<(&'a Relay, &'a Relay)> {
+    let exits = exits.iter().filter(|exit| {
+        entries
+            .iter()
+            .any(|entry| run_by_different_providers(exit, entry))
+    });
+    let exit = pick_random_relay_weighted(exits, |relay| relay.weight)?;
+    let entries = entries
+        .iter()
+        .filter(|entry| run_by_different_providers(exit, entry));
+    let entry = pick_random_relay_weighted(entries, |relay| relay.weight)?;
+    Some((exit, entry))
+}
+
 /// Picks a relay using [pick_random_relay_weighted], using the `weight` member of each relay
 /// as the weight function.
 pub fn pick_random_relay(relays: &[Relay]) -> Option<&Relay> {
@@ -98,6 +126,39 @@ pub fn get_udp2tcp_obfuscator(
     Ok(SelectedObfuscator { config, relay })
 }
 
+pub fn get_tls_obfuscator(relay: Relay, endpoint: &MullvadWireguardEndpoint) -> SelectedObfuscator {
+    let config = ObfuscatorConfig::Tls {
+        endpoint: SocketAddr::new(endpoint.peer.endpoint.ip(), TLS_PORT),
+    };
+
+    SelectedObfuscator { config, relay }
+}
+
+/// Obfuscate traffic to `relay` through a bridge run by the user.
+pub fn get_custom_bridge_obfuscator(
+    bridge: &CustomObfuscationBridge,
+    relay: Relay,
+    endpoint: &MullvadWireguardEndpoint,
+) -> SelectedObfuscator {
+    let config = match bridge {
+        CustomObfuscationBridge::Udp2Tcp { endpoint } => ObfuscatorConfig::Udp2Tcp {
+            endpoint: *endpoint,
+        },
+        CustomObfuscationBridge::Shadowsocks {
+            endpoint: bridge_endpoint,
+            password,
+            cipher,
+        } => ObfuscatorConfig::CustomShadowsocks {
+            endpoint: *bridge_endpoint,
+            peer: endpoint.peer.endpoint,
+            password: password.clone(),
+            cipher: cipher.to_string(),
+        },
+    };
+
+    SelectedObfuscator { config, relay }
+}
+
 fn get_udp2tcp_obfuscator_port(
     obfuscation_settings: &Udp2TcpObfuscationSettings,
     udp2tcp_ports: &[u16],
@@ -136,7 +197,10 @@ pub fn get_shadowsocks_obfuscator(
     )?;
 
     Ok(SelectedObfuscator {
-        config: ObfuscatorConfig::Shadowsocks { endpoint },
+        config: ObfuscatorConfig::Shadowsocks {
+            endpoint,
+            cipher: settings.cipher.unwrap_or_default().to_string(),
+        },
         relay,
     })
 }
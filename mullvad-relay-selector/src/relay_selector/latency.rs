@@ -0,0 +1,75 @@
+//! Round-trip times measured to relays, used to prefer low-latency relays when selecting among the
+//! relays that match the user's constraints.
+
+use mullvad_types::relay_list::RelayList;
+use std::{collections::HashMap, time::Duration};
+
+/// Latency at which the weight of a relay is left unchanged. Relays with lower latency are
+/// preferred over those with higher latency. Relays that have not been measured are treated as if
+/// they had this latency.
+const REFERENCE_LATENCY: Duration = Duration::from_millis(50);
+
+/// Latest round-trip times measured to relays, by hostname.
+#[derive(Debug, Default, Clone)]
+pub struct RelayLatencies {
+    latencies: HashMap<String, Duration>,
+}
+
+impl RelayLatencies {
+    /// Record the latency of the relay with the given hostname, replacing any earlier measurement.
+    pub fn insert(&mut self, hostname: String, latency: Duration) {
+        self.latencies.insert(hostname, latency);
+    }
+
+    /// Replace measurements with those in `other`.
+    pub fn extend(&mut self, other: RelayLatencies) {
+        self.latencies.extend(other.latencies);
+    }
+
+    pub fn get(&self, hostname: &str) -> Option<Duration> {
+        self.latencies.get(hostname).copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.latencies.is_empty()
+    }
+
+    /// Scale the weight of each relay in `relay_list` by the square of how much lower than
+    /// [`REFERENCE_LATENCY`] its latency is, so that weighted random selection favors
+    /// low-latency relays.
+    pub fn apply_to(&self, relay_list: &mut RelayList) {
+        for country in &mut relay_list.countries {
+            for city in &mut country.cities {
+                for relay in &mut city.relays {
+                    if let Some(latency) = self.get(&relay.hostname) {
+                        relay.weight = latency_weight(relay.weight, latency);
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn latency_weight(weight: u64, latency: Duration) -> u64 {
+    if weight == 0 {
+        return 0;
+    }
+    let reference = REFERENCE_LATENCY.as_micros();
+    let latency = latency.as_micros().max(1);
+    let scaled = u128::from(weight) * reference * reference / (latency * latency);
+    // Never exclude a relay entirely, since it may still be the only match for the constraints
+    u64::try_from(scaled).unwrap_or(u64::MAX).max(1)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_latency_weight() {
+        assert_eq!(latency_weight(100, REFERENCE_LATENCY), 100);
+        assert_eq!(latency_weight(100, REFERENCE_LATENCY / 2), 400);
+        assert_eq!(latency_weight(100, REFERENCE_LATENCY * 10), 1);
+        assert_eq!(latency_weight(0, REFERENCE_LATENCY / 2), 0);
+    }
+}
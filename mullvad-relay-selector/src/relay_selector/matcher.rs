@@ -5,8 +5,8 @@ use mullvad_types::{
     constraints::{Constraint, Match},
     custom_list::CustomListsSettings,
     relay_constraints::{
-        GeographicLocationConstraint, InternalBridgeConstraints, LocationConstraint, Ownership,
-        Providers, ShadowsocksSettings,
+        AsnFilter, GeographicLocationConstraint, InternalBridgeConstraints, LocationConstraint,
+        Ownership, Providers, ShadowsocksSettings,
     },
     relay_list::{Relay, RelayEndpointData, RelayList, WireguardRelayEndpointData},
 };
@@ -31,10 +31,14 @@ pub fn filter_matching_relay_list(
             .filter(|relay| filter_on_active(relay))
             // Filter by location
             .filter(|relay| filter_on_location(&locations, relay))
+            // Custom relays are only used when explicitly selected
+            .filter(|relay| !relay.is_custom() || locations.is_only())
             // Filter by ownership
             .filter(|relay| filter_on_ownership(&query.ownership(), relay))
             // Filter by providers
             .filter(|relay| filter_on_providers(query.providers(), relay))
+            // Filter by ASN
+            .filter(|relay| filter_on_asns(query.asns(), relay))
             // Filter by DAITA support
             .filter(|relay| filter_on_daita(&query.wireguard_constraints().daita, relay))
             // Filter by obfuscation support
@@ -80,6 +84,8 @@ pub fn filter_matching_bridges<'a, R: Iterator<Item = &'a Relay> + Clone>(
             .filter(|relay| filter_bridge(relay))
             // Filter by location
             .filter(|relay| filter_on_location(&locations, relay))
+            // Custom relays are only used when explicitly selected
+            .filter(|relay| !relay.is_custom() || locations.is_only())
             // Filter by ownership
             .filter(|relay| filter_on_ownership(&constraints.ownership, relay))
             // Filter by providers
@@ -114,6 +120,11 @@ pub fn filter_on_providers(filter: &Constraint<Providers>, relay: &Relay) -> boo
     filter.matches(relay)
 }
 
+/// Returns whether `relay` satisfy the ASN constraint posed by `filter`.
+pub fn filter_on_asns(filter: &Constraint<AsnFilter>, relay: &Relay) -> bool {
+    filter.matches(relay)
+}
+
 /// Returns whether `relay` satisfy the daita constraint posed by `filter`.
 pub fn filter_on_daita(filter: &Constraint<bool>, relay: &Relay) -> bool {
     match (filter, &relay.endpoint_data) {
@@ -166,6 +177,7 @@ fn filter_on_shadowsocks(
         (
             ShadowsocksSettings {
                 port: Constraint::Only(desired_port),
+                ..
             },
             RelayEndpointData::Wireguard(wg_data),
         ) => {
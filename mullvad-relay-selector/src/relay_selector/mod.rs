@@ -1,15 +1,21 @@
 //! The implementation of the relay selector.
 
 pub mod detailer;
+mod failures;
 mod helpers;
+mod latency;
 pub mod matcher;
 mod parsed_relays;
 pub mod query;
 pub mod relays;
+mod sticky;
 
+use failures::RelayFailures;
+pub use latency::RelayLatencies;
 use matcher::{filter_matching_bridges, filter_matching_relay_list};
 use parsed_relays::ParsedRelays;
 use relays::{Multihop, Singlehop, WireguardConfig};
+use sticky::{StickyKey, StickyRelays};
 
 use crate::{
     detailer::{openvpn_endpoint, wireguard_endpoint},
@@ -25,12 +31,14 @@ use itertools::Itertools;
 use mullvad_types::{
     constraints::Constraint,
     custom_list::CustomListsSettings,
+    custom_relay::CustomRelaySettings,
     endpoint::MullvadWireguardEndpoint,
+    excluded_locations::ExcludedLocations,
     location::{Coordinates, Location},
     relay_constraints::{
         BridgeSettings, BridgeState, InternalBridgeConstraints, ObfuscationSettings,
         OpenVpnConstraints, RelayConstraints, RelayOverride, RelaySettings, ResolvedBridgeSettings,
-        WireguardConstraints,
+        SelectedObfuscation, WireguardConstraints,
     },
     relay_list::{Relay, RelayEndpointData, RelayList},
     settings::Settings,
@@ -44,9 +52,9 @@ use std::{
 };
 use talpid_types::{
     net::{
-        obfuscation::ObfuscatorConfig,
+        obfuscation::{ObfuscatorConfig, PortHoppingConfig},
         proxy::{CustomProxy, Shadowsocks},
-        Endpoint, IpAvailability, IpVersion, TransportProtocol, TunnelType,
+        Endpoint, IpAvailability, IpVersion, ObfuscationType, TransportProtocol, TunnelType,
     },
     ErrorExt,
 };
@@ -76,6 +84,8 @@ pub static WIREGUARD_RETRY_ORDER: LazyLock<Vec<RelayQuery>> = LazyLock::new(|| {
             .udp2tcp()
             .ip_version(IpVersion::V6)
             .build(),
+        // 7
+        RelayQueryBuilder::wireguard().tls().build(),
     ]
 });
 
@@ -106,6 +116,9 @@ pub static OPENVPN_RETRY_ORDER: LazyLock<Vec<RelayQuery>> = LazyLock::new(|| {
 pub struct RelaySelector {
     config: Arc<Mutex<SelectorConfig>>,
     parsed_relays: Arc<Mutex<ParsedRelays>>,
+    latencies: Arc<Mutex<RelayLatencies>>,
+    failures: Arc<Mutex<RelayFailures>>,
+    sticky: Arc<Mutex<StickyRelays>>,
 }
 
 #[derive(Clone)]
@@ -114,7 +127,15 @@ pub struct SelectorConfig {
     pub relay_settings: RelaySettings,
     pub additional_constraints: AdditionalRelayConstraints,
     pub custom_lists: CustomListsSettings,
+    pub custom_relays: CustomRelaySettings,
+    /// Locations that are never selected.
+    pub excluded_locations: ExcludedLocations,
     pub relay_overrides: Vec<RelayOverride>,
+    /// Prefer relays with low measured latency, see [`RelaySelector::update_latencies`].
+    pub prefer_low_latency: bool,
+    /// Reuse the relays that were last connected to, see
+    /// [`RelaySelector::remember_connected_relays`].
+    pub sticky_relays: bool,
     // Wireguard specific data
     pub obfuscation_settings: ObfuscationSettings,
     // OpenVPN specific data
@@ -151,7 +172,11 @@ impl SelectorConfig {
             bridge_settings: settings.bridge_settings.clone(),
             obfuscation_settings: settings.obfuscation_settings.clone(),
             custom_lists: settings.custom_lists.clone(),
+            custom_relays: settings.custom_relays.clone(),
+            excluded_locations: settings.excluded_locations.clone(),
             relay_overrides: settings.relay_overrides.clone(),
+            prefer_low_latency: settings.prefer_low_latency_relays,
+            sticky_relays: settings.sticky_relays,
         }
     }
 }
@@ -207,6 +232,10 @@ struct NormalSelectorConfig<'a> {
     user_preferences: &'a RelayConstraints,
     additional_preferences: &'a AdditionalRelayConstraints,
     custom_lists: &'a CustomListsSettings,
+    custom_relays: &'a CustomRelaySettings,
+    excluded_locations: &'a ExcludedLocations,
+    prefer_low_latency: bool,
+    sticky_relays: bool,
     // Wireguard specific data
     obfuscation_settings: &'a ObfuscationSettings,
     // OpenVPN specific data
@@ -220,6 +249,7 @@ pub enum GetRelay {
     Wireguard {
         endpoint: MullvadWireguardEndpoint,
         obfuscator: Option<SelectedObfuscator>,
+        port_hopping: Option<PortHoppingConfig>,
         inner: WireguardConfig,
     },
     #[cfg(not(target_os = "android"))]
@@ -276,7 +306,11 @@ impl Default for SelectorConfig {
             obfuscation_settings: default_settings.obfuscation_settings,
             bridge_state: default_settings.bridge_state,
             custom_lists: default_settings.custom_lists,
+            custom_relays: default_settings.custom_relays,
+            excluded_locations: default_settings.excluded_locations,
             relay_overrides: default_settings.relay_overrides,
+            prefer_low_latency: default_settings.prefer_low_latency_relays,
+            sticky_relays: default_settings.sticky_relays,
         }
     }
 }
@@ -310,12 +344,30 @@ impl<'a> From<&'a SelectorConfig> for SpecializedSelectorConfig<'a> {
                     bridge_state: &value.bridge_state,
                     bridge_settings: &value.bridge_settings,
                     custom_lists: &value.custom_lists,
+                    custom_relays: &value.custom_relays,
+                    excluded_locations: &value.excluded_locations,
+                    prefer_low_latency: value.prefer_low_latency,
+                    sticky_relays: value.sticky_relays,
                 })
             }
         }
     }
 }
 
+impl NormalSelectorConfig<'_> {
+    /// Returns the location constraints that sticky relays are remembered for.
+    fn sticky_key(&self) -> StickyKey {
+        let constraints = self.user_preferences;
+        let multihop = constraints.tunnel_protocol == TunnelType::Wireguard
+            && constraints.wireguard_constraints.multihop();
+        StickyKey {
+            location: constraints.location.clone(),
+            entry_location: multihop
+                .then(|| constraints.wireguard_constraints.entry_location.clone()),
+        }
+    }
+}
+
 impl<'a> TryFrom<NormalSelectorConfig<'a>> for RelayQuery {
     type Error = crate::Error;
 
@@ -332,6 +384,8 @@ impl<'a> TryFrom<NormalSelectorConfig<'a>> for RelayQuery {
                 ip_version,
                 use_multihop,
                 entry_location,
+                middle_hops,
+                multihop_diversity,
             } = wireguard_constraints;
             let AdditionalWireguardConstraints {
                 daita,
@@ -343,6 +397,8 @@ impl<'a> TryFrom<NormalSelectorConfig<'a>> for RelayQuery {
                 ip_version,
                 use_multihop: Constraint::Only(use_multihop),
                 entry_location,
+                middle_hops: Constraint::Only(middle_hops),
+                multihop_diversity: Constraint::Only(multihop_diversity),
                 obfuscation: ObfuscationQuery::from(obfuscation_settings),
                 daita: Constraint::Only(daita),
                 daita_use_multihop_if_necessary: Constraint::Only(daita_use_multihop_if_necessary),
@@ -386,6 +442,7 @@ impl<'a> TryFrom<NormalSelectorConfig<'a>> for RelayQuery {
         RelayQuery::new(
             value.user_preferences.location.clone(),
             value.user_preferences.providers.clone(),
+            value.user_preferences.asns.clone(),
             value.user_preferences.ownership,
             value.user_preferences.tunnel_protocol,
             wireguard_constraints,
@@ -421,6 +478,9 @@ impl RelaySelector {
         RelaySelector {
             config: Arc::new(Mutex::new(config)),
             parsed_relays: Arc::new(Mutex::new(unsynchronized_parsed_relays)),
+            latencies: Arc::new(Mutex::new(RelayLatencies::default())),
+            failures: Arc::new(Mutex::new(RelayFailures::default())),
+            sticky: Arc::new(Mutex::new(StickyRelays::default())),
         }
     }
 
@@ -432,6 +492,9 @@ impl RelaySelector {
                 &config.relay_overrides,
             ))),
             config: Arc::new(Mutex::new(config)),
+            latencies: Arc::new(Mutex::new(RelayLatencies::default())),
+            failures: Arc::new(Mutex::new(RelayFailures::default())),
+            sticky: Arc::new(Mutex::new(StickyRelays::default())),
         }
     }
 
@@ -451,6 +514,76 @@ impl RelaySelector {
         parsed_relays.set_overrides(relay_overrides);
     }
 
+    /// Record measured relay latencies. They are only used if
+    /// [`SelectorConfig::prefer_low_latency`] is set.
+    pub fn update_latencies(&self, latencies: RelayLatencies) {
+        self.latencies.lock().unwrap().extend(latencies);
+    }
+
+    /// Record that connecting to the relay with the given hostname failed. The relay is less
+    /// likely to be selected for a while afterwards.
+    pub fn record_failure(&self, hostname: &str) {
+        log::debug!("Deprioritizing {hostname} after a failed connection attempt");
+        self.failures.lock().unwrap().record(hostname);
+        self.sticky.lock().unwrap().forget(hostname);
+    }
+
+    /// Record that connecting to `exit`, through `entry` if multihop is used, succeeded. If
+    /// [`SelectorConfig::sticky_relays`] is set, the same relays are selected again for the current
+    /// location constraints, until connecting to them fails.
+    pub fn remember_connected_relays(&self, exit: &Relay, entry: Option<&Relay>) {
+        let config_guard = self.config.lock().unwrap();
+        let SpecializedSelectorConfig::Normal(normal_config) =
+            SpecializedSelectorConfig::from(&*config_guard)
+        else {
+            return;
+        };
+        if normal_config.sticky_relays {
+            self.sticky
+                .lock()
+                .unwrap()
+                .remember(normal_config.sticky_key(), exit, entry);
+        }
+    }
+
+    /// Returns the relays that match the current constraints, as either entry or exit relay. These
+    /// are the relays whose latency matters to the selection.
+    pub fn candidate_relays(&self) -> Vec<Relay> {
+        let config_guard = self.config.lock().unwrap();
+        let SpecializedSelectorConfig::Normal(normal_config) =
+            SpecializedSelectorConfig::from(&*config_guard)
+        else {
+            return vec![];
+        };
+        let Ok(query) = RelayQuery::try_from(normal_config.clone()) else {
+            return vec![];
+        };
+        let mut relay_list = self.parsed_relays.lock().unwrap().parsed_list().clone();
+        normal_config
+            .excluded_locations
+            .remove_from(&mut relay_list);
+
+        let mut candidates =
+            filter_matching_relay_list(&query, &relay_list, normal_config.custom_lists);
+        if query.wireguard_constraints().multihop() {
+            let mut entry_query = query.clone();
+            if entry_query
+                .set_location(query.wireguard_constraints().entry_location.clone())
+                .is_ok()
+            {
+                candidates.extend(filter_matching_relay_list(
+                    &entry_query,
+                    &relay_list,
+                    normal_config.custom_lists,
+                ));
+            }
+        }
+        candidates
+            .into_iter()
+            .unique_by(|relay| relay.hostname.clone())
+            .collect()
+    }
+
     /// Returns all countries and cities. The cities in the object returned does not have any
     /// relays in them.
     pub fn get_relays(&mut self) -> RelayList {
@@ -458,10 +591,25 @@ impl RelaySelector {
         parsed_relays.original_list().clone()
     }
 
+    /// Same as [`Self::get_relays`], except that the custom relays are included.
+    pub fn get_relay_locations(&self) -> RelayList {
+        let mut relay_list = self.parsed_relays.lock().unwrap().original_list().clone();
+        self.config
+            .lock()
+            .unwrap()
+            .custom_relays
+            .add_to(&mut relay_list);
+        relay_list
+    }
+
     pub fn etag(&self) -> Option<String> {
         self.parsed_relays.lock().unwrap().etag()
     }
 
+    pub fn last_modified(&self) -> Option<String> {
+        self.parsed_relays.lock().unwrap().last_modified()
+    }
+
     pub fn last_updated(&self) -> SystemTime {
         self.parsed_relays.lock().unwrap().last_updated()
     }
@@ -514,8 +662,9 @@ impl RelaySelector {
                 Ok(GetRelay::Custom(custom_config.clone()))
             }
             SpecializedSelectorConfig::Normal(normal_config) => {
-                let relay_list = &self.parsed_relays.lock().unwrap().parsed_list().clone();
+                let relay_list = &self.selectable_relay_list(&normal_config);
                 Self::get_relay_inner(&query, relay_list, normal_config.custom_lists)
+                    .map(|relay| Self::apply_custom_relays(relay, normal_config.custom_relays))
             }
         }
     }
@@ -554,6 +703,42 @@ impl RelaySelector {
         }
     }
 
+    /// Same as [`Self::get_relay`], except that the WireGuard retry attempts which use
+    /// `obfuscation` are tried before any other attempts, if the obfuscation mode is auto.
+    pub fn get_relay_with_preferred_obfuscation(
+        &self,
+        retry_attempt: usize,
+        runtime_ip_availability: IpAvailability,
+        obfuscation: ObfuscationType,
+    ) -> Result<GetRelay, Error> {
+        let config_guard = self.config.lock().unwrap();
+        let auto_obfuscation =
+            config_guard.obfuscation_settings.selected_obfuscation == SelectedObfuscation::Auto;
+        let config = SpecializedSelectorConfig::from(&*config_guard);
+        match config {
+            SpecializedSelectorConfig::Normal(normal_config)
+                if auto_obfuscation
+                    && normal_config.user_preferences.tunnel_protocol == TunnelType::Wireguard =>
+            {
+                drop(config_guard);
+                let (mut retry_order, rest): (Vec<_>, Vec<_>) =
+                    WIREGUARD_RETRY_ORDER.iter().cloned().partition(|query| {
+                        uses_obfuscation(&query.wireguard_constraints().obfuscation, obfuscation)
+                    });
+                retry_order.extend(rest);
+                self.get_relay_with_custom_params(
+                    retry_attempt,
+                    &retry_order,
+                    runtime_ip_availability,
+                )
+            }
+            _ => {
+                drop(config_guard);
+                self.get_relay(retry_attempt, runtime_ip_availability)
+            }
+        }
+    }
+
     /// Returns a random relay and relay endpoint matching the current constraints defined by
     /// `retry_order` corresponding to `retry_attempt`.
     pub fn get_relay_with_custom_params(
@@ -572,7 +757,7 @@ impl RelaySelector {
                 Ok(GetRelay::Custom(custom_config.clone()))
             }
             SpecializedSelectorConfig::Normal(normal_config) => {
-                let relay_list = self.parsed_relays.lock().unwrap().parsed_list().clone();
+                let relay_list = self.selectable_relay_list(&normal_config);
                 // Merge user preferences with the relay selector's default preferences.
                 let query = Self::pick_and_merge_query(
                     retry_attempt,
@@ -581,11 +766,101 @@ impl RelaySelector {
                     &normal_config,
                     &relay_list,
                 )?;
-                Self::get_relay_inner(&query, &relay_list, normal_config.custom_lists)
+                let sticky_relay = if normal_config.sticky_relays {
+                    self.get_sticky_relay(&query, &relay_list, &normal_config)
+                } else {
+                    None
+                };
+                match sticky_relay {
+                    Some(relay) => Ok(relay),
+                    None => Self::get_relay_inner(&query, &relay_list, normal_config.custom_lists),
+                }
+                .map(|relay| Self::apply_custom_relays(relay, normal_config.custom_relays))
             }
         }
     }
 
+    /// Returns the relays that were last connected to for the current location constraints, if
+    /// they still match `query`.
+    fn get_sticky_relay(
+        &self,
+        query: &RelayQuery,
+        relay_list: &RelayList,
+        config: &NormalSelectorConfig<'_>,
+    ) -> Option<GetRelay> {
+        let sticky = self.sticky.lock().unwrap();
+        let selection = sticky.get(&config.sticky_key())?;
+
+        let mut sticky_query = query.clone();
+        sticky_query
+            .set_location(Constraint::Only(selection.exit.clone().into()))
+            .ok()?;
+        if let Some(entry) = &selection.entry {
+            let mut wireguard_constraints = sticky_query.wireguard_constraints().clone();
+            wireguard_constraints.entry_location = Constraint::Only(entry.clone().into());
+            sticky_query
+                .set_wireguard_constraints(wireguard_constraints)
+                .ok()?;
+        }
+        Self::get_relay_inner(&sticky_query, relay_list, config.custom_lists).ok()
+    }
+
+    /// Returns the relay list that relays are selected from, including the custom relays and
+    /// excluding the excluded locations. If low latency is preferred, the relays are weighted by
+    /// their latency. Relays that recently failed to connect are weighted down.
+    fn selectable_relay_list(&self, config: &NormalSelectorConfig<'_>) -> RelayList {
+        let mut relay_list = self.parsed_relays.lock().unwrap().parsed_list().clone();
+        config.custom_relays.add_to(&mut relay_list);
+        config.excluded_locations.remove_from(&mut relay_list);
+        if config.prefer_low_latency {
+            self.latencies.lock().unwrap().apply_to(&mut relay_list);
+        }
+        self.failures.lock().unwrap().apply_to(&mut relay_list);
+        relay_list
+    }
+
+    /// Connect to any custom relays in `relay` using their own endpoints and obfuscation.
+    ///
+    /// Custom relays are only obfuscated if they have an obfuscation server of their own, and
+    /// never use port hopping, since they need not accept traffic on the ports of Mullvad relays.
+    fn apply_custom_relays(relay: GetRelay, custom_relays: &CustomRelaySettings) -> GetRelay {
+        let GetRelay::Wireguard {
+            mut endpoint,
+            obfuscator,
+            port_hopping,
+            inner,
+        } = relay
+        else {
+            return relay;
+        };
+        detailer::apply_custom_relays(&mut endpoint, &inner, custom_relays);
+
+        let obfuscator_relay = match &inner {
+            WireguardConfig::Singlehop { exit } => exit,
+            WireguardConfig::Multihop { entry, .. } => entry,
+        };
+        let (obfuscator, port_hopping) = match custom_relays.find(&obfuscator_relay.hostname) {
+            Some(custom_relay) => {
+                let obfuscator = custom_relay.obfuscation.as_ref().map(|bridge| {
+                    helpers::get_custom_bridge_obfuscator(
+                        bridge,
+                        obfuscator_relay.clone(),
+                        &endpoint,
+                    )
+                });
+                (obfuscator, None)
+            }
+            None => (obfuscator, port_hopping),
+        };
+
+        GetRelay::Wireguard {
+            endpoint,
+            obfuscator,
+            port_hopping,
+            inner,
+        }
+    }
+
     /// This function defines the merge between a set of pre-defined queries and `user_preferences`
     /// for the given `retry_attempt`.
     ///
@@ -686,10 +961,12 @@ impl RelaySelector {
         let endpoint = Self::get_wireguard_endpoint(query, parsed_relays, &inner)?;
         let obfuscator =
             Self::get_wireguard_obfuscator(query, inner.clone(), &endpoint, parsed_relays)?;
+        let port_hopping = Self::get_port_hopping_config(query, parsed_relays);
 
         Ok(GetRelay::Wireguard {
             endpoint,
             obfuscator,
+            port_hopping,
             inner,
         })
     }
@@ -781,9 +1058,14 @@ impl RelaySelector {
         // generate a list of potential entry relays, disregarding any location constraint
         let mut entry_query = query.clone();
         entry_query.set_location(Constraint::Any)?;
+        let multihop_diversity =
+            query.wireguard_constraints().multihop_diversity == Constraint::Only(true);
         let mut entry_candidates =
             filter_matching_relay_list(&entry_query, parsed_relays, custom_lists)
                 .into_iter()
+                .filter(|entry| {
+                    !multihop_diversity || helpers::run_by_different_providers(entry, exit)
+                })
                 .map(|entry| RelayWithDistance::new_with_distance_from(entry, &exit.location))
                 .collect_vec();
 
@@ -806,12 +1088,14 @@ impl RelaySelector {
         Ok(Multihop::new(entry.clone(), exit.clone()))
     }
 
-    /// This function selects a valid entry and exit relay to be used in a multihop configuration.
+    /// This function selects a valid entry and exit relay to be used in a multihop configuration,
+    /// along with the number of middle relays requested by the query.
     ///
     /// # Returns
     /// * An `Err` if no exit relay can be chosen
     /// * An `Err` if no entry relay can be chosen
     /// * An `Err` if the chosen entry and exit relays are the same
+    /// * An `Err` if there are not enough relays left to use as middle relays
     /// * `Ok(WireguardConfig::Multihop)` otherwise
     fn get_wireguard_multihop_config(
         query: &RelayQuery,
@@ -841,7 +1125,13 @@ impl RelaySelector {
 
         // We avoid picking the same relay for entry and exit by choosing one and excluding it when
         // choosing the other.
+        let multihop_diversity =
+            query.wireguard_constraints().multihop_diversity == Constraint::Only(true);
         let (exit, entry) = match (exit_candidates.as_slice(), entry_candidates.as_slice()) {
+            // With multihop diversity, the entry and exit must also be run by different providers
+            (exits, entries) if multihop_diversity => {
+                helpers::pick_random_relays_from_different_providers(exits, entries)
+            }
             // In the case where there is only one entry to choose from, we have to pick it before
             // the exit
             (exits, [entry]) if exits.contains(entry) => {
@@ -857,7 +1147,61 @@ impl RelaySelector {
         }
         .ok_or(Error::NoRelay)?;
 
-        Ok(Multihop::new(entry.clone(), exit.clone()))
+        let middle =
+            Self::get_wireguard_middle_relays(query, custom_lists, parsed_relays, entry, exit)?;
+
+        Ok(Multihop::new(entry.clone(), exit.clone()).with_middle(middle))
+    }
+
+    /// Pick the relays to place between `entry` and `exit` in a multihop circuit. These are not
+    /// bound by any location constraint, but must otherwise match `query`. No relay is used more
+    /// than once in the circuit.
+    fn get_wireguard_middle_relays(
+        query: &RelayQuery,
+        custom_lists: &CustomListsSettings,
+        parsed_relays: &RelayList,
+        entry: &Relay,
+        exit: &Relay,
+    ) -> Result<Vec<Relay>, Error> {
+        let count = query.wireguard_constraints().middle_hops.unwrap_or(0);
+        if count == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut middle_query = query.clone();
+        middle_query.set_location(Constraint::Any)?;
+        // DAITA should only be enabled for the entry relay
+        let mut wg_constraints = middle_query.wireguard_constraints().clone();
+        wg_constraints.daita = Constraint::Only(false);
+        middle_query.set_wireguard_constraints(wg_constraints)?;
+
+        // Hops are routed to by IP, so no two hops may share an address
+        let mut candidates = filter_matching_relay_list(&middle_query, parsed_relays, custom_lists);
+        candidates.retain(|relay| {
+            relay.ipv4_addr_in != entry.ipv4_addr_in && relay.ipv4_addr_in != exit.ipv4_addr_in
+        });
+        let multihop_diversity =
+            query.wireguard_constraints().multihop_diversity == Constraint::Only(true);
+        if multihop_diversity {
+            candidates.retain(|relay| {
+                helpers::run_by_different_providers(relay, entry)
+                    && helpers::run_by_different_providers(relay, exit)
+            });
+        }
+
+        let mut middle = Vec::with_capacity(usize::from(count));
+        for _ in 0..count {
+            let relay = helpers::pick_random_relay(&candidates)
+                .cloned()
+                .ok_or(Error::NoRelay)?;
+            candidates.retain(|candidate| {
+                candidate.ipv4_addr_in != relay.ipv4_addr_in
+                    && (!multihop_diversity
+                        || helpers::run_by_different_providers(candidate, &relay))
+            });
+            middle.push(relay);
+        }
+        Ok(middle)
     }
 
     /// Constructs a [`MullvadEndpoint`] with details for how to connect to `relay`.
@@ -912,6 +1256,29 @@ impl RelaySelector {
 
                 Ok(Some(obfuscation))
             }
+            ObfuscationQuery::Tls => Ok(Some(helpers::get_tls_obfuscator(
+                obfuscator_relay,
+                endpoint,
+            ))),
+            ObfuscationQuery::CustomBridge(bridge) => Ok(Some(
+                helpers::get_custom_bridge_obfuscator(bridge, obfuscator_relay, endpoint),
+            )),
+            ObfuscationQuery::PortHopping(_) => Ok(None),
+        }
+    }
+
+    /// Port hopping does not use an obfuscator. Instead, the tunnel moves between the ports that
+    /// the relays accept WireGuard traffic on.
+    fn get_port_hopping_config(
+        query: &RelayQuery,
+        parsed_relays: &RelayList,
+    ) -> Option<PortHoppingConfig> {
+        match &query.wireguard_constraints().obfuscation {
+            ObfuscationQuery::PortHopping(settings) => Some(PortHoppingConfig {
+                interval: settings.interval(),
+                port_ranges: parsed_relays.wireguard.port_ranges.clone(),
+            }),
+            _ => None,
         }
     }
 
@@ -1144,6 +1511,19 @@ impl RelaySelector {
     }
 }
 
+/// Returns whether `query` results in the obfuscation method `obfuscation`.
+fn uses_obfuscation(query: &ObfuscationQuery, obfuscation: ObfuscationType) -> bool {
+    match query {
+        ObfuscationQuery::Udp2tcp(_) => obfuscation == ObfuscationType::Udp2Tcp,
+        ObfuscationQuery::Shadowsocks(_) => obfuscation == ObfuscationType::Shadowsocks,
+        ObfuscationQuery::Tls => obfuscation == ObfuscationType::Tls,
+        ObfuscationQuery::Off
+        | ObfuscationQuery::Auto
+        | ObfuscationQuery::PortHopping(_)
+        | ObfuscationQuery::CustomBridge(_) => false,
+    }
+}
+
 fn apply_ip_availability(
     runtime_ip_availability: IpAvailability,
     user_query: &mut RelayQuery,
@@ -60,6 +60,10 @@ impl ParsedRelays {
         self.parsed_list.etag.clone()
     }
 
+    pub fn last_modified(&self) -> Option<String> {
+        self.parsed_list.last_modified.clone()
+    }
+
     /// The original list of relays, as returned by the Mullvad relays API.
     pub const fn original_list(&self) -> &RelayList {
         &self.original_list
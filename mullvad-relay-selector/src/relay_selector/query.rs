@@ -32,10 +32,11 @@ use crate::Error;
 use mullvad_types::{
     constraints::Constraint,
     relay_constraints::{
-        BridgeConstraints, BridgeSettings, BridgeState, BridgeType, LocationConstraint,
-        ObfuscationSettings, OpenVpnConstraints, Ownership, Providers, RelayConstraints,
-        RelaySettings, SelectedObfuscation, ShadowsocksSettings, TransportPort,
-        Udp2TcpObfuscationSettings, WireguardConstraints,
+        AsnFilter, BridgeConstraints, BridgeSettings, BridgeState, BridgeType,
+        CustomObfuscationBridge, LocationConstraint, ObfuscationSettings, OpenVpnConstraints,
+        Ownership, PortHoppingSettings, Providers, RelayConstraints, RelaySettings,
+        SelectedObfuscation, ShadowsocksSettings, TransportPort, Udp2TcpObfuscationSettings,
+        WireguardConstraints,
     },
     wireguard::QuantumResistantState,
     Intersection,
@@ -77,6 +78,7 @@ use talpid_types::net::{proxy::CustomProxy, IpVersion, TunnelType};
 pub struct RelayQuery {
     location: Constraint<LocationConstraint>,
     providers: Constraint<Providers>,
+    asns: Constraint<AsnFilter>,
     ownership: Constraint<Ownership>,
     tunnel_protocol: TunnelType,
     wireguard_constraints: WireguardRelayQuery,
@@ -88,6 +90,7 @@ impl RelayQuery {
     pub fn new(
         location: Constraint<LocationConstraint>,
         providers: Constraint<Providers>,
+        asns: Constraint<AsnFilter>,
         ownership: Constraint<Ownership>,
         tunnel_protocol: TunnelType,
         wireguard_constraints: WireguardRelayQuery,
@@ -96,6 +99,7 @@ impl RelayQuery {
         let mut query = RelayQuery {
             location,
             providers,
+            asns,
             ownership,
             tunnel_protocol,
             wireguard_constraints,
@@ -134,6 +138,10 @@ impl RelayQuery {
         &self.providers
     }
 
+    pub fn asns(&self) -> &Constraint<AsnFilter> {
+        &self.asns
+    }
+
     pub fn ownership(&self) -> Constraint<Ownership> {
         self.ownership
     }
@@ -212,6 +220,7 @@ impl RelayQuery {
         let constraints = RelayConstraints {
             location: self.location,
             providers: self.providers,
+            asns: self.asns,
             ownership: self.ownership,
             tunnel_protocol: self.tunnel_protocol,
             wireguard_constraints: self.wireguard_constraints.into_constraints(),
@@ -240,6 +249,7 @@ impl Default for RelayQuery {
         RelayQuery {
             location: Constraint::Any,
             providers: Constraint::Any,
+            asns: Constraint::Any,
             ownership: Constraint::Any,
             tunnel_protocol: TunnelType::default(),
             wireguard_constraints: WireguardRelayQuery::new(),
@@ -270,6 +280,8 @@ pub struct WireguardRelayQuery {
     pub ip_version: Constraint<IpVersion>,
     pub use_multihop: Constraint<bool>,
     pub entry_location: Constraint<LocationConstraint>,
+    pub middle_hops: Constraint<u8>,
+    pub multihop_diversity: Constraint<bool>,
     pub obfuscation: ObfuscationQuery,
     pub daita: Constraint<bool>,
     pub daita_use_multihop_if_necessary: Constraint<bool>,
@@ -283,6 +295,9 @@ pub enum ObfuscationQuery {
     Auto,
     Udp2tcp(Udp2TcpObfuscationSettings),
     Shadowsocks(ShadowsocksSettings),
+    PortHopping(PortHoppingSettings),
+    Tls,
+    CustomBridge(CustomObfuscationBridge),
 }
 
 impl ObfuscationQuery {
@@ -306,6 +321,20 @@ impl ObfuscationQuery {
                 shadowsocks: settings,
                 ..Default::default()
             },
+            ObfuscationQuery::PortHopping(settings) => ObfuscationSettings {
+                selected_obfuscation: SelectedObfuscation::PortHopping,
+                port_hopping: settings,
+                ..Default::default()
+            },
+            ObfuscationQuery::Tls => ObfuscationSettings {
+                selected_obfuscation: SelectedObfuscation::Tls,
+                ..Default::default()
+            },
+            ObfuscationQuery::CustomBridge(bridge) => ObfuscationSettings {
+                selected_obfuscation: SelectedObfuscation::CustomBridge,
+                custom_bridge: Some(bridge),
+                ..Default::default()
+            },
         }
     }
 }
@@ -323,6 +352,16 @@ impl From<ObfuscationSettings> for ObfuscationQuery {
             SelectedObfuscation::Shadowsocks => {
                 ObfuscationQuery::Shadowsocks(obfuscation.shadowsocks)
             }
+            SelectedObfuscation::PortHopping => {
+                ObfuscationQuery::PortHopping(obfuscation.port_hopping)
+            }
+            SelectedObfuscation::Tls => ObfuscationQuery::Tls,
+            // The settings are validated when set, so the bridge is only missing if the settings
+            // file has been edited by hand.
+            SelectedObfuscation::CustomBridge => match obfuscation.custom_bridge {
+                Some(bridge) => ObfuscationQuery::CustomBridge(bridge),
+                None => ObfuscationQuery::Auto,
+            },
         }
     }
 }
@@ -338,6 +377,13 @@ impl Intersection for ObfuscationQuery {
             (ObfuscationQuery::Shadowsocks(a), ObfuscationQuery::Shadowsocks(b)) => {
                 Some(ObfuscationQuery::Shadowsocks(a.intersection(b)?))
             }
+            (ObfuscationQuery::PortHopping(a), ObfuscationQuery::PortHopping(b)) => {
+                Some(ObfuscationQuery::PortHopping(a.intersection(b)?))
+            }
+            (ObfuscationQuery::Tls, ObfuscationQuery::Tls) => Some(ObfuscationQuery::Tls),
+            (ObfuscationQuery::CustomBridge(a), ObfuscationQuery::CustomBridge(b)) if a == b => {
+                Some(ObfuscationQuery::CustomBridge(a))
+            }
             _ => None,
         }
     }
@@ -356,6 +402,8 @@ impl WireguardRelayQuery {
             ip_version: Constraint::Any,
             use_multihop: Constraint::Any,
             entry_location: Constraint::Any,
+            middle_hops: Constraint::Any,
+            multihop_diversity: Constraint::Any,
             obfuscation: ObfuscationQuery::Auto,
             daita: Constraint::Any,
             daita_use_multihop_if_necessary: Constraint::Any,
@@ -370,6 +418,8 @@ impl WireguardRelayQuery {
             ip_version: self.ip_version,
             entry_location: self.entry_location,
             use_multihop: self.use_multihop.unwrap_or(false),
+            middle_hops: self.middle_hops.unwrap_or(0),
+            multihop_diversity: self.multihop_diversity.unwrap_or(false),
         }
     }
 }
@@ -388,6 +438,8 @@ impl From<WireguardRelayQuery> for WireguardConstraints {
             ip_version: value.ip_version,
             entry_location: value.entry_location,
             use_multihop: value.use_multihop.unwrap_or(false),
+            middle_hops: value.middle_hops.unwrap_or(0),
+            multihop_diversity: value.multihop_diversity.unwrap_or(false),
         }
     }
 }
@@ -507,8 +559,8 @@ pub mod builder {
     use mullvad_types::{
         constraints::Constraint,
         relay_constraints::{
-            BridgeConstraints, LocationConstraint, RelayConstraints, SelectedObfuscation,
-            ShadowsocksSettings, TransportPort, Udp2TcpObfuscationSettings,
+            BridgeConstraints, LocationConstraint, PortHoppingSettings, RelayConstraints,
+            SelectedObfuscation, ShadowsocksSettings, TransportPort, Udp2TcpObfuscationSettings,
         },
         wireguard::QuantumResistantState,
     };
@@ -518,7 +570,7 @@ pub mod builder {
 
     // Re-exports
     pub use mullvad_types::relay_constraints::{
-        GeographicLocationConstraint, Ownership, Providers,
+        AsnFilter, GeographicLocationConstraint, Ownership, Providers,
     };
     pub use talpid_types::net::{IpVersion, TransportProtocol};
 
@@ -539,6 +591,9 @@ pub mod builder {
     ///  in the final `RelayQuery` is `Constraint::Any`.
     pub struct Any;
 
+    /// TLS obfuscation has no settings, so this marks that it has been selected.
+    pub struct Tls;
+
     // This impl-block is quantified over all configurations, e.g. [`Any`],
     // [`WireguardRelayQuery`] & [`OpenVpnRelayQuery`]
     impl<VpnProtocol> RelayQueryBuilder<VpnProtocol> {
@@ -560,6 +615,12 @@ pub mod builder {
             self
         }
 
+        /// Configure which [`AsnFilter`] to use.
+        pub fn asns(mut self, asns: AsnFilter) -> Self {
+            self.query.asns = Constraint::Only(asns);
+            self
+        }
+
         /// Assemble the final [`RelayQuery`] that has been configured
         /// through `self`.
         pub fn build(mut self) -> RelayQuery {
@@ -732,6 +793,19 @@ pub mod builder {
             self.query.wireguard_constraints.entry_location = Constraint::Only(location.into());
             self
         }
+
+        /// Route through `hops` additional relays between the entry and exit relay. This
+        /// requires multihop to be enabled.
+        pub fn middle_hops(mut self, hops: u8) -> Self {
+            self.query.wireguard_constraints.middle_hops = Constraint::Only(hops);
+            self
+        }
+
+        /// Never pick two relays from the same hosting provider for the multihop circuit.
+        pub fn multihop_diversity(mut self) -> Self {
+            self.query.wireguard_constraints.multihop_diversity = Constraint::Only(true);
+            self
+        }
     }
 
     impl<Multihop, Daita, QuantumResistant>
@@ -768,6 +842,7 @@ pub mod builder {
         {
             let obfuscation = ShadowsocksSettings {
                 port: Constraint::Any,
+                cipher: Constraint::Any,
             };
             let protocol = Wireguard {
                 multihop: self.protocol.multihop,
@@ -782,6 +857,43 @@ pub mod builder {
                 protocol,
             }
         }
+
+        /// Enable TLS obfuscation. The port is always 443, so there is nothing to configure.
+        pub fn tls(
+            mut self,
+        ) -> RelayQueryBuilder<Wireguard<Multihop, Tls, Daita, QuantumResistant>> {
+            let protocol = Wireguard {
+                multihop: self.protocol.multihop,
+                obfuscation: Tls,
+                daita: self.protocol.daita,
+                quantum_resistant: self.protocol.quantum_resistant,
+            };
+            self.query.wireguard_constraints.obfuscation = ObfuscationQuery::Tls;
+            RelayQueryBuilder {
+                query: self.query,
+                protocol,
+            }
+        }
+
+        /// Enable port hopping, using the default interval.
+        pub fn port_hopping(
+            mut self,
+        ) -> RelayQueryBuilder<Wireguard<Multihop, PortHoppingSettings, Daita, QuantumResistant>>
+        {
+            let obfuscation = PortHoppingSettings::default();
+            let protocol = Wireguard {
+                multihop: self.protocol.multihop,
+                obfuscation: obfuscation.clone(),
+                daita: self.protocol.daita,
+                quantum_resistant: self.protocol.quantum_resistant,
+            };
+            self.query.wireguard_constraints.obfuscation =
+                ObfuscationQuery::PortHopping(obfuscation);
+            RelayQueryBuilder {
+                query: self.query,
+                protocol,
+            }
+        }
     }
 
     impl<Multihop, Daita, QuantumResistant>
@@ -940,7 +1052,7 @@ mod test {
     use mullvad_types::{
         constraints::Constraint,
         relay_constraints::{
-            ObfuscationSettings, SelectedObfuscation, ShadowsocksSettings,
+            ObfuscationSettings, PortHoppingSettings, SelectedObfuscation, ShadowsocksSettings,
             Udp2TcpObfuscationSettings,
         },
     };
@@ -1036,7 +1148,10 @@ mod test {
                 },
                 shadowsocks: ShadowsocksSettings {
                     port: port2,
+                    cipher: Constraint::Any,
                 },
+                port_hopping: PortHoppingSettings::default(),
+                custom_bridge: None,
             });
             assert_eq!(query, ObfuscationQuery::Auto);
         }
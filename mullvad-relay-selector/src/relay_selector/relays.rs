@@ -4,15 +4,19 @@
 use mullvad_types::relay_list::{Relay, RelayEndpointData};
 
 /// - [`WireguardConfig::Singlehop`]: A wireguard relay where VPN traffic enters and exits.
-/// - [`WireguardConfig::Multihop`]: Two wireguard relays to be used in a multihop circuit. VPN
-///   traffic will enter through `entry` and eventually exit through `exit` before the traffic will
-///   actually be routed to the internet.
+/// - [`WireguardConfig::Multihop`]: Two or more wireguard relays to be used in a multihop circuit.
+///   VPN traffic will enter through `entry`, pass through each relay in `middle` and eventually
+///   exit through `exit` before the traffic will actually be routed to the internet.
 #[derive(Clone, Debug)]
 pub enum WireguardConfig {
     /// An exit relay.
     Singlehop { exit: Relay },
-    /// An entry and an exit relay.
-    Multihop { exit: Relay, entry: Relay },
+    /// An entry and an exit relay, and any relays in between them.
+    Multihop {
+        exit: Relay,
+        entry: Relay,
+        middle: Vec<Relay>,
+    },
 }
 
 /// A type representing single Wireguard relay.
@@ -36,6 +40,7 @@ pub struct Singlehop(Relay);
 pub struct Multihop {
     entry: Relay,
     exit: Relay,
+    middle: Vec<Relay>,
 }
 
 impl From<Singlehop> for WireguardConfig {
@@ -49,6 +54,7 @@ impl From<Multihop> for WireguardConfig {
         WireguardConfig::Multihop {
             exit: relay.exit,
             entry: relay.entry,
+            middle: relay.middle,
         }
     }
 }
@@ -76,6 +82,19 @@ impl Multihop {
             entry.endpoint_data,
             RelayEndpointData::Wireguard(_)
         ));
-        Multihop { exit, entry }
+        Multihop {
+            exit,
+            entry,
+            middle: Vec::new(),
+        }
+    }
+
+    /// Route traffic through `middle`, in order, between the entry and exit relay.
+    pub fn with_middle(mut self, middle: Vec<Relay>) -> Self {
+        assert!(middle
+            .iter()
+            .all(|relay| matches!(relay.endpoint_data, RelayEndpointData::Wireguard(_))));
+        self.middle = middle;
+        self
     }
 }
@@ -0,0 +1,74 @@
+//! Relays that were last connected to successfully, so that the same relays can be selected again
+//! on reconnect instead of new random ones. This is useful when a service rate-limits by exit IP.
+
+use mullvad_types::{
+    constraints::Constraint,
+    relay_constraints::{GeographicLocationConstraint, LocationConstraint},
+    relay_list::Relay,
+};
+
+/// Maximum number of location constraints to remember relays for.
+const MAX_STICKY_SELECTIONS: usize = 16;
+
+/// The location constraints that relays were selected for. Relays are only reused for the same
+/// constraints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StickyKey {
+    pub location: Constraint<LocationConstraint>,
+    /// The entry location, if multihop is used.
+    pub entry_location: Option<Constraint<LocationConstraint>>,
+}
+
+/// The relays that were last connected to successfully, as location constraints that match only
+/// those relays.
+#[derive(Debug, Clone)]
+pub struct StickySelection {
+    pub exit: GeographicLocationConstraint,
+    pub entry: Option<GeographicLocationConstraint>,
+}
+
+#[derive(Debug, Default)]
+pub struct StickyRelays {
+    /// Most recently used last.
+    selections: Vec<(StickyKey, StickySelection)>,
+}
+
+impl StickyRelays {
+    /// Remember that `exit` and `entry` were connected to successfully for `key`.
+    pub fn remember(&mut self, key: StickyKey, exit: &Relay, entry: Option<&Relay>) {
+        self.selections.retain(|(existing, _)| *existing != key);
+        if self.selections.len() >= MAX_STICKY_SELECTIONS {
+            self.selections.remove(0);
+        }
+        let selection = StickySelection {
+            exit: hostname_constraint(exit),
+            entry: entry.map(hostname_constraint),
+        };
+        self.selections.push((key, selection));
+    }
+
+    pub fn get(&self, key: &StickyKey) -> Option<&StickySelection> {
+        self.selections
+            .iter()
+            .find(|(existing, _)| existing == key)
+            .map(|(_, selection)| selection)
+    }
+
+    /// Forget every selection that includes the relay with the given hostname.
+    pub fn forget(&mut self, hostname: &str) {
+        self.selections.retain(|(_, selection)| {
+            let includes = |location: &GeographicLocationConstraint| {
+                location.get_hostname().is_some_and(|host| host == hostname)
+            };
+            !includes(&selection.exit) && !selection.entry.as_ref().is_some_and(includes)
+        });
+    }
+}
+
+fn hostname_constraint(relay: &Relay) -> GeographicLocationConstraint {
+    GeographicLocationConstraint::hostname(
+        relay.location.country_code.clone(),
+        relay.location.city_code.clone(),
+        relay.hostname.clone(),
+    )
+}
@@ -2,17 +2,18 @@
 
 use std::{
     collections::HashSet,
-    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     sync::LazyLock,
 };
 use talpid_types::net::{
     obfuscation::ObfuscatorConfig,
     wireguard::PublicKey,
-    Endpoint, IpVersion,
+    Endpoint, IpVersion, ObfuscationType,
     TransportProtocol::{Tcp, Udp},
     TunnelType,
 };
 
+use ipnetwork::IpNetwork;
 use mullvad_relay_selector::{
     query::{builder::RelayQueryBuilder, BridgeQuery, ObfuscationQuery, OpenVpnRelayQuery},
     Error, GetRelay, RelaySelector, SelectedObfuscator, SelectorConfig, WireguardConfig,
@@ -20,11 +21,15 @@ use mullvad_relay_selector::{
 };
 use mullvad_types::{
     constraints::Constraint,
+    custom_relay::{self, CustomRelay, CustomRelaySettings},
     endpoint::MullvadEndpoint,
+    excluded_locations::ExcludedLocations,
     location::Location,
     relay_constraints::{
-        BridgeConstraints, BridgeState, GeographicLocationConstraint, Ownership, Providers,
-        RelayConstraints, RelayOverride, RelaySettings, TransportPort,
+        AsnFilter, BridgeConstraints, BridgeState, CustomObfuscationBridge,
+        GeographicLocationConstraint, ObfuscationSettings, Ownership, Providers, RelayConstraints,
+        RelayOverride, RelaySettings, SelectedObfuscation, ShadowsocksCipher, ShadowsocksSettings,
+        TransportPort,
     },
     relay_list::{
         BridgeEndpointData, OpenVpnEndpoint, OpenVpnEndpointData, Relay, RelayEndpointData,
@@ -44,6 +49,7 @@ static DUMMY_LOCATION: LazyLock<Location> = LazyLock::new(|| Location {
 
 static RELAYS: LazyLock<RelayList> = LazyLock::new(|| RelayList {
     etag: None,
+    last_modified: None,
     countries: vec![RelayListCountry {
         name: "Sweden".to_string(),
         code: "se".to_string(),
@@ -63,6 +69,7 @@ static RELAYS: LazyLock<RelayList> = LazyLock::new(|| RelayList {
                     active: true,
                     owned: true,
                     provider: "provider0".to_string(),
+                    asn: None,
                     weight: 1,
                     endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
                         public_key: PublicKey::from_base64(
@@ -84,6 +91,7 @@ static RELAYS: LazyLock<RelayList> = LazyLock::new(|| RelayList {
                     active: true,
                     owned: false,
                     provider: "provider1".to_string(),
+                    asn: None,
                     weight: 1,
                     endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
                         public_key: PublicKey::from_base64(
@@ -105,6 +113,7 @@ static RELAYS: LazyLock<RelayList> = LazyLock::new(|| RelayList {
                     active: true,
                     owned: false,
                     provider: "provider2".to_string(),
+                    asn: None,
                     weight: 1,
                     endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
                         public_key: PublicKey::from_base64(
@@ -126,6 +135,7 @@ static RELAYS: LazyLock<RelayList> = LazyLock::new(|| RelayList {
                     active: true,
                     owned: true,
                     provider: "provider2".to_string(),
+                    asn: None,
                     weight: 1,
                     endpoint_data: RelayEndpointData::Openvpn,
                     location: DUMMY_LOCATION.clone(),
@@ -140,6 +150,7 @@ static RELAYS: LazyLock<RelayList> = LazyLock::new(|| RelayList {
                     active: true,
                     owned: true,
                     provider: "provider0".to_string(),
+                    asn: None,
                     weight: 1,
                     endpoint_data: RelayEndpointData::Openvpn,
                     location: DUMMY_LOCATION.clone(),
@@ -154,6 +165,7 @@ static RELAYS: LazyLock<RelayList> = LazyLock::new(|| RelayList {
                     active: true,
                     owned: true,
                     provider: "provider3".to_string(),
+                    asn: None,
                     weight: 1,
                     endpoint_data: RelayEndpointData::Bridge,
                     location: DUMMY_LOCATION.clone(),
@@ -234,6 +246,7 @@ static SHADOWSOCKS_RELAY: LazyLock<Relay> = LazyLock::new(|| Relay {
     active: true,
     owned: true,
     provider: "provider0".to_string(),
+    asn: None,
     weight: 1,
     endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
         public_key: PublicKey::from_base64("eaNHNoGO88LjV/wDBa7CUUwUzPq/fO2UwcGLy56hKy4=").unwrap(),
@@ -333,6 +346,8 @@ fn assert_wireguard_retry_order() {
             .udp2tcp()
             .ip_version(IpVersion::V6)
             .build(),
+        // 7
+        RelayQueryBuilder::wireguard().tls().build(),
     ];
 
     assert!(
@@ -423,9 +438,12 @@ fn test_wireguard_retry_order() {
                     .matches_eq(&endpoint.peer.endpoint.port()));
                 assert!(match &query.wireguard_constraints().obfuscation {
                     ObfuscationQuery::Auto => true,
-                    ObfuscationQuery::Off => obfuscator.is_none(),
-                    ObfuscationQuery::Udp2tcp(_) | ObfuscationQuery::Shadowsocks(_) =>
-                        obfuscator.is_some(),
+                    ObfuscationQuery::Off | ObfuscationQuery::PortHopping(_) =>
+                        obfuscator.is_none(),
+                    ObfuscationQuery::Udp2tcp(_)
+                    | ObfuscationQuery::Shadowsocks(_)
+                    | ObfuscationQuery::Tls
+                    | ObfuscationQuery::CustomBridge(_) => obfuscator.is_some(),
                 });
             }
             _ => unreachable!(),
@@ -544,6 +562,7 @@ fn test_wireguard_entry() {
     // Define a relay list containing exactly two Wireguard relays in Gothenburg.
     let relays = RelayList {
         etag: None,
+        last_modified: None,
         countries: vec![RelayListCountry {
             name: "Sweden".to_string(),
             code: "se".to_string(),
@@ -563,6 +582,7 @@ fn test_wireguard_entry() {
                         active: true,
                         owned: true,
                         provider: "provider0".to_string(),
+                        asn: None,
                         weight: 1,
                         endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
                             public_key: PublicKey::from_base64(
@@ -584,6 +604,7 @@ fn test_wireguard_entry() {
                         active: true,
                         owned: false,
                         provider: "provider1".to_string(),
+                        asn: None,
                         weight: 1,
                         endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
                             public_key: PublicKey::from_base64(
@@ -636,7 +657,7 @@ fn test_wireguard_entry() {
         let relay = relay_selector.get_relay_by_query(query).unwrap();
         match relay {
             GetRelay::Wireguard {
-                inner: WireguardConfig::Multihop { exit, entry },
+                inner: WireguardConfig::Multihop { exit, entry, .. },
                 ..
             } => {
                 assert_eq!(entry.hostname, specific_hostname);
@@ -663,7 +684,7 @@ fn test_wireguard_entry() {
         let relay = relay_selector.get_relay_by_query(query).unwrap();
         match relay {
             GetRelay::Wireguard {
-                inner: WireguardConfig::Multihop { exit, entry },
+                inner: WireguardConfig::Multihop { exit, entry, .. },
                 ..
             } => {
                 assert_eq!(exit.hostname, specific_hostname);
@@ -715,6 +736,92 @@ fn test_wireguard_entry_hostname_collision() {
         .is_ok())
 }
 
+/// Middle hops should not share an address with the entry or exit relay, or with each other. If
+/// there aren't enough such relays, the relay selector should fail.
+#[test]
+fn test_wireguard_middle_hops() {
+    let relay_selector = default_relay_selector();
+
+    let exit = GeographicLocationConstraint::hostname("se", "got", "se9-wireguard");
+    let entry = GeographicLocationConstraint::hostname("se", "got", "se10-wireguard");
+
+    let query = RelayQueryBuilder::wireguard()
+        .location(exit.clone())
+        .multihop()
+        .entry(entry.clone())
+        .middle_hops(1)
+        .build();
+    let relay = relay_selector.get_relay_by_query(query).unwrap();
+    match relay {
+        GetRelay::Wireguard {
+            inner: WireguardConfig::Multihop { middle, .. },
+            endpoint,
+            ..
+        } => {
+            assert_eq!(middle.len(), 1);
+            // se11-wireguard shares its address with the entry relay, so only one relay remains
+            assert_eq!(middle[0].hostname, SHADOWSOCKS_RELAY.hostname);
+            assert_eq!(endpoint.middle_peers.len(), 1);
+            // The entry peer should only route to the middle relay
+            assert_eq!(
+                endpoint.peer.allowed_ips,
+                vec![ipnetwork::IpNetwork::from(IpAddr::from(middle[0].ipv4_addr_in))]
+            );
+        }
+        wrong_relay => panic!(
+            "Relay selector should have picked a Wireguard multihop relay, instead chose {wrong_relay:?}"
+        ),
+    }
+
+    // There is only one eligible middle relay, so two middle hops cannot be satisfied
+    let query = RelayQueryBuilder::wireguard()
+        .location(exit)
+        .multihop()
+        .entry(entry)
+        .middle_hops(2)
+        .build();
+    assert!(relay_selector.get_relay_by_query(query).is_err());
+}
+
+/// With multihop diversity enabled, the entry and exit relay should never be run by the same
+/// provider, and never both be owned by Mullvad.
+#[test]
+fn test_wireguard_multihop_diversity() {
+    let relay_selector = default_relay_selector();
+    // se9-wireguard is owned, and run by the same provider as the Shadowsocks relay
+    let exit = GeographicLocationConstraint::hostname("se", "got", "se9-wireguard");
+
+    for _ in 0..100 {
+        let query = RelayQueryBuilder::wireguard()
+            .location(exit.clone())
+            .multihop()
+            .entry(GeographicLocationConstraint::city("se", "got"))
+            .multihop_diversity()
+            .build();
+        match relay_selector.get_relay_by_query(query).unwrap() {
+            GetRelay::Wireguard {
+                inner: WireguardConfig::Multihop { exit, entry, .. },
+                ..
+            } => {
+                assert_ne!(exit.provider, entry.provider);
+                assert!(!entry.owned);
+            }
+            wrong_relay => panic!(
+                "Relay selector should have picked a Wireguard multihop relay, instead chose {wrong_relay:?}"
+            ),
+        }
+    }
+
+    // Both relays are owned by Mullvad
+    let query = RelayQueryBuilder::wireguard()
+        .location(exit)
+        .multihop()
+        .entry(SHADOWSOCKS_RELAY_LOCATION.clone())
+        .multihop_diversity()
+        .build();
+    assert!(relay_selector.get_relay_by_query(query).is_err());
+}
+
 /// Test that the relay selector:
 /// * returns an OpenVPN relay given a constraint of a valid transport protocol + port combo
 /// * does *not* return an OpenVPN relay given a constraint of an *invalid* transport protocol +
@@ -857,7 +964,7 @@ fn test_selecting_wireguard_over_shadowsocks_extra_ips() {
     let relay = relay_selector.get_relay_by_query(query).unwrap();
     match relay {
         GetRelay::Wireguard {
-            obfuscator: Some(SelectedObfuscator { config: ObfuscatorConfig::Shadowsocks { endpoint }, .. }),
+            obfuscator: Some(SelectedObfuscator { config: ObfuscatorConfig::Shadowsocks { endpoint, .. }, .. }),
             inner: WireguardConfig::Singlehop { exit },
             ..
         } => {
@@ -871,6 +978,143 @@ fn test_selecting_wireguard_over_shadowsocks_extra_ips() {
     }
 }
 
+/// Test that the Shadowsocks cipher in the user's settings is passed on to the obfuscator
+#[test]
+fn test_selecting_wireguard_over_shadowsocks_with_cipher() {
+    let config = SelectorConfig {
+        obfuscation_settings: ObfuscationSettings {
+            selected_obfuscation: SelectedObfuscation::Shadowsocks,
+            shadowsocks: ShadowsocksSettings {
+                port: Constraint::Any,
+                cipher: Constraint::Only(ShadowsocksCipher::Chacha20IetfPoly1305),
+            },
+            ..ObfuscationSettings::default()
+        },
+        ..SelectorConfig::default()
+    };
+    let relay_selector = RelaySelector::from_list(config, RELAYS.clone());
+
+    let relay = relay_selector
+        .get_relay(0, talpid_types::net::IpAvailability::Ipv4)
+        .unwrap();
+    match relay {
+        GetRelay::Wireguard {
+            obfuscator:
+                Some(SelectedObfuscator {
+                    config: ObfuscatorConfig::Shadowsocks { cipher, .. },
+                    ..
+                }),
+            ..
+        } => assert_eq!(cipher, "chacha20-ietf-poly1305"),
+        wrong_relay => panic!(
+            "Relay selector should have picked a Wireguard relay with Shadowsocks, instead chose {wrong_relay:?}"
+        ),
+    }
+}
+
+/// Test that a custom Shadowsocks bridge is used to reach the selected relay
+#[test]
+fn test_selecting_wireguard_over_custom_shadowsocks_bridge() {
+    let bridge_endpoint = SocketAddr::from(([192, 0, 2, 1], 8388));
+    let config = SelectorConfig {
+        obfuscation_settings: ObfuscationSettings {
+            selected_obfuscation: SelectedObfuscation::CustomBridge,
+            custom_bridge: Some(CustomObfuscationBridge::Shadowsocks {
+                endpoint: bridge_endpoint,
+                password: "secret".to_owned(),
+                cipher: ShadowsocksCipher::Aes128Gcm,
+            }),
+            ..ObfuscationSettings::default()
+        },
+        ..SelectorConfig::default()
+    };
+    let relay_selector = RelaySelector::from_list(config, RELAYS.clone());
+
+    for retry_attempt in 0..WIREGUARD_RETRY_ORDER.len() {
+        let relay = relay_selector
+            .get_relay(retry_attempt, talpid_types::net::IpAvailability::Ipv4)
+            .unwrap();
+        match relay {
+            GetRelay::Wireguard {
+                endpoint,
+                obfuscator:
+                    Some(SelectedObfuscator {
+                        config:
+                            ObfuscatorConfig::CustomShadowsocks {
+                                endpoint: obfuscator_endpoint,
+                                peer,
+                                password,
+                                cipher,
+                            },
+                        ..
+                    }),
+                ..
+            } => {
+                assert_eq!(obfuscator_endpoint, bridge_endpoint);
+                assert_eq!(peer, endpoint.peer.endpoint);
+                assert_eq!(password, "secret");
+                assert_eq!(cipher, "aes-128-gcm");
+            }
+            wrong_relay => panic!(
+                "Relay selector should have used the custom bridge, instead chose {wrong_relay:?}"
+            ),
+        }
+    }
+}
+
+/// Test that retry attempts using the preferred obfuscation method are tried first, unless
+/// obfuscation is not set to auto.
+#[test]
+fn test_preferred_obfuscation() {
+    let relay_selector = RelaySelector::from_list(SelectorConfig::default(), RELAYS.clone());
+    let relay = relay_selector
+        .get_relay_with_preferred_obfuscation(
+            0,
+            talpid_types::net::IpAvailability::Ipv4,
+            ObfuscationType::Tls,
+        )
+        .unwrap();
+    assert!(
+        matches!(
+            relay,
+            GetRelay::Wireguard {
+                obfuscator: Some(SelectedObfuscator {
+                    config: ObfuscatorConfig::Tls { .. },
+                    ..
+                }),
+                ..
+            }
+        ),
+        "expected TLS obfuscation, got {relay:?}"
+    );
+
+    let config = SelectorConfig {
+        obfuscation_settings: ObfuscationSettings {
+            selected_obfuscation: SelectedObfuscation::Off,
+            ..ObfuscationSettings::default()
+        },
+        ..SelectorConfig::default()
+    };
+    let relay_selector = RelaySelector::from_list(config, RELAYS.clone());
+    let relay = relay_selector
+        .get_relay_with_preferred_obfuscation(
+            0,
+            talpid_types::net::IpAvailability::Ipv4,
+            ObfuscationType::Tls,
+        )
+        .unwrap();
+    assert!(
+        matches!(
+            relay,
+            GetRelay::Wireguard {
+                obfuscator: None,
+                ..
+            }
+        ),
+        "expected no obfuscation, got {relay:?}"
+    );
+}
+
 /// Ignore extra IPv4 addresses when overrides are set
 #[test]
 fn test_selecting_wireguard_ignore_extra_ips_override_v4() {
@@ -900,7 +1144,7 @@ fn test_selecting_wireguard_ignore_extra_ips_override_v4() {
     let relay = relay_selector.get_relay_by_query(query_v4).unwrap();
     match relay {
         GetRelay::Wireguard {
-            obfuscator: Some(SelectedObfuscator { config: ObfuscatorConfig::Shadowsocks { endpoint }, .. }),
+            obfuscator: Some(SelectedObfuscator { config: ObfuscatorConfig::Shadowsocks { endpoint, .. }, .. }),
             inner: WireguardConfig::Singlehop { exit },
             ..
         } => {
@@ -943,7 +1187,7 @@ fn test_selecting_wireguard_ignore_extra_ips_override_v6() {
     let relay = relay_selector.get_relay_by_query(query_v6).unwrap();
     match relay {
         GetRelay::Wireguard {
-            obfuscator: Some(SelectedObfuscator { config: ObfuscatorConfig::Shadowsocks { endpoint }, .. }),
+            obfuscator: Some(SelectedObfuscator { config: ObfuscatorConfig::Shadowsocks { endpoint, .. }, .. }),
             inner: WireguardConfig::Singlehop { exit },
             ..
         } => {
@@ -984,6 +1228,60 @@ fn test_selecting_wireguard_endpoint_with_udp2tcp_obfuscation() {
     }
 }
 
+/// Construct a query for a Wireguard configuration where TLS obfuscation is selected. Assert that
+/// the obfuscator connects to port 443 on the selected relay.
+#[test]
+fn test_selecting_wireguard_endpoint_with_tls_obfuscation() {
+    let relay_selector = default_relay_selector();
+    let query = RelayQueryBuilder::wireguard().tls().build();
+
+    let relay = relay_selector.get_relay_by_query(query).unwrap();
+    match relay {
+        GetRelay::Wireguard {
+            endpoint,
+            obfuscator:
+                Some(SelectedObfuscator {
+                    config: ObfuscatorConfig::Tls { endpoint: tls_endpoint },
+                    ..
+                }),
+            ..
+        } => {
+            assert_eq!(tls_endpoint.ip(), endpoint.peer.endpoint.ip());
+            assert_eq!(tls_endpoint.port(), 443);
+        }
+        wrong_relay => panic!(
+            "Relay selector should have picked a Wireguard relay with TLS obfuscation, instead chose {wrong_relay:?}"
+        ),
+    }
+}
+
+/// Construct a query for a Wireguard configuration where port hopping is selected. Assert that
+/// no obfuscator is returned, and that the tunnel may hop between all WireGuard ports.
+#[test]
+fn test_selecting_wireguard_endpoint_with_port_hopping() {
+    let relay_selector = default_relay_selector();
+    let query = RelayQueryBuilder::wireguard().port_hopping().build();
+
+    let relay = relay_selector.get_relay_by_query(query).unwrap();
+    match relay {
+        GetRelay::Wireguard {
+            obfuscator,
+            port_hopping: Some(port_hopping),
+            ..
+        } => {
+            assert!(obfuscator.is_none());
+            assert_eq!(
+                port_hopping.port_ranges,
+                RELAYS.wireguard.port_ranges,
+                "Port hopping should use the WireGuard port ranges of the relay list"
+            );
+        }
+        wrong_relay => panic!(
+            "Relay selector should have picked a Wireguard relay with port hopping, instead chose {wrong_relay:?}"
+        ),
+    }
+}
+
 /// Construct a query for a Wireguard configuration where obfuscation is set to "Auto" and
 /// multihop is explicitly turned off. Assert that the relay selector does *not* return an
 /// obfuscator config.
@@ -1137,6 +1435,34 @@ fn test_providers() {
     }
 }
 
+/// Verify that the ASN filter is applied when selecting relays.
+#[test]
+fn test_asns() {
+    let mut relay_list = RELAYS.clone();
+    for relay in &mut relay_list.countries[0].cities[0].relays {
+        relay.asn = match relay.hostname.as_str() {
+            "se9-wireguard" => Some(64496),
+            "se10-wireguard" => Some(64497),
+            _ => None,
+        };
+    }
+    let relay_selector = RelaySelector::from_list(SelectorConfig::default(), relay_list);
+
+    for _attempt in 0..100 {
+        let query = RelayQueryBuilder::wireguard()
+            .asns(AsnFilter::Only([64496].into()))
+            .build();
+        let relay = unwrap_relay(relay_selector.get_relay_by_query(query).unwrap());
+        assert_eq!(relay.hostname, "se9-wireguard");
+
+        let query = RelayQueryBuilder::wireguard()
+            .asns(AsnFilter::Except([64496].into()))
+            .build();
+        let relay = unwrap_relay(relay_selector.get_relay_by_query(query).unwrap());
+        assert_ne!(relay.hostname, "se9-wireguard");
+    }
+}
+
 /// Verify that bridges are automatically used when bridge mode is set to automatic.
 #[test]
 fn test_openvpn_auto_bridge() {
@@ -1190,6 +1516,7 @@ fn test_openvpn_auto_bridge() {
 fn test_include_in_country() {
     let mut relay_list = RelayList {
         etag: None,
+        last_modified: None,
         countries: vec![RelayListCountry {
             name: "Sweden".to_string(),
             code: "se".to_string(),
@@ -1209,6 +1536,7 @@ fn test_include_in_country() {
                         active: true,
                         owned: true,
                         provider: "31173".to_string(),
+                        asn: None,
                         weight: 1,
                         endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
                             public_key: PublicKey::from_base64(
@@ -1230,6 +1558,7 @@ fn test_include_in_country() {
                         active: true,
                         owned: false,
                         provider: "31173".to_string(),
+                        asn: None,
                         weight: 1,
                         endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
                             public_key: PublicKey::from_base64(
@@ -1440,7 +1769,7 @@ fn test_daita_smart_routing_overrides_multihop() {
             .expect("Expected to find a relay with daita_use_multihop_if_necessary");
         match relay {
                 GetRelay::Wireguard {
-                    inner: WireguardConfig::Multihop { entry, exit: _ },
+                    inner: WireguardConfig::Multihop { entry, exit: _, .. },
                     ..
                 } => {
                     assert!(supports_daita(&entry), "entry relay must support DAITA");
@@ -1507,7 +1836,7 @@ fn test_daita() {
         .expect("Expected to find a relay with daita_use_multihop_if_necessary");
     match relay {
         GetRelay::Wireguard {
-            inner: WireguardConfig::Multihop { exit, entry },
+            inner: WireguardConfig::Multihop { exit, entry, .. },
             ..
         } => {
             assert!(supports_daita(&entry), "entry relay must support DAITA");
@@ -1564,7 +1893,7 @@ fn test_daita() {
     let relay = relay_selector.get_relay_by_query(query).unwrap();
     match relay {
         GetRelay::Wireguard {
-            inner: WireguardConfig::Multihop { exit: _, entry },
+            inner: WireguardConfig::Multihop { exit: _, entry, .. },
             ..
         } => {
             assert!(supports_daita(&entry), "entry relay must support DAITA");
@@ -1584,7 +1913,7 @@ fn test_daita() {
     let relay = relay_selector.get_relay_by_query(query).unwrap();
     match relay {
         GetRelay::Wireguard {
-            inner: WireguardConfig::Multihop { exit, entry: _ },
+            inner: WireguardConfig::Multihop { exit, entry: _, .. },
             ..
         } => {
             assert!(
@@ -1686,3 +2015,132 @@ fn test_runtime_ipv4_unavailable() {
         ),
     }
 }
+
+/// Check that custom relays are only used when selected, and that their own endpoint is connected
+/// to.
+#[test]
+fn test_custom_relay() {
+    const CUSTOM_ENDPOINT: SocketAddr =
+        SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)), 1234);
+
+    let custom_relay = CustomRelay {
+        name: "my-vps".to_string(),
+        public_key: PublicKey::from_base64("BLNHNoGO88LjV/wDBa7CUUwUzPq/fO2UwcGLy56hKy4=").unwrap(),
+        endpoint: CUSTOM_ENDPOINT,
+        allowed_ips: vec![],
+        obfuscation: None,
+    };
+    let config = SelectorConfig {
+        custom_relays: CustomRelaySettings::from(vec![custom_relay]),
+        ..SelectorConfig::default()
+    };
+    let relay_selector = RelaySelector::from_list(config, RELAYS.clone());
+
+    for _ in 0..100 {
+        let query = RelayQueryBuilder::wireguard().build();
+        let relay = relay_selector.get_relay_by_query(query).unwrap();
+        let GetRelay::Wireguard {
+            inner: WireguardConfig::Singlehop { exit },
+            ..
+        } = relay
+        else {
+            panic!("Relay selector should have picked a singlehop relay, instead chose {relay:?}");
+        };
+        assert!(!exit.is_custom());
+    }
+
+    let query = RelayQueryBuilder::wireguard()
+        .multihop()
+        .entry(GeographicLocationConstraint::hostname(
+            custom_relay::COUNTRY_CODE,
+            custom_relay::CITY_CODE,
+            "my-vps",
+        ))
+        .build();
+    let relay = relay_selector.get_relay_by_query(query).unwrap();
+    let GetRelay::Wireguard {
+        endpoint,
+        inner: WireguardConfig::Multihop { entry, exit, .. },
+        ..
+    } = relay
+    else {
+        panic!("Relay selector should have picked a multihop relay, instead chose {relay:?}");
+    };
+    assert_eq!(entry.hostname, "my-vps");
+    assert_eq!(endpoint.peer.endpoint, CUSTOM_ENDPOINT);
+    assert_eq!(
+        endpoint.peer.allowed_ips,
+        vec![IpNetwork::from(IpAddr::from(exit.ipv4_addr_in))]
+    );
+}
+
+/// Check that relays in excluded locations are never selected.
+#[test]
+fn test_excluded_locations() {
+    let excluded = GeographicLocationConstraint::hostname("se", "got", "se9-wireguard");
+    let config = SelectorConfig {
+        excluded_locations: ExcludedLocations::from(vec![excluded]),
+        ..SelectorConfig::default()
+    };
+    let relay_selector = RelaySelector::from_list(config, RELAYS.clone());
+    for _ in 0..100 {
+        let query = RelayQueryBuilder::wireguard().build();
+        let relay = relay_selector.get_relay_by_query(query).unwrap();
+        let GetRelay::Wireguard {
+            inner: WireguardConfig::Singlehop { exit },
+            ..
+        } = relay
+        else {
+            panic!("Relay selector should have picked a singlehop relay, instead chose {relay:?}");
+        };
+        assert_ne!(exit.hostname, "se9-wireguard");
+    }
+
+    let excluded = GeographicLocationConstraint::country("se");
+    let config = SelectorConfig {
+        excluded_locations: ExcludedLocations::from(vec![excluded]),
+        ..SelectorConfig::default()
+    };
+    let relay_selector = RelaySelector::from_list(config, RELAYS.clone());
+    let query = RelayQueryBuilder::wireguard().build();
+    relay_selector
+        .get_relay_by_query(query)
+        .expect_err("Expected no relay, since every relay is excluded");
+}
+
+/// Verify that the relays that were last connected to are selected again when sticky relays are
+/// enabled, until connecting to them fails.
+#[test]
+fn test_sticky_relays() {
+    let config = SelectorConfig {
+        sticky_relays: true,
+        ..SelectorConfig::default()
+    };
+    let relay_selector = RelaySelector::from_list(config, RELAYS.clone());
+    let get_exit = || {
+        let relay = relay_selector
+            .get_relay(0, talpid_types::net::IpAvailability::Ipv4)
+            .unwrap();
+        match relay {
+            GetRelay::Wireguard {
+                inner: WireguardConfig::Singlehop { exit },
+                ..
+            } => exit,
+            wrong_relay => panic!(
+                "Relay selector should have picked a singlehop relay, instead chose {wrong_relay:?}"
+            ),
+        }
+    };
+
+    let exit = get_exit();
+    relay_selector.remember_connected_relays(&exit, None);
+    for _ in 0..100 {
+        assert_eq!(get_exit().hostname, exit.hostname);
+    }
+
+    relay_selector.record_failure(&exit.hostname);
+    assert!(
+        (0..100).any(|_| get_exit().hostname != exit.hostname),
+        "Relay selector should stop reusing a relay that failed"
+    );
+}
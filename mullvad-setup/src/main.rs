@@ -136,9 +136,25 @@ async fn reset_firewall() -> Result<(), Error> {
         return Err(Error::DaemonIsRunning);
     }
 
+    // Rules in a user-managed anchor are not removed along with the default anchor
+    #[cfg(target_os = "macos")]
+    let pf_anchor = {
+        let settings_dir = mullvad_paths::settings_dir().map_err(Error::SettingsPathError)?;
+        mullvad_daemon::settings::SettingsPersister::load(&settings_dir)
+            .await
+            .pf_anchor
+            .clone()
+    };
+
     Firewall::new(
         #[cfg(target_os = "linux")]
         mullvad_types::TUNNEL_FWMARK,
+        #[cfg(target_os = "linux")]
+        firewall::NftablesOptions::default(),
+        #[cfg(windows)]
+        firewall::DEFAULT_SUBLAYER_WEIGHT,
+        #[cfg(target_os = "macos")]
+        pf_anchor,
     )
     .map_err(Error::FirewallError)?
     .reset_policy()
@@ -1,5 +1,10 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use talpid_types::net::proxy::{CustomProxy, Shadowsocks, Socks5Local, Socks5Remote};
+use std::time::Duration;
+use talpid_types::net::proxy::{CustomProxy, HttpProxy, Shadowsocks, Socks5Local, Socks5Remote};
+
+/// The SOCKS port that a Tor client listens on by default.
+pub const DEFAULT_TOR_SOCKS_PORT: u16 = 9050;
 
 /// Settings for API access methods.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -10,6 +15,10 @@ pub struct Settings {
     mullvad_bridges: AccessMethodSetting,
     #[serde(default = "Settings::create_encrypted_dns_proxy")]
     encrypted_dns_proxy: AccessMethodSetting,
+    #[serde(default = "Settings::create_direct_http3")]
+    direct_http3: AccessMethodSetting,
+    #[serde(default = "Settings::create_tor")]
+    tor: AccessMethodSetting,
     /// Custom API access methods.
     custom: Vec<AccessMethodSetting>,
 }
@@ -19,12 +28,16 @@ impl Settings {
         direct: AccessMethodSetting,
         mullvad_bridges: AccessMethodSetting,
         encrypted_dns_proxy: AccessMethodSetting,
+        direct_http3: AccessMethodSetting,
+        tor: AccessMethodSetting,
         custom: Vec<AccessMethodSetting>,
     ) -> Settings {
         Settings {
             direct,
             mullvad_bridges,
             encrypted_dns_proxy,
+            direct_http3,
+            tor,
             custom,
         }
     }
@@ -100,6 +113,8 @@ impl Settings {
         once(&self.direct)
             .chain(once(&self.mullvad_bridges))
             .chain(once(&self.encrypted_dns_proxy))
+            .chain(once(&self.direct_http3))
+            .chain(once(&self.tor))
             .chain(&self.custom)
     }
 
@@ -109,6 +124,8 @@ impl Settings {
         once(&mut self.direct)
             .chain(once(&mut self.mullvad_bridges))
             .chain(once(&mut self.encrypted_dns_proxy))
+            .chain(once(&mut self.direct_http3))
+            .chain(once(&mut self.tor))
             .chain(&mut self.custom)
     }
 
@@ -135,6 +152,14 @@ impl Settings {
         &self.encrypted_dns_proxy
     }
 
+    pub fn direct_http3(&self) -> &AccessMethodSetting {
+        &self.direct_http3
+    }
+
+    pub fn tor(&self) -> &AccessMethodSetting {
+        &self.tor
+    }
+
     fn create_direct() -> AccessMethodSetting {
         let method = BuiltInAccessMethod::Direct;
         AccessMethodSetting::new(method.canonical_name(), true, AccessMethod::from(method))
@@ -149,6 +174,20 @@ impl Settings {
         let method = BuiltInAccessMethod::EncryptedDnsProxy;
         AccessMethodSetting::new(method.canonical_name(), true, AccessMethod::from(method))
     }
+
+    /// HTTP/3 is opt-in, since it is only useful on networks that block TLS over TCP.
+    fn create_direct_http3() -> AccessMethodSetting {
+        let method = BuiltInAccessMethod::DirectHttp3;
+        AccessMethodSetting::new(method.canonical_name(), false, AccessMethod::from(method))
+    }
+
+    /// Tor is opt-in, since it requires a Tor client to be running on this machine.
+    fn create_tor() -> AccessMethodSetting {
+        let method = BuiltInAccessMethod::Tor {
+            socks_port: DEFAULT_TOR_SOCKS_PORT,
+        };
+        AccessMethodSetting::new(method.canonical_name(), false, AccessMethod::from(method))
+    }
 }
 
 impl Default for Settings {
@@ -157,6 +196,8 @@ impl Default for Settings {
             direct: Settings::create_direct(),
             mullvad_bridges: Settings::create_mullvad_bridges(),
             encrypted_dns_proxy: Settings::create_encrypted_dns_proxy(),
+            direct_http3: Settings::create_direct_http3(),
+            tor: Settings::create_tor(),
             custom: vec![],
         }
     }
@@ -181,7 +222,7 @@ pub struct AccessMethodSetting {
     pub access_method: AccessMethod,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Id(uuid::Uuid);
 
 impl Id {
@@ -288,6 +329,12 @@ pub enum BuiltInAccessMethod {
     Direct,
     Bridge,
     EncryptedDnsProxy,
+    /// Connect directly to the API using HTTP/3 over QUIC.
+    DirectHttp3,
+    /// Connect to the API through the SOCKS port of a Tor client running on localhost.
+    Tor {
+        socks_port: u16,
+    },
 }
 
 impl AccessMethod {
@@ -305,6 +352,8 @@ impl BuiltInAccessMethod {
             BuiltInAccessMethod::Direct => "Direct".to_string(),
             BuiltInAccessMethod::Bridge => "Mullvad Bridges".to_string(),
             BuiltInAccessMethod::EncryptedDnsProxy => "Encrypted DNS proxy".to_string(),
+            BuiltInAccessMethod::DirectHttp3 => "Direct (HTTP/3)".to_string(),
+            BuiltInAccessMethod::Tor { .. } => "Tor".to_string(),
         }
     }
 }
@@ -344,3 +393,97 @@ impl From<Shadowsocks> for AccessMethod {
         CustomProxy::Shadowsocks(value).into()
     }
 }
+
+impl From<HttpProxy> for AccessMethod {
+    fn from(value: HttpProxy) -> Self {
+        CustomProxy::Http(value).into()
+    }
+}
+
+/// How reliable and fast an access method has been. Recent requests weigh more than old ones.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct AccessMethodStats {
+    /// Weighted share of requests that succeeded, between 0 and 1.
+    pub success_rate: f64,
+    /// Weighted latency of successful requests.
+    pub latency: Option<Duration>,
+    pub successes: u64,
+    pub failures: u64,
+    /// When a request was last sent using the access method.
+    pub last_used: DateTime<Utc>,
+}
+
+impl AccessMethodStats {
+    /// Score of access methods without any history.
+    pub const NEUTRAL_SCORE: f64 = 0.5;
+    /// How much each request affects the success rate and latency.
+    const WEIGHT: f64 = 0.25;
+    /// Time after which the history of an access method only counts half as much.
+    const HALF_LIFE: Duration = Duration::from_secs(24 * 60 * 60);
+    /// Latency at which the score of an access method is halved.
+    const LATENCY_SCALE: Duration = Duration::from_secs(10);
+
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            success_rate: Self::NEUTRAL_SCORE,
+            latency: None,
+            successes: 0,
+            failures: 0,
+            last_used: now,
+        }
+    }
+
+    /// Record a successful request that took `latency` to complete.
+    pub fn record_success(&mut self, latency: Duration, now: DateTime<Utc>) {
+        self.success_rate += Self::WEIGHT * (1.0 - self.success_rate);
+        self.latency = Some(match self.latency {
+            Some(average) => average.mul_f64(1.0 - Self::WEIGHT) + latency.mul_f64(Self::WEIGHT),
+            None => latency,
+        });
+        self.successes += 1;
+        self.last_used = now;
+    }
+
+    /// Record a request that failed because the API could not be reached.
+    pub fn record_failure(&mut self, now: DateTime<Utc>) {
+        self.success_rate -= Self::WEIGHT * self.success_rate;
+        self.failures += 1;
+        self.last_used = now;
+    }
+
+    /// Returns a score between 0 and 1 describing how likely the access method is to work well.
+    /// The score approaches [`Self::NEUTRAL_SCORE`] as the history grows old.
+    pub fn score(&self, now: DateTime<Utc>) -> f64 {
+        let latency_factor = match self.latency {
+            Some(latency) => 1.0 / (1.0 + latency.div_duration_f64(Self::LATENCY_SCALE)),
+            None => 1.0,
+        };
+        let age = (now - self.last_used).to_std().unwrap_or_default();
+        let relevance = 0.5f64.powf(age.div_duration_f64(Self::HALF_LIFE));
+        Self::NEUTRAL_SCORE + (self.success_rate * latency_factor - Self::NEUTRAL_SCORE) * relevance
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_stats_score() {
+        let now = Utc::now();
+        let mut working = AccessMethodStats::new(now);
+        working.record_success(Duration::from_millis(100), now);
+        let mut failing = AccessMethodStats::new(now);
+        failing.record_failure(now);
+        assert!(working.score(now) > AccessMethodStats::NEUTRAL_SCORE);
+        assert!(failing.score(now) < AccessMethodStats::NEUTRAL_SCORE);
+
+        let mut slow = AccessMethodStats::new(now);
+        slow.record_success(Duration::from_secs(5), now);
+        assert!(working.score(now) > slow.score(now));
+
+        // Old failures are forgiven
+        let much_later = now + chrono::Duration::days(30);
+        assert!((failing.score(much_later) - AccessMethodStats::NEUTRAL_SCORE).abs() < 0.001);
+    }
+}
@@ -29,6 +29,14 @@ impl AccountData {
     }
 }
 
+/// Emitted when the account expiry is fetched or changes.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AccountExpiryEvent {
+    pub expiry: DateTime<Utc>,
+    /// Whether the expiry changed because a payment was made or a voucher was redeemed.
+    pub payment_received: bool,
+}
+
 /// Data structure that's returned from successful invocation of the mullvad API's
 /// `/v1/submit-voucher` RPC.
 #[derive(Deserialize, Serialize, Debug)]
@@ -0,0 +1,87 @@
+//! User-defined incoming connections that are allowed in every state, so that services can be
+//! hosted behind the firewall.
+
+use std::collections::HashSet;
+pub use talpid_types::net::AllowedIncoming;
+
+/// The firewall on Windows can hold at most this many allowed incoming connections.
+pub const MAX_ALLOWED_INCOMING: usize = 16;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("At most {MAX_ALLOWED_INCOMING} incoming connection rules may be added")]
+    TooMany,
+    #[error("Rule for {0} has an invalid port")]
+    InvalidPort(AllowedIncoming),
+    #[error("Rule for {0} has an empty interface name")]
+    EmptyInterface(AllowedIncoming),
+    #[error("Rule for {0} was added more than once")]
+    Duplicate(AllowedIncoming),
+}
+
+/// Check that `allowed_incoming` can be applied to the firewall.
+pub fn validate(allowed_incoming: &[AllowedIncoming]) -> Result<(), Error> {
+    if allowed_incoming.len() > MAX_ALLOWED_INCOMING {
+        return Err(Error::TooMany);
+    }
+    let mut seen = HashSet::new();
+    for allowed in allowed_incoming {
+        if allowed.port == 0 {
+            return Err(Error::InvalidPort(allowed.clone()));
+        }
+        if allowed
+            .interface
+            .as_ref()
+            .is_some_and(|iface| iface.is_empty())
+        {
+            return Err(Error::EmptyInterface(allowed.clone()));
+        }
+        if !seen.insert(allowed) {
+            return Err(Error::Duplicate(allowed.clone()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use talpid_types::net::TransportProtocol;
+
+    fn allowed(protocol: TransportProtocol, port: u16, interface: Option<&str>) -> AllowedIncoming {
+        AllowedIncoming {
+            protocol,
+            port,
+            interface: interface.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let ssh = allowed(TransportProtocol::Tcp, 22, Some("eth0"));
+        let game = allowed(TransportProtocol::Udp, 27015, None);
+        assert_eq!(validate(&[ssh.clone(), game.clone()]), Ok(()));
+
+        assert_eq!(
+            validate(&[ssh.clone(), ssh.clone()]),
+            Err(Error::Duplicate(ssh))
+        );
+
+        let zero_port = allowed(TransportProtocol::Tcp, 0, None);
+        assert_eq!(
+            validate(&[zero_port.clone()]),
+            Err(Error::InvalidPort(zero_port))
+        );
+
+        let empty_interface = allowed(TransportProtocol::Tcp, 80, Some(""));
+        assert_eq!(
+            validate(&[empty_interface.clone()]),
+            Err(Error::EmptyInterface(empty_interface))
+        );
+
+        let too_many: Vec<_> = (1..=MAX_ALLOWED_INCOMING as u16 + 1)
+            .map(|port| allowed(TransportProtocol::Tcp, port, None))
+            .collect();
+        assert_eq!(validate(&too_many), Err(Error::TooMany));
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Software that is known to interfere with the tunnel or the firewall.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ConflictingSoftware {
+    pub kind: ConflictKind,
+    /// Human-readable name of the software.
+    pub name: String,
+    /// Identifies the detected component, such as a bundle identifier or a pf anchor.
+    pub identifier: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictKind {
+    /// A VPN or network filter implemented as a system extension.
+    NetworkExtension,
+    /// A firewall that manages packet filter (pf) rules.
+    PfFirewall,
+}
+
+impl fmt::Display for ConflictingSoftware {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self.kind {
+            ConflictKind::NetworkExtension => "network extension",
+            ConflictKind::PfFirewall => "firewall",
+        };
+        write!(f, "{} ({kind}: {})", self.name, self.identifier)
+    }
+}
@@ -130,14 +130,17 @@ macro_rules! impl_intersection_partialeq {
     };
 }
 
+impl_intersection_partialeq!(u8);
 impl_intersection_partialeq!(u16);
 impl_intersection_partialeq!(bool);
 
 // NOTE: this implementation does not do what you may expect of an intersection
 impl_intersection_partialeq!(relay_constraints::Providers);
+impl_intersection_partialeq!(relay_constraints::AsnFilter);
 // NOTE: should take actual intersection
 impl_intersection_partialeq!(relay_constraints::LocationConstraint);
 impl_intersection_partialeq!(relay_constraints::Ownership);
+impl_intersection_partialeq!(relay_constraints::ShadowsocksCipher);
 // NOTE: it contains an inner constraint
 impl_intersection_partialeq!(talpid_types::net::TransportProtocol);
 impl_intersection_partialeq!(talpid_types::net::TunnelType);
@@ -0,0 +1,158 @@
+//! WireGuard relays run by the user, e.g. on a VPS that is used as the entry relay of a multihop
+//! circuit. They are listed among the relays in the country [`COUNTRY_CODE`], and selected using
+//! the location constraints like any other relay.
+
+use crate::{
+    location::Location,
+    relay_constraints::CustomObfuscationBridge,
+    relay_list::{
+        Relay, RelayEndpointData, RelayList, RelayListCity, RelayListCountry,
+        WireguardRelayEndpointData,
+    },
+};
+use ipnetwork::IpNetwork;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, SocketAddr};
+use talpid_types::net::wireguard::PublicKey;
+
+/// Country code of custom relays in the relay list.
+pub const COUNTRY_CODE: &str = "custom";
+const COUNTRY_NAME: &str = "Custom relays";
+/// City code of custom relays in the relay list.
+pub const CITY_CODE: &str = "custom";
+const CITY_NAME: &str = "Custom relays";
+/// Provider of custom relays in the relay list.
+pub const PROVIDER: &str = "custom";
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Invalid custom relay name")]
+    InvalidName,
+    #[error("Custom relay with name already exists")]
+    DuplicateName,
+    #[error("Custom relay not found")]
+    NotFound,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct CustomRelay {
+    /// Used as the hostname of the relay. Must be unique among all relays.
+    pub name: String,
+    pub public_key: PublicKey,
+    pub endpoint: SocketAddr,
+    /// IPs that the relay may route traffic to. If empty, this is the next hop of the circuit, or
+    /// the whole internet if the relay is the exit relay.
+    #[serde(default)]
+    pub allowed_ips: Vec<IpNetwork>,
+    /// Obfuscation server to reach the relay through, if any.
+    #[serde(default)]
+    pub obfuscation: Option<CustomObfuscationBridge>,
+}
+
+impl CustomRelay {
+    /// Return this relay as it appears in the relay list.
+    pub fn to_relay(&self) -> Relay {
+        let (ipv4_addr_in, ipv6_addr_in) = match self.endpoint.ip() {
+            IpAddr::V4(ipv4) => (ipv4, None),
+            IpAddr::V6(ipv6) => (std::net::Ipv4Addr::UNSPECIFIED, Some(ipv6)),
+        };
+        Relay {
+            hostname: self.name.clone(),
+            ipv4_addr_in,
+            ipv6_addr_in,
+            overridden_ipv4: false,
+            overridden_ipv6: false,
+            include_in_country: true,
+            active: true,
+            owned: false,
+            provider: PROVIDER.to_owned(),
+            asn: None,
+            weight: 1,
+            endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
+                public_key: self.public_key.clone(),
+                daita: false,
+                shadowsocks_extra_addr_in: vec![],
+            }),
+            location: Location {
+                country: COUNTRY_NAME.to_owned(),
+                country_code: COUNTRY_CODE.to_owned(),
+                city: CITY_NAME.to_owned(),
+                city_code: CITY_CODE.to_owned(),
+                latitude: 0.0,
+                longitude: 0.0,
+            },
+        }
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct CustomRelaySettings {
+    relays: Vec<CustomRelay>,
+}
+
+impl From<Vec<CustomRelay>> for CustomRelaySettings {
+    fn from(relays: Vec<CustomRelay>) -> Self {
+        Self { relays }
+    }
+}
+
+impl CustomRelaySettings {
+    pub fn add(&mut self, relay: CustomRelay) -> Result<(), Error> {
+        if relay.name.is_empty() || relay.name.contains(char::is_whitespace) {
+            return Err(Error::InvalidName);
+        }
+        if self.find(&relay.name).is_some() {
+            return Err(Error::DuplicateName);
+        }
+        self.relays.push(relay);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), Error> {
+        let index = self
+            .relays
+            .iter()
+            .position(|relay| relay.name == name)
+            .ok_or(Error::NotFound)?;
+        self.relays.remove(index);
+        Ok(())
+    }
+
+    pub fn find(&self, name: &str) -> Option<&CustomRelay> {
+        self.relays.iter().find(|relay| relay.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &CustomRelay> {
+        self.relays.iter()
+    }
+
+    /// Replace any custom relays in `relay_list` with these.
+    pub fn add_to(&self, relay_list: &mut RelayList) {
+        relay_list
+            .countries
+            .retain(|country| country.code != COUNTRY_CODE);
+        if self.relays.is_empty() {
+            return;
+        }
+        relay_list.countries.push(RelayListCountry {
+            name: COUNTRY_NAME.to_owned(),
+            code: COUNTRY_CODE.to_owned(),
+            cities: vec![RelayListCity {
+                name: CITY_NAME.to_owned(),
+                code: CITY_CODE.to_owned(),
+                latitude: 0.0,
+                longitude: 0.0,
+                relays: self.relays.iter().map(CustomRelay::to_relay).collect(),
+            }],
+        });
+    }
+}
+
+impl IntoIterator for CustomRelaySettings {
+    type Item = CustomRelay;
+    type IntoIter = std::vec::IntoIter<Self::Item>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.relays.into_iter()
+    }
+}
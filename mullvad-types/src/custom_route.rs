@@ -0,0 +1,75 @@
+//! User-defined routes that are installed through or around the tunnel while it is up.
+
+use std::collections::HashSet;
+pub use talpid_types::net::{CustomRoute, RouteTarget};
+
+/// Upper bound on the number of custom routes.
+pub const MAX_CUSTOM_ROUTES: usize = 64;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("At most {MAX_CUSTOM_ROUTES} custom routes may be added")]
+    TooMany,
+    #[error("Route {0} would replace the default route")]
+    DefaultRoute(CustomRoute),
+    #[error("Route {0} has host bits set")]
+    HostBitsSet(CustomRoute),
+    #[error("More than one route was added for {0}")]
+    Duplicate(CustomRoute),
+}
+
+/// Check that `routes` can be installed.
+pub fn validate(routes: &[CustomRoute]) -> Result<(), Error> {
+    if routes.len() > MAX_CUSTOM_ROUTES {
+        return Err(Error::TooMany);
+    }
+    let mut seen = HashSet::new();
+    for route in routes {
+        if route.network.prefix() == 0 {
+            return Err(Error::DefaultRoute(*route));
+        }
+        if route.network.network() != route.network.ip() {
+            return Err(Error::HostBitsSet(*route));
+        }
+        if !seen.insert(route.network) {
+            return Err(Error::Duplicate(*route));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn route(network: &str, via: RouteTarget) -> CustomRoute {
+        CustomRoute {
+            network: network.parse().unwrap(),
+            via,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let tunnel = route("10.10.0.0/16", RouteTarget::Tunnel);
+        let gateway = route("192.0.2.0/24", RouteTarget::DefaultGateway);
+        assert_eq!(validate(&[tunnel, gateway]), Ok(()));
+
+        let conflicting = route("10.10.0.0/16", RouteTarget::DefaultGateway);
+        assert_eq!(
+            validate(&[tunnel, conflicting]),
+            Err(Error::Duplicate(conflicting))
+        );
+
+        let default = route("::/0", RouteTarget::DefaultGateway);
+        assert_eq!(validate(&[default]), Err(Error::DefaultRoute(default)));
+
+        let host_bits = route("10.10.1.5/16", RouteTarget::Tunnel);
+        assert_eq!(validate(&[host_bits]), Err(Error::HostBitsSet(host_bits)));
+
+        let too_many: Vec<_> = (0..=MAX_CUSTOM_ROUTES)
+            .map(|i| route(&format!("10.0.{i}.0/24"), RouteTarget::Tunnel))
+            .collect();
+        assert_eq!(validate(&too_many), Err(Error::TooMany));
+    }
+}
@@ -42,11 +42,14 @@ impl CustomTunnelEndpoint {
         let mut config = self.config.clone();
         config.set_ip(ip);
 
+        let mut generic_options = tunnel_options.generic;
+        generic_options.enable_ipv6 &= !generic_options.block_ipv6;
+
         let parameters = match config {
             ConnectionConfig::OpenVpn(config) => openvpn::TunnelParameters {
                 config,
                 options: tunnel_options.openvpn,
-                generic_options: tunnel_options.generic,
+                generic_options,
                 proxy,
                 #[cfg(target_os = "linux")]
                 fwmark: crate::TUNNEL_FWMARK,
@@ -61,8 +64,9 @@ impl CustomTunnelEndpoint {
                 wireguard::TunnelParameters {
                     connection,
                     options,
-                    generic_options: tunnel_options.generic,
+                    generic_options,
                     obfuscation: None,
+                    port_hopping: None,
                 }
                 .into()
             }
@@ -0,0 +1,105 @@
+//! Traffic sent and received through the tunnel, per calendar month.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Number of months to keep track of. Older months are forgotten.
+pub const MAX_MONTHS: usize = 24;
+
+/// Bytes sent and received through the tunnel during a calendar month, in local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MonthlyUsage {
+    pub year: i32,
+    /// Month of the year, starting at 1.
+    pub month: u32,
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+}
+
+impl MonthlyUsage {
+    pub fn total_bytes(&self) -> u64 {
+        self.tx_bytes.saturating_add(self.rx_bytes)
+    }
+}
+
+impl fmt::Display for MonthlyUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{:02}", self.year, self.month)
+    }
+}
+
+/// Data usage for the most recent months, newest first.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DataUsage {
+    months: Vec<MonthlyUsage>,
+}
+
+impl DataUsage {
+    pub fn new(months: Vec<MonthlyUsage>) -> Self {
+        let mut usage = DataUsage { months };
+        usage
+            .months
+            .sort_by(|a, b| (b.year, b.month).cmp(&(a.year, a.month)));
+        usage.months.truncate(MAX_MONTHS);
+        usage
+    }
+
+    /// Add traffic to the given month.
+    pub fn add(&mut self, year: i32, month: u32, tx_bytes: u64, rx_bytes: u64) {
+        let current = match self.months.first_mut() {
+            Some(usage) if usage.year == year && usage.month == month => usage,
+            _ => {
+                self.months.insert(
+                    0,
+                    MonthlyUsage {
+                        year,
+                        month,
+                        tx_bytes: 0,
+                        rx_bytes: 0,
+                    },
+                );
+                self.months.truncate(MAX_MONTHS);
+                &mut self.months[0]
+            }
+        };
+        current.tx_bytes = current.tx_bytes.saturating_add(tx_bytes);
+        current.rx_bytes = current.rx_bytes.saturating_add(rx_bytes);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &MonthlyUsage> {
+        self.months.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_add_usage() {
+        let mut usage = DataUsage::default();
+        usage.add(2024, 12, 10, 20);
+        usage.add(2024, 12, 1, 2);
+        usage.add(2025, 1, 5, 5);
+
+        let months: Vec<_> = usage.iter().copied().collect();
+        assert_eq!(months.len(), 2);
+        assert_eq!((months[0].year, months[0].month), (2025, 1));
+        assert_eq!(months[1].tx_bytes, 11);
+        assert_eq!(months[1].total_bytes(), 33);
+    }
+
+    #[test]
+    fn test_forget_old_months() {
+        let mut usage = DataUsage::default();
+        for month in 0..MAX_MONTHS + 2 {
+            usage.add(2000 + (month / 12) as i32, (month % 12) as u32 + 1, 1, 1);
+        }
+        assert_eq!(usage.iter().count(), MAX_MONTHS);
+        assert_eq!(
+            usage.iter().last().map(ToString::to_string).as_deref(),
+            Some("2000-03")
+        );
+    }
+}
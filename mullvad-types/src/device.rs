@@ -103,6 +103,22 @@ pub struct DeviceEvent {
     pub new_state: DeviceState,
 }
 
+/// Outcome of removing several devices at once using the `RemoveDevices` RPC.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct DeviceRemovalResult {
+    /// Devices that were removed.
+    pub removed: Vec<DeviceId>,
+    /// Devices that could not be removed.
+    pub failed: Vec<DeviceRemovalFailure>,
+}
+
+/// A device that could not be removed, and why.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DeviceRemovalFailure {
+    pub device_id: DeviceId,
+    pub error: String,
+}
+
 /// Emitted when a device is removed using the `RemoveDevice` RPC.
 /// This is not sent by a normal logout or when it is revoked remotely.
 #[derive(Clone, Debug, Serialize)]
@@ -0,0 +1,114 @@
+//! User-supplied DNS filter lists. The domains in these lists are blocked by the local DNS
+//! forwarder, in addition to the content blockers of the Mullvad DNS servers.
+
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt, path::PathBuf};
+
+/// Largest number of filter lists that may be configured.
+pub const MAX_FILTER_LISTS: usize = 16;
+
+/// Where a filter list is loaded from. Lists are either in hosts file format, or contain one
+/// domain per line.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterListSource {
+    /// A list that is downloaded over HTTPS.
+    Url(String),
+    /// A list that is read from a local file.
+    File(PathBuf),
+}
+
+impl fmt::Display for FilterListSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FilterListSource::Url(url) => f.write_str(url),
+            FilterListSource::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("Filter list URL must be an HTTPS URL: {0}")]
+    InvalidUrl(String),
+    #[error("Filter list path must be absolute: {0}")]
+    RelativePath(String),
+    #[error("No more than {MAX_FILTER_LISTS} filter lists may be added")]
+    TooManyLists,
+    #[error("Filter list was added more than once: {0}")]
+    Duplicate(String),
+}
+
+/// Check that `sources` can be loaded by the daemon.
+pub fn validate(sources: &[FilterListSource]) -> Result<(), Error> {
+    if sources.len() > MAX_FILTER_LISTS {
+        return Err(Error::TooManyLists);
+    }
+    let mut seen = HashSet::new();
+    for source in sources {
+        match source {
+            FilterListSource::Url(url) => {
+                let host = url.strip_prefix("https://").unwrap_or_default();
+                if host.is_empty()
+                    || host.starts_with('/')
+                    || url.chars().any(|c| c.is_whitespace())
+                {
+                    return Err(Error::InvalidUrl(url.clone()));
+                }
+            }
+            FilterListSource::File(path) => {
+                if !path.is_absolute() {
+                    return Err(Error::RelativePath(source.to_string()));
+                }
+            }
+        }
+        if !seen.insert(source) {
+            return Err(Error::Duplicate(source.to_string()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let url = FilterListSource::Url("https://lists.example/hosts.txt".to_owned());
+        #[cfg(unix)]
+        let file = FilterListSource::File(PathBuf::from("/etc/mullvad-vpn/blocklist.txt"));
+        #[cfg(windows)]
+        let file = FilterListSource::File(PathBuf::from(r"C:\ProgramData\blocklist.txt"));
+
+        assert_eq!(validate(&[]), Ok(()));
+        assert_eq!(validate(&[url.clone(), file.clone()]), Ok(()));
+        assert_eq!(
+            validate(&[url.clone(), url.clone()]),
+            Err(Error::Duplicate(url.to_string()))
+        );
+
+        for invalid_url in [
+            "http://lists.example/hosts.txt",
+            "https://",
+            "https:///hosts.txt",
+            "https://lists.example/hosts file.txt",
+            "lists.example/hosts.txt",
+        ] {
+            assert_eq!(
+                validate(&[FilterListSource::Url(invalid_url.to_owned())]),
+                Err(Error::InvalidUrl(invalid_url.to_owned()))
+            );
+        }
+
+        assert_eq!(
+            validate(&[FilterListSource::File(PathBuf::from("blocklist.txt"))]),
+            Err(Error::RelativePath("blocklist.txt".to_owned()))
+        );
+
+        let too_many: Vec<_> = (0..=MAX_FILTER_LISTS)
+            .map(|i| FilterListSource::Url(format!("https://lists.example/{i}.txt")))
+            .collect();
+        assert_eq!(validate(&too_many), Err(Error::TooManyLists));
+    }
+}
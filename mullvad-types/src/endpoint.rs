@@ -13,6 +13,8 @@ pub enum MullvadEndpoint {
 pub struct MullvadWireguardEndpoint {
     pub peer: wireguard::PeerConfig,
     pub exit_peer: Option<wireguard::PeerConfig>,
+    /// Relays between `peer` and `exit_peer`, ordered from the entry side
+    pub middle_peers: Vec<wireguard::PeerConfig>,
     pub ipv4_gateway: Ipv4Addr,
     pub ipv6_gateway: Ipv6Addr,
 }
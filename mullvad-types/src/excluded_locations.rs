@@ -0,0 +1,64 @@
+//! Relays, cities and countries that the user never wants to use, e.g. for legal or performance
+//! reasons. Excluded relays are removed from the relay list before any relay is selected.
+
+use crate::{
+    constraints::Match, relay_constraints::GeographicLocationConstraint, relay_list::RelayList,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ExcludedLocations {
+    locations: BTreeSet<GeographicLocationConstraint>,
+}
+
+impl ExcludedLocations {
+    /// Exclude `location`. Returns whether it was not already excluded.
+    pub fn add(&mut self, location: GeographicLocationConstraint) -> bool {
+        self.locations.insert(location)
+    }
+
+    /// Stop excluding `location`. Returns whether it was excluded.
+    pub fn remove(&mut self, location: &GeographicLocationConstraint) -> bool {
+        self.locations.remove(location)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &GeographicLocationConstraint> {
+        self.locations.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.locations.is_empty()
+    }
+
+    /// Remove all relays that match an excluded location from `relay_list`. Cities and countries
+    /// that are left without relays are removed as well.
+    pub fn remove_from(&self, relay_list: &mut RelayList) {
+        if self.is_empty() {
+            return;
+        }
+        for country in &mut relay_list.countries {
+            for city in &mut country.cities {
+                city.relays.retain(|relay| {
+                    !self
+                        .locations
+                        .iter()
+                        .any(|location| location.matches(relay))
+                });
+            }
+            country.cities.retain(|city| !city.relays.is_empty());
+        }
+        relay_list
+            .countries
+            .retain(|country| !country.cities.is_empty());
+    }
+}
+
+impl From<Vec<GeographicLocationConstraint>> for ExcludedLocations {
+    fn from(locations: Vec<GeographicLocationConstraint>) -> Self {
+        Self {
+            locations: locations.into_iter().collect(),
+        }
+    }
+}
@@ -80,6 +80,7 @@ pub enum FeatureIndicator {
     CustomMtu,
     CustomMssFix,
     Daita,
+    Tls,
 }
 
 impl FeatureIndicator {
@@ -99,6 +100,7 @@ impl FeatureIndicator {
             FeatureIndicator::CustomMtu => "Custom MTU",
             FeatureIndicator::CustomMssFix => "Custom MSS",
             FeatureIndicator::Daita => "DAITA",
+            FeatureIndicator::Tls => "TLS",
         }
     }
 }
@@ -124,12 +126,14 @@ pub fn compute_feature_indicators(
 ) -> FeatureIndicators {
     #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     let split_tunneling = settings.split_tunnel.enable_exclusions;
-    #[cfg(not(any(windows, target_os = "android", target_os = "macos")))]
-    let split_tunneling = false;
+    // Most traffic bypasses the tunnel in include-only mode
+    #[cfg(target_os = "linux")]
+    let split_tunneling =
+        settings.split_tunnel_mode == talpid_types::split_tunnel::SplitTunnelMode::IncludeOnly;
 
     #[cfg(not(target_os = "android"))]
     let lockdown_mode = settings.block_when_disconnected;
-    let lan_sharing = settings.allow_lan;
+    let lan_sharing = settings.allow_lan.is_allowed();
     let dns_content_blockers = settings
         .tunnel_options
         .dns_options
@@ -170,6 +174,11 @@ pub fn compute_feature_indicators(
                 .as_ref()
                 .filter(|obfuscation| obfuscation.obfuscation_type == ObfuscationType::Shadowsocks)
                 .is_some();
+            let tls = endpoint
+                .obfuscation
+                .as_ref()
+                .filter(|obfuscation| obfuscation.obfuscation_type == ObfuscationType::Tls)
+                .is_some();
 
             let mtu = settings.tunnel_options.wireguard.mtu.is_some();
 
@@ -186,6 +195,7 @@ pub fn compute_feature_indicators(
                 (multihop, FeatureIndicator::Multihop),
                 (udp_tcp, FeatureIndicator::Udp2Tcp),
                 (shadowsocks, FeatureIndicator::Shadowsocks),
+                (tls, FeatureIndicator::Tls),
                 (mtu, FeatureIndicator::CustomMtu),
                 (daita, FeatureIndicator::Daita),
             ]
@@ -209,7 +219,7 @@ mod tests {
         Endpoint, ObfuscationEndpoint, TransportProtocol,
     };
 
-    use crate::relay_constraints::RelaySettings;
+    use crate::{lan_sharing::AllowedLan, relay_constraints::RelaySettings};
 
     use super::*;
 
@@ -227,6 +237,8 @@ mod tests {
             obfuscation: Default::default(),
             entry_endpoint: Default::default(),
             tunnel_interface: Default::default(),
+            wireguard_backend: Default::default(),
+            wireguard_worker_threads: Default::default(),
             daita: Default::default(),
         };
 
@@ -262,7 +274,7 @@ mod tests {
             expected_indicators
         );
 
-        settings.allow_lan = true;
+        settings.allow_lan = AllowedLan::All;
 
         expected_indicators.0.insert(FeatureIndicator::LanSharing);
 
@@ -356,6 +368,13 @@ mod tests {
             compute_feature_indicators(&settings, &endpoint, false),
             expected_indicators
         );
+        endpoint.obfuscation.as_mut().unwrap().obfuscation_type = ObfuscationType::Tls;
+        expected_indicators.0.remove(&FeatureIndicator::Shadowsocks);
+        expected_indicators.0.insert(FeatureIndicator::Tls);
+        assert_eq!(
+            compute_feature_indicators(&settings, &endpoint, false),
+            expected_indicators
+        );
 
         settings.tunnel_options.wireguard.mtu = Some(1300);
         expected_indicators.0.insert(FeatureIndicator::CustomMtu);
@@ -437,6 +456,7 @@ mod tests {
             FeatureIndicator::CustomMtu => {}
             FeatureIndicator::CustomMssFix => {}
             FeatureIndicator::Daita => {}
+            FeatureIndicator::Tls => {}
         }
     }
 }
@@ -0,0 +1,90 @@
+//! User-defined traffic that is allowed outside the tunnel, even when the firewall is blocking.
+
+use std::collections::HashSet;
+pub use talpid_types::net::FirewallException;
+
+/// The firewall on Windows can hold at most this many exceptions.
+pub const MAX_FIREWALL_EXCEPTIONS: usize = 16;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("At most {MAX_FIREWALL_EXCEPTIONS} firewall exceptions may be added")]
+    TooMany,
+    #[error("Exception for {0} would allow traffic to every address")]
+    AllAddresses(FirewallException),
+    #[error("Exception for {0} has an invalid port")]
+    InvalidPort(FirewallException),
+    #[error("Exception for {0} has a port but no protocol")]
+    PortWithoutProtocol(FirewallException),
+    #[error("Exception for {0} was added more than once")]
+    Duplicate(FirewallException),
+}
+
+/// Check that `exceptions` can be applied to the firewall.
+pub fn validate(exceptions: &[FirewallException]) -> Result<(), Error> {
+    if exceptions.len() > MAX_FIREWALL_EXCEPTIONS {
+        return Err(Error::TooMany);
+    }
+    let mut seen = HashSet::new();
+    for exception in exceptions {
+        if exception.network.prefix() == 0 {
+            return Err(Error::AllAddresses(*exception));
+        }
+        match (exception.protocol, exception.port) {
+            (_, Some(0)) => return Err(Error::InvalidPort(*exception)),
+            (None, Some(_)) => return Err(Error::PortWithoutProtocol(*exception)),
+            _ => (),
+        }
+        if !seen.insert(exception) {
+            return Err(Error::Duplicate(*exception));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use talpid_types::net::TransportProtocol;
+
+    fn exception(
+        network: &str,
+        protocol: Option<TransportProtocol>,
+        port: Option<u16>,
+    ) -> FirewallException {
+        FirewallException {
+            network: network.parse().unwrap(),
+            protocol,
+            port,
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let udp = exception("192.168.1.10/32", Some(TransportProtocol::Udp), Some(51820));
+        let any = exception("fd00::/8", None, None);
+        assert_eq!(validate(&[udp, any]), Ok(()));
+
+        assert_eq!(validate(&[udp, udp]), Err(Error::Duplicate(udp)));
+
+        let everything = exception("0.0.0.0/0", Some(TransportProtocol::Tcp), None);
+        assert_eq!(
+            validate(&[everything]),
+            Err(Error::AllAddresses(everything))
+        );
+
+        let no_protocol = exception("10.0.0.1/32", None, Some(22));
+        assert_eq!(
+            validate(&[no_protocol]),
+            Err(Error::PortWithoutProtocol(no_protocol))
+        );
+
+        let zero_port = exception("10.0.0.1/32", Some(TransportProtocol::Tcp), Some(0));
+        assert_eq!(validate(&[zero_port]), Err(Error::InvalidPort(zero_port)));
+
+        let too_many: Vec<_> = (0..=MAX_FIREWALL_EXCEPTIONS)
+            .map(|i| exception(&format!("10.0.0.{i}/32"), None, None))
+            .collect();
+        assert_eq!(validate(&too_many), Err(Error::TooMany));
+    }
+}
@@ -0,0 +1,127 @@
+//! Local network traffic that is allowed outside the tunnel. Either all of it, or only traffic
+//! to specific private networks and interfaces.
+
+use std::collections::HashSet;
+use talpid_types::net::ALLOWED_LAN_NETS;
+pub use talpid_types::net::{AllowedLan, LanNetwork};
+
+/// The firewall on Windows can hold at most this many LAN networks.
+pub const MAX_LAN_NETWORKS: usize = 16;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("At least one LAN network must be specified")]
+    Empty,
+    #[error("At most {MAX_LAN_NETWORKS} LAN networks may be added")]
+    TooMany,
+    #[error("LAN network {0} must specify a network or an interface")]
+    Unrestricted(LanNetwork),
+    #[error("LAN network {0} is not a private network")]
+    NotPrivate(LanNetwork),
+    #[error("LAN network {0} has an empty interface name")]
+    EmptyInterface(LanNetwork),
+    #[error("LAN network {0} was added more than once")]
+    Duplicate(LanNetwork),
+}
+
+/// Check that `allow_lan` can be applied to the firewall.
+pub fn validate(allow_lan: &AllowedLan) -> Result<(), Error> {
+    let AllowedLan::Only(networks) = allow_lan else {
+        return Ok(());
+    };
+    if networks.is_empty() {
+        return Err(Error::Empty);
+    }
+    if networks.len() > MAX_LAN_NETWORKS {
+        return Err(Error::TooMany);
+    }
+    let mut seen = HashSet::new();
+    for lan_network in networks {
+        match (&lan_network.network, &lan_network.interface) {
+            (None, None) => return Err(Error::Unrestricted(lan_network.clone())),
+            (Some(network), _) if !is_private(network) => {
+                return Err(Error::NotPrivate(lan_network.clone()))
+            }
+            (_, Some(interface)) if interface.is_empty() => {
+                return Err(Error::EmptyInterface(lan_network.clone()))
+            }
+            _ => (),
+        }
+        if !seen.insert(lan_network) {
+            return Err(Error::Duplicate(lan_network.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether `network` is contained in one of the private networks.
+fn is_private(network: &ipnetwork::IpNetwork) -> bool {
+    ALLOWED_LAN_NETS
+        .iter()
+        .any(|lan| lan.prefix() <= network.prefix() && lan.contains(network.network()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lan_network(network: Option<&str>, interface: Option<&str>) -> LanNetwork {
+        LanNetwork {
+            network: network.map(|network| network.parse().unwrap()),
+            interface: interface.map(str::to_owned),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(&AllowedLan::All), Ok(()));
+        assert_eq!(validate(&AllowedLan::Only(vec![])), Err(Error::Empty));
+
+        let home = lan_network(Some("192.168.1.0/24"), Some("eth0"));
+        let wired = lan_network(None, Some("eth1"));
+        let link_local = lan_network(Some("fe80::/64"), None);
+        assert_eq!(
+            validate(&AllowedLan::Only(vec![
+                home.clone(),
+                wired.clone(),
+                link_local.clone()
+            ])),
+            Ok(())
+        );
+
+        assert_eq!(
+            validate(&AllowedLan::Only(vec![home.clone(), home.clone()])),
+            Err(Error::Duplicate(home))
+        );
+
+        let public = lan_network(Some("8.8.8.0/24"), None);
+        assert_eq!(
+            validate(&AllowedLan::Only(vec![public.clone()])),
+            Err(Error::NotPrivate(public))
+        );
+
+        // Larger than any private network
+        let too_large = lan_network(Some("192.0.0.0/8"), None);
+        assert_eq!(
+            validate(&AllowedLan::Only(vec![too_large.clone()])),
+            Err(Error::NotPrivate(too_large))
+        );
+
+        let unrestricted = lan_network(None, None);
+        assert_eq!(
+            validate(&AllowedLan::Only(vec![unrestricted.clone()])),
+            Err(Error::Unrestricted(unrestricted))
+        );
+
+        let empty_interface = lan_network(None, Some(""));
+        assert_eq!(
+            validate(&AllowedLan::Only(vec![empty_interface.clone()])),
+            Err(Error::EmptyInterface(empty_interface))
+        );
+
+        let too_many = (0..=MAX_LAN_NETWORKS)
+            .map(|i| lan_network(Some(&format!("10.0.{i}.0/24")), None))
+            .collect();
+        assert_eq!(validate(&AllowedLan::Only(too_many)), Err(Error::TooMany));
+    }
+}
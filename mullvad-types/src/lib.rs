@@ -1,16 +1,37 @@
 pub mod access_method;
 pub mod account;
+#[cfg(not(target_os = "android"))]
+pub mod allowed_incoming;
 pub mod auth_failed;
+pub mod conflicting_software;
 pub mod constraints;
 pub mod custom_list;
+pub mod custom_relay;
+#[cfg(not(target_os = "android"))]
+pub mod custom_route;
+pub mod data_usage;
 pub mod device;
+pub mod dns_filter;
 pub mod endpoint;
+pub mod excluded_locations;
 pub mod features;
+#[cfg(not(target_os = "android"))]
+pub mod firewall_exception;
+pub mod lan_sharing;
 pub mod location;
+pub mod pending_request;
+pub mod profile;
 pub mod relay_constraints;
 pub mod relay_list;
 pub mod settings;
+pub mod split_dns;
+#[cfg(target_os = "linux")]
+pub mod split_tunnel_domain;
+#[cfg(target_os = "linux")]
+pub mod split_tunnel_owner;
+pub mod state_hooks;
 pub mod states;
+pub mod trusted_network;
 pub mod version;
 pub mod wireguard;
 
@@ -0,0 +1,53 @@
+use crate::{account::AccountNumber, device::DeviceId};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Identifier of a queued request. It is also sent to the API as an idempotency key, so that a
+/// request that is replayed after its response was lost is only applied once.
+pub type PendingRequestId = String;
+
+/// An API request which could not be sent because the API was unreachable, and which will be
+/// sent once connectivity returns.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PendingRequest {
+    pub id: PendingRequestId,
+    pub created: DateTime<Utc>,
+    pub kind: PendingRequestKind,
+}
+
+impl PendingRequest {
+    pub fn new(kind: PendingRequestKind) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            created: Utc::now(),
+            kind,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingRequestKind {
+    SubmitVoucher {
+        account_number: AccountNumber,
+        voucher: String,
+    },
+    RemoveDevice {
+        account_number: AccountNumber,
+        device_id: DeviceId,
+    },
+}
+
+impl fmt::Display for PendingRequestKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PendingRequestKind::SubmitVoucher { voucher, .. } => {
+                write!(f, "Redeem voucher {voucher}")
+            }
+            PendingRequestKind::RemoveDevice { device_id, .. } => {
+                write!(f, "Revoke device {device_id}")
+            }
+        }
+    }
+}
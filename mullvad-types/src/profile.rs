@@ -0,0 +1,161 @@
+//! Named profiles, each holding a copy of the settings that users typically change together when
+//! switching between situations, e.g. "home" and "travel". Using a profile replaces those settings
+//! with the ones stored in the profile.
+
+use crate::{
+    relay_constraints::{ObfuscationSettings, RelaySettings},
+    settings::{DnsOptions, Settings},
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(any(windows, target_os = "android", target_os = "macos"))]
+use crate::settings::SplitTunnelSettings;
+
+/// Maximum number of characters in a profile name.
+pub const PROFILE_NAME_MAX_SIZE: usize = 30;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Invalid profile name")]
+    InvalidName,
+    #[error("Profile not found")]
+    NotFound,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub relay_settings: RelaySettings,
+    pub obfuscation_settings: ObfuscationSettings,
+    pub dns_options: DnsOptions,
+    #[cfg(any(windows, target_os = "android", target_os = "macos"))]
+    #[serde(default)]
+    pub split_tunnel: SplitTunnelSettings,
+}
+
+impl Profile {
+    /// Create a profile named `name` from the current `settings`.
+    pub fn from_settings(name: String, settings: &Settings) -> Result<Self, Error> {
+        if name.is_empty() || name.chars().count() > PROFILE_NAME_MAX_SIZE {
+            return Err(Error::InvalidName);
+        }
+        Ok(Self {
+            name,
+            relay_settings: settings.relay_settings.clone(),
+            obfuscation_settings: settings.obfuscation_settings.clone(),
+            dns_options: settings.tunnel_options.dns_options.clone(),
+            #[cfg(any(windows, target_os = "android", target_os = "macos"))]
+            split_tunnel: settings.split_tunnel.clone(),
+        })
+    }
+
+    /// Replace the settings that are part of the profile.
+    pub fn apply_to(&self, settings: &mut Settings) {
+        settings.set_relay_settings(self.relay_settings.clone());
+        settings.obfuscation_settings = self.obfuscation_settings.clone();
+        settings.tunnel_options.dns_options = self.dns_options.clone();
+        #[cfg(any(windows, target_os = "android", target_os = "macos"))]
+        {
+            settings.split_tunnel = self.split_tunnel.clone();
+        }
+    }
+
+    /// Returns whether the settings that are part of the profile are the same in `settings`.
+    pub fn is_active(&self, settings: &Settings) -> bool {
+        Self::from_settings(self.name.clone(), settings).is_ok_and(|current| current == *self)
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProfileSettings {
+    profiles: Vec<Profile>,
+}
+
+impl From<Vec<Profile>> for ProfileSettings {
+    fn from(profiles: Vec<Profile>) -> Self {
+        Self { profiles }
+    }
+}
+
+impl ProfileSettings {
+    /// Add `profile`, replacing any existing profile with the same name.
+    pub fn save(&mut self, profile: Profile) {
+        match self
+            .profiles
+            .iter_mut()
+            .find(|existing| existing.name == profile.name)
+        {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), Error> {
+        let index = self
+            .profiles
+            .iter()
+            .position(|profile| profile.name == name)
+            .ok_or(Error::NotFound)?;
+        self.profiles.remove(index);
+        Ok(())
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Profile> {
+        self.profiles.iter()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        constraints::Constraint,
+        relay_constraints::{GeographicLocationConstraint, LocationConstraint, RelayConstraints},
+    };
+
+    #[test]
+    fn test_save_and_apply() {
+        let mut settings = Settings::default();
+        let home = Profile::from_settings("home".to_owned(), &settings).unwrap();
+
+        settings.set_relay_settings(RelaySettings::Normal(RelayConstraints {
+            location: Constraint::Only(LocationConstraint::Location(
+                GeographicLocationConstraint::country("de"),
+            )),
+            ..RelayConstraints::default()
+        }));
+        let dns_options = &mut settings.tunnel_options.dns_options;
+        dns_options.default_options.block_ads = true;
+        let travel = Profile::from_settings("travel".to_owned(), &settings).unwrap();
+        assert!(travel.is_active(&settings));
+        assert!(!home.is_active(&settings));
+
+        let mut profiles = ProfileSettings::default();
+        profiles.save(home.clone());
+        profiles.save(travel);
+        profiles.find("home").unwrap().apply_to(&mut settings);
+        assert_eq!(settings.relay_settings, Settings::default().relay_settings);
+        assert_eq!(settings.tunnel_options.dns_options, DnsOptions::default());
+        assert!(home.is_active(&settings));
+
+        profiles.remove("travel").unwrap();
+        assert!(matches!(profiles.remove("travel"), Err(Error::NotFound)));
+    }
+
+    #[test]
+    fn test_invalid_name() {
+        let settings = Settings::default();
+        assert!(matches!(
+            Profile::from_settings(String::new(), &settings),
+            Err(Error::InvalidName)
+        ));
+        assert!(matches!(
+            Profile::from_settings("x".repeat(PROFILE_NAME_MAX_SIZE + 1), &settings),
+            Err(Error::InvalidName)
+        ));
+    }
+}
@@ -10,10 +10,11 @@ use crate::{
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashSet,
+    collections::{BTreeSet, HashSet},
     fmt,
-    net::{Ipv4Addr, Ipv6Addr},
+    net::{Ipv4Addr, Ipv6Addr, SocketAddr},
     str::FromStr,
+    time::Duration,
 };
 use talpid_types::net::{proxy::CustomProxy, IpVersion, TransportProtocol, TunnelType};
 
@@ -122,6 +123,7 @@ impl fmt::Display for LocationConstraintFormatter<'_> {
 pub struct RelayConstraints {
     pub location: Constraint<LocationConstraint>,
     pub providers: Constraint<Providers>,
+    pub asns: Constraint<AsnFilter>,
     pub ownership: Constraint<Ownership>,
     pub tunnel_protocol: TunnelType,
     pub wireguard_constraints: WireguardConstraints,
@@ -157,6 +159,7 @@ impl fmt::Display for RelayConstraintsFormatter<'_> {
                 })
         )?;
         writeln!(f, "Provider(s): {}", self.constraints.providers)?;
+        writeln!(f, "ASN(s): {}", self.constraints.asns)?;
         write!(f, "Ownership: {}", self.constraints.ownership)
     }
 }
@@ -360,6 +363,47 @@ impl fmt::Display for Providers {
     }
 }
 
+/// Autonomous system number, identifying the upstream network that a relay is hosted in.
+pub type Asn = u32;
+
+/// Limits the set of [`crate::relay_list::Relay`]s used by a `RelaySelector` based on the
+/// [`Asn`] of their upstream network.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AsnFilter {
+    /// Only use relays in one of these networks. Relays whose network is unknown are not used.
+    Only(BTreeSet<Asn>),
+    /// Never use relays in any of these networks.
+    Except(BTreeSet<Asn>),
+}
+
+impl Match<Relay> for AsnFilter {
+    fn matches(&self, relay: &Relay) -> bool {
+        match self {
+            AsnFilter::Only(asns) => relay.asn.is_some_and(|asn| asns.contains(&asn)),
+            AsnFilter::Except(asns) => !relay.asn.is_some_and(|asn| asns.contains(&asn)),
+        }
+    }
+}
+
+impl fmt::Display for AsnFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        let (prefix, asns) = match self {
+            AsnFilter::Only(asns) => ("ASN(s) ", asns),
+            AsnFilter::Except(asns) => ("any ASN except ", asns),
+        };
+        write!(f, "{prefix}")?;
+        for (i, asn) in asns.iter().enumerate() {
+            if i == 0 {
+                write!(f, "AS{asn}")?;
+            } else {
+                write!(f, ", AS{asn}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
 impl fmt::Display for GeographicLocationConstraint {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
@@ -401,6 +445,9 @@ impl fmt::Display for OpenVpnConstraints {
     }
 }
 
+/// The largest number of relays that may be placed between the entry and exit relay.
+pub const MAX_MIDDLE_HOPS: u8 = 3;
+
 /// [`Constraint`]s applicable to WireGuard relays.
 #[derive(Debug, Default, Clone, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case", default)]
@@ -409,6 +456,12 @@ pub struct WireguardConstraints {
     pub ip_version: Constraint<IpVersion>,
     pub use_multihop: bool,
     pub entry_location: Constraint<LocationConstraint>,
+    /// Number of randomly chosen relays to route through between the entry and exit relay. This
+    /// only has an effect if multihop is enabled.
+    pub middle_hops: u8,
+    /// Never use two relays run by the same hosting provider in a multihop circuit. Relays owned
+    /// by Mullvad count as a single provider.
+    pub multihop_diversity: bool,
 }
 
 impl WireguardConstraints {
@@ -445,6 +498,12 @@ impl fmt::Display for WireguardConstraintsFormatter<'_> {
                 }
             });
             write!(f, ", multihop entry {}", location)?;
+            if self.constraints.middle_hops > 0 {
+                write!(f, " via {} middle relay(s)", self.constraints.middle_hops)?;
+            }
+            if self.constraints.multihop_diversity {
+                write!(f, ", relays from different providers")?;
+            }
         }
         Ok(())
     }
@@ -509,6 +568,10 @@ pub enum SelectedObfuscation {
     #[cfg_attr(feature = "clap", clap(name = "udp2tcp"))]
     Udp2Tcp,
     Shadowsocks,
+    PortHopping,
+    Tls,
+    /// Use [`ObfuscationSettings::custom_bridge`]
+    CustomBridge,
 }
 
 impl Intersection for SelectedObfuscation {
@@ -533,6 +596,9 @@ impl fmt::Display for SelectedObfuscation {
             SelectedObfuscation::Off => "off".fmt(f),
             SelectedObfuscation::Udp2Tcp => "udp2tcp".fmt(f),
             SelectedObfuscation::Shadowsocks => "shadowsocks".fmt(f),
+            SelectedObfuscation::PortHopping => "port-hopping".fmt(f),
+            SelectedObfuscation::Tls => "tls".fmt(f),
+            SelectedObfuscation::CustomBridge => "custom-bridge".fmt(f),
         }
     }
 }
@@ -556,17 +622,175 @@ impl fmt::Display for Udp2TcpObfuscationSettings {
 #[serde(rename_all = "snake_case")]
 pub struct ShadowsocksSettings {
     pub port: Constraint<u16>,
+    /// Cipher to encrypt traffic with. [`ShadowsocksCipher::default`] is used if unconstrained.
+    #[serde(default)]
+    pub cipher: Constraint<ShadowsocksCipher>,
 }
 
 impl fmt::Display for ShadowsocksSettings {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self.port {
-            Constraint::Any => write!(f, "any port"),
-            Constraint::Only(port) => write!(f, "port {port}"),
+            Constraint::Any => write!(f, "any port")?,
+            Constraint::Only(port) => write!(f, "port {port}")?,
+        }
+        match self.cipher {
+            Constraint::Any => write!(f, ", default cipher"),
+            Constraint::Only(cipher) => write!(f, ", cipher {cipher}"),
+        }
+    }
+}
+
+/// AEAD ciphers that are supported by the Shadowsocks obfuscation servers.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq, Deserialize, Serialize)]
+#[cfg_attr(feature = "clap", derive(clap::ValueEnum))]
+pub enum ShadowsocksCipher {
+    #[serde(rename = "aes-128-gcm")]
+    #[cfg_attr(feature = "clap", clap(name = "aes-128-gcm"))]
+    Aes128Gcm,
+    #[default]
+    #[serde(rename = "aes-256-gcm")]
+    #[cfg_attr(feature = "clap", clap(name = "aes-256-gcm"))]
+    Aes256Gcm,
+    #[serde(rename = "chacha20-ietf-poly1305")]
+    #[cfg_attr(feature = "clap", clap(name = "chacha20-ietf-poly1305"))]
+    Chacha20IetfPoly1305,
+}
+
+impl ShadowsocksCipher {
+    /// Name of the cipher, as understood by Shadowsocks implementations.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            ShadowsocksCipher::Aes128Gcm => "aes-128-gcm",
+            ShadowsocksCipher::Aes256Gcm => "aes-256-gcm",
+            ShadowsocksCipher::Chacha20IetfPoly1305 => "chacha20-ietf-poly1305",
         }
     }
 }
 
+impl fmt::Display for ShadowsocksCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(f)
+    }
+}
+
+impl FromStr for ShadowsocksCipher {
+    type Err = ShadowsocksCipherParseError;
+
+    fn from_str(s: &str) -> Result<ShadowsocksCipher, Self::Err> {
+        match s {
+            "aes-128-gcm" => Ok(ShadowsocksCipher::Aes128Gcm),
+            "aes-256-gcm" => Ok(ShadowsocksCipher::Aes256Gcm),
+            "chacha20-ietf-poly1305" => Ok(ShadowsocksCipher::Chacha20IetfPoly1305),
+            _ => Err(ShadowsocksCipherParseError),
+        }
+    }
+}
+
+/// Returned when `ShadowsocksCipher::from_str` fails to convert a string into a
+/// [`ShadowsocksCipher`] object.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Not a supported Shadowsocks cipher")]
+pub struct ShadowsocksCipherParseError;
+
+/// Shortest allowed time between port hops, in minutes.
+pub const MIN_PORT_HOPPING_INTERVAL_MINUTES: u16 = 1;
+/// Longest allowed time between port hops, in minutes.
+pub const MAX_PORT_HOPPING_INTERVAL_MINUTES: u16 = 24 * 60;
+
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize, Intersection)]
+#[serde(rename_all = "snake_case")]
+pub struct PortHoppingSettings {
+    /// Number of minutes to stay on a port before moving to another one
+    pub interval_minutes: u16,
+}
+
+impl PortHoppingSettings {
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(u64::from(self.interval_minutes) * 60)
+    }
+}
+
+impl Default for PortHoppingSettings {
+    fn default() -> Self {
+        Self {
+            interval_minutes: 10,
+        }
+    }
+}
+
+impl fmt::Display for PortHoppingSettings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "new port every {} minute(s)", self.interval_minutes)
+    }
+}
+
+/// An obfuscation server run by the user, e.g. on a VPS in a network that is not blocked. It
+/// forwards the obfuscated traffic to the relays.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CustomObfuscationBridge {
+    /// A udp2tcp server. The server decides which relay the traffic is forwarded to, so the relay
+    /// constraints must match that relay.
+    Udp2Tcp { endpoint: SocketAddr },
+    /// A Shadowsocks server, which forwards the traffic to whichever relay is selected.
+    Shadowsocks {
+        endpoint: SocketAddr,
+        password: String,
+        cipher: ShadowsocksCipher,
+    },
+}
+
+impl CustomObfuscationBridge {
+    pub fn endpoint(&self) -> SocketAddr {
+        match self {
+            CustomObfuscationBridge::Udp2Tcp { endpoint }
+            | CustomObfuscationBridge::Shadowsocks { endpoint, .. } => *endpoint,
+        }
+    }
+
+    /// Check that the bridge can possibly be connected to.
+    pub fn validate(&self) -> Result<(), InvalidCustomObfuscationBridge> {
+        let endpoint = self.endpoint();
+        if endpoint.port() == 0 {
+            return Err(InvalidCustomObfuscationBridge::Port);
+        }
+        let ip = endpoint.ip();
+        if ip.is_unspecified() || ip.is_multicast() {
+            return Err(InvalidCustomObfuscationBridge::Address(ip));
+        }
+        if let CustomObfuscationBridge::Shadowsocks { password, .. } = self {
+            if password.is_empty() {
+                return Err(InvalidCustomObfuscationBridge::MissingSecret);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for CustomObfuscationBridge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CustomObfuscationBridge::Udp2Tcp { endpoint } => write!(f, "udp2tcp {endpoint}"),
+            CustomObfuscationBridge::Shadowsocks {
+                endpoint, cipher, ..
+            } => write!(f, "Shadowsocks {endpoint}, cipher {cipher}"),
+        }
+    }
+}
+
+/// Returned when a [`CustomObfuscationBridge`] is not usable.
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum InvalidCustomObfuscationBridge {
+    #[error("The bridge port must not be 0")]
+    Port,
+    #[error("{0} is not a valid bridge address")]
+    Address(std::net::IpAddr),
+    #[error("A Shadowsocks bridge requires a password")]
+    MissingSecret,
+    #[error("Custom bridge obfuscation is selected but no bridge is set")]
+    MissingBridge,
+}
+
 /// Contains obfuscation settings
 #[derive(Default, Debug, Clone, Eq, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -575,6 +799,21 @@ pub struct ObfuscationSettings {
     pub selected_obfuscation: SelectedObfuscation,
     pub udp2tcp: Udp2TcpObfuscationSettings,
     pub shadowsocks: ShadowsocksSettings,
+    pub port_hopping: PortHoppingSettings,
+    pub custom_bridge: Option<CustomObfuscationBridge>,
+}
+
+impl ObfuscationSettings {
+    /// Check that the custom bridge, if any, is usable, and that there is one if it is selected.
+    pub fn validate(&self) -> Result<(), InvalidCustomObfuscationBridge> {
+        match &self.custom_bridge {
+            Some(bridge) => bridge.validate(),
+            None if self.selected_obfuscation == SelectedObfuscation::CustomBridge => {
+                Err(InvalidCustomObfuscationBridge::MissingBridge)
+            }
+            None => Ok(()),
+        }
+    }
 }
 
 /// Limits the set of bridge servers to use in `mullvad-daemon`.
@@ -729,4 +968,42 @@ mod tests {
             GeographicLocationConstraint::hostname("se", "got", "se-got-wg-101")
         );
     }
+
+    #[test]
+    fn validate_custom_obfuscation_bridge() {
+        let shadowsocks = |endpoint: &str, password: &str| CustomObfuscationBridge::Shadowsocks {
+            endpoint: endpoint.parse().unwrap(),
+            password: password.to_owned(),
+            cipher: ShadowsocksCipher::default(),
+        };
+        assert_eq!(shadowsocks("192.0.2.1:443", "secret").validate(), Ok(()));
+        assert_eq!(
+            shadowsocks("192.0.2.1:0", "secret").validate(),
+            Err(InvalidCustomObfuscationBridge::Port)
+        );
+        assert!(matches!(
+            shadowsocks("0.0.0.0:443", "secret").validate(),
+            Err(InvalidCustomObfuscationBridge::Address(_))
+        ));
+        assert_eq!(
+            shadowsocks("192.0.2.1:443", "").validate(),
+            Err(InvalidCustomObfuscationBridge::MissingSecret)
+        );
+
+        let udp2tcp = CustomObfuscationBridge::Udp2Tcp {
+            endpoint: "[2001:db8::1]:443".parse().unwrap(),
+        };
+        assert_eq!(udp2tcp.validate(), Ok(()));
+
+        let mut settings = ObfuscationSettings {
+            selected_obfuscation: SelectedObfuscation::CustomBridge,
+            ..Default::default()
+        };
+        assert_eq!(
+            settings.validate(),
+            Err(InvalidCustomObfuscationBridge::MissingBridge)
+        );
+        settings.custom_bridge = Some(udp2tcp);
+        assert_eq!(settings.validate(), Ok(()));
+    }
 }
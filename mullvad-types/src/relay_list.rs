@@ -6,11 +6,21 @@ use std::{
 };
 use talpid_types::net::{proxy::Shadowsocks, wireguard, TransportProtocol};
 
+/// Default time between relay list updates, in minutes.
+pub const DEFAULT_UPDATE_INTERVAL_MINUTES: u16 = 60;
+/// Shortest allowed time between relay list updates, in minutes.
+pub const MIN_UPDATE_INTERVAL_MINUTES: u16 = 15;
+/// Longest allowed time between relay list updates, in minutes.
+pub const MAX_UPDATE_INTERVAL_MINUTES: u16 = 24 * 60;
+
 /// Stores a list of relays for each country obtained from the API using
 /// `mullvad_api::RelayListProxy`. This can also be passed to frontends.
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub struct RelayList {
     pub etag: Option<String>,
+    /// Value of the `Last-Modified` header of the response that contained the list.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
     pub countries: Vec<RelayListCountry>,
     #[serde(rename = "openvpn")]
     pub openvpn: OpenVpnEndpointData,
@@ -85,6 +95,9 @@ pub struct Relay {
     pub active: bool,
     pub owned: bool,
     pub provider: String,
+    /// Autonomous system number of the network that the relay is hosted in, if known.
+    #[serde(default)]
+    pub asn: Option<u32>,
     pub weight: u64,
     pub endpoint_data: RelayEndpointData,
     pub location: Location,
@@ -108,6 +121,11 @@ impl Relay {
     pub const fn is_openvpn(&self) -> bool {
         matches!(self.endpoint_data, RelayEndpointData::Openvpn)
     }
+
+    /// Returns whether this is a [custom relay](crate::custom_relay::CustomRelay) run by the user.
+    pub fn is_custom(&self) -> bool {
+        self.location.country_code == crate::custom_relay::COUNTRY_CODE
+    }
 }
 
 impl PartialEq for Relay {
@@ -130,6 +148,7 @@ impl PartialEq for Relay {
     ///     # active: true,
     ///     # owned: true,
     ///     # provider: "provider0".to_string(),
+    ///     # asn: None,
     ///     # weight: 1,
     ///     # endpoint_data: RelayEndpointData::Wireguard(WireguardRelayEndpointData {
     ///     #   public_key: PublicKey::from_base64(
@@ -1,5 +1,7 @@
+use crate::dns_filter::FilterListSource;
 use serde::{Deserialize, Serialize};
 use std::net::IpAddr;
+use talpid_types::dns::{EncryptedDnsServer, SplitDnsRule};
 
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 #[serde(rename_all = "snake_case")]
@@ -16,6 +18,12 @@ pub struct DnsOptions {
     pub state: DnsState,
     pub default_options: DefaultDnsOptions,
     pub custom_options: CustomDnsOptions,
+    /// Domains whose queries are sent to DNS servers on the local network. Applies regardless
+    /// of `state`.
+    pub split_dns_rules: Vec<SplitDnsRule>,
+    /// Lists of domains that are blocked, in addition to the default content blockers. Applies
+    /// regardless of `state`. Not supported on Android.
+    pub filter_lists: Vec<FilterListSource>,
 }
 
 /// Default DNS config
@@ -34,6 +42,10 @@ pub struct DefaultDnsOptions {
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, Hash)]
 pub struct CustomDnsOptions {
     pub addresses: Vec<IpAddr>,
+    /// Servers that are queried over DNS-over-TLS or DNS-over-HTTPS. If any are set, they are
+    /// used instead of `addresses`. Not supported on Android.
+    #[serde(default)]
+    pub encrypted_servers: Vec<EncryptedDnsServer>,
 }
 
 impl DefaultDnsOptions {
@@ -2,17 +2,34 @@ use crate::{
     access_method,
     constraints::Constraint,
     custom_list::CustomListsSettings,
+    custom_relay::CustomRelaySettings,
+    excluded_locations::ExcludedLocations,
+    lan_sharing::AllowedLan,
+    profile::ProfileSettings,
     relay_constraints::{
         BridgeSettings, BridgeState, GeographicLocationConstraint, LocationConstraint,
         ObfuscationSettings, RelayConstraints, RelayOverride, RelaySettings,
         RelaySettingsFormatter, SelectedObfuscation, WireguardConstraints,
     },
-    wireguard,
+    relay_list, wireguard,
+};
+#[cfg(not(target_os = "android"))]
+use crate::{
+    allowed_incoming::AllowedIncoming, firewall_exception::FirewallException,
+    state_hooks::StateHooks,
 };
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 #[cfg(any(windows, target_os = "android", target_os = "macos"))]
 use std::collections::HashSet;
+use std::time::Duration;
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::OfflineMonitorBackend;
 use talpid_types::net::{openvpn, GenericTunnelOptions};
+#[cfg(target_os = "linux")]
+use talpid_types::{
+    dns::DnsBackend,
+    split_tunnel::{SplitTunnelMode, SplitTunnelOwner},
+};
 
 mod dns;
 
@@ -20,7 +37,7 @@ mod dns;
 /// latest version that exists in `SettingsVersion`.
 /// This should be bumped when a new version is introduced along with a migration
 /// being added to `mullvad-daemon`.
-pub const CURRENT_SETTINGS_VERSION: SettingsVersion = SettingsVersion::V11;
+pub const CURRENT_SETTINGS_VERSION: SettingsVersion = SettingsVersion::V12;
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Clone, Copy)]
 #[repr(u32)]
@@ -35,6 +52,7 @@ pub enum SettingsVersion {
     V9 = 9,
     V10 = 10,
     V11 = 11,
+    V12 = 12,
 }
 
 impl<'de> Deserialize<'de> for SettingsVersion {
@@ -53,6 +71,7 @@ impl<'de> Deserialize<'de> for SettingsVersion {
             v if v == SettingsVersion::V9 as u32 => Ok(SettingsVersion::V9),
             v if v == SettingsVersion::V10 as u32 => Ok(SettingsVersion::V10),
             v if v == SettingsVersion::V11 as u32 => Ok(SettingsVersion::V11),
+            v if v == SettingsVersion::V12 as u32 => Ok(SettingsVersion::V12),
             v => Err(serde::de::Error::custom(format!(
                 "{v} is not a valid SettingsVersion"
             ))),
@@ -79,14 +98,39 @@ pub struct Settings {
     pub bridge_state: BridgeState,
     /// All of the custom relay lists
     pub custom_lists: CustomListsSettings,
+    /// WireGuard relays run by the user
+    pub custom_relays: CustomRelaySettings,
+    /// Locations that are never selected
+    pub excluded_locations: ExcludedLocations,
+    /// Named sets of settings that can be switched between
+    pub profiles: ProfileSettings,
     /// API access methods
     pub api_access_methods: access_method::Settings,
-    /// If the daemon should allow communication with private (LAN) networks.
-    pub allow_lan: bool,
+    /// Communication with private (LAN) networks that the daemon should allow. Must be valid
+    /// according to [`crate::lan_sharing::validate`].
+    pub allow_lan: AllowedLan,
     /// Extra level of kill switch. When this setting is on, the disconnected state will block
     /// the firewall to not allow any traffic in or out.
     #[cfg(not(target_os = "android"))]
     pub block_when_disconnected: bool,
+    /// Block all traffic, even when no tunnel is wanted. While this is enabled, the daemon stays
+    /// in the blocked state instead of connecting or disconnecting.
+    #[cfg(not(target_os = "android"))]
+    pub block_all: bool,
+    /// Traffic that is allowed outside the tunnel in every state. Must be valid according to
+    /// [`crate::firewall_exception::validate`].
+    #[cfg(not(target_os = "android"))]
+    pub firewall_exceptions: Vec<FirewallException>,
+    /// Incoming connections that are allowed in every state. Must be valid according to
+    /// [`crate::allowed_incoming::validate`].
+    #[cfg(not(target_os = "android"))]
+    pub allowed_incoming: Vec<AllowedIncoming>,
+    /// Executables to run when the tunnel enters a new state
+    #[cfg(not(target_os = "android"))]
+    pub state_hooks: StateHooks,
+    /// Serve metrics in the Prometheus format on localhost
+    #[cfg(not(target_os = "android"))]
+    pub metrics: MetricsSettings,
     /// If the daemon should connect the VPN tunnel directly on start or not.
     pub auto_connect: bool,
     /// Options that should be applied to tunnels of a specific type regardless of where the relays
@@ -94,15 +138,179 @@ pub struct Settings {
     pub tunnel_options: TunnelOptions,
     /// Overrides for relays
     pub relay_overrides: Vec<RelayOverride>,
+    /// Measure the latency to relays in the background, and prefer relays with low latency.
+    pub prefer_low_latency_relays: bool,
+    /// Keep using the relays that were last connected to for the selected locations, until
+    /// connecting to them fails.
+    pub sticky_relays: bool,
+    /// How often to download a new relay list, in minutes. Must be within
+    /// [`relay_list::MIN_UPDATE_INTERVAL_MINUTES`] and [`relay_list::MAX_UPDATE_INTERVAL_MINUTES`].
+    pub relay_list_update_interval_minutes: u16,
     /// Whether to notify users of beta updates.
     pub show_beta_releases: bool,
     /// Split tunneling settings
     #[cfg(any(windows, target_os = "android", target_os = "macos"))]
     pub split_tunnel: SplitTunnelSettings,
+    /// Whether the processes in the split tunnel cgroup bypass the tunnel, or are the only ones
+    /// that use it.
+    #[cfg(target_os = "linux")]
+    pub split_tunnel_mode: SplitTunnelMode,
+    /// Users and groups whose processes are split from the tunnel, in addition to the processes
+    /// in the split tunnel cgroup. Must be valid according to
+    /// [`crate::split_tunnel_owner::validate`].
+    #[cfg(target_os = "linux")]
+    pub split_tunnel_owners: Vec<SplitTunnelOwner>,
+    /// Domains whose traffic is routed outside the tunnel, along with their subdomains. Must be
+    /// valid according to [`crate::split_tunnel_domain::validate`].
+    #[cfg(target_os = "linux")]
+    pub split_tunnel_domains: Vec<String>,
+    /// Firewall mark used for tunnel traffic. [crate::TUNNEL_FWMARK] is used if this is not set.
+    /// Changes take effect when the daemon is restarted.
+    #[cfg(target_os = "linux")]
+    pub tunnel_fwmark: Option<u32>,
+    /// How the firewall rules are ordered relative to other nftables tables. Changes take effect
+    /// when the daemon is restarted.
+    #[cfg(target_os = "linux")]
+    pub nftables: NftablesSettings,
+    /// Routing table and rule priority used for policy routing. Changes take effect when the
+    /// daemon is restarted.
+    #[cfg(target_os = "linux")]
+    pub routing: RoutingSettings,
+    /// Weight of the WFP sublayers that contain the firewall filters. The highest possible weight
+    /// is used if this is not set. Changes take effect when the daemon is restarted.
+    #[cfg(windows)]
+    pub wfp_sublayer_weight: Option<u16>,
+    /// PF anchor that the firewall rules are installed in. If this is set, the main ruleset is
+    /// left untouched, and the anchor must be referenced from the user's own ruleset. Changes take
+    /// effect when the daemon is restarted.
+    #[cfg(target_os = "macos")]
+    pub pf_anchor: Option<String>,
+    /// Ways of managing the system DNS configuration to try, in order. If this is empty, the
+    /// backends are tried in [`DnsBackend::DEFAULT_ORDER`]. Must be valid according to
+    /// [`is_valid_dns_backend_order`]. Changes take effect when the daemon is restarted.
+    #[cfg(target_os = "linux")]
+    pub dns_backends: Vec<DnsBackend>,
+    /// Mechanism used to detect whether the device is offline. Changes take effect when the
+    /// daemon is restarted.
+    #[cfg(not(target_os = "android"))]
+    pub offline_monitor: OfflineMonitorBackend,
     /// Specifies settings schema version
     pub settings_version: SettingsVersion,
 }
 
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct MetricsSettings {
+    /// Whether to serve metrics
+    pub enabled: bool,
+    /// TCP port to listen on. Only connections from localhost are accepted.
+    pub port: u16,
+}
+
+impl MetricsSettings {
+    pub const DEFAULT_PORT: u16 = 9813;
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        MetricsSettings {
+            enabled: false,
+            port: Self::DEFAULT_PORT,
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct NftablesSettings {
+    /// Hook priority of the input, output and forward chains. Chains with a lower priority are
+    /// evaluated first. Must be within [`Self::MIN_PRIORITY`] and [`Self::MAX_PRIORITY`].
+    pub priority: i32,
+    /// Accept forwarded traffic that the firewall has no rule for, and leave it to other tables,
+    /// instead of dropping it.
+    pub cooperative: bool,
+}
+
+#[cfg(target_os = "linux")]
+impl NftablesSettings {
+    /// Packets from excluded apps are marked at priority -150, which must happen before they are
+    /// filtered.
+    pub const MIN_PRIORITY: i32 = -149;
+    pub const MAX_PRIORITY: i32 = 149;
+
+    pub fn is_valid(&self) -> bool {
+        (Self::MIN_PRIORITY..=Self::MAX_PRIORITY).contains(&self.priority)
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RoutingSettings {
+    /// Routing table that routes through the tunnel are added to. [crate::TUNNEL_TABLE_ID] is
+    /// used if this is not set.
+    pub table_id: Option<u32>,
+    /// Priority of the routing rules. Two rules are added, with this priority and the one after
+    /// it. If this is not set, the rules are added without a priority, and the kernel places
+    /// them just before the rule for the main table.
+    pub rule_priority: Option<u32>,
+}
+
+#[cfg(target_os = "linux")]
+impl RoutingSettings {
+    /// Priority 0 is reserved for the local table, and both rules must come before the rule for
+    /// the main table, at 32766.
+    pub const MIN_RULE_PRIORITY: u32 = 1;
+    pub const MAX_RULE_PRIORITY: u32 = 32764;
+
+    /// Tables that are either unspecified or reserved by the kernel: `default`, `main` and
+    /// `local`.
+    const RESERVED_TABLES: [u32; 4] = [0, 253, 254, 255];
+
+    /// Returns the routing table to add routes through the tunnel to.
+    pub fn table_id(&self) -> u32 {
+        self.table_id.unwrap_or(crate::TUNNEL_TABLE_ID)
+    }
+
+    pub fn is_valid_table_id(&self) -> bool {
+        self.table_id
+            .map_or(true, |table_id| !Self::RESERVED_TABLES.contains(&table_id))
+    }
+
+    pub fn is_valid_rule_priority(&self) -> bool {
+        self.rule_priority.map_or(true, |priority| {
+            (Self::MIN_RULE_PRIORITY..=Self::MAX_RULE_PRIORITY).contains(&priority)
+        })
+    }
+}
+
+/// Maximum length of each component of a PF anchor path.
+#[cfg(target_os = "macos")]
+pub const MAX_PF_ANCHOR_NAME_LEN: usize = 63;
+
+/// Returns whether `anchor` is a valid PF anchor path, such as `com.apple/mullvad`. Names that
+/// begin with an underscore are reserved by PF.
+#[cfg(target_os = "macos")]
+pub fn is_valid_pf_anchor(anchor: &str) -> bool {
+    anchor.split('/').all(|name| {
+        !name.is_empty()
+            && name.len() <= MAX_PF_ANCHOR_NAME_LEN
+            && !name.starts_with('_')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    })
+}
+
+/// Returns whether `backends` is a valid order of DNS backends, i.e. that no backend is listed more
+/// than once.
+#[cfg(target_os = "linux")]
+pub fn is_valid_dns_backend_order(backends: &[DnsBackend]) -> bool {
+    let unique: HashSet<_> = backends.iter().collect();
+    unique.len() == backends.len()
+}
+
 #[cfg(any(windows, target_os = "android", target_os = "macos"))]
 #[derive(Debug, Clone, Default, Deserialize, Serialize, PartialEq)]
 pub struct SplitTunnelSettings {
@@ -201,16 +409,52 @@ impl Default for Settings {
             },
             bridge_state: BridgeState::Auto,
             custom_lists: CustomListsSettings::default(),
+            custom_relays: CustomRelaySettings::default(),
+            excluded_locations: ExcludedLocations::default(),
+            profiles: ProfileSettings::default(),
             api_access_methods: access_method::Settings::default(),
-            allow_lan: false,
+            allow_lan: AllowedLan::Blocked,
             #[cfg(not(target_os = "android"))]
             block_when_disconnected: false,
+            #[cfg(not(target_os = "android"))]
+            block_all: false,
+            #[cfg(not(target_os = "android"))]
+            firewall_exceptions: vec![],
+            #[cfg(not(target_os = "android"))]
+            allowed_incoming: vec![],
+            #[cfg(not(target_os = "android"))]
+            state_hooks: StateHooks::default(),
+            #[cfg(not(target_os = "android"))]
+            metrics: MetricsSettings::default(),
             auto_connect: false,
             tunnel_options: TunnelOptions::default(),
             relay_overrides: vec![],
+            prefer_low_latency_relays: false,
+            sticky_relays: false,
+            relay_list_update_interval_minutes: relay_list::DEFAULT_UPDATE_INTERVAL_MINUTES,
             show_beta_releases: false,
             #[cfg(any(windows, target_os = "android", target_os = "macos"))]
             split_tunnel: SplitTunnelSettings::default(),
+            #[cfg(target_os = "linux")]
+            split_tunnel_mode: SplitTunnelMode::default(),
+            #[cfg(target_os = "linux")]
+            split_tunnel_owners: vec![],
+            #[cfg(target_os = "linux")]
+            split_tunnel_domains: vec![],
+            #[cfg(target_os = "linux")]
+            tunnel_fwmark: None,
+            #[cfg(target_os = "linux")]
+            nftables: NftablesSettings::default(),
+            #[cfg(target_os = "linux")]
+            routing: RoutingSettings::default(),
+            #[cfg(windows)]
+            wfp_sublayer_weight: None,
+            #[cfg(target_os = "macos")]
+            pf_anchor: None,
+            #[cfg(target_os = "linux")]
+            dns_backends: vec![],
+            #[cfg(not(target_os = "android"))]
+            offline_monitor: OfflineMonitorBackend::default(),
             settings_version: CURRENT_SETTINGS_VERSION,
         }
     }
@@ -243,6 +487,21 @@ impl Settings {
         }
     }
 
+    /// Returns how long to wait between relay list updates. Out of range values are clamped.
+    pub fn relay_list_update_interval(&self) -> Duration {
+        let minutes = self.relay_list_update_interval_minutes.clamp(
+            relay_list::MIN_UPDATE_INTERVAL_MINUTES,
+            relay_list::MAX_UPDATE_INTERVAL_MINUTES,
+        );
+        Duration::from_secs(u64::from(minutes) * 60)
+    }
+
+    /// Returns the firewall mark to use for tunnel traffic.
+    #[cfg(target_os = "linux")]
+    pub fn tunnel_fwmark(&self) -> u32 {
+        self.tunnel_fwmark.unwrap_or(crate::TUNNEL_FWMARK)
+    }
+
     pub fn set_relay_override(&mut self, relay_override: RelayOverride) {
         let existing_override = self
             .relay_overrides
@@ -286,6 +545,8 @@ impl Default for TunnelOptions {
             generic: GenericTunnelOptions {
                 // Enable IPv6 by default on Android and macOS
                 enable_ipv6: cfg!(target_os = "android") || cfg!(target_os = "macos"),
+                block_ipv6: false,
+                custom_routes: vec![],
             },
             dns_options: DnsOptions::default(),
         }
@@ -0,0 +1,117 @@
+//! Split DNS sends queries for specific domains to DNS servers on the local network, while all
+//! other queries use the regular DNS servers inside the tunnel.
+
+use std::{collections::HashSet, net::IpAddr};
+pub use talpid_types::dns::SplitDnsRule;
+use talpid_types::net::ALLOWED_LAN_NETS;
+
+/// Longest domain name allowed by RFC 1035, excluding the trailing dot.
+const MAX_DOMAIN_LEN: usize = 253;
+/// Longest label allowed by RFC 1035.
+const MAX_LABEL_LEN: usize = 63;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("Invalid split DNS domain: {0}")]
+    InvalidDomain(String),
+    #[error("Split DNS domain {0} has no servers")]
+    NoServers(String),
+    #[error("Split DNS server {0} is not on a private network")]
+    NotPrivate(IpAddr),
+    #[error("Split DNS domain {0} was added more than once")]
+    Duplicate(String),
+}
+
+/// Check that `rules` can be applied to the system DNS configuration.
+pub fn validate(rules: &[SplitDnsRule]) -> Result<(), Error> {
+    let mut seen = HashSet::new();
+    for rule in rules {
+        if !is_valid_domain(&rule.domain) {
+            return Err(Error::InvalidDomain(rule.domain.clone()));
+        }
+        if rule.servers.is_empty() {
+            return Err(Error::NoServers(rule.domain.clone()));
+        }
+        if let Some(server) = rule
+            .servers
+            .iter()
+            .find(|server| !ALLOWED_LAN_NETS.iter().any(|net| net.contains(**server)))
+        {
+            return Err(Error::NotPrivate(*server));
+        }
+        if !seen.insert(rule.domain.to_ascii_lowercase()) {
+            return Err(Error::Duplicate(rule.domain.clone()));
+        }
+    }
+    Ok(())
+}
+
+/// Returns whether `domain` is a valid hostname, without wildcards or a trailing dot.
+pub(crate) fn is_valid_domain(domain: &str) -> bool {
+    domain.len() <= MAX_DOMAIN_LEN
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= MAX_LABEL_LEN
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rule(domain: &str, servers: &[&str]) -> SplitDnsRule {
+        SplitDnsRule {
+            domain: domain.to_owned(),
+            servers: servers
+                .iter()
+                .map(|server| server.parse().unwrap())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate(&[]), Ok(()));
+
+        let corp = rule("corp.example", &["10.0.0.53"]);
+        let home = rule("home.arpa", &["192.168.1.1", "fd00::1"]);
+        assert_eq!(validate(&[corp.clone(), home]), Ok(()));
+
+        assert_eq!(
+            validate(&[corp.clone(), rule("CORP.example", &["10.0.0.54"])]),
+            Err(Error::Duplicate("CORP.example".to_owned()))
+        );
+
+        for domain in [
+            "",
+            "*.corp.example",
+            "corp.example.",
+            "-corp.example",
+            "corp..example",
+        ] {
+            assert_eq!(
+                validate(&[rule(domain, &["10.0.0.53"])]),
+                Err(Error::InvalidDomain(domain.to_owned()))
+            );
+        }
+
+        assert_eq!(
+            validate(&[rule("corp.example", &[])]),
+            Err(Error::NoServers("corp.example".to_owned()))
+        );
+
+        assert_eq!(
+            validate(&[rule("corp.example", &["10.0.0.53", "8.8.8.8"])]),
+            Err(Error::NotPrivate("8.8.8.8".parse().unwrap()))
+        );
+        assert_eq!(
+            validate(&[rule("corp.example", &["127.0.0.1"])]),
+            Err(Error::NotPrivate("127.0.0.1".parse().unwrap()))
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! Domains whose traffic is split from the tunnel. The local DNS forwarder routes the addresses
+//! that these domains resolve to outside the tunnel, until the TTL of their records expires.
+
+use crate::split_dns::is_valid_domain;
+use std::collections::HashSet;
+
+/// Largest number of domains that may be split from the tunnel.
+pub const MAX_SPLIT_TUNNEL_DOMAINS: usize = 256;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("At most {MAX_SPLIT_TUNNEL_DOMAINS} domains may be split from the tunnel")]
+    TooMany,
+    #[error("Invalid split tunnel domain: {0}")]
+    InvalidDomain(String),
+    #[error("Split tunnel domain {0} was added more than once")]
+    Duplicate(String),
+}
+
+/// Check that `domains` can be passed to the DNS forwarder.
+pub fn validate(domains: &[String]) -> Result<(), Error> {
+    if domains.len() > MAX_SPLIT_TUNNEL_DOMAINS {
+        return Err(Error::TooMany);
+    }
+    let mut seen = HashSet::new();
+    for domain in domains {
+        if !is_valid_domain(domain) {
+            return Err(Error::InvalidDomain(domain.clone()));
+        }
+        if !seen.insert(domain.to_ascii_lowercase()) {
+            return Err(Error::Duplicate(domain.clone()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let domains = |domains: &[&str]| -> Vec<String> {
+            domains.iter().map(|domain| domain.to_string()).collect()
+        };
+
+        assert_eq!(validate(&[]), Ok(()));
+        assert_eq!(
+            validate(&domains(&["netflix.com", "video.example.org"])),
+            Ok(())
+        );
+        assert_eq!(
+            validate(&domains(&["netflix.com", "Netflix.com"])),
+            Err(Error::Duplicate("Netflix.com".to_owned()))
+        );
+        assert_eq!(
+            validate(&domains(&["*.netflix.com"])),
+            Err(Error::InvalidDomain("*.netflix.com".to_owned()))
+        );
+
+        let too_many: Vec<_> = (0..=MAX_SPLIT_TUNNEL_DOMAINS)
+            .map(|i| format!("host{i}.example.com"))
+            .collect();
+        assert_eq!(validate(&too_many), Err(Error::TooMany));
+    }
+}
@@ -0,0 +1,67 @@
+//! Users and groups whose processes are split from the tunnel, like the processes in the split
+//! tunnel cgroup. This makes it possible to set up a dedicated user whose traffic never uses the
+//! tunnel.
+
+use std::collections::HashSet;
+pub use talpid_types::split_tunnel::SplitTunnelOwner;
+
+/// Largest number of users and groups that may be split from the tunnel.
+pub const MAX_SPLIT_TUNNEL_OWNERS: usize = 32;
+
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    #[error("At most {MAX_SPLIT_TUNNEL_OWNERS} users and groups may be split from the tunnel")]
+    TooMany,
+    #[error("{0} is root, which the daemon and most system services run as")]
+    Root(SplitTunnelOwner),
+    #[error("{0} was added more than once")]
+    Duplicate(SplitTunnelOwner),
+}
+
+/// Check that `owners` can be applied to the firewall.
+pub fn validate(owners: &[SplitTunnelOwner]) -> Result<(), Error> {
+    if owners.len() > MAX_SPLIT_TUNNEL_OWNERS {
+        return Err(Error::TooMany);
+    }
+    let mut seen = HashSet::new();
+    for owner in owners {
+        if matches!(
+            owner,
+            SplitTunnelOwner::User(0) | SplitTunnelOwner::Group(0)
+        ) {
+            return Err(Error::Root(*owner));
+        }
+        if !seen.insert(owner) {
+            return Err(Error::Duplicate(*owner));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let user = SplitTunnelOwner::User(1001);
+        let group = SplitTunnelOwner::Group(1001);
+
+        assert_eq!(validate(&[]), Ok(()));
+        assert_eq!(validate(&[user, group]), Ok(()));
+        assert_eq!(validate(&[user, user]), Err(Error::Duplicate(user)));
+        assert_eq!(
+            validate(&[SplitTunnelOwner::User(0)]),
+            Err(Error::Root(SplitTunnelOwner::User(0)))
+        );
+        assert_eq!(
+            validate(&[SplitTunnelOwner::Group(0)]),
+            Err(Error::Root(SplitTunnelOwner::Group(0)))
+        );
+
+        let too_many: Vec<_> = (1..=MAX_SPLIT_TUNNEL_OWNERS as u32 + 1)
+            .map(SplitTunnelOwner::User)
+            .collect();
+        assert_eq!(validate(&too_many), Err(Error::TooMany));
+    }
+}
@@ -0,0 +1,55 @@
+//! Executables that the daemon runs when the tunnel enters a new state, e.g. to update firewall
+//! rules or a status bar.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Tunnel states that hooks can be run for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Connected,
+    Disconnected,
+    Error,
+}
+
+impl fmt::Display for HookEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookEvent::Connected => f.write_str("connected"),
+            HookEvent::Disconnected => f.write_str("disconnected"),
+            HookEvent::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// Paths of the executables to run for each state. The daemon only runs executables that can
+/// only be modified by administrators.
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateHooks {
+    pub connected: Option<PathBuf>,
+    pub disconnected: Option<PathBuf>,
+    pub error: Option<PathBuf>,
+}
+
+impl StateHooks {
+    pub fn get(&self, event: HookEvent) -> Option<&Path> {
+        match event {
+            HookEvent::Connected => self.connected.as_deref(),
+            HookEvent::Disconnected => self.disconnected.as_deref(),
+            HookEvent::Error => self.error.as_deref(),
+        }
+    }
+
+    pub fn set(&mut self, event: HookEvent, path: Option<PathBuf>) {
+        let hook = match event {
+            HookEvent::Connected => &mut self.connected,
+            HookEvent::Disconnected => &mut self.disconnected,
+            HookEvent::Error => &mut self.error,
+        };
+        *hook = path;
+    }
+}
@@ -1,10 +1,10 @@
 use crate::{features::FeatureIndicators, location::GeoIpLocation};
 use either::Either;
 use serde::{Deserialize, Serialize};
-use std::fmt;
+use std::{fmt, time::Duration};
 use talpid_types::{
     net::{TunnelEndpoint, TunnelType},
-    tunnel::{ActionAfterDisconnect, ErrorState},
+    tunnel::{ActionAfterDisconnect, ErrorState, TrafficStats},
 };
 
 /// Represents the state the client strives towards.
@@ -86,6 +86,8 @@ pub enum TunnelState {
         feature_indicators: FeatureIndicators,
     },
     Disconnecting(ActionAfterDisconnect),
+    /// The tunnel is down, but all traffic is blocked until the user connects or disconnects.
+    Paused,
     Error(ErrorState),
 }
 
@@ -105,6 +107,11 @@ impl TunnelState {
         matches!(self, TunnelState::Disconnected { .. })
     }
 
+    /// Returns true if the tunnel state is in the paused state.
+    pub const fn is_paused(&self) -> bool {
+        matches!(self, TunnelState::Paused)
+    }
+
     /// Returns the tunnel endpoint for an active connection.
     /// This value exists in the connecting and connected states.
     pub const fn endpoint(&self) -> Option<&TunnelEndpoint> {
@@ -125,3 +132,13 @@ impl TunnelState {
         }
     }
 }
+
+/// Statistics about the current tunnel, sent periodically to clients that listen for them.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TunnelStats {
+    pub traffic: TrafficStats,
+    /// Time since the most recent handshake with the exit peer, if any.
+    pub handshake_age: Option<Duration>,
+    /// Host name of the exit relay, if known.
+    pub relay: Option<String>,
+}
@@ -0,0 +1,202 @@
+//! Rules that decide whether the daemon should connect or disconnect when the device joins a
+//! network, e.g. to stay disconnected on the home Wi-Fi but always connect on unknown Wi-Fi.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Maximum length of an SSID, in bytes.
+pub const SSID_MAX_SIZE: usize = 32;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Invalid SSID")]
+    InvalidSsid,
+    #[error("No rule exists for the network")]
+    NotFound,
+}
+
+/// A network that the device is connected to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Network {
+    Wifi { ssid: String },
+    Ethernet,
+}
+
+impl fmt::Display for Network {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Network::Wifi { ssid } => write!(f, "Wi-Fi \"{ssid}\""),
+            Network::Ethernet => f.write_str("Ethernet"),
+        }
+    }
+}
+
+/// The networks that a rule applies to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkMatch {
+    /// The Wi-Fi network with the given SSID.
+    Ssid(String),
+    /// Any Wi-Fi network that does not have a rule of its own.
+    UnknownWifi,
+    /// Any wired network.
+    Ethernet,
+}
+
+impl fmt::Display for NetworkMatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkMatch::Ssid(ssid) => write!(f, "Wi-Fi \"{ssid}\""),
+            NetworkMatch::UnknownWifi => f.write_str("Unknown Wi-Fi"),
+            NetworkMatch::Ethernet => f.write_str("Ethernet"),
+        }
+    }
+}
+
+/// What to do when joining a network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NetworkAction {
+    /// Disconnect, and do not connect automatically while on the network.
+    Disconnect,
+    /// Connect.
+    Connect,
+    /// Connect, and block all traffic while disconnected as long as the device is on the
+    /// network, regardless of the lockdown mode setting.
+    ConnectAndLockdown,
+}
+
+impl fmt::Display for NetworkAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NetworkAction::Disconnect => f.write_str("disconnect"),
+            NetworkAction::Connect => f.write_str("connect"),
+            NetworkAction::ConnectAndLockdown => f.write_str("connect and lockdown"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedNetworkRule {
+    pub network: NetworkMatch,
+    pub action: NetworkAction,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedNetworks {
+    rules: Vec<TrustedNetworkRule>,
+}
+
+impl From<Vec<TrustedNetworkRule>> for TrustedNetworks {
+    fn from(rules: Vec<TrustedNetworkRule>) -> Self {
+        Self { rules }
+    }
+}
+
+impl TrustedNetworks {
+    /// Add `rule`, replacing any existing rule for the same networks.
+    pub fn set(&mut self, rule: TrustedNetworkRule) -> Result<(), Error> {
+        if let NetworkMatch::Ssid(ssid) = &rule.network {
+            if ssid.is_empty() || ssid.len() > SSID_MAX_SIZE {
+                return Err(Error::InvalidSsid);
+            }
+        }
+        match self
+            .rules
+            .iter_mut()
+            .find(|existing| existing.network == rule.network)
+        {
+            Some(existing) => *existing = rule,
+            None => self.rules.push(rule),
+        }
+        Ok(())
+    }
+
+    pub fn remove(&mut self, network: &NetworkMatch) -> Result<(), Error> {
+        let index = self
+            .rules
+            .iter()
+            .position(|rule| rule.network == *network)
+            .ok_or(Error::NotFound)?;
+        self.rules.remove(index);
+        Ok(())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &TrustedNetworkRule> {
+        self.rules.iter()
+    }
+
+    /// Returns the action to take on `network`, if any rule applies to it. A rule for the SSID of
+    /// a Wi-Fi network takes precedence over the rule for unknown Wi-Fi networks.
+    pub fn action_for(&self, network: &Network) -> Option<NetworkAction> {
+        let find = |network: &NetworkMatch| {
+            self.rules
+                .iter()
+                .find(|rule| rule.network == *network)
+                .map(|rule| rule.action)
+        };
+        match network {
+            Network::Wifi { ssid } => {
+                find(&NetworkMatch::Ssid(ssid.clone())).or_else(|| find(&NetworkMatch::UnknownWifi))
+            }
+            Network::Ethernet => find(&NetworkMatch::Ethernet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_action_for() {
+        let mut networks = TrustedNetworks::default();
+        let home = Network::Wifi {
+            ssid: "home".to_owned(),
+        };
+        let cafe = Network::Wifi {
+            ssid: "cafe".to_owned(),
+        };
+        assert_eq!(networks.action_for(&home), None);
+
+        networks
+            .set(TrustedNetworkRule {
+                network: NetworkMatch::UnknownWifi,
+                action: NetworkAction::ConnectAndLockdown,
+            })
+            .unwrap();
+        networks
+            .set(TrustedNetworkRule {
+                network: NetworkMatch::Ssid("home".to_owned()),
+                action: NetworkAction::Disconnect,
+            })
+            .unwrap();
+        assert_eq!(networks.action_for(&home), Some(NetworkAction::Disconnect));
+        assert_eq!(
+            networks.action_for(&cafe),
+            Some(NetworkAction::ConnectAndLockdown)
+        );
+        assert_eq!(networks.action_for(&Network::Ethernet), None);
+
+        networks
+            .remove(&NetworkMatch::Ssid("home".to_owned()))
+            .unwrap();
+        assert_eq!(
+            networks.action_for(&home),
+            Some(NetworkAction::ConnectAndLockdown)
+        );
+    }
+
+    #[test]
+    fn test_invalid_ssid() {
+        let mut networks = TrustedNetworks::default();
+        for ssid in [String::new(), "x".repeat(SSID_MAX_SIZE + 1)] {
+            let rule = TrustedNetworkRule {
+                network: NetworkMatch::Ssid(ssid),
+                action: NetworkAction::Connect,
+            };
+            assert!(matches!(networks.set(rule), Err(Error::InvalidSsid)));
+        }
+    }
+}
@@ -89,6 +89,10 @@ pub struct DaitaSettings {
     /// Whether to use multihop if the selected relay is not DAITA-compatible. Note that this is
     /// the inverse of of "Direct only" in the GUI.
     pub use_multihop_if_necessary: bool,
+
+    /// Limits on the padding, blocking and machines used by DAITA.
+    #[serde(default)]
+    pub parameters: wireguard::DaitaParameters,
 }
 
 #[cfg(daita)]
@@ -105,6 +109,7 @@ impl Default for DaitaSettings {
         Self {
             enabled: false,
             use_multihop_if_necessary: Self::default_use_multihop_if_necessary(),
+            parameters: wireguard::DaitaParameters::default(),
         }
     }
 }
@@ -119,11 +124,12 @@ pub struct WireguardData {
 }
 
 impl WireguardData {
-    /// Create a public key
-    pub fn get_public_key(&self) -> PublicKey {
+    /// Create a public key, which is due to be rotated once `rotation_interval` has passed
+    pub fn get_public_key(&self, rotation_interval: RotationInterval) -> PublicKey {
         PublicKey {
             key: self.private_key.public_key(),
             created: self.created,
+            next_rotation: self.created + *rotation_interval.as_duration(),
         }
     }
 }
@@ -244,8 +250,32 @@ impl Default for RotationInterval {
 pub struct TunnelOptions {
     /// MTU for the wireguard tunnel
     pub mtu: Option<u16>,
+    /// Interval in seconds at which keepalive packets are sent to the relay. Helps keep NAT
+    /// mappings alive on routers that expire them quickly.
+    pub persistent_keepalive: Option<u16>,
+    /// Send the encrypted tunnel traffic out through this network interface, instead of the one
+    /// used by the default route.
+    #[cfg(target_os = "linux")]
+    pub outgoing_interface: Option<String>,
+    /// Pin the WireGuard implementation instead of letting the daemon pick one. If it cannot be
+    /// used, another one is picked as if this was unset.
+    #[cfg(target_os = "linux")]
+    pub backend: Option<wireguard::Backend>,
+    /// Number of threads the userspace WireGuard implementation processes packets on. By default,
+    /// one per CPU is used.
+    pub worker_threads: Option<u32>,
     /// Obtain a PSK using the relay config client.
     pub quantum_resistant: QuantumResistantState,
+    /// Key encapsulation mechanisms that the PSK is derived from
+    pub quantum_resistant_kem: wireguard::QuantumResistantKem,
+    /// Timeouts and retries used when negotiating an ephemeral peer for PQ or DAITA
+    pub negotiation_retry_policy: wireguard::NegotiationRetryPolicy,
+    /// Cadence and target of the pings used to detect a broken tunnel
+    pub connectivity_check: wireguard::ConnectivityCheckOptions,
+    /// Only assign an IPv6 address inside the tunnel. IPv4 hosts are reached by name through the
+    /// DNS64 and NAT64 service of the relay, but not by IPv4 address. This implies that IPv6 is
+    /// enabled.
+    pub ipv6_only: bool,
     /// Configure DAITA
     #[cfg(daita)]
     pub daita: DaitaSettings,
@@ -258,7 +288,17 @@ impl Default for TunnelOptions {
     fn default() -> Self {
         TunnelOptions {
             mtu: None,
+            persistent_keepalive: None,
+            #[cfg(target_os = "linux")]
+            outgoing_interface: None,
+            #[cfg(target_os = "linux")]
+            backend: None,
+            worker_threads: None,
             quantum_resistant: QuantumResistantState::Auto,
+            quantum_resistant_kem: wireguard::QuantumResistantKem::Auto,
+            negotiation_retry_policy: wireguard::NegotiationRetryPolicy::default(),
+            connectivity_check: wireguard::ConnectivityCheckOptions::default(),
+            ipv6_only: false,
             #[cfg(daita)]
             daita: DaitaSettings::default(),
             rotation_interval: None,
@@ -270,9 +310,21 @@ impl TunnelOptions {
     pub fn into_talpid_tunnel_options(self) -> wireguard::TunnelOptions {
         wireguard::TunnelOptions {
             mtu: self.mtu,
+            persistent_keepalive: self.persistent_keepalive,
+            #[cfg(target_os = "linux")]
+            outgoing_interface: self.outgoing_interface,
+            #[cfg(target_os = "linux")]
+            backend: self.backend,
+            worker_threads: self.worker_threads,
             quantum_resistant: self.quantum_resistant.enabled(),
+            quantum_resistant_kem: self.quantum_resistant_kem,
+            negotiation_retry_policy: self.negotiation_retry_policy,
+            connectivity_check: self.connectivity_check,
+            ipv6_only: self.ipv6_only,
             #[cfg(daita)]
             daita: self.daita.enabled,
+            #[cfg(daita)]
+            daita_parameters: self.daita.parameters,
         }
     }
 }
@@ -282,6 +334,8 @@ impl TunnelOptions {
 pub struct PublicKey {
     pub key: wireguard::PublicKey,
     pub created: DateTime<Utc>,
+    /// Time at which the key is due to be replaced by automatic key rotation
+    pub next_rotation: DateTime<Utc>,
 }
 
 /// Contains a pair of local link addresses that are paired with a specific wireguard
@@ -8,6 +8,7 @@ use tokio::{process::Command, time::timeout};
 
 use crate::{
     fetch::{self, ProgressUpdater},
+    provenance::{self, ProvenanceParameters, ProvenanceVerifier, TrustedBuilder},
     verify::{AppVerifier, Sha256Verifier},
 };
 
@@ -15,6 +16,8 @@ use crate::{
 pub enum DownloadError {
     #[error("Failed to download app")]
     FetchApp(#[source] anyhow::Error),
+    #[error("Failed to download provenance attestation")]
+    FetchAttestation(#[source] anyhow::Error),
     #[error("Failed to verify app")]
     Verification(#[source] anyhow::Error),
     #[error("Failed to launch app")]
@@ -33,11 +36,20 @@ pub struct AppDownloaderParameters<AppProgress> {
     pub app_size: usize,
     pub app_progress: AppProgress,
     pub app_sha256: [u8; 32],
+    /// If set, the installer must also match a build provenance attestation.
+    pub app_provenance: Option<AppProvenance>,
     /// Directory to store the installer in.
     /// Ensure that this has proper permissions set.
     pub cache_dir: PathBuf,
 }
 
+/// Where to find the provenance attestation for an installer, and whom to trust as its builder.
+#[derive(Clone)]
+pub struct AppProvenance {
+    pub attestation_url: String,
+    pub trusted_builders: Vec<TrustedBuilder>,
+}
+
 /// See the [module-level documentation](self).
 #[async_trait::async_trait]
 pub trait AppDownloader: Send {
@@ -91,17 +103,49 @@ impl<AppProgress: ProgressUpdater> AppDownloader for HttpAppDownloader<AppProgre
             fetch::SizeHint::Exact(self.params.app_size),
         )
         .await
-        .map_err(DownloadError::FetchApp)
+        .map_err(DownloadError::FetchApp)?;
+
+        if let Some(app_provenance) = &self.params.app_provenance {
+            let attestation_path = self.attestation_path();
+            // Do not append to a partial or stale attestation
+            let _ = tokio::fs::remove_file(&attestation_path).await;
+            fetch::get_to_file(
+                attestation_path,
+                &app_provenance.attestation_url,
+                &mut SilentProgress,
+                fetch::SizeHint::Maximum(provenance::MAX_ATTESTATION_SIZE),
+            )
+            .await
+            .map_err(DownloadError::FetchAttestation)?;
+        }
+
+        Ok(())
     }
 
     async fn verify(&mut self) -> Result<(), DownloadError> {
         let bin_path = self.bin_path();
-        let hash = self.hash_sha256();
+        let hash = *self.hash_sha256();
+        let provenance =
+            self.params
+                .app_provenance
+                .as_ref()
+                .map(|app_provenance| ProvenanceParameters {
+                    attestation_path: self.attestation_path(),
+                    trusted_builders: app_provenance.trusted_builders.clone(),
+                });
+
+        let result = async {
+            Sha256Verifier::verify(&bin_path, hash).await?;
+            if let Some(parameters) = provenance {
+                ProvenanceVerifier::verify(&bin_path, parameters).await?;
+            }
 
-        match Sha256Verifier::verify(&bin_path, *hash)
-            .await
-            .map_err(DownloadError::Verification)
-        {
+            Ok(())
+        }
+        .await
+        .map_err(DownloadError::Verification);
+
+        match result {
             // Verification succeeded
             Ok(()) => Ok(()),
             // Verification failed
@@ -135,6 +179,17 @@ impl<AppProgress: ProgressUpdater> AppDownloader for HttpAppDownloader<AppProgre
     }
 }
 
+/// Progress updater for auxiliary downloads that should not be reflected in the UI
+struct SilentProgress;
+
+impl ProgressUpdater for SilentProgress {
+    fn set_progress(&mut self, _fraction_complete: f32) {}
+
+    fn clear_progress(&mut self) {}
+
+    fn set_url(&mut self, _url: &str) {}
+}
+
 impl<AppProgress> HttpAppDownloader<AppProgress> {
     fn bin_path(&self) -> PathBuf {
         #[cfg(windows)]
@@ -146,6 +201,12 @@ impl<AppProgress> HttpAppDownloader<AppProgress> {
         self.params.cache_dir.join(bin_filename)
     }
 
+    fn attestation_path(&self) -> PathBuf {
+        let mut path = self.bin_path().into_os_string();
+        path.push(".intoto.json");
+        PathBuf::from(path)
+    }
+
     fn launch_path(&self) -> PathBuf {
         #[cfg(target_os = "windows")]
         {
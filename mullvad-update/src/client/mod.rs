@@ -1,4 +1,5 @@
 pub mod api;
 pub mod app;
 pub mod fetch;
+pub mod provenance;
 pub mod verify;
@@ -0,0 +1,257 @@
+//! Verification of build provenance attestations.
+//!
+//! An attestation is a [DSSE] envelope whose payload is an [in-toto statement] with a
+//! [SLSA provenance] predicate. It is accepted if it is signed by one of the trusted builders,
+//! names that same builder in the predicate, and lists the installer's SHA-256 digest as a subject.
+//!
+//! [DSSE]: https://github.com/secure-systems-lab/dsse/blob/master/envelope.md
+//! [in-toto statement]: https://github.com/in-toto/attestation/blob/main/spec/v1/statement.md
+//! [SLSA provenance]: https://slsa.dev/spec/v1.0/provenance
+
+use std::{collections::BTreeMap, future::Future, path::PathBuf};
+
+use anyhow::{bail, Context};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use serde::Deserialize;
+use tokio::{fs, io::BufReader};
+
+use crate::{format::key::VerifyingKey, verify::AppVerifier};
+
+/// Payload type of in-toto statements in DSSE envelopes
+const IN_TOTO_PAYLOAD_TYPE: &str = "application/vnd.in-toto+json";
+const STATEMENT_TYPE: &str = "https://in-toto.io/Statement/v1";
+const SLSA_PROVENANCE_TYPE: &str = "https://slsa.dev/provenance/v1";
+
+/// Upper bound on the size of an attestation
+pub const MAX_ATTESTATION_SIZE: usize = 1024 * 1024;
+
+/// A builder whose attestations are trusted
+#[derive(Debug, Clone)]
+pub struct TrustedBuilder {
+    /// Builder ID, as it appears in `runDetails.builder.id` of the provenance predicate
+    pub id: String,
+    /// Key used by the builder to sign attestations
+    pub key: VerifyingKey,
+}
+
+/// Parameters for [ProvenanceVerifier]
+#[derive(Debug, Clone)]
+pub struct ProvenanceParameters {
+    /// Path to the DSSE envelope containing the attestation
+    pub attestation_path: PathBuf,
+    /// Builders to accept attestations from
+    pub trusted_builders: Vec<TrustedBuilder>,
+}
+
+/// Verifier that checks an installer against its build provenance attestation
+#[derive(Clone)]
+pub struct ProvenanceVerifier;
+
+impl AppVerifier for ProvenanceVerifier {
+    type Parameters = ProvenanceParameters;
+
+    fn verify(
+        bin_path: impl AsRef<std::path::Path>,
+        parameters: Self::Parameters,
+    ) -> impl Future<Output = anyhow::Result<()>> {
+        let bin_path = bin_path.as_ref().to_owned();
+
+        async move {
+            let attestation = fs::read(&parameters.attestation_path)
+                .await
+                .context("Failed to read attestation")?;
+
+            let file = fs::File::open(&bin_path)
+                .await
+                .context(format!("Failed to open file at {}", bin_path.display()))?;
+            let digest = crate::hash::checksum(BufReader::new(file)).await?;
+
+            verify_attestation(&attestation, &digest, &parameters.trusted_builders)
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Envelope {
+    payload_type: String,
+    /// Base64-encoded statement
+    payload: String,
+    signatures: Vec<EnvelopeSignature>,
+}
+
+#[derive(Deserialize)]
+struct EnvelopeSignature {
+    /// Base64-encoded signature of the pre-authentication encoding of the payload
+    sig: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Statement {
+    #[serde(rename = "_type")]
+    statement_type: String,
+    subject: Vec<Subject>,
+    predicate_type: String,
+    predicate: Predicate,
+}
+
+#[derive(Deserialize)]
+struct Subject {
+    /// Map of digest algorithms to hex-encoded digests
+    digest: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Predicate {
+    run_details: RunDetails,
+}
+
+#[derive(Deserialize)]
+struct RunDetails {
+    builder: Builder,
+}
+
+#[derive(Deserialize)]
+struct Builder {
+    id: String,
+}
+
+/// Verify that `attestation` is a valid provenance attestation for an artifact with the SHA-256
+/// digest `artifact_sha256`, produced by one of `trusted_builders`.
+fn verify_attestation(
+    attestation: &[u8],
+    artifact_sha256: &[u8; 32],
+    trusted_builders: &[TrustedBuilder],
+) -> anyhow::Result<()> {
+    let envelope: Envelope =
+        serde_json::from_slice(attestation).context("Invalid attestation envelope")?;
+    if envelope.payload_type != IN_TOTO_PAYLOAD_TYPE {
+        bail!("Unexpected payload type: {}", envelope.payload_type);
+    }
+    let payload = BASE64
+        .decode(&envelope.payload)
+        .context("Invalid attestation payload encoding")?;
+
+    let builder = find_signer(&envelope, &payload, trusted_builders)
+        .context("Attestation is not signed by a trusted builder")?;
+
+    let statement: Statement =
+        serde_json::from_slice(&payload).context("Invalid in-toto statement")?;
+    if statement.statement_type != STATEMENT_TYPE {
+        bail!("Unexpected statement type: {}", statement.statement_type);
+    }
+    if statement.predicate_type != SLSA_PROVENANCE_TYPE {
+        bail!("Unexpected predicate type: {}", statement.predicate_type);
+    }
+    if statement.predicate.run_details.builder.id != builder.id {
+        bail!(
+            "Attestation signed by {} names a different builder: {}",
+            builder.id,
+            statement.predicate.run_details.builder.id
+        );
+    }
+
+    let expected_digest = hex::encode(artifact_sha256);
+    let matches_subject = statement.subject.iter().any(|subject| {
+        subject
+            .digest
+            .get("sha256")
+            .is_some_and(|digest| digest.eq_ignore_ascii_case(&expected_digest))
+    });
+    if !matches_subject {
+        bail!("Attestation does not cover the installer");
+    }
+
+    Ok(())
+}
+
+/// Return the trusted builder that signed `payload`, if any.
+fn find_signer<'a>(
+    envelope: &Envelope,
+    payload: &[u8],
+    trusted_builders: &'a [TrustedBuilder],
+) -> Option<&'a TrustedBuilder> {
+    let message = pre_auth_encoding(&envelope.payload_type, payload);
+
+    envelope
+        .signatures
+        .iter()
+        .filter_map(|signature| {
+            let bytes = BASE64.decode(&signature.sig).ok()?;
+            ed25519_dalek::Signature::from_slice(&bytes).ok()
+        })
+        .find_map(|signature| {
+            trusted_builders
+                .iter()
+                .find(|builder| builder.key.0.verify_strict(&message, &signature).is_ok())
+        })
+}
+
+/// DSSE pre-authentication encoding. This is what is actually signed.
+fn pre_auth_encoding(payload_type: &str, payload: &[u8]) -> Vec<u8> {
+    let mut message = format!(
+        "DSSEv1 {} {payload_type} {} ",
+        payload_type.len(),
+        payload.len()
+    )
+    .into_bytes();
+    message.extend_from_slice(payload);
+    message
+}
+
+#[cfg(test)]
+mod test {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    use super::*;
+
+    const BUILDER_ID: &str =
+        "https://github.com/mullvad/mullvadvpn-app/.github/workflows/release.yml";
+
+    fn statement(builder_id: &str, digest: &[u8; 32]) -> Vec<u8> {
+        serde_json::to_vec(&serde_json::json!({
+            "_type": STATEMENT_TYPE,
+            "subject": [{ "name": "MullvadVPN.exe", "digest": { "sha256": hex::encode(digest) } }],
+            "predicateType": SLSA_PROVENANCE_TYPE,
+            "predicate": {
+                "buildDefinition": { "buildType": "https://example.com/build" },
+                "runDetails": { "builder": { "id": builder_id } },
+            },
+        }))
+        .unwrap()
+    }
+
+    fn envelope(key: &SigningKey, payload: &[u8]) -> Vec<u8> {
+        let sig = key.sign(&pre_auth_encoding(IN_TOTO_PAYLOAD_TYPE, payload));
+        serde_json::to_vec(&serde_json::json!({
+            "payloadType": IN_TOTO_PAYLOAD_TYPE,
+            "payload": BASE64.encode(payload),
+            "signatures": [{ "keyid": "", "sig": BASE64.encode(sig.to_bytes()) }],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_attestation() {
+        let key = SigningKey::from_bytes(&[1; 32]);
+        let other_key = SigningKey::from_bytes(&[2; 32]);
+        let trusted = [TrustedBuilder {
+            id: BUILDER_ID.to_owned(),
+            key: VerifyingKey(key.verifying_key()),
+        }];
+        let digest = [0xab; 32];
+
+        let attestation = envelope(&key, &statement(BUILDER_ID, &digest));
+        verify_attestation(&attestation, &digest, &trusted).expect("expected valid attestation");
+
+        verify_attestation(&attestation, &[0; 32], &trusted).expect_err("expected digest mismatch");
+
+        let attestation = envelope(&other_key, &statement(BUILDER_ID, &digest));
+        verify_attestation(&attestation, &digest, &trusted).expect_err("expected untrusted signer");
+
+        let attestation = envelope(&key, &statement("https://example.com/builder", &digest));
+        verify_attestation(&attestation, &digest, &trusted).expect_err("expected builder mismatch");
+    }
+}
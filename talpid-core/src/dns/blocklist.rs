@@ -0,0 +1,70 @@
+use std::collections::HashSet;
+
+/// A set of domains that must not be resolved. A blocked domain also blocks all of its
+/// subdomains.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DomainBlocklist {
+    domains: HashSet<String>,
+}
+
+impl DomainBlocklist {
+    /// Block `domain` and its subdomains. Returns whether the domain was newly added.
+    pub fn insert(&mut self, domain: &str) -> bool {
+        self.domains.insert(normalize(domain))
+    }
+
+    /// Number of blocked domains, not counting subdomains.
+    pub fn len(&self) -> usize {
+        self.domains.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty()
+    }
+
+    /// Returns whether `name`, or any domain that it is a subdomain of, is blocked.
+    pub fn is_blocked(&self, name: &str) -> bool {
+        contains_domain(&self.domains, name)
+    }
+}
+
+/// Returns whether `name`, or any domain that it is a subdomain of, is in `domains`.
+pub(super) fn contains_domain(domains: &HashSet<String>, name: &str) -> bool {
+    let name = normalize(name);
+    let mut suffix = name.as_str();
+    loop {
+        if domains.contains(suffix) {
+            return true;
+        }
+        match suffix.split_once('.') {
+            Some((_, parent)) => suffix = parent,
+            None => return false,
+        }
+    }
+}
+
+/// Domain names are case-insensitive, and may be fully qualified.
+pub(super) fn normalize(domain: &str) -> String {
+    domain.trim_end_matches('.').to_ascii_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+    use super::DomainBlocklist;
+
+    #[test]
+    fn test_is_blocked() {
+        let mut blocklist = DomainBlocklist::default();
+        assert!(blocklist.insert("Ads.Example.com"));
+        assert!(!blocklist.insert("ads.example.com."));
+        assert_eq!(blocklist.len(), 1);
+
+        assert!(blocklist.is_blocked("ads.example.com"));
+        assert!(blocklist.is_blocked("ADS.example.com."));
+        assert!(blocklist.is_blocked("tracker.ads.example.com."));
+
+        assert!(!blocklist.is_blocked("example.com."));
+        assert!(!blocklist.is_blocked("badads.example.com."));
+        assert!(!blocklist.is_blocked("."));
+    }
+}
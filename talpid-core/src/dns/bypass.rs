@@ -0,0 +1,206 @@
+//! Split tunneling by domain name. The local forwarder records the addresses that bypassed
+//! domains resolve to, and hands them to the firewall before answering the query. The addresses
+//! are forgotten when the TTL of their records has expired.
+use super::blocklist;
+use futures::channel::{mpsc, oneshot};
+use hickory_resolver::proto::rr::{RData, Record};
+use std::{
+    collections::{HashMap, HashSet},
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::time::Instant;
+
+/// Largest number of addresses that are routed outside the tunnel at once.
+const MAX_BYPASSED_ADDRESSES: usize = 1024;
+
+/// Shortest time that an address is kept, regardless of the TTL of its record. Some services use
+/// very short TTLs, but connections to an address usually outlive them.
+const MIN_TTL: Duration = Duration::from_secs(5 * 60);
+/// Longest time that an address is kept without being resolved again.
+const MAX_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// How long the forwarder waits for the firewall to be updated before answering a query.
+const UPDATE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Channel that the current set of bypassed addresses is sent on. `()` is sent back once the
+/// addresses have been applied.
+pub type BypassUpdateTx = mpsc::UnboundedSender<(Vec<IpAddr>, oneshot::Sender<()>)>;
+
+/// A set of domains whose traffic is routed outside the tunnel. A bypassed domain also bypasses
+/// all of its subdomains.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BypassedDomains {
+    domains: HashSet<String>,
+}
+
+impl BypassedDomains {
+    /// Bypass `domain` and its subdomains. Returns whether the domain was newly added.
+    pub fn insert(&mut self, domain: &str) -> bool {
+        self.domains.insert(blocklist::normalize(domain))
+    }
+
+    /// Number of bypassed domains, not counting subdomains.
+    pub fn len(&self) -> usize {
+        self.domains.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.domains.is_empty()
+    }
+
+    /// Returns whether `name`, or any domain that it is a subdomain of, is bypassed.
+    pub fn contains(&self, name: &str) -> bool {
+        blocklist::contains_domain(&self.domains, name)
+    }
+}
+
+impl<S: AsRef<str>> FromIterator<S> for BypassedDomains {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        let mut domains = Self::default();
+        for domain in iter {
+            domains.insert(domain.as_ref());
+        }
+        domains
+    }
+}
+
+/// Addresses that bypassed domains have resolved to, and when they expire.
+#[derive(Debug, Default)]
+struct BypassedAddresses {
+    expiry: HashMap<IpAddr, Instant>,
+}
+
+impl BypassedAddresses {
+    /// Keep `address` for `ttl`, or longer if it is already known. Returns whether the set of
+    /// addresses changed.
+    fn insert(&mut self, address: IpAddr, ttl: Duration, now: Instant) -> bool {
+        let expiry = now + ttl.clamp(MIN_TTL, MAX_TTL);
+        if let Some(current) = self.expiry.get_mut(&address) {
+            *current = (*current).max(expiry);
+            return false;
+        }
+        if self.expiry.len() >= MAX_BYPASSED_ADDRESSES {
+            log::warn!("Not bypassing {address}, since too many addresses are already bypassed");
+            return false;
+        }
+        self.expiry.insert(address, expiry);
+        true
+    }
+
+    /// Forget all addresses that expired before `now`. Returns whether any were removed.
+    fn expire(&mut self, now: Instant) -> bool {
+        let len = self.expiry.len();
+        self.expiry.retain(|_, expiry| *expiry > now);
+        self.expiry.len() != len
+    }
+
+    fn addresses(&self) -> Vec<IpAddr> {
+        let mut addresses: Vec<_> = self.expiry.keys().copied().collect();
+        addresses.sort();
+        addresses
+    }
+}
+
+/// Records the addresses that bypassed domains resolve to, and sends them to the firewall.
+pub(super) struct BypassTracker {
+    domains: Arc<BypassedDomains>,
+    addresses: Mutex<BypassedAddresses>,
+    update_tx: BypassUpdateTx,
+}
+
+impl BypassTracker {
+    pub fn new(domains: Arc<BypassedDomains>, update_tx: BypassUpdateTx) -> Self {
+        Self {
+            domains,
+            addresses: Mutex::default(),
+            update_tx,
+        }
+    }
+
+    /// Returns whether queries for `name` are bypassed.
+    pub fn is_bypassed(&self, name: &str) -> bool {
+        self.domains.contains(name)
+    }
+
+    /// Bypass the addresses in the A and AAAA records of `records`, and wait until the firewall
+    /// has been updated.
+    pub async fn add(&self, records: &[Record]) {
+        let now = Instant::now();
+        let update = {
+            let mut addresses = self.addresses.lock().unwrap();
+            let mut changed = false;
+            for record in records {
+                let address = match record.data() {
+                    Some(RData::A(a)) => IpAddr::V4(a.0),
+                    Some(RData::AAAA(aaaa)) => IpAddr::V6(aaaa.0),
+                    _ => continue,
+                };
+                let ttl = Duration::from_secs(u64::from(record.ttl()));
+                changed |= addresses.insert(address, ttl, now);
+            }
+            // Send while holding the lock, so that updates arrive in order
+            changed.then(|| self.send(addresses.addresses()))
+        };
+
+        if let Some(done_rx) = update {
+            if tokio::time::timeout(UPDATE_TIMEOUT, done_rx).await.is_err() {
+                log::warn!("Timed out waiting for bypassed addresses to be applied");
+            }
+        }
+    }
+
+    /// Forget expired addresses.
+    pub fn expire(&self) {
+        let mut addresses = self.addresses.lock().unwrap();
+        if addresses.expire(Instant::now()) {
+            let _ = self.send(addresses.addresses());
+        }
+    }
+
+    fn send(&self, addresses: Vec<IpAddr>) -> oneshot::Receiver<()> {
+        log::debug!("Bypassing {} addresses", addresses.len());
+        let (done_tx, done_rx) = oneshot::channel();
+        let _ = self.update_tx.unbounded_send((addresses, done_tx));
+        done_rx
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_bypassed_domains() {
+        let domains: BypassedDomains = ["Netflix.com", "netflix.com."].into_iter().collect();
+        assert_eq!(domains.len(), 1);
+
+        assert!(domains.contains("netflix.com."));
+        assert!(domains.contains("www.NETFLIX.com"));
+        assert!(!domains.contains("notnetflix.com"));
+        assert!(!domains.contains("com."));
+    }
+
+    #[test]
+    fn test_bypassed_addresses() {
+        let mut addresses = BypassedAddresses::default();
+        let now = Instant::now();
+        let first: IpAddr = "192.0.2.1".parse().unwrap();
+        let second: IpAddr = "2001:db8::1".parse().unwrap();
+
+        assert!(addresses.insert(first, Duration::ZERO, now));
+        assert!(!addresses.insert(first, MAX_TTL * 2, now));
+        assert!(addresses.insert(second, Duration::ZERO, now));
+        assert_eq!(addresses.addresses(), vec![first, second]);
+
+        // Short TTLs are extended to the minimum
+        assert!(!addresses.expire(now + MIN_TTL - Duration::from_secs(1)));
+        assert!(addresses.expire(now + MIN_TTL));
+        assert_eq!(addresses.addresses(), vec![first]);
+
+        // Long TTLs are capped
+        assert!(addresses.expire(now + MAX_TTL));
+        assert!(addresses.addresses().is_empty());
+    }
+}
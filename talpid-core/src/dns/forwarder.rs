@@ -0,0 +1,464 @@
+//! A DNS forwarder that listens for plaintext queries on loopback and resolves them using
+//! DNS-over-TLS or DNS-over-HTTPS servers, or plaintext servers when domains are filtered.
+//!
+//! The system resolver is pointed at [FORWARDER_ADDR] while connected, so that queries from the
+//! system are upgraded to the encrypted upstream inside the tunnel, and blocked domains are never
+//! resolved. The forwarder also records the addresses of bypassed domains, see [super::bypass].
+use super::{
+    bypass::{BypassTracker, BypassUpdateTx},
+    BypassedDomains, DomainBlocklist,
+};
+use hickory_resolver::{
+    config::{
+        NameServerConfig, NameServerConfigGroup, Protocol, ResolverConfig, ResolverOpts,
+        TlsClientConfig,
+    },
+    error::ResolveErrorKind,
+    proto::op::{Message, MessageType, OpCode, ResponseCode},
+    TokioAsyncResolver,
+};
+use rustls::{
+    client::{ServerCertVerified, ServerCertVerifier, WebPkiVerifier},
+    Certificate, CertificateError, ClientConfig, OwnedTrustAnchor, RootCertStore, ServerName,
+};
+use sha2::{Digest, Sha256};
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+use talpid_types::dns::{EncryptedDnsProtocol, EncryptedDnsServer, InvalidSpkiPin};
+use tokio::{net::UdpSocket, sync::mpsc, task::JoinSet};
+
+/// Address that the forwarder listens on.
+pub const FORWARDER_ADDR: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 53);
+
+/// Port of plaintext upstream servers.
+const PLAINTEXT_DNS_PORT: u16 = 53;
+
+/// Largest query that is accepted. This is the EDNS buffer size recommended by DNS Flag Day 2020.
+const MAX_QUERY_SIZE: usize = 1232;
+
+/// How often expired addresses of bypassed domains are removed.
+const BYPASS_EXPIRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Errors that can occur when starting the forwarder
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Failed to bind UDP socket
+    #[error("Failed to bind UDP socket on {FORWARDER_ADDR}")]
+    UdpBindError(#[source] io::Error),
+
+    /// The SPKI pin of a server is invalid
+    #[error("Invalid SPKI pin for {0}")]
+    InvalidSpkiPin(String, #[source] InvalidSpkiPin),
+}
+
+/// Servers that the forwarder sends queries to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Upstream {
+    /// DNS-over-TLS or DNS-over-HTTPS servers
+    Encrypted(Vec<EncryptedDnsServer>),
+    /// Plaintext servers, used when domains are filtered without encrypted DNS
+    Plaintext(Vec<IpAddr>),
+}
+
+/// Forwards plaintext DNS queries on [FORWARDER_ADDR] to upstream DNS servers, except for queries
+/// for blocked domains. The forwarder stops when this is dropped.
+pub struct DnsForwarder {
+    upstream: Upstream,
+    blocklist: Arc<DomainBlocklist>,
+    bypassed_domains: Arc<BypassedDomains>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl DnsForwarder {
+    /// Start forwarding queries to `upstream`, and answer queries for domains in `blocklist`
+    /// with NXDOMAIN. The addresses that `bypassed_domains` resolve to are sent on `bypass_tx`
+    /// before the queries are answered.
+    pub async fn start(
+        upstream: Upstream,
+        blocklist: Arc<DomainBlocklist>,
+        bypassed_domains: Arc<BypassedDomains>,
+        bypass_tx: Option<BypassUpdateTx>,
+    ) -> Result<Self, Error> {
+        let resolver =
+            TokioAsyncResolver::tokio(resolver_config(&upstream)?, ResolverOpts::default());
+        let socket = UdpSocket::bind(FORWARDER_ADDR)
+            .await
+            .map_err(Error::UdpBindError)?;
+
+        match &upstream {
+            Upstream::Encrypted(_) => {
+                log::debug!("Forwarding DNS queries on {FORWARDER_ADDR} to encrypted DNS servers")
+            }
+            Upstream::Plaintext(_) => log::debug!("Forwarding DNS queries on {FORWARDER_ADDR}"),
+        }
+        if !blocklist.is_empty() {
+            log::debug!("Blocking {} domains", blocklist.len());
+        }
+
+        let bypass = match bypass_tx {
+            Some(bypass_tx) if !bypassed_domains.is_empty() => {
+                log::debug!("Bypassing {} domains", bypassed_domains.len());
+                Some(Arc::new(BypassTracker::new(
+                    bypassed_domains.clone(),
+                    bypass_tx,
+                )))
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            upstream,
+            blocklist: blocklist.clone(),
+            bypassed_domains,
+            task: tokio::spawn(serve(socket, Arc::new(resolver), blocklist, bypass)),
+        })
+    }
+
+    /// Servers that queries are forwarded to.
+    pub fn upstream(&self) -> &Upstream {
+        &self.upstream
+    }
+
+    /// Domains that are not resolved.
+    pub fn blocklist(&self) -> &Arc<DomainBlocklist> {
+        &self.blocklist
+    }
+
+    /// Domains whose addresses are routed outside the tunnel.
+    pub fn bypassed_domains(&self) -> &Arc<BypassedDomains> {
+        &self.bypassed_domains
+    }
+
+    /// Stop the forwarder and wait until its socket has been closed.
+    pub async fn stop(mut self) {
+        self.task.abort();
+        let _ = (&mut self.task).await;
+    }
+}
+
+impl Drop for DnsForwarder {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+fn resolver_config(upstream: &Upstream) -> Result<ResolverConfig, Error> {
+    let servers = match upstream {
+        Upstream::Encrypted(servers) => servers,
+        Upstream::Plaintext(servers) => {
+            return Ok(ResolverConfig::from_parts(
+                None,
+                vec![],
+                NameServerConfigGroup::from_ips_clear(servers, PLAINTEXT_DNS_PORT, true),
+            ));
+        }
+    };
+
+    let mut config = ResolverConfig::new();
+    for server in servers {
+        let pin = server
+            .spki_digest()
+            .map_err(|error| Error::InvalidSpkiPin(server.hostname.clone(), error))?;
+        let protocol = match server.protocol {
+            EncryptedDnsProtocol::Tls => Protocol::Tls,
+            EncryptedDnsProtocol::Https => Protocol::Https,
+        };
+        let mut name_server =
+            NameServerConfig::new(SocketAddr::new(server.address, server.port()), protocol);
+        name_server.tls_dns_name = Some(server.hostname.clone());
+        name_server.tls_config = Some(TlsClientConfig(Arc::new(client_config(pin))));
+        config.add_name_server(name_server);
+    }
+    Ok(config)
+}
+
+fn client_config(pin: Option<[u8; 32]>) -> ClientConfig {
+    let mut root_store = RootCertStore::empty();
+    root_store.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|ta| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(
+            ta.subject,
+            ta.spki,
+            ta.name_constraints,
+        )
+    }));
+
+    let builder = ClientConfig::builder().with_safe_defaults();
+    match pin {
+        Some(pin) => builder
+            .with_custom_certificate_verifier(Arc::new(PinnedCertVerifier {
+                inner: WebPkiVerifier::new(root_store, None),
+                pin,
+            }))
+            .with_no_client_auth(),
+        None => builder
+            .with_root_certificates(root_store)
+            .with_no_client_auth(),
+    }
+}
+
+/// Validates certificates like [WebPkiVerifier], but also requires the public key of the server
+/// to match a pinned digest.
+struct PinnedCertVerifier {
+    inner: WebPkiVerifier,
+    pin: [u8; 32],
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )?;
+
+        let spki = subject_public_key_info(&end_entity.0).ok_or(
+            rustls::Error::InvalidCertificate(CertificateError::BadEncoding),
+        )?;
+        if Sha256::digest(spki).as_slice() != self.pin {
+            log::warn!("Public key of encrypted DNS server does not match the SPKI pin");
+            return Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ));
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+/// Return the DER encoded SubjectPublicKeyInfo of a DER encoded X.509 certificate.
+fn subject_public_key_info(certificate: &[u8]) -> Option<&[u8]> {
+    const SEQUENCE: u8 = 0x30;
+    const VERSION: u8 = 0xa0;
+
+    let (tag, certificate, _) = der_element(certificate)?;
+    if tag != SEQUENCE {
+        return None;
+    }
+    let (tag, tbs_certificate, _) = der_element(certificate)?;
+    if tag != SEQUENCE {
+        return None;
+    }
+
+    let mut rest = tbs_certificate;
+    if rest.first() == Some(&VERSION) {
+        rest = der_element(rest)?.2;
+    }
+    // Skip the serial number, signature algorithm, issuer, validity and subject
+    for _ in 0..5 {
+        rest = der_element(rest)?.2;
+    }
+
+    let (tag, _, after) = der_element(rest)?;
+    if tag != SEQUENCE {
+        return None;
+    }
+    Some(&rest[..rest.len() - after.len()])
+}
+
+/// Split the first DER element off `input`. Returns its tag, its contents, and the remaining
+/// input.
+fn der_element(input: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let tag = *input.first()?;
+    let first_len_byte = usize::from(*input.get(1)?);
+    let (header_len, content_len) = if first_len_byte < 0x80 {
+        (2, first_len_byte)
+    } else {
+        let len_bytes = first_len_byte & 0x7f;
+        if len_bytes == 0 || len_bytes > std::mem::size_of::<usize>() {
+            return None;
+        }
+        let len = input
+            .get(2..2 + len_bytes)?
+            .iter()
+            .fold(0usize, |len, byte| (len << 8) | usize::from(*byte));
+        (2 + len_bytes, len)
+    };
+    let rest = input.get(header_len..)?;
+    if content_len > rest.len() {
+        return None;
+    }
+    let (content, rest) = rest.split_at(content_len);
+    Some((tag, content, rest))
+}
+
+/// Answer queries received on `socket` until the task is aborted.
+async fn serve(
+    socket: UdpSocket,
+    resolver: Arc<TokioAsyncResolver>,
+    blocklist: Arc<DomainBlocklist>,
+    bypass: Option<Arc<BypassTracker>>,
+) {
+    let (response_tx, mut response_rx) = mpsc::unbounded_channel();
+    // Lookups are aborted when this is dropped
+    let mut lookups = JoinSet::new();
+    let mut buffer = [0u8; MAX_QUERY_SIZE];
+    let mut expiry_interval = tokio::time::interval(BYPASS_EXPIRY_INTERVAL);
+
+    loop {
+        tokio::select! {
+            received = socket.recv_from(&mut buffer) => {
+                let (len, source) = match received {
+                    Ok(received) => received,
+                    Err(error) => {
+                        log::debug!("Failed to receive DNS query: {error}");
+                        continue;
+                    }
+                };
+                if !source.ip().is_loopback() {
+                    log::error!("Dropping a stray DNS query from outside: {source}");
+                    continue;
+                }
+                let query = match Message::from_vec(&buffer[..len]) {
+                    Ok(query) => query,
+                    Err(error) => {
+                        log::trace!("Dropping malformed DNS query: {error}");
+                        continue;
+                    }
+                };
+
+                let resolver = resolver.clone();
+                let blocklist = blocklist.clone();
+                let bypass = bypass.clone();
+                let response_tx = response_tx.clone();
+                lookups.spawn(async move {
+                    let response = resolve(&resolver, &blocklist, bypass.as_deref(), query).await;
+                    let _ = response_tx.send((response, source));
+                });
+            }
+            Some((response, destination)) = response_rx.recv() => {
+                match response.to_vec() {
+                    Ok(bytes) => {
+                        if let Err(error) = socket.send_to(&bytes, destination).await {
+                            log::debug!("Failed to send DNS response: {error}");
+                        }
+                    }
+                    Err(error) => log::error!("Failed to encode DNS response: {error}"),
+                }
+            }
+            // Reap finished lookups
+            Some(_) = lookups.join_next() => (),
+            _ = expiry_interval.tick() => {
+                if let Some(bypass) = &bypass {
+                    bypass.expire();
+                }
+            }
+        }
+    }
+}
+
+async fn resolve(
+    resolver: &TokioAsyncResolver,
+    blocklist: &DomainBlocklist,
+    bypass: Option<&BypassTracker>,
+    query: Message,
+) -> Message {
+    let mut response = Message::new();
+    response
+        .set_id(query.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(query.op_code())
+        .set_recursion_desired(query.recursion_desired())
+        .set_recursion_available(true)
+        .add_queries(query.queries().iter().cloned());
+
+    if query.message_type() != MessageType::Query || query.op_code() != OpCode::Query {
+        response.set_response_code(ResponseCode::NotImp);
+        return response;
+    }
+    let [question] = query.queries() else {
+        response.set_response_code(ResponseCode::FormErr);
+        return response;
+    };
+    if blocklist.is_blocked(&question.name().to_ascii()) {
+        response.set_response_code(ResponseCode::NXDomain);
+        return response;
+    }
+
+    match resolver
+        .lookup(question.name().clone(), question.query_type())
+        .await
+    {
+        Ok(lookup) => {
+            if let Some(bypass) = bypass {
+                if bypass.is_bypassed(&question.name().to_ascii()) {
+                    bypass.add(lookup.records()).await;
+                }
+            }
+            response.add_answers(lookup.records().iter().cloned());
+        }
+        Err(error) => match error.kind() {
+            ResolveErrorKind::NoRecordsFound { response_code, .. } => {
+                response.set_response_code(*response_code);
+            }
+            _ => {
+                log::debug!("Forwarded DNS lookup failed: {error}");
+                response.set_response_code(ResponseCode::ServFail);
+            }
+        },
+    }
+    response
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_der_element() {
+        // Short form length
+        let (tag, content, rest) = der_element(&[0x02, 0x01, 0x05, 0xff]).unwrap();
+        assert_eq!((tag, content, rest), (0x02, &[0x05][..], &[0xff][..]));
+
+        // Long form length
+        let mut input = vec![0x04, 0x81, 0x80];
+        input.extend([0xaa; 0x80]);
+        let (tag, content, rest) = der_element(&input).unwrap();
+        assert_eq!((tag, content.len(), rest.len()), (0x04, 0x80, 0));
+
+        // Truncated contents
+        assert!(der_element(&[0x30, 0x03, 0x01]).is_none());
+    }
+
+    #[test]
+    fn test_subject_public_key_info() {
+        let spki = [0x30, 0x03, 0x02, 0x01, 0x07];
+        let tbs_certificate = [
+            &[0xa0, 0x03, 0x02, 0x01, 0x02][..], // version
+            &[0x02, 0x01, 0x01],                 // serial number
+            &[0x30, 0x00],                       // signature algorithm
+            &[0x30, 0x00],                       // issuer
+            &[0x30, 0x00],                       // validity
+            &[0x30, 0x00],                       // subject
+            &spki,
+            &[0xa3, 0x00], // extensions
+        ]
+        .concat();
+        let certificate = [
+            &[
+                0x30,
+                tbs_certificate.len() as u8 + 2,
+                0x30,
+                tbs_certificate.len() as u8,
+            ][..],
+            &tbs_certificate,
+        ]
+        .concat();
+
+        assert_eq!(subject_public_key_info(&certificate), Some(&spki[..]));
+        assert_eq!(subject_public_key_info(&certificate[..10]), None);
+    }
+}
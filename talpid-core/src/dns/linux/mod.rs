@@ -7,12 +7,10 @@ use self::{
     network_manager::NetworkManager, resolvconf::Resolvconf, static_resolv_conf::StaticResolvConf,
     systemd_resolved::SystemdResolved,
 };
-use std::{
-    env,
-    fmt::{self, Display},
-    net::IpAddr,
-};
+use futures::channel::mpsc;
+use std::{env, fmt, net::IpAddr};
 use talpid_routing::RouteManagerHandle;
+use talpid_types::dns::{DnsBackend, DnsInterference, SplitDnsRule};
 
 use super::ResolvedDnsConfig;
 
@@ -45,16 +43,25 @@ pub enum Error {
 pub struct DnsMonitor {
     route_manager: RouteManagerHandle,
     handle: tokio::runtime::Handle,
+    interference_tx: mpsc::UnboundedSender<DnsInterference>,
+    backends: Vec<DnsBackend>,
     inner: Option<DnsMonitorHolder>,
 }
 
 impl super::DnsMonitorT for DnsMonitor {
     type Error = Error;
 
-    fn new(handle: tokio::runtime::Handle, route_manager: RouteManagerHandle) -> Result<Self> {
+    fn new(
+        handle: tokio::runtime::Handle,
+        route_manager: RouteManagerHandle,
+        interference_tx: mpsc::UnboundedSender<DnsInterference>,
+        backends: Vec<DnsBackend>,
+    ) -> Result<Self> {
         Ok(DnsMonitor {
             route_manager,
             handle,
+            interference_tx,
+            backends,
             inner: None,
         })
     }
@@ -63,9 +70,15 @@ impl super::DnsMonitorT for DnsMonitor {
         let servers = config.tunnel_config();
         self.reset()?;
         // Creating a new DNS monitor for each set, in case the system changed how it manages DNS.
-        let mut inner = DnsMonitorHolder::new()?;
+        let mut inner = DnsMonitorHolder::new(&self.interference_tx, &self.backends)?;
         if !servers.is_empty() {
-            inner.set(&self.handle, &self.route_manager, interface, servers)?;
+            inner.set(
+                &self.handle,
+                &self.route_manager,
+                interface,
+                servers,
+                config.split_dns_rules(),
+            )?;
             self.inner = Some(inner);
         }
         Ok(())
@@ -88,58 +101,63 @@ pub enum DnsMonitorHolder {
 
 impl fmt::Display for DnsMonitorHolder {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        use self::DnsMonitorHolder::*;
-        let name = match self {
-            Resolvconf(..) => "resolvconf",
-            StaticResolvConf(..) => "/etc/resolv.conf",
-            SystemdResolved(..) => "systemd-resolved",
-            NetworkManager(..) => "NetworkManager",
-        };
-        f.write_str(name)
+        write!(f, "{}", self.backend())
     }
 }
 
 impl DnsMonitorHolder {
-    fn new() -> Result<Self> {
-        let dns_module = env::var_os("TALPID_DNS_MODULE");
-
-        let manager = match dns_module.as_ref().and_then(|value| value.to_str()) {
-            Some("static-file") => DnsMonitorHolder::StaticResolvConf(StaticResolvConf::new()?),
-            Some("resolvconf") => DnsMonitorHolder::Resolvconf(Resolvconf::new()?),
-            Some("systemd") => DnsMonitorHolder::SystemdResolved(SystemdResolved::new()?),
-            Some("network-manager") => DnsMonitorHolder::NetworkManager(NetworkManager::new()?),
-            Some(_) | None => Self::with_detected_dns_manager()?,
+    fn new(
+        interference_tx: &mpsc::UnboundedSender<DnsInterference>,
+        backends: &[DnsBackend],
+    ) -> Result<Self> {
+        let manager = match backend_from_env() {
+            Some(backend) => Self::with_backend(backend, interference_tx)?,
+            None => Self::with_detected_dns_manager(interference_tx, backends)?,
         };
         log::debug!("Managing DNS via {}", manager);
         Ok(manager)
     }
 
-    fn with_detected_dns_manager() -> Result<Self> {
-        fn log_err<E: Display>(method: &'static str) -> impl Fn(&E) {
-            move |err: &E| {
-                log::debug!("Can't manage DNS using {method}: {err}");
+    fn with_backend(
+        backend: DnsBackend,
+        interference_tx: &mpsc::UnboundedSender<DnsInterference>,
+    ) -> Result<Self> {
+        let manager = match backend {
+            DnsBackend::SystemdResolved => {
+                DnsMonitorHolder::SystemdResolved(SystemdResolved::new()?)
             }
-        }
+            DnsBackend::NetworkManager => DnsMonitorHolder::NetworkManager(NetworkManager::new()?),
+            DnsBackend::Resolvconf => DnsMonitorHolder::Resolvconf(Resolvconf::new()?),
+            DnsBackend::StaticFile => {
+                DnsMonitorHolder::StaticResolvConf(StaticResolvConf::new(interference_tx.clone())?)
+            }
+        };
+        Ok(manager)
+    }
 
-        SystemdResolved::new()
-            .map(DnsMonitorHolder::SystemdResolved)
-            .inspect_err(log_err("systemd-resolved"))
-            .or_else(|_| {
-                NetworkManager::new()
-                    .map(DnsMonitorHolder::NetworkManager)
-                    .inspect_err(log_err("NetworkManager"))
-            })
-            .or_else(|_| {
-                Resolvconf::new()
-                    .map(DnsMonitorHolder::Resolvconf)
-                    .inspect_err(log_err("resolveconf"))
+    /// Use the first backend in `backends` that can manage DNS, or the first in the default order
+    /// if `backends` is empty.
+    fn with_detected_dns_manager(
+        interference_tx: &mpsc::UnboundedSender<DnsInterference>,
+        backends: &[DnsBackend],
+    ) -> Result<Self> {
+        ordered_backends(backends)
+            .iter()
+            .find_map(|&backend| {
+                Self::with_backend(backend, interference_tx)
+                    .inspect_err(|err| log::debug!("Can't manage DNS using {backend}: {err}"))
+                    .ok()
             })
-            .or_else(|_| {
-                StaticResolvConf::new()
-                    .map(DnsMonitorHolder::StaticResolvConf)
-                    .inspect_err(log_err("/etc/resolv.conf"))
-            })
-            .map_err(|_| Error::NoDnsMonitor)
+            .ok_or(Error::NoDnsMonitor)
+    }
+
+    fn backend(&self) -> DnsBackend {
+        match self {
+            DnsMonitorHolder::SystemdResolved(..) => DnsBackend::SystemdResolved,
+            DnsMonitorHolder::NetworkManager(..) => DnsBackend::NetworkManager,
+            DnsMonitorHolder::Resolvconf(..) => DnsBackend::Resolvconf,
+            DnsMonitorHolder::StaticResolvConf(..) => DnsBackend::StaticFile,
+        }
     }
 
     fn set(
@@ -148,8 +166,14 @@ impl DnsMonitorHolder {
         route_manager: &RouteManagerHandle,
         interface: &str,
         servers: &[IpAddr],
+        split_dns_rules: &[SplitDnsRule],
     ) -> Result<()> {
         use self::DnsMonitorHolder::*;
+        if !split_dns_rules.is_empty() && !matches!(self, SystemdResolved(..)) {
+            log::warn!(
+                "Split DNS is only supported with systemd-resolved. Ignoring split DNS rules"
+            );
+        }
         match self {
             Resolvconf(resolvconf) => resolvconf.set_dns(interface, servers)?,
             StaticResolvConf(static_resolv_conf) => static_resolv_conf.set_dns(servers.to_vec())?,
@@ -157,6 +181,7 @@ impl DnsMonitorHolder {
                 route_manager.clone(),
                 interface,
                 servers,
+                split_dns_rules,
             ))?,
             NetworkManager(network_manager) => network_manager.set_dns(interface, servers)?,
         }
@@ -175,6 +200,59 @@ impl DnsMonitorHolder {
     }
 }
 
+/// Returns the backend that [DnsMonitor] selects the next time that it sets DNS, when it is
+/// configured with `backends`.
+///
+/// This checks the same conditions as the monitor, but without modifying any DNS configuration.
+pub fn active_backend(backends: &[DnsBackend]) -> Result<DnsBackend> {
+    if let Some(backend) = backend_from_env() {
+        return Ok(backend);
+    }
+    ordered_backends(backends)
+        .iter()
+        .copied()
+        .find(|&backend| {
+            let result = match backend {
+                DnsBackend::SystemdResolved => {
+                    SystemdResolved::new().map(|_| ()).map_err(Error::from)
+                }
+                DnsBackend::NetworkManager => {
+                    NetworkManager::new().map(|_| ()).map_err(Error::from)
+                }
+                DnsBackend::Resolvconf => Resolvconf::new().map(|_| ()).map_err(Error::from),
+                // Creating the monitor would restore a backup of /etc/resolv.conf, which may
+                // belong to the monitor that is in use. Writing the file does not depend on any
+                // service being present.
+                DnsBackend::StaticFile => Ok(()),
+            };
+            result
+                .inspect_err(|err| log::debug!("Can't manage DNS using {backend}: {err}"))
+                .is_ok()
+        })
+        .ok_or(Error::NoDnsMonitor)
+}
+
+/// The backend that `TALPID_DNS_MODULE` forces the monitor to use, if any. It takes precedence
+/// over the configured backends.
+fn backend_from_env() -> Option<DnsBackend> {
+    let dns_module = env::var_os("TALPID_DNS_MODULE")?;
+    match dns_module.to_str()? {
+        "static-file" => Some(DnsBackend::StaticFile),
+        "resolvconf" => Some(DnsBackend::Resolvconf),
+        "systemd" => Some(DnsBackend::SystemdResolved),
+        "network-manager" => Some(DnsBackend::NetworkManager),
+        _ => None,
+    }
+}
+
+fn ordered_backends(backends: &[DnsBackend]) -> &[DnsBackend] {
+    if backends.is_empty() {
+        &DnsBackend::DEFAULT_ORDER
+    } else {
+        backends
+    }
+}
+
 /// Returns true if DnsMonitor will use NetworkManager to manage DNS.
 pub fn will_use_nm() -> bool {
     crate::dns::imp::SystemdResolved::new().is_err()
@@ -1,13 +1,33 @@
-use futures::StreamExt;
-use inotify::{Inotify, WatchMask};
+use futures::{channel::mpsc, StreamExt};
+use inotify::{EventMask, Inotify, WatchMask, Watches};
 use parking_lot::Mutex;
 use resolv_conf::{Config, ScopedIp};
-use std::{fs, io, net::IpAddr, sync::Arc};
-use talpid_types::ErrorExt;
+use std::{
+    fs, io,
+    net::IpAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+use talpid_types::{dns::DnsInterference, ErrorExt};
 use triggered::{trigger, Listener, Trigger};
 
 const RESOLV_CONF_BACKUP_PATH: &str = "/etc/resolv.conf.mullvadbackup";
 const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+const RESOLV_CONF_DIR: &str = "/etc";
+const RESOLV_CONF_NAME: &str = "resolv.conf";
+
+/// Keywords found in the header comments or symlink targets of resolv.conf files, and the
+/// software they belong to. More specific keywords must come first.
+const KNOWN_SOURCES: &[(&str, &str)] = &[
+    ("systemd-resolved", "systemd-resolved"),
+    ("/systemd/resolve/", "systemd-resolved"),
+    ("networkmanager", "NetworkManager"),
+    ("dhcpcd", "dhcpcd"),
+    ("dhclient", "dhclient"),
+    ("netconfig", "netconfig"),
+    ("connman", "ConnMan"),
+    ("resolvconf", "resolvconf"),
+];
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -35,11 +55,11 @@ pub struct StaticResolvConf {
 }
 
 impl StaticResolvConf {
-    pub fn new() -> Result<Self> {
+    pub fn new(interference_tx: mpsc::UnboundedSender<DnsInterference>) -> Result<Self> {
         restore_from_backup()?;
 
         let state = Arc::new(Mutex::new(None));
-        let watcher = DnsWatcher::start(state.clone())?;
+        let watcher = DnsWatcher::start(state.clone(), interference_tx)?;
 
         Ok(StaticResolvConf {
             state,
@@ -112,34 +132,50 @@ impl Drop for DnsWatcher {
 }
 
 impl DnsWatcher {
-    fn start(state: Arc<Mutex<Option<State>>>) -> Result<Self> {
+    fn start(
+        state: Arc<Mutex<Option<State>>>,
+        interference_tx: mpsc::UnboundedSender<DnsInterference>,
+    ) -> Result<Self> {
         let watcher = Inotify::init().map_err(Error::WatchResolvConf)?;
-        let mut mask = WatchMask::empty();
+        let mut watches = watcher.watches();
+
         // Documentation for the meaning of these masks can be found in `man inotify`
         //
-        // We do not watch for writes but instead for when a file opened for writing is closed.
-        // This way we don't have collisions.
-        mask.insert(WatchMask::CLOSE_WRITE);
-        // DELETE_SELF is generated if the file watched is itself deleted
-        mask.insert(WatchMask::DELETE_SELF);
-        mask.insert(WatchMask::MOVE_SELF);
-
-        watcher
-            .watches()
-            .add(RESOLV_CONF_PATH, mask)
+        // Most tools replace resolv.conf by renaming a new file over it, or by deleting and
+        // recreating it. A watch on the file itself would stop firing after that, so the parent
+        // directory is watched as well. We do not watch for writes but instead for when a file
+        // opened for writing is closed. This way we don't have collisions.
+        watches
+            .add(
+                RESOLV_CONF_DIR,
+                WatchMask::CLOSE_WRITE | WatchMask::MOVED_TO | WatchMask::DELETE,
+            )
             .map_err(Error::WatchResolvConf)?;
+        // resolv.conf may be a symlink, in which case writes to its target are only seen by a
+        // watch on the file.
+        Self::watch_file(&mut watches);
 
         let (cancel_trigger, cancel_listener) = trigger();
 
-        tokio::spawn(async move { Self::event_loop(watcher, cancel_listener, &state).await });
+        tokio::spawn(async move {
+            Self::event_loop(watcher, watches, cancel_listener, &state, interference_tx).await
+        });
 
         Ok(DnsWatcher { cancel_trigger })
     }
 
+    fn watch_file(watches: &mut Watches) {
+        if let Err(error) = watches.add(RESOLV_CONF_PATH, WatchMask::CLOSE_WRITE) {
+            log::debug!("Failed to watch {RESOLV_CONF_PATH}: {error}");
+        }
+    }
+
     async fn event_loop(
         watcher: Inotify,
+        mut watches: Watches,
         mut cancel_listener: Listener,
         state: &Arc<Mutex<Option<State>>>,
+        interference_tx: mpsc::UnboundedSender<DnsInterference>,
     ) {
         const EVENT_BUFFER_SIZE: usize = 1024;
         let mut buffer = [0; EVENT_BUFFER_SIZE];
@@ -152,58 +188,112 @@ impl DnsWatcher {
                 _ = &mut cancel_listener => {
                     break;
                 },
-                Some(_) = events.next() => {
+                Some(Ok(event)) = events.next() => {
+                    if event.mask.contains(EventMask::IGNORED)
+                        || event.name.as_ref().is_some_and(|name| name != RESOLV_CONF_NAME)
+                    {
+                        continue;
+                    }
+
+                    // The file may have been replaced, so the old watch can be stale
+                    Self::watch_file(&mut watches);
+
                     let mut locked_state = state.lock();
-                    if let Err(error) = Self::update(locked_state.as_mut()) {
-                        log::error!(
-                            "{}",
-                            error.display_chain_with_msg(
-                                "Failed to update DNS state after DNS settings changed"
-                            )
-                        );
+                    match Self::update(locked_state.as_mut()) {
+                        Ok(Some(interference)) => {
+                            log::warn!("{interference}. Restored DNS configuration");
+                            let _ = interference_tx.unbounded_send(interference);
+                        }
+                        Ok(None) => (),
+                        Err(error) => {
+                            log::error!(
+                                "{}",
+                                error.display_chain_with_msg(
+                                    "Failed to update DNS state after DNS settings changed"
+                                )
+                            );
+                        }
                     }
                 }
             }
         }
     }
 
-    fn update(state: Option<&mut State>) -> Result<()> {
-        if let Some(state) = state {
-            let mut new_config = read_config()?;
-            let desired_nameservers = state
-                .desired_dns
-                .iter()
-                .map(|&address| ScopedIp::from(address))
-                .collect();
-
-            if new_config.nameservers != desired_nameservers {
-                state.backup = new_config.clone();
-                new_config.nameservers = desired_nameservers;
-
-                write_config(&new_config)
-            } else {
-                new_config.nameservers.clear();
-                new_config.nameservers.append(&mut state.backup.nameservers);
-                state.backup = new_config;
-
-                write_backup(&state.backup)
-            }
+    /// Restore the desired nameservers if they were changed by someone else, and return who
+    /// changed them.
+    fn update(state: Option<&mut State>) -> Result<Option<DnsInterference>> {
+        let Some(state) = state else {
+            return Ok(None);
+        };
+
+        let contents = read_contents()?;
+        let mut new_config = parse_config(contents.as_deref())?;
+        let desired_nameservers = state
+            .desired_dns
+            .iter()
+            .map(|&address| ScopedIp::from(address))
+            .collect();
+
+        if new_config.nameservers != desired_nameservers {
+            let link_target = fs::read_link(RESOLV_CONF_PATH).ok();
+            let interference = DnsInterference {
+                path: PathBuf::from(RESOLV_CONF_PATH),
+                source: identify_source(contents.as_deref(), link_target.as_deref())
+                    .map(str::to_owned),
+            };
+
+            state.backup = new_config.clone();
+            new_config.nameservers = desired_nameservers;
+
+            write_config(&new_config)?;
+            Ok(Some(interference))
         } else {
-            Ok(())
+            new_config.nameservers.clear();
+            new_config.nameservers.append(&mut state.backup.nameservers);
+            state.backup = new_config;
+
+            write_backup(&state.backup)?;
+            Ok(None)
         }
     }
 }
 
-fn read_config() -> Result<Config> {
-    if !std::path::Path::new(RESOLV_CONF_PATH).exists() {
-        return Ok(Config::new());
+/// Guess which software wrote resolv.conf from the comments that DNS managers and DHCP clients
+/// put at the top of the files they generate, or from where the file links to.
+fn identify_source(contents: Option<&str>, link_target: Option<&Path>) -> Option<&'static str> {
+    let link_target = link_target.map(|target| target.to_string_lossy().to_lowercase());
+    let comments = contents
+        .unwrap_or_default()
+        .lines()
+        .map(str::trim_start)
+        .filter(|line| line.starts_with('#') || line.starts_with(';'))
+        .map(str::to_lowercase);
+
+    link_target.into_iter().chain(comments).find_map(|text| {
+        KNOWN_SOURCES
+            .iter()
+            .find(|(keyword, _)| text.contains(keyword))
+            .map(|(_, name)| *name)
+    })
+}
+
+fn read_contents() -> Result<Option<String>> {
+    match fs::read_to_string(RESOLV_CONF_PATH) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(Error::ReadResolvConf(RESOLV_CONF_PATH, error)),
     }
+}
 
-    let contents = fs::read_to_string(RESOLV_CONF_PATH)
-        .map_err(|e| Error::ReadResolvConf(RESOLV_CONF_PATH, e))?;
-    let config = Config::parse(contents).map_err(|e| Error::Parse(RESOLV_CONF_PATH, e))?;
+fn parse_config(contents: Option<&str>) -> Result<Config> {
+    match contents {
+        Some(contents) => Config::parse(contents).map_err(|e| Error::Parse(RESOLV_CONF_PATH, e)),
+        None => Ok(Config::new()),
+    }
+}
 
-    Ok(config)
+fn read_config() -> Result<Config> {
+    parse_config(read_contents()?.as_deref())
 }
 
 fn write_config(config: &Config) -> Result<()> {
@@ -235,3 +325,32 @@ fn restore_from_backup() -> Result<()> {
         Err(error) => Err(Error::ReadResolvConf(RESOLV_CONF_BACKUP_PATH, error)),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_identify_source() {
+        let network_manager = "# Generated by NetworkManager\nsearch lan\nnameserver 192.168.1.1\n";
+        assert_eq!(
+            identify_source(Some(network_manager), None),
+            Some("NetworkManager")
+        );
+
+        let dhclient = "; generated by /usr/sbin/dhclient-script\nnameserver 10.0.0.1\n";
+        assert_eq!(identify_source(Some(dhclient), None), Some("dhclient"));
+
+        assert_eq!(
+            identify_source(
+                None,
+                Some(Path::new("/run/systemd/resolve/stub-resolv.conf"))
+            ),
+            Some("systemd-resolved")
+        );
+
+        // Search domains must not be mistaken for a generator
+        let unknown = "search networkmanager.example\nnameserver 10.0.0.1\n";
+        assert_eq!(identify_source(Some(unknown), None), None);
+    }
+}
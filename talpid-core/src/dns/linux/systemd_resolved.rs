@@ -1,8 +1,8 @@
 use crate::linux::{iface_index, IfaceIndexLookupError};
-use std::net::IpAddr;
-use talpid_dbus::systemd_resolved::{AsyncHandle, SystemdResolved as DbusInterface};
+use std::{collections::BTreeMap, net::IpAddr};
+use talpid_dbus::systemd_resolved::{AsyncHandle, DnsState, SystemdResolved as DbusInterface};
 use talpid_routing::RouteManagerHandle;
-use talpid_types::ErrorExt;
+use talpid_types::{dns::SplitDnsRule, ErrorExt};
 
 pub(crate) use talpid_dbus::systemd_resolved::Error as SystemdDbusError;
 
@@ -20,6 +20,13 @@ pub enum Error {
 pub struct SystemdResolved {
     pub dbus_interface: AsyncHandle,
     tunnel_index: u32,
+    split_dns_links: Vec<SplitDnsLink>,
+}
+
+/// A non-tunnel link that split DNS rules were applied to, along with its original settings.
+struct SplitDnsLink {
+    dns_state: DnsState,
+    domains: Vec<(String, bool)>,
 }
 
 impl SystemdResolved {
@@ -29,6 +36,7 @@ impl SystemdResolved {
         let systemd_resolved = SystemdResolved {
             dbus_interface,
             tunnel_index: 0,
+            split_dns_links: vec![],
         };
 
         Ok(systemd_resolved)
@@ -36,9 +44,10 @@ impl SystemdResolved {
 
     pub async fn set_dns(
         &mut self,
-        _route_manager: RouteManagerHandle,
+        route_manager: RouteManagerHandle,
         interface_name: &str,
         servers: &[IpAddr],
+        split_dns_rules: &[SplitDnsRule],
     ) -> Result<()> {
         let tunnel_index = iface_index(interface_name)?;
         self.tunnel_index = tunnel_index;
@@ -60,10 +69,116 @@ impl SystemdResolved {
             .set_dns(self.tunnel_index, servers.to_vec())
             .await?;
 
+        for (link_index, (servers, domains)) in
+            Self::split_dns_rules_by_link(&route_manager, split_dns_rules).await
+        {
+            if let Err(error) = self.set_split_dns(link_index, servers, &domains).await {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to apply split DNS rules")
+                );
+            }
+        }
+
         Ok(())
     }
 
+    /// Group the servers and domains of `rules` by the link that the servers are reached through.
+    /// systemd-resolved has a single list of servers per link, so rules whose servers share a
+    /// link also share their servers.
+    async fn split_dns_rules_by_link(
+        route_manager: &RouteManagerHandle,
+        rules: &[SplitDnsRule],
+    ) -> BTreeMap<u32, (Vec<IpAddr>, Vec<String>)> {
+        let mut links: BTreeMap<u32, (Vec<IpAddr>, Vec<String>)> = BTreeMap::new();
+        for rule in rules {
+            for server in &rule.servers {
+                let route = match route_manager.get_destination_route(*server, None).await {
+                    Ok(Some(route)) => route,
+                    Ok(None) => {
+                        log::warn!("No route to split DNS server {server}");
+                        continue;
+                    }
+                    Err(error) => {
+                        log::error!(
+                            "{}",
+                            error.display_chain_with_msg(&format!(
+                                "Failed to find route to split DNS server {server}"
+                            ))
+                        );
+                        continue;
+                    }
+                };
+                let Some(link_index) = route
+                    .get_node()
+                    .get_device()
+                    .and_then(|device| iface_index(device).ok())
+                else {
+                    log::warn!("Failed to find interface of split DNS server {server}");
+                    continue;
+                };
+                let (servers, domains) = links.entry(link_index).or_default();
+                if !servers.contains(server) {
+                    servers.push(*server);
+                }
+                if !domains.contains(&rule.domain) {
+                    domains.push(rule.domain.clone());
+                }
+            }
+        }
+        links
+    }
+
+    async fn set_split_dns(
+        &mut self,
+        link_index: u32,
+        servers: Vec<IpAddr>,
+        domains: &[String],
+    ) -> Result<()> {
+        let link = SplitDnsLink {
+            dns_state: self.dbus_interface.get_dns(link_index).await?,
+            domains: self.dbus_interface.get_domains(link_index).await?,
+        };
+        self.split_dns_links.push(link);
+
+        // Only use the link for the split domains. Everything else still goes to the tunnel
+        let routing_domains: Vec<(&str, bool)> = domains
+            .iter()
+            .map(|domain| (domain.as_str(), true))
+            .collect();
+        self.dbus_interface
+            .set_domains(link_index, &routing_domains)
+            .await?;
+        self.dbus_interface.set_dns(link_index, servers).await?;
+        Ok(())
+    }
+
+    async fn reset_split_dns(&mut self) {
+        for link in self.split_dns_links.drain(..) {
+            let link_index = link.dns_state.interface_index;
+            let domains: Vec<(&str, bool)> = link
+                .domains
+                .iter()
+                .map(|(domain, routing_only)| (domain.as_str(), *routing_only))
+                .collect();
+            if let Err(error) = self.dbus_interface.set_domains(link_index, &domains).await {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to restore split DNS link domains")
+                );
+            }
+            if let Err(error) = self.dbus_interface.set_dns_state(link.dns_state).await {
+                log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to restore split DNS link servers")
+                );
+            }
+        }
+    }
+
     pub async fn reset(&mut self) -> Result<()> {
+        self.reset_split_dns().await;
+
         if let Err(error) = self
             .dbus_interface
             .set_domains(self.tunnel_index, &[])
@@ -3,8 +3,9 @@
 use parking_lot::Mutex;
 use std::{
     collections::{BTreeSet, HashMap},
-    fmt, mem,
+    fmt, fs, io, mem,
     net::{IpAddr, SocketAddr},
+    path::Path,
     sync::{mpsc as sync_mpsc, Arc, RwLock},
     thread,
     time::Duration,
@@ -25,6 +26,7 @@ use system_configuration::{
     },
 };
 use talpid_routing::debounce::BurstGuard;
+use talpid_types::dns::SplitDnsRule;
 
 use super::ResolvedDnsConfig;
 
@@ -54,8 +56,18 @@ pub enum Error {
     /// Failed to load DNS config
     #[error("Failed to load DNS config at path {0}")]
     LoadDnsConfigError(String),
+
+    /// Failed to add or remove split DNS resolver files
+    #[error("Failed to update split DNS resolver files")]
+    SplitDns(#[source] io::Error),
 }
 
+/// Directory of per-domain resolver configurations. See `man 5 resolver`.
+const RESOLVER_DIR: &str = "/etc/resolver";
+/// First line of the resolver files created by us. Used to find and remove them, including files
+/// left behind if the daemon was stopped abruptly.
+const RESOLVER_FILE_MARKER: &str = "# Split DNS rule added by Mullvad VPN";
+
 const STATE_PATH_PATTERN: &str = "State:/Network/Service/.*/DNS";
 const SETUP_PATH_PATTERN: &str = "Setup:/Network/Service/.*/DNS";
 
@@ -413,17 +425,65 @@ impl super::DnsMonitorT for DnsMonitor {
     /// on the tunnel interface, we have to configure all interfaces.
     fn set(&mut self, interface: &str, config: ResolvedDnsConfig) -> Result<()> {
         let port = config.port;
+        let split_dns_rules = config.split_dns_rules().to_vec();
         let servers: Vec<_> = config.addresses().collect();
 
         let mut state = self.state.lock();
-        state.apply_new_config(&self.store, interface, &servers, port)
+        state.apply_new_config(&self.store, interface, &servers, port)?;
+        set_split_dns(&split_dns_rules)
     }
 
     fn reset(&mut self) -> Result<()> {
+        set_split_dns(&[])?;
         self.state.lock().reset(&self.store)
     }
 }
 
+/// Replace the resolver files created by us with one file per rule in `rules`.
+fn set_split_dns(rules: &[SplitDnsRule]) -> Result<()> {
+    remove_resolver_files().map_err(Error::SplitDns)?;
+    if rules.is_empty() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(RESOLVER_DIR).map_err(Error::SplitDns)?;
+    for rule in rules {
+        let path = Path::new(RESOLVER_DIR).join(&rule.domain);
+        if path.exists() {
+            log::warn!(
+                "Not replacing existing resolver file {}. Split DNS rule for {} is ignored",
+                path.display(),
+                rule.domain
+            );
+            continue;
+        }
+        let mut contents = format!("{RESOLVER_FILE_MARKER}\n");
+        for server in &rule.servers {
+            contents.push_str(&format!("nameserver {server}\n"));
+        }
+        fs::write(path, contents).map_err(Error::SplitDns)?;
+    }
+    Ok(())
+}
+
+fn remove_resolver_files() -> io::Result<()> {
+    let entries = match fs::read_dir(RESOLVER_DIR) {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(error) => return Err(error),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        let is_ours = fs::read_to_string(&path)
+            .map(|contents| contents.starts_with(RESOLVER_FILE_MARKER))
+            .unwrap_or(false);
+        if is_ours {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
 impl DnsMonitor {
     /// Spawns the background thread running the CoreFoundation main loop and monitors the system
     /// for DNS changes.
@@ -1,8 +1,17 @@
 use std::fmt;
 use std::net::IpAddr;
+use std::sync::Arc;
+use talpid_types::{
+    dns::{EncryptedDnsServer, SplitDnsRule},
+    net::nat64_address,
+};
 
+#[cfg(target_os = "linux")]
+use futures::channel::mpsc;
 #[cfg(target_os = "linux")]
 use talpid_routing::RouteManagerHandle;
+#[cfg(target_os = "linux")]
+use talpid_types::dns::{DnsBackend, DnsInterference};
 
 #[cfg(target_os = "macos")]
 #[path = "macos.rs"]
@@ -13,7 +22,7 @@ mod imp;
 mod imp;
 
 #[cfg(target_os = "linux")]
-pub use imp::will_use_nm;
+pub use imp::{active_backend, will_use_nm};
 
 #[cfg(windows)]
 #[path = "windows/mod.rs"]
@@ -25,16 +34,40 @@ mod imp;
 
 pub use self::imp::Error;
 
+mod blocklist;
+
+pub use blocklist::DomainBlocklist;
+
+#[cfg(not(target_os = "android"))]
+mod bypass;
+
+#[cfg(not(target_os = "android"))]
+pub use bypass::{BypassUpdateTx, BypassedDomains};
+
+#[cfg(not(target_os = "android"))]
+mod forwarder;
+
+#[cfg(not(target_os = "android"))]
+pub use forwarder::{DnsForwarder, Error as DnsForwarderError, Upstream as DnsForwarderUpstream};
+
 /// DNS configuration
 #[derive(Debug, Clone, PartialEq)]
 pub struct DnsConfig {
     config: InnerDnsConfig,
+    split_dns_rules: Vec<SplitDnsRule>,
+    blocked_domains: Arc<DomainBlocklist>,
+    #[cfg(not(target_os = "android"))]
+    bypassed_domains: Arc<BypassedDomains>,
 }
 
 impl Default for DnsConfig {
     fn default() -> Self {
         Self {
             config: InnerDnsConfig::Default,
+            split_dns_rules: vec![],
+            blocked_domains: Arc::default(),
+            #[cfg(not(target_os = "android"))]
+            bypassed_domains: Arc::default(),
         }
     }
 }
@@ -47,6 +80,77 @@ impl DnsConfig {
                 tunnel_config: tunnel_config.to_owned(),
                 non_tunnel_config: non_tunnel_config.to_owned(),
             },
+            split_dns_rules: vec![],
+            blocked_domains: Arc::default(),
+            #[cfg(not(target_os = "android"))]
+            bypassed_domains: Arc::default(),
+        }
+    }
+
+    /// Resolve all queries using the specified encrypted DNS servers. On desktop platforms, this
+    /// is done by a local forwarder that listens on loopback.
+    pub fn from_encrypted_servers(servers: &[EncryptedDnsServer]) -> Self {
+        DnsConfig {
+            config: InnerDnsConfig::Encrypted {
+                servers: servers.to_owned(),
+            },
+            split_dns_rules: vec![],
+            blocked_domains: Arc::default(),
+            #[cfg(not(target_os = "android"))]
+            bypassed_domains: Arc::default(),
+        }
+    }
+
+    /// Send queries for the domains in `rules` to their servers on the local network, instead
+    /// of to the servers used for everything else.
+    pub fn with_split_dns_rules(mut self, rules: &[SplitDnsRule]) -> Self {
+        self.split_dns_rules = rules.to_owned();
+        self
+    }
+
+    /// Refuse to resolve the domains in `blocklist`. On desktop platforms, this is done by a
+    /// local forwarder that all queries are sent through.
+    pub fn with_blocked_domains(mut self, blocklist: Arc<DomainBlocklist>) -> Self {
+        self.blocked_domains = blocklist;
+        self
+    }
+
+    /// Return the domains that must not be resolved.
+    pub fn blocked_domains(&self) -> &Arc<DomainBlocklist> {
+        &self.blocked_domains
+    }
+
+    /// Route traffic to the addresses that the domains in `domains` resolve to outside the
+    /// tunnel. This is done by a local forwarder that all queries are sent through, and is only
+    /// supported on Linux.
+    #[cfg(not(target_os = "android"))]
+    pub fn with_bypassed_domains(mut self, domains: Arc<BypassedDomains>) -> Self {
+        self.bypassed_domains = domains;
+        self
+    }
+
+    /// Return the domains whose traffic is routed outside the tunnel.
+    #[cfg(not(target_os = "android"))]
+    pub fn bypassed_domains(&self) -> &Arc<BypassedDomains> {
+        &self.bypassed_domains
+    }
+
+    /// Return the encrypted DNS servers that queries should be forwarded to, if any.
+    pub fn encrypted_servers(&self) -> &[EncryptedDnsServer] {
+        match &self.config {
+            InnerDnsConfig::Encrypted { servers } => servers,
+            InnerDnsConfig::Default | InnerDnsConfig::Override { .. } => &[],
+        }
+    }
+
+    /// Return the addresses to configure on the tunnel interface, or `None` if the tunnel
+    /// gateways should be used.
+    #[cfg(target_os = "linux")]
+    pub(crate) fn custom_tunnel_config(&self) -> Option<&[IpAddr]> {
+        match &self.config {
+            InnerDnsConfig::Default => None,
+            InnerDnsConfig::Override { tunnel_config, .. } => Some(tunnel_config),
+            InnerDnsConfig::Encrypted { .. } => Some(&[]),
         }
     }
 }
@@ -64,6 +168,11 @@ enum InnerDnsConfig {
         /// on non-tunnel interface, only allow them in the firewall.
         non_tunnel_config: Vec<IpAddr>,
     },
+    /// Forward queries to encrypted DNS servers
+    Encrypted {
+        /// Servers to use for DNS resolution
+        servers: Vec<EncryptedDnsServer>,
+    },
 }
 
 impl DnsConfig {
@@ -72,22 +181,30 @@ impl DnsConfig {
         default_tun_config: &[IpAddr],
         #[cfg(target_os = "macos")] port: u16,
     ) -> ResolvedDnsConfig {
-        match &self.config {
-            InnerDnsConfig::Default => ResolvedDnsConfig {
-                tunnel_config: default_tun_config.to_owned(),
-                non_tunnel_config: vec![],
-                #[cfg(target_os = "macos")]
-                port,
-            },
+        let (tunnel_config, non_tunnel_config) = match &self.config {
+            InnerDnsConfig::Default => (default_tun_config.to_owned(), vec![]),
             InnerDnsConfig::Override {
                 tunnel_config,
                 non_tunnel_config,
-            } => ResolvedDnsConfig {
-                tunnel_config: tunnel_config.to_owned(),
-                non_tunnel_config: non_tunnel_config.to_owned(),
-                #[cfg(target_os = "macos")]
-                port,
-            },
+            } => (tunnel_config.to_owned(), non_tunnel_config.to_owned()),
+            // Point the tunnel interface at the local forwarder. The DNS monitors only configure
+            // the tunnel config
+            #[cfg(not(target_os = "android"))]
+            InnerDnsConfig::Encrypted { .. } => (vec![forwarder::FORWARDER_ADDR.ip()], vec![]),
+            #[cfg(target_os = "android")]
+            InnerDnsConfig::Encrypted { .. } => {
+                log::warn!("Encrypted DNS is not supported. Using the default DNS servers");
+                (default_tun_config.to_owned(), vec![])
+            }
+        };
+        ResolvedDnsConfig {
+            tunnel_config,
+            non_tunnel_config,
+            split_dns_rules: self.split_dns_rules.clone(),
+            upstream_tunnel_config: vec![],
+            upstream_non_tunnel_config: vec![],
+            #[cfg(target_os = "macos")]
+            port,
         }
     }
 }
@@ -101,6 +218,13 @@ pub struct ResolvedDnsConfig {
     /// For the most part, the tunnel state machine will not handle any of this configuration
     /// on non-tunnel interface, only allow them in the firewall.
     non_tunnel_config: Vec<IpAddr>,
+    /// Domains whose queries are sent to servers on the local network
+    split_dns_rules: Vec<SplitDnsRule>,
+    /// Plaintext servers that the local forwarder sends queries to through the tunnel, when the
+    /// tunnel config points at the forwarder
+    upstream_tunnel_config: Vec<IpAddr>,
+    /// Plaintext servers that the local forwarder sends queries to outside the tunnel
+    upstream_non_tunnel_config: Vec<IpAddr>,
     /// Port to use
     #[cfg(target_os = "macos")]
     port: u16,
@@ -114,6 +238,17 @@ impl fmt::Display for ResolvedDnsConfig {
         f.write_str(" Non-tunnel DNS: ")?;
         Self::fmt_addr_set(f, &self.non_tunnel_config)?;
 
+        for rule in &self.split_dns_rules {
+            write!(f, " Split DNS: {rule}")?;
+        }
+
+        if !self.upstream_tunnel_config.is_empty() || !self.upstream_non_tunnel_config.is_empty() {
+            f.write_str(" Forwarded tunnel DNS: ")?;
+            Self::fmt_addr_set(f, &self.upstream_tunnel_config)?;
+            f.write_str(" Forwarded non-tunnel DNS: ")?;
+            Self::fmt_addr_set(f, &self.upstream_non_tunnel_config)?;
+        }
+
         #[cfg(target_os = "macos")]
         write!(f, " Port: {}", self.port)?;
 
@@ -145,6 +280,101 @@ impl ResolvedDnsConfig {
         &self.non_tunnel_config
     }
 
+    /// Domains whose queries should be sent to servers on the local network.
+    pub fn split_dns_rules(&self) -> &[SplitDnsRule] {
+        &self.split_dns_rules
+    }
+
+    /// Servers of all split DNS rules. Like the non-tunnel config, these must be allowed on
+    /// non-tunnel interfaces.
+    pub fn split_dns_servers(&self) -> impl Iterator<Item = &IpAddr> {
+        self.split_dns_rules
+            .iter()
+            .flat_map(|rule| rule.servers.iter())
+    }
+
+    /// All servers that DNS requests may be sent to inside the tunnel. This includes the servers
+    /// that the local forwarder sends queries to.
+    pub fn allowed_tunnel_servers(&self) -> impl Iterator<Item = &IpAddr> {
+        self.tunnel_config
+            .iter()
+            .chain(self.upstream_tunnel_config.iter())
+    }
+
+    /// All servers that DNS requests may be sent to outside the tunnel. This includes the servers
+    /// of split DNS rules and the servers that the local forwarder sends queries to.
+    pub fn allowed_non_tunnel_servers(&self) -> impl Iterator<Item = &IpAddr> {
+        self.non_tunnel_config
+            .iter()
+            .chain(self.upstream_non_tunnel_config.iter())
+            .chain(self.split_dns_servers())
+    }
+
+    /// Plaintext servers that the local forwarder should send queries to, if any.
+    pub fn upstream_servers(&self) -> impl Iterator<Item = &IpAddr> {
+        self.upstream_tunnel_config
+            .iter()
+            .chain(self.upstream_non_tunnel_config.iter())
+    }
+
+    /// Send all queries through the local forwarder, which then sends them to the configured
+    /// servers. Does nothing if the config already points at the forwarder.
+    #[cfg(not(target_os = "android"))]
+    pub(crate) fn with_local_forwarder(mut self) -> Self {
+        let forwarder = forwarder::FORWARDER_ADDR.ip();
+        if self.tunnel_config.contains(&forwarder) {
+            return self;
+        }
+        self.upstream_tunnel_config = std::mem::replace(&mut self.tunnel_config, vec![forwarder]);
+        self.upstream_non_tunnel_config = std::mem::take(&mut self.non_tunnel_config);
+        self
+    }
+
+    /// Translate IPv4 servers on the tunnel interface to their NAT64 addresses. This is needed when
+    /// the tunnel only has IPv6 addresses. Loopback servers are kept as they are. Other servers
+    /// that cannot be reached through NAT64 are dropped, and `fallback` is used if no servers
+    /// remain.
+    pub(crate) fn translate_to_nat64(mut self, fallback: &[IpAddr]) -> Self {
+        let servers = self.tunnel_config.len();
+        self.tunnel_config = self
+            .tunnel_config
+            .into_iter()
+            .filter_map(|addr| match addr {
+                IpAddr::V4(addr) if addr.is_loopback() => Some(IpAddr::V4(addr)),
+                IpAddr::V4(addr) => nat64_address(addr).map(IpAddr::from),
+                IpAddr::V6(_) => Some(addr),
+            })
+            .collect();
+        if self.tunnel_config.len() < servers {
+            log::warn!("Some DNS servers cannot be reached through NAT64 and are ignored");
+            if self.tunnel_config.is_empty() {
+                self.tunnel_config = fallback.to_owned();
+            }
+        }
+        self
+    }
+
+    /// Drop all IPv6 servers except loopback addresses. This is needed when IPv6 is blocked by the
+    /// firewall. `fallback` is used if no servers remain on the tunnel interface.
+    #[cfg(not(target_os = "android"))]
+    pub(crate) fn without_ipv6(mut self, fallback: &[IpAddr]) -> Self {
+        let servers = self.tunnel_config.len() + self.non_tunnel_config.len();
+        let keep = |addr: &IpAddr| addr.is_ipv4() || addr.is_loopback();
+        self.tunnel_config.retain(keep);
+        self.non_tunnel_config.retain(keep);
+        for rule in &mut self.split_dns_rules {
+            rule.servers.retain(IpAddr::is_ipv4);
+        }
+        self.split_dns_rules.retain(|rule| !rule.servers.is_empty());
+        if self.tunnel_config.len() + self.non_tunnel_config.len() < servers {
+            log::warn!("Ignoring IPv6 DNS servers since IPv6 is blocked");
+            if self.tunnel_config.is_empty() && self.non_tunnel_config.is_empty() {
+                self.tunnel_config = fallback.iter().copied().filter(keep).collect();
+            }
+        }
+        self
+    }
+
     /// Consume `self` and return a vector of all addresses
     pub fn addresses(self) -> impl Iterator<Item = IpAddr> {
         self.non_tunnel_config.into_iter().chain(self.tunnel_config)
@@ -171,9 +401,15 @@ pub struct DnsMonitor {
 
 impl DnsMonitor {
     /// Returns a new `DnsMonitor` that can set and monitor the system DNS.
+    ///
+    /// On Linux, `interference_tx` is notified whenever another process overwrites the DNS
+    /// configuration and it has to be restored. The first of `backends` that can manage DNS is
+    /// used, or the first in [DnsBackend::DEFAULT_ORDER] if it is empty.
     pub fn new(
         #[cfg(target_os = "linux")] handle: tokio::runtime::Handle,
         #[cfg(target_os = "linux")] route_manager: RouteManagerHandle,
+        #[cfg(target_os = "linux")] interference_tx: mpsc::UnboundedSender<DnsInterference>,
+        #[cfg(target_os = "linux")] backends: Vec<DnsBackend>,
     ) -> Result<Self, Error> {
         Ok(DnsMonitor {
             inner: imp::DnsMonitor::new(
@@ -181,6 +417,10 @@ impl DnsMonitor {
                 handle,
                 #[cfg(target_os = "linux")]
                 route_manager,
+                #[cfg(target_os = "linux")]
+                interference_tx,
+                #[cfg(target_os = "linux")]
+                backends,
             )?,
         })
     }
@@ -213,6 +453,8 @@ trait DnsMonitorT: Sized {
     fn new(
         #[cfg(target_os = "linux")] handle: tokio::runtime::Handle,
         #[cfg(target_os = "linux")] route_manager: RouteManagerHandle,
+        #[cfg(target_os = "linux")] interference_tx: mpsc::UnboundedSender<DnsInterference>,
+        #[cfg(target_os = "linux")] backends: Vec<DnsBackend>,
     ) -> Result<Self, Self::Error>;
 
     fn set(&mut self, interface: &str, servers: ResolvedDnsConfig) -> Result<(), Self::Error>;
@@ -1,11 +1,13 @@
-use std::{env, fmt};
+use std::{env, fmt, io};
 
 use super::{DnsMonitorT, ResolvedDnsConfig};
+use talpid_types::dns::SplitDnsRule;
 
 mod auto;
 mod dnsapi;
 mod iphlpapi;
 mod netsh;
+mod nrpt;
 mod tcpip;
 
 /// Errors that can happen when configuring DNS on Windows.
@@ -22,6 +24,14 @@ pub enum Error {
     /// Failed to set DNS config using the tcpip module.
     #[error("Error in tcpip module")]
     Tcpip(#[from] tcpip::Error),
+
+    /// Failed to add or remove split DNS rules.
+    #[error("Failed to update split DNS rules")]
+    SplitDns(#[source] io::Error),
+
+    /// Failed to flush the DNS cache after updating split DNS rules.
+    #[error("Failed to flush DNS resolver cache")]
+    FlushResolverCache(#[source] dnsapi::Error),
 }
 
 pub struct DnsMonitor {
@@ -47,16 +57,18 @@ impl DnsMonitorT for DnsMonitor {
     }
 
     fn set(&mut self, interface: &str, config: ResolvedDnsConfig) -> Result<(), Error> {
+        let split_dns_rules = config.split_dns_rules().to_vec();
         match self.inner {
             DnsMonitorHolder::Auto(ref mut inner) => inner.set(interface, config)?,
             DnsMonitorHolder::Iphlpapi(ref mut inner) => inner.set(interface, config)?,
             DnsMonitorHolder::Netsh(ref mut inner) => inner.set(interface, config)?,
             DnsMonitorHolder::Tcpip(ref mut inner) => inner.set(interface, config)?,
         }
-        Ok(())
+        Self::set_split_dns(&split_dns_rules)
     }
 
     fn reset(&mut self) -> Result<(), Error> {
+        Self::set_split_dns(&[])?;
         match self.inner {
             DnsMonitorHolder::Auto(ref mut inner) => inner.reset()?,
             DnsMonitorHolder::Iphlpapi(ref mut inner) => inner.reset()?,
@@ -67,6 +79,8 @@ impl DnsMonitorT for DnsMonitor {
     }
 
     fn reset_before_interface_removal(&mut self) -> Result<(), Error> {
+        // NRPT rules are not tied to the tunnel interface
+        Self::set_split_dns(&[])?;
         match self.inner {
             DnsMonitorHolder::Auto(ref mut inner) => inner.reset_before_interface_removal()?,
             DnsMonitorHolder::Iphlpapi(ref mut inner) => inner.reset_before_interface_removal()?,
@@ -77,6 +91,18 @@ impl DnsMonitorT for DnsMonitor {
     }
 }
 
+impl DnsMonitor {
+    /// Replace the NRPT rules with `rules`, and flush cached responses for the old rules.
+    fn set_split_dns(rules: &[SplitDnsRule]) -> Result<(), Error> {
+        let removed_rules = nrpt::remove_rules().map_err(Error::SplitDns)?;
+        nrpt::add_rules(rules).map_err(Error::SplitDns)?;
+        if removed_rules || !rules.is_empty() {
+            dnsapi::flush_resolver_cache().map_err(Error::FlushResolverCache)?;
+        }
+        Ok(())
+    }
+}
+
 enum DnsMonitorHolder {
     Auto(auto::DnsMonitor),
     Iphlpapi(iphlpapi::DnsMonitor),
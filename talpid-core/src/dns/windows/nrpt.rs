@@ -0,0 +1,63 @@
+//! Split DNS using the Name Resolution Policy Table (NRPT). Each rule is a registry key that tells
+//! the DNS client service to send queries for a domain to specific servers.
+
+use std::io;
+use talpid_types::dns::SplitDnsRule;
+use winreg::{
+    enums::{HKEY_LOCAL_MACHINE, KEY_READ, KEY_WRITE},
+    RegKey,
+};
+
+const DNS_POLICY_CONFIG_PATH: &str =
+    r"SYSTEM\CurrentControlSet\Services\Dnscache\Parameters\DnsPolicyConfig";
+/// Prefix of the NRPT rules created by us. Used to find and remove them, including rules left
+/// behind if the daemon was stopped abruptly.
+const RULE_PREFIX: &str = "MullvadSplitDns-";
+
+/// Only use the generic DNS servers of the rule.
+const CONFIG_OPTIONS_GENERIC_DNS: u32 = 0x8;
+const RULE_VERSION: u32 = 0x2;
+
+/// Add an NRPT rule for each split DNS rule.
+pub fn add_rules(rules: &[SplitDnsRule]) -> io::Result<()> {
+    let (policy_config, _) =
+        RegKey::predef(HKEY_LOCAL_MACHINE).create_subkey(DNS_POLICY_CONFIG_PATH)?;
+    for (index, rule) in rules.iter().enumerate() {
+        let (key, _) = policy_config.create_subkey(format!("{RULE_PREFIX}{index}"))?;
+        let servers = rule
+            .servers
+            .iter()
+            .map(|server| server.to_string())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        // A leading dot matches the domain and all of its subdomains
+        key.set_value("Name", &vec![format!(".{}", rule.domain)])?;
+        key.set_value("GenericDNSServers", &servers)?;
+        key.set_value("ConfigOptions", &CONFIG_OPTIONS_GENERIC_DNS)?;
+        key.set_value("Version", &RULE_VERSION)?;
+        key.set_value("IPSECCARestriction", &"")?;
+    }
+    Ok(())
+}
+
+/// Remove all NRPT rules added by [add_rules]. Returns whether any rules were removed.
+pub fn remove_rules() -> io::Result<bool> {
+    let policy_config = match RegKey::predef(HKEY_LOCAL_MACHINE)
+        .open_subkey_with_flags(DNS_POLICY_CONFIG_PATH, KEY_READ | KEY_WRITE)
+    {
+        Ok(policy_config) => policy_config,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(error) => return Err(error),
+    };
+    let rule_keys: Vec<String> = policy_config
+        .enum_keys()
+        .collect::<io::Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|name| name.starts_with(RULE_PREFIX))
+        .collect();
+    for name in &rule_keys {
+        policy_config.delete_subkey_all(name)?;
+    }
+    Ok(!rule_keys.is_empty())
+}
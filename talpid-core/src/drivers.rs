@@ -0,0 +1,282 @@
+//! Wintun and WireGuardNT are installed on demand by their respective DLLs when an adapter is
+//! created, and the split tunnel driver is installed by the daemon when it starts. A driver
+//! that is stuck is therefore repaired by removing its service, which causes it to be
+//! reinstalled from the bundled image the next time it is needed.
+
+use std::{
+    ffi::{OsStr, OsString},
+    io,
+    os::windows::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use talpid_types::drivers::{Driver, DriverRepairResult, DriverServiceState, DriverStatus};
+use windows_service::{
+    service::{Service, ServiceAccess, ServiceState},
+    service_manager::{ServiceManager, ServiceManagerAccess},
+};
+use windows_sys::Win32::{
+    Foundation::{ERROR_SERVICE_DOES_NOT_EXIST, ERROR_SERVICE_MARKED_FOR_DELETE},
+    Storage::FileSystem::{
+        GetFileVersionInfoSizeW, GetFileVersionInfoW, VerQueryValueW, VS_FIXEDFILEINFO,
+    },
+};
+
+const WINTUN_SERVICE: &str = "Wintun";
+const WIREGUARD_NT_SERVICE: &str = "WireGuard";
+
+const STOP_TIMEOUT: Duration = Duration::from_secs(8);
+
+/// Errors that can occur while diagnosing or repairing drivers.
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Failed to open service control manager
+    #[error("Failed to connect to service control manager")]
+    OpenServiceControlManager(#[source] windows_service::Error),
+
+    /// Failed to create a service handle
+    #[error("Failed to open service for {0} driver")]
+    OpenServiceHandle(Driver, #[source] windows_service::Error),
+
+    /// Failed to check service status
+    #[error("Failed to query service status for {0} driver")]
+    QueryServiceStatus(Driver, #[source] windows_service::Error),
+
+    /// Failed to delete the driver service
+    #[error("Failed to delete service for {0} driver")]
+    DeleteService(Driver, #[source] windows_service::Error),
+}
+
+/// Return the status of all drivers used by the daemon.
+pub fn query_all() -> Result<Vec<DriverStatus>, Error> {
+    let scm = open_scm()?;
+    Driver::ALL
+        .into_iter()
+        .map(|driver| query_status(&scm, driver))
+        .collect()
+}
+
+/// Remove the service of `driver` so that it is reinstalled from the bundled image the next time
+/// it is needed. The tunnel must not be using the driver when this is called.
+pub fn repair(driver: Driver) -> Result<DriverRepairResult, Error> {
+    log::info!("Removing {driver} driver service");
+
+    let scm = open_scm()?;
+    match open_service(&scm, driver, ServiceAccess::all())? {
+        Some(service) => {
+            if let Err(error) = stop_service(&service) {
+                log::warn!("Failed to stop {driver} driver service: {error}");
+            }
+            match service.delete() {
+                Ok(()) => (),
+                Err(windows_service::Error::Winapi(io_error))
+                    if io_error.raw_os_error() == Some(ERROR_SERVICE_MARKED_FOR_DELETE as i32) => {}
+                Err(error) => return Err(Error::DeleteService(driver, error)),
+            }
+        }
+        None => log::debug!("{driver} driver service is not installed"),
+    }
+
+    let status = query_status(&scm, driver)?;
+    // The split tunnel driver is only installed when the daemon starts, and it cannot be
+    // unloaded while the daemon holds a handle to it.
+    let restart_required = driver == Driver::SplitTunnel;
+
+    Ok(DriverRepairResult {
+        status,
+        restart_required,
+    })
+}
+
+fn open_scm() -> Result<ServiceManager, Error> {
+    ServiceManager::local_computer(None::<OsString>, ServiceManagerAccess::CONNECT)
+        .map_err(Error::OpenServiceControlManager)
+}
+
+fn service_name(driver: Driver) -> &'static str {
+    match driver {
+        Driver::Wintun => WINTUN_SERVICE,
+        Driver::WireguardNt => WIREGUARD_NT_SERVICE,
+        Driver::SplitTunnel => crate::split_tunnel::SPLIT_TUNNEL_SERVICE,
+    }
+}
+
+/// Open the service for `driver`, or return `None` if it does not exist.
+fn open_service(
+    scm: &ServiceManager,
+    driver: Driver,
+    access: ServiceAccess,
+) -> Result<Option<Service>, Error> {
+    match scm.open_service(service_name(driver), access) {
+        Ok(service) => Ok(Some(service)),
+        Err(windows_service::Error::Winapi(io_error))
+            if io_error.raw_os_error() == Some(ERROR_SERVICE_DOES_NOT_EXIST as i32) =>
+        {
+            Ok(None)
+        }
+        Err(error) => Err(Error::OpenServiceHandle(driver, error)),
+    }
+}
+
+fn query_status(scm: &ServiceManager, driver: Driver) -> Result<DriverStatus, Error> {
+    let Some(service) = open_service(
+        scm,
+        driver,
+        ServiceAccess::QUERY_STATUS | ServiceAccess::QUERY_CONFIG,
+    )?
+    else {
+        return Ok(DriverStatus {
+            driver,
+            state: DriverServiceState::NotInstalled,
+            version: None,
+            image_path: None,
+        });
+    };
+
+    let state = match service.query_status() {
+        Ok(status) => map_service_state(status.current_state),
+        Err(windows_service::Error::Winapi(io_error))
+            if io_error.raw_os_error() == Some(ERROR_SERVICE_MARKED_FOR_DELETE as i32) =>
+        {
+            DriverServiceState::MarkedForDeletion
+        }
+        Err(error) => return Err(Error::QueryServiceStatus(driver, error)),
+    };
+
+    let image_path = match service.query_config() {
+        Ok(config) => Some(normalize_driver_path(&config.executable_path)),
+        Err(error) => {
+            log::debug!("Failed to query config for {driver} driver service: {error}");
+            None
+        }
+    };
+    let version = image_path.as_deref().and_then(|path| {
+        file_version(path)
+            .inspect_err(|error| {
+                log::debug!("Failed to read version of {}: {error}", path.display())
+            })
+            .ok()
+    });
+
+    Ok(DriverStatus {
+        driver,
+        state,
+        version,
+        image_path,
+    })
+}
+
+fn stop_service(service: &Service) -> Result<(), windows_service::Error> {
+    if service.query_status()?.current_state == ServiceState::Stopped {
+        return Ok(());
+    }
+    service.stop()?;
+
+    let started = Instant::now();
+    while started.elapsed() < STOP_TIMEOUT {
+        if service.query_status()?.current_state == ServiceState::Stopped {
+            return Ok(());
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+    Err(windows_service::Error::Winapi(io::Error::new(
+        io::ErrorKind::TimedOut,
+        "Timed out waiting for service to stop",
+    )))
+}
+
+fn map_service_state(state: ServiceState) -> DriverServiceState {
+    match state {
+        ServiceState::Stopped => DriverServiceState::Stopped,
+        ServiceState::StartPending => DriverServiceState::StartPending,
+        ServiceState::StopPending => DriverServiceState::StopPending,
+        ServiceState::Running => DriverServiceState::Running,
+        ServiceState::ContinuePending => DriverServiceState::ContinuePending,
+        ServiceState::PausePending => DriverServiceState::PausePending,
+        ServiceState::Paused => DriverServiceState::Paused,
+    }
+}
+
+/// Kernel driver services store NT-style image paths, such as
+/// `\SystemRoot\System32\drivers\wintun.sys` or `\??\C:\...`. Convert these to Win32 paths.
+fn normalize_driver_path(path: &Path) -> PathBuf {
+    let path_str = path.to_string_lossy();
+    if let Some(rest) = path_str.strip_prefix(r"\??\") {
+        return PathBuf::from(rest);
+    }
+
+    const SYSTEM_ROOT_PREFIX: &str = r"\systemroot\";
+    let relative_to_system_root = if path_str
+        .get(..SYSTEM_ROOT_PREFIX.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(SYSTEM_ROOT_PREFIX))
+    {
+        Some(&path_str[SYSTEM_ROOT_PREFIX.len()..])
+    } else if path.is_relative() {
+        Some(&path_str[..])
+    } else {
+        None
+    };
+
+    match relative_to_system_root {
+        Some(rest) => {
+            let system_root =
+                std::env::var_os("SystemRoot").unwrap_or_else(|| OsString::from(r"C:\Windows"));
+            PathBuf::from(system_root).join(rest)
+        }
+        None => path.to_path_buf(),
+    }
+}
+
+/// Read the file version from the version resource of the file at `path`.
+fn file_version(path: &Path) -> io::Result<String> {
+    let wide_path: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let root_block: Vec<u16> = OsStr::new("\\")
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+
+    // SAFETY: `wide_path` is a valid null-terminated string.
+    let size = unsafe { GetFileVersionInfoSizeW(wide_path.as_ptr(), std::ptr::null_mut()) };
+    if size == 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut buffer = vec![0u8; size as usize];
+    // SAFETY: `buffer` is `size` bytes long.
+    if unsafe { GetFileVersionInfoW(wide_path.as_ptr(), 0, size, buffer.as_mut_ptr().cast()) } == 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut info: *mut std::ffi::c_void = std::ptr::null_mut();
+    let mut info_len = 0u32;
+    // SAFETY: `buffer` contains a version resource, and `root_block` is null-terminated.
+    if unsafe {
+        VerQueryValueW(
+            buffer.as_ptr().cast(),
+            root_block.as_ptr(),
+            &mut info,
+            &mut info_len,
+        )
+    } == 0
+        || (info_len as usize) < std::mem::size_of::<VS_FIXEDFILEINFO>()
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Missing fixed file info",
+        ));
+    }
+
+    // SAFETY: `info` points to a `VS_FIXEDFILEINFO` inside `buffer`.
+    let info = unsafe { &*(info as *const VS_FIXEDFILEINFO) };
+    Ok(format!(
+        "{}.{}.{}.{}",
+        info.dwFileVersionMS >> 16,
+        info.dwFileVersionMS & 0xffff,
+        info.dwFileVersionLS >> 16,
+        info.dwFileVersionLS & 0xffff,
+    ))
+}
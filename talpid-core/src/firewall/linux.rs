@@ -9,12 +9,17 @@ use std::{
     env,
     ffi::CStr,
     fs, io,
-    net::{IpAddr, Ipv4Addr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
     sync::LazyLock,
 };
-use talpid_types::net::{
-    AllowedEndpoint, AllowedTunnelTraffic, Endpoint, TransportProtocol, ALLOWED_LAN_MULTICAST_NETS,
-    ALLOWED_LAN_NETS,
+use talpid_types::{
+    net::{
+        AllowedEndpoint, AllowedIncoming, AllowedLan, AllowedTunnelTraffic, Endpoint,
+        FirewallException, LanNetwork, TransportProtocol, ALLOWED_LAN_MULTICAST_NETS,
+        ALLOWED_LAN_NETS,
+    },
+    split_tunnel::{SplitTunnelMode, SplitTunnelOwner},
+    ErrorExt,
 };
 
 /// Priority for rules that tag split tunneling packets. Equals NF_IP_PRI_MANGLE.
@@ -95,23 +100,35 @@ enum End {
     Dst,
 }
 
+/// How the firewall rules are installed alongside other nftables tables.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct NftablesOptions {
+    /// Hook priority of the input, output and forward chains. Base chains with a lower priority
+    /// are evaluated first, regardless of which table they belong to.
+    pub priority: i32,
+    /// Accept forwarded traffic that no rule matches, so that it is left to other tables. Only
+    /// traffic on this host is subject to the kill switch then.
+    pub cooperative: bool,
+}
+
 /// The Linux implementation for the firewall and DNS.
 pub struct Firewall {
     fwmark: u32,
+    nftables: NftablesOptions,
 }
 
 impl Firewall {
     pub fn from_args(args: FirewallArguments) -> Result<Self> {
-        Firewall::new(args.fwmark)
+        Firewall::new(args.fwmark, args.nftables)
     }
 
-    pub fn new(fwmark: u32) -> Result<Self> {
-        Ok(Firewall { fwmark })
+    pub fn new(fwmark: u32, nftables: NftablesOptions) -> Result<Self> {
+        Ok(Firewall { fwmark, nftables })
     }
 
     pub fn apply_policy(&mut self, policy: FirewallPolicy) -> Result<()> {
         let table = Table::new(&TABLE_NAME, ProtoFamily::Inet);
-        let batch = PolicyBatch::new(&table).finalize(&policy, self.fwmark)?;
+        let batch = PolicyBatch::new(&table, self.nftables).finalize(&policy, self.fwmark)?;
         Self::send_and_process(&batch)?;
         Self::apply_kernel_config(&policy);
         self.verify_tables(&[TABLE_NAME])
@@ -246,7 +263,7 @@ struct PolicyBatch<'a> {
 impl<'a> PolicyBatch<'a> {
     /// Bootstrap a new nftnl message batch object and add the initial messages creating the
     /// table and chains.
-    pub fn new(table: &'a Table) -> Self {
+    pub fn new(table: &'a Table, nftables: NftablesOptions) -> Self {
         let mut batch = Batch::new();
 
         batch_deprecated_tables(&mut batch);
@@ -262,18 +279,22 @@ impl<'a> PolicyBatch<'a> {
         batch.add(&prerouting_chain, nftnl::MsgType::Add);
 
         let mut out_chain = Chain::new(&OUT_CHAIN_NAME, table);
-        out_chain.set_hook(nftnl::Hook::Out, 0);
+        out_chain.set_hook(nftnl::Hook::Out, nftables.priority);
         out_chain.set_policy(nftnl::Policy::Drop);
         batch.add(&out_chain, nftnl::MsgType::Add);
 
         let mut in_chain = Chain::new(&IN_CHAIN_NAME, table);
-        in_chain.set_hook(nftnl::Hook::In, 0);
+        in_chain.set_hook(nftnl::Hook::In, nftables.priority);
         in_chain.set_policy(nftnl::Policy::Drop);
         batch.add(&in_chain, nftnl::MsgType::Add);
 
         let mut forward_chain = Chain::new(&FORWARD_CHAIN_NAME, table);
-        forward_chain.set_hook(nftnl::Hook::Forward, 0);
-        forward_chain.set_policy(nftnl::Policy::Drop);
+        forward_chain.set_hook(nftnl::Hook::Forward, nftables.priority);
+        forward_chain.set_policy(if nftables.cooperative {
+            nftnl::Policy::Accept
+        } else {
+            nftnl::Policy::Drop
+        });
         batch.add(&forward_chain, nftnl::MsgType::Add);
 
         let mut mangle_chain = Chain::new(&MANGLE_CHAIN_NAME, table);
@@ -303,6 +324,10 @@ impl<'a> PolicyBatch<'a> {
     /// policy.
     pub fn finalize(mut self, policy: &FirewallPolicy, fwmark: u32) -> Result<FinalizedBatch> {
         self.add_loopback_rules()?;
+        // Important to drop IPv6 before anything else is allowed
+        if policy.block_ipv6() {
+            self.add_drop_ipv6_rules();
+        }
         self.add_split_tunneling_rules(policy, fwmark)?;
         self.add_dhcp_client_rules();
         self.add_ndp_rules();
@@ -317,7 +342,7 @@ impl<'a> PolicyBatch<'a> {
             tunnel, dns_config, ..
         } = policy
         {
-            for server in dns_config.tunnel_config() {
+            for server in dns_config.allowed_tunnel_servers() {
                 let allow_rule = allow_tunnel_dns_rule(
                     &self.mangle_chain,
                     &tunnel.interface,
@@ -335,23 +360,52 @@ impl<'a> PolicyBatch<'a> {
             }
         }
 
+        // Traffic to the addresses of bypassed domains is marked like the traffic of split
+        // processes, regardless of the split tunnel mode
+        if let FirewallPolicy::Connected {
+            split_tunnel_addresses,
+            ..
+        } = policy
+        {
+            for address in split_tunnel_addresses {
+                let mut rule = Rule::new(&self.mangle_chain);
+                check_ip(&mut rule, End::Dst, *address);
+                add_split_tunnel_marks(&mut rule, fwmark);
+                self.batch.add(&rule, nftnl::MsgType::Add);
+            }
+        }
+
+        // Processes that run as a split user or group are treated like the processes in the
+        // cgroup below. In include-only mode, their packets are accepted before they can be
+        // marked, so that they use the tunnel.
+        for owner in policy.split_tunnel_owners() {
+            let mut rule = Rule::new(&self.mangle_chain);
+            check_owner(&mut rule, owner);
+            match policy.split_tunnel_mode() {
+                SplitTunnelMode::Exclude => add_split_tunnel_marks(&mut rule, fwmark),
+                SplitTunnelMode::IncludeOnly => add_verdict(&mut rule, &Verdict::Accept),
+            }
+            self.batch.add(&rule, nftnl::MsgType::Add);
+        }
+
         // Split tunneled processes have their PIDs added to a net_cls cgroup.
         // This causes all packets sent by that process to be marked with the
         // cgroups classid (`NET_CLS_CLASSID`). This rule checks incoming packets for that classid.
         // If the packet has the classid set then the packet will have two new marks applied to it.
         // The `split_tunnel::MARK` as a connection tracking mark and the `fwmark` as packet
-        // metadata.
+        // metadata. In include-only mode, the check is inverted so that every packet that is
+        // *not* sent by a split process is marked instead.
         let mut rule = Rule::new(&self.mangle_chain);
         rule.add_expr(&nft_expr!(meta cgroup));
-        rule.add_expr(&nft_expr!(cmp == split_tunnel::NET_CLS_CLASSID));
-        // Loads `split_tunnel::MARK` into first nftnl register
-        rule.add_expr(&nft_expr!(immediate data split_tunnel::MARK));
-        // Sets `split_tunnel::MARK` as connection tracker mark
-        rule.add_expr(&nft_expr!(ct mark set));
-        // Loads `fwmark` into first nftnl register
-        rule.add_expr(&nft_expr!(immediate data fwmark));
-        // Sets `fwmark` as metadata mark for packet
-        rule.add_expr(&nft_expr!(meta mark set));
+        match policy.split_tunnel_mode() {
+            SplitTunnelMode::Exclude => {
+                rule.add_expr(&nft_expr!(cmp == split_tunnel::NET_CLS_CLASSID));
+            }
+            SplitTunnelMode::IncludeOnly => {
+                rule.add_expr(&nft_expr!(cmp != split_tunnel::NET_CLS_CLASSID));
+            }
+        }
+        add_split_tunnel_marks(&mut rule, fwmark);
         self.batch.add(&rule, nftnl::MsgType::Add);
 
         for chain in &[&self.in_chain, &self.out_chain, &self.forward_chain] {
@@ -421,6 +475,15 @@ impl<'a> PolicyBatch<'a> {
         Ok(())
     }
 
+    fn add_drop_ipv6_rules(&mut self) {
+        for chain in &[&self.out_chain, &self.in_chain, &self.forward_chain] {
+            let mut rule = Rule::new(chain);
+            check_l3proto(&mut rule, IpAddr::V6(Ipv6Addr::UNSPECIFIED));
+            add_verdict(&mut rule, &Verdict::Drop);
+            self.batch.add(&rule, nftnl::MsgType::Add);
+        }
+    }
+
     fn add_dhcp_client_rules(&mut self) {
         use self::TransportProtocol::Udp;
         // Outgoing DHCPv4 request
@@ -543,6 +606,7 @@ impl<'a> PolicyBatch<'a> {
                 allow_lan,
                 allowed_endpoint,
                 allowed_tunnel_traffic,
+                ..
             } => {
                 self.add_allow_tunnel_endpoint_rules(peer_endpoint, fwmark);
                 self.add_allow_endpoint_rules(allowed_endpoint);
@@ -564,22 +628,31 @@ impl<'a> PolicyBatch<'a> {
                             self.add_allow_in_tunnel_endpoint_rules(&tunnel.interface, endpoint1)?;
                             self.add_allow_in_tunnel_endpoint_rules(&tunnel.interface, endpoint2)?;
                         }
+                        AllowedTunnelTraffic::Multiple(endpoints) => {
+                            for endpoint in endpoints {
+                                self.add_allow_in_tunnel_endpoint_rules(
+                                    &tunnel.interface,
+                                    endpoint,
+                                )?;
+                            }
+                        }
                     }
-                    if *allow_lan {
+                    if allow_lan.is_allowed() {
                         self.add_block_cve_2019_14899(tunnel);
                     }
                 }
-                *allow_lan
+                allow_lan
             }
             FirewallPolicy::Connected {
                 peer_endpoint,
                 tunnel,
                 allow_lan,
                 dns_config,
+                ..
             } => {
                 self.add_allow_tunnel_endpoint_rules(peer_endpoint, fwmark);
 
-                for server in dns_config.tunnel_config() {
+                for server in dns_config.allowed_tunnel_servers() {
                     self.add_allow_tunnel_dns_rule(
                         &tunnel.interface,
                         TransportProtocol::Udp,
@@ -591,7 +664,7 @@ impl<'a> PolicyBatch<'a> {
                         *server,
                     )?;
                 }
-                for server in dns_config.non_tunnel_config() {
+                for server in dns_config.allowed_non_tunnel_servers() {
                     self.add_allow_local_dns_rule(
                         &tunnel.interface,
                         TransportProtocol::Udp,
@@ -608,14 +681,15 @@ impl<'a> PolicyBatch<'a> {
                 // can't leak to the wrong IPs in the tunnel or on the LAN.
                 self.add_drop_dns_rule();
                 self.add_allow_tunnel_rules(&tunnel.interface)?;
-                if *allow_lan {
+                if allow_lan.is_allowed() {
                     self.add_block_cve_2019_14899(tunnel);
                 }
-                *allow_lan
+                allow_lan
             }
             FirewallPolicy::Blocked {
                 allow_lan,
                 allowed_endpoint,
+                ..
             } => {
                 if let Some(endpoint) = allowed_endpoint {
                     self.add_allow_endpoint_rules(endpoint);
@@ -623,12 +697,18 @@ impl<'a> PolicyBatch<'a> {
 
                 // Important to drop DNS before allowing LAN (to stop DNS leaking to the LAN)
                 self.add_drop_dns_rule();
-                *allow_lan
+                allow_lan
             }
         };
 
-        if allow_lan {
-            self.add_allow_lan_rules();
+        // DNS has already been dropped, so exceptions cannot be used to leak DNS
+        self.add_exception_rules(policy.exceptions());
+        self.add_allowed_incoming_rules(policy.allowed_incoming());
+
+        match allow_lan {
+            AllowedLan::Blocked => (),
+            AllowedLan::All => self.add_allow_lan_rules(),
+            AllowedLan::Only(networks) => self.add_allow_lan_network_rules(networks)?,
         }
 
         // Reject any remaining outgoing traffic
@@ -724,6 +804,64 @@ impl<'a> PolicyBatch<'a> {
         self.batch.add(&out_rule, nftnl::MsgType::Add);
     }
 
+    /// Adds firewall rules that allow user-defined traffic outside the tunnel, along with
+    /// responses to it.
+    fn add_exception_rules(&mut self, exceptions: &[FirewallException]) {
+        for exception in exceptions {
+            let mut out_rule = Rule::new(&self.out_chain);
+            check_net(&mut out_rule, End::Dst, exception.network);
+            match (exception.protocol, exception.port) {
+                (Some(protocol), Some(port)) => check_port(&mut out_rule, protocol, End::Dst, port),
+                (Some(protocol), None) => check_l4proto(&mut out_rule, protocol),
+                (None, _) => (),
+            }
+            add_verdict(&mut out_rule, &Verdict::Accept);
+            self.batch.add(&out_rule, nftnl::MsgType::Add);
+
+            let mut in_rule = Rule::new(&self.in_chain);
+            check_net(&mut in_rule, End::Src, exception.network);
+            let allowed_states = nftnl::expr::ct::States::ESTABLISHED.bits();
+            in_rule.add_expr(&nft_expr!(ct state));
+            in_rule.add_expr(&nft_expr!(bitwise mask allowed_states, xor 0u32));
+            in_rule.add_expr(&nft_expr!(cmp != 0u32));
+            add_verdict(&mut in_rule, &Verdict::Accept);
+            self.batch.add(&in_rule, nftnl::MsgType::Add);
+        }
+    }
+
+    /// Adds firewall rules that accept user-defined incoming connections, along with responses to
+    /// them. Rules for interfaces that do not exist are skipped.
+    fn add_allowed_incoming_rules(&mut self, allowed_incoming: &[AllowedIncoming]) {
+        for allowed in allowed_incoming {
+            let mut in_rule = Rule::new(&self.in_chain);
+            let mut out_rule = Rule::new(&self.out_chain);
+
+            if let Some(interface) = &allowed.interface {
+                let result = check_iface(&mut in_rule, Direction::In, interface)
+                    .and_then(|()| check_iface(&mut out_rule, Direction::Out, interface));
+                if let Err(error) = result {
+                    log::warn!(
+                        "{}",
+                        error.display_chain_with_msg(&format!("Not allowing incoming {allowed}"))
+                    );
+                    continue;
+                }
+            }
+
+            check_port(&mut in_rule, allowed.protocol, End::Dst, allowed.port);
+            add_verdict(&mut in_rule, &Verdict::Accept);
+            self.batch.add(&in_rule, nftnl::MsgType::Add);
+
+            check_port(&mut out_rule, allowed.protocol, End::Src, allowed.port);
+            let allowed_states = nftnl::expr::ct::States::ESTABLISHED.bits();
+            out_rule.add_expr(&nft_expr!(ct state));
+            out_rule.add_expr(&nft_expr!(bitwise mask allowed_states, xor 0u32));
+            out_rule.add_expr(&nft_expr!(cmp != 0u32));
+            add_verdict(&mut out_rule, &Verdict::Accept);
+            self.batch.add(&out_rule, nftnl::MsgType::Add);
+        }
+    }
+
     fn add_allow_tunnel_dns_rule(
         &mut self,
         interface: &str,
@@ -888,6 +1026,57 @@ impl<'a> PolicyBatch<'a> {
         self.add_dhcp_server_rules();
     }
 
+    /// Adds firewall rules that only allow traffic to and from the given local networks. Networks
+    /// on interfaces that do not exist are skipped.
+    fn add_allow_lan_network_rules(&mut self, networks: &[LanNetwork]) -> Result<()> {
+        for lan_network in networks {
+            if let Some(interface) = &lan_network.interface {
+                if let Err(error) = crate::linux::iface_index(interface) {
+                    log::warn!(
+                        "{}",
+                        error.display_chain_with_msg(&format!(
+                            "Not allowing LAN traffic for {lan_network}"
+                        ))
+                    );
+                    continue;
+                }
+            }
+
+            let (nets, multicast_nets) = match lan_network.network {
+                Some(network) => (vec![network], vec![]),
+                None => (
+                    ALLOWED_LAN_NETS.to_vec(),
+                    ALLOWED_LAN_MULTICAST_NETS.to_vec(),
+                ),
+            };
+
+            // Output and forward chains
+            for chain in &[&self.out_chain, &self.forward_chain] {
+                for net in nets.iter().chain(&multicast_nets) {
+                    let mut out_rule = Rule::new(chain);
+                    if let Some(interface) = &lan_network.interface {
+                        check_iface(&mut out_rule, Direction::Out, interface)?;
+                    }
+                    check_net(&mut out_rule, End::Dst, *net);
+                    add_verdict(&mut out_rule, &Verdict::Accept);
+                    self.batch.add(&out_rule, nftnl::MsgType::Add);
+                }
+            }
+
+            // Input chain
+            for net in &nets {
+                let mut in_rule = Rule::new(&self.in_chain);
+                if let Some(interface) = &lan_network.interface {
+                    check_iface(&mut in_rule, Direction::In, interface)?;
+                }
+                check_net(&mut in_rule, End::Src, *net);
+                add_verdict(&mut in_rule, &Verdict::Accept);
+                self.batch.add(&in_rule, nftnl::MsgType::Add);
+            }
+        }
+        Ok(())
+    }
+
     fn add_dhcp_server_rules(&mut self) {
         use TransportProtocol::Udp;
         // Outgoing DHCPv4 response
@@ -938,6 +1127,32 @@ fn allow_tunnel_dns_rule<'a>(
     Ok(rule)
 }
 
+/// Mark a packet so that it is routed outside the tunnel, along with the rest of its connection.
+fn add_split_tunnel_marks(rule: &mut Rule<'_>, fwmark: u32) {
+    // Loads `split_tunnel::MARK` into first nftnl register
+    rule.add_expr(&nft_expr!(immediate data split_tunnel::MARK));
+    // Sets `split_tunnel::MARK` as connection tracker mark
+    rule.add_expr(&nft_expr!(ct mark set));
+    // Loads `fwmark` into first nftnl register
+    rule.add_expr(&nft_expr!(immediate data fwmark));
+    // Sets `fwmark` as metadata mark for packet
+    rule.add_expr(&nft_expr!(meta mark set));
+}
+
+/// Match packets sent by sockets that are owned by a user or group.
+fn check_owner(rule: &mut Rule<'_>, owner: &SplitTunnelOwner) {
+    match *owner {
+        SplitTunnelOwner::User(uid) => {
+            rule.add_expr(&nft_expr!(meta skuid));
+            rule.add_expr(&nft_expr!(cmp == uid));
+        }
+        SplitTunnelOwner::Group(gid) => {
+            rule.add_expr(&nft_expr!(meta skgid));
+            rule.add_expr(&nft_expr!(cmp == gid));
+        }
+    }
+}
+
 fn allow_interface_rule<'a>(
     chain: &'a Chain<'_>,
     direction: Direction,
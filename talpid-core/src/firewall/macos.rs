@@ -1,6 +1,7 @@
 use std::env;
 use std::io;
 use std::net::{IpAddr, Ipv4Addr};
+use std::process::Command;
 use std::ptr;
 use std::sync::LazyLock;
 
@@ -8,8 +9,8 @@ use ipnetwork::IpNetwork;
 use libc::{c_int, sysctlbyname};
 use pfctl::{DropAction, FilterRuleAction, Ip, RedirectRule, Uid};
 use talpid_types::net::{
-    AllowedEndpoint, AllowedTunnelTraffic, TransportProtocol, ALLOWED_LAN_MULTICAST_NETS,
-    ALLOWED_LAN_NETS,
+    AllowedEndpoint, AllowedIncoming, AllowedLan, AllowedTunnelTraffic, FirewallException,
+    LanNetwork, TransportProtocol, ALLOWED_LAN_MULTICAST_NETS, ALLOWED_LAN_NETS,
 };
 
 use super::{FirewallArguments, FirewallPolicy};
@@ -18,9 +19,9 @@ pub use pfctl::Error;
 
 type Result<T> = std::result::Result<T, Error>;
 
-/// TODO(linus): This crate is not supposed to be Mullvad-aware. So at some point this should be
-/// replaced by allowing the anchor name to be configured from the public API of this crate.
-const ANCHOR_NAME: &str = "mullvad";
+/// Anchor that the firewall rules are installed in, unless another anchor is given. It is added
+/// to and removed from the main ruleset by the firewall.
+pub const DEFAULT_ANCHOR_NAME: &str = "mullvad";
 
 /// If NAT firewall rules should be applied to force Apple services through the tunnel.
 ///
@@ -47,14 +48,20 @@ pub struct Firewall {
     pf: pfctl::PfCtl,
     pf_was_enabled: Option<bool>,
     rule_logging: RuleLogging,
+    anchor: String,
+    /// Whether the anchor is referenced from the main ruleset by the user rather than by us.
+    nested_anchor: bool,
 }
 
 impl Firewall {
-    pub fn from_args(_args: FirewallArguments) -> Result<Self> {
-        Self::new()
+    pub fn from_args(args: FirewallArguments) -> Result<Self> {
+        Self::new(args.pf_anchor)
     }
 
-    pub fn new() -> Result<Self> {
+    /// Create a firewall that installs its rules in `anchor`. If no anchor is given, the rules are
+    /// installed in [`DEFAULT_ANCHOR_NAME`], which is added to the main ruleset. Otherwise, the
+    /// main ruleset is not modified, and the anchor must be referenced from it by the user.
+    pub fn new(anchor: Option<String>) -> Result<Self> {
         // Allows controlling whether firewall rules should log to pflog0. Useful for debugging the
         // rules. The firewall rules can be inspected by running `tcpdump -netttti pflog0`.
         let firewall_debugging = env::var("TALPID_FIREWALL_DEBUG");
@@ -66,10 +73,18 @@ impl Firewall {
         };
         log::trace!("Firewall debug log policy: {:?}", rule_logging);
 
+        let nested_anchor = anchor.is_some();
+        let anchor = anchor.unwrap_or_else(|| DEFAULT_ANCHOR_NAME.to_owned());
+        if nested_anchor {
+            log::info!("Installing firewall rules in user-managed PF anchor \"{anchor}\"");
+        }
+
         Ok(Firewall {
             pf: pfctl::PfCtl::new()?,
             pf_was_enabled: None,
             rule_logging,
+            anchor,
+            nested_anchor,
         })
     }
 
@@ -132,7 +147,7 @@ impl Firewall {
             return Ok(false);
         }
 
-        if policy.allow_lan() {
+        if *policy.allow_lan() == AllowedLan::All {
             let net_is_lan = ALLOWED_LAN_NETS
                 .iter()
                 .chain(ALLOWED_LAN_MULTICAST_NETS.iter())
@@ -168,6 +183,9 @@ impl Firewall {
                 AllowedTunnelTraffic::Two(endpoint1, endpoint2) => {
                     endpoint1.address != remote_address && endpoint2.address != remote_address
                 }
+                AllowedTunnelTraffic::Multiple(endpoints) => endpoints
+                    .iter()
+                    .all(|endpoint| endpoint.address != remote_address),
             }
         } else {
             // Non-tunnel traffic: Clear all states except traffic destined for the VPN endpoint
@@ -191,6 +209,10 @@ impl Firewall {
         let mut new_filter_rules = vec![];
 
         new_filter_rules.append(&mut self.get_allow_loopback_rules()?);
+        // Important to block IPv6 before anything else is allowed
+        if policy.block_ipv6() {
+            new_filter_rules.append(&mut self.get_block_ipv6_rules()?);
+        }
         new_filter_rules.append(&mut self.get_allow_dhcp_client_rules()?);
         new_filter_rules.append(&mut self.get_allow_ndp_rules()?);
         new_filter_rules.append(&mut self.get_policy_specific_rules(policy)?);
@@ -215,7 +237,7 @@ impl Firewall {
         if *NAT_WORKAROUND {
             anchor_change.set_nat_rules(self.get_nat_rules(policy)?);
         }
-        self.pf.set_rules(ANCHOR_NAME, anchor_change)?;
+        self.pf.set_rules(&self.anchor, anchor_change)?;
 
         Ok(())
     }
@@ -369,6 +391,8 @@ impl Firewall {
                 allow_lan,
                 allowed_endpoint,
                 allowed_tunnel_traffic,
+                exceptions,
+                allowed_incoming,
                 redirect_interface,
                 dns_redirect_port: _,
             } => {
@@ -378,6 +402,8 @@ impl Firewall {
                 // Important to block DNS after allow relay rule (so the relay can operate
                 // over port 53) but before allow LAN (so DNS does not leak to the LAN)
                 rules.append(&mut self.get_block_dns_rules()?);
+                rules.append(&mut self.get_exception_rules(exceptions)?);
+                rules.append(&mut self.get_allowed_incoming_rules(allowed_incoming)?);
 
                 if let Some(tunnel) = tunnel {
                     match redirect_interface {
@@ -403,9 +429,7 @@ impl Firewall {
                     }
                 }
 
-                if *allow_lan {
-                    rules.append(&mut self.get_allow_lan_rules()?);
-                }
+                rules.append(&mut self.get_allow_lan_rules(allow_lan)?);
 
                 Ok(rules)
             }
@@ -414,17 +438,19 @@ impl Firewall {
                 tunnel,
                 allow_lan,
                 dns_config,
+                exceptions,
+                allowed_incoming,
                 redirect_interface,
                 dns_redirect_port: _,
             } => {
                 let mut rules = vec![];
 
-                for server in dns_config.tunnel_config() {
+                for server in dns_config.allowed_tunnel_servers() {
                     rules.append(
                         &mut self.get_allow_tunnel_dns_rules_when_connected(tunnel, *server)?,
                     );
                 }
-                for server in dns_config.non_tunnel_config() {
+                for server in dns_config.allowed_non_tunnel_servers() {
                     rules.append(
                         &mut self.get_allow_local_dns_rules_when_connected(tunnel, *server)?,
                     );
@@ -435,10 +461,10 @@ impl Firewall {
                 // Important to block DNS *before* we allow the tunnel and allow LAN. So DNS
                 // can't leak to the wrong IPs in the tunnel or on the LAN.
                 rules.append(&mut self.get_block_dns_rules()?);
+                rules.append(&mut self.get_exception_rules(exceptions)?);
+                rules.append(&mut self.get_allowed_incoming_rules(allowed_incoming)?);
 
-                if *allow_lan {
-                    rules.append(&mut self.get_allow_lan_rules()?);
-                }
+                rules.append(&mut self.get_allow_lan_rules(allow_lan)?);
 
                 if let Some(redirect_interface) = redirect_interface {
                     enable_forwarding();
@@ -461,6 +487,8 @@ impl Firewall {
             FirewallPolicy::Blocked {
                 allow_lan,
                 allowed_endpoint,
+                exceptions,
+                allowed_incoming,
                 ..
             } => {
                 let mut rules = Vec::new();
@@ -468,11 +496,13 @@ impl Firewall {
                     rules.push(self.get_allowed_endpoint_rule(allowed_endpoint)?);
                 }
 
-                if *allow_lan {
-                    // Important to block DNS before allow LAN (so DNS does not leak to the LAN)
+                // Important to block DNS before allow LAN and exceptions (so DNS does not leak)
+                if allow_lan.is_allowed() || !exceptions.is_empty() {
                     rules.append(&mut self.get_block_dns_rules()?);
-                    rules.append(&mut self.get_allow_lan_rules()?);
                 }
+                rules.append(&mut self.get_exception_rules(exceptions)?);
+                rules.append(&mut self.get_allowed_incoming_rules(allowed_incoming)?);
+                rules.append(&mut self.get_allow_lan_rules(allow_lan)?);
 
                 Ok(rules)
             }
@@ -618,6 +648,53 @@ impl Firewall {
         rule.build()
     }
 
+    /// Allow user-defined traffic outside the tunnel. Responses are allowed by the state created
+    /// by outgoing packets.
+    fn get_exception_rules(
+        &self,
+        exceptions: &[FirewallException],
+    ) -> Result<Vec<pfctl::FilterRule>> {
+        let mut rules = Vec::with_capacity(exceptions.len());
+        for exception in exceptions {
+            let port = exception
+                .port
+                .map(pfctl::Port::from)
+                .unwrap_or(pfctl::Port::Any);
+            let mut rule = self.create_rule_builder(FilterRuleAction::Pass);
+            rule.direction(pfctl::Direction::Out)
+                .quick(true)
+                .keep_state(pfctl::StatePolicy::Keep)
+                .to(pfctl::Endpoint::new(exception.network, port));
+            if let Some(protocol) = exception.protocol {
+                rule.proto(as_pfctl_proto(protocol));
+            }
+            rules.push(rule.build()?);
+        }
+        Ok(rules)
+    }
+
+    /// Allow user-defined incoming connections. Responses are allowed by the state created by
+    /// incoming packets.
+    fn get_allowed_incoming_rules(
+        &self,
+        allowed_incoming: &[AllowedIncoming],
+    ) -> Result<Vec<pfctl::FilterRule>> {
+        let mut rules = Vec::with_capacity(allowed_incoming.len());
+        for allowed in allowed_incoming {
+            let mut rule = self.create_rule_builder(FilterRuleAction::Pass);
+            rule.direction(pfctl::Direction::In)
+                .quick(true)
+                .keep_state(pfctl::StatePolicy::Keep)
+                .proto(as_pfctl_proto(allowed.protocol))
+                .to(pfctl::Port::from(allowed.port));
+            if let Some(interface) = &allowed.interface {
+                rule.interface(interface);
+            }
+            rules.push(rule.build()?);
+        }
+        Ok(rules)
+    }
+
     fn get_block_dns_rules(&self) -> Result<Vec<pfctl::FilterRule>> {
         let block_tcp_dns_rule = self
             .create_rule_builder(FilterRuleAction::Drop(DropAction::Return))
@@ -677,6 +754,15 @@ impl Firewall {
 
                 rules
             }
+            AllowedTunnelTraffic::Multiple(endpoints) => {
+                let mut rules = Vec::with_capacity(endpoints.len());
+                for endpoint in endpoints {
+                    let pfctl_proto = as_pfctl_proto(endpoint.protocol);
+                    base_rule = base_rule.to(endpoint.address).proto(pfctl_proto);
+                    rules.push(base_rule.build()?);
+                }
+                rules
+            }
             AllowedTunnelTraffic::All => {
                 vec![base_rule.build()?]
             }
@@ -696,7 +782,72 @@ impl Firewall {
         Ok(vec![lo0_rule])
     }
 
-    fn get_allow_lan_rules(&self) -> Result<Vec<pfctl::FilterRule>> {
+    fn get_block_ipv6_rules(&self) -> Result<Vec<pfctl::FilterRule>> {
+        let block_ipv6_rule = self
+            .create_rule_builder(FilterRuleAction::Drop(DropAction::Drop))
+            .quick(true)
+            .af(pfctl::AddrFamily::Ipv6)
+            .build()?;
+        Ok(vec![block_ipv6_rule])
+    }
+
+    fn get_allow_lan_rules(&self, allow_lan: &AllowedLan) -> Result<Vec<pfctl::FilterRule>> {
+        match allow_lan {
+            AllowedLan::Blocked => Ok(vec![]),
+            AllowedLan::All => self.get_allow_all_lan_rules(),
+            AllowedLan::Only(networks) => self.get_allow_lan_network_rules(networks),
+        }
+    }
+
+    /// Only allow traffic to and from the given local networks
+    fn get_allow_lan_network_rules(
+        &self,
+        networks: &[LanNetwork],
+    ) -> Result<Vec<pfctl::FilterRule>> {
+        let mut rules = vec![];
+        for lan_network in networks {
+            let (nets, multicast_nets) = match lan_network.network {
+                Some(network) => (vec![network], vec![]),
+                None => (
+                    ALLOWED_LAN_NETS.to_vec(),
+                    ALLOWED_LAN_MULTICAST_NETS.to_vec(),
+                ),
+            };
+
+            let mut rule_builder = self.create_rule_builder(FilterRuleAction::Pass);
+            rule_builder
+                .quick(true)
+                .keep_state(pfctl::StatePolicy::Keep);
+            if let Some(interface) = &lan_network.interface {
+                rule_builder.interface(interface);
+            }
+            for net in &nets {
+                let allow_out = rule_builder
+                    .direction(pfctl::Direction::Out)
+                    .from(pfctl::Ip::Any)
+                    .to(pfctl::Ip::from(*net))
+                    .build()?;
+                let allow_in = rule_builder
+                    .direction(pfctl::Direction::In)
+                    .from(pfctl::Ip::from(*net))
+                    .to(pfctl::Ip::Any)
+                    .build()?;
+                rules.push(allow_out);
+                rules.push(allow_in);
+            }
+            for multicast_net in &multicast_nets {
+                let allow_multicast_out = rule_builder
+                    .direction(pfctl::Direction::Out)
+                    .from(pfctl::Ip::Any)
+                    .to(pfctl::Ip::from(*multicast_net))
+                    .build()?;
+                rules.push(allow_multicast_out);
+            }
+        }
+        Ok(rules)
+    }
+
+    fn get_allow_all_lan_rules(&self) -> Result<Vec<pfctl::FilterRule>> {
         let mut rules = vec![];
         for net in &*ALLOWED_LAN_NETS {
             let mut rule_builder = self.create_rule_builder(FilterRuleAction::Pass);
@@ -925,12 +1076,12 @@ impl Firewall {
     fn remove_rules(&mut self) -> Result<()> {
         // remove_anchor() does not deactivate active rules
         self.pf
-            .flush_rules(ANCHOR_NAME, pfctl::RulesetKind::Filter)?;
+            .flush_rules(&self.anchor, pfctl::RulesetKind::Filter)?;
         if *NAT_WORKAROUND {
-            self.pf.flush_rules(ANCHOR_NAME, pfctl::RulesetKind::Nat)?;
+            self.pf.flush_rules(&self.anchor, pfctl::RulesetKind::Nat)?;
         }
         self.pf
-            .flush_rules(ANCHOR_NAME, pfctl::RulesetKind::Scrub)?;
+            .flush_rules(&self.anchor, pfctl::RulesetKind::Scrub)?;
         Ok(())
     }
 
@@ -959,35 +1110,68 @@ impl Firewall {
     }
 
     fn add_anchor(&mut self) -> Result<()> {
+        if self.nested_anchor {
+            return Ok(());
+        }
         self.pf
-            .try_add_anchor(ANCHOR_NAME, pfctl::AnchorKind::Scrub)?;
+            .try_add_anchor(&self.anchor, pfctl::AnchorKind::Scrub)?;
         if *NAT_WORKAROUND {
             self.pf
-                .try_add_anchor(ANCHOR_NAME, pfctl::AnchorKind::Nat)?;
+                .try_add_anchor(&self.anchor, pfctl::AnchorKind::Nat)?;
         }
         self.pf
-            .try_add_anchor(ANCHOR_NAME, pfctl::AnchorKind::Filter)?;
+            .try_add_anchor(&self.anchor, pfctl::AnchorKind::Filter)?;
         self.pf
-            .try_add_anchor(ANCHOR_NAME, pfctl::AnchorKind::Redirect)?;
+            .try_add_anchor(&self.anchor, pfctl::AnchorKind::Redirect)?;
         Ok(())
     }
 
     fn remove_anchor(&mut self) -> Result<()> {
+        if self.nested_anchor {
+            return Ok(());
+        }
         self.pf
-            .try_remove_anchor(ANCHOR_NAME, pfctl::AnchorKind::Scrub)?;
+            .try_remove_anchor(&self.anchor, pfctl::AnchorKind::Scrub)?;
         // Opportunistically remove Nat anchor.
         // This won't fail because `try_remove_anchor` promises to convert
         // `pfctl::Error::AnchorDoesNotExist` to an `Ok(())` value.
         self.pf
-            .try_remove_anchor(ANCHOR_NAME, pfctl::AnchorKind::Nat)?;
+            .try_remove_anchor(&self.anchor, pfctl::AnchorKind::Nat)?;
         self.pf
-            .try_remove_anchor(ANCHOR_NAME, pfctl::AnchorKind::Redirect)?;
+            .try_remove_anchor(&self.anchor, pfctl::AnchorKind::Redirect)?;
         self.pf
-            .try_remove_anchor(ANCHOR_NAME, pfctl::AnchorKind::Filter)?;
+            .try_remove_anchor(&self.anchor, pfctl::AnchorKind::Filter)?;
         Ok(())
     }
 }
 
+/// Describe the rules in the PF anchor `anchor`, along with the anchors that are referenced from the
+/// main ruleset.
+pub fn describe_rules(anchor: &str) -> io::Result<String> {
+    let sections = [
+        ("Anchors in the main ruleset", vec!["-s", "Anchors"]),
+        ("Filter rules", vec!["-a", anchor, "-s", "rules"]),
+        ("NAT and redirect rules", vec!["-a", anchor, "-s", "nat"]),
+    ];
+
+    let mut description = format!("Anchor: {anchor}\n");
+    for (title, args) in sections {
+        let output = Command::new("/sbin/pfctl").args(&args).output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "pfctl {} failed: {}",
+                args.join(" "),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        description.push_str(&format!(
+            "\n{title}:\n{}",
+            String::from_utf8_lossy(&output.stdout)
+        ));
+    }
+    Ok(description)
+}
+
 fn as_pfctl_proto(protocol: TransportProtocol) -> pfctl::Proto {
     match protocol {
         TransportProtocol::Udp => pfctl::Proto::Udp,
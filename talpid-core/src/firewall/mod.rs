@@ -6,7 +6,11 @@ use std::{
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     sync::LazyLock,
 };
-use talpid_types::net::{AllowedEndpoint, AllowedTunnelTraffic, ALLOWED_LAN_NETS};
+use talpid_types::net::{AllowedEndpoint, AllowedLan, AllowedTunnelTraffic, ALLOWED_LAN_NETS};
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::{AllowedIncoming, FirewallException};
+#[cfg(target_os = "linux")]
+use talpid_types::split_tunnel::{SplitTunnelMode, SplitTunnelOwner};
 
 #[cfg(target_os = "macos")]
 #[path = "macos.rs"]
@@ -25,6 +29,12 @@ mod imp;
 mod imp;
 
 pub use self::imp::Error;
+#[cfg(target_os = "linux")]
+pub use self::imp::NftablesOptions;
+#[cfg(windows)]
+pub use self::imp::{describe_filters, DEFAULT_SUBLAYER_WEIGHT};
+#[cfg(target_os = "macos")]
+pub use self::imp::{describe_rules, DEFAULT_ANCHOR_NAME};
 
 #[cfg(any(target_os = "linux", target_os = "macos"))]
 static IPV6_LINK_LOCAL: LazyLock<Ipv6Network> =
@@ -85,12 +95,27 @@ pub enum FirewallPolicy {
         peer_endpoint: AllowedEndpoint,
         /// Metadata about the tunnel and tunnel interface.
         tunnel: Option<crate::tunnel::TunnelMetadata>,
-        /// Flag setting if communication with LAN networks should be possible.
-        allow_lan: bool,
+        /// Communication with LAN networks that should be possible.
+        allow_lan: AllowedLan,
         /// Host that should be reachable while connecting.
         allowed_endpoint: AllowedEndpoint,
         /// Networks for which to permit in-tunnel traffic.
         allowed_tunnel_traffic: AllowedTunnelTraffic,
+        /// Traffic that is allowed outside the tunnel.
+        #[cfg(not(target_os = "android"))]
+        exceptions: Vec<FirewallException>,
+        /// Incoming connections that are allowed.
+        #[cfg(not(target_os = "android"))]
+        allowed_incoming: Vec<AllowedIncoming>,
+        /// Drop all IPv6 traffic except on the loopback interface.
+        #[cfg(not(target_os = "android"))]
+        block_ipv6: bool,
+        /// Whether split processes bypass the tunnel, or are the only ones that use it.
+        #[cfg(target_os = "linux")]
+        split_tunnel_mode: SplitTunnelMode,
+        /// Users and groups whose processes are split from the tunnel.
+        #[cfg(target_os = "linux")]
+        split_tunnel_owners: Vec<SplitTunnelOwner>,
         /// Interface to redirect (VPN tunnel) traffic to
         #[cfg(target_os = "macos")]
         redirect_interface: Option<String>,
@@ -106,11 +131,29 @@ pub enum FirewallPolicy {
         peer_endpoint: AllowedEndpoint,
         /// Metadata about the tunnel and tunnel interface.
         tunnel: crate::tunnel::TunnelMetadata,
-        /// Flag setting if communication with LAN networks should be possible.
-        allow_lan: bool,
+        /// Communication with LAN networks that should be possible.
+        allow_lan: AllowedLan,
         /// Servers that are allowed to respond to DNS requests.
         #[cfg(not(target_os = "android"))]
         dns_config: ResolvedDnsConfig,
+        /// Traffic that is allowed outside the tunnel.
+        #[cfg(not(target_os = "android"))]
+        exceptions: Vec<FirewallException>,
+        /// Incoming connections that are allowed.
+        #[cfg(not(target_os = "android"))]
+        allowed_incoming: Vec<AllowedIncoming>,
+        /// Drop all IPv6 traffic except on the loopback interface.
+        #[cfg(not(target_os = "android"))]
+        block_ipv6: bool,
+        /// Whether split processes bypass the tunnel, or are the only ones that use it.
+        #[cfg(target_os = "linux")]
+        split_tunnel_mode: SplitTunnelMode,
+        /// Users and groups whose processes are split from the tunnel.
+        #[cfg(target_os = "linux")]
+        split_tunnel_owners: Vec<SplitTunnelOwner>,
+        /// Addresses of bypassed domains, which are routed outside the tunnel.
+        #[cfg(target_os = "linux")]
+        split_tunnel_addresses: Vec<IpAddr>,
         /// Interface to redirect (VPN tunnel) traffic to
         #[cfg(target_os = "macos")]
         redirect_interface: Option<String>,
@@ -122,10 +165,25 @@ pub enum FirewallPolicy {
 
     /// Block all network traffic in and out from the computer.
     Blocked {
-        /// Flag setting if communication with LAN networks should be possible.
-        allow_lan: bool,
+        /// Communication with LAN networks that should be possible.
+        allow_lan: AllowedLan,
         /// Host that should be reachable while in the blocked state.
         allowed_endpoint: Option<AllowedEndpoint>,
+        /// Traffic that is allowed outside the tunnel.
+        #[cfg(not(target_os = "android"))]
+        exceptions: Vec<FirewallException>,
+        /// Incoming connections that are allowed.
+        #[cfg(not(target_os = "android"))]
+        allowed_incoming: Vec<AllowedIncoming>,
+        /// Drop all IPv6 traffic except on the loopback interface.
+        #[cfg(not(target_os = "android"))]
+        block_ipv6: bool,
+        /// Whether split processes bypass the tunnel, or are the only ones that use it.
+        #[cfg(target_os = "linux")]
+        split_tunnel_mode: SplitTunnelMode,
+        /// Users and groups whose processes are split from the tunnel.
+        #[cfg(target_os = "linux")]
+        split_tunnel_owners: Vec<SplitTunnelOwner>,
         /// Destination port for DNS traffic redirection. Traffic destined to `127.0.0.1:53` will
         /// be redirected to `127.0.0.1:$dns_redirect_port`.
         #[cfg(target_os = "macos")]
@@ -181,12 +239,83 @@ impl FirewallPolicy {
         }
     }
 
-    /// Return whether LAN traffic is allowed
-    pub fn allow_lan(&self) -> bool {
+    /// Return the LAN traffic that is allowed
+    pub fn allow_lan(&self) -> &AllowedLan {
         match self {
             FirewallPolicy::Connecting { allow_lan, .. }
             | FirewallPolicy::Connected { allow_lan, .. }
-            | FirewallPolicy::Blocked { allow_lan, .. } => *allow_lan,
+            | FirewallPolicy::Blocked { allow_lan, .. } => allow_lan,
+        }
+    }
+
+    /// Return the traffic that is allowed outside the tunnel
+    #[cfg(not(target_os = "android"))]
+    pub fn exceptions(&self) -> &[FirewallException] {
+        match self {
+            FirewallPolicy::Connecting { exceptions, .. }
+            | FirewallPolicy::Connected { exceptions, .. }
+            | FirewallPolicy::Blocked { exceptions, .. } => exceptions,
+        }
+    }
+
+    /// Return the incoming connections that are allowed
+    #[cfg(not(target_os = "android"))]
+    pub fn allowed_incoming(&self) -> &[AllowedIncoming] {
+        match self {
+            FirewallPolicy::Connecting {
+                allowed_incoming, ..
+            }
+            | FirewallPolicy::Connected {
+                allowed_incoming, ..
+            }
+            | FirewallPolicy::Blocked {
+                allowed_incoming, ..
+            } => allowed_incoming,
+        }
+    }
+
+    /// Return whether all IPv6 traffic should be dropped
+    #[cfg(not(target_os = "android"))]
+    pub fn block_ipv6(&self) -> bool {
+        match self {
+            FirewallPolicy::Connecting { block_ipv6, .. }
+            | FirewallPolicy::Connected { block_ipv6, .. }
+            | FirewallPolicy::Blocked { block_ipv6, .. } => *block_ipv6,
+        }
+    }
+
+    /// Return which traffic is sent outside the tunnel by split tunneling
+    #[cfg(target_os = "linux")]
+    pub fn split_tunnel_mode(&self) -> SplitTunnelMode {
+        match self {
+            FirewallPolicy::Connecting {
+                split_tunnel_mode, ..
+            }
+            | FirewallPolicy::Connected {
+                split_tunnel_mode, ..
+            }
+            | FirewallPolicy::Blocked {
+                split_tunnel_mode, ..
+            } => *split_tunnel_mode,
+        }
+    }
+
+    /// Return the users and groups whose processes are split from the tunnel
+    #[cfg(target_os = "linux")]
+    pub fn split_tunnel_owners(&self) -> &[SplitTunnelOwner] {
+        match self {
+            FirewallPolicy::Connecting {
+                split_tunnel_owners,
+                ..
+            }
+            | FirewallPolicy::Connected {
+                split_tunnel_owners,
+                ..
+            }
+            | FirewallPolicy::Blocked {
+                split_tunnel_owners,
+                ..
+            } => split_tunnel_owners,
         }
     }
 }
@@ -205,7 +334,7 @@ impl fmt::Display for FirewallPolicy {
                 if let Some(tunnel) = tunnel {
                     write!(
                         f,
-                        "Connecting to {} over \"{}\" (ip: {}, v4 gw: {}, v6 gw: {:?}, allowed in-tunnel traffic: {}), {} LAN. Allowing endpoint {}",
+                        "Connecting to {} over \"{}\" (ip: {}, v4 gw: {}, v6 gw: {:?}, allowed in-tunnel traffic: {}), LAN {}. Allowing endpoint {}",
                         peer_endpoint,
                         tunnel.interface,
                         tunnel
@@ -217,16 +346,14 @@ impl fmt::Display for FirewallPolicy {
                         tunnel.ipv4_gateway,
                         tunnel.ipv6_gateway,
                         allowed_tunnel_traffic,
-                        if *allow_lan { "Allowing" } else { "Blocking" },
+                        allow_lan,
                         allowed_endpoint,
                     )
                 } else {
                     write!(
                         f,
-                        "Connecting to {}, {} LAN, interface: none. Allowing endpoint {}",
-                        peer_endpoint,
-                        if *allow_lan { "Allowing" } else { "Blocking" },
-                        allowed_endpoint,
+                        "Connecting to {}, LAN {}, interface: none. Allowing endpoint {}",
+                        peer_endpoint, allow_lan, allowed_endpoint,
                     )
                 }
             }
@@ -237,7 +364,7 @@ impl fmt::Display for FirewallPolicy {
                 ..
             } => write!(
                 f,
-                "Connected to {} over \"{}\" (ip: {}, v4 gw: {}, v6 gw: {:?}), {} LAN",
+                "Connected to {} over \"{}\" (ip: {}, v4 gw: {}, v6 gw: {:?}), LAN {}",
                 peer_endpoint,
                 tunnel.interface,
                 tunnel
@@ -248,7 +375,7 @@ impl fmt::Display for FirewallPolicy {
                     .join(","),
                 tunnel.ipv4_gateway,
                 tunnel.ipv6_gateway,
-                if *allow_lan { "Allowing" } else { "Blocking" }
+                allow_lan
             ),
             FirewallPolicy::Blocked {
                 allow_lan,
@@ -256,8 +383,8 @@ impl fmt::Display for FirewallPolicy {
                 ..
             } => write!(
                 f,
-                "Blocked. {} LAN. Allowing endpoint: {}",
-                if *allow_lan { "Allowing" } else { "Blocking" },
+                "Blocked. LAN {}. Allowing endpoint: {}",
+                allow_lan,
                 allowed_endpoint
                     .as_ref()
                     .map(|endpoint| -> &dyn std::fmt::Display { endpoint })
@@ -271,6 +398,8 @@ impl fmt::Display for FirewallPolicy {
 /// by manipulating the OS firewall and DNS settings.
 pub struct Firewall {
     inner: imp::Firewall,
+    /// The policy that was most recently applied successfully.
+    applied_policy: Option<FirewallPolicy>,
 }
 
 /// Arguments required when first initializing the firewall.
@@ -278,11 +407,21 @@ pub struct FirewallArguments {
     /// Initial firewall state to enter during init.
     pub initial_state: InitialFirewallState,
     /// This argument is required for the blocked state to configure the firewall correctly.
-    pub allow_lan: bool,
+    pub allow_lan: AllowedLan,
     /// Specifies the firewall mark used to identify traffic that is allowed to be excluded from
     /// the tunnel and _leaked_ during blocked states.
     #[cfg(target_os = "linux")]
     pub fwmark: u32,
+    /// How the firewall rules are installed alongside other nftables tables.
+    #[cfg(target_os = "linux")]
+    pub nftables: NftablesOptions,
+    /// Weight of the WFP sublayers that contain the firewall filters.
+    #[cfg(windows)]
+    pub sublayer_weight: u16,
+    /// PF anchor to install the firewall rules in. If set, the anchor must be referenced from the
+    /// main ruleset by the user.
+    #[cfg(target_os = "macos")]
+    pub pf_anchor: Option<String>,
 }
 
 /// State to enter during firewall init.
@@ -298,16 +437,29 @@ impl Firewall {
     pub fn from_args(args: FirewallArguments) -> Result<Self, Error> {
         Ok(Firewall {
             inner: imp::Firewall::from_args(args)?,
+            applied_policy: None,
         })
     }
 
     /// Createsa new firewall instance.
-    pub fn new(#[cfg(target_os = "linux")] fwmark: u32) -> Result<Self, Error> {
+    pub fn new(
+        #[cfg(target_os = "linux")] fwmark: u32,
+        #[cfg(target_os = "linux")] nftables: NftablesOptions,
+        #[cfg(windows)] sublayer_weight: u16,
+        #[cfg(target_os = "macos")] pf_anchor: Option<String>,
+    ) -> Result<Self, Error> {
         Ok(Firewall {
             inner: imp::Firewall::new(
                 #[cfg(target_os = "linux")]
                 fwmark,
+                #[cfg(target_os = "linux")]
+                nftables,
+                #[cfg(windows)]
+                sublayer_weight,
+                #[cfg(target_os = "macos")]
+                pf_anchor,
             )?,
+            applied_policy: None,
         })
     }
 
@@ -315,13 +467,22 @@ impl Firewall {
     /// until this method is called again with another policy, or until `reset_policy` is called.
     pub fn apply_policy(&mut self, policy: FirewallPolicy) -> Result<(), Error> {
         log::info!("Applying firewall policy: {}", policy);
-        self.inner.apply_policy(policy)
+        self.applied_policy = None;
+        self.inner.apply_policy(policy.clone())?;
+        self.applied_policy = Some(policy);
+        Ok(())
     }
 
     /// Resets/removes any currently enforced `FirewallPolicy`. Returns the system to the same state
     /// it had before any policy was applied through this `Firewall` instance.
     pub fn reset_policy(&mut self) -> Result<(), Error> {
         log::info!("Resetting firewall policy");
+        self.applied_policy = None;
         self.inner.reset_policy()
     }
+
+    /// Returns the policy that is currently enforced, if any.
+    pub fn applied_policy(&self) -> Option<&FirewallPolicy> {
+        self.applied_policy.as_ref()
+    }
 }
@@ -2,12 +2,21 @@
 
 use crate::{dns::ResolvedDnsConfig, tunnel::TunnelMetadata};
 
-use std::{ffi::CStr, io, net::IpAddr, ptr, sync::LazyLock};
+use std::{
+    ffi::{c_char, c_void, CStr},
+    io,
+    net::IpAddr,
+    ptr,
+    sync::LazyLock,
+};
 
 use self::winfw::*;
 use super::{FirewallArguments, FirewallPolicy, InitialFirewallState};
 use talpid_types::{
-    net::{AllowedEndpoint, AllowedTunnelTraffic},
+    net::{
+        AllowedEndpoint, AllowedIncoming, AllowedLan, AllowedTunnelTraffic, FirewallException,
+        LanNetwork,
+    },
     tunnel::FirewallPolicyError,
     ErrorExt,
 };
@@ -72,11 +81,23 @@ pub enum Error {
     /// Failure to reset firewall policies
     #[error("Failed to reset firewall policies")]
     ResettingPolicy(#[source] FirewallPolicyError),
+
+    /// The firewall cannot allow traffic to more than two tunnel endpoints
+    #[error("Cannot allow in-tunnel traffic to more than two endpoints")]
+    TooManyTunnelEndpoints,
+
+    /// Failure to list the installed WFP filters
+    #[error("Failed to describe WFP filters")]
+    Diagnostics,
 }
 
 /// Timeout for acquiring the WFP transaction lock
 const WINFW_TIMEOUT_SECONDS: u32 = 5;
 
+/// Default weight of the WFP sublayers that contain the firewall filters. Sublayers with a higher
+/// weight are evaluated first.
+pub const DEFAULT_SUBLAYER_WEIGHT: u16 = u16::MAX;
+
 const LOGGING_CONTEXT: &[u8] = b"WinFw\0";
 
 /// The Windows implementation for the firewall.
@@ -85,16 +106,17 @@ pub struct Firewall(());
 impl Firewall {
     pub fn from_args(args: FirewallArguments) -> Result<Self, Error> {
         if let InitialFirewallState::Blocked(allowed_endpoint) = args.initial_state {
-            Self::initialize_blocked(allowed_endpoint, args.allow_lan)
+            Self::initialize_blocked(allowed_endpoint, args.allow_lan, args.sublayer_weight)
         } else {
-            Self::new()
+            Self::new(args.sublayer_weight)
         }
     }
 
-    pub fn new() -> Result<Self, Error> {
+    pub fn new(sublayer_weight: u16) -> Result<Self, Error> {
         unsafe {
             WinFw_Initialize(
                 WINFW_TIMEOUT_SECONDS,
+                sublayer_weight,
                 Some(log_sink),
                 LOGGING_CONTEXT.as_ptr(),
             )
@@ -107,13 +129,18 @@ impl Firewall {
 
     fn initialize_blocked(
         allowed_endpoint: AllowedEndpoint,
-        allow_lan: bool,
+        allow_lan: AllowedLan,
+        sublayer_weight: u16,
     ) -> Result<Self, Error> {
-        let cfg = &WinFwSettings::new(allow_lan);
+        let lan = WinFwLanContainer::from(&allow_lan);
+        let exceptions = WinFwExceptionContainer::from(&[][..]);
+        let allowed_incoming = WinFwAllowedIncomingContainer::from(&[][..]);
+        let cfg = &WinFwSettings::new(&lan, &exceptions, &allowed_incoming, false);
         let allowed_endpoint = WinFwAllowedEndpointContainer::from(allowed_endpoint);
         unsafe {
             WinFw_InitializeBlocked(
                 WINFW_TIMEOUT_SECONDS,
+                sublayer_weight,
                 cfg,
                 &allowed_endpoint.as_endpoint(),
                 Some(log_sink),
@@ -144,8 +171,14 @@ impl Firewall {
                 allow_lan,
                 allowed_endpoint,
                 allowed_tunnel_traffic,
+                exceptions,
+                allowed_incoming,
+                block_ipv6,
             } => {
-                let cfg = &WinFwSettings::new(allow_lan);
+                let lan = WinFwLanContainer::from(&allow_lan);
+                let exceptions = WinFwExceptionContainer::from(&exceptions[..]);
+                let allowed_incoming = WinFwAllowedIncomingContainer::from(&allowed_incoming[..]);
+                let cfg = &WinFwSettings::new(&lan, &exceptions, &allowed_incoming, block_ipv6);
 
                 self.set_connecting_state(
                     &peer_endpoint,
@@ -160,15 +193,27 @@ impl Firewall {
                 tunnel,
                 allow_lan,
                 dns_config,
+                exceptions,
+                allowed_incoming,
+                block_ipv6,
             } => {
-                let cfg = &WinFwSettings::new(allow_lan);
+                let lan = WinFwLanContainer::from(&allow_lan);
+                let exceptions = WinFwExceptionContainer::from(&exceptions[..]);
+                let allowed_incoming = WinFwAllowedIncomingContainer::from(&allowed_incoming[..]);
+                let cfg = &WinFwSettings::new(&lan, &exceptions, &allowed_incoming, block_ipv6);
                 self.set_connected_state(&peer_endpoint, cfg, &tunnel, &dns_config)
             }
             FirewallPolicy::Blocked {
                 allow_lan,
                 allowed_endpoint,
+                exceptions,
+                allowed_incoming,
+                block_ipv6,
             } => {
-                let cfg = &WinFwSettings::new(allow_lan);
+                let lan = WinFwLanContainer::from(&allow_lan);
+                let exceptions = WinFwExceptionContainer::from(&exceptions[..]);
+                let allowed_incoming = WinFwAllowedIncomingContainer::from(&allowed_incoming[..]);
+                let cfg = &WinFwSettings::new(&lan, &exceptions, &allowed_incoming, block_ipv6);
                 self.set_blocked_state(
                     cfg,
                     allowed_endpoint.map(WinFwAllowedEndpointContainer::from),
@@ -203,7 +248,7 @@ impl Firewall {
     fn set_connecting_state(
         &mut self,
         endpoint: &AllowedEndpoint,
-        winfw_settings: &WinFwSettings,
+        winfw_settings: &WinFwSettings<'_>,
         tunnel_metadata: &Option<TunnelMetadata>,
         allowed_endpoint: &WinFwAllowedEndpoint<'_>,
         allowed_tunnel_traffic: &AllowedTunnelTraffic,
@@ -269,6 +314,7 @@ impl Firewall {
                 (endpoint1, endpoint2)
             }
             AllowedTunnelTraffic::None | AllowedTunnelTraffic::All => (None, None),
+            AllowedTunnelTraffic::Multiple(_) => return Err(Error::TooManyTunnelEndpoints),
         };
 
         let allowed_tunnel_traffic = WinFwAllowedTunnelTraffic {
@@ -312,7 +358,7 @@ impl Firewall {
     fn set_connected_state(
         &mut self,
         endpoint: &AllowedEndpoint,
-        winfw_settings: &WinFwSettings,
+        winfw_settings: &WinFwSettings<'_>,
         tunnel_metadata: &TunnelMetadata,
         dns_config: &ResolvedDnsConfig,
     ) -> Result<(), Error> {
@@ -342,16 +388,14 @@ impl Firewall {
         let relay_client_wstr_ptrs_len = relay_client_wstr_ptrs.len();
 
         let tunnel_dns_servers: Vec<WideCString> = dns_config
-            .tunnel_config()
-            .iter()
+            .allowed_tunnel_servers()
             .cloned()
             .map(widestring_ip)
             .collect();
         let tunnel_dns_servers: Vec<*const u16> =
             tunnel_dns_servers.iter().map(|ip| ip.as_ptr()).collect();
         let non_tunnel_dns_servers: Vec<WideCString> = dns_config
-            .non_tunnel_config()
-            .iter()
+            .allowed_non_tunnel_servers()
             .cloned()
             .map(widestring_ip)
             .collect();
@@ -384,7 +428,7 @@ impl Firewall {
 
     fn set_blocked_state(
         &mut self,
-        winfw_settings: &WinFwSettings,
+        winfw_settings: &WinFwSettings<'_>,
         allowed_endpoint: Option<WinFwAllowedEndpointContainer>,
     ) -> Result<(), Error> {
         log::trace!("Applying 'blocked' firewall policy");
@@ -458,6 +502,33 @@ pub extern "system" fn log_sink(
 }
 
 /// Convert `mb_string`, with the given character encoding `codepage`, to a UTF-16 string.
+/// Describe all WFP sublayers, and all WFP filters that are either installed in one of the firewall
+/// sublayers or that block traffic.
+pub fn describe_filters() -> Result<String, Error> {
+    let mut lines: Vec<String> = vec![];
+    unsafe {
+        WinFw_DescribeFilters(
+            Some(diagnostics_sink),
+            &mut lines as *mut Vec<String> as *mut c_void,
+        )
+        .into_result()?
+    };
+    Ok(lines.join("\n"))
+}
+
+extern "system" fn diagnostics_sink(line: *const c_char, context: *mut c_void) {
+    if line.is_null() || context.is_null() {
+        return;
+    }
+    let lines = unsafe { &mut *(context as *mut Vec<String>) };
+    let mb_string = unsafe { CStr::from_ptr(line) };
+    let line = match multibyte_to_wide(mb_string, CP_ACP) {
+        Ok(wide_str) => String::from_utf16_lossy(&wide_str),
+        Err(_) => mb_string.to_string_lossy().into_owned(),
+    };
+    lines.push(line);
+}
+
 fn multibyte_to_wide(mb_string: &CStr, codepage: u32) -> Result<Vec<u16>, io::Error> {
     if mb_string.is_empty() {
         return Ok(vec![]);
@@ -556,11 +627,15 @@ fn with_wmi_if_enabled(f: impl FnOnce(&wmi::WMIConnection)) {
 
 #[allow(non_snake_case)]
 mod winfw {
-    use super::{widestring_ip, AllowedEndpoint, AllowedTunnelTraffic, Error, WideCString};
+    use super::{
+        widestring_ip, AllowedEndpoint, AllowedIncoming, AllowedLan, AllowedTunnelTraffic, Error,
+        FirewallException, LanNetwork, WideCString,
+    };
     use std::ffi::{c_char, c_void};
     use talpid_types::net::TransportProtocol;
 
     type LogSink = extern "system" fn(level: log::Level, msg: *const c_char, context: *mut c_void);
+    type DiagnosticsSink = extern "system" fn(line: *const c_char, context: *mut c_void);
 
     pub struct WinFwAllowedEndpointContainer {
         _clients: Box<[WideCString]>,
@@ -640,7 +715,9 @@ mod winfw {
                 AllowedTunnelTraffic::None => WinFwAllowedTunnelTrafficType::None,
                 AllowedTunnelTraffic::All => WinFwAllowedTunnelTrafficType::All,
                 AllowedTunnelTraffic::One(..) => WinFwAllowedTunnelTrafficType::One,
-                AllowedTunnelTraffic::Two(..) => WinFwAllowedTunnelTrafficType::Two,
+                AllowedTunnelTraffic::Two(..) | AllowedTunnelTraffic::Multiple(..) => {
+                    WinFwAllowedTunnelTrafficType::Two
+                }
             }
         }
     }
@@ -668,17 +745,189 @@ mod winfw {
         }
     }
 
+    pub struct WinFwLanContainer {
+        permit_lan: bool,
+        _strings: Box<[(Option<WideCString>, Option<WideCString>)]>,
+        networks: Box<[WinFwLanNetwork]>,
+    }
+
+    impl From<&AllowedLan> for WinFwLanContainer {
+        fn from(allow_lan: &AllowedLan) -> Self {
+            let lan_networks: &[LanNetwork] = match allow_lan {
+                AllowedLan::Only(networks) => networks,
+                AllowedLan::Blocked | AllowedLan::All => &[],
+            };
+            let strings = lan_networks
+                .iter()
+                .map(|lan_network| {
+                    (
+                        lan_network
+                            .network
+                            .map(|network| widestring_ip(network.ip())),
+                        lan_network
+                            .interface
+                            .as_ref()
+                            .map(WideCString::from_str_truncate),
+                    )
+                })
+                .collect::<Box<_>>();
+            let networks = lan_networks
+                .iter()
+                .zip(strings.iter())
+                .map(|(lan_network, (ip, interface))| WinFwLanNetwork {
+                    ip: ip
+                        .as_ref()
+                        .map(|ip| ip.as_ptr())
+                        .unwrap_or(std::ptr::null()),
+                    prefix_length: lan_network
+                        .network
+                        .map(|network| network.prefix())
+                        .unwrap_or(0),
+                    interface_alias: interface
+                        .as_ref()
+                        .map(|alias| alias.as_ptr())
+                        .unwrap_or(std::ptr::null()),
+                })
+                .collect::<Box<_>>();
+
+            WinFwLanContainer {
+                permit_lan: *allow_lan == AllowedLan::All,
+                _strings: strings,
+                networks,
+            }
+        }
+    }
+
+    #[repr(C)]
+    pub struct WinFwLanNetwork {
+        ip: *const libc::wchar_t,
+        prefix_length: u8,
+        interface_alias: *const libc::wchar_t,
+    }
+
+    pub struct WinFwExceptionContainer {
+        _ips: Box<[WideCString]>,
+        exceptions: Box<[WinFwException]>,
+    }
+
+    impl From<&[FirewallException]> for WinFwExceptionContainer {
+        fn from(exceptions: &[FirewallException]) -> Self {
+            let ips = exceptions
+                .iter()
+                .map(|exception| widestring_ip(exception.network.ip()))
+                .collect::<Box<_>>();
+            let exceptions = exceptions
+                .iter()
+                .zip(ips.iter())
+                .map(|(exception, ip)| WinFwException {
+                    ip: ip.as_ptr(),
+                    prefix_length: exception.network.prefix(),
+                    filter_protocol: exception.protocol.is_some(),
+                    protocol: exception
+                        .protocol
+                        .map(WinFwProt::from)
+                        .unwrap_or(WinFwProt::Tcp),
+                    port: exception.port.unwrap_or(0),
+                })
+                .collect::<Box<_>>();
+
+            WinFwExceptionContainer {
+                _ips: ips,
+                exceptions,
+            }
+        }
+    }
+
     #[repr(C)]
-    pub struct WinFwSettings {
+    pub struct WinFwException {
+        ip: *const libc::wchar_t,
+        prefix_length: u8,
+        filter_protocol: bool,
+        protocol: WinFwProt,
+        port: u16,
+    }
+
+    pub struct WinFwAllowedIncomingContainer {
+        _interfaces: Box<[Option<WideCString>]>,
+        allowed_incoming: Box<[WinFwAllowedIncoming]>,
+    }
+
+    impl From<&[AllowedIncoming]> for WinFwAllowedIncomingContainer {
+        fn from(allowed_incoming: &[AllowedIncoming]) -> Self {
+            let interfaces = allowed_incoming
+                .iter()
+                .map(|allowed| {
+                    allowed
+                        .interface
+                        .as_ref()
+                        .map(WideCString::from_str_truncate)
+                })
+                .collect::<Box<_>>();
+            let allowed_incoming = allowed_incoming
+                .iter()
+                .zip(interfaces.iter())
+                .map(|(allowed, interface)| WinFwAllowedIncoming {
+                    protocol: WinFwProt::from(allowed.protocol),
+                    port: allowed.port,
+                    interface_alias: interface
+                        .as_ref()
+                        .map(|alias| alias.as_ptr())
+                        .unwrap_or(std::ptr::null()),
+                })
+                .collect::<Box<_>>();
+
+            WinFwAllowedIncomingContainer {
+                _interfaces: interfaces,
+                allowed_incoming,
+            }
+        }
+    }
+
+    #[repr(C)]
+    pub struct WinFwAllowedIncoming {
+        protocol: WinFwProt,
+        port: u16,
+        interface_alias: *const libc::wchar_t,
+    }
+
+    #[repr(C)]
+    pub struct WinFwSettings<'a> {
         permitDhcp: bool,
         permitLan: bool,
+        numLanNetworks: u32,
+        lanNetworks: *const WinFwLanNetwork,
+        numExceptions: u32,
+        exceptions: *const WinFwException,
+        numAllowedIncoming: u32,
+        allowedIncoming: *const WinFwAllowedIncoming,
+        blockIpv6: bool,
+
+        _phantom: std::marker::PhantomData<(
+            &'a WinFwLanContainer,
+            &'a WinFwExceptionContainer,
+            &'a WinFwAllowedIncomingContainer,
+        )>,
     }
 
-    impl WinFwSettings {
-        pub fn new(permit_lan: bool) -> WinFwSettings {
+    impl<'a> WinFwSettings<'a> {
+        pub fn new(
+            lan: &'a WinFwLanContainer,
+            exceptions: &'a WinFwExceptionContainer,
+            allowed_incoming: &'a WinFwAllowedIncomingContainer,
+            block_ipv6: bool,
+        ) -> Self {
             WinFwSettings {
                 permitDhcp: true,
-                permitLan: permit_lan,
+                permitLan: lan.permit_lan,
+                numLanNetworks: lan.networks.len() as u32,
+                lanNetworks: lan.networks.as_ptr(),
+                numExceptions: exceptions.exceptions.len() as u32,
+                exceptions: exceptions.exceptions.as_ptr(),
+                numAllowedIncoming: allowed_incoming.allowed_incoming.len() as u32,
+                allowedIncoming: allowed_incoming.allowed_incoming.as_ptr(),
+                blockIpv6: block_ipv6,
+
+                _phantom: std::marker::PhantomData,
             }
         }
     }
@@ -693,6 +942,7 @@ mod winfw {
 
     ffi_error!(InitializationResult, Error::Initialization);
     ffi_error!(DeinitializationResult, Error::Deinitialization);
+    ffi_error!(DiagnosticsResult, Error::Diagnostics);
 
     #[derive(Debug)]
     #[allow(dead_code)]
@@ -726,6 +976,7 @@ mod winfw {
         #[link_name = "WinFw_Initialize"]
         pub fn WinFw_Initialize(
             timeout: libc::c_uint,
+            sublayer_weight: u16,
             sink: Option<LogSink>,
             sink_context: *const u8,
         ) -> InitializationResult;
@@ -733,7 +984,8 @@ mod winfw {
         #[link_name = "WinFw_InitializeBlocked"]
         pub fn WinFw_InitializeBlocked(
             timeout: libc::c_uint,
-            settings: &WinFwSettings,
+            sublayer_weight: u16,
+            settings: &WinFwSettings<'_>,
             allowed_endpoint: *const WinFwAllowedEndpoint<'_>,
             sink: Option<LogSink>,
             sink_context: *const u8,
@@ -744,7 +996,7 @@ mod winfw {
 
         #[link_name = "WinFw_ApplyPolicyConnecting"]
         pub fn WinFw_ApplyPolicyConnecting(
-            settings: &WinFwSettings,
+            settings: &WinFwSettings<'_>,
             relay: &WinFwEndpoint,
             relayClient: *const *const libc::wchar_t,
             relayClientLen: usize,
@@ -755,7 +1007,7 @@ mod winfw {
 
         #[link_name = "WinFw_ApplyPolicyConnected"]
         pub fn WinFw_ApplyPolicyConnected(
-            settings: &WinFwSettings,
+            settings: &WinFwSettings<'_>,
             relay: &WinFwEndpoint,
             relayClient: *const *const libc::wchar_t,
             relayClientLen: usize,
@@ -768,11 +1020,17 @@ mod winfw {
 
         #[link_name = "WinFw_ApplyPolicyBlocked"]
         pub fn WinFw_ApplyPolicyBlocked(
-            settings: &WinFwSettings,
+            settings: &WinFwSettings<'_>,
             allowed_endpoint: *const WinFwAllowedEndpoint<'_>,
         ) -> WinFwPolicyStatus;
 
         #[link_name = "WinFw_Reset"]
         pub fn WinFw_Reset() -> WinFwPolicyStatus;
+
+        #[link_name = "WinFw_DescribeFilters"]
+        pub fn WinFw_DescribeFilters(
+            sink: Option<DiagnosticsSink>,
+            sink_context: *mut c_void,
+        ) -> DiagnosticsResult;
     }
 }
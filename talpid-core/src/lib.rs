@@ -14,6 +14,10 @@ pub mod window;
 
 mod offline;
 
+/// Diagnostics and repair of kernel drivers.
+#[cfg(target_os = "windows")]
+pub mod drivers;
+
 /// Split tunneling
 pub mod split_tunnel;
 
@@ -0,0 +1,182 @@
+//! Detection of captive portals. A captive portal intercepts plain HTTP requests and redirects
+//! them to a login page until the user has logged in, and drops all other traffic, including the
+//! traffic to the relay.
+
+#[cfg(target_os = "linux")]
+use std::os::fd::AsRawFd;
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpSocket,
+};
+
+/// Port that probes are sent to. Portals only intercept plain HTTP.
+pub const PROBE_PORT: u16 = 80;
+
+/// How long to wait for the response to a probe.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Largest response header that is accepted.
+const MAX_HEADER_LEN: usize = 8 * 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to send the probe")]
+    Io(#[from] io::Error),
+
+    #[error("Timed out waiting for a response to the probe")]
+    Timeout,
+
+    #[error("Received an invalid HTTP response")]
+    InvalidResponse,
+}
+
+/// A captive portal that a probe was redirected to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CaptivePortal {
+    /// Page that the probe was redirected to, where the user is expected to log in.
+    pub login_url: String,
+}
+
+impl CaptivePortal {
+    /// Host name or address of the login page.
+    pub fn login_host(&self) -> Option<&str> {
+        url_host(&self.login_url)
+    }
+}
+
+/// Send a plain HTTP request for `host` to `address`, and return the captive portal that it was
+/// redirected to, if any. Redirects to `host` itself, e.g. to upgrade to HTTPS, do not indicate
+/// a portal.
+pub async fn probe(
+    address: IpAddr,
+    host: &str,
+    #[cfg(target_os = "linux")] fwmark: u32,
+) -> Result<Option<CaptivePortal>, Error> {
+    let response = tokio::time::timeout(
+        PROBE_TIMEOUT,
+        send_probe(
+            SocketAddr::new(address, PROBE_PORT),
+            host,
+            #[cfg(target_os = "linux")]
+            fwmark,
+        ),
+    )
+    .await
+    .map_err(|_| Error::Timeout)??;
+
+    let login_url = parse_redirect(&response).ok_or(Error::InvalidResponse)?;
+    Ok(login_url
+        .filter(|url| url_host(url).is_some_and(|url_host| !url_host.eq_ignore_ascii_case(host)))
+        .map(|login_url| CaptivePortal { login_url }))
+}
+
+/// Send the request and return the response header.
+async fn send_probe(
+    address: SocketAddr,
+    host: &str,
+    #[cfg(target_os = "linux")] fwmark: u32,
+) -> Result<String, Error> {
+    let socket = if address.is_ipv4() {
+        TcpSocket::new_v4()?
+    } else {
+        TcpSocket::new_v6()?
+    };
+    // Send the probe outside the tunnel
+    #[cfg(target_os = "linux")]
+    nix::sys::socket::setsockopt(socket.as_raw_fd(), nix::sys::socket::sockopt::Mark, &fwmark)
+        .map_err(io::Error::from)?;
+
+    let mut stream = socket.connect(address).await?;
+    let request = format!("GET / HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = vec![];
+    let mut buffer = [0u8; 1024];
+    while !response.windows(4).any(|window| window == b"\r\n\r\n") {
+        let len = stream.read(&mut buffer).await?;
+        if len == 0 {
+            break;
+        }
+        response.extend_from_slice(&buffer[..len]);
+        if response.len() > MAX_HEADER_LEN {
+            return Err(Error::InvalidResponse);
+        }
+    }
+    // The body may have been partially read as well, and is not necessarily text
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+/// Return the target of a redirect in `response`, or `None` if the response is not a redirect.
+/// Returns `None` on the outer level if the response cannot be parsed.
+fn parse_redirect(response: &str) -> Option<Option<String>> {
+    let mut lines = response.lines();
+    let status = lines
+        .next()?
+        .strip_prefix("HTTP/1.")?
+        .split(' ')
+        .nth(1)?
+        .parse::<u16>()
+        .ok()?;
+    if !(300..400).contains(&status) {
+        return Some(None);
+    }
+    let location = lines
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("location"))
+        .map(|(_, value)| value.trim().to_owned());
+    Some(location)
+}
+
+/// Return the host of an absolute URL, without the port or brackets around IPv6 addresses.
+fn url_host(url: &str) -> Option<&str> {
+    let (_scheme, rest) = url.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host_and_port = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = match host_and_port.strip_prefix('[') {
+        Some(ipv6) => ipv6.split_once(']')?.0,
+        None => host_and_port.split(':').next()?,
+    };
+    (!host.is_empty()).then_some(host)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_redirect() {
+        let response = "HTTP/1.1 302 Found\r\nContent-Length: 0\r\nLocation: http://portal.example/login?next=1\r\n\r\n";
+        assert_eq!(
+            parse_redirect(response),
+            Some(Some("http://portal.example/login?next=1".to_owned()))
+        );
+
+        let response = "HTTP/1.1 204 No Content\r\nLocation: http://portal.example\r\n\r\n";
+        assert_eq!(parse_redirect(response), Some(None));
+
+        assert_eq!(parse_redirect("HTTP/1.1 301 Moved\r\n\r\n"), Some(None));
+        assert_eq!(parse_redirect("SSH-2.0-OpenSSH\r\n"), None);
+    }
+
+    #[test]
+    fn test_url_host() {
+        assert_eq!(
+            url_host("http://portal.example/login"),
+            Some("portal.example")
+        );
+        assert_eq!(url_host("https://192.0.2.1:8443"), Some("192.0.2.1"));
+        assert_eq!(
+            url_host("http://user@[2001:db8::1]:8080/"),
+            Some("2001:db8::1")
+        );
+        assert_eq!(url_host("/login"), None);
+    }
+}
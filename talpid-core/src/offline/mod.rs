@@ -4,8 +4,16 @@ use futures::channel::mpsc::UnboundedSender;
 use std::sync::LazyLock;
 #[cfg(not(target_os = "android"))]
 use talpid_routing::RouteManagerHandle;
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::OfflineMonitorBackend;
 use talpid_types::{net::Connectivity, ErrorExt};
 
+#[cfg(not(target_os = "android"))]
+pub mod captive_portal;
+
+#[cfg(target_os = "linux")]
+mod network_manager;
+
 #[cfg(target_os = "macos")]
 #[path = "macos.rs"]
 mod imp;
@@ -29,12 +37,20 @@ static FORCE_DISABLE_OFFLINE_MONITOR: LazyLock<bool> = LazyLock::new(|| {
         .unwrap_or(false)
 });
 
-pub struct MonitorHandle(Option<imp::MonitorHandle>);
+pub struct MonitorHandle(Option<Monitor>);
+
+enum Monitor {
+    Platform(imp::MonitorHandle),
+    #[cfg(target_os = "linux")]
+    NetworkManager(network_manager::MonitorHandle),
+}
 
 impl MonitorHandle {
     pub async fn connectivity(&self) -> Connectivity {
         match self.0.as_ref() {
-            Some(monitor) => monitor.connectivity().await,
+            Some(Monitor::Platform(monitor)) => monitor.connectivity().await,
+            #[cfg(target_os = "linux")]
+            Some(Monitor::NetworkManager(monitor)) => monitor.connectivity().await,
             None => Connectivity::PresumeOnline,
         }
     }
@@ -42,31 +58,44 @@ impl MonitorHandle {
 
 pub async fn spawn_monitor(
     sender: UnboundedSender<Connectivity>,
+    #[cfg(not(target_os = "android"))] backend: OfflineMonitorBackend,
     #[cfg(not(target_os = "android"))] route_manager: RouteManagerHandle,
     #[cfg(target_os = "linux")] fwmark: Option<u32>,
     #[cfg(target_os = "android")] connectivity_listener: ConnectivityListener,
 ) -> MonitorHandle {
-    let monitor = if *FORCE_DISABLE_OFFLINE_MONITOR {
-        None
-    } else {
-        imp::spawn_monitor(
+    if *FORCE_DISABLE_OFFLINE_MONITOR {
+        return MonitorHandle(None);
+    }
+
+    #[cfg(not(target_os = "android"))]
+    log::debug!("Using offline monitor backend: {backend}");
+
+    #[cfg(not(target_os = "android"))]
+    let monitor = match backend {
+        OfflineMonitorBackend::Routes => imp::spawn_monitor(
             sender,
-            #[cfg(not(target_os = "android"))]
             route_manager,
             #[cfg(target_os = "linux")]
             fwmark,
-            #[cfg(target_os = "android")]
-            connectivity_listener,
         )
         .await
-        .inspect_err(|error| {
-            log::warn!(
-                "{}",
-                error.display_chain_with_msg("Failed to spawn offline monitor")
-            );
-        })
-        .ok()
+        .map(Monitor::Platform)
+        .map_err(|error| error.display_chain_with_msg("Failed to spawn offline monitor")),
+        #[cfg(target_os = "linux")]
+        OfflineMonitorBackend::NetworkManager => network_manager::spawn_monitor(sender)
+            .await
+            .map(Monitor::NetworkManager)
+            .map_err(|error| {
+                error.display_chain_with_msg("Failed to spawn NetworkManager offline monitor")
+            }),
+        OfflineMonitorBackend::Disabled => return MonitorHandle(None),
     };
 
-    MonitorHandle(monitor)
+    #[cfg(target_os = "android")]
+    let monitor = imp::spawn_monitor(sender, connectivity_listener)
+        .await
+        .map(Monitor::Platform)
+        .map_err(|error| error.display_chain_with_msg("Failed to spawn offline monitor"));
+
+    MonitorHandle(monitor.inspect_err(|error| log::warn!("{error}")).ok())
 }
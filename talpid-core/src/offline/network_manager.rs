@@ -0,0 +1,91 @@
+//! Offline monitor that asks NetworkManager whether the host is connected, rather than looking
+//! for default routes itself. This is an alternative for setups where the routing table does not
+//! reflect whether the host is online, such as some bridged virtual machines.
+//!
+//! NetworkManager does not report which IP versions are available, so IPv6 is presumed to be
+//! unavailable while connected.
+use futures::channel::mpsc::UnboundedSender;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use talpid_dbus::network_manager::{self, NetworkManager};
+use talpid_types::{net::Connectivity, ErrorExt};
+
+/// How often NetworkManager is asked for its state.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    #[error("Failed to connect to NetworkManager")]
+    NetworkManager(#[source] network_manager::Error),
+}
+
+pub struct MonitorHandle {
+    connectivity: Arc<Mutex<Connectivity>>,
+    _notify_tx: Arc<UnboundedSender<Connectivity>>,
+}
+
+impl MonitorHandle {
+    #[allow(clippy::unused_async)]
+    pub async fn connectivity(&self) -> Connectivity {
+        *self.connectivity.lock().unwrap()
+    }
+}
+
+pub async fn spawn_monitor(
+    notify_tx: UnboundedSender<Connectivity>,
+) -> Result<MonitorHandle, Error> {
+    let network_manager = Arc::new(NetworkManager::new().map_err(Error::NetworkManager)?);
+    network_manager
+        .ensure_network_manager_exists()
+        .map_err(Error::NetworkManager)?;
+
+    let connectivity = Arc::new(Mutex::new(
+        check_connectivity(network_manager.clone()).await,
+    ));
+    let notify_tx = Arc::new(notify_tx);
+    let sender = Arc::downgrade(&notify_tx);
+    let monitor_handle = MonitorHandle {
+        connectivity: connectivity.clone(),
+        _notify_tx: notify_tx,
+    };
+
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            let Some(sender) = sender.upgrade() else {
+                return;
+            };
+            let new_connectivity = check_connectivity(network_manager.clone()).await;
+            let changed = {
+                let mut connectivity = connectivity.lock().unwrap();
+                std::mem::replace(&mut *connectivity, new_connectivity) != new_connectivity
+            };
+            if changed {
+                let _ = sender.unbounded_send(new_connectivity);
+            }
+        }
+    });
+
+    Ok(monitor_handle)
+}
+
+async fn check_connectivity(network_manager: Arc<NetworkManager>) -> Connectivity {
+    // D-Bus calls are blocking
+    let result = tokio::task::spawn_blocking(move || network_manager.is_connected()).await;
+    match result {
+        Ok(Ok(true)) => Connectivity::PresumeOnline,
+        Ok(Ok(false)) => Connectivity::Offline,
+        Ok(Err(error)) => {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg(
+                    "Failed to get NetworkManager state. Presuming connectivity"
+                )
+            );
+            Connectivity::PresumeOnline
+        }
+        Err(_) => Connectivity::PresumeOnline,
+    }
+}
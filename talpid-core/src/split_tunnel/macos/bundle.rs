@@ -0,0 +1,128 @@
+//! Identification of apps by bundle identifier, such as `com.apple.Safari`.
+//!
+//! An excluded app may be given as a bundle identifier instead of a path. A process is then
+//! excluded if its executable is located anywhere inside an app bundle with that identifier. This
+//! also covers helper executables nested inside the bundle, and keeps matching after the app has
+//! been updated or moved.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use system_configuration::core_foundation::{
+    base::{CFType, TCFType, ToVoid},
+    dictionary::CFDictionary,
+    propertylist::{create_with_data, kCFPropertyListImmutable, CFPropertyList},
+    string::CFString,
+};
+
+const BUNDLE_EXTENSION: &str = "app";
+const INFO_PLIST: &str = "Contents/Info.plist";
+const BUNDLE_IDENTIFIER_KEY: &str = "CFBundleIdentifier";
+
+/// Return whether an excluded app refers to a bundle identifier rather than a path.
+/// Bundle identifiers are reverse-DNS strings, such as `com.apple.Safari`.
+pub fn is_bundle_id(app: &Path) -> bool {
+    let Some(app) = app.to_str() else {
+        return false;
+    };
+    let mut labels = app.split('.');
+    labels.clone().count() >= 2
+        && labels.all(|label| {
+            !label.is_empty()
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+}
+
+/// Return all app bundles that `exec_path` is contained in, innermost first.
+fn enclosing_bundles(exec_path: &Path) -> impl Iterator<Item = &Path> {
+    exec_path
+        .ancestors()
+        .skip(1)
+        .filter(|path| path.extension().is_some_and(|ext| ext == BUNDLE_EXTENSION))
+}
+
+/// Read the bundle identifier from the `Info.plist` of the app bundle at `bundle`.
+fn read_bundle_id(bundle: &Path) -> Option<String> {
+    let data = fs::read(bundle.join(INFO_PLIST)).ok()?;
+    let (plist, _format) = create_with_data(data, kCFPropertyListImmutable).ok()?;
+    // SAFETY: `create_with_data` returns an owned reference on success
+    let plist = unsafe { CFPropertyList::wrap_under_create_rule(plist) };
+
+    plist
+        .downcast_into::<CFDictionary>()?
+        .find(CFString::from_static_string(BUNDLE_IDENTIFIER_KEY).to_void())
+        .map(|ptr| unsafe { CFType::wrap_under_get_rule(*ptr) })
+        .and_then(|id| id.downcast::<CFString>())
+        .map(|id| id.to_string())
+}
+
+/// Maps app bundles to their bundle identifiers, so that `Info.plist` is only read once per
+/// bundle.
+#[derive(Debug, Default)]
+pub struct BundleIds {
+    ids: HashMap<PathBuf, Option<String>>,
+}
+
+impl BundleIds {
+    /// Return the identifier in `excluded` of any bundle that `exec_path` is contained in.
+    pub fn find_excluded(
+        &mut self,
+        exec_path: &Path,
+        excluded: &HashSet<String>,
+    ) -> Option<String> {
+        if excluded.is_empty() {
+            return None;
+        }
+        enclosing_bundles(exec_path).find_map(|bundle| {
+            self.ids
+                .entry(bundle.to_path_buf())
+                .or_insert_with(|| read_bundle_id(bundle))
+                .as_ref()
+                .filter(|id| excluded.contains(*id))
+                .cloned()
+        })
+    }
+
+    /// Forget all known bundles. Bundles may have been replaced since they were last read.
+    pub fn clear(&mut self) {
+        self.ids.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_is_bundle_id() {
+        assert!(is_bundle_id(Path::new("com.apple.Safari")));
+        assert!(is_bundle_id(Path::new("org.mozilla.firefox")));
+        assert!(is_bundle_id(Path::new("com.microsoft.VSCode.helper-1")));
+
+        assert!(!is_bundle_id(Path::new("/Applications/Safari.app")));
+        assert!(!is_bundle_id(Path::new("Safari.app/Contents")));
+        assert!(!is_bundle_id(Path::new("Safari")));
+        assert!(!is_bundle_id(Path::new("com..Safari")));
+    }
+
+    #[test]
+    fn test_enclosing_bundles() {
+        let path = Path::new(
+            "/Applications/Slack.app/Contents/Frameworks/Slack Helper.app/Contents/MacOS/Slack Helper",
+        );
+        let bundles: Vec<_> = enclosing_bundles(path).collect();
+        assert_eq!(
+            bundles,
+            vec![
+                Path::new("/Applications/Slack.app/Contents/Frameworks/Slack Helper.app"),
+                Path::new("/Applications/Slack.app"),
+            ]
+        );
+
+        assert_eq!(enclosing_bundles(Path::new("/usr/bin/curl")).count(), 0);
+    }
+}
@@ -13,6 +13,7 @@ use self::process::ExclusionStatus;
 #[allow(non_camel_case_types)]
 mod bindings;
 mod bpf;
+mod bundle;
 mod default;
 mod process;
 mod tun;
@@ -1,10 +1,11 @@
 //! This module keeps tracks of maintains a list of processes, and keeps it up to date by observing
 //! the syscalls `fork`, `exec`, and `exit`.
-//! Each process has an exclusion state, based on which paths the process monitor is instructed to
-//! exclude.
+//! Each process has an exclusion state, based on which paths and bundle identifiers the process
+//! monitor is instructed to exclude.
 //! The module currently relies on the `eslogger` tool to do so, which in turn relies on the
 //! Endpoint Security framework.
 
+use super::bundle::{self, BundleIds};
 use futures::channel::oneshot;
 use libc::pid_t;
 use serde::Deserialize;
@@ -297,6 +298,8 @@ pub enum ExclusionStatus {
 struct InnerProcessStates {
     processes: HashMap<pid_t, ProcessInfo>,
     exclude_paths: HashSet<PathBuf>,
+    exclude_bundle_ids: HashSet<String>,
+    bundle_ids: BundleIds,
 }
 
 impl ProcessStates {
@@ -305,6 +308,8 @@ impl ProcessStates {
         let mut states = InnerProcessStates {
             processes: HashMap::new(),
             exclude_paths: HashSet::new(),
+            exclude_bundle_ids: HashSet::new(),
+            bundle_ids: BundleIds::default(),
         };
 
         let processes = list_pids().map_err(Error::InitializePids)?;
@@ -319,8 +324,18 @@ impl ProcessStates {
         })
     }
 
+    /// Set the apps to exclude. Each app is either the path of an executable or a bundle
+    /// identifier, such as `com.apple.Safari`.
     pub fn exclude_paths(&self, paths: HashSet<PathBuf>) {
         let mut inner = self.inner.lock().unwrap();
+        let inner = &mut *inner;
+
+        let exclude_bundle_ids: HashSet<String> = paths
+            .iter()
+            .filter(|path| bundle::is_bundle_id(path))
+            .filter_map(|path| path.to_str().map(str::to_owned))
+            .collect();
+        inner.bundle_ids.clear();
 
         for info in inner.processes.values_mut() {
             // Remove no-longer excluded paths from exclusion list
@@ -335,10 +350,19 @@ impl ProcessStates {
                 new_exclude_paths.insert(info.exec_path.clone());
             }
 
+            // Check if own app bundle is excluded
+            if let Some(bundle_id) = inner
+                .bundle_ids
+                .find_excluded(&info.exec_path, &exclude_bundle_ids)
+            {
+                new_exclude_paths.insert(PathBuf::from(bundle_id));
+            }
+
             info.excluded_by_paths = new_exclude_paths;
         }
 
         inner.exclude_paths = paths;
+        inner.exclude_bundle_ids = exclude_bundle_ids;
     }
 
     pub fn get_process_status(&self, pid: pid_t) -> ExclusionStatus {
@@ -415,6 +439,16 @@ impl InnerProcessStates {
         if self.exclude_paths.contains(&info.exec_path) {
             info.excluded_by_paths.insert(info.exec_path.clone());
             log::trace!("Excluding {pid} by path: {}", info.exec_path.display());
+            return;
+        }
+
+        // Exclude if the app bundle that the executable belongs to is excluded
+        if let Some(bundle_id) = self
+            .bundle_ids
+            .find_excluded(&info.exec_path, &self.exclude_bundle_ids)
+        {
+            log::trace!("Excluding {pid} by bundle identifier: {bundle_id}");
+            info.excluded_by_paths.insert(PathBuf::from(bundle_id));
         }
     }
 
@@ -428,6 +462,7 @@ impl InnerProcessStates {
 #[derive(Debug, Clone)]
 struct ProcessInfo {
     exec_path: PathBuf,
+    /// Excluded paths and bundle identifiers that this process, or any of its ancestors, matched
     excluded_by_paths: HashSet<PathBuf>,
 }
 
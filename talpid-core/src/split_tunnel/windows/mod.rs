@@ -2,10 +2,13 @@
 
 mod driver;
 mod path_monitor;
+mod pattern;
 mod service;
 mod volume_monitor;
 mod windows;
 
+pub(crate) use service::SPLIT_TUNNEL_SERVICE;
+
 use crate::{tunnel::TunnelMetadata, tunnel_state_machine::TunnelCommand};
 use futures::channel::{mpsc, oneshot};
 use std::{
@@ -16,7 +19,8 @@ use std::{
     path::{Path, PathBuf},
     sync::{
         atomic::{AtomicBool, Ordering},
-        mpsc as sync_mpsc, Arc, Mutex, MutexGuard, RwLock, Weak,
+        mpsc::{self as sync_mpsc, RecvTimeoutError},
+        Arc, Mutex, MutexGuard, RwLock, Weak,
     },
     time::Duration,
 };
@@ -32,6 +36,10 @@ use windows_sys::Win32::Foundation::ERROR_OPERATION_ABORTED;
 const DRIVER_EVENT_BUFFER_SIZE: usize = 2048;
 const RESERVED_IP_V4: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 123);
 
+/// How often excluded path patterns are matched against the filesystem again, so that new or
+/// moved executables are picked up.
+const PATTERN_RESCAN_INTERVAL: Duration = Duration::from_secs(10);
+
 /// Errors that may occur in [`SplitTunnel`].
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -396,10 +404,14 @@ impl SplitTunnel {
         let monitored_paths = Arc::new(Mutex::new(vec![]));
         let monitored_paths_copy = monitored_paths.clone();
 
+        let expanded_paths = Arc::new(Mutex::new(vec![]));
+        let expanded_paths_copy = expanded_paths.clone();
+
         let (monitor_tx, monitor_rx) = sync_mpsc::channel();
 
         let path_monitor = path_monitor::PathMonitor::spawn(monitor_tx.clone())
             .map_err(Error::StartPathMonitor)?;
+        let path_monitor_copy = path_monitor.clone();
         let volume_monitor = volume_monitor::VolumeMonitor::spawn(
             path_monitor.clone(),
             monitor_tx,
@@ -432,21 +444,27 @@ impl SplitTunnel {
                 let response = match request {
                     Request::SetPaths(paths) => {
                         let mut monitored_paths_guard = monitored_paths.lock().unwrap();
+                        let mut expanded_paths_guard = expanded_paths.lock().unwrap();
+
+                        let expanded = pattern::expand_paths(&paths);
 
-                        let result = if !paths.is_empty() {
-                            handle.set_config(&paths).map_err(Error::SetConfiguration)
+                        let result = if !expanded.is_empty() {
+                            handle
+                                .set_config(&expanded)
+                                .map_err(Error::SetConfiguration)
                         } else {
                             handle.clear_config().map_err(Error::SetConfiguration)
                         };
 
                         if result.is_ok() {
-                            if let Err(error) = path_monitor.set_paths(&paths) {
+                            if let Err(error) = path_monitor.set_paths(&expanded) {
                                 log::error!(
                                     "{}",
                                     error.display_chain_with_msg("Failed to update path monitor")
                                 );
                             }
                             *monitored_paths_guard = paths;
+                            *expanded_paths_guard = expanded;
                         }
 
                         result
@@ -480,6 +498,7 @@ impl SplitTunnel {
                         }
 
                         monitored_paths.lock().unwrap().clear();
+                        expanded_paths.lock().unwrap().clear();
                         excluded_processes.write().unwrap().clear();
 
                         let _ = response_tx.send(Ok(()));
@@ -518,21 +537,49 @@ impl SplitTunnel {
 
         let handle_copy = handle.clone();
 
-        std::thread::spawn(move || {
-            while let Ok(()) = monitor_rx.recv() {
-                let paths = monitored_paths_copy.lock().unwrap();
-                let result = if paths.len() > 0 {
-                    log::debug!("Re-resolving excluded paths");
-                    handle_copy.set_config(&paths)
-                } else {
-                    continue;
-                };
-                if let Err(error) = result {
+        std::thread::spawn(move || loop {
+            let paths_changed = match monitor_rx.recv_timeout(PATTERN_RESCAN_INTERVAL) {
+                Ok(()) => true,
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            let paths = monitored_paths_copy.lock().unwrap();
+            if paths.is_empty() {
+                continue;
+            }
+            if !paths_changed && !paths.iter().any(|path| pattern::is_pattern(path)) {
+                continue;
+            }
+
+            let mut expanded_paths = expanded_paths_copy.lock().unwrap();
+            let expanded = pattern::expand_paths(&paths);
+
+            if paths_changed {
+                log::debug!("Re-resolving excluded paths");
+            } else if *expanded_paths != expanded {
+                log::debug!("Files matching excluded path patterns changed");
+                if let Err(error) = path_monitor_copy.set_paths(&expanded) {
                     log::error!(
                         "{}",
-                        error.display_chain_with_msg("Failed to update excluded paths")
+                        error.display_chain_with_msg("Failed to update path monitor")
                     );
                 }
+            } else {
+                continue;
+            }
+
+            let result = if !expanded.is_empty() {
+                handle_copy.set_config(&expanded)
+            } else {
+                handle_copy.clear_config()
+            };
+            match result {
+                Ok(()) => *expanded_paths = expanded,
+                Err(error) => log::error!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to update excluded paths")
+                ),
             }
         });
 
@@ -0,0 +1,175 @@
+//! Expansion of wildcard patterns, such as `C:\Games\**\*.exe`, in excluded paths.
+//!
+//! The driver only accepts concrete paths, so any pattern is matched against the filesystem and
+//! replaced by the files it currently matches. `*` and `?` match any sequence of characters and
+//! any single character within a path component, and a `**` component matches any number of
+//! directories. Matching is case-insensitive.
+
+use std::{
+    ffi::{OsStr, OsString},
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// Upper bound on the number of files that a single pattern may expand to.
+const MAX_EXPANDED_PATHS: usize = 1024;
+
+const RECURSIVE_WILDCARD: &str = "**";
+
+/// Return whether `path` contains any wildcards.
+pub fn is_pattern(path: &OsStr) -> bool {
+    path.to_string_lossy().contains(['*', '?'])
+}
+
+/// Replace all patterns in `paths` with the files that they match. Concrete paths are passed
+/// through as-is.
+pub fn expand_paths<T: AsRef<OsStr>>(paths: &[T]) -> Vec<OsString> {
+    let mut expanded = vec![];
+
+    for path in paths {
+        let path = path.as_ref();
+        if !is_pattern(path) {
+            expanded.push(path.to_os_string());
+            continue;
+        }
+
+        let matches = expand(Path::new(path));
+        log::debug!(
+            "Pattern {} matched {} file(s)",
+            Path::new(path).display(),
+            matches.len()
+        );
+        expanded.extend(matches.into_iter().map(PathBuf::into_os_string));
+    }
+
+    expanded.sort();
+    expanded.dedup();
+    expanded
+}
+
+fn expand(pattern: &Path) -> Vec<PathBuf> {
+    if !pattern.is_absolute() {
+        log::warn!("Ignoring relative path pattern: {}", pattern.display());
+        return vec![];
+    }
+
+    let mut base = PathBuf::new();
+    let mut rest = vec![];
+
+    for component in pattern.components() {
+        let Component::Normal(name) = component else {
+            base.push(component);
+            continue;
+        };
+        if rest.is_empty() && !is_pattern(name) {
+            base.push(name);
+            continue;
+        }
+        match name.to_str() {
+            Some(name) => rest.push(name.to_lowercase()),
+            None => {
+                log::warn!("Ignoring non-UTF-8 path pattern: {}", pattern.display());
+                return vec![];
+            }
+        }
+    }
+
+    let mut matches = vec![];
+    walk(&base, &rest, &mut matches);
+
+    if matches.len() >= MAX_EXPANDED_PATHS {
+        log::warn!(
+            "Pattern {} matched too many files. Only the first {MAX_EXPANDED_PATHS} are excluded",
+            pattern.display()
+        );
+        matches.truncate(MAX_EXPANDED_PATHS);
+    }
+
+    matches
+}
+
+fn walk(path: &Path, components: &[String], matches: &mut Vec<PathBuf>) {
+    if matches.len() >= MAX_EXPANDED_PATHS {
+        return;
+    }
+
+    let Some((component, rest)) = components.split_first() else {
+        if path.is_file() {
+            matches.push(path.to_path_buf());
+        }
+        return;
+    };
+
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+
+    if component == RECURSIVE_WILDCARD {
+        walk(path, rest, matches);
+    }
+
+    for entry in entries.flatten() {
+        if component == RECURSIVE_WILDCARD {
+            // Do not follow links, since they may form cycles
+            if entry.file_type().map(|t| t.is_dir()).unwrap_or(false) {
+                walk(&entry.path(), components, matches);
+            }
+            continue;
+        }
+
+        let name = entry.file_name().to_string_lossy().to_lowercase();
+        if matches_component(component, &name) {
+            walk(&entry.path(), rest, matches);
+        }
+    }
+}
+
+/// Match a single path component against a pattern containing `*` and `?`.
+fn matches_component(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    let (mut p, mut n) = (0, 0);
+    let mut backtrack = None;
+
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod test {
+    use super::matches_component;
+
+    #[test]
+    fn test_matches_component() {
+        assert!(matches_component("*.exe", "game.exe"));
+        assert!(matches_component("*.exe", ".exe"));
+        assert!(matches_component("game?.exe", "game1.exe"));
+        assert!(matches_component("*launcher*", "steamlauncher64.exe"));
+        assert!(matches_component("*", ""));
+
+        assert!(!matches_component("*.exe", "game.dll"));
+        assert!(!matches_component("game?.exe", "game.exe"));
+        assert!(!matches_component("game", "game.exe"));
+    }
+}
@@ -13,7 +13,7 @@ use windows_service::{
 };
 use windows_sys::Win32::Foundation::{ERROR_SERVICE_ALREADY_RUNNING, ERROR_SERVICE_DOES_NOT_EXIST};
 
-const SPLIT_TUNNEL_SERVICE: &str = "mullvad-split-tunnel";
+pub(crate) const SPLIT_TUNNEL_SERVICE: &str = "mullvad-split-tunnel";
 const SPLIT_TUNNEL_DISPLAY_NAME: &str = "Mullvad Split Tunnel Service";
 const DRIVER_FILENAME: &str = "mullvad-split-tunnel.sys";
 
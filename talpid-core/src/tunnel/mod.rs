@@ -168,7 +168,9 @@ impl TunnelMonitor {
             TunnelParameters::OpenVpn(params) => match &params.proxy {
                 Some(CustomProxy::Shadowsocks(_)) => Some(std::env::current_exe().unwrap()),
                 Some(CustomProxy::Socks5Local(_)) => None,
-                Some(CustomProxy::Socks5Remote(_)) | None => Some(resource_dir.join("openvpn.exe")),
+                Some(CustomProxy::Socks5Remote(_)) | Some(CustomProxy::Http(_)) | None => {
+                    Some(resource_dir.join("openvpn.exe"))
+                }
             },
             _ => Some(std::env::current_exe().unwrap()),
         }
@@ -1,14 +1,20 @@
 use futures::channel::{mpsc, oneshot};
 use futures::stream::Fuse;
 use futures::StreamExt;
+#[cfg(not(target_os = "android"))]
+use std::sync::Arc;
+use std::time::Instant;
 
+use talpid_tunnel::TrafficStatsHandle;
 use talpid_types::net::{AllowedClients, AllowedEndpoint, TunnelParameters};
-use talpid_types::tunnel::{ErrorStateCause, FirewallPolicyError};
+use talpid_types::tunnel::{ConnectionTimings, ErrorStateCause, FirewallPolicyError};
 use talpid_types::{BoxedError, ErrorExt};
 
 #[cfg(target_os = "macos")]
 use crate::dns::DnsConfig;
 use crate::dns::ResolvedDnsConfig;
+#[cfg(not(target_os = "android"))]
+use crate::dns::{DnsForwarder, DnsForwarderUpstream};
 use crate::firewall::FirewallPolicy;
 #[cfg(target_os = "macos")]
 use crate::resolver::LOCAL_DNS_RESOLVER;
@@ -33,6 +39,7 @@ pub struct ConnectedState {
     tunnel_parameters: TunnelParameters,
     tunnel_close_event: TunnelCloseEvent,
     tunnel_close_tx: oneshot::Sender<()>,
+    traffic_stats: TrafficStatsHandle,
 }
 
 impl ConnectedState {
@@ -44,6 +51,8 @@ impl ConnectedState {
         tunnel_parameters: TunnelParameters,
         tunnel_close_event: TunnelCloseEvent,
         tunnel_close_tx: oneshot::Sender<()>,
+        traffic_stats: TrafficStatsHandle,
+        mut timings: ConnectionTimings,
     ) -> (Box<dyn TunnelState>, TunnelStateTransition) {
         let connected_state = ConnectedState {
             metadata,
@@ -51,14 +60,18 @@ impl ConnectedState {
             tunnel_parameters,
             tunnel_close_event,
             tunnel_close_tx,
+            traffic_stats,
         };
 
         let tunnel_interface = Some(connected_state.metadata.interface.clone());
         let tunnel_endpoint = talpid_types::net::TunnelEndpoint {
             tunnel_interface,
+            wireguard_backend: connected_state.metadata.wireguard_backend,
+            wireguard_worker_threads: connected_state.metadata.wireguard_worker_threads,
             ..connected_state.tunnel_parameters.get_tunnel_endpoint()
         };
 
+        let apply_started = Instant::now();
         if let Err(error) = connected_state.set_firewall_policy(shared_values) {
             DisconnectingState::enter(
                 connected_state.tunnel_close_tx,
@@ -73,9 +86,14 @@ impl ConnectedState {
                 AfterDisconnect::Block(ErrorStateCause::SetDnsError),
             )
         } else {
+            let firewall_and_dns = apply_started.elapsed();
+            timings.firewall_and_dns = Some(firewall_and_dns);
+            timings.total = timings.total.map(|total| total + firewall_and_dns);
+            log::info!("Connection timings: {timings}");
+
             (
                 Box::new(connected_state),
-                TunnelStateTransition::Connected(tunnel_endpoint),
+                TunnelStateTransition::Connected(tunnel_endpoint, timings),
             )
         }
     }
@@ -136,9 +154,21 @@ impl ConnectedState {
         FirewallPolicy::Connected {
             peer_endpoint,
             tunnel: self.metadata.clone(),
-            allow_lan: shared_values.allow_lan,
+            allow_lan: shared_values.allow_lan.clone(),
             #[cfg(not(target_os = "android"))]
             dns_config: Self::resolve_dns(&self.metadata, shared_values),
+            #[cfg(not(target_os = "android"))]
+            exceptions: shared_values.firewall_exceptions.clone(),
+            #[cfg(not(target_os = "android"))]
+            allowed_incoming: shared_values.allowed_incoming.clone(),
+            #[cfg(not(target_os = "android"))]
+            block_ipv6: shared_values.block_ipv6,
+            #[cfg(target_os = "linux")]
+            split_tunnel_mode: shared_values.split_tunnel_mode,
+            #[cfg(target_os = "linux")]
+            split_tunnel_owners: shared_values.split_tunnel_owners.clone(),
+            #[cfg(target_os = "linux")]
+            split_tunnel_addresses: shared_values.split_tunnel_addresses.clone(),
             #[cfg(target_os = "macos")]
             redirect_interface,
             #[cfg(target_os = "macos")]
@@ -150,16 +180,47 @@ impl ConnectedState {
         metadata: &TunnelMetadata,
         shared_values: &SharedTunnelStateValues,
     ) -> ResolvedDnsConfig {
-        shared_values.dns_config.resolve(
-            &metadata.gateways(),
+        let gateways = metadata.gateways();
+        let mut dns_config = shared_values.dns_config.resolve(
+            &gateways,
             #[cfg(target_os = "macos")]
             53,
-        )
+        );
+        // IPv4 servers are only reachable through the DNS64/NAT64 service of the relay
+        if metadata.is_ipv6_only() {
+            dns_config = dns_config.translate_to_nat64(&gateways);
+        }
+        // IPv6 servers are unreachable when IPv6 is blocked by the firewall
+        #[cfg(not(target_os = "android"))]
+        if !metadata.is_ipv6_only() && shared_values.block_ipv6 {
+            dns_config = dns_config.without_ipv6(&gateways);
+        }
+        // Blocked and bypassed domains are handled by the local forwarder
+        #[cfg(not(target_os = "android"))]
+        if !shared_values.dns_config.blocked_domains().is_empty()
+            || !shared_values.dns_config.bypassed_domains().is_empty()
+        {
+            dns_config = dns_config.with_local_forwarder();
+        }
+        dns_config
     }
 
     fn set_dns(&self, shared_values: &mut SharedTunnelStateValues) -> Result<(), BoxedError> {
         let dns_config: ResolvedDnsConfig = Self::resolve_dns(&self.metadata, shared_values);
 
+        #[cfg(target_os = "linux")]
+        let had_bypassed_addresses = !shared_values.split_tunnel_addresses.is_empty();
+
+        #[cfg(not(target_os = "android"))]
+        Self::update_dns_forwarder(shared_values, &dns_config)?;
+
+        // The addresses of bypassed domains are forgotten when the forwarder is restarted
+        #[cfg(target_os = "linux")]
+        if had_bypassed_addresses && shared_values.split_tunnel_addresses.is_empty() {
+            self.set_firewall_policy(shared_values)
+                .map_err(BoxedError::new)?;
+        }
+
         #[cfg(not(target_os = "macos"))]
         shared_values
             .dns_monitor
@@ -199,6 +260,64 @@ impl ConnectedState {
         Ok(())
     }
 
+    /// Start, restart, or stop the DNS forwarder to match the DNS config.
+    #[cfg(not(target_os = "android"))]
+    fn update_dns_forwarder(
+        shared_values: &mut SharedTunnelStateValues,
+        dns_config: &ResolvedDnsConfig,
+    ) -> Result<(), BoxedError> {
+        let encrypted_servers = shared_values.dns_config.encrypted_servers();
+        let upstream_servers: Vec<_> = dns_config.upstream_servers().copied().collect();
+        let upstream = if !encrypted_servers.is_empty() {
+            Some(DnsForwarderUpstream::Encrypted(encrypted_servers.to_vec()))
+        } else if !upstream_servers.is_empty() {
+            Some(DnsForwarderUpstream::Plaintext(upstream_servers))
+        } else {
+            None
+        };
+        let blocklist = shared_values.dns_config.blocked_domains().clone();
+        let bypassed_domains = shared_values.dns_config.bypassed_domains().clone();
+
+        if let Some(forwarder) = &shared_values.dns_forwarder {
+            if Some(forwarder.upstream()) == upstream.as_ref()
+                && Arc::ptr_eq(forwarder.blocklist(), &blocklist)
+                && forwarder.bypassed_domains() == &bypassed_domains
+            {
+                return Ok(());
+            }
+        }
+        Self::stop_dns_forwarder(shared_values);
+
+        #[cfg(target_os = "linux")]
+        let bypass_tx = Some(shared_values.bypass_tx.clone());
+        // Bypassed domains are only routed outside the tunnel on Linux
+        #[cfg(not(target_os = "linux"))]
+        let bypass_tx = None;
+
+        if let Some(upstream) = upstream {
+            let forwarder = shared_values
+                .runtime
+                .block_on(DnsForwarder::start(
+                    upstream,
+                    blocklist,
+                    bypassed_domains,
+                    bypass_tx,
+                ))
+                .map_err(BoxedError::new)?;
+            shared_values.dns_forwarder = Some(forwarder);
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "android"))]
+    fn stop_dns_forwarder(shared_values: &mut SharedTunnelStateValues) {
+        if let Some(forwarder) = shared_values.dns_forwarder.take() {
+            shared_values.runtime.block_on(forwarder.stop());
+        }
+        #[cfg(target_os = "linux")]
+        shared_values.split_tunnel_addresses.clear();
+    }
+
     fn reset_dns(shared_values: &mut SharedTunnelStateValues) {
         #[cfg(not(target_os = "macos"))]
         if let Err(error) = shared_values.dns_monitor.reset_before_interface_removal() {
@@ -210,6 +329,9 @@ impl ConnectedState {
         shared_values
             .runtime
             .block_on(shared_values.filtering_resolver.disable_forward());
+
+        #[cfg(not(target_os = "android"))]
+        Self::stop_dns_forwarder(shared_values);
     }
 
     fn reset_routes(
@@ -285,6 +407,103 @@ impl ConnectedState {
                 let _ = tx.send(());
                 SameState(self)
             }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::FirewallExceptions(exceptions, complete_tx)) => {
+                let consequence = if shared_values.set_firewall_exceptions(exceptions) {
+                    match self.set_firewall_policy(shared_values) {
+                        Ok(()) => SameState(self),
+                        Err(error) => self.disconnect(
+                            shared_values,
+                            AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                        ),
+                    }
+                } else {
+                    SameState(self)
+                };
+
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::AllowIncoming(allowed_incoming, complete_tx)) => {
+                let consequence = if shared_values.set_allowed_incoming(allowed_incoming) {
+                    match self.set_firewall_policy(shared_values) {
+                        Ok(()) => SameState(self),
+                        Err(error) => self.disconnect(
+                            shared_values,
+                            AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                        ),
+                    }
+                } else {
+                    SameState(self)
+                };
+
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::BlockIpv6(block_ipv6, complete_tx)) => {
+                // The tunnel configuration depends on this setting, so reconnect
+                let consequence = if shared_values.set_block_ipv6(block_ipv6) {
+                    self.disconnect(shared_values, AfterDisconnect::Reconnect(0))
+                } else {
+                    SameState(self)
+                };
+
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelMode(mode, complete_tx)) => {
+                let consequence = if shared_values.set_split_tunnel_mode(mode) {
+                    match self.set_firewall_policy(shared_values) {
+                        Ok(()) => SameState(self),
+                        Err(error) => self.disconnect(
+                            shared_values,
+                            AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                        ),
+                    }
+                } else {
+                    SameState(self)
+                };
+
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelOwners(owners, complete_tx)) => {
+                let consequence = if shared_values.set_split_tunnel_owners(owners) {
+                    match self.set_firewall_policy(shared_values) {
+                        Ok(()) => SameState(self),
+                        Err(error) => self.disconnect(
+                            shared_values,
+                            AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                        ),
+                    }
+                } else {
+                    SameState(self)
+                };
+
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelAddresses(addresses, complete_tx)) => {
+                let consequence = if shared_values.set_split_tunnel_addresses(addresses) {
+                    match self.set_firewall_policy(shared_values) {
+                        Ok(()) => SameState(self),
+                        Err(error) => self.disconnect(
+                            shared_values,
+                            AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                        ),
+                    }
+                } else {
+                    SameState(self)
+                };
+
+                let _ = complete_tx.send(());
+                consequence
+            }
             Some(TunnelCommand::Dns(servers, complete_tx)) => {
                 let consequence = if shared_values.set_dns_config(servers) {
                     #[cfg(target_os = "android")]
@@ -328,6 +547,22 @@ impl ConnectedState {
                 let _ = complete_tx.send(());
                 SameState(self)
             }
+            Some(TunnelCommand::GetTrafficStats(tx)) => {
+                let _ = tx.send(self.traffic_stats.get());
+                SameState(self)
+            }
+            Some(TunnelCommand::GetDiagnostics(tx)) => {
+                let mut diagnostics = shared_values.tunnel_diagnostics(
+                    "connected",
+                    &self.tunnel_parameters,
+                    Some(&self.metadata),
+                );
+                diagnostics.dns_servers = Self::resolve_dns(&self.metadata, shared_values)
+                    .addresses()
+                    .collect();
+                let _ = tx.send(diagnostics);
+                SameState(self)
+            }
             Some(TunnelCommand::Connectivity(connectivity)) => {
                 shared_values.connectivity = connectivity;
                 if connectivity.is_offline() {
@@ -348,6 +583,7 @@ impl ConnectedState {
             Some(TunnelCommand::Block(reason)) => {
                 self.disconnect(shared_values, AfterDisconnect::Block(reason))
             }
+            Some(TunnelCommand::Pause) => self.disconnect(shared_values, AfterDisconnect::Pause),
             #[cfg(target_os = "android")]
             Some(TunnelCommand::BypassSocket(fd, done_tx)) => {
                 shared_values.bypass_socket(fd, done_tx);
@@ -397,7 +633,7 @@ impl ConnectedState {
     }
 
     fn handle_tunnel_events(
-        self: Box<Self>,
+        mut self: Box<Self>,
         event: Option<(TunnelEvent, oneshot::Sender<()>)>,
         shared_values: &mut SharedTunnelStateValues,
     ) -> EventConsequence {
@@ -407,6 +643,18 @@ impl ConnectedState {
             Some((TunnelEvent::Down, _)) | None => {
                 self.disconnect(shared_values, AfterDisconnect::Reconnect(0))
             }
+            Some((TunnelEvent::PeerEndpointChanged(endpoint), _done_tx)) => {
+                if let TunnelParameters::Wireguard(params) = &mut self.tunnel_parameters {
+                    params.connection.peer.endpoint = endpoint;
+                }
+                match self.set_firewall_policy(shared_values) {
+                    Ok(()) => SameState(self),
+                    Err(error) => self.disconnect(
+                        shared_values,
+                        AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                    ),
+                }
+            }
             Some(_) => SameState(self),
         }
     }
@@ -8,11 +8,11 @@ use futures::future::Fuse;
 use futures::{FutureExt, StreamExt};
 use talpid_routing::RouteManagerHandle;
 use talpid_tunnel::tun_provider::TunProvider;
-use talpid_tunnel::{EventHook, TunnelArgs, TunnelEvent, TunnelMetadata};
+use talpid_tunnel::{EventHook, TrafficStatsHandle, TunnelArgs, TunnelEvent, TunnelMetadata};
 use talpid_types::net::{
     AllowedClients, AllowedEndpoint, AllowedTunnelTraffic, IpAvailability, TunnelParameters,
 };
-use talpid_types::tunnel::{ErrorStateCause, FirewallPolicyError};
+use talpid_types::tunnel::{ConnectionTimings, ErrorStateCause, FirewallPolicyError};
 use talpid_types::ErrorExt;
 
 use super::connected_state::TunnelEventsReceiver;
@@ -47,7 +47,13 @@ pub struct ConnectingState {
     allowed_tunnel_traffic: AllowedTunnelTraffic,
     tunnel_close_event: TunnelCloseEvent,
     tunnel_close_tx: oneshot::Sender<()>,
+    /// Traffic statistics published by the tunnel
+    traffic_stats: TrafficStatsHandle,
     retry_attempt: u32,
+    /// When this connection attempt started
+    connect_started: Instant,
+    /// Timings of the phases that have been completed so far
+    timings: ConnectionTimings,
 }
 
 impl ConnectingState {
@@ -88,7 +94,15 @@ impl ConnectingState {
             talpid_types::net::Connectivity::PresumeOnline => IpAvailability::Ipv4,
             talpid_types::net::Connectivity::Online(ip_availability) => ip_availability,
         };
+        // Relays cannot be reached over IPv6 when it is blocked
+        #[cfg(not(target_os = "android"))]
+        let ip_availability = if shared_values.block_ipv6 {
+            IpAvailability::Ipv4
+        } else {
+            ip_availability
+        };
 
+        let connect_started = Instant::now();
         match shared_values.runtime.block_on(
             shared_values
                 .tunnel_parameters_generator
@@ -98,6 +112,8 @@ impl ConnectingState {
                 ErrorState::enter(shared_values, ErrorStateCause::TunnelParameterError(err))
             }
             Ok(tunnel_parameters) => {
+                let relay_selection = connect_started.elapsed();
+
                 #[cfg(windows)]
                 if let Err(error) = shared_values.split_tunnel.set_tunnel_addresses(None) {
                     log::error!(
@@ -140,7 +156,7 @@ impl ConnectingState {
                         }
                     }
 
-                    let connecting_state = Self::start_tunnel(
+                    let mut connecting_state = Self::start_tunnel(
                         shared_values.runtime.clone(),
                         tunnel_parameters,
                         &shared_values.log_dir,
@@ -148,7 +164,14 @@ impl ConnectingState {
                         shared_values.tun_provider.clone(),
                         &shared_values.route_manager,
                         retry_attempt,
+                        #[cfg(target_os = "linux")]
+                        shared_values
+                            .dns_config
+                            .custom_tunnel_config()
+                            .map(<[_]>::to_vec),
                     );
+                    connecting_state.connect_started = connect_started;
+                    connecting_state.timings.relay_selection = Some(relay_selection);
 
                     let params = connecting_state.tunnel_parameters.clone();
                     (
@@ -195,9 +218,19 @@ impl ConnectingState {
         let policy = FirewallPolicy::Connecting {
             peer_endpoint,
             tunnel: tunnel_metadata.clone(),
-            allow_lan: shared_values.allow_lan,
+            allow_lan: shared_values.allow_lan.clone(),
             allowed_endpoint: shared_values.allowed_endpoint.clone(),
             allowed_tunnel_traffic,
+            #[cfg(not(target_os = "android"))]
+            exceptions: shared_values.firewall_exceptions.clone(),
+            #[cfg(not(target_os = "android"))]
+            allowed_incoming: shared_values.allowed_incoming.clone(),
+            #[cfg(not(target_os = "android"))]
+            block_ipv6: shared_values.block_ipv6,
+            #[cfg(target_os = "linux")]
+            split_tunnel_mode: shared_values.split_tunnel_mode,
+            #[cfg(target_os = "linux")]
+            split_tunnel_owners: shared_values.split_tunnel_owners.clone(),
             #[cfg(target_os = "macos")]
             redirect_interface,
             #[cfg(target_os = "macos")]
@@ -229,6 +262,7 @@ impl ConnectingState {
         tun_provider: Arc<Mutex<TunProvider>>,
         route_manager: &RouteManagerHandle,
         retry_attempt: u32,
+        #[cfg(target_os = "linux")] dns_servers: Option<Vec<std::net::IpAddr>>,
     ) -> Self {
         let (event_tx, event_rx) = mpsc::unbounded();
         let event_hook = EventHook::new(event_tx);
@@ -241,6 +275,8 @@ impl ConnectingState {
         let (tunnel_close_event_tx, tunnel_close_event_rx) = oneshot::channel();
 
         let tunnel_parameters = parameters.clone();
+        let traffic_stats = TrafficStatsHandle::default();
+        let tunnel_traffic_stats = traffic_stats.clone();
 
         tokio::task::spawn_blocking(move || {
             let start = Instant::now();
@@ -253,6 +289,9 @@ impl ConnectingState {
                 tun_provider,
                 retry_attempt,
                 route_manager,
+                #[cfg(target_os = "linux")]
+                dns_servers,
+                traffic_stats: tunnel_traffic_stats,
             };
 
             let block_reason = match TunnelMonitor::start(&tunnel_parameters, &log_dir, args) {
@@ -296,7 +335,10 @@ impl ConnectingState {
             allowed_tunnel_traffic: INITIAL_ALLOWED_TUNNEL_TRAFFIC,
             tunnel_close_event: tunnel_close_event_rx.fuse(),
             tunnel_close_tx,
+            traffic_stats,
             retry_attempt,
+            connect_started: Instant::now(),
+            timings: ConnectionTimings::default(),
         }
     }
 
@@ -313,6 +355,12 @@ impl ConnectingState {
                     log::debug!("WireGuard tunnel timed out");
                     None
                 }
+                tunnel::Error::WireguardTunnelMonitoringError(
+                    talpid_wireguard::Error::HandshakeTimeout,
+                ) => {
+                    log::warn!("The relay stopped responding to handshakes");
+                    Some(ErrorStateCause::RelayNotResponding)
+                }
                 error @ tunnel::Error::WireguardTunnelMonitoringError(..)
                     if !should_retry(&error, retry_attempt) =>
                 {
@@ -427,6 +475,63 @@ impl ConnectingState {
                 let _ = tx.send(());
                 SameState(self)
             }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::FirewallExceptions(exceptions, complete_tx)) => {
+                let consequence = if shared_values.set_firewall_exceptions(exceptions) {
+                    self.reset_firewall(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::AllowIncoming(allowed_incoming, complete_tx)) => {
+                let consequence = if shared_values.set_allowed_incoming(allowed_incoming) {
+                    self.reset_firewall(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::BlockIpv6(block_ipv6, complete_tx)) => {
+                // The tunnel configuration depends on this setting, so reconnect
+                let consequence = if shared_values.set_block_ipv6(block_ipv6) {
+                    self.disconnect(shared_values, AfterDisconnect::Reconnect(0))
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelMode(mode, complete_tx)) => {
+                let consequence = if shared_values.set_split_tunnel_mode(mode) {
+                    self.reset_firewall(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelOwners(owners, complete_tx)) => {
+                let consequence = if shared_values.set_split_tunnel_owners(owners) {
+                    self.reset_firewall(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelAddresses(_, complete_tx)) => {
+                // Only routed outside the tunnel while connected
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
             Some(TunnelCommand::Dns(servers, complete_tx)) => {
                 let consequence = if shared_values.set_dns_config(servers) {
                     #[cfg(target_os = "android")]
@@ -448,6 +553,18 @@ impl ConnectingState {
                 let _ = complete_tx.send(());
                 SameState(self)
             }
+            Some(TunnelCommand::GetTrafficStats(tx)) => {
+                let _ = tx.send(None);
+                SameState(self)
+            }
+            Some(TunnelCommand::GetDiagnostics(tx)) => {
+                let _ = tx.send(shared_values.tunnel_diagnostics(
+                    "connecting",
+                    &self.tunnel_parameters,
+                    self.tunnel_metadata.as_ref(),
+                ));
+                SameState(self)
+            }
             Some(TunnelCommand::Connectivity(connectivity)) => {
                 shared_values.connectivity = connectivity;
                 if connectivity.is_offline() {
@@ -468,6 +585,7 @@ impl ConnectingState {
             Some(TunnelCommand::Block(reason)) => {
                 self.disconnect(shared_values, AfterDisconnect::Block(reason))
             }
+            Some(TunnelCommand::Pause) => self.disconnect(shared_values, AfterDisconnect::Pause),
             #[cfg(target_os = "android")]
             Some(TunnelCommand::BypassSocket(fd, done_tx)) => {
                 shared_values.bypass_socket(fd, done_tx);
@@ -572,14 +690,44 @@ impl ConnectingState {
                     ),
                 }
             }
-            Some((TunnelEvent::Up(metadata), _)) => NewState(ConnectedState::enter(
-                shared_values,
-                metadata,
-                self.tunnel_events,
-                self.tunnel_parameters,
-                self.tunnel_close_event,
-                self.tunnel_close_tx,
-            )),
+            Some((TunnelEvent::Up(metadata, tunnel_timings), _)) => {
+                let timings = ConnectionTimings {
+                    relay_selection: self.timings.relay_selection,
+                    total: Some(self.connect_started.elapsed()),
+                    ..tunnel_timings
+                };
+                NewState(ConnectedState::enter(
+                    shared_values,
+                    metadata,
+                    self.tunnel_events,
+                    self.tunnel_parameters,
+                    self.tunnel_close_event,
+                    self.tunnel_close_tx,
+                    self.traffic_stats,
+                    timings,
+                ))
+            }
+            Some((TunnelEvent::PeerEndpointChanged(endpoint), _done_tx)) => {
+                if let TunnelParameters::Wireguard(params) = &mut self.tunnel_parameters {
+                    params.connection.peer.endpoint = endpoint;
+                }
+                match Self::set_firewall_policy(
+                    shared_values,
+                    &self.tunnel_parameters,
+                    &self.tunnel_metadata,
+                    self.allowed_tunnel_traffic.clone(),
+                ) {
+                    Ok(()) => SameState(self),
+                    Err(error) => self.disconnect(
+                        shared_values,
+                        AfterDisconnect::Block(ErrorStateCause::SetFirewallPolicyError(error)),
+                    ),
+                }
+            }
+            Some((TunnelEvent::EphemeralPeerNegotiationRetry(retry), _)) => {
+                let _ = shared_values.negotiation_retry_tx.unbounded_send(retry);
+                SameState(self)
+            }
             Some((TunnelEvent::Down, _)) => {
                 // It is important to reset this before the tunnel device is down,
                 // or else commands that reapply the firewall rules will fail since
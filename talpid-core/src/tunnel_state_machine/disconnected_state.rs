@@ -1,5 +1,5 @@
 use super::{
-    ConnectingState, EventConsequence, SharedTunnelStateValues, TunnelCommand,
+    ConnectingState, EventConsequence, PausedState, SharedTunnelStateValues, TunnelCommand,
     TunnelCommandReceiver, TunnelState, TunnelStateTransition,
 };
 #[cfg(not(target_os = "android"))]
@@ -78,8 +78,15 @@ impl DisconnectedState {
     ) {
         let result = if shared_values.block_when_disconnected {
             let policy = FirewallPolicy::Blocked {
-                allow_lan: shared_values.allow_lan,
+                allow_lan: shared_values.allow_lan.clone(),
                 allowed_endpoint: Some(shared_values.allowed_endpoint.clone()),
+                exceptions: shared_values.firewall_exceptions.clone(),
+                allowed_incoming: shared_values.allowed_incoming.clone(),
+                block_ipv6: shared_values.block_ipv6,
+                #[cfg(target_os = "linux")]
+                split_tunnel_mode: shared_values.split_tunnel_mode,
+                #[cfg(target_os = "linux")]
+                split_tunnel_owners: shared_values.split_tunnel_owners.clone(),
                 #[cfg(target_os = "macos")]
                 dns_redirect_port: shared_values.filtering_resolver.listening_port(),
             };
@@ -182,6 +189,52 @@ impl TunnelState for DisconnectedState {
                 let _ = tx.send(());
                 SameState(self)
             }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::FirewallExceptions(exceptions, complete_tx)) => {
+                if shared_values.set_firewall_exceptions(exceptions) {
+                    Self::set_firewall_policy(shared_values, false);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::AllowIncoming(allowed_incoming, complete_tx)) => {
+                if shared_values.set_allowed_incoming(allowed_incoming) {
+                    Self::set_firewall_policy(shared_values, false);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::BlockIpv6(block_ipv6, complete_tx)) => {
+                if shared_values.set_block_ipv6(block_ipv6) {
+                    Self::set_firewall_policy(shared_values, false);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelMode(mode, complete_tx)) => {
+                if shared_values.set_split_tunnel_mode(mode) {
+                    Self::set_firewall_policy(shared_values, false);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelOwners(owners, complete_tx)) => {
+                if shared_values.set_split_tunnel_owners(owners) {
+                    Self::set_firewall_policy(shared_values, false);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelAddresses(_, complete_tx)) => {
+                // Only routed outside the tunnel while connected
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
             Some(TunnelCommand::Dns(servers, complete_tx)) => {
                 // Same situation as allow LAN above.
                 shared_values.set_dns_config(servers);
@@ -223,12 +276,21 @@ impl TunnelState for DisconnectedState {
                     SameState(self)
                 }
             }
+            Some(TunnelCommand::GetTrafficStats(tx)) => {
+                let _ = tx.send(None);
+                SameState(self)
+            }
+            Some(TunnelCommand::GetDiagnostics(tx)) => {
+                let _ = tx.send(shared_values.diagnostics("disconnected"));
+                SameState(self)
+            }
             Some(TunnelCommand::Connectivity(connectivity)) => {
                 shared_values.connectivity = connectivity;
                 SameState(self)
             }
             Some(TunnelCommand::Connect) => NewState(ConnectingState::enter(shared_values, 0)),
             Some(TunnelCommand::Block(_reason)) => SameState(self),
+            Some(TunnelCommand::Pause) => NewState(PausedState::enter(shared_values)),
             #[cfg(target_os = "android")]
             Some(TunnelCommand::BypassSocket(fd, done_tx)) => {
                 shared_values.bypass_socket(fd, done_tx);
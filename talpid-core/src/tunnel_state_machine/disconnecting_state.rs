@@ -1,7 +1,7 @@
 use super::{
     connecting_state::TunnelCloseEvent, ConnectingState, DisconnectedState, ErrorState,
-    EventConsequence, EventResult, SharedTunnelStateValues, TunnelCommand, TunnelCommandReceiver,
-    TunnelState, TunnelStateTransition,
+    EventConsequence, EventResult, PausedState, SharedTunnelStateValues, TunnelCommand,
+    TunnelCommandReceiver, TunnelState, TunnelStateTransition,
 };
 use futures::{channel::oneshot, future::FusedFuture, StreamExt};
 use talpid_types::tunnel::{ActionAfterDisconnect, ErrorStateCause};
@@ -46,6 +46,35 @@ impl DisconnectingState {
                 shared_values.allowed_endpoint = endpoint;
                 let _ = tx.send(());
             }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::FirewallExceptions(exceptions, complete_tx)) => {
+                let _ = shared_values.set_firewall_exceptions(exceptions);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::AllowIncoming(allowed_incoming, complete_tx)) => {
+                let _ = shared_values.set_allowed_incoming(allowed_incoming);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::BlockIpv6(block_ipv6, complete_tx)) => {
+                let _ = shared_values.set_block_ipv6(block_ipv6);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelMode(mode, complete_tx)) => {
+                let _ = shared_values.set_split_tunnel_mode(mode);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelOwners(owners, complete_tx)) => {
+                let _ = shared_values.set_split_tunnel_owners(owners);
+                let _ = complete_tx.send(());
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelAddresses(_, complete_tx)) => {
+                let _ = complete_tx.send(());
+            }
             Some(TunnelCommand::Dns(servers, complete_tx)) => {
                 let _ = shared_values.set_dns_config(servers);
                 let _ = complete_tx.send(());
@@ -55,6 +84,12 @@ impl DisconnectingState {
                 shared_values.block_when_disconnected = block_when_disconnected;
                 let _ = complete_tx.send(());
             }
+            Some(TunnelCommand::GetTrafficStats(tx)) => {
+                let _ = tx.send(None);
+            }
+            Some(TunnelCommand::GetDiagnostics(tx)) => {
+                let _ = tx.send(shared_values.diagnostics("disconnecting"));
+            }
             Some(TunnelCommand::Connectivity(connectivity)) => {
                 shared_values.connectivity = connectivity;
 
@@ -87,6 +122,9 @@ impl DisconnectingState {
                     _ => AfterDisconnect::Block(reason),
                 }
             }
+            Some(TunnelCommand::Pause) => {
+                self.after_disconnect = AfterDisconnect::Pause;
+            }
             None => {
                 if let AfterDisconnect::Reconnect(_) = self.after_disconnect {
                     self.after_disconnect = AfterDisconnect::Nothing;
@@ -129,6 +167,7 @@ impl DisconnectingState {
             AfterDisconnect::Reconnect(retry_attempt) => {
                 ConnectingState::enter(shared_values, retry_attempt)
             }
+            AfterDisconnect::Pause => PausedState::enter(shared_values),
         }
     }
 }
@@ -175,6 +214,7 @@ pub enum AfterDisconnect {
     Nothing,
     Block(ErrorStateCause),
     Reconnect(u32),
+    Pause,
 }
 
 impl AfterDisconnect {
@@ -184,6 +224,7 @@ impl AfterDisconnect {
             AfterDisconnect::Nothing => ActionAfterDisconnect::Nothing,
             AfterDisconnect::Block(..) => ActionAfterDisconnect::Block,
             AfterDisconnect::Reconnect(..) => ActionAfterDisconnect::Reconnect,
+            AfterDisconnect::Pause => ActionAfterDisconnect::Pause,
         }
     }
 }
@@ -1,6 +1,6 @@
 use super::{
-    ConnectingState, DisconnectedState, EventConsequence, SharedTunnelStateValues, TunnelCommand,
-    TunnelCommandReceiver, TunnelState, TunnelStateTransition,
+    ConnectingState, DisconnectedState, EventConsequence, PausedState, SharedTunnelStateValues,
+    TunnelCommand, TunnelCommandReceiver, TunnelState, TunnelStateTransition,
 };
 #[cfg(target_os = "macos")]
 use crate::dns::DnsConfig;
@@ -74,12 +74,19 @@ impl ErrorState {
     }
 
     #[cfg(not(target_os = "android"))]
-    fn set_firewall_policy(
+    pub(super) fn set_firewall_policy(
         shared_values: &mut SharedTunnelStateValues,
     ) -> Result<(), FirewallPolicyError> {
         let policy = FirewallPolicy::Blocked {
-            allow_lan: shared_values.allow_lan,
+            allow_lan: shared_values.allow_lan.clone(),
             allowed_endpoint: Some(shared_values.allowed_endpoint.clone()),
+            exceptions: shared_values.firewall_exceptions.clone(),
+            allowed_incoming: shared_values.allowed_incoming.clone(),
+            block_ipv6: shared_values.block_ipv6,
+            #[cfg(target_os = "linux")]
+            split_tunnel_mode: shared_values.split_tunnel_mode,
+            #[cfg(target_os = "linux")]
+            split_tunnel_owners: shared_values.split_tunnel_owners.clone(),
             #[cfg(target_os = "macos")]
             dns_redirect_port: shared_values.filtering_resolver.listening_port(),
         };
@@ -105,7 +112,7 @@ impl ErrorState {
             })
     }
 
-    fn reset_dns(shared_values: &mut SharedTunnelStateValues) {
+    pub(super) fn reset_dns(shared_values: &mut SharedTunnelStateValues) {
         if let Err(error) = shared_values.dns_monitor.reset() {
             log::error!("{}", error.display_chain_with_msg("Unable to reset DNS"));
         }
@@ -155,6 +162,52 @@ impl TunnelState for ErrorState {
                 let _ = tx.send(());
                 SameState(self)
             }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::FirewallExceptions(exceptions, complete_tx)) => {
+                if shared_values.set_firewall_exceptions(exceptions) {
+                    let _ = Self::set_firewall_policy(shared_values);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::AllowIncoming(allowed_incoming, complete_tx)) => {
+                if shared_values.set_allowed_incoming(allowed_incoming) {
+                    let _ = Self::set_firewall_policy(shared_values);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::BlockIpv6(block_ipv6, complete_tx)) => {
+                if shared_values.set_block_ipv6(block_ipv6) {
+                    let _ = Self::set_firewall_policy(shared_values);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelMode(mode, complete_tx)) => {
+                if shared_values.set_split_tunnel_mode(mode) {
+                    let _ = Self::set_firewall_policy(shared_values);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelOwners(owners, complete_tx)) => {
+                if shared_values.set_split_tunnel_owners(owners) {
+                    let _ = Self::set_firewall_policy(shared_values);
+                }
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelAddresses(_, complete_tx)) => {
+                // Only routed outside the tunnel while connected
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
             Some(TunnelCommand::Dns(servers, complete_tx)) => {
                 let consequence = if shared_values.set_dns_config(servers) {
                     #[cfg(target_os = "android")]
@@ -184,6 +237,14 @@ impl TunnelState for ErrorState {
                 let _ = complete_tx.send(());
                 SameState(self)
             }
+            Some(TunnelCommand::GetTrafficStats(tx)) => {
+                let _ = tx.send(None);
+                SameState(self)
+            }
+            Some(TunnelCommand::GetDiagnostics(tx)) => {
+                let _ = tx.send(shared_values.diagnostics("error"));
+                SameState(self)
+            }
             Some(TunnelCommand::Connectivity(connectivity)) => {
                 shared_values.connectivity = connectivity;
                 if !connectivity.is_offline()
@@ -209,6 +270,7 @@ impl TunnelState for ErrorState {
             Some(TunnelCommand::Block(reason)) => {
                 NewState(ErrorState::enter(shared_values, reason))
             }
+            Some(TunnelCommand::Pause) => NewState(PausedState::enter(shared_values)),
             #[cfg(target_os = "android")]
             Some(TunnelCommand::BypassSocket(fd, done_tx)) => {
                 shared_values.bypass_socket(fd, done_tx);
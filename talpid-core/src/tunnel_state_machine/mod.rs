@@ -3,6 +3,7 @@ mod connecting_state;
 mod disconnected_state;
 mod disconnecting_state;
 mod error_state;
+mod paused_state;
 
 use self::{
     connected_state::ConnectedState,
@@ -10,7 +11,10 @@ use self::{
     disconnected_state::DisconnectedState,
     disconnecting_state::{AfterDisconnect, DisconnectingState},
     error_state::ErrorState,
+    paused_state::PausedState,
 };
+#[cfg(target_os = "linux")]
+use crate::firewall::NftablesOptions;
 #[cfg(any(windows, target_os = "android", target_os = "macos"))]
 use crate::split_tunnel;
 use crate::{
@@ -34,6 +38,8 @@ use futures::{
     channel::{mpsc, oneshot},
     stream, StreamExt,
 };
+#[cfg(target_os = "linux")]
+use std::net::IpAddr;
 #[cfg(target_os = "android")]
 use std::os::unix::io::RawFd;
 use std::{
@@ -44,11 +50,23 @@ use std::{
     sync::{Arc, Mutex},
     time::Duration,
 };
+#[cfg(target_os = "linux")]
+use talpid_types::dns::DnsInterference;
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::{AllowedIncoming, FirewallException, OfflineMonitorBackend};
+#[cfg(target_os = "linux")]
+use talpid_types::split_tunnel::{SplitTunnelMode, SplitTunnelOwner};
 #[cfg(target_os = "android")]
 use talpid_types::{android::AndroidContext, ErrorExt};
 use talpid_types::{
-    net::{AllowedEndpoint, Connectivity, IpAvailability, TunnelParameters},
-    tunnel::{ErrorStateCause, ParameterGenerationError, TunnelStateTransition},
+    net::{
+        wireguard::NegotiationRetry, AllowedEndpoint, AllowedLan, Connectivity, IpAvailability,
+        TunnelParameters,
+    },
+    tunnel::{
+        ErrorStateCause, ParameterGenerationError, TrafficStats, TunnelDiagnostics,
+        TunnelStateTransition,
+    },
 };
 
 #[cfg(target_os = "android")]
@@ -92,8 +110,8 @@ pub enum Error {
 
 /// Settings used to initialize the tunnel state machine.
 pub struct InitialTunnelState {
-    /// Whether to allow LAN traffic when not in the (non-blocking) disconnected state.
-    pub allow_lan: bool,
+    /// LAN traffic to allow when not in the (non-blocking) disconnected state.
+    pub allow_lan: AllowedLan,
     /// Block traffic unless connected to the VPN.
     #[cfg(not(target_os = "android"))]
     pub block_when_disconnected: bool,
@@ -102,8 +120,41 @@ pub struct InitialTunnelState {
     /// A single endpoint that is allowed to communicate outside the tunnel, i.e.
     /// in any of the blocking states.
     pub allowed_endpoint: AllowedEndpoint,
+    /// User-defined destinations that are allowed outside the tunnel in every state.
+    #[cfg(not(target_os = "android"))]
+    pub firewall_exceptions: Vec<FirewallException>,
+    /// Incoming connections that are allowed in every state.
+    #[cfg(not(target_os = "android"))]
+    pub allowed_incoming: Vec<AllowedIncoming>,
+    /// Drop all IPv6 traffic in every state.
+    #[cfg(not(target_os = "android"))]
+    pub block_ipv6: bool,
+    /// Whether processes in the split tunnel cgroup bypass the tunnel, or are the only ones that
+    /// use it.
+    #[cfg(target_os = "linux")]
+    pub split_tunnel_mode: SplitTunnelMode,
+    /// Users and groups whose processes are split from the tunnel, like the processes in the
+    /// split tunnel cgroup.
+    #[cfg(target_os = "linux")]
+    pub split_tunnel_owners: Vec<SplitTunnelOwner>,
     /// Whether to reset any existing firewall rules when initializing the disconnected state.
     pub reset_firewall: bool,
+    /// How the firewall rules are installed alongside other nftables tables.
+    #[cfg(target_os = "linux")]
+    pub nftables: NftablesOptions,
+    /// Weight of the WFP sublayers that contain the firewall filters.
+    #[cfg(windows)]
+    pub sublayer_weight: u16,
+    /// PF anchor to install the firewall rules in, instead of the default anchor.
+    #[cfg(target_os = "macos")]
+    pub pf_anchor: Option<String>,
+    /// Ways of managing the system DNS configuration to try, in order. The default order is used
+    /// if this is empty.
+    #[cfg(target_os = "linux")]
+    pub dns_backends: Vec<talpid_types::dns::DnsBackend>,
+    /// Mechanism used to detect whether the host is offline.
+    #[cfg(not(target_os = "android"))]
+    pub offline_monitor: OfflineMonitorBackend,
     /// Programs to exclude from the tunnel using the split tunnel driver.
     #[cfg(any(target_os = "windows", target_os = "macos"))]
     pub exclude_paths: Vec<OsString>,
@@ -132,11 +183,13 @@ pub async fn spawn(
     resource_dir: PathBuf,
     state_change_listener: impl Sender<TunnelStateTransition> + Send + 'static,
     offline_state_listener: mpsc::UnboundedSender<Connectivity>,
+    negotiation_retry_listener: mpsc::UnboundedSender<NegotiationRetry>,
     route_manager: RouteManagerHandle,
     #[cfg(target_os = "windows")] volume_update_rx: mpsc::UnboundedReceiver<()>,
     #[cfg(target_os = "android")] android_context: AndroidContext,
     #[cfg(target_os = "android")] connectivity_listener: ConnectivityListener,
     #[cfg(target_os = "linux")] linux_ids: LinuxNetworkingIdentifiers,
+    #[cfg(target_os = "linux")] dns_interference_listener: mpsc::UnboundedSender<DnsInterference>,
 ) -> Result<TunnelStateMachineHandle, Error> {
     let (command_tx, command_rx) = mpsc::unbounded();
     let command_tx = Arc::new(command_tx);
@@ -155,6 +208,7 @@ pub async fn spawn(
         settings: initial_settings,
         command_tx: weak_command_tx,
         offline_state_tx: offline_state_listener,
+        negotiation_retry_tx: negotiation_retry_listener,
         tunnel_parameters_generator,
         tun_provider,
         log_dir,
@@ -167,6 +221,8 @@ pub async fn spawn(
         connectivity_listener,
         #[cfg(target_os = "linux")]
         linux_ids,
+        #[cfg(target_os = "linux")]
+        dns_interference_tx: dns_interference_listener,
     };
 
     let state_machine = TunnelStateMachine::new(init_args).await?;
@@ -198,8 +254,8 @@ pub async fn spawn(
 
 /// Representation of external commands for the tunnel state machine.
 pub enum TunnelCommand {
-    /// Enable or disable LAN access in the firewall.
-    AllowLan(bool, oneshot::Sender<()>),
+    /// Set the LAN access allowed by the firewall.
+    AllowLan(AllowedLan, oneshot::Sender<()>),
     /// Endpoint that should never be blocked. `()` is sent to the
     /// channel after attempting to set the firewall policy, regardless
     /// of whether it succeeded.
@@ -210,6 +266,37 @@ pub enum TunnelCommand {
     /// Enable or disable the block_when_disconnected feature.
     #[cfg(not(target_os = "android"))]
     BlockWhenDisconnected(bool, oneshot::Sender<()>),
+    /// Set the user-defined destinations that are allowed outside the tunnel. `()` is sent to
+    /// the channel after attempting to set the firewall policy, regardless of whether it
+    /// succeeded.
+    #[cfg(not(target_os = "android"))]
+    FirewallExceptions(Vec<FirewallException>, oneshot::Sender<()>),
+    /// Set the incoming connections that are allowed. `()` is sent to the channel after
+    /// attempting to set the firewall policy, regardless of whether it succeeded.
+    #[cfg(not(target_os = "android"))]
+    AllowIncoming(Vec<AllowedIncoming>, oneshot::Sender<()>),
+    /// Enable or disable dropping all IPv6 traffic. `()` is sent to the channel after attempting
+    /// to set the firewall policy, regardless of whether it succeeded.
+    #[cfg(not(target_os = "android"))]
+    BlockIpv6(bool, oneshot::Sender<()>),
+    /// Set which traffic is sent outside the tunnel by split tunneling. `()` is sent to the
+    /// channel after attempting to set the firewall policy, regardless of whether it succeeded.
+    #[cfg(target_os = "linux")]
+    SplitTunnelMode(SplitTunnelMode, oneshot::Sender<()>),
+    /// Set the users and groups whose processes are split from the tunnel. `()` is sent to the
+    /// channel after attempting to set the firewall policy, regardless of whether it succeeded.
+    #[cfg(target_os = "linux")]
+    SplitTunnelOwners(Vec<SplitTunnelOwner>, oneshot::Sender<()>),
+    /// Set the addresses that bypassed domains have resolved to, which are routed outside the
+    /// tunnel while connected. Sent by the DNS forwarder. `()` is sent to the channel after
+    /// attempting to set the firewall policy, regardless of whether it succeeded.
+    #[cfg(target_os = "linux")]
+    SplitTunnelAddresses(Vec<IpAddr>, oneshot::Sender<()>),
+    /// Get the traffic statistics of the current tunnel. `None` is sent unless the tunnel is
+    /// connected and collects statistics.
+    GetTrafficStats(oneshot::Sender<Option<TrafficStats>>),
+    /// Describe the configuration that is currently applied.
+    GetDiagnostics(oneshot::Sender<TunnelDiagnostics>),
     /// Notify the state machine of the connectivity of the device.
     Connectivity(Connectivity),
     /// Open tunnel connection.
@@ -218,6 +305,9 @@ pub enum TunnelCommand {
     Disconnect,
     /// Block all network access unless tunnel is disconnecting or disconnected
     Block(ErrorStateCause),
+    /// Close the tunnel connection, but keep blocking all network access until told to connect or
+    /// disconnect.
+    Pause,
     /// Bypass a socket, allowing traffic to flow through outside the tunnel.
     #[cfg(target_os = "android")]
     BypassSocket(RawFd, oneshot::Sender<()>),
@@ -260,6 +350,7 @@ struct TunnelStateMachineInitArgs<G: TunnelParametersGenerator> {
     settings: InitialTunnelState,
     command_tx: std::sync::Weak<mpsc::UnboundedSender<TunnelCommand>>,
     offline_state_tx: mpsc::UnboundedSender<Connectivity>,
+    negotiation_retry_tx: mpsc::UnboundedSender<NegotiationRetry>,
     tunnel_parameters_generator: G,
     tun_provider: TunProvider,
     log_dir: Option<PathBuf>,
@@ -272,6 +363,8 @@ struct TunnelStateMachineInitArgs<G: TunnelParametersGenerator> {
     connectivity_listener: ConnectivityListener,
     #[cfg(target_os = "linux")]
     linux_ids: LinuxNetworkingIdentifiers,
+    #[cfg(target_os = "linux")]
+    dns_interference_tx: mpsc::UnboundedSender<DnsInterference>,
 }
 
 impl TunnelStateMachine {
@@ -314,9 +407,15 @@ impl TunnelStateMachine {
             // firewall stub completely.
             #[cfg(target_os = "android")]
             initial_state: InitialFirewallState::None,
-            allow_lan: args.settings.allow_lan,
+            allow_lan: args.settings.allow_lan.clone(),
             #[cfg(target_os = "linux")]
             fwmark: args.linux_ids.fwmark,
+            #[cfg(target_os = "linux")]
+            nftables: args.settings.nftables,
+            #[cfg(windows)]
+            sublayer_weight: args.settings.sublayer_weight,
+            #[cfg(target_os = "macos")]
+            pf_anchor: args.settings.pf_anchor.clone(),
         };
 
         let firewall = Firewall::from_args(fw_args).map_err(Error::InitFirewallError)?;
@@ -326,9 +425,29 @@ impl TunnelStateMachine {
             runtime.clone(),
             #[cfg(target_os = "linux")]
             args.route_manager.clone(),
+            #[cfg(target_os = "linux")]
+            args.dns_interference_tx,
+            #[cfg(target_os = "linux")]
+            args.settings.dns_backends.clone(),
         )
         .map_err(Error::InitDnsMonitorError)?;
 
+        #[cfg(target_os = "linux")]
+        let (bypass_tx, mut bypass_rx) = mpsc::unbounded();
+        #[cfg(target_os = "linux")]
+        {
+            let command_tx = args.command_tx.clone();
+            tokio::spawn(async move {
+                while let Some((addresses, done_tx)) = bypass_rx.next().await {
+                    let Some(tx) = command_tx.upgrade() else {
+                        break;
+                    };
+                    let _ =
+                        tx.unbounded_send(TunnelCommand::SplitTunnelAddresses(addresses, done_tx));
+                }
+            });
+        }
+
         let (offline_tx, mut offline_rx) = mpsc::unbounded();
         let initial_offline_state_tx = args.offline_state_tx.clone();
         tokio::spawn(async move {
@@ -344,6 +463,8 @@ impl TunnelStateMachine {
         let offline_monitor = offline::spawn_monitor(
             offline_tx,
             #[cfg(not(target_os = "android"))]
+            args.settings.offline_monitor,
+            #[cfg(not(target_os = "android"))]
             args.route_manager.clone(),
             #[cfg(target_os = "linux")]
             Some(args.linux_ids.fwmark),
@@ -392,14 +513,31 @@ impl TunnelStateMachine {
             connectivity,
             dns_config: args.settings.dns_config,
             allowed_endpoint: args.settings.allowed_endpoint,
+            #[cfg(not(target_os = "android"))]
+            firewall_exceptions: args.settings.firewall_exceptions,
+            #[cfg(not(target_os = "android"))]
+            allowed_incoming: args.settings.allowed_incoming,
+            #[cfg(not(target_os = "android"))]
+            block_ipv6: args.settings.block_ipv6,
+            #[cfg(target_os = "linux")]
+            split_tunnel_mode: args.settings.split_tunnel_mode,
+            #[cfg(target_os = "linux")]
+            split_tunnel_owners: args.settings.split_tunnel_owners,
+            #[cfg(target_os = "linux")]
+            split_tunnel_addresses: vec![],
+            #[cfg(target_os = "linux")]
+            bypass_tx,
             tunnel_parameters_generator: Box::new(args.tunnel_parameters_generator),
             tun_provider: Arc::new(Mutex::new(args.tun_provider)),
+            negotiation_retry_tx: args.negotiation_retry_tx,
             log_dir: args.log_dir,
             resource_dir: args.resource_dir,
             #[cfg(target_os = "linux")]
             connectivity_check_was_enabled: None,
             #[cfg(target_os = "macos")]
             filtering_resolver,
+            #[cfg(not(target_os = "android"))]
+            dns_forwarder: None,
         };
 
         tokio::task::spawn_blocking(move || {
@@ -477,8 +615,8 @@ struct SharedTunnelStateValues {
     dns_monitor: DnsMonitor,
     route_manager: RouteManagerHandle,
     _offline_monitor: offline::MonitorHandle,
-    /// Should LAN access be allowed outside the tunnel.
-    allow_lan: bool,
+    /// LAN access that should be allowed outside the tunnel.
+    allow_lan: AllowedLan,
     /// Should network access be allowed when in the disconnected state.
     #[cfg(not(target_os = "android"))]
     block_when_disconnected: bool,
@@ -488,10 +626,33 @@ struct SharedTunnelStateValues {
     dns_config: crate::dns::DnsConfig,
     /// Endpoint that should not be blocked by the firewall.
     allowed_endpoint: AllowedEndpoint,
+    /// User-defined destinations that should not be blocked by the firewall.
+    #[cfg(not(target_os = "android"))]
+    firewall_exceptions: Vec<FirewallException>,
+    /// Incoming connections that should not be blocked by the firewall.
+    #[cfg(not(target_os = "android"))]
+    allowed_incoming: Vec<AllowedIncoming>,
+    /// Whether all IPv6 traffic should be dropped by the firewall.
+    #[cfg(not(target_os = "android"))]
+    block_ipv6: bool,
+    /// Which traffic is sent outside the tunnel by split tunneling.
+    #[cfg(target_os = "linux")]
+    split_tunnel_mode: SplitTunnelMode,
+    /// Users and groups whose processes are split from the tunnel.
+    #[cfg(target_os = "linux")]
+    split_tunnel_owners: Vec<SplitTunnelOwner>,
+    /// Addresses of bypassed domains that are routed outside the tunnel while connected.
+    #[cfg(target_os = "linux")]
+    split_tunnel_addresses: Vec<IpAddr>,
+    /// Passed to the DNS forwarder, which sends the addresses of bypassed domains on it.
+    #[cfg(target_os = "linux")]
+    bypass_tx: crate::dns::BypassUpdateTx,
     /// The generator of new `TunnelParameter`s
     tunnel_parameters_generator: Box<dyn TunnelParametersGenerator>,
     /// The provider of tunnel devices.
     tun_provider: Arc<Mutex<TunProvider>>,
+    /// Receives ephemeral peer negotiations that are being retried.
+    negotiation_retry_tx: mpsc::UnboundedSender<NegotiationRetry>,
     /// Directory to store tunnel log file.
     log_dir: Option<PathBuf>,
     /// Resource directory path.
@@ -504,6 +665,10 @@ struct SharedTunnelStateValues {
     /// Filtering resolver handle
     #[cfg(target_os = "macos")]
     filtering_resolver: crate::resolver::ResolverHandle,
+
+    /// Forwarder for encrypted DNS servers and blocked domains. Only running while connected.
+    #[cfg(not(target_os = "android"))]
+    dns_forwarder: Option<crate::dns::DnsForwarder>,
 }
 
 impl SharedTunnelStateValues {
@@ -565,7 +730,7 @@ impl SharedTunnelStateValues {
             .map_err(|error| ErrorStateCause::from(&error))
     }
 
-    pub fn set_allow_lan(&mut self, allow_lan: bool) -> bool {
+    pub fn set_allow_lan(&mut self, allow_lan: AllowedLan) -> bool {
         if self.allow_lan != allow_lan {
             self.allow_lan = allow_lan;
             true
@@ -574,6 +739,66 @@ impl SharedTunnelStateValues {
         }
     }
 
+    #[cfg(not(target_os = "android"))]
+    pub fn set_firewall_exceptions(&mut self, exceptions: Vec<FirewallException>) -> bool {
+        if self.firewall_exceptions != exceptions {
+            self.firewall_exceptions = exceptions;
+            true
+        } else {
+            false
+        }
+    }
+
+    #[cfg(not(target_os = "android"))]
+    pub fn set_allowed_incoming(&mut self, allowed_incoming: Vec<AllowedIncoming>) -> bool {
+        if self.allowed_incoming != allowed_incoming {
+            self.allowed_incoming = allowed_incoming;
+            true
+        } else {
+            false
+        }
+    }
+
+    #[cfg(not(target_os = "android"))]
+    pub fn set_block_ipv6(&mut self, block_ipv6: bool) -> bool {
+        if self.block_ipv6 != block_ipv6 {
+            self.block_ipv6 = block_ipv6;
+            true
+        } else {
+            false
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn set_split_tunnel_mode(&mut self, split_tunnel_mode: SplitTunnelMode) -> bool {
+        if self.split_tunnel_mode != split_tunnel_mode {
+            self.split_tunnel_mode = split_tunnel_mode;
+            true
+        } else {
+            false
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn set_split_tunnel_owners(&mut self, split_tunnel_owners: Vec<SplitTunnelOwner>) -> bool {
+        if self.split_tunnel_owners != split_tunnel_owners {
+            self.split_tunnel_owners = split_tunnel_owners;
+            true
+        } else {
+            false
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn set_split_tunnel_addresses(&mut self, split_tunnel_addresses: Vec<IpAddr>) -> bool {
+        if self.split_tunnel_addresses != split_tunnel_addresses {
+            self.split_tunnel_addresses = split_tunnel_addresses;
+            true
+        } else {
+            false
+        }
+    }
+
     pub fn set_dns_config(&mut self, dns_config: DnsConfig) -> bool {
         if self.dns_config != dns_config {
             self.dns_config = dns_config;
@@ -583,6 +808,42 @@ impl SharedTunnelStateValues {
         }
     }
 
+    /// Describe the configuration that does not depend on the tunnel, in the state `state`.
+    pub fn diagnostics(&self, state: &str) -> TunnelDiagnostics {
+        TunnelDiagnostics {
+            state: state.to_owned(),
+            firewall_policy: self.firewall.applied_policy().map(ToString::to_string),
+            ..TunnelDiagnostics::default()
+        }
+    }
+
+    /// Describe the configuration of a tunnel that is being set up, or is up, in addition to
+    /// [`Self::diagnostics`]. `metadata` is only known once the tunnel interface exists.
+    pub fn tunnel_diagnostics(
+        &self,
+        state: &str,
+        parameters: &TunnelParameters,
+        metadata: Option<&TunnelMetadata>,
+    ) -> TunnelDiagnostics {
+        let (wireguard, tunnel_routes) = match parameters {
+            TunnelParameters::Wireguard(params) => {
+                let connection = &params.connection;
+                let exit_peer = connection.exit_peer.as_ref().unwrap_or(&connection.peer);
+                (Some(connection.clone()), exit_peer.allowed_ips.clone())
+            }
+            TunnelParameters::OpenVpn(_) => (None, talpid_types::net::all_of_the_internet()),
+        };
+        TunnelDiagnostics {
+            interface: metadata.map(|metadata| metadata.interface.clone()),
+            tunnel_addresses: metadata
+                .map(|metadata| metadata.ips.clone())
+                .unwrap_or_default(),
+            wireguard,
+            tunnel_routes,
+            ..self.diagnostics(state)
+        }
+    }
+
     /// NetworkManager's connectivity check can get hung when DNS requests fail, thus the TSM
     /// should always disable it before applying firewall rules. The connectivity check should be
     /// reset whenever the firewall is cleared.
@@ -649,7 +910,7 @@ impl SharedTunnelStateValues {
             let addrs: Vec<_> = self.dns_config.resolve(&[]).addresses().collect();
             config.dns_servers = if addrs.is_empty() { None } else { Some(addrs) };
         }
-        config.allow_lan = self.allow_lan;
+        config.allow_lan = self.allow_lan.is_allowed();
         config.excluded_packages = self.excluded_packages.clone();
     }
 
@@ -0,0 +1,245 @@
+use super::{
+    ConnectingState, DisconnectedState, ErrorState, EventConsequence, SharedTunnelStateValues,
+    TunnelCommand, TunnelCommandReceiver, TunnelState, TunnelStateTransition,
+};
+#[cfg(target_os = "macos")]
+use crate::dns::DnsConfig;
+use futures::StreamExt;
+#[cfg(target_os = "macos")]
+use std::net::Ipv4Addr;
+use talpid_types::tunnel::{ErrorStateCause, FirewallPolicyError};
+#[cfg(any(windows, target_os = "macos"))]
+use talpid_types::ErrorExt;
+
+/// The tunnel has been torn down on request of the user, but all network connections are still
+/// blocked. Unlike the disconnected state, this never lets traffic leak outside the tunnel.
+pub struct PausedState(());
+
+impl PausedState {
+    pub(super) fn enter(
+        shared_values: &mut SharedTunnelStateValues,
+    ) -> (Box<dyn TunnelState>, TunnelStateTransition) {
+        #[cfg(windows)]
+        if let Err(error) = shared_values.split_tunnel.set_tunnel_addresses(None) {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg(
+                    "Failed to register addresses with split tunnel driver"
+                )
+            );
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            // Set system DNS to our local DNS resolver
+            let system_dns = DnsConfig::default().resolve(
+                &[Ipv4Addr::LOCALHOST.into()],
+                shared_values.filtering_resolver.listening_port(),
+            );
+            if let Err(err) = shared_values.dns_monitor.set("lo", system_dns) {
+                log::error!(
+                    "{}",
+                    err.display_chain_with_msg(
+                        "Failed to configure system to use filtering resolver"
+                    )
+                );
+                return ErrorState::enter(shared_values, ErrorStateCause::SetDnsError);
+            }
+        }
+
+        // If traffic cannot be blocked, the error state is the only honest state to be in
+        if let Err(error) = Self::set_firewall_policy(shared_values) {
+            return ErrorState::enter(
+                shared_values,
+                ErrorStateCause::SetFirewallPolicyError(error),
+            );
+        }
+
+        (Box::new(PausedState(())), TunnelStateTransition::Paused)
+    }
+
+    #[cfg(not(target_os = "android"))]
+    fn set_firewall_policy(
+        shared_values: &mut SharedTunnelStateValues,
+    ) -> Result<(), FirewallPolicyError> {
+        ErrorState::set_firewall_policy(shared_values)
+    }
+
+    #[cfg(target_os = "android")]
+    fn set_firewall_policy(
+        shared_values: &mut SharedTunnelStateValues,
+    ) -> Result<(), FirewallPolicyError> {
+        shared_values
+            .restart_tunnel(true)
+            .map_err(|_| FirewallPolicyError::Generic)
+    }
+
+    fn update_firewall_policy(
+        self: Box<Self>,
+        shared_values: &mut SharedTunnelStateValues,
+    ) -> EventConsequence {
+        match Self::set_firewall_policy(shared_values) {
+            Ok(()) => EventConsequence::SameState(self),
+            Err(error) => EventConsequence::NewState(ErrorState::enter(
+                shared_values,
+                ErrorStateCause::SetFirewallPolicyError(error),
+            )),
+        }
+    }
+}
+
+impl TunnelState for PausedState {
+    fn handle_event(
+        self: Box<Self>,
+        runtime: &tokio::runtime::Handle,
+        commands: &mut TunnelCommandReceiver,
+        shared_values: &mut SharedTunnelStateValues,
+    ) -> EventConsequence {
+        use self::EventConsequence::*;
+
+        match runtime.block_on(commands.next()) {
+            Some(TunnelCommand::AllowLan(allow_lan, complete_tx)) => {
+                let consequence = if shared_values.set_allow_lan(allow_lan) {
+                    self.update_firewall_policy(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::AllowEndpoint(endpoint, tx)) => {
+                let consequence = if shared_values.allowed_endpoint != endpoint {
+                    shared_values.allowed_endpoint = endpoint;
+                    self.update_firewall_policy(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = tx.send(());
+                consequence
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::FirewallExceptions(exceptions, complete_tx)) => {
+                let consequence = if shared_values.set_firewall_exceptions(exceptions) {
+                    self.update_firewall_policy(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::AllowIncoming(allowed_incoming, complete_tx)) => {
+                let consequence = if shared_values.set_allowed_incoming(allowed_incoming) {
+                    self.update_firewall_policy(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::BlockIpv6(block_ipv6, complete_tx)) => {
+                let consequence = if shared_values.set_block_ipv6(block_ipv6) {
+                    self.update_firewall_policy(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelMode(mode, complete_tx)) => {
+                let consequence = if shared_values.set_split_tunnel_mode(mode) {
+                    self.update_firewall_policy(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelOwners(owners, complete_tx)) => {
+                let consequence = if shared_values.set_split_tunnel_owners(owners) {
+                    self.update_firewall_policy(shared_values)
+                } else {
+                    SameState(self)
+                };
+                let _ = complete_tx.send(());
+                consequence
+            }
+            #[cfg(target_os = "linux")]
+            Some(TunnelCommand::SplitTunnelAddresses(_, complete_tx)) => {
+                // Only routed outside the tunnel while connected
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            Some(TunnelCommand::Dns(servers, complete_tx)) => {
+                // DNS is blocked while paused, so the new servers only take effect once the
+                // tunnel is up again
+                let _ = shared_values.set_dns_config(servers);
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            #[cfg(not(target_os = "android"))]
+            Some(TunnelCommand::BlockWhenDisconnected(block_when_disconnected, complete_tx)) => {
+                shared_values.block_when_disconnected = block_when_disconnected;
+                let _ = complete_tx.send(());
+                SameState(self)
+            }
+            Some(TunnelCommand::GetTrafficStats(tx)) => {
+                let _ = tx.send(None);
+                SameState(self)
+            }
+            Some(TunnelCommand::GetDiagnostics(tx)) => {
+                let _ = tx.send(shared_values.diagnostics("paused"));
+                SameState(self)
+            }
+            Some(TunnelCommand::Connectivity(connectivity)) => {
+                shared_values.connectivity = connectivity;
+                SameState(self)
+            }
+            Some(TunnelCommand::Connect) => {
+                ErrorState::reset_dns(shared_values);
+                NewState(ConnectingState::enter(shared_values, 0))
+            }
+            Some(TunnelCommand::Disconnect) | None => {
+                #[cfg(target_os = "linux")]
+                shared_values.reset_connectivity_check();
+                ErrorState::reset_dns(shared_values);
+                NewState(DisconnectedState::enter(shared_values, true))
+            }
+            Some(TunnelCommand::Block(reason)) => {
+                NewState(ErrorState::enter(shared_values, reason))
+            }
+            Some(TunnelCommand::Pause) => SameState(self),
+            #[cfg(target_os = "android")]
+            Some(TunnelCommand::BypassSocket(fd, done_tx)) => {
+                shared_values.bypass_socket(fd, done_tx);
+                SameState(self)
+            }
+            #[cfg(target_os = "android")]
+            Some(TunnelCommand::SetExcludedApps(result_tx, paths)) => {
+                if shared_values.set_excluded_paths(paths) {
+                    if let Err(err) = shared_values.restart_tunnel(true) {
+                        let _ =
+                            result_tx.send(Err(crate::split_tunnel::Error::SetExcludedApps(err)));
+                    }
+                } else {
+                    let _ = result_tx.send(Ok(()));
+                }
+                SameState(self)
+            }
+            #[cfg(windows)]
+            Some(TunnelCommand::SetExcludedApps(result_tx, paths)) => {
+                shared_values.exclude_paths(paths, result_tx);
+                SameState(self)
+            }
+            #[cfg(target_os = "macos")]
+            Some(TunnelCommand::SetExcludedApps(result_tx, paths)) => {
+                let _ = result_tx.send(shared_values.set_exclude_paths(paths).map(|_| ()));
+                SameState(self)
+            }
+        }
+    }
+}
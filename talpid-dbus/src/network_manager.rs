@@ -23,6 +23,7 @@ const NM_BUS: &str = "org.freedesktop.NetworkManager";
 const NM_MANAGER: &str = "org.freedesktop.NetworkManager";
 const NM_MANAGER_PATH: &str = "/org/freedesktop/NetworkManager";
 const CONNECTIVITY_CHECK_KEY: &str = "ConnectivityCheckEnabled";
+const STATE_KEY: &str = "State";
 
 const NM_DNS_MANAGER: &str = "org.freedesktop.NetworkManager.DnsManager";
 const NM_DNS_MANAGER_PATH: &str = "/org/freedesktop/NetworkManager/DnsManager";
@@ -39,12 +40,17 @@ const NM_DEVICE_STATE_IP_CHECK: u32 = 80;
 const NM_DEVICE_STATE_SECONDARY: u32 = 90;
 const NM_DEVICE_STATE_ACTIVATED: u32 = 100;
 
+/// `NM_STATE_CONNECTED_SITE`. In this and later states, a default route is available, even if
+/// NetworkManager's connectivity check has not succeeded.
+const NM_STATE_CONNECTED_SITE: u32 = 60;
+
 const NM_SETTINGS_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings";
 const NM_SETTINGS_CONNECTION_INTERFACE: &str = "org.freedesktop.NetworkManager.Settings.Connection";
 const NM_SETTINGS_PATH: &str = "/org/freedesktop/NetworkManager/Settings";
 const NM_CONNECTION_ACTIVE: &str = "org.freedesktop.NetworkManager.Connection.Active";
 
 const NM_ADD_CONNECTION_VOLATILE: u32 = 0x2;
+const NM_SETTINGS_UPDATE2_FLAG_IN_MEMORY: u32 = 0x2;
 
 const RPC_TIMEOUT: std::time::Duration = Duration::from_secs(3);
 
@@ -106,6 +112,9 @@ pub enum Error {
 
     #[error("Failed to get devices from NetworkManager object")]
     ObtainDevices,
+
+    #[error("Connection settings do not contain a WireGuard configuration")]
+    MissingWireguardConfig,
 }
 
 pub type VariantRefArg = Variant<Box<dyn RefArg>>;
@@ -141,6 +150,44 @@ impl NetworkManager {
         Ok(tunnel)
     }
 
+    /// Update the WireGuard settings of an active tunnel. The connection profile is replaced with
+    /// `config`, and the `wireguard` settings are reapplied to the device without reactivating it.
+    pub fn update_wg_tunnel(&self, tunnel: &WireguardTunnel, config: &DeviceConfig) -> Result<()> {
+        let wireguard_config = config
+            .get("wireguard")
+            .ok_or(Error::MissingWireguardConfig)?;
+
+        match self.update_connection_2(tunnel, config) {
+            Ok(_result) => (),
+            Err(Error::Dbus(dbus_error)) if dbus_error.name() == Some(DBUS_UNKNOWN_METHOD) => {
+                tunnel
+                    .config_proxy(&self.connection)
+                    .method_call::<(), _, _, _>(
+                        NM_SETTINGS_CONNECTION_INTERFACE,
+                        "UpdateUnsaved",
+                        (config,),
+                    )
+                    .map_err(Error::Dbus)?;
+            }
+            Err(err) => return Err(err),
+        }
+
+        // Only replace the WireGuard settings of the applied connection. The IP settings may
+        // contain routes and DNS servers that have been applied since the tunnel was created.
+        let (mut settings, version_id): (NetworkSettings<'_>, u64) = tunnel
+            .device_proxy(&self.connection)
+            .method_call(NM_DEVICE, "GetAppliedConnection", (0u32,))?;
+        settings.insert(
+            "wireguard".to_string(),
+            wireguard_config
+                .iter()
+                .map(|(key, value)| (key.clone(), Variant(value.0.box_clone())))
+                .collect(),
+        );
+
+        self.reapply_settings(&tunnel.device_path, settings, version_id)
+    }
+
     pub fn get_interface_name(&self, tunnel: &WireguardTunnel) -> Result<String> {
         tunnel
             .device_proxy(&self.connection)
@@ -260,6 +307,23 @@ impl NetworkManager {
             .map_err(Error::Dbus)
     }
 
+    fn update_connection_2(
+        &self,
+        tunnel: &WireguardTunnel,
+        settings_map: &DeviceConfig,
+    ) -> Result<(VariantMap,)> {
+        let args: VariantMap = HashMap::new();
+
+        tunnel
+            .config_proxy(&self.connection)
+            .method_call(
+                NM_SETTINGS_CONNECTION_INTERFACE,
+                "Update2",
+                (settings_map, NM_SETTINGS_UPDATE2_FLAG_IN_MEMORY, args),
+            )
+            .map_err(Error::Dbus)
+    }
+
     fn add_connection_unsaved(
         &self,
         settings_map: &DeviceConfig,
@@ -375,6 +439,13 @@ impl NetworkManager {
         }
     }
 
+    /// Returns whether NetworkManager considers the host to be connected to a network with a
+    /// default route.
+    pub fn is_connected(&self) -> Result<bool> {
+        let state: u32 = self.nm_manager().get(NM_MANAGER, STATE_KEY)?;
+        Ok(state >= NM_STATE_CONNECTED_SITE)
+    }
+
     fn nm_manager(&self) -> Proxy<'_, &SyncConnection> {
         Proxy::new(NM_BUS, NM_MANAGER_PATH, RPC_TIMEOUT, &*self.connection)
     }
@@ -532,31 +603,7 @@ impl NetworkManager {
             settings_backup.insert(top_key.to_string(), inner_dict);
         }
 
-        // Update the DNS config
-        let v4_dns: Vec<u32> = servers
-            .iter()
-            .filter_map(|server| {
-                match server {
-                    // Network-byte order
-                    IpAddr::V4(server) => Some(u32::to_be((*server).into())),
-                    IpAddr::V6(_) => None,
-                }
-            })
-            .collect();
-        if !v4_dns.is_empty() {
-            Self::update_dns_config(&mut settings, "ipv4", v4_dns);
-        }
-
-        let v6_dns: Vec<Vec<u8>> = servers
-            .iter()
-            .filter_map(|server| match server {
-                IpAddr::V4(_) => None,
-                IpAddr::V6(server) => Some(server.octets().to_vec()),
-            })
-            .collect();
-        if !v6_dns.is_empty() {
-            Self::update_dns_config(&mut settings, "ipv6", v6_dns);
-        }
+        Self::add_dns_servers(&mut settings, servers);
 
         if let Some(wg_config) = settings.get_mut("wireguard") {
             if !wg_config.contains_key("fwmark") {
@@ -582,6 +629,35 @@ impl NetworkManager {
         Ok(())
     }
 
+    /// Configure `servers` as the DNS servers of a connection. They are given precedence over the
+    /// DNS servers of all other connections.
+    pub fn add_dns_servers(settings: &mut NetworkSettings<'_>, servers: &[IpAddr]) {
+        let v4_dns: Vec<u32> = servers
+            .iter()
+            .filter_map(|server| {
+                match server {
+                    // Network-byte order
+                    IpAddr::V4(server) => Some(u32::to_be((*server).into())),
+                    IpAddr::V6(_) => None,
+                }
+            })
+            .collect();
+        if !v4_dns.is_empty() {
+            Self::update_dns_config(settings, "ipv4", v4_dns);
+        }
+
+        let v6_dns: Vec<Vec<u8>> = servers
+            .iter()
+            .filter_map(|server| match server {
+                IpAddr::V4(_) => None,
+                IpAddr::V6(server) => Some(server.octets().to_vec()),
+            })
+            .collect();
+        if !v6_dns.is_empty() {
+            Self::update_dns_config(settings, "ipv6", v6_dns);
+        }
+    }
+
     fn update_dns_config<'a, T>(
         settings: &mut NetworkSettings<'a>,
         ip_protocol: &'static str,
@@ -1,2 +1,3 @@
+pub mod pmtud;
 #[cfg(unix)]
 pub mod unix;
@@ -0,0 +1,564 @@
+//! Path MTU discovery.
+//!
+//! The largest packet that can traverse a path is found by sending probes with the "don't
+//! fragment" bit set and searching for the largest one that makes it to the destination and back.
+//! This does not rely on ICMP "fragmentation needed" messages reaching us, since these are often
+//! dropped along the way, but they are used to fail a probe early when they do arrive.
+
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr, UdpSocket},
+    ops::RangeInclusive,
+    time::{Duration, Instant},
+};
+
+/// Size of an IPv4 header without options, in bytes.
+const IPV4_HEADER_SIZE: u16 = 20;
+/// Size of an ICMP echo header, in bytes.
+const ICMP_HEADER_SIZE: u16 = 8;
+/// Number of times a size is probed before concluding that it does not fit the path. Probes may
+/// be lost for reasons unrelated to their size.
+const PROBE_ATTEMPTS: u32 = 3;
+
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMP_DESTINATION_UNREACHABLE: u8 = 3;
+const ICMP_FRAGMENTATION_NEEDED: u8 = 4;
+const ICMP_ECHO_REQUEST: u8 = 8;
+
+/// Path MTU discovery errors
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Failed to open ICMP socket
+    #[error("Failed to open ICMP socket")]
+    Open(#[source] io::Error),
+
+    /// Failed to set socket options
+    #[error("Failed to set socket options")]
+    SocketOp(#[source] io::Error),
+
+    /// Failed to bind socket to interface
+    #[cfg(target_os = "macos")]
+    #[error("Failed to bind socket to interface")]
+    BindInterface(#[source] crate::unix::IfaceIndexLookupError),
+
+    /// Failed to send probe
+    #[error("Failed to send probe")]
+    Send(#[source] io::Error),
+
+    /// Failed to receive probe response
+    #[error("Failed to receive probe response")]
+    Receive(#[source] io::Error),
+
+    /// The range of packet sizes to probe is empty or too small to fit a probe
+    #[error("Invalid range of packet sizes: {0:?}")]
+    InvalidRange(RangeInclusive<u16>),
+
+    /// Not even the smallest probe made it through
+    #[error("No response to probes of {0} bytes")]
+    NoResponse(u16),
+}
+
+/// Something that can check whether packets of a given size traverse a path.
+pub trait Probe {
+    /// Send a single probe with a total IP packet size of `packet_size` bytes. Returns whether
+    /// the probe made it through.
+    fn probe(&mut self, packet_size: u16) -> Result<bool, Error>;
+}
+
+/// Return the largest packet size in `sizes` for which `prober` succeeds.
+///
+/// The largest size is tried first, since that is the common case, after which a binary search is
+/// performed. Fails if not even the smallest size succeeds.
+pub fn discover(prober: &mut impl Probe, sizes: RangeInclusive<u16>) -> Result<u16, Error> {
+    if sizes.is_empty() {
+        return Err(Error::InvalidRange(sizes));
+    }
+    let (mut low, mut high) = (*sizes.start(), *sizes.end());
+
+    let mut passes = |size| -> Result<bool, Error> {
+        for _ in 0..PROBE_ATTEMPTS {
+            if prober.probe(size)? {
+                return Ok(true);
+            }
+        }
+        log::trace!("Probe of {size} bytes did not make it through");
+        Ok(false)
+    };
+
+    if passes(high)? {
+        return Ok(high);
+    }
+    if !passes(low)? {
+        return Err(Error::NoResponse(low));
+    }
+    high -= 1;
+
+    // `low` is always known to pass, and everything above `high` to fail
+    while low < high {
+        let mid = low + (high - low).div_ceil(2);
+        if passes(mid)? {
+            low = mid;
+        } else {
+            high = mid - 1;
+        }
+    }
+    Ok(low)
+}
+
+/// Probes the path to an IPv4 host using ICMP echo requests that must not be fragmented.
+pub struct IcmpProber {
+    socket: UdpSocket,
+    destination: Ipv4Addr,
+    timeout: Duration,
+    id: u16,
+    sequence: u16,
+    buffer: Vec<u8>,
+}
+
+impl IcmpProber {
+    /// Creates a prober for `destination`. A probe is considered lost if no response arrives
+    /// within `timeout`.
+    pub fn new(
+        destination: Ipv4Addr,
+        #[cfg(any(target_os = "linux", target_os = "macos"))] interface: Option<&str>,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let socket = Socket::new(
+            Domain::IPV4,
+            if cfg!(target_os = "android") {
+                Type::DGRAM
+            } else {
+                Type::RAW
+            },
+            Some(Protocol::ICMPV4),
+        )
+        .map_err(Error::Open)?;
+
+        set_dont_fragment(&socket).map_err(Error::SocketOp)?;
+
+        #[cfg(target_os = "linux")]
+        if let Some(interface) = interface {
+            socket
+                .bind_device(Some(interface.as_bytes()))
+                .map_err(Error::SocketOp)?;
+        }
+
+        #[cfg(target_os = "macos")]
+        if let Some(interface) = interface {
+            let index = crate::unix::iface_index(interface).map_err(Error::BindInterface)?;
+            socket
+                .bind_device_by_index_v4(std::num::NonZeroU32::new(index))
+                .map_err(Error::SocketOp)?;
+        }
+
+        // Raw sockets do not receive anything on Windows unless bound to an address
+        #[cfg(windows)]
+        socket
+            .bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)).into())
+            .map_err(Error::SocketOp)?;
+
+        Ok(Self {
+            socket: UdpSocket::from(socket),
+            destination,
+            timeout,
+            id: random_id(),
+            sequence: 0,
+            buffer: vec![0; usize::from(u16::MAX)],
+        })
+    }
+
+    /// Wait for a response to the probe with the current sequence number.
+    fn wait_for_response(&mut self, icmp_size: usize) -> Result<bool, Error> {
+        let deadline = Instant::now() + self.timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(false);
+            }
+            self.socket
+                .set_read_timeout(Some(remaining))
+                .map_err(Error::SocketOp)?;
+
+            let (length, source) = match self.socket.recv_from(&mut self.buffer) {
+                Ok(received) => received,
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(false)
+                }
+                Err(error) => return Err(Error::Receive(error)),
+            };
+
+            let expected = ExpectedResponse {
+                // Datagram ICMP sockets replace the identifier with one chosen by the kernel
+                id: (!cfg!(target_os = "android")).then_some(self.id),
+                sequence: self.sequence,
+                icmp_size,
+            };
+            let packet = if cfg!(target_os = "android") {
+                &self.buffer[..length]
+            } else {
+                strip_ipv4_header(&self.buffer[..length])
+            };
+            match parse_response(packet, &expected) {
+                Response::EchoReply if source.ip() == self.destination => return Ok(true),
+                Response::FragmentationNeeded => return Ok(false),
+                _ => continue,
+            }
+        }
+    }
+}
+
+impl Probe for IcmpProber {
+    fn probe(&mut self, packet_size: u16) -> Result<bool, Error> {
+        let Some(icmp_size) = packet_size.checked_sub(IPV4_HEADER_SIZE) else {
+            return Err(Error::InvalidRange(packet_size..=packet_size));
+        };
+        if icmp_size < ICMP_HEADER_SIZE {
+            return Err(Error::InvalidRange(packet_size..=packet_size));
+        }
+        let icmp_size = usize::from(icmp_size);
+
+        self.sequence = self.sequence.wrapping_add(1);
+        let mut request = vec![0; icmp_size];
+        write_echo_request(&mut request, self.id, self.sequence);
+
+        let destination = SocketAddr::from((self.destination, 0));
+        match self.socket.send_to(&request, destination) {
+            Ok(_) => (),
+            // The packet does not even fit the MTU of the local interface
+            Err(error) if is_message_too_big(&error) => return Ok(false),
+            Err(error) => return Err(Error::Send(error)),
+        }
+
+        self.wait_for_response(icmp_size)
+    }
+}
+
+/// Set the "don't fragment" bit on all IPv4 packets sent on `socket`. Sending a packet that is
+/// larger than the MTU of the outgoing interface then fails with an error for which
+/// [`is_message_too_big`] returns `true`.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub fn set_dont_fragment(socket: &Socket) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // Set DF, but do not let the cached path MTU prevent probes larger than it from being sent
+    let value: libc::c_int = libc::IP_PMTUDISC_PROBE;
+    // SAFETY: `value` is a valid `c_int` that outlives the call, and its size is passed along
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_MTU_DISCOVER,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of_val(&value) as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Set the "don't fragment" bit on all IPv4 packets sent on `socket`. Sending a packet that is
+/// larger than the MTU of the outgoing interface then fails with an error for which
+/// [`is_message_too_big`] returns `true`.
+#[cfg(target_os = "macos")]
+pub fn set_dont_fragment(socket: &Socket) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    const IP_DONTFRAG: libc::c_int = 28;
+
+    let value: libc::c_int = 1;
+    // SAFETY: `value` is a valid `c_int` that outlives the call, and its size is passed along
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            IP_DONTFRAG,
+            &value as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of_val(&value) as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Set the "don't fragment" bit on all IPv4 packets sent on `socket`. Sending a packet that is
+/// larger than the MTU of the outgoing interface then fails with an error for which
+/// [`is_message_too_big`] returns `true`.
+#[cfg(windows)]
+pub fn set_dont_fragment(socket: &Socket) -> io::Result<()> {
+    use std::os::windows::io::AsRawSocket;
+    use windows_sys::Win32::Networking::WinSock::{
+        setsockopt, IPPROTO_IP, IP_DONTFRAGMENT, SOCKET, SOCKET_ERROR,
+    };
+
+    let value: u32 = 1;
+    // SAFETY: `value` is a valid DWORD that outlives the call, and its size is passed along
+    let result = unsafe {
+        setsockopt(
+            socket.as_raw_socket() as SOCKET,
+            IPPROTO_IP,
+            IP_DONTFRAGMENT,
+            &value as *const u32 as *const u8,
+            std::mem::size_of_val(&value) as i32,
+        )
+    };
+    if result == SOCKET_ERROR {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// Returns whether a send failed because the packet was too large to be sent without
+/// fragmentation.
+pub fn is_message_too_big(error: &io::Error) -> bool {
+    #[cfg(unix)]
+    const EMSGSIZE: i32 = libc::EMSGSIZE;
+    #[cfg(windows)]
+    const EMSGSIZE: i32 = windows_sys::Win32::Networking::WinSock::WSAEMSGSIZE;
+
+    error.raw_os_error() == Some(EMSGSIZE)
+}
+
+fn random_id() -> u16 {
+    use std::{
+        collections::hash_map::RandomState,
+        hash::{BuildHasher, Hasher},
+    };
+    RandomState::new().build_hasher().finish() as u16
+}
+
+/// Write an ICMP echo request filling all of `buffer`, which must fit at least the ICMP header.
+fn write_echo_request(buffer: &mut [u8], id: u16, sequence: u16) {
+    const ICMP_CHECKSUM_OFFSET: usize = 2;
+
+    buffer.fill(0);
+    buffer[0] = ICMP_ECHO_REQUEST;
+    buffer[4..6].copy_from_slice(&id.to_be_bytes());
+    buffer[6..8].copy_from_slice(&sequence.to_be_bytes());
+
+    let checksum = internet_checksum::checksum(buffer);
+    buffer[ICMP_CHECKSUM_OFFSET..ICMP_CHECKSUM_OFFSET + 2].copy_from_slice(&checksum);
+}
+
+/// Return the payload of an IPv4 packet, or an empty slice if it is malformed.
+fn strip_ipv4_header(packet: &[u8]) -> &[u8] {
+    let Some(first_byte) = packet.first() else {
+        return &[];
+    };
+    let header_len = usize::from(first_byte & 0x0f) * 4;
+    packet.get(header_len..).unwrap_or(&[])
+}
+
+struct ExpectedResponse {
+    /// ICMP identifier of the probe, if it is known.
+    id: Option<u16>,
+    sequence: u16,
+    /// Size of the ICMP message of the probe.
+    icmp_size: usize,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Response {
+    /// The probe made it to the destination and back.
+    EchoReply,
+    /// A router on the path could not forward the probe without fragmenting it.
+    FragmentationNeeded,
+    /// The message does not concern the probe.
+    Unrelated,
+}
+
+/// Interpret an ICMP message that was received while waiting for the response to a probe.
+fn parse_response(icmp: &[u8], expected: &ExpectedResponse) -> Response {
+    let matches_probe = |echo: &[u8]| {
+        let (Some(id), Some(sequence)) = (echo.get(4..6), echo.get(6..8)) else {
+            return false;
+        };
+        expected
+            .id
+            .is_none_or(|expected| id == expected.to_be_bytes())
+            && sequence == expected.sequence.to_be_bytes()
+    };
+
+    match icmp {
+        [ICMP_ECHO_REPLY, 0, ..] if matches_probe(icmp) && icmp.len() == expected.icmp_size => {
+            Response::EchoReply
+        }
+        // The original IP header and the start of the probe follow the ICMP header
+        [ICMP_DESTINATION_UNREACHABLE, ICMP_FRAGMENTATION_NEEDED, ..] => {
+            let original = strip_ipv4_header(icmp.get(8..).unwrap_or(&[]));
+            match original {
+                [ICMP_ECHO_REQUEST, ..] if matches_probe(original) => Response::FragmentationNeeded,
+                _ => Response::Unrelated,
+            }
+        }
+        _ => Response::Unrelated,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Prober for a path that drops every packet larger than `mtu`.
+    struct MockPath {
+        mtu: u16,
+        /// Number of probes to drop regardless of their size.
+        lose_next: u32,
+        probes: Vec<u16>,
+    }
+
+    impl MockPath {
+        fn new(mtu: u16) -> Self {
+            Self {
+                mtu,
+                lose_next: 0,
+                probes: vec![],
+            }
+        }
+    }
+
+    impl Probe for MockPath {
+        fn probe(&mut self, packet_size: u16) -> Result<bool, Error> {
+            self.probes.push(packet_size);
+            if self.lose_next > 0 {
+                self.lose_next -= 1;
+                return Ok(false);
+            }
+            Ok(packet_size <= self.mtu)
+        }
+    }
+
+    #[test]
+    fn test_discover_path_mtu() {
+        for mtu in [576, 577, 1000, 1279, 1280, 1499] {
+            let mut path = MockPath::new(mtu);
+            assert_eq!(discover(&mut path, 576..=1500).unwrap(), mtu);
+        }
+    }
+
+    #[test]
+    fn test_discover_largest_size_first() {
+        let mut path = MockPath::new(1500);
+        assert_eq!(discover(&mut path, 576..=1500).unwrap(), 1500);
+        assert_eq!(path.probes, [1500]);
+    }
+
+    #[test]
+    fn test_discover_lost_probes() {
+        let mut path = MockPath::new(1500);
+        path.lose_next = PROBE_ATTEMPTS - 1;
+        assert_eq!(discover(&mut path, 576..=1500).unwrap(), 1500);
+    }
+
+    #[test]
+    fn test_discover_no_response() {
+        let mut path = MockPath::new(500);
+        assert!(matches!(
+            discover(&mut path, 576..=1500),
+            Err(Error::NoResponse(576))
+        ));
+    }
+
+    #[test]
+    fn test_discover_empty_range() {
+        let mut path = MockPath::new(1500);
+        #[allow(clippy::reversed_empty_ranges)]
+        let sizes = 1500..=576;
+        assert!(matches!(
+            discover(&mut path, sizes),
+            Err(Error::InvalidRange(_))
+        ));
+        assert!(path.probes.is_empty());
+    }
+
+    #[test]
+    fn test_echo_request() {
+        let mut request = [0xffu8; 16];
+        write_echo_request(&mut request, 0x1dcd, 0x0001);
+        assert_eq!(request[0], ICMP_ECHO_REQUEST);
+        assert_eq!(request[4..8], [0x1d, 0xcd, 0x00, 0x01]);
+        assert!(request[8..].iter().all(|byte| *byte == 0));
+        // A valid checksum makes the checksum of the whole message zero
+        assert_eq!(internet_checksum::checksum(&request), [0, 0]);
+    }
+
+    #[test]
+    fn test_parse_echo_reply() {
+        let expected = ExpectedResponse {
+            id: Some(0x1dcd),
+            sequence: 1,
+            icmp_size: 16,
+        };
+        let mut reply = [0u8; 16];
+        write_echo_request(&mut reply, 0x1dcd, 1);
+        reply[0] = ICMP_ECHO_REPLY;
+        assert_eq!(parse_response(&reply, &expected), Response::EchoReply);
+
+        // Truncated replies do not prove that the probe made it through
+        assert_eq!(parse_response(&reply[..12], &expected), Response::Unrelated);
+
+        let mut other_sequence = reply;
+        other_sequence[7] = 2;
+        assert_eq!(
+            parse_response(&other_sequence, &expected),
+            Response::Unrelated
+        );
+
+        let unknown_id = ExpectedResponse {
+            id: None,
+            ..expected
+        };
+        let mut other_id = reply;
+        other_id[4] = 0;
+        assert_eq!(parse_response(&other_id, &unknown_id), Response::EchoReply);
+    }
+
+    #[test]
+    fn test_parse_fragmentation_needed() {
+        let expected = ExpectedResponse {
+            id: Some(0x1dcd),
+            sequence: 1,
+            icmp_size: 1472,
+        };
+
+        let mut message = vec![ICMP_DESTINATION_UNREACHABLE, ICMP_FRAGMENTATION_NEEDED];
+        message.extend([0, 0, 0, 0, 0x05, 0x78]);
+        // Original IPv4 header without options
+        message.push(0x45);
+        message.extend([0; 19]);
+        // Start of the original probe
+        let mut probe = [0u8; 8];
+        write_echo_request(&mut probe, 0x1dcd, 1);
+        message.extend(probe);
+
+        assert_eq!(
+            parse_response(&message, &expected),
+            Response::FragmentationNeeded
+        );
+
+        let other_probe = ExpectedResponse {
+            sequence: 2,
+            ..expected
+        };
+        assert_eq!(parse_response(&message, &other_probe), Response::Unrelated);
+    }
+
+    #[test]
+    fn test_strip_ipv4_header() {
+        let mut packet = vec![0x46];
+        packet.extend([0; 23]);
+        packet.extend([1, 2, 3]);
+        assert_eq!(strip_ipv4_header(&packet), [1, 2, 3]);
+        assert!(strip_ipv4_header(&packet[..10]).is_empty());
+        assert!(strip_ipv4_header(&[]).is_empty());
+    }
+}
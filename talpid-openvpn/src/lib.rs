@@ -21,7 +21,7 @@ use std::{
 use talpid_routing::RequiredRoute;
 use talpid_tunnel::EventHook;
 use talpid_types::{
-    net::{openvpn, proxy::CustomProxy},
+    net::{openvpn, proxy::CustomProxy, CustomRoute, RouteTarget},
     ErrorExt,
 };
 use tokio::task;
@@ -312,12 +312,29 @@ impl OpenVpnMonitor<OpenVpnCommand> {
                 route_manager,
                 #[cfg(target_os = "linux")]
                 ipv6_enabled,
+                custom_routes: Self::custom_routes(params),
             },
             #[cfg(windows)]
             Box::new(wintun),
         )
     }
 
+    /// Returns the user-defined routes, without routes through the tunnel for IPv6 unless IPv6 is
+    /// enabled in the tunnel.
+    fn custom_routes(params: &openvpn::TunnelParameters) -> Vec<CustomRoute> {
+        params
+            .generic_options
+            .custom_routes
+            .iter()
+            .filter(|route| {
+                route.via == RouteTarget::DefaultGateway
+                    || route.network.is_ipv4()
+                    || params.generic_options.enable_ipv6
+            })
+            .copied()
+            .collect()
+    }
+
     #[cfg(windows)]
     fn new_wintun_context(
         params: &openvpn::TunnelParameters,
@@ -543,15 +560,20 @@ impl<C: OpenVpnBuilder + Send + 'static> OpenVpnMonitor<C> {
     fn create_proxy_auth_file(
         proxy_settings: &Option<CustomProxy>,
     ) -> std::result::Result<Option<mktemp::TempFile>, io::Error> {
-        if let Some(CustomProxy::Socks5Remote(remote_proxy)) = proxy_settings {
-            if let Some(ref proxy_auth) = remote_proxy.auth {
-                return Ok(Some(Self::create_credentials_file(
-                    proxy_auth.username(),
-                    proxy_auth.password(),
-                )?));
-            }
-        }
-        Ok(None)
+        let credentials = match proxy_settings {
+            Some(CustomProxy::Socks5Remote(remote_proxy)) => remote_proxy
+                .auth
+                .as_ref()
+                .map(|auth| (auth.username(), auth.password())),
+            Some(CustomProxy::Http(http_proxy)) => http_proxy
+                .auth
+                .as_ref()
+                .map(|auth| (auth.username(), auth.password())),
+            _ => None,
+        };
+        credentials
+            .map(|(username, password)| Self::create_credentials_file(username, password))
+            .transpose()
     }
 
     /// Starts a proxy service, as applicable.
@@ -775,7 +797,7 @@ mod event_server {
     use talpid_tunnel::{EventHook, TunnelMetadata};
     #[cfg(any(target_os = "macos", target_os = "windows"))]
     use talpid_types::net::proxy::CustomProxy;
-    use talpid_types::ErrorExt;
+    use talpid_types::{net::CustomRoute, ErrorExt};
     use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
     use tonic::{
         transport::{server::Connected, Server},
@@ -813,6 +835,8 @@ mod event_server {
         pub route_manager: talpid_routing::RouteManagerHandle,
         #[cfg(target_os = "linux")]
         pub ipv6_enabled: bool,
+        /// User-defined routes through or around the tunnel
+        pub custom_routes: Vec<CustomRoute>,
     }
 
     impl OpenvpnEventProxyImpl {
@@ -873,6 +897,11 @@ mod event_server {
 
             let metadata = Self::get_tunnel_metadata(&env)?;
 
+            let tunnel_node = talpid_routing::Node::device(metadata.interface.clone());
+            routes.extend(self.custom_routes.iter().map(|route| {
+                talpid_routing::RequiredRoute::from_custom_route(route, tunnel_node.clone())
+            }));
+
             #[cfg(windows)]
             {
                 let tunnel_device = metadata.interface.clone();
@@ -899,7 +928,10 @@ mod event_server {
 
             self.event_hook
                 .clone()
-                .on_event(talpid_tunnel::TunnelEvent::Up(metadata))
+                .on_event(talpid_tunnel::TunnelEvent::Up(
+                    metadata,
+                    talpid_types::tunnel::ConnectionTimings::default(),
+                ))
                 .await;
 
             Ok(Response::new(()))
@@ -949,6 +981,8 @@ mod event_server {
                 ips,
                 ipv4_gateway,
                 ipv6_gateway,
+                wireguard_backend: None,
+                wireguard_worker_threads: None,
             })
         }
     }
@@ -6,7 +6,10 @@ use std::{
     process::Stdio,
     time::Duration,
 };
-use talpid_types::net::{self, proxy::CustomProxy};
+use talpid_types::net::{
+    self,
+    proxy::{CustomProxy, HttpAuthScheme},
+};
 
 static BASE_ARGUMENTS: &[&[&str]] = &[
     &["--client"],
@@ -343,6 +346,31 @@ impl OpenVpnCommand {
                 args.push("255.255.255.255".to_owned());
                 args.push("net_gateway".to_owned());
             }
+            Some(CustomProxy::Http(ref http_proxy)) => {
+                args.push("--http-proxy".to_owned());
+                args.push(http_proxy.endpoint.ip().to_string());
+                args.push(http_proxy.endpoint.port().to_string());
+
+                if let Some(ref auth) = http_proxy.auth {
+                    if let Some(ref auth_file) = self.proxy_auth_path {
+                        args.push(auth_file.to_string_lossy().to_string());
+                        args.push(
+                            match auth.scheme() {
+                                HttpAuthScheme::Basic => "basic",
+                                HttpAuthScheme::Ntlm => "ntlm",
+                            }
+                            .to_owned(),
+                        );
+                    } else {
+                        log::error!("Proxy credentials present but credentials file missing");
+                    }
+                }
+
+                args.push("--route".to_owned());
+                args.push(http_proxy.endpoint.ip().to_string());
+                args.push("255.255.255.255".to_owned());
+                args.push("net_gateway".to_owned());
+            }
             None => {}
         };
         args
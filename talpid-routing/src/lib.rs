@@ -4,6 +4,8 @@
 
 use ipnetwork::IpNetwork;
 use std::{fmt, net::IpAddr};
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::{CustomRoute, RouteTarget};
 
 #[cfg(any(target_os = "windows", target_os = "macos"))]
 /// Burst guard
@@ -197,6 +199,15 @@ impl RequiredRoute {
         self.mtu = Some(mtu);
         self
     }
+
+    /// Constructs a required route from a user-defined route. Routes through the tunnel are sent
+    /// to `tunnel_node`, and routes around it to the default node.
+    pub fn from_custom_route(route: &CustomRoute, tunnel_node: Node) -> Self {
+        match route.via {
+            RouteTarget::Tunnel => Self::new(route.network, tunnel_node),
+            RouteTarget::DefaultGateway => Self::new(route.network, NetNode::DefaultNode),
+        }
+    }
 }
 
 /// A NetNode represents a network node - either a real one or a symbolic default one.
@@ -207,8 +218,8 @@ pub enum NetNode {
     /// of the route manager
     RealNode(Node),
     /// A default node is a symbolic node that will resolve to the network node used in the current
-    /// most preferable default route
-    #[cfg(not(target_os = "linux"))]
+    /// most preferable default route. On Linux, the node is resolved once when the route is added,
+    /// using the route that traffic outside the tunnel takes.
     DefaultNode,
 }
 
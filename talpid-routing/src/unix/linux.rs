@@ -55,13 +55,28 @@ static SUPPRESS_RULE_V6: LazyLock<RuleMessage> = LazyLock::new(|| {
     v6_rule
 });
 
-fn all_rules(fwmark: u32, table: u32) -> [RuleMessage; 4] {
-    [
+/// Returns the routing rules. If `priority` is set, the suppress rules use it, and the rules for
+/// unmarked traffic use the priority after it. Otherwise, the kernel picks the priorities.
+fn all_rules(fwmark: u32, table: u32, priority: Option<u32>) -> [RuleMessage; 4] {
+    let mut rules = [
         no_fwmark_rule_v4(fwmark, table),
         no_fwmark_rule_v6(fwmark, table),
         SUPPRESS_RULE_V4.clone(),
         SUPPRESS_RULE_V6.clone(),
-    ]
+    ];
+    if let Some(priority) = priority {
+        for rule in &mut rules {
+            // The suppress rules must be evaluated before unmarked traffic is sent to the tunnel
+            let is_suppress_rule = rule.nlas.contains(&RuleNla::SuppressPrefixLen(0));
+            let priority = if is_suppress_rule {
+                priority
+            } else {
+                priority + 1
+            };
+            rule.nlas.push(RuleNla::Priority(priority));
+        }
+    }
+    rules
 }
 
 fn no_fwmark_rule_v4(fwmark: u32, table: u32) -> RuleMessage {
@@ -143,10 +158,12 @@ pub struct RouteManagerImpl {
     /// Firewall mark identifies traffic which shouldn't be routed via the tunnel routing table. It
     /// is used to construct a routing rule.
     fwmark: u32,
+    /// Priority of the first routing rule, or `None` to let the kernel pick it.
+    rule_priority: Option<u32>,
 }
 
 impl RouteManagerImpl {
-    pub async fn new(table_id: u32, fwmark: u32) -> Result<Self> {
+    pub async fn new(fwmark: u32, table_id: u32, rule_priority: Option<u32>) -> Result<Self> {
         let (mut connection, handle, messages) =
             rtnetlink::new_connection().map_err(Error::Connect)?;
 
@@ -170,6 +187,7 @@ impl RouteManagerImpl {
             added_routes: HashSet::new(),
             table_id,
             fwmark,
+            rule_priority,
         };
 
         monitor.clear_routing_rules().await?;
@@ -182,7 +200,7 @@ impl RouteManagerImpl {
 
         self.clear_routing_rules().await?;
 
-        for rule in all_rules(self.fwmark, self.table_id)
+        for rule in all_rules(self.fwmark, self.table_id, self.rule_priority)
             .iter()
             .filter(|rule| rule.header.family as u16 == AF_INET || enable_ipv6)
         {
@@ -202,7 +220,7 @@ impl RouteManagerImpl {
 
     async fn clear_routing_rules(&mut self) -> Result<()> {
         let rules = self.get_rules().await?;
-        for rule in all_rules(self.fwmark, self.table_id) {
+        for rule in all_rules(self.fwmark, self.table_id, self.rule_priority) {
             let mut matching_rule = None;
 
             // `RTM_DELRULE` is way too picky about which rules are considered the same.
@@ -218,9 +236,14 @@ impl RouteManagerImpl {
                 if (found_rule.header.flags & rule.header.flags) != rule.header.flags {
                     continue;
                 }
-                // Match NLAs
+                // Match NLAs. The priority is ignored, so that rules are also removed if the
+                // configured priority has changed
                 let mut contains_nlas = true;
-                for nla in &rule.nlas {
+                for nla in rule
+                    .nlas
+                    .iter()
+                    .filter(|nla| !matches!(nla, RuleNla::Priority(_)))
+                {
                     if !found_rule.nlas.contains(nla) {
                         contains_nlas = false;
                         break;
@@ -286,18 +309,29 @@ impl RouteManagerImpl {
         let mut required_normal_routes = HashSet::new();
 
         for route in required_routes {
-            match route.node {
-                NetNode::RealNode(node) => {
-                    let table = if route.main_table {
-                        RT_TABLE_MAIN.into()
-                    } else {
-                        self.table_id
+            let node = match route.node {
+                NetNode::RealNode(node) => node,
+                // Resolve the default node once, using the route that traffic outside the tunnel
+                // currently takes
+                NetNode::DefaultNode => {
+                    let Some(default_route) = self
+                        .query_route(&route.prefix.ip(), Some(self.fwmark), None)
+                        .await?
+                    else {
+                        log::warn!("No default route to {}. Skipping route", route.prefix);
+                        continue;
                     };
-                    let mut new_route = Route::new(node, route.prefix).table(table);
-                    new_route.mtu = route.mtu.map(u32::from);
-                    required_normal_routes.insert(new_route);
+                    default_route.get_node().clone()
                 }
-            }
+            };
+            let table = if route.main_table {
+                RT_TABLE_MAIN.into()
+            } else {
+                self.table_id
+            };
+            let mut new_route = Route::new(node, route.prefix).table(table);
+            new_route.mtu = route.mtu.map(u32::from);
+            required_normal_routes.insert(new_route);
         }
 
         for normal_route in required_normal_routes.into_iter() {
@@ -385,6 +419,9 @@ impl RouteManagerImpl {
             RouteManagerCommand::GetDestinationRoute(destination, mark, result_tx) => {
                 let _ = result_tx.send(self.get_destination_route(&destination, mark).await);
             }
+            RouteManagerCommand::GetInterfaceRoute(destination, interface, result_tx) => {
+                let _ = result_tx.send(self.get_interface_route(&destination, &interface).await);
+            }
             RouteManagerCommand::GetMtuForRoute(ip, result_tx) => {
                 let _ = result_tx.send(self.get_mtu_for_route(ip).await);
             }
@@ -821,6 +858,27 @@ impl RouteManagerImpl {
         &self,
         destination: &IpAddr,
         fwmark: Option<u32>,
+    ) -> Result<Option<Route>> {
+        self.query_route(destination, fwmark, None).await
+    }
+
+    /// Look up the route that marked traffic to `destination` would take if it was forced out
+    /// through `interface`.
+    async fn get_interface_route(
+        &self,
+        destination: &IpAddr,
+        interface: &str,
+    ) -> Result<Option<Route>> {
+        let iface_idx = self.find_iface_idx(interface).ok_or(Error::LinkNotFound)?;
+        self.query_route(destination, Some(self.fwmark), Some(iface_idx))
+            .await
+    }
+
+    async fn query_route(
+        &self,
+        destination: &IpAddr,
+        fwmark: Option<u32>,
+        iface_idx: Option<u32>,
     ) -> Result<Option<Route>> {
         let mut request = self.handle.route().get(get_ip_version(destination));
         let octets = match destination {
@@ -831,6 +889,9 @@ impl RouteManagerImpl {
         if let Some(mark) = fwmark {
             message.nlas.push(RouteNla::Mark(mark));
         }
+        if let Some(iface_idx) = iface_idx {
+            message.nlas.push(RouteNla::Oif(iface_idx));
+        }
         message.header.destination_prefix_length = 8u8 * (octets.len() as u8);
         message.header.flags = RouteFlags::RTM_F_FIB_MATCH;
         message.nlas.push(RouteNla::Destination(octets));
@@ -911,12 +972,33 @@ impl NetworkInterface {
 mod test {
     use super::*;
 
+    /// The suppress rules must come before the rules that send unmarked traffic to the tunnel
+    #[test]
+    fn test_rule_priorities() {
+        let priority = |rule: &RuleMessage| {
+            rule.nlas.iter().find_map(|nla| match nla {
+                RuleNla::Priority(priority) => Some(*priority),
+                _ => None,
+            })
+        };
+
+        let [no_fwmark_v4, no_fwmark_v6, suppress_v4, suppress_v6] = all_rules(1, 2, Some(100));
+        assert_eq!(priority(&suppress_v4), Some(100));
+        assert_eq!(priority(&suppress_v6), Some(100));
+        assert_eq!(priority(&no_fwmark_v4), Some(101));
+        assert_eq!(priority(&no_fwmark_v6), Some(101));
+
+        assert!(all_rules(1, 2, None)
+            .iter()
+            .all(|rule| priority(rule).is_none()));
+    }
+
     /// Tests if dropping inside a tokio runtime panics
     #[test]
     fn test_drop_in_executor() {
         let runtime = tokio::runtime::Runtime::new().expect("Failed to initialize runtime");
         runtime.block_on(async {
-            let manager = RouteManagerImpl::new(0, 0)
+            let manager = RouteManagerImpl::new(0, 0, None)
                 .await
                 .expect("Failed to initialize route manager");
             std::mem::drop(manager);
@@ -928,7 +1010,7 @@ mod test {
     fn test_drop() {
         let runtime = tokio::runtime::Runtime::new().expect("Failed to initialize runtime");
         let manager = runtime.block_on(async {
-            RouteManagerImpl::new(1000, 1000)
+            RouteManagerImpl::new(1000, 1000, None)
                 .await
                 .expect("Failed to initialize route manager")
         });
@@ -99,6 +99,12 @@ pub(crate) enum RouteManagerCommand {
         Option<Fwmark>,
         oneshot::Sender<Result<Option<Route>, PlatformError>>,
     ),
+    /// Attempt to fetch a route for the given destination that goes through a specific interface.
+    GetInterfaceRoute(
+        IpAddr,
+        String,
+        oneshot::Sender<Result<Option<Route>, PlatformError>>,
+    ),
 }
 
 /// Commands for the underlying route manager object.
@@ -167,9 +173,13 @@ pub struct RouteManagerHandle {
 
 impl RouteManagerHandle {
     /// Construct a route manager.
+    ///
+    /// On Linux, `rule_priority` is the priority of the first of the routing rules. If it is `None`,
+    /// the kernel picks the priorities.
     pub async fn spawn(
         #[cfg(target_os = "linux")] fwmark: u32,
         #[cfg(target_os = "linux")] table_id: u32,
+        #[cfg(target_os = "linux")] rule_priority: Option<u32>,
         #[cfg(target_os = "android")] android_context: AndroidContext,
     ) -> Result<Self, Error> {
         let (manage_tx, manage_rx) = mpsc::unbounded();
@@ -179,6 +189,8 @@ impl RouteManagerHandle {
             fwmark,
             #[cfg(target_os = "linux")]
             table_id,
+            #[cfg(target_os = "linux")]
+            rule_priority,
             #[cfg(target_os = "macos")]
             Arc::downgrade(&manage_tx),
             #[cfg(target_os = "android")]
@@ -377,6 +389,28 @@ impl RouteManagerHandle {
             .map_err(Error::PlatformError)
     }
 
+    /// Get the route that traffic to `destination` would take if it was sent out through
+    /// `interface`. Returns `None` if the interface has no route to the destination.
+    #[cfg(target_os = "linux")]
+    pub async fn get_interface_route(
+        &self,
+        destination: IpAddr,
+        interface: String,
+    ) -> Result<Option<Route>, Error> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.tx
+            .unbounded_send(RouteManagerCommand::GetInterfaceRoute(
+                destination,
+                interface,
+                response_tx,
+            ))
+            .map_err(|_| Error::RouteManagerDown)?;
+        response_rx
+            .await
+            .map_err(|_| Error::ManagerChannelDown)?
+            .map_err(Error::PlatformError)
+    }
+
     /// Listen for route changes.
     #[cfg(target_os = "linux")]
     pub async fn get_mtu_for_route(&self, ip: IpAddr) -> Result<u16, Error> {
@@ -4,7 +4,7 @@
 // Usage: ./psk-exchange <tuncfg_server_ip> <wireguard_public_key>
 // e. g. ./psk-exchange 10.64.0.1 NkECLsf+VbZUjve7RVN6sE3NYUcYUmUn8qpFugqbXFk=
 
-use talpid_types::net::wireguard::{PrivateKey, PublicKey};
+use talpid_types::net::wireguard::{PrivateKey, PublicKey, QuantumResistantKem};
 
 #[tokio::main]
 async fn main() {
@@ -13,7 +13,7 @@ async fn main() {
         .next()
         .expect("Give tuncfg server IP as first argument")
         .parse()
-        .expect("tuncfg IP argument not a valid IPv4");
+        .expect("tuncfg IP argument not a valid IP address");
     let public_key_string = args
         .next()
         .expect("Give WireGuard public key as second argument");
@@ -26,7 +26,7 @@ async fn main() {
         tuncfg_server_ip,
         public_key, // Parent connection's public key.
         ephemeral_private_key.public_key(),
-        true,  // Whether to negotiate a "PQ-safe" PSK.
+        Some(QuantumResistantKem::Auto), // KEMs to negotiate a "PQ-safe" PSK with, if any.
         false, // Whether to use DAITA (Does not work with Linux kernel WireGuard.)
     )
     .await
@@ -1,10 +1,15 @@
 use proto::PostQuantumRequestV1;
 use std::fmt;
 #[cfg(not(target_os = "ios"))]
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 #[cfg(not(target_os = "ios"))]
-use std::net::{IpAddr, Ipv4Addr};
-use talpid_types::net::wireguard::{PresharedKey, PublicKey};
+use std::{future::Future, time::Duration};
+use talpid_types::net::wireguard::{PresharedKey, PublicKey, QuantumResistantKem};
+#[cfg(not(target_os = "ios"))]
+use talpid_types::{
+    net::wireguard::{NegotiationRetry, NegotiationRetryPolicy},
+    ErrorExt,
+};
 use tonic::transport::Channel;
 #[cfg(not(target_os = "ios"))]
 use tonic::transport::Endpoint;
@@ -27,7 +32,9 @@ const DAITA_VERSION: u32 = 2;
 #[derive(Debug)]
 pub enum Error {
     GrpcConnectError(tonic::transport::Error),
+    ConnectTimeout,
     GrpcError(tonic::Status),
+    Timeout,
     MissingCiphertexts,
     InvalidCiphertextLength {
         algorithm: &'static str,
@@ -36,6 +43,7 @@ pub enum Error {
     },
     InvalidCiphertextCount {
         actual: usize,
+        expected: usize,
     },
     MissingDaitaResponse,
     #[cfg(target_os = "ios")]
@@ -49,7 +57,9 @@ impl std::fmt::Display for Error {
         use Error::*;
         match self {
             GrpcConnectError(err) => write!(f, "Failed to connect to config service: {err:?}"),
+            ConnectTimeout => "Timed out connecting to config service".fmt(f),
             GrpcError(status) => write!(f, "RPC failed: {status}"),
+            Timeout => "Timed out negotiating ephemeral peer".fmt(f),
             MissingCiphertexts => write!(f, "Found no ciphertexts in response"),
             InvalidCiphertextLength {
                 algorithm,
@@ -59,8 +69,11 @@ impl std::fmt::Display for Error {
                 f,
                 "Expected a {expected} bytes ciphertext for {algorithm}, got {actual} bytes"
             ),
-            InvalidCiphertextCount { actual } => {
-                write!(f, "Expected 2 ciphertext in the response, got {actual}")
+            InvalidCiphertextCount { actual, expected } => {
+                write!(
+                    f,
+                    "Expected {expected} ciphertexts in the response, got {actual}"
+                )
             }
             MissingDaitaResponse => "Expected DAITA configuration in response".fmt(f),
             #[cfg(target_os = "ios")]
@@ -71,6 +84,21 @@ impl std::fmt::Display for Error {
     }
 }
 
+impl Error {
+    /// Whether the negotiation might succeed if it is attempted again, e.g. because the error may
+    /// have been caused by packet loss.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::GrpcConnectError(_) | Error::ConnectTimeout | Error::Timeout => true,
+            Error::GrpcError(status) => matches!(
+                status.code(),
+                tonic::Code::Unavailable | tonic::Code::DeadlineExceeded
+            ),
+            _ => false,
+        }
+    }
+}
+
 impl std::error::Error for Error {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
@@ -97,12 +125,15 @@ pub struct DaitaSettings {
 }
 
 /// Negotiate a short-lived peer with a PQ-safe PSK or with DAITA enabled.
+///
+/// The PSK is derived from the KEMs selected by `post_quantum`. No PSK is negotiated if it is
+/// `None`.
 #[cfg(not(target_os = "ios"))]
 pub async fn request_ephemeral_peer(
-    service_address: Ipv4Addr,
+    service_address: IpAddr,
     parent_pubkey: PublicKey,
     ephemeral_pubkey: PublicKey,
-    enable_post_quantum: bool,
+    post_quantum: Option<QuantumResistantKem>,
     enable_daita: bool,
 ) -> Result<EphemeralPeer, Error> {
     log::debug!("Connecting to relay config service at {service_address}");
@@ -113,21 +144,78 @@ pub async fn request_ephemeral_peer(
         client,
         parent_pubkey,
         ephemeral_pubkey,
-        enable_post_quantum,
+        post_quantum,
         enable_daita,
     )
     .await
 }
 
+/// Negotiate a short-lived peer like [`request_ephemeral_peer`], but retry transient failures
+/// as described by `policy`. Each attempt fails with [`Error::Timeout`] if it takes longer than
+/// `attempt_timeout`. `on_retry` is awaited before waiting for the backoff delay of every retry.
+#[cfg(not(target_os = "ios"))]
+#[allow(clippy::too_many_arguments)]
+pub async fn request_ephemeral_peer_with_retries<F: Future<Output = ()>>(
+    service_address: IpAddr,
+    parent_pubkey: PublicKey,
+    ephemeral_pubkey: PublicKey,
+    post_quantum: Option<QuantumResistantKem>,
+    enable_daita: bool,
+    policy: &NegotiationRetryPolicy,
+    attempt_timeout: Duration,
+    mut on_retry: impl FnMut(NegotiationRetry) -> F,
+) -> Result<EphemeralPeer, Error> {
+    let mut retry = 0;
+    loop {
+        let attempt = async {
+            log::debug!("Connecting to relay config service at {service_address}");
+            let client = tokio::time::timeout(
+                policy.connect_timeout,
+                connect_relay_config_client(service_address),
+            )
+            .await
+            .map_err(|_timeout| Error::ConnectTimeout)??;
+            log::debug!("Connected to relay config service at {service_address}");
+
+            request_ephemeral_peer_with(
+                client,
+                parent_pubkey.clone(),
+                ephemeral_pubkey.clone(),
+                post_quantum,
+                enable_daita,
+            )
+            .await
+        };
+        let result = tokio::time::timeout(attempt_timeout, attempt)
+            .await
+            .unwrap_or(Err(Error::Timeout));
+
+        match result {
+            Err(error) if error.is_transient() && retry < policy.retries => {
+                retry += 1;
+                let retry = NegotiationRetry {
+                    retry,
+                    max_retries: policy.retries,
+                    delay: policy.backoff(retry),
+                };
+                log::warn!("{}", error.display_chain_with_msg(&retry.to_string()));
+                on_retry(retry).await;
+                tokio::time::sleep(retry.delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
 pub async fn request_ephemeral_peer_with(
     mut client: RelayConfigService,
     parent_pubkey: PublicKey,
     ephemeral_pubkey: PublicKey,
-    enable_quantum_resistant: bool,
+    post_quantum: Option<QuantumResistantKem>,
     enable_daita: bool,
 ) -> Result<EphemeralPeer, Error> {
-    let (pq_request, kem_secrets) = if enable_quantum_resistant {
-        let (pq_request, kem_secrets) = post_quantum_secrets().await;
+    let (pq_request, kem_secrets) = if let Some(kem) = post_quantum {
+        let (pq_request, kem_secrets) = post_quantum_secrets(kem).await;
         log::debug!("Generated PQ secrets");
         (Some(pq_request), Some(kem_secrets))
     } else {
@@ -155,25 +243,29 @@ pub async fn request_ephemeral_peer_with(
 
     let response = response.into_inner();
 
-    let psk = if let Some((cme_kem_secret, ml_kem_secret)) = kem_secrets {
+    let psk = if let Some(kem_secrets) = kem_secrets {
         let ciphertexts = response
             .post_quantum
             .ok_or(Error::MissingCiphertexts)?
             .ciphertexts;
 
-        // Unpack the ciphertexts into one per KEM without needing to access them by index.
-        let [cme_ciphertext, ml_kem_ciphertext] = <&[Vec<u8>; 2]>::try_from(ciphertexts.as_slice())
-            .map_err(|_| Error::InvalidCiphertextCount {
+        if ciphertexts.len() != kem_secrets.len() {
+            return Err(Error::InvalidCiphertextCount {
                 actual: ciphertexts.len(),
-            })?;
+                expected: kem_secrets.len(),
+            });
+        }
+        // The ciphertexts are in the same order as the public keys in the request
+        let mut ciphertexts = ciphertexts.iter();
 
         // Store the PSK data on the heap. So it can be passed around and then zeroized on drop
         // without being stored in a bunch of places on the stack.
         let mut psk_data = Box::new([0u8; 32]);
 
         // Decapsulate Classic McEliece and mix into PSK
-        {
-            let mut shared_secret = classic_mceliece::decapsulate(&cme_kem_secret, cme_ciphertext)?;
+        if let Some(cme_kem_secret) = &kem_secrets.classic_mceliece {
+            let cme_ciphertext = ciphertexts.next().expect("ciphertext count was checked");
+            let mut shared_secret = classic_mceliece::decapsulate(cme_kem_secret, cme_ciphertext)?;
             xor_assign(&mut psk_data, shared_secret.as_array());
 
             // This should happen automatically due to `SharedSecret` implementing ZeroizeOnDrop.
@@ -182,7 +274,8 @@ pub async fn request_ephemeral_peer_with(
             shared_secret.zeroize();
         }
         // Decapsulate ML-KEM and mix into PSK
-        {
+        if let Some(ml_kem_secret) = &kem_secrets.ml_kem {
+            let ml_kem_ciphertext = ciphertexts.next().expect("ciphertext count was checked");
             let mut shared_secret = ml_kem_secret.decapsulate(ml_kem_ciphertext)?;
             xor_assign(&mut psk_data, &shared_secret);
 
@@ -227,28 +320,44 @@ const fn get_platform() -> proto::DaitaPlatform {
     PLATFORM
 }
 
-async fn post_quantum_secrets() -> (
-    PostQuantumRequestV1,
-    (classic_mceliece_rust::SecretKey<'static>, ml_kem::Keypair),
-) {
-    let (cme_kem_pubkey, cme_kem_secret) = classic_mceliece::generate_keys().await;
-    let ml_kem_keypair = ml_kem::keypair();
-
-    (
-        proto::PostQuantumRequestV1 {
-            kem_pubkeys: vec![
-                proto::KemPubkeyV1 {
-                    algorithm_name: classic_mceliece::ALGORITHM_NAME.to_owned(),
-                    key_data: cme_kem_pubkey.as_array().to_vec(),
-                },
-                proto::KemPubkeyV1 {
-                    algorithm_name: ml_kem::ALGORITHM_NAME.to_owned(),
-                    key_data: ml_kem_keypair.encapsulation_key(),
-                },
-            ],
-        },
-        (cme_kem_secret, ml_kem_keypair),
-    )
+/// Secret keys for the KEMs used in a PQ request.
+struct KemSecrets {
+    classic_mceliece: Option<classic_mceliece_rust::SecretKey<'static>>,
+    ml_kem: Option<ml_kem::Keypair>,
+}
+
+impl KemSecrets {
+    /// Number of KEMs used
+    fn len(&self) -> usize {
+        usize::from(self.classic_mceliece.is_some()) + usize::from(self.ml_kem.is_some())
+    }
+}
+
+async fn post_quantum_secrets(kem: QuantumResistantKem) -> (PostQuantumRequestV1, KemSecrets) {
+    let mut kem_pubkeys = vec![];
+    let mut secrets = KemSecrets {
+        classic_mceliece: None,
+        ml_kem: None,
+    };
+
+    if kem.uses_classic_mceliece() {
+        let (cme_kem_pubkey, cme_kem_secret) = classic_mceliece::generate_keys().await;
+        kem_pubkeys.push(proto::KemPubkeyV1 {
+            algorithm_name: classic_mceliece::ALGORITHM_NAME.to_owned(),
+            key_data: cme_kem_pubkey.as_array().to_vec(),
+        });
+        secrets.classic_mceliece = Some(cme_kem_secret);
+    }
+    if kem.uses_ml_kem() {
+        let ml_kem_keypair = ml_kem::keypair();
+        kem_pubkeys.push(proto::KemPubkeyV1 {
+            algorithm_name: ml_kem::ALGORITHM_NAME.to_owned(),
+            key_data: ml_kem_keypair.encapsulation_key(),
+        });
+        secrets.ml_kem = Some(ml_kem_keypair);
+    }
+
+    (proto::PostQuantumRequestV1 { kem_pubkeys }, secrets)
 }
 
 /// Performs `dst = dst ^ src`.
@@ -262,15 +371,15 @@ fn xor_assign(dst: &mut [u8; 32], src: &[u8; 32]) {
 /// On non-Windows platforms the connection is made with a socket where the MSS
 /// value has been speficically lowered, to avoid MTU issues. See the `socket` module.
 #[cfg(not(target_os = "ios"))]
-async fn connect_relay_config_client(ip: Ipv4Addr) -> Result<RelayConfigService, Error> {
+async fn connect_relay_config_client(ip: IpAddr) -> Result<RelayConfigService, Error> {
     use hyper_util::rt::tokio::TokioIo;
 
     let endpoint = Endpoint::from_static("tcp://0.0.0.0:0");
-    let addr = SocketAddr::new(IpAddr::V4(ip), CONFIG_SERVICE_PORT);
+    let addr = SocketAddr::new(ip, CONFIG_SERVICE_PORT);
 
     let connection = endpoint
         .connect_with_connector(service_fn(move |_| async move {
-            let sock = socket::TcpSocket::new()?;
+            let sock = socket::TcpSocket::new(addr.is_ipv6())?;
             let stream = sock.connect(addr).await?;
             let sniffer = socket_sniffer::SocketSniffer {
                 s: stream,
@@ -32,9 +32,13 @@ mod sys {
     }
 
     impl TcpSocket {
-        pub fn new() -> io::Result<Self> {
-            let socket = StdTcpSocket::new_v4()?;
-            try_set_tcp_sock_mtu(&socket);
+        pub fn new(ipv6: bool) -> io::Result<Self> {
+            let socket = if ipv6 {
+                StdTcpSocket::new_v6()?
+            } else {
+                StdTcpSocket::new_v4()?
+            };
+            try_set_tcp_sock_mtu(&socket, ipv6);
             Ok(Self { socket })
         }
 
@@ -43,8 +47,8 @@ mod sys {
         }
     }
 
-    fn try_set_tcp_sock_mtu(sock: &impl AsRawFd) {
-        let mss = c_int::from(desired_mss());
+    fn try_set_tcp_sock_mtu(sock: &impl AsRawFd, ipv6: bool) {
+        let mss = c_int::from(desired_mss(ipv6));
 
         log::debug!("Tunnel config TCP socket MSS: {mss}");
 
@@ -67,10 +71,16 @@ mod sys {
         }
     }
 
-    const fn desired_mss() -> u16 {
+    const fn desired_mss(ipv6: bool) -> u16 {
         const IPV4_HEADER_SIZE: u16 = 20;
+        const IPV6_HEADER_SIZE: u16 = 40;
         const MAX_TCP_HEADER_SIZE: u16 = 60;
-        let mtu = CONFIG_CLIENT_MTU.saturating_sub(IPV4_HEADER_SIZE);
+        let ip_header_size = if ipv6 {
+            IPV6_HEADER_SIZE
+        } else {
+            IPV4_HEADER_SIZE
+        };
+        let mtu = CONFIG_CLIENT_MTU.saturating_sub(ip_header_size);
         mtu.saturating_sub(MAX_TCP_HEADER_SIZE)
     }
 }
@@ -84,10 +94,13 @@ mod sys {
     }
 
     impl TcpSocket {
-        pub fn new() -> io::Result<Self> {
-            Ok(Self {
-                socket: StdTcpSocket::new_v4()?,
-            })
+        pub fn new(ipv6: bool) -> io::Result<Self> {
+            let socket = if ipv6 {
+                StdTcpSocket::new_v6()?
+            } else {
+                StdTcpSocket::new_v4()?
+            };
+            Ok(Self { socket })
         }
 
         pub async fn connect(self, addr: SocketAddr) -> io::Result<TcpStream> {
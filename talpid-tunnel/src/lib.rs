@@ -1,5 +1,5 @@
 use std::{
-    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
     path::Path,
     sync::{Arc, Mutex},
 };
@@ -17,7 +17,10 @@ use futures::{
     SinkExt,
 };
 use talpid_routing::RouteManagerHandle;
-use talpid_types::net::AllowedTunnelTraffic;
+use talpid_types::{
+    net::{wireguard, AllowedTunnelTraffic},
+    tunnel::{ConnectionTimings, TrafficStats},
+};
 use tun_provider::TunProvider;
 
 /// Size of IPv4 header in bytes
@@ -49,6 +52,28 @@ pub struct TunnelArgs<'a> {
     pub retry_attempt: u32,
     /// Route manager handle.
     pub route_manager: RouteManagerHandle,
+    /// DNS servers to configure on the tunnel interface, if they should not be the tunnel
+    /// gateways.
+    #[cfg(target_os = "linux")]
+    pub dns_servers: Option<Vec<IpAddr>>,
+    /// Where the tunnel publishes its traffic statistics, if it collects any.
+    pub traffic_stats: TrafficStatsHandle,
+}
+
+/// Shared handle to the latest traffic statistics of a tunnel.
+#[derive(Clone, Debug, Default)]
+pub struct TrafficStatsHandle(Arc<Mutex<Option<TrafficStats>>>);
+
+impl TrafficStatsHandle {
+    /// Replace the published statistics.
+    pub fn set(&self, stats: TrafficStats) {
+        *self.0.lock().unwrap() = Some(stats);
+    }
+
+    /// Return the most recently published statistics, if any.
+    pub fn get(&self) -> Option<TrafficStats> {
+        self.0.lock().unwrap().clone()
+    }
 }
 
 #[derive(Clone)]
@@ -80,17 +105,31 @@ pub struct TunnelMetadata {
     pub ipv4_gateway: Ipv4Addr,
     /// The IP to the IPv6 default gateway on the tunnel interface.
     pub ipv6_gateway: Option<Ipv6Addr>,
+    /// The WireGuard implementation that the tunnel runs on, if it is a WireGuard tunnel.
+    pub wireguard_backend: Option<wireguard::Backend>,
+    /// The number of threads that process packets, if the tunnel runs on a userspace WireGuard
+    /// implementation.
+    pub wireguard_worker_threads: Option<u32>,
 }
 
 impl TunnelMetadata {
-    /// Return a copy of all gateway addresses
+    /// Return a copy of all gateway addresses that can be reached from the tunnel IPs
     pub fn gateways(&self) -> Vec<IpAddr> {
-        let mut addrs = vec![self.ipv4_gateway.into()];
+        let mut addrs = vec![];
+        if !self.is_ipv6_only() {
+            addrs.push(self.ipv4_gateway.into());
+        }
         if let Some(gateway) = self.ipv6_gateway {
             addrs.push(gateway.into());
         }
         addrs
     }
+
+    /// Return whether the tunnel only has IPv6 addresses, in which case IPv4 hosts can only be
+    /// reached through NAT64.
+    pub fn is_ipv6_only(&self) -> bool {
+        !self.ips.is_empty() && self.ips.iter().all(IpAddr::is_ipv6)
+    }
 }
 
 /// Possible events from the VPN tunnel and the child process managing it.
@@ -100,8 +139,14 @@ pub enum TunnelEvent {
     AuthFailed(Option<String>),
     /// Sent when the tunnel interface has been created, before routes are set up.
     InterfaceUp(TunnelMetadata, AllowedTunnelTraffic),
-    /// Sent when the tunnel comes up and is ready for traffic.
-    Up(TunnelMetadata),
+    /// Sent when the tunnel comes up and is ready for traffic. Includes the time spent on each
+    /// phase of the tunnel setup that the tunnel implementation knows about.
+    Up(TunnelMetadata, ConnectionTimings),
+    /// Sent before the tunnel moves to another endpoint on the entry relay, e.g. when port hopping.
+    /// The tunnel must not start using the new endpoint until the event has been handled.
+    PeerEndpointChanged(SocketAddr),
+    /// Sent when negotiation of an ephemeral peer failed and is about to be retried.
+    EphemeralPeerNegotiationRetry(wireguard::NegotiationRetry),
     /// Sent when the tunnel goes down, but before destroying the tunnel device.
     Down,
 }
@@ -67,9 +67,13 @@ pub struct TunConfig {
 }
 
 impl TunConfig {
-    /// Return a copy of all gateway addresses
+    /// Return a copy of all gateway addresses that can be reached from the tunnel addresses
     pub fn gateways(&self) -> Vec<IpAddr> {
-        let mut servers = vec![self.ipv4_gateway.into()];
+        let ipv6_only = !self.addresses.is_empty() && self.addresses.iter().all(IpAddr::is_ipv6);
+        let mut servers = vec![];
+        if !ipv6_only {
+            servers.push(self.ipv4_gateway.into());
+        }
         if let Some(gateway) = self.ipv6_gateway {
             servers.push(gateway.into());
         }
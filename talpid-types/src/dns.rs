@@ -0,0 +1,164 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde::{Deserialize, Serialize};
+use std::{fmt, net::IpAddr, path::PathBuf};
+
+/// Another process replaced the DNS configuration applied by the daemon while the tunnel was up.
+/// The configuration has been restored when this is emitted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DnsInterference {
+    /// Path of the file that was overwritten.
+    pub path: PathBuf,
+    /// Name of the software that overwrote the configuration, if it could be identified.
+    pub source: Option<String>,
+}
+
+impl fmt::Display for DnsInterference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.source {
+            Some(source) => write!(f, "{source} overwrote {}", self.path.display()),
+            None => write!(f, "Unknown software overwrote {}", self.path.display()),
+        }
+    }
+}
+
+/// Protocol used to reach an encrypted DNS server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EncryptedDnsProtocol {
+    /// DNS-over-TLS (RFC 7858)
+    Tls,
+    /// DNS-over-HTTPS (RFC 8484)
+    Https,
+}
+
+impl EncryptedDnsProtocol {
+    /// Port used by the protocol unless another one is specified.
+    pub const fn default_port(self) -> u16 {
+        match self {
+            EncryptedDnsProtocol::Tls => 853,
+            EncryptedDnsProtocol::Https => 443,
+        }
+    }
+}
+
+impl fmt::Display for EncryptedDnsProtocol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncryptedDnsProtocol::Tls => f.write_str("DNS-over-TLS"),
+            EncryptedDnsProtocol::Https => f.write_str("DNS-over-HTTPS"),
+        }
+    }
+}
+
+/// A DNS server that is queried over DNS-over-TLS or DNS-over-HTTPS.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EncryptedDnsServer {
+    /// Address of the server.
+    pub address: IpAddr,
+    pub protocol: EncryptedDnsProtocol,
+    /// Name that the certificate of the server is validated against.
+    pub hostname: String,
+    /// Port of the server. The default port of `protocol` is used if this is `None`.
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Base64 encoded SHA-256 digest of the SubjectPublicKeyInfo of the server certificate. If
+    /// set, the certificate is only accepted if its public key matches, in addition to the usual
+    /// validation.
+    #[serde(default)]
+    pub spki_pin: Option<String>,
+}
+
+/// Error returned if an SPKI pin is not a base64 encoded SHA-256 digest
+#[derive(Debug, thiserror::Error)]
+pub enum InvalidSpkiPin {
+    #[error("Invalid SPKI pin: {0}")]
+    Format(#[from] base64::DecodeError),
+    #[error("Invalid SPKI pin length: {0}")]
+    Length(usize),
+}
+
+impl EncryptedDnsServer {
+    /// Port to connect to.
+    pub fn port(&self) -> u16 {
+        self.port.unwrap_or(self.protocol.default_port())
+    }
+
+    /// Decode the SPKI pin, if there is one.
+    pub fn spki_digest(&self) -> Result<Option<[u8; 32]>, InvalidSpkiPin> {
+        let Some(pin) = &self.spki_pin else {
+            return Ok(None);
+        };
+        let bytes = STANDARD.decode(pin)?;
+        let digest = <[u8; 32]>::try_from(bytes.as_slice())
+            .map_err(|_| InvalidSpkiPin::Length(bytes.len()))?;
+        Ok(Some(digest))
+    }
+}
+
+impl fmt::Display for EncryptedDnsServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let address = std::net::SocketAddr::new(self.address, self.port());
+        write!(f, "{} ({address}, {})", self.hostname, self.protocol)?;
+        if self.spki_pin.is_some() {
+            f.write_str(", pinned")?;
+        }
+        Ok(())
+    }
+}
+
+/// Sends queries for a domain and all of its subdomains to specific DNS servers on the local
+/// network, instead of to the DNS servers used for everything else.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SplitDnsRule {
+    /// Domain to match, e.g. `corp.example`.
+    pub domain: String,
+    /// Servers that matching queries are sent to.
+    pub servers: Vec<IpAddr>,
+}
+
+impl fmt::Display for SplitDnsRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "*.{} ->", self.domain)?;
+        for (i, server) in self.servers.iter().enumerate() {
+            let separator = if i == 0 { " " } else { ", " };
+            write!(f, "{separator}{server}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A way of managing the DNS configuration of the system on Linux.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DnsBackend {
+    SystemdResolved,
+    NetworkManager,
+    Resolvconf,
+    /// Overwrite `/etc/resolv.conf` directly.
+    StaticFile,
+}
+
+#[cfg(target_os = "linux")]
+impl DnsBackend {
+    /// The order that backends are tried in, unless another order is configured.
+    pub const DEFAULT_ORDER: [DnsBackend; 4] = [
+        DnsBackend::SystemdResolved,
+        DnsBackend::NetworkManager,
+        DnsBackend::Resolvconf,
+        DnsBackend::StaticFile,
+    ];
+}
+
+#[cfg(target_os = "linux")]
+impl fmt::Display for DnsBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DnsBackend::SystemdResolved => "systemd-resolved",
+            DnsBackend::NetworkManager => "NetworkManager",
+            DnsBackend::Resolvconf => "resolvconf",
+            DnsBackend::StaticFile => "/etc/resolv.conf",
+        };
+        f.write_str(name)
+    }
+}
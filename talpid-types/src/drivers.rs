@@ -0,0 +1,82 @@
+use std::{fmt, path::PathBuf};
+
+/// A kernel driver that the daemon depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Driver {
+    /// The Wintun driver, used by OpenVPN.
+    Wintun,
+    /// The WireGuardNT driver.
+    WireguardNt,
+    /// The split tunnel driver.
+    SplitTunnel,
+}
+
+impl Driver {
+    /// All drivers that can be diagnosed.
+    pub const ALL: [Driver; 3] = [Driver::Wintun, Driver::WireguardNt, Driver::SplitTunnel];
+}
+
+impl fmt::Display for Driver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Driver::Wintun => f.write_str("Wintun"),
+            Driver::WireguardNt => f.write_str("WireGuardNT"),
+            Driver::SplitTunnel => f.write_str("split tunnel"),
+        }
+    }
+}
+
+/// State of the service backing a kernel driver.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriverServiceState {
+    /// No service is registered for the driver.
+    NotInstalled,
+    /// The service has been marked for deletion, but is still loaded.
+    MarkedForDeletion,
+    Stopped,
+    StartPending,
+    StopPending,
+    Running,
+    ContinuePending,
+    PausePending,
+    Paused,
+}
+
+impl fmt::Display for DriverServiceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match self {
+            DriverServiceState::NotInstalled => "not installed",
+            DriverServiceState::MarkedForDeletion => "marked for deletion",
+            DriverServiceState::Stopped => "stopped",
+            DriverServiceState::StartPending => "start pending",
+            DriverServiceState::StopPending => "stop pending",
+            DriverServiceState::Running => "running",
+            DriverServiceState::ContinuePending => "continue pending",
+            DriverServiceState::PausePending => "pause pending",
+            DriverServiceState::Paused => "paused",
+        };
+        f.write_str(state)
+    }
+}
+
+/// Result of diagnosing a kernel driver.
+#[derive(Debug, Clone)]
+pub struct DriverStatus {
+    /// The driver that was diagnosed.
+    pub driver: Driver,
+    /// State of the driver service.
+    pub state: DriverServiceState,
+    /// File version of the installed driver image, if it could be determined.
+    pub version: Option<String>,
+    /// Path to the installed driver image, if it could be determined.
+    pub image_path: Option<PathBuf>,
+}
+
+/// Result of attempting to repair a kernel driver.
+#[derive(Debug, Clone)]
+pub struct DriverRepairResult {
+    /// State of the driver after the repair.
+    pub status: DriverStatus,
+    /// If true, the driver is reinstalled only after the daemon has been restarted.
+    pub restart_required: bool,
+}
@@ -1,5 +1,6 @@
 #[cfg(target_os = "android")]
 pub mod android;
+pub mod dns;
 pub mod net;
 pub mod tunnel;
 
@@ -7,6 +8,9 @@ pub mod tunnel;
 pub mod cgroup;
 
 #[cfg(target_os = "windows")]
+pub mod drivers;
+
+#[cfg(any(target_os = "windows", target_os = "linux"))]
 pub mod split_tunnel;
 
 mod error;
@@ -74,6 +74,8 @@ impl TunnelParameters {
                 obfuscation: None,
                 entry_endpoint: None,
                 tunnel_interface: None,
+                wireguard_backend: None,
+                wireguard_worker_threads: None,
                 #[cfg(daita)]
                 daita: false,
             },
@@ -91,6 +93,8 @@ impl TunnelParameters {
                     .get_exit_endpoint()
                     .map(|_| params.connection.get_endpoint()),
                 tunnel_interface: None,
+                wireguard_backend: None,
+                wireguard_worker_threads: None,
                 #[cfg(daita)]
                 daita: params.options.daita,
             },
@@ -203,6 +207,8 @@ pub struct TunnelEndpoint {
     pub obfuscation: Option<ObfuscationEndpoint>,
     pub entry_endpoint: Option<Endpoint>,
     pub tunnel_interface: Option<String>,
+    pub wireguard_backend: Option<wireguard::Backend>,
+    pub wireguard_worker_threads: Option<u32>,
     #[cfg(daita)]
     pub daita: bool,
 }
@@ -239,6 +245,7 @@ pub enum ObfuscationType {
     #[serde(rename = "udp2tcp")]
     Udp2Tcp,
     Shadowsocks,
+    Tls,
 }
 
 impl fmt::Display for ObfuscationType {
@@ -246,6 +253,7 @@ impl fmt::Display for ObfuscationType {
         match self {
             ObfuscationType::Udp2Tcp => "Udp2Tcp".fmt(f),
             ObfuscationType::Shadowsocks => "Shadowsocks".fmt(f),
+            ObfuscationType::Tls => "TLS".fmt(f),
         }
     }
 }
@@ -267,13 +275,21 @@ impl From<&ObfuscatorConfig> for ObfuscationEndpoint {
                 },
                 ObfuscationType::Udp2Tcp,
             ),
-            ObfuscatorConfig::Shadowsocks { endpoint } => (
+            ObfuscatorConfig::Shadowsocks { endpoint, .. }
+            | ObfuscatorConfig::CustomShadowsocks { endpoint, .. } => (
                 Endpoint {
                     address: *endpoint,
                     protocol: TransportProtocol::Udp,
                 },
                 ObfuscationType::Shadowsocks,
             ),
+            ObfuscatorConfig::Tls { endpoint } => (
+                Endpoint {
+                    address: *endpoint,
+                    protocol: TransportProtocol::Tcp,
+                },
+                ObfuscationType::Tls,
+            ),
         };
 
         ObfuscationEndpoint {
@@ -318,6 +334,160 @@ impl fmt::Display for Endpoint {
     }
 }
 
+/// Outgoing traffic that the firewall should allow outside the tunnel in every tunnel state, such
+/// as traffic to a printer or to a corporate endpoint. Replies to the allowed traffic are also
+/// allowed. DNS requests are never allowed by an exception.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct FirewallException {
+    /// Host or network that traffic may be sent to.
+    pub network: IpNetwork,
+    /// Only allow traffic using this protocol. Any protocol is allowed if `None`.
+    pub protocol: Option<TransportProtocol>,
+    /// Only allow traffic to this port. Every port is allowed if `None`.
+    pub port: Option<u16>,
+}
+
+impl fmt::Display for FirewallException {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.protocol {
+            Some(protocol) => write!(f, "{protocol} to {}", self.network)?,
+            None => write!(f, "Any protocol to {}", self.network)?,
+        }
+        if let Some(port) = self.port {
+            write!(f, " port {port}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Incoming connections that the firewall should allow in every tunnel state, such as connections
+/// to an SSH server or a game server. Responses to the allowed connections are also allowed.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct AllowedIncoming {
+    pub protocol: TransportProtocol,
+    /// Local port that connections may be made to.
+    pub port: u16,
+    /// Only allow connections on the interface with this name. Any interface is allowed if
+    /// `None`.
+    pub interface: Option<String>,
+}
+
+impl fmt::Display for AllowedIncoming {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} port {}", self.protocol, self.port)?;
+        match &self.interface {
+            Some(interface) => write!(f, " on {interface}"),
+            None => write!(f, " on any interface"),
+        }
+    }
+}
+
+/// A user-defined route that is installed while the tunnel is up.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct CustomRoute {
+    /// Destination network of the route.
+    pub network: IpNetwork,
+    /// Where traffic to the network is sent.
+    pub via: RouteTarget,
+}
+
+impl fmt::Display for CustomRoute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} via {}", self.network, self.via)
+    }
+}
+
+/// Where traffic matching a [`CustomRoute`] is sent.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RouteTarget {
+    /// Send the traffic through the tunnel.
+    Tunnel,
+    /// Send the traffic around the tunnel, through the gateway of the current default route.
+    DefaultGateway,
+}
+
+impl fmt::Display for RouteTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RouteTarget::Tunnel => f.write_str("tunnel"),
+            RouteTarget::DefaultGateway => f.write_str("default gateway"),
+        }
+    }
+}
+
+/// Local network traffic that the firewall should allow outside the tunnel.
+#[derive(Debug, Default, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AllowedLan {
+    /// Block all local network traffic.
+    #[default]
+    Blocked,
+    /// Allow traffic to and from all private networks, on every interface.
+    All,
+    /// Only allow traffic that matches one of these networks.
+    Only(Vec<LanNetwork>),
+}
+
+impl AllowedLan {
+    /// Returns whether any local network traffic is allowed.
+    pub fn is_allowed(&self) -> bool {
+        !matches!(self, AllowedLan::Blocked)
+    }
+}
+
+impl From<bool> for AllowedLan {
+    fn from(allow_lan: bool) -> Self {
+        if allow_lan {
+            AllowedLan::All
+        } else {
+            AllowedLan::Blocked
+        }
+    }
+}
+
+impl fmt::Display for AllowedLan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AllowedLan::Blocked => f.write_str("blocked"),
+            AllowedLan::All => f.write_str("allowed"),
+            AllowedLan::Only(networks) => {
+                f.write_str("allowed for ")?;
+                for (i, network) in networks.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{network}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Local network traffic that is allowed when only some of it should be.
+#[derive(Debug, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
+pub struct LanNetwork {
+    /// Private network to allow traffic to and from. Every private network in
+    /// [`ALLOWED_LAN_NETS`] is allowed if `None`, as well as [`ALLOWED_LAN_MULTICAST_NETS`].
+    pub network: Option<IpNetwork>,
+    /// Only allow traffic on the interface with this name. Any interface is allowed if `None`.
+    pub interface: Option<String>,
+}
+
+impl fmt::Display for LanNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.network {
+            Some(network) => write!(f, "{network}")?,
+            None => f.write_str("all private networks")?,
+        }
+        match &self.interface {
+            Some(interface) => write!(f, " on {interface}"),
+            None => write!(f, " on any interface"),
+        }
+    }
+}
+
 /// Host that should be reachable in any tunnel state.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct AllowedEndpoint {
@@ -436,6 +606,9 @@ pub enum AllowedTunnelTraffic {
     /// Only allow communication with these two specific endpoints. The intended use case for this
     /// is while negotiating for example a PSK with both the entry & exit relays in a multihop setup.
     Two(Endpoint, Endpoint),
+    /// Only allow communication with these endpoints. This is used instead of [`Self::Two`] when
+    /// a multihop circuit has more than two relays.
+    Multiple(Vec<Endpoint>),
 }
 
 impl AllowedTunnelTraffic {
@@ -456,6 +629,15 @@ impl fmt::Display for AllowedTunnelTraffic {
                 f.write_str(", ")?;
                 endpoint2.fmt(f)
             }
+            AllowedTunnelTraffic::Multiple(endpoints) => {
+                for (i, endpoint) in endpoints.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    endpoint.fmt(f)?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -550,6 +732,13 @@ pub struct GenericTunnelOptions {
     /// Enable configuration of IPv6 on the tunnel interface, allowing IPv6 communication to be
     /// forwarded through the tunnel.
     pub enable_ipv6: bool,
+    /// Drop all IPv6 traffic at the firewall, in every tunnel state, and never configure IPv6
+    /// on the tunnel interface. Takes precedence over `enable_ipv6`.
+    #[serde(default)]
+    pub block_ipv6: bool,
+    /// Routes to install through or around the tunnel while it is up.
+    #[serde(default)]
+    pub custom_routes: Vec<CustomRoute>,
 }
 
 /// Returns a vector of IP networks representing all of the internet, 0.0.0.0/0.
@@ -562,6 +751,36 @@ pub fn all_of_the_internet() -> Vec<ipnetwork::IpNetwork> {
     ]
 }
 
+/// The NAT64 well-known prefix, `64:ff9b::/96`, defined in RFC 6052.
+pub const NAT64_WELL_KNOWN_PREFIX: Ipv6Addr = Ipv6Addr::new(0x64, 0xff9b, 0, 0, 0, 0, 0, 0);
+
+/// Returns the IPv6 address that a NAT64 gateway using the well-known prefix translates to `ip`.
+///
+/// The well-known prefix may only be used for globally reachable IPv4 addresses, so `None` is
+/// returned for private, loopback, link-local and other special-purpose addresses.
+pub fn nat64_address(ip: Ipv4Addr) -> Option<Ipv6Addr> {
+    if ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        || is_shared_address_space(ip)
+    {
+        return None;
+    }
+    let mut octets = NAT64_WELL_KNOWN_PREFIX.octets();
+    octets[12..].copy_from_slice(&ip.octets());
+    Some(Ipv6Addr::from(octets))
+}
+
+/// Returns whether `ip` belongs to the carrier-grade NAT range `100.64.0.0/10` (RFC 6598).
+fn is_shared_address_space(ip: Ipv4Addr) -> bool {
+    let octets = ip.octets();
+    octets[0] == 100 && (octets[1] & 0b1100_0000) == 0b0100_0000
+}
+
 /// Details about the hosts's connectivity.
 ///
 /// Information about the host's connectivity, such as the preesence of
@@ -632,3 +851,56 @@ impl Connectivity {
         }
     }
 }
+
+/// Mechanism used to detect whether the host is offline. The tunnel is not connected while the
+/// host is offline.
+#[cfg(not(target_os = "android"))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OfflineMonitorBackend {
+    /// Watch the routing table, and consider the host offline while there is no default route.
+    #[default]
+    Routes,
+    /// Consider the host offline while NetworkManager reports that it is not connected to a
+    /// network with a default route.
+    #[cfg(target_os = "linux")]
+    NetworkManager,
+    /// Never consider the host offline.
+    Disabled,
+}
+
+#[cfg(not(target_os = "android"))]
+impl fmt::Display for OfflineMonitorBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OfflineMonitorBackend::Routes => f.write_str("routes"),
+            #[cfg(target_os = "linux")]
+            OfflineMonitorBackend::NetworkManager => f.write_str("NetworkManager"),
+            OfflineMonitorBackend::Disabled => f.write_str("disabled"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_nat64_address() {
+        assert_eq!(
+            nat64_address(Ipv4Addr::new(192, 0, 2, 33)),
+            None,
+            "documentation addresses must not be translated"
+        );
+        assert_eq!(
+            nat64_address(Ipv4Addr::new(9, 9, 9, 9)),
+            Some("64:ff9b::909:909".parse().unwrap())
+        );
+        assert_eq!(nat64_address(Ipv4Addr::new(10, 64, 0, 1)), None);
+        assert_eq!(nat64_address(Ipv4Addr::new(100, 64, 0, 1)), None);
+        assert_eq!(
+            nat64_address(Ipv4Addr::new(100, 128, 0, 1)),
+            Some("64:ff9b::6480:1".parse().unwrap())
+        );
+    }
+}
@@ -1,25 +1,56 @@
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
+use std::{net::SocketAddr, ops::RangeInclusive, time::Duration};
 
 use super::{Endpoint, TransportProtocol};
 
 #[derive(Clone, Eq, PartialEq, Deserialize, Serialize, Debug)]
 pub enum ObfuscatorConfig {
-    Udp2Tcp { endpoint: SocketAddr },
-    Shadowsocks { endpoint: SocketAddr },
+    Udp2Tcp {
+        endpoint: SocketAddr,
+    },
+    Shadowsocks {
+        endpoint: SocketAddr,
+        /// Name of the AEAD cipher to use, e.g. "aes-256-gcm"
+        cipher: String,
+    },
+    Tls {
+        endpoint: SocketAddr,
+    },
+    /// A Shadowsocks server that is not run by Mullvad, which forwards traffic to `peer`
+    CustomShadowsocks {
+        endpoint: SocketAddr,
+        /// WireGuard endpoint of the relay
+        peer: SocketAddr,
+        password: String,
+        /// Name of the AEAD cipher to use, e.g. "aes-256-gcm"
+        cipher: String,
+    },
 }
 
 impl ObfuscatorConfig {
     pub fn get_obfuscator_endpoint(&self) -> Endpoint {
         match self {
-            ObfuscatorConfig::Udp2Tcp { endpoint } => Endpoint {
-                address: *endpoint,
-                protocol: TransportProtocol::Tcp,
-            },
-            ObfuscatorConfig::Shadowsocks { endpoint } => Endpoint {
+            ObfuscatorConfig::Udp2Tcp { endpoint } | ObfuscatorConfig::Tls { endpoint } => {
+                Endpoint {
+                    address: *endpoint,
+                    protocol: TransportProtocol::Tcp,
+                }
+            }
+            ObfuscatorConfig::Shadowsocks { endpoint, .. }
+            | ObfuscatorConfig::CustomShadowsocks { endpoint, .. } => Endpoint {
                 address: *endpoint,
                 protocol: TransportProtocol::Udp,
             },
         }
     }
 }
+
+/// Periodically moves the tunnel to another port on the same relay, so that no single flow lives
+/// for very long.
+#[derive(Clone, Eq, PartialEq, Deserialize, Serialize, Debug)]
+pub struct PortHoppingConfig {
+    /// Time to stay on a port before moving to another one
+    pub interval: Duration,
+    /// Ports that the relay accepts WireGuard traffic on
+    pub port_ranges: Vec<RangeInclusive<u16>>,
+}
@@ -1,6 +1,6 @@
 use crate::net::Endpoint;
 use serde::{Deserialize, Serialize};
-use std::{fmt, net::SocketAddr};
+use std::{fmt, net::SocketAddr, str::FromStr};
 
 use super::TransportProtocol;
 
@@ -9,6 +9,9 @@ pub enum Error {
     /// Validation of SOCKS5 username or password failed.
     #[error("Invalid SOCKS5 authentication credentials: {0}")]
     InvalidSocksAuthValues(&'static str),
+    /// Validation of HTTP proxy username or password failed.
+    #[error("Invalid HTTP proxy authentication credentials: {0}")]
+    InvalidHttpAuthValues(&'static str),
 }
 
 /// Types of bridges that can be used to proxy a connection to a tunnel
@@ -44,6 +47,7 @@ pub enum CustomProxy {
     Shadowsocks(Shadowsocks),
     Socks5Local(Socks5Local),
     Socks5Remote(Socks5Remote),
+    Http(HttpProxy),
 }
 
 impl CustomProxy {
@@ -61,6 +65,10 @@ impl CustomProxy {
                 endpoint: Endpoint::from_socket_address(settings.endpoint, TransportProtocol::Tcp),
                 proxy_type: ProxyType::Shadowsocks,
             },
+            CustomProxy::Http(settings) => ProxyEndpoint {
+                endpoint: Endpoint::from_socket_address(settings.endpoint, TransportProtocol::Tcp),
+                proxy_type: ProxyType::Custom,
+            },
         }
     }
 }
@@ -83,6 +91,12 @@ impl From<Shadowsocks> for CustomProxy {
     }
 }
 
+impl From<HttpProxy> for CustomProxy {
+    fn from(value: HttpProxy) -> Self {
+        CustomProxy::Http(value)
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub struct Shadowsocks {
     pub endpoint: SocketAddr,
@@ -105,6 +119,117 @@ pub struct Socks5Remote {
     pub auth: Option<SocksAuth>,
 }
 
+/// An HTTP proxy which is asked to open a tunnel using the `CONNECT` method.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct HttpProxy {
+    pub endpoint: SocketAddr,
+    pub auth: Option<HttpAuth>,
+}
+
+/// Authentication schemes supported for HTTP proxies.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpAuthScheme {
+    /// Credentials are sent in plain text, as described in RFC 7617.
+    Basic,
+    /// NTLMv2 challenge-response authentication, commonly used by Windows domain proxies.
+    Ntlm,
+}
+
+impl FromStr for HttpAuthScheme {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("basic") {
+            return Ok(HttpAuthScheme::Basic);
+        }
+        if s.eq_ignore_ascii_case("ntlm") {
+            return Ok(HttpAuthScheme::Ntlm);
+        }
+        Err(Error::InvalidHttpAuthValues(
+            "Authentication scheme should be basic or ntlm",
+        ))
+    }
+}
+
+impl fmt::Display for HttpAuthScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpAuthScheme::Basic => f.write_str("Basic"),
+            HttpAuthScheme::Ntlm => f.write_str("NTLM"),
+        }
+    }
+}
+
+/// Credentials for an [`HttpProxy`].
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+pub struct HttpAuth {
+    scheme: HttpAuthScheme,
+    username: String,
+    password: String,
+}
+
+impl HttpAuth {
+    /// Validate HTTP proxy credentials. For NTLM, the username may be prefixed by a domain, as in
+    /// `DOMAIN\user`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::{HttpAuth, HttpAuthScheme};
+    ///
+    /// let basic = HttpAuth::new(HttpAuthScheme::Basic, "user".to_string(), "hunter2".to_string());
+    /// assert!(basic.is_ok());
+    ///
+    /// let ntlm = HttpAuth::new(HttpAuthScheme::Ntlm, "CORP\\user".to_string(), "hunter2".to_string());
+    /// assert!(ntlm.is_ok());
+    /// ```
+    ///
+    /// The username must not be empty, and may not contain a colon when using basic
+    /// authentication.
+    ///
+    /// ```
+    /// use talpid_types::net::proxy::{HttpAuth, HttpAuthScheme};
+    ///
+    /// let empty = HttpAuth::new(HttpAuthScheme::Ntlm, "".to_string(), "hunter2".to_string());
+    /// assert!(empty.is_err());
+    ///
+    /// let colon = HttpAuth::new(HttpAuthScheme::Basic, "us:er".to_string(), "hunter2".to_string());
+    /// assert!(colon.is_err());
+    /// ```
+    pub fn new(scheme: HttpAuthScheme, username: String, password: String) -> Result<Self, Error> {
+        if username.is_empty() {
+            return Err(Error::InvalidHttpAuthValues("Username should not be empty"));
+        }
+        if scheme == HttpAuthScheme::Basic && username.contains(':') {
+            return Err(Error::InvalidHttpAuthValues(
+                "Username should not contain ':' when using basic authentication",
+            ));
+        }
+
+        Ok(HttpAuth {
+            scheme,
+            username,
+            password,
+        })
+    }
+
+    /// Read the authentication scheme.
+    pub fn scheme(&self) -> HttpAuthScheme {
+        self.scheme
+    }
+
+    /// Read the username.
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    /// Read the password.
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+}
+
 /// A valid SOCKS5 username/password authentication according to
 /// RFC 1929: <https://datatracker.ietf.org/doc/html/rfc1929>.
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -233,6 +358,25 @@ impl Socks5Remote {
     }
 }
 
+impl HttpProxy {
+    pub fn new<I: Into<SocketAddr>>(endpoint: I) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            auth: None,
+        }
+    }
+
+    pub fn new_with_authentication<I: Into<SocketAddr>>(
+        endpoint: I,
+        authentication: HttpAuth,
+    ) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            auth: Some(authentication),
+        }
+    }
+}
+
 /// List of ciphers usable by a Shadowsocks proxy.
 pub const SHADOWSOCKS_CIPHERS: [&str; 19] = [
     // Stream ciphers.
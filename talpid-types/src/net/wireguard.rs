@@ -6,6 +6,8 @@ use std::{
     cmp, fmt,
     hash::{Hash, Hasher},
     net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str::FromStr,
+    time::Duration,
 };
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
@@ -17,6 +19,8 @@ pub struct TunnelParameters {
     pub options: TunnelOptions,
     pub generic_options: GenericTunnelOptions,
     pub obfuscation: Option<super::obfuscation::ObfuscatorConfig>,
+    /// Move the entry peer to a new port at a fixed interval
+    pub port_hopping: Option<super::obfuscation::PortHoppingConfig>,
 }
 
 impl TunnelParameters {
@@ -35,6 +39,10 @@ pub struct ConnectionConfig {
     pub tunnel: TunnelConfig,
     pub peer: PeerConfig,
     pub exit_peer: Option<PeerConfig>,
+    /// Relays between `peer` and `exit_peer`, in the order that traffic passes through them.
+    /// This is always empty unless `exit_peer` is set.
+    #[serde(default)]
+    pub middle_peers: Vec<PeerConfig>,
     /// Gateway used by the tunnel (a private address).
     pub ipv4_gateway: Ipv4Addr,
     pub ipv6_gateway: Option<Ipv6Addr>,
@@ -71,6 +79,10 @@ pub struct PeerConfig {
     /// ephemeral and living in memory only.
     #[serde(skip)]
     pub psk: Option<PresharedKey>,
+    /// Interval in seconds at which to send keepalive packets to the peer. This is set from
+    /// [`TunnelOptions::persistent_keepalive`] when the tunnel is created.
+    #[serde(skip)]
+    pub persistent_keepalive: Option<u16>,
     /// Enable constant packet sizes for `entry_peer``
     #[cfg(daita)]
     #[serde(skip)]
@@ -89,11 +101,300 @@ pub struct TunnelConfig {
 pub struct TunnelOptions {
     /// MTU for the wireguard tunnel
     pub mtu: Option<u16>,
+    /// Persistent keepalive interval in seconds, applied to every peer
+    pub persistent_keepalive: Option<u16>,
+    /// Name of the physical interface that traffic to the relay must leave through
+    #[cfg(target_os = "linux")]
+    pub outgoing_interface: Option<String>,
+    /// WireGuard implementation to use, instead of choosing one automatically
+    #[cfg(target_os = "linux")]
+    pub backend: Option<Backend>,
+    /// Number of worker threads for the userspace implementation, or `None` for the default
+    pub worker_threads: Option<u32>,
     /// Perform PQ-safe PSK exchange when connecting
     pub quantum_resistant: bool,
+    /// Key encapsulation mechanisms used for the PQ-safe PSK exchange
+    pub quantum_resistant_kem: QuantumResistantKem,
+    /// How a failed ephemeral peer negotiation is retried
+    pub negotiation_retry_policy: NegotiationRetryPolicy,
+    /// How the connectivity of the tunnel is probed
+    pub connectivity_check: ConnectivityCheckOptions,
+    /// Only use IPv6 inside the tunnel. IPv4 destinations are reached through the NAT64 gateway
+    /// of the relay.
+    pub ipv6_only: bool,
     /// Enable DAITA during tunnel config
     #[cfg(daita)]
     pub daita: bool,
+    /// Limits applied to the DAITA parameters provided by the relay
+    #[cfg(daita)]
+    pub daita_parameters: DaitaParameters,
+}
+
+/// WireGuard implementation that a tunnel runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    /// In-kernel WireGuard, configured directly by the daemon.
+    Kernel,
+    /// In-kernel WireGuard, configured through NetworkManager.
+    NetworkManager,
+    /// Userspace WireGuard.
+    Userspace,
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Kernel => f.write_str("kernel"),
+            Backend::NetworkManager => f.write_str("NetworkManager"),
+            Backend::Userspace => f.write_str("userspace"),
+        }
+    }
+}
+
+impl FromStr for Backend {
+    type Err = BackendParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kernel" => Ok(Backend::Kernel),
+            "network-manager" => Ok(Backend::NetworkManager),
+            "userspace" => Ok(Backend::Userspace),
+            _ => Err(BackendParseError),
+        }
+    }
+}
+
+/// Returned when a string is not the name of a [`Backend`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Not a valid WireGuard backend. Expected 'kernel', 'network-manager' or 'userspace'")]
+pub struct BackendParseError;
+
+/// Key encapsulation mechanisms (KEMs) that the preshared key of a quantum-resistant tunnel is
+/// derived from. When several are used, the key is only broken if every one of them is.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum QuantumResistantKem {
+    /// Combine every KEM that is supported, currently Classic McEliece and ML-KEM.
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    /// Only use Classic McEliece.
+    #[serde(rename = "classic_mceliece")]
+    ClassicMcEliece,
+    /// Only use ML-KEM.
+    #[serde(rename = "ml_kem")]
+    MlKem,
+}
+
+impl QuantumResistantKem {
+    /// Whether the Classic McEliece KEM is used.
+    pub fn uses_classic_mceliece(&self) -> bool {
+        matches!(
+            self,
+            QuantumResistantKem::Auto | QuantumResistantKem::ClassicMcEliece
+        )
+    }
+
+    /// Whether the ML-KEM KEM is used.
+    pub fn uses_ml_kem(&self) -> bool {
+        matches!(self, QuantumResistantKem::Auto | QuantumResistantKem::MlKem)
+    }
+}
+
+impl fmt::Display for QuantumResistantKem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QuantumResistantKem::Auto => f.write_str("auto"),
+            QuantumResistantKem::ClassicMcEliece => f.write_str("Classic McEliece"),
+            QuantumResistantKem::MlKem => f.write_str("ML-KEM"),
+        }
+    }
+}
+
+impl FromStr for QuantumResistantKem {
+    type Err = QuantumResistantKemParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(QuantumResistantKem::Auto),
+            "classic-mceliece" => Ok(QuantumResistantKem::ClassicMcEliece),
+            "ml-kem" => Ok(QuantumResistantKem::MlKem),
+            _ => Err(QuantumResistantKemParseError),
+        }
+    }
+}
+
+/// Returned when a string is not the name of a [`QuantumResistantKem`].
+#[derive(thiserror::Error, Debug, Clone, PartialEq, Eq)]
+#[error("Not a valid KEM. Expected 'auto', 'classic-mceliece' or 'ml-kem'")]
+pub struct QuantumResistantKemParseError;
+
+/// How negotiation of an ephemeral peer with the config service of a relay is retried when it
+/// fails, e.g. because packets are lost on the way to the relay.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NegotiationRetryPolicy {
+    /// Maximum time to wait for a connection to the config service.
+    pub connect_timeout: Duration,
+    /// Number of times that a failed negotiation is retried before the tunnel is restarted.
+    pub retries: u32,
+    /// Delay before the first retry. It is doubled for every subsequent retry.
+    pub initial_backoff: Duration,
+    /// Upper bound on the delay between two retries.
+    pub max_backoff: Duration,
+}
+
+impl Default for NegotiationRetryPolicy {
+    fn default() -> Self {
+        NegotiationRetryPolicy {
+            connect_timeout: Duration::from_secs(5),
+            retries: 2,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(8),
+        }
+    }
+}
+
+impl NegotiationRetryPolicy {
+    /// Delay before retry number `retry`, where the first retry is number 1.
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let factor = 2u32.saturating_pow(retry.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// A failed ephemeral peer negotiation that is about to be retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct NegotiationRetry {
+    /// The retry that is about to be made, starting at 1.
+    pub retry: u32,
+    /// Number of retries allowed by the [`NegotiationRetryPolicy`].
+    pub max_retries: u32,
+    /// Time until the retry is made.
+    pub delay: Duration,
+}
+
+impl fmt::Display for NegotiationRetry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Ephemeral peer negotiation failed, retrying in {} ms (retry {} of {})",
+            self.delay.as_millis(),
+            self.retry,
+            self.max_retries
+        )
+    }
+}
+
+/// How the connectivity monitor probes a tunnel that does not appear to receive any traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConnectivityCheckOptions {
+    /// Time between two pings sent through the tunnel.
+    pub ping_interval: Duration,
+    /// Number of pings that may go unanswered before the tunnel is considered broken.
+    pub failure_threshold: u32,
+    /// Address inside the tunnel that is pinged. The gateway of the relay is pinged if this is
+    /// `None`.
+    pub ping_target: Option<Ipv4Addr>,
+}
+
+impl Default for ConnectivityCheckOptions {
+    fn default() -> Self {
+        ConnectivityCheckOptions {
+            ping_interval: Duration::from_secs(3),
+            failure_threshold: 5,
+            ping_target: None,
+        }
+    }
+}
+
+impl ConnectivityCheckOptions {
+    /// Time without incoming traffic after the first ping before the tunnel is considered broken.
+    pub fn ping_timeout(&self) -> Duration {
+        self.ping_interval.saturating_mul(self.failure_threshold)
+    }
+}
+
+/// Upper bound on the number of entries in the DAITA event and action buffers.
+pub const MAX_DAITA_BUFFER_CAPACITY: u32 = 1 << 16;
+
+/// Limits that the user places on DAITA, on top of the parameters that the relay provides.
+/// Lower limits reduce the bandwidth overhead of DAITA, at the cost of weaker protection
+/// against traffic analysis.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DaitaParameters {
+    /// Maximum share of the traffic that may be padding, in percent. The limit of the relay is
+    /// used if it is lower.
+    pub max_padding_percent: Option<u8>,
+    /// Maximum share of the time that outgoing traffic may be blocked, in percent. The limit of
+    /// the relay is used if it is lower.
+    pub max_blocking_percent: Option<u8>,
+    /// Indices of the machines provided by the relay that should be run. Every machine is run if
+    /// this is `None`.
+    pub machines: Option<Vec<u32>>,
+    /// Number of events that can be buffered before they are handled by the machines.
+    pub events_capacity: Option<u32>,
+    /// Number of actions that can be buffered before they are handled by the tunnel.
+    pub actions_capacity: Option<u32>,
+}
+
+impl DaitaParameters {
+    /// Check that every limit is within its valid range.
+    pub fn validate(&self) -> Result<(), DaitaParametersError> {
+        let valid_percent = |percent: Option<u8>| percent.is_none_or(|p| (1..=100).contains(&p));
+        let valid_capacity = |capacity: Option<u32>| {
+            capacity.is_none_or(|c| (1..=MAX_DAITA_BUFFER_CAPACITY).contains(&c))
+        };
+
+        if !valid_percent(self.max_padding_percent) {
+            return Err(DaitaParametersError::InvalidMaxPadding);
+        }
+        if !valid_percent(self.max_blocking_percent) {
+            return Err(DaitaParametersError::InvalidMaxBlocking);
+        }
+        if self.machines.as_ref().is_some_and(Vec::is_empty) {
+            return Err(DaitaParametersError::NoMachines);
+        }
+        if !valid_capacity(self.events_capacity) || !valid_capacity(self.actions_capacity) {
+            return Err(DaitaParametersError::InvalidCapacity);
+        }
+        Ok(())
+    }
+}
+
+/// Returned when [`DaitaParameters`] contain a limit that is out of range.
+#[derive(thiserror::Error, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaitaParametersError {
+    #[error("The maximum padding must be between 1 and 100 percent")]
+    InvalidMaxPadding,
+    #[error("The maximum blocking must be between 1 and 100 percent")]
+    InvalidMaxBlocking,
+    #[error("At least one DAITA machine must be selected")]
+    NoMachines,
+    #[error("DAITA buffers must hold between 1 and {MAX_DAITA_BUFFER_CAPACITY} entries")]
+    InvalidCapacity,
+}
+
+/// The tunnel runs on a different backend than the one selected in the settings, because the
+/// selected one could not be used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackendFallback {
+    pub requested: Backend,
+    pub actual: Backend,
+}
+
+impl fmt::Display for BackendFallback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "The {} WireGuard backend could not be used, fell back to {}",
+            self.requested, self.actual
+        )
+    }
 }
 
 /// Wireguard x25519 private key
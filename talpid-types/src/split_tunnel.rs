@@ -1,6 +1,12 @@
+#[cfg(target_os = "linux")]
+use serde::{Deserialize, Serialize};
+#[cfg(target_os = "linux")]
+use std::fmt;
+#[cfg(target_os = "windows")]
 use std::path::PathBuf;
 
 /// A process that is being excluded from the tunnel.
+#[cfg(target_os = "windows")]
 #[derive(Debug, Clone)]
 pub struct ExcludedProcess {
     /// Process identifier.
@@ -11,3 +17,47 @@ pub struct ExcludedProcess {
     /// not due to its path being in the config.
     pub inherited: bool,
 }
+
+/// Which traffic is sent outside the tunnel by split tunneling.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitTunnelMode {
+    /// Split processes bypass the tunnel, and all other traffic uses it.
+    #[default]
+    Exclude,
+    /// Only split processes use the tunnel, and all other traffic bypasses it.
+    IncludeOnly,
+}
+
+#[cfg(target_os = "linux")]
+impl fmt::Display for SplitTunnelMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitTunnelMode::Exclude => f.write_str("exclude"),
+            SplitTunnelMode::IncludeOnly => f.write_str("include-only"),
+        }
+    }
+}
+
+/// All processes that run as a user or group. Their traffic is split from the tunnel in the same
+/// way as the traffic of processes in the split tunnel cgroup.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SplitTunnelOwner {
+    /// Processes whose effective UID is this.
+    User(u32),
+    /// Processes whose effective GID is this.
+    Group(u32),
+}
+
+#[cfg(target_os = "linux")]
+impl fmt::Display for SplitTunnelOwner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SplitTunnelOwner::User(uid) => write!(f, "UID {uid}"),
+            SplitTunnelOwner::Group(gid) => write!(f, "GID {gid}"),
+        }
+    }
+}
@@ -1,8 +1,11 @@
-use crate::net::{IpVersion, TunnelEndpoint};
+use crate::net::{wireguard, IpVersion, TunnelEndpoint};
+use ipnetwork::IpNetwork;
 use serde::{Deserialize, Serialize};
-use std::fmt;
-#[cfg(target_os = "android")]
-use std::net::IpAddr;
+use std::{
+    fmt,
+    net::{IpAddr, SocketAddr},
+    time::{Duration, SystemTime},
+};
 
 /// Event emitted from the states in `talpid_core::tunnel_state_machine` when the tunnel state
 /// machine enters a new state.
@@ -20,13 +23,124 @@ pub enum TunnelStateTransition {
     /// Network is secured but tunnel is still connecting.
     Connecting(TunnelEndpoint),
     /// Tunnel is connected.
-    Connected(TunnelEndpoint),
+    Connected(TunnelEndpoint, ConnectionTimings),
     /// Disconnecting tunnel.
     Disconnecting(ActionAfterDisconnect),
+    /// Tunnel is paused by the user. No tunnel is running, but all connections are still blocked.
+    Paused,
     /// Tunnel is disconnected but usually secured by blocking all connections.
     Error(ErrorState),
 }
 
+/// Time spent in each phase of establishing a tunnel. Phases that were not part of the connection
+/// attempt are `None`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ConnectionTimings {
+    /// Generating tunnel parameters, which includes selecting a relay.
+    pub relay_selection: Option<Duration>,
+    /// Starting the local obfuscation proxy.
+    pub obfuscation: Option<Duration>,
+    /// Negotiating an ephemeral peer, for quantum resistance or DAITA.
+    pub ephemeral_peer: Option<Duration>,
+    /// Waiting for traffic to pass through the tunnel after it was configured.
+    pub handshake: Option<Duration>,
+    /// Applying the firewall policy and DNS config of the connected state.
+    pub firewall_and_dns: Option<Duration>,
+    /// Time from when the attempt started until the tunnel was connected.
+    pub total: Option<Duration>,
+}
+
+impl fmt::Display for ConnectionTimings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phases = [
+            ("relay selection", self.relay_selection),
+            ("obfuscation", self.obfuscation),
+            ("ephemeral peer", self.ephemeral_peer),
+            ("handshake", self.handshake),
+            ("firewall and DNS", self.firewall_and_dns),
+            ("total", self.total),
+        ];
+        let mut first = true;
+        for (name, duration) in phases {
+            let Some(duration) = duration else {
+                continue;
+            };
+            if !first {
+                write!(f, ", ")?;
+            }
+            write!(f, "{name}: {} ms", duration.as_millis())?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+/// Throughput of a tunnel over one sampling interval.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TransferRate {
+    /// Bytes sent per second.
+    pub tx_bytes_per_second: u64,
+    /// Bytes received per second.
+    pub rx_bytes_per_second: u64,
+}
+
+/// Traffic sent and received through the current tunnel.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TrafficStats {
+    /// Total number of bytes sent since the tunnel was created.
+    pub tx_bytes: u64,
+    /// Total number of bytes received since the tunnel was created.
+    pub rx_bytes: u64,
+    /// Throughput over the most recent sampling interval.
+    pub rate: TransferRate,
+    /// Throughput over the most recent sampling intervals, oldest first.
+    pub history: Vec<TransferRate>,
+    /// Time of the most recent handshake with the exit peer, if any.
+    pub last_handshake: Option<SystemTime>,
+    /// Address that the exit peer is currently reached at.
+    pub endpoint: Option<SocketAddr>,
+}
+
+/// The configuration that the tunnel state machine has applied, for diagnosing connections that
+/// are up but do not work.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct TunnelDiagnostics {
+    /// Name of the current tunnel state, e.g. "connected".
+    pub state: String,
+    /// Name of the tunnel interface, while a tunnel is up.
+    pub interface: Option<String>,
+    /// Addresses of the tunnel interface.
+    pub tunnel_addresses: Vec<IpAddr>,
+    /// Parameters of the WireGuard tunnel, including the private key.
+    pub wireguard: Option<wireguard::ConnectionConfig>,
+    /// Destinations that are routed through the tunnel.
+    pub tunnel_routes: Vec<IpNetwork>,
+    /// Summary of the firewall policy that is enforced, if any.
+    pub firewall_policy: Option<String>,
+    /// DNS servers that the system is configured to use while connected.
+    pub dns_servers: Vec<IpAddr>,
+}
+
+impl fmt::Display for TransferRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sent: {} B/s, received: {} B/s",
+            self.tx_bytes_per_second, self.rx_bytes_per_second
+        )
+    }
+}
+
+impl fmt::Display for TrafficStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "sent: {} bytes, received: {} bytes",
+            self.tx_bytes, self.rx_bytes
+        )
+    }
+}
+
 /// Action that will be taken after disconnection is complete.
 #[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -34,6 +148,7 @@ pub enum ActionAfterDisconnect {
     Nothing,
     Block,
     Reconnect,
+    Pause,
 }
 
 /// Represents the tunnel state machine entering an error state during a [`TunnelStateTransition`].
@@ -92,6 +207,8 @@ pub enum ErrorStateCause {
     CreateTunnelDevice { os_error: Option<i32> },
     /// Failed to start connection to remote server.
     StartTunnelError,
+    /// The relay stopped completing handshakes while traffic was sent through the tunnel.
+    RelayNotResponding,
     /// Tunnel parameter generation failure
     TunnelParameterError(ParameterGenerationError),
     /// This device is offline, no tunnels can be established.
@@ -108,6 +225,13 @@ pub enum ErrorStateCause {
     /// Missing permissions required by macOS split tunneling.
     #[cfg(target_os = "macos")]
     NeedFullDiskPermissions,
+    /// The user has chosen to block all traffic, regardless of whether a tunnel is wanted.
+    #[cfg(not(target_os = "android"))]
+    BlockedByUser,
+    /// The network has a captive portal, which must be logged in to before any tunnel can be
+    /// established.
+    #[cfg(not(target_os = "android"))]
+    CaptivePortal { login_url: String },
 }
 
 impl ErrorStateCause {
@@ -198,6 +322,7 @@ impl fmt::Display for ErrorStateCause {
                 );
             }
             StartTunnelError => "Failed to start connection to remote server",
+            RelayNotResponding => "The relay stopped responding",
             #[cfg(target_os = "windows")]
             CreateTunnelDevice {
                 os_error: Some(error),
@@ -214,6 +339,12 @@ impl fmt::Display for ErrorStateCause {
             SplitTunnelError => "The split tunneling module reported an error",
             #[cfg(target_os = "macos")]
             NeedFullDiskPermissions => "Need full disk access to enable split tunneling",
+            #[cfg(not(target_os = "android"))]
+            BlockedByUser => "All traffic is blocked by the user",
+            #[cfg(not(target_os = "android"))]
+            CaptivePortal { login_url } => {
+                return write!(f, "The network requires logging in at {login_url}");
+            }
             #[cfg(target_os = "android")]
             NotPrepared => "This device is not prepared",
             #[cfg(target_os = "android")]
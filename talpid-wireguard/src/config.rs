@@ -1,10 +1,14 @@
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+use std::net::SocketAddr;
 use std::{
     borrow::Cow,
     ffi::CString,
-    net::{Ipv4Addr, Ipv6Addr},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
 };
 use talpid_types::net::wireguard::{PeerConfig, PrivateKey};
 use talpid_types::net::{obfuscation::ObfuscatorConfig, wireguard, GenericTunnelOptions};
+#[cfg(not(target_os = "android"))]
+use talpid_types::net::{CustomRoute, RouteTarget};
 
 /// Name to use for the tunnel device
 #[cfg(target_os = "linux")]
@@ -19,10 +23,15 @@ pub struct Config {
     pub entry_peer: wireguard::PeerConfig,
     /// Multihop exit peer
     pub exit_peer: Option<wireguard::PeerConfig>,
+    /// Relays between the entry and exit peer, in the order that traffic passes through them
+    pub middle_peers: Vec<wireguard::PeerConfig>,
     /// IPv4 gateway
     pub ipv4_gateway: Ipv4Addr,
     /// IPv6 gateway
     pub ipv6_gateway: Option<Ipv6Addr>,
+    /// Only IPv6 is used inside the tunnel. IPv4 hosts are reached through the NAT64 gateway of
+    /// the relay
+    pub ipv6_only: bool,
     /// Maximum transmission unit for the tunnel
     pub mtu: u16,
     /// Firewall mark
@@ -31,12 +40,40 @@ pub struct Config {
     /// Enable IPv6 routing rules
     #[cfg(target_os = "linux")]
     pub enable_ipv6: bool,
+    /// DNS servers to configure on the tunnel interface. This is only used when the interface is
+    /// managed by NetworkManager.
+    #[cfg(target_os = "linux")]
+    pub dns_servers: Vec<IpAddr>,
+    /// Physical interface that traffic to the relay is routed through, instead of the default
+    /// route
+    #[cfg(target_os = "linux")]
+    pub outgoing_interface: Option<String>,
+    /// WireGuard implementation selected by the user, if any
+    #[cfg(target_os = "linux")]
+    pub backend: Option<wireguard::Backend>,
+    /// Number of packet processing threads to use if the tunnel runs in userspace
+    pub worker_threads: Option<u32>,
     /// Obfuscator config to be used for reaching the relay.
     pub obfuscator_config: Option<ObfuscatorConfig>,
+    /// Endpoint that userspace WireGuard reaches over TCP by itself, instead of going through a
+    /// local udp2tcp obfuscator
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    pub tcp_endpoint: Option<SocketAddr>,
     /// Enable quantum-resistant PSK exchange
     pub quantum_resistant: bool,
+    /// KEMs used by the quantum-resistant PSK exchange
+    pub quantum_resistant_kem: wireguard::QuantumResistantKem,
+    /// Timeouts and retries of the ephemeral peer negotiation
+    pub negotiation_retry_policy: wireguard::NegotiationRetryPolicy,
+    /// Cadence and target of the connectivity monitor pings
+    pub connectivity_check: wireguard::ConnectivityCheckOptions,
     /// Enable DAITA
     pub daita: bool,
+    /// Limits applied to the DAITA parameters provided by the relay
+    pub daita_parameters: wireguard::DaitaParameters,
+    /// User-defined routes through or around the tunnel
+    #[cfg(not(target_os = "android"))]
+    pub custom_routes: Vec<CustomRoute>,
 }
 
 /// Configuration errors
@@ -49,6 +86,15 @@ pub enum Error {
     /// Peer has no valid IPs
     #[error("Supplied peer has no valid IPs")]
     InvalidPeerIpError,
+
+    /// An IPv6-only tunnel was requested, but there is no IPv6 tunnel IP or gateway
+    #[error("IPv6-only tunnel requires an IPv6 tunnel IP and gateway")]
+    NoIpv6TunnelIpError,
+
+    /// Middle relays were requested, but the multihop tunnel only has room for two hops
+    #[cfg(target_os = "android")]
+    #[error("Multihop with more than two relays is not supported")]
+    MiddlePeersNotSupported,
 }
 
 impl Config {
@@ -81,41 +127,97 @@ impl Config {
         if tunnel.addresses.is_empty() {
             return Err(Error::InvalidTunnelIpError);
         }
-        tunnel
-            .addresses
-            .retain(|ip| ip.is_ipv4() || generic_options.enable_ipv6);
+        #[cfg(target_os = "android")]
+        if !connection.middle_peers.is_empty() {
+            return Err(Error::MiddlePeersNotSupported);
+        }
+        // Blocking IPv6 takes precedence over every other IPv6 option
+        let ipv6_only = wg_options.ipv6_only && !generic_options.block_ipv6;
+        let enable_ipv6 = (generic_options.enable_ipv6 || ipv6_only) && !generic_options.block_ipv6;
+        tunnel.addresses.retain(|ip| {
+            if ipv6_only {
+                ip.is_ipv6()
+            } else {
+                ip.is_ipv4() || enable_ipv6
+            }
+        });
+
+        let ipv6_gateway = connection.ipv6_gateway.filter(|_opt| enable_ipv6);
 
-        let ipv6_gateway = connection
-            .ipv6_gateway
-            .filter(|_opt| generic_options.enable_ipv6);
+        if ipv6_only && (tunnel.addresses.is_empty() || ipv6_gateway.is_none()) {
+            return Err(Error::NoIpv6TunnelIpError);
+        }
 
         let mut config = Config {
             tunnel,
             entry_peer: connection.peer.clone(),
             exit_peer: connection.exit_peer.clone(),
+            middle_peers: connection.middle_peers.clone(),
             ipv4_gateway: connection.ipv4_gateway,
             ipv6_gateway,
+            ipv6_only,
             mtu,
             #[cfg(target_os = "linux")]
             fwmark: connection.fwmark,
             #[cfg(target_os = "linux")]
-            enable_ipv6: generic_options.enable_ipv6,
+            enable_ipv6,
+            #[cfg(target_os = "linux")]
+            dns_servers: std::iter::once(IpAddr::from(connection.ipv4_gateway))
+                .filter(|_| !ipv6_only)
+                .chain(ipv6_gateway.map(IpAddr::from))
+                .collect(),
+            #[cfg(target_os = "linux")]
+            outgoing_interface: wg_options.outgoing_interface.clone(),
+            #[cfg(target_os = "linux")]
+            backend: wg_options.backend,
+            worker_threads: wg_options.worker_threads,
             obfuscator_config: obfuscator_config.to_owned(),
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            tcp_endpoint: None,
             quantum_resistant: wg_options.quantum_resistant,
+            quantum_resistant_kem: wg_options.quantum_resistant_kem,
+            negotiation_retry_policy: wg_options.negotiation_retry_policy,
+            connectivity_check: wg_options.connectivity_check,
             #[cfg(daita)]
             daita: wg_options.daita,
             #[cfg(not(daita))]
             daita: false,
+            #[cfg(daita)]
+            daita_parameters: wg_options.daita_parameters.clone(),
+            #[cfg(not(daita))]
+            daita_parameters: Default::default(),
+            // Routes through the tunnel are only kept for address families that it carries
+            #[cfg(not(target_os = "android"))]
+            custom_routes: generic_options
+                .custom_routes
+                .iter()
+                .filter(|route| match (route.via, route.network.is_ipv4()) {
+                    (RouteTarget::DefaultGateway, _) => true,
+                    (RouteTarget::Tunnel, true) => !ipv6_only,
+                    (RouteTarget::Tunnel, false) => enable_ipv6,
+                })
+                .copied()
+                .collect(),
         };
 
         for peer in config.peers_mut() {
-            peer.allowed_ips
-                .retain(|ip| ip.is_ipv4() || generic_options.enable_ipv6);
+            peer.persistent_keepalive = wg_options.persistent_keepalive;
+            peer.allowed_ips.retain(|ip| ip.is_ipv4() || enable_ipv6);
             if peer.allowed_ips.is_empty() {
                 return Err(Error::InvalidPeerIpError);
             }
         }
 
+        // Only the exit peer carries traffic to the internet. The allowed IPs of the other peers
+        // are relay addresses, which may still be IPv4
+        if ipv6_only {
+            let exit_peer = config.exit_peer_mut();
+            exit_peer.allowed_ips.retain(|ip| ip.is_ipv6());
+            if exit_peer.allowed_ips.is_empty() {
+                return Err(Error::InvalidPeerIpError);
+            }
+        }
+
         Ok(config)
     }
 
@@ -135,6 +237,15 @@ impl Config {
         self.exit_peer.is_some()
     }
 
+    /// Return the gateway of the relay that is reached inside the tunnel. This is the IPv6 gateway
+    /// if the tunnel is IPv6-only.
+    pub fn in_tunnel_gateway(&self) -> IpAddr {
+        match self.ipv6_gateway {
+            Some(gateway) if self.ipv6_only => gateway.into(),
+            _ => self.ipv4_gateway.into(),
+        }
+    }
+
     /// Return the exit peer. `exit_peer` if it is set, otherwise `entry_peer`.
     pub fn exit_peer(&self) -> &wireguard::PeerConfig {
         self.exit_peer.as_ref().unwrap_or(&self.entry_peer)
@@ -150,6 +261,7 @@ impl Config {
         self.exit_peer
             .as_ref()
             .into_iter()
+            .chain(self.middle_peers.iter())
             .chain(std::iter::once(&self.entry_peer))
     }
 
@@ -158,6 +270,7 @@ impl Config {
         self.exit_peer
             .as_mut()
             .into_iter()
+            .chain(self.middle_peers.iter_mut())
             .chain(std::iter::once(&mut self.entry_peer))
     }
 
@@ -254,6 +367,12 @@ fn write_peer_to_config(wg_conf: &mut WgConfigBuffer, peer: &PeerConfig) {
     if let Some(ref psk) = peer.psk {
         wg_conf.add::<&[u8]>("preshared_key", psk.as_bytes().as_ref());
     }
+    if let Some(interval) = peer.persistent_keepalive {
+        wg_conf.add(
+            "persistent_keepalive_interval",
+            interval.to_string().as_str(),
+        );
+    }
     for addr in &peer.allowed_ips {
         wg_conf.add("allowed_ip", addr.to_string().as_str());
     }
@@ -1,7 +1,8 @@
-use std::net::Ipv4Addr;
+use std::net::IpAddr;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
+use talpid_types::net::{nat64_address, wireguard::ConnectivityCheckOptions};
 use tokio::sync::broadcast;
 use tokio::time::Instant;
 
@@ -28,18 +29,25 @@ use pinger::Pinger;
 /// timeout. A connection is considered to be established the first time an increase in incoming
 /// traffic is observed.
 ///
-/// The connectivity monitor will start sending pings and start the countdown to `PING_TIMEOUT` in
-/// the following cases:
+/// The connectivity monitor will start sending pings and start the countdown to the ping timeout
+/// in the following cases:
 /// - In case that we have observed a bump in the outgoing traffic but no corresponding incoming
 ///   traffic for longer than `BYTES_RX_TIMEOUT`, then the monitor will start pinging.
 /// - In case that no increase in outgoing or incoming traffic has been observed for longer than
 ///   `TRAFFIC_TIMEOUT`, then the monitor will start pinging as well.
 ///
 /// Once a connection established, a connection is only considered broken once the connectivity
-/// monitor has started pinging and no traffic has been received for a duration of `PING_TIMEOUT`.
+/// monitor has started pinging and no traffic has been received for the ping timeout, which is
+/// `PING_TIMEOUT` unless [`ConnectivityCheckOptions`] say otherwise.
+///
+/// An established connection is also considered broken if traffic is sent but no handshake has
+/// completed for `HANDSHAKE_TIMEOUT` after the keys of the last one expired. This is reported
+/// as [`Error::HandshakeTimeout`].
 pub struct Check {
     conn_state: ConnState,
     ping_state: PingState,
+    handshake_state: HandshakeState,
+    ping_timeout: Duration,
     cancel_receiver: CancelReceiver,
     retry_attempt: u32,
 }
@@ -95,24 +103,44 @@ impl CancelToken {
 }
 
 impl Check {
+    /// Create a check that pings `options.ping_target` through the tunnel, or `gateway` if no
+    /// target is set. If `gateway` is an IPv6 address, the target is reached through NAT64.
     pub fn new(
-        addr: Ipv4Addr,
+        gateway: IpAddr,
         #[cfg(any(target_os = "macos", target_os = "linux"))] interface: String,
         retry_attempt: u32,
+        options: ConnectivityCheckOptions,
         cancel_receiver: CancelReceiver,
     ) -> Result<Check, Error> {
         Ok(Check {
             conn_state: ConnState::new(Instant::now(), Default::default()),
             ping_state: PingState::new(
-                addr,
+                Self::ping_target(gateway, &options),
                 #[cfg(any(target_os = "macos", target_os = "linux"))]
                 interface,
+                options.ping_interval,
             )?,
+            handshake_state: HandshakeState::default(),
+            ping_timeout: options.ping_timeout(),
             retry_attempt,
             cancel_receiver,
         })
     }
 
+    fn ping_target(gateway: IpAddr, options: &ConnectivityCheckOptions) -> IpAddr {
+        match (gateway, options.ping_target) {
+            (_, None) => gateway,
+            (IpAddr::V4(_), Some(target)) => target.into(),
+            (IpAddr::V6(_), Some(target)) => match nat64_address(target) {
+                Some(translated) => translated.into(),
+                None => {
+                    log::warn!("Cannot reach ping target {target} through NAT64, pinging gateway");
+                    gateway
+                }
+            },
+        }
+    }
+
     #[cfg(test)]
     /// Create a new [Check] with a custom initial state.
     pub(super) fn mock(conn_state: ConnState, ping_state: PingState) -> (Self, CancelToken) {
@@ -121,6 +149,8 @@ impl Check {
             Check {
                 conn_state,
                 ping_state,
+                handshake_state: HandshakeState::default(),
+                ping_timeout: PING_TIMEOUT,
                 retry_attempt: 0,
                 cancel_receiver,
             },
@@ -140,18 +170,24 @@ impl Check {
             .send_icmp()
             .await
             .map_err(Error::PingError)?;
-        self.establish_connectivity_inner(
-            self.retry_attempt,
-            ESTABLISH_TIMEOUT,
-            ESTABLISH_TIMEOUT_MULTIPLIER,
-            MAX_ESTABLISH_TIMEOUT,
-            tunnel_handle,
-        )
-        .await
+        let connected = self
+            .establish_connectivity_inner(
+                self.retry_attempt,
+                ESTABLISH_TIMEOUT,
+                ESTABLISH_TIMEOUT_MULTIPLIER,
+                MAX_ESTABLISH_TIMEOUT,
+                tunnel_handle,
+            )
+            .await?;
+        if !connected && !self.should_shut_down() {
+            Self::log_peer_status(tunnel_handle).await;
+        }
+        Ok(connected)
     }
 
     pub(crate) async fn reset(&mut self, current_iteration: Instant) {
         self.ping_state.reset().await;
+        self.handshake_state = HandshakeState::default();
         self.conn_state.reset_after_suspension(current_iteration);
     }
 
@@ -228,14 +264,18 @@ impl Check {
         now: Instant,
         tunnel_handle: &TunnelType,
     ) -> Result<bool, Error> {
-        Self::check_connectivity_interval(
+        let connected = Self::check_connectivity_interval(
             &mut self.conn_state,
             &mut self.ping_state,
             now,
-            PING_TIMEOUT,
+            self.ping_timeout,
             tunnel_handle,
         )
-        .await
+        .await?;
+        if connected && self.handshake_state.timed_out(now, &self.conn_state) {
+            return Err(Error::HandshakeTimeout(HANDSHAKE_TIMEOUT));
+        }
+        Ok(connected)
     }
 
     /// Returns true if connection is established
@@ -275,6 +315,27 @@ impl Check {
         }
     }
 
+    /// Log when each peer last completed a handshake, to tell a failing handshake apart from a
+    /// tunnel that does not pass traffic.
+    async fn log_peer_status(tunnel_handle: &TunnelType) {
+        let Ok(Some(stats)) = Self::get_stats(tunnel_handle).await else {
+            return;
+        };
+        for peer in stats.values() {
+            let endpoint = peer
+                .endpoint
+                .map(|endpoint| endpoint.to_string())
+                .unwrap_or_else(|| "unknown endpoint".to_owned());
+            match peer.last_handshake_age() {
+                Some(age) => log::warn!(
+                    "Last handshake with {endpoint} was {} seconds ago",
+                    age.as_secs()
+                ),
+                None => log::warn!("No handshake has been completed with {endpoint}"),
+            }
+        }
+    }
+
     async fn maybe_send_ping(
         conn_state: &mut ConnState,
         ping_state: &mut PingState,
@@ -282,12 +343,13 @@ impl Check {
     ) -> Result<(), Error> {
         // Only send out a ping if we haven't received a byte in a while or no traffic has flowed
         // in the last 2 minutes, but if a ping already has been sent out, only send one out every
-        // 3 seconds.
+        // ping interval.
         if (conn_state.rx_timed_out() || conn_state.traffic_timed_out())
             && ping_state
                 .initial_ping_timestamp
                 .map(|initial_ping_timestamp| {
-                    initial_ping_timestamp.elapsed() / ping_state.num_pings_sent < SECONDS_PER_PING
+                    initial_ping_timestamp.elapsed() / ping_state.num_pings_sent
+                        < ping_state.interval
                 })
                 .unwrap_or(true)
         {
@@ -305,16 +367,56 @@ impl Check {
     }
 }
 
+/// Tracks whether the relay completes handshakes while traffic is sent to it.
+#[derive(Debug, Default)]
+struct HandshakeState {
+    /// When the keys were first seen expired while traffic was being sent
+    stale_since: Option<Instant>,
+}
+
+impl HandshakeState {
+    /// Returns true if traffic has been sent for `HANDSHAKE_TIMEOUT` without any handshake
+    /// replacing the expired keys of a peer.
+    fn timed_out(&mut self, now: Instant, conn_state: &ConnState) -> bool {
+        let ConnState::Connected {
+            tx_timestamp,
+            stats,
+            ..
+        } = conn_state
+        else {
+            self.stale_since = None;
+            return false;
+        };
+
+        // An idle tunnel does not perform any handshakes, so expired keys are only a problem if
+        // traffic is being sent.
+        let sending = now.saturating_duration_since(*tx_timestamp) < BYTES_RX_TIMEOUT;
+        let keys_expired = stats.values().any(|peer| {
+            peer.last_handshake_age()
+                .is_some_and(|age| age >= REJECT_AFTER_TIME)
+        });
+        if !(sending && keys_expired) {
+            self.stale_since = None;
+            return false;
+        }
+
+        let stale_since = *self.stale_since.get_or_insert(now);
+        now.saturating_duration_since(stale_since) >= HANDSHAKE_TIMEOUT
+    }
+}
+
 pub(super) struct PingState {
     initial_ping_timestamp: Option<Instant>,
     num_pings_sent: u32,
+    interval: Duration,
     pinger: Box<dyn Pinger>,
 }
 
 impl PingState {
     pub(super) fn new(
-        addr: Ipv4Addr,
+        addr: IpAddr,
         #[cfg(any(target_os = "macos", target_os = "linux"))] interface: String,
+        interval: Duration,
     ) -> Result<Self, Error> {
         let pinger = pinger::new_pinger(
             addr,
@@ -323,13 +425,17 @@ impl PingState {
         )
         .map_err(Error::PingError)?;
 
-        Ok(Self::new_with(pinger))
+        Ok(Self {
+            interval,
+            ..Self::new_with(pinger)
+        })
     }
 
     pub(super) fn new_with(pinger: Box<dyn Pinger>) -> Self {
         Self {
             initial_ping_timestamp: None,
             num_pings_sent: 0,
+            interval: SECONDS_PER_PING,
             pinger,
         }
     }
@@ -517,6 +623,7 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 0,
+                ..Default::default()
             },
         );
         conn_state.update(Instant::now(), stats);
@@ -542,6 +649,7 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 0,
+                ..Default::default()
             },
         );
         conn_state.update(connect_time, stats);
@@ -566,6 +674,7 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 0,
+                ..Default::default()
             },
         );
         conn_state.update(start, stats);
@@ -577,6 +686,7 @@ mod test {
             Stats {
                 rx_bytes: 1,
                 tx_bytes: 1,
+                ..Default::default()
             },
         );
         conn_state.update(update_time, stats);
@@ -607,6 +717,68 @@ mod test {
         assert!(!checker.check_connectivity(now, &tunnel).await.unwrap())
     }
 
+    /// Test that the relay is only considered unresponsive if traffic is sent for
+    /// `HANDSHAKE_TIMEOUT` after the keys have expired
+    #[test]
+    fn test_handshake_timeout() {
+        let start = Instant::now();
+        let connected_at = |tx_timestamp: Instant, handshake_age: Duration| {
+            let mut stats = StatsMap::new();
+            stats.insert(
+                [0u8; 32],
+                Stats {
+                    last_handshake: std::time::SystemTime::now().checked_sub(handshake_age),
+                    ..Default::default()
+                },
+            );
+            ConnState::Connected {
+                rx_timestamp: start,
+                tx_timestamp,
+                stats,
+            }
+        };
+        let expired = REJECT_AFTER_TIME + Duration::from_secs(1);
+        let later = start + HANDSHAKE_TIMEOUT;
+
+        // An idle tunnel may have expired keys
+        let mut handshake_state = HandshakeState::default();
+        assert!(!handshake_state.timed_out(start, &connected_at(start, expired)));
+        assert!(!handshake_state.timed_out(later, &connected_at(start, expired)));
+
+        // A fresh handshake resets the timeout
+        let mut handshake_state = HandshakeState::default();
+        assert!(!handshake_state.timed_out(start, &connected_at(start, expired)));
+        assert!(!handshake_state.timed_out(later, &connected_at(later, Duration::ZERO)));
+        assert!(!handshake_state.timed_out(later, &connected_at(later, expired)));
+
+        let mut handshake_state = HandshakeState::default();
+        assert!(!handshake_state.timed_out(start, &connected_at(start, expired)));
+        assert!(handshake_state.timed_out(later, &connected_at(later, expired)));
+    }
+
+    #[tokio::test]
+    /// Verify that `check_connectivity()` uses the configured ping timeout instead of
+    /// `PING_TIMEOUT`.
+    async fn test_custom_ping_timeout() {
+        let tunnel = MockTunnel::never_incrementing().boxed();
+        let pinger = MockPinger::default();
+        let now = Instant::now();
+        let start = now
+            .checked_sub(BYTES_RX_TIMEOUT + Duration::from_secs(5))
+            .unwrap();
+        let ping_time = now.checked_sub(Duration::from_secs(5)).unwrap();
+        let (mut checker, _cancel_token) = mock_checker(start, Box::new(pinger));
+
+        checker.conn_state = connected_state(start);
+        Check::maybe_send_ping(&mut checker.conn_state, &mut checker.ping_state, ping_time)
+            .await
+            .unwrap();
+        assert!(checker.check_connectivity(now, &tunnel).await.unwrap());
+
+        checker.ping_timeout = Duration::from_secs(2);
+        assert!(!checker.check_connectivity(now, &tunnel).await.unwrap());
+    }
+
     #[tokio::test]
     /// Verify that `check_connectivity()` returns `true` if the tunnel is connected and traffic is
     /// flowing constantly.
@@ -658,6 +830,7 @@ mod test {
                     Stats {
                         tx_bytes: 0,
                         rx_bytes: 0,
+                        ..Default::default()
                     },
                 );
                 MockTunnel::new(move || Ok(tunnel_stats.clone())).boxed()
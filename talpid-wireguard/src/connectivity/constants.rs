@@ -18,3 +18,9 @@ pub(crate) const ESTABLISH_TIMEOUT_MULTIPLIER: u32 = 2;
 pub(crate) const MAX_ESTABLISH_TIMEOUT: Duration = PING_TIMEOUT;
 /// Number of seconds to wait between sending ICMP packets
 pub(crate) const SECONDS_PER_PING: Duration = Duration::from_secs(3);
+/// Age at which WireGuard stops using the keys of a handshake. Traffic can only flow if a new
+/// handshake completes before this.
+pub(crate) const REJECT_AFTER_TIME: Duration = Duration::from_secs(180);
+/// Time that outgoing traffic may go without a new handshake once the keys have expired, before
+/// the relay is assumed to have stopped responding.
+pub(crate) const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
@@ -11,4 +11,8 @@ pub enum Error {
     /// Failed to send ping
     #[error("Ping failed")]
     PingError(#[from] pinger::Error),
+
+    /// The relay has stopped completing handshakes
+    #[error("No handshake has completed within {0:?} of the keys expiring")]
+    HandshakeTimeout(std::time::Duration),
 }
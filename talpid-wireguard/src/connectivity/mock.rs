@@ -35,6 +35,7 @@ pub fn connected_state(timestamp: Instant) -> ConnState {
         Stats {
             tx_bytes: 0,
             rx_bytes: 0,
+            ..Default::default()
         },
     );
     ConnState::Connected {
@@ -65,6 +66,7 @@ impl MockTunnel {
             Stats {
                 tx_bytes: 0,
                 rx_bytes: 0,
+                ..Default::default()
             },
         );
         let peers = std::sync::Mutex::new(map);
@@ -89,6 +91,7 @@ impl MockTunnel {
                     Stats {
                         tx_bytes: 0,
                         rx_bytes: 0,
+                        ..Default::default()
                     },
                 );
                 Ok(map)
@@ -103,6 +106,10 @@ impl Tunnel for MockTunnel {
         "mock-tunnel".to_string()
     }
 
+    fn backend(&self) -> talpid_types::net::wireguard::Backend {
+        talpid_types::net::wireguard::Backend::Userspace
+    }
+
     fn stop(self: Box<Self>) -> Result<(), TunnelError> {
         Ok(())
     }
@@ -128,6 +128,7 @@ mod test {
             Stats {
                 tx_bytes: 0,
                 rx_bytes: 0,
+                ..Default::default()
             },
         );
         let tunnel_stats = std::sync::Mutex::new(map);
@@ -5,12 +5,15 @@ use tokio::net::UdpSocket;
 
 use std::{
     io::{self, Write},
-    net::{Ipv4Addr, SocketAddr},
+    net::{IpAddr, SocketAddr},
     time::Duration,
 };
 
 const SEND_RETRY_ATTEMPTS: u32 = 10;
 
+const ICMPV4_ECHO_REQUEST: u8 = 0x08;
+const ICMPV6_ECHO_REQUEST: u8 = 0x80;
+
 /// Pinger errors
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
@@ -61,18 +64,23 @@ pub struct Pinger {
 impl Pinger {
     /// Creates a new `Pinger`.
     pub fn new(
-        addr: Ipv4Addr,
+        addr: IpAddr,
         #[cfg(any(target_os = "linux", target_os = "macos"))] interface_name: String,
     ) -> Result<Self> {
-        let addr = SocketAddr::new(addr.into(), 0);
+        let addr = SocketAddr::new(addr, 0);
+        let (domain, protocol) = if addr.is_ipv6() {
+            (Domain::IPV6, Protocol::ICMPV6)
+        } else {
+            (Domain::IPV4, Protocol::ICMPV4)
+        };
         let sock = Socket::new(
-            Domain::IPV4,
+            domain,
             if cfg!(target_os = "android") {
                 Type::DGRAM
             } else {
                 Type::RAW
             },
-            Some(Protocol::ICMPV4),
+            Some(protocol),
         )
         .map_err(Error::Open)?;
         sock.set_nonblocking(true).map_err(Error::Open)?;
@@ -82,7 +90,7 @@ impl Pinger {
             .map_err(Error::SocketOp)?;
 
         #[cfg(target_os = "macos")]
-        Self::set_device_index(&sock, &interface_name)?;
+        Self::set_device_index(&sock, &interface_name, addr.is_ipv6())?;
 
         let sock =
             UdpSocket::from_std(std::net::UdpSocket::from(sock)).map_err(Error::ConvertSocket)?;
@@ -96,13 +104,17 @@ impl Pinger {
     }
 
     #[cfg(target_os = "macos")]
-    fn set_device_index(socket: &Socket, interface_name: &str) -> Result<()> {
+    fn set_device_index(socket: &Socket, interface_name: &str, ipv6: bool) -> Result<()> {
         let index = nix::net::if_::if_nametoindex(interface_name).map_err(Error::DeviceIdx)?;
         // Asserting that `index` is non-zero since otherwise `if_nametoindex` would have return
         // an error
-        socket
-            .bind_device_by_index_v4(std::num::NonZeroU32::new(index))
-            .map_err(Error::BindSocketByDevice)?;
+        let index = std::num::NonZeroU32::new(index);
+        if ipv6 {
+            socket.bind_device_by_index_v6(index)
+        } else {
+            socket.bind_device_by_index_v4(index)
+        }
+        .map_err(Error::BindSocketByDevice)?;
 
         Ok(())
     }
@@ -122,8 +134,13 @@ impl Pinger {
         }
     }
 
-    fn construct_icmpv4_packet(&mut self, buffer: &mut [u8]) -> Result<()> {
-        if !construct_icmpv4_packet_inner(buffer, self) {
+    fn construct_icmp_packet(&mut self, buffer: &mut [u8]) -> Result<()> {
+        let constructed = if self.addr.is_ipv6() {
+            construct_icmpv6_packet_inner(buffer, self)
+        } else {
+            construct_icmpv4_packet_inner(buffer, self)
+        };
+        if !constructed {
             return Err(Error::BufferTooSmall);
         }
         Ok(())
@@ -147,7 +164,7 @@ fn should_retry_send(_err: &io::Error) -> bool {
 impl super::Pinger for Pinger {
     async fn send_icmp(&mut self) -> Result<()> {
         let mut message = [0u8; 50];
-        self.construct_icmpv4_packet(&mut message)?;
+        self.construct_icmp_packet(&mut message)?;
         self.send_ping_request(&message, self.addr).await
     }
 }
@@ -179,13 +196,39 @@ fn construct_icmpv4_packet_inner(
     packet_writer: &mut impl PayloadWriter,
 ) -> bool {
     const ICMP_CHECKSUM_OFFSET: usize = 2;
+    if !construct_echo_request(buffer, packet_writer, ICMPV4_ECHO_REQUEST) {
+        return false;
+    }
+
+    let checksum = internet_checksum::checksum(buffer);
+    (&mut buffer[ICMP_CHECKSUM_OFFSET..])
+        .write_all(&checksum)
+        .unwrap();
+
+    true
+}
+
+/// The ICMPv6 checksum covers the source address, which is chosen by the kernel. The checksum is
+/// therefore left as zero for the kernel to fill in.
+fn construct_icmpv6_packet_inner(
+    buffer: &mut [u8],
+    packet_writer: &mut impl PayloadWriter,
+) -> bool {
+    construct_echo_request(buffer, packet_writer, ICMPV6_ECHO_REQUEST)
+}
+
+fn construct_echo_request(
+    buffer: &mut [u8],
+    packet_writer: &mut impl PayloadWriter,
+    icmp_type: u8,
+) -> bool {
     if buffer.len() < 14 {
         return false;
     }
 
     let mut writer = &mut buffer[..];
     // ICMP type - Echo (ping) request
-    writer.write_u8(0x08).unwrap();
+    writer.write_u8(icmp_type).unwrap();
     // Code - 0
     writer.write_u8(0x00).unwrap();
     // Checksum -filled in later
@@ -201,11 +244,6 @@ fn construct_icmpv4_packet_inner(
     // payload
     packet_writer.write_payload(writer);
 
-    let checksum = internet_checksum::checksum(buffer);
-    (&mut buffer[ICMP_CHECKSUM_OFFSET..])
-        .write_all(&checksum)
-        .unwrap();
-
     true
 }
 
@@ -265,4 +303,16 @@ mod test {
             &mut TestPayload {}
         ));
     }
+
+    #[test]
+    fn test_icmpv6_packet() {
+        let mut buffer = [0u8; 64];
+        assert!(construct_icmpv6_packet_inner(
+            &mut buffer[..],
+            &mut TestPayload {}
+        ));
+        // Echo request, code 0, checksum left to the kernel
+        assert_eq!(buffer[..4], [0x80, 0x00, 0x00, 0x00]);
+        assert_eq!(buffer[4..8], [0x1d, 0xcd, 0x00, 0x01]);
+    }
 }
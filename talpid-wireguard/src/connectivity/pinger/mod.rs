@@ -13,7 +13,7 @@ pub trait Pinger: Send {
 
 /// Create a new pinger
 pub fn new_pinger(
-    addr: std::net::Ipv4Addr,
+    addr: std::net::IpAddr,
     #[cfg(any(target_os = "linux", target_os = "macos"))] interface_name: String,
 ) -> Result<Box<dyn Pinger>, Error> {
     Ok(Box::new(icmp::Pinger::new(
@@ -8,7 +8,6 @@ use super::{config::Config, obfuscation::ObfuscatorHandle, CloseMsg, Error, Tunn
 #[cfg(target_os = "android")]
 use std::sync::Mutex;
 use std::{
-    net::IpAddr,
     sync::{mpsc as sync_mpsc, Arc},
     time::Duration,
 };
@@ -16,8 +15,9 @@ use std::{
 use talpid_tunnel::tun_provider::TunProvider;
 
 use ipnetwork::IpNetwork;
-use talpid_tunnel_config_client::EphemeralPeer;
-use talpid_types::net::wireguard::{PrivateKey, PublicKey};
+use talpid_tunnel::{EventHook, TunnelEvent};
+use talpid_tunnel_config_client::{DaitaSettings, EphemeralPeer};
+use talpid_types::net::wireguard::{DaitaParameters, PrivateKey, PublicKey};
 use tokio::sync::Mutex as AsyncMutex;
 
 const INITIAL_PSK_EXCHANGE_TIMEOUT: Duration = Duration::from_secs(8);
@@ -31,6 +31,7 @@ pub async fn config_ephemeral_peers(
     retry_attempt: u32,
     obfuscator: Arc<AsyncMutex<Option<ObfuscatorHandle>>>,
     close_obfs_sender: sync_mpsc::Sender<CloseMsg>,
+    event_hook: &EventHook,
 ) -> std::result::Result<(), CloseMsg> {
     let iface_name = {
         let tunnel = tunnel.lock().await;
@@ -44,8 +45,15 @@ pub async fn config_ephemeral_peers(
     log::trace!("Temporarily lowering tunnel MTU before ephemeral peer config");
     try_set_ipv4_mtu(&iface_name, talpid_tunnel::MIN_IPV4_MTU);
 
-    config_ephemeral_peers_inner(tunnel, config, retry_attempt, obfuscator, close_obfs_sender)
-        .await?;
+    config_ephemeral_peers_inner(
+        tunnel,
+        config,
+        retry_attempt,
+        obfuscator,
+        close_obfs_sender,
+        event_hook,
+    )
+    .await?;
 
     log::trace!("Resetting tunnel MTU");
     try_set_ipv4_mtu(&iface_name, config.mtu);
@@ -75,6 +83,7 @@ pub async fn config_ephemeral_peers(
     retry_attempt: u32,
     obfuscator: Arc<AsyncMutex<Option<ObfuscatorHandle>>>,
     close_obfs_sender: sync_mpsc::Sender<CloseMsg>,
+    event_hook: &EventHook,
     #[cfg(target_os = "android")] tun_provider: Arc<Mutex<TunProvider>>,
 ) -> Result<(), CloseMsg> {
     config_ephemeral_peers_inner(
@@ -83,6 +92,7 @@ pub async fn config_ephemeral_peers(
         retry_attempt,
         obfuscator,
         close_obfs_sender,
+        event_hook,
         #[cfg(target_os = "android")]
         tun_provider,
     )
@@ -95,6 +105,7 @@ async fn config_ephemeral_peers_inner(
     retry_attempt: u32,
     obfuscator: Arc<AsyncMutex<Option<ObfuscatorHandle>>>,
     close_obfs_sender: sync_mpsc::Sender<CloseMsg>,
+    event_hook: &EventHook,
     #[cfg(target_os = "android")] tun_provider: Arc<Mutex<TunProvider>>,
 ) -> Result<(), CloseMsg> {
     let ephemeral_private_key = PrivateKey::new_from_random();
@@ -104,6 +115,7 @@ async fn config_ephemeral_peers_inner(
     let exit_ephemeral_peer = request_ephemeral_peer(
         retry_attempt,
         config,
+        event_hook,
         ephemeral_private_key.public_key(),
         config.quantum_resistant,
         exit_should_have_daita,
@@ -114,14 +126,50 @@ async fn config_ephemeral_peers_inner(
 
     log::debug!("Retrieved ephemeral peer");
 
+    let mut middle_psks = Vec::with_capacity(config.middle_peers.len());
+
     if config.is_multihop() {
+        // Set up tunnels that end at each middle peer in turn
+        for index in 0..config.middle_peers.len() {
+            let mut middle_tun_config = config.clone();
+            let mut middle_peer = middle_tun_config.middle_peers.split_off(index).remove(0);
+            middle_peer
+                .allowed_ips
+                .push(IpNetwork::from(config.in_tunnel_gateway()));
+            middle_tun_config.exit_peer = Some(middle_peer);
+
+            let close_obfs_sender = close_obfs_sender.clone();
+            let middle_config = reconfigure_tunnel(
+                tunnel,
+                middle_tun_config,
+                obfuscator.clone(),
+                close_obfs_sender,
+                #[cfg(target_os = "android")]
+                &tun_provider,
+            )
+            .await?;
+            let middle_ephemeral_peer = request_ephemeral_peer(
+                retry_attempt,
+                &middle_config,
+                event_hook,
+                ephemeral_private_key.public_key(),
+                config.quantum_resistant,
+                false,
+            )
+            .await?;
+            log::debug!("Successfully exchanged PSK with middle peer {}", index + 1);
+
+            middle_psks.push(middle_ephemeral_peer.psk);
+        }
+
         // Set up tunnel to lead to entry
         let mut entry_tun_config = config.clone();
         entry_tun_config.exit_peer = None;
+        entry_tun_config.middle_peers.clear();
         entry_tun_config
             .entry_peer
             .allowed_ips
-            .push(IpNetwork::new(IpAddr::V4(config.ipv4_gateway), 32).unwrap());
+            .push(IpNetwork::from(config.in_tunnel_gateway()));
 
         let close_obfs_sender = close_obfs_sender.clone();
         let entry_config = reconfigure_tunnel(
@@ -136,6 +184,7 @@ async fn config_ephemeral_peers_inner(
         let entry_ephemeral_peer = request_ephemeral_peer(
             retry_attempt,
             &entry_config,
+            event_hook,
             ephemeral_private_key.public_key(),
             config.quantum_resistant,
             config.daita,
@@ -148,6 +197,9 @@ async fn config_ephemeral_peers_inner(
     }
 
     config.exit_peer_mut().psk = exit_ephemeral_peer.psk;
+    for (peer, psk) in config.middle_peers.iter_mut().zip(middle_psks) {
+        peer.psk = psk;
+    }
     if config.daita {
         log::trace!("Enabling constant packet size for entry peer");
         config.entry_peer.constant_packet_size = true;
@@ -169,6 +221,7 @@ async fn config_ephemeral_peers_inner(
         let Some(daita) = daita else {
             unreachable!("missing DAITA settings");
         };
+        let daita = limit_daita_settings(daita, &config.daita_parameters);
 
         // Start local DAITA machines
         let mut tunnel = tunnel.lock().await;
@@ -257,6 +310,7 @@ async fn reconfigure_tunnel(
 async fn request_ephemeral_peer(
     retry_attempt: u32,
     config: &Config,
+    event_hook: &EventHook,
     wg_psk_pubkey: PublicKey,
     enable_pq: bool,
     enable_daita: bool,
@@ -269,23 +323,66 @@ async fn request_ephemeral_peer(
             .saturating_mul(PSK_EXCHANGE_TIMEOUT_MULTIPLIER.saturating_pow(retry_attempt)),
     );
 
-    let ephemeral = tokio::time::timeout(
+    let ephemeral = talpid_tunnel_config_client::request_ephemeral_peer_with_retries(
+        config.in_tunnel_gateway(),
+        config.tunnel.private_key.public_key(),
+        wg_psk_pubkey,
+        enable_pq.then_some(config.quantum_resistant_kem),
+        enable_daita,
+        &config.negotiation_retry_policy,
         timeout,
-        talpid_tunnel_config_client::request_ephemeral_peer(
-            config.ipv4_gateway,
-            config.tunnel.private_key.public_key(),
-            wg_psk_pubkey,
-            enable_pq,
-            enable_daita,
-        ),
+        |retry| {
+            let mut event_hook = event_hook.clone();
+            async move {
+                event_hook
+                    .on_event(TunnelEvent::EphemeralPeerNegotiationRetry(retry))
+                    .await
+            }
+        },
     )
     .await
-    .map_err(|_timeout_err| {
-        log::warn!("Timeout while negotiating ephemeral peer");
-        CloseMsg::EphemeralPeerNegotiationTimeout
-    })?
-    .map_err(Error::EphemeralPeerNegotiationError)
-    .map_err(CloseMsg::SetupError)?;
+    .map_err(|error| match error {
+        talpid_tunnel_config_client::Error::Timeout => {
+            log::warn!("Timeout while negotiating ephemeral peer");
+            CloseMsg::EphemeralPeerNegotiationTimeout
+        }
+        error => CloseMsg::SetupError(Error::EphemeralPeerNegotiationError(error)),
+    })?;
 
     Ok(ephemeral)
 }
+
+/// Restrict the DAITA settings provided by the relay to the limits configured by the user.
+fn limit_daita_settings(mut daita: DaitaSettings, parameters: &DaitaParameters) -> DaitaSettings {
+    // A fraction of zero means that there is no limit
+    let limit_frac = |relay_frac: f64, percent: Option<u8>| match percent {
+        Some(percent) if relay_frac == 0.0 => f64::from(percent) / 100.0,
+        Some(percent) => relay_frac.min(f64::from(percent) / 100.0),
+        None => relay_frac,
+    };
+    daita.max_padding_frac = limit_frac(daita.max_padding_frac, parameters.max_padding_percent);
+    daita.max_blocking_frac = limit_frac(daita.max_blocking_frac, parameters.max_blocking_percent);
+
+    if let Some(selected) = &parameters.machines {
+        let machines: Vec<String> = daita
+            .client_machines
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| u32::try_from(*index).is_ok_and(|index| selected.contains(&index)))
+            .map(|(_, machine)| machine.clone())
+            .collect();
+        if machines.len() < selected.len() {
+            log::warn!(
+                "Ignoring unknown DAITA machines. The relay provides {}",
+                daita.client_machines.len()
+            );
+        }
+        if machines.is_empty() {
+            log::warn!("None of the selected DAITA machines exist, using every machine");
+        } else {
+            daita.client_machines = machines;
+        }
+    }
+
+    daita
+}
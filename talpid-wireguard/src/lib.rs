@@ -17,6 +17,7 @@ use std::{
     path::Path,
     pin::Pin,
     sync::{mpsc as sync_mpsc, Arc, Mutex},
+    time::Instant,
 };
 #[cfg(any(target_os = "linux", target_os = "windows"))]
 use std::{env, sync::LazyLock};
@@ -34,6 +35,7 @@ use talpid_routing::RouteManagerHandle;
 use talpid_tunnel_config_client::DaitaSettings;
 use talpid_types::{
     net::{wireguard::TunnelParameters, AllowedTunnelTraffic, Endpoint, TransportProtocol},
+    tunnel::ConnectionTimings,
     BoxedError, ErrorExt,
 };
 use tokio::sync::Mutex as AsyncMutex;
@@ -44,6 +46,7 @@ mod connectivity;
 mod ephemeral;
 mod logging;
 mod obfuscation;
+mod port_hopping;
 mod stats;
 #[cfg(wireguard_go)]
 mod wireguard_go;
@@ -77,6 +80,10 @@ pub enum Error {
     #[error("Tunnel timed out")]
     TimeoutError,
 
+    /// The relay stopped completing handshakes
+    #[error("The relay stopped responding to handshakes")]
+    HandshakeTimeout,
+
     /// Invalid WireGuard configuration
     #[error("Invalid WireGuard configuration")]
     WireguardConfigError(#[from] crate::config::Error),
@@ -97,6 +104,11 @@ pub enum Error {
     #[error("Failed while negotiating ephemeral peer")]
     EphemeralPeerNegotiationError(#[source] talpid_tunnel_config_client::Error),
 
+    /// The outgoing interface has no route to the relay
+    #[cfg(target_os = "linux")]
+    #[error("No route to the relay through interface {0}")]
+    NoOutgoingInterfaceRoute(String),
+
     /// Failed to set up IP interfaces.
     #[cfg(windows)]
     #[error("Failed to set up IP interfaces")]
@@ -118,6 +130,10 @@ impl Error {
 
             Error::SetupRoutingError(error) => error.is_recoverable(),
 
+            // The interface may come back up
+            #[cfg(target_os = "linux")]
+            Error::NoOutgoingInterfaceRoute(_) => true,
+
             #[cfg(target_os = "android")]
             Error::TunnelError(TunnelError::BypassError(_)) => true,
 
@@ -175,22 +191,43 @@ impl WireguardMonitor {
         let desired_mtu = get_desired_mtu(params);
         let mut config = crate::config::Config::from_parameters(params, desired_mtu)
             .map_err(Error::WireguardConfigError)?;
+        #[cfg(target_os = "linux")]
+        if let Some(dns_servers) = args.dns_servers.clone() {
+            config.dns_servers = dns_servers;
+        }
 
         let endpoint_addrs = [params.get_next_hop_endpoint().address.ip()];
 
         let (close_obfs_sender, close_obfs_listener) = sync_mpsc::channel();
+        let mut timings = ConnectionTimings::default();
+        // Userspace WireGuard can reach udp2tcp endpoints by itself, in which case no obfuscator
+        // is started below.
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        if Self::uses_userspace_wireguard(&config) {
+            obfuscation::apply_native_tcp(&mut config);
+        }
         // Start obfuscation server and patch the WireGuard config to point the endpoint to it.
+        let obfuscation_started = Instant::now();
         let obfuscator = args
             .runtime
             .block_on(obfuscation::apply_obfuscation_config(
                 &mut config,
                 close_obfs_sender.clone(),
             ))?;
+        if obfuscator.is_some() {
+            timings.obfuscation = Some(obfuscation_started.elapsed());
+        }
         // Don't adjust MTU if overridden by user
         if params.options.mtu.is_none() {
             if let Some(obfuscator) = obfuscator.as_ref() {
                 config.mtu = config.mtu.saturating_sub(obfuscator.packet_overhead());
             }
+            #[cfg(any(target_os = "linux", target_os = "macos"))]
+            if config.tcp_endpoint.is_some() {
+                config.mtu = config
+                    .mtu
+                    .saturating_sub(tunnel_obfuscation::udp2tcp::tcp_packet_overhead());
+            }
             config.mtu = clamp_mtu(params, config.mtu);
         }
 
@@ -209,16 +246,19 @@ impl WireguardMonitor {
             setup_done_tx,
         )?;
         let iface_name = tunnel.get_interface_name();
+        let backend = tunnel.backend();
+        let worker_threads = tunnel.worker_threads();
 
         let obfuscator = Arc::new(AsyncMutex::new(obfuscator));
 
-        let gateway = config.ipv4_gateway;
+        let gateway = config.in_tunnel_gateway();
         let (cancel_token, cancel_receiver) = connectivity::CancelToken::new();
         let mut connectivity_monitor = connectivity::Check::new(
             gateway,
             #[cfg(any(target_os = "macos", target_os = "linux"))]
             iface_name.clone(),
             args.retry_attempt,
+            config.connectivity_check,
             cancel_receiver,
         )
         .map_err(Error::ConnectivityMonitorError)?;
@@ -237,6 +277,7 @@ impl WireguardMonitor {
         let moved_close_obfs_sender = close_obfs_sender.clone();
         let moved_obfuscator = monitor.obfuscator.clone();
         let detect_mtu = params.options.mtu.is_none();
+        let port_hopping = params.port_hopping.clone();
         let tunnel_fut = async move {
             let tunnel = moved_tunnel;
             let close_obfs_sender: sync_mpsc::Sender<CloseMsg> = moved_close_obfs_sender;
@@ -245,7 +286,7 @@ impl WireguardMonitor {
             Self::add_device_ip_addresses(&iface_name, &config.tunnel.addresses, setup_done_rx)
                 .await?;
 
-            let metadata = Self::tunnel_metadata(&iface_name, &config);
+            let metadata = Self::tunnel_metadata(&iface_name, backend, worker_threads, &config);
             let allowed_traffic = Self::allowed_traffic_during_tunnel_config(&config);
             event_hook
                 .on_event(TunnelEvent::InterfaceUp(metadata.clone(), allowed_traffic))
@@ -259,8 +300,18 @@ impl WireguardMonitor {
                 .map_err(Error::SetupRoutingError)
                 .map_err(CloseMsg::SetupError)?;
 
+            #[cfg(target_os = "linux")]
+            let outgoing_interface_routes =
+                Self::get_outgoing_interface_routes(&args.route_manager, &config, &endpoint_addrs)
+                    .await
+                    .map_err(CloseMsg::SetupError)?;
+            #[cfg(not(target_os = "linux"))]
+            let outgoing_interface_routes = Vec::<RequiredRoute>::new();
+
             let routes = Self::get_pre_tunnel_routes(&iface_name, &config)
                 .chain(Self::get_endpoint_routes(&endpoint_addrs))
+                .chain(outgoing_interface_routes)
+                .chain(Self::get_custom_routes(&iface_name, &config))
                 .collect();
 
             args.route_manager
@@ -271,12 +322,14 @@ impl WireguardMonitor {
 
             let ephemeral_obfs_sender = close_obfs_sender.clone();
             if config.quantum_resistant || config.daita {
+                let negotiation_started = Instant::now();
                 if let Err(e) = ephemeral::config_ephemeral_peers(
                     &tunnel,
                     &mut config,
                     args.retry_attempt,
                     obfuscator.clone(),
                     ephemeral_obfs_sender,
+                    &event_hook,
                 )
                 .await
                 {
@@ -287,8 +340,9 @@ impl WireguardMonitor {
                     log_tunnel_data_usage(&config, &tunnel).await;
                     return Err(e);
                 }
+                timings.ephemeral_peer = Some(negotiation_started.elapsed());
 
-                let metadata = Self::tunnel_metadata(&iface_name, &config);
+                let metadata = Self::tunnel_metadata(&iface_name, backend, worker_threads, &config);
                 event_hook
                     .on_event(TunnelEvent::InterfaceUp(
                         metadata,
@@ -300,6 +354,7 @@ impl WireguardMonitor {
             if detect_mtu {
                 let config = config.clone();
                 let iface_name = iface_name.clone();
+                let tunnel = Arc::downgrade(&tunnel);
                 tokio::task::spawn(async move {
                     if config.daita {
                         // TODO: For now, we assume the MTU during the tunnel lifetime.
@@ -307,26 +362,45 @@ impl WireguardMonitor {
                         log::warn!("MTU detection is not supported with DAITA. Skipping");
                         return;
                     }
+                    let IpAddr::V4(gateway) = gateway else {
+                        log::warn!("MTU detection is not supported in IPv6-only tunnels. Skipping");
+                        return;
+                    };
 
-                    if let Err(e) = mtu_detection::automatic_mtu_correction(
+                    let mtu = match mtu_detection::automatic_mtu_correction(
                         gateway,
-                        iface_name,
+                        iface_name.clone(),
                         config.mtu,
                         #[cfg(windows)]
                         config.ipv6_gateway.is_some(),
                     )
                     .await
                     {
-                        log::error!(
-                            "{}",
-                            e.display_chain_with_msg(
-                                "Failed to automatically adjust MTU based on dropped packets"
-                            )
-                        );
+                        Ok(mtu) => mtu,
+                        Err(e) => {
+                            log::error!(
+                                "{}",
+                                e.display_chain_with_msg(
+                                    "Failed to automatically adjust MTU based on dropped packets"
+                                )
+                            );
+                            config.mtu
+                        }
                     };
+
+                    mtu_detection::monitor_mtu(
+                        tunnel,
+                        gateway,
+                        iface_name,
+                        mtu,
+                        #[cfg(windows)]
+                        config.ipv6_gateway.is_some(),
+                    )
+                    .await;
                 });
             }
 
+            let handshake_started = Instant::now();
             let lock = tunnel.lock().await;
             let borrowed_tun = lock.as_ref().expect("The tunnel was dropped unexpectedly");
             match connectivity_monitor
@@ -347,6 +421,7 @@ impl WireguardMonitor {
                 }
             }?;
             drop(lock);
+            timings.handshake = Some(handshake_started.elapsed());
 
             // Add any default route(s) that may exist.
             args.route_manager
@@ -355,20 +430,48 @@ impl WireguardMonitor {
                 .map_err(Error::SetupRoutingError)
                 .map_err(CloseMsg::SetupError)?;
 
-            let metadata = Self::tunnel_metadata(&iface_name, &config);
-            event_hook.on_event(TunnelEvent::Up(metadata)).await;
+            let metadata = Self::tunnel_metadata(&iface_name, backend, worker_threads, &config);
+            event_hook
+                .on_event(TunnelEvent::Up(metadata, timings))
+                .await;
 
-            if let Err(error) = connectivity::Monitor::init(connectivity_monitor)
+            tokio::spawn(stats::collect_traffic_stats(
+                Arc::downgrade(&tunnel),
+                *config.exit_peer().public_key.as_bytes(),
+                args.traffic_stats,
+            ));
+
+            if let Some(port_hopping) = port_hopping {
+                tokio::spawn(port_hopping::run(
+                    Arc::downgrade(&tunnel),
+                    config.clone(),
+                    port_hopping,
+                    event_hook.clone(),
+                ));
+            }
+
+            let close_msg = match connectivity::Monitor::init(connectivity_monitor)
                 .run(Arc::downgrade(&tunnel))
                 .await
             {
-                log::error!(
-                    "{}",
-                    error.display_chain_with_msg("Connectivity monitor failed")
-                );
-            }
+                Ok(()) => CloseMsg::PingErr,
+                Err(error @ connectivity::Error::HandshakeTimeout(_)) => {
+                    log::warn!(
+                        "{}",
+                        error.display_chain_with_msg("Relay stopped responding")
+                    );
+                    CloseMsg::HandshakeTimeout
+                }
+                Err(error) => {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Connectivity monitor failed")
+                    );
+                    CloseMsg::PingErr
+                }
+            };
 
-            Err::<Infallible, CloseMsg>(CloseMsg::PingErr)
+            Err::<Infallible, CloseMsg>(close_msg)
         };
 
         let close_sender = close_obfs_sender.clone();
@@ -406,7 +509,9 @@ impl WireguardMonitor {
         let mut config =
             Config::from_parameters(params, desired_mtu).map_err(Error::WireguardConfigError)?;
         let (close_obfs_sender, close_obfs_listener) = sync_mpsc::channel();
+        let mut timings = ConnectionTimings::default();
         // Start obfuscation server and patch the WireGuard config to point the endpoint to it.
+        let obfuscation_started = Instant::now();
         let obfuscator = args
             .runtime
             .block_on(obfuscation::apply_obfuscation_config(
@@ -414,6 +519,9 @@ impl WireguardMonitor {
                 close_obfs_sender.clone(),
                 args.tun_provider.clone(),
             ))?;
+        if obfuscator.is_some() {
+            timings.obfuscation = Some(obfuscation_started.elapsed());
+        }
         // Don't adjust MTU if overridden by user
         if params.options.mtu.is_none() {
             if let Some(obfuscator) = obfuscator.as_ref() {
@@ -426,8 +534,9 @@ impl WireguardMonitor {
 
         let (cancel_token, cancel_receiver) = connectivity::CancelToken::new();
         let connectivity_check = connectivity::Check::new(
-            config.ipv4_gateway,
+            config.in_tunnel_gateway(),
             args.retry_attempt,
+            config.connectivity_check,
             cancel_receiver.clone(),
         )
         .map_err(Error::ConnectivityMonitorError)?;
@@ -445,6 +554,8 @@ impl WireguardMonitor {
         ))?;
 
         let iface_name = tunnel.get_interface_name();
+        let backend = tunnel.backend();
+        let worker_threads = tunnel.worker_threads();
         let tunnel = Arc::new(AsyncMutex::new(Some(tunnel)));
         let mut event_hook = args.event_hook;
         let monitor = WireguardMonitor {
@@ -458,11 +569,12 @@ impl WireguardMonitor {
 
         let moved_close_obfs_sender = close_obfs_sender.clone();
         let moved_obfuscator = monitor.obfuscator.clone();
+        let port_hopping = params.port_hopping.clone();
         let tunnel_fut = async move {
             let close_obfs_sender: sync_mpsc::Sender<CloseMsg> = moved_close_obfs_sender;
             let obfuscator = moved_obfuscator;
 
-            let metadata = Self::tunnel_metadata(&iface_name, &config);
+            let metadata = Self::tunnel_metadata(&iface_name, backend, worker_threads, &config);
             let allowed_traffic = Self::allowed_traffic_during_tunnel_config(&config);
             event_hook
                 .on_event(TunnelEvent::InterfaceUp(metadata.clone(), allowed_traffic))
@@ -470,6 +582,7 @@ impl WireguardMonitor {
 
             if should_negotiate_ephemeral_peer {
                 let ephemeral_obfs_sender = close_obfs_sender.clone();
+                let negotiation_started = Instant::now();
 
                 if let Err(e) = ephemeral::config_ephemeral_peers(
                     &tunnel,
@@ -477,6 +590,7 @@ impl WireguardMonitor {
                     args.retry_attempt,
                     obfuscator.clone(),
                     ephemeral_obfs_sender,
+                    &event_hook,
                     args.tun_provider,
                 )
                 .await
@@ -488,8 +602,9 @@ impl WireguardMonitor {
                     log_tunnel_data_usage(&config, &tunnel).await;
                     return Err(e);
                 }
+                timings.ephemeral_peer = Some(negotiation_started.elapsed());
 
-                let metadata = Self::tunnel_metadata(&iface_name, &config);
+                let metadata = Self::tunnel_metadata(&iface_name, backend, worker_threads, &config);
                 event_hook
                     .on_event(TunnelEvent::InterfaceUp(
                         metadata,
@@ -498,20 +613,48 @@ impl WireguardMonitor {
                     .await;
             }
 
-            let metadata = Self::tunnel_metadata(&iface_name, &config);
-            event_hook.on_event(TunnelEvent::Up(metadata)).await;
+            let metadata = Self::tunnel_metadata(&iface_name, backend, worker_threads, &config);
+            event_hook
+                .on_event(TunnelEvent::Up(metadata, timings))
+                .await;
+
+            tokio::spawn(stats::collect_traffic_stats(
+                Arc::downgrade(&tunnel),
+                *config.exit_peer().public_key.as_bytes(),
+                args.traffic_stats,
+            ));
+
+            if let Some(port_hopping) = port_hopping {
+                tokio::spawn(port_hopping::run(
+                    Arc::downgrade(&tunnel),
+                    config.clone(),
+                    port_hopping,
+                    event_hook.clone(),
+                ));
+            }
 
-            if let Err(error) = connectivity::Monitor::init(connectivity_check)
+            let close_msg = match connectivity::Monitor::init(connectivity_check)
                 .run(Arc::downgrade(&tunnel))
                 .await
             {
-                log::error!(
-                    "{}",
-                    error.display_chain_with_msg("Connectivity monitor failed")
-                );
-            }
+                Ok(()) => CloseMsg::PingErr,
+                Err(error @ connectivity::Error::HandshakeTimeout(_)) => {
+                    log::warn!(
+                        "{}",
+                        error.display_chain_with_msg("Relay stopped responding")
+                    );
+                    CloseMsg::HandshakeTimeout
+                }
+                Err(error) => {
+                    log::error!(
+                        "{}",
+                        error.display_chain_with_msg("Connectivity monitor failed")
+                    );
+                    CloseMsg::PingErr
+                }
+            };
 
-            Err::<Infallible, CloseMsg>(CloseMsg::PingErr)
+            Err::<Infallible, CloseMsg>(close_msg)
         };
 
         let close_sender = close_obfs_sender.clone();
@@ -535,19 +678,28 @@ impl WireguardMonitor {
         // During ephemeral peer negotiation, only allow traffic to the config service.
         if config.quantum_resistant || config.daita {
             let config_endpoint = Endpoint::new(
-                config.ipv4_gateway,
+                config.in_tunnel_gateway(),
                 talpid_tunnel_config_client::CONFIG_SERVICE_PORT,
                 TransportProtocol::Tcp,
             );
             if config.is_multihop() {
-                // If multihop is enabled, allow traffic to the exit peer as well.
-                AllowedTunnelTraffic::Two(
-                    config_endpoint,
-                    Endpoint::from_socket_address(
-                        config.exit_peer().endpoint,
-                        TransportProtocol::Udp,
-                    ),
-                )
+                // If multihop is enabled, allow traffic to the middle and exit peers as well.
+                let mut relay_endpoints = config
+                    .middle_peers
+                    .iter()
+                    .chain(std::iter::once(config.exit_peer()))
+                    .map(|peer| {
+                        Endpoint::from_socket_address(peer.endpoint, TransportProtocol::Udp)
+                    });
+                if config.middle_peers.is_empty() {
+                    AllowedTunnelTraffic::Two(config_endpoint, relay_endpoints.next().unwrap())
+                } else {
+                    AllowedTunnelTraffic::Multiple(
+                        std::iter::once(config_endpoint)
+                            .chain(relay_endpoints)
+                            .collect(),
+                    )
+                }
             } else {
                 AllowedTunnelTraffic::One(config_endpoint)
             }
@@ -694,11 +846,16 @@ impl WireguardMonitor {
         log_path: Option<&Path>,
         tun_provider: Arc<Mutex<TunProvider>>,
     ) -> Result<TunnelType> {
+        use talpid_types::net::wireguard::Backend;
+
         log::debug!("Tunnel MTU: {}", config.mtu);
 
-        let userspace_wireguard = *FORCE_USERSPACE_WIREGUARD || config.daita;
-        if userspace_wireguard {
-            log::debug!("Using userspace WireGuard implementation");
+        if Self::uses_userspace_wireguard(config) {
+            if config.daita && !*FORCE_USERSPACE_WIREGUARD {
+                log::debug!("Using userspace WireGuard implementation since DAITA is enabled");
+            } else {
+                log::debug!("Using userspace WireGuard implementation");
+            }
 
             let tunnel = runtime
                 .block_on(Self::open_wireguard_go_tunnel(
@@ -707,29 +864,65 @@ impl WireguardMonitor {
                     tun_provider,
                 ))
                 .map(Box::new)?;
-            Ok(tunnel)
-        } else {
-            let res = if will_nm_manage_dns() {
-                log::debug!("Using kernel WireGuard implementation through NetworkManager");
-                wireguard_kernel::NetworkManagerTunnel::new(runtime.clone(), config)
-                    .map(|tunnel| Box::new(tunnel) as TunnelType)
-            } else {
-                log::debug!("Using kernel WireGuard implementation through netlink");
-                wireguard_kernel::NetlinkTunnel::new(runtime.clone(), config)
-                    .map(|tunnel| Box::new(tunnel) as TunnelType)
-            };
+            return Ok(tunnel);
+        }
 
-            res.or_else(|err| {
-                    log::warn!("Failed to initialize kernel WireGuard tunnel, falling back to userspace WireGuard implementation:\n{}",err.display_chain() );
-                    Ok(runtime
-                        .block_on(Self::open_wireguard_go_tunnel(
-                            config,
-                            log_path,
-                            tun_provider,
-                        ))
-                        .map(Box::new)?)
-                })
+        // Unless a backend has been selected, prefer NetworkManager when it manages DNS, so that
+        // it does not fight over the interface. Some NetworkManager versions cannot create
+        // WireGuard devices however, in which case the tunnel is created directly over netlink
+        // instead.
+        let use_network_manager = match config.backend {
+            Some(backend) => backend == Backend::NetworkManager,
+            None => will_nm_manage_dns(),
+        };
+        if use_network_manager {
+            log::debug!("Using kernel WireGuard implementation through NetworkManager");
+            match wireguard_kernel::NetworkManagerTunnel::new(runtime.clone(), config) {
+                Ok(tunnel) => return Ok(Box::new(tunnel)),
+                Err(err) => log::warn!(
+                    "{}",
+                    err.display_chain_with_msg(
+                        "Failed to create WireGuard tunnel through NetworkManager, falling back to netlink"
+                    )
+                ),
+            }
+        }
+
+        log::debug!("Using kernel WireGuard implementation through netlink");
+        match wireguard_kernel::NetlinkTunnel::new(runtime.clone(), config) {
+            Ok(tunnel) => return Ok(Box::new(tunnel)),
+            Err(err) => log::warn!(
+                "{}",
+                err.display_chain_with_msg(
+                    "Failed to initialize kernel WireGuard tunnel, falling back to userspace WireGuard implementation"
+                )
+            ),
         }
+
+        let tunnel = runtime
+            .block_on(Self::open_wireguard_go_tunnel(
+                config,
+                log_path,
+                tun_provider,
+            ))
+            .map(Box::new)?;
+        Ok(tunnel)
+    }
+
+    /// Whether [`Self::open_tunnel`] will run the tunnel in userspace.
+    #[cfg(target_os = "linux")]
+    fn uses_userspace_wireguard(config: &Config) -> bool {
+        use talpid_types::net::wireguard::Backend;
+
+        // DAITA is only implemented by the userspace backend. Toggling DAITA causes a reconnect, so
+        // the kernel backends never have to enable it on a running tunnel.
+        *FORCE_USERSPACE_WIREGUARD || config.daita || config.backend == Some(Backend::Userspace)
+    }
+
+    /// Whether [`Self::open_tunnel`] will run the tunnel in userspace.
+    #[cfg(target_os = "macos")]
+    fn uses_userspace_wireguard(_config: &Config) -> bool {
+        true
     }
 
     /// Configure and start a Wireguard-go tunnel.
@@ -802,6 +995,7 @@ impl WireguardMonitor {
             Ok(CloseMsg::EphemeralPeerNegotiationTimeout) | Ok(CloseMsg::PingErr) => {
                 Err(Error::TimeoutError)
             }
+            Ok(CloseMsg::HandshakeTimeout) => Err(Error::HandshakeTimeout),
             Ok(CloseMsg::Stop) | Ok(CloseMsg::ObfuscatorExpired) => Ok(()),
             Ok(CloseMsg::SetupError(error)) => Err(error),
             Ok(CloseMsg::ObfuscatorFailed(error)) => Err(error),
@@ -852,6 +1046,36 @@ impl WireguardMonitor {
         })
     }
 
+    /// Returns routes to the peer endpoints through the configured outgoing interface, if any.
+    /// These take precedence over the default route for traffic that bypasses the tunnel.
+    #[cfg(target_os = "linux")]
+    async fn get_outgoing_interface_routes(
+        route_manager: &talpid_routing::RouteManagerHandle,
+        config: &Config,
+        endpoints: &[IpAddr],
+    ) -> Result<Vec<RequiredRoute>> {
+        let Some(interface) = &config.outgoing_interface else {
+            return Ok(vec![]);
+        };
+        let mut routes = Vec::with_capacity(endpoints.len());
+        for endpoint in endpoints {
+            let route = route_manager
+                .get_interface_route(*endpoint, interface.clone())
+                .await
+                .map_err(Error::SetupRoutingError)?
+                .ok_or_else(|| Error::NoOutgoingInterfaceRoute(interface.clone()))?;
+            log::debug!(
+                "Routing traffic to {endpoint} through {interface}: {:?}",
+                route.get_node()
+            );
+            routes.push(RequiredRoute::new(
+                ipnetwork::IpNetwork::from(*endpoint),
+                route.get_node().clone(),
+            ));
+        }
+        Ok(routes)
+    }
+
     #[cfg_attr(not(target_os = "windows"), allow(unused_variables))]
     #[cfg(not(target_os = "android"))]
     fn get_tunnel_nodes(
@@ -883,13 +1107,19 @@ impl WireguardMonitor {
         config: &'a Config,
     ) -> impl Iterator<Item = RequiredRoute> + 'a {
         let gateway_node = talpid_routing::Node::device(iface_name.to_string());
-        let gateway_routes = std::iter::once(RequiredRoute::new(
-            ipnetwork::Ipv4Network::from(config.ipv4_gateway).into(),
-            gateway_node.clone(),
-        ))
-        .chain(config.ipv6_gateway.map(|gateway| {
-            RequiredRoute::new(ipnetwork::Ipv6Network::from(gateway).into(), gateway_node)
-        }));
+        // The IPv4 gateway cannot be reached without an IPv4 address in the tunnel
+        let ipv4_gateway = Some(config.ipv4_gateway).filter(|_| !config.ipv6_only);
+        let gateway_routes = ipv4_gateway
+            .map(|gateway| {
+                RequiredRoute::new(
+                    ipnetwork::Ipv4Network::from(gateway).into(),
+                    gateway_node.clone(),
+                )
+            })
+            .into_iter()
+            .chain(config.ipv6_gateway.map(|gateway| {
+                RequiredRoute::new(ipnetwork::Ipv6Network::from(gateway).into(), gateway_node)
+            }));
 
         let (node_v4, node_v6) = Self::get_tunnel_nodes(iface_name, config);
 
@@ -913,6 +1143,23 @@ impl WireguardMonitor {
         routes
     }
 
+    /// Return the user-defined routes through or around the tunnel.
+    #[cfg(not(target_os = "android"))]
+    fn get_custom_routes<'a>(
+        iface_name: &str,
+        config: &'a Config,
+    ) -> impl Iterator<Item = RequiredRoute> + 'a {
+        let (node_v4, node_v6) = Self::get_tunnel_nodes(iface_name, config);
+        config.custom_routes.iter().map(move |route| {
+            let node = if route.network.is_ipv4() {
+                node_v4.clone()
+            } else {
+                node_v6.clone()
+            };
+            RequiredRoute::from_custom_route(route, node)
+        })
+    }
+
     /// Return any 0.0.0.0/0 routes specified by the allowed IPs.
     #[cfg(not(target_os = "android"))]
     fn get_post_tunnel_routes<'a>(
@@ -956,26 +1203,37 @@ impl WireguardMonitor {
                 false => IPV6_HEADER_SIZE,
             };
             const PADDING_BYTES_MARGIN: u16 = 15;
-            let mtu = config.mtu - ip_overhead - WIREGUARD_HEADER_SIZE - PADDING_BYTES_MARGIN;
+            // Every hop after the entry adds another layer of encapsulation
+            let extra_hops = config.middle_peers.len() as u16 + 1;
+            let mtu = config.mtu
+                - extra_hops * (ip_overhead + WIREGUARD_HEADER_SIZE)
+                - PADDING_BYTES_MARGIN;
 
             route.mtu(mtu)
         }
     }
 
-    fn tunnel_metadata(interface_name: &str, config: &Config) -> TunnelMetadata {
+    fn tunnel_metadata(
+        interface_name: &str,
+        backend: talpid_types::net::wireguard::Backend,
+        worker_threads: Option<u32>,
+        config: &Config,
+    ) -> TunnelMetadata {
         TunnelMetadata {
             interface: interface_name.to_string(),
             ips: config.tunnel.addresses.clone(),
             ipv4_gateway: config.ipv4_gateway,
             ipv6_gateway: config.ipv6_gateway,
+            wireguard_backend: Some(backend),
+            wireguard_worker_threads: worker_threads,
         }
     }
 }
 
 /// Log the tunnel stats from the current tunnel.
 ///
-/// This will log the amount of outgoing and incoming data to and from the exit (and entry) relay
-/// so far.
+/// This will log the amount of outgoing and incoming data to and from the exit (and entry and
+/// middle) relays so far.
 async fn log_tunnel_data_usage(config: &Config, tunnel: &Arc<AsyncMutex<Option<TunnelType>>>) {
     let tunnel = tunnel.lock().await;
     let Some(tunnel) = &*tunnel else { return };
@@ -990,6 +1248,11 @@ async fn log_tunnel_data_usage(config: &Config, tunnel: &Arc<AsyncMutex<Option<T
     {
         log::warn!("Exit peer stats: {:?}", stats);
     };
+    for (index, peer) in config.middle_peers.iter().enumerate() {
+        if let Some(stats) = tunnel_stats.get(peer.public_key.as_bytes()) {
+            log::warn!("Middle peer {} stats: {:?}", index + 1, stats);
+        }
+    }
     let pubkey = config.entry_peer.public_key.as_bytes();
     if let Some(stats) = tunnel_stats.get(pubkey) {
         log::warn!("Entry peer stats: {:?}", stats);
@@ -1001,6 +1264,7 @@ enum CloseMsg {
     Stop,
     EphemeralPeerNegotiationTimeout,
     PingErr,
+    HandshakeTimeout,
     SetupError(Error),
     ObfuscatorExpired,
     ObfuscatorFailed(Error),
@@ -1010,6 +1274,12 @@ enum CloseMsg {
 #[async_trait::async_trait]
 pub(crate) trait Tunnel: Send + Sync {
     fn get_interface_name(&self) -> String;
+    /// The WireGuard implementation backing this tunnel.
+    fn backend(&self) -> talpid_types::net::wireguard::Backend;
+    /// The number of threads that process packets, for implementations where this is tunable.
+    fn worker_threads(&self) -> Option<u32> {
+        None
+    }
     fn stop(self: Box<Self>) -> std::result::Result<(), TunnelError>;
     async fn get_tunnel_stats(&self) -> std::result::Result<stats::StatsMap, TunnelError>;
     fn set_config<'a>(
@@ -1,10 +1,14 @@
-use std::{io, net::IpAddr, time::Duration};
+use std::{io, net::IpAddr, sync::Weak, time::Duration};
 
 use futures::{future, stream::FuturesUnordered, Future, TryStreamExt};
 use surge_ping::{Client, Config, PingIdentifier, PingSequence, SurgeError};
 use talpid_tunnel::{ICMP_HEADER_SIZE, IPV4_HEADER_SIZE, MIN_IPV4_MTU};
+use talpid_types::ErrorExt;
+use tokio::sync::Mutex;
 use tokio_stream::StreamExt;
 
+use crate::TunnelType;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     /// Failed to set MTU on the active tunnel
@@ -35,9 +39,11 @@ const PING_TIMEOUT: Duration = Duration::from_secs(10);
 /// considered dropped, so we return the largest collected packet size.
 const PING_OFFSET_TIMEOUT: Duration = Duration::from_secs(2);
 const MTU_STEP_SIZE: u16 = 20;
+/// Time between MTU checks once the tunnel is up.
+const MTU_RECHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
 
 /// Verify that the current MTU doesn't cause dropped packets, otherwise lower it to the
-/// largest value which doesn't.
+/// largest value which doesn't. Returns the MTU of the tunnel after the check.
 ///
 /// Note: This does not take fragmentation into account, so it should only be used as an extra
 /// safety measure after the normal MTU calculation using header sizes and safety margins.
@@ -46,7 +52,7 @@ pub async fn automatic_mtu_correction(
     iface_name: String,
     current_tunnel_mtu: u16,
     #[cfg(windows)] ipv6: bool,
-) -> Result<(), Error> {
+) -> Result<u16, Error> {
     log::debug!("Starting MTU detection");
     let verified_mtu = detect_mtu(
         gateway,
@@ -66,7 +72,51 @@ pub async fn automatic_mtu_correction(
     } else {
         log::debug!("MTU {verified_mtu} verified to not drop packets");
     };
-    Ok(())
+    Ok(verified_mtu)
+}
+
+/// Repeat [automatic_mtu_correction] every [MTU_RECHECK_INTERVAL] until the tunnel is closed.
+///
+/// The path to the relay may change while connected, e.g. when roaming between networks, so an
+/// MTU that worked when the tunnel came up is not guaranteed to keep working. The MTU is only
+/// ever lowered here. Larger packets cannot be probed without raising the interface MTU first,
+/// so it is only restored when reconnecting.
+pub async fn monitor_mtu(
+    tunnel: Weak<Mutex<Option<TunnelType>>>,
+    gateway: std::net::Ipv4Addr,
+    iface_name: String,
+    mut current_tunnel_mtu: u16,
+    #[cfg(windows)] ipv6: bool,
+) {
+    loop {
+        tokio::time::sleep(MTU_RECHECK_INTERVAL).await;
+
+        let Some(tunnel) = tunnel.upgrade() else {
+            return;
+        };
+        if tunnel.lock().await.is_none() {
+            return;
+        }
+        drop(tunnel);
+
+        match automatic_mtu_correction(
+            gateway,
+            iface_name.clone(),
+            current_tunnel_mtu,
+            #[cfg(windows)]
+            ipv6,
+        )
+        .await
+        {
+            Ok(mtu) => current_tunnel_mtu = mtu,
+            // Pings may be dropped for reasons unrelated to the MTU, such as a brief loss of
+            // connectivity. Leave it to the connectivity monitor to deal with that.
+            Err(error) => log::debug!(
+                "{}",
+                error.display_chain_with_msg("Failed to verify tunnel MTU")
+            ),
+        }
+    }
 }
 
 #[cfg(windows)]
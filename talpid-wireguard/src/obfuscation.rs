@@ -13,7 +13,7 @@ use talpid_tunnel::tun_provider::TunProvider;
 use talpid_types::{net::obfuscation::ObfuscatorConfig, ErrorExt};
 
 use tunnel_obfuscation::{
-    create_obfuscator, shadowsocks, udp2tcp, Settings as ObfuscationSettings,
+    create_obfuscator, shadowsocks, tls, udp2tcp, Settings as ObfuscationSettings,
 };
 
 /// Begin running obfuscation machine, if configured. This function will patch `config`'s endpoint
@@ -26,6 +26,10 @@ pub async fn apply_obfuscation_config(
     let Some(ref obfuscator_config) = config.obfuscator_config else {
         return Ok(None);
     };
+    #[cfg(any(target_os = "linux", target_os = "macos"))]
+    if config.tcp_endpoint.is_some() {
+        return Ok(None);
+    }
 
     let settings = settings_from_config(
         obfuscator_config,
@@ -68,6 +72,21 @@ pub async fn apply_obfuscation_config(
     }))
 }
 
+/// Let userspace WireGuard send packets over TCP by itself if udp2tcp obfuscation is used, so that
+/// no local proxy is needed. The obfuscation endpoint becomes the endpoint of the first peer.
+///
+/// Returns whether the TCP transport is used.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn apply_native_tcp(config: &mut Config) -> bool {
+    let Some(ObfuscatorConfig::Udp2Tcp { endpoint }) = config.obfuscator_config else {
+        return false;
+    };
+    log::debug!("Connecting to {endpoint} over TCP without a udp2tcp proxy");
+    config.tcp_endpoint = Some(endpoint);
+    patch_endpoint(config, endpoint);
+    true
+}
+
 /// Patch the first peer in the WireGuard configuration to use the local proxy endpoint
 fn patch_endpoint(config: &mut Config, endpoint: SocketAddr) {
     log::trace!("Patching first WireGuard peer to become {endpoint}");
@@ -84,9 +103,11 @@ fn settings_from_config(
             #[cfg(target_os = "linux")]
             fwmark,
         }),
-        ObfuscatorConfig::Shadowsocks { endpoint } => {
+        ObfuscatorConfig::Shadowsocks { endpoint, cipher } => {
             ObfuscationSettings::Shadowsocks(shadowsocks::Settings {
                 shadowsocks_endpoint: *endpoint,
+                cipher: cipher.clone(),
+                password: shadowsocks::MULLVAD_PASSWORD.to_owned(),
                 wireguard_endpoint: if endpoint.is_ipv4() {
                     SocketAddr::from((Ipv4Addr::LOCALHOST, 51820))
                 } else {
@@ -96,6 +117,24 @@ fn settings_from_config(
                 fwmark,
             })
         }
+        ObfuscatorConfig::CustomShadowsocks {
+            endpoint,
+            peer,
+            password,
+            cipher,
+        } => ObfuscationSettings::Shadowsocks(shadowsocks::Settings {
+            shadowsocks_endpoint: *endpoint,
+            cipher: cipher.clone(),
+            password: password.clone(),
+            wireguard_endpoint: *peer,
+            #[cfg(target_os = "linux")]
+            fwmark,
+        }),
+        ObfuscatorConfig::Tls { endpoint } => ObfuscationSettings::Tls(tls::Settings {
+            peer: *endpoint,
+            #[cfg(target_os = "linux")]
+            fwmark,
+        }),
     }
 }
 
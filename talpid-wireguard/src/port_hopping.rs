@@ -0,0 +1,86 @@
+use std::{ops::RangeInclusive, sync::Weak};
+
+use rand::seq::IteratorRandom;
+use talpid_tunnel::{EventHook, TunnelEvent};
+use talpid_types::{net::obfuscation::PortHoppingConfig, ErrorExt};
+use tokio::{
+    sync::Mutex,
+    time::{Instant, MissedTickBehavior},
+};
+
+use crate::config::Config;
+#[cfg(target_os = "android")]
+use crate::Tunnel;
+use crate::TunnelType;
+
+/// Move the entry peer of `config` to a new port every `settings.interval`, until the tunnel is
+/// closed.
+pub async fn run(
+    tunnel: Weak<Mutex<Option<TunnelType>>>,
+    mut config: Config,
+    settings: PortHoppingConfig,
+    mut event_hook: EventHook,
+) {
+    let mut interval =
+        tokio::time::interval_at(Instant::now() + settings.interval, settings.interval);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+
+        let current_port = config.entry_peer.endpoint.port();
+        let Some(port) = select_port(&settings.port_ranges, current_port) else {
+            log::warn!("Not port hopping since there is no other port to use");
+            return;
+        };
+        let mut endpoint = config.entry_peer.endpoint;
+        endpoint.set_port(port);
+
+        let Some(tunnel) = tunnel.upgrade() else {
+            return;
+        };
+
+        log::debug!("Moving tunnel from port {current_port} to {port}");
+        // The firewall only lets traffic through to the current endpoint, so it has to be updated
+        // before the tunnel starts using the new one.
+        event_hook
+            .on_event(TunnelEvent::PeerEndpointChanged(endpoint))
+            .await;
+        config.entry_peer.endpoint = endpoint;
+
+        let mut lock = tunnel.lock().await;
+        let Some(tunnel) = lock.as_mut() else {
+            return;
+        };
+        if let Err(error) = tunnel.set_config(config.clone()).await {
+            log::error!(
+                "{}",
+                error.display_chain_with_msg("Failed to move tunnel to a new port")
+            );
+        }
+    }
+}
+
+/// Pick a random port from `port_ranges` other than `current_port`.
+fn select_port(port_ranges: &[RangeInclusive<u16>], current_port: u16) -> Option<u16> {
+    port_ranges
+        .iter()
+        .cloned()
+        .flatten()
+        .filter(|port| *port != current_port)
+        .choose(&mut rand::thread_rng())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_select_port() {
+        assert_eq!(select_port(&[53..=53, 443..=443], 53), Some(443));
+        assert_eq!(select_port(&[53..=53], 53), None);
+
+        let port = select_port(&[4000..=5000], 4500).unwrap();
+        assert!((4000..=5000).contains(&port));
+        assert_ne!(port, 4500);
+    }
+}
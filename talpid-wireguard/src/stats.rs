@@ -1,9 +1,218 @@
-/// Contains bytes sent and received through a tunnel
+use std::{
+    collections::VecDeque,
+    net::SocketAddr,
+    sync::Weak,
+    time::{Duration, Instant, SystemTime},
+};
+
+use talpid_tunnel::TrafficStatsHandle;
+use talpid_types::{
+    tunnel::{TrafficStats, TransferRate},
+    ErrorExt,
+};
+use tokio::{sync::Mutex, time::MissedTickBehavior};
+
+#[cfg(target_os = "android")]
+use crate::Tunnel;
+use crate::TunnelType;
+
+/// How often the tunnel counters are sampled to compute transfer rates
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+/// Number of transfer rates to keep in the history
+const HISTORY_LENGTH: usize = 60;
+
+/// Contains bytes sent and received through a tunnel, and the state of the peer
 #[derive(Default, Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Stats {
     pub tx_bytes: u64,
     pub rx_bytes: u64,
+    /// Time of the most recent handshake with the peer, or `None` if there hasn't been one
+    pub last_handshake: Option<SystemTime>,
+    /// Endpoint that the peer is currently reached at
+    pub endpoint: Option<SocketAddr>,
+}
+
+impl Stats {
+    /// Time elapsed since the most recent handshake with the peer
+    pub fn last_handshake_age(&self) -> Option<Duration> {
+        self.last_handshake
+            .map(|time| time.elapsed().unwrap_or(Duration::ZERO))
+    }
 }
 
 /// A map from peer pubkeys to peer stats.
 pub type StatsMap = std::collections::HashMap<[u8; 32], Stats>;
+
+/// Computes transfer rates from successive samples of the cumulative tunnel counters.
+#[derive(Debug, Default)]
+pub struct RateCalculator {
+    last_sample: Option<(Instant, Stats)>,
+    history: VecDeque<TransferRate>,
+}
+
+impl RateCalculator {
+    /// Record the counters as they were at `now`, and return the resulting statistics.
+    pub fn sample(&mut self, now: Instant, stats: Stats) -> TrafficStats {
+        if let Some((last_time, last_stats)) = self.last_sample {
+            let elapsed = now.saturating_duration_since(last_time);
+            if elapsed.is_zero() {
+                return self.traffic_stats();
+            }
+            // The counters start over if the peer is replaced, so never report a negative rate.
+            let rate = TransferRate {
+                tx_bytes_per_second: per_second(
+                    stats.tx_bytes.saturating_sub(last_stats.tx_bytes),
+                    elapsed,
+                ),
+                rx_bytes_per_second: per_second(
+                    stats.rx_bytes.saturating_sub(last_stats.rx_bytes),
+                    elapsed,
+                ),
+            };
+            if self.history.len() == HISTORY_LENGTH {
+                self.history.pop_front();
+            }
+            self.history.push_back(rate);
+        }
+        self.last_sample = Some((now, stats));
+        self.traffic_stats()
+    }
+
+    /// Return the statistics as of the latest sample.
+    pub fn traffic_stats(&self) -> TrafficStats {
+        let stats = self.last_sample.map(|(_, stats)| stats).unwrap_or_default();
+        TrafficStats {
+            tx_bytes: stats.tx_bytes,
+            rx_bytes: stats.rx_bytes,
+            rate: self.history.back().copied().unwrap_or_default(),
+            history: self.history.iter().copied().collect(),
+            last_handshake: stats.last_handshake,
+            endpoint: stats.endpoint,
+        }
+    }
+}
+
+fn per_second(bytes: u64, elapsed: Duration) -> u64 {
+    (bytes as f64 / elapsed.as_secs_f64()) as u64
+}
+
+/// Periodically sample the counters of `peer` and publish the statistics to `handle`, until the
+/// tunnel is closed.
+pub async fn collect_traffic_stats(
+    tunnel: Weak<Mutex<Option<TunnelType>>>,
+    peer: [u8; 32],
+    handle: TrafficStatsHandle,
+) {
+    let mut calculator = RateCalculator::default();
+    let mut interval = tokio::time::interval(SAMPLE_INTERVAL);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+
+        let Some(tunnel) = tunnel.upgrade() else {
+            return;
+        };
+        let lock = tunnel.lock().await;
+        let Some(tunnel) = lock.as_ref() else {
+            return;
+        };
+        let stats = match tunnel.get_tunnel_stats().await {
+            Ok(stats) => stats,
+            Err(error) => {
+                log::debug!(
+                    "{}",
+                    error.display_chain_with_msg("Failed to sample tunnel stats")
+                );
+                continue;
+            }
+        };
+        drop(lock);
+
+        if let Some(stats) = stats.get(&peer) {
+            handle.set(calculator.sample(Instant::now(), *stats));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rate_calculation() {
+        let start = Instant::now();
+        let mut calculator = RateCalculator::default();
+
+        let stats = calculator.sample(
+            start,
+            Stats {
+                tx_bytes: 1000,
+                rx_bytes: 2000,
+                ..Default::default()
+            },
+        );
+        assert_eq!(stats.rate, TransferRate::default());
+        assert!(stats.history.is_empty());
+
+        let stats = calculator.sample(
+            start + Duration::from_secs(2),
+            Stats {
+                tx_bytes: 3000,
+                rx_bytes: 10000,
+                ..Default::default()
+            },
+        );
+        assert_eq!(stats.tx_bytes, 3000);
+        assert_eq!(stats.rx_bytes, 10000);
+        assert_eq!(
+            stats.rate,
+            TransferRate {
+                tx_bytes_per_second: 1000,
+                rx_bytes_per_second: 4000,
+            }
+        );
+        assert_eq!(stats.history, vec![stats.rate]);
+    }
+
+    #[test]
+    fn test_counter_reset() {
+        let start = Instant::now();
+        let mut calculator = RateCalculator::default();
+
+        calculator.sample(
+            start,
+            Stats {
+                tx_bytes: 5000,
+                rx_bytes: 5000,
+                ..Default::default()
+            },
+        );
+        let stats = calculator.sample(
+            start + Duration::from_secs(1),
+            Stats {
+                tx_bytes: 10,
+                rx_bytes: 10,
+                ..Default::default()
+            },
+        );
+        assert_eq!(stats.rate, TransferRate::default());
+    }
+
+    #[test]
+    fn test_history_length() {
+        let start = Instant::now();
+        let mut calculator = RateCalculator::default();
+
+        for i in 0..(HISTORY_LENGTH as u64 + 10) {
+            calculator.sample(
+                start + Duration::from_secs(i),
+                Stats {
+                    tx_bytes: i * 100,
+                    rx_bytes: 0,
+                    ..Default::default()
+                },
+            );
+        }
+        assert_eq!(calculator.traffic_stats().history.len(), HISTORY_LENGTH);
+    }
+}
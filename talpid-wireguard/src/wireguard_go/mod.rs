@@ -13,16 +13,18 @@ use crate::connectivity;
 use crate::logging::{clean_up_logging, initialize_logging};
 #[cfg(all(unix, not(target_os = "android")))]
 use ipnetwork::IpNetwork;
-#[cfg(daita)]
+#[cfg(any(daita, all(unix, not(target_os = "android"))))]
 use std::ffi::CString;
 #[cfg(unix)]
 use std::os::unix::io::{AsRawFd, RawFd};
 #[cfg(unix)]
 use std::sync::{Arc, Mutex};
 use std::{
+    env,
     future::Future,
     path::{Path, PathBuf},
     pin::Pin,
+    sync::LazyLock,
 };
 #[cfg(target_os = "android")]
 use talpid_routing::RouteManagerHandle;
@@ -49,6 +51,26 @@ const DAITA_ACTIONS_CAPACITY: u32 = 1024;
 
 type Result<T> = std::result::Result<T, TunnelError>;
 
+/// Overrides the number of worker threads set in the tunnel config.
+static WORKER_THREADS_OVERRIDE: LazyLock<Option<u32>> = LazyLock::new(|| {
+    let value = env::var("TALPID_WIREGUARD_WORKER_THREADS").ok()?;
+    match value.parse() {
+        Ok(threads) => Some(threads),
+        Err(_) => {
+            log::warn!("Ignoring invalid TALPID_WIREGUARD_WORKER_THREADS value: {value}");
+            None
+        }
+    }
+});
+
+/// Configure the number of packet processing threads, and return the number that is used.
+fn apply_worker_threads(config: &Config) -> u32 {
+    let requested = WORKER_THREADS_OVERRIDE.or(config.worker_threads);
+    let threads = wireguard_go_rs::set_worker_threads(requested);
+    log::debug!("Userspace WireGuard uses {threads} worker threads");
+    threads
+}
+
 struct LoggingContext {
     ordinal: u64,
     #[allow(dead_code)]
@@ -172,6 +194,8 @@ impl WgGoTunnel {
 pub(crate) struct WgGoTunnelState {
     interface_name: String,
     tunnel_handle: wireguard_go_rs::Tunnel,
+    /// Number of packet processing threads in effect when the tunnel was started
+    worker_threads: u32,
     // holding on to the tunnel device and the log file ensures that the associated file handles
     // live long enough and get closed when the tunnel is stopped
     #[cfg(unix)]
@@ -248,10 +272,15 @@ impl WgGoTunnel {
             .map_err(TunnelError::LoggingError)?;
 
         let mtu = config.mtu as isize;
+        let worker_threads = apply_worker_threads(config);
+        let tcp_endpoint = config
+            .tcp_endpoint
+            .map(|endpoint| CString::new(endpoint.to_string()).unwrap());
 
         let handle = wireguard_go_rs::Tunnel::turn_on(
             mtu,
             &wg_config_str,
+            tcp_endpoint.as_deref(),
             tunnel_fd,
             Some(logging::wg_go_logging_callback),
             logging_context.ordinal,
@@ -261,6 +290,7 @@ impl WgGoTunnel {
         Ok(WgGoTunnel(WgGoTunnelState {
             interface_name,
             tunnel_handle: handle,
+            worker_threads,
             _tunnel_device: tunnel_device,
             _logging_context: logging_context,
             #[cfg(daita)]
@@ -291,6 +321,7 @@ impl WgGoTunnel {
             log::warn!("Failed to register default route callback");
         }
 
+        let worker_threads = apply_worker_threads(config);
         let handle = wireguard_go_rs::Tunnel::turn_on(
             c"Mullvad",
             config.mtu,
@@ -332,6 +363,7 @@ impl WgGoTunnel {
         Ok(WgGoTunnel(WgGoTunnelState {
             interface_name: interface_name.to_owned(),
             tunnel_handle: handle,
+            worker_threads,
             _logging_context: logging_context,
             _socket_update_cb: socket_update_cb,
             #[cfg(daita)]
@@ -437,6 +469,7 @@ impl WgGoTunnel {
             .map_err(TunnelError::LoggingError)?;
 
         let wg_config_str = config.to_userspace_format();
+        let worker_threads = apply_worker_threads(config);
 
         let handle = wireguard_go_rs::Tunnel::turn_on(
             &wg_config_str,
@@ -452,6 +485,7 @@ impl WgGoTunnel {
         let tunnel = WgGoTunnel::Singlehop(WgGoTunnelState {
             interface_name,
             tunnel_handle: handle,
+            worker_threads,
             _tunnel_device: tunnel_device,
             _logging_context: logging_context,
             tun_provider,
@@ -513,6 +547,7 @@ impl WgGoTunnel {
             .find(|addr| addr.is_ipv4())
             .map(|addr| CString::new(addr.to_string()).unwrap())
             .ok_or(TunnelError::SetConfigError)?;
+        let worker_threads = apply_worker_threads(config);
 
         let handle = wireguard_go_rs::Tunnel::turn_on_multihop(
             &exit_config_str,
@@ -530,6 +565,7 @@ impl WgGoTunnel {
         let tunnel = WgGoTunnel::Multihop(WgGoTunnelState {
             interface_name,
             tunnel_handle: handle,
+            worker_threads,
             _tunnel_device: tunnel_device,
             _logging_context: logging_context,
             tun_provider,
@@ -586,10 +622,11 @@ impl WgGoTunnel {
     }
     async fn ensure_tunnel_is_running(&self) -> Result<()> {
         let state = self.as_state();
-        let addr = state.config.ipv4_gateway;
+        let addr = state.config.in_tunnel_gateway();
         let cancel_receiver = state.cancel_receiver.clone();
-        let mut check = connectivity::Check::new(addr, 0, cancel_receiver)
-            .map_err(|err| TunnelError::RecoverableStartWireguardError(Box::new(err)))?;
+        let mut check =
+            connectivity::Check::new(addr, 0, state.config.connectivity_check, cancel_receiver)
+                .map_err(|err| TunnelError::RecoverableStartWireguardError(Box::new(err)))?;
 
         // TODO: retry attempt?
 
@@ -615,6 +652,14 @@ impl Tunnel for WgGoTunnel {
         self.as_state().interface_name.clone()
     }
 
+    fn backend(&self) -> talpid_types::net::wireguard::Backend {
+        talpid_types::net::wireguard::Backend::Userspace
+    }
+
+    fn worker_threads(&self) -> Option<u32> {
+        Some(self.as_state().worker_threads)
+    }
+
     fn stop(self: Box<Self>) -> Result<()> {
         self.into_state().stop()
     }
@@ -654,8 +699,14 @@ impl Tunnel for WgGoTunnel {
                 &machines,
                 settings.max_padding_frac,
                 settings.max_blocking_frac,
-                DAITA_EVENTS_CAPACITY,
-                DAITA_ACTIONS_CAPACITY,
+                config
+                    .daita_parameters
+                    .events_capacity
+                    .unwrap_or(DAITA_EVENTS_CAPACITY),
+                config
+                    .daita_parameters
+                    .actions_capacity
+                    .unwrap_or(DAITA_ACTIONS_CAPACITY),
             )
             .map_err(|e| TunnelError::StartDaita(Box::new(e)))?;
 
@@ -665,6 +716,11 @@ impl Tunnel for WgGoTunnel {
 
 mod stats {
     use super::{Stats, StatsMap};
+    use std::{
+        num::ParseIntError,
+        str::FromStr,
+        time::{Duration, UNIX_EPOCH},
+    };
 
     #[derive(thiserror::Error, Debug, PartialEq)]
     pub enum Error {
@@ -672,7 +728,7 @@ mod stats {
         PubKeyParse(String, #[source] hex::FromHexError),
 
         #[error("Failed to parse integer from string \"{0}\"")]
-        IntParse(String, #[source] std::num::ParseIntError),
+        IntParse(String, #[source] ParseIntError),
     }
 
     impl Stats {
@@ -682,6 +738,9 @@ mod stats {
             let mut peer = None;
             let mut tx_bytes = None;
             let mut rx_bytes = None;
+            let mut last_handshake_sec = 0;
+            let mut last_handshake_nsec = 0;
+            let mut endpoint = None;
 
             // parts iterates over keys and values
             let parts = config.split('\n').filter_map(|line| {
@@ -700,22 +759,24 @@ mod stats {
                         peer = Some(buffer);
                         tx_bytes = None;
                         rx_bytes = None;
+                        last_handshake_sec = 0;
+                        last_handshake_nsec = 0;
+                        endpoint = None;
                     }
                     "rx_bytes" => {
-                        rx_bytes = Some(
-                            value
-                                .trim()
-                                .parse()
-                                .map_err(|err| Error::IntParse(value.to_string(), err))?,
-                        );
+                        rx_bytes = Some(parse_int(value)?);
                     }
                     "tx_bytes" => {
-                        tx_bytes = Some(
-                            value
-                                .trim()
-                                .parse()
-                                .map_err(|err| Error::IntParse(value.to_string(), err))?,
-                        );
+                        tx_bytes = Some(parse_int(value)?);
+                    }
+                    "last_handshake_time_sec" => {
+                        last_handshake_sec = parse_int(value)?;
+                    }
+                    "last_handshake_time_nsec" => {
+                        last_handshake_nsec = parse_int(value)?;
+                    }
+                    "endpoint" => {
+                        endpoint = value.trim().parse().ok();
                     }
 
                     _ => continue,
@@ -724,11 +785,18 @@ mod stats {
                 if let (Some(peer_val), Some(tx_bytes_val), Some(rx_bytes_val)) =
                     (peer, tx_bytes, rx_bytes)
                 {
+                    // A zero timestamp means that no handshake has been completed
+                    let last_handshake = (last_handshake_sec != 0 || last_handshake_nsec != 0)
+                        .then(|| {
+                            UNIX_EPOCH + Duration::new(last_handshake_sec, last_handshake_nsec)
+                        });
                     map.insert(
                         peer_val,
                         Self {
                             tx_bytes: tx_bytes_val,
                             rx_bytes: rx_bytes_val,
+                            last_handshake,
+                            endpoint,
                         },
                     );
                     peer = None;
@@ -740,9 +808,17 @@ mod stats {
         }
     }
 
+    fn parse_int<T: FromStr<Err = ParseIntError>>(value: &str) -> std::result::Result<T, Error> {
+        value
+            .trim()
+            .parse()
+            .map_err(|err| Error::IntParse(value.to_string(), err))
+    }
+
     #[cfg(test)]
     mod test {
         use super::super::stats::{Error, Stats};
+        use std::time::{Duration, UNIX_EPOCH};
 
         #[test]
         fn test_parsing() {
@@ -755,6 +831,35 @@ mod stats {
             assert_eq!(actual_keys, [pubkey]);
             assert_eq!(stats[&pubkey].rx_bytes, 2396);
             assert_eq!(stats[&pubkey].tx_bytes, 2740);
+            assert_eq!(
+                stats[&pubkey].last_handshake,
+                Some(UNIX_EPOCH + Duration::new(1578420649, 369416131))
+            );
+        }
+
+        #[test]
+        fn test_parsing_handshake_and_endpoint() {
+            let input = "public_key=0000000000000000000000000000000000000000000000000000000000000000\nendpoint=192.168.1.1:51820\nlast_handshake_time_sec=0\nlast_handshake_time_nsec=0\ntx_bytes=148\nrx_bytes=0\npublic_key=0101010101010101010101010101010101010101010101010101010101010101\nendpoint=[2001:db8::1]:51820\nlast_handshake_time_sec=1578420649\nlast_handshake_time_nsec=0\ntx_bytes=2740\nrx_bytes=2396\n";
+
+            let stats = Stats::parse_config_str(input).expect("Failed to parse valid input");
+            assert_eq!(stats.len(), 2);
+
+            let no_handshake = stats[&[0u8; 32]];
+            assert_eq!(no_handshake.last_handshake, None);
+            assert_eq!(
+                no_handshake.endpoint,
+                Some("192.168.1.1:51820".parse().unwrap())
+            );
+
+            let handshake = stats[&[1u8; 32]];
+            assert_eq!(
+                handshake.last_handshake,
+                Some(UNIX_EPOCH + Duration::from_secs(1578420649))
+            );
+            assert_eq!(
+                handshake.endpoint,
+                Some("[2001:db8::1]:51820".parse().unwrap())
+            );
         }
 
         #[test]
@@ -88,6 +88,10 @@ impl Tunnel for NetlinkTunnel {
         }
     }
 
+    fn backend(&self) -> talpid_types::net::wireguard::Backend {
+        talpid_types::net::wireguard::Backend::Kernel
+    }
+
     fn stop(self: Box<Self>) -> std::result::Result<(), TunnelError> {
         let Self {
             mut netlink_connections,
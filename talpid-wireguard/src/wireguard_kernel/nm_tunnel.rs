@@ -13,7 +13,6 @@ use talpid_dbus::{
         WireguardTunnel,
     },
 };
-use talpid_net::unix::iface_index;
 use talpid_tunnel_config_client::DaitaSettings;
 
 #[derive(thiserror::Error, Debug)]
@@ -69,6 +68,10 @@ impl Tunnel for NetworkManagerTunnel {
         self.interface_name.clone()
     }
 
+    fn backend(&self) -> talpid_types::net::wireguard::Backend {
+        talpid_types::net::wireguard::Backend::NetworkManager
+    }
+
     fn stop(mut self: Box<Self>) -> std::result::Result<(), TunnelError> {
         if let Some(tunnel) = self.tunnel.take() {
             if let Err(err) = self.network_manager.remove_tunnel(tunnel) {
@@ -98,21 +101,22 @@ impl Tunnel for NetworkManagerTunnel {
         &mut self,
         config: Config,
     ) -> Pin<Box<dyn Future<Output = std::result::Result<(), TunnelError>> + Send>> {
-        let interface_name = self.interface_name.clone();
-        let mut wg = self.netlink_connections.wg_handle.clone();
-        Box::pin(async move {
-            let index = iface_index(&interface_name).map_err(|err| {
-                log::error!("Failed to fetch WireGuard device index: {}", err);
-                TunnelError::SetConfigError
-            })?;
-            wg.set_config(index, &config).await.map_err(|err| {
-                log::error!("Failed to apply WireGuard config: {}", err);
-                TunnelError::SetConfigError
-            })
-        })
+        // Go through NM so that the connection profile does not go stale
+        let result = match &self.tunnel {
+            Some(tunnel) => self
+                .network_manager
+                .update_wg_tunnel(tunnel, &convert_config_to_dbus(&config))
+                .map_err(|err| {
+                    log::error!("Failed to update WireGuard tunnel via NM: {}", err);
+                    TunnelError::SetConfigError
+                }),
+            None => Err(TunnelError::SetConfigError),
+        };
+        Box::pin(async move { result })
     }
 
-    /// Outright fail to start - this tunnel type does not support DAITA.
+    /// Outright fail to start - this tunnel type does not support DAITA. The userspace backend is
+    /// selected instead of this one whenever DAITA is enabled.
     fn start_daita(&mut self, _: DaitaSettings) -> std::result::Result<(), TunnelError> {
         Err(TunnelError::DaitaNotSupported)
     }
@@ -153,6 +157,12 @@ fn convert_config_to_dbus(config: &Config) -> DeviceConfig {
             "public-key".into(),
             Variant(Box::new(peer.public_key.to_base64())),
         );
+        if let Some(interval) = peer.persistent_keepalive {
+            peer_config.insert(
+                "persistent-keepalive".into(),
+                Variant(Box::new(u32::from(interval))),
+            );
+        }
 
         peer_configs.push(peer_config);
     }
@@ -202,11 +212,19 @@ fn convert_config_to_dbus(config: &Config) -> DeviceConfig {
 
     let mut settings = HashMap::new();
     settings.insert("ipv4".into(), ipv4_config);
+    // IPv6 DNS servers cannot be added without IPv6 being configured on the interface
+    let dns_servers: Vec<_> = config
+        .dns_servers
+        .iter()
+        .filter(|server| server.is_ipv4() || !ipv6_config.is_empty())
+        .copied()
+        .collect();
     if !ipv6_config.is_empty() {
         settings.insert("ipv6".into(), ipv6_config);
     }
     settings.insert("wireguard".into(), wireguard_config);
     settings.insert("connection".into(), connection_config);
+    NetworkManager::add_dns_servers(&mut settings, &dns_servers);
 
     settings
 }
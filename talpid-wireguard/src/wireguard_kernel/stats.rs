@@ -1,5 +1,7 @@
 use super::wg_message::{DeviceMessage, DeviceNla, PeerNla};
 use crate::stats::{Stats, StatsMap};
+use nix::sys::time::TimeSpec;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 impl Stats {
     pub fn parse_device_message(message: &DeviceMessage) -> StatsMap {
@@ -8,20 +10,23 @@ impl Stats {
         for nla in &message.nlas {
             if let DeviceNla::Peers(peers) = nla {
                 for msg in peers {
-                    let mut tx_bytes = 0;
-                    let mut rx_bytes = 0;
+                    let mut stats = Stats::default();
                     let mut pub_key = None;
 
                     for nla in &msg.0 {
                         match nla {
-                            PeerNla::TxBytes(bytes) => tx_bytes = *bytes,
-                            PeerNla::RxBytes(bytes) => rx_bytes = *bytes,
+                            PeerNla::TxBytes(bytes) => stats.tx_bytes = *bytes,
+                            PeerNla::RxBytes(bytes) => stats.rx_bytes = *bytes,
                             PeerNla::PublicKey(key) => pub_key = Some(*key),
+                            PeerNla::LastHandshakeTime(time) => {
+                                stats.last_handshake = handshake_time(time)
+                            }
+                            PeerNla::Endpoint(endpoint) => stats.endpoint = Some(endpoint.to_std()),
                             _ => continue,
                         }
                     }
                     if let Some(key) = pub_key {
-                        map.insert(key, Stats { tx_bytes, rx_bytes });
+                        map.insert(key, stats);
                     }
                 }
             }
@@ -30,3 +35,15 @@ impl Stats {
         map
     }
 }
+
+/// The kernel reports a zero timestamp if no handshake has been completed.
+fn handshake_time(time: &TimeSpec) -> Option<SystemTime> {
+    let since_epoch = Duration::new(
+        u64::try_from(time.tv_sec()).ok()?,
+        u32::try_from(time.tv_nsec()).ok()?,
+    );
+    if since_epoch.is_zero() {
+        return None;
+    }
+    Some(UNIX_EPOCH + since_epoch)
+}
@@ -24,6 +24,7 @@ use std::{
     pin::Pin,
     ptr,
     sync::{Arc, LazyLock, Mutex},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 use talpid_types::{BoxedError, ErrorExt};
 use talpid_windows::net;
@@ -190,6 +191,9 @@ pub struct WgNtTunnel {
 
 const WIREGUARD_KEY_LENGTH: usize = 32;
 
+/// Number of 100 ns intervals between 1601-01-01 and the Unix epoch
+const FILETIME_UNIX_EPOCH: u64 = 116_444_736_000_000_000;
+
 /// See `WIREGUARD_ALLOWED_IP` at <https://git.zx2c4.com/wireguard-nt/tree/api/wireguard.h>.
 #[derive(Clone, Copy)]
 #[repr(C, align(8))]
@@ -330,6 +334,13 @@ bitflags! {
     }
 }
 
+/// Convert a handshake timestamp, in 100 ns intervals since 1601-01-01, to a [SystemTime].
+/// The timestamp is zero if no handshake has been completed.
+fn handshake_time(last_handshake: u64) -> Option<SystemTime> {
+    let since_epoch = last_handshake.checked_sub(FILETIME_UNIX_EPOCH)?;
+    Some(UNIX_EPOCH + Duration::from_nanos(since_epoch.saturating_mul(100)))
+}
+
 /// See `WIREGUARD_PEER` at <https://git.zx2c4.com/wireguard-nt/tree/api/wireguard.h>.
 #[derive(Debug, Eq, PartialEq, Clone, Copy)]
 #[repr(C, align(8))]
@@ -945,6 +956,9 @@ fn serialize_config(config: &Config) -> Result<Vec<MaybeUninit<u8>>> {
         if peer.psk.is_some() {
             flags |= WgPeerFlag::HAS_PRESHARED_KEY;
         }
+        if peer.persistent_keepalive.is_some() {
+            flags |= WgPeerFlag::HAS_PERSISTENT_KEEPALIVE;
+        }
         #[cfg(daita)]
         let constant_packet_size = if peer.constant_packet_size { 1 } else { 0 };
         let wg_peer = WgPeer {
@@ -956,7 +970,7 @@ fn serialize_config(config: &Config) -> Result<Vec<MaybeUninit<u8>>> {
                 .as_ref()
                 .map(|psk| *psk.as_bytes())
                 .unwrap_or([0u8; WIREGUARD_KEY_LENGTH]),
-            persistent_keepalive: 0,
+            persistent_keepalive: peer.persistent_keepalive.unwrap_or(0),
             endpoint: net::inet_sockaddr_from_socketaddr(peer.endpoint).into(),
             tx_bytes: 0,
             rx_bytes: 0,
@@ -1054,6 +1068,10 @@ impl Tunnel for WgNtTunnel {
         self.interface_name.clone()
     }
 
+    fn backend(&self) -> talpid_types::net::wireguard::Backend {
+        talpid_types::net::wireguard::Backend::Kernel
+    }
+
     async fn get_tunnel_stats(&self) -> std::result::Result<StatsMap, super::TunnelError> {
         let Some(ref device) = self.device else {
             log::error!("Failed to obtain tunnel stats as device no longer exists");
@@ -1076,6 +1094,8 @@ impl Tunnel for WgNtTunnel {
                     Stats {
                         tx_bytes: peer.tx_bytes,
                         rx_bytes: peer.rx_bytes,
+                        last_handshake: handshake_time(peer.last_handshake),
+                        endpoint: net::try_socketaddr_from_inet_sockaddr(peer.endpoint.addr).ok(),
                     },
                 );
             }
@@ -1160,16 +1180,25 @@ mod tests {
             allowed_ips: vec!["1.3.3.0/24".parse().unwrap()],
             endpoint: "1.2.3.4:1234".parse().unwrap(),
             psk: None,
+            persistent_keepalive: None,
             constant_packet_size: false,
         },
         exit_peer: None,
+        middle_peers: vec![],
         ipv4_gateway: "0.0.0.0".parse().unwrap(),
         ipv6_gateway: None,
+        ipv6_only: false,
         mtu: 0,
+        worker_threads: None,
         obfuscator_config: None,
         #[cfg(daita)]
         daita: false,
         quantum_resistant: false,
+        quantum_resistant_kem: Default::default(),
+        negotiation_retry_policy: Default::default(),
+        connectivity_check: Default::default(),
+        daita_parameters: Default::default(),
+        custom_routes: vec![],
     });
 
     static WG_STRUCT_CONFIG: LazyLock<Interface> = LazyLock::new(|| Interface {
@@ -190,7 +190,9 @@ async fn wait_for_daemon_reconnect(
             TunnelState::Connecting { .. } => Ok(state),
             TunnelState::Connected { .. } => return None,
             TunnelState::Disconnecting { .. } => return None,
-            TunnelState::Disconnected { .. } => Err(Error::UnexpectedTunnelState(Box::new(state))),
+            TunnelState::Disconnected { .. } | TunnelState::Paused => {
+                Err(Error::UnexpectedTunnelState(Box::new(state)))
+            }
             TunnelState::Error(state) => Err(Error::UnexpectedErrorState(state)),
         }),
         _ => None,
@@ -84,8 +84,11 @@ pub async fn test_dns_leak_custom_public_ip(
             default_options: settings::DefaultDnsOptions::default(),
             custom_options: settings::CustomDnsOptions {
                 addresses: vec![CONFIG_IP],
+                encrypted_servers: vec![],
             },
             state: settings::DnsState::Custom,
+            split_dns_rules: vec![],
+            filter_lists: vec![],
         })
         .await
         .expect("failed to configure DNS server");
@@ -116,8 +119,11 @@ pub async fn test_dns_leak_custom_private_ip(
             default_options: settings::DefaultDnsOptions::default(),
             custom_options: settings::CustomDnsOptions {
                 addresses: vec![CONFIG_IP],
+                encrypted_servers: vec![],
             },
             state: settings::DnsState::Custom,
+            split_dns_rules: vec![],
+            filter_lists: vec![],
         })
         .await
         .expect("failed to configure DNS server");
@@ -367,8 +373,11 @@ pub async fn test_dns_config_custom_private(
             default_options: settings::DefaultDnsOptions::default(),
             custom_options: settings::CustomDnsOptions {
                 addresses: vec![IpAddr::V4(TEST_CONFIG.host_bridge_ip)],
+                encrypted_servers: vec![],
             },
             state: settings::DnsState::Custom,
+            split_dns_rules: vec![],
+            filter_lists: vec![],
         })
         .await
         .context("failed to configure DNS server")?;
@@ -401,8 +410,11 @@ pub async fn test_dns_config_custom_public(
             default_options: settings::DefaultDnsOptions::default(),
             custom_options: settings::CustomDnsOptions {
                 addresses: vec![custom_ip],
+                encrypted_servers: vec![],
             },
             state: settings::DnsState::Custom,
+            split_dns_rules: vec![],
+            filter_lists: vec![],
         })
         .await
         .context("failed to configure DNS server")?;
@@ -507,6 +519,8 @@ pub async fn test_content_blockers(
                 default_options: test_opts,
                 custom_options: settings::CustomDnsOptions::default(),
                 state: settings::DnsState::Default,
+                split_dns_rules: vec![],
+                filter_lists: vec![],
             })
             .await
             .context("failed to configure DNS server")?;
@@ -669,10 +683,12 @@ async fn connect_local_wg_relay(mullvad_client: &mut MullvadProxyClient) -> Resu
                 allowed_ips: vec!["0.0.0.0/0".parse().unwrap()],
                 endpoint: peer_addr,
                 psk: None,
+                persistent_keepalive: None,
                 constant_packet_size: false,
             },
             ipv4_gateway: CUSTOM_TUN_GATEWAY,
             exit_peer: None,
+            middle_peers: vec![],
             #[cfg(target_os = "linux")]
             fwmark: None,
             ipv6_gateway: None,
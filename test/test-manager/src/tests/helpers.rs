@@ -696,9 +696,11 @@ pub fn unreachable_wireguard_tunnel() -> talpid_types::net::wireguard::Connectio
             ],
             endpoint: "1.3.3.7:1234".parse().unwrap(),
             psk: None,
+            persistent_keepalive: None,
             constant_packet_size: false,
         },
         exit_peer: None,
+        middle_peers: vec![],
         ipv4_gateway: Ipv4Addr::new(10, 64, 10, 1),
         ipv6_gateway: None,
         #[cfg(target_os = "linux")]
@@ -765,6 +767,7 @@ async fn intersect_with_current_location(
         constraint.location,
         Constraint::Any,
         Constraint::Any,
+        Constraint::Any,
         query.tunnel_protocol(),
         WireguardRelayQuery {
             entry_location: constraint.wireguard_constraints.entry_location,
@@ -367,6 +367,8 @@ pub async fn test_connected_state(
                     obfuscation: _,
                     entry_endpoint: None,
                     tunnel_interface: _,
+                    wireguard_backend: _,
+                    wireguard_worker_threads: _,
                     daita: _,
                 },
             ..
@@ -2,6 +2,7 @@ use async_trait::async_trait;
 use std::net::SocketAddr;
 
 pub mod shadowsocks;
+pub mod tls;
 pub mod udp2tcp;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -19,6 +20,12 @@ pub enum Error {
 
     #[error("Failed to run Shadowsocks")]
     RunShadowsocksObfuscator(#[source] shadowsocks::Error),
+
+    #[error("Failed to initialize TLS obfuscator")]
+    CreateTlsObfuscator(#[source] tls::Error),
+
+    #[error("Failed to run TLS obfuscator")]
+    RunTlsObfuscator(#[source] tls::Error),
 }
 
 #[async_trait]
@@ -42,6 +49,7 @@ pub trait Obfuscator: Send {
 pub enum Settings {
     Udp2Tcp(udp2tcp::Settings),
     Shadowsocks(shadowsocks::Settings),
+    Tls(tls::Settings),
 }
 
 pub async fn create_obfuscator(settings: &Settings) -> Result<Box<dyn Obfuscator>> {
@@ -54,6 +62,10 @@ pub async fn create_obfuscator(settings: &Settings) -> Result<Box<dyn Obfuscator
             .await
             .map(box_obfuscator)
             .map_err(Error::CreateShadowsocksObfuscator),
+        Settings::Tls(s) => tls::Tls::new(s)
+            .await
+            .map(box_obfuscator)
+            .map_err(Error::CreateTlsObfuscator),
     }
 }
 
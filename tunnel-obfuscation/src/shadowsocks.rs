@@ -19,11 +19,11 @@ use shadowsocks::{
 };
 #[cfg(any(target_os = "android", target_os = "linux"))]
 use std::os::fd::AsRawFd;
-use std::{io, net::SocketAddr, sync::Arc};
+use std::{io, net::SocketAddr, str::FromStr, sync::Arc};
 use tokio::{net::UdpSocket, sync::oneshot};
 
-const SHADOWSOCKS_CIPHER: CipherKind = CipherKind::AES_256_GCM;
-const SHADOWSOCKS_PASSWORD: &str = "mullvad";
+/// Password used by the Shadowsocks servers on the relays
+pub const MULLVAD_PASSWORD: &str = "mullvad";
 
 type Result<T> = std::result::Result<T, Error>;
 
@@ -54,11 +54,15 @@ pub enum Error {
     /// Failed to receive remote socket descriptor
     #[error("Failed to receive remote socket descriptor")]
     ReceiveRemoteFd,
+    /// The cipher is unknown or not an AEAD cipher
+    #[error("Unsupported Shadowsocks cipher: {0}")]
+    UnsupportedCipher(String),
 }
 
 pub struct Shadowsocks {
     udp_client_addr: SocketAddr,
     wireguard_endpoint: SocketAddr,
+    cipher: CipherKind,
     server: tokio::task::JoinHandle<Result<()>>,
     // The receiver will implicitly shut down when this is dropped
     _shutdown_tx: oneshot::Sender<()>,
@@ -72,12 +76,21 @@ pub struct Settings {
     pub shadowsocks_endpoint: SocketAddr,
     /// Remote WireGuard endpoint
     pub wireguard_endpoint: SocketAddr,
+    /// Name of the AEAD cipher to use, e.g. "aes-256-gcm"
+    pub cipher: String,
+    pub password: String,
     #[cfg(target_os = "linux")]
     pub fwmark: Option<u32>,
 }
 
 impl Shadowsocks {
     pub(crate) async fn new(settings: &Settings) -> Result<Self> {
+        // Only AEAD ciphers are supported for UDP relaying by the servers
+        let cipher = CipherKind::from_str(&settings.cipher)
+            .ok()
+            .filter(CipherKind::is_aead)
+            .ok_or_else(|| Error::UnsupportedCipher(settings.cipher.clone()))?;
+
         let (local_udp_socket, udp_client_addr) =
             create_local_udp_socket(settings.shadowsocks_endpoint.is_ipv4()).await?;
 
@@ -95,6 +108,8 @@ impl Shadowsocks {
 
         let server = tokio::spawn(run_forwarding(
             settings.shadowsocks_endpoint,
+            cipher,
+            settings.password.clone(),
             remote_socket,
             local_udp_socket,
             settings.wireguard_endpoint,
@@ -104,6 +119,7 @@ impl Shadowsocks {
         Ok(Shadowsocks {
             udp_client_addr,
             wireguard_endpoint: settings.wireguard_endpoint,
+            cipher,
             server,
             _shutdown_tx: shutdown_tx,
             #[cfg(target_os = "android")]
@@ -114,6 +130,8 @@ impl Shadowsocks {
 
 async fn run_forwarding(
     shadowsocks_endpoint: SocketAddr,
+    cipher: CipherKind,
+    password: String,
     remote_socket: UdpSocket,
     local_udp_socket: UdpSocket,
     wireguard_endpoint: SocketAddr,
@@ -123,7 +141,7 @@ async fn run_forwarding(
         .await
         .map_err(Error::WaitForUdpClient)?;
 
-    let shadowsocks = connect_shadowsocks(remote_socket, shadowsocks_endpoint);
+    let shadowsocks = connect_shadowsocks(remote_socket, shadowsocks_endpoint, cipher, &password);
     let shadowsocks = Arc::new(shadowsocks);
 
     let local_udp = Arc::new(local_udp_socket);
@@ -157,13 +175,14 @@ async fn run_forwarding(
     Ok(())
 }
 
-fn connect_shadowsocks(remote_socket: UdpSocket, shadowsocks_endpoint: SocketAddr) -> ProxySocket {
+fn connect_shadowsocks(
+    remote_socket: UdpSocket,
+    shadowsocks_endpoint: SocketAddr,
+    cipher: CipherKind,
+    password: &str,
+) -> ProxySocket {
     let ss_context = Context::new_shared(ServerType::Local);
-    let ss_config: ServerConfig = ServerConfig::new(
-        shadowsocks_endpoint,
-        SHADOWSOCKS_PASSWORD,
-        SHADOWSOCKS_CIPHER,
-    );
+    let ss_config: ServerConfig = ServerConfig::new(shadowsocks_endpoint, password, cipher);
     ProxySocket::from_socket(UdpSocketType::Client, ss_context, &ss_config, remote_socket)
 }
 
@@ -300,11 +319,11 @@ impl Obfuscator for Shadowsocks {
         // This math relies on the packet structure of Shadowsocks AEAD UDP packets.
         // https://shadowsocks.org/doc/aead.html
         // Those packets look like this: [salt][address][payload][tag]
-        debug_assert!(SHADOWSOCKS_CIPHER.is_aead());
+        debug_assert!(self.cipher.is_aead());
 
-        let overhead = SHADOWSOCKS_CIPHER.salt_len()
+        let overhead = self.cipher.salt_len()
             + Address::from(self.wireguard_endpoint).serialized_len()
-            + SHADOWSOCKS_CIPHER.tag_len();
+            + self.cipher.tag_len();
 
         u16::try_from(overhead).expect("packet overhead is less than u16::MAX")
     }
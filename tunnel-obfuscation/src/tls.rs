@@ -0,0 +1,425 @@
+//! TLS mimicry obfuscation
+//!
+//! Makes the tunnel look like an HTTPS connection, for networks that block TCP streams which are
+//! not TLS. A TLS 1.3 ClientHello resembling that of a web browser is sent to the relay, and once
+//! the relay has answered with a ServerHello, every WireGuard packet is carried in a TLS
+//! application data record.
+//!
+//! No encryption is added on top of WireGuard. Its packets are already indistinguishable from
+//! random data, which is also what TLS 1.3 application data looks like on the wire.
+//!
+//! Note: It is important not to connect to the relay right away. The remote socket must be
+//! protected in `VpnService` so that the socket is not routed through the tunnel.
+
+use super::Obfuscator;
+use async_trait::async_trait;
+#[cfg(target_os = "linux")]
+use nix::sys::socket::{setsockopt, sockopt};
+use rand::Rng;
+#[cfg(any(target_os = "android", target_os = "linux"))]
+use std::os::fd::AsRawFd;
+use std::{io, net::SocketAddr, sync::Arc};
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+        TcpSocket, UdpSocket,
+    },
+    sync::oneshot,
+};
+
+const CONTENT_TYPE_CHANGE_CIPHER_SPEC: u8 = 20;
+const CONTENT_TYPE_ALERT: u8 = 21;
+const CONTENT_TYPE_HANDSHAKE: u8 = 22;
+const CONTENT_TYPE_APPLICATION_DATA: u8 = 23;
+
+const HANDSHAKE_TYPE_CLIENT_HELLO: u8 = 1;
+const HANDSHAKE_TYPE_SERVER_HELLO: u8 = 2;
+
+/// Record version used by clients in the first ClientHello, for compatibility with old servers
+const INITIAL_RECORD_VERSION: [u8; 2] = [0x03, 0x01];
+/// Record version used by TLS 1.3 for everything else
+const RECORD_VERSION: [u8; 2] = [0x03, 0x03];
+
+const RECORD_HEADER_LEN: usize = 5;
+/// Largest record payload allowed by TLS 1.3 (2^14 bytes of plaintext plus expansion)
+const MAX_RECORD_LEN: usize = (1 << 14) + 256;
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(thiserror::Error, Debug)]
+pub enum Error {
+    /// Failed to bind local UDP socket
+    #[error("Failed to bind UDP socket")]
+    BindUdp(#[source] io::Error),
+    /// Missing UDP listener address
+    #[error("Failed to retrieve UDP socket bind address")]
+    GetUdpLocalAddress(#[source] io::Error),
+    /// Failed to create remote TCP socket
+    #[error("Failed to create TCP socket")]
+    CreateTcpSocket(#[source] io::Error),
+    /// Failed to set fwmark
+    #[cfg(target_os = "linux")]
+    #[error("Failed to set fwmark")]
+    SetFwmark(#[source] nix::Error),
+    /// Failed to wait for UDP client
+    #[error("Failed to wait for UDP client")]
+    WaitForUdpClient(#[source] io::Error),
+    /// Failed to connect to the relay
+    #[error("Failed to connect to TLS endpoint")]
+    Connect(#[source] io::Error),
+    /// Failed to send or receive handshake messages
+    #[error("Failed to perform TLS handshake")]
+    Handshake(#[source] io::Error),
+    /// The relay did not answer with a ServerHello
+    #[error("Unexpected response to ClientHello")]
+    UnexpectedServerResponse,
+}
+
+#[derive(Debug)]
+pub struct Settings {
+    /// Remote TLS endpoint
+    pub peer: SocketAddr,
+    #[cfg(target_os = "linux")]
+    pub fwmark: Option<u32>,
+}
+
+pub struct Tls {
+    udp_client_addr: SocketAddr,
+    server: tokio::task::JoinHandle<Result<()>>,
+    // The receiver will implicitly shut down when this is dropped
+    _shutdown_tx: oneshot::Sender<()>,
+    #[cfg(target_os = "android")]
+    outbound_fd: i32,
+}
+
+impl Tls {
+    pub(crate) async fn new(settings: &Settings) -> Result<Self> {
+        let (local_udp_socket, udp_client_addr) =
+            create_local_udp_socket(settings.peer.is_ipv4()).await?;
+
+        let remote_socket = create_remote_socket(
+            settings.peer.is_ipv4(),
+            #[cfg(target_os = "linux")]
+            settings.fwmark,
+        )?;
+
+        #[cfg(target_os = "android")]
+        let outbound_fd = remote_socket.as_raw_fd();
+
+        let (shutdown_tx, shutdown_rx) = oneshot::channel();
+        let server = tokio::spawn(run_forwarding(
+            settings.peer,
+            remote_socket,
+            local_udp_socket,
+            shutdown_rx,
+        ));
+
+        Ok(Tls {
+            udp_client_addr,
+            server,
+            _shutdown_tx: shutdown_tx,
+            #[cfg(target_os = "android")]
+            outbound_fd,
+        })
+    }
+}
+
+async fn run_forwarding(
+    peer: SocketAddr,
+    remote_socket: TcpSocket,
+    local_udp_socket: UdpSocket,
+    shutdown_rx: oneshot::Receiver<()>,
+) -> Result<()> {
+    wait_for_local_udp_client(&local_udp_socket)
+        .await
+        .map_err(Error::WaitForUdpClient)?;
+
+    let stream = remote_socket.connect(peer).await.map_err(Error::Connect)?;
+    // Disables the Nagle algorithm on the TCP socket. Improves performance
+    stream.set_nodelay(true).map_err(Error::Connect)?;
+    let (read_half, mut write_half) = stream.into_split();
+    let mut read_half = BufReader::new(read_half);
+
+    handshake(&mut read_half, &mut write_half).await?;
+    log::trace!("TLS handshake with {peer} completed");
+
+    let local_udp = Arc::new(local_udp_socket);
+    let mut client = tokio::spawn(handle_outgoing(write_half, local_udp.clone()));
+    let mut server = tokio::spawn(handle_incoming(read_half, local_udp));
+
+    tokio::select! {
+        _ = shutdown_rx => {
+            log::trace!("Stopping TLS obfuscation");
+        }
+        _result = &mut server => log::trace!("TLS connection closed"),
+        _result = &mut client => log::trace!("Local UDP client closed"),
+    }
+
+    client.abort();
+    server.abort();
+
+    Ok(())
+}
+
+/// Send a ClientHello and wait for the ServerHello.
+async fn handshake(
+    read_half: &mut BufReader<OwnedReadHalf>,
+    write_half: &mut OwnedWriteHalf,
+) -> Result<()> {
+    write_half
+        .write_all(&client_hello())
+        .await
+        .map_err(Error::Handshake)?;
+
+    let (content_type, payload) = read_record(read_half).await.map_err(Error::Handshake)?;
+    if content_type != CONTENT_TYPE_HANDSHAKE
+        || payload.first() != Some(&HANDSHAKE_TYPE_SERVER_HELLO)
+    {
+        return Err(Error::UnexpectedServerResponse);
+    }
+
+    // Browsers send a dummy ChangeCipherSpec after the ServerHello ("middlebox compatibility
+    // mode"), so a connection without one stands out.
+    write_half
+        .write_all(&record(
+            CONTENT_TYPE_CHANGE_CIPHER_SPEC,
+            RECORD_VERSION,
+            &[0x01],
+        ))
+        .await
+        .map_err(Error::Handshake)
+}
+
+async fn handle_outgoing(mut tcp_write: OwnedWriteHalf, local_udp_read: Arc<UdpSocket>) {
+    let mut rx_buffer = vec![0u8; u16::MAX as usize];
+
+    loop {
+        let read_n = match local_udp_read.recv(&mut rx_buffer).await {
+            Ok(read_n) => read_n,
+            Err(error) => {
+                log::error!("Failed to read from local UDP socket: {error}");
+                break;
+            }
+        };
+        if read_n > MAX_RECORD_LEN {
+            log::trace!("Dropping packet that does not fit in a TLS record");
+            continue;
+        }
+
+        let record = record(
+            CONTENT_TYPE_APPLICATION_DATA,
+            RECORD_VERSION,
+            &rx_buffer[..read_n],
+        );
+        if let Err(error) = tcp_write.write_all(&record).await {
+            log::error!("Failed to write to TLS connection: {error}");
+            break;
+        }
+    }
+}
+
+async fn handle_incoming(mut tcp_read: BufReader<OwnedReadHalf>, local_udp_write: Arc<UdpSocket>) {
+    loop {
+        let (content_type, payload) = match read_record(&mut tcp_read).await {
+            Ok(record) => record,
+            Err(error) => {
+                log::error!("Failed to read from TLS connection: {error}");
+                break;
+            }
+        };
+
+        match content_type {
+            CONTENT_TYPE_APPLICATION_DATA => (),
+            CONTENT_TYPE_ALERT => {
+                log::debug!("TLS connection closed by relay");
+                break;
+            }
+            // Remaining handshake messages carry nothing of interest
+            _ => continue,
+        }
+
+        if let Err(error) = local_udp_write.send(&payload).await {
+            log::error!("Failed to write to local UDP socket: {error}");
+            if is_fatal_socket_io_error(&error) {
+                break;
+            }
+        }
+    }
+}
+
+/// Read a single record, returning its content type and payload.
+async fn read_record(reader: &mut (impl AsyncRead + Unpin)) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0u8; RECORD_HEADER_LEN];
+    reader.read_exact(&mut header).await?;
+
+    let len = usize::from(u16::from_be_bytes([header[3], header[4]]));
+    if len > MAX_RECORD_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "TLS record exceeds maximum length",
+        ));
+    }
+
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload).await?;
+    Ok((header[0], payload))
+}
+
+fn record(content_type: u8, version: [u8; 2], payload: &[u8]) -> Vec<u8> {
+    let len = u16::try_from(payload.len()).expect("record payload is less than u16::MAX");
+
+    let mut record = Vec::with_capacity(RECORD_HEADER_LEN + payload.len());
+    record.push(content_type);
+    record.extend_from_slice(&version);
+    record.extend_from_slice(&len.to_be_bytes());
+    record.extend_from_slice(payload);
+    record
+}
+
+/// Build a ClientHello record similar to what a browser would send to a server that it reaches
+/// by IP address, i.e. without a server name.
+fn client_hello() -> Vec<u8> {
+    let mut rng = rand::thread_rng();
+
+    let mut extensions = vec![];
+    // supported_versions: TLS 1.3
+    push_extension(&mut extensions, 0x002b, &[0x02, 0x03, 0x04]);
+    // supported_groups: x25519, secp256r1, secp384r1
+    push_extension(
+        &mut extensions,
+        0x000a,
+        &[0x00, 0x06, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18],
+    );
+    // signature_algorithms: ecdsa_secp256r1_sha256, rsa_pss_rsae_sha256, rsa_pkcs1_sha256,
+    // ecdsa_secp384r1_sha384
+    push_extension(
+        &mut extensions,
+        0x000d,
+        &[0x00, 0x08, 0x04, 0x03, 0x08, 0x04, 0x04, 0x01, 0x05, 0x03],
+    );
+    // key_share: a random x25519 public key
+    let mut key_share = vec![0x00, 0x24, 0x00, 0x1d, 0x00, 0x20];
+    key_share.extend_from_slice(&rng.gen::<[u8; 32]>());
+    push_extension(&mut extensions, 0x0033, &key_share);
+    // psk_key_exchange_modes: psk_dhe_ke
+    push_extension(&mut extensions, 0x002d, &[0x01, 0x01]);
+    // application_layer_protocol_negotiation: h2, http/1.1
+    push_extension(&mut extensions, 0x0010, b"\x00\x0c\x02h2\x08http/1.1");
+
+    let mut hello = vec![];
+    // legacy_version
+    hello.extend_from_slice(&RECORD_VERSION);
+    // random
+    hello.extend_from_slice(&rng.gen::<[u8; 32]>());
+    // legacy_session_id
+    hello.push(32);
+    hello.extend_from_slice(&rng.gen::<[u8; 32]>());
+    // cipher_suites: TLS_AES_128_GCM_SHA256, TLS_AES_256_GCM_SHA384,
+    // TLS_CHACHA20_POLY1305_SHA256
+    hello.extend_from_slice(&[0x00, 0x06, 0x13, 0x01, 0x13, 0x02, 0x13, 0x03]);
+    // legacy_compression_methods: null
+    hello.extend_from_slice(&[0x01, 0x00]);
+    push_u16_len(&mut hello, extensions.len());
+    hello.extend_from_slice(&extensions);
+
+    let mut handshake = vec![HANDSHAKE_TYPE_CLIENT_HELLO];
+    handshake.extend_from_slice(&u32::try_from(hello.len()).unwrap().to_be_bytes()[1..]);
+    handshake.extend_from_slice(&hello);
+
+    record(CONTENT_TYPE_HANDSHAKE, INITIAL_RECORD_VERSION, &handshake)
+}
+
+fn push_extension(extensions: &mut Vec<u8>, extension_type: u16, data: &[u8]) {
+    extensions.extend_from_slice(&extension_type.to_be_bytes());
+    push_u16_len(extensions, data.len());
+    extensions.extend_from_slice(data);
+}
+
+fn push_u16_len(buffer: &mut Vec<u8>, len: usize) {
+    let len = u16::try_from(len).expect("length is less than u16::MAX");
+    buffer.extend_from_slice(&len.to_be_bytes());
+}
+
+fn create_remote_socket(
+    ipv4: bool,
+    #[cfg(target_os = "linux")] fwmark: Option<u32>,
+) -> Result<TcpSocket> {
+    let socket = if ipv4 {
+        TcpSocket::new_v4()
+    } else {
+        TcpSocket::new_v6()
+    }
+    .map_err(Error::CreateTcpSocket)?;
+    #[cfg(target_os = "linux")]
+    if let Some(fwmark) = fwmark {
+        setsockopt(socket.as_raw_fd(), sockopt::Mark, &fwmark).map_err(Error::SetFwmark)?;
+    }
+
+    Ok(socket)
+}
+
+async fn create_local_udp_socket(ipv4: bool) -> Result<(UdpSocket, SocketAddr)> {
+    let random_bind_addr = if ipv4 {
+        SocketAddr::new("127.0.0.1".parse().unwrap(), 0)
+    } else {
+        SocketAddr::new("::1".parse().unwrap(), 0)
+    };
+    let local_udp_socket = UdpSocket::bind(random_bind_addr)
+        .await
+        .map_err(Error::BindUdp)?;
+    let udp_client_addr = local_udp_socket
+        .local_addr()
+        .map_err(Error::GetUdpLocalAddress)?;
+
+    Ok((local_udp_socket, udp_client_addr))
+}
+
+/// Wait for a client to connect to `udp_listener` and connect the socket to that address
+async fn wait_for_local_udp_client(udp_listener: &UdpSocket) -> io::Result<()> {
+    log::trace!("Waiting for UDP socket client");
+    let client_addr = udp_listener.peek_sender().await?;
+
+    log::trace!("UDP connection from {client_addr}");
+    udp_listener.connect(client_addr).await
+}
+
+#[async_trait]
+impl Obfuscator for Tls {
+    fn endpoint(&self) -> SocketAddr {
+        self.udp_client_addr
+    }
+
+    async fn run(self: Box<Self>) -> crate::Result<()> {
+        match self.server.await {
+            Ok(result) => result.map_err(crate::Error::RunTlsObfuscator),
+            Err(_err) if _err.is_cancelled() => Ok(()),
+            Err(_err) => panic!("server handle panicked"),
+        }
+    }
+
+    #[cfg(target_os = "android")]
+    fn remote_socket_fd(&self) -> std::os::unix::io::RawFd {
+        self.outbound_fd
+    }
+
+    fn packet_overhead(&self) -> u16 {
+        let max_tcp_header_len = 60; // https://datatracker.ietf.org/doc/html/rfc9293#section-3.1-6.22.1
+        let udp_header_len = 8; // https://datatracker.ietf.org/doc/html/rfc768
+
+        let overhead = max_tcp_header_len - udp_header_len + RECORD_HEADER_LEN;
+
+        u16::try_from(overhead).expect("packet overhead is less than u16::MAX")
+    }
+}
+
+fn is_fatal_socket_io_error(error: &io::Error) -> bool {
+    matches!(
+        error.kind(),
+        io::ErrorKind::NotConnected
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+    )
+}
@@ -87,14 +87,20 @@ impl Obfuscator for Udp2Tcp {
     }
 
     fn packet_overhead(&self) -> u16 {
-        let max_tcp_header_len = 60; // https://datatracker.ietf.org/doc/html/rfc9293#section-3.1-6.22.1
-        let udp_header_len = 8; // https://datatracker.ietf.org/doc/html/rfc768
+        tcp_packet_overhead()
+    }
+}
 
-        // TODO: Make `HEADER_LEN` constant public in udp-over-tcp lib and use it instead
-        let udp_over_tcp_header_len = size_of::<u16>();
+/// Number of bytes that are added to each packet when it is sent over TCP with udp-over-tcp
+/// framing instead of over UDP.
+pub fn tcp_packet_overhead() -> u16 {
+    let max_tcp_header_len = 60; // https://datatracker.ietf.org/doc/html/rfc9293#section-3.1-6.22.1
+    let udp_header_len = 8; // https://datatracker.ietf.org/doc/html/rfc768
 
-        let overhead = max_tcp_header_len - udp_header_len + udp_over_tcp_header_len;
+    // TODO: Make `HEADER_LEN` constant public in udp-over-tcp lib and use it instead
+    let udp_over_tcp_header_len = size_of::<u16>();
 
-        u16::try_from(overhead).expect("packet overhead is less than u16::MAX")
-    }
+    let overhead = max_tcp_header_len - udp_header_len + udp_over_tcp_header_len;
+
+    u16::try_from(overhead).expect("packet overhead is less than u16::MAX")
 }
@@ -37,7 +37,7 @@ void Init::handleRequest(const std::vector<std::wstring> &arguments)
 		timeout = wcstoul(keyvalue.begin()->second.c_str(), nullptr, 10);
 	}
 
-	auto success = WinFw_Initialize(timeout, &Init::ErrorForwarder, this);
+	auto success = WinFw_Initialize(timeout, MAXUINT16, &Init::ErrorForwarder, this);
 
 	m_messageSink((success
 		? L"Initialization completed successfully."
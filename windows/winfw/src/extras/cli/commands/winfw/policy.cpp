@@ -17,6 +17,13 @@ WinFwSettings CreateSettings(const std::wstring &dhcp, const std::wstring &lan)
 
 	s.permitDhcp = (0 == _wcsicmp(dhcp.c_str(), L"yes"));
 	s.permitLan = (0 == _wcsicmp(lan.c_str(), L"yes"));
+	s.numLanNetworks = 0;
+	s.lanNetworks = nullptr;
+	s.numExceptions = 0;
+	s.exceptions = nullptr;
+	s.numAllowedIncoming = 0;
+	s.allowedIncoming = nullptr;
+	s.blockIpv6 = false;
 
 	return s;
 }
@@ -0,0 +1,322 @@
+#include "stdafx.h"
+#include "diagnostics.h"
+#include "mullvadguids.h"
+#include "guidhash.h"
+#include "libwfp/objectenumerator.h"
+#include <libcommon/string.h>
+#include <algorithm>
+#include <sstream>
+#include <unordered_map>
+#include <unordered_set>
+#include <vector>
+
+namespace
+{
+
+struct SublayerInfo
+{
+	GUID key;
+	std::wstring name;
+	uint16_t weight;
+	std::wstring provider;
+};
+
+std::wstring DisplayName(const FWPM_DISPLAY_DATA0 &displayData)
+{
+	if (nullptr == displayData.name)
+	{
+		return L"(unnamed)";
+	}
+
+	return displayData.name;
+}
+
+std::wstring ConditionFieldName(const GUID &fieldKey)
+{
+	static const std::unordered_map<GUID, std::wstring> names =
+	{
+		{ FWPM_CONDITION_IP_REMOTE_ADDRESS, L"remote address" },
+		{ FWPM_CONDITION_IP_LOCAL_ADDRESS, L"local address" },
+		{ FWPM_CONDITION_IP_REMOTE_PORT, L"remote port" },
+		{ FWPM_CONDITION_IP_LOCAL_PORT, L"local port" },
+		{ FWPM_CONDITION_IP_PROTOCOL, L"protocol" },
+		{ FWPM_CONDITION_ALE_APP_ID, L"application" },
+		{ FWPM_CONDITION_ALE_USER_ID, L"user" },
+		{ FWPM_CONDITION_IP_LOCAL_INTERFACE, L"local interface" },
+		{ FWPM_CONDITION_INTERFACE_TYPE, L"interface type" },
+		{ FWPM_CONDITION_FLAGS, L"flags" },
+		{ FWPM_CONDITION_ICMP_TYPE, L"icmp type" },
+		{ FWPM_CONDITION_ICMP_CODE, L"icmp code" },
+	};
+
+	const auto name = names.find(fieldKey);
+
+	if (names.end() == name)
+	{
+		return common::string::FormatGuid(fieldKey);
+	}
+
+	return name->second;
+}
+
+std::wstring MatchTypeName(FWP_MATCH_TYPE matchType)
+{
+	switch (matchType)
+	{
+	case FWP_MATCH_EQUAL: return L"==";
+	case FWP_MATCH_GREATER: return L">";
+	case FWP_MATCH_LESS: return L"<";
+	case FWP_MATCH_GREATER_OR_EQUAL: return L">=";
+	case FWP_MATCH_LESS_OR_EQUAL: return L"<=";
+	case FWP_MATCH_RANGE: return L"in";
+	case FWP_MATCH_FLAGS_ALL_SET: return L"has all flags";
+	case FWP_MATCH_FLAGS_ANY_SET: return L"has any flag";
+	case FWP_MATCH_FLAGS_NONE_SET: return L"has no flags";
+	case FWP_MATCH_EQUAL_CASE_INSENSITIVE: return L"==";
+	case FWP_MATCH_NOT_EQUAL: return L"!=";
+	case FWP_MATCH_PREFIX: return L"starts with";
+	case FWP_MATCH_NOT_PREFIX: return L"does not start with";
+	default: return L"?";
+	}
+}
+
+//
+// FWP_VALUE0 and FWP_CONDITION_VALUE0 share the members used here.
+//
+template<typename T>
+std::wstring FormatValue(const T &value)
+{
+	std::wstringstream ss;
+
+	switch (value.type)
+	{
+	case FWP_EMPTY:
+	{
+		ss << L"(empty)";
+		break;
+	}
+	case FWP_UINT8:
+	{
+		ss << static_cast<uint32_t>(value.uint8);
+		break;
+	}
+	case FWP_UINT16:
+	{
+		ss << value.uint16;
+		break;
+	}
+	case FWP_UINT32:
+	{
+		ss << value.uint32;
+		break;
+	}
+	case FWP_UINT64:
+	{
+		ss << *value.uint64;
+		break;
+	}
+	case FWP_BYTE_ARRAY16_TYPE:
+	{
+		ss << common::string::FormatIpv6(value.byteArray16->byteArray16);
+		break;
+	}
+	case FWP_BYTE_BLOB_TYPE:
+	{
+		//
+		// Application IDs are stored as null-terminated wide strings.
+		//
+		ss << std::wstring(reinterpret_cast<const wchar_t *>(value.byteBlob->data),
+			value.byteBlob->size / sizeof(wchar_t)).c_str();
+		break;
+	}
+	case FWP_SID:
+	{
+		ss << common::string::FormatSid(*value.sid);
+		break;
+	}
+	default:
+	{
+		ss << L"(type " << value.type << L")";
+		break;
+	}
+	}
+
+	return ss.str();
+}
+
+std::wstring FormatConditionValue(const FWP_CONDITION_VALUE0 &value)
+{
+	std::wstringstream ss;
+
+	switch (value.type)
+	{
+	case FWP_V4_ADDR_MASK:
+	{
+		ss << common::string::FormatIpv4(value.v4AddrMask->addr)
+			<< L" mask " << common::string::FormatIpv4(value.v4AddrMask->mask);
+		break;
+	}
+	case FWP_V6_ADDR_MASK:
+	{
+		ss << common::string::FormatIpv6(value.v6AddrMask->addr)
+			<< L"/" << static_cast<uint32_t>(value.v6AddrMask->prefixLength);
+		break;
+	}
+	case FWP_RANGE_TYPE:
+	{
+		ss << FormatValue(value.rangeValue->valueLow)
+			<< L"-" << FormatValue(value.rangeValue->valueHigh);
+		break;
+	}
+	default:
+	{
+		return FormatValue(value);
+	}
+	}
+
+	return ss.str();
+}
+
+std::wstring ActionName(const FWPM_ACTION0 &action)
+{
+	switch (action.type)
+	{
+	case FWP_ACTION_BLOCK: return L"block";
+	case FWP_ACTION_PERMIT: return L"permit";
+	case FWP_ACTION_CALLOUT_TERMINATING: return L"callout terminating";
+	case FWP_ACTION_CALLOUT_INSPECTION: return L"callout inspection";
+	case FWP_ACTION_CALLOUT_UNKNOWN: return L"callout unknown";
+	default: return L"?";
+	}
+}
+
+std::wstring FormatWeight(const FWP_VALUE0 &weight)
+{
+	if (FWP_UINT64 == weight.type)
+	{
+		return std::to_wstring(*weight.uint64);
+	}
+
+	if (FWP_UINT8 == weight.type)
+	{
+		return std::to_wstring(weight.uint8) + L" (relative)";
+	}
+
+	return L"automatic";
+}
+
+} // anonymous namespace
+
+//static
+void Diagnostics::DescribeFilters(wfp::FilterEngine &engine, LineSink sink)
+{
+	const auto emit = [&](const std::wstringstream &ss)
+	{
+		sink(common::string::ToAnsi(ss.str()));
+	};
+
+	const std::unordered_set<GUID> mullvadSublayers =
+	{
+		MullvadGuids::SublayerBaseline(),
+		MullvadGuids::SublayerDns(),
+		MullvadGuids::SublayerPersistent(),
+	};
+
+	std::unordered_map<GUID, std::wstring> layerNames;
+
+	wfp::ObjectEnumerator::Layers(engine, [&](const FWPM_LAYER0 &layer)
+	{
+		layerNames[layer.layerKey] = DisplayName(layer.displayData);
+		return true;
+	});
+
+	std::vector<SublayerInfo> sublayers;
+
+	wfp::ObjectEnumerator::Sublayers(engine, [&](const FWPM_SUBLAYER0 &sublayer)
+	{
+		sublayers.push_back(SublayerInfo
+		{
+			sublayer.subLayerKey,
+			DisplayName(sublayer.displayData),
+			sublayer.weight,
+			nullptr != sublayer.providerKey
+				? common::string::FormatGuid(*sublayer.providerKey)
+				: std::wstring(L"none")
+		});
+		return true;
+	});
+
+	//
+	// Sublayers with a higher weight are evaluated first.
+	//
+	std::sort(sublayers.begin(), sublayers.end(), [](const SublayerInfo &lhs, const SublayerInfo &rhs)
+	{
+		return lhs.weight > rhs.weight;
+	});
+
+	std::unordered_map<GUID, std::wstring> sublayerNames;
+
+	sink("Sublayers:");
+
+	for (const auto &sublayer : sublayers)
+	{
+		sublayerNames[sublayer.key] = sublayer.name;
+
+		std::wstringstream ss;
+		ss << L"  " << sublayer.name << L" " << common::string::FormatGuid(sublayer.key)
+			<< L" weight=" << sublayer.weight
+			<< L" provider=" << sublayer.provider;
+		emit(ss);
+	}
+
+	const auto lookup = [](const std::unordered_map<GUID, std::wstring> &names, const GUID &key)
+	{
+		const auto name = names.find(key);
+		return names.end() == name ? common::string::FormatGuid(key) : name->second;
+	};
+
+	sink("Filters:");
+
+	wfp::ObjectEnumerator::Filters(engine, [&](const FWPM_FILTER0 &filter)
+	{
+		if (0 == mullvadSublayers.count(filter.subLayerKey)
+			&& FWP_ACTION_BLOCK != filter.action.type)
+		{
+			return true;
+		}
+
+		std::wstringstream ss;
+
+		ss << L"  " << DisplayName(filter.displayData) << L" " << common::string::FormatGuid(filter.filterKey);
+		emit(ss);
+
+		ss.str(std::wstring());
+		ss << L"    layer: " << lookup(layerNames, filter.layerKey);
+		emit(ss);
+
+		ss.str(std::wstring());
+		ss << L"    sublayer: " << lookup(sublayerNames, filter.subLayerKey);
+		emit(ss);
+
+		ss.str(std::wstring());
+		ss << L"    weight: " << FormatWeight(filter.effectiveWeight);
+		emit(ss);
+
+		ss.str(std::wstring());
+		ss << L"    action: " << ActionName(filter.action);
+		emit(ss);
+
+		for (UINT32 i = 0; i < filter.numFilterConditions; ++i)
+		{
+			const auto &condition = filter.filterCondition[i];
+
+			ss.str(std::wstring());
+			ss << L"    condition: " << ConditionFieldName(condition.fieldKey)
+				<< L" " << MatchTypeName(condition.matchType)
+				<< L" " << FormatConditionValue(condition.conditionValue);
+			emit(ss);
+		}
+
+		return true;
+	});
+}
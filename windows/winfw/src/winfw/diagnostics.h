@@ -0,0 +1,20 @@
+#pragma once
+
+#include "libwfp/filterengine.h"
+#include <functional>
+#include <string>
+
+class Diagnostics
+{
+public:
+
+	using LineSink = std::function<void(const std::string &line)>;
+
+	Diagnostics() = delete;
+
+	//
+	// Describe all sublayers, and all filters that are either installed in
+	// a Mullvad sublayer or that block traffic.
+	//
+	static void DescribeFilters(wfp::FilterEngine &engine, LineSink sink);
+};
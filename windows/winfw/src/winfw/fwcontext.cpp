@@ -1,5 +1,6 @@
 #include "stdafx.h"
 #include "fwcontext.h"
+#include "mullvadguids.h"
 #include "mullvadobjects.h"
 #include "objectpurger.h"
 #include "rules/ifirewallrule.h"
@@ -10,12 +11,16 @@
 #include "rules/baseline/permitdhcpserver.h"
 #include "rules/baseline/permitlan.h"
 #include "rules/baseline/permitlanservice.h"
+#include "rules/baseline/permitlannetwork.h"
 #include "rules/baseline/permitloopback.h"
 #include "rules/baseline/permitvpntunnel.h"
 #include "rules/baseline/permitvpntunnelservice.h"
 #include "rules/baseline/permitdns.h"
 #include "rules/baseline/permitendpoint.h"
+#include "rules/baseline/permitexception.h"
+#include "rules/baseline/permitincoming.h"
 #include "rules/dns/blockall.h"
+#include "rules/dns/blockipv6.h"
 #include "rules/dns/permitloopback.h"
 #include "rules/dns/permittunnel.h"
 #include "rules/dns/permitnontunnel.h"
@@ -64,6 +69,105 @@ void AppendSettingsRules
 		ruleset.emplace_back(std::make_unique<baseline::PermitLanService>());
 		ruleset.emplace_back(baseline::PermitDhcpServer::WithExtent(baseline::PermitDhcpServer::Extent::IPv4Only));
 	}
+	else
+	{
+		//
+		// User-defined LAN networks.
+		//
+
+		if (settings.numLanNetworks > MullvadGuids::NumLanNetworkFilters)
+		{
+			THROW_ERROR("Too many LAN networks");
+		}
+
+		for (uint32_t i = 0; i < settings.numLanNetworks; ++i)
+		{
+			const auto &lanNetwork = settings.lanNetworks[i];
+
+			std::optional<wfp::IpAddress> address;
+			std::optional<std::wstring> interfaceAlias;
+
+			if (nullptr != lanNetwork.ip)
+			{
+				address = wfp::IpAddress(lanNetwork.ip);
+			}
+
+			if (nullptr != lanNetwork.interfaceAlias)
+			{
+				interfaceAlias = lanNetwork.interfaceAlias;
+			}
+
+			ruleset.emplace_back(std::make_unique<baseline::PermitLanNetwork>(
+				i,
+				address,
+				lanNetwork.prefixLength,
+				interfaceAlias
+			));
+		}
+	}
+
+	//
+	// User-defined exceptions. DNS requests are still blocked in the DNS sublayer.
+	//
+
+	if (settings.numExceptions > MullvadGuids::NumExceptionFilters)
+	{
+		THROW_ERROR("Too many firewall exceptions");
+	}
+
+	for (uint32_t i = 0; i < settings.numExceptions; ++i)
+	{
+		const auto &exception = settings.exceptions[i];
+
+		std::optional<WinFwProtocol> protocol;
+		std::optional<uint16_t> port;
+
+		if (exception.filterProtocol)
+		{
+			protocol = exception.protocol;
+
+			if (0 != exception.port)
+			{
+				port = exception.port;
+			}
+		}
+
+		ruleset.emplace_back(std::make_unique<baseline::PermitException>(
+			i,
+			wfp::IpAddress(exception.ip),
+			exception.prefixLength,
+			protocol,
+			port
+		));
+	}
+
+	//
+	// User-defined inbound connections.
+	//
+
+	if (settings.numAllowedIncoming > MullvadGuids::NumIncomingFilters)
+	{
+		THROW_ERROR("Too many allowed incoming connections");
+	}
+
+	for (uint32_t i = 0; i < settings.numAllowedIncoming; ++i)
+	{
+		const auto &allowed = settings.allowedIncoming[i];
+
+		std::optional<std::wstring> interfaceAlias;
+
+		if (nullptr != allowed.interfaceAlias)
+		{
+			interfaceAlias = allowed.interfaceAlias;
+		}
+
+		ruleset.emplace_back(std::make_unique<baseline::PermitIncoming>(
+			i,
+			allowed.protocol,
+			allowed.port,
+			interfaceAlias
+		));
+	}
 
 	//
 	// DNS management
@@ -72,6 +176,16 @@ void AppendSettingsRules
 	ruleset.emplace_back(std::make_unique<baseline::PermitDns>());
 	ruleset.emplace_back(std::make_unique<dns::PermitLoopback>());
 	ruleset.emplace_back(std::make_unique<dns::BlockAll>());
+
+	//
+	// Blocking IPv6 must override every permit filter in the baseline sublayer,
+	// so it is also installed in the DNS sublayer.
+	//
+
+	if (settings.blockIpv6)
+	{
+		ruleset.emplace_back(std::make_unique<dns::BlockIpv6>());
+	}
 }
 
 //
@@ -133,9 +247,11 @@ void AppendNetBlockedRules(FwContext::Ruleset &ruleset)
 
 FwContext::FwContext
 (
-	uint32_t timeout
+	uint32_t timeout,
+	uint16_t sublayerWeight
 )
-	: m_baseline(0)
+	: m_sublayerWeight(sublayerWeight)
+	, m_baseline(0)
 	, m_activePolicy(Policy::None)
 {
 	auto engine = wfp::FilterEngine::StandardSession(timeout);
@@ -157,10 +273,12 @@ FwContext::FwContext
 FwContext::FwContext
 (
 	uint32_t timeout,
+	uint16_t sublayerWeight,
 	const WinFwSettings &settings,
 	const std::optional<WinFwAllowedEndpoint> &allowedEndpoint
 )
-	: m_baseline(0)
+	: m_sublayerWeight(sublayerWeight)
+	, m_baseline(0)
 	, m_activePolicy(Policy::None)
 {
 	auto engine = wfp::FilterEngine::StandardSession(timeout);
@@ -412,8 +530,8 @@ bool FwContext::applyCommonBaseConfiguration(SessionController &controller, wfp:
 	// Install structural objects
 	//
 	return controller.addProvider(*MullvadObjects::Provider())
-		&& controller.addSublayer(*MullvadObjects::SublayerBaseline())
-		&& controller.addSublayer(*MullvadObjects::SublayerDns());
+		&& controller.addSublayer(*MullvadObjects::SublayerBaseline(m_sublayerWeight))
+		&& controller.addSublayer(*MullvadObjects::SublayerDns(m_sublayerWeight));
 }
 
 bool FwContext::applyRuleset(const Ruleset &ruleset)
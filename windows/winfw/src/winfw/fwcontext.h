@@ -14,12 +14,13 @@ class FwContext
 {
 public:
 
-	FwContext(uint32_t timeout);
+	FwContext(uint32_t timeout, uint16_t sublayerWeight);
 
 	// This ctor applies the "blocked" policy.
 	FwContext
 	(
 		uint32_t timeout,
+		uint16_t sublayerWeight,
 		const WinFwSettings &settings,
 		const std::optional<WinFwAllowedEndpoint> &allowedEndpoint
 	);
@@ -79,6 +80,7 @@ private:
 
 	std::unique_ptr<SessionController> m_sessionController;
 
+	uint16_t m_sublayerWeight;
 	uint32_t m_baseline;
 	Policy m_activePolicy;
 };
@@ -1,5 +1,6 @@
 #include "stdafx.h"
 #include "mullvadguids.h"
+#include <libcommon/error.h>
 #include <algorithm>
 #include <iterator>
 
@@ -130,6 +131,26 @@ MullvadGuids::DetailedIdentityRegistry MullvadGuids::DetailedRegistry(IdentityQu
 	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitDhcpServer_Outbound_Response_Ipv4()));
 	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitVpnRelay()));
 	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitEndpoint()));
+
+	for (size_t i = 0; i < NumLanNetworkFilters; ++i)
+	{
+		registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitLanNetwork_Outbound_Ipv4(i)));
+		registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitLanNetwork_Outbound_Ipv6(i)));
+		registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitLanNetwork_Inbound_Ipv4(i)));
+		registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitLanNetwork_Inbound_Ipv6(i)));
+	}
+
+	for (size_t i = 0; i < NumExceptionFilters; ++i)
+	{
+		registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitException(i)));
+	}
+
+	for (size_t i = 0; i < NumIncomingFilters; ++i)
+	{
+		registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitIncoming_Ipv4(i)));
+		registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitIncoming_Ipv6(i)));
+	}
+
 	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitVpnTunnel_Outbound_Ipv4_1()));
 	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitVpnTunnel_Outbound_Ipv6_1()));
 	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Baseline_PermitVpnTunnel_Outbound_Ipv4_2()));
@@ -155,6 +176,10 @@ MullvadGuids::DetailedIdentityRegistry MullvadGuids::DetailedRegistry(IdentityQu
 	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Dns_PermitNonTunnel_Outbound_Ipv6()));
 	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Dns_PermitTunnel_Outbound_Ipv4()));
 	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Dns_PermitTunnel_Outbound_Ipv6()));
+	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Dns_BlockIpv6_PermitLoopback_Outbound()));
+	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Dns_BlockIpv6_PermitLoopback_Inbound()));
+	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Dns_BlockIpv6_Outbound()));
+	registry.insert(std::make_pair(WfpObjectType::Filter, Filter_Dns_BlockIpv6_Inbound()));
 
 	if (IdentityQualifier::IncludePersistent == (qualifier & IdentityQualifier::IncludePersistent))
 	{
@@ -666,6 +691,223 @@ const GUID &MullvadGuids::Filter_Baseline_PermitEndpoint()
 	return g;
 }
 
+//static
+const GUID &MullvadGuids::Filter_Baseline_PermitLanNetwork_Outbound_Ipv4(size_t index)
+{
+	static const GUID g[NumLanNetworkFilters] =
+	{
+		{ 0x736dfcb2, 0xd87, 0x43df, { 0xa2, 0x22, 0x98, 0xd1, 0x2d, 0x3, 0xda, 0x96 } },
+		{ 0x3fd74c0, 0xcc02, 0x48eb, { 0xb6, 0x38, 0x9e, 0xfc, 0x59, 0xed, 0xa2, 0x44 } },
+		{ 0xf344b856, 0x6617, 0x41d6, { 0xb6, 0x52, 0x1c, 0xd6, 0x13, 0x98, 0x12, 0xa6 } },
+		{ 0x8c60cbd9, 0xd1ba, 0x464e, { 0xaf, 0x54, 0xeb, 0x6b, 0xe2, 0x22, 0x24, 0xc2 } },
+		{ 0x574be754, 0x8d0c, 0x4ee1, { 0xb1, 0x21, 0x71, 0x25, 0x7b, 0x20, 0x54, 0xf5 } },
+		{ 0xe83d4077, 0x70ce, 0x4012, { 0x89, 0x90, 0x7a, 0xc5, 0xb, 0x54, 0xac, 0xbe } },
+		{ 0x6597c045, 0x8804, 0x4596, { 0xb5, 0x9d, 0xfe, 0xb8, 0xb2, 0xb0, 0xb2, 0x24 } },
+		{ 0xe71c5dfb, 0xb34, 0x4617, { 0xae, 0xd2, 0xdf, 0xac, 0xb, 0xfb, 0x4d, 0x4 } },
+		{ 0xce71561d, 0x14d1, 0x4207, { 0xb4, 0xd5, 0xa2, 0x37, 0xa4, 0x8f, 0xc7, 0x80 } },
+		{ 0xc9975361, 0xd974, 0x406b, { 0x9e, 0x29, 0x52, 0xf5, 0x32, 0xa3, 0xfa, 0xc0 } },
+		{ 0x1168c43b, 0xe7d9, 0x4099, { 0xb5, 0xf5, 0x6b, 0x20, 0x5e, 0x73, 0x1d, 0xe2 } },
+		{ 0xdb691ac, 0xe2eb, 0x48fd, { 0x9d, 0xa6, 0x3a, 0xbf, 0x81, 0x6d, 0x5d, 0x65 } },
+		{ 0xb8a07a58, 0xe77f, 0x4cd5, { 0xbd, 0x55, 0xdf, 0x93, 0x7c, 0xee, 0xe1, 0xe8 } },
+		{ 0x1ef78e41, 0x792a, 0x4c75, { 0xad, 0xa8, 0x84, 0x4c, 0x81, 0x69, 0xf0, 0xd0 } },
+		{ 0x8e0fb33c, 0xf308, 0x402d, { 0x9c, 0xcd, 0xbb, 0x15, 0x8a, 0xaa, 0x69, 0x92 } },
+		{ 0xd6013225, 0xe8de, 0x4c56, { 0x90, 0x85, 0x41, 0x74, 0xad, 0xad, 0x30, 0x9c } }
+	};
+
+	if (index >= NumLanNetworkFilters)
+	{
+		THROW_ERROR("LAN network filter index is out of range");
+	}
+
+	return g[index];
+}
+
+//static
+const GUID &MullvadGuids::Filter_Baseline_PermitLanNetwork_Outbound_Ipv6(size_t index)
+{
+	static const GUID g[NumLanNetworkFilters] =
+	{
+		{ 0xdbfdd65a, 0x9eca, 0x41c4, { 0xa1, 0xf0, 0xcc, 0xc4, 0xa2, 0x73, 0x63, 0xf8 } },
+		{ 0xa2619317, 0xed92, 0x4421, { 0xad, 0x79, 0x50, 0x6e, 0x1d, 0xe8, 0xcb, 0x2 } },
+		{ 0x7a638edc, 0xc57c, 0x4313, { 0x8e, 0xbb, 0xb1, 0x4f, 0x81, 0xb0, 0x80, 0xba } },
+		{ 0x26d4b9e6, 0xbe19, 0x46af, { 0x90, 0x4, 0xca, 0xa9, 0xae, 0x7f, 0x7a, 0x9c } },
+		{ 0x561a1afd, 0xbdb2, 0x47b8, { 0x92, 0xa5, 0x3d, 0x10, 0x6a, 0xd4, 0x8d, 0xca } },
+		{ 0xf1fba04b, 0x19b3, 0x4a1e, { 0xa0, 0xa5, 0x54, 0x63, 0xf5, 0xfa, 0xed, 0x2 } },
+		{ 0x30244870, 0x1d6e, 0x40b1, { 0xa8, 0x60, 0x84, 0x16, 0x75, 0x58, 0x5d, 0x77 } },
+		{ 0x5d74c32, 0x4baa, 0x4e8c, { 0xb4, 0x57, 0xc4, 0x70, 0xea, 0x51, 0x13, 0xa0 } },
+		{ 0x93058db6, 0x7973, 0x49c8, { 0xad, 0x59, 0x95, 0x85, 0xa1, 0xde, 0x3b, 0xb4 } },
+		{ 0xee42652c, 0x1, 0x4501, { 0xac, 0x67, 0x12, 0xa4, 0xe3, 0xd5, 0xc3, 0x9c } },
+		{ 0x471967aa, 0x9588, 0x4d63, { 0xa1, 0x55, 0xf7, 0xcf, 0xe1, 0x6b, 0xbd, 0xe1 } },
+		{ 0xbcd84bd, 0x6891, 0x43b0, { 0xbd, 0x2a, 0xe3, 0x35, 0xfc, 0x9a, 0x97, 0x27 } },
+		{ 0xf29a23bf, 0x6f03, 0x4e61, { 0xbc, 0x80, 0xc6, 0x1, 0x87, 0xe7, 0xa4, 0x66 } },
+		{ 0x9e8d0b3e, 0xc16a, 0x41d3, { 0x87, 0x5a, 0xd5, 0x59, 0x96, 0x4e, 0x86, 0x7d } },
+		{ 0xa8d58a1b, 0xf58, 0x4f91, { 0x9b, 0xce, 0x68, 0x93, 0x68, 0x93, 0xa2, 0x2b } },
+		{ 0x9eab1ba1, 0x3721, 0x4aee, { 0xa4, 0xe3, 0x69, 0x97, 0xf2, 0x59, 0x57, 0xd } }
+	};
+
+	if (index >= NumLanNetworkFilters)
+	{
+		THROW_ERROR("LAN network filter index is out of range");
+	}
+
+	return g[index];
+}
+
+//static
+const GUID &MullvadGuids::Filter_Baseline_PermitLanNetwork_Inbound_Ipv4(size_t index)
+{
+	static const GUID g[NumLanNetworkFilters] =
+	{
+		{ 0xa39b5e0d, 0xa4e, 0x4687, { 0x96, 0x50, 0x79, 0x5f, 0x98, 0x3f, 0x4e, 0xa0 } },
+		{ 0x6905ede7, 0x1b7b, 0x4159, { 0x8c, 0xc9, 0x3a, 0x32, 0x4f, 0xfd, 0x5e, 0xb } },
+		{ 0xf24ae22b, 0x6fe7, 0x48e8, { 0x8f, 0xc9, 0x55, 0xb5, 0xd8, 0x15, 0xd2, 0xd2 } },
+		{ 0x3694adeb, 0xeee7, 0x4c2a, { 0xb3, 0x60, 0x3b, 0xe1, 0x5b, 0x70, 0x29, 0xfa } },
+		{ 0x1fce2914, 0xf5a9, 0x420b, { 0xba, 0xed, 0xe8, 0x4, 0x6c, 0xca, 0x9a, 0xea } },
+		{ 0x1ad23eb6, 0x5212, 0x48a2, { 0x99, 0x63, 0xcf, 0x8a, 0xeb, 0x33, 0x1b, 0xee } },
+		{ 0x35c0ca19, 0x99ac, 0x4a9f, { 0x8a, 0xbe, 0xf, 0x37, 0x4c, 0xa6, 0xf7, 0xde } },
+		{ 0x85553586, 0x35a0, 0x4e79, { 0xbf, 0x33, 0xc7, 0xdd, 0x50, 0x8f, 0xf3, 0x5c } },
+		{ 0x8951771c, 0xd5d1, 0x45a8, { 0x9c, 0x2d, 0x8a, 0x4c, 0xcb, 0x4f, 0xb1, 0x98 } },
+		{ 0xbcc7b062, 0xefe7, 0x48d7, { 0x97, 0xd3, 0x44, 0xe, 0xfb, 0xfe, 0x81, 0xa9 } },
+		{ 0xb787ab59, 0x92e5, 0x4650, { 0xa7, 0xe6, 0x80, 0x3, 0x5c, 0xc2, 0xac, 0x2 } },
+		{ 0x32a0814a, 0x72fb, 0x47ec, { 0xbf, 0x22, 0x3, 0xed, 0x90, 0x80, 0x89, 0x20 } },
+		{ 0xc03ef272, 0x598d, 0x4679, { 0xb5, 0x8c, 0x5c, 0xdc, 0xbf, 0x6a, 0x80, 0xff } },
+		{ 0x8c9d1558, 0xc2f5, 0x4ba8, { 0xa8, 0xdf, 0x10, 0x3, 0xc6, 0xd0, 0x18, 0xd3 } },
+		{ 0xf58887a1, 0xdd2, 0x440b, { 0x82, 0x36, 0x89, 0x5d, 0x75, 0x4b, 0x45, 0xaa } },
+		{ 0x28f0e568, 0x6956, 0x4c22, { 0x8e, 0x46, 0xfa, 0x7e, 0x94, 0xdb, 0x35, 0x1d } }
+	};
+
+	if (index >= NumLanNetworkFilters)
+	{
+		THROW_ERROR("LAN network filter index is out of range");
+	}
+
+	return g[index];
+}
+
+//static
+const GUID &MullvadGuids::Filter_Baseline_PermitLanNetwork_Inbound_Ipv6(size_t index)
+{
+	static const GUID g[NumLanNetworkFilters] =
+	{
+		{ 0x995cb5a3, 0x83a1, 0x49e3, { 0x8c, 0xe1, 0x2b, 0x74, 0x82, 0xe3, 0xef, 0xac } },
+		{ 0x2d7bb0c5, 0x473d, 0x4935, { 0x9c, 0xfe, 0x2, 0x79, 0xc9, 0xb3, 0x1e, 0xd9 } },
+		{ 0x868943c7, 0x2ac7, 0x4c3e, { 0xa0, 0x55, 0x63, 0xa2, 0xf4, 0x7d, 0x66, 0xc6 } },
+		{ 0x42f70352, 0x2d0e, 0x4414, { 0x9c, 0x33, 0x2c, 0xd3, 0x58, 0x66, 0xd1, 0xa2 } },
+		{ 0xd2dd8abd, 0xb8ae, 0x442f, { 0x89, 0xef, 0x56, 0x8f, 0x43, 0x7d, 0xd0, 0x6a } },
+		{ 0xe68e7936, 0xd1a5, 0x4df4, { 0x8f, 0xa9, 0x1b, 0x92, 0xe2, 0xe, 0x1d, 0x5a } },
+		{ 0x6055062, 0xee1d, 0x46f9, { 0x9a, 0x80, 0xa, 0x6d, 0x2d, 0x8d, 0x47, 0x4e } },
+		{ 0x788ae525, 0x5934, 0x4204, { 0xa1, 0x19, 0x91, 0x18, 0x6, 0xf1, 0x37, 0xbf } },
+		{ 0x26710e5b, 0x3275, 0x4faf, { 0xa0, 0xb6, 0x59, 0xa, 0x3d, 0x4b, 0x40, 0x4a } },
+		{ 0x965cd0bc, 0xf29f, 0x4452, { 0x82, 0xb3, 0x72, 0xdc, 0xd9, 0xbf, 0x73, 0xe7 } },
+		{ 0x8376fde6, 0x1bed, 0x4bf6, { 0x8e, 0x76, 0x7e, 0xa7, 0xd6, 0xdb, 0x26, 0x94 } },
+		{ 0x8e5a9007, 0x66ab, 0x4b65, { 0x85, 0x52, 0xdf, 0x6e, 0xcc, 0x76, 0x5e, 0x8f } },
+		{ 0x724730d0, 0xfb3, 0x4613, { 0xb4, 0x98, 0xd9, 0xdd, 0x2, 0x66, 0xf9, 0xf5 } },
+		{ 0xc83a929d, 0xf468, 0x411e, { 0x90, 0xc8, 0x72, 0xac, 0x5d, 0x69, 0x9, 0x20 } },
+		{ 0xdf26c578, 0xbeec, 0x48c0, { 0xb3, 0xe8, 0x45, 0x9b, 0x93, 0x8, 0xf5, 0x42 } },
+		{ 0x212f2323, 0xc98e, 0x4877, { 0xa4, 0x56, 0xd5, 0xba, 0xe4, 0x7a, 0xc1, 0x5d } }
+	};
+
+	if (index >= NumLanNetworkFilters)
+	{
+		THROW_ERROR("LAN network filter index is out of range");
+	}
+
+	return g[index];
+}
+
+//static
+const GUID &MullvadGuids::Filter_Baseline_PermitException(size_t index)
+{
+	static const GUID g[NumExceptionFilters] =
+	{
+		{ 0xde0e89ef, 0x1f8a, 0x4c6f, { 0xb1, 0xbb, 0x41, 0x87, 0xe4, 0xb5, 0x10, 0x10 } },
+		{ 0xe7798c2d, 0x5e91, 0x4c5f, { 0xad, 0x2c, 0xd, 0x9e, 0xd5, 0x10, 0x2c, 0x80 } },
+		{ 0x2d621b2f, 0x106f, 0x4769, { 0x9e, 0x5, 0x9e, 0x65, 0x32, 0xa4, 0x51, 0x5c } },
+		{ 0xe4c1ee73, 0x9618, 0x488d, { 0xbf, 0x86, 0x8b, 0x77, 0xf0, 0xc2, 0x7f, 0x91 } },
+		{ 0x22f8992a, 0x7895, 0x4a89, { 0xae, 0xe4, 0xdc, 0xe7, 0xd0, 0xec, 0x21, 0xdc } },
+		{ 0x42d41301, 0xadbb, 0x414a, { 0x90, 0x16, 0x9, 0xe5, 0x2f, 0xe1, 0x93, 0xce } },
+		{ 0xd645b2be, 0xc1d4, 0x44ce, { 0xb3, 0x4, 0x3, 0x90, 0x20, 0xb2, 0x7a, 0xbb } },
+		{ 0x2592c984, 0x494c, 0x4517, { 0xa8, 0xef, 0x76, 0xf0, 0x3c, 0x3c, 0x42, 0x77 } },
+		{ 0xd3edefc6, 0xc63c, 0x4af2, { 0x8f, 0x89, 0x81, 0xa, 0xc9, 0x40, 0x3, 0x13 } },
+		{ 0x91f34008, 0xe4a, 0x4a85, { 0xa7, 0xb6, 0xcd, 0xbc, 0x43, 0x94, 0x4d, 0x70 } },
+		{ 0x460c8dbb, 0xceb5, 0x4be0, { 0xa7, 0x68, 0xbe, 0x57, 0x17, 0x75, 0x39, 0x6f } },
+		{ 0xcf35f005, 0xf7dd, 0x4697, { 0x92, 0x6a, 0xcc, 0x9, 0x97, 0x5f, 0xf6, 0x59 } },
+		{ 0x83d1d4ed, 0x5fc4, 0x44fe, { 0x8a, 0xd4, 0x55, 0x16, 0x73, 0xa1, 0xd0, 0xbf } },
+		{ 0x93cfcec6, 0x7975, 0x488e, { 0xa1, 0x86, 0xb2, 0xb8, 0xd5, 0xca, 0xe6, 0x28 } },
+		{ 0x1d734517, 0x1fe5, 0x4498, { 0x94, 0xd0, 0x4b, 0x19, 0xf8, 0x14, 0x5c, 0x97 } },
+		{ 0x1724f1a1, 0x46c1, 0x415e, { 0x88, 0xce, 0xfe, 0x33, 0xd1, 0xec, 0x97, 0x85 } }
+	};
+
+	if (index >= NumExceptionFilters)
+	{
+		THROW_ERROR("Exception filter index is out of range");
+	}
+
+	return g[index];
+}
+
+//static
+const GUID &MullvadGuids::Filter_Baseline_PermitIncoming_Ipv4(size_t index)
+{
+	static const GUID g[NumIncomingFilters] =
+	{
+		{ 0x871e379f, 0x251e, 0x40c8, { 0xba, 0xa9, 0x73, 0xf7, 0x68, 0xd, 0x33, 0xc7 } },
+		{ 0x3d04809a, 0x7612, 0x4eb4, { 0xb7, 0xb3, 0x98, 0xf9, 0xe4, 0xa4, 0x5a, 0x39 } },
+		{ 0x18b5a1a9, 0xc0a5, 0x4bf2, { 0xb5, 0x94, 0x6a, 0x3c, 0xbd, 0xfd, 0xe6, 0x3c } },
+		{ 0xfaaca3ff, 0xcdc, 0x44b4, { 0xbb, 0x2c, 0x78, 0x39, 0x17, 0xa9, 0x3, 0x37 } },
+		{ 0xe1db5b25, 0x8185, 0x4cb3, { 0xab, 0x13, 0xd9, 0xb5, 0xec, 0x85, 0xf8, 0xdb } },
+		{ 0x6432478a, 0xe244, 0x4be9, { 0x89, 0xe9, 0x3f, 0xe7, 0xfc, 0x6e, 0x23, 0x7a } },
+		{ 0xed49efd2, 0x5109, 0x44a0, { 0x8a, 0x8e, 0x34, 0x41, 0x8f, 0x5b, 0x5a, 0x64 } },
+		{ 0x21f182f6, 0x25b6, 0x4098, { 0x85, 0xd5, 0x3d, 0x8, 0xda, 0xba, 0x3b, 0xa } },
+		{ 0x5575495d, 0xaf46, 0x4d9f, { 0x85, 0xcd, 0xb5, 0x87, 0x99, 0x8a, 0x49, 0x51 } },
+		{ 0x89e667dd, 0xb8c3, 0x4337, { 0xaa, 0x9c, 0x3d, 0xa8, 0x98, 0xe5, 0x34, 0x47 } },
+		{ 0x44efcec0, 0xbe7f, 0x427e, { 0xa9, 0xd7, 0x91, 0xdf, 0x24, 0x9a, 0x59, 0x42 } },
+		{ 0xca143d9b, 0x7d40, 0x4067, { 0xa3, 0x95, 0xcd, 0x7d, 0xec, 0x21, 0x91, 0x99 } },
+		{ 0xad987059, 0x5d34, 0x4582, { 0xa7, 0xf1, 0x7, 0xc0, 0x96, 0x18, 0xb3, 0xdc } },
+		{ 0x47ee2664, 0x6a50, 0x4352, { 0x84, 0x82, 0x55, 0x83, 0xae, 0x7c, 0xd1, 0x5a } },
+		{ 0x40a55a9d, 0x7dcc, 0x472d, { 0x8f, 0x5e, 0x37, 0xa1, 0x28, 0xd5, 0xb, 0x2c } },
+		{ 0x3c36dd76, 0x3988, 0x4bfc, { 0x96, 0xba, 0x4a, 0xda, 0x85, 0xbf, 0x82, 0xc7 } }
+	};
+
+	if (index >= NumIncomingFilters)
+	{
+		THROW_ERROR("Incoming filter index is out of range");
+	}
+
+	return g[index];
+}
+
+//static
+const GUID &MullvadGuids::Filter_Baseline_PermitIncoming_Ipv6(size_t index)
+{
+	static const GUID g[NumIncomingFilters] =
+	{
+		{ 0xff26f72b, 0x416b, 0x4293, { 0xab, 0x28, 0xe2, 0x0, 0xc1, 0xf3, 0xb, 0x6d } },
+		{ 0x91d858d0, 0x34ac, 0x4ddb, { 0x9b, 0xbd, 0x2e, 0x61, 0x6f, 0xe9, 0x7, 0xa5 } },
+		{ 0x292ccd1d, 0x9374, 0x4250, { 0xb5, 0x80, 0x70, 0x19, 0x4a, 0xc7, 0x11, 0xd3 } },
+		{ 0x33ff7ce6, 0x91ff, 0x4317, { 0xb2, 0x11, 0x93, 0x7e, 0xa4, 0x8f, 0x3f, 0x85 } },
+		{ 0xd313b380, 0xb7a, 0x4b06, { 0xbd, 0x6a, 0xe2, 0x90, 0x7d, 0xfc, 0xd0, 0xd2 } },
+		{ 0xd5592835, 0xb71c, 0x45be, { 0x82, 0x6d, 0x6d, 0xdd, 0x6f, 0x6f, 0x64, 0x2e } },
+		{ 0x97a73a09, 0x55d1, 0x4a29, { 0x93, 0x14, 0x70, 0xb1, 0xe1, 0x7, 0xc0, 0x7b } },
+		{ 0x198bf61a, 0x1cff, 0x4553, { 0xab, 0xb3, 0x2c, 0x2, 0xec, 0xd7, 0xad, 0xaa } },
+		{ 0x60c2e43d, 0x3712, 0x4199, { 0xb9, 0x96, 0xd5, 0x13, 0x6b, 0x49, 0x45, 0x65 } },
+		{ 0xc0e55f7d, 0xbe74, 0x491a, { 0x91, 0xe, 0xed, 0x21, 0x89, 0xca, 0x7, 0x7a } },
+		{ 0x32cca5b4, 0xe9a9, 0x41d6, { 0xaf, 0x31, 0x24, 0xc2, 0xf3, 0x1b, 0xb0, 0xe7 } },
+		{ 0x98f93c61, 0x95f5, 0x4b45, { 0x9c, 0x8, 0xb4, 0xb3, 0x88, 0x98, 0x57, 0xbb } },
+		{ 0xd227e540, 0x5cf7, 0x47b6, { 0xbe, 0xf3, 0xd4, 0x57, 0x14, 0x69, 0xe8, 0xb2 } },
+		{ 0x2e98bc3e, 0xc93a, 0x4a0e, { 0xae, 0xf3, 0x9, 0x2d, 0x7e, 0x41, 0x73, 0x74 } },
+		{ 0x1153c7ad, 0x7643, 0x48ca, { 0xbd, 0x46, 0xee, 0x65, 0xf5, 0x46, 0x3a, 0xe8 } },
+		{ 0x663f259a, 0x5bd1, 0x49b2, { 0xb9, 0xec, 0xc6, 0x20, 0xeb, 0xbc, 0xcc, 0xdd } }
+	};
+
+	if (index >= NumIncomingFilters)
+	{
+		THROW_ERROR("Incoming filter index is out of range");
+	}
+
+	return g[index];
+}
+
 //static
 const GUID &MullvadGuids::Filter_Baseline_PermitVpnTunnel_Outbound_Ipv4_1()
 {
@@ -1016,3 +1258,59 @@ const GUID &MullvadGuids::Filter_Dns_PermitLoopback_Outbound_Ipv6()
 
 	return g;
 }
+
+//static
+const GUID &MullvadGuids::Filter_Dns_BlockIpv6_PermitLoopback_Outbound()
+{
+	static const GUID g =
+	{
+		0x61abb9ac,
+		0x39b0,
+		0x4345,
+		{ 0x92, 0x22, 0x64, 0xb4, 0xa1, 0xc8, 0x29, 0xb0 }
+	};
+
+	return g;
+}
+
+//static
+const GUID &MullvadGuids::Filter_Dns_BlockIpv6_PermitLoopback_Inbound()
+{
+	static const GUID g =
+	{
+		0x6352e8ae,
+		0xb1e6,
+		0x4d21,
+		{ 0x8d, 0x73, 0x2d, 0x61, 0x21, 0xc9, 0x86, 0x2b }
+	};
+
+	return g;
+}
+
+//static
+const GUID &MullvadGuids::Filter_Dns_BlockIpv6_Outbound()
+{
+	static const GUID g =
+	{
+		0x6b374efe,
+		0x4c73,
+		0x4be7,
+		{ 0x9c, 0xbe, 0xed, 0x6f, 0xb1, 0x5e, 0xac, 0x12 }
+	};
+
+	return g;
+}
+
+//static
+const GUID &MullvadGuids::Filter_Dns_BlockIpv6_Inbound()
+{
+	static const GUID g =
+	{
+		0x673137ff,
+		0x2b4a,
+		0x43f0,
+		{ 0xb1, 0x9e, 0xb7, 0x23, 0x38, 0x23, 0xb1, 0xbb }
+	};
+
+	return g;
+}
@@ -54,6 +54,13 @@ public:
 	static const GUID &Filter_Baseline_PermitLanService_Inbound_Ipv4();
 	static const GUID &Filter_Baseline_PermitLanService_Inbound_Ipv6();
 
+	// There are up to four filters per user-defined LAN network, up to `NumLanNetworkFilters`.
+	static constexpr size_t NumLanNetworkFilters = 16;
+	static const GUID &Filter_Baseline_PermitLanNetwork_Outbound_Ipv4(size_t index);
+	static const GUID &Filter_Baseline_PermitLanNetwork_Outbound_Ipv6(size_t index);
+	static const GUID &Filter_Baseline_PermitLanNetwork_Inbound_Ipv4(size_t index);
+	static const GUID &Filter_Baseline_PermitLanNetwork_Inbound_Ipv6(size_t index);
+
 	static const GUID &Filter_Baseline_PermitLoopback_Outbound_Ipv4();
 	static const GUID &Filter_Baseline_PermitLoopback_Inbound_Ipv4();
 	static const GUID &Filter_Baseline_PermitLoopback_Outbound_Ipv6();
@@ -71,6 +78,15 @@ public:
 
 	static const GUID &Filter_Baseline_PermitEndpoint();
 
+	// There is one filter per user-defined exception, up to `NumExceptionFilters`.
+	static constexpr size_t NumExceptionFilters = 16;
+	static const GUID &Filter_Baseline_PermitException(size_t index);
+
+	// There is one filter per IP version and allowed incoming connection, up to `NumIncomingFilters`.
+	static constexpr size_t NumIncomingFilters = 16;
+	static const GUID &Filter_Baseline_PermitIncoming_Ipv4(size_t index);
+	static const GUID &Filter_Baseline_PermitIncoming_Ipv6(size_t index);
+
 	static const GUID &Filter_Baseline_PermitVpnTunnel_Outbound_Ipv4_1();
 	static const GUID &Filter_Baseline_PermitVpnTunnel_Outbound_Ipv6_1();
 	static const GUID &Filter_Baseline_PermitVpnTunnel_Outbound_Ipv4_2();
@@ -100,6 +116,10 @@ public:
 	static const GUID &Filter_Dns_PermitTunnel_Outbound_Ipv6();
 	static const GUID &Filter_Dns_PermitLoopback_Outbound_Ipv4();
 	static const GUID &Filter_Dns_PermitLoopback_Outbound_Ipv6();
+	static const GUID &Filter_Dns_BlockIpv6_PermitLoopback_Outbound();
+	static const GUID &Filter_Dns_BlockIpv6_PermitLoopback_Inbound();
+	static const GUID &Filter_Dns_BlockIpv6_Outbound();
+	static const GUID &Filter_Dns_BlockIpv6_Inbound();
 
 	//
 	// Persistent and boot-time filters
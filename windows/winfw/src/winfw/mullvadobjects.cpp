@@ -16,7 +16,7 @@ std::unique_ptr<wfp::ProviderBuilder> MullvadObjects::Provider()
 }
 
 //static
-std::unique_ptr<wfp::SublayerBuilder> MullvadObjects::SublayerBaseline()
+std::unique_ptr<wfp::SublayerBuilder> MullvadObjects::SublayerBaseline(uint16_t weight)
 {
 	auto builder = std::make_unique<wfp::SublayerBuilder>();
 
@@ -25,13 +25,13 @@ std::unique_ptr<wfp::SublayerBuilder> MullvadObjects::SublayerBaseline()
 		.description(L"Filters that enforce a good baseline")
 		.key(MullvadGuids::SublayerBaseline())
 		.provider(MullvadGuids::Provider())
-		.weight(MAXUINT16);
+		.weight(weight);
 
 	return builder;
 }
 
 //static
-std::unique_ptr<wfp::SublayerBuilder> MullvadObjects::SublayerDns()
+std::unique_ptr<wfp::SublayerBuilder> MullvadObjects::SublayerDns(uint16_t weight)
 {
 	auto builder = std::make_unique<wfp::SublayerBuilder>();
 
@@ -40,7 +40,7 @@ std::unique_ptr<wfp::SublayerBuilder> MullvadObjects::SublayerDns()
 		.description(L"Filters that restrict DNS traffic")
 		.key(MullvadGuids::SublayerDns())
 		.provider(MullvadGuids::Provider())
-		.weight(MAXUINT16 - 1);
+		.weight(weight - 1);
 
 	return builder;
 }
@@ -60,7 +60,7 @@ std::unique_ptr<wfp::ProviderBuilder> MullvadObjects::ProviderPersistent()
 }
 
 //static
-std::unique_ptr<wfp::SublayerBuilder> MullvadObjects::SublayerPersistent()
+std::unique_ptr<wfp::SublayerBuilder> MullvadObjects::SublayerPersistent(uint16_t weight)
 {
 	auto builder = std::make_unique<wfp::SublayerBuilder>();
 
@@ -70,7 +70,7 @@ std::unique_ptr<wfp::SublayerBuilder> MullvadObjects::SublayerPersistent()
 		.key(MullvadGuids::SublayerPersistent())
 		.provider(MullvadGuids::ProviderPersistent())
 		.persistent()
-		.weight(MAXUINT16);
+		.weight(weight);
 
 	return builder;
 }
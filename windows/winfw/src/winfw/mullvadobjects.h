@@ -4,6 +4,7 @@
 #include "libwfp/sublayerbuilder.h"
 #include "libwfp/conditionbuilder.h"
 #include "libwfp/filterbuilder.h"
+#include <cstdint>
 #include <memory>
 
 class MullvadObjects
@@ -13,9 +14,11 @@ public:
 	MullvadObjects() = delete;
 
 	static std::unique_ptr<wfp::ProviderBuilder> Provider();
-	static std::unique_ptr<wfp::SublayerBuilder> SublayerBaseline();
-	static std::unique_ptr<wfp::SublayerBuilder> SublayerDns();
+	static std::unique_ptr<wfp::SublayerBuilder> SublayerBaseline(uint16_t weight);
+
+	// The DNS sublayer is given a weight of `weight - 1`.
+	static std::unique_ptr<wfp::SublayerBuilder> SublayerDns(uint16_t weight);
 
 	static std::unique_ptr<wfp::ProviderBuilder> ProviderPersistent();
-	static std::unique_ptr<wfp::SublayerBuilder> SublayerPersistent();
+	static std::unique_ptr<wfp::SublayerBuilder> SublayerPersistent(uint16_t weight);
 };
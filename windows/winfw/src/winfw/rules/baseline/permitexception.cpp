@@ -0,0 +1,88 @@
+#include "stdafx.h"
+#include "permitexception.h"
+#include <winfw/mullvadguids.h>
+#include <winfw/rules/shared.h>
+#include <libwfp/filterbuilder.h>
+#include <libwfp/conditionbuilder.h>
+#include <libwfp/ipnetwork.h>
+#include <libwfp/conditions/conditionprotocol.h>
+#include <libwfp/conditions/conditionip.h>
+#include <libwfp/conditions/conditionport.h>
+#include <libcommon/error.h>
+
+using namespace wfp::conditions;
+
+namespace rules::baseline
+{
+
+namespace
+{
+
+const GUID &OutboundLayerFromIp(const wfp::IpAddress &ip)
+{
+	switch (ip.type())
+	{
+		case wfp::IpAddress::Type::Ipv4: return FWPM_LAYER_ALE_AUTH_CONNECT_V4;
+		case wfp::IpAddress::Type::Ipv6: return FWPM_LAYER_ALE_AUTH_CONNECT_V6;
+		default:
+		{
+			THROW_ERROR("Missing case handler in switch clause");
+		}
+	};
+}
+
+} // anonymous namespace
+
+PermitException::PermitException
+(
+	size_t index,
+	const wfp::IpAddress &address,
+	uint8_t prefixLength,
+	std::optional<WinFwProtocol> protocol,
+	std::optional<uint16_t> port
+)
+	: m_index(index)
+	, m_address(address)
+	, m_prefixLength(prefixLength)
+	, m_protocol(protocol)
+	, m_port(port)
+{
+}
+
+bool PermitException::apply(IObjectInstaller &objectInstaller)
+{
+	wfp::FilterBuilder filterBuilder;
+
+	//
+	// Permit outbound connections to a user-defined destination.
+	// Responses are permitted since the connection is authorized.
+	//
+
+	filterBuilder
+		.key(MullvadGuids::Filter_Baseline_PermitException(m_index))
+		.name(L"Permit outbound connections to a user-defined destination")
+		.description(L"This filter is part of a rule that permits traffic to a user-defined destination")
+		.provider(MullvadGuids::Provider())
+		.layer(OutboundLayerFromIp(m_address))
+		.sublayer(MullvadGuids::SublayerBaseline())
+		.weight(wfp::FilterBuilder::WeightClass::Max)
+		.permit();
+
+	wfp::ConditionBuilder conditionBuilder(OutboundLayerFromIp(m_address));
+
+	conditionBuilder.add_condition(ConditionIp::Remote(wfp::IpNetwork(m_address, m_prefixLength)));
+
+	if (m_protocol.has_value())
+	{
+		conditionBuilder.add_condition(CreateProtocolCondition(m_protocol.value()));
+
+		if (m_port.has_value())
+		{
+			conditionBuilder.add_condition(ConditionPort::Remote(m_port.value()));
+		}
+	}
+
+	return objectInstaller.addFilter(filterBuilder, conditionBuilder);
+}
+
+}
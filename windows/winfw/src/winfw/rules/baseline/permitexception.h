@@ -0,0 +1,39 @@
+#pragma once
+
+#include <winfw/rules/ifirewallrule.h>
+#include <winfw/winfw.h>
+#include <libwfp/ipaddress.h>
+#include <optional>
+
+namespace rules::baseline
+{
+
+class PermitException : public IFirewallRule
+{
+public:
+
+	//
+	// `index` identifies the filter, and must be unique among the exceptions.
+	// `port` is only used if `protocol` is set.
+	//
+	PermitException
+	(
+		size_t index,
+		const wfp::IpAddress &address,
+		uint8_t prefixLength,
+		std::optional<WinFwProtocol> protocol,
+		std::optional<uint16_t> port
+	);
+
+	bool apply(IObjectInstaller &objectInstaller) override;
+
+private:
+
+	const size_t m_index;
+	const wfp::IpAddress m_address;
+	const uint8_t m_prefixLength;
+	const std::optional<WinFwProtocol> m_protocol;
+	const std::optional<uint16_t> m_port;
+};
+
+}
@@ -0,0 +1,77 @@
+#include "stdafx.h"
+#include "permitincoming.h"
+#include <winfw/mullvadguids.h>
+#include <winfw/rules/shared.h>
+#include <libwfp/filterbuilder.h>
+#include <libwfp/conditionbuilder.h>
+#include <libwfp/conditions/conditioninterface.h>
+#include <libwfp/conditions/conditionport.h>
+#include <libwfp/conditions/conditionprotocol.h>
+
+using namespace wfp::conditions;
+
+namespace rules::baseline
+{
+
+PermitIncoming::PermitIncoming
+(
+	size_t index,
+	WinFwProtocol protocol,
+	uint16_t port,
+	const std::optional<std::wstring> &interfaceAlias
+)
+	: m_index(index)
+	, m_protocol(protocol)
+	, m_port(port)
+	, m_interfaceAlias(interfaceAlias)
+{
+}
+
+bool PermitIncoming::apply(IObjectInstaller &objectInstaller)
+{
+	return applyLayer(
+		objectInstaller,
+		MullvadGuids::Filter_Baseline_PermitIncoming_Ipv4(m_index),
+		FWPM_LAYER_ALE_AUTH_RECV_ACCEPT_V4,
+		L"Permit inbound connections to a user-defined port (IPv4)"
+	) && applyLayer(
+		objectInstaller,
+		MullvadGuids::Filter_Baseline_PermitIncoming_Ipv6(m_index),
+		FWPM_LAYER_ALE_AUTH_RECV_ACCEPT_V6,
+		L"Permit inbound connections to a user-defined port (IPv6)"
+	);
+}
+
+bool PermitIncoming::applyLayer(IObjectInstaller &objectInstaller, const GUID &key, const GUID &layer, const wchar_t *name) const
+{
+	wfp::FilterBuilder filterBuilder;
+
+	//
+	// Permit inbound connections to a user-defined local port.
+	// Responses are permitted since the connection is authorized.
+	//
+
+	filterBuilder
+		.key(key)
+		.name(name)
+		.description(L"This filter is part of a rule that permits hosting user-defined services")
+		.provider(MullvadGuids::Provider())
+		.layer(layer)
+		.sublayer(MullvadGuids::SublayerBaseline())
+		.weight(wfp::FilterBuilder::WeightClass::Max)
+		.permit();
+
+	wfp::ConditionBuilder conditionBuilder(layer);
+
+	conditionBuilder.add_condition(CreateProtocolCondition(m_protocol));
+	conditionBuilder.add_condition(ConditionPort::Local(m_port));
+
+	if (m_interfaceAlias.has_value())
+	{
+		conditionBuilder.add_condition(ConditionInterface::Alias(m_interfaceAlias.value()));
+	}
+
+	return objectInstaller.addFilter(filterBuilder, conditionBuilder);
+}
+
+}
@@ -0,0 +1,39 @@
+#pragma once
+
+#include <winfw/rules/ifirewallrule.h>
+#include <winfw/winfw.h>
+#include <string>
+#include <optional>
+
+namespace rules::baseline
+{
+
+class PermitIncoming : public IFirewallRule
+{
+public:
+
+	//
+	// `index` identifies the filters, and must be unique among the allowed incoming connections.
+	// If `interfaceAlias` is set, connections are only permitted on that interface.
+	//
+	PermitIncoming
+	(
+		size_t index,
+		WinFwProtocol protocol,
+		uint16_t port,
+		const std::optional<std::wstring> &interfaceAlias
+	);
+
+	bool apply(IObjectInstaller &objectInstaller) override;
+
+private:
+
+	bool applyLayer(IObjectInstaller &objectInstaller, const GUID &key, const GUID &layer, const wchar_t *name) const;
+
+	const size_t m_index;
+	const WinFwProtocol m_protocol;
+	const uint16_t m_port;
+	const std::optional<std::wstring> m_interfaceAlias;
+};
+
+}
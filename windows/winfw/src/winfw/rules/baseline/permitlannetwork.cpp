@@ -0,0 +1,175 @@
+#include "stdafx.h"
+#include "permitlannetwork.h"
+#include <winfw/mullvadguids.h>
+#include <libwfp/filterbuilder.h>
+#include <libwfp/conditionbuilder.h>
+#include <libwfp/ipnetwork.h>
+#include <libwfp/conditions/conditioninterface.h>
+#include <libwfp/conditions/conditionip.h>
+#include <vector>
+
+using namespace wfp::conditions;
+
+namespace rules::baseline
+{
+
+namespace
+{
+
+std::vector<wfp::IpNetwork> PrivateNetworks(wfp::IpAddress::Type type)
+{
+	if (wfp::IpAddress::Type::Ipv4 == type)
+	{
+		return {
+			wfp::IpNetwork(wfp::IpAddress::Literal({ 10, 0, 0, 0 }), 8),
+			wfp::IpNetwork(wfp::IpAddress::Literal({ 172, 16, 0, 0 }), 12),
+			wfp::IpNetwork(wfp::IpAddress::Literal({ 192, 168, 0, 0 }), 16),
+			wfp::IpNetwork(wfp::IpAddress::Literal({ 169, 254, 0, 0 }), 16),
+		};
+	}
+
+	return {
+		wfp::IpNetwork(wfp::IpAddress::Literal6({ 0xFE80, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0 }), 10),
+		wfp::IpNetwork(wfp::IpAddress::Literal6({ 0xFC00, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0 }), 7),
+	};
+}
+
+std::vector<wfp::IpNetwork> MulticastNetworks(wfp::IpAddress::Type type)
+{
+	if (wfp::IpAddress::Type::Ipv4 == type)
+	{
+		return {
+			wfp::IpNetwork(wfp::IpAddress::Literal({ 255, 255, 255, 255 }), 32),
+			wfp::IpNetwork(wfp::IpAddress::Literal({ 224, 0, 0, 0 }), 24),
+			wfp::IpNetwork(wfp::IpAddress::Literal({ 239, 0, 0, 0 }), 8),
+		};
+	}
+
+	return {
+		wfp::IpNetwork(wfp::IpAddress::Literal6({ 0xFF01, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0 }), 16),
+		wfp::IpNetwork(wfp::IpAddress::Literal6({ 0xFF02, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0 }), 16),
+		wfp::IpNetwork(wfp::IpAddress::Literal6({ 0xFF03, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0 }), 16),
+		wfp::IpNetwork(wfp::IpAddress::Literal6({ 0xFF04, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0 }), 16),
+		wfp::IpNetwork(wfp::IpAddress::Literal6({ 0xFF05, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0, 0x0 }), 16),
+	};
+}
+
+} // anonymous namespace
+
+PermitLanNetwork::PermitLanNetwork
+(
+	size_t index,
+	const std::optional<wfp::IpAddress> &address,
+	uint8_t prefixLength,
+	const std::optional<std::wstring> &interfaceAlias
+)
+	: m_index(index)
+	, m_address(address)
+	, m_prefixLength(prefixLength)
+	, m_interfaceAlias(interfaceAlias)
+{
+}
+
+bool PermitLanNetwork::apply(IObjectInstaller &objectInstaller)
+{
+	return applyIpVersion(objectInstaller, wfp::IpAddress::Type::Ipv4)
+		&& applyIpVersion(objectInstaller, wfp::IpAddress::Type::Ipv6);
+}
+
+bool PermitLanNetwork::applyIpVersion(IObjectInstaller &objectInstaller, wfp::IpAddress::Type type) const
+{
+	std::vector<wfp::IpNetwork> networks;
+	std::vector<wfp::IpNetwork> multicastNetworks;
+
+	if (m_address.has_value())
+	{
+		if (m_address->type() != type)
+		{
+			return true;
+		}
+
+		networks.emplace_back(m_address.value(), m_prefixLength);
+	}
+	else
+	{
+		networks = PrivateNetworks(type);
+		multicastNetworks = MulticastNetworks(type);
+	}
+
+	const bool ipv4 = (wfp::IpAddress::Type::Ipv4 == type);
+
+	wfp::FilterBuilder filterBuilder;
+
+	//
+	// #1 Permit outbound connections and multicast to the network.
+	//
+
+	const GUID &outboundLayer = ipv4 ? FWPM_LAYER_ALE_AUTH_CONNECT_V4 : FWPM_LAYER_ALE_AUTH_CONNECT_V6;
+
+	filterBuilder
+		.key(ipv4
+			? MullvadGuids::Filter_Baseline_PermitLanNetwork_Outbound_Ipv4(m_index)
+			: MullvadGuids::Filter_Baseline_PermitLanNetwork_Outbound_Ipv6(m_index))
+		.name(ipv4
+			? L"Permit outbound connections on a user-defined LAN network (IPv4)"
+			: L"Permit outbound connections on a user-defined LAN network (IPv6)")
+		.description(L"This filter is part of a rule that permits traffic on user-defined LAN networks")
+		.provider(MullvadGuids::Provider())
+		.layer(outboundLayer)
+		.sublayer(MullvadGuids::SublayerBaseline())
+		.weight(wfp::FilterBuilder::WeightClass::Medium)
+		.permit();
+
+	wfp::ConditionBuilder outboundConditions(outboundLayer);
+
+	for (const auto &network : networks)
+	{
+		outboundConditions.add_condition(ConditionIp::Remote(network));
+	}
+
+	for (const auto &network : multicastNetworks)
+	{
+		outboundConditions.add_condition(ConditionIp::Remote(network));
+	}
+
+	if (m_interfaceAlias.has_value())
+	{
+		outboundConditions.add_condition(ConditionInterface::Alias(m_interfaceAlias.value()));
+	}
+
+	if (!objectInstaller.addFilter(filterBuilder, outboundConditions))
+	{
+		return false;
+	}
+
+	//
+	// #2 Permit inbound connections from the network.
+	//
+
+	const GUID &inboundLayer = ipv4 ? FWPM_LAYER_ALE_AUTH_RECV_ACCEPT_V4 : FWPM_LAYER_ALE_AUTH_RECV_ACCEPT_V6;
+
+	filterBuilder
+		.key(ipv4
+			? MullvadGuids::Filter_Baseline_PermitLanNetwork_Inbound_Ipv4(m_index)
+			: MullvadGuids::Filter_Baseline_PermitLanNetwork_Inbound_Ipv6(m_index))
+		.name(ipv4
+			? L"Permit inbound connections on a user-defined LAN network (IPv4)"
+			: L"Permit inbound connections on a user-defined LAN network (IPv6)")
+		.layer(inboundLayer);
+
+	wfp::ConditionBuilder inboundConditions(inboundLayer);
+
+	for (const auto &network : networks)
+	{
+		inboundConditions.add_condition(ConditionIp::Remote(network));
+	}
+
+	if (m_interfaceAlias.has_value())
+	{
+		inboundConditions.add_condition(ConditionInterface::Alias(m_interfaceAlias.value()));
+	}
+
+	return objectInstaller.addFilter(filterBuilder, inboundConditions);
+}
+
+}
@@ -0,0 +1,40 @@
+#pragma once
+
+#include <winfw/rules/ifirewallrule.h>
+#include <libwfp/ipaddress.h>
+#include <string>
+#include <optional>
+
+namespace rules::baseline
+{
+
+class PermitLanNetwork : public IFirewallRule
+{
+public:
+
+	//
+	// `index` identifies the filters, and must be unique among the LAN networks.
+	// If `address` is not set, all private networks and LAN multicast are permitted.
+	// If `interfaceAlias` is set, traffic is only permitted on that interface.
+	//
+	PermitLanNetwork
+	(
+		size_t index,
+		const std::optional<wfp::IpAddress> &address,
+		uint8_t prefixLength,
+		const std::optional<std::wstring> &interfaceAlias
+	);
+
+	bool apply(IObjectInstaller &objectInstaller) override;
+
+private:
+
+	bool applyIpVersion(IObjectInstaller &objectInstaller, wfp::IpAddress::Type type) const;
+
+	const size_t m_index;
+	const std::optional<wfp::IpAddress> m_address;
+	const uint8_t m_prefixLength;
+	const std::optional<std::wstring> m_interfaceAlias;
+};
+
+}
@@ -0,0 +1,93 @@
+#include "stdafx.h"
+#include "blockipv6.h"
+#include <winfw/mullvadguids.h>
+#include <libwfp/filterbuilder.h>
+#include <libwfp/conditionbuilder.h>
+#include <libwfp/nullconditionbuilder.h>
+#include <libwfp/conditions/conditionloopback.h>
+
+using namespace wfp::conditions;
+
+namespace rules::dns
+{
+
+bool BlockIpv6::apply(IObjectInstaller &objectInstaller)
+{
+	wfp::FilterBuilder filterBuilder;
+
+	//
+	// #1 Permit outbound loopback connections, IPv6.
+	//
+
+	filterBuilder
+		.key(MullvadGuids::Filter_Dns_BlockIpv6_PermitLoopback_Outbound())
+		.name(L"Permit outbound loopback traffic (IPv6)")
+		.description(L"This filter is part of a rule that blocks all IPv6 traffic")
+		.provider(MullvadGuids::Provider())
+		.layer(FWPM_LAYER_ALE_AUTH_CONNECT_V6)
+		.sublayer(MullvadGuids::SublayerDns())
+		.weight(wfp::FilterBuilder::WeightClass::Max)
+		.permit();
+
+	{
+		wfp::ConditionBuilder conditionBuilder(FWPM_LAYER_ALE_AUTH_CONNECT_V6);
+
+		conditionBuilder.add_condition(std::make_unique<ConditionLoopback>());
+
+		if (false == objectInstaller.addFilter(filterBuilder, conditionBuilder))
+		{
+			return false;
+		}
+	}
+
+	//
+	// #2 Permit inbound loopback connections, IPv6.
+	//
+
+	filterBuilder
+		.key(MullvadGuids::Filter_Dns_BlockIpv6_PermitLoopback_Inbound())
+		.name(L"Permit inbound loopback traffic (IPv6)")
+		.layer(FWPM_LAYER_ALE_AUTH_RECV_ACCEPT_V6);
+
+	{
+		wfp::ConditionBuilder conditionBuilder(FWPM_LAYER_ALE_AUTH_RECV_ACCEPT_V6);
+
+		conditionBuilder.add_condition(std::make_unique<ConditionLoopback>());
+
+		if (false == objectInstaller.addFilter(filterBuilder, conditionBuilder))
+		{
+			return false;
+		}
+	}
+
+	//
+	// #3 Block all outbound connections, IPv6.
+	//
+
+	filterBuilder
+		.key(MullvadGuids::Filter_Dns_BlockIpv6_Outbound())
+		.name(L"Block all outbound connections (IPv6)")
+		.layer(FWPM_LAYER_ALE_AUTH_CONNECT_V6)
+		.weight(wfp::FilterBuilder::WeightClass::Medium)
+		.block();
+
+	wfp::NullConditionBuilder nullConditionBuilder;
+
+	if (false == objectInstaller.addFilter(filterBuilder, nullConditionBuilder))
+	{
+		return false;
+	}
+
+	//
+	// #4 Block all inbound connections, IPv6.
+	//
+
+	filterBuilder
+		.key(MullvadGuids::Filter_Dns_BlockIpv6_Inbound())
+		.name(L"Block all inbound connections (IPv6)")
+		.layer(FWPM_LAYER_ALE_AUTH_RECV_ACCEPT_V6);
+
+	return objectInstaller.addFilter(filterBuilder, nullConditionBuilder);
+}
+
+}
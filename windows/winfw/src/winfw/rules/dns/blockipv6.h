@@ -0,0 +1,22 @@
+#pragma once
+
+#include <winfw/rules/ifirewallrule.h>
+
+namespace rules::dns
+{
+
+//
+// Blocks all IPv6 traffic except on the loopback interface.
+// Lives in the DNS sublayer so that it overrides the permit filters in the baseline sublayer.
+//
+class BlockIpv6 : public IFirewallRule
+{
+public:
+
+	BlockIpv6() = default;
+	~BlockIpv6() = default;
+
+	bool apply(IObjectInstaller &objectInstaller) override;
+};
+
+}
@@ -3,6 +3,7 @@
 #include "fwcontext.h"
 #include "objectpurger.h"
 #include "mullvadobjects.h"
+#include "diagnostics.h"
 #include "rules/persistent/blockall.h"
 #include "libwfp/ipnetwork.h"
 #include <windows.h>
@@ -20,6 +21,8 @@ void *g_logSinkContext = nullptr;
 
 FwContext *g_fwContext = nullptr;
 
+uint16_t g_sublayerWeight = MAXUINT16;
+
 WINFW_POLICY_STATUS
 HandlePolicyException(const common::error::WindowsException &err)
 {
@@ -54,6 +57,7 @@ bool
 WINFW_API
 WinFw_Initialize(
 	uint32_t timeout,
+	uint16_t sublayerWeight,
 	MullvadLogSink logSink,
 	void *logSinkContext
 )
@@ -69,13 +73,22 @@ WinFw_Initialize(
 			THROW_ERROR("Cannot initialize WINFW twice");
 		}
 
+		//
+		// The DNS sublayer is assigned a weight one less than the baseline sublayer.
+		//
+		if (0 == sublayerWeight)
+		{
+			THROW_ERROR("Invalid argument: sublayerWeight");
+		}
+
 		// Convert seconds to milliseconds.
 		uint32_t timeout_ms = timeout * 1000;
 
 		g_logSink = logSink;
 		g_logSinkContext = logSinkContext;
+		g_sublayerWeight = sublayerWeight;
 
-		g_fwContext = new FwContext(timeout_ms);
+		g_fwContext = new FwContext(timeout_ms, sublayerWeight);
 	}
 	catch (std::exception &err)
 	{
@@ -100,6 +113,7 @@ bool
 WINFW_API
 WinFw_InitializeBlocked(
 	uint32_t timeout,
+	uint16_t sublayerWeight,
 	const WinFwSettings *settings,
 	const WinFwAllowedEndpoint *allowedEndpoint,
 	MullvadLogSink logSink,
@@ -117,6 +131,11 @@ WinFw_InitializeBlocked(
 			THROW_ERROR("Cannot initialize WINFW twice");
 		}
 
+		if (0 == sublayerWeight)
+		{
+			THROW_ERROR("Invalid argument: sublayerWeight");
+		}
+
 		if (nullptr == settings)
 		{
 			THROW_ERROR("Invalid argument: settings");
@@ -127,8 +146,9 @@ WinFw_InitializeBlocked(
 
 		g_logSink = logSink;
 		g_logSinkContext = logSinkContext;
+		g_sublayerWeight = sublayerWeight;
 
-		g_fwContext = new FwContext(timeout_ms, *settings, MakeOptional(allowedEndpoint));
+		g_fwContext = new FwContext(timeout_ms, sublayerWeight, *settings, MakeOptional(allowedEndpoint));
 	}
 	catch (std::exception &err)
 	{
@@ -187,7 +207,7 @@ WinFw_Deinitialize(WINFW_CLEANUP_POLICY cleanupPolicy)
 				ObjectPurger::GetRemoveNonPersistentFunctor()(engine);
 
 				return controller.addProvider(*MullvadObjects::ProviderPersistent())
-					&& controller.addSublayer(*MullvadObjects::SublayerPersistent())
+					&& controller.addSublayer(*MullvadObjects::SublayerPersistent(g_sublayerWeight))
 					&& blockAll.apply(controller);
 			});
 		}
@@ -476,3 +496,42 @@ WinFw_Reset()
 		return WINFW_POLICY_STATUS_GENERAL_FAILURE;
 	}
 }
+
+WINFW_LINKAGE
+bool
+WINFW_API
+WinFw_DescribeFilters(
+	WinFwDiagnosticsSink sink,
+	void *sinkContext
+)
+{
+	try
+	{
+		if (nullptr == sink)
+		{
+			THROW_ERROR("Invalid argument: sink");
+		}
+
+		auto engine = wfp::FilterEngine::StandardSession(DEINITIALIZE_TIMEOUT);
+
+		Diagnostics::DescribeFilters(*engine, [&](const std::string &line)
+		{
+			sink(line.c_str(), sinkContext);
+		});
+	}
+	catch (std::exception &err)
+	{
+		if (nullptr != g_logSink)
+		{
+			g_logSink(MULLVAD_LOG_LEVEL_ERROR, err.what(), g_logSinkContext);
+		}
+
+		return false;
+	}
+	catch (...)
+	{
+		return false;
+	}
+
+	return true;
+}
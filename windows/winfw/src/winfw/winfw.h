@@ -19,6 +19,50 @@
 // Structures
 ///////////////////////////////////////////////////////////////////////////////
 
+enum WinFwProtocol : uint8_t
+{
+	Tcp = 0,
+	Udp = 1,
+};
+
+typedef struct tag_WinFwException
+{
+	// Destination network.
+	const wchar_t *ip;
+	uint8_t prefixLength;
+
+	// Only permit `protocol` if this is set.
+	bool filterProtocol;
+	WinFwProtocol protocol;
+
+	// Destination port, or 0 to permit any port. Ignored unless `filterProtocol` is set.
+	uint16_t port;
+}
+WinFwException;
+
+typedef struct tag_WinFwLanNetwork
+{
+	// Private network, or nullptr to permit all private networks and LAN multicast.
+	const wchar_t *ip;
+	uint8_t prefixLength;
+
+	// Only permit traffic on this interface, unless this is nullptr.
+	const wchar_t *interfaceAlias;
+}
+WinFwLanNetwork;
+
+typedef struct tag_WinFwAllowedIncoming
+{
+	WinFwProtocol protocol;
+
+	// Local port.
+	uint16_t port;
+
+	// Only permit connections on this interface, unless this is nullptr.
+	const wchar_t *interfaceAlias;
+}
+WinFwAllowedIncoming;
+
 typedef struct tag_WinFwSettings
 {
 	// Permit outbound DHCP requests and inbound DHCP responses on all interfaces.
@@ -26,15 +70,26 @@ typedef struct tag_WinFwSettings
 
 	// Permit all traffic to and from private address ranges.
 	bool permitLan;
+
+	// Only permit traffic to and from these private networks.
+	// Ignored if `permitLan` is set.
+	uint32_t numLanNetworks;
+	const WinFwLanNetwork *lanNetworks;
+
+	// User-defined destinations that are permitted in every policy.
+	// DNS requests are blocked regardless.
+	uint32_t numExceptions;
+	const WinFwException *exceptions;
+
+	// User-defined inbound connections that are permitted in every policy.
+	uint32_t numAllowedIncoming;
+	const WinFwAllowedIncoming *allowedIncoming;
+
+	// Block all IPv6 traffic except on the loopback interface.
+	bool blockIpv6;
 }
 WinFwSettings;
 
-enum WinFwProtocol : uint8_t
-{
-	Tcp = 0,
-	Udp = 1,
-};
-
 typedef struct tag_WinFwEndpoint
 {
 	const wchar_t *ip;
@@ -84,6 +139,10 @@ WinFwAllowedTunnelTraffic;
 // transaction lock to become available. Specify 0 to use a default timeout
 // determined by Windows.
 //
+// The sublayer weight determines the order in which Mullvad's sublayers are
+// evaluated, relative to the sublayers of other software. The DNS sublayer
+// is given a weight of `sublayerWeight - 1`. Must not be zero.
+//
 
 extern "C"
 WINFW_LINKAGE
@@ -91,6 +150,7 @@ bool
 WINFW_API
 WinFw_Initialize(
 	uint32_t timeout,
+	uint16_t sublayerWeight,
 	MullvadLogSink logSink,
 	void *logSinkContext
 );
@@ -111,6 +171,7 @@ bool
 WINFW_API
 WinFw_InitializeBlocked(
 	uint32_t timeout,
+	uint16_t sublayerWeight,
 	const WinFwSettings *settings,
 	const WinFwAllowedEndpoint *allowedEndpoint,
 	MullvadLogSink logSink,
@@ -227,3 +288,23 @@ WINFW_LINKAGE
 WINFW_POLICY_STATUS
 WINFW_API
 WinFw_Reset();
+
+typedef void (WINFW_API *WinFwDiagnosticsSink)(const char *line, void *context);
+
+//
+// DescribeFilters:
+//
+// Describe all sublayers in the system, and all filters that are either installed
+// in a Mullvad sublayer or that block traffic. Each line of the description
+// is passed to `sink` as a null-terminated string.
+//
+// This does not require WINFW to be initialized.
+//
+extern "C"
+WINFW_LINKAGE
+bool
+WINFW_API
+WinFw_DescribeFilters(
+	WinFwDiagnosticsSink sink,
+	void *sinkContext
+);
@@ -111,6 +111,16 @@ func wgSetConfig(tunnelHandle int32, cSettings *C.char) C.int32_t {
 	return 0
 }
 
+//export wgSetWorkerThreads
+func wgSetWorkerThreads(threads int32) int32 {
+	// GOMAXPROCS bounds the number of threads executing Go code, which is what the
+	// encryption and decryption workers of every tunnel are scheduled on.
+	if threads > 0 {
+		runtime.GOMAXPROCS(int(threads))
+	}
+	return int32(runtime.GOMAXPROCS(0))
+}
+
 //export wgFreePtr
 func wgFreePtr(ptr unsafe.Pointer) {
 	C.free(ptr)
@@ -15,6 +15,7 @@ package main
 import "C"
 import (
 	"bufio"
+	"net/netip"
 	"os"
 	"strings"
 	"unsafe"
@@ -24,6 +25,7 @@ import (
 	"golang.zx2c4.com/wireguard/tun"
 
 	"github.com/mullvad/mullvadvpn-app/wireguard/libwg/logging"
+	"github.com/mullvad/mullvadvpn-app/wireguard/libwg/tcpbind"
 	"github.com/mullvad/mullvadvpn-app/wireguard/libwg/tunnelcontainer"
 )
 
@@ -32,8 +34,11 @@ import (
 type LogSink = unsafe.Pointer
 type LogContext = C.uint64_t
 
+// If `cTcpEndpoint` is not null, packets to that endpoint are sent over TCP. See the tcpbind
+// package for details.
+//
 //export wgTurnOn
-func wgTurnOn(mtu int, cSettings *C.char, fd int, logSink LogSink, logContext LogContext) C.int32_t {
+func wgTurnOn(mtu int, cSettings *C.char, cTcpEndpoint *C.char, fd int, logSink LogSink, logContext LogContext) C.int32_t {
 	logger := logging.NewLogger(logSink, logging.LogContext(logContext))
 
 	if cSettings == nil {
@@ -42,6 +47,16 @@ func wgTurnOn(mtu int, cSettings *C.char, fd int, logSink LogSink, logContext Lo
 	}
 	settings := goStringFixed(cSettings)
 
+	var bind conn.Bind = conn.NewDefaultBind()
+	if cTcpEndpoint != nil {
+		tcpEndpoint, err := netip.ParseAddrPort(C.GoString(cTcpEndpoint))
+		if err != nil {
+			logger.Errorf("Invalid TCP endpoint: %s\n", err)
+			return ERROR_INVALID_ARGUMENT
+		}
+		bind = tcpbind.New(bind, tcpEndpoint)
+	}
+
 	file := os.NewFile(uintptr(fd), "")
 	tunDevice, err := tun.CreateTUNFromFile(file, mtu)
 	if err != nil {
@@ -52,7 +67,7 @@ func wgTurnOn(mtu int, cSettings *C.char, fd int, logSink LogSink, logContext Lo
 		return ERROR_GENERAL_FAILURE
 	}
 
-	device := device.NewDevice(tunDevice, conn.NewDefaultBind(), logger)
+	device := device.NewDevice(tunDevice, bind, logger)
 
 	setErr := device.IpcSetOperation(bufio.NewReader(strings.NewReader(settings)))
 	if setErr != nil {
@@ -0,0 +1,13 @@
+/* SPDX-License-Identifier: Apache-2.0
+ *
+ * Copyright (C) 2025 Mullvad VPN AB. All Rights Reserved.
+ */
+
+package tcpbind
+
+import "syscall"
+
+// Firewall marks do not exist on macOS. The relay is reached through a route to its address.
+func markControl(mark uint32) func(network, address string, c syscall.RawConn) error {
+	return nil
+}
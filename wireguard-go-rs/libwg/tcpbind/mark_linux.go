@@ -0,0 +1,29 @@
+/* SPDX-License-Identifier: Apache-2.0
+ *
+ * Copyright (C) 2025 Mullvad VPN AB. All Rights Reserved.
+ */
+
+package tcpbind
+
+import (
+	"syscall"
+
+	"golang.org/x/sys/unix"
+)
+
+// Set the firewall mark on the TCP socket, so that it is routed outside the tunnel.
+func markControl(mark uint32) func(network, address string, c syscall.RawConn) error {
+	if mark == 0 {
+		return nil
+	}
+	return func(network, address string, c syscall.RawConn) error {
+		var sockErr error
+		err := c.Control(func(fd uintptr) {
+			sockErr = unix.SetsockoptInt(int(fd), unix.SOL_SOCKET, unix.SO_MARK, int(mark))
+		})
+		if err != nil {
+			return err
+		}
+		return sockErr
+	}
+}
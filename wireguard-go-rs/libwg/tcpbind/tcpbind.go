@@ -0,0 +1,233 @@
+/* SPDX-License-Identifier: Apache-2.0
+ *
+ * Copyright (C) 2025 Mullvad VPN AB. All Rights Reserved.
+ */
+
+// Package tcpbind carries WireGuard packets to a single endpoint over TCP.
+//
+// Each packet is prefixed by its length as a 16-bit big endian integer. This is the framing used
+// by udp-over-tcp, so any relay that accepts udp2tcp obfuscation also accepts this transport,
+// without the need for a local UDP proxy.
+package tcpbind
+
+import (
+	"bufio"
+	"encoding/binary"
+	"errors"
+	"io"
+	"net"
+	"net/netip"
+	"sync"
+	"time"
+
+	"golang.zx2c4.com/wireguard/conn"
+)
+
+const (
+	headerLen = 2
+	// Maximum size of a packet that fits in a frame
+	maxPacketLen = 1<<16 - 1
+
+	dialTimeout = 5 * time.Second
+)
+
+// Bind sends packets destined to the TCP endpoint over TCP and leaves all other traffic to the
+// wrapped bind. The wrapped bind is still needed for multihop, where the packets to the exit
+// relay are sent through the tunnel over UDP.
+type Bind struct {
+	inner    conn.Bind
+	endpoint netip.AddrPort
+
+	mu   sync.Mutex
+	cond *sync.Cond
+	// Connection to the endpoint, or nil if no connection has been made yet or the last one failed
+	conn   *tcpConn
+	mark   uint32
+	closed bool
+}
+
+type tcpConn struct {
+	conn   net.Conn
+	reader *bufio.Reader
+	// Serializes writes, so that frames are not interleaved
+	writeMu sync.Mutex
+}
+
+// New creates a bind that reaches `endpoint` over TCP and everything else through `inner`.
+func New(inner conn.Bind, endpoint netip.AddrPort) *Bind {
+	bind := &Bind{
+		inner:    inner,
+		endpoint: endpoint,
+	}
+	bind.cond = sync.NewCond(&bind.mu)
+	return bind
+}
+
+func (b *Bind) Open(port uint16) ([]conn.ReceiveFunc, uint16, error) {
+	fns, actualPort, err := b.inner.Open(port)
+	if err != nil {
+		return nil, 0, err
+	}
+	tcpEndpoint, err := b.inner.ParseEndpoint(b.endpoint.String())
+	if err != nil {
+		b.inner.Close()
+		return nil, 0, err
+	}
+
+	b.mu.Lock()
+	b.closed = false
+	b.mu.Unlock()
+
+	return append(fns, b.makeReceiveTcp(tcpEndpoint)), actualPort, nil
+}
+
+func (b *Bind) Close() error {
+	b.mu.Lock()
+	b.closed = true
+	if b.conn != nil {
+		b.conn.conn.Close()
+		b.conn = nil
+	}
+	b.cond.Broadcast()
+	b.mu.Unlock()
+
+	return b.inner.Close()
+}
+
+func (b *Bind) SetMark(mark uint32) error {
+	b.mu.Lock()
+	b.mark = mark
+	b.mu.Unlock()
+
+	return b.inner.SetMark(mark)
+}
+
+func (b *Bind) Send(bufs [][]byte, ep conn.Endpoint) error {
+	if ep.DstToString() != b.endpoint.String() {
+		return b.inner.Send(bufs, ep)
+	}
+
+	c, err := b.connect()
+	if err != nil {
+		return err
+	}
+
+	c.writeMu.Lock()
+	defer c.writeMu.Unlock()
+	for _, buf := range bufs {
+		if len(buf) > maxPacketLen {
+			continue
+		}
+		frame := make([]byte, headerLen+len(buf))
+		binary.BigEndian.PutUint16(frame, uint16(len(buf)))
+		copy(frame[headerLen:], buf)
+		if _, err := c.conn.Write(frame); err != nil {
+			b.disconnect(c)
+			return err
+		}
+	}
+	return nil
+}
+
+func (b *Bind) ParseEndpoint(s string) (conn.Endpoint, error) {
+	return b.inner.ParseEndpoint(s)
+}
+
+func (b *Bind) BatchSize() int {
+	return b.inner.BatchSize()
+}
+
+// Return the current connection, or connect to the endpoint if there is none.
+func (b *Bind) connect() (*tcpConn, error) {
+	b.mu.Lock()
+	defer b.mu.Unlock()
+
+	if b.closed {
+		return nil, net.ErrClosed
+	}
+	if b.conn != nil {
+		return b.conn, nil
+	}
+
+	dialer := net.Dialer{
+		Timeout: dialTimeout,
+		Control: markControl(b.mark),
+	}
+	c, err := dialer.Dial("tcp", b.endpoint.String())
+	if err != nil {
+		return nil, err
+	}
+	if tcp, ok := c.(*net.TCPConn); ok {
+		// Packets are sent as soon as possible, like they would be over UDP
+		tcp.SetNoDelay(true)
+	}
+
+	b.conn = &tcpConn{
+		conn:   c,
+		reader: bufio.NewReader(c),
+	}
+	b.cond.Broadcast()
+	return b.conn, nil
+}
+
+// Close `c` if it is still the current connection. The next send connects again.
+func (b *Bind) disconnect(c *tcpConn) {
+	b.mu.Lock()
+	defer b.mu.Unlock()
+
+	if b.conn == c {
+		c.conn.Close()
+		b.conn = nil
+	}
+}
+
+// Block until there is a connection to read from, or the bind has been closed.
+func (b *Bind) waitForConn() (*tcpConn, error) {
+	b.mu.Lock()
+	defer b.mu.Unlock()
+
+	for b.conn == nil && !b.closed {
+		b.cond.Wait()
+	}
+	if b.closed {
+		return nil, net.ErrClosed
+	}
+	return b.conn, nil
+}
+
+func (b *Bind) makeReceiveTcp(tcpEndpoint conn.Endpoint) conn.ReceiveFunc {
+	return func(packets [][]byte, sizes []int, eps []conn.Endpoint) (int, error) {
+		for {
+			c, err := b.waitForConn()
+			if err != nil {
+				return 0, err
+			}
+
+			n, err := readFrame(c.reader, packets[0])
+			if err != nil {
+				// A failed connection must not stop the receive routine, since wireguard-go
+				// never restarts it. Wait for a new connection instead.
+				b.disconnect(c)
+				continue
+			}
+			sizes[0] = n
+			eps[0] = tcpEndpoint
+			return 1, nil
+		}
+	}
+}
+
+func readFrame(reader io.Reader, buf []byte) (int, error) {
+	var header [headerLen]byte
+	if _, err := io.ReadFull(reader, header[:]); err != nil {
+		return 0, err
+	}
+	n := int(binary.BigEndian.Uint16(header[:]))
+	if n > len(buf) {
+		return 0, errors.New("frame does not fit in receive buffer")
+	}
+	if _, err := io.ReadFull(reader, buf[:n]); err != nil {
+		return 0, err
+	}
+	return n, nil
+}
@@ -82,10 +82,14 @@ impl Tunnel {
     /// The `logging_callback` let's you provide a Rust function that receives any logging output
     /// from wireguard-go. `logging_context` is a value that will be passed to each invocation of
     /// `logging_callback`.
+    ///
+    /// If `tcp_endpoint` is set to an `ip:port` string, packets to that endpoint are sent over TCP
+    /// using udp-over-tcp framing instead of UDP.
     #[cfg(not(target_os = "windows"))]
     pub fn turn_on(
         #[cfg(not(target_os = "android"))] mtu: isize,
         settings: &CStr,
+        #[cfg(not(target_os = "android"))] tcp_endpoint: Option<&CStr>,
         device: Fd,
         logging_callback: Option<LoggingCallback>,
         logging_context: LoggingContext,
@@ -96,6 +100,8 @@ impl Tunnel {
                 #[cfg(not(target_os = "android"))]
                 mtu,
                 settings.as_ptr(),
+                #[cfg(not(target_os = "android"))]
+                tcp_endpoint.map_or(core::ptr::null(), CStr::as_ptr),
                 device,
                 logging_callback,
                 logging_context,
@@ -310,6 +316,23 @@ pub fn update_bind() {
     unsafe { ffi::wgUpdateBind() }
 }
 
+/// Limit the number of threads that run WireGuard packet processing, or use the default of one
+/// per CPU if `threads` is `None`. Returns the limit that is in effect afterwards.
+///
+/// This applies to the whole Go runtime, and thus to every tunnel in the process.
+pub fn set_worker_threads(threads: Option<u32>) -> u32 {
+    let threads = threads
+        .filter(|&threads| threads > 0)
+        .or_else(|| {
+            let cpus = std::thread::available_parallelism().ok()?;
+            u32::try_from(cpus.get()).ok()
+        })
+        .map_or(0, |threads| i32::try_from(threads).unwrap_or(i32::MAX));
+    // SAFETY: wgSetWorkerThreads has no preconditions.
+    let effective = unsafe { ffi::wgSetWorkerThreads(threads) };
+    u32::try_from(effective).unwrap_or(0)
+}
+
 fn result_from_code(code: i32) -> Result<(), Error> {
     // NOTE: must be kept in sync with enum definition
     Err(match code {
@@ -347,6 +370,7 @@ mod ffi {
         pub fn wgTurnOn(
             mtu: isize,
             settings: *const c_char,
+            tcp_endpoint: *const c_char,
             fd: Fd,
             logging_callback: Option<LoggingCallback>,
             logging_context: LoggingContext,
@@ -441,6 +465,12 @@ mod ffi {
         #[cfg(target_os = "android")]
         pub fn wgGetSocketV6(handle: i32) -> Fd;
 
+        /// Set the maximum number of threads that may execute Go code simultaneously. Values
+        /// below 1 leave the current limit unchanged.
+        ///
+        /// Returns the limit that is in effect after the call.
+        pub fn wgSetWorkerThreads(threads: i32) -> i32;
+
         /// Rebind endpoint sockets
         #[cfg(target_os = "windows")]
         pub fn wgUpdateBind();